@@ -4,6 +4,7 @@
 //! with payload positions in request templates.
 
 use crate::error::{AttackError, AttackResult};
+use crate::graphql::{GraphQlAttackConfig, GraphQlMode};
 use crate::parser::{ParsedTemplate, PayloadPositionParser};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -19,6 +20,9 @@ pub enum AttackMode {
     Pitchfork,
     /// Multiple positions, all combinations of payloads
     ClusterBomb,
+    /// GraphQL probes generated from the captured request or an
+    /// introspection result; no payload positions
+    GraphQl(GraphQlAttackConfig),
 }
 
 /// Represents a single attack request with payload values
@@ -389,6 +393,7 @@ impl AttackModeFactory {
             AttackMode::BatteringRam => Box::new(BatteringRamMode),
             AttackMode::Pitchfork => Box::new(PitchforkMode),
             AttackMode::ClusterBomb => Box::new(ClusterBombMode),
+            AttackMode::GraphQl(config) => Box::new(GraphQlMode::new(config.clone())),
         }
     }
 }
@@ -397,6 +402,7 @@ impl AttackModeFactory {
 mod tests {
     use super::*;
     use crate::parser::PayloadPositionParser;
+    use crate::raw_request::RawRequest;
     
     fn create_test_payload_sets() -> HashMap<String, Vec<String>> {
         let mut payload_sets = HashMap::new();
@@ -530,4 +536,59 @@ mod tests {
         assert!(result.contains(&vec![("a".to_string(), "2".to_string()), ("b".to_string(), "x".to_string())]));
         assert!(result.contains(&vec![("a".to_string(), "2".to_string()), ("b".to_string(), "y".to_string())]));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_path_traversal_attack_end_to_end() {
+        let template = "GET /download?file=report.pdf&dir=§dir§ HTTP/1.1\r\nHost: target.test\r\n\r\n";
+        let traversal_template = "GET /static/§path§ HTTP/1.1\r\nHost: target.test\r\n\r\n";
+        let payloads = vec!["../../etc/passwd".to_string(), "..%2f..%2fetc%2fpasswd".to_string(), "a b#c".to_string()];
+
+        let mut sets = HashMap::new();
+        sets.insert("path".to_string(), payloads.clone());
+        let parsed = PayloadPositionParser::parse(traversal_template).unwrap();
+        let urls: Vec<String> = SniperMode
+            .generate_requests(&parsed, &sets)
+            .unwrap()
+            .iter()
+            .map(|r| RawRequest::parse(r.request.as_bytes()).unwrap().to_request_data(None).unwrap().url)
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://target.test/static/../../etc/passwd",
+                "https://target.test/static/..%2f..%2fetc%2fpasswd",
+                "https://target.test/static/a%20b%23c",
+            ]
+        );
+
+        // In a query value, separators of the query are encoded
+        let mut sets = HashMap::new();
+        sets.insert("dir".to_string(), vec!["../x&file=/etc/passwd".to_string()]);
+        let parsed = PayloadPositionParser::parse(template).unwrap();
+        let requests = SniperMode.generate_requests(&parsed, &sets).unwrap();
+        let request = RawRequest::parse(requests[0].request.as_bytes()).unwrap().to_request_data(None).unwrap();
+        assert_eq!(request.url, "https://target.test/download?file=report.pdf&dir=../x%26file=/etc/passwd");
+        assert_eq!(requests[0].payload_values["dir"], "../x&file=/etc/passwd");
+    }
+
+    #[test]
+    fn test_cookie_fuzzing_attack_end_to_end() {
+        let template = "GET /account HTTP/1.1\r\nHost: target.test\r\nCookie: theme=dark; §name§=§session§\r\n\r\n";
+        let parsed = PayloadPositionParser::parse(template).unwrap();
+        let mut sets = HashMap::new();
+        sets.insert("name".to_string(), vec!["role".to_string(), "is=admin".to_string()]);
+        sets.insert("session".to_string(), vec!["admin".to_string(), "x; role=admin".to_string()]);
+
+        let cookies: Vec<String> = PitchforkMode
+            .generate_requests(&parsed, &sets)
+            .unwrap()
+            .iter()
+            .map(|r| {
+                let request = RawRequest::parse(r.request.as_bytes()).unwrap().to_request_data(None).unwrap();
+                request.get_header("Cookie").unwrap().clone()
+            })
+            .collect();
+        // A payload cannot end its pair early and smuggle in another cookie
+        assert_eq!(cookies, vec!["theme=dark; role=admin", "theme=dark; is%3Dadmin=x%3B%20role=admin"]);
+    }
+}
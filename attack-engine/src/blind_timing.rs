@@ -0,0 +1,226 @@
+//! Confirming time-based blind findings
+//!
+//! A payload like `SLEEP(5)` shows itself as a slow response, but so does a
+//! busy server, a garbage collection pause or a congested link, and one slow
+//! response is not evidence of either. A result that came back slower than
+//! the baseline by at least `min_delay_ms` is therefore probed again: the
+//! unmodified request (control) and the payload request (probe) are sent in
+//! pairs at a constant rate, alternating which of the two goes first, so
+//! drift in the target's load affects both alike. The delay is reported only
+//! when the confidence interval of the paired differences lies entirely
+//! above `min_delay_ms`.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Most control/probe pairs sent for one result
+pub const MAX_TIMING_PAIRS: u32 = 30;
+
+/// Two-sided confidence level of the delay estimate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfidenceLevel {
+    P90,
+    #[default]
+    P95,
+    P99,
+}
+
+impl ConfidenceLevel {
+    /// Student's t critical value for `df` degrees of freedom
+    fn t_critical(self, df: usize) -> f64 {
+        // Two-sided critical values for df 1..=29 (MAX_TIMING_PAIRS - 1)
+        const P90: [f64; 29] = [
+            6.314, 2.920, 2.353, 2.132, 2.015, 1.943, 1.895, 1.860, 1.833, 1.812, 1.796, 1.782, 1.771, 1.761,
+            1.753, 1.746, 1.740, 1.734, 1.729, 1.725, 1.721, 1.717, 1.714, 1.711, 1.708, 1.706, 1.703, 1.701,
+            1.699,
+        ];
+        const P95: [f64; 29] = [
+            12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160, 2.145,
+            2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048,
+            2.045,
+        ];
+        const P99: [f64; 29] = [
+            63.657, 9.925, 5.841, 4.604, 4.032, 3.707, 3.499, 3.355, 3.250, 3.169, 3.106, 3.055, 3.012, 2.977,
+            2.947, 2.921, 2.898, 2.878, 2.861, 2.845, 2.831, 2.819, 2.807, 2.797, 2.787, 2.779, 2.771, 2.763,
+            2.756,
+        ];
+        let table = match self {
+            ConfidenceLevel::P90 => &P90,
+            ConfidenceLevel::P95 => &P95,
+            ConfidenceLevel::P99 => &P99,
+        };
+        // Beyond the table the last value errs on the wide side
+        table[df.clamp(1, table.len()) - 1]
+    }
+}
+
+impl fmt::Display for ConfidenceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfidenceLevel::P90 => "90%",
+            ConfidenceLevel::P95 => "95%",
+            ConfidenceLevel::P99 => "99%",
+        })
+    }
+}
+
+/// How slow results are re-probed before a delay is reported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimingConfirmation {
+    /// Control/probe pairs sent per slow result
+    pub pairs: u32,
+    /// Smallest delay reported, about what the payloads ask the target to
+    /// sleep
+    pub min_delay_ms: u64,
+    pub confidence: ConfidenceLevel,
+    /// Time between the starts of consecutive probing requests
+    pub interval_ms: u64,
+}
+
+impl Default for TimingConfirmation {
+    fn default() -> Self {
+        Self {
+            pairs: 6,
+            min_delay_ms: 2000,
+            confidence: ConfidenceLevel::default(),
+            interval_ms: 250,
+        }
+    }
+}
+
+/// Response times of one control/probe pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingPair {
+    pub control_ms: u64,
+    pub probe_ms: u64,
+}
+
+/// Delay of the probe over the control, estimated from paired samples
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DelayEstimate {
+    pub pairs: usize,
+    /// Mean of probe minus control
+    pub mean_ms: f64,
+    pub lower_ms: f64,
+    pub upper_ms: f64,
+    pub confidence: ConfidenceLevel,
+}
+
+impl TimingConfirmation {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if !(3..=MAX_TIMING_PAIRS).contains(&self.pairs) {
+            return Err(format!("Delay confirmation needs between 3 and {} pairs", MAX_TIMING_PAIRS));
+        }
+        if self.min_delay_ms == 0 {
+            return Err("Delay confirmation needs a minimum delay above 0 ms".to_string());
+        }
+        Ok(())
+    }
+
+    /// Whether a result taking `duration_ms` against a baseline of
+    /// `baseline_ms` is slow enough to probe again
+    pub fn should_confirm(&self, baseline_ms: u64, duration_ms: u64) -> bool {
+        duration_ms.saturating_sub(baseline_ms) >= self.min_delay_ms
+    }
+
+    /// Whether the probe goes before the control in pair `index`
+    pub fn probe_first(index: usize) -> bool {
+        index % 2 == 1
+    }
+
+    /// Confidence interval of the delay; `None` with fewer than two pairs
+    pub fn estimate(&self, pairs: &[TimingPair]) -> Option<DelayEstimate> {
+        if pairs.len() < 2 {
+            return None;
+        }
+        let differences: Vec<f64> = pairs
+            .iter()
+            .map(|pair| pair.probe_ms as f64 - pair.control_ms as f64)
+            .collect();
+        let n = differences.len() as f64;
+        let mean = differences.iter().sum::<f64>() / n;
+        let variance = differences.iter().map(|d| (d - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let margin = self.confidence.t_critical(differences.len() - 1) * (variance / n).sqrt();
+        Some(DelayEstimate {
+            pairs: differences.len(),
+            mean_ms: mean,
+            lower_ms: mean - margin,
+            upper_ms: mean + margin,
+            confidence: self.confidence,
+        })
+    }
+
+    /// Whether `estimate` shows a delay of at least `min_delay_ms`
+    pub fn is_significant(&self, estimate: &DelayEstimate) -> bool {
+        estimate.lower_ms >= self.min_delay_ms as f64
+    }
+}
+
+impl fmt::Display for DelayEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "delay {:+.0} ms ({} CI {:.0}..{:.0} ms over {} pairs)",
+            self.mean_ms, self.confidence, self.lower_ms, self.upper_ms, self.pairs
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(samples: &[(u64, u64)]) -> Vec<TimingPair> {
+        samples
+            .iter()
+            .map(|&(control_ms, probe_ms)| TimingPair { control_ms, probe_ms })
+            .collect()
+    }
+
+    #[test]
+    fn test_consistent_delay_is_significant() {
+        let timing = TimingConfirmation { min_delay_ms: 4000, ..Default::default() };
+        let estimate = timing
+            .estimate(&pairs(&[(110, 5120), (95, 5080), (130, 5150), (100, 5105), (120, 5090), (105, 5110)]))
+            .unwrap();
+        assert!((estimate.mean_ms - 4999.17).abs() < 0.01);
+        assert!(estimate.lower_ms > 4950.0 && estimate.upper_ms < 5050.0);
+        assert!(timing.is_significant(&estimate));
+        assert_eq!(estimate.to_string(), "delay +4999 ms (95% CI 4980..5018 ms over 6 pairs)");
+    }
+
+    #[test]
+    fn test_single_slow_response_is_not_significant() {
+        let timing = TimingConfirmation { min_delay_ms: 2000, ..Default::default() };
+        // One pair hit a stall; the rest show no delay
+        let estimate = timing
+            .estimate(&pairs(&[(100, 6100), (110, 120), (90, 105), (105, 98), (100, 112), (95, 101)]))
+            .unwrap();
+        assert!(estimate.mean_ms > 900.0);
+        assert!(estimate.lower_ms < 0.0);
+        assert!(!timing.is_significant(&estimate));
+
+        assert!(timing.estimate(&pairs(&[(100, 5100)])).is_none());
+    }
+
+    #[test]
+    fn test_confirmation_settings() {
+        let timing = TimingConfirmation::default();
+        assert!(timing.validate().is_ok());
+        assert!(TimingConfirmation { pairs: 2, ..Default::default() }.validate().is_err());
+        assert!(TimingConfirmation { min_delay_ms: 0, ..Default::default() }.validate().is_err());
+
+        assert!(timing.should_confirm(150, 2150));
+        assert!(!timing.should_confirm(150, 2149));
+        assert!(!timing.should_confirm(3000, 100));
+        assert!(!TimingConfirmation::probe_first(0) && TimingConfirmation::probe_first(1));
+
+        // Wider intervals at higher confidence and fewer degrees of freedom
+        assert!(ConfidenceLevel::P99.t_critical(5) > ConfidenceLevel::P95.t_critical(5));
+        assert!(ConfidenceLevel::P95.t_critical(2) > ConfidenceLevel::P95.t_critical(20));
+        assert_eq!(ConfidenceLevel::P95.t_critical(100), ConfidenceLevel::P95.t_critical(29));
+    }
+}
@@ -0,0 +1,425 @@
+//! Content discovery mode
+//!
+//! Brute-forces paths under a base URL from a wordlist: every word is tried
+//! bare and with each configured extension. Servers that answer unknown paths
+//! with a 200 page (or a redirect to a login or error page) would make every
+//! word a hit, so each directory is baselined first with a few random paths;
+//! responses similar enough to one of those are soft-404s rather than
+//! findings. Directories found can be descended into up to a depth limit.
+//!
+//! Unlike the payload-position modes, discovery decides the next requests
+//! from the responses of the previous ones, so it is not an [`AttackMode`]
+//! the Intruder coordinator expands up front: a runner asks this module which
+//! requests to send for a directory and how to classify each response.
+//!
+//! [`AttackMode`]: crate::attack_modes::AttackMode
+
+use crate::types::{HttpHeaders, HttpRequestData, HttpResponseData};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use url::Url;
+
+/// Random paths requested per directory to learn what "not found" looks like
+pub const BASELINE_PROBES: usize = 3;
+
+/// Deepest recursion accepted
+pub const MAX_DISCOVERY_DEPTH: u32 = 10;
+
+/// Longest title kept from an HTML response
+const MAX_TITLE_LENGTH: usize = 200;
+
+/// Settings of a discovery run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiscoveryConfig {
+    /// Directory the words are appended to, e.g. `https://target.test/app/`
+    pub base_url: String,
+    /// Paths to try; blank lines and lines starting with `#` are skipped
+    pub wordlist: Vec<String>,
+    /// Extensions tried after each word, without the dot
+    pub extensions: Vec<String>,
+    /// Headers sent with every request, e.g. a session cookie
+    pub headers: HashMap<String, String>,
+    /// Descend into directories that were found
+    pub recursive: bool,
+    /// Directory levels below `base_url` that are searched; 0 searches only
+    /// `base_url`
+    pub max_depth: u32,
+    /// Responses at least this similar (0 to 1) to a baseline response of
+    /// their directory are soft-404s
+    pub similarity_threshold: f64,
+    /// Status codes that are never findings
+    pub ignored_status_codes: Vec<i32>,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            base_url: String::new(),
+            wordlist: Vec::new(),
+            extensions: Vec::new(),
+            headers: HashMap::new(),
+            recursive: false,
+            max_depth: 2,
+            similarity_threshold: 0.9,
+            ignored_status_codes: vec![404],
+        }
+    }
+}
+
+impl DiscoveryConfig {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        let base = Url::parse(&self.base_url).map_err(|e| format!("Invalid base URL {}: {}", self.base_url, e))?;
+        if !matches!(base.scheme(), "http" | "https") {
+            return Err(format!("Base URL must be http or https, got {}", base.scheme()));
+        }
+        if self.words().next().is_none() {
+            return Err("The wordlist is empty".to_string());
+        }
+        if !(self.similarity_threshold > 0.0 && self.similarity_threshold <= 1.0) {
+            return Err(format!("Similarity threshold must be in (0, 1], got {}", self.similarity_threshold));
+        }
+        if self.max_depth > MAX_DISCOVERY_DEPTH {
+            return Err(format!("Depth is limited to {}", MAX_DISCOVERY_DEPTH));
+        }
+        if let Some(extension) = self.extensions.iter().find(|e| e.is_empty() || e.contains(['/', '?', '#'])) {
+            return Err(format!("Invalid extension {:?}", extension));
+        }
+        Ok(())
+    }
+
+    /// Words of the wordlist that are tried
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.wordlist
+            .iter()
+            .map(|word| word.trim().trim_start_matches('/'))
+            .filter(|word| !word.is_empty() && !word.starts_with('#'))
+    }
+
+    /// Requests per directory, baseline included
+    pub fn requests_per_directory(&self) -> usize {
+        let per_word = |word: &str| if word.ends_with('/') { 1 } else { 1 + self.extensions.len() };
+        self.words().map(per_word).sum::<usize>() + BASELINE_PROBES
+    }
+}
+
+/// A path to request in a directory
+#[derive(Debug, Clone)]
+pub struct DiscoveryRequest {
+    /// Path relative to the directory, e.g. `admin` or `backup.zip`
+    pub path: String,
+    pub request: HttpRequestData,
+}
+
+/// Generates the requests of a discovery run
+#[derive(Debug, Clone)]
+pub struct DiscoveryMode {
+    config: DiscoveryConfig,
+}
+
+impl DiscoveryMode {
+    pub fn new(config: DiscoveryConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &DiscoveryConfig {
+        &self.config
+    }
+
+    /// Base URL as a directory, with a trailing slash
+    pub fn base_directory(&self) -> Result<Url, String> {
+        let mut base = Url::parse(&self.config.base_url).map_err(|e| e.to_string())?;
+        base.set_query(None);
+        base.set_fragment(None);
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
+        }
+        Ok(base)
+    }
+
+    /// Word requests for `directory`, each word followed by its extensions
+    pub fn requests_for(&self, directory: &Url) -> Vec<DiscoveryRequest> {
+        let mut paths = Vec::new();
+        for word in self.config.words() {
+            paths.push(word.to_string());
+            // Directories are not tried with extensions
+            if !word.ends_with('/') {
+                paths.extend(self.config.extensions.iter().map(|ext| format!("{}.{}", word, ext.trim_start_matches('.'))));
+            }
+        }
+        paths.into_iter().filter_map(|path| self.request(directory, path)).collect()
+    }
+
+    /// Requests for paths that should not exist in `directory`: a file, a
+    /// directory and, if extensions are tried, a file with the first one
+    pub fn baseline_requests(&self, directory: &Url) -> Vec<DiscoveryRequest> {
+        let random = || uuid::Uuid::new_v4().simple().to_string()[..16].to_string();
+        let extension = self.config.extensions.first().map(|ext| ext.trim_start_matches('.')).unwrap_or("html");
+        [random(), format!("{}/", random()), format!("{}.{}", random(), extension)]
+            .into_iter()
+            .take(BASELINE_PROBES)
+            .filter_map(|path| self.request(directory, path))
+            .collect()
+    }
+
+    fn request(&self, directory: &Url, path: String) -> Option<DiscoveryRequest> {
+        let url = directory.join(&path).ok()?;
+        let mut request = HttpRequestData::new("GET".to_string(), url.to_string());
+        if !self.config.headers.is_empty() {
+            request.headers = Some(HttpHeaders {
+                headers: self.config.headers.clone(),
+            });
+        }
+        Some(DiscoveryRequest { path, request })
+    }
+
+    /// Whether directories found at `depth` are searched
+    pub fn descends_from(&self, depth: u32) -> bool {
+        self.config.recursive && depth < self.config.max_depth
+    }
+
+    /// Classify the response to `path` against the directory's baseline
+    pub fn classify(&self, path: &str, response: &HttpResponseData, baseline: &Soft404Baseline) -> DiscoveryVerdict {
+        if self.config.ignored_status_codes.contains(&response.status_code) {
+            return DiscoveryVerdict::NotFound;
+        }
+        if baseline.matches(path, response, self.config.similarity_threshold) {
+            return DiscoveryVerdict::Soft404;
+        }
+        DiscoveryVerdict::Found {
+            is_directory: is_directory(path, response),
+        }
+    }
+}
+
+/// Outcome of one discovery request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryVerdict {
+    Found { is_directory: bool },
+    /// Looks like the directory's answer for paths that do not exist
+    Soft404,
+    NotFound,
+}
+
+/// What a response looks like once the requested path is taken out of it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseSignature {
+    pub status_code: i32,
+    pub length: usize,
+    /// Redirect target with the path removed
+    pub location: Option<String>,
+    words: HashSet<String>,
+}
+
+impl ResponseSignature {
+    /// Signature of the response to `path`; pages that echo the path back
+    /// ("/foo was not found") compare equal for any path
+    pub fn new(path: &str, response: &HttpResponseData) -> Self {
+        let body = String::from_utf8_lossy(&response.body);
+        let path = path.trim_end_matches('/');
+        let without_path = if path.is_empty() { body.to_string() } else { body.replace(path, "") };
+        let location = header(response, "location").map(|location| {
+            if path.is_empty() {
+                location.to_string()
+            } else {
+                location.replace(path, "")
+            }
+        });
+        Self {
+            status_code: response.status_code,
+            length: without_path.len(),
+            location,
+            words: without_path
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    /// 0 for different statuses or redirect targets, else the overlap of the
+    /// bodies' words weighted with the closeness of their lengths
+    pub fn similarity(&self, other: &ResponseSignature) -> f64 {
+        if self.status_code != other.status_code || self.location != other.location {
+            return 0.0;
+        }
+        let lengths = self.length.min(other.length) as f64 / self.length.max(other.length).max(1) as f64;
+        let words = if self.words.is_empty() && other.words.is_empty() {
+            1.0
+        } else {
+            self.words.intersection(&other.words).count() as f64 / self.words.union(&other.words).count() as f64
+        };
+        if self.length == other.length {
+            return words;
+        }
+        0.8 * words + 0.2 * lengths
+    }
+}
+
+/// Responses of a directory to paths that do not exist
+#[derive(Debug, Clone, Default)]
+pub struct Soft404Baseline {
+    samples: Vec<ResponseSignature>,
+}
+
+impl Soft404Baseline {
+    /// Record the response to the baseline probe `path`
+    pub fn record(&mut self, path: &str, response: &HttpResponseData) {
+        self.samples.push(ResponseSignature::new(path, response));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn matches(&self, path: &str, response: &HttpResponseData, threshold: f64) -> bool {
+        let signature = ResponseSignature::new(path, response);
+        self.samples.iter().any(|sample| sample.similarity(&signature) >= threshold)
+    }
+}
+
+fn header<'a>(response: &'a HttpResponseData, name: &str) -> Option<&'a str> {
+    response
+        .headers
+        .as_ref()?
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+/// A path is a directory when it was requested with a trailing slash and
+/// exists, or when the server redirects it to itself with one
+fn is_directory(path: &str, response: &HttpResponseData) -> bool {
+    if path.ends_with('/') {
+        return (200..300).contains(&response.status_code) || matches!(response.status_code, 401 | 403);
+    }
+    if !matches!(response.status_code, 301 | 302 | 303 | 307 | 308) {
+        return false;
+    }
+    header(response, "location").is_some_and(|location| {
+        let location = location.split(['?', '#']).next().unwrap_or_default();
+        location.ends_with(&format!("{}/", path))
+    })
+}
+
+/// Contents of the `<title>` element of an HTML response
+pub fn html_title(response: &HttpResponseData) -> Option<String> {
+    let body = String::from_utf8_lossy(&response.body);
+    let lower = body.to_ascii_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title: String = body[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        return None;
+    }
+    Some(title.chars().take(MAX_TITLE_LENGTH).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status_code: i32, headers: &[(&str, &str)], body: &str) -> HttpResponseData {
+        HttpResponseData {
+            status_code,
+            headers: Some(HttpHeaders {
+                headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }),
+            body: body.as_bytes().to_vec(),
+            tls: None,
+            timing: None,
+            protocol: None,
+        }
+    }
+
+    fn mode(config: DiscoveryConfig) -> DiscoveryMode {
+        DiscoveryMode::new(DiscoveryConfig {
+            base_url: "https://target.test/app?x=1".to_string(),
+            ..config
+        })
+    }
+
+    #[test]
+    fn test_requests_for_words_and_extensions() {
+        let mode = mode(DiscoveryConfig {
+            wordlist: vec!["admin".to_string(), "# comment".to_string(), "".to_string(), "/static/".to_string()],
+            extensions: vec!["php".to_string(), ".bak".to_string()],
+            headers: HashMap::from([("Cookie".to_string(), "sid=1".to_string())]),
+            ..Default::default()
+        });
+        let base = mode.base_directory().unwrap();
+        assert_eq!(base.as_str(), "https://target.test/app/");
+
+        let requests = mode.requests_for(&base);
+        let urls: Vec<&str> = requests.iter().map(|r| r.request.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://target.test/app/admin",
+                "https://target.test/app/admin.php",
+                "https://target.test/app/admin.bak",
+                "https://target.test/app/static/",
+            ]
+        );
+        assert_eq!(requests[0].request.get_header("Cookie").map(String::as_str), Some("sid=1"));
+        assert_eq!(mode.config().requests_per_directory(), 4 + BASELINE_PROBES);
+
+        let baseline = mode.baseline_requests(&base);
+        assert_eq!(baseline.len(), BASELINE_PROBES);
+        assert!(baseline[1].path.ends_with('/') && baseline[2].path.ends_with(".php"));
+    }
+
+    #[test]
+    fn test_soft_404s_are_told_apart_from_findings() {
+        let mode = mode(DiscoveryConfig::default());
+        let mut baseline = Soft404Baseline::default();
+        let not_found = |path: &str| response(200, &[], &format!("<html><title>Oops</title>Sorry, /app/{} does not exist. Request 8812</html>", path));
+        baseline.record("a1b2c3", &not_found("a1b2c3"));
+        baseline.record("q9w8e7/", &response(302, &[("Location", "/login?next=/app/q9w8e7/")], ""));
+
+        // The custom error page, whatever path it echoes
+        assert_eq!(mode.classify("backup", &not_found("backup"), &baseline), DiscoveryVerdict::Soft404);
+        // Redirects of unknown directories to the login page
+        let login = response(302, &[("location", "/login?next=/app/private/")], "");
+        assert_eq!(mode.classify("private/", &login, &baseline), DiscoveryVerdict::Soft404);
+        assert_eq!(mode.classify("gone", &response(404, &[], ""), &baseline), DiscoveryVerdict::NotFound);
+
+        let admin = response(200, &[], "<html><title>\n  Admin   console </title><form>user password</form></html>");
+        assert_eq!(mode.classify("admin.php", &admin, &baseline), DiscoveryVerdict::Found { is_directory: false });
+        assert_eq!(html_title(&admin).as_deref(), Some("Admin console"));
+
+        let moved = response(301, &[("Location", "https://target.test/app/images/")], "");
+        assert_eq!(mode.classify("images", &moved, &baseline), DiscoveryVerdict::Found { is_directory: true });
+        let forbidden = response(403, &[], "Forbidden");
+        assert_eq!(mode.classify("private/", &forbidden, &baseline), DiscoveryVerdict::Found { is_directory: true });
+    }
+
+    #[test]
+    fn test_validate_and_depth() {
+        let valid = DiscoveryConfig {
+            base_url: "https://target.test/".to_string(),
+            wordlist: vec!["admin".to_string()],
+            recursive: true,
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+        let mode = DiscoveryMode::new(valid.clone());
+        assert!(mode.descends_from(0));
+        assert!(!mode.descends_from(1));
+
+        for invalid in [
+            DiscoveryConfig { base_url: "ftp://target.test/".to_string(), ..valid.clone() },
+            DiscoveryConfig { wordlist: vec!["#".to_string(), " ".to_string()], ..valid.clone() },
+            DiscoveryConfig { similarity_threshold: 0.0, ..valid.clone() },
+            DiscoveryConfig { max_depth: MAX_DISCOVERY_DEPTH + 1, ..valid.clone() },
+            DiscoveryConfig { extensions: vec!["php?x".to_string()], ..valid.clone() },
+        ] {
+            assert!(invalid.validate().is_err(), "{:?}", invalid);
+        }
+    }
+}
@@ -174,6 +174,7 @@ impl AttackEngine {
         if let Some(ref session) = request.session_data {
             request.request_template.apply_session(session);
         }
+        request.execution_config.header_randomization.apply(&mut request.request_template);
         
         // Check agent availability
         if !self.agent_manager.is_agent_available(agent_id).await? {
@@ -202,6 +203,8 @@ impl AttackEngine {
             }),
             body: b"Mock response".to_vec(),
             tls: None,
+            timing: None,
+            protocol: None,
         };
         
         result = result.with_response(response, duration);
@@ -0,0 +1,934 @@
+//! GraphQL attack surface fuzzing
+//!
+//! Probes for a GraphQL endpoint, built either from a captured request (its
+//! variables, the string literals in its root fields, and the root fields
+//! themselves) or from an introspection result (every argument of every
+//! query and mutation field). Three kinds of probe are generated:
+//! - type confusion: a value of the wrong type for an argument or variable
+//! - injection: classic injection strings in string arguments
+//! - alias batching: the same field repeated under many aliases in one
+//!   request, which slips past per-request rate limits
+//!
+//! The request template is the captured HTTP request; each probe replaces its
+//! JSON body.
+
+use crate::attack_modes::{AttackModeExecutor, AttackRequest};
+use crate::error::{AttackError, AttackResult};
+use crate::parser::ParsedTemplate;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Strings sent into string arguments when no payloads are configured
+pub const DEFAULT_INJECTION_PAYLOADS: &[&str] = &[
+    "'",
+    "' OR '1'='1",
+    "\" OR \"1\"=\"1",
+    "1 AND SLEEP(5)",
+    "{\"$ne\": null}",
+    "{{7*7}}",
+    "${7*7}",
+    "<script>alert(1)</script>",
+    "../../../../etc/passwd",
+    "$(id)",
+];
+
+/// Aliased copies of a field per alias batching probe by default
+pub const DEFAULT_ALIAS_BATCH_SIZE: usize = 100;
+
+/// Settings of a GraphQL attack
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GraphQlAttackConfig {
+    /// Introspection result (`{"data": {"__schema": ...}}`); without one the
+    /// captured request is probed
+    #[serde(default)]
+    pub introspection: Option<String>,
+    /// Aliased copies per alias batching probe; 0 skips alias batching
+    #[serde(default = "default_alias_batch_size")]
+    pub alias_batch_size: usize,
+    /// Injection strings; empty uses [`DEFAULT_INJECTION_PAYLOADS`]
+    #[serde(default)]
+    pub injection_payloads: Vec<String>,
+}
+
+fn default_alias_batch_size() -> usize {
+    DEFAULT_ALIAS_BATCH_SIZE
+}
+
+impl Default for GraphQlAttackConfig {
+    fn default() -> Self {
+        Self {
+            introspection: None,
+            alias_batch_size: DEFAULT_ALIAS_BATCH_SIZE,
+            injection_payloads: Vec::new(),
+        }
+    }
+}
+
+impl GraphQlAttackConfig {
+    fn injection_payloads(&self) -> Vec<String> {
+        if self.injection_payloads.is_empty() {
+            DEFAULT_INJECTION_PAYLOADS.iter().map(|p| p.to_string()).collect()
+        } else {
+            self.injection_payloads.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphQlProbeKind {
+    TypeConfusion,
+    Injection,
+    AliasBatching,
+}
+
+impl GraphQlProbeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GraphQlProbeKind::TypeConfusion => "type_confusion",
+            GraphQlProbeKind::Injection => "injection",
+            GraphQlProbeKind::AliasBatching => "alias_batching",
+        }
+    }
+}
+
+/// One GraphQL request to send
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphQlProbe {
+    pub kind: GraphQlProbeKind,
+    /// What is probed: `$variable`, `field(argument)`, `Mutation.field(argument)`
+    /// or a bare field for alias batching
+    pub target: String,
+    /// Value sent, or the number of aliases
+    pub payload: String,
+    /// JSON request body
+    pub body: Value,
+}
+
+impl GraphQlProbe {
+    /// Payload values recorded with the probe's results
+    pub fn payload_values(&self) -> HashMap<String, String> {
+        HashMap::from([
+            ("kind".to_string(), self.kind.as_str().to_string()),
+            ("target".to_string(), self.target.clone()),
+            ("payload".to_string(), self.payload.clone()),
+        ])
+    }
+}
+
+// ============================================================================
+// QUERY SCANNING
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Name,
+    Punct,
+    Spread,
+    String,
+    Number,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: TokenKind,
+    text: &'a str,
+    start: usize,
+    end: usize,
+}
+
+impl Token<'_> {
+    fn is(&self, punct: &str) -> bool {
+        self.kind == TokenKind::Punct && self.text == punct
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> AttackError {
+    AttackError::InvalidAttackConfig { reason: reason.into() }
+}
+
+fn tokenize(src: &str) -> AttackResult<Vec<Token<'_>>> {
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' | b',' => {
+                i += 1;
+                continue;
+            }
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            b'"' if src[i..].starts_with("\"\"\"") => {
+                let close = src[i + 3..]
+                    .find("\"\"\"")
+                    .ok_or_else(|| invalid("Unterminated block string in GraphQL query"))?;
+                i += close + 6;
+                TokenKind::String
+            }
+            b'"' => {
+                i += 1;
+                loop {
+                    match bytes.get(i) {
+                        Some(b'\\') => i += 2,
+                        Some(b'"') => break,
+                        Some(b'\n') | None => return Err(invalid("Unterminated string in GraphQL query")),
+                        Some(_) => i += 1,
+                    }
+                }
+                i += 1;
+                TokenKind::String
+            }
+            b'.' if src[i..].starts_with("...") => {
+                i += 3;
+                TokenKind::Spread
+            }
+            b'-' | b'0'..=b'9' => {
+                i += 1;
+                while i < bytes.len() && matches!(bytes[i], b'0'..=b'9' | b'.' | b'e' | b'E' | b'+' | b'-') {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            b'_' | b'a'..=b'z' | b'A'..=b'Z' => {
+                while i < bytes.len() && (bytes[i] == b'_' || bytes[i].is_ascii_alphanumeric()) {
+                    i += 1;
+                }
+                TokenKind::Name
+            }
+            b'{' | b'}' | b'(' | b')' | b'[' | b']' | b':' | b'=' | b'@' | b'!' | b'$' | b'|' | b'&' => {
+                i += 1;
+                TokenKind::Punct
+            }
+            // Unicode BOM and anything else the grammar ignores or rejects
+            _ => {
+                i += src[i..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            }
+        };
+        tokens.push(Token { kind, text: &src[start..i], start, end: i });
+    }
+    Ok(tokens)
+}
+
+/// Index of the bracket closing the one at `open`
+fn matching(tokens: &[Token], open: usize) -> AttackResult<usize> {
+    let (opener, closer) = match tokens[open].text {
+        "{" => ("{", "}"),
+        "(" => ("(", ")"),
+        _ => ("[", "]"),
+    };
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if token.is(opener) {
+            depth += 1;
+        } else if token.is(closer) {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(i);
+            }
+        }
+    }
+    Err(invalid(format!("Unbalanced '{}' in GraphQL query", opener)))
+}
+
+/// Skip `@directive(args)` runs starting at `i`
+fn skip_directives(tokens: &[Token], mut i: usize) -> AttackResult<usize> {
+    while tokens.get(i).is_some_and(|t| t.is("@")) {
+        i += 2;
+        if tokens.get(i).is_some_and(|t| t.is("(")) {
+            i = matching(tokens, i)? + 1;
+        }
+    }
+    Ok(i)
+}
+
+#[derive(Debug, Clone)]
+struct OperationSpan {
+    name: Option<String>,
+    /// Token indices of the root selection set's braces
+    open: usize,
+    close: usize,
+}
+
+fn operations(tokens: &[Token]) -> AttackResult<Vec<OperationSpan>> {
+    let mut operations = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i];
+        if token.is("{") {
+            let close = matching(tokens, i)?;
+            operations.push(OperationSpan { name: None, open: i, close });
+            i = close + 1;
+        } else if token.kind == TokenKind::Name && matches!(token.text, "query" | "mutation" | "subscription" | "fragment") {
+            let name = tokens.get(i + 1).filter(|t| t.kind == TokenKind::Name).map(|t| t.text.to_string());
+            let mut open = i + 1;
+            while open < tokens.len() && !tokens[open].is("{") {
+                if tokens[open].is("(") || tokens[open].is("[") {
+                    open = matching(tokens, open)?;
+                }
+                open += 1;
+            }
+            if open >= tokens.len() {
+                return Err(invalid("GraphQL operation without a selection set"));
+            }
+            let close = matching(tokens, open)?;
+            if token.text != "fragment" {
+                operations.push(OperationSpan { name, open, close });
+            }
+            i = close + 1;
+        } else {
+            i += 1;
+        }
+    }
+    Ok(operations)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum ArgumentValue {
+    Variable,
+    /// Byte range of a string literal, quotes included
+    String(usize, usize),
+    Other,
+}
+
+#[derive(Debug, Clone)]
+struct RootField {
+    alias: Option<String>,
+    name: String,
+    arguments: Vec<(String, ArgumentValue)>,
+    /// Bytes from the field name (after any alias) to the end of the field
+    start: usize,
+    end: usize,
+}
+
+impl RootField {
+    fn key(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.name)
+    }
+}
+
+fn root_fields(tokens: &[Token], operation: &OperationSpan) -> AttackResult<Vec<RootField>> {
+    let mut fields = Vec::new();
+    let mut i = operation.open + 1;
+    while i < operation.close {
+        if tokens[i].kind == TokenKind::Spread {
+            // Fragment spreads and inline fragments are left out
+            i += 1;
+            if tokens[i].kind == TokenKind::Name && tokens[i].text == "on" {
+                i += 2;
+            } else if tokens[i].kind == TokenKind::Name {
+                i += 1;
+            }
+            i = skip_directives(tokens, i)?;
+            if tokens[i].is("{") {
+                i = matching(tokens, i)? + 1;
+            }
+            continue;
+        }
+        if tokens[i].kind != TokenKind::Name {
+            return Err(invalid(format!("Unexpected '{}' in GraphQL selection set", tokens[i].text)));
+        }
+
+        let (alias, name_at) = if tokens[i + 1].is(":") {
+            (Some(tokens[i].text.to_string()), i + 2)
+        } else {
+            (None, i)
+        };
+        let mut field = RootField {
+            alias,
+            name: tokens[name_at].text.to_string(),
+            arguments: Vec::new(),
+            start: tokens[name_at].start,
+            end: tokens[name_at].end,
+        };
+
+        let mut j = name_at + 1;
+        if tokens[j].is("(") {
+            let close = matching(tokens, j)?;
+            let mut k = j + 1;
+            while k < close {
+                let name = tokens[k].text.to_string();
+                let value_at = k + 2;
+                let (value, next) = match tokens[value_at] {
+                    t if t.is("$") => (ArgumentValue::Variable, value_at + 2),
+                    t if t.is("{") || t.is("[") => (ArgumentValue::Other, matching(tokens, value_at)? + 1),
+                    t if t.kind == TokenKind::String => (ArgumentValue::String(t.start, t.end), value_at + 1),
+                    _ => (ArgumentValue::Other, value_at + 1),
+                };
+                field.arguments.push((name, value));
+                k = next;
+            }
+            j = close + 1;
+        }
+        j = skip_directives(tokens, j)?;
+        if tokens[j].is("{") {
+            j = matching(tokens, j)? + 1;
+        }
+        field.end = tokens[j - 1].end;
+        fields.push(field);
+        i = j;
+    }
+    Ok(fields)
+}
+
+// ============================================================================
+// CAPTURED REQUESTS
+// ============================================================================
+
+/// Values of the wrong type for a variable currently holding `value`
+fn confused_values(value: &Value) -> Vec<Value> {
+    match value {
+        Value::String(s) => vec![json!(1337), json!(true), json!([s]), json!({ "$ne": null }), Value::Null],
+        Value::Number(n) => vec![json!(n.to_string()), json!(2147483648u64), json!(1.5), json!(-1)],
+        Value::Bool(b) => vec![json!(b.to_string()), json!(1)],
+        Value::Array(items) => vec![items.first().cloned().unwrap_or(json!("a")), json!({})],
+        Value::Object(_) => vec![json!("a"), json!([])],
+        Value::Null => vec![json!("a"), json!(1)],
+    }
+}
+
+fn graphql_string(value: &str) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+/// Probes for a captured GraphQL request body
+/// (`{"query": ..., "variables": ..., "operationName": ...}`)
+pub fn captured_request_probes(body: &str, config: &GraphQlAttackConfig) -> AttackResult<Vec<GraphQlProbe>> {
+    let request: Value = serde_json::from_str(body)
+        .map_err(|e| invalid(format!("GraphQL request body is not JSON: {}", e)))?;
+    let query = request
+        .get("query")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("GraphQL request body has no query"))?;
+    let variables = request.get("variables").and_then(Value::as_object).cloned().unwrap_or_default();
+    let operation_name = request.get("operationName").and_then(Value::as_str);
+
+    let tokens = tokenize(query)?;
+    let operations = operations(&tokens)?;
+    let operation = match operation_name {
+        Some(name) => operations.iter().find(|op| op.name.as_deref() == Some(name)),
+        None => operations.first(),
+    }
+    .ok_or_else(|| invalid("GraphQL request has no operation to probe"))?;
+    let fields = root_fields(&tokens, operation)?;
+
+    let with = |query: &str, variables: &Map<String, Value>| {
+        let mut body = request.clone();
+        body["query"] = json!(query);
+        body["variables"] = Value::Object(variables.clone());
+        body
+    };
+    let injections = config.injection_payloads();
+    let mut probes = Vec::new();
+
+    for (name, value) in &variables {
+        let target = format!("${}", name);
+        for confused in confused_values(value) {
+            let mut vars = variables.clone();
+            vars.insert(name.clone(), confused.clone());
+            probes.push(GraphQlProbe {
+                kind: GraphQlProbeKind::TypeConfusion,
+                target: target.clone(),
+                payload: confused.to_string(),
+                body: with(query, &vars),
+            });
+        }
+        if value.is_string() {
+            for payload in &injections {
+                let mut vars = variables.clone();
+                vars.insert(name.clone(), json!(payload));
+                probes.push(GraphQlProbe {
+                    kind: GraphQlProbeKind::Injection,
+                    target: target.clone(),
+                    payload: payload.clone(),
+                    body: with(query, &vars),
+                });
+            }
+        }
+    }
+
+    for field in &fields {
+        for (argument, value) in &field.arguments {
+            if let ArgumentValue::String(start, end) = value {
+                for payload in &injections {
+                    let probed = format!("{}{}{}", &query[..*start], graphql_string(payload), &query[*end..]);
+                    probes.push(GraphQlProbe {
+                        kind: GraphQlProbeKind::Injection,
+                        target: format!("{}({})", field.name, argument),
+                        payload: payload.clone(),
+                        body: with(&probed, &variables),
+                    });
+                }
+            }
+        }
+    }
+
+    if config.alias_batch_size > 0 && !fields.is_empty() {
+        let mut batch = String::new();
+        for copy in 0..config.alias_batch_size {
+            for field in &fields {
+                batch.push_str(&format!(" b{}_{}: {}", copy, field.key(), &query[field.start..field.end]));
+            }
+        }
+        let batched = format!(
+            "{}{} {}",
+            &query[..tokens[operation.open].end],
+            batch,
+            &query[tokens[operation.close].start..]
+        );
+        let target = fields.iter().map(|f| f.name.as_str()).collect::<Vec<_>>().join(",");
+        probes.push(GraphQlProbe {
+            kind: GraphQlProbeKind::AliasBatching,
+            target,
+            payload: config.alias_batch_size.to_string(),
+            body: with(&batched, &variables),
+        });
+    }
+
+    Ok(probes)
+}
+
+// ============================================================================
+// INTROSPECTION
+// ============================================================================
+
+/// Argument of a root field, from introspection
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaArgument {
+    pub name: String,
+    /// Type as written in GraphQL (`[ID!]!`)
+    pub type_name: String,
+    /// Named type under any list and non-null wrappers
+    pub base_type: String,
+    pub required: bool,
+    pub is_list: bool,
+}
+
+/// Query or mutation field, from introspection
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaField {
+    /// `query` or `mutation`
+    pub operation: String,
+    /// Name of the root type (`Query`, `Mutation`, ...)
+    pub parent: String,
+    pub name: String,
+    pub arguments: Vec<SchemaArgument>,
+    /// Whether the field needs a selection set
+    pub returns_composite: bool,
+}
+
+/// Root fields and named types of an introspected schema
+#[derive(Debug, Clone, Default)]
+pub struct IntrospectedSchema {
+    pub fields: Vec<SchemaField>,
+    /// Kind (`SCALAR`, `ENUM`, `INPUT_OBJECT`, ...) of each named type
+    pub type_kinds: HashMap<String, String>,
+    /// Values of each enum type
+    pub enum_values: HashMap<String, Vec<String>>,
+}
+
+/// Type text, base name and outer non-null of an introspection type reference
+fn type_ref(value: &Value) -> (String, String, bool) {
+    match value.get("kind").and_then(Value::as_str) {
+        Some("NON_NULL") => {
+            let (text, base, _) = type_ref(&value["ofType"]);
+            (format!("{}!", text), base, true)
+        }
+        Some("LIST") => {
+            let (text, base, _) = type_ref(&value["ofType"]);
+            (format!("[{}]", text), base, false)
+        }
+        _ => {
+            let name = value.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+            (name.clone(), name, false)
+        }
+    }
+}
+
+/// Parse an introspection result, with or without the `data` envelope
+pub fn parse_introspection(json: &str) -> AttackResult<IntrospectedSchema> {
+    let value: Value = serde_json::from_str(json)
+        .map_err(|e| invalid(format!("Introspection result is not JSON: {}", e)))?;
+    let schema = value
+        .pointer("/data/__schema")
+        .or_else(|| value.get("__schema"))
+        .ok_or_else(|| invalid("Introspection result has no __schema"))?;
+    let types = schema.get("types").and_then(Value::as_array).cloned().unwrap_or_default();
+
+    let mut parsed = IntrospectedSchema::default();
+    for ty in &types {
+        let name = ty.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+        let kind = ty.get("kind").and_then(Value::as_str).unwrap_or_default().to_string();
+        if let Some(values) = ty.get("enumValues").and_then(Value::as_array) {
+            let values = values.iter().filter_map(|v| v.get("name")?.as_str().map(str::to_string)).collect();
+            parsed.enum_values.insert(name.clone(), values);
+        }
+        parsed.type_kinds.insert(name, kind);
+    }
+
+    for (operation, root) in [("query", "queryType"), ("mutation", "mutationType")] {
+        let Some(root_name) = schema.pointer(&format!("/{}/name", root)).and_then(Value::as_str) else {
+            continue;
+        };
+        let Some(root_type) = types.iter().find(|t| t.get("name").and_then(Value::as_str) == Some(root_name)) else {
+            continue;
+        };
+        for field in root_type.get("fields").and_then(Value::as_array).into_iter().flatten() {
+            let name = field.get("name").and_then(Value::as_str).unwrap_or_default();
+            if name.starts_with("__") {
+                continue;
+            }
+            let arguments = field
+                .get("args")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .map(|arg| {
+                    let (type_name, base_type, required) = type_ref(&arg["type"]);
+                    SchemaArgument {
+                        name: arg.get("name").and_then(Value::as_str).unwrap_or_default().to_string(),
+                        is_list: type_name.trim_end_matches('!').starts_with('['),
+                        type_name,
+                        base_type,
+                        required,
+                    }
+                })
+                .collect();
+            let (_, returns, _) = type_ref(&field["type"]);
+            let returns_composite = matches!(
+                parsed.type_kinds.get(&returns).map(String::as_str),
+                Some("OBJECT" | "INTERFACE" | "UNION")
+            );
+            parsed.fields.push(SchemaField {
+                operation: operation.to_string(),
+                parent: root_name.to_string(),
+                name: name.to_string(),
+                arguments,
+                returns_composite,
+            });
+        }
+    }
+    Ok(parsed)
+}
+
+impl IntrospectedSchema {
+    fn kind(&self, type_name: &str) -> &str {
+        self.type_kinds.get(type_name).map(String::as_str).unwrap_or("SCALAR")
+    }
+
+    /// A value the argument accepts, for filling required arguments.
+    /// `None` for input objects, which cannot be made up.
+    fn sample_value(&self, argument: &SchemaArgument) -> Option<Value> {
+        let value = match (argument.base_type.as_str(), self.kind(&argument.base_type)) {
+            ("Int", _) => json!(1),
+            ("Float", _) => json!(1.0),
+            ("Boolean", _) => json!(true),
+            ("ID", _) => json!("1"),
+            (_, "ENUM") => {
+                let first = self.enum_values.get(&argument.base_type)?.first()?;
+                json!(first)
+            }
+            (_, "INPUT_OBJECT") => return None,
+            _ => json!("test"),
+        };
+        Some(if argument.is_list { json!([value]) } else { value })
+    }
+
+    /// Values of the wrong type for the argument
+    fn confused_values(&self, argument: &SchemaArgument) -> Vec<Value> {
+        match (argument.base_type.as_str(), self.kind(&argument.base_type)) {
+            ("Int", _) => vec![json!("1"), json!(2147483648u64), json!(1.5), json!(-1)],
+            ("Float", _) => vec![json!("1.0"), json!("NaN"), json!(true)],
+            ("Boolean", _) => vec![json!("true"), json!(1)],
+            ("String" | "ID", _) => vec![json!(1337), json!(true), json!(["a"]), json!({ "$ne": null })],
+            (_, "ENUM") => vec![json!("NOT_A_VALUE"), json!(0)],
+            (_, "INPUT_OBJECT") => vec![json!("a"), json!([])],
+            _ => vec![json!(1337), json!("a"), json!({})],
+        }
+    }
+
+    fn takes_strings(&self, argument: &SchemaArgument) -> bool {
+        matches!(argument.base_type.as_str(), "String" | "ID")
+            || (self.kind(&argument.base_type) == "SCALAR"
+                && !matches!(argument.base_type.as_str(), "Int" | "Float" | "Boolean"))
+    }
+}
+
+/// Operation calling `field` with every argument bound to a variable, `copies` times
+fn field_operation(field: &SchemaField, copies: usize) -> String {
+    let definitions: Vec<String> =
+        field.arguments.iter().map(|a| format!("${}: {}", a.name, a.type_name)).collect();
+    let arguments: Vec<String> = field.arguments.iter().map(|a| format!("{}: ${}", a.name, a.name)).collect();
+
+    let mut call = field.name.clone();
+    if !arguments.is_empty() {
+        call.push_str(&format!("({})", arguments.join(", ")));
+    }
+    if field.returns_composite {
+        call.push_str(" { __typename }");
+    }
+
+    let mut operation = field.operation.clone();
+    if !definitions.is_empty() {
+        operation.push_str(&format!("({})", definitions.join(", ")));
+    }
+    if copies <= 1 {
+        format!("{} {{ {} }}", operation, call)
+    } else {
+        let batch: Vec<String> = (0..copies).map(|i| format!("b{}: {}", i, call)).collect();
+        format!("{} {{ {} }}", operation, batch.join(" "))
+    }
+}
+
+/// Probes for every argument of every root field of an introspected schema.
+/// Fields with a required input object argument are skipped.
+pub fn schema_probes(schema: &IntrospectedSchema, config: &GraphQlAttackConfig) -> Vec<GraphQlProbe> {
+    let injections = config.injection_payloads();
+    let mut probes = Vec::new();
+
+    for field in &schema.fields {
+        let mut variables = Map::new();
+        let mut complete = true;
+        for argument in &field.arguments {
+            match schema.sample_value(argument) {
+                Some(value) => {
+                    variables.insert(argument.name.clone(), value);
+                }
+                None if argument.required => complete = false,
+                None => {}
+            }
+        }
+        if !complete {
+            continue;
+        }
+        let query = field_operation(field, 1);
+        let body = |variables: &Map<String, Value>, query: &str| json!({ "query": query, "variables": variables });
+
+        for argument in &field.arguments {
+            let target = format!("{}.{}({})", field.parent, field.name, argument.name);
+            for confused in schema.confused_values(argument) {
+                let mut vars = variables.clone();
+                vars.insert(argument.name.clone(), confused.clone());
+                probes.push(GraphQlProbe {
+                    kind: GraphQlProbeKind::TypeConfusion,
+                    target: target.clone(),
+                    payload: confused.to_string(),
+                    body: body(&vars, &query),
+                });
+            }
+            if schema.takes_strings(argument) {
+                for payload in &injections {
+                    let mut vars = variables.clone();
+                    let value = if argument.is_list { json!([payload]) } else { json!(payload) };
+                    vars.insert(argument.name.clone(), value);
+                    probes.push(GraphQlProbe {
+                        kind: GraphQlProbeKind::Injection,
+                        target: target.clone(),
+                        payload: payload.clone(),
+                        body: body(&vars, &query),
+                    });
+                }
+            }
+        }
+
+        if config.alias_batch_size > 0 {
+            probes.push(GraphQlProbe {
+                kind: GraphQlProbeKind::AliasBatching,
+                target: format!("{}.{}", field.parent, field.name),
+                payload: config.alias_batch_size.to_string(),
+                body: body(&variables, &field_operation(field, config.alias_batch_size)),
+            });
+        }
+    }
+    probes
+}
+
+// ============================================================================
+// ATTACK MODE
+// ============================================================================
+
+/// Head and body of a raw HTTP request
+fn split_request(raw: &str) -> AttackResult<(&str, &str)> {
+    raw.split_once("\r\n\r\n")
+        .or_else(|| raw.split_once("\n\n"))
+        .ok_or_else(|| invalid("GraphQL attack template has no request body"))
+}
+
+/// `head` with its Content-Length set for `body`, followed by `body`
+fn with_body(head: &str, body: &str) -> String {
+    let newline = if head.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<&str> = head
+        .lines()
+        .filter(|line| !line.to_ascii_lowercase().starts_with("content-length:"))
+        .collect();
+    let content_length = format!("Content-Length: {}", body.len());
+    lines.push(&content_length);
+    format!("{}{}{}{}", lines.join(newline), newline, newline, body)
+}
+
+/// GraphQL mode: probes generated from the captured request or an
+/// introspection result. Payload sets are not used.
+pub struct GraphQlMode {
+    config: GraphQlAttackConfig,
+}
+
+impl GraphQlMode {
+    pub fn new(config: GraphQlAttackConfig) -> Self {
+        Self { config }
+    }
+
+    /// Probes for the request in `template`
+    pub fn probes(&self, template: &ParsedTemplate) -> AttackResult<Vec<GraphQlProbe>> {
+        match &self.config.introspection {
+            Some(introspection) => Ok(schema_probes(&parse_introspection(introspection)?, &self.config)),
+            None => {
+                let (_, body) = split_request(&template.template)?;
+                captured_request_probes(body, &self.config)
+            }
+        }
+    }
+}
+
+impl AttackModeExecutor for GraphQlMode {
+    fn generate_requests(
+        &self,
+        template: &ParsedTemplate,
+        _payload_sets: &HashMap<String, Vec<String>>,
+    ) -> AttackResult<Vec<AttackRequest>> {
+        let (head, _) = split_request(&template.template)?;
+        Ok(self
+            .probes(template)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, probe)| AttackRequest {
+                request: with_body(head, &probe.body.to_string()),
+                payload_values: probe.payload_values(),
+                index,
+            })
+            .collect())
+    }
+
+    fn count_requests(
+        &self,
+        template: &ParsedTemplate,
+        _payload_sets: &HashMap<String, Vec<String>>,
+    ) -> AttackResult<usize> {
+        Ok(self.probes(template)?.len())
+    }
+
+    fn description(&self) -> String {
+        "GraphQL: type confusion, injection and alias batching probes for each field and argument".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::PayloadPositionParser;
+
+    const CAPTURED: &str = r#"{"query":"mutation Login($user: String!, $tries: Int) { login(user: $user, password: \"hunter2\", tries: $tries) { token } me: viewer { id } }","variables":{"user":"alice","tries":3},"operationName":"Login"}"#;
+
+    fn config() -> GraphQlAttackConfig {
+        GraphQlAttackConfig {
+            introspection: None,
+            alias_batch_size: 3,
+            injection_payloads: vec!["'".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_captured_request_probes() {
+        let probes = captured_request_probes(CAPTURED, &config()).unwrap();
+        let count = |kind, target: &str| probes.iter().filter(|p| p.kind == kind && p.target == target).count();
+
+        assert_eq!(count(GraphQlProbeKind::TypeConfusion, "$user"), 5);
+        assert_eq!(count(GraphQlProbeKind::TypeConfusion, "$tries"), 4);
+        assert_eq!(count(GraphQlProbeKind::Injection, "$user"), 1);
+        assert_eq!(count(GraphQlProbeKind::Injection, "$tries"), 0);
+
+        let literal = probes.iter().find(|p| p.target == "login(password)").unwrap();
+        assert!(literal.body["query"].as_str().unwrap().contains(r#"password: "'""#));
+        assert_eq!(literal.body["variables"]["user"], "alice");
+
+        let batch = probes.iter().find(|p| p.kind == GraphQlProbeKind::AliasBatching).unwrap();
+        let query = batch.body["query"].as_str().unwrap();
+        assert!(query.starts_with("mutation Login($user: String!, $tries: Int) {"));
+        assert!(query.contains("b2_login: login(user: $user"));
+        assert!(query.contains("b0_me: viewer { id }"));
+        assert_eq!(query.matches("login(").count(), 3);
+        assert_eq!(batch.body["operationName"], "Login");
+        assert_eq!(batch.target, "login,viewer");
+    }
+
+    #[test]
+    fn test_schema_probes() {
+        let introspection = r#"{"data":{"__schema":{
+            "queryType":{"name":"Query"},"mutationType":{"name":"Mutation"},
+            "types":[
+                {"kind":"OBJECT","name":"Query","fields":[
+                    {"name":"user","args":[{"name":"id","type":{"kind":"NON_NULL","ofType":{"kind":"SCALAR","name":"ID"}}}],
+                     "type":{"kind":"OBJECT","name":"User"}},
+                    {"name":"search","args":[{"name":"filter","type":{"kind":"NON_NULL","ofType":{"kind":"INPUT_OBJECT","name":"Filter"}}}],
+                     "type":{"kind":"LIST","ofType":{"kind":"OBJECT","name":"User"}}}
+                ]},
+                {"kind":"OBJECT","name":"Mutation","fields":[
+                    {"name":"setRole","args":[
+                        {"name":"id","type":{"kind":"NON_NULL","ofType":{"kind":"SCALAR","name":"ID"}}},
+                        {"name":"role","type":{"kind":"ENUM","name":"Role"}}],
+                     "type":{"kind":"SCALAR","name":"Boolean"}}
+                ]},
+                {"kind":"OBJECT","name":"User","fields":[]},
+                {"kind":"INPUT_OBJECT","name":"Filter"},
+                {"kind":"ENUM","name":"Role","enumValues":[{"name":"ADMIN"},{"name":"USER"}]},
+                {"kind":"SCALAR","name":"ID"},{"kind":"SCALAR","name":"Boolean"}
+            ]}}}"#;
+
+        let schema = parse_introspection(introspection).unwrap();
+        assert_eq!(schema.fields.len(), 3);
+        assert_eq!(schema.fields[0].arguments[0].type_name, "ID!");
+
+        let probes = schema_probes(&schema, &config());
+        assert!(probes.iter().all(|p| !p.target.starts_with("Query.search")));
+
+        let role = probes
+            .iter()
+            .find(|p| p.target == "Mutation.setRole(role)" && p.payload == "\"NOT_A_VALUE\"")
+            .unwrap();
+        assert_eq!(role.body["query"], "mutation($id: ID!, $role: Role) { setRole(id: $id, role: $role) }");
+        assert_eq!(role.body["variables"]["id"], "1");
+
+        let injection = probes.iter().find(|p| p.kind == GraphQlProbeKind::Injection && p.target == "Query.user(id)").unwrap();
+        assert_eq!(injection.body["variables"]["id"], "'");
+
+        let batch = probes.iter().find(|p| p.kind == GraphQlProbeKind::AliasBatching && p.target == "Query.user").unwrap();
+        assert_eq!(
+            batch.body["query"],
+            "query($id: ID!) { b0: user(id: $id) { __typename } b1: user(id: $id) { __typename } b2: user(id: $id) { __typename } }"
+        );
+    }
+
+    #[test]
+    fn test_graphql_mode_requests() {
+        let raw = format!(
+            "POST /graphql HTTP/1.1\r\nHost: api.test\r\nContent-Type: application/json\r\ncontent-length: 12\r\n\r\n{}",
+            CAPTURED
+        );
+        let template = PayloadPositionParser::parse(&raw).unwrap();
+        let mode = GraphQlMode::new(config());
+        let requests = mode.generate_requests(&template, &HashMap::new()).unwrap();
+
+        assert_eq!(requests.len(), mode.count_requests(&template, &HashMap::new()).unwrap());
+        let (head, body) = requests[0].request.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("POST /graphql HTTP/1.1\r\nHost: api.test"));
+        assert!(head.ends_with(&format!("Content-Length: {}", body.len())));
+        assert!(!head.contains("content-length: 12"));
+        assert_eq!(requests[0].payload_values["kind"], "type_confusion");
+    }
+}
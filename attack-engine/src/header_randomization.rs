@@ -0,0 +1,211 @@
+//! Per-request header randomization
+//!
+//! Targets behind naive bot detection block a client that sends thousands of
+//! requests with the same fingerprint. An attack can vary, on every request it
+//! generates, the `User-Agent` (picked from a list), `X-Forwarded-For` (a
+//! random public IPv4 address) and `Accept-Language` (the request's primary
+//! language followed by a few others with jittered quality values).
+
+use crate::types::HttpRequestData;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
+
+/// Languages appended after the primary language of `Accept-Language`
+const SECONDARY_LANGUAGES: &[&str] = &["en", "de", "fr", "es", "it", "nl", "pt", "pl", "sv", "ja"];
+
+/// Headers to randomize on every request of an attack
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderRandomizationConfig {
+    /// `User-Agent` values, one picked at random per request; the request's
+    /// own header is kept when empty
+    pub user_agents: Vec<String>,
+    /// Send a random public IPv4 address as `X-Forwarded-For`
+    pub random_forwarded_for: bool,
+    /// Vary the secondary languages and quality values of `Accept-Language`
+    pub jitter_accept_language: bool,
+}
+
+impl HeaderRandomizationConfig {
+    /// Whether any header is randomized
+    pub fn is_enabled(&self) -> bool {
+        !self.user_agents.is_empty() || self.random_forwarded_for || self.jitter_accept_language
+    }
+
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        for user_agent in &self.user_agents {
+            if user_agent.trim().is_empty() {
+                return Err("User-Agent values must not be empty".to_string());
+            }
+            if user_agent.chars().any(|c| c.is_control()) {
+                return Err(format!("User-Agent {:?} contains control characters", user_agent));
+            }
+        }
+        Ok(())
+    }
+
+    /// Randomize the configured headers of `request`
+    pub fn apply(&self, request: &mut HttpRequestData) {
+        self.apply_with(request, &mut rand::thread_rng());
+    }
+
+    fn apply_with<R: Rng>(&self, request: &mut HttpRequestData, rng: &mut R) {
+        if let Some(user_agent) = self.user_agents.choose(rng) {
+            replace_header(request, "User-Agent", user_agent.clone());
+        }
+        if self.random_forwarded_for {
+            replace_header(request, "X-Forwarded-For", public_ipv4(rng).to_string());
+        }
+        if self.jitter_accept_language {
+            let current = header(request, "Accept-Language");
+            let primary = current
+                .as_deref()
+                .and_then(|value| value.split(',').next())
+                .map(|tag| tag.split(';').next().unwrap_or_default().trim().to_string())
+                .filter(|tag| !tag.is_empty() && tag != "*")
+                .unwrap_or_else(|| "en-US".to_string());
+            replace_header(request, "Accept-Language", accept_language(&primary, rng));
+        }
+    }
+}
+
+/// Value of header `name`, whatever its case
+fn header(request: &HttpRequestData, name: &str) -> Option<String> {
+    let headers = request.headers.as_ref()?;
+    headers
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone())
+}
+
+/// Set header `name`, dropping any spelling of it in another case
+fn replace_header(request: &mut HttpRequestData, name: &str, value: String) {
+    if let Some(headers) = request.headers.as_mut() {
+        headers.headers.retain(|key, _| !key.eq_ignore_ascii_case(name));
+    }
+    request.set_header(name.to_string(), value);
+}
+
+/// Random address outside the private, loopback, link-local, shared,
+/// documentation, multicast and reserved ranges
+fn public_ipv4<R: Rng>(rng: &mut R) -> Ipv4Addr {
+    loop {
+        let address = Ipv4Addr::from(rng.gen::<u32>());
+        let [a, b, c, _] = address.octets();
+        let reserved = a == 0
+            || a == 10
+            || a == 127
+            || a >= 224
+            || (a == 100 && (64..128).contains(&b))
+            || (a == 169 && b == 254)
+            || (a == 172 && (16..32).contains(&b))
+            || (a == 192 && b == 168)
+            || (a == 192 && b == 0 && (c == 0 || c == 2))
+            || (a == 198 && (b == 18 || b == 19))
+            || (a == 198 && b == 51 && c == 100)
+            || (a == 203 && b == 0 && c == 113);
+        if !reserved {
+            return address;
+        }
+    }
+}
+
+/// `primary` followed by up to three other languages with descending,
+/// randomly spaced quality values
+fn accept_language<R: Rng>(primary: &str, rng: &mut R) -> String {
+    let base = primary.split('-').next().unwrap_or(primary).to_ascii_lowercase();
+    let mut languages: Vec<String> = Vec::new();
+    if !base.eq_ignore_ascii_case(primary) {
+        languages.push(base.clone());
+    }
+    let others: Vec<&&str> = SECONDARY_LANGUAGES.iter().filter(|language| **language != base).collect();
+    let count = rng.gen_range(0..=3);
+    languages.extend(others.choose_multiple(rng, count).map(|language| language.to_string()));
+
+    let mut value = primary.to_string();
+    let mut quality = 10u32;
+    for language in languages {
+        quality = quality.saturating_sub(rng.gen_range(1..=2)).max(1);
+        value.push_str(&format!(",{};q=0.{}", language, quality));
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::HttpHeaders;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::collections::HashMap;
+
+    fn request(headers: &[(&str, &str)]) -> HttpRequestData {
+        let mut request = HttpRequestData::new("GET".to_string(), "https://target.test/".to_string());
+        request.headers = Some(HttpHeaders {
+            headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+        });
+        request
+    }
+
+    #[test]
+    fn test_headers_are_replaced_whatever_their_case() {
+        let config = HeaderRandomizationConfig {
+            user_agents: vec!["agent-a".to_string(), "agent-b".to_string()],
+            random_forwarded_for: true,
+            jitter_accept_language: true,
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut seen = Vec::new();
+        for _ in 0..20 {
+            let mut req = request(&[("user-agent", "curl/8.0"), ("accept-language", "de-DE,de;q=0.9")]);
+            config.apply_with(&mut req, &mut rng);
+            let headers = &req.headers.as_ref().unwrap().headers;
+            assert_eq!(headers.len(), 3);
+
+            let user_agent = headers["User-Agent"].clone();
+            assert!(config.user_agents.contains(&user_agent));
+            seen.push(user_agent);
+
+            let address: Ipv4Addr = headers["X-Forwarded-For"].parse().unwrap();
+            assert!(!address.is_private() && !address.is_loopback() && !address.is_multicast());
+
+            let language = &headers["Accept-Language"];
+            assert!(language.starts_with("de-DE,de;q=0."), "{}", language);
+        }
+        assert!(seen.iter().any(|ua| ua == "agent-a") && seen.iter().any(|ua| ua == "agent-b"));
+    }
+
+    #[test]
+    fn test_accept_language_quality_descends() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..50 {
+            let value = accept_language("en-US", &mut rng);
+            let qualities: Vec<u32> = value
+                .split(',')
+                .skip(1)
+                .map(|part| part.split_once(";q=0.").unwrap().1.parse().unwrap())
+                .collect();
+            assert!(value.starts_with("en-US,en;q=0."), "{}", value);
+            assert!(qualities.windows(2).all(|pair| pair[0] >= pair[1]), "{}", value);
+            assert!(!value[6..].contains("en-US"));
+        }
+        // Without an Accept-Language header the default primary language is used
+        let config = HeaderRandomizationConfig { jitter_accept_language: true, ..Default::default() };
+        let mut req = HttpRequestData::new("GET".to_string(), "https://target.test/".to_string());
+        config.apply(&mut req);
+        assert!(req.get_header("Accept-Language").unwrap().starts_with("en-US"));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(!HeaderRandomizationConfig::default().is_enabled());
+        let blank = HeaderRandomizationConfig { user_agents: vec![" ".to_string()], ..Default::default() };
+        assert!(blank.validate().is_err());
+        let injected = HeaderRandomizationConfig { user_agents: vec!["a\r\nX-Evil: 1".to_string()], ..Default::default() };
+        assert!(injected.validate().is_err());
+    }
+}
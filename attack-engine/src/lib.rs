@@ -11,14 +11,22 @@ pub mod resource;
 pub mod payload;
 pub mod parser;
 pub mod attack_modes;
+pub mod graphql;
 pub mod security;
+pub mod upload;
+pub mod rate_limit;
+pub mod raw_request;
+pub mod header_randomization;
+pub mod discovery;
+pub mod blind_timing;
+pub mod tampering;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export core types with explicit names to avoid conflicts
 pub use types::{
-    HttpRequestData, HttpResponseData, HttpHeaders, TlsDetails,
+    HttpRequestData, HttpResponseData, HttpHeaders, ResponseTiming, TlsDetails,
     AttackRequest, ExecutionConfig, DistributionStrategy,
     AttackResult as AttackResultData, // Rename to avoid conflict with error::AttackResult
     AgentInfo, AgentStatus, AttackContext, ModuleType, Priority
@@ -44,11 +52,11 @@ pub use resource::{
 
 pub use payload::{
     PayloadGenerator, PayloadConfig, WordlistGenerator, NumberRangeGenerator, 
-    CustomGenerator, PayloadGeneratorFactory
+    CustomGenerator, PayloadGeneratorFactory, PayloadProcessor, PayloadProcessorChain, ProcessedGenerator
 };
 
 pub use parser::{
-    PayloadPosition, ParsedTemplate, PayloadPositionParser, TemplateUtils
+    PayloadLocation, PayloadPosition, ParsedTemplate, PayloadPositionParser, TemplateUtils
 };
 
 pub use attack_modes::{
@@ -56,6 +64,29 @@ pub use attack_modes::{
     SniperMode, BatteringRamMode, PitchforkMode, ClusterBombMode, AttackModeFactory
 };
 
+pub use graphql::{
+    GraphQlAttackConfig, GraphQlMode, GraphQlProbe, GraphQlProbeKind, IntrospectedSchema, SchemaArgument, SchemaField
+};
+
+pub use upload::{
+    UploadBody, UploadCatalog, UploadCatalogGenerator, UploadField, UploadPartTemplate, UploadPosition,
+    UploadSource, UploadTemplate
+};
+
+pub use rate_limit::{PacingProfile, RateLimitConfig, RateLimiter};
+
+pub use raw_request::{RawHeader, RawRequest};
+
+pub use header_randomization::HeaderRandomizationConfig;
+
+pub use blind_timing::{ConfidenceLevel, DelayEstimate, TimingConfirmation, TimingPair};
+
+pub use discovery::{
+    DiscoveryConfig, DiscoveryMode, DiscoveryRequest, DiscoveryVerdict, ResponseSignature, Soft404Baseline
+};
+
+pub use tampering::{assess, tamper_probes, TamperBaseline, TamperChecks, TamperEffect, TamperKind, TamperProbe};
+
 pub use security::{
     SecurityManager, MaskingConfig, SecureString, SecurityViolation, 
     ViolationType, Severity
@@ -2,6 +2,13 @@
 //! 
 //! This module provides functionality to parse and validate payload positions
 //! marked with §marker§ syntax in request templates.
+//!
+//! Markers may sit anywhere in the raw request. Where they sit decides how a
+//! payload is encoded when injected: characters that would end a path
+//! segment, query parameter or cookie early (spaces, `?`, `&`, `;`, ...) are
+//! percent-encoded there, while `/`, `.` and `%` are left alone so traversal
+//! sequences and pre-encoded payloads reach the target as written. Payloads
+//! in other headers and in the body are injected as is.
 
 use crate::error::{AttackError, AttackResult};
 use serde::{Deserialize, Serialize};
@@ -20,6 +27,105 @@ pub struct PayloadPosition {
     pub payload_set_id: String,
     /// Position index for ordering
     pub index: usize,
+    /// Part of the request the marker is in
+    #[serde(default)]
+    pub location: PayloadLocation,
+}
+
+/// Part of a raw request a payload position is in
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PayloadLocation {
+    /// Path of the request target
+    Path,
+    QueryName,
+    QueryValue,
+    /// Name of a pair in a `Cookie` header
+    CookieName,
+    /// Value of a pair in a `Cookie` header
+    CookieValue,
+    /// Any other header, the method or the HTTP version
+    Header,
+    #[default]
+    Body,
+}
+
+impl PayloadLocation {
+    /// Location of byte offset `offset` of the raw request `template`
+    pub fn at(template: &str, offset: usize) -> Self {
+        let head_end = template
+            .find("\r\n\r\n")
+            .into_iter()
+            .chain(template.find("\n\n"))
+            .min()
+            .unwrap_or(template.len());
+        if offset >= head_end {
+            return PayloadLocation::Body;
+        }
+
+        let line_start = template[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = template[offset..].find(['\r', '\n']).map_or(template.len(), |i| offset + i);
+        let line = &template[line_start..line_end];
+        let column = offset - line_start;
+
+        if line_start == 0 {
+            // Request line: METHOD SP target SP version
+            let target_start = match line.find(' ') {
+                Some(space) => space + 1,
+                None => return PayloadLocation::Header,
+            };
+            let target_end = line.rfind(' ').filter(|end| *end >= target_start).unwrap_or(line.len());
+            if column < target_start || column > target_end {
+                return PayloadLocation::Header;
+            }
+            let before = &line[target_start..column];
+            return match before.find('?') {
+                None => PayloadLocation::Path,
+                Some(query) => {
+                    let parameter = &before[query + 1..];
+                    let parameter = parameter.rsplit('&').next().unwrap_or(parameter);
+                    if parameter.contains('=') {
+                        PayloadLocation::QueryValue
+                    } else {
+                        PayloadLocation::QueryName
+                    }
+                }
+            };
+        }
+
+        match line.split_once(':') {
+            Some((name, _)) if column > name.len() && name.trim().eq_ignore_ascii_case("cookie") => {
+                let value = &line[name.len() + 1..column];
+                let pair = value.rsplit(';').next().unwrap_or(value);
+                if pair.contains('=') {
+                    PayloadLocation::CookieValue
+                } else {
+                    PayloadLocation::CookieName
+                }
+            }
+            _ => PayloadLocation::Header,
+        }
+    }
+
+    /// `payload` encoded for this location
+    pub fn encode(&self, payload: &str) -> String {
+        let reserved: &[u8] = match self {
+            PayloadLocation::Path => b"?#",
+            PayloadLocation::QueryName => b"&=#+",
+            PayloadLocation::QueryValue => b"&#+",
+            PayloadLocation::CookieName => b";,\"\\=",
+            PayloadLocation::CookieValue => b";,\"\\",
+            PayloadLocation::Header | PayloadLocation::Body => return payload.to_string(),
+        };
+        let mut encoded = String::with_capacity(payload.len());
+        for byte in payload.bytes() {
+            if byte.is_ascii_graphic() && !reserved.contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        encoded
+    }
 }
 
 /// Result of parsing payload positions from a template
@@ -74,6 +180,7 @@ impl PayloadPositionParser {
                         marker: marker_content.to_string(),
                         payload_set_id: payload_id.to_string(),
                         index: position_index,
+                        location: PayloadLocation::at(template, absolute_start),
                     };
                     
                     positions.push(position);
@@ -138,26 +245,35 @@ impl PayloadPositionParser {
         Ok(())
     }
     
-    /// Inject payloads into a parsed template
+    /// Inject payloads into a parsed template, each encoded for its location
     pub fn inject_payloads(
         parsed: &ParsedTemplate,
         payload_values: &HashMap<String, String>,
     ) -> AttackResult<String> {
-        let mut result = parsed.processed_template.clone();
-        
-        // Replace placeholders with actual payload values
-        for position in &parsed.positions {
-            let placeholder = format!("{{PAYLOAD_{}}}", position.index);
-            
+        let mut positions: Vec<&PayloadPosition> = parsed.positions.iter().collect();
+        positions.sort_by_key(|position| position.start);
+
+        // Splice the payloads into the original template, so payloads that
+        // look like placeholders are not replaced again
+        let mut result = String::with_capacity(parsed.template.len());
+        let mut copied = 0;
+        for position in positions {
             let payload_value = payload_values
                 .get(&position.payload_set_id)
                 .ok_or_else(|| AttackError::InvalidPayloadConfig {
                     reason: format!("No payload value provided for marker: {}", position.payload_set_id),
                 })?;
-            
-            result = result.replace(&placeholder, payload_value);
+
+            // Positions outside the template text (upload parts) are filled elsewhere
+            if position.start == position.end {
+                continue;
+            }
+            result.push_str(&parsed.template[copied..position.start]);
+            result.push_str(&position.location.encode(payload_value));
+            copied = position.end;
         }
-        
+        result.push_str(&parsed.template[copied..]);
+
         Ok(result)
     }
     
@@ -361,6 +477,53 @@ mod tests {
         assert_eq!(highlights[1].2, "action");
     }
     
+    #[test]
+    fn test_payload_locations() {
+        let template = "GET /files/§dir§/x?§key§=§value§&b=§b§ HTTP/1.1\r\nHost: target.test\r\nX-Id: §id§\r\nCookie: a=1; §name§=§session§\r\n\r\nq=§body§";
+        let parsed = PayloadPositionParser::parse(template).unwrap();
+        let locations: Vec<(&str, PayloadLocation)> =
+            parsed.positions.iter().map(|p| (p.payload_set_id.as_str(), p.location)).collect();
+        assert_eq!(
+            locations,
+            vec![
+                ("dir", PayloadLocation::Path),
+                ("key", PayloadLocation::QueryName),
+                ("value", PayloadLocation::QueryValue),
+                ("b", PayloadLocation::QueryValue),
+                ("id", PayloadLocation::Header),
+                ("name", PayloadLocation::CookieName),
+                ("session", PayloadLocation::CookieValue),
+                ("body", PayloadLocation::Body),
+            ]
+        );
+
+        // Bare LF line endings, method and version positions
+        let parsed = PayloadPositionParser::parse("§verb§ /§p§ HTTP/§v§\ncookie:§c§\n\n").unwrap();
+        let locations: Vec<PayloadLocation> = parsed.positions.iter().map(|p| p.location).collect();
+        assert_eq!(
+            locations,
+            vec![PayloadLocation::Header, PayloadLocation::Path, PayloadLocation::Header, PayloadLocation::CookieName]
+        );
+    }
+
+    #[test]
+    fn test_payloads_are_encoded_for_their_location() {
+        assert_eq!(PayloadLocation::Path.encode("../../etc/passwd"), "../../etc/passwd");
+        assert_eq!(PayloadLocation::Path.encode("..%2f a?b#c"), "..%2f%20a%3Fb%23c");
+        assert_eq!(PayloadLocation::QueryName.encode("a=b&c"), "a%3Db%26c");
+        assert_eq!(PayloadLocation::QueryValue.encode("1=1 OR 'x'+1"), "1=1%20OR%20'x'%2B1");
+        assert_eq!(PayloadLocation::QueryValue.encode("é"), "%C3%A9");
+        assert_eq!(PayloadLocation::CookieName.encode("a=b"), "a%3Db");
+        assert_eq!(PayloadLocation::CookieValue.encode("x; admin=1, \"q\"\\"), "x%3B%20admin=1%2C%20%22q%22%5C");
+        assert_eq!(PayloadLocation::Header.encode("a b\r\nX: 1"), "a b\r\nX: 1");
+        assert_eq!(PayloadLocation::Body.encode("{\"a\": 1}"), "{\"a\": 1}");
+
+        // Payloads looking like placeholders are injected as they are
+        let parsed = PayloadPositionParser::parse("GET /§a§/§b§ HTTP/1.1").unwrap();
+        let values = HashMap::from([("a".to_string(), "{PAYLOAD_1}".to_string()), ("b".to_string(), "x".to_string())]);
+        assert_eq!(PayloadPositionParser::inject_payloads(&parsed, &values).unwrap(), "GET /{PAYLOAD_1}/x HTTP/1.1");
+    }
+
     #[test]
     fn test_template_utils() {
         let template_with_markers = "GET /api/§endpoint§ HTTP/1.1";
@@ -4,8 +4,12 @@
 //! used in fuzzing and brute-force attacks.
 
 use crate::error::{AttackError, AttackResult};
+use crate::upload::{UploadCatalog, UploadCatalogGenerator};
 use async_trait::async_trait;
+use base64::Engine as _;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sha2::Digest;
 use std::path::Path;
 use tokio::fs;
 
@@ -41,6 +45,15 @@ pub enum PayloadConfig {
     Custom { 
         values: Vec<String> 
     },
+    /// Built-in malicious-file payloads for multipart upload positions
+    UploadCatalog {
+        catalog: UploadCatalog
+    },
+    /// Payloads of `source` passed through `processors` in order
+    Processed {
+        source: Box<PayloadConfig>,
+        processors: Vec<PayloadProcessor>,
+    },
 }
 
 /// Generator for file-based wordlist payloads
@@ -339,6 +352,130 @@ impl PayloadGenerator for CustomGenerator {
     }
 }
 
+/// A transformation applied to each generated payload, like Burp's payload
+/// processing rules
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PayloadProcessor {
+    /// Percent-encode all but unreserved characters (`A-Z a-z 0-9 - . _ ~`)
+    UrlEncode,
+    /// Percent-encode every byte
+    UrlEncodeAll,
+    Base64,
+    /// Lowercase hex of the UTF-8 bytes
+    Hex,
+    /// Lowercase hex digests
+    Md5,
+    Sha1,
+    Sha256,
+    Lowercase,
+    Uppercase,
+    Prefix { value: String },
+    Suffix { value: String },
+    /// Replace every match of `pattern`; `replacement` may use `$1`-style groups
+    RegexReplace { pattern: String, replacement: String },
+}
+
+fn percent_encode(payload: &str, all: bool) -> String {
+    let mut encoded = String::with_capacity(payload.len());
+    for &byte in payload.as_bytes() {
+        if !all && (byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~')) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn hex_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Processors with their regexes compiled, ready to apply
+#[derive(Debug, Clone)]
+pub struct PayloadProcessorChain {
+    steps: Vec<(PayloadProcessor, Option<Regex>)>,
+}
+
+impl PayloadProcessorChain {
+    /// Compile a chain; fails on an invalid regex
+    pub fn new(processors: Vec<PayloadProcessor>) -> AttackResult<Self> {
+        let steps = processors
+            .into_iter()
+            .map(|processor| {
+                let regex = match &processor {
+                    PayloadProcessor::RegexReplace { pattern, .. } => {
+                        Some(Regex::new(pattern).map_err(|e| AttackError::InvalidPayloadConfig {
+                            reason: format!("Invalid processor regex '{}': {}", pattern, e),
+                        })?)
+                    }
+                    _ => None,
+                };
+                Ok((processor, regex))
+            })
+            .collect::<AttackResult<Vec<_>>>()?;
+        Ok(Self { steps })
+    }
+
+    /// Run `payload` through every processor in order
+    pub fn apply(&self, payload: &str) -> String {
+        self.steps.iter().fold(payload.to_string(), |current, (processor, regex)| match processor {
+            PayloadProcessor::UrlEncode => percent_encode(&current, false),
+            PayloadProcessor::UrlEncodeAll => percent_encode(&current, true),
+            PayloadProcessor::Base64 => base64::engine::general_purpose::STANDARD.encode(current.as_bytes()),
+            PayloadProcessor::Hex => hex_string(current.as_bytes()),
+            PayloadProcessor::Md5 => hex_string(&md5::Md5::digest(current.as_bytes())),
+            PayloadProcessor::Sha1 => hex_string(&sha1::Sha1::digest(current.as_bytes())),
+            PayloadProcessor::Sha256 => hex_string(&sha2::Sha256::digest(current.as_bytes())),
+            PayloadProcessor::Lowercase => current.to_lowercase(),
+            PayloadProcessor::Uppercase => current.to_uppercase(),
+            PayloadProcessor::Prefix { value } => format!("{}{}", value, current),
+            PayloadProcessor::Suffix { value } => format!("{}{}", current, value),
+            PayloadProcessor::RegexReplace { replacement, .. } => match regex {
+                Some(regex) => regex.replace_all(&current, replacement.as_str()).into_owned(),
+                None => current,
+            },
+        })
+    }
+}
+
+/// Generator that processes the payloads of another generator
+pub struct ProcessedGenerator {
+    source: Box<dyn PayloadGenerator>,
+    chain: PayloadProcessorChain,
+}
+
+impl ProcessedGenerator {
+    /// Wrap `source`, applying `processors` to each of its payloads
+    pub fn new(source: Box<dyn PayloadGenerator>, processors: Vec<PayloadProcessor>) -> AttackResult<Self> {
+        Ok(Self {
+            source,
+            chain: PayloadProcessorChain::new(processors)?,
+        })
+    }
+}
+
+#[async_trait]
+impl PayloadGenerator for ProcessedGenerator {
+    async fn generate(&self) -> AttackResult<Vec<String>> {
+        let payloads = self.source.generate().await?;
+        Ok(payloads.iter().map(|payload| self.chain.apply(payload)).collect())
+    }
+
+    async fn count(&self) -> AttackResult<usize> {
+        self.source.count().await
+    }
+
+    fn description(&self) -> String {
+        format!("{} ({} processor(s))", self.source.description(), self.chain.steps.len())
+    }
+
+    fn validate(&self) -> AttackResult<()> {
+        self.source.validate()
+    }
+}
+
 /// Factory for creating payload generators from configuration
 pub struct PayloadGeneratorFactory;
 
@@ -355,6 +492,18 @@ impl PayloadGeneratorFactory {
             PayloadConfig::Custom { .. } => {
                 Ok(Box::new(CustomGenerator::from_config(config)?))
             }
+            PayloadConfig::UploadCatalog { catalog } => {
+                Ok(Box::new(UploadCatalogGenerator::new(catalog.clone())))
+            }
+            PayloadConfig::Processed { source, processors } => {
+                // Upload payloads are structured multipart bodies, not text
+                if matches!(**source, PayloadConfig::UploadCatalog { .. } | PayloadConfig::Processed { .. }) {
+                    return Err(AttackError::InvalidPayloadConfig {
+                        reason: "Processors apply to wordlist, number range and custom payloads only".to_string(),
+                    });
+                }
+                Ok(Box::new(ProcessedGenerator::new(Self::create(source)?, processors.clone())?))
+            }
         }
     }
 }
@@ -466,4 +615,60 @@ mod tests {
         let generator = PayloadGeneratorFactory::create(&custom_config);
         assert!(generator.is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_processor_chain() {
+        let apply = |processors: Vec<PayloadProcessor>, payload: &str| {
+            PayloadProcessorChain::new(processors).unwrap().apply(payload)
+        };
+
+        assert_eq!(apply(vec![PayloadProcessor::UrlEncode], "a b&c=ü~"), "a%20b%26c%3D%C3%BC~");
+        assert_eq!(apply(vec![PayloadProcessor::UrlEncodeAll], "ab"), "%61%62");
+        assert_eq!(apply(vec![PayloadProcessor::Base64], "admin:admin"), "YWRtaW46YWRtaW4=");
+        assert_eq!(apply(vec![PayloadProcessor::Hex], "AZ"), "415a");
+        assert_eq!(apply(vec![PayloadProcessor::Md5], "password"), "5f4dcc3b5aa765d61d8327deb882cf99");
+        assert_eq!(apply(vec![PayloadProcessor::Sha1], "password"), "5baa61e4c9b93f3f0682250b6cf8331b7ee68fd8");
+        assert_eq!(
+            apply(vec![PayloadProcessor::Sha256], "password"),
+            "5e884898da28047151d0e56f8dc6292773603d0d6aabbdd62a11ef721d1542d8"
+        );
+
+        // Burp-style chain: add a prefix, hash, then wrap
+        let chain = vec![
+            PayloadProcessor::Uppercase,
+            PayloadProcessor::Prefix { value: "user:".to_string() },
+            PayloadProcessor::RegexReplace { pattern: r"(\w+):(\w+)".to_string(), replacement: "$2=$1".to_string() },
+            PayloadProcessor::Suffix { value: ";".to_string() },
+            PayloadProcessor::Lowercase,
+        ];
+        assert_eq!(apply(chain, "bob"), "bob=user;");
+
+        assert!(PayloadProcessorChain::new(vec![PayloadProcessor::RegexReplace {
+            pattern: "(".to_string(),
+            replacement: String::new(),
+        }])
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_processed_payload_config() {
+        let config: PayloadConfig = serde_json::from_value(serde_json::json!({
+            "Processed": {
+                "source": { "Custom": { "values": ["a", "b"] } },
+                "processors": [{ "type": "base64" }, { "type": "suffix", "value": "!" }]
+            }
+        }))
+        .unwrap();
+        let generator = PayloadGeneratorFactory::create(&config).unwrap();
+        assert_eq!(generator.count().await.unwrap(), 2);
+        assert_eq!(generator.generate().await.unwrap(), vec!["YQ==!", "Yg==!"]);
+
+        let upload = PayloadConfig::Processed {
+            source: Box::new(PayloadConfig::UploadCatalog {
+                catalog: UploadCatalog::ContentTypes,
+            }),
+            processors: vec![PayloadProcessor::Base64],
+        };
+        assert!(PayloadGeneratorFactory::create(&upload).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Request pacing for attacks
+//!
+//! An attack can be capped to a number of requests per second across all its
+//! agents, keep a minimum delay between requests to the same host, and back
+//! off from a host that answers with 429 or 503. The limiter is shared by all
+//! agents of an attack.
+//!
+//! On top of the limits, a pacing profile shapes when requests go out: at a
+//! constant rate, at a rate ramping up linearly so a load-sensitive target is
+//! approached gently, or in bursts released together and separated by idle
+//! gaps, which is how a race window is hit on purpose.
+
+use crate::error::BackoffStrategy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Rate limits and backoff of an attack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Requests per second across all agents; unlimited when unset
+    pub max_requests_per_second: Option<f64>,
+    /// Requests an agent has in flight at once; `concurrent_requests_per_agent`
+    /// when unset
+    pub max_connections_per_agent: Option<u32>,
+    /// Minimum delay between two requests to the same host
+    pub per_host_delay_ms: u64,
+    /// Response codes that make the attack back off from the host
+    pub backoff_status_codes: Vec<i32>,
+    /// Delay after consecutive backoff responses, unless the response has a
+    /// `Retry-After` header
+    pub backoff: BackoffStrategy,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            max_requests_per_second: None,
+            max_connections_per_agent: None,
+            per_host_delay_ms: 0,
+            backoff_status_codes: vec![429, 503],
+            backoff: BackoffStrategy::Exponential {
+                initial_delay_ms: 1000,
+                multiplier: 2.0,
+                max_delay_ms: 60000,
+            },
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(rps) = self.max_requests_per_second {
+            if !(rps.is_finite() && rps > 0.0) {
+                return Err(format!("Requests per second must be positive, got {}", rps));
+            }
+        }
+        if self.max_connections_per_agent == Some(0) {
+            return Err("Connections per agent must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How the requests of an attack are spread over time
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PacingProfile {
+    /// As fast as the rate limits and connections allow
+    #[default]
+    Unpaced,
+    /// Evenly spaced requests
+    Constant { requests_per_second: f64 },
+    /// Rate rising linearly from `start_rps` to `end_rps` over
+    /// `ramp_seconds` from the first request, then held at `end_rps`
+    LinearRamp { start_rps: f64, end_rps: f64, ramp_seconds: u64 },
+    /// `size` requests released at once, then `idle_ms` before the next
+    /// burst. Each agent needs `size` connections to send a burst together.
+    Burst { size: u32, idle_ms: u64 },
+}
+
+impl PacingProfile {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        let positive = |rps: f64, name: &str| {
+            if rps.is_finite() && rps > 0.0 {
+                Ok(())
+            } else {
+                Err(format!("Pacing {} must be positive, got {}", name, rps))
+            }
+        };
+        match self {
+            Self::Unpaced => Ok(()),
+            Self::Constant { requests_per_second } => positive(*requests_per_second, "requests per second"),
+            Self::LinearRamp { start_rps, end_rps, .. } => {
+                positive(*start_rps, "start rate")?;
+                positive(*end_rps, "end rate")
+            }
+            Self::Burst { size: 0, .. } => Err("Pacing bursts need at least one request".to_string()),
+            Self::Burst { .. } => Ok(()),
+        }
+    }
+
+    /// Highest rate the profile sustains over time; unbounded when `None`
+    pub fn sustained_requests_per_second(&self) -> Option<f64> {
+        match self {
+            Self::Unpaced => None,
+            Self::Constant { requests_per_second } => Some(*requests_per_second),
+            Self::LinearRamp { start_rps, end_rps, .. } => Some(start_rps.max(*end_rps)),
+            Self::Burst { idle_ms: 0, .. } => None,
+            Self::Burst { size, idle_ms } => Some(*size as f64 * 1000.0 / *idle_ms as f64),
+        }
+    }
+}
+
+/// Where an attack is in its pacing profile
+#[derive(Debug, Default)]
+struct PacingState {
+    /// Start of the first request, which a ramp is measured from
+    started: Option<Instant>,
+    /// Earliest start of the next paced request
+    next_request: Option<Instant>,
+    /// Requests of the current burst already released
+    burst_sent: u32,
+}
+
+impl PacingState {
+    /// Start of a request that could go out at `earliest`
+    fn slot(&mut self, profile: &PacingProfile, earliest: Instant) -> Instant {
+        let start = self.next_request.map_or(earliest, |next| earliest.max(next));
+        let started = *self.started.get_or_insert(start);
+        match profile {
+            PacingProfile::Unpaced => {}
+            PacingProfile::Constant { requests_per_second } => {
+                self.next_request = Some(start + Duration::from_secs_f64(1.0 / requests_per_second));
+            }
+            PacingProfile::LinearRamp { start_rps, end_rps, ramp_seconds } => {
+                let elapsed = start.duration_since(started).as_secs_f64();
+                let progress = match *ramp_seconds {
+                    0 => 1.0,
+                    ramp => (elapsed / ramp as f64).min(1.0),
+                };
+                let rate = start_rps + (end_rps - start_rps) * progress;
+                self.next_request = Some(start + Duration::from_secs_f64(1.0 / rate));
+            }
+            PacingProfile::Burst { size, idle_ms } => {
+                self.burst_sent += 1;
+                if self.burst_sent >= *size {
+                    self.burst_sent = 0;
+                    self.next_request = Some(start + Duration::from_millis(*idle_ms));
+                }
+            }
+        }
+        start
+    }
+}
+
+#[derive(Debug, Default)]
+struct HostState {
+    /// Earliest start of the next request to the host
+    next_request: Option<Instant>,
+    /// No requests to the host before this
+    backoff_until: Option<Instant>,
+    /// Backoff responses in a row
+    consecutive_backoffs: u32,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    /// Earliest start of the next request of the attack
+    next_request: Option<Instant>,
+    pacing: PacingState,
+    hosts: HashMap<String, HostState>,
+}
+
+/// Paces the requests of one attack
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    pacing: PacingProfile,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            pacing: PacingProfile::default(),
+            state: Mutex::new(LimiterState::default()),
+        }
+    }
+
+    /// Spread the requests over time following `pacing`, within the limits
+    pub fn with_pacing(mut self, pacing: PacingProfile) -> Self {
+        self.pacing = pacing;
+        self
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Wait until a request to `host` may be sent
+    pub async fn acquire(&self, host: &str) {
+        loop {
+            let start = self.reserve(host);
+            tokio::time::sleep_until(start).await;
+            // A backoff that started while waiting moves the request back
+            match self.backoff_until(host) {
+                Some(until) if until > Instant::now() => continue,
+                _ => return,
+            }
+        }
+    }
+
+    /// Reserve the next free slot for `host` and return its start
+    fn reserve(&self, host: &str) -> Instant {
+        let now = Instant::now();
+        let mut guard = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let state = &mut *guard;
+        let interval = self
+            .config
+            .max_requests_per_second
+            .map(|rps| Duration::from_secs_f64(1.0 / rps))
+            .unwrap_or_default();
+        let host_delay = Duration::from_millis(self.config.per_host_delay_ms);
+
+        let host_state = state.hosts.entry(host.to_string()).or_default();
+        let mut start = now;
+        for limit in [host_state.next_request, host_state.backoff_until, state.next_request].into_iter().flatten() {
+            start = start.max(limit);
+        }
+        let start = state.pacing.slot(&self.pacing, start);
+        if !host_delay.is_zero() {
+            host_state.next_request = Some(start + host_delay);
+        }
+        if !interval.is_zero() {
+            state.next_request = Some(start + interval);
+        }
+        start
+    }
+
+    fn backoff_until(&self, host: &str) -> Option<Instant> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.hosts.get(host).and_then(|h| h.backoff_until)
+    }
+
+    /// Record the response status of a request to `host`. Returns the
+    /// backoff delay when the status makes the attack back off.
+    pub fn record_response(&self, host: &str, status_code: i32, retry_after: Option<&str>) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let host_state = state.hosts.entry(host.to_string()).or_default();
+        if !self.config.backoff_status_codes.contains(&status_code) {
+            host_state.consecutive_backoffs = 0;
+            return None;
+        }
+
+        let delay = retry_after
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| {
+                Duration::from_millis(self.config.backoff.calculate_delay(host_state.consecutive_backoffs))
+            });
+        host_state.consecutive_backoffs += 1;
+        let until = Instant::now() + delay;
+        host_state.backoff_until = Some(host_state.backoff_until.map_or(until, |current| current.max(until)));
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_per_second_and_host_delay() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_requests_per_second: Some(20.0),
+            per_host_delay_ms: 150,
+            ..Default::default()
+        });
+
+        let started = Instant::now();
+        limiter.acquire("a.test").await;
+        limiter.acquire("b.test").await;
+        // Paced to one request every 50ms across hosts
+        assert!(started.elapsed() >= Duration::from_millis(45));
+        limiter.acquire("a.test").await;
+        // The second request to a.test waits for the host delay
+        assert!(started.elapsed() >= Duration::from_millis(145));
+    }
+
+    #[tokio::test]
+    async fn test_backoff_on_throttling_responses() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            backoff: BackoffStrategy::Exponential {
+                initial_delay_ms: 40,
+                multiplier: 2.0,
+                max_delay_ms: 1000,
+            },
+            ..Default::default()
+        });
+
+        assert_eq!(limiter.record_response("a.test", 200, None), None);
+        assert_eq!(limiter.record_response("a.test", 429, None), Some(Duration::from_millis(40)));
+        assert_eq!(limiter.record_response("a.test", 503, None), Some(Duration::from_millis(80)));
+        assert_eq!(limiter.record_response("a.test", 429, Some("2")), Some(Duration::from_secs(2)));
+
+        // Other hosts are not held back
+        let started = Instant::now();
+        limiter.acquire("b.test").await;
+        assert!(started.elapsed() < Duration::from_millis(20));
+
+        // A successful response resets the exponential delay
+        limiter.record_response("b.test", 200, None);
+        assert_eq!(limiter.record_response("b.test", 429, None), Some(Duration::from_millis(40)));
+        limiter.acquire("b.test").await;
+        assert!(started.elapsed() >= Duration::from_millis(35));
+    }
+
+    #[test]
+    fn test_pacing_profiles() {
+        let gaps = |pacing: PacingProfile, count: usize| {
+            let limiter = RateLimiter::new(RateLimitConfig::default()).with_pacing(pacing);
+            let starts: Vec<Instant> = (0..count).map(|_| limiter.reserve("a.test")).collect();
+            starts.windows(2).map(|pair| pair[1] - pair[0]).collect::<Vec<_>>()
+        };
+        let ms = Duration::from_millis;
+
+        let constant = gaps(PacingProfile::Constant { requests_per_second: 10.0 }, 3);
+        assert!(constant.iter().all(|gap| *gap >= ms(100) && *gap < ms(105)));
+
+        // 100ms apart at first, 50ms once the ramp is over
+        let ramp = gaps(PacingProfile::LinearRamp { start_rps: 10.0, end_rps: 20.0, ramp_seconds: 1 }, 30);
+        assert!(ramp[0] >= ms(100) && ramp[0] < ms(105));
+        assert!(ramp[1] < ramp[0]);
+        assert!(ramp[28] >= ms(50) && ramp[28] < ms(55));
+
+        let bursts = gaps(PacingProfile::Burst { size: 3, idle_ms: 200 }, 6);
+        assert!(bursts[0] < ms(5) && bursts[1] < ms(5));
+        assert!(bursts[2] >= ms(200));
+        assert!(bursts[3] < ms(5) && bursts[4] < ms(5));
+
+        // The rate limit still applies to a burst
+        let limiter = RateLimiter::new(RateLimitConfig { max_requests_per_second: Some(10.0), ..Default::default() })
+            .with_pacing(PacingProfile::Burst { size: 2, idle_ms: 0 });
+        let (first, second) = (limiter.reserve("a.test"), limiter.reserve("a.test"));
+        assert!(second - first >= ms(100));
+    }
+
+    #[test]
+    fn test_validate_pacing() {
+        assert!(PacingProfile::default().validate().is_ok());
+        assert!(PacingProfile::Constant { requests_per_second: 0.0 }.validate().is_err());
+        assert!(PacingProfile::LinearRamp { start_rps: 1.0, end_rps: f64::NAN, ramp_seconds: 5 }.validate().is_err());
+        assert!(PacingProfile::Burst { size: 0, idle_ms: 100 }.validate().is_err());
+        assert_eq!(PacingProfile::Burst { size: 5, idle_ms: 500 }.sustained_requests_per_second(), Some(10.0));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RateLimitConfig::default().validate().is_ok());
+        let zero_rps = RateLimitConfig { max_requests_per_second: Some(0.0), ..Default::default() };
+        assert!(zero_rps.validate().is_err());
+        let no_connections = RateLimitConfig { max_connections_per_agent: Some(0), ..Default::default() };
+        assert!(no_connections.validate().is_err());
+    }
+}
@@ -0,0 +1,351 @@
+//! Raw HTTP/1.1 requests
+//!
+//! Repeater can take a request as typed: request line, headers and body in
+//! one string. Request-smuggling and parser-differential work depends on the
+//! exact bytes, so [`RawRequest`] keeps everything a structured request would
+//! normalize away: header order and duplicates, the whitespace after each
+//! colon, lines without a colon, folded lines, and per-line endings (CRLF,
+//! bare LF or bare CR). [`RawRequest::to_bytes`] reproduces the input exactly.
+//!
+//! Converting to [`HttpRequestData`] is lossy, since its headers are a map:
+//! repeated headers are joined with `, ` in order of appearance.
+
+use crate::error::{AttackError, AttackResult};
+use crate::types::{HttpHeaders, HttpRequestData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A header line as written
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawHeader {
+    /// Text before the first colon; the whole line if there is none
+    pub name: String,
+    /// The colon and the whitespace after it, empty if there is no colon
+    pub separator: String,
+    pub value: String,
+    /// `\r\n`, `\n`, `\r`, or empty on a last line without one
+    pub line_ending: String,
+}
+
+impl RawHeader {
+    /// A well-formed `Name: value` line ending in CRLF
+    pub fn new(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            separator: ": ".to_string(),
+            value: value.to_string(),
+            line_ending: "\r\n".to_string(),
+        }
+    }
+}
+
+/// An HTTP/1.x request kept byte for byte
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawRequest {
+    pub request_line: String,
+    pub request_line_ending: String,
+    pub headers: Vec<RawHeader>,
+    /// Ending of the empty line that closes the headers; `None` if the input
+    /// ends without one
+    pub header_terminator: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// Split off the first line of `input` and its ending
+fn next_line(input: &str) -> (&str, &str, &str) {
+    match input.find(['\r', '\n']) {
+        Some(end) => {
+            let ending_len = if input[end..].starts_with("\r\n") { 2 } else { 1 };
+            (&input[..end], &input[end..end + ending_len], &input[end + ending_len..])
+        }
+        None => (input, "", ""),
+    }
+}
+
+impl RawRequest {
+    /// Parse `raw`. The request line and headers must be UTF-8; the body is
+    /// taken as is.
+    pub fn parse(raw: &[u8]) -> AttackResult<Self> {
+        let invalid = |reason: String| AttackError::ValidationError {
+            field: "raw_request".to_string(),
+            reason,
+        };
+
+        // The head ends at the first empty line, whatever the line endings
+        let mut head_len = raw.len();
+        let mut line_start = 0;
+        let mut i = 0;
+        while i < raw.len() {
+            if raw[i] == b'\r' || raw[i] == b'\n' {
+                let ending = if raw[i] == b'\r' && raw.get(i + 1) == Some(&b'\n') { 2 } else { 1 };
+                if i == line_start && line_start > 0 {
+                    head_len = i + ending;
+                    break;
+                }
+                i += ending;
+                line_start = i;
+            } else {
+                i += 1;
+            }
+        }
+        let head = std::str::from_utf8(&raw[..head_len])
+            .map_err(|e| invalid(format!("request line and headers are not UTF-8: {}", e)))?;
+
+        let (request_line, request_line_ending, mut rest) = next_line(head);
+        if request_line.trim().is_empty() {
+            return Err(invalid("missing request line".to_string()));
+        }
+
+        let mut headers = Vec::new();
+        let mut header_terminator = None;
+        while !rest.is_empty() {
+            let (line, line_ending, remaining) = next_line(rest);
+            rest = remaining;
+            if line.is_empty() {
+                header_terminator = Some(line_ending.to_string());
+                break;
+            }
+            let header = match line.find(':') {
+                Some(colon) => {
+                    let after = &line[colon + 1..];
+                    let value = after.trim_start_matches([' ', '\t']);
+                    RawHeader {
+                        name: line[..colon].to_string(),
+                        separator: line[colon..line.len() - value.len()].to_string(),
+                        value: value.to_string(),
+                        line_ending: line_ending.to_string(),
+                    }
+                }
+                None => RawHeader {
+                    name: line.to_string(),
+                    separator: String::new(),
+                    value: String::new(),
+                    line_ending: line_ending.to_string(),
+                },
+            };
+            headers.push(header);
+        }
+
+        Ok(Self {
+            request_line: request_line.to_string(),
+            request_line_ending: request_line_ending.to_string(),
+            headers,
+            header_terminator,
+            body: raw[head_len..].to_vec(),
+        })
+    }
+
+    /// The request exactly as parsed (or as edited)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.request_line.len() + self.body.len() + 64 * self.headers.len());
+        out.extend_from_slice(self.request_line.as_bytes());
+        out.extend_from_slice(self.request_line_ending.as_bytes());
+        for header in &self.headers {
+            out.extend_from_slice(header.name.as_bytes());
+            out.extend_from_slice(header.separator.as_bytes());
+            out.extend_from_slice(header.value.as_bytes());
+            out.extend_from_slice(header.line_ending.as_bytes());
+        }
+        if let Some(terminator) = &self.header_terminator {
+            out.extend_from_slice(terminator.as_bytes());
+        }
+        out.extend_from_slice(&self.body);
+        out
+    }
+
+    fn request_line_part(&self, index: usize) -> &str {
+        self.request_line.split_whitespace().nth(index).unwrap_or_default()
+    }
+
+    pub fn method(&self) -> &str {
+        self.request_line_part(0)
+    }
+
+    /// Request target: origin form (`/path?query`) or absolute form
+    pub fn target(&self) -> &str {
+        self.request_line_part(1)
+    }
+
+    /// Protocol version, e.g. `HTTP/1.1`; empty for HTTP/0.9-style lines
+    pub fn version(&self) -> &str {
+        self.request_line_part(2)
+    }
+
+    /// Values of the headers named `name` (case-insensitive), in order
+    pub fn header_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.headers
+            .iter()
+            .filter(move |h| !h.separator.is_empty() && h.name.trim().eq_ignore_ascii_case(name))
+            .map(|h| h.value.trim_end())
+    }
+
+    /// Structured request for sending. An origin-form target is resolved
+    /// against the Host header, or `base_url` when there is none; `base_url`
+    /// also gives the scheme (https by default).
+    pub fn to_request_data(&self, base_url: Option<&str>) -> AttackResult<HttpRequestData> {
+        let invalid = |reason: String| AttackError::ValidationError {
+            field: "raw_request".to_string(),
+            reason,
+        };
+        let method = self.method();
+        let target = self.target();
+        if method.is_empty() || target.is_empty() {
+            return Err(invalid(format!("malformed request line '{}'", self.request_line)));
+        }
+
+        let url = if target.starts_with("http://") || target.starts_with("https://") {
+            target.to_string()
+        } else {
+            let base = base_url
+                .map(|u| url::Url::parse(u).map_err(|e| invalid(format!("invalid base URL '{}': {}", u, e))))
+                .transpose()?;
+            let scheme = base.as_ref().map(|b| b.scheme().to_string()).unwrap_or_else(|| "https".to_string());
+            let host = match self.header_values("host").next() {
+                Some(host) if !host.is_empty() => host.to_string(),
+                _ => base
+                    .as_ref()
+                    .and_then(|b| {
+                        b.host_str().map(|h| match b.port() {
+                            Some(port) => format!("{}:{}", h, port),
+                            None => h.to_string(),
+                        })
+                    })
+                    .ok_or_else(|| invalid("no Host header and no URL to send the request to".to_string()))?,
+            };
+            let path = if target.starts_with('/') { target.to_string() } else { format!("/{}", target) };
+            format!("{}://{}{}", scheme, host, path)
+        };
+
+        let mut headers: HashMap<String, String> = HashMap::new();
+        for header in self.headers.iter().filter(|h| !h.separator.is_empty()) {
+            let name = header.name.trim().to_string();
+            let value = header.value.trim_end();
+            headers
+                .entry(name)
+                .and_modify(|existing| {
+                    existing.push_str(", ");
+                    existing.push_str(value);
+                })
+                .or_insert_with(|| value.to_string());
+        }
+
+        Ok(HttpRequestData {
+            method: method.to_string(),
+            url,
+            headers: Some(HttpHeaders { headers }),
+            body: self.body.clone(),
+            tls: None,
+        })
+    }
+
+    /// Raw form of a structured request, with CRLF line endings. Headers
+    /// are sorted by name, since the map has no order.
+    pub fn from_request_data(request: &HttpRequestData) -> AttackResult<Self> {
+        let url = url::Url::parse(&request.url).map_err(|e| AttackError::ValidationError {
+            field: "url".to_string(),
+            reason: e.to_string(),
+        })?;
+        let mut target = url.path().to_string();
+        if let Some(query) = url.query() {
+            target.push('?');
+            target.push_str(query);
+        }
+
+        let mut names: Vec<&String> = request.headers.iter().flat_map(|h| h.headers.keys()).collect();
+        names.sort_by_key(|name| name.to_ascii_lowercase());
+        let mut headers = Vec::new();
+        if !names.iter().any(|name| name.eq_ignore_ascii_case("host")) {
+            let host = match url.port() {
+                Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+                None => url.host_str().unwrap_or_default().to_string(),
+            };
+            headers.push(RawHeader::new("Host", &host));
+        }
+        if let Some(map) = request.headers.as_ref().map(|h| &h.headers) {
+            headers.extend(names.iter().map(|name| RawHeader::new(name, &map[*name])));
+        }
+
+        Ok(Self {
+            request_line: format!("{} {} HTTP/1.1", request.method, target),
+            request_line_ending: "\r\n".to_string(),
+            headers,
+            header_terminator: Some("\r\n".to_string()),
+            body: request.body.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_is_lossless() {
+        let inputs: [&[u8]; 6] = [
+            b"POST /login HTTP/1.1\r\nHost: app.test\r\nContent-Length: 5\r\n\r\na=1&b",
+            // Duplicate and conflicting framing headers, as in CL.TE probes
+            b"POST / HTTP/1.1\r\nHost: app.test\r\nContent-Length: 6\r\nTransfer-Encoding : chunked\r\nTransfer-Encoding:\tx\r\n\r\n0\r\n\r\nG",
+            // Bare LF and bare CR line endings, mixed
+            b"GET /a HTTP/1.1\nHost: app.test\r\nX-A:no-space\rX-B:  two spaces  \n\n",
+            // Folded line, line without a colon, no terminating empty line
+            b"GET / HTTP/1.1\r\nX-Folded: a\r\n b\r\nnot a header\r\nHost: app.test",
+            // Just a request line, no line ending
+            b"GET /",
+            // Binary body
+            b"PUT /f HTTP/1.1\r\n\r\n\x00\xff\r\n\r\n",
+        ];
+        for input in inputs {
+            let parsed = RawRequest::parse(input).unwrap();
+            assert_eq!(parsed.to_bytes(), input, "{:?}", String::from_utf8_lossy(input));
+        }
+
+        let parsed = RawRequest::parse(inputs[1]).unwrap();
+        assert_eq!(parsed.headers.len(), 4);
+        assert_eq!(parsed.headers[2].name, "Transfer-Encoding ");
+        assert_eq!(parsed.header_values("transfer-encoding").collect::<Vec<_>>(), vec!["chunked", "x"]);
+        assert_eq!(parsed.body, b"0\r\n\r\nG");
+
+        let mixed = RawRequest::parse(inputs[2]).unwrap();
+        assert_eq!(mixed.request_line_ending, "\n");
+        assert_eq!(mixed.headers[1].separator, ":");
+        assert_eq!(mixed.headers[1].line_ending, "\r");
+        assert_eq!(mixed.headers[2].separator, ":  ");
+        assert_eq!(mixed.header_terminator.as_deref(), Some("\n"));
+
+        let unterminated = RawRequest::parse(inputs[3]).unwrap();
+        assert_eq!(unterminated.header_terminator, None);
+        assert_eq!(unterminated.headers[1].name, " b");
+        assert_eq!(unterminated.headers[2].separator, "");
+
+        assert!(RawRequest::parse(b"\r\nHost: x\r\n\r\n").is_err());
+        assert!(RawRequest::parse(b"GET /\xff HTTP/1.1\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn test_to_and_from_request_data() {
+        let raw = RawRequest::parse(b"POST /api?x=1 HTTP/1.1\r\nHost: app.test:8443\r\nX-Dup: a\r\nX-Dup: b\r\n\r\n{}").unwrap();
+        let request = raw.to_request_data(Some("http://ignored.test")).unwrap();
+        assert_eq!(request.method, "POST");
+        // Host header wins over the base URL's host, the scheme comes from it
+        assert_eq!(request.url, "http://app.test:8443/api?x=1");
+        let headers = request.headers.as_ref().unwrap();
+        assert_eq!(headers.headers.get("X-Dup"), Some(&"a, b".to_string()));
+        assert_eq!(request.body, b"{}");
+
+        let no_host = RawRequest::parse(b"GET /x HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(
+            no_host.to_request_data(Some("https://base.test:444/other")).unwrap().url,
+            "https://base.test:444/x"
+        );
+        assert!(no_host.to_request_data(None).is_err());
+
+        let absolute = RawRequest::parse(b"GET http://proxy.test/p HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(absolute.to_request_data(None).unwrap().url, "http://proxy.test/p");
+
+        let back = RawRequest::from_request_data(&request).unwrap();
+        assert_eq!(
+            back.to_bytes(),
+            b"POST /api?x=1 HTTP/1.1\r\nHost: app.test:8443\r\nX-Dup: a, b\r\n\r\n{}".to_vec()
+        );
+    }
+}
@@ -0,0 +1,418 @@
+//! Parameter pollution and method tampering probes
+//!
+//! Variants of a captured request that servers and the frameworks in front
+//! of them often handle inconsistently:
+//!
+//! - Parameter pollution: each query (or form body) parameter sent a second
+//!   time with a marker value. Whether the first, the last or both values win
+//!   shows in a changed response, or in the marker coming back.
+//! - Method override: the request with `X-HTTP-Method-Override` and its
+//!   relatives asking for another verb. A server that honours them answers
+//!   differently.
+//! - Verb tampering: the request sent as `HEAD` and `PUT`. Access rules
+//!   written for some verbs only let the others through. `DELETE` is never
+//!   sent, as a server honouring it would delete the resource.
+//!
+//! Each probe's response is compared with baseline replays of the unmodified
+//! request; [`assess`] says what, if anything, the probe changed.
+
+use crate::types::{HttpHeaders, HttpRequestData, HttpResponseData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Value of the duplicated parameters, looked for in the responses
+pub const POLLUTION_MARKER: &str = "proxxyhpp7";
+
+/// Parameters of one request that are duplicated, in order of appearance
+pub const MAX_POLLUTED_PARAMETERS: usize = 10;
+
+/// Headers some frameworks take the real method from
+pub const METHOD_OVERRIDE_HEADERS: [&str; 3] = ["X-HTTP-Method-Override", "X-HTTP-Method", "X-Method-Override"];
+
+/// Verbs the request is also sent as
+const TAMPERED_VERBS: [&str; 2] = ["HEAD", "PUT"];
+
+/// Body lengths within this fraction of the baseline, plus a few bytes,
+/// count as unchanged
+const LENGTH_TOLERANCE: f64 = 0.05;
+const LENGTH_SLACK: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TamperKind {
+    ParameterPollution,
+    MethodOverride,
+    VerbTampering,
+}
+
+impl TamperKind {
+    /// Value stored with a finding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TamperKind::ParameterPollution => "parameter_pollution",
+            TamperKind::MethodOverride => "method_override",
+            TamperKind::VerbTampering => "verb_tampering",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "parameter_pollution" => Some(TamperKind::ParameterPollution),
+            "method_override" => Some(TamperKind::MethodOverride),
+            "verb_tampering" => Some(TamperKind::VerbTampering),
+            _ => None,
+        }
+    }
+}
+
+/// Which probes to send
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TamperChecks {
+    pub parameter_pollution: bool,
+    pub method_override: bool,
+    pub verb_tampering: bool,
+}
+
+impl Default for TamperChecks {
+    fn default() -> Self {
+        Self {
+            parameter_pollution: true,
+            method_override: true,
+            verb_tampering: true,
+        }
+    }
+}
+
+impl TamperChecks {
+    pub fn any(&self) -> bool {
+        self.parameter_pollution || self.method_override || self.verb_tampering
+    }
+}
+
+/// A variant of the captured request
+#[derive(Debug, Clone)]
+pub struct TamperProbe {
+    pub kind: TamperKind,
+    /// What was changed, e.g. `id duplicated` or `GET → HEAD`
+    pub label: String,
+    pub request: HttpRequestData,
+}
+
+/// What a probe's response shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TamperEffect {
+    /// The request was denied (401/403) but the probe got a 2xx
+    AccessBypass,
+    /// `PUT` on a resource that answers the original verb got a 2xx
+    VerbAccepted,
+    /// The duplicated parameter's marker value is in the response
+    MarkerReflected,
+    StatusChanged,
+    /// Same status, body length outside what the baseline varied by
+    ContentChanged,
+}
+
+impl TamperEffect {
+    /// Value stored with a finding
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TamperEffect::AccessBypass => "access_bypass",
+            TamperEffect::VerbAccepted => "verb_accepted",
+            TamperEffect::MarkerReflected => "marker_reflected",
+            TamperEffect::StatusChanged => "status_changed",
+            TamperEffect::ContentChanged => "content_changed",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "access_bypass" => Some(TamperEffect::AccessBypass),
+            "verb_accepted" => Some(TamperEffect::VerbAccepted),
+            "marker_reflected" => Some(TamperEffect::MarkerReflected),
+            "status_changed" => Some(TamperEffect::StatusChanged),
+            "content_changed" => Some(TamperEffect::ContentChanged),
+            _ => None,
+        }
+    }
+}
+
+/// Responses to the unmodified request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TamperBaseline {
+    pub status_code: i32,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// The marker appears without any probe, so its presence means nothing
+    pub marker_present: bool,
+}
+
+impl TamperBaseline {
+    /// Baseline from replays of the unmodified request; `None` without any,
+    /// or when they disagree on the status
+    pub fn from_responses(responses: &[HttpResponseData]) -> Option<Self> {
+        let first = responses.first()?;
+        if responses.iter().any(|response| response.status_code != first.status_code) {
+            return None;
+        }
+        let lengths = responses.iter().map(|response| response.body.len());
+        Some(Self {
+            status_code: first.status_code,
+            min_length: lengths.clone().min().unwrap_or_default(),
+            max_length: lengths.max().unwrap_or_default(),
+            marker_present: responses.iter().any(contains_marker),
+        })
+    }
+
+    fn length_changed(&self, length: usize) -> bool {
+        let slack = (self.max_length as f64 * LENGTH_TOLERANCE) as usize + LENGTH_SLACK;
+        length + slack < self.min_length || length > self.max_length + slack
+    }
+}
+
+/// Probes of `request` for the enabled `checks`
+pub fn tamper_probes(request: &HttpRequestData, checks: &TamperChecks) -> Vec<TamperProbe> {
+    let mut probes = Vec::new();
+    if checks.parameter_pollution {
+        probes.extend(pollution_probes(request));
+    }
+    if checks.method_override {
+        let method = if request.method.eq_ignore_ascii_case("PUT") { "GET" } else { "PUT" };
+        for name in METHOD_OVERRIDE_HEADERS {
+            let mut probe = request.clone();
+            set_header(&mut probe, name, method);
+            probes.push(TamperProbe {
+                kind: TamperKind::MethodOverride,
+                label: format!("{}: {}", name, method),
+                request: probe,
+            });
+        }
+    }
+    if checks.verb_tampering {
+        for verb in TAMPERED_VERBS {
+            if request.method.eq_ignore_ascii_case(verb) {
+                continue;
+            }
+            let mut probe = request.clone();
+            probe.method = verb.to_string();
+            if verb == "HEAD" {
+                probe.body.clear();
+                remove_header(&mut probe, "content-length");
+            }
+            probes.push(TamperProbe {
+                kind: TamperKind::VerbTampering,
+                label: format!("{} → {}", request.method.to_ascii_uppercase(), verb),
+                request: probe,
+            });
+        }
+    }
+    probes
+}
+
+/// One probe per parameter, duplicated with the marker value. The query is
+/// extended as written, so the original encoding of the other parameters
+/// is kept.
+fn pollution_probes(request: &HttpRequestData) -> Vec<TamperProbe> {
+    let mut probes = Vec::new();
+    let mut seen = HashSet::new();
+
+    let (before_fragment, fragment) = match request.url.split_once('#') {
+        Some((url, fragment)) => (url, Some(fragment)),
+        None => (request.url.as_str(), None),
+    };
+    if let Some((_, query)) = before_fragment.split_once('?') {
+        for name in parameter_names(query) {
+            if probes.len() >= MAX_POLLUTED_PARAMETERS || !seen.insert(name.to_string()) {
+                continue;
+            }
+            let mut probe = request.clone();
+            probe.url = format!("{}&{}={}", before_fragment, name, POLLUTION_MARKER);
+            if let Some(fragment) = fragment {
+                probe.url = format!("{}#{}", probe.url, fragment);
+            }
+            probes.push(TamperProbe {
+                kind: TamperKind::ParameterPollution,
+                label: format!("{} duplicated in query", name),
+                request: probe,
+            });
+        }
+    }
+
+    let form = header(request, "content-type")
+        .is_some_and(|value| value.to_ascii_lowercase().starts_with("application/x-www-form-urlencoded"));
+    if let (true, Ok(body)) = (form, std::str::from_utf8(&request.body)) {
+        for name in parameter_names(body) {
+            if probes.len() >= MAX_POLLUTED_PARAMETERS || !seen.insert(name.to_string()) {
+                continue;
+            }
+            let mut probe = request.clone();
+            probe.body = format!("{}&{}={}", body, name, POLLUTION_MARKER).into_bytes();
+            if header(&probe, "content-length").is_some() {
+                let length = probe.body.len().to_string();
+                set_header(&mut probe, "Content-Length", &length);
+            }
+            probes.push(TamperProbe {
+                kind: TamperKind::ParameterPollution,
+                label: format!("{} duplicated in body", name),
+                request: probe,
+            });
+        }
+    }
+    probes
+}
+
+fn parameter_names(encoded: &str) -> impl Iterator<Item = &str> {
+    encoded
+        .split('&')
+        .map(|pair| pair.split('=').next().unwrap_or_default())
+        .filter(|name| !name.is_empty())
+}
+
+/// What `response` to `probe` changed compared with `baseline`
+pub fn assess(probe: &TamperProbe, baseline: &TamperBaseline, response: &HttpResponseData) -> Option<TamperEffect> {
+    let success = |status: i32| (200..300).contains(&status);
+    if matches!(baseline.status_code, 401 | 403) && success(response.status_code) {
+        return Some(TamperEffect::AccessBypass);
+    }
+    match probe.kind {
+        TamperKind::ParameterPollution if !baseline.marker_present && contains_marker(response) => {
+            Some(TamperEffect::MarkerReflected)
+        }
+        TamperKind::ParameterPollution | TamperKind::MethodOverride => {
+            if response.status_code != baseline.status_code {
+                Some(TamperEffect::StatusChanged)
+            } else if baseline.length_changed(response.body.len()) {
+                Some(TamperEffect::ContentChanged)
+            } else {
+                None
+            }
+        }
+        // HEAD has no body and often a different status; only a bypass counts
+        TamperKind::VerbTampering
+            if probe.request.method == "PUT" && success(baseline.status_code) && success(response.status_code) =>
+        {
+            Some(TamperEffect::VerbAccepted)
+        }
+        TamperKind::VerbTampering => None,
+    }
+}
+
+fn contains_marker(response: &HttpResponseData) -> bool {
+    response
+        .body
+        .windows(POLLUTION_MARKER.len())
+        .any(|window| window == POLLUTION_MARKER.as_bytes())
+}
+
+fn header<'a>(request: &'a HttpRequestData, name: &str) -> Option<&'a str> {
+    request
+        .headers
+        .as_ref()?
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn set_header(request: &mut HttpRequestData, name: &str, value: &str) {
+    remove_header(request, name);
+    request
+        .headers
+        .get_or_insert_with(|| HttpHeaders { headers: Default::default() })
+        .headers
+        .insert(name.to_string(), value.to_string());
+}
+
+fn remove_header(request: &mut HttpRequestData, name: &str) {
+    if let Some(headers) = request.headers.as_mut() {
+        headers.headers.retain(|key, _| !key.eq_ignore_ascii_case(name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, url: &str, headers: &[(&str, &str)], body: &str) -> HttpRequestData {
+        HttpRequestData {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: Some(HttpHeaders {
+                headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }),
+            body: body.as_bytes().to_vec(),
+            tls: None,
+        }
+    }
+
+    fn response(status_code: i32, body: &str) -> HttpResponseData {
+        HttpResponseData {
+            status_code,
+            headers: None,
+            body: body.as_bytes().to_vec(),
+            tls: None,
+            timing: None,
+            protocol: None,
+        }
+    }
+
+    #[test]
+    fn test_probes_of_a_request() {
+        let original = request(
+            "POST",
+            "https://target.test/api/orders?id=5&sort=asc&id=6#top",
+            &[("Content-Type", "application/x-www-form-urlencoded"), ("Content-Length", "13")],
+            "qty=1&note=hi",
+        );
+        let probes = tamper_probes(&original, &TamperChecks::default());
+        let labels: Vec<&str> = probes.iter().map(|probe| probe.label.as_str()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                "id duplicated in query",
+                "sort duplicated in query",
+                "qty duplicated in body",
+                "note duplicated in body",
+                "X-HTTP-Method-Override: PUT",
+                "X-HTTP-Method: PUT",
+                "X-Method-Override: PUT",
+                "POST → HEAD",
+                "POST → PUT",
+            ]
+        );
+        assert_eq!(probes[0].request.url, "https://target.test/api/orders?id=5&sort=asc&id=6&id=proxxyhpp7#top");
+        assert_eq!(probes[2].request.body, b"qty=1&note=hi&qty=proxxyhpp7");
+        assert_eq!(header(&probes[2].request, "content-length"), Some("28"));
+        assert_eq!(header(&probes[4].request, "x-http-method-override"), Some("PUT"));
+        assert!(probes[7].request.body.is_empty() && header(&probes[7].request, "content-length").is_none());
+
+        let only_verbs = TamperChecks { parameter_pollution: false, method_override: false, verb_tampering: true };
+        let probes = tamper_probes(&request("PUT", "https://target.test/a", &[], ""), &only_verbs);
+        assert_eq!(probes.iter().map(|probe| probe.label.as_str()).collect::<Vec<_>>(), vec!["PUT → HEAD"]);
+    }
+
+    #[test]
+    fn test_assess_against_baseline() {
+        let baseline = TamperBaseline::from_responses(&[response(200, &"a".repeat(1000)), response(200, &"a".repeat(1010))]).unwrap();
+        assert_eq!((baseline.min_length, baseline.max_length), (1000, 1010));
+        assert!(TamperBaseline::from_responses(&[response(200, ""), response(500, "")]).is_none());
+
+        let probes = tamper_probes(&request("GET", "https://target.test/doc?id=1", &[], ""), &TamperChecks::default());
+        let (pollution, head, put) = (&probes[0], &probes[4], &probes[5]);
+        assert_eq!((head.request.method.as_str(), put.request.method.as_str()), ("HEAD", "PUT"));
+
+        assert_eq!(assess(pollution, &baseline, &response(200, &"a".repeat(1040))), None);
+        assert_eq!(assess(pollution, &baseline, &response(200, &"a".repeat(1200))), Some(TamperEffect::ContentChanged));
+        assert_eq!(assess(pollution, &baseline, &response(400, "")), Some(TamperEffect::StatusChanged));
+        assert_eq!(
+            assess(pollution, &baseline, &response(200, &format!("{}{}", "a".repeat(995), POLLUTION_MARKER))),
+            Some(TamperEffect::MarkerReflected)
+        );
+
+        assert_eq!(assess(head, &baseline, &response(405, "")), None);
+        assert_eq!(assess(put, &baseline, &response(200, "")), Some(TamperEffect::VerbAccepted));
+        let denied = TamperBaseline::from_responses(&[response(403, "no")]).unwrap();
+        assert_eq!(assess(head, &denied, &response(200, "")), Some(TamperEffect::AccessBypass));
+        assert_eq!(assess(&probes[1], &denied, &response(403, "no")), None);
+    }
+}
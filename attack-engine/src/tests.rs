@@ -64,6 +64,8 @@ mod tests {
                 headers,
                 body,
                 tls,
+                timing: None,
+                protocol: None,
             }
         }
     }
@@ -86,6 +88,11 @@ mod tests {
                 timeout_seconds,
                 retry_attempts,
                 distribution_strategy,
+                rate_limit: Default::default(),
+                header_randomization: Default::default(),
+                baseline_requests: 0,
+                pacing: Default::default(),
+                timing_confirmation: None,
             }
         }
     }
@@ -580,6 +587,7 @@ mod tests {
                                 // Should use cartesian product
                                 prop_assert_eq!(count, 6); // 2 * 3 = 6
                             }
+                            AttackMode::GraphQl(_) => unreachable!("not generated by arb_attack_mode"),
                         }
                     }
                 }
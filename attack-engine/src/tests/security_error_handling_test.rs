@@ -55,6 +55,8 @@ fn arb_http_response_with_sensitive_data() -> impl Strategy<Value = HttpResponse
             headers,
             body,
             tls: None,
+            timing: None,
+            protocol: None,
         }
     })
 }
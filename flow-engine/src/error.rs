@@ -35,6 +35,9 @@ pub enum FlowEngineError {
     #[error("Session validation failed: {0}")]
     SessionValidation(String),
 
+    #[error("Invalid flow step: {0}")]
+    InvalidStep(String),
+
     #[error("Flow profile not found: {id}")]
     ProfileNotFound { id: String },
 
@@ -44,6 +47,9 @@ pub enum FlowEngineError {
     #[error("User intervention required: {reason}")]
     InterventionRequired { reason: String },
 
+    #[error("Render failed: {0}")]
+    Render(String),
+
     #[error("Database error: {0}")]
     Database(String),
 
@@ -3,7 +3,15 @@
 //! Handles Chromium browser lifecycle, configuration, and proxy integration.
 
 use crate::error::{FlowEngineError, FlowResult};
-use chromiumoxide::{Browser, BrowserConfig};
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    ContinueRequestParams, EnableParams as FetchEnableParams, EventRequestPaused, RequestPattern,
+    RequestStage,
+};
+use chromiumoxide::cdp::browser_protocol::network::ResourceType;
+use chromiumoxide::cdp::browser_protocol::security::SetIgnoreCertificateErrorsParams;
+use chromiumoxide::{Browser, BrowserConfig, Page};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use futures::StreamExt;
 use std::sync::Arc;
 use tokio::sync::RwLock;
@@ -46,6 +54,43 @@ impl ProxyConfig {
     }
 }
 
+/// Certificate and transport security controls applied at launch
+#[derive(Debug, Clone, Default)]
+pub struct SecurityOptions {
+    /// Hosts (wildcards allowed, e.g. `*.staging.corp`) whose certificate errors are
+    /// ignored while `ignore_ssl_errors` is off. Decided per top-level navigation,
+    /// so subresources inherit the decision of the page that loads them.
+    pub cert_error_allowlist: Vec<String>,
+    /// Discard HSTS state stored in the profile so previously pinned hosts can be
+    /// reached over plain HTTP and their certificate errors stay bypassable.
+    /// Chromium's built-in preload list is unaffected.
+    pub ignore_hsts: bool,
+    /// Disable QUIC/HTTP3 so all traffic stays on TCP where the proxy can see it
+    pub disable_quic: bool,
+}
+
+impl SecurityOptions {
+    /// Whether certificate errors on this URL's host should be ignored
+    pub fn allows_cert_errors(&self, url: &str) -> bool {
+        let host = match url::Url::parse(url) {
+            Ok(parsed) => parsed.host_str().unwrap_or_default().to_string(),
+            Err(_) => return false,
+        };
+        self.cert_error_allowlist
+            .iter()
+            .any(|pattern| host_matches(pattern, &host))
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host.ends_with(&format!(".{}", suffix)),
+        None => pattern == host,
+    }
+}
+
 /// Browser launch options
 #[derive(Debug, Clone)]
 pub struct BrowserOptions {
@@ -55,14 +100,19 @@ pub struct BrowserOptions {
     pub proxy: Option<ProxyConfig>,
     /// Additional Chrome arguments
     pub extra_args: Vec<String>,
-    /// User data directory (for persistent sessions)
+    /// User data directory (for persistent sessions); takes precedence over `profile`
     pub user_data_dir: Option<String>,
+    /// Named persistent profile managed by `BrowserManager`.
+    /// `None` launches with a throwaway profile.
+    pub profile: Option<String>,
     /// Ignore SSL certificate errors (useful when proxying)
     pub ignore_ssl_errors: bool,
     /// Window size
     pub window_size: Option<(u32, u32)>,
     /// CA certificate path for SSL trust (Proxxy CA cert)
     pub ca_cert_path: Option<String>,
+    /// HSTS, per-host certificate error and QUIC controls
+    pub security: SecurityOptions,
 }
 
 impl Default for BrowserOptions {
@@ -72,9 +122,11 @@ impl Default for BrowserOptions {
             proxy: None,
             extra_args: Vec::new(),
             user_data_dir: None,
+            profile: None,
             ignore_ssl_errors: true,
             window_size: Some((1920, 1080)),
             ca_cert_path: None,
+            security: SecurityOptions::default(),
         }
     }
 }
@@ -99,13 +151,33 @@ impl BrowserOptions {
         self.headless = headless;
         self
     }
+
+    /// Reuse a named persistent profile (cookies, local storage, device trust)
+    pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = Some(name.into());
+        self
+    }
+
+    /// Set HSTS / certificate error / QUIC controls
+    pub fn with_security(mut self, security: SecurityOptions) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Per-host certificate error handling is needed
+    fn uses_cert_allowlist(&self) -> bool {
+        !self.ignore_ssl_errors && !self.security.cert_error_allowlist.is_empty()
+    }
 }
 
 /// Managed browser instance
 pub struct ManagedBrowser {
     browser: Browser,
     options: BrowserOptions,
+    /// Throwaway profile directory removed on close
     user_data_dir: Option<PathBuf>,
+    /// Profile directory that outlives the browser
+    persistent: bool,
 }
 
 impl ManagedBrowser {
@@ -114,6 +186,37 @@ impl ManagedBrowser {
         &self.browser
     }
 
+    /// Launch options this browser was started with
+    pub fn options(&self) -> &BrowserOptions {
+        &self.options
+    }
+
+    /// Open a page with the launch-time security controls applied.
+    /// Prefer this over `browser().new_page()` for pages that navigate to targets.
+    pub async fn new_page(&self, url: &str) -> FlowResult<Page> {
+        if !self.options.uses_cert_allowlist() {
+            return self
+                .browser
+                .new_page(url)
+                .await
+                .map_err(|e| FlowEngineError::BrowserLaunch(format!("Failed to create page: {}", e)));
+        }
+
+        let page = self
+            .browser
+            .new_page("about:blank")
+            .await
+            .map_err(|e| FlowEngineError::BrowserLaunch(format!("Failed to create page: {}", e)))?;
+        install_cert_allowlist(&page, self.options.security.clone()).await?;
+
+        if url != "about:blank" {
+            page.goto(url)
+                .await
+                .map_err(|e| FlowEngineError::Navigation(format!("Failed to open {}: {}", url, e)))?;
+        }
+        Ok(page)
+    }
+
     /// Check if browser is still connected
     pub async fn is_connected(&self) -> bool {
         // Chromiumoxide doesn't expose a direct method, but we can try a simple operation
@@ -121,7 +224,15 @@ impl ManagedBrowser {
     }
 
     /// Close the browser
-    pub async fn close(self) -> FlowResult<()> {
+    pub async fn close(mut self) -> FlowResult<()> {
+        if self.persistent {
+            // Shut down cleanly so cookies and storage are flushed to the profile
+            if let Err(e) = self.browser.close().await {
+                warn!("Graceful browser shutdown failed: {}", e);
+            }
+            let _ = self.browser.wait().await;
+        }
+
         // Browser will be closed when dropped
         drop(self.browser);
         info!("Browser closed");
@@ -139,16 +250,188 @@ impl ManagedBrowser {
     }
 }
 
+/// Pause top-level document requests and toggle certificate error handling for
+/// the page before the connection is made, based on the destination host.
+async fn install_cert_allowlist(page: &Page, security: SecurityOptions) -> FlowResult<()> {
+    let cdp_err = |e: chromiumoxide::error::CdpError| FlowEngineError::BrowserLaunch(e.to_string());
+
+    page.execute(SetIgnoreCertificateErrorsParams::new(false))
+        .await
+        .map_err(cdp_err)?;
+
+    let mut paused = page
+        .event_listener::<EventRequestPaused>()
+        .await
+        .map_err(cdp_err)?;
+
+    let pattern = RequestPattern::builder()
+        .resource_type(ResourceType::Document)
+        .request_stage(RequestStage::Request)
+        .build();
+    page.execute(FetchEnableParams::builder().pattern(pattern).build())
+        .await
+        .map_err(cdp_err)?;
+
+    let page = page.clone();
+    tokio::spawn(async move {
+        let mut ignoring = false;
+        while let Some(event) = paused.next().await {
+            let is_main_frame = page.mainframe().await.ok().flatten().as_ref() == Some(&event.frame_id);
+            if is_main_frame {
+                let allow = security.allows_cert_errors(&event.request.url);
+                if allow != ignoring {
+                    if let Err(e) = page.execute(SetIgnoreCertificateErrorsParams::new(allow)).await {
+                        warn!("Failed to update certificate error handling: {}", e);
+                    } else {
+                        ignoring = allow;
+                    }
+                }
+            }
+
+            if let Err(e) = page
+                .execute(ContinueRequestParams::new(event.request_id.clone()))
+                .await
+            {
+                warn!("Failed to resume paused navigation: {}", e);
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Remove persisted HSTS state from a profile directory
+fn clear_hsts_state(user_data_dir: &std::path::Path) {
+    for file in ["Default/TransportSecurity", "TransportSecurity"] {
+        let path = user_data_dir.join(file);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("Failed to clear HSTS state {:?}: {}", path, e);
+            }
+        }
+    }
+}
+
+/// Persistent browser profile on disk
+#[derive(Debug, Clone, Serialize)]
+pub struct BrowserProfileInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub size_bytes: u64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// Lock files left behind when Chromium exits without a clean shutdown
+const PROFILE_LOCK_FILES: [&str; 3] = ["SingletonLock", "SingletonSocket", "SingletonCookie"];
+
 /// Browser launcher and manager
 pub struct BrowserManager {
     active_browser: Arc<RwLock<Option<ManagedBrowser>>>,
+    /// Root directory for named persistent profiles
+    profiles_dir: Option<PathBuf>,
 }
 
 impl BrowserManager {
     pub fn new() -> Self {
         Self {
             active_browser: Arc::new(RwLock::new(None)),
+            profiles_dir: None,
+        }
+    }
+
+    /// Create a manager that keeps named profiles under `dir`
+    pub fn with_profiles_dir(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            active_browser: Arc::new(RwLock::new(None)),
+            profiles_dir: Some(dir.into()),
+        }
+    }
+
+    /// Directory for a named profile
+    pub fn profile_path(&self, name: &str) -> FlowResult<PathBuf> {
+        if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+            return Err(FlowEngineError::BrowserLaunch(format!(
+                "Invalid profile name '{}'. Use only alphanumeric, -, and _",
+                name
+            )));
+        }
+
+        let root = self.profiles_dir.as_ref().ok_or_else(|| {
+            FlowEngineError::BrowserLaunch("No profiles directory configured".to_string())
+        })?;
+        Ok(root.join(name))
+    }
+
+    /// List persistent profiles
+    pub fn list_profiles(&self) -> FlowResult<Vec<BrowserProfileInfo>> {
+        let root = match &self.profiles_dir {
+            Some(root) if root.exists() => root,
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut profiles = Vec::new();
+        for entry in std::fs::read_dir(root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let path = entry.path();
+            let last_used = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map(DateTime::<Utc>::from);
+
+            profiles.push(BrowserProfileInfo {
+                name: entry.file_name().to_string_lossy().to_string(),
+                size_bytes: dir_size(&path),
+                path,
+                last_used,
+            });
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(profiles)
+    }
+
+    /// Delete a persistent profile and all state stored in it
+    pub async fn delete_profile(&self, name: &str) -> FlowResult<bool> {
+        let path = self.profile_path(name)?;
+
+        let in_use = self
+            .active_browser
+            .read()
+            .await
+            .as_ref()
+            .is_some_and(|b| b.options.profile.as_deref() == Some(name));
+        if in_use {
+            return Err(FlowEngineError::BrowserLaunch(format!(
+                "Profile '{}' is in use by the active browser",
+                name
+            )));
+        }
+
+        if !path.exists() {
+            return Ok(false);
+        }
+        std::fs::remove_dir_all(&path)?;
+        info!("Deleted browser profile '{}'", name);
+        Ok(true)
+    }
+
+    /// Resolve the user data directory for a launch: (path, persistent)
+    fn resolve_user_data_dir(&self, options: &BrowserOptions) -> FlowResult<(PathBuf, bool)> {
+        if let Some(dir) = &options.user_data_dir {
+            return Ok((PathBuf::from(dir), true));
+        }
+
+        if let Some(name) = &options.profile {
+            let path = self.profile_path(name)?;
+            std::fs::create_dir_all(&path)?;
+            return Ok((path, true));
         }
+
+        // Use a unique user data directory to avoid SingletonLock errors
+        Ok((std::env::temp_dir().join(format!("proxxy_browser_{}", Uuid::new_v4())), false))
     }
 
     /// Launch a new browser instance
@@ -159,8 +442,14 @@ impl BrowserManager {
         // Build browser configuration
         let mut config_builder = BrowserConfig::builder();
         
-        // Use a unique user data directory to avoid SingletonLock errors
-        let user_data_dir = std::env::temp_dir().join(format!("proxxy_browser_{}", Uuid::new_v4()));
+        let (user_data_dir, persistent) = self.resolve_user_data_dir(&options)?;
+        if persistent {
+            // Only one browser runs per manager, so leftover locks are stale
+            for lock in PROFILE_LOCK_FILES {
+                let _ = std::fs::remove_file(user_data_dir.join(lock));
+            }
+            info!("Using persistent browser profile: {:?}", user_data_dir);
+        }
         config_builder = config_builder.user_data_dir(&user_data_dir);
 
         // Set headed mode (with_head makes browser visible)
@@ -180,6 +469,14 @@ impl BrowserManager {
             config_builder = config_builder.arg("--ignore-ssl-errors");
         }
 
+        if options.security.disable_quic {
+            config_builder = config_builder.arg("--disable-quic");
+        }
+
+        if options.security.ignore_hsts {
+            clear_hsts_state(&user_data_dir);
+        }
+
         // Set window size
         if let Some((width, height)) = options.window_size {
             config_builder = config_builder.arg(format!("--window-size={},{}", width, height));
@@ -235,7 +532,8 @@ impl BrowserManager {
         let managed = ManagedBrowser {
             browser,
             options,
-            user_data_dir: Some(user_data_dir),
+            user_data_dir: (!persistent).then_some(user_data_dir),
+            persistent,
         };
 
         let mut guard = self.active_browser.write().await;
@@ -264,6 +562,20 @@ impl BrowserManager {
     }
 }
 
+fn dir_size(path: &std::path::Path) -> u64 {
+    std::fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| match e.file_type() {
+                    Ok(t) if t.is_dir() => dir_size(&e.path()),
+                    _ => e.metadata().map(|m| m.len()).unwrap_or(0),
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
 impl Default for BrowserManager {
     fn default() -> Self {
         Self::new()
@@ -289,8 +601,56 @@ mod tests {
         let opts = BrowserOptions::default();
         assert!(opts.headless);
         assert!(opts.ignore_ssl_errors);
+        assert!(!opts.uses_cert_allowlist());
 
         let headed = BrowserOptions::headed();
         assert!(!headed.headless);
     }
+
+    #[test]
+    fn test_cert_error_allowlist() {
+        let security = SecurityOptions {
+            cert_error_allowlist: vec!["*.staging.corp".to_string(), "legacy.example.com".to_string()],
+            ..Default::default()
+        };
+
+        assert!(security.allows_cert_errors("https://app.staging.corp/login"));
+        assert!(security.allows_cert_errors("https://LEGACY.example.com:8443/"));
+        assert!(!security.allows_cert_errors("https://staging.corp/"));
+        assert!(!security.allows_cert_errors("https://www.example.com/"));
+
+        let mut opts = BrowserOptions::default().with_security(security);
+        assert!(!opts.uses_cert_allowlist());
+        opts.ignore_ssl_errors = false;
+        assert!(opts.uses_cert_allowlist());
+    }
+
+    #[tokio::test]
+    async fn test_named_profiles() {
+        let root = std::env::temp_dir().join(format!("proxxy_profiles_test_{}", Uuid::new_v4()));
+        let manager = BrowserManager::with_profiles_dir(&root);
+
+        assert!(manager.profile_path("../escape").is_err());
+        assert!(BrowserManager::new().profile_path("work").is_err());
+        assert!(manager.list_profiles().unwrap().is_empty());
+
+        let (path, persistent) = manager
+            .resolve_user_data_dir(&BrowserOptions::default().with_profile("work"))
+            .unwrap();
+        assert!(persistent);
+        assert_eq!(path, root.join("work"));
+        std::fs::write(path.join("Cookies"), b"data").unwrap();
+
+        let (_, ephemeral) = manager.resolve_user_data_dir(&BrowserOptions::default()).unwrap();
+        assert!(!ephemeral);
+
+        let profiles = manager.list_profiles().unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, "work");
+        assert_eq!(profiles[0].size_bytes, 4);
+
+        assert!(manager.delete_profile("work").await.unwrap());
+        assert!(!manager.delete_profile("work").await.unwrap());
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
@@ -0,0 +1,618 @@
+//! DOM Crawler Module
+//!
+//! Drives a managed browser through in-scope pages, following links, clicking
+//! navigation elements and submitting benign forms. Because pages are rendered,
+//! client-side routes (History API / hash routers) are discovered alongside the
+//! regular HTTP navigations that flow through the agent proxy.
+
+use crate::error::{FlowEngineError, FlowResult};
+use crate::flow::browser::{BrowserManager, BrowserOptions, ProxyConfig};
+use crate::flow::model::WaitCondition;
+use crate::flow::page::PageController;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// Keywords that mark a form or clickable element as unsafe to trigger
+const DEFAULT_DANGEROUS_KEYWORDS: &[&str] = &[
+    "delete", "remove", "destroy", "logout", "log out", "sign out", "signout",
+    "unsubscribe", "cancel", "purchase", "checkout", "pay", "transfer", "reset",
+];
+
+/// URL path fragments that are never visited (would end the crawl session)
+const DEFAULT_EXCLUDED_PATHS: &[&str] = &["logout", "signout", "log-out", "sign-out", "logoff"];
+
+/// Installed on every document; records client-side route changes
+const ROUTE_HOOK_SCRIPT: &str = r#"
+(function() {
+    if (window.__proxxyCrawlHooked) return;
+    window.__proxxyCrawlHooked = true;
+    window.__proxxyCrawlRoutes = [];
+    const record = () => { try { window.__proxxyCrawlRoutes.push(location.href); } catch (e) {} };
+    const wrap = (name) => {
+        const original = history[name];
+        history[name] = function() {
+            const result = original.apply(this, arguments);
+            record();
+            return result;
+        };
+    };
+    wrap('pushState');
+    wrap('replaceState');
+    window.addEventListener('popstate', record);
+    window.addEventListener('hashchange', record);
+})();
+"#;
+
+/// Collects links, forms and recorded routes from the current document
+const COLLECT_SCRIPT: &str = r#"
+(() => {
+    const links = new Set();
+    document.querySelectorAll('a[href], area[href], [routerlink], [data-href]').forEach(el => {
+        const raw = el.getAttribute('href') || el.getAttribute('routerlink') || el.getAttribute('data-href');
+        if (!raw) return;
+        try { links.add(new URL(raw, location.href).href); } catch (e) {}
+    });
+    const forms = Array.from(document.forms).map((form, index) => {
+        const submit = form.querySelector('button[type=submit], input[type=submit], button:not([type])');
+        return {
+            index,
+            action: form.action || location.href,
+            method: (form.getAttribute('method') || 'GET').toUpperCase(),
+            has_password: !!form.querySelector('input[type=password]'),
+            has_file: !!form.querySelector('input[type=file]'),
+            label: [form.id, form.name, form.getAttribute('action'), submit ? (submit.innerText || submit.value) : '']
+                .filter(Boolean).join(' '),
+        };
+    });
+    return {
+        url: location.href,
+        title: document.title || null,
+        links: Array.from(links),
+        forms,
+        routes: window.__proxxyCrawlRoutes || [],
+    };
+})()
+"#;
+
+/// Selector for elements that may trigger client-side navigation when clicked
+const CLICKABLE_SELECTOR: &str =
+    "button:not([type=submit]):not([disabled]), [role=link], [role=tab], [role=menuitem], [onclick]";
+
+/// Where a route was discovered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RouteSource {
+    /// The crawl start URL
+    Start,
+    /// An anchor or router link in the DOM
+    Link,
+    /// A History API / hash change recorded in the page
+    HistoryApi,
+    /// Location changed after clicking an element
+    Click,
+    /// Location changed after submitting a form
+    Form,
+}
+
+/// A route discovered while crawling
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredRoute {
+    /// Normalized URL
+    pub url: String,
+    /// Depth at which it was discovered (start URL = 0)
+    pub depth: usize,
+    /// How it was discovered
+    pub source: RouteSource,
+    /// Document title when visited
+    pub title: Option<String>,
+    /// Whether the crawler actually rendered this route
+    pub visited: bool,
+}
+
+/// Crawl result summary
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrawlResult {
+    /// All in-scope routes, in discovery order
+    pub routes: Vec<DiscoveredRoute>,
+    /// Number of pages rendered
+    pub pages_visited: usize,
+    /// Number of forms submitted
+    pub forms_submitted: usize,
+    /// Non-fatal errors encountered (navigation failures etc.)
+    pub errors: Vec<String>,
+    /// Duration in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Crawler configuration
+#[derive(Debug, Clone)]
+pub struct CrawlConfig {
+    /// Maximum link depth from the start URL
+    pub max_depth: usize,
+    /// Maximum number of pages to render
+    pub max_pages: usize,
+    /// Allowed hosts (`example.com`, `*.example.com`). Empty = start URL host only
+    pub scope_hosts: Vec<String>,
+    /// Path substrings that are never visited
+    pub excluded_paths: Vec<String>,
+    /// Click buttons / role=link elements to surface client-side routes
+    pub click_elements: bool,
+    /// Maximum elements clicked per page
+    pub max_clicks_per_page: usize,
+    /// Submit forms that look benign
+    pub submit_forms: bool,
+    /// Allow submitting POST forms (GET forms only when false)
+    pub allow_post_forms: bool,
+    /// Keywords that mark a form or element as dangerous to trigger
+    pub dangerous_keywords: Vec<String>,
+    /// Time to let the page settle after navigation / interaction
+    pub settle_ms: u64,
+    /// Navigation timeout in seconds
+    pub page_timeout_secs: u64,
+    /// Run the browser headless
+    pub headless: bool,
+    /// Agent proxy to route traffic through
+    pub proxy: Option<ProxyConfig>,
+    /// Proxxy CA certificate for TLS trust
+    pub ca_cert_path: Option<String>,
+}
+
+impl Default for CrawlConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            max_pages: 100,
+            scope_hosts: Vec::new(),
+            excluded_paths: DEFAULT_EXCLUDED_PATHS.iter().map(|s| s.to_string()).collect(),
+            click_elements: true,
+            max_clicks_per_page: 20,
+            submit_forms: true,
+            allow_post_forms: false,
+            dangerous_keywords: DEFAULT_DANGEROUS_KEYWORDS.iter().map(|s| s.to_string()).collect(),
+            settle_ms: 800,
+            page_timeout_secs: 30,
+            headless: true,
+            proxy: None,
+            ca_cert_path: None,
+        }
+    }
+}
+
+impl CrawlConfig {
+    /// Route crawler traffic through an agent proxy
+    pub fn with_proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Set depth and page limits
+    pub fn with_limits(mut self, max_depth: usize, max_pages: usize) -> Self {
+        self.max_depth = max_depth;
+        self.max_pages = max_pages;
+        self
+    }
+}
+
+/// Form metadata collected from the DOM
+#[derive(Debug, Clone, Deserialize)]
+struct FormInfo {
+    index: usize,
+    action: String,
+    method: String,
+    has_password: bool,
+    has_file: bool,
+    label: String,
+}
+
+/// Page snapshot returned by the collect script
+#[derive(Debug, Clone, Deserialize)]
+struct PageSnapshot {
+    url: String,
+    title: Option<String>,
+    links: Vec<String>,
+    forms: Vec<FormInfo>,
+    routes: Vec<String>,
+}
+
+/// DOM crawler - explores rendered pages with a managed browser
+pub struct DomCrawler {
+    browser_manager: BrowserManager,
+    config: CrawlConfig,
+}
+
+/// Mutable crawl bookkeeping
+struct CrawlState {
+    queue: VecDeque<(String, usize)>,
+    seen: HashSet<String>,
+    result: CrawlResult,
+}
+
+impl CrawlState {
+    fn route_mut(&mut self, url: &str) -> Option<&mut DiscoveredRoute> {
+        self.result.routes.iter_mut().find(|r| r.url == url)
+    }
+}
+
+impl DomCrawler {
+    /// Create a crawler with the given configuration
+    pub fn new(config: CrawlConfig) -> Self {
+        Self {
+            browser_manager: BrowserManager::new(),
+            config,
+        }
+    }
+
+    /// Crawl starting at `start_url`
+    pub async fn crawl(&self, start_url: &str) -> FlowResult<CrawlResult> {
+        let start_time = std::time::Instant::now();
+
+        let start = normalize_url(start_url)
+            .ok_or_else(|| FlowEngineError::Navigation(format!("Invalid start URL: {}", start_url)))?;
+        let start_host = Url::parse(&start)
+            .ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+        let scope = if self.config.scope_hosts.is_empty() {
+            vec![start_host]
+        } else {
+            self.config.scope_hosts.clone()
+        };
+
+        info!("🕸️ Starting DOM crawl: {} (depth: {}, pages: {})", start, self.config.max_depth, self.config.max_pages);
+
+        let mut options = BrowserOptions::default().headless(self.config.headless);
+        options.proxy = self.config.proxy.clone();
+        options.ca_cert_path = self.config.ca_cert_path.clone();
+
+        let browser_arc = self.browser_manager.launch(options).await?;
+        let browser_guard = browser_arc.read().await;
+        let managed = browser_guard.as_ref()
+            .ok_or_else(|| FlowEngineError::BrowserLaunch("No browser available".to_string()))?;
+
+        let page = managed.new_page("about:blank").await?;
+
+        if let Err(e) = page.evaluate_on_new_document(ROUTE_HOOK_SCRIPT).await {
+            warn!("Failed to register route hook: {}", e);
+        }
+
+        let controller = PageController::new(page)
+            .with_timeout(Duration::from_secs(self.config.page_timeout_secs));
+
+        let mut state = CrawlState {
+            queue: VecDeque::new(),
+            seen: HashSet::new(),
+            result: CrawlResult::default(),
+        };
+        self.enqueue(&mut state, &scope, &start, 0, RouteSource::Start);
+
+        while let Some((url, depth)) = state.queue.pop_front() {
+            if state.result.pages_visited >= self.config.max_pages {
+                info!("Page limit reached ({}), stopping crawl", self.config.max_pages);
+                break;
+            }
+
+            if let Err(e) = self.crawl_page(&controller, &mut state, &scope, &url, depth).await {
+                debug!("Crawl of {} failed: {}", url, e);
+                state.result.errors.push(format!("{}: {}", url, e));
+            }
+        }
+
+        drop(browser_guard);
+        self.browser_manager.close().await.ok();
+
+        state.result.duration_ms = start_time.elapsed().as_millis() as u64;
+        info!(
+            "🕸️ DOM crawl finished: {} routes, {} pages, {} forms in {}ms",
+            state.result.routes.len(),
+            state.result.pages_visited,
+            state.result.forms_submitted,
+            state.result.duration_ms
+        );
+
+        Ok(state.result)
+    }
+
+    /// Render a single page and harvest routes from it
+    async fn crawl_page(
+        &self,
+        controller: &PageController,
+        state: &mut CrawlState,
+        scope: &[String],
+        url: &str,
+        depth: usize,
+    ) -> FlowResult<()> {
+        self.load(controller, url).await?;
+        state.result.pages_visited += 1;
+
+        let snapshot = self.snapshot(controller).await?;
+        if let Some(route) = state.route_mut(url) {
+            route.visited = true;
+            route.title = snapshot.title.clone();
+        }
+
+        // Redirects and client-side routing on load land on a different URL
+        self.enqueue(state, scope, &snapshot.url, depth, RouteSource::HistoryApi);
+        for route in &snapshot.routes {
+            self.enqueue(state, scope, route, depth, RouteSource::HistoryApi);
+        }
+
+        if depth >= self.config.max_depth {
+            return Ok(());
+        }
+
+        for link in &snapshot.links {
+            self.enqueue(state, scope, link, depth + 1, RouteSource::Link);
+        }
+
+        if self.config.click_elements {
+            self.click_elements(controller, state, scope, url, depth).await;
+        }
+
+        if self.config.submit_forms {
+            for form in snapshot.forms.iter().filter(|f| self.is_benign_form(f)) {
+                if let Err(e) = self.submit_form(controller, state, scope, url, depth, form).await {
+                    debug!("Form {} on {} failed: {}", form.index, url, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Click navigation-like elements and record any resulting location change
+    async fn click_elements(
+        &self,
+        controller: &PageController,
+        state: &mut CrawlState,
+        scope: &[String],
+        url: &str,
+        depth: usize,
+    ) {
+        let labels_script = format!(
+            "Array.from(document.querySelectorAll({:?})).map(el => (el.innerText || el.getAttribute('aria-label') || '').trim())",
+            CLICKABLE_SELECTOR
+        );
+        let labels: Vec<String> = match controller.execute_script(&labels_script).await {
+            Ok(value) => serde_json::from_value(value).unwrap_or_default(),
+            Err(_) => return,
+        };
+
+        let mut clicked = 0;
+        for (index, label) in labels.iter().enumerate() {
+            if clicked >= self.config.max_clicks_per_page {
+                break;
+            }
+            if self.is_dangerous(label) {
+                debug!("Skipping dangerous element '{}' on {}", label, url);
+                continue;
+            }
+
+            // Every click starts from a fresh render of the source page
+            if clicked > 0 && self.load(controller, url).await.is_err() {
+                return;
+            }
+
+            let script = format!(
+                "(() => {{ const el = document.querySelectorAll({:?})[{}]; if (!el) return false; el.click(); return true; }})()",
+                CLICKABLE_SELECTOR, index
+            );
+            if !matches!(controller.execute_script(&script).await, Ok(serde_json::Value::Bool(true))) {
+                continue;
+            }
+            clicked += 1;
+            tokio::time::sleep(Duration::from_millis(self.config.settle_ms)).await;
+
+            if let Ok(snapshot) = self.snapshot(controller).await {
+                self.enqueue(state, scope, &snapshot.url, depth + 1, RouteSource::Click);
+                for route in &snapshot.routes {
+                    self.enqueue(state, scope, route, depth + 1, RouteSource::Click);
+                }
+            }
+        }
+    }
+
+    /// Fill a form with placeholder values, submit it and record where it lands
+    async fn submit_form(
+        &self,
+        controller: &PageController,
+        state: &mut CrawlState,
+        scope: &[String],
+        url: &str,
+        depth: usize,
+        form: &FormInfo,
+    ) -> FlowResult<()> {
+        self.load(controller, url).await?;
+
+        let script = format!(
+            r#"(() => {{
+                const form = document.forms[{}];
+                if (!form) return false;
+                form.querySelectorAll('input, textarea').forEach(el => {{
+                    if (el.disabled || el.readOnly || el.value) return;
+                    switch ((el.type || 'text').toLowerCase()) {{
+                        case 'hidden': case 'checkbox': case 'radio': case 'submit': case 'button': return;
+                        case 'email': el.value = 'crawler@example.com'; break;
+                        case 'number': case 'range': el.value = '1'; break;
+                        case 'tel': el.value = '5555550100'; break;
+                        case 'url': el.value = 'https://example.com'; break;
+                        case 'date': el.value = '2024-01-01'; break;
+                        default: el.value = 'proxxy';
+                    }}
+                    el.dispatchEvent(new Event('input', {{ bubbles: true }}));
+                }});
+                if (form.requestSubmit) form.requestSubmit(); else form.submit();
+                return true;
+            }})()"#,
+            form.index
+        );
+
+        if controller.execute_script(&script).await? == serde_json::Value::Bool(true) {
+            state.result.forms_submitted += 1;
+            tokio::time::sleep(Duration::from_millis(self.config.settle_ms)).await;
+            let landed = controller.get_url().await?;
+            self.enqueue(state, scope, &landed, depth + 1, RouteSource::Form);
+        }
+
+        Ok(())
+    }
+
+    /// Navigate and wait for the page to settle
+    async fn load(&self, controller: &PageController, url: &str) -> FlowResult<()> {
+        let timeout = Duration::from_secs(self.config.page_timeout_secs);
+        tokio::time::timeout(timeout, controller.navigate(url))
+            .await
+            .map_err(|_| FlowEngineError::Timeout {
+                condition: "Navigation".to_string(),
+                details: format!("{} did not load within {}s", url, timeout.as_secs()),
+            })??;
+        controller.wait_for_condition(&WaitCondition::PageLoaded).await?;
+        tokio::time::sleep(Duration::from_millis(self.config.settle_ms)).await;
+        Ok(())
+    }
+
+    async fn snapshot(&self, controller: &PageController) -> FlowResult<PageSnapshot> {
+        let value = controller.execute_script(COLLECT_SCRIPT).await?;
+        serde_json::from_value(value)
+            .map_err(|e| FlowEngineError::Replay(format!("Failed to parse page snapshot: {}", e)))
+    }
+
+    /// Record a route and queue it for rendering if it is new and in scope
+    fn enqueue(&self, state: &mut CrawlState, scope: &[String], raw: &str, depth: usize, source: RouteSource) {
+        let Some(url) = normalize_url(raw) else { return };
+        if !is_in_scope(&url, scope) || self.is_excluded(&url) || !state.seen.insert(url.clone()) {
+            return;
+        }
+
+        debug!("Discovered route {} ({:?}, depth {})", url, source, depth);
+        state.result.routes.push(DiscoveredRoute {
+            url: url.clone(),
+            depth,
+            source,
+            title: None,
+            visited: false,
+        });
+        if depth <= self.config.max_depth {
+            state.queue.push_back((url, depth));
+        }
+    }
+
+    fn is_excluded(&self, url: &str) -> bool {
+        let path = Url::parse(url).map(|u| u.path().to_lowercase()).unwrap_or_default();
+        self.config.excluded_paths.iter().any(|p| path.contains(&p.to_lowercase()))
+    }
+
+    fn is_dangerous(&self, label: &str) -> bool {
+        let label = label.to_lowercase();
+        self.config.dangerous_keywords.iter().any(|k| label.contains(&k.to_lowercase()))
+    }
+
+    fn is_benign_form(&self, form: &FormInfo) -> bool {
+        if form.has_password || form.has_file {
+            return false;
+        }
+        if form.method != "GET" && !self.config.allow_post_forms {
+            return false;
+        }
+        !self.is_dangerous(&form.label) && !self.is_excluded(&form.action)
+    }
+}
+
+/// Normalize a URL for deduplication.
+///
+/// Fragments are dropped unless they look like hash-router routes (`#/path`, `#!/path`).
+/// Returns `None` for non-HTTP(S) URLs.
+pub fn normalize_url(raw: &str) -> Option<String> {
+    let mut url = Url::parse(raw.trim()).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    let keep_fragment = url
+        .fragment()
+        .map(|f| f.starts_with('/') || f.starts_with("!/"))
+        .unwrap_or(false);
+    if !keep_fragment {
+        url.set_fragment(None);
+    }
+
+    Some(url.to_string())
+}
+
+/// Check a URL's host against scope patterns (`example.com` or `*.example.com`)
+pub fn is_in_scope(url: &str, scope_hosts: &[String]) -> bool {
+    let Some(host) = Url::parse(url).ok().and_then(|u| u.host_str().map(|h| h.to_lowercase())) else {
+        return false;
+    };
+
+    scope_hosts.iter().any(|pattern| {
+        let pattern = pattern.trim().to_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(base) => host == base || host.ends_with(&format!(".{}", base)),
+            None => host == pattern,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn form(method: &str, label: &str) -> FormInfo {
+        FormInfo {
+            index: 0,
+            action: "https://example.com/search".to_string(),
+            method: method.to_string(),
+            has_password: false,
+            has_file: false,
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_url_fragments() {
+        assert_eq!(
+            normalize_url("https://example.com/page#section").as_deref(),
+            Some("https://example.com/page")
+        );
+        assert_eq!(
+            normalize_url("https://example.com/#/dashboard").as_deref(),
+            Some("https://example.com/#/dashboard")
+        );
+        assert_eq!(normalize_url("javascript:void(0)"), None);
+        assert_eq!(normalize_url("mailto:admin@example.com"), None);
+    }
+
+    #[test]
+    fn test_scope_matching() {
+        let scope = vec!["example.com".to_string(), "*.api.test".to_string()];
+        assert!(is_in_scope("https://example.com/a", &scope));
+        assert!(!is_in_scope("https://evil.com/a", &scope));
+        assert!(!is_in_scope("https://sub.example.com/a", &scope));
+        assert!(is_in_scope("https://v1.api.test/x", &scope));
+        assert!(is_in_scope("https://api.test/x", &scope));
+    }
+
+    #[test]
+    fn test_benign_form_detection() {
+        let crawler = DomCrawler::new(CrawlConfig::default());
+        assert!(crawler.is_benign_form(&form("GET", "search-form Search")));
+        assert!(!crawler.is_benign_form(&form("POST", "contact Send")));
+        assert!(!crawler.is_benign_form(&form("GET", "Delete account")));
+
+        let mut login = form("GET", "login");
+        login.has_password = true;
+        assert!(!crawler.is_benign_form(&login));
+
+        let crawler = DomCrawler::new(CrawlConfig { allow_post_forms: true, ..Default::default() });
+        assert!(crawler.is_benign_form(&form("POST", "contact Send")));
+    }
+
+    #[test]
+    fn test_logout_paths_excluded() {
+        let crawler = DomCrawler::new(CrawlConfig::default());
+        assert!(crawler.is_excluded("https://example.com/account/logout"));
+        assert!(!crawler.is_excluded("https://example.com/account"));
+    }
+}
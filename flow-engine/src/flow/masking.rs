@@ -0,0 +1,140 @@
+//! Value Masking Module
+//!
+//! Decides which recorded inputs are sensitive and keeps their values out of
+//! logs, step listings and replay screenshots. Values are still stored in
+//! `SecretString` so replays can type them; revealing one is an explicit,
+//! policy-gated action.
+
+use crate::flow::model::FlowStep;
+use secrecy::SecretString;
+use serde::{Deserialize, Serialize};
+
+/// Placeholder shown instead of a masked value
+pub const MASKED_VALUE: &str = "********";
+
+/// Policy for tagging and masking sensitive inputs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaskingPolicy {
+    /// Treat `<input type="password">` as sensitive
+    pub mask_password_fields: bool,
+    /// Case-insensitive substrings matched against a field's name, id,
+    /// placeholder, aria-label and autocomplete attributes
+    pub field_patterns: Vec<String>,
+    /// Whether masked values may be revealed through the API
+    pub allow_reveal: bool,
+}
+
+impl Default for MaskingPolicy {
+    fn default() -> Self {
+        Self {
+            mask_password_fields: true,
+            field_patterns: [
+                "password", "passwd", "pwd", "secret", "token", "otp", "mfa", "2fa", "pin", "cvv", "cvc",
+                "card", "ssn", "api_key", "apikey",
+            ]
+            .iter()
+            .map(|p| p.to_string())
+            .collect(),
+            allow_reveal: false,
+        }
+    }
+}
+
+impl MaskingPolicy {
+    /// Whether a field should be tagged sensitive.
+    /// `attributes` are the field's identifying attributes (name, id, placeholder, ...).
+    pub fn is_sensitive<'a>(&self, is_password: bool, attributes: impl IntoIterator<Item = &'a str>) -> bool {
+        if is_password && self.mask_password_fields {
+            return true;
+        }
+
+        attributes.into_iter().any(|attr| {
+            let attr = attr.to_lowercase();
+            self.field_patterns
+                .iter()
+                .filter(|p| !p.is_empty())
+                .any(|p| attr.contains(&p.to_lowercase()))
+        })
+    }
+}
+
+/// Copy of a step that is safe to display: masked typed values are replaced
+pub fn masked_step(step: &FlowStep) -> FlowStep {
+    match step {
+        FlowStep::Type { selector, is_masked: true, clear_first, .. } => FlowStep::Type {
+            selector: selector.clone(),
+            value: SecretString::new(MASKED_VALUE.into()),
+            is_masked: true,
+            clear_first: *clear_first,
+        },
+        other => other.clone(),
+    }
+}
+
+/// Mask every sensitive value in a list of steps
+pub fn masked_steps(steps: &[FlowStep]) -> Vec<FlowStep> {
+    steps.iter().map(masked_step).collect()
+}
+
+/// Mask a JSON-encoded step list.
+/// Input that does not parse as steps is withheld rather than passed through.
+pub fn mask_steps_json(steps_json: &str) -> String {
+    serde_json::from_str::<Vec<FlowStep>>(steps_json)
+        .ok()
+        .and_then(|steps| serde_json::to_string(&masked_steps(&steps)).ok())
+        .unwrap_or_else(|| "[]".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::model::SmartSelector;
+    use secrecy::ExposeSecret;
+
+    fn type_step(value: &str, is_masked: bool) -> FlowStep {
+        FlowStep::Type {
+            selector: SmartSelector::id("field"),
+            value: SecretString::new(value.into()),
+            is_masked,
+            clear_first: false,
+        }
+    }
+
+    #[test]
+    fn test_sensitive_field_detection() {
+        let policy = MaskingPolicy::default();
+        assert!(policy.is_sensitive(true, []));
+        assert!(policy.is_sensitive(false, ["user_Password_confirm"]));
+        assert!(policy.is_sensitive(false, ["email", "One-time OTP code"]));
+        assert!(!policy.is_sensitive(false, ["email", "username"]));
+
+        let relaxed = MaskingPolicy {
+            mask_password_fields: false,
+            field_patterns: vec!["badge".into()],
+            ..Default::default()
+        };
+        assert!(!relaxed.is_sensitive(true, ["login"]));
+        assert!(relaxed.is_sensitive(false, ["employee-badge"]));
+    }
+
+    #[test]
+    fn test_masking_steps() {
+        let steps = vec![type_step("hunter2", true), type_step("alice", false)];
+
+        let masked = masked_steps(&steps);
+        match (&masked[0], &masked[1]) {
+            (FlowStep::Type { value: a, .. }, FlowStep::Type { value: b, .. }) => {
+                assert_eq!(a.expose_secret(), MASKED_VALUE);
+                assert_eq!(b.expose_secret(), "alice");
+            }
+            other => panic!("unexpected steps {:?}", other),
+        }
+
+        let json = serde_json::to_string(&steps).unwrap();
+        let masked_json = mask_steps_json(&json);
+        assert!(!masked_json.contains("hunter2"));
+        assert!(masked_json.contains("alice"));
+        assert_eq!(mask_steps_json("not json"), "[]");
+    }
+}
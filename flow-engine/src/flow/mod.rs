@@ -6,3 +6,7 @@ pub mod page;
 pub mod analyzer;
 pub mod recorder;
 pub mod replayer;
+pub mod parallel;
+pub mod masking;
+pub mod crawler;
+pub mod render;
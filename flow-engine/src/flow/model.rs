@@ -3,6 +3,7 @@
 //! Core data structures for recording and replaying browser flows.
 //! These models support any user-defined flow: login, checkout, form filling, etc.
 
+use crate::error::{FlowEngineError, FlowResult};
 use chrono::{DateTime, Utc};
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
@@ -82,6 +83,56 @@ impl FlowProfile {
     pub fn step_count(&self) -> usize {
         self.steps.len()
     }
+
+    /// Insert a step before `index` (`index == step_count()` appends)
+    pub fn insert_step(&mut self, index: usize, step: FlowStep) -> FlowResult<()> {
+        if index > self.steps.len() {
+            return Err(self.out_of_range(index));
+        }
+        self.steps.insert(index, step);
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Remove and return the step at `index`
+    pub fn remove_step(&mut self, index: usize) -> FlowResult<FlowStep> {
+        if index >= self.steps.len() {
+            return Err(self.out_of_range(index));
+        }
+        self.updated_at = Utc::now();
+        Ok(self.steps.remove(index))
+    }
+
+    /// Move the step at `from` so it ends up at position `to`
+    pub fn move_step(&mut self, from: usize, to: usize) -> FlowResult<()> {
+        if from >= self.steps.len() {
+            return Err(self.out_of_range(from));
+        }
+        if to >= self.steps.len() {
+            return Err(self.out_of_range(to));
+        }
+        let step = self.steps.remove(from);
+        self.steps.insert(to, step);
+        self.updated_at = Utc::now();
+        Ok(())
+    }
+
+    /// Mutable access to the step at `index`
+    pub fn step_mut(&mut self, index: usize) -> FlowResult<&mut FlowStep> {
+        if index >= self.steps.len() {
+            return Err(self.out_of_range(index));
+        }
+        self.updated_at = Utc::now();
+        Ok(&mut self.steps[index])
+    }
+
+    fn out_of_range(&self, index: usize) -> FlowEngineError {
+        FlowEngineError::InvalidStep(format!(
+            "index {} out of range for {} step(s)",
+            index,
+            self.steps.len()
+        ))
+    }
 }
 
 /// Profile status
@@ -113,6 +164,9 @@ pub struct FlowMeta {
     pub success_indicators: Vec<String>,
     /// Custom key-value metadata
     pub custom: serde_json::Value,
+    /// Persistent browser profile to replay in (remember-me / device trust state).
+    /// Replays use a fresh profile when unset.
+    pub browser_profile: Option<String>,
 }
 
 /// Individual step in a browser flow
@@ -217,6 +271,58 @@ pub enum FlowStep {
     },
 }
 
+impl FlowStep {
+    /// Step variant name, e.g. `Click`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FlowStep::Navigate { .. } => "Navigate",
+            FlowStep::Click { .. } => "Click",
+            FlowStep::Type { .. } => "Type",
+            FlowStep::Wait { .. } => "Wait",
+            FlowStep::CheckSession { .. } => "CheckSession",
+            FlowStep::Submit { .. } => "Submit",
+            FlowStep::Select { .. } => "Select",
+            FlowStep::Hover { .. } => "Hover",
+            FlowStep::KeyPress { .. } => "KeyPress",
+            FlowStep::Screenshot { .. } => "Screenshot",
+            FlowStep::Extract { .. } => "Extract",
+            FlowStep::ExecuteScript { .. } => "ExecuteScript",
+            FlowStep::Custom { .. } => "Custom",
+        }
+    }
+
+    /// Element selector targeted by this step, if any
+    pub fn selector_mut(&mut self) -> Option<&mut SmartSelector> {
+        match self {
+            FlowStep::Click { selector, .. }
+            | FlowStep::Type { selector, .. }
+            | FlowStep::Submit { selector, .. }
+            | FlowStep::Select { selector, .. }
+            | FlowStep::Hover { selector }
+            | FlowStep::Extract { selector, .. } => Some(selector),
+            _ => None,
+        }
+    }
+
+    /// Replace the typed value of a `Type` step. The value stays wrapped in a
+    /// `SecretString`; `is_masked` is only changed when given.
+    pub fn set_typed_value(&mut self, new_value: SecretString, masked: Option<bool>) -> FlowResult<()> {
+        match self {
+            FlowStep::Type { value, is_masked, .. } => {
+                *value = new_value;
+                if let Some(masked) = masked {
+                    *is_masked = masked;
+                }
+                Ok(())
+            }
+            other => Err(FlowEngineError::InvalidStep(format!(
+                "{} step has no typed value",
+                other.kind()
+            ))),
+        }
+    }
+}
+
 /// What to extract from an element
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ExtractType {
@@ -397,6 +503,43 @@ mod tests {
         assert_eq!(profile.step_count(), 2);
     }
 
+    #[test]
+    fn test_step_editing() {
+        let mut profile = FlowProfile::new("Login", "https://example.com/login");
+        profile.add_step(FlowStep::Click { selector: SmartSelector::id("a"), wait_for: None });
+        profile.add_step(FlowStep::Click { selector: SmartSelector::id("b"), wait_for: None });
+
+        profile
+            .insert_step(1, FlowStep::Type {
+                selector: SmartSelector::id("password"),
+                value: SecretString::new("old".into()),
+                is_masked: true,
+                clear_first: true,
+            })
+            .unwrap();
+        assert!(profile.insert_step(9, FlowStep::Screenshot { filename: None }).is_err());
+
+        profile.move_step(0, 2).unwrap();
+        assert!(matches!(&profile.steps[2], FlowStep::Click { selector, .. } if selector.value == "#a"));
+
+        let step = profile.step_mut(0).unwrap();
+        step.set_typed_value(SecretString::new("new".into()), None).unwrap();
+        step.selector_mut().unwrap().value = "#pass".to_string();
+        match &profile.steps[0] {
+            FlowStep::Type { value, is_masked, selector, .. } => {
+                assert_eq!(value.expose_secret(), "new");
+                assert!(*is_masked);
+                assert_eq!(selector.value, "#pass");
+            }
+            other => panic!("unexpected step {:?}", other),
+        }
+        assert!(profile.steps[1].clone().set_typed_value(SecretString::new("x".into()), None).is_err());
+
+        assert!(profile.remove_step(3).is_err());
+        profile.remove_step(1).unwrap();
+        assert_eq!(profile.step_count(), 2);
+    }
+
     #[test]
     fn test_smart_selector_creation() {
         let css = SmartSelector::css(".login-button");
@@ -5,6 +5,7 @@
 use crate::error::{FlowEngineError, FlowResult};
 use crate::flow::model::{SmartSelector, SelectorType, WaitCondition};
 use chromiumoxide::Page;
+use std::sync::Mutex;
 use std::time::Duration;
 use tracing::{debug, info, warn, error};
 
@@ -15,6 +16,8 @@ const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
 pub struct PageController {
     page: Page,
     default_timeout: Duration,
+    /// Alternative selector used by the last lookup whose primary selector missed
+    healed_selector: Mutex<Option<String>>,
 }
 
 impl PageController {
@@ -23,6 +26,7 @@ impl PageController {
         Self {
             page,
             default_timeout: DEFAULT_TIMEOUT,
+            healed_selector: Mutex::new(None),
         }
     }
 
@@ -37,6 +41,11 @@ impl PageController {
         &self.page
     }
 
+    /// Take the alternative selector that replaced a stale primary selector, if any
+    pub fn take_healed_selector(&self) -> Option<String> {
+        self.healed_selector.lock().ok()?.take()
+    }
+
     /// Navigate to a URL
     pub async fn navigate(&self, url: &str) -> FlowResult<()> {
         info!("Navigating to: {}", url);
@@ -173,6 +182,18 @@ impl PageController {
         Ok(())
     }
 
+    /// Render an input's value as bullets so screenshots don't capture it
+    pub async fn mask_element(&self, selector: &SmartSelector) -> FlowResult<()> {
+        let element = self.find_element_with_fallback(selector).await?;
+
+        element
+            .call_js_fn("function() { this.style.webkitTextSecurity = 'disc'; }", false)
+            .await
+            .map_err(|e| FlowEngineError::Replay(format!("Masking element failed: {}", e)))?;
+
+        Ok(())
+    }
+
     /// Hover over an element
     pub async fn hover(&self, selector: &SmartSelector) -> FlowResult<()> {
         let element = self.find_element_with_fallback(selector).await?;
@@ -288,6 +309,9 @@ impl PageController {
 
                 if let Ok(element) = self.page.find_element(&alt_css).await {
                     info!("Found element using alternative selector: {}", alt.value);
+                    if let Ok(mut healed) = self.healed_selector.lock() {
+                        *healed = Some(alt.value.clone());
+                    }
                     return Ok(element);
                 }
             }
@@ -0,0 +1,246 @@
+//! Parallel Replay Module
+//!
+//! Replays a single flow on several agents at once. Each agent gets its own
+//! browser routed through its own proxy, so sessions can be generated in bulk
+//! or geo-dependent behavior compared across vantage points.
+
+use crate::error::FlowEngineError;
+use crate::flow::browser::ProxyConfig;
+use crate::flow::model::FlowProfile;
+use crate::flow::replayer::{FlowReplayer, ReplayOptions, ReplayResult};
+use futures::stream::{self, StreamExt};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Agent a replay is routed through
+#[derive(Debug, Clone)]
+pub struct ReplayTarget {
+    pub agent_id: String,
+    /// Agent proxy; `None` replays directly
+    pub proxy: Option<ProxyConfig>,
+}
+
+impl ReplayTarget {
+    pub fn new(agent_id: impl Into<String>, proxy: Option<ProxyConfig>) -> Self {
+        Self {
+            agent_id: agent_id.into(),
+            proxy,
+        }
+    }
+}
+
+/// Outcome of the replay on one agent
+#[derive(Debug, Clone)]
+pub struct AgentReplayResult {
+    pub agent_id: String,
+    pub result: ReplayResult,
+}
+
+/// Aggregated outcome of a parallel replay
+#[derive(Debug, Clone, Default)]
+pub struct ParallelReplayResult {
+    /// Per-agent results, in target order
+    pub results: Vec<AgentReplayResult>,
+    /// Wall-clock duration of the whole run in milliseconds
+    pub duration_ms: u64,
+}
+
+impl ParallelReplayResult {
+    /// Number of agents whose replay succeeded
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|r| r.result.success).count()
+    }
+
+    /// Number of agents whose replay failed
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.succeeded()
+    }
+
+    /// Session cookies captured per agent
+    pub fn sessions(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.results.iter().filter_map(|r| {
+            r.result
+                .session_cookies
+                .as_deref()
+                .map(|cookies| (r.agent_id.as_str(), cookies))
+        })
+    }
+}
+
+/// Progress callback: (agent_id, current_step, total_steps)
+pub type AgentProgressCallback = Arc<dyn Fn(&str, usize, usize) + Send + Sync>;
+
+/// Replays one flow concurrently on multiple agents
+pub struct ParallelReplayer {
+    options: ReplayOptions,
+    profiles_dir: Option<PathBuf>,
+    max_concurrency: usize,
+    timeout: Option<Duration>,
+    progress_callback: Option<AgentProgressCallback>,
+}
+
+impl ParallelReplayer {
+    /// Create a parallel replayer; `options` apply to every agent
+    pub fn new(options: ReplayOptions) -> Self {
+        Self {
+            options,
+            profiles_dir: None,
+            max_concurrency: 4,
+            timeout: None,
+            progress_callback: None,
+        }
+    }
+
+    /// Maximum number of browsers running at the same time
+    pub fn with_max_concurrency(mut self, max_concurrency: usize) -> Self {
+        self.max_concurrency = max_concurrency.max(1);
+        self
+    }
+
+    /// Per-agent time limit; replays exceeding it are reported as failed
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Keep named browser profiles under `dir`
+    pub fn with_profiles_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.profiles_dir = Some(dir.into());
+        self
+    }
+
+    /// Set progress callback
+    pub fn set_progress_callback(&mut self, callback: AgentProgressCallback) {
+        self.progress_callback = Some(callback);
+    }
+
+    /// Replay `profile` on every target and collect the results
+    pub async fn execute(&self, profile: &FlowProfile, targets: Vec<ReplayTarget>) -> ParallelReplayResult {
+        let start_time = Instant::now();
+        info!(
+            "Starting parallel replay: {} on {} agent(s), concurrency {}",
+            profile.name,
+            targets.len(),
+            self.max_concurrency
+        );
+
+        let mut results: Vec<(usize, AgentReplayResult)> = stream::iter(targets.into_iter().enumerate())
+            .map(|(index, target)| async move { (index, self.execute_one(profile, target).await) })
+            .buffer_unordered(self.max_concurrency)
+            .collect()
+            .await;
+        results.sort_by_key(|(index, _)| *index);
+
+        let result = ParallelReplayResult {
+            results: results.into_iter().map(|(_, r)| r).collect(),
+            duration_ms: start_time.elapsed().as_millis() as u64,
+        };
+
+        info!(
+            "Parallel replay completed: {} succeeded, {} failed in {}ms",
+            result.succeeded(),
+            result.failed(),
+            result.duration_ms
+        );
+        result
+    }
+
+    async fn execute_one(&self, profile: &FlowProfile, target: ReplayTarget) -> AgentReplayResult {
+        let start_time = Instant::now();
+        let total_steps = profile.steps.len();
+
+        let mut options = self.options.clone();
+        options.proxy = target.proxy.clone();
+        // Chromium locks its profile directory, so each agent needs its own copy
+        options.browser_profile = options
+            .browser_profile
+            .as_deref()
+            .or(profile.meta.browser_profile.as_deref())
+            .map(|name| agent_profile_name(name, &target.agent_id));
+
+        let mut replayer = FlowReplayer::with_options(options);
+        if let Some(dir) = &self.profiles_dir {
+            replayer = replayer.with_profiles_dir(dir.clone());
+        }
+        if let Some(cb) = &self.progress_callback {
+            let cb = Arc::clone(cb);
+            let agent_id = target.agent_id.clone();
+            replayer.set_progress_callback(Arc::new(move |current, total| cb(&agent_id, current, total)));
+        }
+
+        let outcome = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, replayer.execute(profile))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(FlowEngineError::Timeout {
+                        condition: "replay".to_string(),
+                        details: format!("exceeded {}s", timeout.as_secs()),
+                    })
+                }),
+            None => replayer.execute(profile).await,
+        };
+
+        let result = outcome.unwrap_or_else(|e| {
+            warn!("Replay on agent {} failed: {}", target.agent_id, e);
+            ReplayResult::failed(e.to_string(), total_steps, start_time.elapsed().as_millis() as u64)
+        });
+
+        AgentReplayResult {
+            agent_id: target.agent_id,
+            result,
+        }
+    }
+}
+
+/// Profile name used for one agent's copy of a persistent profile
+fn agent_profile_name(profile: &str, agent_id: &str) -> String {
+    let agent: String = agent_id
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}-{}", profile, agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_profile_names_are_distinct_and_safe() {
+        assert_eq!(agent_profile_name("corp", "agent-1"), "corp-agent-1");
+        assert_eq!(agent_profile_name("corp", "eu west/2"), "corp-eu_west_2");
+        assert_ne!(agent_profile_name("corp", "a"), agent_profile_name("corp", "b"));
+    }
+
+    #[test]
+    fn test_aggregation() {
+        let ok = ReplayResult {
+            session_cookies: Some("[]".to_string()),
+            success: true,
+            error: None,
+            ..ReplayResult::failed("", 2, 10)
+        };
+        let result = ParallelReplayResult {
+            results: vec![
+                AgentReplayResult { agent_id: "a".into(), result: ok },
+                AgentReplayResult { agent_id: "b".into(), result: ReplayResult::failed("boom", 2, 10) },
+            ],
+            duration_ms: 10,
+        };
+
+        assert_eq!(result.succeeded(), 1);
+        assert_eq!(result.failed(), 1);
+        assert_eq!(result.sessions().collect::<Vec<_>>(), vec![("a", "[]")]);
+    }
+
+    #[tokio::test]
+    async fn test_no_targets() {
+        let profile = FlowProfile::new("login", "https://example.com");
+        let result = ParallelReplayer::new(ReplayOptions::default())
+            .execute(&profile, Vec::new())
+            .await;
+        assert!(result.results.is_empty());
+    }
+}
@@ -0,0 +1,256 @@
+//! Response Rendering
+//!
+//! Renders captured HTML responses to PNG screenshots for visual previews and
+//! evidence. Pages are rendered in a dedicated headless browser that cannot
+//! reach the network: every request the page makes is failed, host resolution
+//! is disabled, and scripts do not run unless explicitly allowed. A stored
+//! response is untrusted content; rendering it must not call back to the
+//! target or anyone else.
+
+use crate::error::{FlowEngineError, FlowResult};
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetDeviceMetricsOverrideParams, SetScriptExecutionDisabledParams,
+};
+use chromiumoxide::cdp::browser_protocol::fetch::{
+    EnableParams as FetchEnableParams, EventRequestPaused, FailRequestParams, RequestPattern,
+};
+use chromiumoxide::cdp::browser_protocol::network::ErrorReason;
+use chromiumoxide::cdp::browser_protocol::page::{CaptureScreenshotFormat, SetDocumentContentParams};
+use chromiumoxide::page::ScreenshotParams;
+use chromiumoxide::{Browser, BrowserConfig, Page};
+use futures::StreamExt;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Screenshot settings
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    /// Viewport width in CSS pixels
+    pub width: u32,
+    /// Viewport height in CSS pixels
+    pub height: u32,
+    /// Capture the whole document instead of the viewport
+    pub full_page: bool,
+    /// Run the page's scripts. Off by default; network access stays blocked either way.
+    pub allow_scripts: bool,
+    /// Time given to layout and inline resources after the content is set
+    pub settle: Duration,
+    /// Limit for one render, browser startup included
+    pub timeout: Duration,
+    /// Larger documents are refused
+    pub max_html_bytes: usize,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            width: 1280,
+            height: 800,
+            full_page: false,
+            allow_scripts: false,
+            settle: Duration::from_millis(300),
+            timeout: Duration::from_secs(20),
+            max_html_bytes: 5 * 1024 * 1024,
+        }
+    }
+}
+
+struct RenderBrowser {
+    browser: Browser,
+    handler: JoinHandle<()>,
+    profile_dir: PathBuf,
+}
+
+impl RenderBrowser {
+    async fn shutdown(mut self) {
+        let _ = self.browser.close().await;
+        let _ = self.browser.wait().await;
+        self.handler.abort();
+        if let Err(e) = std::fs::remove_dir_all(&self.profile_dir) {
+            debug!("Failed to remove render profile {:?}: {}", self.profile_dir, e);
+        }
+    }
+}
+
+/// Renders HTML to PNG. The browser is started on first use and reused;
+/// renders run one at a time.
+pub struct ResponseRenderer {
+    browser: Mutex<Option<RenderBrowser>>,
+}
+
+impl ResponseRenderer {
+    pub fn new() -> Self {
+        Self {
+            browser: Mutex::new(None),
+        }
+    }
+
+    /// Render `html` and return the PNG screenshot
+    pub async fn render(&self, html: &str, options: &RenderOptions) -> FlowResult<Vec<u8>> {
+        if html.len() > options.max_html_bytes {
+            return Err(FlowEngineError::Render(format!(
+                "Document of {} bytes exceeds the {} byte render limit",
+                html.len(),
+                options.max_html_bytes
+            )));
+        }
+
+        let mut guard = self.browser.lock().await;
+        let result = tokio::time::timeout(options.timeout, async {
+            if guard.is_none() {
+                *guard = Some(launch_render_browser().await?);
+            }
+            let browser = &guard.as_ref().expect("render browser was just launched").browser;
+            render_page(browser, html, options).await
+        })
+        .await
+        .unwrap_or_else(|_| {
+            Err(FlowEngineError::Timeout {
+                condition: "response render".to_string(),
+                details: format!("no screenshot after {:?}", options.timeout),
+            })
+        });
+
+        // A failed render may leave the browser wedged; start fresh next time
+        if result.is_err() {
+            if let Some(browser) = guard.take() {
+                warn!("Restarting render browser after a failed render");
+                browser.shutdown().await;
+            }
+        }
+        result
+    }
+
+    /// Stop the render browser, if running
+    pub async fn close(&self) {
+        if let Some(browser) = self.browser.lock().await.take() {
+            browser.shutdown().await;
+        }
+    }
+}
+
+impl Default for ResponseRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn launch_render_browser() -> FlowResult<RenderBrowser> {
+    let profile_dir = std::env::temp_dir().join(format!("proxxy_render_{}", Uuid::new_v4()));
+    let config = BrowserConfig::builder()
+        .user_data_dir(&profile_dir)
+        // Nothing resolves and nothing connects, whatever the page asks for
+        .arg("--host-resolver-rules=MAP * ~NOTFOUND")
+        .arg("--proxy-server=http://127.0.0.1:9")
+        .arg("--disable-background-networking")
+        .arg("--disable-component-update")
+        .arg("--disable-domain-reliability")
+        .arg("--disable-sync")
+        .arg("--disable-extensions")
+        .arg("--disable-breakpad")
+        .arg("--no-first-run")
+        .arg("--no-default-browser-check")
+        .arg("--mute-audio")
+        .arg("--hide-scrollbars")
+        .viewport(None)
+        .build()
+        .map_err(FlowEngineError::BrowserLaunch)?;
+
+    let (browser, mut handler) = Browser::launch(config)
+        .await
+        .map_err(|e| FlowEngineError::BrowserLaunch(format!("Failed to launch render browser: {}", e)))?;
+    let handler = tokio::spawn(async move {
+        while let Some(event) = handler.next().await {
+            if let Err(e) = event {
+                debug!("Render browser event error: {:?}", e);
+            }
+        }
+    });
+
+    info!("Render browser started");
+    Ok(RenderBrowser {
+        browser,
+        handler,
+        profile_dir,
+    })
+}
+
+async fn render_page(browser: &Browser, html: &str, options: &RenderOptions) -> FlowResult<Vec<u8>> {
+    let cdp_err = |e: chromiumoxide::error::CdpError| FlowEngineError::Render(e.to_string());
+
+    let page = browser.new_page("about:blank").await.map_err(cdp_err)?;
+    let result = async {
+        block_requests(&page).await?;
+        page.execute(SetScriptExecutionDisabledParams::new(!options.allow_scripts))
+            .await
+            .map_err(cdp_err)?;
+        page.execute(SetDeviceMetricsOverrideParams::new(
+            options.width as i64,
+            options.height as i64,
+            1.0,
+            false,
+        ))
+        .await
+        .map_err(cdp_err)?;
+
+        let frame_id = page
+            .mainframe()
+            .await
+            .map_err(cdp_err)?
+            .ok_or_else(|| FlowEngineError::Render("Page has no main frame".to_string()))?;
+        page.execute(SetDocumentContentParams::new(frame_id, html))
+            .await
+            .map_err(cdp_err)?;
+        tokio::time::sleep(options.settle).await;
+
+        page.screenshot(
+            ScreenshotParams::builder()
+                .format(CaptureScreenshotFormat::Png)
+                .full_page(options.full_page)
+                .build(),
+        )
+        .await
+        .map_err(cdp_err)
+    }
+    .await;
+
+    if let Err(e) = page.close().await {
+        debug!("Failed to close render page: {}", e);
+    }
+    result
+}
+
+/// Fail every request the page makes
+async fn block_requests(page: &Page) -> FlowResult<()> {
+    let cdp_err = |e: chromiumoxide::error::CdpError| FlowEngineError::Render(e.to_string());
+
+    let mut paused = page
+        .event_listener::<EventRequestPaused>()
+        .await
+        .map_err(cdp_err)?;
+    page.execute(
+        FetchEnableParams::builder()
+            .pattern(RequestPattern::builder().url_pattern("*").build())
+            .build(),
+    )
+    .await
+    .map_err(cdp_err)?;
+
+    let page = page.clone();
+    tokio::spawn(async move {
+        while let Some(event) = paused.next().await {
+            debug!("Blocked render subresource: {}", event.request.url);
+            if let Err(e) = page
+                .execute(FailRequestParams::new(event.request_id.clone(), ErrorReason::BlockedByClient))
+                .await
+            {
+                debug!("Failed to block render request: {}", e);
+            }
+        }
+    });
+    Ok(())
+}
@@ -5,9 +5,10 @@
 use crate::error::{FlowEngineError, FlowResult};
 use crate::flow::model::{FlowProfile, FlowStep, WaitCondition, ExtractType};
 use crate::flow::page::PageController;
-use crate::flow::browser::{BrowserManager, BrowserOptions};
+use crate::flow::browser::{BrowserManager, BrowserOptions, ProxyConfig};
 use secrecy::ExposeSecret;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 use tracing::{debug, info, warn};
@@ -33,6 +34,22 @@ pub struct ReplayResult {
     pub duration_ms: u64,
 }
 
+impl ReplayResult {
+    /// Result for a replay that could not run to completion
+    pub fn failed(error: impl Into<String>, total_steps: usize, duration_ms: u64) -> Self {
+        Self {
+            success: false,
+            error: Some(error.into()),
+            steps_completed: 0,
+            total_steps,
+            session_cookies: None,
+            extracted_data: HashMap::new(),
+            final_url: None,
+            duration_ms,
+        }
+    }
+}
+
 /// Replay options
 #[derive(Debug, Clone)]
 pub struct ReplayOptions {
@@ -46,6 +63,14 @@ pub struct ReplayOptions {
     pub screenshot_on_failure: bool,
     /// Variable substitutions
     pub variables: HashMap<String, String>,
+    /// Persistent browser profile, overriding the flow's own setting
+    pub browser_profile: Option<String>,
+    /// Agent proxy to route browser traffic through
+    pub proxy: Option<ProxyConfig>,
+    /// Proxxy CA certificate to trust when routed through an agent
+    pub ca_cert_path: Option<String>,
+    /// Where failure screenshots are written; none are saved when unset
+    pub screenshot_dir: Option<PathBuf>,
 }
 
 impl Default for ReplayOptions {
@@ -56,6 +81,10 @@ impl Default for ReplayOptions {
             step_delay_ms: 500,
             screenshot_on_failure: true,
             variables: HashMap::new(),
+            browser_profile: None,
+            proxy: None,
+            ca_cert_path: None,
+            screenshot_dir: None,
         }
     }
 }
@@ -63,11 +92,28 @@ impl Default for ReplayOptions {
 /// Progress callback type
 pub type ProgressCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
 
+/// Per-step status reported while a flow replays. Step indices are zero-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayEvent {
+    /// A step began executing
+    StepStarted { step_index: usize, total_steps: usize, kind: &'static str },
+    /// A step's primary selector missed and an alternative matched instead
+    SelectorHealed { step_index: usize, original: String, healed: String },
+    /// A step finished successfully
+    StepCompleted { step_index: usize, total_steps: usize },
+    /// A step failed; `screenshot` names the saved failure screenshot
+    StepFailed { step_index: usize, error: String, screenshot: Option<String> },
+}
+
+/// Replay event callback type
+pub type ReplayEventCallback = Arc<dyn Fn(ReplayEvent) + Send + Sync>;
+
 /// Flow Replayer - executes recorded flows
 pub struct FlowReplayer {
     browser_manager: BrowserManager,
     options: ReplayOptions,
     progress_callback: Option<ProgressCallback>,
+    event_callback: Option<ReplayEventCallback>,
 }
 
 impl FlowReplayer {
@@ -77,6 +123,7 @@ impl FlowReplayer {
             browser_manager: BrowserManager::new(),
             options: ReplayOptions::default(),
             progress_callback: None,
+            event_callback: None,
         }
     }
 
@@ -86,9 +133,16 @@ impl FlowReplayer {
             browser_manager: BrowserManager::new(),
             options,
             progress_callback: None,
+            event_callback: None,
         }
     }
 
+    /// Keep named browser profiles under `dir`
+    pub fn with_profiles_dir(mut self, dir: impl Into<std::path::PathBuf>) -> Self {
+        self.browser_manager = BrowserManager::with_profiles_dir(dir);
+        self
+    }
+
     /// Set progress callback
     pub fn set_progress_callback(&mut self, callback: ProgressCallback) {
         self.progress_callback = Some(callback);
@@ -100,6 +154,28 @@ impl FlowReplayer {
         }
     }
 
+    /// Set per-step event callback
+    pub fn set_event_callback(&mut self, callback: ReplayEventCallback) {
+        self.event_callback = Some(callback);
+    }
+
+    fn report_event(&self, event: ReplayEvent) {
+        if let Some(ref cb) = self.event_callback {
+            cb(event);
+        }
+    }
+
+    /// Report a selector that was healed while executing `step`
+    fn report_healing(&self, controller: &PageController, step_index: usize, step: &FlowStep) {
+        if let Some(healed) = controller.take_healed_selector() {
+            let original = self
+                .get_step_selector(step)
+                .map(|s| s.value.clone())
+                .unwrap_or_default();
+            self.report_event(ReplayEvent::SelectorHealed { step_index, original, healed });
+        }
+    }
+
     /// Execute a flow profile
     pub async fn execute(&self, profile: &FlowProfile) -> FlowResult<ReplayResult> {
         let start_time = std::time::Instant::now();
@@ -108,9 +184,16 @@ impl FlowReplayer {
         info!("Starting replay: {} ({} steps)", profile.name, total_steps);
 
         // Launch browser
-        let browser_opts = BrowserOptions::default()
+        let mut browser_opts = BrowserOptions::default()
             .headless(!self.options.headed);
-        
+        if let Some(name) = self.options.browser_profile.as_ref().or(profile.meta.browser_profile.as_ref()) {
+            browser_opts = browser_opts.with_profile(name.clone());
+        }
+        if let Some(proxy) = &self.options.proxy {
+            browser_opts = browser_opts.with_proxy(proxy.clone());
+        }
+        browser_opts.ca_cert_path = self.options.ca_cert_path.clone();
+
         let browser_arc = self.browser_manager.launch(browser_opts).await?;
 
         // Get browser and create page
@@ -118,10 +201,7 @@ impl FlowReplayer {
         let managed = browser_guard.as_ref()
             .ok_or_else(|| FlowEngineError::BrowserLaunch("No browser available".to_string()))?;
 
-        let page = managed.browser()
-            .new_page("about:blank")
-            .await
-            .map_err(|e| FlowEngineError::BrowserLaunch(format!("Failed to create page: {}", e)))?;
+        let page = managed.new_page("about:blank").await?;
 
         let controller = PageController::new(page);
         
@@ -161,14 +241,22 @@ impl FlowReplayer {
         // Execute each step
         for (i, step) in profile.steps.iter().enumerate() {
             info!("▶️  Executing step {}/{}: {:?}", i + 1, total_steps, step);
+            self.report_event(ReplayEvent::StepStarted { step_index: i, total_steps, kind: step.kind() });
+            controller.take_healed_selector();
 
-            if let Err(e) = self.execute_step(&controller, step, &mut extracted_data).await {
+            let outcome = self.execute_step(&controller, step, &mut extracted_data).await;
+            self.report_healing(&controller, i, step);
+
+            if let Err(e) = outcome {
                 // Log the current URL when step fails to understand where we are
                 if let Ok(url) = controller.get_url().await {
                     warn!("❌ Step {} failed on URL: {}", i + 1, url);
                 }
                 warn!("❌ Step {} failed: {}", i + 1, e);
 
+                let screenshot = self.save_failure_screenshot(&controller, profile, i).await;
+                self.report_event(ReplayEvent::StepFailed { step_index: i, error: e.to_string(), screenshot });
+
                 let duration_ms = start_time.elapsed().as_millis() as u64;
                 let final_url = controller.get_url().await.ok();
 
@@ -189,6 +277,7 @@ impl FlowReplayer {
             }
 
             steps_completed += 1;
+            self.report_event(ReplayEvent::StepCompleted { step_index: i, total_steps });
             self.report_progress(steps_completed, total_steps);
 
             // Delay between steps if configured
@@ -248,7 +337,12 @@ impl FlowReplayer {
                 }
             }
 
-            FlowStep::Type { selector, value, clear_first, .. } => {
+            FlowStep::Type { selector, value, clear_first, is_masked } => {
+                if *is_masked {
+                    if let Err(e) = controller.mask_element(selector).await {
+                        warn!("Could not mask sensitive field {}: {}", selector.value, e);
+                    }
+                }
                 let text = self.substitute_variables(value.expose_secret());
                 controller.type_text(selector, &text, *clear_first).await?;
             }
@@ -410,6 +504,36 @@ impl FlowReplayer {
         Ok(())
     }
 
+    /// Save a screenshot of the failed step, returning its file name
+    async fn save_failure_screenshot(
+        &self,
+        controller: &PageController,
+        profile: &FlowProfile,
+        step_index: usize,
+    ) -> Option<String> {
+        if !self.options.screenshot_on_failure {
+            return None;
+        }
+        let dir = self.options.screenshot_dir.as_deref()?;
+
+        let data = match controller.screenshot().await {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("Failed to capture failure screenshot: {}", e);
+                return None;
+            }
+        };
+
+        let file_name = failure_screenshot_name(profile, step_index);
+        match write_screenshot(dir, &file_name, &data).await {
+            Ok(()) => Some(file_name),
+            Err(e) => {
+                warn!("Failed to save failure screenshot to {}: {}", dir.display(), e);
+                None
+            }
+        }
+    }
+
     /// Extract cookies from the page
     async fn extract_cookies(&self, controller: &PageController) -> FlowResult<String> {
         let script = r#"
@@ -446,6 +570,21 @@ impl FlowReplayer {
     }
 }
 
+/// File name for a failure screenshot: unique per flow, step and moment
+fn failure_screenshot_name(profile: &FlowProfile, step_index: usize) -> String {
+    format!(
+        "{}-step{}-{}.png",
+        profile.id,
+        step_index + 1,
+        chrono::Utc::now().timestamp_millis()
+    )
+}
+
+async fn write_screenshot(dir: &Path, file_name: &str, data: &[u8]) -> std::io::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+    tokio::fs::write(dir.join(file_name), data).await
+}
+
 impl Default for FlowReplayer {
     fn default() -> Self {
         Self::new()
@@ -474,5 +613,29 @@ mod tests {
         assert!(!opts.headed);
         assert!(!opts.step_by_step);
         assert!(opts.screenshot_on_failure);
+        assert!(opts.screenshot_dir.is_none());
+    }
+
+    #[test]
+    fn test_replay_events() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut replayer = FlowReplayer::new();
+        let sink = Arc::clone(&events);
+        replayer.set_event_callback(Arc::new(move |event| sink.lock().unwrap().push(event)));
+
+        replayer.report_event(ReplayEvent::StepStarted { step_index: 0, total_steps: 2, kind: "Click" });
+        replayer.report_event(ReplayEvent::StepCompleted { step_index: 0, total_steps: 2 });
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(events[1], ReplayEvent::StepCompleted { step_index: 0, .. }));
+    }
+
+    #[test]
+    fn test_failure_screenshot_name() {
+        let profile = FlowProfile::new("login", "https://example.com");
+        let name = failure_screenshot_name(&profile, 2);
+        assert!(name.starts_with(&format!("{}-step3-", profile.id)));
+        assert!(name.ends_with(".png"));
     }
 }
@@ -32,9 +32,13 @@ pub mod flow;
 // Re-exports
 pub use error::FlowEngineError;
 pub use flow::model::{FlowProfile, FlowStep, SmartSelector, FlowMeta, FlowType};
-pub use secrecy::SecretString;
-pub use flow::browser::{BrowserManager, BrowserOptions, ProxyConfig, ManagedBrowser};
+pub use secrecy::{ExposeSecret, SecretString};
+pub use flow::browser::{BrowserManager, BrowserOptions, ProxyConfig, ManagedBrowser, SecurityOptions, BrowserProfileInfo};
 pub use flow::page::PageController;
 pub use flow::analyzer::{SelectorAnalyzer, AnalyzerConfig, ElementInfo, SelectorBlacklist};
 pub use flow::recorder::{FlowRecorder, RecordingConfig, RecordingState, RecordedEvent};
-pub use flow::replayer::{FlowReplayer, ReplayOptions, ReplayResult};
+pub use flow::replayer::{FlowReplayer, ReplayOptions, ReplayResult, ReplayEvent, ReplayEventCallback};
+pub use flow::masking::MaskingPolicy;
+pub use flow::parallel::{ParallelReplayer, ParallelReplayResult, AgentReplayResult, ReplayTarget};
+pub use flow::crawler::{DomCrawler, CrawlConfig, CrawlResult, DiscoveredRoute, RouteSource};
+pub use flow::render::{RenderOptions, ResponseRenderer};
@@ -0,0 +1,14 @@
+-- Routes discovered by the DOM crawler (rendered SPA routes, links, form targets)
+CREATE TABLE discovered_routes (
+    url TEXT PRIMARY KEY,
+    crawl_id TEXT NOT NULL,
+    host TEXT NOT NULL,
+    depth INTEGER NOT NULL,
+    source TEXT NOT NULL, -- 'Start', 'Link', 'HistoryApi', 'Click', 'Form'
+    title TEXT,
+    visited BOOLEAN NOT NULL DEFAULT 0,
+    discovered_at INTEGER NOT NULL
+);
+
+CREATE INDEX idx_discovered_routes_host ON discovered_routes(host);
+CREATE INDEX idx_discovered_routes_crawl ON discovered_routes(crawl_id);
@@ -0,0 +1,2 @@
+-- Flow replayed to mint fresh sessions during an intruder attack (JSON FlowSessionConfig)
+ALTER TABLE intruder_attacks ADD COLUMN flow_session TEXT;
@@ -0,0 +1,18 @@
+-- Content-addressed artifact blobs (files live under <project>.proxxy/blobs)
+CREATE TABLE blobs (
+    hash TEXT PRIMARY KEY, -- SHA-256, lowercase hex
+    size INTEGER NOT NULL,
+    content_type TEXT,
+    created_at INTEGER NOT NULL
+);
+
+-- Rows that keep a blob alive; blobs without references are garbage collected
+CREATE TABLE blob_refs (
+    blob_hash TEXT NOT NULL REFERENCES blobs(hash) ON DELETE CASCADE,
+    owner_kind TEXT NOT NULL, -- e.g. 'flow_execution', 'finding', 'export'
+    owner_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    PRIMARY KEY (blob_hash, owner_kind, owner_id)
+);
+
+CREATE INDEX idx_blob_refs_owner ON blob_refs(owner_kind, owner_id);
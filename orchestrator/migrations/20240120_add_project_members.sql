@@ -0,0 +1,6 @@
+-- Access list of a shared project; an empty table means the project is unshared
+CREATE TABLE project_members (
+    principal TEXT PRIMARY KEY,
+    role TEXT NOT NULL, -- 'Viewer', 'Editor', 'Owner'
+    added_at INTEGER NOT NULL
+);
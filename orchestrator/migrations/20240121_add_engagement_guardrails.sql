@@ -0,0 +1,23 @@
+-- Time-limited permissions to target hosts outside the allowlist
+CREATE TABLE allowlist_overrides (
+    id TEXT PRIMARY KEY,
+    host_pattern TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    granted_by TEXT,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
+
+-- Audit trail of blocked requests, overrides and allowlist changes
+CREATE TABLE engagement_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    event TEXT NOT NULL,
+    tool TEXT,
+    target TEXT,
+    principal TEXT,
+    details TEXT
+);
+
+CREATE INDEX idx_engagement_audit_timestamp ON engagement_audit(timestamp);
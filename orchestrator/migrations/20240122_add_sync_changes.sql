@@ -0,0 +1,42 @@
+-- Project Sync Migration
+-- Change log read by incremental sync between orchestrators
+
+CREATE TABLE IF NOT EXISTS sync_changes (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity TEXT NOT NULL, -- 'transaction', 'finding'
+    entity_id TEXT NOT NULL,
+    origin TEXT NOT NULL DEFAULT '' -- '' for local changes, else the sync node they came from
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_changes_entity ON sync_changes(entity, entity_id);
+
+CREATE TRIGGER IF NOT EXISTS sync_transaction_insert AFTER INSERT ON http_transactions
+BEGIN
+    INSERT INTO sync_changes (entity, entity_id) VALUES ('transaction', NEW.request_id);
+END;
+
+-- Bodies are not watched: only archive export/import rewrites them
+CREATE TRIGGER IF NOT EXISTS sync_transaction_update AFTER UPDATE ON http_transactions
+WHEN OLD.res_status IS NOT NEW.res_status OR OLD.res_timestamp IS NOT NEW.res_timestamp
+BEGIN
+    INSERT INTO sync_changes (entity, entity_id) VALUES ('transaction', NEW.request_id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS sync_finding_insert AFTER INSERT ON intruder_results
+WHEN NEW.is_highlighted
+BEGIN
+    INSERT INTO sync_changes (entity, entity_id) VALUES ('finding', NEW.id);
+END;
+
+CREATE TRIGGER IF NOT EXISTS sync_finding_update AFTER UPDATE OF is_highlighted ON intruder_results
+WHEN OLD.is_highlighted IS NOT NEW.is_highlighted
+BEGIN
+    INSERT INTO sync_changes (entity, entity_id) VALUES ('finding', NEW.id);
+END;
+
+-- Existing data is part of the first sync
+INSERT INTO sync_changes (entity, entity_id)
+SELECT 'transaction', request_id FROM http_transactions ORDER BY req_timestamp;
+
+INSERT INTO sync_changes (entity, entity_id)
+SELECT 'finding', id FROM intruder_results WHERE is_highlighted ORDER BY executed_at;
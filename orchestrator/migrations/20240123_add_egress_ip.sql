@@ -0,0 +1,17 @@
+-- Egress IP Migration
+-- Source address targets saw for each agent, as reported by its egress self-check
+
+ALTER TABLE agents ADD COLUMN egress_ip TEXT;
+ALTER TABLE http_transactions ADD COLUMN egress_ip TEXT;
+ALTER TABLE intruder_results ADD COLUMN egress_ip TEXT;
+ALTER TABLE repeater_history ADD COLUMN egress_ip TEXT;
+
+-- Every egress IP change of an agent, for rotating NAT/VPN setups
+CREATE TABLE IF NOT EXISTS agent_egress_log (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    agent_id TEXT NOT NULL,
+    ip TEXT NOT NULL,
+    observed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_egress_log_agent ON agent_egress_log(agent_id, observed_at DESC);
@@ -0,0 +1,4 @@
+-- Response Protocol Migration
+-- Protocol the agent spoke with the origin (HTTP/1.1, HTTP/2)
+
+ALTER TABLE http_transactions ADD COLUMN res_protocol TEXT;
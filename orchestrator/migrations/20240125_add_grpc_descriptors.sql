@@ -0,0 +1,9 @@
+-- gRPC Descriptors Migration
+-- Protobuf descriptor sets (protoc --descriptor_set_out) used to decode captured gRPC calls
+
+CREATE TABLE IF NOT EXISTS grpc_descriptor_sets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    descriptor_set BLOB NOT NULL,
+    created_at INTEGER NOT NULL
+);
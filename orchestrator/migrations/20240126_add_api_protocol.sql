@@ -0,0 +1,5 @@
+-- API Protocol Migration
+-- Kind of API call (rest, graphql, grpc, soap, form, multipart, binary), tagged at ingest
+
+ALTER TABLE http_transactions ADD COLUMN api_protocol TEXT;
+CREATE INDEX IF NOT EXISTS idx_http_transactions_api_protocol ON http_transactions(api_protocol);
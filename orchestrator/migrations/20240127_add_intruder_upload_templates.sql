@@ -0,0 +1,2 @@
+-- Multipart body with payload positions inside its parts (JSON UploadTemplate)
+ALTER TABLE intruder_attacks ADD COLUMN upload_template TEXT;
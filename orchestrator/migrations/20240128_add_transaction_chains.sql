@@ -0,0 +1,11 @@
+-- Transaction Chains Migration
+-- Links a request to the transaction that led to it (a followed redirect or a retry)
+
+ALTER TABLE http_transactions ADD COLUMN parent_id TEXT;
+-- 'redirect' or 'retry'
+ALTER TABLE http_transactions ADD COLUMN parent_link TEXT;
+-- Absolute Location of a 3xx response, matched against follow-up requests
+ALTER TABLE http_transactions ADD COLUMN redirect_url TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_http_transactions_parent ON http_transactions(parent_id);
+CREATE INDEX IF NOT EXISTS idx_http_transactions_redirect ON http_transactions(agent_id, redirect_url);
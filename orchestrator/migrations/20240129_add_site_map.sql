@@ -0,0 +1,94 @@
+-- Site Map Migration
+-- Per host/path/method counters kept up to date by triggers, so the site map never scans http_transactions
+
+-- Host and path of absolute URLs (NULL for CONNECT authorities); the query string is not part of the path
+ALTER TABLE http_transactions ADD COLUMN site_rest TEXT GENERATED ALWAYS AS (
+    CASE WHEN instr(req_url, '://') > 0 THEN substr(
+        CASE WHEN instr(req_url, '?') > 0 THEN substr(req_url, 1, instr(req_url, '?') - 1) ELSE req_url END,
+        instr(req_url, '://') + 3
+    ) END
+) VIRTUAL;
+ALTER TABLE http_transactions ADD COLUMN site_host TEXT GENERATED ALWAYS AS (
+    CASE WHEN instr(site_rest, '/') > 0 THEN substr(site_rest, 1, instr(site_rest, '/') - 1) ELSE site_rest END
+) VIRTUAL;
+ALTER TABLE http_transactions ADD COLUMN site_path TEXT GENERATED ALWAYS AS (
+    CASE WHEN instr(site_rest, '/') > 0 THEN substr(site_rest, instr(site_rest, '/')) ELSE '/' END
+) VIRTUAL;
+
+CREATE TABLE IF NOT EXISTS site_map (
+    host TEXT NOT NULL,
+    path TEXT NOT NULL,
+    method TEXT NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    status_2xx INTEGER NOT NULL DEFAULT 0,
+    status_3xx INTEGER NOT NULL DEFAULT 0,
+    status_4xx INTEGER NOT NULL DEFAULT 0,
+    status_5xx INTEGER NOT NULL DEFAULT 0,
+    -- Most recent transaction and its status
+    last_request_id TEXT,
+    last_status INTEGER,
+    last_seen INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (host, path, method)
+);
+
+CREATE TRIGGER IF NOT EXISTS site_map_insert AFTER INSERT ON http_transactions
+WHEN NEW.site_host IS NOT NULL
+BEGIN
+    INSERT INTO site_map (host, path, method, hits, status_2xx, status_3xx, status_4xx, status_5xx, last_request_id, last_status, last_seen)
+    VALUES (
+        NEW.site_host, NEW.site_path, NEW.req_method, 1,
+        IFNULL(NEW.res_status BETWEEN 200 AND 299, 0),
+        IFNULL(NEW.res_status BETWEEN 300 AND 399, 0),
+        IFNULL(NEW.res_status BETWEEN 400 AND 499, 0),
+        IFNULL(NEW.res_status BETWEEN 500 AND 599, 0),
+        NEW.request_id, NEW.res_status, NEW.req_timestamp
+    )
+    ON CONFLICT (host, path, method) DO UPDATE SET
+        hits = hits + 1,
+        status_2xx = status_2xx + excluded.status_2xx,
+        status_3xx = status_3xx + excluded.status_3xx,
+        status_4xx = status_4xx + excluded.status_4xx,
+        status_5xx = status_5xx + excluded.status_5xx,
+        last_request_id = CASE WHEN excluded.last_seen >= last_seen THEN excluded.last_request_id ELSE last_request_id END,
+        last_status = CASE WHEN excluded.last_seen >= last_seen THEN excluded.last_status ELSE last_status END,
+        last_seen = MAX(last_seen, excluded.last_seen);
+END;
+
+CREATE TRIGGER IF NOT EXISTS site_map_response AFTER UPDATE OF res_status ON http_transactions
+WHEN NEW.site_host IS NOT NULL AND OLD.res_status IS NOT NEW.res_status
+BEGIN
+    UPDATE site_map SET
+        status_2xx = status_2xx - IFNULL(OLD.res_status BETWEEN 200 AND 299, 0) + IFNULL(NEW.res_status BETWEEN 200 AND 299, 0),
+        status_3xx = status_3xx - IFNULL(OLD.res_status BETWEEN 300 AND 399, 0) + IFNULL(NEW.res_status BETWEEN 300 AND 399, 0),
+        status_4xx = status_4xx - IFNULL(OLD.res_status BETWEEN 400 AND 499, 0) + IFNULL(NEW.res_status BETWEEN 400 AND 499, 0),
+        status_5xx = status_5xx - IFNULL(OLD.res_status BETWEEN 500 AND 599, 0) + IFNULL(NEW.res_status BETWEEN 500 AND 599, 0),
+        last_status = CASE WHEN last_request_id = NEW.request_id THEN NEW.res_status ELSE last_status END
+    WHERE host = NEW.site_host AND path = NEW.site_path AND method = NEW.req_method;
+END;
+
+CREATE TRIGGER IF NOT EXISTS site_map_delete AFTER DELETE ON http_transactions
+WHEN OLD.site_host IS NOT NULL
+BEGIN
+    UPDATE site_map SET
+        hits = hits - 1,
+        status_2xx = status_2xx - IFNULL(OLD.res_status BETWEEN 200 AND 299, 0),
+        status_3xx = status_3xx - IFNULL(OLD.res_status BETWEEN 300 AND 399, 0),
+        status_4xx = status_4xx - IFNULL(OLD.res_status BETWEEN 400 AND 499, 0),
+        status_5xx = status_5xx - IFNULL(OLD.res_status BETWEEN 500 AND 599, 0)
+    WHERE host = OLD.site_host AND path = OLD.site_path AND method = OLD.req_method;
+    DELETE FROM site_map
+    WHERE host = OLD.site_host AND path = OLD.site_path AND method = OLD.req_method AND hits <= 0;
+END;
+
+-- Existing traffic; the bare columns come from the row with MAX(req_timestamp)
+INSERT INTO site_map (host, path, method, hits, status_2xx, status_3xx, status_4xx, status_5xx, last_request_id, last_status, last_seen)
+SELECT
+    site_host, site_path, req_method, COUNT(*),
+    SUM(IFNULL(res_status BETWEEN 200 AND 299, 0)),
+    SUM(IFNULL(res_status BETWEEN 300 AND 399, 0)),
+    SUM(IFNULL(res_status BETWEEN 400 AND 499, 0)),
+    SUM(IFNULL(res_status BETWEEN 500 AND 599, 0)),
+    request_id, res_status, MAX(req_timestamp)
+FROM http_transactions
+WHERE site_host IS NOT NULL
+GROUP BY site_host, site_path, req_method;
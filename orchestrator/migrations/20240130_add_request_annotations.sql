@@ -0,0 +1,8 @@
+-- Request Annotations Migration
+-- Triage comment, highlight color and tags on captured transactions
+
+ALTER TABLE http_transactions ADD COLUMN comment TEXT;
+-- 'red', 'orange', 'yellow', 'green', 'cyan', 'blue', 'purple', 'pink', 'gray'
+ALTER TABLE http_transactions ADD COLUMN highlight_color TEXT;
+-- JSON array of strings
+ALTER TABLE http_transactions ADD COLUMN tags TEXT;
@@ -0,0 +1,17 @@
+-- Passive Findings Migration
+-- Matches of custom passive checks against stored responses
+
+CREATE TABLE IF NOT EXISTS passive_findings (
+    id TEXT PRIMARY KEY,
+    request_id TEXT NOT NULL,
+    check_id TEXT NOT NULL,
+    check_name TEXT NOT NULL,
+    severity TEXT NOT NULL, -- 'info', 'low', 'medium', 'high', 'critical'
+    description TEXT NOT NULL DEFAULT '',
+    evidence TEXT NOT NULL DEFAULT '',
+    created_at INTEGER NOT NULL,
+    FOREIGN KEY(request_id) REFERENCES http_transactions(request_id) ON DELETE CASCADE,
+    UNIQUE(request_id, check_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_passive_findings_created ON passive_findings(created_at DESC);
@@ -0,0 +1,2 @@
+-- Settings of GraphQL attacks (JSON GraphQlAttackConfig): introspection result, alias batch size, injection strings
+ALTER TABLE intruder_attacks ADD COLUMN graphql_config TEXT;
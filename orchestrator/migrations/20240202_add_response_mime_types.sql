@@ -0,0 +1,6 @@
+-- Response MIME Types Migration
+-- Declared Content-Type and the type sniffed from the body, set at capture
+
+ALTER TABLE http_transactions ADD COLUMN res_declared_type TEXT;
+
+ALTER TABLE http_transactions ADD COLUMN res_detected_type TEXT;
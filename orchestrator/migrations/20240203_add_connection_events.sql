@@ -0,0 +1,17 @@
+-- Connection Events Migration
+-- CONNECT tunnels, TLS and upstream failures, blocks and pass-throughs that
+-- produce no HTTP transaction
+
+CREATE TABLE IF NOT EXISTS connection_events (
+    id TEXT PRIMARY KEY,
+    agent_id TEXT NOT NULL,
+    kind TEXT NOT NULL, -- 'connect', 'pass_through', 'tls_failure', 'upstream_error', 'blocked', 'no_traffic'
+    target TEXT NOT NULL,
+    client_addr TEXT NOT NULL DEFAULT '',
+    reason TEXT NOT NULL DEFAULT '',
+    request_id TEXT, -- transaction the failure belongs to, if any
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_connection_events_created ON connection_events(created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_connection_events_kind ON connection_events(kind);
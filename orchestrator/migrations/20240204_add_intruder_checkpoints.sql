@@ -0,0 +1,2 @@
+-- Progress of paused attacks (JSON AttackCheckpoint): distribution and per-assignment cursors
+ALTER TABLE intruder_attacks ADD COLUMN checkpoint TEXT;
@@ -0,0 +1,2 @@
+-- Execution settings of an attack (JSON ExecutionConfig): concurrency, timeouts, rate limits and backoff
+ALTER TABLE intruder_attacks ADD COLUMN execution_config TEXT;
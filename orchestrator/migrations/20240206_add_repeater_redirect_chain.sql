@@ -0,0 +1,2 @@
+-- Redirects a repeater execution followed before its final response (JSON array of hops)
+ALTER TABLE repeater_history ADD COLUMN redirect_chain TEXT;
@@ -0,0 +1,13 @@
+-- Saved Views Migration
+-- Named filter definitions and sort orders of the request list, per project
+
+CREATE TABLE IF NOT EXISTS saved_views (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    filter TEXT NOT NULL, -- filter expression as typed in the request list
+    sort_field TEXT NOT NULL DEFAULT 'timestamp', -- 'timestamp', 'method', 'url', 'status', 'length'
+    sort_direction TEXT NOT NULL DEFAULT 'desc', -- 'asc', 'desc'
+    is_default INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
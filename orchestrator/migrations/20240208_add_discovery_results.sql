@@ -0,0 +1,19 @@
+-- Content Discovery Migration
+-- Paths found by discovery runs; soft-404s and real 404s are not stored
+
+CREATE TABLE IF NOT EXISTS discovery_results (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    path TEXT NOT NULL, -- relative to the run's base URL
+    status_code INTEGER NOT NULL,
+    size INTEGER NOT NULL, -- response body length in bytes
+    title TEXT,
+    is_directory INTEGER NOT NULL DEFAULT 0,
+    depth INTEGER NOT NULL DEFAULT 0,
+    agent_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE(run_id, url)
+);
+
+CREATE INDEX IF NOT EXISTS idx_discovery_results_run ON discovery_results(run_id, path);
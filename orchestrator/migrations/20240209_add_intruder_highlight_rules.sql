@@ -0,0 +1,4 @@
+-- Highlight rules of an attack (JSON array of ResultHighlightRule), checked against each result as it arrives
+ALTER TABLE intruder_attacks ADD COLUMN highlight_rules TEXT;
+-- Rules a highlighted result matched and what they saw
+ALTER TABLE intruder_results ADD COLUMN highlight_reason TEXT;
@@ -0,0 +1,2 @@
+-- Typical response of an attack's unmodified request (JSON AttackBaseline), measured before it starts
+ALTER TABLE intruder_attacks ADD COLUMN baseline TEXT;
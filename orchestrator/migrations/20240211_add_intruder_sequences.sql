@@ -0,0 +1,2 @@
+-- Request steps sent before each request of a sequence attack (JSON AttackSequence)
+ALTER TABLE intruder_attacks ADD COLUMN sequence TEXT;
@@ -0,0 +1,2 @@
+-- Request phases timed by the agent (JSON RequestTiming); duration_ms is its total
+ALTER TABLE http_transactions ADD COLUMN timing TEXT;
@@ -0,0 +1,24 @@
+-- Tamper Checks Migration
+-- Parameter pollution, method override and verb tampering probes whose
+-- response differed from the baseline replays of the captured request
+
+CREATE TABLE IF NOT EXISTS tamper_findings (
+    id TEXT PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    request_id TEXT NOT NULL, -- captured request the probe was made from
+    method TEXT NOT NULL,
+    url TEXT NOT NULL,
+    kind TEXT NOT NULL, -- parameter_pollution, method_override or verb_tampering
+    probe TEXT NOT NULL, -- what the probe changed, e.g. 'GET → PUT'
+    effect TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    baseline_status INTEGER NOT NULL,
+    probe_status INTEGER NOT NULL,
+    baseline_length INTEGER NOT NULL,
+    probe_length INTEGER NOT NULL,
+    agent_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    UNIQUE(run_id, request_id, probe)
+);
+
+CREATE INDEX IF NOT EXISTS idx_tamper_findings_run ON tamper_findings(run_id, url);
@@ -0,0 +1,15 @@
+-- Agent Usage Migration
+-- Requests and bytes each agent proxied per UTC day, for attributing egress
+-- costs. Kept when the agent is deleted, so there is no foreign key.
+
+CREATE TABLE IF NOT EXISTS agent_usage (
+    agent_id TEXT NOT NULL,
+    day TEXT NOT NULL, -- YYYY-MM-DD, UTC
+    requests INTEGER NOT NULL DEFAULT 0,
+    responses INTEGER NOT NULL DEFAULT 0,
+    bytes_sent INTEGER NOT NULL DEFAULT 0, -- requests to the targets
+    bytes_received INTEGER NOT NULL DEFAULT 0, -- responses from the targets
+    PRIMARY KEY (agent_id, day)
+);
+
+CREATE INDEX IF NOT EXISTS idx_agent_usage_day ON agent_usage(day);
@@ -0,0 +1,13 @@
+-- Flow Reveal Audit Migration
+-- One row per masked flow step value revealed through the API. Kept when the
+-- profile is deleted, so there is no foreign key.
+
+CREATE TABLE IF NOT EXISTS flow_reveal_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    profile_id TEXT NOT NULL,
+    step_index INTEGER NOT NULL,
+    principal TEXT -- NULL for the admin token or with access control off
+);
+
+CREATE INDEX IF NOT EXISTS idx_flow_reveal_audit_profile ON flow_reveal_audit(profile_id);
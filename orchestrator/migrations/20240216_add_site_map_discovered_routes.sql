@@ -0,0 +1,32 @@
+-- Crawled routes join the site map before any traffic reaches them; they count no hits until requests are captured
+-- Host and path are derived like http_transactions.site_host/site_path, with the fragment dropped as well
+
+CREATE TRIGGER IF NOT EXISTS site_map_discovered AFTER INSERT ON discovered_routes
+BEGIN
+    INSERT INTO site_map (host, path, method, last_seen)
+    SELECT
+        CASE WHEN instr(rest, '/') > 0 THEN substr(rest, 1, instr(rest, '/') - 1) ELSE rest END,
+        CASE WHEN instr(rest, '/') > 0 THEN substr(rest, instr(rest, '/')) ELSE '/' END,
+        'GET', NEW.discovered_at
+    FROM (
+        SELECT substr(CASE WHEN instr(url, '?') > 0 THEN substr(url, 1, instr(url, '?') - 1) ELSE url END, instr(url, '://') + 3) AS rest
+        FROM (SELECT CASE WHEN instr(NEW.url, '#') > 0 THEN substr(NEW.url, 1, instr(NEW.url, '#') - 1) ELSE NEW.url END AS url)
+        WHERE instr(url, '://') > 0
+    )
+    WHERE rest <> ''
+    ON CONFLICT (host, path, method) DO NOTHING;
+END;
+
+-- Routes crawled before this migration
+INSERT INTO site_map (host, path, method, last_seen)
+SELECT
+    CASE WHEN instr(rest, '/') > 0 THEN substr(rest, 1, instr(rest, '/') - 1) ELSE rest END,
+    CASE WHEN instr(rest, '/') > 0 THEN substr(rest, instr(rest, '/')) ELSE '/' END,
+    'GET', discovered_at
+FROM (
+    SELECT substr(CASE WHEN instr(url, '?') > 0 THEN substr(url, 1, instr(url, '?') - 1) ELSE url END, instr(url, '://') + 3) AS rest, discovered_at
+    FROM (SELECT CASE WHEN instr(url, '#') > 0 THEN substr(url, 1, instr(url, '#') - 1) ELSE url END AS url, discovered_at FROM discovered_routes)
+    WHERE instr(url, '://') > 0
+)
+WHERE rest <> ''
+ON CONFLICT (host, path, method) DO NOTHING;
@@ -0,0 +1,522 @@
+//! Authorization Matrix
+//!
+//! Replays captured requests once per stored session, most privileged
+//! first, and optionally once with no credentials at all, then lays the
+//! responses out as a matrix of status codes and body lengths per endpoint.
+//! The first session is the reference: a less privileged identity that gets
+//! a 2xx response of about the same length as the reference most likely
+//! sees the same data, which is how IDOR and broken access control show
+//! themselves. Such cells are flagged for review.
+//!
+//! The credentials of the captured request are removed before each replay,
+//! so a column only carries what its own session supplies. Requests outside
+//! the project scope are skipped, and replays are held to the Replay rules
+//! of engagement.
+
+use crate::engagement::EngagementGuard;
+use crate::models::engagement::ActiveTool;
+use crate::repeater::RepeaterManager;
+use crate::session_integration::ExpirationHandling;
+use crate::Database;
+use attack_engine::{AttackError, AttackResult, HttpRequestData, HttpResponseData};
+use dashmap::DashMap;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Most captured requests a run replays
+pub const MAX_MATRIX_REQUESTS: usize = 500;
+
+/// Relative body length difference under which two responses count as
+/// the same, unless a run asks otherwise
+pub const DEFAULT_LENGTH_TOLERANCE: f64 = 0.05;
+
+/// Headers that carry credentials whatever the sessions define
+const CREDENTIAL_HEADERS: [&str; 3] = ["authorization", "cookie", "proxy-authorization"];
+
+/// Label of the column replayed without credentials
+pub const ANONYMOUS_COLUMN: &str = "anonymous";
+
+/// What to replay and as whom
+#[derive(Debug, Clone)]
+pub struct AccessMatrixConfig {
+    /// Captured requests, by ID
+    pub request_ids: Vec<String>,
+    /// Stored sessions from most to least privileged; the first is the
+    /// reference the others are compared with
+    pub sessions: Vec<Uuid>,
+    /// Also replay every request without credentials
+    pub include_anonymous: bool,
+    pub agent_id: String,
+    /// See [`DEFAULT_LENGTH_TOLERANCE`]
+    pub length_tolerance: f64,
+}
+
+impl AccessMatrixConfig {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if self.request_ids.is_empty() || self.request_ids.len() > MAX_MATRIX_REQUESTS {
+            return Err(format!("Select between 1 and {} requests", MAX_MATRIX_REQUESTS));
+        }
+        if self.sessions.is_empty() {
+            return Err("Select at least one session as the reference".to_string());
+        }
+        if self.sessions.len() + usize::from(self.include_anonymous) < 2 {
+            return Err("Compare the reference session with at least one other session or anonymous".to_string());
+        }
+        if self.sessions.iter().collect::<HashSet<_>>().len() != self.sessions.len() {
+            return Err("A session is selected more than once".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.length_tolerance) {
+            return Err("Length tolerance must be between 0 and 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How a cell compares with the reference cell of its row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellVerdict {
+    /// The cell is the reference
+    Reference,
+    /// A response other than 2xx
+    Denied,
+    /// 2xx with about the reference's length: possibly the same data
+    Similar,
+    /// 2xx that differs from the reference, or a reference that was not 2xx
+    Different,
+    /// The request could not be sent or the session not applied
+    Failed,
+}
+
+/// Response of one identity to one request
+#[derive(Debug, Clone)]
+pub struct AccessCell {
+    /// Session name, or [`ANONYMOUS_COLUMN`]
+    pub identity: String,
+    pub status_code: Option<i32>,
+    pub length: Option<usize>,
+    pub error: Option<String>,
+    pub verdict: CellVerdict,
+}
+
+impl AccessCell {
+    fn response(identity: String, response: &HttpResponseData) -> Self {
+        Self {
+            identity,
+            status_code: Some(response.status_code),
+            length: Some(response.body.len()),
+            error: None,
+            verdict: CellVerdict::Failed,
+        }
+    }
+
+    fn failed(identity: String, error: String) -> Self {
+        Self {
+            identity,
+            status_code: None,
+            length: None,
+            error: Some(error),
+            verdict: CellVerdict::Failed,
+        }
+    }
+}
+
+/// One replayed request and its cells, in column order
+#[derive(Debug, Clone)]
+pub struct AccessMatrixRow {
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    /// Method and path with ID-like segments replaced, shared by the rows
+    /// of one endpoint
+    pub endpoint: String,
+    pub cells: Vec<AccessCell>,
+}
+
+impl AccessMatrixRow {
+    /// Whether a less privileged identity got what the reference got
+    pub fn flagged(&self) -> bool {
+        self.cells.iter().any(|cell| cell.verdict == CellVerdict::Similar)
+    }
+}
+
+/// A captured request the run did not replay
+#[derive(Debug, Clone)]
+pub struct SkippedRequest {
+    pub request_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessMatrixStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Progress and rows of a matrix run
+#[derive(Debug, Clone)]
+pub struct AccessMatrixRun {
+    pub id: String,
+    pub agent_id: String,
+    pub status: AccessMatrixStatus,
+    /// Column labels, the reference first
+    pub columns: Vec<String>,
+    pub requested: usize,
+    pub rows: Vec<AccessMatrixRow>,
+    pub skipped: Vec<SkippedRequest>,
+    pub error: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Column of a run: a stored session or no credentials
+#[derive(Debug, Clone)]
+enum Identity {
+    Session { id: Uuid, name: String },
+    Anonymous,
+}
+
+impl Identity {
+    fn label(&self) -> String {
+        match self {
+            Identity::Session { name, .. } => name.clone(),
+            Identity::Anonymous => ANONYMOUS_COLUMN.to_string(),
+        }
+    }
+}
+
+/// Matrix runs of the orchestrator, kept in memory
+pub struct AccessMatrix {
+    database: Arc<Database>,
+    repeater: Arc<RepeaterManager>,
+    runs: Arc<DashMap<String, AccessMatrixRun>>,
+    cancellations: Arc<DashMap<String, Arc<AtomicBool>>>,
+}
+
+impl AccessMatrix {
+    pub fn new(database: Arc<Database>, repeater: Arc<RepeaterManager>) -> Self {
+        Self {
+            database,
+            repeater,
+            runs: Arc::new(DashMap::new()),
+            cancellations: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Start replaying in the background; returns the run ID
+    pub async fn start(&self, config: AccessMatrixConfig) -> AttackResult<String> {
+        config.validate().map_err(|reason| AttackError::ValidationError {
+            field: "access_matrix".to_string(),
+            reason,
+        })?;
+        self.repeater.validate_agent_availability(&config.agent_id).await?;
+
+        // Every header a selected session sets is a credential to remove
+        let mut identities = Vec::new();
+        let mut stripped: HashSet<String> = CREDENTIAL_HEADERS.iter().map(|name| name.to_string()).collect();
+        for session_id in &config.sessions {
+            let session = self.repeater.get_session(session_id).await.ok_or_else(|| AttackError::ValidationError {
+                field: "sessions".to_string(),
+                reason: format!("Session {} not found", session_id),
+            })?;
+            stripped.extend(session.headers.keys().map(|name| name.to_ascii_lowercase()));
+            identities.push(Identity::Session { id: session.id, name: session.name });
+        }
+        if config.include_anonymous {
+            identities.push(Identity::Anonymous);
+        }
+
+        let scope_rules = self.database.get_scope_rules().await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("get_scope_rules: {}", e),
+        })?;
+        let guard = EngagementGuard::load(self.database.clone(), ActiveTool::Replay).await?;
+
+        let id = Uuid::new_v4().to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancellations.insert(id.clone(), cancelled.clone());
+        self.runs.insert(
+            id.clone(),
+            AccessMatrixRun {
+                id: id.clone(),
+                agent_id: config.agent_id.clone(),
+                status: AccessMatrixStatus::Running,
+                columns: identities.iter().map(Identity::label).collect(),
+                requested: config.request_ids.len(),
+                rows: Vec::new(),
+                skipped: Vec::new(),
+                error: None,
+                started_at: chrono::Utc::now(),
+                finished_at: None,
+            },
+        );
+        info!(
+            "🔑 Access matrix run {} replaying {} requests as {} identities",
+            id,
+            config.request_ids.len(),
+            identities.len()
+        );
+
+        let (database, repeater, runs, cancellations, run_id) = (
+            self.database.clone(),
+            self.repeater.clone(),
+            self.runs.clone(),
+            self.cancellations.clone(),
+            id.clone(),
+        );
+        tokio::spawn(async move {
+            let outcome: Result<AccessMatrixStatus, String> = async {
+                for request_id in &config.request_ids {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return Ok(AccessMatrixStatus::Cancelled);
+                    }
+                    let skip = |reason: String| {
+                        if let Some(mut run) = runs.get_mut(&run_id) {
+                            run.skipped.push(SkippedRequest { request_id: request_id.clone(), reason });
+                        }
+                    };
+
+                    let request: HttpRequestData = match database.get_request_by_id(request_id).await {
+                        Ok(Some((_, request))) => request.into(),
+                        Ok(None) => {
+                            skip("Request not found".to_string());
+                            continue;
+                        }
+                        Err(e) => return Err(format!("Failed to load request {}: {}", request_id, e)),
+                    };
+                    if !crate::scope::is_in_scope(&scope_rules, &request.url) {
+                        skip("Out of scope".to_string());
+                        continue;
+                    }
+                    if let Err(e) = guard.check(&request.url).await {
+                        skip(e.to_string());
+                        continue;
+                    }
+
+                    let base = strip_credentials(request, &stripped);
+                    let mut cells = Vec::with_capacity(identities.len());
+                    for identity in &identities {
+                        let cell = match replay(&repeater, &base, identity, &config.agent_id).await {
+                            Ok(response) => AccessCell::response(identity.label(), &response),
+                            Err(AttackError::AgentUnavailable { agent_id }) => {
+                                return Err(format!("Agent {} is unavailable", agent_id));
+                            }
+                            Err(e) => {
+                                warn!("Access matrix run {}: {} as {} failed: {}", run_id, base.url, identity.label(), e);
+                                AccessCell::failed(identity.label(), e.to_string())
+                            }
+                        };
+                        cells.push(cell);
+                    }
+                    classify_row(&mut cells, config.length_tolerance);
+
+                    if let Some(mut run) = runs.get_mut(&run_id) {
+                        run.rows.push(AccessMatrixRow {
+                            request_id: request_id.clone(),
+                            endpoint: endpoint_key(&base.method, &base.url),
+                            method: base.method,
+                            url: base.url,
+                            cells,
+                        });
+                    }
+                }
+                Ok(AccessMatrixStatus::Completed)
+            }
+            .await;
+
+            cancellations.remove(&run_id);
+            if let Some(mut run) = runs.get_mut(&run_id) {
+                match outcome {
+                    Ok(status) => run.status = status,
+                    Err(error) => {
+                        run.status = AccessMatrixStatus::Failed;
+                        run.error = Some(error);
+                    }
+                }
+                run.finished_at = Some(chrono::Utc::now());
+                let flagged = run.rows.iter().filter(|row| row.flagged()).count();
+                info!("🔑 Access matrix run {} {:?} with {} flagged rows", run_id, run.status, flagged);
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// Stop replaying; returns false if the run is not running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.cancellations.get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<AccessMatrixRun> {
+        self.runs.get(id).map(|run| run.clone())
+    }
+
+    /// Every run, newest first
+    pub fn list(&self) -> Vec<AccessMatrixRun> {
+        let mut runs: Vec<AccessMatrixRun> = self.runs.iter().map(|run| run.clone()).collect();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.started_at));
+        runs
+    }
+
+    pub fn delete(&self, id: &str) -> bool {
+        self.cancel(id);
+        self.runs.remove(id).is_some()
+    }
+}
+
+/// Send `request` as `identity`. Sessions that have expired fail the cell
+/// rather than falling back to no credentials.
+async fn replay(
+    repeater: &RepeaterManager,
+    request: &HttpRequestData,
+    identity: &Identity,
+    agent_id: &str,
+) -> AttackResult<HttpResponseData> {
+    let request = match identity {
+        Identity::Session { id, .. } => {
+            repeater
+                .apply_session_to_request(request.clone(), id, Some(ExpirationHandling::Fail))
+                .await?
+                .0
+        }
+        Identity::Anonymous => request.clone(),
+    };
+    repeater.execute_through_agent(&request, agent_id).await
+}
+
+/// `request` without the headers named in `stripped` (lowercase)
+pub fn strip_credentials(mut request: HttpRequestData, stripped: &HashSet<String>) -> HttpRequestData {
+    if let Some(headers) = request.headers.as_mut() {
+        headers.headers.retain(|name, _| !stripped.contains(&name.to_ascii_lowercase()));
+    }
+    request
+}
+
+/// Set the verdict of every cell against the first one
+pub fn classify_row(cells: &mut [AccessCell], length_tolerance: f64) {
+    let Some((reference, others)) = cells.split_first_mut() else {
+        return;
+    };
+    if reference.error.is_none() {
+        reference.verdict = CellVerdict::Reference;
+    }
+    let reference_success = reference.status_code.filter(|status| (200..300).contains(status));
+    for cell in others {
+        cell.verdict = match (cell.status_code, cell.length) {
+            (Some(status), Some(length)) if (200..300).contains(&status) => match (reference_success, reference.length) {
+                (Some(_), Some(reference_length)) if similar_length(reference_length, length, length_tolerance) => {
+                    CellVerdict::Similar
+                }
+                _ => CellVerdict::Different,
+            },
+            (Some(_), _) => CellVerdict::Denied,
+            _ => CellVerdict::Failed,
+        };
+    }
+}
+
+fn similar_length(a: usize, b: usize, tolerance: f64) -> bool {
+    a.abs_diff(b) as f64 <= tolerance * a.max(b) as f64
+}
+
+/// `METHOD /path` with numeric, UUID and long hex segments replaced by
+/// `{id}`, so `/api/users/1` and `/api/users/2` are one endpoint
+pub fn endpoint_key(method: &str, url: &str) -> String {
+    let path = url::Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_else(|_| url.split('?').next().unwrap_or(url).to_string());
+    let path: Vec<&str> = path
+        .split('/')
+        .map(|segment| {
+            let id_like = (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()))
+                || Uuid::parse_str(segment).is_ok()
+                || (segment.len() >= 16 && segment.chars().all(|c| c.is_ascii_hexdigit()));
+            if id_like {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect();
+    format!("{} {}", method.to_ascii_uppercase(), path.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attack_engine::HttpHeaders;
+
+    fn cell(identity: &str, status_code: i32, length: usize) -> AccessCell {
+        AccessCell {
+            identity: identity.to_string(),
+            status_code: Some(status_code),
+            length: Some(length),
+            error: None,
+            verdict: CellVerdict::Failed,
+        }
+    }
+
+    #[test]
+    fn test_lower_privilege_with_reference_response_is_flagged() {
+        let mut cells = vec![
+            cell("admin", 200, 1000),
+            cell("user", 200, 1020),
+            cell("other user", 200, 180),
+            cell(ANONYMOUS_COLUMN, 401, 30),
+            AccessCell::failed("expired".to_string(), "Session expired".to_string()),
+        ];
+        classify_row(&mut cells, DEFAULT_LENGTH_TOLERANCE);
+        let verdicts: Vec<CellVerdict> = cells.iter().map(|cell| cell.verdict).collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                CellVerdict::Reference,
+                CellVerdict::Similar,
+                CellVerdict::Different,
+                CellVerdict::Denied,
+                CellVerdict::Failed,
+            ]
+        );
+
+        // A reference that was denied itself shows nothing to compare with
+        let mut cells = vec![cell("admin", 403, 20), cell("user", 200, 20)];
+        classify_row(&mut cells, DEFAULT_LENGTH_TOLERANCE);
+        assert_eq!(cells[1].verdict, CellVerdict::Different);
+    }
+
+    #[test]
+    fn test_credentials_are_stripped_and_endpoints_grouped() {
+        let request = HttpRequestData {
+            method: "GET".to_string(),
+            url: "http://localhost:3000/api/users/2?expand=1".to_string(),
+            headers: Some(HttpHeaders {
+                headers: [("Cookie", "sid=1"), ("X-Api-Key", "k"), ("Accept", "*/*")]
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+            }),
+            body: Vec::new(),
+            tls: None,
+        };
+        let stripped: HashSet<String> = ["cookie", "x-api-key"].into_iter().map(String::from).collect();
+        let headers = strip_credentials(request, &stripped).headers.unwrap().headers;
+        assert_eq!(headers.keys().collect::<Vec<_>>(), vec!["Accept"]);
+
+        assert_eq!(endpoint_key("get", "http://localhost:3000/api/users/2?expand=1"), "GET /api/users/{id}");
+        assert_eq!(
+            endpoint_key("GET", "http://host/api/documents/6f1c1a9e-2b7e-4c39-9a43-3e0c2f1d8b10/raw"),
+            "GET /api/documents/{id}/raw"
+        );
+        assert_eq!(endpoint_key("POST", "http://host/api/user/profile"), "POST /api/user/profile");
+    }
+}
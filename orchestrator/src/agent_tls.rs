@@ -0,0 +1,347 @@
+//! Agent Channel TLS
+//!
+//! Mutual TLS for the gRPC channel agents connect over. The orchestrator keeps
+//! its own control-plane CA under `<projects_dir>/pki`, separate from the MITM
+//! CA it hands to agents: it signs the gRPC server certificate and the client
+//! certificates issued when an agent is enrolled. Agents pin the control-plane
+//! CA, so they verify the orchestrator before any CA material is sent, and the
+//! orchestrator only serves agents whose certificate is enrolled and not
+//! revoked. Enrollments are recorded by certificate fingerprint in the
+//! server-wide database.
+
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose, SanType, PKCS_ECDSA_P256_SHA256,
+};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Row, Sqlite};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use time::{Duration, OffsetDateTime};
+use tonic::transport::{Certificate as TlsCertificate, Identity, ServerTlsConfig};
+
+const CA_COMMON_NAME: &str = "Proxxy Control Plane CA";
+const CA_ORGANIZATION: &str = "Proxxy Distributed MITM";
+
+/// Validity of issued agent certificates
+const AGENT_CERT_DAYS: i64 = 365;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AgentTlsError {
+    #[error("PKI I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Certificate error: {0}")]
+    Certificate(String),
+    #[error("Enrollment database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Agent name must not be empty")]
+    EmptyName,
+}
+
+/// Settings for serving the agent gRPC channel over mutual TLS
+#[derive(Debug, Clone, Default)]
+pub struct GrpcTlsConfig {
+    /// DNS names and IPs agents use to reach the orchestrator, put in the
+    /// server certificate (defaults to localhost)
+    pub server_names: Vec<String>,
+}
+
+/// Client certificate issued on enrollment; the key is not kept
+#[derive(Debug, Clone)]
+pub struct IssuedAgentCert {
+    pub name: String,
+    pub fingerprint: String,
+    pub cert_pem: String,
+    pub key_pem: String,
+    /// Control-plane CA the agent pins (`--orchestrator-ca`)
+    pub ca_cert_pem: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentCertificateInfo {
+    pub fingerprint: String,
+    pub name: String,
+    pub issued_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+pub struct AgentTlsService {
+    pki_dir: PathBuf,
+    ca_cert: Certificate,
+    ca_cert_pem: String,
+    pool: Pool<Sqlite>,
+}
+
+impl AgentTlsService {
+    /// Load (or create) the control-plane CA under `<projects_dir>/pki` and
+    /// the enrollment table in `<projects_dir>/server.db`
+    pub async fn open(projects_dir: &Path) -> Result<Self, AgentTlsError> {
+        let pki_dir = projects_dir.join("pki");
+        std::fs::create_dir_all(&pki_dir)?;
+        let cert_path = pki_dir.join("control-ca.pem");
+        let key_path = pki_dir.join("control-ca.key");
+
+        let (ca_cert, ca_cert_pem) = if cert_path.exists() && key_path.exists() {
+            let key_pair = KeyPair::from_pem(&std::fs::read_to_string(&key_path)?).map_err(cert_error)?;
+            // rcgen cannot load a certificate for signing: rebuild it with the
+            // same name and key, which verifies against the stored one
+            let cert = Certificate::from_params(ca_params(key_pair)).map_err(cert_error)?;
+            (cert, std::fs::read_to_string(&cert_path)?)
+        } else {
+            let key_pair = KeyPair::generate(&PKCS_ECDSA_P256_SHA256).map_err(cert_error)?;
+            let cert = Certificate::from_params(ca_params(key_pair)).map_err(cert_error)?;
+            let pem = cert.serialize_pem().map_err(cert_error)?;
+            std::fs::write(&cert_path, &pem)?;
+            write_private(&key_path, &cert.serialize_private_key_pem())?;
+            (cert, pem)
+        };
+
+        let options = SqliteConnectOptions::from_str(&format!(
+            "sqlite:{}",
+            projects_dir.join("server.db").to_string_lossy()
+        ))?
+        .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(2).connect_with(options).await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS agent_certificates (
+                fingerprint TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                issued_at INTEGER NOT NULL,
+                expires_at INTEGER NOT NULL,
+                revoked_at INTEGER
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            pki_dir,
+            ca_cert,
+            ca_cert_pem,
+            pool,
+        })
+    }
+
+    pub fn ca_cert_pem(&self) -> &str {
+        &self.ca_cert_pem
+    }
+
+    /// Server TLS for the gRPC listener. A fresh server certificate for
+    /// `server_names` is issued on each start; client certificates are checked
+    /// against the control-plane CA here and against enrollments per call.
+    pub fn server_tls_config(&self, server_names: &[String]) -> Result<ServerTlsConfig, AgentTlsError> {
+        let names = if server_names.is_empty() {
+            vec!["localhost".to_string(), "127.0.0.1".to_string()]
+        } else {
+            server_names.to_vec()
+        };
+
+        let mut params = CertificateParams::new(Vec::<String>::new());
+        params.distinguished_name = distinguished_name(&names[0]);
+        params.subject_alt_names = names.iter().map(|n| san(n)).collect();
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+        set_validity(&mut params, AGENT_CERT_DAYS);
+
+        let cert = Certificate::from_params(params).map_err(cert_error)?;
+        let cert_pem = cert.serialize_pem_with_signer(&self.ca_cert).map_err(cert_error)?;
+        let key_pem = cert.serialize_private_key_pem();
+        std::fs::write(self.pki_dir.join("server.pem"), &cert_pem)?;
+        write_private(&self.pki_dir.join("server.key"), &key_pem)?;
+
+        Ok(ServerTlsConfig::new()
+            .identity(Identity::from_pem(cert_pem, key_pem))
+            .client_ca_root(TlsCertificate::from_pem(&self.ca_cert_pem)))
+    }
+
+    /// Issue and enroll a client certificate for an agent
+    pub async fn enroll_agent(&self, name: &str) -> Result<IssuedAgentCert, AgentTlsError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(AgentTlsError::EmptyName);
+        }
+
+        let mut params = CertificateParams::new(Vec::<String>::new());
+        params.distinguished_name = distinguished_name(name);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        set_validity(&mut params, AGENT_CERT_DAYS);
+        let expires_at = params.not_after.unix_timestamp();
+
+        let cert = Certificate::from_params(params).map_err(cert_error)?;
+        let cert_der = cert.serialize_der_with_signer(&self.ca_cert).map_err(cert_error)?;
+        let fingerprint = cert_fingerprint(&cert_der);
+        let issued = IssuedAgentCert {
+            name: name.to_string(),
+            cert_pem: pem_encode("CERTIFICATE", &cert_der),
+            key_pem: cert.serialize_private_key_pem(),
+            ca_cert_pem: self.ca_cert_pem.clone(),
+            fingerprint,
+        };
+
+        sqlx::query("INSERT INTO agent_certificates (fingerprint, name, issued_at, expires_at) VALUES (?, ?, ?, ?)")
+            .bind(&issued.fingerprint)
+            .bind(&issued.name)
+            .bind(chrono::Utc::now().timestamp())
+            .bind(expires_at)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(issued)
+    }
+
+    /// Enrolled agent name for a presented certificate (DER), if it is
+    /// enrolled, unexpired and not revoked
+    pub async fn enrolled_agent(&self, cert_der: &[u8]) -> Result<Option<String>, AgentTlsError> {
+        let row = sqlx::query(
+            "SELECT name FROM agent_certificates WHERE fingerprint = ? AND revoked_at IS NULL AND expires_at > ?",
+        )
+        .bind(cert_fingerprint(cert_der))
+        .bind(chrono::Utc::now().timestamp())
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| r.get("name")))
+    }
+
+    /// All issued certificates, newest first
+    pub async fn list_agent_certificates(&self) -> Result<Vec<AgentCertificateInfo>, AgentTlsError> {
+        let rows = sqlx::query(
+            "SELECT fingerprint, name, issued_at, expires_at, revoked_at FROM agent_certificates
+             ORDER BY issued_at DESC, fingerprint",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AgentCertificateInfo {
+                fingerprint: row.get("fingerprint"),
+                name: row.get("name"),
+                issued_at: row.get("issued_at"),
+                expires_at: row.get("expires_at"),
+                revoked_at: row.get("revoked_at"),
+            })
+            .collect())
+    }
+
+    /// Revoke a certificate; false if unknown or already revoked
+    pub async fn revoke_agent_certificate(&self, fingerprint: &str) -> Result<bool, AgentTlsError> {
+        let result =
+            sqlx::query("UPDATE agent_certificates SET revoked_at = ? WHERE fingerprint = ? AND revoked_at IS NULL")
+                .bind(chrono::Utc::now().timestamp())
+                .bind(fingerprint.to_ascii_lowercase())
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// SHA-256 of a DER certificate, lowercase hex
+pub fn cert_fingerprint(der: &[u8]) -> String {
+    Sha256::digest(der).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn cert_error(e: impl std::fmt::Display) -> AgentTlsError {
+    AgentTlsError::Certificate(e.to_string())
+}
+
+fn ca_params(key_pair: KeyPair) -> CertificateParams {
+    let mut params = CertificateParams::default();
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, CA_COMMON_NAME);
+    dn.push(DnType::OrganizationName, CA_ORGANIZATION);
+    params.distinguished_name = dn;
+    params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    set_validity(&mut params, 365 * 10);
+    params.key_pair = Some(key_pair);
+    params
+}
+
+fn distinguished_name(common_name: &str) -> DistinguishedName {
+    let mut dn = DistinguishedName::new();
+    dn.push(DnType::CommonName, common_name);
+    dn.push(DnType::OrganizationName, CA_ORGANIZATION);
+    dn
+}
+
+fn san(name: &str) -> SanType {
+    match name.parse::<IpAddr>() {
+        Ok(ip) => SanType::IpAddress(ip),
+        Err(_) => SanType::DnsName(name.to_string()),
+    }
+}
+
+fn set_validity(params: &mut CertificateParams, days: i64) {
+    let not_before = OffsetDateTime::now_utc() - Duration::days(1);
+    params.not_before = not_before;
+    params.not_after = not_before + Duration::days(days);
+}
+
+fn pem_encode(label: &str, der: &[u8]) -> String {
+    use base64::Engine;
+    let encoded = base64::engine::general_purpose::STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap_or_default());
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+/// Write a private key readable only by the owner
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    std::fs::write(path, contents)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pem_der(pem: &str) -> Vec<u8> {
+        use base64::Engine;
+        let body: String = pem.lines().filter(|l| !l.starts_with("-----")).collect();
+        base64::engine::general_purpose::STANDARD.decode(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_enrollment_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let tls = AgentTlsService::open(dir.path()).await.unwrap();
+        assert!(tls.ca_cert_pem().contains("BEGIN CERTIFICATE"));
+        assert!(tls.server_tls_config(&["proxxy.internal".to_string()]).is_ok());
+
+        let issued = tls.enroll_agent("edge-1").await.unwrap();
+        assert_eq!(issued.fingerprint, cert_fingerprint(&pem_der(&issued.cert_pem)));
+        assert!(issued.key_pem.contains("PRIVATE KEY"));
+
+        let der = pem_der(&issued.cert_pem);
+        assert_eq!(tls.enrolled_agent(&der).await.unwrap().as_deref(), Some("edge-1"));
+        assert_eq!(tls.enrolled_agent(b"not a cert").await.unwrap(), None);
+
+        assert!(tls.revoke_agent_certificate(&issued.fingerprint).await.unwrap());
+        assert!(!tls.revoke_agent_certificate(&issued.fingerprint).await.unwrap());
+        assert_eq!(tls.enrolled_agent(&der).await.unwrap(), None);
+        assert_eq!(tls.list_agent_certificates().await.unwrap().len(), 1);
+
+        assert!(matches!(tls.enroll_agent(" ").await, Err(AgentTlsError::EmptyName)));
+    }
+
+    #[tokio::test]
+    async fn test_ca_is_reused() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = AgentTlsService::open(dir.path()).await.unwrap().ca_cert_pem().to_string();
+        let second = AgentTlsService::open(dir.path()).await.unwrap().ca_cert_pem().to_string();
+        assert_eq!(first, second);
+    }
+}
@@ -0,0 +1,385 @@
+//! Reference documentation of the gRPC API
+//!
+//! The agent channel and project sync are gRPC services, so they are not in
+//! the OpenAPI document. Their reference is rendered to Markdown from the
+//! bundled `proxy.proto`, comments included, and served next to the OpenAPI
+//! JSON under `/api-docs`. Only the constructs the file uses are understood:
+//! services, messages, enums and oneofs, nested to any depth.
+
+/// The proto file the orchestrator was built with
+pub const PROXY_PROTO: &str = include_str!("../../proto/proxy.proto");
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcDoc {
+    pub name: String,
+    pub request: String,
+    pub response: String,
+    pub client_streaming: bool,
+    pub server_streaming: bool,
+    pub doc: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceDoc {
+    pub name: String,
+    pub doc: String,
+    pub rpcs: Vec<RpcDoc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDoc {
+    pub name: String,
+    /// Type as written, e.g. `repeated string` or `map<string, string>`
+    pub ty: String,
+    pub number: String,
+    /// Oneof the field belongs to
+    pub oneof: Option<String>,
+    pub doc: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageDoc {
+    /// Qualified within the package, e.g. `ConnectionEvent`
+    pub name: String,
+    pub doc: String,
+    pub fields: Vec<FieldDoc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumValueDoc {
+    pub name: String,
+    pub number: String,
+    pub doc: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDoc {
+    /// Qualified within the package, e.g. `ConnectionEvent.Kind`
+    pub name: String,
+    pub doc: String,
+    pub values: Vec<EnumValueDoc>,
+}
+
+/// Everything documented in a proto file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProtoDoc {
+    pub package: String,
+    pub services: Vec<ServiceDoc>,
+    pub messages: Vec<MessageDoc>,
+    pub enums: Vec<EnumDoc>,
+}
+
+enum Block {
+    Service(usize),
+    Message(usize),
+    Enum(usize),
+    Oneof(String),
+    /// Anything else with braces, e.g. an option value
+    Other,
+}
+
+/// `statement` without its trailing comment, and the comment
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    match line.split_once("//") {
+        Some((statement, comment)) => (statement.trim(), Some(comment.trim())),
+        None => (line.trim(), None),
+    }
+}
+
+/// Type and name of `message Name {`-style openers
+fn opener<'a>(statement: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = statement.strip_prefix(keyword)?.strip_suffix('{')?;
+    rest.starts_with(char::is_whitespace).then(|| rest.trim())
+}
+
+/// `(stream Type)` as type and streaming flag
+fn rpc_type(part: &str) -> (String, bool) {
+    let inner = part.trim().trim_start_matches('(').trim_end_matches(')').trim();
+    match inner.strip_prefix("stream ") {
+        Some(ty) => (ty.trim().to_string(), true),
+        None => (inner.to_string(), false),
+    }
+}
+
+fn parse_rpc(statement: &str, doc: String) -> Option<RpcDoc> {
+    let rest = statement.strip_prefix("rpc ")?.trim_end_matches(';').trim_end_matches("{}").trim();
+    let (name, rest) = rest.split_once('(')?;
+    let (request, response) = rest.split_once("returns")?;
+    let (request, client_streaming) = rpc_type(&format!("({}", request));
+    let (response, server_streaming) = rpc_type(response);
+    Some(RpcDoc {
+        name: name.trim().to_string(),
+        request,
+        response,
+        client_streaming,
+        server_streaming,
+        doc,
+    })
+}
+
+/// Type, name and number of a field statement such as `repeated string tags = 1;`
+fn parse_field(statement: &str) -> Option<(String, String, String)> {
+    let (left, number) = statement.trim_end_matches(';').split_once('=')?;
+    let left = left.trim();
+    let (ty, name) = match left.find('>') {
+        Some(end) if left.starts_with("map<") => (&left[..=end], left[end + 1..].trim()),
+        _ => left.rsplit_once(char::is_whitespace)?,
+    };
+    Some((ty.trim().to_string(), name.to_string(), number.trim().to_string()))
+}
+
+/// Parse the declarations and their leading (or trailing) comments
+pub fn parse_proto(source: &str) -> ProtoDoc {
+    let mut doc = ProtoDoc::default();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    let mut comment: Vec<String> = Vec::new();
+
+    for line in source.lines() {
+        let (statement, trailing) = split_comment(line);
+        if statement.is_empty() {
+            match trailing {
+                Some(text) => comment.push(text.to_string()),
+                None => comment.clear(),
+            }
+            continue;
+        }
+        let mut text = std::mem::take(&mut comment);
+        text.extend(trailing.map(str::to_string));
+        let text = text.join(" ");
+        let qualified = |names: &[String], name: &str| {
+            names.iter().map(String::as_str).chain([name]).collect::<Vec<_>>().join(".")
+        };
+
+        if let Some(package) = statement.strip_prefix("package ") {
+            doc.package = package.trim_end_matches(';').trim().to_string();
+        } else if let Some(name) = opener(statement, "service") {
+            doc.services.push(ServiceDoc { name: name.to_string(), doc: text, rpcs: Vec::new() });
+            blocks.push(Block::Service(doc.services.len() - 1));
+        } else if let Some(name) = statement.strip_suffix("{}").and_then(|s| opener(&format!("{} {{", s), "message").map(str::to_string)) {
+            // Field-less message on one line
+            doc.messages.push(MessageDoc { name: qualified(&names, &name), doc: text, fields: Vec::new() });
+        } else if let Some(name) = opener(statement, "message") {
+            doc.messages.push(MessageDoc { name: qualified(&names, name), doc: text, fields: Vec::new() });
+            blocks.push(Block::Message(doc.messages.len() - 1));
+            names.push(name.to_string());
+        } else if let Some(name) = opener(statement, "enum") {
+            doc.enums.push(EnumDoc { name: qualified(&names, name), doc: text, values: Vec::new() });
+            blocks.push(Block::Enum(doc.enums.len() - 1));
+        } else if let Some(name) = opener(statement, "oneof") {
+            blocks.push(Block::Oneof(name.to_string()));
+        } else if statement.starts_with('}') {
+            if let Some(Block::Message(_)) = blocks.pop() {
+                names.pop();
+            }
+        } else if statement.ends_with('{') {
+            blocks.push(Block::Other);
+        } else if statement.starts_with("rpc ") {
+            if let (Some(Block::Service(index)), Some(rpc)) = (blocks.last(), parse_rpc(statement, text)) {
+                doc.services[*index].rpcs.push(rpc);
+            }
+        } else if statement.starts_with("reserved ") || statement.starts_with("option ") {
+            continue;
+        } else {
+            // The innermost message owns oneof fields
+            let oneof = match blocks.last() {
+                Some(Block::Oneof(name)) => Some(name.clone()),
+                _ => None,
+            };
+            let owner = blocks.iter().rev().find_map(|block| match block {
+                Block::Message(index) => Some(Block::Message(*index)),
+                Block::Enum(index) => Some(Block::Enum(*index)),
+                _ => None,
+            });
+            match owner {
+                Some(Block::Enum(index)) if oneof.is_none() => {
+                    if let Some((name, number)) = statement.trim_end_matches(';').split_once('=') {
+                        doc.enums[index].values.push(EnumValueDoc {
+                            name: name.trim().to_string(),
+                            number: number.trim().to_string(),
+                            doc: text,
+                        });
+                    }
+                }
+                Some(Block::Message(index)) => {
+                    if let Some((ty, name, number)) = parse_field(statement) {
+                        doc.messages[index].fields.push(FieldDoc { name, ty, number, oneof, doc: text });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    doc
+}
+
+/// Text safe inside a Markdown table cell
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+impl ProtoDoc {
+    pub fn to_markdown(&self) -> String {
+        let mut out = format!("# gRPC API: `{}`\n\n", self.package);
+        out.push_str("Generated from `proxy.proto`. Streaming sides are marked `stream`.\n");
+
+        out.push_str("\n## Services\n");
+        for service in &self.services {
+            out.push_str(&format!("\n### {}\n\n", service.name));
+            if !service.doc.is_empty() {
+                out.push_str(&format!("{}\n\n", service.doc));
+            }
+            out.push_str("| Method | Request | Response | Description |\n|---|---|---|---|\n");
+            let side = |streaming: bool, ty: &str| {
+                format!("{}[`{}`](#{})", if streaming { "stream " } else { "" }, ty, anchor(ty))
+            };
+            for rpc in &service.rpcs {
+                out.push_str(&format!(
+                    "| `{}` | {} | {} | {} |\n",
+                    rpc.name,
+                    side(rpc.client_streaming, &rpc.request),
+                    side(rpc.server_streaming, &rpc.response),
+                    cell(&rpc.doc)
+                ));
+            }
+        }
+
+        out.push_str("\n## Messages\n");
+        for message in &self.messages {
+            out.push_str(&format!("\n### {}\n\n", message.name));
+            if !message.doc.is_empty() {
+                out.push_str(&format!("{}\n\n", message.doc));
+            }
+            if message.fields.is_empty() {
+                out.push_str("No fields.\n");
+                continue;
+            }
+            out.push_str("| Field | Type | Number | Description |\n|---|---|---|---|\n");
+            for field in &message.fields {
+                let doc = match &field.oneof {
+                    Some(oneof) if field.doc.is_empty() => format!("One of `{}`.", oneof),
+                    Some(oneof) => format!("One of `{}`. {}", oneof, field.doc),
+                    None => field.doc.clone(),
+                };
+                out.push_str(&format!(
+                    "| `{}` | `{}` | {} | {} |\n",
+                    field.name,
+                    field.ty,
+                    field.number,
+                    cell(&doc)
+                ));
+            }
+        }
+
+        out.push_str("\n## Enums\n");
+        for item in &self.enums {
+            out.push_str(&format!("\n### {}\n\n", item.name));
+            if !item.doc.is_empty() {
+                out.push_str(&format!("{}\n\n", item.doc));
+            }
+            out.push_str("| Value | Number | Description |\n|---|---|---|\n");
+            for value in &item.values {
+                out.push_str(&format!("| `{}` | {} | {} |\n", value.name, value.number, cell(&value.doc)));
+            }
+        }
+        out
+    }
+}
+
+/// Heading anchor of a message, as GitHub-style renderers generate it
+fn anchor(name: &str) -> String {
+    name.to_lowercase().replace('.', "")
+}
+
+/// Markdown reference of the bundled proto
+pub fn grpc_markdown() -> String {
+    parse_proto(PROXY_PROTO).to_markdown()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTO: &str = r#"
+syntax = "proto3";
+
+package demo;
+
+// Talks to agents
+service Agents {
+  // Event stream
+  rpc Stream (stream Event) returns (stream Command);
+  rpc Register (RegisterRequest) returns (RegisterResponse);
+}
+
+// Something happened
+message Event {
+  enum Kind {
+    // Opened
+    OPEN = 0;
+    CLOSED = 1; // Closed again
+  }
+  Kind kind = 1;
+  repeated string tags = 2;
+  map<string, string> labels = 3;
+  oneof detail {
+    // Why | how
+    string reason = 4;
+    bytes payload = 5;
+  }
+}
+
+message Command {}
+"#;
+
+    #[test]
+    fn test_parse_proto() {
+        let doc = parse_proto(PROTO);
+        assert_eq!(doc.package, "demo");
+        assert_eq!(doc.services.len(), 1);
+        let service = &doc.services[0];
+        assert_eq!(service.doc, "Talks to agents");
+        assert_eq!(service.rpcs[0].name, "Stream");
+        assert_eq!(service.rpcs[0].request, "Event");
+        assert!(service.rpcs[0].client_streaming && service.rpcs[0].server_streaming);
+        assert_eq!(service.rpcs[1].response, "RegisterResponse");
+        assert!(!service.rpcs[1].client_streaming);
+
+        let names: Vec<_> = doc.messages.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["Event", "Command"]);
+        let fields = &doc.messages[0].fields;
+        let described: Vec<_> = fields.iter().map(|f| (f.name.as_str(), f.ty.as_str(), f.number.as_str())).collect();
+        assert_eq!(
+            described,
+            vec![
+                ("kind", "Kind", "1"),
+                ("tags", "repeated string", "2"),
+                ("labels", "map<string, string>", "3"),
+                ("reason", "string", "4"),
+                ("payload", "bytes", "5"),
+            ]
+        );
+        assert_eq!(fields[3].oneof.as_deref(), Some("detail"));
+        assert_eq!(fields[3].doc, "Why | how");
+        assert_eq!(fields[0].oneof, None);
+
+        assert_eq!(doc.enums[0].name, "Event.Kind");
+        assert_eq!(doc.enums[0].values[0].doc, "Opened");
+        assert_eq!(doc.enums[0].values[1].doc, "Closed again");
+    }
+
+    #[test]
+    fn test_markdown() {
+        let markdown = parse_proto(PROTO).to_markdown();
+        assert!(markdown.contains("| `Stream` | stream [`Event`](#event) | stream [`Command`](#command) | Event stream |"));
+        assert!(markdown.contains("| `reason` | `string` | 4 | One of `detail`. Why \\| how |"));
+        assert!(markdown.contains("### Event.Kind"));
+
+        // The bundled proto documents every service
+        let bundled = grpc_markdown();
+        assert!(bundled.contains("### ProxyService"));
+        assert!(bundled.contains("### ProjectSync"));
+        assert!(bundled.contains("### LiveStreams"));
+        assert!(bundled.contains("| `AUTO_PASS_THROUGH` | 6 |"));
+    }
+}
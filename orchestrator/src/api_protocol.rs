@@ -0,0 +1,290 @@
+//! API Protocol Tagging
+//!
+//! Classifies captured transactions by the kind of API call they are (REST/JSON,
+//! GraphQL, gRPC, SOAP, form posts, multipart uploads, binary payloads), so
+//! traffic can be filtered by protocol. Requests are classified at ingest from
+//! their headers and body; bodiless requests (a `GET /api/users`) are tagged
+//! once their response arrives. Page loads and static assets stay untagged.
+
+use std::collections::HashMap;
+
+/// Bytes of a body inspected when sniffing its format
+const SNIFF_LEN: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApiProtocol {
+    Rest,
+    GraphQl,
+    Grpc,
+    Soap,
+    Form,
+    Multipart,
+    Binary,
+}
+
+impl ApiProtocol {
+    pub const ALL: [ApiProtocol; 7] = [
+        ApiProtocol::Rest,
+        ApiProtocol::GraphQl,
+        ApiProtocol::Grpc,
+        ApiProtocol::Soap,
+        ApiProtocol::Form,
+        ApiProtocol::Multipart,
+        ApiProtocol::Binary,
+    ];
+
+    /// Value stored in `http_transactions.api_protocol`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiProtocol::Rest => "rest",
+            ApiProtocol::GraphQl => "graphql",
+            ApiProtocol::Grpc => "grpc",
+            ApiProtocol::Soap => "soap",
+            ApiProtocol::Form => "form",
+            ApiProtocol::Multipart => "multipart",
+            ApiProtocol::Binary => "binary",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|p| p.as_str() == value)
+    }
+}
+
+/// Classify a request. `None` when nothing marks it as an API call; the
+/// response can still tag it through [`classify_response`].
+pub fn classify_request(url: &str, headers: &HashMap<String, String>, body: &[u8]) -> Option<ApiProtocol> {
+    let content_type = content_type(headers);
+
+    if crate::grpc_decode::is_grpc(&content_type) {
+        return Some(ApiProtocol::Grpc);
+    }
+    if is_graphql_request(url, &content_type, body) {
+        return Some(ApiProtocol::GraphQl);
+    }
+    if header(headers, "soapaction").is_some() || is_soap(&content_type, body) {
+        return Some(ApiProtocol::Soap);
+    }
+    if content_type.starts_with("multipart/") {
+        return Some(ApiProtocol::Multipart);
+    }
+    if content_type == "application/x-www-form-urlencoded" {
+        return Some(ApiProtocol::Form);
+    }
+    if is_json(&content_type, body) {
+        return Some(ApiProtocol::Rest);
+    }
+    if !body.is_empty() && is_binary(&content_type, body) {
+        return Some(ApiProtocol::Binary);
+    }
+    None
+}
+
+/// Classify a transaction from its response, for requests that carried
+/// nothing to classify by
+pub fn classify_response(headers: &HashMap<String, String>, body: &[u8]) -> Option<ApiProtocol> {
+    let content_type = content_type(headers);
+
+    if crate::grpc_decode::is_grpc(&content_type) {
+        Some(ApiProtocol::Grpc)
+    } else if content_type == "application/graphql-response+json" {
+        Some(ApiProtocol::GraphQl)
+    } else if is_soap(&content_type, body) {
+        Some(ApiProtocol::Soap)
+    } else if is_json(&content_type, body) {
+        Some(ApiProtocol::Rest)
+    } else {
+        None
+    }
+}
+
+/// Lowercased media type without parameters
+fn content_type(headers: &HashMap<String, String>) -> String {
+    header(headers, "content-type")
+        .and_then(|v| v.split(';').next())
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase()
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Leading text of a body, whitespace trimmed, if it is UTF-8
+fn sniff(body: &[u8]) -> Option<&str> {
+    let head = &body[..body.len().min(SNIFF_LEN)];
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // Cut mid-character: keep the valid prefix
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    Some(text.trim_start())
+}
+
+fn is_graphql_request(url: &str, content_type: &str, body: &[u8]) -> bool {
+    if content_type == "application/graphql" {
+        return true;
+    }
+
+    // GET /graphql?query={...}
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (url, ""),
+    };
+    let graphql_path = path.trim_end_matches('/').to_ascii_lowercase().ends_with("graphql");
+    if graphql_path && query.split('&').any(|p| p.starts_with("query=") || p.starts_with("extensions=")) {
+        return true;
+    }
+
+    // POST {"query": "...", "variables": {...}}, single or batched
+    if !is_json(content_type, body) {
+        return false;
+    }
+    let value: serde_json::Value = match serde_json::from_slice(body) {
+        Ok(value) => value,
+        Err(_) => return false,
+    };
+    let operation = match &value {
+        serde_json::Value::Array(items) => items.first(),
+        other => Some(other),
+    };
+    operation.and_then(|op| op.as_object()).is_some_and(|op| {
+        op.get("query").is_some_and(|q| q.is_string())
+            || op.get("extensions").and_then(|e| e.get("persistedQuery")).is_some()
+    })
+}
+
+fn is_soap(content_type: &str, body: &[u8]) -> bool {
+    if content_type == "application/soap+xml" {
+        return true;
+    }
+    if !content_type.is_empty() && content_type != "text/xml" && content_type != "application/xml" {
+        return false;
+    }
+    let text = match sniff(body) {
+        Some(text) if text.starts_with('<') => text,
+        _ => return false,
+    };
+    text.contains("schemas.xmlsoap.org/soap/envelope")
+        || text.contains("www.w3.org/2003/05/soap-envelope")
+        || text.contains(":Envelope")
+}
+
+fn is_json(content_type: &str, body: &[u8]) -> bool {
+    if content_type == "application/json" || content_type.ends_with("+json") {
+        return true;
+    }
+    // Untyped or mistyped (text/plain) JSON bodies
+    if !content_type.is_empty() && content_type != "text/plain" {
+        return false;
+    }
+    matches!(sniff(body), Some(text) if text.starts_with('{') || text.starts_with('['))
+        && serde_json::from_slice::<serde_json::Value>(body).is_ok()
+}
+
+fn is_binary(content_type: &str, body: &[u8]) -> bool {
+    match content_type {
+        "application/octet-stream"
+        | "application/protobuf"
+        | "application/x-protobuf"
+        | "application/vnd.google.protobuf"
+        | "application/msgpack"
+        | "application/x-msgpack"
+        | "application/cbor"
+        | "application/x-thrift" => true,
+        "" => sniff(body).is_none() || body.contains(&0),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_classify_request() {
+        let json = headers(&[("Content-Type", "application/json; charset=utf-8")]);
+        assert_eq!(
+            classify_request("https://a.test/graphql", &json, br#"{"query":"{ me { id } }","variables":{}}"#),
+            Some(ApiProtocol::GraphQl)
+        );
+        assert_eq!(
+            classify_request("https://a.test/api", &json, br#"[{"operationName":"A","extensions":{"persistedQuery":{}}}]"#),
+            Some(ApiProtocol::GraphQl)
+        );
+        assert_eq!(
+            classify_request("https://a.test/api/users", &json, br#"{"name":"bob"}"#),
+            Some(ApiProtocol::Rest)
+        );
+        assert_eq!(
+            classify_request("https://a.test/graphql?query=%7Bme%7D", &HashMap::new(), b""),
+            Some(ApiProtocol::GraphQl)
+        );
+        assert_eq!(
+            classify_request("https://a.test/svc", &headers(&[("content-type", "application/grpc+proto")]), b"\0\0\0\0\0"),
+            Some(ApiProtocol::Grpc)
+        );
+        assert_eq!(
+            classify_request(
+                "https://a.test/ws",
+                &headers(&[("Content-Type", "text/xml")]),
+                br#"<?xml version="1.0"?><soap:Envelope xmlns:soap="http://schemas.xmlsoap.org/soap/envelope/"/>"#
+            ),
+            Some(ApiProtocol::Soap)
+        );
+        assert_eq!(
+            classify_request("https://a.test/ws", &headers(&[("SOAPAction", "\"urn:Get\"")]), b"<x/>"),
+            Some(ApiProtocol::Soap)
+        );
+        assert_eq!(
+            classify_request("https://a.test/up", &headers(&[("Content-Type", "multipart/form-data; boundary=x")]), b"--x"),
+            Some(ApiProtocol::Multipart)
+        );
+        assert_eq!(
+            classify_request("https://a.test/login", &headers(&[("Content-Type", "application/x-www-form-urlencoded")]), b"a=1"),
+            Some(ApiProtocol::Form)
+        );
+        assert_eq!(
+            classify_request("https://a.test/blob", &HashMap::new(), &[0x89, 0x50, 0x00, 0xff]),
+            Some(ApiProtocol::Binary)
+        );
+        assert_eq!(classify_request("https://a.test/", &HashMap::new(), b""), None);
+        assert_eq!(
+            classify_request("https://a.test/", &headers(&[("Content-Type", "text/plain")]), b"hello"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_classify_response() {
+        assert_eq!(
+            classify_response(&headers(&[("Content-Type", "application/problem+json")]), b"{}"),
+            Some(ApiProtocol::Rest)
+        );
+        assert_eq!(
+            classify_response(&headers(&[("Content-Type", "application/soap+xml")]), b""),
+            Some(ApiProtocol::Soap)
+        );
+        assert_eq!(
+            classify_response(&headers(&[("Content-Type", "text/html")]), b"<html></html>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for protocol in ApiProtocol::ALL {
+            assert_eq!(ApiProtocol::parse(protocol.as_str()), Some(protocol));
+        }
+        assert_eq!(ApiProtocol::parse("html"), None);
+    }
+}
@@ -0,0 +1,288 @@
+//! API Authentication
+//!
+//! Bearer-token authentication for the REST, GraphQL and WebSocket endpoints.
+//! Authentication is on when the server is started with an admin token. The
+//! admin token has full access and manages API keys; each API key belongs to
+//! a principal and acts as that user for project access checks. API keys are
+//! server-wide, so they live in their own database next to the projects, and
+//! only a SHA-256 of each key is stored.
+
+use axum::http::HeaderMap;
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Row, Sqlite};
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Prefix of generated API keys, to make leaked keys easy to spot
+pub const API_KEY_PREFIX: &str = "pxy_";
+
+/// `last_used_at` is refreshed at most this often per key
+const LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Missing API token")]
+    Missing,
+    #[error("Invalid or revoked API token")]
+    Invalid,
+    #[error("Auth database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Authenticated caller, attached to each request by the auth middleware
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Caller {
+    /// Holder of the admin token
+    Admin,
+    /// Holder of an API key, acting as its principal
+    ApiKey { id: String, principal: String },
+}
+
+/// API key metadata; the key itself is only returned once, on creation
+#[derive(Debug, Clone)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    pub name: String,
+    pub principal: String,
+    /// First characters of the key, for telling keys apart
+    pub prefix: String,
+    pub created_at: i64,
+    pub last_used_at: Option<i64>,
+    pub revoked_at: Option<i64>,
+}
+
+pub struct AuthService {
+    admin_token: Option<String>,
+    pool: Pool<Sqlite>,
+}
+
+impl AuthService {
+    /// Open (or create) the key database at `path`
+    pub async fn open(path: &Path, admin_token: Option<String>) -> Result<Self, AuthError> {
+        let options = SqliteConnectOptions::from_str(&format!("sqlite:{}", path.to_string_lossy()))?
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(2).connect_with(options).await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_keys (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                principal TEXT NOT NULL,
+                key_hash TEXT NOT NULL UNIQUE,
+                prefix TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                last_used_at INTEGER,
+                revoked_at INTEGER
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        Ok(Self {
+            admin_token: admin_token.filter(|t| !t.trim().is_empty()),
+            pool,
+        })
+    }
+
+    /// Whether requests must carry a token
+    pub fn enabled(&self) -> bool {
+        self.admin_token.is_some()
+    }
+
+    /// Round trip to the key database
+    pub async fn ping(&self) -> Result<(), AuthError> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Resolve a bearer token to its caller
+    pub async fn authenticate(&self, token: &str) -> Result<Caller, AuthError> {
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(AuthError::Missing);
+        }
+        if let Some(admin) = &self.admin_token {
+            if constant_time_eq(admin.as_bytes(), token.as_bytes()) {
+                return Ok(Caller::Admin);
+            }
+        }
+        if !token.starts_with(API_KEY_PREFIX) {
+            return Err(AuthError::Invalid);
+        }
+
+        let row = sqlx::query("SELECT id, principal FROM api_keys WHERE key_hash = ? AND revoked_at IS NULL")
+            .bind(hash_key(token))
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or(AuthError::Invalid)?;
+        let id: String = row.get("id");
+
+        let now = chrono::Utc::now().timestamp();
+        sqlx::query("UPDATE api_keys SET last_used_at = ? WHERE id = ? AND (last_used_at IS NULL OR last_used_at < ?)")
+            .bind(now)
+            .bind(&id)
+            .bind(now - LAST_USED_RESOLUTION_SECS)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(Caller::ApiKey {
+            id,
+            principal: row.get("principal"),
+        })
+    }
+
+    /// Create a key acting as `principal`. Returns the key, which is not stored.
+    pub async fn create_api_key(&self, name: &str, principal: &str) -> Result<(String, ApiKeyInfo), AuthError> {
+        let key = format!("{}{}{}", API_KEY_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let info = ApiKeyInfo {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            principal: principal.to_string(),
+            prefix: key[..API_KEY_PREFIX.len() + 8].to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            last_used_at: None,
+            revoked_at: None,
+        };
+
+        sqlx::query(
+            "INSERT INTO api_keys (id, name, principal, key_hash, prefix, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&info.id)
+        .bind(&info.name)
+        .bind(&info.principal)
+        .bind(hash_key(&key))
+        .bind(&info.prefix)
+        .bind(info.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok((key, info))
+    }
+
+    /// All keys, revoked ones included, newest first
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKeyInfo>, AuthError> {
+        let rows = sqlx::query(
+            "SELECT id, name, principal, prefix, created_at, last_used_at, revoked_at
+             FROM api_keys ORDER BY created_at DESC, id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ApiKeyInfo {
+                id: row.get("id"),
+                name: row.get("name"),
+                principal: row.get("principal"),
+                prefix: row.get("prefix"),
+                created_at: row.get("created_at"),
+                last_used_at: row.get("last_used_at"),
+                revoked_at: row.get("revoked_at"),
+            })
+            .collect())
+    }
+
+    /// Revoke a key; false if it does not exist or was already revoked
+    pub async fn revoke_api_key(&self, id: &str) -> Result<bool, AuthError> {
+        let result = sqlx::query("UPDATE api_keys SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(chrono::Utc::now().timestamp())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Token from an `Authorization: Bearer <token>` header
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(strip_bearer)
+}
+
+/// Token from an authorization value, with or without the "Bearer" scheme
+pub fn strip_bearer(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let token = match value.split_once(' ') {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        Some(_) => return None,
+        None if value.eq_ignore_ascii_case("bearer") => return None,
+        None => value,
+    };
+    Some(token).filter(|t| !t.is_empty())
+}
+
+fn hash_key(key: &str) -> String {
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn service(admin_token: Option<&str>) -> (AuthService, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let service = AuthService::open(&dir.path().join("server.db"), admin_token.map(str::to_string))
+            .await
+            .unwrap();
+        (service, dir)
+    }
+
+    #[tokio::test]
+    async fn test_admin_token() {
+        let (auth, _dir) = service(Some("s3cret")).await;
+        assert!(auth.enabled());
+        assert_eq!(auth.authenticate("s3cret").await.unwrap(), Caller::Admin);
+        assert!(matches!(auth.authenticate("s3cre").await, Err(AuthError::Invalid)));
+        assert!(matches!(auth.authenticate(" ").await, Err(AuthError::Missing)));
+
+        let (open, _dir) = service(Some("  ")).await;
+        assert!(!open.enabled());
+    }
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let (auth, _dir) = service(Some("admin")).await;
+        let (key, info) = auth.create_api_key("ci", "alice").await.unwrap();
+        assert!(key.starts_with(API_KEY_PREFIX) && key.starts_with(&info.prefix));
+
+        assert_eq!(
+            auth.authenticate(&key).await.unwrap(),
+            Caller::ApiKey {
+                id: info.id.clone(),
+                principal: "alice".to_string()
+            }
+        );
+        let listed = auth.list_api_keys().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].last_used_at.is_some());
+
+        assert!(auth.revoke_api_key(&info.id).await.unwrap());
+        assert!(!auth.revoke_api_key(&info.id).await.unwrap());
+        assert!(matches!(auth.authenticate(&key).await, Err(AuthError::Invalid)));
+    }
+
+    #[test]
+    fn test_bearer_parsing() {
+        assert_eq!(strip_bearer("Bearer abc"), Some("abc"));
+        assert_eq!(strip_bearer("bearer  abc "), Some("abc"));
+        assert_eq!(strip_bearer("abc"), Some("abc"));
+        assert_eq!(strip_bearer("Basic abc"), None);
+        assert_eq!(strip_bearer("Bearer "), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Artifact Blob Store
+//!
+//! Project-scoped, content-addressed storage for binary artifacts such as
+//! screenshots, large bodies, PoCs and exports. Blobs are named by the SHA-256
+//! of their content and stored under `<project>.proxxy/blobs/<2 hex>/<hash>`,
+//! so identical content is stored once.
+//!
+//! Rows that need a blob record a reference (`Database::add_blob_ref`). Garbage
+//! collection removes blobs nobody references, but only once they are older
+//! than a grace period: an upload is unreferenced until its owner row is saved.
+
+use crate::database::BlobRow;
+use crate::Database;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio_stream::{Stream, StreamExt};
+use tracing::{debug, info, warn};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Unreferenced blobs younger than this are kept by garbage collection
+pub const BLOB_GC_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// Interval between automatic garbage collection runs
+pub const BLOB_GC_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Blob store errors
+#[derive(Debug, thiserror::Error)]
+pub enum BlobError {
+    #[error("No active project loaded")]
+    NoProject,
+    #[error("Invalid blob hash: {0}")]
+    InvalidHash(String),
+    #[error("Blob not found: {0}")]
+    NotFound(String),
+    #[error("Blob I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Blob database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Metadata of a stored blob
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BlobInfo {
+    /// SHA-256 of the content, lowercase hex
+    pub hash: String,
+    pub size: i64,
+    pub content_type: Option<String>,
+    pub created_at: i64,
+    pub ref_count: i64,
+}
+
+impl From<BlobRow> for BlobInfo {
+    fn from(row: BlobRow) -> Self {
+        Self {
+            hash: row.hash,
+            size: row.size,
+            content_type: row.content_type,
+            created_at: row.created_at,
+            ref_count: row.ref_count,
+        }
+    }
+}
+
+/// Outcome of a garbage collection run
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct BlobGcReport {
+    pub blobs_removed: u64,
+    pub bytes_freed: u64,
+    /// Abandoned partial uploads removed
+    pub temp_files_removed: u64,
+}
+
+/// Content-addressed blob storage for the active project
+pub struct BlobStore {
+    db: Arc<Database>,
+}
+
+impl BlobStore {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+
+    /// Whether `hash` is a well-formed blob name (64 lowercase hex digits)
+    pub fn is_valid_hash(hash: &str) -> bool {
+        hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+    }
+
+    /// Store content read from a stream. Data is hashed while it is written,
+    /// so uploads of any size never have to be held in memory.
+    pub async fn put_stream<S, B, E>(&self, mut stream: S, content_type: Option<&str>) -> Result<BlobInfo, BlobError>
+    where
+        S: Stream<Item = Result<B, E>> + Unpin,
+        B: AsRef<[u8]>,
+        E: std::fmt::Display,
+    {
+        let root = self.root().await?;
+        let tmp_dir = root.join("tmp");
+        tokio::fs::create_dir_all(&tmp_dir).await?;
+        let tmp_path = tmp_dir.join(Uuid::new_v4().to_string());
+
+        let mut hasher = Sha256::new();
+        let mut size = 0u64;
+        let written: std::io::Result<()> = async {
+            let mut file = tokio::fs::File::create(&tmp_path).await?;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+                let chunk = chunk.as_ref();
+                hasher.update(chunk);
+                size += chunk.len() as u64;
+                file.write_all(chunk).await?;
+            }
+            file.sync_all().await
+        }
+        .await;
+
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(e.into());
+        }
+
+        let hash = format!("{:x}", hasher.finalize());
+        let path = blob_path(&root, &hash);
+        if tokio::fs::try_exists(&path).await? {
+            // Same content is already stored
+            tokio::fs::remove_file(&tmp_path).await?;
+        } else {
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            tokio::fs::rename(&tmp_path, &path).await?;
+        }
+
+        self.db.record_blob(&hash, size as i64, content_type).await?;
+        debug!("Stored blob {} ({} bytes)", hash, size);
+
+        self.info(&hash).await
+    }
+
+    /// Store an in-memory buffer
+    pub async fn put(&self, data: &[u8], content_type: Option<&str>) -> Result<BlobInfo, BlobError> {
+        self.put_stream(tokio_stream::iter([Ok::<_, std::io::Error>(data)]), content_type)
+            .await
+    }
+
+    /// Metadata of a stored blob
+    pub async fn info(&self, hash: &str) -> Result<BlobInfo, BlobError> {
+        validate_hash(hash)?;
+        self.db
+            .get_blob(hash)
+            .await?
+            .map(BlobInfo::from)
+            .ok_or_else(|| BlobError::NotFound(hash.to_string()))
+    }
+
+    /// Open a blob for streaming reads
+    pub async fn open(&self, hash: &str) -> Result<(BlobInfo, tokio::fs::File), BlobError> {
+        let info = self.info(hash).await?;
+        let path = blob_path(&self.root().await?, hash);
+
+        match tokio::fs::File::open(&path).await {
+            Ok(file) => Ok((info, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Err(BlobError::NotFound(hash.to_string())),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reference a blob from a row so garbage collection keeps it
+    pub async fn add_ref(&self, hash: &str, owner_kind: &str, owner_id: &str) -> Result<(), BlobError> {
+        self.info(hash).await?;
+        self.db.add_blob_ref(hash, owner_kind, owner_id).await?;
+        Ok(())
+    }
+
+    /// Drop a row's reference; the blob is collected once nothing references it
+    pub async fn remove_ref(&self, hash: &str, owner_kind: &str, owner_id: &str) -> Result<(), BlobError> {
+        validate_hash(hash)?;
+        self.db.remove_blob_ref(hash, owner_kind, owner_id).await?;
+        Ok(())
+    }
+
+    /// Remove unreferenced blobs and abandoned uploads older than `grace`
+    pub async fn gc(&self, grace: Duration) -> Result<BlobGcReport, BlobError> {
+        let root = self.root().await?;
+        let cutoff = chrono::Utc::now().timestamp() - grace.as_secs() as i64;
+        let mut report = BlobGcReport::default();
+
+        for blob in self.db.unreferenced_blobs(cutoff).await? {
+            // The record goes first so a concurrent add_ref cannot point at a deleted file
+            if !self.db.delete_unreferenced_blob(&blob.hash).await? {
+                continue;
+            }
+            match tokio::fs::remove_file(blob_path(&root, &blob.hash)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => warn!("Failed to remove blob {}: {}", blob.hash, e),
+            }
+            report.blobs_removed += 1;
+            report.bytes_freed += blob.size.max(0) as u64;
+        }
+
+        report.temp_files_removed = remove_stale_temp_files(&root.join("tmp"), grace).await;
+
+        if report.blobs_removed > 0 || report.temp_files_removed > 0 {
+            info!(
+                "🧹 Blob GC removed {} blob(s), {} bytes, {} partial upload(s)",
+                report.blobs_removed, report.bytes_freed, report.temp_files_removed
+            );
+        }
+        Ok(report)
+    }
+
+    async fn root(&self) -> Result<PathBuf, BlobError> {
+        self.db.blobs_dir().await.ok_or(BlobError::NoProject)
+    }
+}
+
+fn validate_hash(hash: &str) -> Result<(), BlobError> {
+    if BlobStore::is_valid_hash(hash) {
+        Ok(())
+    } else {
+        Err(BlobError::InvalidHash(hash.to_string()))
+    }
+}
+
+fn blob_path(root: &Path, hash: &str) -> PathBuf {
+    root.join(&hash[..2]).join(hash)
+}
+
+async fn remove_stale_temp_files(tmp_dir: &Path, grace: Duration) -> u64 {
+    let mut entries = match tokio::fs::read_dir(tmp_dir).await {
+        Ok(entries) => entries,
+        Err(_) => return 0,
+    };
+
+    let mut removed = 0;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let stale = entry
+            .metadata()
+            .await
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= grace);
+        if stale && tokio::fs::remove_file(entry.path()).await.is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn test_store() -> (BlobStore, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(temp_dir.path().to_str().unwrap()).await.unwrap());
+        db.create_project("blobs").await.unwrap();
+        db.load_project("blobs").await.unwrap();
+        (BlobStore::new(db), temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_content_addressing() {
+        let (store, _dir) = test_store().await;
+
+        let first = store.put(b"screenshot bytes", Some("image/png")).await.unwrap();
+        let second = store.put(b"screenshot bytes", None).await.unwrap();
+        assert_eq!(first.hash, second.hash);
+        assert_eq!(first.size, 16);
+        assert_eq!(second.content_type.as_deref(), Some("image/png"));
+
+        let (_, mut file) = store.open(&first.hash).await.unwrap();
+        let mut content = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(&mut file, &mut content).await.unwrap();
+        assert_eq!(content, b"screenshot bytes");
+
+        assert!(matches!(store.open("../../etc/passwd").await, Err(BlobError::InvalidHash(_))));
+        assert!(matches!(store.open(&"0".repeat(64)).await, Err(BlobError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_gc_keeps_referenced_blobs() {
+        let (store, _dir) = test_store().await;
+
+        let kept = store.put(b"referenced", None).await.unwrap();
+        let dropped = store.put(b"orphan", None).await.unwrap();
+        store.add_ref(&kept.hash, "flow_execution", "exec-1").await.unwrap();
+        assert_eq!(store.info(&kept.hash).await.unwrap().ref_count, 1);
+
+        // Fresh uploads are inside the grace period
+        assert_eq!(store.gc(BLOB_GC_GRACE).await.unwrap().blobs_removed, 0);
+
+        let report = store.gc(Duration::ZERO).await.unwrap();
+        assert_eq!(report.blobs_removed, 1);
+        assert_eq!(report.bytes_freed, 6);
+        assert!(store.open(&kept.hash).await.is_ok());
+        assert!(matches!(store.open(&dropped.hash).await, Err(BlobError::NotFound(_))));
+
+        store.remove_ref(&kept.hash, "flow_execution", "exec-1").await.unwrap();
+        assert_eq!(store.gc(Duration::ZERO).await.unwrap().blobs_removed, 1);
+    }
+}
@@ -0,0 +1,276 @@
+//! MITM CA Rotation
+//!
+//! Replaces the CA agents sign intercepted leaves with, for example at the
+//! end of an engagement or after a device that trusted it was lost. The new
+//! CA is pushed to every online agent over its command stream; agents that
+//! are offline get it when they next register. The previous certificate and
+//! key are moved to `<ca_dir>/archive/<fingerprint>` and kept for a grace
+//! period, so a rotation can still be undone by hand, then the key is
+//! deleted. Rotations are recorded in the server-wide database.
+//!
+//! A CA whose stored key is encrypted and was not unlocked at startup is
+//! unlocked here too, and pushed to agents the same way.
+
+use crate::agent_tls::cert_fingerprint;
+use crate::pb::{intercept_command, CaRotation as CaRotationCommand, InterceptCommand};
+use crate::AgentRegistry;
+use proxy_core::CertificateAuthority;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Row, Sqlite};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Archived keys are kept this long unless a rotation asks otherwise
+pub const DEFAULT_GRACE_PERIOD_HOURS: u32 = 72;
+
+#[derive(Debug, thiserror::Error)]
+pub enum CaRotationError {
+    #[error("CA archive I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("CA error: {0}")]
+    Certificate(#[from] proxy_core::ProxyError),
+    #[error("Rotation database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// One recorded rotation
+#[derive(Debug, Clone)]
+pub struct CaRotationRecord {
+    pub id: i64,
+    pub rotated_at: i64,
+    pub rotated_by: String,
+    /// SHA-256 of the certificates (DER)
+    pub previous_fingerprint: String,
+    pub new_fingerprint: String,
+    pub agents_online: u32,
+    /// Agents the new CA was sent to; the others get it on registration
+    pub agents_notified: u32,
+    /// The archived key is deleted after this time
+    pub grace_until: i64,
+    pub purged_at: Option<i64>,
+}
+
+pub struct CaRotationService {
+    ca: Arc<CertificateAuthority>,
+    ca_dir: PathBuf,
+    pool: Pool<Sqlite>,
+    /// Rotations run one at a time
+    rotating: tokio::sync::Mutex<()>,
+}
+
+impl CaRotationService {
+    /// Rotation history in `<projects_dir>/server.db` for the CA kept in
+    /// `ca_dir`. Archived keys past their grace period are deleted.
+    pub async fn open(
+        projects_dir: &Path,
+        ca: Arc<CertificateAuthority>,
+        ca_dir: &Path,
+    ) -> Result<Self, CaRotationError> {
+        let options = SqliteConnectOptions::from_str(&format!(
+            "sqlite:{}",
+            projects_dir.join("server.db").to_string_lossy()
+        ))?
+        .create_if_missing(true);
+        let pool = SqlitePoolOptions::new().max_connections(2).connect_with(options).await?;
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS ca_rotations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                rotated_at INTEGER NOT NULL,
+                rotated_by TEXT NOT NULL,
+                previous_fingerprint TEXT NOT NULL,
+                new_fingerprint TEXT NOT NULL,
+                agents_online INTEGER NOT NULL,
+                agents_notified INTEGER NOT NULL,
+                grace_until INTEGER NOT NULL,
+                purged_at INTEGER
+            )
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let service = Self {
+            ca,
+            ca_dir: ca_dir.to_path_buf(),
+            pool,
+            rotating: tokio::sync::Mutex::new(()),
+        };
+        service.purge_expired().await?;
+        Ok(service)
+    }
+
+    /// Generate a new CA, archive the current one for `grace_period_hours`
+    /// and send the new one to every online agent.
+    pub async fn rotate(
+        &self,
+        grace_period_hours: u32,
+        rotated_by: &str,
+        registry: &AgentRegistry,
+    ) -> Result<CaRotationRecord, CaRotationError> {
+        let _rotating = self.rotating.lock().await;
+
+        let previous_fingerprint = cert_fingerprint(&self.ca.get_ca_cert_der()?);
+        let archive_dir = self.archive_dir(&previous_fingerprint);
+        self.ca.rotate(&self.ca_dir, &archive_dir)?;
+        let new_fingerprint = cert_fingerprint(&self.ca.get_ca_cert_der()?);
+        info!("🔐 Rotated MITM CA {} -> {}", previous_fingerprint, new_fingerprint);
+
+        let (agents_online, agents_notified) = self.push_to_agents(registry).await?;
+
+        let rotated_at = chrono::Utc::now().timestamp();
+        let grace_until = rotated_at + i64::from(grace_period_hours) * 3600;
+        let result = sqlx::query(
+            "INSERT INTO ca_rotations (rotated_at, rotated_by, previous_fingerprint, new_fingerprint,
+             agents_online, agents_notified, grace_until) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(rotated_at)
+        .bind(rotated_by)
+        .bind(&previous_fingerprint)
+        .bind(&new_fingerprint)
+        .bind(agents_online as i64)
+        .bind(agents_notified as i64)
+        .bind(grace_until)
+        .execute(&self.pool)
+        .await?;
+
+        // A zero grace period deletes the archived key right away
+        self.purge_expired().await?;
+        let purged_at = (grace_until <= rotated_at).then_some(rotated_at);
+
+        Ok(CaRotationRecord {
+            id: result.last_insert_rowid(),
+            rotated_at,
+            rotated_by: rotated_by.to_string(),
+            previous_fingerprint,
+            new_fingerprint,
+            agents_online,
+            agents_notified,
+            grace_until,
+            purged_at,
+        })
+    }
+
+    /// Decrypt the stored CA key with `passphrase` and send the CA to every
+    /// online agent, which until now kept its own. Returns the number of
+    /// agents notified.
+    pub async fn unlock(&self, passphrase: &str, registry: &AgentRegistry) -> Result<u32, CaRotationError> {
+        let _rotating = self.rotating.lock().await;
+        self.ca.unlock(&self.ca_dir, passphrase)?;
+        info!("🔓 Unlocked the MITM CA key");
+        let (_, agents_notified) = self.push_to_agents(registry).await?;
+        Ok(agents_notified)
+    }
+
+    /// Send the current CA to the online agents; returns how many are online
+    /// and how many it was sent to
+    async fn push_to_agents(&self, registry: &AgentRegistry) -> Result<(u32, u32), CaRotationError> {
+        let command = InterceptCommand {
+            command: Some(intercept_command::Command::CaRotation(CaRotationCommand {
+                ca_cert_pem: self.ca.get_ca_cert_pem()?,
+                ca_key_pem: self.ca.get_ca_key_pem()?,
+            })),
+        };
+        let agents = registry.list_agents();
+        let mut agents_notified = 0;
+        for agent in &agents {
+            if agent.command_tx.send(Ok(command.clone())).await.is_ok() {
+                agents_notified += 1;
+            } else {
+                warn!("Failed to send CA to agent {}; it gets it on registration", agent.id);
+            }
+        }
+        Ok((agents.len() as u32, agents_notified))
+    }
+
+    /// All rotations, newest first
+    pub async fn history(&self) -> Result<Vec<CaRotationRecord>, CaRotationError> {
+        let rows = sqlx::query(
+            "SELECT id, rotated_at, rotated_by, previous_fingerprint, new_fingerprint, agents_online,
+             agents_notified, grace_until, purged_at FROM ca_rotations ORDER BY id DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| CaRotationRecord {
+                id: row.get("id"),
+                rotated_at: row.get("rotated_at"),
+                rotated_by: row.get("rotated_by"),
+                previous_fingerprint: row.get("previous_fingerprint"),
+                new_fingerprint: row.get("new_fingerprint"),
+                agents_online: row.get::<i64, _>("agents_online") as u32,
+                agents_notified: row.get::<i64, _>("agents_notified") as u32,
+                grace_until: row.get("grace_until"),
+                purged_at: row.get("purged_at"),
+            })
+            .collect())
+    }
+
+    /// Delete archived keys whose grace period is over; the archived
+    /// certificates stay. Returns the number deleted.
+    pub async fn purge_expired(&self) -> Result<usize, CaRotationError> {
+        let now = chrono::Utc::now().timestamp();
+        let rows = sqlx::query(
+            "SELECT id, previous_fingerprint FROM ca_rotations WHERE purged_at IS NULL AND grace_until <= ?",
+        )
+        .bind(now)
+        .fetch_all(&self.pool)
+        .await?;
+
+        for row in &rows {
+            let fingerprint: String = row.get("previous_fingerprint");
+            let key_path = self.archive_dir(&fingerprint).join("ca.key");
+            match std::fs::remove_file(&key_path) {
+                Ok(()) => info!("🔐 Deleted archived CA key {}", fingerprint),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            sqlx::query("UPDATE ca_rotations SET purged_at = ? WHERE id = ?")
+                .bind(now)
+                .bind(row.get::<i64, _>("id"))
+                .execute(&self.pool)
+                .await?;
+        }
+        Ok(rows.len())
+    }
+
+    fn archive_dir(&self, fingerprint: &str) -> PathBuf {
+        self.ca_dir.join("archive").join(fingerprint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rotation_is_archived_and_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let ca_dir = dir.path().join("certs");
+        let ca = Arc::new(CertificateAuthority::new(&ca_dir).unwrap());
+        let original = cert_fingerprint(&ca.get_ca_cert_der().unwrap());
+        let service = CaRotationService::open(dir.path(), ca.clone(), &ca_dir).await.unwrap();
+        let registry = AgentRegistry::new();
+
+        let kept = service.rotate(24, "admin", &registry).await.unwrap();
+        assert_eq!(kept.previous_fingerprint, original);
+        assert_eq!(kept.new_fingerprint, cert_fingerprint(&ca.get_ca_cert_der().unwrap()));
+        assert_eq!((kept.agents_online, kept.agents_notified, kept.purged_at), (0, 0, None));
+        assert!(ca_dir.join("archive").join(&original).join("ca.key").exists());
+
+        let purged = service.rotate(0, "alice", &registry).await.unwrap();
+        assert!(purged.purged_at.is_some());
+        let archive = ca_dir.join("archive").join(&purged.previous_fingerprint);
+        assert!(archive.join("ca.pem").exists());
+        assert!(!archive.join("ca.key").exists());
+
+        let history = service.history().await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].rotated_by, "alice");
+        assert!(history[0].purged_at.is_some() && history[1].purged_at.is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Body charset detection
+//!
+//! Captured bodies are stored as the bytes that went over the wire. For
+//! display, search and checks they are decoded to UTF-8 using, in order: a
+//! byte order mark, the `charset` parameter of Content-Type, a `<meta>`
+//! charset or `<?xml encoding?>` declaration near the start of the body, and
+//! finally UTF-8 itself. Bodies that are neither declared nor valid UTF-8 are
+//! treated as binary.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// How far into a body declarations are looked for (HTML requires `<meta
+/// charset>` within the first 1024 bytes)
+const DECLARATION_SCAN: usize = 1024;
+
+/// Text form of a body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedBody<'a> {
+    pub text: Cow<'a, str>,
+    /// WHATWG name of the charset used, e.g. "Shift_JIS"
+    pub charset: &'static str,
+    /// Bytes that did not decode were replaced with U+FFFD
+    pub lossy: bool,
+}
+
+fn content_type(headers: Option<&HashMap<String, String>>) -> Option<&str> {
+    headers?
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.as_str())
+}
+
+/// Charset named by a Content-Type value, if it is one we know
+pub fn charset_from_content_type(content_type: &str) -> Option<&'static Encoding> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        Encoding::for_label(value.trim().trim_matches(|c| c == '"' || c == '\'').as_bytes())
+    })
+}
+
+/// Value following `key` up to the next quote, space, `;`, `/` or `>`
+fn declared_value<'a>(head: &'a str, key: &str) -> Option<&'a str> {
+    let start = head.find(key)? + key.len();
+    let value = head[start..].trim_start_matches(|c: char| c == '"' || c == '\'' || c.is_whitespace());
+    let end = value
+        .find(|c: char| c == '"' || c == '\'' || c == ';' || c == '/' || c == '>' || c.is_whitespace())
+        .unwrap_or(value.len());
+    Some(&value[..end])
+}
+
+/// Charset declared inside the document: `<meta charset>`, `<meta
+/// http-equiv content="...; charset=">` or an XML declaration
+pub fn charset_from_document(body: &[u8]) -> Option<&'static Encoding> {
+    // Declarations are ASCII in every charset we would find them in
+    let head: String = body[..body.len().min(DECLARATION_SCAN)]
+        .iter()
+        .map(|&b| if b.is_ascii() { b.to_ascii_lowercase() as char } else { ' ' })
+        .collect();
+
+    if head.trim_start().starts_with("<?xml") {
+        let decl_end = head.find("?>").unwrap_or(head.len());
+        if let Some(label) = declared_value(&head[..decl_end], "encoding=") {
+            return Encoding::for_label(label.as_bytes());
+        }
+    }
+
+    let mut rest = head.as_str();
+    while let Some(pos) = rest.find("<meta") {
+        rest = &rest[pos + 5..];
+        let tag = &rest[..rest.find('>').unwrap_or(rest.len())];
+        if let Some(encoding) = declared_value(tag, "charset=").and_then(|label| Encoding::for_label(label.as_bytes())) {
+            // A UTF-16 declaration in an ASCII-readable document is wrong; HTML treats it as UTF-8
+            return Some(if encoding == UTF_16LE || encoding == UTF_16BE { UTF_8 } else { encoding });
+        }
+    }
+    None
+}
+
+/// Charset for a body with the given headers, or None when nothing is
+/// declared
+pub fn detect_charset(headers: Option<&HashMap<String, String>>, body: &[u8]) -> Option<&'static Encoding> {
+    if let Some((encoding, _)) = Encoding::for_bom(body) {
+        return Some(encoding);
+    }
+    content_type(headers)
+        .and_then(charset_from_content_type)
+        .or_else(|| charset_from_document(body))
+}
+
+/// Decode `body` as text. None for undeclared bodies that are not UTF-8,
+/// which callers show as binary.
+pub fn decode_body<'a>(headers: Option<&HashMap<String, String>>, body: &'a [u8]) -> Option<DecodedBody<'a>> {
+    match detect_charset(headers, body) {
+        Some(encoding) => {
+            let (text, used, lossy) = encoding.decode(body);
+            Some(DecodedBody {
+                text,
+                charset: used.name(),
+                lossy,
+            })
+        }
+        None => std::str::from_utf8(body).ok().map(|text| DecodedBody {
+            text: Cow::Borrowed(text),
+            charset: UTF_8.name(),
+            lossy: false,
+        }),
+    }
+}
+
+/// Decode `body` as text, replacing what does not decode. For search and
+/// pattern matching, where binary noise does no harm.
+pub fn decode_body_lossy<'a>(headers: Option<&HashMap<String, String>>, body: &'a [u8]) -> Cow<'a, str> {
+    let encoding = detect_charset(headers, body).unwrap_or(UTF_8);
+    encoding.decode(body).0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(content_type: &str) -> HashMap<String, String> {
+        HashMap::from([("Content-Type".to_string(), content_type.to_string())])
+    }
+
+    #[test]
+    fn test_content_type_charset() {
+        // "café" in ISO-8859-1 (decoded as windows-1252, per WHATWG)
+        let body = b"caf\xe9";
+        let decoded = decode_body(Some(&headers("text/plain; charset=\"ISO-8859-1\"")), body).unwrap();
+        assert_eq!(decoded.text, "café");
+        assert_eq!(decoded.charset, "windows-1252");
+        assert!(!decoded.lossy);
+
+        // "日本" in Shift_JIS
+        let body = b"\x93\xfa\x96\x7b";
+        let decoded = decode_body(Some(&headers("text/html;charset=shift_jis")), body).unwrap();
+        assert_eq!(decoded.text, "日本");
+        assert_eq!(decoded.charset, "Shift_JIS");
+    }
+
+    #[test]
+    fn test_document_declarations() {
+        let html = b"<html><head><META http-equiv=\"Content-Type\" content=\"text/html; charset=Shift_JIS\"></head>\x93\xfa\x96\x7b";
+        assert!(decode_body(Some(&headers("text/html")), html).unwrap().text.ends_with("日本"));
+
+        let html = b"<!doctype html><meta charset=iso-8859-2><p>\xb1";
+        assert_eq!(decode_body(None, html).unwrap().text, "<!doctype html><meta charset=iso-8859-2><p>ą");
+
+        let xml = b"<?xml version=\"1.0\" encoding='EUC-JP'?><a>\xc6\xfc</a>";
+        assert_eq!(detect_charset(None, xml), Some(encoding_rs::EUC_JP));
+        assert_eq!(decode_body(None, xml).unwrap().text, "<?xml version=\"1.0\" encoding='EUC-JP'?><a>日</a>");
+    }
+
+    #[test]
+    fn test_undeclared_bodies() {
+        assert_eq!(decode_body(None, "ümlaut".as_bytes()).unwrap().text, "ümlaut");
+        assert!(matches!(decode_body(None, "plain".as_bytes()).unwrap().text, Cow::Borrowed(_)));
+        assert!(decode_body(None, &[0x89, b'P', b'N', b'G', 0xff]).is_none());
+        assert_eq!(decode_body_lossy(None, b"a\xffb"), "a\u{fffd}b");
+
+        // The byte order mark wins over a wrong header
+        let bom = b"\xff\xfeh\x00i\x00";
+        assert_eq!(decode_body(Some(&headers("text/plain; charset=latin1")), bom).unwrap().text, "hi");
+    }
+}
@@ -0,0 +1,265 @@
+//! Traffic coloring rules
+//!
+//! Rules give incoming transactions a highlight color and tags when all of
+//! their conditions match, e.g. a red highlight for server errors or an
+//! `admin` tag for URLs under `/admin`. They are evaluated once the response
+//! is stored, so the request list shows the interesting traffic without any
+//! manual triage. The rules are a project setting.
+
+use crate::database::annotations::{normalize_tags, HighlightColor};
+use crate::models::settings::VersionedSetting;
+use crate::pb::{HttpRequestData, HttpResponseData};
+use crate::Database;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+/// Only the start of larger bodies is searched
+pub const MAX_MATCHED_BODY: usize = 1024 * 1024;
+
+/// Coloring rules of a project, stored under the `coloring` setting
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct ColoringConfig {
+    pub rules: Vec<ColoringRule>,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl VersionedSetting for ColoringConfig {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColoringRule {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    /// All conditions have to match
+    pub conditions: Vec<ColoringCondition>,
+    /// One of the highlight colors (`red`, `orange`, ...)
+    pub color: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum ColoringCondition {
+    /// Response status at or above `status`, e.g. 500 for server errors
+    StatusAtLeast { status: i32 },
+    StatusIn { statuses: Vec<i32> },
+    /// Case-insensitive substring of the request URL
+    UrlContains { pattern: String },
+    Method { methods: Vec<String> },
+    /// Case-insensitive substring of the decoded response body
+    ResponseContains { text: String },
+    /// Case-insensitive substring of a response header value
+    HeaderMatch { header: String, value: String },
+}
+
+/// Color and tags the rules give a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColoringOutcome {
+    pub color: Option<HighlightColor>,
+    pub tags: Vec<String>,
+}
+
+impl ColoringConfig {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.rules {
+            if rule.conditions.is_empty() {
+                return Err(format!("Coloring rule '{}' has no conditions", rule.name));
+            }
+            if let Some(color) = &rule.color {
+                if HighlightColor::from_tag(color).is_none() {
+                    return Err(format!("Coloring rule '{}' has unknown color '{}'", rule.name, color));
+                }
+            }
+            if rule.color.is_none() && normalize_tags(&rule.tags).is_empty() {
+                return Err(format!("Coloring rule '{}' sets neither a color nor tags", rule.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.rules.iter().any(|rule| rule.enabled)
+    }
+
+    /// Outcome of the enabled rules for a transaction. The first matching
+    /// rule with a color decides the color; tags of all matching rules add up.
+    pub fn evaluate(&self, request: &HttpRequestData, response: &HttpResponseData) -> ColoringOutcome {
+        let mut outcome = ColoringOutcome::default();
+        let mut body = None;
+        for rule in self.rules.iter().filter(|rule| rule.enabled) {
+            let matched = rule.conditions.iter().all(|condition| {
+                condition.matches(request, response, || {
+                    body.get_or_insert_with(|| {
+                        let matched = &response.body[..response.body.len().min(MAX_MATCHED_BODY)];
+                        crate::charset::decode_body_lossy(response.headers.as_ref().map(|h| &h.headers), matched)
+                            .to_lowercase()
+                    })
+                    .clone()
+                })
+            });
+            if !matched {
+                continue;
+            }
+            if outcome.color.is_none() {
+                outcome.color = rule.color.as_deref().and_then(HighlightColor::from_tag);
+            }
+            outcome.tags.extend(rule.tags.iter().cloned());
+        }
+        outcome.tags = normalize_tags(&outcome.tags);
+        outcome
+    }
+}
+
+impl ColoringCondition {
+    /// `body` yields the lowercased response body, decoded on first use
+    fn matches(&self, request: &HttpRequestData, response: &HttpResponseData, body: impl FnOnce() -> String) -> bool {
+        match self {
+            ColoringCondition::StatusAtLeast { status } => response.status_code >= *status,
+            ColoringCondition::StatusIn { statuses } => statuses.contains(&response.status_code),
+            ColoringCondition::UrlContains { pattern } => {
+                request.url.to_lowercase().contains(&pattern.to_lowercase())
+            }
+            ColoringCondition::Method { methods } => methods.iter().any(|m| m.eq_ignore_ascii_case(&request.method)),
+            ColoringCondition::ResponseContains { text } => body().contains(&text.to_lowercase()),
+            ColoringCondition::HeaderMatch { header, value } => response.headers.as_ref().is_some_and(|headers| {
+                headers.headers.iter().any(|(name, v)| {
+                    name.eq_ignore_ascii_case(header) && v.to_lowercase().contains(&value.to_lowercase())
+                })
+            }),
+        }
+    }
+}
+
+/// Apply the project's coloring rules to the stored transaction `request_id`.
+/// A color set by hand (or by an earlier rule) is left alone; tags are added.
+pub async fn apply(db: &Database, request_id: &str, response: &HttpResponseData) {
+    let config = match db.get_coloring_config().await {
+        Ok(config) if config.is_active() => config,
+        Ok(_) => return,
+        Err(e) => {
+            warn!("Failed to load coloring rules: {}", e);
+            return;
+        }
+    };
+    let request = match db.get_request_by_id(request_id).await {
+        Ok(Some((_, request))) => request,
+        Ok(None) => {
+            debug!("No stored request {} to color", request_id);
+            return;
+        }
+        Err(e) => {
+            warn!("Failed to load request {} for coloring: {}", request_id, e);
+            return;
+        }
+    };
+
+    let outcome = config.evaluate(&request, response);
+    let result = async {
+        if let Some(color) = outcome.color {
+            let current = db.get_request_annotation(request_id).await?;
+            if current.is_some_and(|annotation| annotation.color.is_none()) {
+                db.set_request_color(request_id, Some(color)).await?;
+            }
+        }
+        if !outcome.tags.is_empty() {
+            db.add_request_tags(request_id, &outcome.tags).await?;
+        }
+        Ok::<_, sqlx::Error>(())
+    };
+    if let Err(e) = result.await {
+        warn!("Failed to color {}: {}", request_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::HttpHeaders;
+
+    fn rule(name: &str, conditions: Vec<ColoringCondition>, color: Option<&str>, tags: &[&str]) -> ColoringRule {
+        ColoringRule {
+            id: name.to_string(),
+            name: name.to_string(),
+            enabled: true,
+            conditions,
+            color: color.map(str::to_string),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+
+    fn transaction(method: &str, url: &str, status: i32, body: &str) -> (HttpRequestData, HttpResponseData) {
+        let request = HttpRequestData {
+            method: method.to_string(),
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let response = HttpResponseData {
+            status_code: status,
+            headers: Some(HttpHeaders {
+                headers: [("Content-Type".to_string(), "text/html; charset=utf-8".to_string())].into(),
+            }),
+            body: body.as_bytes().to_vec().into(),
+            ..Default::default()
+        };
+        (request, response)
+    }
+
+    #[test]
+    fn test_matching_rules_color_and_tag() {
+        let config = ColoringConfig {
+            rules: vec![
+                rule("errors", vec![ColoringCondition::StatusAtLeast { status: 500 }], Some("red"), &["server-error"]),
+                rule("admin", vec![ColoringCondition::UrlContains { pattern: "/Admin".into() }], Some("purple"), &["admin"]),
+                rule(
+                    "traces",
+                    vec![
+                        ColoringCondition::ResponseContains { text: "Exception".into() },
+                        ColoringCondition::HeaderMatch { header: "content-type".into(), value: "HTML".into() },
+                    ],
+                    None,
+                    &["exception"],
+                ),
+            ],
+            version: 0,
+        };
+        assert!(config.validate().is_ok());
+
+        let (req, res) = transaction("GET", "https://app.test/admin/users", 500, "java.lang.NullPointerException");
+        let outcome = config.evaluate(&req, &res);
+        // The first matching rule picks the color
+        assert_eq!(outcome.color, Some(HighlightColor::Red));
+        assert_eq!(outcome.tags, vec!["server-error", "admin", "exception"]);
+
+        let (req, res) = transaction("GET", "https://app.test/home", 200, "ok");
+        assert_eq!(config.evaluate(&req, &res), ColoringOutcome::default());
+
+        let mut disabled = config.clone();
+        disabled.rules.iter_mut().for_each(|rule| rule.enabled = false);
+        let (req, res) = transaction("POST", "https://app.test/admin", 503, "");
+        assert_eq!(disabled.evaluate(&req, &res), ColoringOutcome::default());
+        assert_eq!(config.evaluate(&req, &res).color, Some(HighlightColor::Red));
+    }
+
+    #[test]
+    fn test_validate() {
+        let status = || vec![ColoringCondition::StatusIn { statuses: vec![401, 403] }];
+        let unknown_color = ColoringConfig { rules: vec![rule("x", status(), Some("teal"), &[])], version: 0 };
+        assert!(unknown_color.validate().is_err());
+        let no_effect = ColoringConfig { rules: vec![rule("x", status(), None, &[" "])], version: 0 };
+        assert!(no_effect.validate().is_err());
+        let no_conditions = ColoringConfig { rules: vec![rule("x", vec![], Some("red"), &[])], version: 0 };
+        assert!(no_conditions.validate().is_err());
+    }
+}
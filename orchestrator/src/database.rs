@@ -1,5 +1,5 @@
 use crate::pb::{traffic_event, SystemMetricsEvent, TrafficEvent};
-use crate::models::settings::{ScopeConfig, InterceptionConfig};
+use crate::models::settings::{ScopeConfig, InterceptionConfig, VersionedSetting};
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Row, Sqlite};
 use std::path::{PathBuf};
 use std::sync::Arc;
@@ -12,10 +12,52 @@ use serde::{Serialize, de::DeserializeOwned};
 pub mod repeater;
 pub mod intruder;
 pub mod flow;
+pub mod crawl;
+pub mod blobs;
+pub mod access;
+pub mod engagement;
+pub mod archive;
+pub mod sync;
+pub mod grpc;
+pub mod protocols;
+pub mod chains;
+pub mod site_map;
+pub mod annotations;
+pub mod passive;
+pub mod burp;
+pub mod har;
+pub mod search;
+pub mod connections;
+pub mod views;
+pub mod discovery;
+pub mod tampering;
+pub mod usage;
 
 pub use repeater::*;
 pub use intruder::*;
 pub use flow::*;
+pub use crawl::*;
+pub use blobs::*;
+pub use protocols::*;
+pub use chains::{ChainLink, ChainRow};
+pub use annotations::{AnnotatedTransaction, HighlightColor, RequestAnnotation};
+pub use passive::PassiveFindingRow;
+pub use connections::ConnectionEventRow;
+pub use discovery::DiscoveryResultRow;
+pub use tampering::TamperFindingRow;
+pub use usage::{AgentUsageRow, UsageDelta};
+pub use site_map::{SiteMapEntry, SiteMapNode, StatusCounts};
+pub use archive::{ArchiveError, ArchiveManifest, ExportOptions};
+pub use burp::{BurpImportError, BurpImportSummary};
+
+/// Errors from saving versioned settings
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("'{key}' settings were changed by someone else (expected version {expected}, current {current}); reload and retry")]
+    VersionConflict { key: String, expected: u64, current: u64 },
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
 
 #[derive(Debug, Clone)]
 pub struct Project {
@@ -33,10 +75,64 @@ pub struct AgentInfo {
     pub version: String,
 }
 
+/// Row of the traffic list
+#[derive(Debug, Clone)]
+pub struct RecentRequest {
+    pub agent_id: String,
+    pub event: TrafficEvent,
+    pub status: Option<i32>,
+    pub api_protocol: Option<String>,
+    /// Transaction this one followed as a redirect or retry
+    pub parent_id: Option<String>,
+    pub annotation: RequestAnnotation,
+}
+
+/// Traffic list row from a query selecting the request columns with
+/// `res_status`, `api_protocol`, `parent_id` and the annotation columns
+pub(crate) fn recent_request(row: &sqlx::sqlite::SqliteRow) -> RecentRequest {
+    let request_id: String = row.get("request_id");
+    let agent_id: String = row.get("agent_id");
+    let method: String = row.get("req_method");
+    let url: String = row.get("req_url");
+    let headers_json: Option<String> = row.get("req_headers");
+    let body: Vec<u8> = row.get::<Option<Vec<u8>>, _>("req_body").unwrap_or_default();
+    let tls_json: Option<String> = row.get("tls_info");
+
+    // Imported transactions may lack these columns
+    let headers: Option<crate::pb::HttpHeaders> =
+        headers_json.and_then(|json| serde_json::from_str(&json).ok()).flatten();
+    let tls: Option<crate::pb::TlsDetails> = tls_json.and_then(|json| serde_json::from_str(&json).ok()).flatten();
+
+    RecentRequest {
+        agent_id,
+        event: TrafficEvent {
+            request_id,
+            event: Some(traffic_event::Event::Request(crate::pb::HttpRequestData {
+                method,
+                url,
+                headers,
+                body: body.into(),
+                tls,
+            })),
+        },
+        status: row.get("res_status"),
+        api_protocol: row.get("api_protocol"),
+        parent_id: row.get("parent_id"),
+        annotation: RequestAnnotation::from_row(row),
+    }
+}
+
 /// Full HTTP transaction with both request and response data
 #[derive(Debug, Clone)]
 pub struct FullTransaction {
     pub agent_id: String,
+    /// Source IP the target saw, if the agent reported one
+    pub egress_ip: Option<String>,
+    /// API protocol tag (`rest`, `graphql`, ...), if the transaction was classified
+    pub api_protocol: Option<String>,
+    /// Transaction this one followed as a redirect or retry
+    pub parent_id: Option<String>,
+    pub annotation: RequestAnnotation,
     pub request: crate::pb::HttpRequestData,
     pub response: Option<crate::pb::HttpResponseData>,
 }
@@ -198,6 +294,57 @@ impl Database {
         Ok(())
     }
 
+    /// Directory holding persistent browser profiles for the active project
+    pub async fn browser_profiles_dir(&self) -> Option<PathBuf> {
+        let active = self.active_project.read().await.clone()?;
+        Some(
+            self.projects_dir
+                .join(format!("{}.proxxy", active))
+                .join("browser-profiles"),
+        )
+    }
+
+    /// Directory holding screenshots of failed replay steps for the active project
+    pub async fn replay_screenshots_dir(&self) -> Option<PathBuf> {
+        let active = self.active_project.read().await.clone()?;
+        Some(
+            self.projects_dir
+                .join(format!("{}.proxxy", active))
+                .join("replay-screenshots"),
+        )
+    }
+
+    /// Directory holding custom passive check definitions for the active project
+    pub async fn passive_checks_dir(&self) -> Option<PathBuf> {
+        let active = self.active_project.read().await.clone()?;
+        Some(
+            self.projects_dir
+                .join(format!("{}.proxxy", active))
+                .join("passive-checks"),
+        )
+    }
+
+    /// Directory holding hook scripts for the active project
+    pub async fn hook_scripts_dir(&self) -> Option<PathBuf> {
+        let active = self.active_project.read().await.clone()?;
+        Some(
+            self.projects_dir
+                .join(format!("{}.proxxy", active))
+                .join("hook-scripts"),
+        )
+    }
+
+    /// Name of the active project
+    pub async fn active_project_name(&self) -> Option<String> {
+        self.active_project.read().await.clone()
+    }
+
+    /// Directory holding artifact blobs for the active project
+    pub async fn blobs_dir(&self) -> Option<PathBuf> {
+        let active = self.active_project.read().await.clone()?;
+        Some(self.projects_dir.join(format!("{}.proxxy", active)).join("blobs"))
+    }
+
     pub async fn get_pool(&self) -> Result<Pool<Sqlite>, Box<dyn std::error::Error>> {
         let guard = self.pool.read().await;
         if let Some(pool) = guard.as_ref() {
@@ -211,6 +358,15 @@ impl Database {
         self.pool.read().await.clone()
     }
 
+    /// Round trip to the active project's database; without a loaded
+    /// project there is nothing to reach, which is fine
+    pub async fn ping(&self) -> Result<(), sqlx::Error> {
+        match self.pool().await {
+            Some(pool) => sqlx::query("SELECT 1").execute(&pool).await.map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
     pub async fn upsert_agent(
         &self,
         id: &str,
@@ -287,6 +443,52 @@ impl Database {
         Ok(())
     }
 
+    /// Record the egress IP an agent reported. Returns whether it changed;
+    /// changes are kept in the agent's egress log.
+    pub async fn record_agent_egress(&self, agent_id: &str, ip: &str) -> Result<bool, sqlx::Error> {
+        let pool = match self.get_pool().await {
+             Ok(p) => p,
+             Err(_) => return Ok(false),
+        };
+
+        let changed = sqlx::query("UPDATE agents SET egress_ip = ? WHERE id = ? AND egress_ip IS NOT ?")
+            .bind(ip)
+            .bind(agent_id)
+            .bind(ip)
+            .execute(&pool)
+            .await?
+            .rows_affected()
+            > 0;
+
+        if changed {
+            sqlx::query("INSERT INTO agent_egress_log (agent_id, ip, observed_at) VALUES (?, ?, ?)")
+                .bind(agent_id)
+                .bind(ip)
+                .bind(chrono::Utc::now().timestamp())
+                .execute(&pool)
+                .await?;
+        }
+        Ok(changed)
+    }
+
+    /// Egress IPs an agent has used, newest first, as (ip, first seen)
+    pub async fn get_agent_egress_history(&self, agent_id: &str, limit: i64) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+             Ok(p) => p,
+             Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT ip, observed_at FROM agent_egress_log WHERE agent_id = ? ORDER BY id DESC LIMIT ?",
+        )
+        .bind(agent_id)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(|r| (r.get("ip"), r.get("observed_at"))).collect())
+    }
+
     pub async fn save_request(
         &self,
         event: &TrafficEvent,
@@ -316,12 +518,26 @@ impl Database {
                     return Ok(());
                 }
 
+                let empty = std::collections::HashMap::new();
+                let api_protocol = crate::api_protocol::classify_request(
+                    &req.url,
+                    req.headers.as_ref().map(|h| &h.headers).unwrap_or(&empty),
+                    &req.body,
+                );
+
+                let (parent_id, parent_link) =
+                    match Self::find_chain_parent(&pool, agent_id, &req.method, &req.url, &req.body, timestamp).await? {
+                        Some((id, link)) => (Some(id), Some(link.as_str())),
+                        None => (None, None),
+                    };
+
                 sqlx::query(
                     r#"
                     INSERT INTO http_transactions (
-                        request_id, agent_id, req_method, req_url, req_headers, req_body, req_timestamp, tls_info
+                        request_id, agent_id, req_method, req_url, req_headers, req_body, req_timestamp, tls_info, egress_ip, api_protocol,
+                        parent_id, parent_link
                     )
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, (SELECT egress_ip FROM agents WHERE id = ?), ?, ?, ?)
                     "#
                 )
                 .bind(&event.request_id)
@@ -329,30 +545,57 @@ impl Database {
                 .bind(&req.method)
                 .bind(&req.url)
                 .bind(headers_json)
-                .bind(&req.body)
+                .bind(req.body.as_ref())
                 .bind(timestamp)
                 .bind(tls_json)
+                .bind(agent_id)
+                .bind(api_protocol.map(|p| p.as_str()))
+                .bind(parent_id)
+                .bind(parent_link)
                 .execute(&pool)
                 .await?;
             }
             Some(traffic_event::Event::Response(res)) => {
                 let headers_json = serde_json::to_string(&res.headers).unwrap_or_default();
                 let timestamp = chrono::Utc::now().timestamp();
-
+                let empty = std::collections::HashMap::new();
+                let res_headers = res.headers.as_ref().map(|h| &h.headers).unwrap_or(&empty);
+                let api_protocol = crate::api_protocol::classify_response(res_headers, &res.body);
+                let redirect_url =
+                    Self::redirect_url_for(&pool, &event.request_id, res.status_code, res_headers).await?;
+                let declared_type = crate::mime_sniff::declared_type(res_headers);
+                let detected_type = crate::mime_sniff::sniff(&res.body);
+                let timing_json = res.timing.as_ref().and_then(|t| serde_json::to_string(t).ok());
+
+                // The request's own tag wins; the response only tags bodiless calls
                 sqlx::query(
                     r#"
                     UPDATE http_transactions SET
                         res_status = ?,
                         res_headers = ?,
                         res_body = ?,
-                        res_timestamp = ?
+                        res_timestamp = ?,
+                        res_protocol = ?,
+                        api_protocol = COALESCE(api_protocol, ?),
+                        redirect_url = ?,
+                        res_declared_type = ?,
+                        res_detected_type = ?,
+                        timing = ?,
+                        duration_ms = ?
                     WHERE request_id = ?
                     "#,
                 )
                 .bind(res.status_code)
                 .bind(headers_json)
-                .bind(&res.body)
+                .bind(res.body.as_ref())
                 .bind(timestamp)
+                .bind(Some(&res.protocol).filter(|p| !p.is_empty()))
+                .bind(api_protocol.map(|p| p.as_str()))
+                .bind(redirect_url)
+                .bind(declared_type)
+                .bind(detected_type)
+                .bind(timing_json)
+                .bind(res.timing.as_ref().map(|t| (t.total_us / 1000) as i64))
                 .bind(&event.request_id)
                 .execute(&pool)
                 .await?;
@@ -364,64 +607,80 @@ impl Database {
         Ok(())
     }
 
-    pub async fn get_recent_requests(&self, agent_id: Option<&str>, limit: i64) -> Result<Vec<(String, TrafficEvent, Option<i32>)>, sqlx::Error> {
-        self.get_recent_requests_paginated(agent_id, limit, 0).await
+    pub async fn get_recent_requests(&self, agent_id: Option<&str>, limit: i64) -> Result<Vec<RecentRequest>, sqlx::Error> {
+        self.get_recent_requests_paginated(agent_id, None, None, limit, 0).await
     }
 
+    /// Newest transactions first, optionally narrowed to one agent, one
+    /// API protocol (`rest`, `graphql`, ...) and/or one tag
     pub async fn get_recent_requests_paginated(
         &self, 
         agent_id: Option<&str>, 
+        api_protocol: Option<&str>,
+        tag: Option<&str>,
         limit: i64, 
         offset: i64
-    ) -> Result<Vec<(String, TrafficEvent, Option<i32>)>, sqlx::Error> {
+    ) -> Result<Vec<RecentRequest>, sqlx::Error> {
         let pool = match self.get_pool().await {
              Ok(p) => p,
              Err(_) => return Ok(Vec::new()),
         };
 
-        let query = if let Some(aid) = agent_id {
-            sqlx::query(
-                "SELECT request_id, agent_id, req_method, req_url, req_headers, req_body, tls_info, res_status FROM http_transactions WHERE agent_id = ? ORDER BY req_timestamp DESC LIMIT ? OFFSET ?"
-            )
-            .bind(aid)
-            .bind(limit)
-            .bind(offset)
-        } else {
-            sqlx::query(
-                "SELECT request_id, agent_id, req_method, req_url, req_headers, req_body, tls_info, res_status FROM http_transactions ORDER BY req_timestamp DESC LIMIT ? OFFSET ?"
-            )
-            .bind(limit)
-            .bind(offset)
-        };
-
-        let rows = query.fetch_all(&pool).await?;
+        let rows = sqlx::query(
+            r#"SELECT request_id, agent_id, req_method, req_url, req_headers, req_body, tls_info, res_status, api_protocol, parent_id,
+                comment, highlight_color, tags
+            FROM http_transactions
+            WHERE (? IS NULL OR agent_id = ?) AND (? IS NULL OR api_protocol = ?)
+              AND (? IS NULL OR EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?))
+            ORDER BY req_timestamp DESC LIMIT ? OFFSET ?"#
+        )
+        .bind(agent_id)
+        .bind(agent_id)
+        .bind(api_protocol)
+        .bind(api_protocol)
+        .bind(tag)
+        .bind(tag)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&pool)
+        .await?;
 
-        let mut results = Vec::new();
-        for row in rows {
-            let request_id: String = row.get("request_id");
-            let agent_id: String = row.get("agent_id");
-            let method: String = row.get("req_method");
-            let url: String = row.get("req_url");
-            let headers_json: String = row.get("req_headers");
-            let body: Vec<u8> = row.get("req_body");
-            let tls_json: String = row.get("tls_info");
-            let res_status: Option<i32> = row.get("res_status");
+        Ok(rows.iter().map(recent_request).collect())
+    }
 
-            let headers: Option<crate::pb::HttpHeaders> = serde_json::from_str(&headers_json).ok();
-            let tls: Option<crate::pb::TlsDetails> = serde_json::from_str(&tls_json).ok();
+    /// Total length and bytes `offset..offset + length` of a stored body,
+    /// read without loading the rest. None if the transaction does not exist.
+    pub async fn body_range(
+        &self,
+        request_id: &str,
+        part: crate::hex_view::BodyPart,
+        offset: u64,
+        length: u64,
+    ) -> Result<Option<(u64, Vec<u8>)>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(None),
+        };
+        let column = match part {
+            crate::hex_view::BodyPart::Request => "req_body",
+            crate::hex_view::BodyPart::Response => "res_body",
+        };
+        // substr() counts bytes on BLOBs, from 1
+        let row = sqlx::query(&format!(
+            "SELECT length({0}) AS total, substr({0}, ?, ?) AS chunk FROM http_transactions WHERE request_id = ?",
+            column
+        ))
+        .bind(offset as i64 + 1)
+        .bind(length as i64)
+        .bind(request_id)
+        .fetch_optional(&pool)
+        .await?;
 
-            results.push((agent_id, TrafficEvent {
-                request_id,
-                event: Some(traffic_event::Event::Request(crate::pb::HttpRequestData {
-                    method,
-                    url,
-                    headers,
-                    body,
-                    tls,
-                })),
-            }, res_status));
-        }
-        Ok(results)
+        Ok(row.map(|row| {
+            let total: Option<i64> = row.get("total");
+            let chunk: Option<Vec<u8>> = row.get("chunk");
+            (total.unwrap_or(0) as u64, chunk.unwrap_or_default())
+        }))
     }
 
     pub async fn get_request_by_id(
@@ -454,7 +713,7 @@ impl Database {
                 method,
                 url,
                 headers,
-                body,
+                body: body.into(),
                 tls,
             })))
         } else {
@@ -558,9 +817,10 @@ impl Database {
         };
         let row = sqlx::query(
             r#"SELECT 
-                agent_id,
+                agent_id, egress_ip, api_protocol, parent_id,
+                comment, highlight_color, tags,
                 req_method, req_url, req_headers, req_body, tls_info,
-                res_status, res_headers, res_body
+                res_status, res_headers, res_body, res_protocol, timing
             FROM http_transactions 
             WHERE request_id = ?"#
         )
@@ -585,7 +845,7 @@ impl Database {
                 method,
                 url,
                 headers: req_headers,
-                body: req_body,
+                body: req_body.into(),
                 tls,
             };
             
@@ -602,8 +862,12 @@ impl Database {
                 Some(crate::pb::HttpResponseData {
                     status_code: res_status.unwrap_or(0),
                     headers: res_headers,
-                    body: res_body.unwrap_or_default(),
+                    body: res_body.unwrap_or_default().into(),
                     tls: None,
+                    timing: row
+                        .get::<Option<String>, _>("timing")
+                        .and_then(|json| serde_json::from_str(&json).ok()),
+                    protocol: row.get::<Option<String>, _>("res_protocol").unwrap_or_default(),
                 })
             } else {
                 None
@@ -611,6 +875,10 @@ impl Database {
 
             Ok(Some(FullTransaction {
                 agent_id,
+                egress_ip: row.get("egress_ip"),
+                api_protocol: row.get("api_protocol"),
+                parent_id: row.get("parent_id"),
+                annotation: RequestAnnotation::from_row(&row),
                 request,
                 response,
             }))
@@ -979,14 +1247,88 @@ impl Database {
         Ok(())
     }
 
+    /// Save a versioned setting only if the stored copy is still at `expected_version`
+    /// (compare-and-swap), so concurrent editors cannot silently overwrite each other.
+    /// Returns the saved value with its version incremented.
+    pub async fn save_versioned_setting<T>(
+        &self,
+        key: &str,
+        value: &T,
+        expected_version: u64,
+    ) -> Result<T, SettingsError>
+    where
+        T: Serialize + VersionedSetting + Clone,
+    {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::NotConnected, e.to_string()))
+        })?;
+
+        let mut saved = value.clone();
+        saved.set_version(expected_version + 1);
+        let value_json = serde_json::to_string(&saved)
+            .map_err(|e| sqlx::Error::Protocol(e.to_string()))?;
+        let timestamp = chrono::Utc::now().timestamp();
+
+        // Settings saved before versioning existed count as version 0
+        let result = if expected_version == 0 {
+            sqlx::query(
+                r#"
+                INSERT INTO project_settings (key, value, updated_at)
+                VALUES (?, ?, ?)
+                ON CONFLICT(key) DO UPDATE SET
+                    value = excluded.value,
+                    updated_at = excluded.updated_at
+                WHERE COALESCE(json_extract(project_settings.value, '$.version'), 0) = 0
+                "#
+            )
+            .bind(key)
+            .bind(&value_json)
+            .bind(timestamp)
+            .execute(&pool)
+            .await?
+        } else {
+            sqlx::query(
+                r#"
+                UPDATE project_settings SET value = ?, updated_at = ?
+                WHERE key = ? AND COALESCE(json_extract(value, '$.version'), 0) = ?
+                "#
+            )
+            .bind(&value_json)
+            .bind(timestamp)
+            .bind(key)
+            .bind(expected_version as i64)
+            .execute(&pool)
+            .await?
+        };
+
+        if result.rows_affected() == 0 {
+            let current = self
+                .get_setting::<serde_json::Value>(key)
+                .await?
+                .and_then(|v| v.get("version").and_then(|v| v.as_u64()))
+                .unwrap_or(0);
+            return Err(SettingsError::VersionConflict {
+                key: key.to_string(),
+                expected: expected_version,
+                current,
+            });
+        }
+
+        Ok(saved)
+    }
+
     /// Get scope configuration
     pub async fn get_scope_config(&self) -> Result<ScopeConfig, sqlx::Error> {
         Ok(self.get_setting("scope").await?.unwrap_or_default())
     }
 
-    /// Save scope configuration
-    pub async fn save_scope_config(&self, config: &ScopeConfig) -> Result<(), sqlx::Error> {
-        self.save_setting("scope", config).await
+    /// Save scope configuration if nobody saved it since `expected_version` was read
+    pub async fn save_scope_config(
+        &self,
+        config: &ScopeConfig,
+        expected_version: u64,
+    ) -> Result<ScopeConfig, SettingsError> {
+        self.save_versioned_setting("scope", config, expected_version).await
     }
 
     /// Get interception configuration
@@ -994,92 +1336,105 @@ impl Database {
         Ok(self.get_setting("interception").await?.unwrap_or_default())
     }
 
-    /// Save interception configuration
-    pub async fn save_interception_config(&self, config: &InterceptionConfig) -> Result<(), sqlx::Error> {
-        self.save_setting("interception", config).await
+    /// Save interception configuration if nobody saved it since `expected_version` was read
+    pub async fn save_interception_config(
+        &self,
+        config: &InterceptionConfig,
+        expected_version: u64,
+    ) -> Result<InterceptionConfig, SettingsError> {
+        self.save_versioned_setting("interception", config, expected_version).await
     }
 
-    // ============================================================================
-    // PROJECT IMPORT/EXPORT (.proxxy format)
-    // ============================================================================
+    /// Get the traffic coloring rules
+    pub async fn get_coloring_config(&self) -> Result<crate::coloring::ColoringConfig, sqlx::Error> {
+        Ok(self.get_setting("coloring").await?.unwrap_or_default())
+    }
 
-    /// Export project to .proxxy file (ZIP archive)
-    pub async fn export_project(&self, name: &str, output_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        use std::io::Write;
-        
-        let folder_name = format!("{}.proxxy", name);
-        let project_path = self.projects_dir.join(folder_name);
-        if !project_path.exists() {
-            return Err("Project does not exist".into());
-        }
+    /// Save the traffic coloring rules if nobody saved them since `expected_version` was read
+    pub async fn save_coloring_config(
+        &self,
+        config: &crate::coloring::ColoringConfig,
+        expected_version: u64,
+    ) -> Result<crate::coloring::ColoringConfig, SettingsError> {
+        self.save_versioned_setting("coloring", config, expected_version).await
+    }
 
-        let db_path = project_path.join("proxxy.db");
-        if !db_path.exists() {
-            return Err("Project database not found".into());
-        }
+    /// Get the session handling macros
+    pub async fn get_session_macros(&self) -> Result<crate::session_integration::macros::SessionMacroConfig, sqlx::Error> {
+        Ok(self.get_setting("session_macros").await?.unwrap_or_default())
+    }
 
-        // Create ZIP archive
-        let file = std::fs::File::create(output_path)?;
-        let mut zip = zip::ZipWriter::new(file);
-        
-        let options: zip::write::FileOptions<'_, ()> = zip::write::FileOptions::default()
-            .compression_method(zip::CompressionMethod::Deflated);
-
-        // Add database file
-        zip.start_file("proxxy.db", options)?;
-        let db_content = std::fs::read(&db_path)?;
-        zip.write_all(&db_content)?;
-
-        // Add metadata
-        let metadata = serde_json::json!({
-            "name": name,
-            "exported_at": chrono::Utc::now().to_rfc3339(),
-            "version": "1.0"
-        });
-        zip.start_file("metadata.json", options)?;
-        zip.write_all(serde_json::to_string_pretty(&metadata)?.as_bytes())?;
-
-        zip.finish()?;
-        info!("✓ Exported project '{}' to {}", name, output_path);
-        Ok(())
+    /// Save the session handling macros if nobody saved them since `expected_version` was read
+    pub async fn save_session_macros(
+        &self,
+        config: &crate::session_integration::macros::SessionMacroConfig,
+        expected_version: u64,
+    ) -> Result<crate::session_integration::macros::SessionMacroConfig, SettingsError> {
+        self.save_versioned_setting("session_macros", config, expected_version).await
     }
 
-    /// Import project from .proxxy file
-    pub async fn import_project(&self, proxxy_path: &str, project_name: Option<&str>) -> Result<String, Box<dyn std::error::Error>> {
-        use std::io::Read;
-        
-        let file = std::fs::File::open(proxxy_path)?;
-        let mut archive = zip::ZipArchive::new(file)?;
-
-        // Read metadata
-        let mut metadata_file = archive.by_name("metadata.json")?;
-        let mut metadata_content = String::new();
-        metadata_file.read_to_string(&mut metadata_content)?;
-        drop(metadata_file); // Release borrow
-        
-        let metadata: serde_json::Value = serde_json::from_str(&metadata_content)?;
-        
-        let original_name = metadata["name"].as_str().ok_or("Invalid metadata")?;
-        let final_name = project_name.unwrap_or(original_name);
+    /// Get the default Repeater/Intruder agent and its fallbacks
+    pub async fn get_default_agent_config(&self) -> Result<crate::models::settings::DefaultAgentConfig, sqlx::Error> {
+        Ok(self.get_setting("default_agent").await?.unwrap_or_default())
+    }
 
-        // Create project directory with .proxxy extension
-        let folder_name = format!("{}.proxxy", final_name);
-        let project_path = self.projects_dir.join(folder_name);
-        if project_path.exists() {
-            return Err(format!("Project '{}' already exists", final_name).into());
-        }
-        fs::create_dir_all(&project_path)?;
+    /// Save the default agent if nobody saved it since `expected_version` was read
+    pub async fn save_default_agent_config(
+        &self,
+        config: &crate::models::settings::DefaultAgentConfig,
+        expected_version: u64,
+    ) -> Result<crate::models::settings::DefaultAgentConfig, SettingsError> {
+        self.save_versioned_setting("default_agent", config, expected_version).await
+    }
 
-        // Extract database
-        let mut db_file = archive.by_name("proxxy.db")?;
-        let mut db_content = Vec::new();
-        db_file.read_to_end(&mut db_content)?;
-        
-        let db_path = project_path.join("proxxy.db");
-        std::fs::write(&db_path, db_content)?;
+    /// Get DNS resolver configuration pushed to agents at registration
+    pub async fn get_dns_config(&self) -> Result<proxy_core::DnsConfig, sqlx::Error> {
+        Ok(self.get_setting("dns").await?.unwrap_or_default())
+    }
+
+    /// Save DNS resolver configuration
+    pub async fn save_dns_config(&self, config: &proxy_core::DnsConfig) -> Result<(), sqlx::Error> {
+        self.save_setting("dns", config).await
+    }
+
+    /// Get hosts agents resolve to a fixed address, pushed at registration
+    pub async fn get_host_overrides(&self) -> Result<std::collections::HashMap<String, std::net::IpAddr>, sqlx::Error> {
+        Ok(self.get_setting("host_overrides").await?.unwrap_or_default())
+    }
+
+    /// Save host overrides
+    pub async fn save_host_overrides(&self, overrides: &std::collections::HashMap<String, std::net::IpAddr>) -> Result<(), sqlx::Error> {
+        self.save_setting("host_overrides", overrides).await
+    }
+
+    /// Get client certificates presented to upstreams, pushed at registration
+    pub async fn get_client_certificates(&self) -> Result<Vec<proxy_core::ClientCertificateConfig>, sqlx::Error> {
+        Ok(self.get_setting("client_certificates").await?.unwrap_or_default())
+    }
+
+    /// Save client certificates
+    pub async fn save_client_certificates(&self, certificates: &[proxy_core::ClientCertificateConfig]) -> Result<(), sqlx::Error> {
+        self.save_setting("client_certificates", &certificates).await
+    }
+
+    /// Get per-host TLS policies for agent upstream connections
+    pub async fn get_tls_policies(&self) -> Result<Vec<proxy_core::TlsPolicyConfig>, sqlx::Error> {
+        Ok(self.get_setting("tls_policies").await?.unwrap_or_default())
+    }
+
+    /// Save TLS policies
+    pub async fn save_tls_policies(&self, policies: &[proxy_core::TlsPolicyConfig]) -> Result<(), sqlx::Error> {
+        self.save_setting("tls_policies", &policies).await
+    }
+
+    /// Get masking policy for recorded flow inputs
+    pub async fn get_masking_policy(&self) -> Result<flow_engine::MaskingPolicy, sqlx::Error> {
+        Ok(self.get_setting("flow_masking").await?.unwrap_or_default())
+    }
 
-        info!("✓ Imported project '{}' from {}", final_name, proxxy_path);
-        Ok(final_name.to_string())
+    /// Save masking policy for recorded flow inputs
+    pub async fn save_masking_policy(&self, policy: &flow_engine::MaskingPolicy) -> Result<(), sqlx::Error> {
+        self.save_setting("flow_masking", policy).await
     }
 }
 
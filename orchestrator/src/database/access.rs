@@ -0,0 +1,66 @@
+//! Database operations for project access lists
+
+use crate::models::access::{ProjectMember, ProjectRole};
+use sqlx::Row;
+
+impl super::Database {
+    /// Members of the active project's access list
+    pub async fn list_project_members(&self) -> Result<Vec<ProjectMember>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query("SELECT principal, role, added_at FROM project_members ORDER BY added_at ASC")
+            .fetch_all(&pool)
+            .await?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let role: String = row.get("role");
+                Some(ProjectMember {
+                    principal: row.get("principal"),
+                    role: ProjectRole::parse(&role)?,
+                    added_at: row.get("added_at"),
+                })
+            })
+            .collect())
+    }
+
+    /// Add a member or change its role
+    pub async fn set_project_member(&self, principal: &str, role: ProjectRole) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO project_members (principal, role, added_at)
+            VALUES (?, ?, ?)
+            ON CONFLICT(principal) DO UPDATE SET role = excluded.role
+            "#,
+        )
+        .bind(principal)
+        .bind(role.as_str())
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a member. Returns whether it was on the list.
+    pub async fn remove_project_member(&self, principal: &str) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query("DELETE FROM project_members WHERE principal = ?")
+            .bind(principal)
+            .execute(&pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
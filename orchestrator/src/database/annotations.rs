@@ -0,0 +1,371 @@
+//! Database operations for request annotations
+//!
+//! Triage marks on a transaction: a free-text comment, a highlight color and
+//! any number of tags. Tags are stored as a JSON array in `tags` and matched
+//! with `json_each`. They travel with the data into HAR exports, project
+//! archives and the Markdown notes report.
+
+use serde::Serialize;
+use sqlx::{Pool, Row, Sqlite};
+
+/// Highlight colors offered in the UI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Purple,
+    Pink,
+    Gray,
+}
+
+impl HighlightColor {
+    /// Value stored in `http_transactions.highlight_color`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HighlightColor::Red => "red",
+            HighlightColor::Orange => "orange",
+            HighlightColor::Yellow => "yellow",
+            HighlightColor::Green => "green",
+            HighlightColor::Cyan => "cyan",
+            HighlightColor::Blue => "blue",
+            HighlightColor::Purple => "purple",
+            HighlightColor::Pink => "pink",
+            HighlightColor::Gray => "gray",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "red" => Some(HighlightColor::Red),
+            "orange" => Some(HighlightColor::Orange),
+            "yellow" => Some(HighlightColor::Yellow),
+            "green" => Some(HighlightColor::Green),
+            "cyan" => Some(HighlightColor::Cyan),
+            "blue" => Some(HighlightColor::Blue),
+            "purple" => Some(HighlightColor::Purple),
+            "pink" => Some(HighlightColor::Pink),
+            "gray" => Some(HighlightColor::Gray),
+            _ => None,
+        }
+    }
+}
+
+/// Comment, color and tags of a transaction
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestAnnotation {
+    pub comment: Option<String>,
+    pub color: Option<HighlightColor>,
+    pub tags: Vec<String>,
+}
+
+impl RequestAnnotation {
+    /// From the `comment`, `highlight_color` and `tags` columns of `row`
+    pub(crate) fn from_row(row: &sqlx::sqlite::SqliteRow) -> Self {
+        Self {
+            comment: row.get("comment"),
+            color: row
+                .get::<Option<String>, _>("highlight_color")
+                .as_deref()
+                .and_then(HighlightColor::from_tag),
+            tags: row
+                .get::<Option<String>, _>("tags")
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// A transaction with at least one annotation, as exported
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnnotatedTransaction {
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    /// Unix seconds of the request
+    pub timestamp: i64,
+    pub status: Option<i32>,
+    pub comment: Option<String>,
+    pub color: Option<&'static str>,
+    pub tags: Vec<String>,
+}
+
+/// Annotated transactions in `pool`, oldest first, optionally only those
+/// carrying `tag`. Takes a pool so archive snapshots can be read too.
+pub(crate) async fn annotated_transactions(
+    pool: &Pool<Sqlite>,
+    tag: Option<&str>,
+) -> Result<Vec<AnnotatedTransaction>, sqlx::Error> {
+    let rows = sqlx::query(
+        r#"
+        SELECT request_id, req_method, req_url, req_timestamp, res_status, comment, highlight_color, tags
+        FROM http_transactions
+        WHERE (comment IS NOT NULL OR highlight_color IS NOT NULL OR tags IS NOT NULL)
+          AND (? IS NULL OR EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?))
+        ORDER BY req_timestamp, request_id
+        "#,
+    )
+    .bind(tag)
+    .bind(tag)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let annotation = RequestAnnotation::from_row(row);
+            AnnotatedTransaction {
+                request_id: row.get("request_id"),
+                method: row.get("req_method"),
+                url: row.get("req_url"),
+                timestamp: row.get("req_timestamp"),
+                status: row.get("res_status"),
+                comment: annotation.comment,
+                color: annotation.color.map(|c| c.as_str()),
+                tags: annotation.tags,
+            }
+        })
+        .collect())
+}
+
+/// Markdown report of the analyst notes on `project`'s transactions
+pub fn render_annotation_report(project: &str, transactions: &[AnnotatedTransaction]) -> String {
+    let mut report = format!("# Analyst notes: {}\n\n", project);
+    if transactions.is_empty() {
+        report.push_str("No annotated requests.\n");
+        return report;
+    }
+    report.push_str(&format!("{} annotated request(s).\n", transactions.len()));
+
+    for tx in transactions {
+        report.push_str(&format!("\n## {} {}\n\n", tx.method, tx.url));
+        let time = chrono::DateTime::from_timestamp(tx.timestamp, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        let status = tx.status.map(|s| s.to_string()).unwrap_or_else(|| "none".to_string());
+        report.push_str(&format!("- Request: `{}` at {} (status {})\n", tx.request_id, time, status));
+        if let Some(color) = tx.color {
+            report.push_str(&format!("- Highlight: {}\n", color));
+        }
+        if !tx.tags.is_empty() {
+            report.push_str(&format!("- Tags: {}\n", tx.tags.join(", ")));
+        }
+        if let Some(comment) = &tx.comment {
+            report.push('\n');
+            for line in comment.lines() {
+                report.push_str(&format!("> {}\n", line));
+            }
+        }
+    }
+    report
+}
+
+/// Trimmed, non-empty, deduplicated tags in the order given
+pub fn normalize_tags<I, S>(tags: I) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.as_ref().trim();
+        if !tag.is_empty() && !normalized.iter().any(|t| t == tag) {
+            normalized.push(tag.to_string());
+        }
+    }
+    normalized
+}
+
+impl super::Database {
+    /// Annotation of `request_id`, or `None` if there is no such transaction
+    pub async fn get_request_annotation(&self, request_id: &str) -> Result<Option<RequestAnnotation>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(None),
+        };
+
+        let row = sqlx::query("SELECT comment, highlight_color, tags FROM http_transactions WHERE request_id = ?")
+            .bind(request_id)
+            .fetch_optional(&pool)
+            .await?;
+        Ok(row.as_ref().map(RequestAnnotation::from_row))
+    }
+
+    /// Set or clear (`None` or blank) the comment of `request_id`.
+    /// Returns false if there is no such transaction.
+    pub async fn set_request_comment(&self, request_id: &str, comment: Option<&str>) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        let comment = comment.map(str::trim).filter(|c| !c.is_empty());
+
+        let result = sqlx::query("UPDATE http_transactions SET comment = ? WHERE request_id = ?")
+            .bind(comment)
+            .bind(request_id)
+            .execute(&pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear the highlight color of `request_id`.
+    /// Returns false if there is no such transaction.
+    pub async fn set_request_color(&self, request_id: &str, color: Option<HighlightColor>) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query("UPDATE http_transactions SET highlight_color = ? WHERE request_id = ?")
+            .bind(color.map(|c| c.as_str()))
+            .bind(request_id)
+            .execute(&pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace the tags of `request_id`. Returns false if there is no such transaction.
+    pub async fn set_request_tags(&self, request_id: &str, tags: &[String]) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        let tags = normalize_tags(tags);
+        let json = if tags.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&tags).unwrap_or_default())
+        };
+
+        let result = sqlx::query("UPDATE http_transactions SET tags = ? WHERE request_id = ?")
+            .bind(json)
+            .bind(request_id)
+            .execute(&pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Add `tags` to those of `request_id`, keeping the existing ones first.
+    /// Returns false if there is no such transaction.
+    pub async fn add_request_tags(&self, request_id: &str, tags: &[String]) -> Result<bool, sqlx::Error> {
+        let Some(annotation) = self.get_request_annotation(request_id).await? else {
+            return Ok(false);
+        };
+        let merged = normalize_tags(annotation.tags.iter().chain(tags));
+        if merged == annotation.tags {
+            return Ok(true);
+        }
+        self.set_request_tags(request_id, &merged).await
+    }
+
+    /// Annotated transactions of the active project, oldest first
+    pub async fn list_annotated_transactions(&self, tag: Option<&str>) -> Result<Vec<AnnotatedTransaction>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+        annotated_transactions(&pool, tag).await
+    }
+
+    /// Every tag in use with the number of transactions carrying it, most used first
+    pub async fn list_request_tags(&self) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT tag.value AS tag, COUNT(*) AS hits
+            FROM http_transactions, json_each(http_transactions.tags) AS tag
+            WHERE http_transactions.tags IS NOT NULL
+            GROUP BY tag.value
+            ORDER BY hits DESC, tag.value
+            "#,
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| (row.get("tag"), row.get("hits"))).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::{traffic_event, HttpRequestData, TrafficEvent};
+    use crate::Database;
+    use tempfile::TempDir;
+
+    async fn request(db: &Database, id: &str) {
+        let event = TrafficEvent {
+            request_id: id.to_string(),
+            event: Some(traffic_event::Event::Request(HttpRequestData {
+                method: "GET".to_string(),
+                url: format!("https://a.test/{}", id),
+                ..Default::default()
+            })),
+        };
+        db.save_request(&event, "agent-1").await.unwrap();
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        assert_eq!(normalize_tags([" idor ", "", "sqli", "idor"]), vec!["idor", "sqli"]);
+    }
+
+    #[tokio::test]
+    async fn test_annotate_and_filter_by_tag() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+        request(&db, "r1").await;
+        request(&db, "r2").await;
+        request(&db, "r3").await;
+
+        assert!(db.set_request_comment("r1", Some("  admin panel ")).await.unwrap());
+        assert!(db.set_request_color("r1", Some(HighlightColor::Red)).await.unwrap());
+        assert!(db.set_request_tags("r1", &["idor".to_string(), "auth".to_string()]).await.unwrap());
+        assert!(db.set_request_tags("r2", &["idor".to_string()]).await.unwrap());
+        assert!(!db.set_request_comment("missing", Some("x")).await.unwrap());
+
+        let annotation = db.get_request_annotation("r1").await.unwrap().unwrap();
+        assert_eq!(annotation.comment.as_deref(), Some("admin panel"));
+        assert_eq!(annotation.color, Some(HighlightColor::Red));
+        assert_eq!(annotation.tags, vec!["idor", "auth"]);
+        assert_eq!(db.get_request_annotation("r3").await.unwrap().unwrap(), RequestAnnotation::default());
+
+        let tagged = db.get_recent_requests_paginated(None, None, Some("idor"), 10, 0).await.unwrap();
+        let mut ids: Vec<_> = tagged.iter().map(|r| r.event.request_id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["r1", "r2"]);
+        assert_eq!(tagged.iter().find(|r| r.event.request_id == "r2").unwrap().annotation.tags, vec!["idor"]);
+
+        assert_eq!(db.list_request_tags().await.unwrap(), vec![("idor".to_string(), 2), ("auth".to_string(), 1)]);
+
+        assert!(db.add_request_tags("r2", &["idor".to_string(), "script".to_string()]).await.unwrap());
+        assert_eq!(db.get_request_annotation("r2").await.unwrap().unwrap().tags, vec!["idor", "script"]);
+        assert!(!db.add_request_tags("missing", &["x".to_string()]).await.unwrap());
+
+        let annotated = db.list_annotated_transactions(None).await.unwrap();
+        let ids: Vec<_> = annotated.iter().map(|t| t.request_id.as_str()).collect();
+        assert_eq!(ids.len(), 2);
+        assert!(!ids.contains(&"r3"));
+        let r1 = annotated.iter().find(|t| t.request_id == "r1").unwrap();
+        assert_eq!(r1.color, Some("red"));
+        assert_eq!(db.list_annotated_transactions(Some("auth")).await.unwrap().len(), 1);
+
+        let report = render_annotation_report("p", &annotated);
+        assert!(report.starts_with("# Analyst notes: p\n\n2 annotated request(s).\n"));
+        assert!(report.contains("## GET https://a.test/r1\n"));
+        assert!(report.contains("- Highlight: red\n- Tags: idor, auth\n\n> admin panel\n"));
+
+        db.set_request_comment("r1", None).await.unwrap();
+        db.set_request_color("r1", None).await.unwrap();
+        db.set_request_tags("r1", &[]).await.unwrap();
+        assert_eq!(db.get_request_annotation("r1").await.unwrap().unwrap(), RequestAnnotation::default());
+        assert_eq!(db.get_recent_requests_paginated(None, None, Some("auth"), 10, 0).await.unwrap().len(), 0);
+    }
+}
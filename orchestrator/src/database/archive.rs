@@ -0,0 +1,755 @@
+//! Project archives (.proxxy files)
+//!
+//! Format v2 is a ZIP container of zstd-compressed entries:
+//!
+//! - `manifest.json`: format version, export selection and the SHA-256 of each entry
+//! - `proxxy.db.zst`: consistent SQLite snapshot with traffic bodies stripped
+//! - `bodies.jsonl.zst`: the body chunks of every transaction, in order
+//! - `chunks/<sha256>.zst`: body chunks, stored once however often they occur
+//! - `blobs/<sha256>.zst`: artifact blobs
+//! - `annotations.json`: comments, highlights and tags of the exported
+//!   transactions, readable without importing (the database keeps them too)
+//!
+//! Chunks and blobs are named by the hash of their content and verified on
+//! import. Readers ignore manifest fields they do not know; a writer raises
+//! `min_reader_version` only when older readers must refuse an archive.
+//! Version 1 archives (plain database plus `metadata.json`) still import.
+
+use super::{Database, ScopeRule};
+use crate::blob_store::BlobStore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use tokio_stream::StreamExt;
+use tracing::info;
+
+pub const ARCHIVE_FORMAT: &str = "proxxy-archive";
+
+/// Archive format written by this build, and the newest one it can read
+pub const ARCHIVE_FORMAT_VERSION: u32 = 2;
+
+/// Bodies are split into chunks of this size for deduplication
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
+
+const ZSTD_LEVEL: i32 = 3;
+
+const DB_ENTRY: &str = "proxxy.db.zst";
+const BODY_INDEX_ENTRY: &str = "bodies.jsonl.zst";
+const ANNOTATIONS_ENTRY: &str = "annotations.json";
+
+/// Project archive errors
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Project '{0}' does not exist")]
+    ProjectNotFound(String),
+    #[error("Project '{0}' already exists")]
+    ProjectExists(String),
+    #[error("Not a valid .proxxy archive: {0}")]
+    InvalidArchive(String),
+    #[error("Archive requires reader version {required}; this build reads up to version {supported}")]
+    UnsupportedVersion { required: u32, supported: u32 },
+    #[error("Integrity check failed for archive entry {0}")]
+    Integrity(String),
+    #[error("Archive I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Archive ZIP error: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Archive database error: {0}")]
+    Database(#[from] sqlx::Error),
+    #[error("Archive metadata error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What an export includes. The selection applies to captured traffic;
+/// everything else in the project is always exported.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Only traffic captured at or after this Unix timestamp
+    pub since: Option<i64>,
+    /// Only traffic captured at or before this Unix timestamp
+    pub until: Option<i64>,
+    /// Only traffic matching the project's scope rules
+    #[serde(default)]
+    pub scope_only: bool,
+}
+
+/// Archive entry with its integrity hash
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    pub path: String,
+    /// SHA-256 of the stored (compressed) bytes, lowercase hex
+    pub sha256: String,
+    pub size: u64,
+}
+
+/// Contents of `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    pub format: String,
+    pub format_version: u32,
+    /// Oldest reader version able to import the archive
+    pub min_reader_version: u32,
+    pub name: String,
+    pub exported_at: String,
+    pub producer: String,
+    pub selection: ExportOptions,
+    pub transactions: u64,
+    /// Distinct body chunks stored
+    pub body_chunks: u64,
+    /// Chunk references across all bodies; the gap to `body_chunks` is what deduplication saved
+    pub body_chunk_refs: u64,
+    pub blobs: u64,
+    /// Transactions with a comment, highlight or tags
+    #[serde(default)]
+    pub annotated_transactions: u64,
+    /// Database, body index and annotations; chunks and blobs are verified by their names
+    pub entries: Vec<ArchiveEntry>,
+    /// Fields written by newer versions, kept as-is
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// One line of the body index. `None` is a NULL body, an empty list an empty one.
+#[derive(Serialize, Deserialize)]
+struct BodyIndexEntry {
+    request_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    req: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    res: Option<Vec<String>>,
+}
+
+impl Database {
+    /// Export a project to a .proxxy archive
+    pub async fn export_project(
+        &self,
+        name: &str,
+        output_path: &str,
+        options: &ExportOptions,
+    ) -> Result<ArchiveManifest, ArchiveError> {
+        let project_path = self.projects_dir.join(format!("{}.proxxy", name));
+        let db_path = project_path.join("proxxy.db");
+        if !db_path.exists() {
+            return Err(ArchiveError::ProjectNotFound(name.to_string()));
+        }
+
+        let staging = Staging::new()?;
+        let snapshot_path = staging.path().join("proxxy.db");
+
+        // A consistent copy, even while the project is loaded and capturing
+        let source = open_sqlite(&db_path).await?;
+        sqlx::query("VACUUM INTO ?")
+            .bind(snapshot_path.to_string_lossy().to_string())
+            .execute(&source)
+            .await?;
+        source.close().await;
+
+        let snapshot = open_sqlite(&snapshot_path).await?;
+        apply_selection(&snapshot, options).await?;
+        let annotated = super::annotations::annotated_transactions(&snapshot, None).await?;
+        let annotations = serde_json::to_vec_pretty(&annotated)?;
+
+        let mut chunks = ChunkStore::new(staging.path().join("chunks"));
+        let mut index = Vec::new();
+        let mut transactions = 0u64;
+        {
+            let mut rows = sqlx::query("SELECT request_id, req_body, res_body FROM http_transactions").fetch(&snapshot);
+            while let Some(row) = rows.next().await {
+                let row = row?;
+                let entry = BodyIndexEntry {
+                    request_id: row.get("request_id"),
+                    req: row
+                        .get::<Option<Vec<u8>>, _>("req_body")
+                        .map(|body| chunks.store(&body))
+                        .transpose()?,
+                    res: row
+                        .get::<Option<Vec<u8>>, _>("res_body")
+                        .map(|body| chunks.store(&body))
+                        .transpose()?,
+                };
+                serde_json::to_writer(&mut index, &entry)?;
+                index.push(b'\n');
+                transactions += 1;
+            }
+        }
+
+        // Bodies live in the chunk store now
+        sqlx::query("UPDATE http_transactions SET req_body = NULL, res_body = NULL")
+            .execute(&snapshot)
+            .await?;
+        sqlx::query("VACUUM").execute(&snapshot).await?;
+        snapshot.close().await;
+
+        let blobs = list_blob_files(&project_path.join("blobs"))?;
+        let manifest = ArchiveManifest {
+            format: ARCHIVE_FORMAT.to_string(),
+            format_version: ARCHIVE_FORMAT_VERSION,
+            min_reader_version: ARCHIVE_FORMAT_VERSION,
+            name: name.to_string(),
+            exported_at: chrono::Utc::now().to_rfc3339(),
+            producer: format!("orchestrator {}", env!("CARGO_PKG_VERSION")),
+            selection: options.clone(),
+            transactions,
+            body_chunks: chunks.stored.len() as u64,
+            body_chunk_refs: chunks.refs,
+            blobs: blobs.len() as u64,
+            annotated_transactions: annotated.len() as u64,
+            entries: Vec::new(),
+            extra: serde_json::Map::new(),
+        };
+
+        let output = PathBuf::from(output_path);
+        let staging_path = staging.path().to_path_buf();
+        let chunk_hashes: Vec<String> = chunks.stored.into_iter().collect();
+        let manifest = tokio::task::spawn_blocking(move || {
+            write_archive(&output, &staging_path, &index, &annotations, &chunk_hashes, &blobs, manifest)
+        })
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))??;
+
+        info!(
+            "✓ Exported project '{}' to {} ({} transactions, {}/{} body chunks after dedup)",
+            name, output_path, manifest.transactions, manifest.body_chunks, manifest.body_chunk_refs
+        );
+        Ok(manifest)
+    }
+
+    /// Import a project from a .proxxy archive (format v1 or v2)
+    pub async fn import_project(&self, proxxy_path: &str, project_name: Option<&str>) -> Result<String, ArchiveError> {
+        let staging = Staging::new()?;
+        let archive_path = PathBuf::from(proxxy_path);
+        let staging_path = staging.path().to_path_buf();
+        let extracted = tokio::task::spawn_blocking(move || extract_archive(&archive_path, &staging_path))
+            .await
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))??;
+
+        let final_name = project_name.unwrap_or(&extracted.name).to_string();
+        let project_path = self.projects_dir.join(format!("{}.proxxy", final_name));
+        if project_path.exists() {
+            return Err(ArchiveError::ProjectExists(final_name));
+        }
+
+        let staged_db = staging.path().join("proxxy.db");
+        if extracted.format_version >= 2 {
+            restore_bodies(&staged_db, staging.path()).await?;
+        }
+
+        let installed = install_project(staging.path(), &project_path);
+        if installed.is_err() {
+            let _ = fs::remove_dir_all(&project_path);
+        }
+        installed?;
+
+        info!(
+            "✓ Imported project '{}' from {} (format v{})",
+            final_name, proxxy_path, extracted.format_version
+        );
+        Ok(final_name)
+    }
+}
+
+async fn open_sqlite(path: &Path) -> Result<Pool<Sqlite>, sqlx::Error> {
+    let options = SqliteConnectOptions::new().filename(path);
+    SqlitePoolOptions::new().max_connections(1).connect_with(options).await
+}
+
+/// Drop traffic outside the export selection from the snapshot
+async fn apply_selection(pool: &Pool<Sqlite>, options: &ExportOptions) -> Result<(), sqlx::Error> {
+    if let Some(since) = options.since {
+        sqlx::query("DELETE FROM http_transactions WHERE req_timestamp < ?")
+            .bind(since)
+            .execute(pool)
+            .await?;
+    }
+    if let Some(until) = options.until {
+        sqlx::query("DELETE FROM http_transactions WHERE req_timestamp > ?")
+            .bind(until)
+            .execute(pool)
+            .await?;
+    }
+
+    if options.scope_only {
+        let rules = sqlx::query_as::<_, ScopeRule>("SELECT * FROM scope_rules")
+            .fetch_all(pool)
+            .await?;
+        let rows = sqlx::query("SELECT request_id, req_url FROM http_transactions")
+            .fetch_all(pool)
+            .await?;
+
+        let mut tx = pool.begin().await?;
+        for row in rows {
+            let url: String = row.get("req_url");
+            if !crate::scope::is_in_scope(&rules, &url) {
+                sqlx::query("DELETE FROM http_transactions WHERE request_id = ?")
+                    .bind(row.get::<String, _>("request_id"))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+/// Put the reassembled bodies back into an extracted database
+async fn restore_bodies(db_path: &Path, staging: &Path) -> Result<(), ArchiveError> {
+    let pool = open_sqlite(db_path).await?;
+    let chunks_dir = staging.join("chunks");
+    let index = io::BufReader::new(fs::File::open(staging.join("bodies.jsonl"))?);
+
+    let mut tx = pool.begin().await?;
+    for line in index.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: BodyIndexEntry = serde_json::from_str(&line)?;
+        let req_body = entry.req.map(|hashes| read_chunks(&chunks_dir, &hashes)).transpose()?;
+        let res_body = entry.res.map(|hashes| read_chunks(&chunks_dir, &hashes)).transpose()?;
+
+        sqlx::query("UPDATE http_transactions SET req_body = ?, res_body = ? WHERE request_id = ?")
+            .bind(req_body)
+            .bind(res_body)
+            .bind(&entry.request_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    pool.close().await;
+
+    Ok(())
+}
+
+fn read_chunks(chunks_dir: &Path, hashes: &[String]) -> Result<Vec<u8>, ArchiveError> {
+    let mut body = Vec::new();
+    for hash in hashes {
+        if !BlobStore::is_valid_hash(hash) {
+            return Err(ArchiveError::InvalidArchive(format!("bad chunk reference {}", hash)));
+        }
+        let mut chunk = fs::File::open(chunks_dir.join(hash))
+            .map_err(|_| ArchiveError::InvalidArchive(format!("missing body chunk {}", hash)))?;
+        chunk.read_to_end(&mut body)?;
+    }
+    Ok(body)
+}
+
+/// Move an extracted project into the projects directory
+fn install_project(staging: &Path, project_path: &Path) -> Result<(), ArchiveError> {
+    fs::create_dir_all(project_path)?;
+    fs::copy(staging.join("proxxy.db"), project_path.join("proxxy.db"))?;
+
+    for blob in fs::read_dir(staging.join("blobs"))? {
+        let blob = blob?;
+        let hash = blob.file_name().to_string_lossy().to_string();
+        let dest = project_path.join("blobs").join(&hash[..2]);
+        fs::create_dir_all(&dest)?;
+        fs::copy(blob.path(), dest.join(&hash))?;
+    }
+    Ok(())
+}
+
+/// Blob files of a project's content-addressed store
+fn list_blob_files(blobs_dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut blobs = Vec::new();
+    let Ok(prefixes) = fs::read_dir(blobs_dir) else {
+        return Ok(blobs);
+    };
+
+    for prefix in prefixes {
+        let prefix = prefix?.path();
+        if !prefix.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(&prefix)? {
+            let path = file?.path();
+            // Skips partial uploads, which are not named by hash
+            if let Some(hash) = path.file_name().and_then(|n| n.to_str()).filter(|n| BlobStore::is_valid_hash(n)) {
+                blobs.push((hash.to_string(), path.clone()));
+            }
+        }
+    }
+    Ok(blobs)
+}
+
+fn write_archive(
+    output: &Path,
+    staging: &Path,
+    index: &[u8],
+    annotations: &[u8],
+    chunk_hashes: &[String],
+    blobs: &[(String, PathBuf)],
+    mut manifest: ArchiveManifest,
+) -> Result<ArchiveManifest, ArchiveError> {
+    let mut zip = zip::ZipWriter::new(io::BufWriter::new(fs::File::create(output)?));
+
+    // Entries are compressed with zstd already
+    let stored: zip::write::FileOptions<'_, ()> =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    let stored_large = stored.large_file(true);
+
+    zip.start_file(DB_ENTRY, stored_large)?;
+    let mut writer = HashingWriter::new(&mut zip);
+    zstd::stream::copy_encode(fs::File::open(staging.join("proxxy.db"))?, &mut writer, ZSTD_LEVEL)?;
+    manifest.entries.push(writer.into_entry(DB_ENTRY));
+
+    zip.start_file(BODY_INDEX_ENTRY, stored_large)?;
+    let mut writer = HashingWriter::new(&mut zip);
+    zstd::stream::copy_encode(index, &mut writer, ZSTD_LEVEL)?;
+    manifest.entries.push(writer.into_entry(BODY_INDEX_ENTRY));
+
+    // Plain JSON so other tools can read the notes straight from the ZIP
+    zip.start_file(
+        ANNOTATIONS_ENTRY,
+        zip::write::FileOptions::<'_, ()>::default().compression_method(zip::CompressionMethod::Deflated),
+    )?;
+    let mut writer = HashingWriter::new(&mut zip);
+    writer.write_all(annotations)?;
+    manifest.entries.push(writer.into_entry(ANNOTATIONS_ENTRY));
+
+    for hash in chunk_hashes {
+        zip.start_file(format!("chunks/{}.zst", hash), stored)?;
+        io::copy(&mut fs::File::open(staging.join("chunks").join(hash))?, &mut zip)?;
+    }
+
+    for (hash, path) in blobs {
+        zip.start_file(format!("blobs/{}.zst", hash), stored_large)?;
+        zstd::stream::copy_encode(fs::File::open(path)?, &mut zip, ZSTD_LEVEL)?;
+    }
+
+    // Written last, once every hash is known
+    zip.start_file(
+        "manifest.json",
+        zip::write::FileOptions::<'_, ()>::default().compression_method(zip::CompressionMethod::Deflated),
+    )?;
+    zip.write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+    zip.finish()?.flush()?;
+
+    Ok(manifest)
+}
+
+/// What an archive unpacked into the staging directory
+struct Extracted {
+    name: String,
+    format_version: u32,
+}
+
+fn extract_archive(archive_path: &Path, staging: &Path) -> Result<Extracted, ArchiveError> {
+    let mut archive = zip::ZipArchive::new(fs::File::open(archive_path)?)?;
+    let names: Vec<String> = archive.file_names().map(|n| n.to_string()).collect();
+    if !names.iter().any(|n| n == "manifest.json") {
+        return extract_legacy(&mut archive, staging);
+    }
+
+    let manifest: ArchiveManifest = serde_json::from_reader(archive.by_name("manifest.json")?)?;
+    if manifest.format != ARCHIVE_FORMAT {
+        return Err(ArchiveError::InvalidArchive(format!("unknown format '{}'", manifest.format)));
+    }
+    if manifest.min_reader_version > ARCHIVE_FORMAT_VERSION {
+        return Err(ArchiveError::UnsupportedVersion {
+            required: manifest.min_reader_version,
+            supported: ARCHIVE_FORMAT_VERSION,
+        });
+    }
+
+    for (path, dest) in [(DB_ENTRY, "proxxy.db"), (BODY_INDEX_ENTRY, "bodies.jsonl")] {
+        let entry = manifest
+            .entries
+            .iter()
+            .find(|e| e.path == path)
+            .ok_or_else(|| ArchiveError::InvalidArchive(format!("manifest does not list {}", path)))?;
+        extract_verified_entry(&mut archive, entry, &staging.join(dest))?;
+    }
+
+    for name in &names {
+        if let Some(hash) = content_name(name, "chunks/") {
+            let mut chunk = Vec::new();
+            // A chunk never decompresses beyond the chunk size
+            zstd::stream::Decoder::new(archive.by_name(name)?)?
+                .take(BODY_CHUNK_SIZE as u64 + 1)
+                .read_to_end(&mut chunk)?;
+            if chunk.len() > BODY_CHUNK_SIZE || sha256_hex(&chunk) != hash {
+                return Err(ArchiveError::Integrity(name.clone()));
+            }
+            fs::write(staging.join("chunks").join(hash), chunk)?;
+        } else if let Some(hash) = content_name(name, "blobs/") {
+            let dest = staging.join("blobs").join(hash);
+            let mut writer = HashingWriter::new(fs::File::create(&dest)?);
+            zstd::stream::copy_decode(archive.by_name(name)?, &mut writer)?;
+            if writer.into_entry(name).sha256 != hash {
+                return Err(ArchiveError::Integrity(name.clone()));
+            }
+        }
+    }
+
+    Ok(Extracted {
+        name: manifest.name,
+        format_version: manifest.format_version,
+    })
+}
+
+/// Version 1: the raw database and a `metadata.json`
+fn extract_legacy(archive: &mut zip::ZipArchive<fs::File>, staging: &Path) -> Result<Extracted, ArchiveError> {
+    let metadata: serde_json::Value = serde_json::from_reader(
+        archive
+            .by_name("metadata.json")
+            .map_err(|_| ArchiveError::InvalidArchive("no manifest or metadata".to_string()))?,
+    )?;
+    let name = metadata["name"]
+        .as_str()
+        .ok_or_else(|| ArchiveError::InvalidArchive("metadata has no project name".to_string()))?
+        .to_string();
+
+    let mut db = archive.by_name("proxxy.db")?;
+    io::copy(&mut db, &mut fs::File::create(staging.join("proxxy.db"))?)?;
+
+    Ok(Extracted { name, format_version: 1 })
+}
+
+/// Decompress a manifest entry, checking the hash of its stored bytes
+fn extract_verified_entry(
+    archive: &mut zip::ZipArchive<fs::File>,
+    entry: &ArchiveEntry,
+    dest: &Path,
+) -> Result<(), ArchiveError> {
+    let mut reader = HashingReader::new(archive.by_name(&entry.path)?);
+    zstd::stream::copy_decode(&mut reader, fs::File::create(dest)?)?;
+    // The decoder may stop before trailing bytes; they count for the hash too
+    io::copy(&mut reader, &mut io::sink())?;
+
+    if reader.hex() != entry.sha256 {
+        return Err(ArchiveError::Integrity(entry.path.clone()));
+    }
+    Ok(())
+}
+
+/// Hash from a content-addressed entry name such as `chunks/<hash>.zst`
+fn content_name<'a>(name: &'a str, dir: &str) -> Option<&'a str> {
+    name.strip_prefix(dir)?
+        .strip_suffix(".zst")
+        .filter(|hash| BlobStore::is_valid_hash(hash))
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Writes body chunks to the staging directory once per distinct content
+struct ChunkStore {
+    dir: PathBuf,
+    stored: HashSet<String>,
+    refs: u64,
+}
+
+impl ChunkStore {
+    fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            stored: HashSet::new(),
+            refs: 0,
+        }
+    }
+
+    /// Chunk a body; returns the chunk hashes in order
+    fn store(&mut self, body: &[u8]) -> io::Result<Vec<String>> {
+        let mut hashes = Vec::new();
+        for chunk in body.chunks(BODY_CHUNK_SIZE) {
+            let hash = sha256_hex(chunk);
+            self.refs += 1;
+            if !self.stored.contains(&hash) {
+                fs::write(self.dir.join(&hash), zstd::encode_all(chunk, ZSTD_LEVEL)?)?;
+                self.stored.insert(hash.clone());
+            }
+            hashes.push(hash);
+        }
+        Ok(hashes)
+    }
+}
+
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl<W: Write> HashingWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+            size: 0,
+        }
+    }
+
+    fn into_entry(self, path: &str) -> ArchiveEntry {
+        ArchiveEntry {
+            path: path.to_string(),
+            sha256: format!("{:x}", self.hasher.finalize()),
+            size: self.size,
+        }
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.size += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    fn hex(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Scratch directory, removed when dropped
+struct Staging(PathBuf);
+
+impl Staging {
+    fn new() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("proxxy-archive-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(path.join("chunks"))?;
+        fs::create_dir_all(path.join("blobs"))?;
+        Ok(Self(path))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn insert_transaction(db: &Database, id: &str, url: &str, timestamp: i64, body: Option<&[u8]>) {
+        let pool = db.get_pool().await.unwrap();
+        sqlx::query("INSERT OR IGNORE INTO agents (id, name, hostname, version, status, last_heartbeat) VALUES ('a1', 'a1', 'h', '1', 'Online', 0)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO http_transactions (request_id, agent_id, req_method, req_url, req_timestamp, res_body) VALUES (?, 'a1', 'GET', ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(url)
+        .bind(timestamp)
+        .bind(body)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_deduplicates_bodies() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::new(temp_dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("source").await.unwrap();
+        db.load_project("source").await.unwrap();
+
+        let bundle = vec![b'x'; BODY_CHUNK_SIZE + 10];
+        insert_transaction(&db, "r1", "https://app.example.com/app.js", 100, Some(&bundle)).await;
+        insert_transaction(&db, "r2", "https://app.example.com/app.js", 200, Some(&bundle)).await;
+        insert_transaction(&db, "r3", "https://app.example.com/empty", 300, Some(b"")).await;
+        insert_transaction(&db, "r4", "https://app.example.com/old", 10, Some(b"old")).await;
+        db.set_request_tags("r2", &["cached".to_string()]).await.unwrap();
+        db.set_request_comment("r4", Some("outside the selection")).await.unwrap();
+
+        let archive = temp_dir.path().join("export.proxxy");
+        let options = ExportOptions {
+            since: Some(50),
+            ..Default::default()
+        };
+        let manifest = db
+            .export_project("source", archive.to_str().unwrap(), &options)
+            .await
+            .unwrap();
+        assert_eq!(manifest.transactions, 3);
+        // Both copies of the bundle share two chunks (a full and a partial one)
+        assert_eq!(manifest.body_chunk_refs, 4);
+        assert_eq!(manifest.body_chunks, 2);
+        assert_eq!(manifest.annotated_transactions, 1);
+
+        let mut zip = zip::ZipArchive::new(fs::File::open(&archive).unwrap()).unwrap();
+        let notes: serde_json::Value = serde_json::from_reader(zip.by_name(ANNOTATIONS_ENTRY).unwrap()).unwrap();
+        assert_eq!(notes[0]["request_id"], "r2");
+        assert_eq!(notes[0]["tags"][0], "cached");
+
+        let name = db.import_project(archive.to_str().unwrap(), Some("copy")).await.unwrap();
+        assert_eq!(name, "copy");
+        db.load_project("copy").await.unwrap();
+
+        let pool = db.get_pool().await.unwrap();
+        let rows = sqlx::query("SELECT request_id, res_body FROM http_transactions ORDER BY request_id")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        let bodies: Vec<(String, Option<Vec<u8>>)> =
+            rows.iter().map(|r| (r.get("request_id"), r.get("res_body"))).collect();
+        assert_eq!(
+            bodies,
+            vec![
+                ("r1".to_string(), Some(bundle.clone())),
+                ("r2".to_string(), Some(bundle)),
+                ("r3".to_string(), Some(Vec::new())),
+            ]
+        );
+
+        assert!(matches!(
+            db.import_project(archive.to_str().unwrap(), Some("copy")).await,
+            Err(ArchiveError::ProjectExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_manifest_keeps_unknown_fields() {
+        let json = r#"{
+            "format": "proxxy-archive", "format_version": 3, "min_reader_version": 2,
+            "name": "p", "exported_at": "", "producer": "future", "selection": {},
+            "transactions": 0, "body_chunks": 0, "body_chunk_refs": 0, "blobs": 0,
+            "entries": [], "signatures": ["abc"]
+        }"#;
+        let manifest: ArchiveManifest = serde_json::from_str(json).unwrap();
+        assert_eq!(manifest.format_version, 3);
+        assert!(manifest.extra.contains_key("signatures"));
+    }
+}
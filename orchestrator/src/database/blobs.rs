@@ -0,0 +1,179 @@
+//! Database operations for artifact blobs
+//!
+//! Blob contents live on disk; these tables record what exists and which rows
+//! still reference it. A blob's reference count is the number of `blob_refs`
+//! rows pointing at it.
+
+use sqlx::Row;
+
+/// Blob metadata as stored in database
+#[derive(Debug, Clone)]
+pub struct BlobRow {
+    pub hash: String,
+    pub size: i64,
+    pub content_type: Option<String>,
+    pub created_at: i64,
+    pub ref_count: i64,
+}
+
+fn blob_from_row(row: &sqlx::sqlite::SqliteRow) -> BlobRow {
+    BlobRow {
+        hash: row.get("hash"),
+        size: row.get("size"),
+        content_type: row.get("content_type"),
+        created_at: row.get("created_at"),
+        ref_count: row.get("ref_count"),
+    }
+}
+
+impl super::Database {
+    /// Record a stored blob. Storing the same content again keeps the original record.
+    pub async fn record_blob(
+        &self,
+        hash: &str,
+        size: i64,
+        content_type: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            "INSERT INTO blobs (hash, size, content_type, created_at) VALUES (?, ?, ?, ?)
+             ON CONFLICT(hash) DO NOTHING",
+        )
+        .bind(hash)
+        .bind(size)
+        .bind(content_type)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get blob metadata by hash
+    pub async fn get_blob(&self, hash: &str) -> Result<Option<BlobRow>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let row = sqlx::query(
+            r#"
+            SELECT hash, size, content_type, created_at,
+                   (SELECT COUNT(*) FROM blob_refs WHERE blob_hash = blobs.hash) AS ref_count
+            FROM blobs
+            WHERE hash = ?
+            "#,
+        )
+        .bind(hash)
+        .fetch_optional(&pool)
+        .await?;
+
+        Ok(row.as_ref().map(blob_from_row))
+    }
+
+    /// Reference a blob from a row (`owner_kind` names the table or feature)
+    pub async fn add_blob_ref(&self, hash: &str, owner_kind: &str, owner_id: &str) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            "INSERT OR IGNORE INTO blob_refs (blob_hash, owner_kind, owner_id, created_at) VALUES (?, ?, ?, ?)",
+        )
+        .bind(hash)
+        .bind(owner_kind)
+        .bind(owner_id)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Drop one row's reference to a blob
+    pub async fn remove_blob_ref(&self, hash: &str, owner_kind: &str, owner_id: &str) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("DELETE FROM blob_refs WHERE blob_hash = ? AND owner_kind = ? AND owner_id = ?")
+            .bind(hash)
+            .bind(owner_kind)
+            .bind(owner_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Hashes of the blobs a row references, oldest reference first
+    pub async fn blob_refs_for_owner(&self, owner_kind: &str, owner_id: &str) -> Result<Vec<String>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        sqlx::query_scalar(
+            "SELECT blob_hash FROM blob_refs WHERE owner_kind = ? AND owner_id = ? ORDER BY created_at",
+        )
+        .bind(owner_kind)
+        .bind(owner_id)
+        .fetch_all(&pool)
+        .await
+    }
+
+    /// Drop every blob reference held by a row, e.g. when the row is deleted
+    pub async fn remove_blob_refs_for_owner(&self, owner_kind: &str, owner_id: &str) -> Result<u64, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query("DELETE FROM blob_refs WHERE owner_kind = ? AND owner_id = ?")
+            .bind(owner_kind)
+            .bind(owner_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Blobs without references stored no later than `created_before` (unix seconds)
+    pub async fn unreferenced_blobs(&self, created_before: i64) -> Result<Vec<BlobRow>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT hash, size, content_type, created_at, 0 AS ref_count
+            FROM blobs
+            WHERE created_at <= ?
+              AND NOT EXISTS (SELECT 1 FROM blob_refs WHERE blob_hash = blobs.hash)
+            "#,
+        )
+        .bind(created_before)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(blob_from_row).collect())
+    }
+
+    /// Delete a blob record if it is still unreferenced. Returns whether it was deleted.
+    pub async fn delete_unreferenced_blob(&self, hash: &str) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query(
+            "DELETE FROM blobs WHERE hash = ? AND NOT EXISTS (SELECT 1 FROM blob_refs WHERE blob_hash = ?)",
+        )
+        .bind(hash)
+        .bind(hash)
+        .execute(&pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,359 @@
+//! Import of Burp Suite "Save items" XML
+//!
+//! Burp exports each item with its URL, time, comment and the raw request and
+//! response, usually base64 encoded. Items become ordinary transactions owned
+//! by a `burp-import` agent row, so the site map, search and annotations work
+//! on them like on captured traffic.
+
+use crate::pb::HttpHeaders;
+use base64::Engine as _;
+use quick_xml::events::Event;
+use std::collections::HashMap;
+
+/// Agent the imported transactions are attributed to
+pub const BURP_IMPORT_AGENT: &str = "burp-import";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BurpImportError {
+    #[error("Not a Burp items export: {0}")]
+    InvalidXml(String),
+    #[error("Import database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// One `<item>` of an export
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BurpItem {
+    pub url: String,
+    /// Unix seconds, `None` if `<time>` was missing or unreadable
+    pub time: Option<i64>,
+    pub method: String,
+    pub request: Vec<u8>,
+    pub response: Option<Vec<u8>>,
+    pub comment: Option<String>,
+}
+
+/// Outcome of an import
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BurpImportSummary {
+    pub imported: u64,
+    /// Items without a URL or request
+    pub skipped: u64,
+}
+
+/// Parsed head and body of a raw HTTP message
+struct RawMessage<'a> {
+    start_line: &'a str,
+    headers: HashMap<String, String>,
+    body: &'a [u8],
+}
+
+/// Split a raw HTTP/1.x message into start line, headers and body. Repeated
+/// headers are joined with `, `.
+fn parse_raw_message(raw: &[u8]) -> RawMessage<'_> {
+    let (head, body) = match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(i) => (&raw[..i], &raw[i + 4..]),
+        None => match raw.windows(2).position(|w| w == b"\n\n") {
+            Some(i) => (&raw[..i], &raw[i + 2..]),
+            None => (raw, &raw[raw.len()..]),
+        },
+    };
+    let head = std::str::from_utf8(head).unwrap_or_default();
+    let mut lines = head.lines();
+    let start_line = lines.next().unwrap_or_default().trim();
+
+    let mut headers: HashMap<String, String> = HashMap::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim().to_string(), value.trim());
+        headers
+            .entry(name)
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+
+    RawMessage { start_line, headers, body }
+}
+
+/// Unix seconds of a Burp `<time>`, written like Java's `Date.toString()`
+/// (`Mon Jan 01 12:00:00 UTC 2024`). The zone name is not resolved; times are
+/// read as UTC.
+fn parse_burp_time(time: &str) -> Option<i64> {
+    chrono::NaiveDateTime::parse_from_str(time.trim(), "%a %b %d %H:%M:%S %Z %Y")
+        .ok()
+        .map(|t| t.and_utc().timestamp())
+}
+
+/// Items of a Burp "Save items" export
+pub fn parse_burp_items(xml: &str) -> Result<Vec<BurpItem>, BurpImportError> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut items = Vec::new();
+    let mut item: Option<BurpItem> = None;
+    let mut element = String::new();
+    let mut base64 = false;
+    let mut text: Vec<u8> = Vec::new();
+    let mut saw_items = false;
+    let mut depth = 0usize;
+
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| BurpImportError::InvalidXml(format!("at byte {}: {}", reader.buffer_position(), e)))?;
+        match event {
+            Event::Start(start) => {
+                depth += 1;
+                let name = String::from_utf8_lossy(start.name().as_ref()).into_owned();
+                match name.as_str() {
+                    "items" => saw_items = true,
+                    "item" => item = Some(BurpItem::default()),
+                    _ => {}
+                }
+                base64 = start
+                    .try_get_attribute("base64")
+                    .ok()
+                    .flatten()
+                    .is_some_and(|a| a.value.as_ref() == b"true");
+                element = name;
+                text.clear();
+            }
+            Event::Text(t) => {
+                let unescaped = t.unescape().map_err(|e| BurpImportError::InvalidXml(e.to_string()))?;
+                text.extend_from_slice(unescaped.as_bytes());
+            }
+            Event::CData(c) => text.extend_from_slice(&c.into_inner()),
+            Event::End(end) => {
+                depth = depth.saturating_sub(1);
+                let name = end.name();
+                if name.as_ref() == b"item" {
+                    items.extend(item.take());
+                } else if let Some(item) = item.as_mut().filter(|_| name.as_ref() == element.as_bytes()) {
+                    let value = if base64 {
+                        let compact: Vec<u8> = text.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+                        base64::engine::general_purpose::STANDARD.decode(compact).map_err(|e| {
+                            BurpImportError::InvalidXml(format!("invalid base64 in <{}>: {}", element, e))
+                        })?
+                    } else {
+                        std::mem::take(&mut text)
+                    };
+                    let as_text = || String::from_utf8_lossy(&value).trim().to_string();
+                    match element.as_str() {
+                        "url" => item.url = as_text(),
+                        "time" => item.time = parse_burp_time(&as_text()),
+                        "method" => item.method = as_text(),
+                        "request" => item.request = value,
+                        "response" if !value.is_empty() => item.response = Some(value),
+                        "comment" => item.comment = Some(as_text()).filter(|c| !c.is_empty()),
+                        _ => {}
+                    }
+                }
+                element.clear();
+                text.clear();
+            }
+            Event::Eof if depth > 0 => {
+                return Err(BurpImportError::InvalidXml("document ends inside an element".to_string()));
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !saw_items {
+        return Err(BurpImportError::InvalidXml("no <items> element".to_string()));
+    }
+    Ok(items)
+}
+
+impl super::Database {
+    /// Store the items of a Burp "Save items" export as transactions,
+    /// keeping their URLs, times and comments. Items without a URL or request
+    /// are skipped. Nothing is stored if the XML is malformed.
+    pub async fn import_burp_xml(&self, xml: &str) -> Result<BurpImportSummary, BurpImportError> {
+        let items = parse_burp_items(xml)?;
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        let now = chrono::Utc::now().timestamp();
+        let mut summary = BurpImportSummary::default();
+        let mut tx = pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO agents (id, name, hostname, version, status, last_heartbeat)
+            VALUES (?, 'Burp Suite import', 'import', '-', 'Offline', ?)
+            "#,
+        )
+        .bind(BURP_IMPORT_AGENT)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        for item in items {
+            if item.url.is_empty() || item.request.is_empty() {
+                summary.skipped += 1;
+                continue;
+            }
+
+            let request = parse_raw_message(&item.request);
+            let method = match request.start_line.split_whitespace().next() {
+                Some(method) => method.to_string(),
+                None => item.method.clone(),
+            };
+            let request_headers = HttpHeaders { headers: request.headers };
+            let request_protocol =
+                crate::api_protocol::classify_request(&item.url, &request_headers.headers, request.body);
+            let timestamp = item.time.unwrap_or(now);
+
+            let response = item.response.as_deref().map(parse_raw_message);
+            let (status, protocol) = match &response {
+                Some(response) => {
+                    let mut parts = response.start_line.split_whitespace();
+                    let protocol = parts.next().map(str::to_string);
+                    (parts.next().and_then(|s| s.parse::<i32>().ok()), protocol)
+                }
+                None => (None, None),
+            };
+            let response_protocol = response
+                .as_ref()
+                .and_then(|r| crate::api_protocol::classify_response(&r.headers, r.body));
+            let response_headers = response.as_ref().map(|r| HttpHeaders { headers: r.headers.clone() });
+
+            sqlx::query(
+                r#"
+                INSERT INTO http_transactions (
+                    request_id, agent_id, req_method, req_url, req_headers, req_body, req_timestamp,
+                    res_status, res_headers, res_body, res_timestamp, res_protocol, api_protocol, comment
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(uuid::Uuid::new_v4().to_string())
+            .bind(BURP_IMPORT_AGENT)
+            .bind(method)
+            .bind(&item.url)
+            .bind(serde_json::to_string(&Some(request_headers)).unwrap_or_default())
+            .bind(request.body)
+            .bind(timestamp)
+            .bind(status)
+            .bind(response_headers.map(|h| serde_json::to_string(&Some(h)).unwrap_or_default()))
+            .bind(response.as_ref().map(|r| r.body))
+            .bind(response.as_ref().map(|_| timestamp))
+            .bind(protocol)
+            .bind(request_protocol.or(response_protocol).map(|p| p.as_str()))
+            .bind(item.comment)
+            .execute(&mut *tx)
+            .await?;
+            summary.imported += 1;
+        }
+
+        tx.commit().await?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+    use base64::engine::general_purpose::STANDARD;
+    use tempfile::TempDir;
+
+    fn export() -> String {
+        let request = STANDARD.encode("POST /api/login?next=%2F HTTP/1.1\r\nHost: shop.test\r\nContent-Type: application/json\r\nCookie: a=1\r\nCookie: b=2\r\n\r\n{\"user\":\"bob\"}");
+        let response = STANDARD.encode("HTTP/1.1 401 Unauthorized\r\nContent-Type: application/json\r\n\r\n{\"error\":\"denied\"}");
+        format!(
+            r#"<?xml version="1.0"?>
+<!DOCTYPE items [
+<!ELEMENT items (item*)>
+<!ATTLIST items burpVersion CDATA "">
+]>
+<items burpVersion="2023.10.3.4" exportTime="Tue Jan 09 10:15:00 UTC 2024">
+  <item>
+    <time>Mon Jan 08 09:30:00 UTC 2024</time>
+    <url><![CDATA[https://shop.test/api/login?next=%2F]]></url>
+    <host ip="10.0.0.5">shop.test</host>
+    <port>443</port>
+    <protocol>https</protocol>
+    <method><![CDATA[POST]]></method>
+    <path><![CDATA[/api/login?next=%2F]]></path>
+    <extension>null</extension>
+    <request base64="true"><![CDATA[{request}]]></request>
+    <status>401</status>
+    <responselength>18</responselength>
+    <mimetype>JSON</mimetype>
+    <response base64="true"><![CDATA[{response}]]></response>
+    <comment>Login &amp; lockout</comment>
+  </item>
+  <item>
+    <time>Mon Jan 08 09:31:00 UTC 2024</time>
+    <url><![CDATA[http://shop.test/robots.txt]]></url>
+    <method><![CDATA[GET]]></method>
+    <request base64="false"><![CDATA[GET /robots.txt HTTP/1.1
+Host: shop.test
+
+]]></request>
+    <response base64="true"></response>
+    <comment></comment>
+  </item>
+  <item>
+    <url></url>
+  </item>
+</items>"#
+        )
+    }
+
+    #[test]
+    fn test_parse_burp_items() {
+        let items = parse_burp_items(&export()).unwrap();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].url, "https://shop.test/api/login?next=%2F");
+        assert_eq!(items[0].time, Some(1704706200));
+        assert_eq!(items[0].comment.as_deref(), Some("Login & lockout"));
+        assert!(items[0].response.as_ref().unwrap().starts_with(b"HTTP/1.1 401"));
+        assert!(items[1].request.starts_with(b"GET /robots.txt"));
+        assert_eq!(items[1].response, None);
+        assert_eq!(items[1].comment, None);
+
+        assert!(matches!(parse_burp_items("<html></html>"), Err(BurpImportError::InvalidXml(_))));
+        assert!(matches!(parse_burp_items("<items><item><url>x</item>"), Err(BurpImportError::InvalidXml(_))));
+    }
+
+    #[tokio::test]
+    async fn test_import_burp_xml() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+
+        let summary = db.import_burp_xml(&export()).await.unwrap();
+        assert_eq!(summary, BurpImportSummary { imported: 2, skipped: 1 });
+
+        let requests = db.get_recent_requests(Some(BURP_IMPORT_AGENT), 10).await.unwrap();
+        assert_eq!(requests.len(), 2);
+        // Newest first
+        let login = &requests[1];
+        let Some(crate::pb::traffic_event::Event::Request(req)) = &login.event.event else {
+            panic!("expected a request");
+        };
+        assert_eq!(req.method, "POST");
+        assert_eq!(login.status, Some(401));
+        assert_eq!(req.url, "https://shop.test/api/login?next=%2F");
+        assert_eq!(&req.body[..], b"{\"user\":\"bob\"}");
+        let headers = &req.headers.as_ref().unwrap().headers;
+        assert_eq!(headers.get("Cookie").map(String::as_str), Some("a=1, b=2"));
+        assert_eq!(login.annotation.comment.as_deref(), Some("Login & lockout"));
+
+        let hosts = db.list_site_map_hosts().await.unwrap();
+        assert_eq!(hosts.len(), 1);
+        assert_eq!(hosts[0].name, "shop.test");
+        assert_eq!(hosts[0].hits, 2);
+        assert_eq!(hosts[0].statuses.client_error, 1);
+        assert_eq!(hosts[0].last_seen, 1704706260);
+
+        assert!(matches!(db.import_burp_xml("<items><item>").await, Err(BurpImportError::InvalidXml(_))));
+    }
+}
@@ -0,0 +1,284 @@
+//! Database operations for redirect and retry chains
+//!
+//! A transaction that a client sent because of an earlier one records it as
+//! `parent_id`: the request following a 3xx to its Location, or a repeat of a
+//! request whose previous attempt failed with 429/5xx. Links are made at ingest,
+//! per agent, within [`CHAIN_WINDOW_SECS`] of the earlier response.
+
+use sqlx::{Pool, Row, Sqlite};
+use std::collections::HashMap;
+
+/// How long after a response a follow-up request is still linked to it
+pub const CHAIN_WINDOW_SECS: i64 = 30;
+
+/// Why a transaction followed its parent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainLink {
+    Redirect,
+    Retry,
+}
+
+impl ChainLink {
+    /// Value stored in `http_transactions.parent_link`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChainLink::Redirect => "redirect",
+            ChainLink::Retry => "retry",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "redirect" => Some(ChainLink::Redirect),
+            "retry" => Some(ChainLink::Retry),
+            _ => None,
+        }
+    }
+}
+
+/// One transaction of a chain
+#[derive(Debug, Clone)]
+pub struct ChainRow {
+    pub request_id: String,
+    pub parent_id: Option<String>,
+    pub link: Option<ChainLink>,
+    pub method: String,
+    pub url: String,
+    pub status: Option<i32>,
+    pub timestamp: i64,
+}
+
+/// Absolute URL a redirect response points at, resolving a relative Location
+/// against the request URL
+pub fn redirect_target(status: i32, request_url: &str, headers: &HashMap<String, String>) -> Option<String> {
+    if !(300..400).contains(&status) {
+        return None;
+    }
+    let location = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("location"))
+        .map(|(_, value)| value.trim())
+        .filter(|value| !value.is_empty())?;
+
+    let target = reqwest::Url::parse(request_url).ok()?.join(location).ok()?;
+    Some(strip_fragment(target))
+}
+
+/// Browsers never send the fragment, so a Location with one would not match
+fn strip_fragment(mut url: reqwest::Url) -> String {
+    url.set_fragment(None);
+    url.to_string()
+}
+
+/// Status codes after which clients and tools typically retry
+fn is_retryable_status(status: i32) -> bool {
+    status == 429 || (500..600).contains(&status)
+}
+
+impl super::Database {
+    /// Earlier transaction of `agent_id` that a new request follows from, if any.
+    /// Redirects win over retries; a transaction is parent to at most one other.
+    pub(crate) async fn find_chain_parent(
+        pool: &Pool<Sqlite>,
+        agent_id: &str,
+        method: &str,
+        url: &str,
+        body: &[u8],
+        now: i64,
+    ) -> Result<Option<(String, ChainLink)>, sqlx::Error> {
+        let since = now - CHAIN_WINDOW_SECS;
+        let normalized = reqwest::Url::parse(url).map(strip_fragment).unwrap_or_else(|_| url.to_string());
+
+        let redirect = sqlx::query(
+            r#"
+            SELECT request_id FROM http_transactions t
+            WHERE agent_id = ? AND redirect_url = ? AND res_timestamp >= ?
+              AND NOT EXISTS (SELECT 1 FROM http_transactions c WHERE c.parent_id = t.request_id)
+            ORDER BY res_timestamp DESC LIMIT 1
+            "#,
+        )
+        .bind(agent_id)
+        .bind(&normalized)
+        .bind(since)
+        .fetch_optional(pool)
+        .await?;
+        if let Some(row) = redirect {
+            return Ok(Some((row.get("request_id"), ChainLink::Redirect)));
+        }
+
+        let previous = sqlx::query(
+            r#"
+            SELECT request_id, res_status FROM http_transactions t
+            WHERE agent_id = ? AND req_method = ? AND req_url = ? AND req_body = ? AND res_timestamp >= ?
+              AND NOT EXISTS (SELECT 1 FROM http_transactions c WHERE c.parent_id = t.request_id)
+            ORDER BY res_timestamp DESC LIMIT 1
+            "#,
+        )
+        .bind(agent_id)
+        .bind(method)
+        .bind(url)
+        .bind(body)
+        .bind(since)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(previous.and_then(|row| {
+            let status: Option<i32> = row.get("res_status");
+            status
+                .filter(|s| is_retryable_status(*s))
+                .map(|_| (row.get("request_id"), ChainLink::Retry))
+        }))
+    }
+
+    /// Where the response to `request_id` redirects, if it is a redirect
+    pub(crate) async fn redirect_url_for(
+        pool: &Pool<Sqlite>,
+        request_id: &str,
+        status: i32,
+        headers: &HashMap<String, String>,
+    ) -> Result<Option<String>, sqlx::Error> {
+        if !(300..400).contains(&status) {
+            return Ok(None);
+        }
+        let request_url: Option<String> = sqlx::query("SELECT req_url FROM http_transactions WHERE request_id = ?")
+            .bind(request_id)
+            .fetch_optional(pool)
+            .await?
+            .map(|row| row.get("req_url"));
+
+        Ok(request_url.and_then(|url| redirect_target(status, &url, headers)))
+    }
+
+    /// The redirect/retry chain `request_id` belongs to, from the first
+    /// request to the last
+    pub async fn get_transaction_chain(&self, request_id: &str) -> Result<Vec<ChainRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        // Walk up to the root, then collect everything below it
+        let rows = sqlx::query(
+            r#"
+            WITH RECURSIVE
+                ancestors(request_id, parent_id) AS (
+                    SELECT request_id, parent_id FROM http_transactions WHERE request_id = ?
+                    UNION ALL
+                    SELECT t.request_id, t.parent_id FROM http_transactions t
+                    JOIN ancestors a ON t.request_id = a.parent_id
+                ),
+                chain(request_id, depth) AS (
+                    SELECT request_id, 0 FROM ancestors WHERE parent_id IS NULL
+                        OR parent_id NOT IN (SELECT request_id FROM http_transactions)
+                    UNION ALL
+                    SELECT t.request_id, c.depth + 1 FROM http_transactions t
+                    JOIN chain c ON t.parent_id = c.request_id
+                )
+            SELECT t.request_id, t.parent_id, t.parent_link, t.req_method, t.req_url, t.res_status, t.req_timestamp
+            FROM chain c JOIN http_transactions t ON t.request_id = c.request_id
+            ORDER BY c.depth, t.req_timestamp
+            "#,
+        )
+        .bind(request_id)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ChainRow {
+                request_id: row.get("request_id"),
+                parent_id: row.get("parent_id"),
+                link: row
+                    .get::<Option<String>, _>("parent_link")
+                    .as_deref()
+                    .and_then(ChainLink::from_tag),
+                method: row.get("req_method"),
+                url: row.get("req_url"),
+                status: row.get("res_status"),
+                timestamp: row.get("req_timestamp"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::{traffic_event, HttpHeaders, HttpRequestData, HttpResponseData, TrafficEvent};
+    use crate::Database;
+    use tempfile::TempDir;
+
+    async fn request(db: &Database, id: &str, method: &str, url: &str) {
+        let event = TrafficEvent {
+            request_id: id.to_string(),
+            event: Some(traffic_event::Event::Request(HttpRequestData {
+                method: method.to_string(),
+                url: url.to_string(),
+                ..Default::default()
+            })),
+        };
+        db.save_request(&event, "agent-1").await.unwrap();
+    }
+
+    async fn response(db: &Database, id: &str, status: i32, location: Option<&str>) {
+        let headers = location
+            .map(|l| HashMap::from([("Location".to_string(), l.to_string())]))
+            .unwrap_or_default();
+        let event = TrafficEvent {
+            request_id: id.to_string(),
+            event: Some(traffic_event::Event::Response(HttpResponseData {
+                status_code: status,
+                headers: Some(HttpHeaders { headers }),
+                ..Default::default()
+            })),
+        };
+        db.save_request(&event, "agent-1").await.unwrap();
+    }
+
+    #[test]
+    fn test_redirect_target() {
+        let location = |l: &str| HashMap::from([("location".to_string(), l.to_string())]);
+        assert_eq!(
+            redirect_target(302, "https://a.test/login?next=1", &location("/home#top")),
+            Some("https://a.test/home".to_string())
+        );
+        assert_eq!(
+            redirect_target(301, "http://a.test/x", &location("https://b.test/y")),
+            Some("https://b.test/y".to_string())
+        );
+        assert_eq!(redirect_target(200, "https://a.test/", &location("/home")), None);
+        assert_eq!(redirect_target(302, "https://a.test/", &HashMap::new()), None);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_and_retry_chain() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+
+        request(&db, "login", "POST", "https://a.test/login").await;
+        response(&db, "login", 302, Some("/dashboard")).await;
+        request(&db, "dash-1", "GET", "https://a.test/dashboard").await;
+        response(&db, "dash-1", 503, None).await;
+        request(&db, "dash-2", "GET", "https://a.test/dashboard").await;
+        response(&db, "dash-2", 200, None).await;
+        // A plain reload after a success is not a retry
+        request(&db, "dash-3", "GET", "https://a.test/dashboard").await;
+        request(&db, "other", "GET", "https://a.test/other").await;
+
+        let chain = db.get_transaction_chain("dash-1").await.unwrap();
+        let ids: Vec<_> = chain.iter().map(|r| r.request_id.as_str()).collect();
+        assert_eq!(ids, vec!["login", "dash-1", "dash-2"]);
+        assert_eq!(chain[0].link, None);
+        assert_eq!(chain[1].link, Some(ChainLink::Redirect));
+        assert_eq!(chain[1].parent_id.as_deref(), Some("login"));
+        assert_eq!(chain[2].link, Some(ChainLink::Retry));
+        assert_eq!(chain[2].status, Some(200));
+
+        let lone = db.get_transaction_chain("dash-3").await.unwrap();
+        assert_eq!(lone.len(), 1);
+        assert!(db.get_transaction_chain("missing").await.unwrap().is_empty());
+    }
+}
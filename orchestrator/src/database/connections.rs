@@ -0,0 +1,173 @@
+//! Database operations for connection-level events
+
+use crate::pb::{connection_event::Kind, ConnectionEvent};
+use sqlx::Row;
+
+/// A stored connection event
+#[derive(Debug, Clone)]
+pub struct ConnectionEventRow {
+    pub id: String,
+    pub agent_id: String,
+    pub kind: String,
+    pub target: String,
+    pub client_addr: String,
+    pub reason: String,
+    pub request_id: Option<String>,
+    pub created_at: i64,
+}
+
+/// Value stored in `connection_events.kind`
+pub fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Connect => "connect",
+        Kind::PassThrough => "pass_through",
+        Kind::TlsFailure => "tls_failure",
+        Kind::UpstreamError => "upstream_error",
+        Kind::Blocked => "blocked",
+        Kind::NoTraffic => "no_traffic",
+        Kind::AutoPassThrough => "auto_pass_through",
+    }
+}
+
+pub fn kind_from_name(name: &str) -> Option<Kind> {
+    [
+        Kind::Connect,
+        Kind::PassThrough,
+        Kind::TlsFailure,
+        Kind::UpstreamError,
+        Kind::Blocked,
+        Kind::NoTraffic,
+        Kind::AutoPassThrough,
+    ]
+    .into_iter()
+    .find(|kind| kind_name(*kind) == name)
+}
+
+impl super::Database {
+    pub async fn save_connection_event(
+        &self,
+        agent_id: &str,
+        event: &ConnectionEvent,
+        request_id: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO connection_events
+                (id, agent_id, kind, target, client_addr, reason, request_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(agent_id)
+        .bind(kind_name(event.kind()))
+        .bind(&event.target)
+        .bind(&event.client_addr)
+        .bind(&event.reason)
+        .bind(request_id.filter(|id| !id.is_empty()))
+        .bind(chrono::Utc::now().timestamp_millis())
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Events, newest first, optionally of one kind, from one agent, or
+    /// whose target contains `target`
+    pub async fn list_connection_events(
+        &self,
+        kind: Option<&str>,
+        agent_id: Option<&str>,
+        target: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<ConnectionEventRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM connection_events
+            WHERE (? IS NULL OR kind = ?)
+              AND (? IS NULL OR agent_id = ?)
+              AND (? IS NULL OR instr(target, ?) > 0)
+            ORDER BY created_at DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(kind)
+        .bind(kind)
+        .bind(agent_id)
+        .bind(agent_id)
+        .bind(target)
+        .bind(target)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ConnectionEventRow {
+                id: row.get("id"),
+                agent_id: row.get("agent_id"),
+                kind: row.get("kind"),
+                target: row.get("target"),
+                client_addr: row.get("client_addr"),
+                reason: row.get("reason"),
+                request_id: row.get("request_id"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_save_and_filter_connection_events() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+
+        let event = |kind: Kind, target: &str, reason: &str| ConnectionEvent {
+            kind: kind as i32,
+            target: target.to_string(),
+            client_addr: "10.0.0.5:51000".to_string(),
+            reason: reason.to_string(),
+        };
+        db.save_connection_event("a1", &event(Kind::Connect, "app.test:443", ""), Some(""))
+            .await
+            .unwrap();
+        db.save_connection_event("a1", &event(Kind::NoTraffic, "pinned.test:443", "no request"), None)
+            .await
+            .unwrap();
+        db.save_connection_event(
+            "a2",
+            &event(Kind::TlsFailure, "https://app.test/login", "invalid peer certificate"),
+            Some("r1"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_connection_events(None, None, None, 10).await.unwrap().len(), 3);
+        let failures = db
+            .list_connection_events(Some("tls_failure"), None, Some("app.test"), 10)
+            .await
+            .unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].request_id.as_deref(), Some("r1"));
+        assert_eq!(failures[0].reason, "invalid peer certificate");
+
+        let connects = db.list_connection_events(Some("connect"), Some("a1"), None, 10).await.unwrap();
+        assert_eq!(connects.len(), 1);
+        assert_eq!(connects[0].request_id, None);
+    }
+}
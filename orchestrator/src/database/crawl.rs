@@ -0,0 +1,117 @@
+//! Database operations for DOM crawl results
+//!
+//! Routes discovered by the browser crawler are merged into a single
+//! per-project table keyed by URL, so repeated crawls enrich the site map.
+
+use sqlx::Row;
+use tracing::info;
+
+/// Discovered route as stored in database
+#[derive(Debug, Clone)]
+pub struct DiscoveredRouteRow {
+    pub url: String,
+    pub crawl_id: String,
+    pub host: String,
+    pub depth: i64,
+    pub source: String,
+    pub title: Option<String>,
+    pub visited: bool,
+    pub discovered_at: i64,
+}
+
+impl super::Database {
+    /// Save routes from a crawl. Known URLs keep their original discovery
+    /// record but are upgraded to visited / titled when the new crawl rendered them.
+    /// New routes are added to the site map (without hits) by a trigger.
+    pub async fn save_discovered_routes(
+        &self,
+        routes: &[DiscoveredRouteRow],
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let mut tx = pool.begin().await?;
+        for route in routes {
+            sqlx::query(
+                r#"
+                INSERT INTO discovered_routes (
+                    url, crawl_id, host, depth, source, title, visited, discovered_at
+                )
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                ON CONFLICT(url) DO UPDATE SET
+                    title = COALESCE(excluded.title, discovered_routes.title),
+                    visited = MAX(discovered_routes.visited, excluded.visited)
+                "#,
+            )
+            .bind(&route.url)
+            .bind(&route.crawl_id)
+            .bind(&route.host)
+            .bind(route.depth)
+            .bind(&route.source)
+            .bind(&route.title)
+            .bind(route.visited)
+            .bind(route.discovered_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        info!("✓ Saved {} discovered route(s)", routes.len());
+        Ok(())
+    }
+
+    /// List discovered routes, optionally filtered by host
+    pub async fn list_discovered_routes(
+        &self,
+        host: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<DiscoveredRouteRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = if let Some(host) = host {
+            sqlx::query(
+                r#"
+                SELECT url, crawl_id, host, depth, source, title, visited, discovered_at
+                FROM discovered_routes
+                WHERE host = ?
+                ORDER BY url ASC
+                LIMIT ?
+                "#,
+            )
+            .bind(host)
+            .bind(limit)
+            .fetch_all(&pool)
+            .await?
+        } else {
+            sqlx::query(
+                r#"
+                SELECT url, crawl_id, host, depth, source, title, visited, discovered_at
+                FROM discovered_routes
+                ORDER BY host ASC, url ASC
+                LIMIT ?
+                "#,
+            )
+            .bind(limit)
+            .fetch_all(&pool)
+            .await?
+        };
+
+        Ok(rows
+            .into_iter()
+            .map(|r| DiscoveredRouteRow {
+                url: r.get("url"),
+                crawl_id: r.get("crawl_id"),
+                host: r.get("host"),
+                depth: r.get("depth"),
+                source: r.get("source"),
+                title: r.get("title"),
+                visited: r.get("visited"),
+                discovered_at: r.get("discovered_at"),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,145 @@
+//! Database operations for content discovery results
+
+use sqlx::Row;
+
+/// A path found by a discovery run
+#[derive(Debug, Clone)]
+pub struct DiscoveryResultRow {
+    pub id: String,
+    pub run_id: String,
+    pub url: String,
+    /// Relative to the run's base URL
+    pub path: String,
+    pub status_code: i32,
+    /// Response body length in bytes
+    pub size: i64,
+    pub title: Option<String>,
+    pub is_directory: bool,
+    pub depth: u32,
+    pub agent_id: String,
+    pub created_at: i64,
+}
+
+impl super::Database {
+    /// Record a finding; a URL is stored once per run
+    pub async fn save_discovery_result(&self, result: &DiscoveryResultRow) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO discovery_results
+                (id, run_id, url, path, status_code, size, title, is_directory, depth, agent_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&result.id)
+        .bind(&result.run_id)
+        .bind(&result.url)
+        .bind(&result.path)
+        .bind(result.status_code)
+        .bind(result.size)
+        .bind(&result.title)
+        .bind(result.is_directory)
+        .bind(result.depth as i64)
+        .bind(&result.agent_id)
+        .bind(result.created_at)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Findings ordered by path, of one run or of all runs
+    pub async fn list_discovery_results(
+        &self,
+        run_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<DiscoveryResultRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT * FROM discovery_results WHERE (? IS NULL OR run_id = ?) ORDER BY run_id, path LIMIT ?",
+        )
+        .bind(run_id)
+        .bind(run_id)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| DiscoveryResultRow {
+                id: row.get("id"),
+                run_id: row.get("run_id"),
+                url: row.get("url"),
+                path: row.get("path"),
+                status_code: row.get("status_code"),
+                size: row.get("size"),
+                title: row.get("title"),
+                is_directory: row.get("is_directory"),
+                depth: row.get::<i64, _>("depth") as u32,
+                agent_id: row.get("agent_id"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    /// Delete the findings of a run; returns how many were deleted
+    pub async fn delete_discovery_results(&self, run_id: &str) -> Result<u64, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        let result = sqlx::query("DELETE FROM discovery_results WHERE run_id = ?")
+            .bind(run_id)
+            .execute(&pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiscoveryResultRow;
+    use crate::Database;
+    use tempfile::TempDir;
+
+    fn row(run_id: &str, path: &str) -> DiscoveryResultRow {
+        DiscoveryResultRow {
+            id: uuid::Uuid::new_v4().to_string(),
+            run_id: run_id.to_string(),
+            url: format!("https://a.test/{}", path),
+            path: path.to_string(),
+            status_code: 200,
+            size: 512,
+            title: Some("Admin".to_string()),
+            is_directory: path.ends_with('/'),
+            depth: 0,
+            agent_id: "agent-1".to_string(),
+            created_at: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_results_are_stored_once_per_run_and_deleted_with_it() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+
+        for result in [row("run-1", "admin/"), row("run-1", "admin/"), row("run-1", "backup.zip"), row("run-2", "admin/")] {
+            db.save_discovery_result(&result).await.unwrap();
+        }
+        let results = db.list_discovery_results(Some("run-1"), 10).await.unwrap();
+        let paths: Vec<&str> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["admin/", "backup.zip"]);
+        assert!(results[0].is_directory);
+        assert_eq!(results[0].title.as_deref(), Some("Admin"));
+
+        assert_eq!(db.delete_discovery_results("run-1").await.unwrap(), 2);
+        assert_eq!(db.list_discovery_results(None, 10).await.unwrap().len(), 1);
+    }
+}
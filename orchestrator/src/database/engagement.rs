@@ -0,0 +1,174 @@
+//! Database operations for rules of engagement
+
+use super::SettingsError;
+use crate::models::engagement::{
+    AllowlistOverride, EngagementAuditEntry, EngagementEvent, EngagementSchedule, TargetAllowlist,
+};
+use sqlx::Row;
+
+impl super::Database {
+    /// Get the target allowlist
+    pub async fn get_target_allowlist(&self) -> Result<TargetAllowlist, sqlx::Error> {
+        Ok(self.get_setting("target_allowlist").await?.unwrap_or_default())
+    }
+
+    /// Save the target allowlist if nobody saved it since `expected_version` was read
+    pub async fn save_target_allowlist(
+        &self,
+        allowlist: &TargetAllowlist,
+        expected_version: u64,
+    ) -> Result<TargetAllowlist, SettingsError> {
+        self.save_versioned_setting("target_allowlist", allowlist, expected_version).await
+    }
+
+    /// Get the engagement schedule
+    pub async fn get_engagement_schedule(&self) -> Result<EngagementSchedule, sqlx::Error> {
+        Ok(self.get_setting("engagement_schedule").await?.unwrap_or_default())
+    }
+
+    /// Save the engagement schedule if nobody saved it since `expected_version` was read
+    pub async fn save_engagement_schedule(
+        &self,
+        schedule: &EngagementSchedule,
+        expected_version: u64,
+    ) -> Result<EngagementSchedule, SettingsError> {
+        self.save_versioned_setting("engagement_schedule", schedule, expected_version).await
+    }
+
+    /// Record a new allowlist override
+    pub async fn add_allowlist_override(&self, o: &AllowlistOverride) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO allowlist_overrides (id, host_pattern, reason, granted_by, created_at, expires_at, revoked_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&o.id)
+        .bind(&o.host_pattern)
+        .bind(&o.reason)
+        .bind(&o.granted_by)
+        .bind(o.created_at)
+        .bind(o.expires_at)
+        .bind(o.revoked_at)
+        .execute(&pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Allowlist overrides, newest first; `active_only` drops expired and revoked ones
+    pub async fn list_allowlist_overrides(&self, active_only: bool) -> Result<Vec<AllowlistOverride>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, host_pattern, reason, granted_by, created_at, expires_at, revoked_at
+            FROM allowlist_overrides
+            ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        let now = chrono::Utc::now().timestamp();
+        Ok(rows
+            .iter()
+            .map(|row| AllowlistOverride {
+                id: row.get("id"),
+                host_pattern: row.get("host_pattern"),
+                reason: row.get("reason"),
+                granted_by: row.get("granted_by"),
+                created_at: row.get("created_at"),
+                expires_at: row.get("expires_at"),
+                revoked_at: row.get("revoked_at"),
+            })
+            .filter(|o| !active_only || o.is_active(now))
+            .collect())
+    }
+
+    /// Revoke an override. Returns whether an active override was revoked.
+    pub async fn revoke_allowlist_override(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query("UPDATE allowlist_overrides SET revoked_at = ? WHERE id = ? AND revoked_at IS NULL")
+            .bind(chrono::Utc::now().timestamp())
+            .bind(id)
+            .execute(&pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Append an entry to the engagement audit trail
+    pub async fn record_engagement_event(
+        &self,
+        event: EngagementEvent,
+        tool: Option<&str>,
+        target: Option<&str>,
+        principal: Option<&str>,
+        details: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO engagement_audit (timestamp, event, tool, target, principal, details)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(chrono::Utc::now().timestamp())
+        .bind(event.as_str())
+        .bind(tool)
+        .bind(target)
+        .bind(principal)
+        .bind(details)
+        .execute(&pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most recent engagement audit entries, newest first
+    pub async fn list_engagement_audit(&self, limit: i64) -> Result<Vec<EngagementAuditEntry>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, event, tool, target, principal, details
+            FROM engagement_audit
+            ORDER BY id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| EngagementAuditEntry {
+                id: row.get("id"),
+                timestamp: row.get("timestamp"),
+                event: row.get("event"),
+                tool: row.get("tool"),
+                target: row.get("target"),
+                principal: row.get("principal"),
+                details: row.get("details"),
+            })
+            .collect())
+    }
+}
@@ -20,6 +20,32 @@ pub struct FlowProfileRow {
     pub status: String,
 }
 
+impl FlowProfileRow {
+    /// Convert into the flow-engine model used for replay
+    pub fn to_flow_profile(&self) -> Result<flow_engine::FlowProfile, serde_json::Error> {
+        use flow_engine::flow::model::ProfileStatus;
+        use flow_engine::{FlowProfile, FlowType};
+
+        let flow_type = match self.flow_type.as_str() {
+            "Login" => FlowType::Login,
+            "Checkout" => FlowType::Checkout,
+            "FormSubmission" => FlowType::FormSubmission,
+            "Navigation" => FlowType::Navigation,
+            _ => FlowType::Custom(self.flow_type.clone()),
+        };
+
+        let mut profile = FlowProfile::new(&self.name, &self.start_url);
+        profile.id = uuid::Uuid::parse_str(&self.id).unwrap_or_else(|_| uuid::Uuid::new_v4());
+        profile.flow_type = flow_type;
+        profile.steps = serde_json::from_str(&self.steps)?;
+        profile.status = ProfileStatus::Active;
+        if let Some(meta) = self.meta.as_deref().and_then(|m| serde_json::from_str(m).ok()) {
+            profile.meta = meta;
+        }
+        Ok(profile)
+    }
+}
+
 /// Flow execution record
 #[derive(Debug, Clone)]
 pub struct FlowExecutionRow {
@@ -36,6 +62,16 @@ pub struct FlowExecutionRow {
     pub extracted_data: Option<String>, // JSON
 }
 
+/// Reveal of a masked flow step value, as kept in the audit trail
+#[derive(Debug, Clone)]
+pub struct FlowRevealAuditRow {
+    pub id: i64,
+    pub timestamp: i64,
+    pub profile_id: String,
+    pub step_index: i64,
+    pub principal: Option<String>,
+}
+
 impl super::Database {
     // ========== Flow Profiles ==========
 
@@ -445,4 +481,56 @@ impl super::Database {
         }
         Ok(count)
     }
+
+    // ========== Reveal Audit ==========
+
+    /// Record that the typed value of a masked step was revealed
+    pub async fn record_flow_reveal(
+        &self,
+        profile_id: &str,
+        step_index: i64,
+        principal: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            "INSERT INTO flow_reveal_audit (timestamp, profile_id, step_index, principal) VALUES (?, ?, ?, ?)",
+        )
+        .bind(chrono::Utc::now().timestamp())
+        .bind(profile_id)
+        .bind(step_index)
+        .bind(principal)
+        .execute(&pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Most recent reveals of masked values, newest first
+    pub async fn list_flow_reveals(&self, limit: i64) -> Result<Vec<FlowRevealAuditRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT id, timestamp, profile_id, step_index, principal FROM flow_reveal_audit ORDER BY id DESC LIMIT ?",
+        )
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|r| FlowRevealAuditRow {
+                id: r.get("id"),
+                timestamp: r.get("timestamp"),
+                profile_id: r.get("profile_id"),
+                step_index: r.get("step_index"),
+                principal: r.get("principal"),
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,70 @@
+//! Database operations for gRPC descriptor sets
+
+use sqlx::Row;
+
+/// Uploaded protobuf descriptor set
+#[derive(Debug, Clone)]
+pub struct GrpcDescriptorSetRow {
+    pub id: String,
+    pub name: String,
+    /// Serialized `FileDescriptorSet`
+    pub descriptor_set: Vec<u8>,
+    pub created_at: i64,
+}
+
+impl super::Database {
+    /// Store a descriptor set for decoding captured gRPC calls
+    pub async fn save_grpc_descriptor_set(&self, name: &str, descriptor_set: &[u8]) -> Result<String, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        sqlx::query("INSERT INTO grpc_descriptor_sets (id, name, descriptor_set, created_at) VALUES (?, ?, ?, ?)")
+            .bind(&id)
+            .bind(name)
+            .bind(descriptor_set)
+            .bind(chrono::Utc::now().timestamp())
+            .execute(&pool)
+            .await?;
+
+        Ok(id)
+    }
+
+    /// All descriptor sets, oldest first
+    pub async fn list_grpc_descriptor_sets(&self) -> Result<Vec<GrpcDescriptorSetRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT id, name, descriptor_set, created_at FROM grpc_descriptor_sets ORDER BY created_at, id",
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| GrpcDescriptorSetRow {
+                id: row.get("id"),
+                name: row.get("name"),
+                descriptor_set: row.get("descriptor_set"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    pub async fn delete_grpc_descriptor_set(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query("DELETE FROM grpc_descriptor_sets WHERE id = ?")
+            .bind(id)
+            .execute(&pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,209 @@
+//! HAR 1.2 export
+//!
+//! Stored transactions as an HTTP Archive for other tools. Annotations go
+//! along: the comment in each entry's standard `comment` field, the
+//! highlight color and tags in a `_proxxy` custom field.
+
+use super::annotations::RequestAnnotation;
+use crate::pb::HttpHeaders;
+use base64::Engine as _;
+use serde_json::{json, Value};
+use sqlx::Row;
+
+fn har_headers(json: Option<String>) -> (Vec<Value>, Option<String>) {
+    let headers = json
+        .and_then(|json| serde_json::from_str::<Option<HttpHeaders>>(&json).ok().flatten())
+        .map(|h| h.headers)
+        .unwrap_or_default();
+    let mut pairs: Vec<(String, String)> = headers.into_iter().collect();
+    pairs.sort();
+
+    let content_type = pairs
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.clone());
+    let list = pairs
+        .into_iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect();
+    (list, content_type)
+}
+
+/// Body as HAR text, base64 encoded when it is not UTF-8
+fn har_text(body: &[u8]) -> (String, Option<&'static str>) {
+    match std::str::from_utf8(body) {
+        Ok(text) => (text.to_string(), None),
+        Err(_) => (base64::engine::general_purpose::STANDARD.encode(body), Some("base64")),
+    }
+}
+
+fn har_entry(row: &sqlx::sqlite::SqliteRow) -> Value {
+    let annotation = RequestAnnotation::from_row(row);
+    let started: i64 = row.get("req_timestamp");
+    let duration: Option<i64> = row.get("duration_ms");
+
+    let (request_headers, request_type) = har_headers(row.get("req_headers"));
+    let request_body: Option<Vec<u8>> = row.get("req_body");
+    let mut request = json!({
+        "method": row.get::<String, _>("req_method"),
+        "url": row.get::<String, _>("req_url"),
+        "httpVersion": "HTTP/1.1",
+        "cookies": [],
+        "headers": request_headers,
+        "queryString": [],
+        "headersSize": -1,
+        "bodySize": request_body.as_ref().map(|b| b.len() as i64).unwrap_or(0),
+    });
+    if let Some(body) = request_body.filter(|b| !b.is_empty()) {
+        let (text, _) = har_text(&body);
+        request["postData"] = json!({
+            "mimeType": request_type.unwrap_or_default(),
+            "text": text,
+        });
+    }
+
+    let (response_headers, response_type) = har_headers(row.get("res_headers"));
+    let response_body: Vec<u8> = row.get::<Option<Vec<u8>>, _>("res_body").unwrap_or_default();
+    let (text, encoding) = har_text(&response_body);
+    let mut content = json!({
+        "size": response_body.len(),
+        "mimeType": response_type.unwrap_or_default(),
+        "text": text,
+    });
+    if let Some(encoding) = encoding {
+        content["encoding"] = json!(encoding);
+    }
+    let response = json!({
+        "status": row.get::<Option<i32>, _>("res_status").unwrap_or(0),
+        "statusText": "",
+        "httpVersion": row.get::<Option<String>, _>("res_protocol").unwrap_or_else(|| "HTTP/1.1".to_string()),
+        "cookies": [],
+        "headers": response_headers,
+        "content": content,
+        "redirectURL": row.get::<Option<String>, _>("redirect_url").unwrap_or_default(),
+        "headersSize": -1,
+        "bodySize": response_body.len(),
+    });
+
+    let mut entry = json!({
+        "startedDateTime": chrono::DateTime::from_timestamp(started, 0).map(|t| t.to_rfc3339()).unwrap_or_default(),
+        "time": duration.unwrap_or(0),
+        "request": request,
+        "response": response,
+        "cache": {},
+        "timings": { "send": 0, "wait": duration.unwrap_or(0), "receive": 0 },
+        "_proxxy": {
+            "requestId": row.get::<String, _>("request_id"),
+            "highlight": annotation.color.map(|c| c.as_str()),
+            "tags": annotation.tags,
+        },
+    });
+    if let Some(comment) = annotation.comment {
+        entry["comment"] = json!(comment);
+    }
+    entry
+}
+
+impl super::Database {
+    /// HAR log of the newest `limit` transactions, oldest first, optionally
+    /// only those tagged `tag`
+    pub async fn export_har(&self, tag: Option<&str>, limit: i64) -> Result<Value, sqlx::Error> {
+        let entries = match self.get_pool().await.ok() {
+            Some(pool) => {
+                let rows = sqlx::query(
+                    r#"
+                    SELECT * FROM (
+                        SELECT request_id, req_method, req_url, req_headers, req_body, req_timestamp,
+                            res_status, res_headers, res_body, res_protocol, redirect_url, duration_ms,
+                            comment, highlight_color, tags
+                        FROM http_transactions
+                        WHERE ? IS NULL OR EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ?)
+                        ORDER BY req_timestamp DESC LIMIT ?
+                    ) ORDER BY req_timestamp, request_id
+                    "#,
+                )
+                .bind(tag)
+                .bind(tag)
+                .bind(limit)
+                .fetch_all(&pool)
+                .await?;
+                rows.iter().map(har_entry).collect()
+            }
+            None => Vec::new(),
+        };
+
+        Ok(json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": "Proxxy", "version": env!("CARGO_PKG_VERSION") },
+                "entries": entries,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::database::HighlightColor;
+    use crate::pb::{traffic_event, HttpHeaders, HttpRequestData, HttpResponseData, TrafficEvent};
+    use crate::Database;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_export_har_carries_annotations() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+
+        for (id, body) in [("r1", b"{\"ok\":true}".to_vec()), ("r2", vec![0xff, 0x00])] {
+            let request = TrafficEvent {
+                request_id: id.to_string(),
+                event: Some(traffic_event::Event::Request(HttpRequestData {
+                    method: "POST".to_string(),
+                    url: format!("https://a.test/{}", id),
+                    headers: Some(HttpHeaders {
+                        headers: HashMap::from([("Content-Type".to_string(), "application/json".to_string())]),
+                    }),
+                    body: b"x=1".to_vec().into(),
+                    ..Default::default()
+                })),
+            };
+            db.save_request(&request, "agent-1").await.unwrap();
+            let response = TrafficEvent {
+                request_id: id.to_string(),
+                event: Some(traffic_event::Event::Response(HttpResponseData {
+                    status_code: 200,
+                    body: body.into(),
+                    ..Default::default()
+                })),
+            };
+            db.save_request(&response, "agent-1").await.unwrap();
+        }
+        db.set_request_comment("r1", Some("token in body")).await.unwrap();
+        db.set_request_color("r1", Some(HighlightColor::Orange)).await.unwrap();
+        db.set_request_tags("r1", &["secrets".to_string()]).await.unwrap();
+
+        let har = db.export_har(None, 10).await.unwrap();
+        let entries = har["log"]["entries"].as_array().unwrap();
+        assert_eq!(har["log"]["version"], "1.2");
+        assert_eq!(entries.len(), 2);
+
+        let r1 = entries.iter().find(|e| e["_proxxy"]["requestId"] == "r1").unwrap();
+        assert_eq!(r1["comment"], "token in body");
+        assert_eq!(r1["_proxxy"]["highlight"], "orange");
+        assert_eq!(r1["_proxxy"]["tags"][0], "secrets");
+        assert_eq!(r1["request"]["postData"]["mimeType"], "application/json");
+        assert_eq!(r1["response"]["content"]["text"], "{\"ok\":true}");
+
+        let r2 = entries.iter().find(|e| e["_proxxy"]["requestId"] == "r2").unwrap();
+        assert!(r2.get("comment").is_none());
+        assert_eq!(r2["response"]["content"]["encoding"], "base64");
+        assert_eq!(r2["response"]["content"]["text"], "/wA=");
+
+        let tagged = db.export_har(Some("secrets"), 10).await.unwrap();
+        assert_eq!(tagged["log"]["entries"].as_array().unwrap().len(), 1);
+    }
+}
@@ -33,6 +33,12 @@ pub struct IntruderResult {
     pub status_code: Option<i32>,
     pub response_length: Option<i64>,
     pub is_highlighted: bool,
+    /// Highlight rules the result matched, with what they saw
+    #[serde(default)]
+    pub highlight_reason: Option<String>,
+    /// Source IP the target saw; filled from the agent's last report when unset
+    #[serde(default)]
+    pub egress_ip: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +50,40 @@ pub struct PayloadSet {
     pub created_at: i64,
 }
 
+/// Insert one result; without an egress IP it gets the agent's last reported one
+async fn insert_result<'e, E>(executor: E, result: &IntruderResult) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'e, Database = Sqlite>,
+{
+    sqlx::query(
+        r#"
+        INSERT INTO intruder_results (
+            id, attack_id, request_data, response_data, agent_id, 
+            payload_values, executed_at, duration_ms, status_code, 
+            response_length, is_highlighted, highlight_reason, egress_ip
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, COALESCE(?, (SELECT egress_ip FROM agents WHERE id = ?)))
+        "#,
+    )
+    .bind(&result.id)
+    .bind(&result.attack_id)
+    .bind(&result.request_data)
+    .bind(&result.response_data)
+    .bind(&result.agent_id)
+    .bind(&result.payload_values)
+    .bind(result.executed_at)
+    .bind(result.duration_ms)
+    .bind(result.status_code)
+    .bind(result.response_length)
+    .bind(result.is_highlighted)
+    .bind(&result.highlight_reason)
+    .bind(&result.egress_ip)
+    .bind(&result.agent_id)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
 /// Buffered result writer for high-volume intruder results
 #[derive(Clone)]
 pub struct IntruderResultBuffer {
@@ -92,29 +132,7 @@ impl IntruderResultBuffer {
         let mut tx = self.pool.begin().await?;
 
         for result in results {
-            sqlx::query(
-                r#"
-                INSERT INTO intruder_results (
-                    id, attack_id, request_data, response_data, agent_id, 
-                    payload_values, executed_at, duration_ms, status_code, 
-                    response_length, is_highlighted
-                )
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                "#,
-            )
-            .bind(&result.id)
-            .bind(&result.attack_id)
-            .bind(&result.request_data)
-            .bind(&result.response_data)
-            .bind(&result.agent_id)
-            .bind(&result.payload_values)
-            .bind(result.executed_at)
-            .bind(result.duration_ms)
-            .bind(result.status_code)
-            .bind(result.response_length)
-            .bind(result.is_highlighted)
-            .execute(&mut *tx)
-            .await?;
+            insert_result(&mut *tx, &result).await?;
         }
 
         tx.commit().await?;
@@ -303,6 +321,285 @@ impl Database {
         Ok(())
     }
 
+    /// Link (or unlink) a flow used to mint sessions for an attack
+    pub async fn set_intruder_attack_flow_session(
+        &self,
+        attack_id: &str,
+        flow_session: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET flow_session = ? WHERE id = ?")
+            .bind(flow_session)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Flow session configuration (JSON) linked to an attack
+    pub async fn get_intruder_attack_flow_session(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let flow_session: Option<Option<String>> =
+            sqlx::query_scalar("SELECT flow_session FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(flow_session.flatten())
+    }
+
+    /// Set (or clear) the multipart upload template of an attack
+    pub async fn set_intruder_attack_upload_template(
+        &self,
+        attack_id: &str,
+        upload_template: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET upload_template = ? WHERE id = ?")
+            .bind(upload_template)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Multipart upload template (JSON) of an attack
+    pub async fn get_intruder_attack_upload_template(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let upload_template: Option<Option<String>> =
+            sqlx::query_scalar("SELECT upload_template FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(upload_template.flatten())
+    }
+
+    /// Set (or clear) the GraphQL settings of an attack
+    pub async fn set_intruder_attack_graphql_config(
+        &self,
+        attack_id: &str,
+        graphql_config: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET graphql_config = ? WHERE id = ?")
+            .bind(graphql_config)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// GraphQL settings (JSON) of an attack
+    pub async fn get_intruder_attack_graphql_config(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let graphql_config: Option<Option<String>> =
+            sqlx::query_scalar("SELECT graphql_config FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(graphql_config.flatten())
+    }
+
+    /// Store (or clear) the checkpoint of a paused attack
+    pub async fn set_intruder_attack_checkpoint(
+        &self,
+        attack_id: &str,
+        checkpoint: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET checkpoint = ? WHERE id = ?")
+            .bind(checkpoint)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Checkpoint (JSON) of a paused attack
+    pub async fn get_intruder_attack_checkpoint(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let checkpoint: Option<Option<String>> =
+            sqlx::query_scalar("SELECT checkpoint FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(checkpoint.flatten())
+    }
+
+    /// Set (or clear) the highlight rules of an attack
+    pub async fn set_intruder_attack_highlight_rules(
+        &self,
+        attack_id: &str,
+        highlight_rules: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET highlight_rules = ? WHERE id = ?")
+            .bind(highlight_rules)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Highlight rules (JSON) of an attack
+    pub async fn get_intruder_attack_highlight_rules(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let highlight_rules: Option<Option<String>> =
+            sqlx::query_scalar("SELECT highlight_rules FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(highlight_rules.flatten())
+    }
+
+    /// Set (or clear) the request steps of a sequence attack
+    pub async fn set_intruder_attack_sequence(&self, attack_id: &str, sequence: Option<&str>) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET sequence = ? WHERE id = ?")
+            .bind(sequence)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Request steps (JSON) of a sequence attack
+    pub async fn get_intruder_attack_sequence(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let sequence: Option<Option<String>> =
+            sqlx::query_scalar("SELECT sequence FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(sequence.flatten())
+    }
+
+    /// Set (or clear) the measured baseline of an attack
+    pub async fn set_intruder_attack_baseline(&self, attack_id: &str, baseline: Option<&str>) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET baseline = ? WHERE id = ?")
+            .bind(baseline)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Measured baseline (JSON) of an attack
+    pub async fn get_intruder_attack_baseline(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let baseline: Option<Option<String>> =
+            sqlx::query_scalar("SELECT baseline FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(baseline.flatten())
+    }
+
+    /// Set (or clear) the execution settings of an attack
+    pub async fn set_intruder_attack_execution_config(
+        &self,
+        attack_id: &str,
+        execution_config: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE intruder_attacks SET execution_config = ? WHERE id = ?")
+            .bind(execution_config)
+            .bind(attack_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Execution settings (JSON) of an attack
+    pub async fn get_intruder_attack_execution_config(&self, attack_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let execution_config: Option<Option<String>> =
+            sqlx::query_scalar("SELECT execution_config FROM intruder_attacks WHERE id = ?")
+                .bind(attack_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(execution_config.flatten())
+    }
+
+    /// Stored response (JSON serialized HttpResponseData) of one intruder result
+    pub async fn get_intruder_result_response(&self, result_id: &str) -> Result<Option<String>, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let response: Option<Option<String>> =
+            sqlx::query_scalar("SELECT response_data FROM intruder_results WHERE id = ?")
+                .bind(result_id)
+                .fetch_optional(&pool)
+                .await?;
+
+        Ok(response.flatten())
+    }
+
     /// Delete an intruder attack and all its results
     pub async fn delete_intruder_attack(&self, attack_id: &str) -> Result<(), sqlx::Error> {
         let pool = self.get_pool().await.map_err(|e| {
@@ -366,9 +663,9 @@ impl Database {
             INSERT INTO intruder_results (
                 id, attack_id, request_data, response_data, agent_id, 
                 payload_values, executed_at, duration_ms, status_code, 
-                response_length, is_highlighted
+                response_length, is_highlighted, egress_ip
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, (SELECT egress_ip FROM agents WHERE id = ?))
             "#,
         )
         .bind(&id)
@@ -382,12 +679,21 @@ impl Database {
         .bind(status_code)
         .bind(response_length)
         .bind(is_highlighted)
+        .bind(agent_id)
         .execute(&pool)
         .await?;
 
         Ok(id)
     }
 
+    /// Save a complete intruder result as it is
+    pub async fn insert_intruder_result(&self, result: &IntruderResult) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        insert_result(&pool, result).await
+    }
+
     /// Get intruder results for an attack
     pub async fn get_intruder_results(
         &self,
@@ -407,7 +713,7 @@ impl Database {
             r#"
             SELECT id, attack_id, request_data, response_data, agent_id, 
                    payload_values, executed_at, duration_ms, status_code, 
-                   response_length, is_highlighted
+                   response_length, is_highlighted, highlight_reason, egress_ip
             FROM intruder_results 
             WHERE attack_id = ? 
             ORDER BY executed_at DESC 
@@ -434,6 +740,8 @@ impl Database {
                 status_code: row.get("status_code"),
                 response_length: row.get("response_length"),
                 is_highlighted: row.get("is_highlighted"),
+                highlight_reason: row.get("highlight_reason"),
+                egress_ip: row.get("egress_ip"),
             });
         }
 
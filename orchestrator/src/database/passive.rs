@@ -0,0 +1,125 @@
+//! Database operations for passive check findings
+
+use sqlx::Row;
+
+/// A custom passive check that matched a stored response
+#[derive(Debug, Clone)]
+pub struct PassiveFindingRow {
+    pub id: String,
+    pub request_id: String,
+    pub check_id: String,
+    pub check_name: String,
+    pub severity: String,
+    pub description: String,
+    pub evidence: String,
+    pub created_at: i64,
+}
+
+impl super::Database {
+    /// Record that check `check_id` matched `request_id`; a check reports a
+    /// transaction once, later matches are ignored
+    pub async fn save_passive_finding(
+        &self,
+        request_id: &str,
+        check_id: &str,
+        check_name: &str,
+        severity: &str,
+        description: &str,
+        evidence: &str,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO passive_findings
+                (id, request_id, check_id, check_name, severity, description, evidence, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(request_id)
+        .bind(check_id)
+        .bind(check_name)
+        .bind(severity)
+        .bind(description)
+        .bind(evidence)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Findings, newest first, optionally for one transaction
+    pub async fn list_passive_findings(
+        &self,
+        request_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<PassiveFindingRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT * FROM passive_findings WHERE (? IS NULL OR request_id = ?) ORDER BY created_at DESC, check_id LIMIT ?",
+        )
+        .bind(request_id)
+        .bind(request_id)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| PassiveFindingRow {
+                id: row.get("id"),
+                request_id: row.get("request_id"),
+                check_id: row.get("check_id"),
+                check_name: row.get("check_name"),
+                severity: row.get("severity"),
+                description: row.get("description"),
+                evidence: row.get("evidence"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pb::{traffic_event, HttpRequestData, TrafficEvent};
+    use crate::Database;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_findings_are_recorded_once_and_follow_their_transaction() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+        let event = TrafficEvent {
+            request_id: "r1".to_string(),
+            event: Some(traffic_event::Event::Request(HttpRequestData {
+                method: "GET".to_string(),
+                url: "https://a.test/".to_string(),
+                ..Default::default()
+            })),
+        };
+        db.save_request(&event, "agent-1").await.unwrap();
+
+        for _ in 0..2 {
+            db.save_passive_finding("r1", "server-version", "Server version", "low", "", "1.18.0")
+                .await
+                .unwrap();
+        }
+        let findings = db.list_passive_findings(Some("r1"), 10).await.unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].evidence, "1.18.0");
+
+        db.delete_requests_by_host("a.test").await.unwrap();
+        assert!(db.list_passive_findings(None, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+//! Database operations for per-protocol traffic views
+
+use sqlx::Row;
+
+/// Distinct endpoint seen for an API protocol
+#[derive(Debug, Clone)]
+pub struct ApiEndpointRow {
+    pub method: String,
+    /// Request URL without its query string
+    pub endpoint: String,
+    pub hits: i64,
+    pub last_seen: i64,
+    /// Most recent transaction to this endpoint
+    pub last_request_id: String,
+}
+
+impl super::Database {
+    /// Number of transactions per API protocol; untagged traffic is left out
+    pub async fn count_api_protocols(&self) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT api_protocol, COUNT(*) AS hits FROM http_transactions
+             WHERE api_protocol IS NOT NULL GROUP BY api_protocol ORDER BY hits DESC",
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| (row.get("api_protocol"), row.get("hits"))).collect())
+    }
+
+    /// Endpoints called with `api_protocol`, most recently seen first
+    pub async fn list_api_endpoints(&self, api_protocol: &str, limit: i64) -> Result<Vec<ApiEndpointRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        // SQLite returns the bare columns from the row holding MAX()
+        let rows = sqlx::query(
+            r#"
+            SELECT req_method, endpoint, COUNT(*) AS hits, MAX(req_timestamp) AS last_seen, request_id
+            FROM (
+                SELECT req_method, req_timestamp, request_id,
+                    CASE WHEN instr(req_url, '?') > 0 THEN substr(req_url, 1, instr(req_url, '?') - 1) ELSE req_url END AS endpoint
+                FROM http_transactions
+                WHERE api_protocol = ?
+            )
+            GROUP BY req_method, endpoint
+            ORDER BY last_seen DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(api_protocol)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| ApiEndpointRow {
+                method: row.get("req_method"),
+                endpoint: row.get("endpoint"),
+                hits: row.get("hits"),
+                last_seen: row.get("last_seen"),
+                last_request_id: row.get("request_id"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pb::{traffic_event, HttpHeaders, HttpRequestData, HttpResponseData, TrafficEvent};
+    use crate::Database;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    fn headers(content_type: &str) -> Option<HttpHeaders> {
+        Some(HttpHeaders {
+            headers: HashMap::from([("Content-Type".to_string(), content_type.to_string())]),
+        })
+    }
+
+    async fn request(db: &Database, id: &str, method: &str, url: &str, content_type: &str, body: &[u8]) {
+        let event = TrafficEvent {
+            request_id: id.to_string(),
+            event: Some(traffic_event::Event::Request(HttpRequestData {
+                method: method.to_string(),
+                url: url.to_string(),
+                headers: if content_type.is_empty() { None } else { headers(content_type) },
+                body: body.to_vec().into(),
+                tls: None,
+            })),
+        };
+        db.save_request(&event, "agent-1").await.unwrap();
+    }
+
+    async fn response(db: &Database, id: &str, content_type: &str, body: &[u8]) {
+        let event = TrafficEvent {
+            request_id: id.to_string(),
+            event: Some(traffic_event::Event::Response(HttpResponseData {
+                status_code: 200,
+                headers: headers(content_type),
+                body: body.to_vec().into(),
+                ..Default::default()
+            })),
+        };
+        db.save_request(&event, "agent-1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_protocol_tagging_and_views() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+
+        let envelope = br#"<s:Envelope xmlns:s="http://schemas.xmlsoap.org/soap/envelope/"/>"#;
+        request(&db, "r1", "POST", "https://a.test/ws/Orders.svc", "text/xml", envelope).await;
+        request(&db, "r2", "POST", "https://a.test/ws/Orders.svc?wsdl=1", "text/xml", envelope).await;
+        request(&db, "r3", "POST", "https://a.test/graphql", "application/json", br#"{"query":"{a}"}"#).await;
+        request(&db, "r4", "GET", "https://a.test/api/users", "", b"").await;
+        request(&db, "r5", "GET", "https://a.test/", "", b"").await;
+        response(&db, "r4", "application/json", b"[]").await;
+        response(&db, "r5", "text/html", b"<html></html>").await;
+        // A JSON error body does not retag a SOAP call
+        response(&db, "r1", "application/json", b"{}").await;
+
+        let soap = db.get_recent_requests_paginated(None, Some("soap"), None, 10, 0).await.unwrap();
+        assert_eq!(soap.len(), 2);
+        let rest = db.get_recent_requests_paginated(None, Some("rest"), None, 10, 0).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].event.request_id, "r4");
+        let all = db.get_recent_requests_paginated(Some("agent-1"), None, None, 10, 0).await.unwrap();
+        assert_eq!(all.len(), 5);
+        assert!(all.iter().any(|r| r.event.request_id == "r5" && r.api_protocol.is_none()));
+
+        let mut counts = db.count_api_protocols().await.unwrap();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![("graphql".to_string(), 1), ("rest".to_string(), 1), ("soap".to_string(), 2)]
+        );
+
+        let endpoints = db.list_api_endpoints("soap", 10).await.unwrap();
+        assert_eq!(endpoints.len(), 1);
+        assert_eq!(endpoints[0].endpoint, "https://a.test/ws/Orders.svc");
+        assert_eq!(endpoints[0].hits, 2);
+    }
+}
@@ -24,6 +24,12 @@ pub struct RepeaterExecution {
     pub executed_at: i64,
     pub duration_ms: Option<i64>,
     pub status_code: Option<i32>,
+    /// Source IP the target saw, if the agent reported one
+    #[serde(default)]
+    pub egress_ip: Option<String>,
+    /// Redirects followed before the response (JSON array of hops)
+    #[serde(default)]
+    pub redirect_chain: Option<String>,
 }
 
 impl Database {
@@ -230,8 +236,8 @@ impl Database {
 
         sqlx::query(
             r#"
-            INSERT INTO repeater_history (id, tab_id, request_data, response_data, agent_id, executed_at, duration_ms, status_code)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO repeater_history (id, tab_id, request_data, response_data, agent_id, executed_at, duration_ms, status_code, egress_ip)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, (SELECT egress_ip FROM agents WHERE id = ?))
             "#,
         )
         .bind(&id)
@@ -242,12 +248,32 @@ impl Database {
         .bind(timestamp)
         .bind(duration_ms)
         .bind(status_code)
+        .bind(agent_id)
         .execute(&pool)
         .await?;
 
         Ok(id)
     }
 
+    /// Store the redirect chain (JSON) of an execution
+    pub async fn set_repeater_execution_redirects(
+        &self,
+        execution_id: &str,
+        redirect_chain: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query("UPDATE repeater_history SET redirect_chain = ? WHERE id = ?")
+            .bind(redirect_chain)
+            .bind(execution_id)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
     /// Get repeater execution history for a tab
     pub async fn get_repeater_history(
         &self,
@@ -263,7 +289,7 @@ impl Database {
 
         let rows = sqlx::query(
             r#"
-            SELECT id, tab_id, request_data, response_data, agent_id, executed_at, duration_ms, status_code
+            SELECT id, tab_id, request_data, response_data, agent_id, executed_at, duration_ms, status_code, egress_ip, redirect_chain
             FROM repeater_history 
             WHERE tab_id = ? 
             ORDER BY executed_at DESC 
@@ -286,6 +312,8 @@ impl Database {
                 executed_at: row.get("executed_at"),
                 duration_ms: row.get("duration_ms"),
                 status_code: row.get("status_code"),
+                egress_ip: row.get("egress_ip"),
+                redirect_chain: row.get("redirect_chain"),
             });
         }
 
@@ -301,7 +329,7 @@ impl Database {
 
         let row = sqlx::query(
             r#"
-            SELECT id, tab_id, request_data, response_data, agent_id, executed_at, duration_ms, status_code
+            SELECT id, tab_id, request_data, response_data, agent_id, executed_at, duration_ms, status_code, egress_ip, redirect_chain
             FROM repeater_history 
             WHERE id = ?
             "#
@@ -320,6 +348,8 @@ impl Database {
                 executed_at: row.get("executed_at"),
                 duration_ms: row.get("duration_ms"),
                 status_code: row.get("status_code"),
+                egress_ip: row.get("egress_ip"),
+                redirect_chain: row.get("redirect_chain"),
             }))
         } else {
             Ok(None)
@@ -0,0 +1,265 @@
+//! Full-text search over captured traffic
+//!
+//! Bodies are stored as captured, in whatever charset the target used, so
+//! SQL `LIKE` cannot see non-UTF-8 text. Search decodes each body with its
+//! declared charset (see `crate::charset`) and matches case-insensitively
+//! against the URL and both bodies.
+//!
+//! Each hit carries snippets of the text around its matches, so a result
+//! list can show why a transaction matched without loading the bodies.
+//! Offsets are byte offsets into the decoded (UTF-8) text.
+
+use super::{recent_request, RecentRequest};
+use crate::charset::decode_body_lossy;
+use crate::pb::HttpHeaders;
+use sqlx::Row;
+use tokio_stream::StreamExt;
+
+/// Most transactions a single search looks through, newest first
+const MAX_SCANNED: i64 = 20_000;
+
+/// Characters of context on each side of a match
+const SNIPPET_CONTEXT_CHARS: usize = 60;
+
+/// Most snippets returned per transaction
+pub const MAX_SNIPPETS_PER_HIT: usize = 10;
+
+/// Where a snippet was taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetSource {
+    Url,
+    RequestBody,
+    ResponseBody,
+}
+
+/// Text around one or more nearby matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchSnippet {
+    pub source: SnippetSource,
+    /// Offset of `text` in the decoded URL or body
+    pub offset: usize,
+    pub text: String,
+    /// Matched ranges within `text`, `(start, end)`
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// A matching transaction and why it matched
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub request: RecentRequest,
+    pub snippets: Vec<SearchSnippet>,
+}
+
+/// Snippets for the matches of `needle` (lowercase) in `text`, at most `max`
+pub fn find_snippets(text: &str, needle: &str, source: SnippetSource, max: usize) -> Vec<SearchSnippet> {
+    if needle.is_empty() || max == 0 {
+        return Vec::new();
+    }
+    // Lowercasing can change byte lengths; `spans` maps the start of each
+    // lowercased character back to its original one
+    let mut lowered = String::with_capacity(text.len());
+    let mut spans = Vec::with_capacity(text.len());
+    for (index, c) in text.char_indices() {
+        spans.push((lowered.len(), index));
+        lowered.extend(c.to_lowercase());
+    }
+    let original_start = |lowered_offset: usize| {
+        let i = spans.partition_point(|&(start, _)| start <= lowered_offset);
+        spans[i - 1].1
+    };
+    let original_end = |lowered_offset: usize| {
+        let i = spans.partition_point(|&(start, _)| start < lowered_offset);
+        spans.get(i).map(|&(_, original)| original).unwrap_or(text.len())
+    };
+
+    let mut snippets: Vec<SearchSnippet> = Vec::new();
+    for (start, matched) in lowered.match_indices(needle) {
+        let (start, end) = (original_start(start), original_end(start + matched.len()));
+        let window_start = chars_before(text, start, SNIPPET_CONTEXT_CHARS);
+        let window_end = chars_after(text, end, SNIPPET_CONTEXT_CHARS);
+
+        // Matches close enough to share context go in one snippet
+        if let Some(last) = snippets.last_mut() {
+            if window_start <= last.offset + last.text.len() {
+                last.text = text[last.offset..window_end.max(last.offset + last.text.len())].to_string();
+                last.highlights.push((start - last.offset, end - last.offset));
+                continue;
+            }
+        }
+        if snippets.len() == max {
+            break;
+        }
+        snippets.push(SearchSnippet {
+            source,
+            offset: window_start,
+            text: text[window_start..window_end].to_string(),
+            highlights: vec![(start - window_start, end - window_start)],
+        });
+    }
+    snippets
+}
+
+fn chars_before(text: &str, offset: usize, count: usize) -> usize {
+    text[..offset].char_indices().rev().take(count).last().map(|(i, _)| i).unwrap_or(offset)
+}
+
+fn chars_after(text: &str, offset: usize, count: usize) -> usize {
+    text[offset..].char_indices().nth(count).map(|(i, _)| offset + i).unwrap_or(text.len())
+}
+
+fn body_snippets(
+    headers: Option<String>,
+    body: Option<Vec<u8>>,
+    needle: &str,
+    source: SnippetSource,
+    max: usize,
+) -> Vec<SearchSnippet> {
+    let Some(body) = body.filter(|b| !b.is_empty()) else {
+        return Vec::new();
+    };
+    if max == 0 {
+        return Vec::new();
+    }
+    let headers = headers
+        .and_then(|json| serde_json::from_str::<Option<HttpHeaders>>(&json).ok().flatten())
+        .map(|h| h.headers);
+    find_snippets(&decode_body_lossy(headers.as_ref(), &body), needle, source, max)
+}
+
+impl super::Database {
+    /// Newest transactions whose URL or decoded bodies contain `text`
+    /// (case-insensitive), at most `limit`, each with up to
+    /// `max_snippets` snippets of its matches
+    pub async fn search_transactions(
+        &self,
+        text: &str,
+        limit: usize,
+        max_snippets: usize,
+    ) -> Result<Vec<SearchHit>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let needle = text.trim().to_lowercase();
+        if needle.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        // At least one snippet, so every hit shows a match
+        let max_snippets = max_snippets.clamp(1, MAX_SNIPPETS_PER_HIT);
+
+        let mut rows = sqlx::query(
+            r#"SELECT request_id, agent_id, req_method, req_url, req_headers, req_body, tls_info, res_status, api_protocol, parent_id,
+                comment, highlight_color, tags, res_headers, res_body
+            FROM http_transactions
+            ORDER BY req_timestamp DESC LIMIT ?"#,
+        )
+        .bind(MAX_SCANNED)
+        .fetch(&pool);
+
+        let mut results = Vec::new();
+        while let Some(row) = rows.next().await {
+            let row = row?;
+            let mut snippets = find_snippets(&row.get::<String, _>("req_url"), &needle, SnippetSource::Url, max_snippets);
+            snippets.extend(body_snippets(
+                row.get("req_headers"),
+                row.get("req_body"),
+                &needle,
+                SnippetSource::RequestBody,
+                max_snippets - snippets.len(),
+            ));
+            snippets.extend(body_snippets(
+                row.get("res_headers"),
+                row.get("res_body"),
+                &needle,
+                SnippetSource::ResponseBody,
+                max_snippets - snippets.len(),
+            ));
+            if !snippets.is_empty() {
+                results.push(SearchHit {
+                    request: recent_request(&row),
+                    snippets,
+                });
+                if results.len() >= limit {
+                    break;
+                }
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_snippets, SnippetSource};
+    use crate::pb::{traffic_event, HttpHeaders, HttpRequestData, HttpResponseData, TrafficEvent};
+    use crate::Database;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_search_decodes_declared_charsets() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+
+        // "ログイン" (login) in Shift_JIS, and "Größe" in ISO-8859-1
+        let bodies: [(&str, &str, &[u8]); 2] = [
+            ("r1", "text/html; charset=Shift_JIS", b"\x83\x8d\x83\x4f\x83\x43\x83\x93"),
+            ("r2", "text/plain; charset=iso-8859-1", b"Gr\xf6\xdfe"),
+        ];
+        for (id, content_type, body) in bodies {
+            let request = TrafficEvent {
+                request_id: id.to_string(),
+                event: Some(traffic_event::Event::Request(HttpRequestData {
+                    method: "GET".to_string(),
+                    url: format!("https://a.test/{}", id),
+                    ..Default::default()
+                })),
+            };
+            db.save_request(&request, "agent-1").await.unwrap();
+            let response = TrafficEvent {
+                request_id: id.to_string(),
+                event: Some(traffic_event::Event::Response(HttpResponseData {
+                    status_code: 200,
+                    headers: Some(HttpHeaders {
+                        headers: HashMap::from([("Content-Type".to_string(), content_type.to_string())]),
+                    }),
+                    body: body.to_vec().into(),
+                    ..Default::default()
+                })),
+            };
+            db.save_request(&response, "agent-1").await.unwrap();
+        }
+
+        let found = db.search_transactions("ログイン", 10, 3).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].request.event.request_id, "r1");
+        assert_eq!(found[0].snippets[0].source, SnippetSource::ResponseBody);
+        assert_eq!(found[0].snippets[0].text, "ログイン");
+        let found = db.search_transactions("größe", 10, 3).await.unwrap();
+        assert_eq!(found[0].request.event.request_id, "r2");
+        assert_eq!(found[0].snippets[0].highlights, vec![(0, "Größe".len())]);
+        assert_eq!(db.search_transactions("a.test", 1, 3).await.unwrap().len(), 1);
+        assert!(db.search_transactions("  ", 10, 3).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snippets_merge_nearby_matches_and_respect_limits() {
+        let text = format!("{}Token=abc token=def{}TOKEN=ghi", "x".repeat(100), "y".repeat(200));
+        let snippets = find_snippets(&text, "token", SnippetSource::ResponseBody, 5);
+        assert_eq!(snippets.len(), 2);
+        assert_eq!(snippets[0].offset, 40);
+        assert_eq!(snippets[0].highlights, vec![(60, 65), (70, 75)]);
+        assert_eq!(&snippets[0].text[60..65], "Token");
+        assert_eq!(&snippets[1].text[snippets[1].highlights[0].0..snippets[1].highlights[0].1], "TOKEN");
+        assert!(snippets[1].text.ends_with("TOKEN=ghi"));
+
+        assert_eq!(find_snippets(&text, "token", SnippetSource::Url, 1).len(), 1);
+
+        // Offsets stay on the original text when lowercasing changes lengths
+        let snippets = find_snippets("KELVIN \u{212a}elvin", "kelvin", SnippetSource::Url, 5);
+        assert_eq!(snippets[0].highlights, vec![(0, 6), (7, 15)]);
+    }
+}
@@ -0,0 +1,317 @@
+//! Database operations for the site map
+//!
+//! `site_map` holds one row of counters per host, path and method. Triggers on
+//! `http_transactions` keep it current as requests, responses, synced and
+//! deleted transactions come and go, so reading the map only touches the rows
+//! of the requested host.
+
+use sqlx::Row;
+use std::collections::BTreeMap;
+
+/// Responses by status class
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusCounts {
+    pub success: i64,
+    pub redirect: i64,
+    pub client_error: i64,
+    pub server_error: i64,
+}
+
+impl StatusCounts {
+    fn add(&mut self, other: &StatusCounts) {
+        self.success += other.success;
+        self.redirect += other.redirect;
+        self.client_error += other.client_error;
+        self.server_error += other.server_error;
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Self {
+        Self {
+            success: row.get("status_2xx"),
+            redirect: row.get("status_3xx"),
+            client_error: row.get("status_4xx"),
+            server_error: row.get("status_5xx"),
+        }
+    }
+}
+
+/// Counters of one method on one path
+#[derive(Debug, Clone)]
+pub struct SiteMapEntry {
+    pub method: String,
+    pub path: String,
+    pub hits: i64,
+    pub statuses: StatusCounts,
+    pub last_request_id: Option<String>,
+    pub last_status: Option<i32>,
+    pub last_seen: i64,
+}
+
+/// Host or path segment, with the counts of everything below it
+#[derive(Debug, Clone)]
+pub struct SiteMapNode {
+    pub name: String,
+    /// Path up to and including this segment; `/` for a host
+    pub path: String,
+    pub hits: i64,
+    pub statuses: StatusCounts,
+    pub last_seen: i64,
+    /// Methods requested on exactly this path
+    pub methods: Vec<SiteMapEntry>,
+    pub children: Vec<SiteMapNode>,
+}
+
+#[derive(Default)]
+struct NodeBuilder {
+    methods: Vec<SiteMapEntry>,
+    children: BTreeMap<String, NodeBuilder>,
+}
+
+impl NodeBuilder {
+    fn finish(self, name: String, path: String) -> SiteMapNode {
+        let children: Vec<SiteMapNode> = self
+            .children
+            .into_iter()
+            .map(|(segment, child)| {
+                let child_path = format!("{}/{}", path.trim_end_matches('/'), segment);
+                child.finish(segment, child_path)
+            })
+            .collect();
+
+        let mut node = SiteMapNode {
+            name,
+            path,
+            hits: 0,
+            statuses: StatusCounts::default(),
+            last_seen: 0,
+            methods: self.methods,
+            children: Vec::new(),
+        };
+        for entry in &node.methods {
+            node.hits += entry.hits;
+            node.statuses.add(&entry.statuses);
+            node.last_seen = node.last_seen.max(entry.last_seen);
+        }
+        for child in &children {
+            node.hits += child.hits;
+            node.statuses.add(&child.statuses);
+            node.last_seen = node.last_seen.max(child.last_seen);
+        }
+        node.children = children;
+        node
+    }
+}
+
+/// Tree of `host` from its entries; `/a/b` and `/a/b/` share a node
+pub fn build_site_tree(host: &str, entries: Vec<SiteMapEntry>) -> SiteMapNode {
+    let mut root = NodeBuilder::default();
+    for entry in entries {
+        let mut node = &mut root;
+        for segment in entry.path.split('/').filter(|s| !s.is_empty()) {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+        node.methods.push(entry);
+    }
+    root.finish(host.to_string(), "/".to_string())
+}
+
+impl super::Database {
+    /// Every host with its totals, without path breakdown
+    pub async fn list_site_map_hosts(&self) -> Result<Vec<SiteMapNode>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT host, SUM(hits) AS hits,
+                SUM(status_2xx) AS status_2xx, SUM(status_3xx) AS status_3xx,
+                SUM(status_4xx) AS status_4xx, SUM(status_5xx) AS status_5xx,
+                MAX(last_seen) AS last_seen
+            FROM site_map
+            GROUP BY host
+            ORDER BY host
+            "#,
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| SiteMapNode {
+                name: row.get("host"),
+                path: "/".to_string(),
+                hits: row.get("hits"),
+                statuses: StatusCounts::from_row(row),
+                last_seen: row.get("last_seen"),
+                methods: Vec::new(),
+                children: Vec::new(),
+            })
+            .collect())
+    }
+
+    /// Path tree of `host`, or `None` if no traffic to it is stored
+    pub async fn get_site_map(&self, host: &str) -> Result<Option<SiteMapNode>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(None),
+        };
+
+        let rows = sqlx::query("SELECT * FROM site_map WHERE host = ? ORDER BY path, method")
+            .bind(host)
+            .fetch_all(&pool)
+            .await?;
+        if rows.is_empty() {
+            return Ok(None);
+        }
+
+        let entries = rows
+            .iter()
+            .map(|row| SiteMapEntry {
+                method: row.get("method"),
+                path: row.get("path"),
+                hits: row.get("hits"),
+                statuses: StatusCounts::from_row(row),
+                last_request_id: row.get("last_request_id"),
+                last_status: row.get("last_status"),
+                last_seen: row.get("last_seen"),
+            })
+            .collect();
+        Ok(Some(build_site_tree(host, entries)))
+    }
+
+    /// Latest request of every method and path, of one host or of all,
+    /// ordered by host and path
+    pub async fn list_site_map_request_ids(&self, host: Option<&str>) -> Result<Vec<String>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        sqlx::query_scalar(
+            r#"
+            SELECT last_request_id FROM site_map
+            WHERE last_request_id IS NOT NULL AND (? IS NULL OR host = ?)
+            ORDER BY host, path, method
+            "#,
+        )
+        .bind(host)
+        .bind(host)
+        .fetch_all(&pool)
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::{traffic_event, HttpRequestData, HttpResponseData, TrafficEvent};
+    use crate::Database;
+    use tempfile::TempDir;
+
+    async fn transaction(db: &Database, id: &str, method: &str, url: &str, status: Option<i32>) {
+        let request = TrafficEvent {
+            request_id: id.to_string(),
+            event: Some(traffic_event::Event::Request(HttpRequestData {
+                method: method.to_string(),
+                url: url.to_string(),
+                ..Default::default()
+            })),
+        };
+        db.save_request(&request, "agent-1").await.unwrap();
+        if let Some(status_code) = status {
+            let response = TrafficEvent {
+                request_id: id.to_string(),
+                event: Some(traffic_event::Event::Response(HttpResponseData {
+                    status_code,
+                    ..Default::default()
+                })),
+            };
+            db.save_request(&response, "agent-1").await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_site_map_follows_traffic() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+
+        transaction(&db, "r1", "GET", "https://a.test/api/users?page=1", Some(200)).await;
+        transaction(&db, "r2", "GET", "https://a.test/api/users?page=2", Some(500)).await;
+        transaction(&db, "r3", "POST", "https://a.test/api/users", Some(201)).await;
+        transaction(&db, "r4", "GET", "https://a.test/", Some(302)).await;
+        transaction(&db, "r5", "GET", "https://a.test/api/orders/7", None).await;
+        transaction(&db, "r6", "GET", "https://b.test:8443", Some(404)).await;
+        transaction(&db, "r7", "CONNECT", "a.test:443", None).await;
+
+        let hosts = db.list_site_map_hosts().await.unwrap();
+        let names: Vec<_> = hosts.iter().map(|h| h.name.as_str()).collect();
+        assert_eq!(names, vec!["a.test", "b.test:8443"]);
+        assert_eq!(hosts[0].hits, 5);
+        assert_eq!(hosts[1].statuses.client_error, 1);
+
+        let tree = db.get_site_map("a.test").await.unwrap().unwrap();
+        assert_eq!(tree.hits, 5);
+        assert_eq!(tree.statuses, StatusCounts { success: 2, redirect: 1, client_error: 0, server_error: 1 });
+        assert_eq!(tree.methods.len(), 1);
+        let api = &tree.children[0];
+        assert_eq!((api.name.as_str(), api.path.as_str(), api.hits), ("api", "/api", 4));
+        let child_names: Vec<_> = api.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(child_names, vec!["orders", "users"]);
+        assert_eq!(api.children[0].children[0].path, "/api/orders/7");
+
+        let users = &api.children[1];
+        assert_eq!(users.hits, 3);
+        let get = users.methods.iter().find(|m| m.method == "GET").unwrap();
+        assert_eq!(get.hits, 2);
+        assert_eq!(get.statuses.server_error, 1);
+        assert_eq!(get.last_request_id.as_deref(), Some("r2"));
+        assert_eq!(get.last_status, Some(500));
+
+        let request_ids = db.list_site_map_request_ids(Some("a.test")).await.unwrap();
+        assert!(request_ids.contains(&"r2".to_string()) && request_ids.contains(&"r3".to_string()));
+        assert!(!request_ids.contains(&"r1".to_string()) && !request_ids.contains(&"r6".to_string()));
+
+        db.delete_requests_by_host("b.test").await.unwrap();
+        assert_eq!(db.list_site_map_hosts().await.unwrap().len(), 1);
+        assert!(db.get_site_map("b.test:8443").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_site_map_includes_crawled_routes() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+
+        let route = |url: &str| crate::database::DiscoveredRouteRow {
+            url: url.to_string(),
+            crawl_id: "c1".to_string(),
+            host: "a.test".to_string(),
+            depth: 1,
+            source: "Link".to_string(),
+            title: None,
+            visited: false,
+            discovered_at: 10,
+        };
+        db.save_discovered_routes(&[route("https://a.test/app/settings?tab=2"), route("https://a.test/app#/inbox")])
+            .await
+            .unwrap();
+        transaction(&db, "r1", "GET", "https://a.test/app/settings", Some(200)).await;
+
+        let tree = db.get_site_map("a.test").await.unwrap().unwrap();
+        assert_eq!(tree.hits, 1);
+        let app = &tree.children[0];
+        assert_eq!(app.path, "/app");
+        assert_eq!(app.methods.len(), 1);
+        assert_eq!(app.methods[0].hits, 0);
+        let settings = &app.children[0];
+        assert_eq!((settings.path.as_str(), settings.hits), ("/app/settings", 1));
+        assert_eq!(settings.methods[0].last_request_id.as_deref(), Some("r1"));
+    }
+}
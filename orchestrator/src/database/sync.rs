@@ -0,0 +1,410 @@
+//! Database operations for project sync
+
+use crate::pb::{SyncFinding, SyncTransaction};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Row, Sqlite, SqliteConnection};
+
+/// Body bytes after which a batch is cut short, to keep gRPC messages bounded
+const SYNC_BATCH_BYTES: usize = 16 * 1024 * 1024;
+
+/// Progress of sync with one peer
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncPeerState {
+    /// Peer's sync identity, learned on the first pull
+    pub peer_node_id: Option<String>,
+    /// Last entry of the peer's change log applied here
+    pub pulled_seq: i64,
+    /// Last entry of the local change log sent to the peer
+    pub pushed_seq: i64,
+    pub last_synced_at: Option<i64>,
+}
+
+/// Changed records read from the change log
+#[derive(Debug, Default)]
+pub struct SyncBatch {
+    pub transactions: Vec<SyncTransaction>,
+    pub findings: Vec<SyncFinding>,
+    /// Cursor to continue from
+    pub next_seq: i64,
+    pub more: bool,
+}
+
+impl super::Database {
+    /// This orchestrator's sync identity in the loaded project
+    pub async fn sync_node_id(&self) -> Result<String, sqlx::Error> {
+        if let Some(id) = self.get_setting::<String>("sync_node_id").await? {
+            return Ok(id);
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.save_setting("sync_node_id", &id).await?;
+        Ok(id)
+    }
+
+    /// Sync progress with the peer at `peer` (its gRPC address)
+    pub async fn get_sync_peer_state(&self, peer: &str) -> Result<SyncPeerState, sqlx::Error> {
+        Ok(self.get_setting(&format!("sync_peer:{}", peer)).await?.unwrap_or_default())
+    }
+
+    pub async fn save_sync_peer_state(&self, peer: &str, state: &SyncPeerState) -> Result<(), sqlx::Error> {
+        self.save_setting(&format!("sync_peer:{}", peer), state).await
+    }
+
+    /// Records changed after `after_seq`, oldest change first, leaving out
+    /// changes received from `exclude_origin`
+    pub async fn read_sync_changes(
+        &self,
+        after_seq: i64,
+        exclude_origin: &str,
+        limit: i64,
+    ) -> Result<SyncBatch, sqlx::Error> {
+        let mut batch = SyncBatch {
+            next_seq: after_seq,
+            ..Default::default()
+        };
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(batch),
+        };
+
+        let changes = sqlx::query(
+            r#"
+            SELECT entity, entity_id, MAX(seq) AS last_seq
+            FROM sync_changes
+            WHERE seq > ? AND origin != ?
+            GROUP BY entity, entity_id
+            ORDER BY last_seq
+            LIMIT ?
+            "#,
+        )
+        .bind(after_seq)
+        .bind(exclude_origin)
+        .bind(limit + 1)
+        .fetch_all(&pool)
+        .await?;
+
+        let mut bytes = 0;
+        for (i, change) in changes.iter().enumerate() {
+            if i as i64 == limit || bytes >= SYNC_BATCH_BYTES {
+                batch.more = true;
+                break;
+            }
+
+            let id: String = change.get("entity_id");
+            match change.get::<String, _>("entity").as_str() {
+                "transaction" => {
+                    if let Some(t) = read_transaction(&pool, &id).await? {
+                        bytes += t.req_body.len() + t.res_body.len();
+                        batch.transactions.push(t);
+                    }
+                }
+                "finding" => {
+                    if let Some(f) = read_finding(&pool, &id).await? {
+                        bytes += f.request_data.len() + f.response_data.len();
+                        batch.findings.push(f);
+                    }
+                }
+                _ => {}
+            }
+            batch.next_seq = change.get("last_seq");
+        }
+
+        Ok(batch)
+    }
+
+    /// Apply records received from the sync node `origin`. Changes this
+    /// logs are tagged with `origin` so they are not sent back to it.
+    pub async fn apply_sync_changes(
+        &self,
+        origin: &str,
+        transactions: &[SyncTransaction],
+        findings: &[SyncFinding],
+    ) -> Result<u32, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let mut conn = pool.acquire().await?;
+        // Immediate, so every change log entry written meanwhile is ours
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+        match apply_batch(&mut conn, origin, transactions, findings).await {
+            Ok(applied) => {
+                sqlx::query("COMMIT").execute(&mut *conn).await?;
+                Ok(applied)
+            }
+            Err(e) => {
+                let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+                Err(e)
+            }
+        }
+    }
+}
+
+async fn read_transaction(pool: &Pool<Sqlite>, request_id: &str) -> Result<Option<SyncTransaction>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT request_id, agent_id, req_method, req_url, req_headers, req_body, req_timestamp, tls_info,
+               res_status, res_headers, res_body, res_timestamp, egress_ip, res_protocol, api_protocol
+        FROM http_transactions
+        WHERE request_id = ?
+        "#,
+    )
+    .bind(request_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        let res_status: Option<i32> = row.get("res_status");
+        SyncTransaction {
+            request_id: row.get("request_id"),
+            agent_id: row.get("agent_id"),
+            req_method: row.get("req_method"),
+            req_url: row.get("req_url"),
+            req_headers: row.get::<Option<String>, _>("req_headers").unwrap_or_default(),
+            req_body: row.get::<Option<Vec<u8>>, _>("req_body").unwrap_or_default(),
+            req_timestamp: row.get("req_timestamp"),
+            tls_info: row.get::<Option<String>, _>("tls_info").unwrap_or_default(),
+            has_response: res_status.is_some(),
+            res_status: res_status.unwrap_or_default(),
+            res_headers: row.get::<Option<String>, _>("res_headers").unwrap_or_default(),
+            res_body: row.get::<Option<Vec<u8>>, _>("res_body").unwrap_or_default(),
+            res_timestamp: row.get::<Option<i64>, _>("res_timestamp").unwrap_or_default(),
+            egress_ip: row.get::<Option<String>, _>("egress_ip").unwrap_or_default(),
+            res_protocol: row.get::<Option<String>, _>("res_protocol").unwrap_or_default(),
+            api_protocol: row.get::<Option<String>, _>("api_protocol").unwrap_or_default(),
+        }
+    }))
+}
+
+async fn read_finding(pool: &Pool<Sqlite>, id: &str) -> Result<Option<SyncFinding>, sqlx::Error> {
+    let row = sqlx::query(
+        r#"
+        SELECT r.id, r.attack_id, a.name AS attack_name, a.attack_mode, a.request_template,
+               r.agent_id, r.request_data, r.payload_values, r.executed_at, r.response_data,
+               r.status_code, r.response_length, r.duration_ms, r.is_highlighted, r.egress_ip
+        FROM intruder_results r
+        JOIN intruder_attacks a ON a.id = r.attack_id
+        WHERE r.id = ?
+        "#,
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|row| {
+        let response_data: Option<String> = row.get("response_data");
+        SyncFinding {
+            id: row.get("id"),
+            attack_id: row.get("attack_id"),
+            attack_name: row.get("attack_name"),
+            attack_mode: row.get("attack_mode"),
+            request_template: row.get("request_template"),
+            agent_id: row.get("agent_id"),
+            request_data: row.get("request_data"),
+            payload_values: row.get("payload_values"),
+            executed_at: row.get("executed_at"),
+            has_response: response_data.is_some(),
+            response_data: response_data.unwrap_or_default(),
+            status_code: row.get::<Option<i32>, _>("status_code").unwrap_or_default(),
+            response_length: row.get::<Option<i64>, _>("response_length").unwrap_or_default(),
+            duration_ms: row.get::<Option<i64>, _>("duration_ms").unwrap_or_default(),
+            is_highlighted: row.get::<Option<bool>, _>("is_highlighted").unwrap_or_default(),
+            egress_ip: row.get::<Option<String>, _>("egress_ip").unwrap_or_default(),
+        }
+    }))
+}
+
+async fn apply_batch(
+    conn: &mut SqliteConnection,
+    origin: &str,
+    transactions: &[SyncTransaction],
+    findings: &[SyncFinding],
+) -> Result<u32, sqlx::Error> {
+    let before: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(seq), 0) FROM sync_changes")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    for t in transactions {
+        ensure_agent(conn, &t.agent_id).await?;
+        // A response captured on either side completes the transaction
+        sqlx::query(
+            r#"
+            INSERT INTO http_transactions (
+                request_id, agent_id, req_method, req_url, req_headers, req_body, req_timestamp, tls_info,
+                res_status, res_headers, res_body, res_timestamp, egress_ip, res_protocol, api_protocol
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(request_id) DO UPDATE SET
+                res_status = COALESCE(excluded.res_status, res_status),
+                res_headers = COALESCE(excluded.res_headers, res_headers),
+                res_body = COALESCE(excluded.res_body, res_body),
+                res_timestamp = COALESCE(excluded.res_timestamp, res_timestamp),
+                egress_ip = COALESCE(egress_ip, excluded.egress_ip),
+                res_protocol = COALESCE(excluded.res_protocol, res_protocol),
+                api_protocol = COALESCE(api_protocol, excluded.api_protocol)
+            "#,
+        )
+        .bind(&t.request_id)
+        .bind(&t.agent_id)
+        .bind(&t.req_method)
+        .bind(&t.req_url)
+        .bind(&t.req_headers)
+        .bind(&t.req_body)
+        .bind(t.req_timestamp)
+        .bind(&t.tls_info)
+        .bind(t.has_response.then_some(t.res_status))
+        .bind(t.has_response.then_some(&t.res_headers))
+        .bind(t.has_response.then_some(&t.res_body))
+        .bind(t.has_response.then_some(t.res_timestamp))
+        .bind(Some(&t.egress_ip).filter(|ip| !ip.is_empty()))
+        .bind(Some(&t.res_protocol).filter(|p| t.has_response && !p.is_empty()))
+        .bind(Some(&t.api_protocol).filter(|p| !p.is_empty()))
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    for f in findings {
+        ensure_agent(conn, &f.agent_id).await?;
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO intruder_attacks (
+                id, name, request_template, attack_mode, payload_sets, target_agents,
+                distribution_strategy, created_at, updated_at, status
+            )
+            VALUES (?, ?, ?, ?, '[]', '[]', 'round_robin', ?, ?, 'completed')
+            "#,
+        )
+        .bind(&f.attack_id)
+        .bind(&f.attack_name)
+        .bind(&f.request_template)
+        .bind(&f.attack_mode)
+        .bind(f.executed_at)
+        .bind(f.executed_at)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO intruder_results (
+                id, attack_id, request_data, response_data, agent_id, payload_values,
+                executed_at, duration_ms, status_code, response_length, is_highlighted, egress_ip
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET is_highlighted = excluded.is_highlighted
+            "#,
+        )
+        .bind(&f.id)
+        .bind(&f.attack_id)
+        .bind(&f.request_data)
+        .bind(f.has_response.then_some(&f.response_data))
+        .bind(&f.agent_id)
+        .bind(&f.payload_values)
+        .bind(f.executed_at)
+        .bind(f.has_response.then_some(f.duration_ms))
+        .bind(f.has_response.then_some(f.status_code))
+        .bind(f.has_response.then_some(f.response_length))
+        .bind(f.is_highlighted)
+        .bind(Some(&f.egress_ip).filter(|ip| !ip.is_empty()))
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    // Everything the triggers just logged came from the peer
+    sqlx::query("UPDATE sync_changes SET origin = ? WHERE seq > ?")
+        .bind(origin)
+        .bind(before)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok((transactions.len() + findings.len()) as u32)
+}
+
+/// Synced records may reference agents that never connected here
+async fn ensure_agent(conn: &mut SqliteConnection, agent_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO agents (id, name, hostname, version, status, last_heartbeat)
+        VALUES (?, ?, 'remote', 'unknown', 'Offline', 0)
+        "#,
+    )
+    .bind(agent_id)
+    .bind(agent_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pb::SyncTransaction;
+    use crate::Database;
+    use tempfile::TempDir;
+
+    async fn project(dir: &TempDir, name: &str) -> Database {
+        let db = Database::new(dir.path().join(name).to_str().unwrap()).await.unwrap();
+        db.create_project(name).await.unwrap();
+        db.load_project(name).await.unwrap();
+        db
+    }
+
+    fn transaction(id: &str, res_status: Option<i32>) -> SyncTransaction {
+        SyncTransaction {
+            request_id: id.to_string(),
+            agent_id: "field-agent".to_string(),
+            req_method: "GET".to_string(),
+            req_url: format!("https://app.example.com/{}", id),
+            req_timestamp: 100,
+            has_response: res_status.is_some(),
+            res_status: res_status.unwrap_or_default(),
+            res_body: b"ok".to_vec(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_changes_are_not_echoed_to_origin() {
+        let dir = TempDir::new().unwrap();
+        let laptop = project(&dir, "laptop").await;
+        let server = project(&dir, "server").await;
+
+        laptop
+            .apply_sync_changes("seed", &[transaction("r1", None), transaction("r2", Some(200))], &[])
+            .await
+            .unwrap();
+        let to_seed = laptop.read_sync_changes(0, "seed", 100).await.unwrap();
+        assert!(to_seed.transactions.is_empty());
+        assert_eq!(to_seed.next_seq, 0);
+
+        let from_laptop = laptop.read_sync_changes(0, "server", 100).await.unwrap();
+        assert_eq!(from_laptop.transactions.len(), 2);
+        assert!(!from_laptop.more);
+
+        server
+            .apply_sync_changes("laptop", &from_laptop.transactions, &[])
+            .await
+            .unwrap();
+        let back = server.read_sync_changes(0, "laptop", 100).await.unwrap();
+        assert!(back.transactions.is_empty());
+        let for_others = server.read_sync_changes(0, "other", 100).await.unwrap();
+        assert_eq!(for_others.transactions.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_response_completes_synced_transaction() {
+        let dir = TempDir::new().unwrap();
+        let db = project(&dir, "p").await;
+
+        db.apply_sync_changes("a", &[transaction("r1", None)], &[]).await.unwrap();
+        db.apply_sync_changes("b", &[transaction("r1", Some(404))], &[]).await.unwrap();
+        // A late copy without the response does not erase it
+        db.apply_sync_changes("c", &[transaction("r1", None)], &[]).await.unwrap();
+
+        let batch = db.read_sync_changes(0, "x", 10).await.unwrap();
+        assert_eq!(batch.transactions.len(), 1);
+        assert!(batch.transactions[0].has_response);
+        assert_eq!(batch.transactions[0].res_status, 404);
+
+        let paged = db.read_sync_changes(0, "x", 0).await.unwrap();
+        assert!(paged.more);
+        assert_eq!(paged.next_seq, 0);
+    }
+}
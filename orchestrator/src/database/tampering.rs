@@ -0,0 +1,167 @@
+//! Database operations for tamper check findings
+
+use sqlx::Row;
+
+/// A tamper probe whose response differed from the baseline
+#[derive(Debug, Clone)]
+pub struct TamperFindingRow {
+    pub id: String,
+    pub run_id: String,
+    /// Captured request the probe was made from
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    /// `TamperKind` tag
+    pub kind: String,
+    /// What the probe changed
+    pub probe: String,
+    pub effect: String,
+    pub severity: String,
+    pub baseline_status: i32,
+    pub probe_status: i32,
+    pub baseline_length: i64,
+    pub probe_length: i64,
+    pub agent_id: String,
+    pub created_at: i64,
+}
+
+impl super::Database {
+    /// Record a finding; a probe of a request is stored once per run
+    pub async fn save_tamper_finding(&self, finding: &TamperFindingRow) -> Result<(), sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO tamper_findings
+                (id, run_id, request_id, method, url, kind, probe, effect, severity,
+                 baseline_status, probe_status, baseline_length, probe_length, agent_id, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&finding.id)
+        .bind(&finding.run_id)
+        .bind(&finding.request_id)
+        .bind(&finding.method)
+        .bind(&finding.url)
+        .bind(&finding.kind)
+        .bind(&finding.probe)
+        .bind(&finding.effect)
+        .bind(&finding.severity)
+        .bind(finding.baseline_status)
+        .bind(finding.probe_status)
+        .bind(finding.baseline_length)
+        .bind(finding.probe_length)
+        .bind(&finding.agent_id)
+        .bind(finding.created_at)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Findings ordered by URL, of one run or of all runs
+    pub async fn list_tamper_findings(
+        &self,
+        run_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<TamperFindingRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            "SELECT * FROM tamper_findings WHERE (? IS NULL OR run_id = ?) ORDER BY run_id, url, created_at LIMIT ?",
+        )
+        .bind(run_id)
+        .bind(run_id)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| TamperFindingRow {
+                id: row.get("id"),
+                run_id: row.get("run_id"),
+                request_id: row.get("request_id"),
+                method: row.get("method"),
+                url: row.get("url"),
+                kind: row.get("kind"),
+                probe: row.get("probe"),
+                effect: row.get("effect"),
+                severity: row.get("severity"),
+                baseline_status: row.get("baseline_status"),
+                probe_status: row.get("probe_status"),
+                baseline_length: row.get("baseline_length"),
+                probe_length: row.get("probe_length"),
+                agent_id: row.get("agent_id"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    /// Delete the findings of a run; returns how many were deleted
+    pub async fn delete_tamper_findings(&self, run_id: &str) -> Result<u64, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        let result = sqlx::query("DELETE FROM tamper_findings WHERE run_id = ?")
+            .bind(run_id)
+            .execute(&pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TamperFindingRow;
+    use crate::Database;
+    use tempfile::TempDir;
+
+    fn finding(run_id: &str, url: &str, probe: &str) -> TamperFindingRow {
+        TamperFindingRow {
+            id: uuid::Uuid::new_v4().to_string(),
+            run_id: run_id.to_string(),
+            request_id: "r1".to_string(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            kind: "verb_tampering".to_string(),
+            probe: probe.to_string(),
+            effect: "access_bypass".to_string(),
+            severity: "high".to_string(),
+            baseline_status: 403,
+            probe_status: 200,
+            baseline_length: 12,
+            probe_length: 640,
+            agent_id: "agent-1".to_string(),
+            created_at: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_findings_are_stored_once_per_probe_and_deleted_with_the_run() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+
+        for row in [
+            finding("run-1", "https://a.test/b", "GET → HEAD"),
+            finding("run-1", "https://a.test/b", "GET → HEAD"),
+            finding("run-1", "https://a.test/a", "GET → PUT"),
+            finding("run-2", "https://a.test/a", "GET → PUT"),
+        ] {
+            db.save_tamper_finding(&row).await.unwrap();
+        }
+        let findings = db.list_tamper_findings(Some("run-1"), 10).await.unwrap();
+        let probes: Vec<&str> = findings.iter().map(|f| f.probe.as_str()).collect();
+        assert_eq!(probes, vec!["GET → PUT", "GET → HEAD"]);
+        assert_eq!((findings[0].baseline_status, findings[0].probe_status), (403, 200));
+
+        assert_eq!(db.delete_tamper_findings("run-1").await.unwrap(), 2);
+        assert_eq!(db.list_tamper_findings(None, 10).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,205 @@
+//! Database operations for per-agent usage accounting
+//!
+//! Every request and response an agent streams is counted against the agent
+//! and the UTC day it arrived on, whether or not it is in scope, since the
+//! agent proxied it either way. Sizes are those of the message on the wire
+//! as HTTP/1.1, which is close enough to attribute egress costs.
+
+use crate::pb::{traffic_event, HttpHeaders, TrafficEvent};
+use sqlx::Row;
+
+/// Usage of one agent on one day
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentUsageRow {
+    pub agent_id: String,
+    /// `YYYY-MM-DD`, UTC
+    pub day: String,
+    pub requests: i64,
+    pub responses: i64,
+    /// Requests to the targets
+    pub bytes_sent: i64,
+    /// Responses from the targets
+    pub bytes_received: i64,
+}
+
+/// What one traffic event adds to its agent's usage
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UsageDelta {
+    pub requests: i64,
+    pub responses: i64,
+    pub bytes_sent: i64,
+    pub bytes_received: i64,
+}
+
+impl UsageDelta {
+    /// Usage of a request or response event; `None` for other events
+    pub fn of(event: &TrafficEvent) -> Option<Self> {
+        match event.event.as_ref()? {
+            traffic_event::Event::Request(req) => Some(Self {
+                requests: 1,
+                // "METHOD URL HTTP/1.1\r\n"
+                bytes_sent: (req.method.len() + req.url.len() + 12 + headers_size(req.headers.as_ref()) + req.body.len())
+                    as i64,
+                ..Default::default()
+            }),
+            traffic_event::Event::Response(res) => Some(Self {
+                responses: 1,
+                // "HTTP/1.1 200 OK\r\n", give or take the reason phrase
+                bytes_received: (17 + headers_size(res.headers.as_ref()) + res.body.len()) as i64,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Header lines and the blank line ending them
+fn headers_size(headers: Option<&HttpHeaders>) -> usize {
+    let lines: usize = headers
+        .map(|h| h.headers.iter().map(|(name, value)| name.len() + value.len() + 4).sum())
+        .unwrap_or_default();
+    lines + 2
+}
+
+impl super::Database {
+    /// Add `delta` to the usage of `agent_id` on `day`
+    pub async fn record_agent_usage(&self, agent_id: &str, day: &str, delta: &UsageDelta) -> Result<(), sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(()),
+        };
+
+        sqlx::query(
+            r#"
+            INSERT INTO agent_usage (agent_id, day, requests, responses, bytes_sent, bytes_received)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(agent_id, day) DO UPDATE SET
+                requests = requests + excluded.requests,
+                responses = responses + excluded.responses,
+                bytes_sent = bytes_sent + excluded.bytes_sent,
+                bytes_received = bytes_received + excluded.bytes_received
+            "#,
+        )
+        .bind(agent_id)
+        .bind(day)
+        .bind(delta.requests)
+        .bind(delta.responses)
+        .bind(delta.bytes_sent)
+        .bind(delta.bytes_received)
+        .execute(&pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Daily usage ordered by day and agent, of one agent or of all, between
+    /// `since` and `until` inclusive (`YYYY-MM-DD`)
+    pub async fn list_agent_usage(
+        &self,
+        agent_id: Option<&str>,
+        since: Option<&str>,
+        until: Option<&str>,
+    ) -> Result<Vec<AgentUsageRow>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query(
+            r#"
+            SELECT * FROM agent_usage
+            WHERE (? IS NULL OR agent_id = ?) AND (? IS NULL OR day >= ?) AND (? IS NULL OR day <= ?)
+            ORDER BY day, agent_id
+            "#,
+        )
+        .bind(agent_id)
+        .bind(agent_id)
+        .bind(since)
+        .bind(since)
+        .bind(until)
+        .bind(until)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| AgentUsageRow {
+                agent_id: row.get("agent_id"),
+                day: row.get("day"),
+                requests: row.get("requests"),
+                responses: row.get("responses"),
+                bytes_sent: row.get("bytes_sent"),
+                bytes_received: row.get("bytes_received"),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::{HttpRequestData, HttpResponseData};
+    use crate::Database;
+    use tempfile::TempDir;
+
+    fn request(url: &str, body: &str) -> TrafficEvent {
+        TrafficEvent {
+            request_id: "r1".to_string(),
+            event: Some(traffic_event::Event::Request(HttpRequestData {
+                method: "GET".to_string(),
+                url: url.to_string(),
+                headers: Some(HttpHeaders {
+                    headers: [("Host".to_string(), "a.test".to_string())].into_iter().collect(),
+                }),
+                body: body.as_bytes().to_vec().into(),
+                ..Default::default()
+            })),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usage_adds_up_per_agent_and_day() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+
+        // "GET http://a.test/ HTTP/1.1\r\nHost: a.test\r\n\r\n"
+        let sent = UsageDelta::of(&request("http://a.test/", "")).unwrap();
+        assert_eq!((sent.requests, sent.bytes_sent), (1, 45));
+        let received = UsageDelta::of(&TrafficEvent {
+            request_id: "r1".to_string(),
+            event: Some(traffic_event::Event::Response(HttpResponseData {
+                status_code: 200,
+                body: vec![0; 100].into(),
+                ..Default::default()
+            })),
+        })
+        .unwrap();
+        assert_eq!((received.responses, received.bytes_received), (1, 119));
+
+        for (agent_id, day, delta) in [
+            ("agent-1", "2024-02-13", sent),
+            ("agent-1", "2024-02-13", received),
+            ("agent-1", "2024-02-14", sent),
+            ("agent-2", "2024-02-13", sent),
+        ] {
+            db.record_agent_usage(agent_id, day, &delta).await.unwrap();
+        }
+
+        let usage = db.list_agent_usage(Some("agent-1"), None, None).await.unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(
+            usage[0],
+            AgentUsageRow {
+                agent_id: "agent-1".to_string(),
+                day: "2024-02-13".to_string(),
+                requests: 1,
+                responses: 1,
+                bytes_sent: 45,
+                bytes_received: 119,
+            }
+        );
+        let day = db.list_agent_usage(None, Some("2024-02-13"), Some("2024-02-13")).await.unwrap();
+        assert_eq!(day.iter().map(|u| u.agent_id.as_str()).collect::<Vec<_>>(), vec!["agent-1", "agent-2"]);
+    }
+}
@@ -0,0 +1,205 @@
+//! Database operations for saved views
+//!
+//! A view is a named filter of the request list with a sort order, such as
+//! "in-scope 5xx JSON responses" sorted by status. The filter expression is
+//! stored as written and applied by the client. At most one view is the
+//! project's default, opened with the request list.
+
+use sqlx::Row;
+
+/// Column the request list is sorted by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewSortField {
+    Timestamp,
+    Method,
+    Url,
+    Status,
+    Length,
+}
+
+impl ViewSortField {
+    /// Value stored in `saved_views.sort_field`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ViewSortField::Timestamp => "timestamp",
+            ViewSortField::Method => "method",
+            ViewSortField::Url => "url",
+            ViewSortField::Status => "status",
+            ViewSortField::Length => "length",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "timestamp" => Some(ViewSortField::Timestamp),
+            "method" => Some(ViewSortField::Method),
+            "url" => Some(ViewSortField::Url),
+            "status" => Some(ViewSortField::Status),
+            "length" => Some(ViewSortField::Length),
+            _ => None,
+        }
+    }
+}
+
+/// A saved view
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedView {
+    pub id: String,
+    pub name: String,
+    pub filter: String,
+    pub sort_field: ViewSortField,
+    /// Ascending when true, newest/largest first otherwise
+    pub sort_ascending: bool,
+    pub is_default: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+fn saved_view(row: &sqlx::sqlite::SqliteRow) -> SavedView {
+    SavedView {
+        id: row.get("id"),
+        name: row.get("name"),
+        filter: row.get("filter"),
+        sort_field: ViewSortField::from_tag(&row.get::<String, _>("sort_field")).unwrap_or(ViewSortField::Timestamp),
+        sort_ascending: row.get::<String, _>("sort_direction") == "asc",
+        is_default: row.get("is_default"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+impl super::Database {
+    /// Saved views, by name
+    pub async fn list_saved_views(&self) -> Result<Vec<SavedView>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        let rows = sqlx::query("SELECT * FROM saved_views ORDER BY name COLLATE NOCASE")
+            .fetch_all(&pool)
+            .await?;
+        Ok(rows.iter().map(saved_view).collect())
+    }
+
+    pub async fn get_saved_view(&self, id: &str) -> Result<Option<SavedView>, sqlx::Error> {
+        let pool = match self.get_pool().await {
+            Ok(p) => p,
+            Err(_) => return Ok(None),
+        };
+
+        let row = sqlx::query("SELECT * FROM saved_views WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&pool)
+            .await?;
+        Ok(row.as_ref().map(saved_view))
+    }
+
+    /// Create a view, or update the one with the same name. Making it the
+    /// default unsets the previous default.
+    pub async fn save_view(
+        &self,
+        name: &str,
+        filter: &str,
+        sort_field: ViewSortField,
+        sort_ascending: bool,
+        is_default: bool,
+    ) -> Result<SavedView, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+        let now = chrono::Utc::now().timestamp();
+
+        let mut tx = pool.begin().await?;
+        if is_default {
+            sqlx::query("UPDATE saved_views SET is_default = 0 WHERE name != ?")
+                .bind(name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query(
+            r#"
+            INSERT INTO saved_views (id, name, filter, sort_field, sort_direction, is_default, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(name) DO UPDATE SET
+                filter = excluded.filter,
+                sort_field = excluded.sort_field,
+                sort_direction = excluded.sort_direction,
+                is_default = excluded.is_default,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind(name)
+        .bind(filter)
+        .bind(sort_field.as_str())
+        .bind(if sort_ascending { "asc" } else { "desc" })
+        .bind(is_default)
+        .bind(now)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        let row = sqlx::query("SELECT * FROM saved_views WHERE name = ?")
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(saved_view(&row))
+    }
+
+    /// Delete a view. Returns false if there is no such view.
+    pub async fn delete_saved_view(&self, id: &str) -> Result<bool, sqlx::Error> {
+        let pool = self.get_pool().await.map_err(|e| {
+            sqlx::Error::Io(std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))
+        })?;
+
+        let result = sqlx::query("DELETE FROM saved_views WHERE id = ?")
+            .bind(id)
+            .execute(&pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn test_save_update_and_delete_views() {
+        let dir = TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+
+        let errors = db
+            .save_view("Server errors", "scope:in status:>=500 mime:json", ViewSortField::Status, false, true)
+            .await
+            .unwrap();
+        assert!(errors.is_default);
+        let posts = db.save_view("posts", "method:POST", ViewSortField::Timestamp, true, true).await.unwrap();
+
+        // Only one default, and saving under an existing name updates that view
+        let updated = db
+            .save_view("Server errors", "status:>=500", ViewSortField::Url, true, false)
+            .await
+            .unwrap();
+        assert_eq!(updated.id, errors.id);
+        assert_eq!(updated.filter, "status:>=500");
+        assert_eq!(updated.sort_field, ViewSortField::Url);
+        assert!(updated.sort_ascending);
+
+        let views = db.list_saved_views().await.unwrap();
+        let names: Vec<_> = views.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["posts", "Server errors"]);
+        assert_eq!(views.iter().filter(|v| v.is_default).count(), 1);
+        assert!(db.get_saved_view(&posts.id).await.unwrap().unwrap().is_default);
+
+        assert!(db.delete_saved_view(&posts.id).await.unwrap());
+        assert!(!db.delete_saved_view(&posts.id).await.unwrap());
+        assert_eq!(db.list_saved_views().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,384 @@
+//! Decoder toolbox
+//!
+//! Chains of encode/decode operations over raw bytes: base64 and base64url,
+//! URL and HTML entity escaping, hex, gzip and deflate, JWT segments, UTF-16
+//! and digests. Every step takes the bytes the previous one produced, so a
+//! chain like "gunzip, then base64-decode, then hex" works on binary data
+//! without it ever being forced through a string.
+
+use base64::Engine;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use sha2::Digest;
+use std::io::{Read, Write};
+
+/// Largest output a step may produce; guards decompression against bombs
+pub const MAX_TRANSFORM_OUTPUT: usize = 16 * 1024 * 1024;
+
+/// Longest chain of operations
+pub const MAX_TRANSFORM_STEPS: usize = 64;
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum TransformError {
+    #[error("Step {step} ({op:?}): {reason}")]
+    Step { step: usize, op: TransformOp, reason: String },
+    #[error("At most {MAX_TRANSFORM_STEPS} operations can be chained")]
+    TooManySteps,
+}
+
+/// One operation of a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransformOp {
+    Base64Encode,
+    Base64Decode,
+    Base64UrlEncode,
+    Base64UrlDecode,
+    /// Percent-encodes everything but RFC 3986 unreserved characters
+    UrlEncode,
+    /// Percent-decoding; `+` becomes a space
+    UrlDecode,
+    HtmlEncode,
+    /// Named (the common ones) and numeric character references
+    HtmlDecode,
+    HexEncode,
+    /// Hex digits, ignoring whitespace, `:` separators and a `0x` prefix
+    HexDecode,
+    Gzip,
+    Gunzip,
+    /// zlib-wrapped deflate, as in `Content-Encoding: deflate`
+    Deflate,
+    /// zlib-wrapped or raw deflate
+    Inflate,
+    /// Decoded JSON header of a JWT
+    JwtHeader,
+    /// Decoded JSON claims of a JWT
+    JwtPayload,
+    /// Raw signature bytes of a JWT
+    JwtSignature,
+    Utf16LeEncode,
+    Utf16BeEncode,
+    /// UTF-16 to UTF-8; little-endian unless the input has a byte order mark
+    Utf16Decode,
+    /// Digests produce raw bytes; chain `HexEncode` for the usual notation
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+/// Output of one step
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformStep {
+    pub op: TransformOp,
+    pub output: Vec<u8>,
+}
+
+/// Run `ops` over `input` in order, returning every intermediate output
+pub fn transform(input: &[u8], ops: &[TransformOp]) -> Result<Vec<TransformStep>, TransformError> {
+    if ops.len() > MAX_TRANSFORM_STEPS {
+        return Err(TransformError::TooManySteps);
+    }
+    let mut steps: Vec<TransformStep> = Vec::with_capacity(ops.len());
+    for (index, op) in ops.iter().enumerate() {
+        let current = steps.last().map_or(input, |step| step.output.as_slice());
+        let output = apply(*op, current).map_err(|reason| TransformError::Step {
+            step: index + 1,
+            op: *op,
+            reason,
+        })?;
+        if output.len() > MAX_TRANSFORM_OUTPUT {
+            return Err(TransformError::Step {
+                step: index + 1,
+                op: *op,
+                reason: format!("output exceeds {} bytes", MAX_TRANSFORM_OUTPUT),
+            });
+        }
+        steps.push(TransformStep { op: *op, output });
+    }
+    Ok(steps)
+}
+
+fn apply(op: TransformOp, input: &[u8]) -> Result<Vec<u8>, String> {
+    let standard = &base64::engine::general_purpose::STANDARD;
+    let url_safe = &base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    Ok(match op {
+        TransformOp::Base64Encode => standard.encode(input).into_bytes(),
+        TransformOp::Base64Decode => decode_base64(input)?,
+        TransformOp::Base64UrlEncode => url_safe.encode(input).into_bytes(),
+        TransformOp::Base64UrlDecode => decode_base64(input)?,
+        TransformOp::UrlEncode => url_encode(input).into_bytes(),
+        TransformOp::UrlDecode => url_decode(input),
+        TransformOp::HtmlEncode => html_encode(text(input)?).into_bytes(),
+        TransformOp::HtmlDecode => html_decode(text(input)?).into_bytes(),
+        TransformOp::HexEncode => input.iter().map(|b| format!("{:02x}", b)).collect::<String>().into_bytes(),
+        TransformOp::HexDecode => hex_decode(text(input)?)?,
+        TransformOp::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(input).and_then(|_| encoder.finish()).map_err(|e| e.to_string())?
+        }
+        TransformOp::Gunzip => read_limited(GzDecoder::new(input))?,
+        TransformOp::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(input).and_then(|_| encoder.finish()).map_err(|e| e.to_string())?
+        }
+        TransformOp::Inflate => {
+            read_limited(ZlibDecoder::new(input)).or_else(|_| read_limited(DeflateDecoder::new(input)))?
+        }
+        TransformOp::JwtHeader => jwt_segment(input, 0)?,
+        TransformOp::JwtPayload => jwt_segment(input, 1)?,
+        TransformOp::JwtSignature => jwt_segment(input, 2)?,
+        TransformOp::Utf16LeEncode => text(input)?.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+        TransformOp::Utf16BeEncode => text(input)?.encode_utf16().flat_map(u16::to_be_bytes).collect(),
+        TransformOp::Utf16Decode => {
+            let (decoded, _, had_errors) = encoding_rs::UTF_16LE.decode(input);
+            if had_errors {
+                return Err("invalid UTF-16".to_string());
+            }
+            decoded.into_owned().into_bytes()
+        }
+        TransformOp::Md5 => md5::Md5::digest(input).to_vec(),
+        TransformOp::Sha1 => sha1::Sha1::digest(input).to_vec(),
+        TransformOp::Sha256 => sha2::Sha256::digest(input).to_vec(),
+        TransformOp::Sha512 => sha2::Sha512::digest(input).to_vec(),
+    })
+}
+
+fn text(input: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(input).map_err(|_| "input is not UTF-8 text".to_string())
+}
+
+/// Either alphabet, with or without padding, ignoring whitespace
+fn decode_base64(input: &[u8]) -> Result<Vec<u8>, String> {
+    let cleaned: Vec<u8> = input
+        .iter()
+        .filter(|b| !b.is_ascii_whitespace() && **b != b'=')
+        .map(|b| match b {
+            b'-' => b'+',
+            b'_' => b'/',
+            other => *other,
+        })
+        .collect();
+    base64::engine::general_purpose::STANDARD_NO_PAD
+        .decode(cleaned)
+        .map_err(|e| format!("invalid base64: {}", e))
+}
+
+fn url_encode(input: &[u8]) -> String {
+    let mut encoded = String::with_capacity(input.len() * 3);
+    for &byte in input {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Malformed escapes are kept as they are
+fn url_decode(input: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        match input[i] {
+            b'%' if i + 2 < input.len() => {
+                match std::str::from_utf8(&input[i + 1..i + 3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            other => decoded.push(other),
+        }
+        i += 1;
+    }
+    decoded
+}
+
+fn html_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => encoded.push_str("&amp;"),
+            '<' => encoded.push_str("&lt;"),
+            '>' => encoded.push_str("&gt;"),
+            '"' => encoded.push_str("&quot;"),
+            '\'' => encoded.push_str("&#x27;"),
+            other => encoded.push(other),
+        }
+    }
+    encoded
+}
+
+/// Unknown or malformed references are kept as they are
+fn html_decode(input: &str) -> String {
+    let mut decoded = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 32)
+            .and_then(|end| Some((end, character_reference(&rest[1..end + 1])?)));
+        match reference {
+            Some((end, c)) => {
+                decoded.push(c);
+                rest = &rest[end + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn character_reference(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "copy" => '©',
+        "reg" => '®',
+        _ => return None,
+    })
+}
+
+fn hex_decode(input: &str) -> Result<Vec<u8>, String> {
+    let trimmed = input.trim();
+    let digits: Vec<u8> = trimmed
+        .strip_prefix("0x")
+        .or_else(|| trimmed.strip_prefix("0X"))
+        .unwrap_or(trimmed)
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace() && *b != b':')
+        .collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err("odd number of hex digits".to_string());
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| format!("invalid hex digits {:?}", String::from_utf8_lossy(pair)))
+        })
+        .collect()
+}
+
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+    reader
+        .take(MAX_TRANSFORM_OUTPUT as u64 + 1)
+        .read_to_end(&mut output)
+        .map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+/// Segment `index` of a JWT, optionally prefixed with `Bearer`
+fn jwt_segment(input: &[u8], index: usize) -> Result<Vec<u8>, String> {
+    let value = text(input)?.trim();
+    let token = match value.split_once(char::is_whitespace) {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+        _ => value,
+    };
+    let segments: Vec<&str> = token.split('.').collect();
+    if segments.len() != 3 {
+        return Err(format!("a JWT has 3 segments, found {}", segments.len()));
+    }
+    decode_base64(segments[index].as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TransformOp::*;
+
+    fn run(input: &str, ops: &[TransformOp]) -> Vec<u8> {
+        transform(input.as_bytes(), ops).unwrap().pop().unwrap().output
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let binary: Vec<u8> = (0..=255).collect();
+        for (encode, decode) in [
+            (Base64Encode, Base64Decode),
+            (Base64UrlEncode, Base64UrlDecode),
+            (UrlEncode, UrlDecode),
+            (HexEncode, HexDecode),
+            (Gzip, Gunzip),
+            (Deflate, Inflate),
+        ] {
+            let steps = transform(&binary, &[encode, decode]).unwrap();
+            assert_eq!(steps[1].output, binary, "{:?}", encode);
+        }
+
+        let steps = transform("<a href=\"x\">Tom & 'Jerry' ü</a>".as_bytes(), &[HtmlEncode, HtmlDecode]).unwrap();
+        assert_eq!(steps[0].output, b"&lt;a href=&quot;x&quot;&gt;Tom &amp; &#x27;Jerry&#x27; \xc3\xbc&lt;/a&gt;");
+        assert_eq!(steps[1].output, "<a href=\"x\">Tom & 'Jerry' ü</a>".as_bytes());
+
+        assert_eq!(run("héllo", &[Utf16LeEncode]), b"h\0\xe9\0l\0l\0o\0");
+        let mut big_endian = vec![0xfe, 0xff];
+        big_endian.extend(run("héllo", &[Utf16BeEncode]));
+        assert_eq!(transform(&big_endian, &[Utf16Decode]).unwrap()[0].output, "héllo".as_bytes());
+        assert_eq!(run("hi", &[Utf16LeEncode, Utf16Decode]), b"hi");
+    }
+
+    #[test]
+    fn test_decoding_is_lenient() {
+        assert_eq!(run("a%2Fb+c%zz%", &[UrlDecode]), b"a/b c%zz%");
+        assert_eq!(run("&lt;&#60;&#x3C;&bogus;&amp", &[HtmlDecode]), b"<<<&bogus;&amp");
+        assert_eq!(run("0x DE:AD be ef", &[HexDecode]), [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(run(" SGVs\nbG8 ", &[Base64Decode]), b"Hello");
+        assert_eq!(run("-_8", &[Base64UrlDecode]), [0xfb, 0xff]);
+    }
+
+    #[test]
+    fn test_digests_and_jwt_segments() {
+        assert_eq!(run("password", &[Md5, HexEncode]), b"5f4dcc3b5aa765d61d8327deb882cf99");
+        assert_eq!(
+            run("abc", &[Sha256, HexEncode]),
+            b"ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(run("abc", &[Sha1]).len(), 20);
+        assert_eq!(run("abc", &[Sha512]).len(), 64);
+
+        let token = "Bearer eyJhbGciOiJIUzI1NiJ9.eyJzdWIiOiJhbGljZSJ9.AAEC";
+        assert_eq!(run(token, &[JwtHeader]), br#"{"alg":"HS256"}"#);
+        assert_eq!(run(token, &[JwtPayload]), br#"{"sub":"alice"}"#);
+        assert_eq!(run(token, &[JwtSignature]), [0, 1, 2]);
+    }
+
+    #[test]
+    fn test_errors_name_the_failing_step() {
+        assert!(matches!(
+            transform(b"abc", &[HexEncode, Gunzip]),
+            Err(TransformError::Step { step: 2, op: Gunzip, .. })
+        ));
+        assert!(matches!(
+            transform(&[0xff, 0xfe, 0xfd], &[HtmlDecode]),
+            Err(TransformError::Step { step: 1, .. })
+        ));
+        assert_eq!(transform(b"", &[Md5; MAX_TRANSFORM_STEPS + 1]), Err(TransformError::TooManySteps));
+        assert_eq!(transform(b"same", &[]).unwrap(), Vec::new());
+    }
+}
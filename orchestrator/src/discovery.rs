@@ -0,0 +1,363 @@
+//! Content Discovery Runs
+//!
+//! Runs the discovery mode of the attack engine: every directory is first
+//! baselined with random paths, then its wordlist requests are spread over
+//! the run's agents in turn, a few at a time. Findings go to the
+//! `discovery_results` table as they come in and, for recursive runs, the
+//! directories among them are queued to be searched the same way.
+//!
+//! Discovery is a brute-force attack, so its requests are held to the
+//! Intruder rules of engagement.
+
+use crate::database::DiscoveryResultRow;
+use crate::engagement::EngagementGuard;
+use crate::models::engagement::ActiveTool;
+use crate::repeater::RepeaterManager;
+use crate::Database;
+use attack_engine::discovery::html_title;
+use attack_engine::{
+    AttackError, AttackResult, DiscoveryConfig, DiscoveryMode, DiscoveryRequest, DiscoveryVerdict, HttpResponseData,
+    Soft404Baseline,
+};
+use dashmap::DashMap;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+use url::Url;
+use uuid::Uuid;
+
+/// Requests in flight per run when none is given
+pub const DEFAULT_DISCOVERY_CONCURRENCY: usize = 10;
+
+/// Failed requests in a row after which a run gives up
+const MAX_CONSECUTIVE_ERRORS: usize = 50;
+
+/// What to search and with which agents
+#[derive(Debug, Clone)]
+pub struct DiscoveryRunConfig {
+    pub discovery: DiscoveryConfig,
+    /// Agents the requests are spread over; the project's default agent
+    /// when empty
+    pub agents: Vec<String>,
+    /// Requests in flight at once
+    pub concurrency: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiscoveryStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Progress of a discovery run
+#[derive(Debug, Clone)]
+pub struct DiscoveryRun {
+    pub id: String,
+    pub base_url: String,
+    pub agents: Vec<String>,
+    pub status: DiscoveryStatus,
+    /// Directories queued so far, the base URL included
+    pub directories_queued: usize,
+    pub directories_searched: usize,
+    /// Requests of the directories queued so far, baselines included
+    pub requests_planned: usize,
+    pub requests_sent: usize,
+    pub findings: usize,
+    pub soft_404s: usize,
+    pub failed_requests: usize,
+    pub error: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Discovery runs of the orchestrator; findings are stored, progress is
+/// kept in memory
+pub struct DiscoveryManager {
+    database: Arc<Database>,
+    repeater: Arc<RepeaterManager>,
+    runs: Arc<DashMap<String, DiscoveryRun>>,
+    cancellations: Arc<DashMap<String, Arc<AtomicBool>>>,
+}
+
+/// State shared by the requests of one run
+struct RunContext {
+    id: String,
+    mode: DiscoveryMode,
+    base: Url,
+    agents: Vec<String>,
+    next_agent: AtomicUsize,
+    consecutive_errors: AtomicUsize,
+    guard: EngagementGuard,
+    repeater: Arc<RepeaterManager>,
+    database: Arc<Database>,
+    runs: Arc<DashMap<String, DiscoveryRun>>,
+}
+
+impl DiscoveryManager {
+    pub fn new(database: Arc<Database>, repeater: Arc<RepeaterManager>) -> Self {
+        Self {
+            database,
+            repeater,
+            runs: Arc::new(DashMap::new()),
+            cancellations: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Start searching in the background; returns the run ID
+    pub async fn start(&self, config: DiscoveryRunConfig) -> AttackResult<String> {
+        config.discovery.validate().map_err(|reason| AttackError::ValidationError {
+            field: "discovery".to_string(),
+            reason,
+        })?;
+        if config.concurrency == 0 {
+            return Err(AttackError::ValidationError {
+                field: "concurrency".to_string(),
+                reason: "At least one request must be allowed in flight".to_string(),
+            });
+        }
+
+        let agents = if config.agents.is_empty() {
+            vec![self.repeater.resolve_agent(None, "").await?]
+        } else {
+            for agent_id in &config.agents {
+                self.repeater.validate_agent_availability(agent_id).await?;
+            }
+            config.agents.clone()
+        };
+
+        let guard = EngagementGuard::load(self.database.clone(), ActiveTool::Intruder).await?;
+        guard.check(&config.discovery.base_url).await?;
+
+        let mode = DiscoveryMode::new(config.discovery.clone());
+        let base = mode.base_directory().map_err(|reason| AttackError::ValidationError {
+            field: "base_url".to_string(),
+            reason,
+        })?;
+
+        let id = Uuid::new_v4().to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancellations.insert(id.clone(), cancelled.clone());
+        self.runs.insert(
+            id.clone(),
+            DiscoveryRun {
+                id: id.clone(),
+                base_url: base.to_string(),
+                agents: agents.clone(),
+                status: DiscoveryStatus::Running,
+                directories_queued: 1,
+                directories_searched: 0,
+                requests_planned: config.discovery.requests_per_directory(),
+                requests_sent: 0,
+                findings: 0,
+                soft_404s: 0,
+                failed_requests: 0,
+                error: None,
+                started_at: chrono::Utc::now(),
+                finished_at: None,
+            },
+        );
+        info!("🔎 Discovery run {} searching {} with {} agent(s)", id, base, agents.len());
+
+        let context = Arc::new(RunContext {
+            id: id.clone(),
+            mode,
+            base,
+            agents,
+            next_agent: AtomicUsize::new(0),
+            consecutive_errors: AtomicUsize::new(0),
+            guard,
+            repeater: self.repeater.clone(),
+            database: self.database.clone(),
+            runs: self.runs.clone(),
+        });
+        let cancellations = self.cancellations.clone();
+        tokio::spawn(async move {
+            let outcome = context.search(config.concurrency, &cancelled).await;
+            cancellations.remove(&context.id);
+            if let Some(mut run) = context.runs.get_mut(&context.id) {
+                match outcome {
+                    Ok(status) => run.status = status,
+                    Err(error) => {
+                        run.status = DiscoveryStatus::Failed;
+                        run.error = Some(error);
+                    }
+                }
+                run.finished_at = Some(chrono::Utc::now());
+                info!("🔎 Discovery run {} {:?} with {} findings", context.id, run.status, run.findings);
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// Stop searching; returns false if the run is not running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.cancellations.get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<DiscoveryRun> {
+        self.runs.get(id).map(|run| run.clone())
+    }
+
+    /// Every run, newest first
+    pub fn list(&self) -> Vec<DiscoveryRun> {
+        let mut runs: Vec<DiscoveryRun> = self.runs.iter().map(|run| run.clone()).collect();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.started_at));
+        runs
+    }
+
+    /// Forget a run and delete its findings
+    pub async fn delete(&self, id: &str) -> AttackResult<bool> {
+        self.cancel(id);
+        let deleted = self.database.delete_discovery_results(id).await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("delete_discovery_results: {}", e),
+        })?;
+        Ok(self.runs.remove(id).is_some() || deleted > 0)
+    }
+}
+
+impl RunContext {
+    /// Search the base directory and, breadth first, the directories found
+    async fn search(self: &Arc<Self>, concurrency: usize, cancelled: &AtomicBool) -> Result<DiscoveryStatus, String> {
+        let mut queue = VecDeque::from([(self.base.clone(), 0u32)]);
+        let mut queued = HashSet::from([self.base.to_string()]);
+
+        while let Some((directory, depth)) = queue.pop_front() {
+            let mut baseline = Soft404Baseline::default();
+            for probe in self.mode.baseline_requests(&directory) {
+                if cancelled.load(Ordering::Relaxed) {
+                    return Ok(DiscoveryStatus::Cancelled);
+                }
+                if let Some((response, _)) = self.send(&probe).await? {
+                    baseline.record(&probe.path, &response);
+                }
+            }
+
+            let requests = self.mode.requests_for(&directory);
+            for batch in requests.chunks(concurrency) {
+                if cancelled.load(Ordering::Relaxed) {
+                    return Ok(DiscoveryStatus::Cancelled);
+                }
+                let mut tasks = tokio::task::JoinSet::new();
+                for request in batch.iter().cloned() {
+                    let context = self.clone();
+                    tasks.spawn(async move {
+                        let sent = context.send(&request).await;
+                        (request, sent)
+                    });
+                }
+                while let Some(joined) = tasks.join_next().await {
+                    let (request, sent) = joined.map_err(|e| e.to_string())?;
+                    let Some((response, agent_id)) = sent? else {
+                        continue;
+                    };
+                    let Some(child) = self.record(&request, &response, &agent_id, &baseline, depth).await else {
+                        continue;
+                    };
+                    if queued.insert(child.to_string()) {
+                        queue.push_back((child, depth + 1));
+                        if let Some(mut run) = self.runs.get_mut(&self.id) {
+                            run.directories_queued += 1;
+                            run.requests_planned += self.mode.config().requests_per_directory();
+                        }
+                    }
+                }
+            }
+
+            if let Some(mut run) = self.runs.get_mut(&self.id) {
+                run.directories_searched += 1;
+            }
+        }
+        Ok(DiscoveryStatus::Completed)
+    }
+
+    /// Send `request` through the next agent. `None` if it failed; an error
+    /// once too many requests in a row failed.
+    async fn send(&self, request: &DiscoveryRequest) -> Result<Option<(HttpResponseData, String)>, String> {
+        let agent_id = &self.agents[self.next_agent.fetch_add(1, Ordering::Relaxed) % self.agents.len()];
+        let result = match self.guard.check(&request.request.url).await {
+            Ok(()) => self.repeater.execute_through_agent(&request.request, agent_id).await,
+            Err(e) => Err(e),
+        };
+
+        if let Some(mut run) = self.runs.get_mut(&self.id) {
+            run.requests_sent += 1;
+            if result.is_err() {
+                run.failed_requests += 1;
+            }
+        }
+        match result {
+            Ok(response) => {
+                self.consecutive_errors.store(0, Ordering::Relaxed);
+                Ok(Some((response, agent_id.clone())))
+            }
+            Err(e) => {
+                warn!("Discovery run {}: {} failed: {}", self.id, request.request.url, e);
+                if self.consecutive_errors.fetch_add(1, Ordering::Relaxed) + 1 >= MAX_CONSECUTIVE_ERRORS {
+                    return Err(format!("{} requests in a row failed, last: {}", MAX_CONSECUTIVE_ERRORS, e));
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Classify a response and store it if it is a finding. Returns the
+    /// directory to search next, if it is one and the run goes that deep.
+    async fn record(
+        &self,
+        request: &DiscoveryRequest,
+        response: &HttpResponseData,
+        agent_id: &str,
+        baseline: &Soft404Baseline,
+        depth: u32,
+    ) -> Option<Url> {
+        let is_directory = match self.mode.classify(&request.path, response, baseline) {
+            DiscoveryVerdict::Found { is_directory } => is_directory,
+            DiscoveryVerdict::Soft404 => {
+                if let Some(mut run) = self.runs.get_mut(&self.id) {
+                    run.soft_404s += 1;
+                }
+                return None;
+            }
+            DiscoveryVerdict::NotFound => return None,
+        };
+
+        let url = request.request.url.clone();
+        let path = url.strip_prefix(self.base.as_str()).unwrap_or(&url).to_string();
+        let row = DiscoveryResultRow {
+            id: Uuid::new_v4().to_string(),
+            run_id: self.id.clone(),
+            url,
+            path,
+            status_code: response.status_code,
+            size: response.body.len() as i64,
+            title: html_title(response),
+            is_directory,
+            depth,
+            agent_id: agent_id.to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+        };
+        if let Err(e) = self.database.save_discovery_result(&row).await {
+            warn!("Discovery run {}: failed to store {}: {}", self.id, row.url, e);
+        }
+        if let Some(mut run) = self.runs.get_mut(&self.id) {
+            run.findings += 1;
+        }
+        info!("🔎 {} {} ({} bytes)", response.status_code, row.url, row.size);
+
+        if !is_directory || !self.mode.descends_from(depth) {
+            return None;
+        }
+        let directory = format!("{}/", row.url.trim_end_matches('/'));
+        Url::parse(&directory).ok()
+    }
+}
@@ -0,0 +1,236 @@
+//! Engagement Guardrails
+//!
+//! Enforces the rules of engagement for active tooling: the target allowlist
+//! and the testing schedule. Tools load an `EngagementGuard` once per
+//! operation and check every outgoing URL with it; refusals are returned as
+//! `AttackError::SecurityViolation` and recorded in the engagement audit
+//! trail. Checks fail closed: a URL without a host is refused while the
+//! allowlist is enabled.
+
+use crate::models::engagement::{
+    host_matches, target_host, ActiveTool, AllowlistOverride, EngagementEvent, EngagementSchedule,
+    TargetAllowlist,
+};
+use crate::Database;
+use attack_engine::{AttackError, AttackResult};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Snapshot of the allowlist, active overrides and schedule used by one tool run
+pub struct EngagementGuard {
+    db: Arc<Database>,
+    tool: ActiveTool,
+    allowlist: TargetAllowlist,
+    overrides: Vec<AllowlistOverride>,
+    schedule: EngagementSchedule,
+    // Targets already written to the audit trail, so a large attack logs each host once
+    audited: Mutex<HashSet<(EngagementEvent, String)>>,
+}
+
+impl EngagementGuard {
+    /// Load the current allowlist for `tool`
+    pub async fn load(db: Arc<Database>, tool: ActiveTool) -> AttackResult<Self> {
+        let allowlist = db.get_target_allowlist().await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("get_target_allowlist: {}", e),
+        })?;
+        let overrides = if allowlist.enabled {
+            db.list_allowlist_overrides(true)
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("list_allowlist_overrides: {}", e),
+                })?
+        } else {
+            Vec::new()
+        };
+        let schedule = db.get_engagement_schedule().await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("get_engagement_schedule: {}", e),
+        })?;
+
+        Ok(Self {
+            db,
+            tool,
+            allowlist,
+            overrides,
+            schedule,
+            audited: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Refuse to run outside the engagement windows
+    pub async fn check_window(&self) -> AttackResult<()> {
+        if self.schedule.is_open(chrono::Utc::now()) {
+            return Ok(());
+        }
+
+        let details = format!("outside the testing windows ({})", self.schedule.timezone);
+        // One entry per run: a long attack keeps hitting the closed window
+        self.audit(EngagementEvent::OutsideWindow, "", &details).await;
+        Err(AttackError::SecurityViolation {
+            violation_type: "outside_engagement_window".to_string(),
+            details: format!("{} refused: {}", self.tool.as_str(), details),
+        })
+    }
+
+    /// Refuse `url` outside the engagement windows, or unless its host is
+    /// allowlisted or covered by an active override
+    pub async fn check(&self, url: &str) -> AttackResult<()> {
+        self.check_window().await?;
+
+        if !self.allowlist.enabled {
+            return Ok(());
+        }
+
+        let host = target_host(url);
+        if let Some(host) = &host {
+            if self.allowlist.allows(host) {
+                return Ok(());
+            }
+
+            let now = chrono::Utc::now().timestamp();
+            let granted = self
+                .overrides
+                .iter()
+                .find(|o| o.is_active(now) && host_matches(&o.host_pattern, host));
+            if let Some(o) = granted {
+                let details = format!("override {}: {}", o.id, o.reason);
+                self.audit(EngagementEvent::OverrideUsed, host, &details).await;
+                return Ok(());
+            }
+        }
+
+        let target = host.as_deref().unwrap_or(url);
+        self.audit(EngagementEvent::Blocked, target, url).await;
+        Err(AttackError::SecurityViolation {
+            violation_type: "target_not_allowlisted".to_string(),
+            details: format!(
+                "{} refused to target {}: host is outside the engagement allowlist",
+                self.tool.as_str(),
+                target
+            ),
+        })
+    }
+
+    async fn audit(&self, event: EngagementEvent, target: &str, details: &str) {
+        let first = self
+            .audited
+            .lock()
+            .map(|mut audited| audited.insert((event, target.to_string())))
+            .unwrap_or(true);
+        if !first {
+            return;
+        }
+
+        match event {
+            EngagementEvent::Blocked => warn!("🛑 {} blocked: {} is not allowlisted", self.tool.as_str(), target),
+            EngagementEvent::OutsideWindow => warn!("🛑 {} blocked: {}", self.tool.as_str(), details),
+            _ => warn!("⚠️ {} targeting {} under an allowlist override", self.tool.as_str(), target),
+        }
+        let target = Some(target).filter(|t| !t.is_empty());
+        if let Err(e) = self
+            .db
+            .record_engagement_event(event, Some(self.tool.as_str()), target, None, Some(details))
+            .await
+        {
+            warn!("Failed to record engagement audit entry: {}", e);
+        }
+    }
+}
+
+/// Check a single URL for tools that send one request at a time
+pub async fn authorize_target(db: &Arc<Database>, tool: ActiveTool, url: &str) -> AttackResult<()> {
+    EngagementGuard::load(Arc::clone(db), tool).await?.check(url).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn test_db() -> (Arc<Database>, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(temp_dir.path().to_str().unwrap()).await.unwrap());
+        db.create_project("engagement").await.unwrap();
+        db.load_project("engagement").await.unwrap();
+        (db, temp_dir)
+    }
+
+    #[tokio::test]
+    async fn test_guard_blocks_and_audits() {
+        let (db, _dir) = test_db().await;
+        let allowlist = TargetAllowlist {
+            enabled: true,
+            hosts: vec!["*.staging.example.com".into()],
+            version: 0,
+        };
+        db.save_target_allowlist(&allowlist, 0).await.unwrap();
+
+        let guard = EngagementGuard::load(db.clone(), ActiveTool::Intruder).await.unwrap();
+        assert!(guard.check("https://api.staging.example.com/login").await.is_ok());
+        assert!(matches!(
+            guard.check("https://www.example.com/").await,
+            Err(AttackError::SecurityViolation { .. })
+        ));
+        assert!(guard.check("https://www.example.com/other").await.is_err());
+        assert!(guard.check("not a url").await.is_err());
+
+        // Repeated blocks of one host are audited once
+        let audit = db.list_engagement_audit(10).await.unwrap();
+        assert_eq!(audit.len(), 2);
+        assert!(audit.iter().all(|e| e.event == "Blocked" && e.tool.as_deref() == Some("Intruder")));
+    }
+
+    #[tokio::test]
+    async fn test_override_allows_target() {
+        let (db, _dir) = test_db().await;
+        let allowlist = TargetAllowlist {
+            enabled: true,
+            hosts: vec![],
+            version: 0,
+        };
+        db.save_target_allowlist(&allowlist, 0).await.unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        db.add_allowlist_override(&AllowlistOverride {
+            id: "o1".into(),
+            host_pattern: "prod.example.com".into(),
+            reason: "approved retest".into(),
+            granted_by: Some("alice".into()),
+            created_at: now,
+            expires_at: now + 3600,
+            revoked_at: None,
+        })
+        .await
+        .unwrap();
+
+        assert!(authorize_target(&db, ActiveTool::Repeater, "https://prod.example.com/").await.is_ok());
+
+        assert!(db.revoke_allowlist_override("o1").await.unwrap());
+        assert!(authorize_target(&db, ActiveTool::Repeater, "https://prod.example.com/").await.is_err());
+
+        let events: Vec<String> = db.list_engagement_audit(10).await.unwrap().into_iter().map(|e| e.event).collect();
+        assert_eq!(events, vec!["Blocked", "OverrideUsed"]);
+    }
+
+    #[tokio::test]
+    async fn test_closed_window_blocks_active_tools() {
+        let (db, _dir) = test_db().await;
+        let today = chrono::Utc::now().date_naive();
+        let schedule = EngagementSchedule {
+            enabled: true,
+            end_date: today.pred_opt(),
+            ..Default::default()
+        };
+        db.save_engagement_schedule(&schedule, 0).await.unwrap();
+
+        let guard = EngagementGuard::load(db.clone(), ActiveTool::Intruder).await.unwrap();
+        assert!(guard.check_window().await.is_err());
+        assert!(guard.check("https://app.example.com/").await.is_err());
+        assert!(guard.check("https://app.example.com/next").await.is_err());
+
+        let audit = db.list_engagement_audit(10).await.unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].event, "OutsideWindow");
+        assert_eq!(audit[0].target, None);
+    }
+}
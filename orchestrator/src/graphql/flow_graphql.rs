@@ -3,7 +3,8 @@
 //! GraphQL types for browser flow recording and replay.
 
 use async_graphql::{SimpleObject, InputObject, Enum};
-use crate::database::flow::{FlowProfileRow, FlowExecutionRow};
+use crate::database::flow::{FlowProfileRow, FlowExecutionRow, FlowRevealAuditRow};
+use crate::database::crawl::DiscoveredRouteRow;
 
 // ============================================================================
 // ENUMS
@@ -127,7 +128,8 @@ impl From<FlowProfileRow> for FlowProfileGql {
             name: row.name,
             flow_type: FlowTypeGql::from(row.flow_type.as_str()),
             start_url: row.start_url,
-            steps: row.steps,
+            // Sensitive typed values are only available via reveal
+            steps: flow_engine::flow::masking::mask_steps_json(&row.steps),
             meta: row.meta,
             created_at: row.created_at,
             updated_at: row.updated_at,
@@ -175,6 +177,62 @@ impl From<FlowExecutionRow> for FlowExecutionGql {
     }
 }
 
+#[derive(SimpleObject, Debug, Clone)]
+pub struct BrowserProfileGql {
+    pub name: String,
+    pub size_bytes: u64,
+    pub last_used: Option<i64>,
+}
+
+impl From<flow_engine::BrowserProfileInfo> for BrowserProfileGql {
+    fn from(info: flow_engine::BrowserProfileInfo) -> Self {
+        Self {
+            name: info.name,
+            size_bytes: info.size_bytes,
+            last_used: info.last_used.map(|t| t.timestamp()),
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct MaskingPolicyGql {
+    pub mask_password_fields: bool,
+    pub field_patterns: Vec<String>,
+    pub allow_reveal: bool,
+}
+
+impl From<flow_engine::MaskingPolicy> for MaskingPolicyGql {
+    fn from(policy: flow_engine::MaskingPolicy) -> Self {
+        Self {
+            mask_password_fields: policy.mask_password_fields,
+            field_patterns: policy.field_patterns,
+            allow_reveal: policy.allow_reveal,
+        }
+    }
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct FlowRevealAuditGql {
+    pub id: i64,
+    pub timestamp: i64,
+    pub profile_id: String,
+    pub step_index: i64,
+    /// Empty for the admin token or with access control off
+    pub principal: Option<String>,
+}
+
+impl From<FlowRevealAuditRow> for FlowRevealAuditGql {
+    fn from(row: FlowRevealAuditRow) -> Self {
+        Self {
+            id: row.id,
+            timestamp: row.timestamp,
+            profile_id: row.profile_id,
+            step_index: row.step_index,
+            principal: row.principal,
+        }
+    }
+}
+
 #[derive(SimpleObject)]
 pub struct FlowOperationResult {
     pub success: bool,
@@ -198,6 +256,74 @@ pub struct CreateFlowProfileInput {
 pub struct UpdateFlowProfileInput {
     pub name: Option<String>,
     pub status: Option<ProfileStatusGql>,
+    /// Persistent browser profile to replay in; empty string clears it
+    pub browser_profile: Option<String>,
+}
+
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+#[graphql(rename_items = "PascalCase")]
+pub enum SelectorTypeGql {
+    Css,
+    XPath,
+    Text,
+    AriaLabel,
+    Placeholder,
+}
+
+impl From<SelectorTypeGql> for flow_engine::flow::model::SelectorType {
+    fn from(t: SelectorTypeGql) -> Self {
+        use flow_engine::flow::model::SelectorType;
+        match t {
+            SelectorTypeGql::Css => SelectorType::Css,
+            SelectorTypeGql::XPath => SelectorType::XPath,
+            SelectorTypeGql::Text => SelectorType::Text,
+            SelectorTypeGql::AriaLabel => SelectorType::AriaLabel,
+            SelectorTypeGql::Placeholder => SelectorType::Placeholder,
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct InsertFlowStepInput {
+    pub profile_id: String,
+    /// Position to insert at; appends when omitted
+    pub index: Option<i32>,
+    pub step: String, // JSON-encoded FlowStep
+}
+
+#[derive(InputObject)]
+pub struct UpdateFlowStepInput {
+    pub profile_id: String,
+    pub index: i32,
+    /// Replace the whole step (JSON-encoded FlowStep); other fields apply on top
+    pub step: Option<String>,
+    pub selector: Option<String>,
+    pub selector_type: Option<SelectorTypeGql>,
+    /// New typed value (Type steps) or option value (Select steps)
+    pub value: Option<String>,
+    pub is_masked: Option<bool>,
+}
+
+#[derive(InputObject)]
+pub struct MaskingPolicyInput {
+    pub mask_password_fields: bool,
+    pub field_patterns: Vec<String>,
+    pub allow_reveal: bool,
+}
+
+impl MaskingPolicyInput {
+    pub fn to_policy(&self) -> flow_engine::MaskingPolicy {
+        flow_engine::MaskingPolicy {
+            mask_password_fields: self.mask_password_fields,
+            field_patterns: self
+                .field_patterns
+                .iter()
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect(),
+            allow_reveal: self.allow_reveal,
+        }
+    }
 }
 
 #[derive(InputObject)]
@@ -214,6 +340,7 @@ pub struct ReplayFlowInput {
     pub agent_id: String,
     pub variables: Option<String>, // JSON object of variable substitutions
     pub headed: Option<bool>, // Show browser window
+    pub browser_profile: Option<String>, // Overrides the flow's persistent profile
 }
 
 // ============================================================================
@@ -273,6 +400,96 @@ pub struct FlowReplayResult {
     pub session_cookies: Option<String>,
 }
 
+#[derive(Enum, Copy, Clone, Eq, PartialEq, Debug)]
+#[graphql(rename_items = "PascalCase")]
+pub enum ReplayStepStatusGql {
+    Started,
+    Completed,
+    SelectorHealed,
+    Failed,
+}
+
+/// Live status of one step of a running replay
+#[derive(SimpleObject, Debug, Clone)]
+pub struct FlowReplayProgressGql {
+    /// Execution id returned by `replayFlow`
+    pub replay_id: String,
+    pub step_index: i32,
+    pub total_steps: Option<i32>,
+    pub status: ReplayStepStatusGql,
+    pub step_type: Option<String>,
+    pub original_selector: Option<String>,
+    pub healed_selector: Option<String>,
+    pub error: Option<String>,
+    /// File name of the failure screenshot in the project's replay screenshot folder
+    pub screenshot: Option<String>,
+}
+
+impl FlowReplayProgressGql {
+    pub fn from_event(replay_id: &str, event: flow_engine::ReplayEvent) -> Self {
+        use flow_engine::ReplayEvent;
+
+        let mut gql = Self {
+            replay_id: replay_id.to_string(),
+            step_index: 0,
+            total_steps: None,
+            status: ReplayStepStatusGql::Started,
+            step_type: None,
+            original_selector: None,
+            healed_selector: None,
+            error: None,
+            screenshot: None,
+        };
+        match event {
+            ReplayEvent::StepStarted { step_index, total_steps, kind } => {
+                gql.step_index = step_index as i32;
+                gql.total_steps = Some(total_steps as i32);
+                gql.step_type = Some(kind.to_string());
+            }
+            ReplayEvent::SelectorHealed { step_index, original, healed } => {
+                gql.step_index = step_index as i32;
+                gql.status = ReplayStepStatusGql::SelectorHealed;
+                gql.original_selector = Some(original);
+                gql.healed_selector = Some(healed);
+            }
+            ReplayEvent::StepCompleted { step_index, total_steps } => {
+                gql.step_index = step_index as i32;
+                gql.total_steps = Some(total_steps as i32);
+                gql.status = ReplayStepStatusGql::Completed;
+            }
+            ReplayEvent::StepFailed { step_index, error, screenshot } => {
+                gql.step_index = step_index as i32;
+                gql.status = ReplayStepStatusGql::Failed;
+                gql.error = Some(error);
+                gql.screenshot = screenshot;
+            }
+        }
+        gql
+    }
+}
+
+#[derive(InputObject)]
+pub struct ParallelReplayFlowInput {
+    pub profile_id: String,
+    pub agent_ids: Vec<String>,
+    pub variables: Option<String>, // JSON object of variable substitutions
+    pub headed: Option<bool>,
+    pub browser_profile: Option<String>, // Each agent gets its own copy
+    pub max_concurrency: Option<i32>,
+}
+
+#[derive(SimpleObject, Debug, Clone)]
+pub struct AgentExecutionGql {
+    pub agent_id: String,
+    pub execution_id: String,
+}
+
+#[derive(SimpleObject)]
+pub struct ParallelFlowReplayResult {
+    pub success: bool,
+    pub executions: Vec<AgentExecutionGql>,
+}
+
 #[derive(InputObject)]
 pub struct StopRecordingInput {
     pub save: bool, // Whether to save the recording
@@ -300,3 +517,54 @@ pub struct FlowProfileWithTrafficGql {
     pub profile: FlowProfileGql,
     pub traffic: Vec<TrafficItemGql>,
 }
+
+// ============================================================================
+// DOM CRAWL TYPES
+// ============================================================================
+
+#[derive(InputObject)]
+pub struct StartDomCrawlInput {
+    pub start_url: String,
+    pub proxy_port: Option<i32>, // Agent proxy port (default 9095)
+    pub max_depth: Option<i32>,
+    pub max_pages: Option<i32>,
+    pub scope_hosts: Option<Vec<String>>, // Defaults to the start URL host
+    pub submit_forms: Option<bool>,
+    pub allow_post_forms: Option<bool>,
+    pub headed: Option<bool>, // Show browser window
+}
+
+#[derive(SimpleObject)]
+pub struct DomCrawlStartResult {
+    pub success: bool,
+    pub crawl_id: Option<String>,
+    pub message: String,
+}
+
+/// A route found by the DOM crawler
+#[derive(SimpleObject, Debug, Clone)]
+pub struct DiscoveredRouteGql {
+    pub url: String,
+    pub crawl_id: String,
+    pub host: String,
+    pub depth: i64,
+    pub source: String,
+    pub title: Option<String>,
+    pub visited: bool,
+    pub discovered_at: i64,
+}
+
+impl From<DiscoveredRouteRow> for DiscoveredRouteGql {
+    fn from(row: DiscoveredRouteRow) -> Self {
+        Self {
+            url: row.url,
+            crawl_id: row.crawl_id,
+            host: row.host,
+            depth: row.depth,
+            source: row.source,
+            title: row.title,
+            visited: row.visited,
+            discovered_at: row.discovered_at,
+        }
+    }
+}
@@ -1,13 +1,32 @@
 use crate::pb::{traffic_event, SystemMetricsEvent, TrafficEvent};
 use crate::Database;
-use crate::models::settings::{ScopeConfig, InterceptionConfig, InterceptionRule, RuleCondition, RuleAction};
-use crate::repeater::{RepeaterManager, CreateRepeaterTabRequest, RepeaterExecutionRequest, RepeaterTabConfig, RepeaterExecutionResponse};
-use crate::intruder::{IntruderManager, IntruderAttackConfig, PayloadSetConfig};
+use crate::models::settings::{DefaultAgentConfig, ScopeConfig, InterceptionConfig, InterceptionRule, RuleCondition, RuleAction};
+use crate::interception::{InterceptQueue, InterceptStatus, InterceptedRequest};
+use crate::coloring::{ColoringCondition, ColoringConfig, ColoringRule};
+use crate::passive_checks::{MatcherDefinition, PassiveCheckDefinition, PassiveScanner, Severity};
+use crate::hook_scripts::{HookScriptDefinition, HookScriptStore};
+use crate::auth::{AuthService, Caller};
+use crate::models::access::{check_access, Principal, ProjectMember, ProjectRole};
+use crate::models::engagement::{ActiveTool, AllowlistOverride, EngagementAuditEntry, EngagementEvent, EngagementSchedule, EngagementWindow, TargetAllowlist};
+use crate::database::SettingsError;
+use crate::database::search::{SearchSnippet, SnippetSource};
+use crate::database::views::{SavedView, ViewSortField};
+use crate::repeater::{RepeaterManager, CreateRepeaterTabRequest, RepeaterExecutionRequest, RepeaterExecutionOptions, RepeaterTabConfig, RepeaterExecutionResponse, RedirectHop};
+use crate::intruder::{AttackSimulation, IntruderManager, IntruderAttackConfig, PayloadSetConfig, SimulationOptions};
+use crate::intruder::baseline::{AttackBaseline, ResultDelta};
+use crate::intruder::flow_sessions::FlowSessionConfig;
+use crate::intruder::highlighting::{HighlightCondition, ResultHighlightRule};
+use crate::intruder::sequence::{AttackSequence, SequenceStep};
+use crate::discovery::{DiscoveryManager, DiscoveryRun, DiscoveryRunConfig, DiscoveryStatus};
+use crate::access_matrix::{AccessCell, AccessMatrix, AccessMatrixConfig, AccessMatrixRow, AccessMatrixRun, AccessMatrixStatus, CellVerdict};
+use crate::tamper_checks::{TamperCheckManager, TamperRun, TamperRunConfig, TamperRunStatus, TamperTarget};
+use crate::sequencer::{FipsTest, RandomnessQuality, RandomnessReport, Sequencer, SequencerConfig, SequencerRun, SequencerStatus};
 use crate::database::intruder::{IntruderAttack, IntruderResult, PayloadSet};
+use crate::session_integration::macros::{ExtractionSource, MacroStep, MacroTool, SessionMacro, SessionMacroConfig, TokenExtraction};
 use crate::session_integration::{SessionManager, SessionSelectionCriteria, SessionApplicationResult, SessionRefreshResult, ExpirationHandling, AuthFailureDetectionConfig, SessionStatistics};
-use attack_engine::{HttpRequestData, HttpResponseData, AttackMode, DistributionStrategy, PayloadConfig};
+use attack_engine::{HttpRequestData, HttpResponseData, AttackMode, DistributionStrategy, GraphQlAttackConfig, PayloadConfig, PayloadProcessor, UploadCatalog, UploadField, UploadPosition, UploadTemplate};
 use proxy_common::session::{Session, SessionStatus, Cookie, SameSite, SessionEvent};
-use async_graphql::{ComplexObject, Context, Object, Schema, SimpleObject, Subscription, InputObject};
+use async_graphql::{ComplexObject, Context, ErrorExtensions, Object, Schema, SimpleObject, Subscription, InputObject};
 use base64::Engine;
 use std::sync::Arc;
 use tokio_stream::Stream;
@@ -15,6 +34,7 @@ use tokio_stream::StreamExt;
 use uuid::Uuid;
 
 pub mod flow_graphql;
+pub mod observer;
 
 pub type ProxySchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
 
@@ -30,6 +50,11 @@ impl QueryRoot {
         "Hello from Proxxy!"
     }
 
+    /// Whether the server is in read-only observer mode (all mutations are rejected)
+    async fn read_only(&self, ctx: &Context<'_>) -> bool {
+        ctx.data_opt::<observer::ReadOnlyMode>().is_some_and(|mode| mode.0)
+    }
+
     /// List available projects
     async fn projects(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ProjectGql>> {
         let db = ctx.data::<Arc<Database>>()?;
@@ -45,24 +70,32 @@ impl QueryRoot {
         &self, 
         ctx: &Context<'_>,
         agent_id: Option<String>,
+        #[graphql(desc = "Only transactions tagged with this API protocol")]
+        protocol: Option<ApiProtocolGql>,
+        #[graphql(desc = "Only transactions carrying this tag")]
+        tag: Option<String>,
         limit: Option<i32>,
         offset: Option<i32>,
     ) -> async_graphql::Result<Vec<TrafficEventGql>> {
         let db = ctx.data::<Arc<Database>>()?;
         let limit = limit.unwrap_or(50) as i64;
         let offset = offset.unwrap_or(0) as i64;
+        let protocol = protocol.map(|p| crate::api_protocol::ApiProtocol::from(p).as_str());
         
         let events = db
-            .get_recent_requests_paginated(agent_id.as_deref(), limit, offset)
+            .get_recent_requests_paginated(agent_id.as_deref(), protocol, tag.as_deref(), limit, offset)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
         // OPTIMIZATION: Pre-allocate with known capacity
         let mut result = Vec::with_capacity(events.len());
-        for (aid, event, status) in events {
-            let mut gql = TrafficEventGql::from(event);
-            gql.agent_id = Some(aid);
-            if let Some(s) = status {
+        for row in events {
+            let mut gql = TrafficEventGql::from(row.event);
+            gql.agent_id = Some(row.agent_id);
+            gql.api_protocol = ApiProtocolGql::from_tag(row.api_protocol.as_deref());
+            gql.parent_id = row.parent_id;
+            gql.set_annotation(row.annotation);
+            if let Some(s) = row.status {
                 gql.status = Some(s);
             }
             result.push(gql);
@@ -70,6 +103,116 @@ impl QueryRoot {
         Ok(result)
     }
 
+    /// Transactions whose URL or bodies contain `text`, newest first. Bodies
+    /// are decoded with their declared charset, so non-UTF-8 pages match too.
+    async fn search_requests(
+        &self,
+        ctx: &Context<'_>,
+        text: String,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<TrafficEventGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let hits = db
+            .search_transactions(&text, limit.unwrap_or(50).max(0) as usize, 1)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(hits.into_iter().map(|hit| TrafficEventGql::from_recent(hit.request)).collect())
+    }
+
+    /// Like `search_requests`, with snippets of the text around each match
+    /// (`max_snippets` per transaction, at most 10)
+    async fn search_hits(
+        &self,
+        ctx: &Context<'_>,
+        text: String,
+        limit: Option<i32>,
+        max_snippets: Option<i32>,
+    ) -> async_graphql::Result<Vec<SearchHitGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let hits = db
+            .search_transactions(
+                &text,
+                limit.unwrap_or(50).max(0) as usize,
+                max_snippets.unwrap_or(3).max(0) as usize,
+            )
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(hits
+            .into_iter()
+            .map(|hit| SearchHitGql {
+                transaction: TrafficEventGql::from_recent(hit.request),
+                snippets: hit.snippets.into_iter().map(SearchSnippetGql::from).collect(),
+            })
+            .collect())
+    }
+
+    /// Hex and ASCII rows of a stored body range, `length` bytes (default
+    /// 4096, at most 65536) from `offset`, for paging through binary payloads
+    async fn body_hex(
+        &self,
+        ctx: &Context<'_>,
+        request_id: String,
+        part: Option<BodyPartGql>,
+        offset: Option<i64>,
+        length: Option<i64>,
+    ) -> async_graphql::Result<HexDumpGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let part = part.unwrap_or(BodyPartGql::Response).into();
+        let offset = u64::try_from(offset.unwrap_or(0)).map_err(|_| async_graphql::Error::new("offset must not be negative"))?;
+        let length = length
+            .map(u64::try_from)
+            .transpose()
+            .map_err(|_| async_graphql::Error::new("length must not be negative"))?;
+        let dump = crate::hex_view::hex_dump(db, &request_id, part, offset, length)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(HexDumpGql::from(dump))
+    }
+
+    /// Decode a JWT (bare or with a `Bearer` prefix) without verifying it;
+    /// null if `value` is not a signed JWT
+    async fn decode_token(&self, value: String) -> Option<DecodedTokenGql> {
+        let now = chrono::Utc::now();
+        crate::jwt::DecodedToken::decode(&value, now).map(|token| DecodedTokenGql::new(token, now))
+    }
+
+    /// Run a chain of encode/decode operations over `input`, each step
+    /// working on the bytes the previous one produced
+    async fn transform(
+        &self,
+        input: String,
+        #[graphql(default_with = "TransformInputFormat::Text")] input_format: TransformInputFormat,
+        operations: Vec<TransformOpGql>,
+    ) -> async_graphql::Result<TransformResultGql> {
+        let bytes = match input_format {
+            TransformInputFormat::Text => input.into_bytes(),
+            TransformInputFormat::Base64 => base64::engine::general_purpose::STANDARD
+                .decode(input.trim())
+                .map_err(|e| async_graphql::Error::new(format!("Invalid base64 input: {}", e)))?,
+            TransformInputFormat::Hex => crate::decoder::transform(input.as_bytes(), &[crate::decoder::TransformOp::HexDecode])
+                .map_err(|e| async_graphql::Error::new(format!("Invalid hex input: {}", e)))?
+                .remove(0)
+                .output,
+        };
+        let ops: Vec<crate::decoder::TransformOp> = operations.into_iter().map(Into::into).collect();
+        let steps = crate::decoder::transform(&bytes, &ops).map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let output = steps.last().map_or(bytes, |step| step.output.clone());
+        Ok(TransformResultGql {
+            output: TransformOutputGql::new(&output),
+            steps: steps
+                .into_iter()
+                .map(|step| TransformStepGql {
+                    operation: step.op.into(),
+                    length: step.output.len() as i32,
+                    is_text: std::str::from_utf8(&step.output).is_ok(),
+                })
+                .collect(),
+        })
+    }
+
     /// Get single request by ID (HEAVYWEIGHT - includes body/headers when requested)
     /// Use this for detail view - GraphQL will only parse body/headers for this ONE request
     async fn request(
@@ -105,6 +248,10 @@ impl QueryRoot {
             
             let mut gql = TrafficEventGql::from(request_event);
             gql.agent_id = Some(tx.agent_id);
+            gql.egress_ip = tx.egress_ip;
+            gql.api_protocol = ApiProtocolGql::from_tag(tx.api_protocol.as_deref());
+            gql.parent_id = tx.parent_id;
+            gql.set_annotation(tx.annotation);
             gql.url = Some(tx.request.url);
             gql.method = Some(tx.request.method);
             gql.response_event = response_event;
@@ -145,6 +292,52 @@ impl QueryRoot {
         Ok(result)
     }
 
+    /// Egress IPs an agent has used, newest first. Changes show where
+    /// rotating NAT or VPN exits switched the source address targets saw.
+    async fn agent_egress_history(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: String,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<EgressObservationGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let history = db
+            .get_agent_egress_history(&agent_id, limit.unwrap_or(50).min(1000) as i64)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(history
+            .into_iter()
+            .map(|(ip, observed_at)| EgressObservationGql {
+                ip,
+                observed_at: chrono::DateTime::from_timestamp(observed_at, 0)
+                    .unwrap_or_default()
+                    .to_rfc3339(),
+            })
+            .collect())
+    }
+
+    /// Requests and bytes proxied per agent per UTC day, of one agent or of
+    /// all, from `since` to `until` inclusive (`YYYY-MM-DD`)
+    async fn agent_usage(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: Option<String>,
+        since: Option<String>,
+        until: Option<String>,
+    ) -> async_graphql::Result<Vec<AgentUsageGql>> {
+        for day in [&since, &until].into_iter().flatten() {
+            chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d")
+                .map_err(|_| async_graphql::Error::new(format!("Invalid day '{}', expected YYYY-MM-DD", day)))?;
+        }
+        let db = ctx.data::<Arc<Database>>()?;
+        let usage = db
+            .list_agent_usage(agent_id.as_deref(), since.as_deref(), until.as_deref())
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(usage.into_iter().map(AgentUsageGql::from).collect())
+    }
+
     async fn system_metrics(
         &self,
         ctx: &Context<'_>,
@@ -197,2035 +390,6897 @@ impl QueryRoot {
         })
     }
 
-    /// Get target scope rules
-    async fn scope_rules(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ScopeRuleGql>> {
+    /// Requests held by pause rules and waiting for a decision, oldest first
+    async fn intercepted_requests(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<InterceptedRequestGql>> {
+        let intercepts = ctx.data::<Arc<InterceptQueue>>()?;
+        Ok(intercepts
+            .list()
+            .await
+            .into_iter()
+            .map(|i| InterceptedRequestGql::new(i, InterceptStatus::Pending))
+            .collect())
+    }
+
+    /// How often each interception rule was checked and matched on the agents,
+    /// and what it costs per request
+    async fn interception_rule_stats(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<InterceptionRuleStatsGql>> {
+        let config = ctx.data::<Arc<tokio::sync::RwLock<InterceptionConfig>>>()?.read().await.clone();
+        let stats = ctx.data::<Arc<InterceptQueue>>()?.rule_stats(&config).await;
+
+        Ok(stats
+            .into_iter()
+            .map(|stat| InterceptionRuleStatsGql {
+                avg_eval_micros: stat.avg_eval_micros(),
+                total_eval_micros: stat.eval_time_ns as f64 / 1000.0,
+                rule_id: stat.rule_id,
+                name: stat.name,
+                active: stat.active,
+                evaluations: stat.evaluations,
+                hits: stat.hits,
+                last_hit: stat
+                    .last_hit
+                    .and_then(|ts| chrono::DateTime::from_timestamp(ts, 0))
+                    .map(|d| d.to_rfc3339()),
+            })
+            .collect())
+    }
+
+    /// Access list of the active project; empty when the project is not shared
+    async fn project_members(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ProjectMemberGql>> {
+        require_project_role(ctx, ProjectRole::Viewer).await?;
         let db = ctx.data::<Arc<Database>>()?;
-        let rules = db.get_scope_rules().await
+        let members = db.list_project_members().await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(rules.into_iter().map(ScopeRuleGql::from).collect())
+
+        Ok(members.into_iter().map(ProjectMemberGql::from).collect())
     }
 
-    /// Get all repeater tabs
-    async fn repeater_tabs(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<RepeaterTabGql>> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
-        let tabs = repeater_manager.get_tabs().await;
-        
-        Ok(tabs.into_iter().map(RepeaterTabGql::from).collect())
+    /// Hosts active tooling may target (rules of engagement)
+    async fn target_allowlist(&self, ctx: &Context<'_>) -> async_graphql::Result<TargetAllowlistGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let allowlist = db.get_target_allowlist().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(TargetAllowlistGql::from(allowlist))
     }
 
-    /// Get a specific repeater tab by ID
-    async fn repeater_tab(
+    /// Active testing windows
+    async fn engagement_schedule(&self, ctx: &Context<'_>) -> async_graphql::Result<EngagementScheduleGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let schedule = db.get_engagement_schedule().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(EngagementScheduleGql::from(schedule))
+    }
+
+    /// Allowlist overrides, newest first
+    async fn allowlist_overrides(
         &self,
         ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<RepeaterTabGql>> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
-        
-        if let Some(tab) = repeater_manager.get_tab(&id).await {
-            Ok(Some(RepeaterTabGql::from(tab)))
-        } else {
-            Ok(None)
-        }
-    }
+        include_inactive: Option<bool>,
+    ) -> async_graphql::Result<Vec<AllowlistOverrideGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let overrides = db.list_allowlist_overrides(!include_inactive.unwrap_or(false)).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
-    /// Get CA certificate PEM
-    async fn ca_cert_pem(&self, ctx: &Context<'_>) -> async_graphql::Result<String> {
-        let ca = ctx.data::<Arc<proxy_core::CertificateAuthority>>()?;
-        Ok(ca.get_ca_cert_pem().unwrap_or_default())
+        Ok(overrides.into_iter().map(AllowlistOverrideGql::from).collect())
     }
 
-    /// Get execution history for a repeater tab
-    async fn repeater_history(
+    /// Engagement audit trail: blocked requests, overrides and allowlist changes
+    async fn engagement_audit(
         &self,
         ctx: &Context<'_>,
-        tab_id: String,
         limit: Option<i32>,
-    ) -> async_graphql::Result<Vec<RepeaterExecutionGql>> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
-        let limit = limit.map(|l| l as i64);
-        
-        let executions = repeater_manager
-            .get_execution_history(&tab_id, limit)
-            .await
+    ) -> async_graphql::Result<Vec<EngagementAuditEntryGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let entries = db.list_engagement_audit(limit.unwrap_or(100).clamp(1, 1000) as i64).await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(executions.into_iter().map(RepeaterExecutionGql::from).collect())
+
+        Ok(entries.into_iter().map(EngagementAuditEntryGql::from).collect())
     }
 
-    /// Get a specific repeater execution by ID
-    async fn repeater_execution(
-        &self,
-        ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<RepeaterExecutionGql>> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
-        
-        if let Some(execution) = repeater_manager.get_execution(&id).await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))? {
-            Ok(Some(RepeaterExecutionGql::from(execution)))
-        } else {
-            Ok(None)
-        }
+    /// Get the DNS resolver configuration agents receive at registration
+    async fn dns_config(&self, ctx: &Context<'_>) -> async_graphql::Result<DnsConfigGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let config = db.get_dns_config().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(DnsConfigGql::from(config))
     }
 
-    /// Get all intruder attacks
-    async fn intruder_attacks(
-        &self,
-        ctx: &Context<'_>,
-        limit: Option<i32>,
-    ) -> async_graphql::Result<Vec<IntruderAttackGql>> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        let limit = limit.map(|l| l as i64);
-        
-        let attacks = intruder_manager
-            .list_attacks(limit)
-            .await
+    /// Hosts agents resolve to a fixed address instead of through DNS
+    async fn host_overrides(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<HostOverrideGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let overrides = db.get_host_overrides().await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(attacks.into_iter().map(IntruderAttackGql::from).collect())
+
+        Ok(HostOverrideGql::list(overrides))
     }
 
-    /// Get a specific intruder attack by ID
-    async fn intruder_attack(
+    /// Client certificates agents present to upstreams requiring mutual TLS.
+    /// Private keys are never returned.
+    async fn client_certificates(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ClientCertificateGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let certificates = db.get_client_certificates().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(certificates.iter().map(ClientCertificateGql::from).collect())
+    }
+
+    /// TLS settings agents use for upstream connections, per host pattern
+    async fn tls_policies(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<TlsPolicyGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let policies = db.get_tls_policies().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(policies.iter().map(TlsPolicyGql::from).collect())
+    }
+
+    /// Rules that color and tag incoming transactions
+    async fn coloring_rules(&self, ctx: &Context<'_>) -> async_graphql::Result<ColoringConfigGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let config = db.get_coloring_config().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(ColoringConfigGql::from(config))
+    }
+
+    /// Session handling macros run before Repeater and Intruder requests
+    async fn session_macros(&self, ctx: &Context<'_>) -> async_graphql::Result<SessionMacroConfigGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let config = db.get_session_macros().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(SessionMacroConfigGql::from(config))
+    }
+
+    /// Agent Repeater and Intruder use when none is chosen, and the agent it
+    /// resolves to right now
+    async fn default_agent(&self, ctx: &Context<'_>) -> async_graphql::Result<DefaultAgentConfigGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let config = db.get_default_agent_config().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(DefaultAgentConfigGql::new(config, ctx.data::<Arc<crate::AgentRegistry>>()?))
+    }
+
+    /// Get masking policy for recorded flow inputs
+    async fn flow_masking_policy(&self, ctx: &Context<'_>) -> async_graphql::Result<flow_graphql::MaskingPolicyGql> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let policy = db.get_masking_policy().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(flow_graphql::MaskingPolicyGql::from(policy))
+    }
+
+    /// Reveal the typed value of a masked flow step (Owner only, and the
+    /// policy must allow reveal). Every reveal is audited.
+    async fn reveal_flow_step_value(
         &self,
         ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<IntruderAttackGql>> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        
-        if let Some(attack) = intruder_manager.get_attack(&id).await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))? {
-            Ok(Some(IntruderAttackGql::from(attack)))
-        } else {
-            Ok(None)
+        profile_id: String,
+        index: i32,
+    ) -> async_graphql::Result<String> {
+        use flow_engine::{ExposeSecret, SecretString};
+
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let policy = db.get_masking_policy().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if !policy.allow_reveal {
+            return Err(async_graphql::Error::new("Revealing masked values is disabled by the masking policy"));
         }
+
+        let row = db
+            .get_flow_profile(&profile_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Flow profile not found"))?;
+        let profile = flow_profile_for_replay(&row)?;
+
+        let step = usize::try_from(index).ok().and_then(|i| profile.steps.get(i));
+        let value: &SecretString = match step {
+            Some(flow_engine::FlowStep::Type { value, .. }) => value,
+            Some(other) => {
+                return Err(async_graphql::Error::new(format!("{} step has no typed value", other.kind())))
+            }
+            None => return Err(async_graphql::Error::new(format!("Step {} not found", index))),
+        };
+
+        // No audit record, no secret
+        let principal = ctx.data_opt::<Principal>().map(|p| p.0.as_str());
+        db.record_flow_reveal(&profile_id, index as i64, principal)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::warn!(
+            "🔓 Revealed typed value of step {} in flow profile {} (by {})",
+            index,
+            profile_id,
+            principal.unwrap_or("admin")
+        );
+        Ok(value.expose_secret().to_string())
     }
 
-    /// Get intruder attack results
-    async fn intruder_results(
+    /// Audit trail of revealed masked values (Owner only)
+    async fn flow_reveal_audit(
         &self,
         ctx: &Context<'_>,
-        attack_id: String,
         limit: Option<i32>,
-        offset: Option<i32>,
-    ) -> async_graphql::Result<Vec<IntruderResultGql>> {
-        let db = ctx.data::<Arc<crate::Database>>()?;
-        let limit = limit.map(|l| l as i64);
-        let offset = offset.map(|o| o as i64);
-        
-        let results = db
-            .get_intruder_results(&attack_id, limit, offset)
+    ) -> async_graphql::Result<Vec<flow_graphql::FlowRevealAuditGql>> {
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let rows = db.list_flow_reveals(limit.unwrap_or(100).clamp(1, 1000) as i64).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(rows.into_iter().map(flow_graphql::FlowRevealAuditGql::from).collect())
+    }
+
+    /// API keys, revoked ones included (admin only when authentication is on)
+    async fn api_keys(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ApiKeyGql>> {
+        let auth = require_admin(ctx)?;
+        let keys = auth
+            .list_api_keys()
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(results.into_iter().map(IntruderResultGql::from).collect())
+        Ok(keys.into_iter().map(ApiKeyGql::from).collect())
     }
 
-    /// Get intruder attack statistics
-    async fn intruder_attack_stats(
-        &self,
-        ctx: &Context<'_>,
-        attack_id: String,
-    ) -> async_graphql::Result<String> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        
-        let stats = intruder_manager
-            .get_attack_statistics(&attack_id)
+    /// MITM CA rotations, newest first
+    async fn ca_rotations(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<CaRotationGql>> {
+        require_admin(ctx)?;
+        let rotations = ctx
+            .data::<Arc<crate::ca_rotation::CaRotationService>>()?
+            .history()
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(stats.to_string())
+        Ok(rotations.into_iter().map(CaRotationGql::from).collect())
     }
 
-    /// Get all payload sets
-    async fn payload_sets(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PayloadSetGql>> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        
-        let sets = intruder_manager
-            .list_payload_sets()
+    /// Whether the MITM CA key is encrypted and still waiting for its
+    /// passphrase
+    async fn ca_locked(&self, ctx: &Context<'_>) -> async_graphql::Result<bool> {
+        require_admin(ctx)?;
+        Ok(ctx.data::<Arc<proxy_core::CertificateAuthority>>()?.is_locked())
+    }
+
+    /// Log levels in effect
+    async fn log_levels(&self, ctx: &Context<'_>) -> async_graphql::Result<LogLevelsGql> {
+        require_admin(ctx)?;
+        crate::logging::current_log_levels()
+            .map(LogLevelsGql::from)
+            .ok_or_else(|| async_graphql::Error::new(crate::logging::LoggingError::NotInitialized.to_string()))
+    }
+
+    /// Client certificates issued to agents for the mutual-TLS gRPC channel
+    async fn agent_certificates(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<AgentCertificateGql>> {
+        require_admin(ctx)?;
+        let agent_tls = ctx.data::<Arc<crate::agent_tls::AgentTlsService>>()?;
+        let certs = agent_tls
+            .list_agent_certificates()
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(sets.into_iter().map(PayloadSetGql::from).collect())
+        Ok(certs.into_iter().map(AgentCertificateGql::from).collect())
     }
 
-    /// Get a specific payload set by ID
-    async fn payload_set(
-        &self,
-        ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<PayloadSetGql>> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        
-        if let Some(set) = intruder_manager.get_payload_set(&id).await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))? {
-            Ok(Some(PayloadSetGql::from(set)))
-        } else {
-            Ok(None)
-        }
-    }
+    /// Protobuf descriptor sets used to decode captured gRPC calls
+    async fn grpc_descriptor_sets(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<GrpcDescriptorSetGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let sets = db
+            .list_grpc_descriptor_sets()
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
-    /// Get all available sessions
-    async fn sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<SessionGql>> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let sessions = session_manager.get_sessions().await;
-        
-        Ok(sessions.into_iter().map(SessionGql::from).collect())
+        Ok(sets.into_iter().map(GrpcDescriptorSetGql::from).collect())
     }
 
-    /// Get active sessions (non-expired, valid status)
-    async fn active_sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<SessionGql>> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let sessions = session_manager.get_active_sessions().await;
-        
-        Ok(sessions.into_iter().map(SessionGql::from).collect())
-    }
+    /// Transaction counts per API protocol
+    async fn api_protocols(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ApiProtocolCountGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let counts = db
+            .count_api_protocols()
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
-    /// Get a specific session by ID
-    async fn session(
-        &self,
-        ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<SessionGql>> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session_id = Uuid::parse_str(&id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-        
-        if let Some(session) = session_manager.get_session(&session_id).await {
-            Ok(Some(SessionGql::from(session)))
-        } else {
-            Ok(None)
-        }
+        Ok(counts
+            .into_iter()
+            .filter_map(|(tag, count)| {
+                ApiProtocolGql::from_tag(Some(&tag)).map(|protocol| ApiProtocolCountGql { protocol, count })
+            })
+            .collect())
     }
 
-    /// Select best session based on criteria
-    async fn select_session(
+    /// Redirect/retry chain a transaction belongs to, first request first;
+    /// a single entry when it is not part of a chain
+    async fn transaction_chain(
         &self,
         ctx: &Context<'_>,
-        criteria: Option<SessionSelectionCriteriaInput>,
-    ) -> async_graphql::Result<Option<SessionGql>> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let criteria = criteria.map(|c| c.into()).unwrap_or_default();
-        
-        if let Some(session) = session_manager.select_session(&criteria).await {
-            Ok(Some(SessionGql::from(session)))
-        } else {
-            Ok(None)
-        }
-    }
-
-    /// Get session statistics
-    async fn session_statistics(&self, ctx: &Context<'_>) -> async_graphql::Result<SessionStatisticsGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let stats = session_manager.get_session_statistics().await;
-        
-        Ok(SessionStatisticsGql::from(stats))
-    }
+        request_id: String,
+    ) -> async_graphql::Result<Vec<TransactionChainEntryGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let rows = db
+            .get_transaction_chain(&request_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
-    /// Get authentication failure detection configuration
-    async fn auth_failure_config(&self, ctx: &Context<'_>) -> async_graphql::Result<AuthFailureDetectionConfigGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let config = session_manager.get_auth_failure_config().await;
-        
-        Ok(AuthFailureDetectionConfigGql::from(config))
+        Ok(rows.into_iter().map(TransactionChainEntryGql::from).collect())
     }
 
-    // ========== Flow Profile Queries ==========
-
-    /// List all flow profiles
-    async fn flow_profiles(
-        &self,
-        ctx: &Context<'_>,
-        status: Option<String>,
-        limit: Option<i32>,
-    ) -> async_graphql::Result<Vec<flow_graphql::FlowProfileGql>> {
+    /// Tags in use on transactions, most used first
+    async fn request_tags(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<RequestTagGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        let limit = limit.unwrap_or(50) as i64;
-        
-        let profiles = db
-            .list_flow_profiles(status.as_deref(), limit)
+        let tags = db
+            .list_request_tags()
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(profiles.into_iter().map(flow_graphql::FlowProfileGql::from).collect())
+
+        Ok(tags.into_iter().map(|(tag, count)| RequestTagGql { tag, count }).collect())
     }
 
-    /// Get a single flow profile by ID
-    async fn flow_profile(
+    /// HAR 1.2 log of the newest transactions (default 1000), optionally only
+    /// those tagged `tag`. Comments go in each entry's `comment`, highlight
+    /// and tags in `_proxxy`.
+    async fn export_har(
         &self,
         ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<flow_graphql::FlowProfileGql>> {
+        tag: Option<String>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<String> {
         let db = ctx.data::<Arc<Database>>()?;
-        
-        let profile = db
-            .get_flow_profile(&id)
+        let har = db
+            .export_har(tag.as_deref(), limit.unwrap_or(1000) as i64)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(profile.map(flow_graphql::FlowProfileGql::from))
+
+        serde_json::to_string_pretty(&har).map_err(|e| async_graphql::Error::new(e.to_string()))
     }
 
-    /// Get flow profile with correlated HTTP traffic from recording time window
-    async fn flow_profile_with_traffic(
-        &self,
-        ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<Option<flow_graphql::FlowProfileWithTrafficGql>> {
+    /// Markdown report of the comments, highlights and tags in the active
+    /// project, optionally only transactions tagged `tag`
+    async fn annotation_report(&self, ctx: &Context<'_>, tag: Option<String>) -> async_graphql::Result<String> {
         let db = ctx.data::<Arc<Database>>()?;
-        
-        // Get the profile
-        let profile = db
-            .get_flow_profile(&id)
+        let project = db
+            .active_project_name()
             .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        if profile.is_none() {
-            return Ok(None);
-        }
-        
-        let profile = profile.unwrap();
-        let profile_gql = flow_graphql::FlowProfileGql::from(profile.clone());
-        
-        // Get HTTP traffic within the recording time window (created_at to updated_at)
-        let traffic_rows = db
-            .get_requests_by_time_range(profile.created_at, profile.updated_at)
+            .ok_or_else(|| async_graphql::Error::new("No active project loaded"))?;
+        let annotated = db
+            .list_annotated_transactions(tag.as_deref())
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        // Convert to GraphQL type
-        let traffic: Vec<flow_graphql::TrafficItemGql> = traffic_rows
-            .into_iter()
-            .map(|(request_id, method, path, host, status_code, timestamp)| {
-                flow_graphql::TrafficItemGql {
-                    id: request_id.clone(),
-                    request_id,
-                    method,
-                    path,
-                    host,
-                    status_code,
-                    timestamp,
-                }
-            })
-            .collect();
-        
-        Ok(Some(flow_graphql::FlowProfileWithTrafficGql {
-            profile: profile_gql,
-            traffic,
-        }))
+
+        Ok(crate::database::annotations::render_annotation_report(&project, &annotated))
     }
 
-    /// Get execution history for a flow profile
-    async fn flow_executions(
+    /// Custom passive checks compiled for the active project
+    async fn passive_checks(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PassiveCheckGql>> {
+        let scanner = ctx.data::<Arc<PassiveScanner>>()?;
+        Ok(scanner.list().await.into_iter().map(PassiveCheckGql::from).collect())
+    }
+
+    /// Hook scripts of the active project, as pushed to the agents
+    async fn hook_scripts(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<HookScriptGql>> {
+        let store = ctx.data::<Arc<HookScriptStore>>()?;
+        Ok(store.list().await.into_iter().map(HookScriptGql::from).collect())
+    }
+
+    /// Responses matched by custom passive checks, newest first
+    async fn passive_findings(
         &self,
         ctx: &Context<'_>,
-        profile_id: String,
+        request_id: Option<String>,
         limit: Option<i32>,
-    ) -> async_graphql::Result<Vec<flow_graphql::FlowExecutionGql>> {
+    ) -> async_graphql::Result<Vec<PassiveFindingGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        let limit = limit.unwrap_or(20) as i64;
-        
-        let executions = db
-            .get_flow_executions(&profile_id, limit)
+        let rows = db
+            .list_passive_findings(request_id.as_deref(), limit.unwrap_or(200) as i64)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(executions.into_iter().map(flow_graphql::FlowExecutionGql::from).collect())
-    }
-}
-
-// ============================================================================
-// MUTATION ROOT
-// ============================================================================
-
-pub struct MutationRoot;
 
-#[Object]
-impl MutationRoot {
-    async fn intercept(&self, _id: String, _action: String) -> bool {
-        // TODO: Implement interception logic
-        true
+        Ok(rows.into_iter().map(PassiveFindingGql::from).collect())
     }
 
-    async fn delete_requests_by_host(
+    /// Connection-level events (CONNECTs, TLS and upstream failures, blocks,
+    /// pass-throughs), newest first
+    async fn connection_events(
         &self,
         ctx: &Context<'_>,
-        host: String,
-    ) -> async_graphql::Result<bool> {
+        kind: Option<ConnectionEventKindGql>,
+        agent_id: Option<String>,
+        target: Option<String>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<ConnectionEventGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        db.delete_requests_by_host(&host)
+        let kind = kind.map(|k| crate::database::connections::kind_name(k.into()));
+        let rows = db
+            .list_connection_events(kind, agent_id.as_deref(), target.as_deref(), limit.unwrap_or(200) as i64)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(true)
-    }
 
-    async fn create_project(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<ProjectOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
-        db.create_project(&name).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(ProjectOperationResult { success: true, message: format!("Project '{}' created", name) })
+        Ok(rows.into_iter().map(ConnectionEventGql::from).collect())
     }
 
-    async fn load_project(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<ProjectOperationResult> {
+    /// Saved filters and sort orders of the request list, by name
+    async fn saved_views(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<SavedViewGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        let scope_state = ctx.data::<Arc<tokio::sync::RwLock<ScopeConfig>>>()?;
-        let interception_state = ctx.data::<Arc<tokio::sync::RwLock<InterceptionConfig>>>()?;
-        
-        // Load project (connects to DB)
-        db.load_project(&name).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        // Load settings from DB and update in-memory state
-        let scope_config = db.get_scope_config().await
-            .map_err(|e| async_graphql::Error::new(format!("Failed to load scope config: {}", e)))?;
-        let interception_config = db.get_interception_config().await
-            .map_err(|e| async_graphql::Error::new(format!("Failed to load interception config: {}", e)))?;
-        
-        // Update in-memory state
-        *scope_state.write().await = scope_config;
-        *interception_state.write().await = interception_config;
-        
-        Ok(ProjectOperationResult { 
-            success: true, 
-            message: format!("Project '{}' loaded with settings", name) 
-        })
+        let views = db
+            .list_saved_views()
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(views.into_iter().map(SavedViewGql::from).collect())
     }
 
-    async fn delete_project(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<ProjectOperationResult> {
+    /// Hosts seen with their totals; with `host`, that host's tree of paths
+    /// and the methods requested on each
+    async fn site_map(&self, ctx: &Context<'_>, host: Option<String>) -> async_graphql::Result<Vec<SiteMapNodeGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        db.delete_project(&name).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(ProjectOperationResult { success: true, message: format!("Project '{}' deleted", name) })
+        let nodes = match host {
+            Some(host) => db
+                .get_site_map(&host)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .into_iter()
+                .collect(),
+            None => db
+                .list_site_map_hosts()
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?,
+        };
+
+        Ok(nodes.into_iter().map(SiteMapNodeGql::from).collect())
     }
 
-    async fn add_scope_rule(
+    /// sqlmap or ffuf input for a stored request: the raw request file with the
+    /// insertion points marked and the command line that runs the tool on it
+    async fn export_to_tool(
         &self,
         ctx: &Context<'_>,
-        rule_type: String,
-        pattern: String,
-        is_regex: bool,
-    ) -> async_graphql::Result<ScopeRuleGql> {
+        request_id: String,
+        tool: ExternalToolGql,
+        insertion_points: Option<Vec<InsertionPointInput>>,
+        #[graphql(desc = "ffuf wordlist per insertion point, in order")]
+        wordlists: Option<Vec<String>>,
+        request_file_name: Option<String>,
+    ) -> async_graphql::Result<ToolExportGql> {
+        use crate::tool_export::{ffuf_export, sqlmap_export, DEFAULT_REQUEST_FILE};
+
         let db = ctx.data::<Arc<Database>>()?;
-        let rule = db.add_scope_rule(&rule_type, &pattern, is_regex)
+        let (_, request) = db
+            .get_request_by_id(&request_id)
             .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(ScopeRuleGql::from(rule))
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Request not found"))?;
+
+        let points: Vec<_> = insertion_points
+            .unwrap_or_default()
+            .into_iter()
+            .map(InsertionPointInput::into_point)
+            .collect();
+        let file_name = request_file_name.unwrap_or_else(|| DEFAULT_REQUEST_FILE.to_string());
+        let export = match tool {
+            ExternalToolGql::Sqlmap => sqlmap_export(&request, &points, &file_name),
+            ExternalToolGql::Ffuf => ffuf_export(&request, &points, &wordlists.unwrap_or_default(), &file_name),
+        }
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(ToolExportGql {
+            request_file: export.request_file,
+            request_file_name: export.request_file_name,
+            command: export.command,
+            config: export.config,
+        })
     }
 
-    async fn delete_scope_rule(
+    /// Distinct endpoints (method + URL without query) called with a protocol,
+    /// e.g. every SOAP endpoint seen
+    async fn api_endpoints(
         &self,
         ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<bool> {
+        protocol: ApiProtocolGql,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<ApiEndpointGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        db.delete_scope_rule(&id)
+        let protocol_tag = crate::api_protocol::ApiProtocol::from(protocol).as_str();
+        let rows = db
+            .list_api_endpoints(protocol_tag, limit.unwrap_or(500) as i64)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(true)
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ApiEndpointGql {
+                protocol,
+                method: row.method,
+                endpoint: row.endpoint,
+                hits: row.hits,
+                last_seen: chrono::DateTime::from_timestamp(row.last_seen, 0)
+                    .map(|d| d.to_rfc3339())
+                    .unwrap_or_default(),
+                last_request_id: row.last_request_id,
+            })
+            .collect())
     }
 
-    async fn toggle_scope_rule(
-        &self,
-        ctx: &Context<'_>,
-        id: String,
-        enabled: bool,
-    ) -> async_graphql::Result<bool> {
+    /// Get target scope rules
+    async fn scope_rules(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ScopeRuleGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        db.toggle_scope_rule(&id, enabled)
-            .await
+        let rules = db.get_scope_rules().await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        Ok(true)
+        
+        Ok(rules.into_iter().map(ScopeRuleGql::from).collect())
     }
 
-    async fn unload_project(&self, ctx: &Context<'_>) -> async_graphql::Result<ProjectOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
-        let scope_state = ctx.data::<Arc<tokio::sync::RwLock<ScopeConfig>>>()?;
-        let interception_state = ctx.data::<Arc<tokio::sync::RwLock<InterceptionConfig>>>()?;
-        
-        // Unload project (disconnects DB)
-        db.unload_project().await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        // Reset settings to defaults
-        *scope_state.write().await = ScopeConfig::default();
-        *interception_state.write().await = InterceptionConfig::default();
+    /// Get all repeater tabs
+    async fn repeater_tabs(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<RepeaterTabGql>> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        let tabs = repeater_manager.get_tabs().await;
         
-        Ok(ProjectOperationResult { 
-            success: true, 
-            message: "Project unloaded and settings reset".to_string() 
-        })
+        Ok(tabs.into_iter().map(RepeaterTabGql::from).collect())
     }
 
-    /// Replay a captured HTTP request
-    async fn replay_request(
+    /// Get a specific repeater tab by ID
+    async fn repeater_tab(
         &self,
         ctx: &Context<'_>,
-        request_id: String,
-    ) -> async_graphql::Result<ReplayResult> {
-        use crate::pb::{intercept_command, ExecuteRequest, InterceptCommand};
+        id: String,
+    ) -> async_graphql::Result<Option<RepeaterTabGql>> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        
+        if let Some(tab) = repeater_manager.get_tab(&id).await {
+            Ok(Some(RepeaterTabGql::from(tab)))
+        } else {
+            Ok(None)
+        }
+    }
 
-        let db = ctx.data::<Arc<Database>>()?;
-        let registry = ctx.data::<Arc<crate::AgentRegistry>>()?;
+    /// Content discovery runs, newest first
+    async fn discovery_runs(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<DiscoveryRunGql>> {
+        let discovery = ctx.data::<Arc<DiscoveryManager>>()?;
+        Ok(discovery.list().into_iter().map(DiscoveryRunGql::from).collect())
+    }
 
-        // 1. Get request from database
-        let request_data = db
-            .get_request_by_id(&request_id)
-            .await
-            .map_err(|e| async_graphql::Error::new(format!("Database error: {}", e)))?
-            .ok_or_else(|| async_graphql::Error::new("Request not found"))?;
+    async fn discovery_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<DiscoveryRunGql>> {
+        let discovery = ctx.data::<Arc<DiscoveryManager>>()?;
+        Ok(discovery.get(&id).map(DiscoveryRunGql::from))
+    }
 
-        // 2. Get agent ID for this request
-        let agent_id = db
-            .get_agent_id_for_request(&request_id)
+    /// Paths found by discovery, of one run or of all runs
+    async fn discovery_results(
+        &self,
+        ctx: &Context<'_>,
+        run_id: Option<String>,
+        #[graphql(default = 1000)] limit: i32,
+    ) -> async_graphql::Result<Vec<DiscoveryResultGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let results = db
+            .list_discovery_results(run_id.as_deref(), limit.clamp(1, 10_000) as i64)
             .await
-            .map_err(|e| async_graphql::Error::new(format!("Database error: {}", e)))?
-            .ok_or_else(|| async_graphql::Error::new("Agent not found for request"))?;
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(results.into_iter().map(DiscoveryResultGql::from).collect())
+    }
 
-        // 3. Get agent command channel
-        let agent_tx = registry.get_agent_tx(&agent_id).ok_or_else(|| {
-            async_graphql::Error::new(format!("Agent {} is not online", agent_id))
-        })?;
+    /// Sequencer runs, newest first
+    async fn sequencer_runs(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<SequencerRunGql>> {
+        let sequencer = ctx.data::<Arc<Sequencer>>()?;
+        Ok(sequencer.list().into_iter().map(SequencerRunGql::from).collect())
+    }
 
-        // 4. Generate new request ID for replay
-        let replay_request_id = format!("{}-replay-{}", request_id, chrono::Utc::now().timestamp());
+    /// A Sequencer run with its report once collection has finished
+    async fn sequencer_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<SequencerRunGql>> {
+        let sequencer = ctx.data::<Arc<Sequencer>>()?;
+        Ok(sequencer.get(&id).map(SequencerRunGql::from))
+    }
 
-        // 5. Send execute command to agent
-        let execute_cmd = InterceptCommand {
-            command: Some(intercept_command::Command::Execute(ExecuteRequest {
-                request_id: replay_request_id.clone(),
-                request: Some(request_data.1.clone()),
-            })),
-        };
+    /// Authorization matrix runs, newest first
+    async fn access_matrix_runs(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<AccessMatrixRunGql>> {
+        let matrix = ctx.data::<Arc<AccessMatrix>>()?;
+        Ok(matrix.list().into_iter().map(AccessMatrixRunGql::from).collect())
+    }
 
-        agent_tx.send(Ok(execute_cmd)).await.map_err(|e| {
-            async_graphql::Error::new(format!("Failed to send command to agent: {}", e))
-        })?;
+    async fn access_matrix_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<AccessMatrixRunGql>> {
+        let matrix = ctx.data::<Arc<AccessMatrix>>()?;
+        Ok(matrix.get(&id).map(AccessMatrixRunGql::from))
+    }
 
-        Ok(ReplayResult {
-            success: true,
-            message: format!("Replay request sent to agent {}", agent_id),
-            replay_request_id: Some(replay_request_id),
-            original_url: request_data.1.url,
-            original_method: request_data.1.method,
-        })
+    /// Tamper check runs, newest first
+    async fn tamper_runs(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<TamperRunGql>> {
+        let tamper_checks = ctx.data::<Arc<TamperCheckManager>>()?;
+        Ok(tamper_checks.list().into_iter().map(TamperRunGql::from).collect())
     }
 
-    /// Update scope configuration
-    async fn update_scope(
+    async fn tamper_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<Option<TamperRunGql>> {
+        let tamper_checks = ctx.data::<Arc<TamperCheckManager>>()?;
+        Ok(tamper_checks.get(&id).map(TamperRunGql::from))
+    }
+
+    /// Probes that changed the response, of one run or of all runs
+    async fn tamper_findings(
         &self,
         ctx: &Context<'_>,
-        input: ScopeInputGql,
-    ) -> async_graphql::Result<ScopeConfigGql> {
+        run_id: Option<String>,
+        #[graphql(default = 1000)] limit: i32,
+    ) -> async_graphql::Result<Vec<TamperFindingGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        
-        let config = input.to_scope_config();
-        db.save_scope_config(&config).await
+        let findings = db
+            .list_tamper_findings(run_id.as_deref(), limit.clamp(1, 10_000) as i64)
+            .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(ScopeConfigGql::from(config))
+        Ok(findings.into_iter().map(TamperFindingGql::from).collect())
     }
 
-    /// Toggle interception on/off
-    async fn toggle_interception(
+    /// Randomness report of tokens collected elsewhere, one per entry
+    async fn analyze_tokens(&self, tokens: Vec<String>) -> async_graphql::Result<RandomnessReportGql> {
+        if tokens.len() > crate::sequencer::MAX_SAMPLES {
+            return Err(async_graphql::Error::new(format!(
+                "at most {} tokens can be analysed",
+                crate::sequencer::MAX_SAMPLES
+            )));
+        }
+        crate::sequencer::analyze(&tokens)
+            .map(RandomnessReportGql::from)
+            .map_err(async_graphql::Error::new)
+    }
+
+    /// Get CA certificate PEM
+    async fn ca_cert_pem(&self, ctx: &Context<'_>) -> async_graphql::Result<String> {
+        let ca = ctx.data::<Arc<proxy_core::CertificateAuthority>>()?;
+        Ok(ca.get_ca_cert_pem().unwrap_or_default())
+    }
+
+    /// Get execution history for a repeater tab
+    async fn repeater_history(
         &self,
         ctx: &Context<'_>,
-        enabled: bool,
-    ) -> async_graphql::Result<InterceptionConfigGql> {
-        let db = ctx.data::<Arc<Database>>()?;
-        
-        let mut config = db.get_interception_config().await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        config.enabled = enabled;
+        tab_id: String,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<RepeaterExecutionGql>> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        let limit = limit.map(|l| l as i64);
         
-        db.save_interception_config(&config).await
+        let executions = repeater_manager
+            .get_execution_history(&tab_id, limit)
+            .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        Ok(InterceptionConfigGql::from(config))
+        Ok(executions.into_iter().map(RepeaterExecutionGql::from).collect())
     }
 
-    /// Add interception rule
-    async fn add_interception_rule(
+    /// Get a specific repeater execution by ID
+    async fn repeater_execution(
         &self,
         ctx: &Context<'_>,
-        rule: InterceptionRuleInputGql,
-    ) -> async_graphql::Result<InterceptionRuleGql> {
-        let db = ctx.data::<Arc<Database>>()?;
-        
-        let mut config = db.get_interception_config().await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        id: String,
+    ) -> async_graphql::Result<Option<RepeaterExecutionGql>> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
         
-        let new_rule = rule.to_interception_rule();
-        config.rules.push(new_rule.clone());
+        if let Some(execution) = repeater_manager.get_execution(&id).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))? {
+            Ok(Some(RepeaterExecutionGql::from(execution)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get all intruder attacks
+    async fn intruder_attacks(
+        &self,
+        ctx: &Context<'_>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<IntruderAttackGql>> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        let limit = limit.map(|l| l as i64);
         
-        db.save_interception_config(&config).await
+        let attacks = intruder_manager
+            .list_attacks(limit)
+            .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        Ok(InterceptionRuleGql::from(new_rule))
+        Ok(attacks.into_iter().map(IntruderAttackGql::from).collect())
     }
 
-    /// Remove interception rule by ID
-    async fn remove_interception_rule(
+    /// Get a specific intruder attack by ID
+    async fn intruder_attack(
         &self,
         ctx: &Context<'_>,
         id: String,
-    ) -> async_graphql::Result<bool> {
-        let db = ctx.data::<Arc<Database>>()?;
-        
-        let mut config = db.get_interception_config().await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        let before_len = config.rules.len();
-        config.rules.retain(|r| r.id != id);
-        let removed = config.rules.len() < before_len;
+    ) -> async_graphql::Result<Option<IntruderAttackGql>> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
         
-        if removed {
-            db.save_interception_config(&config).await
-                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if let Some(attack) = intruder_manager.get_attack(&id).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))? {
+            Ok(Some(IntruderAttackGql::from(attack)))
+        } else {
+            Ok(None)
         }
-        
-        Ok(removed)
     }
 
-    /// Export project to .proxxy file
-    async fn export_project(
+    /// Get intruder attack results
+    async fn intruder_results(
         &self,
         ctx: &Context<'_>,
-        name: String,
-        output_path: String,
-    ) -> async_graphql::Result<ProjectOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
+        attack_id: String,
+        limit: Option<i32>,
+        offset: Option<i32>,
+    ) -> async_graphql::Result<Vec<IntruderResultGql>> {
+        let db = ctx.data::<Arc<crate::Database>>()?;
+        let limit = limit.map(|l| l as i64);
+        let offset = offset.map(|o| o as i64);
         
-        db.export_project(&name, &output_path).await
+        let results = db
+            .get_intruder_results(&attack_id, limit, offset)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let baseline = ctx
+            .data::<Arc<IntruderManager>>()?
+            .get_attack_baseline(&attack_id)
+            .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        Ok(ProjectOperationResult {
-            success: true,
-            message: format!("Project '{}' exported to {}", name, output_path),
-        })
+        Ok(results
+            .into_iter()
+            .map(|result| IntruderResultGql::from(result).with_baseline(baseline.clone()))
+            .collect())
     }
 
-    /// Import project from .proxxy file
-    async fn import_project(
+    /// Get intruder attack statistics
+    async fn intruder_attack_stats(
         &self,
         ctx: &Context<'_>,
-        proxxy_path: String,
-        project_name: Option<String>,
-    ) -> async_graphql::Result<ProjectOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
+        attack_id: String,
+    ) -> async_graphql::Result<String> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
         
-        let imported_name = db.import_project(&proxxy_path, project_name.as_deref()).await
+        let stats = intruder_manager
+            .get_attack_statistics(&attack_id)
+            .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        Ok(ProjectOperationResult {
-            success: true,
-            message: format!("Project '{}' imported from {}", imported_name, proxxy_path),
-        })
+        Ok(stats.to_string())
     }
 
-    /// Create a new repeater tab
-    async fn create_repeater_tab(
-        &self,
-        ctx: &Context<'_>,
-        input: CreateRepeaterTabInput,
-    ) -> async_graphql::Result<RepeaterTabGql> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
-        
-        let request = CreateRepeaterTabRequest {
-            name: input.name,
-            request_template: input.request_template.into(),
-            target_agent_id: input.target_agent_id,
-        };
+    /// Get all payload sets
+    async fn payload_sets(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<PayloadSetGql>> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
         
-        let tab_id = repeater_manager
-            .create_tab(request)
+        let sets = intruder_manager
+            .list_payload_sets()
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        let tab = repeater_manager
-            .get_tab(&tab_id)
-            .await
-            .ok_or_else(|| async_graphql::Error::new("Failed to retrieve created tab"))?;
-        
-        Ok(RepeaterTabGql::from(tab))
+        Ok(sets.into_iter().map(PayloadSetGql::from).collect())
     }
 
-    /// Update a repeater tab
-    async fn update_repeater_tab(
+    /// Get a specific payload set by ID
+    async fn payload_set(
         &self,
         ctx: &Context<'_>,
         id: String,
-        input: UpdateRepeaterTabInput,
-    ) -> async_graphql::Result<RepeaterTabGql> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+    ) -> async_graphql::Result<Option<PayloadSetGql>> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
         
-        repeater_manager
-            .update_tab(
-                &id,
-                input.name,
-                input.request_template.map(|rt| rt.into()),
-                input.target_agent_id.map(Some),
-            )
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if let Some(set) = intruder_manager.get_payload_set(&id).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))? {
+            Ok(Some(PayloadSetGql::from(set)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get all available sessions
+    async fn sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<SessionGql>> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let sessions = session_manager.get_sessions().await;
         
-        let tab = repeater_manager
-            .get_tab(&id)
-            .await
-            .ok_or_else(|| async_graphql::Error::new("Tab not found after update"))?;
+        Ok(sessions.into_iter().map(SessionGql::from).collect())
+    }
+
+    /// Get active sessions (non-expired, valid status)
+    async fn active_sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<SessionGql>> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let sessions = session_manager.get_active_sessions().await;
         
-        Ok(RepeaterTabGql::from(tab))
+        Ok(sessions.into_iter().map(SessionGql::from).collect())
     }
 
-    /// Delete a repeater tab
-    async fn delete_repeater_tab(
+    /// Get a specific session by ID
+    async fn session(
         &self,
         ctx: &Context<'_>,
         id: String,
-    ) -> async_graphql::Result<bool> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
-        
-        repeater_manager
-            .delete_tab(&id)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+    ) -> async_graphql::Result<Option<SessionGql>> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session_id = Uuid::parse_str(&id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
         
-        Ok(true)
+        if let Some(session) = session_manager.get_session(&session_id).await {
+            Ok(Some(SessionGql::from(session)))
+        } else {
+            Ok(None)
+        }
     }
 
-    /// Execute a repeater request
-    async fn execute_repeater_request(
+    /// Select best session based on criteria
+    async fn select_session(
         &self,
         ctx: &Context<'_>,
-        input: ExecuteRepeaterRequestInput,
-    ) -> async_graphql::Result<RepeaterExecutionGql> {
-        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        criteria: Option<SessionSelectionCriteriaInput>,
+    ) -> async_graphql::Result<Option<SessionGql>> {
         let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let criteria = criteria.map(|c| c.into()).unwrap_or_default();
         
-        // Apply session if provided
-        let mut request_data: HttpRequestData = input.request_data.into();
-        let mut session_application_result = None;
-        
-        if let Some(session_id_str) = &input.session_id {
-            let session_id = Uuid::parse_str(session_id_str)
-                .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-            
-            let expiration_handling = input.expiration_handling.unwrap_or_default().into();
-            
-            match session_manager.apply_session_to_request(request_data, &session_id, expiration_handling).await {
-                Ok((modified_request, app_result)) => {
-                    request_data = modified_request;
-                    session_application_result = Some(app_result);
-                }
-                Err(e) => {
-                    return Err(async_graphql::Error::new(format!("Failed to apply session: {}", e)));
-                }
-            }
+        if let Some(session) = session_manager.select_session(&criteria).await {
+            Ok(Some(SessionGql::from(session)))
+        } else {
+            Ok(None)
         }
+    }
+
+    /// Get session statistics
+    async fn session_statistics(&self, ctx: &Context<'_>) -> async_graphql::Result<SessionStatisticsGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let stats = session_manager.get_session_statistics().await;
         
-        let request = RepeaterExecutionRequest {
-            tab_id: input.tab_id,
-            request_data,
-            target_agent_id: input.target_agent_id,
-            session_id: input.session_id,
-        };
-        
-        let execution = repeater_manager
-            .execute_request(request)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        // Broadcast the execution result for real-time updates
-        if let Ok(broadcast) = ctx.data::<tokio::sync::broadcast::Sender<RepeaterExecutionGql>>() {
-            let _ = broadcast.send(RepeaterExecutionGql::from(execution.clone()));
-        }
+        Ok(SessionStatisticsGql::from(stats))
+    }
+
+    /// Get authentication failure detection configuration
+    async fn auth_failure_config(&self, ctx: &Context<'_>) -> async_graphql::Result<AuthFailureDetectionConfigGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let config = session_manager.get_auth_failure_config().await;
         
-        Ok(RepeaterExecutionGql::from(execution))
+        Ok(AuthFailureDetectionConfigGql::from(config))
     }
 
-    /// Create a new intruder attack
-    async fn create_intruder_attack(
+    // ========== Flow Profile Queries ==========
+
+    /// List all flow profiles
+    async fn flow_profiles(
         &self,
         ctx: &Context<'_>,
-        input: CreateIntruderAttackInput,
-    ) -> async_graphql::Result<IntruderAttackGql> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        
-        // Convert session data if provided
-        let session_data = if let Some(session_input) = input.session_data {
-            Some(session_input.into())
-        } else {
-            None
-        };
-        
-        let config = IntruderAttackConfig {
-            name: input.name,
-            request_template: input.request_template,
-            attack_mode: input.attack_mode.into(),
-            payload_sets: input.payload_sets.into_iter().map(|ps| ps.into()).collect(),
-            target_agents: input.target_agents,
-            distribution_strategy: input.distribution_strategy.into(),
-            session_data,
-            execution_config: None,
-        };
+        status: Option<String>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<flow_graphql::FlowProfileGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let limit = limit.unwrap_or(50) as i64;
         
-        let attack_id = intruder_manager
-            .create_attack(config)
+        let profiles = db
+            .list_flow_profiles(status.as_deref(), limit)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        let attack = intruder_manager
-            .get_attack(&attack_id)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?
-            .ok_or_else(|| async_graphql::Error::new("Failed to retrieve created attack"))?;
-        
-        Ok(IntruderAttackGql::from(attack))
+        Ok(profiles.into_iter().map(flow_graphql::FlowProfileGql::from).collect())
     }
 
-    /// Start an intruder attack
-    async fn start_intruder_attack(
+    /// List persistent browser profiles of the active project
+    async fn browser_profiles(
         &self,
         ctx: &Context<'_>,
-        attack_id: String,
-    ) -> async_graphql::Result<IntruderAttackGql> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
-        
-        // Update attack status to running
-        intruder_manager
-            .update_attack_status(&attack_id, "running")
-            .await
+    ) -> async_graphql::Result<Vec<flow_graphql::BrowserProfileGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let Some(dir) = db.browser_profiles_dir().await else {
+            return Ok(Vec::new());
+        };
+        let profiles = flow_engine::BrowserManager::with_profiles_dir(dir)
+            .list_profiles()
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        // TODO: Start actual attack execution
-        // This would involve creating an execution config and starting the attack
-        
-        let attack = intruder_manager
-            .get_attack(&attack_id)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?
-            .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
-        
-        Ok(IntruderAttackGql::from(attack))
+
+        Ok(profiles.into_iter().map(flow_graphql::BrowserProfileGql::from).collect())
     }
 
-    /// Stop an intruder attack
-    async fn stop_intruder_attack(
+    /// Get a single flow profile by ID
+    async fn flow_profile(
         &self,
         ctx: &Context<'_>,
-        attack_id: String,
-    ) -> async_graphql::Result<IntruderAttackGql> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        id: String,
+    ) -> async_graphql::Result<Option<flow_graphql::FlowProfileGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
         
-        // Update attack status to stopped
-        intruder_manager
-            .update_attack_status(&attack_id, "stopped")
+        let profile = db
+            .get_flow_profile(&id)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        // TODO: Stop actual attack execution
-        
-        let attack = intruder_manager
-            .get_attack(&attack_id)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?
-            .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
-        
-        Ok(IntruderAttackGql::from(attack))
+        Ok(profile.map(flow_graphql::FlowProfileGql::from))
     }
 
-    /// Delete an intruder attack
-    async fn delete_intruder_attack(
+    /// Get flow profile with correlated HTTP traffic from recording time window
+    async fn flow_profile_with_traffic(
         &self,
         ctx: &Context<'_>,
-        attack_id: String,
-    ) -> async_graphql::Result<bool> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        id: String,
+    ) -> async_graphql::Result<Option<flow_graphql::FlowProfileWithTrafficGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
         
-        intruder_manager
-            .delete_attack(&attack_id)
+        // Get the profile
+        let profile = db
+            .get_flow_profile(&id)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        Ok(true)
-    }
-
-    /// Create a new payload set
-    async fn create_payload_set(
-        &self,
-        ctx: &Context<'_>,
-        input: CreatePayloadSetInput,
-    ) -> async_graphql::Result<PayloadSetGql> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        if profile.is_none() {
+            return Ok(None);
+        }
         
-        let payload_config: PayloadConfig = input.configuration.into();
+        let profile = profile.unwrap();
+        let profile_gql = flow_graphql::FlowProfileGql::from(profile.clone());
         
-        let set_id = intruder_manager
-            .create_payload_set(&input.name, &payload_config)
+        // Get HTTP traffic within the recording time window (created_at to updated_at)
+        let traffic_rows = db
+            .get_requests_by_time_range(profile.created_at, profile.updated_at)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        let set = intruder_manager
-            .get_payload_set(&set_id)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?
-            .ok_or_else(|| async_graphql::Error::new("Failed to retrieve created payload set"))?;
-        
-        Ok(PayloadSetGql::from(set))
+        // Convert to GraphQL type
+        let traffic: Vec<flow_graphql::TrafficItemGql> = traffic_rows
+            .into_iter()
+            .map(|(request_id, method, path, host, status_code, timestamp)| {
+                flow_graphql::TrafficItemGql {
+                    id: request_id.clone(),
+                    request_id,
+                    method,
+                    path,
+                    host,
+                    status_code,
+                    timestamp,
+                }
+            })
+            .collect();
+        
+        Ok(Some(flow_graphql::FlowProfileWithTrafficGql {
+            profile: profile_gql,
+            traffic,
+        }))
     }
 
-    /// Delete a payload set
-    async fn delete_payload_set(
+    /// Get execution history for a flow profile
+    async fn flow_executions(
         &self,
         ctx: &Context<'_>,
-        id: String,
-    ) -> async_graphql::Result<bool> {
-        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        profile_id: String,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<flow_graphql::FlowExecutionGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let limit = limit.unwrap_or(20) as i64;
         
-        intruder_manager
-            .delete_payload_set(&id)
+        let executions = db
+            .get_flow_executions(&profile_id, limit)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        Ok(true)
+        Ok(executions.into_iter().map(flow_graphql::FlowExecutionGql::from).collect())
     }
 
-    /// Add or update a session
-    async fn add_session(
+    /// Get routes discovered by the DOM crawler
+    async fn discovered_routes(
         &self,
         ctx: &Context<'_>,
-        input: SessionInput,
-    ) -> async_graphql::Result<SessionGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session: Session = input.into();
-        
-        session_manager
-            .add_session(session.clone())
+        host: Option<String>,
+        limit: Option<i32>,
+    ) -> async_graphql::Result<Vec<flow_graphql::DiscoveredRouteGql>> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let limit = limit.unwrap_or(1000) as i64;
+
+        let routes = db
+            .list_discovered_routes(host.as_deref(), limit)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(SessionGql::from(session))
+
+        Ok(routes.into_iter().map(flow_graphql::DiscoveredRouteGql::from).collect())
     }
+}
 
-    /// Remove a session
-    async fn remove_session(
+// ============================================================================
+// MUTATION ROOT
+// ============================================================================
+
+pub struct MutationRoot;
+
+#[Object]
+impl MutationRoot {
+    /// Forward or drop a held request; `action` is "forward" or "drop"
+    async fn intercept(&self, ctx: &Context<'_>, id: String, action: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let intercepts = ctx.data::<Arc<InterceptQueue>>()?;
+        let result = match action.to_ascii_lowercase().as_str() {
+            "forward" => intercepts.forward(&id, None).await,
+            "drop" => intercepts.drop_request(&id).await,
+            other => return Err(async_graphql::Error::new(format!("Unknown intercept action '{}'", other))),
+        };
+        result.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// Send a held request on unchanged
+    async fn forward_intercepted(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<InterceptedRequestGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let intercepts = ctx.data::<Arc<InterceptQueue>>()?;
+        let intercept = intercepts.forward(&id, None).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(InterceptedRequestGql::new(intercept, InterceptStatus::Forwarded))
+    }
+
+    /// Answer a held request with an error instead of sending it upstream
+    async fn drop_intercepted(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<InterceptedRequestGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let intercepts = ctx.data::<Arc<InterceptQueue>>()?;
+        let intercept = intercepts.drop_request(&id).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(InterceptedRequestGql::new(intercept, InterceptStatus::Dropped))
+    }
+
+    /// Send a held request on with the given edits applied
+    async fn modify_intercepted_request(
         &self,
         ctx: &Context<'_>,
         id: String,
+        edit: InterceptedRequestEditInput,
+    ) -> async_graphql::Result<InterceptedRequestGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let intercepts = ctx.data::<Arc<InterceptQueue>>()?;
+        let held = intercepts.get(&id).await
+            .ok_or_else(|| async_graphql::Error::new(format!("No intercepted request with ID {}", id)))?;
+        let edited = edit.apply(held.request)?;
+        let intercept = intercepts.forward(&id, Some(edited)).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(InterceptedRequestGql::new(intercept, InterceptStatus::Forwarded))
+    }
+
+    async fn delete_requests_by_host(
+        &self,
+        ctx: &Context<'_>,
+        host: String,
     ) -> async_graphql::Result<bool> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session_id = Uuid::parse_str(&id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-        
-        session_manager
-            .remove_session(&session_id)
+        let db = ctx.data::<Arc<Database>>()?;
+        db.delete_requests_by_host(&host)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
         Ok(true)
     }
 
-    /// Apply session to a request template (for testing/validation)
-    async fn apply_session_to_request(
+    /// Set the triage comment of a transaction; null or blank clears it
+    async fn set_request_comment(
         &self,
         ctx: &Context<'_>,
-        input: ApplySessionToRequestInput,
-    ) -> async_graphql::Result<SessionApplicationResultGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session_id = Uuid::parse_str(&input.session_id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-        
-        let request: HttpRequestData = input.request_template.into();
-        let expiration_handling = input.expiration_handling.unwrap_or_default().into();
-        
-        let (_, result) = session_manager
-            .apply_session_to_request(request, &session_id, expiration_handling)
+        request_id: String,
+        comment: Option<String>,
+    ) -> async_graphql::Result<RequestAnnotationGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let found = db
+            .set_request_comment(&request_id, comment.as_deref())
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(SessionApplicationResultGql::from(result))
+        request_annotation(db, request_id, found).await
     }
 
-    /// Validate a session against a target URL
-    async fn validate_session(
+    /// Set the highlight color of a transaction; null clears it
+    async fn set_request_color(
         &self,
         ctx: &Context<'_>,
-        session_id: String,
-        validation_url: String,
-    ) -> async_graphql::Result<bool> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session_id = Uuid::parse_str(&session_id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-        
-        let is_valid = session_manager
-            .validate_session(&session_id, &validation_url)
+        request_id: String,
+        color: Option<HighlightColorGql>,
+    ) -> async_graphql::Result<RequestAnnotationGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let found = db
+            .set_request_color(&request_id, color.map(Into::into))
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(is_valid)
+        request_annotation(db, request_id, found).await
     }
 
-    /// Refresh a session manually
-    async fn refresh_session_manually(
+    /// Replace the tags of a transaction; blank and duplicate tags are dropped
+    async fn set_request_tags(
         &self,
         ctx: &Context<'_>,
-        input: RefreshSessionManuallyInput,
-    ) -> async_graphql::Result<SessionRefreshResultGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session_id = Uuid::parse_str(&input.session_id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-        
-        let new_session_data: Session = input.new_session_data.into();
-        
-        let result = session_manager
-            .refresh_session_manually(&session_id, new_session_data)
+        request_id: String,
+        tags: Vec<String>,
+    ) -> async_graphql::Result<RequestAnnotationGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let found = db
+            .set_request_tags(&request_id, &tags)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(SessionRefreshResultGql::from(result))
+        request_annotation(db, request_id, found).await
     }
 
-    /// Refresh a session via LSR profile
-    async fn refresh_session_via_lsr(
+    /// Add or replace custom passive checks from YAML (one check per
+    /// document). The checks are saved in the project and take effect on the
+    /// next response.
+    async fn upload_passive_checks(
         &self,
         ctx: &Context<'_>,
-        session_id: String,
-        profile_id: String,
-    ) -> async_graphql::Result<SessionRefreshResultGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let session_id = Uuid::parse_str(&session_id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
-        let profile_id = Uuid::parse_str(&profile_id)
-            .map_err(|e| async_graphql::Error::new(format!("Invalid profile ID: {}", e)))?;
-        
-        let result = session_manager
-            .refresh_session_via_lsr(&session_id, &profile_id)
+        yaml: String,
+    ) -> async_graphql::Result<Vec<PassiveCheckGql>> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let dir = passive_checks_dir(ctx).await?;
+        let checks = ctx
+            .data::<Arc<PassiveScanner>>()?
+            .upload(&dir, &yaml)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(SessionRefreshResultGql::from(result))
+
+        Ok(checks.into_iter().map(PassiveCheckGql::from).collect())
     }
 
-    /// Update authentication failure detection configuration
-    async fn update_auth_failure_config(
-        &self,
-        ctx: &Context<'_>,
-        input: AuthFailureDetectionConfigInput,
-    ) -> async_graphql::Result<AuthFailureDetectionConfigGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()?;
-        let config: AuthFailureDetectionConfig = input.into();
-        
-        session_manager.update_auth_failure_config(config.clone()).await;
-        
-        Ok(AuthFailureDetectionConfigGql::from(config))
+    /// Save a named view of the request list; saving under an existing name
+    /// replaces that view
+    async fn save_view(&self, ctx: &Context<'_>, input: SavedViewInput) -> async_graphql::Result<SavedViewGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let name = input.name.trim();
+        if name.is_empty() {
+            return Err(async_graphql::Error::new("View name cannot be empty"));
+        }
+
+        let view = db
+            .save_view(
+                name,
+                input.filter.trim(),
+                input.sort_field.unwrap_or(ViewSortFieldGql::Timestamp).into(),
+                input.sort_direction == Some(SortDirectionGql::Asc),
+                input.is_default.unwrap_or(false),
+            )
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(SavedViewGql::from(view))
     }
 
-    // ========== Flow Profile Mutations ==========
+    async fn delete_saved_view(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        ctx.data::<Arc<Database>>()?
+            .delete_saved_view(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
 
-    /// Create a new flow profile
-    async fn create_flow_profile(
-        &self,
-        ctx: &Context<'_>,
-        input: flow_graphql::CreateFlowProfileInput,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
-        
-        let profile_id = Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp();
-        
-        let row = crate::database::flow::FlowProfileRow {
-            id: profile_id.clone(),
-            name: input.name.clone(),
-            flow_type: String::from(input.flow_type),
-            start_url: input.start_url,
-            steps: "[]".to_string(),
-            meta: None,
-            created_at: now,
-            updated_at: now,
-            agent_id: input.agent_id,
-            status: "Active".to_string(),
-        };
-        
-        db.save_flow_profile(&row)
+    async fn delete_passive_check(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let dir = passive_checks_dir(ctx).await?;
+        ctx.data::<Arc<PassiveScanner>>()?
+            .delete(&dir, &id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
+
+    /// Recompile the checks after editing files in the project's
+    /// `passive-checks` directory. Returns the number loaded.
+    async fn reload_passive_checks(&self, ctx: &Context<'_>) -> async_graphql::Result<i32> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let dir = passive_checks_dir(ctx).await?;
+        let count = ctx
+            .data::<Arc<PassiveScanner>>()?
+            .load_dir(&dir)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(flow_graphql::FlowOperationResult {
-            success: true,
-            message: format!("Flow profile '{}' created", input.name),
-            profile_id: Some(profile_id),
-        })
+        Ok(count as i32)
     }
 
-    /// Delete a flow profile
-    async fn delete_flow_profile(
+    /// Add or replace a hook script. It is saved in the project and pushed
+    /// to every agent, which swaps it in without restarting its proxy.
+    async fn upload_hook_script(
         &self,
         ctx: &Context<'_>,
         id: String,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
-        
-        let deleted = db
-            .delete_flow_profile(&id)
+        source: String,
+    ) -> async_graphql::Result<HookScriptGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let dir = hook_scripts_dir(ctx).await?;
+        let script = ctx
+            .data::<Arc<HookScriptStore>>()?
+            .upload(&dir, &id, &source)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(flow_graphql::FlowOperationResult {
-            success: deleted,
-            message: if deleted {
-                "Flow profile deleted".to_string()
-            } else {
-                "Flow profile not found".to_string()
-            },
-            profile_id: Some(id),
-        })
+
+        Ok(script.into())
     }
 
-    /// Update a flow profile
-    async fn update_flow_profile(
-        &self,
-        ctx: &Context<'_>,
-        id: String,
-        input: flow_graphql::UpdateFlowProfileInput,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
-        
-        let status_str = input.status.map(String::from);
-        
-        let updated = db
-            .update_flow_profile(&id, input.name.as_deref(), None, None, status_str.as_deref())
+    async fn delete_hook_script(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let dir = hook_scripts_dir(ctx).await?;
+        ctx.data::<Arc<HookScriptStore>>()?
+            .delete(&dir, &id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
+
+    /// Reload and push the scripts after editing files in the project's
+    /// `hook-scripts` directory. Returns the number loaded.
+    async fn reload_hook_scripts(&self, ctx: &Context<'_>) -> async_graphql::Result<i32> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let dir = hook_scripts_dir(ctx).await?;
+        let count = ctx
+            .data::<Arc<HookScriptStore>>()?
+            .load_dir(&dir)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-        
-        Ok(flow_graphql::FlowOperationResult {
-            success: updated,
-            message: if updated {
-                "Flow profile updated".to_string()
-            } else {
-                "Flow profile not found".to_string()
-            },
-            profile_id: Some(id),
-        })
+        Ok(count as i32)
     }
 
-    /// Start recording a new browser flow
-    async fn start_flow_recording(
+    /// Pause or resume traffic capture on one agent, or on all connected
+    /// agents. Paused agents keep proxying but report nothing for hosts
+    /// matching `hosts` (every host if omitted). Returns the number of agents
+    /// the change was sent to.
+    async fn set_capture_paused(
         &self,
         ctx: &Context<'_>,
-        input: flow_graphql::StartRecordingInput,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let db = ctx.data::<Arc<Database>>()?;
-        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
-        
-        // Create profile in "Recording" state
-        let profile_id = Uuid::new_v4().to_string();
-        let now = chrono::Utc::now().timestamp();
-        let start_url = input.start_url.clone();
-        
-        let row = crate::database::flow::FlowProfileRow {
-            id: profile_id.clone(),
-            name: input.name.clone(),
-            flow_type: String::from(input.flow_type),
-            start_url: start_url.clone(),
-            steps: "[]".to_string(),
-            meta: None,
-            created_at: now,
-            updated_at: now,
-            agent_id: input.agent_id,
-            status: "Recording".to_string(),
+        paused: bool,
+        hosts: Option<Vec<String>>,
+        agent_id: Option<String>,
+    ) -> async_graphql::Result<i32> {
+        use crate::pb::{intercept_command, CaptureState, InterceptCommand};
+
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let registry = ctx.data::<Arc<crate::AgentRegistry>>()?;
+        let command = InterceptCommand {
+            command: Some(intercept_command::Command::Capture(CaptureState {
+                paused,
+                hosts: if paused { hosts.unwrap_or_default() } else { Vec::new() },
+            })),
+        };
+
+        let targets = match agent_id {
+            Some(id) => vec![(
+                id.clone(),
+                registry
+                    .get_agent_tx(&id)
+                    .ok_or_else(|| async_graphql::Error::new(format!("Agent {} is not online", id)))?,
+            )],
+            None => registry.list_agents().into_iter().map(|agent| (agent.id, agent.command_tx)).collect(),
         };
+
+        let mut sent = 0;
+        for (id, tx) in targets {
+            if tx.send(Ok(command.clone())).await.is_ok() {
+                sent += 1;
+            } else {
+                tracing::warn!("Failed to send capture state to agent {}", id);
+            }
+        }
+        Ok(sent)
+    }
+
+    async fn create_project(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<ProjectOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        db.create_project(&name).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(ProjectOperationResult { success: true, message: format!("Project '{}' created", name) })
+    }
+
+    async fn load_project(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<ProjectOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let scope_state = ctx.data::<Arc<tokio::sync::RwLock<ScopeConfig>>>()?;
+        let interception_state = ctx.data::<Arc<tokio::sync::RwLock<InterceptionConfig>>>()?;
         
-        db.save_flow_profile(&row)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        // Load project (connects to DB)
+        db.load_project(&name).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        // Launch browser with recording
-        // Proxy port 8080 is default - can be made configurable
-        if let Err(e) = recording_service.start_recording(
-            profile_id.clone(),
-            start_url,
-            Some(9095), // Proxxy agent default port
-        ).await {
-            // Update profile status to Failed
-            let _ = db.update_flow_profile(&profile_id, None, None, None, Some("Failed")).await;
-            return Err(async_graphql::Error::new(format!("Failed to start recording: {}", e)));
+        // Load settings from DB and update in-memory state
+        let scope_config = db.get_scope_config().await
+            .map_err(|e| async_graphql::Error::new(format!("Failed to load scope config: {}", e)))?;
+        let interception_config = db.get_interception_config().await
+            .map_err(|e| async_graphql::Error::new(format!("Failed to load interception config: {}", e)))?;
+        
+        // Update in-memory state
+        *scope_state.write().await = scope_config;
+        ctx.data::<Arc<InterceptQueue>>()?.push_rules(&interception_config).await;
+        *interception_state.write().await = interception_config;
+        if let Some(dir) = db.passive_checks_dir().await {
+            ctx.data::<Arc<PassiveScanner>>()?
+                .load_dir(&dir)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        }
+        if let Some(dir) = db.hook_scripts_dir().await {
+            ctx.data::<Arc<HookScriptStore>>()?
+                .load_dir(&dir)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         }
         
-        Ok(flow_graphql::FlowOperationResult {
-            success: true,
-            message: format!("Recording started for '{}'", input.name),
-            profile_id: Some(profile_id),
+        Ok(ProjectOperationResult { 
+            success: true, 
+            message: format!("Project '{}' loaded with settings", name) 
         })
     }
 
-    /// Stop recording and save the flow
-    async fn stop_flow_recording(
+    async fn delete_project(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<ProjectOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        db.delete_project(&name).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(ProjectOperationResult { success: true, message: format!("Project '{}' deleted", name) })
+    }
+
+    /// Add a member to the active project or change its role.
+    /// Sharing starts with the caller adding themselves as Owner, so nobody gets locked out.
+    async fn set_project_member(
         &self,
         ctx: &Context<'_>,
-        profile_id: String,
-        input: flow_graphql::StopRecordingInput,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        principal: String,
+        role: ProjectRoleGql,
+    ) -> async_graphql::Result<Vec<ProjectMemberGql>> {
         let db = ctx.data::<Arc<Database>>()?;
-        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
-        
-        // Stop recording (closes browser) and harvest steps
-        match recording_service.stop_recording(input.save).await {
-            Ok(Some((recorded_profile_id, steps))) => {
-                if input.save {
-                    // Profile ID sanity check
-                    if recorded_profile_id != profile_id {
-                        tracing::warn!("profile_id mismatch in recording: {} vs {}", profile_id, recorded_profile_id);
-                    }
-
-                    // Serialize steps to JSON
-                    let steps_json = serde_json::to_string(&steps)
-                        .map_err(|e| async_graphql::Error::new(format!("Failed to serialize steps: {}", e)))?;
+        let role = ProjectRole::from(role);
+        let members = db.list_project_members().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
 
-                    // Update profile with steps and status Active
-                    db.update_flow_profile(&profile_id, None, Some(&steps_json), None, Some("Active"))
-                        .await
-                        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-                    
-                    return Ok(flow_graphql::FlowOperationResult {
-                        success: true,
-                        message: format!("Recording saved with {} steps", steps.len()),
-                        profile_id: Some(profile_id),
-                    });
-                }
-            },
-            Ok(None) => {
-                // Recording discarded or not saved
-            },
-            Err(e) => {
-                tracing::warn!("Error stopping recording: {}", e);
-                // Continue to discard/fail handling if needed, or return error
-                if input.save {
-                     return Err(async_graphql::Error::new(format!("Failed to stop recording: {}", e)));
-                }
-            }
+        if principal.trim().is_empty() {
+            return Err(async_graphql::Error::new("Principal cannot be empty"));
         }
-        
-        if !input.save {
-             // Delete the recording
-             db.delete_flow_profile(&profile_id)
-                 .await
-                 .map_err(|e| async_graphql::Error::new(e.to_string()))?;
-             
-             Ok(flow_graphql::FlowOperationResult {
-                 success: true,
-                 message: "Recording discarded".to_string(),
-                 profile_id: None,
-             })
-         } else {
-             // Fallback if save=true but no steps returned (shouldn't happen with updated service logic unless internal error)
-             Err(async_graphql::Error::new("Failed to save recording: No data returned"))
+        if members.is_empty() {
+            let caller = ctx.data_opt::<Principal>()
+                .ok_or_else(|| async_graphql::Error::new("Sharing a project requires an identified user"))?;
+            if caller.0 != principal || role != ProjectRole::Owner {
+                return Err(async_graphql::Error::new("The first member must be yourself as Owner"));
+            }
+        } else {
+            require_project_role(ctx, ProjectRole::Owner).await?;
+            let owners = members.iter().filter(|m| m.role == ProjectRole::Owner).count();
+            let demotes_last_owner = role != ProjectRole::Owner
+                && owners == 1
+                && members.iter().any(|m| m.principal == principal && m.role == ProjectRole::Owner);
+            if demotes_last_owner {
+                return Err(async_graphql::Error::new("A shared project needs at least one Owner"));
+            }
         }
+
+        db.set_project_member(&principal, role).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!("👥 Project member {} set to {}", principal, role.as_str());
+
+        let members = db.list_project_members().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(members.into_iter().map(ProjectMemberGql::from).collect())
     }
 
-    /// DEBUG: Launch browser with proxy for manual testing
-    async fn debug_launch_browser(
+    /// Remove a member from the active project. Removing the only member unshares it.
+    async fn remove_project_member(
         &self,
         ctx: &Context<'_>,
-        start_url: String,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
-        
-        if let Err(e) = recording_service.debug_launch_browser(
-            start_url,
-            Some(9095),
-        ).await {
-            return Err(async_graphql::Error::new(format!("Failed to launch browser: {}", e)));
+        principal: String,
+    ) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let members = db.list_project_members().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let owners = members.iter().filter(|m| m.role == ProjectRole::Owner).count();
+        let removes_last_owner = members.len() > 1
+            && owners == 1
+            && members.iter().any(|m| m.principal == principal && m.role == ProjectRole::Owner);
+        if removes_last_owner {
+            return Err(async_graphql::Error::new("A shared project needs at least one Owner"));
         }
-        
-        Ok(flow_graphql::FlowOperationResult {
-            success: true,
-            message: "Debug browser launched. Check terminal for proxy logs.".to_string(),
-            profile_id: None,
-        })
+
+        db.remove_project_member(&principal).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
     }
 
-    /// DEBUG: Close the debug browser
-    async fn debug_close_browser(
+    /// Replace the target allowlist. Changes are recorded in the engagement audit trail.
+    async fn update_target_allowlist(
         &self,
         ctx: &Context<'_>,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
-        
-        if let Err(e) = recording_service.debug_close_browser().await {
-            return Err(async_graphql::Error::new(format!("Failed to close browser: {}", e)));
+        enabled: bool,
+        hosts: Vec<String>,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<TargetAllowlistGql> {
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let hosts: Vec<String> = hosts
+            .into_iter()
+            .map(|h| h.trim().to_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+        if let Some(invalid) = hosts.iter().find(|h| glob::Pattern::new(h).is_err()) {
+            return Err(async_graphql::Error::new(format!("Invalid host pattern: {}", invalid)));
         }
-        
-        Ok(flow_graphql::FlowOperationResult {
-            success: true,
-            message: "Debug browser closed.".to_string(),
-            profile_id: None,
-        })
-    }
 
-    /// Open response content in the managed browser (new tab if already open)
-    async fn open_response_in_browser(
+        let current = db.get_target_allowlist().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let expected_version = parse_expected_version(expected_version)?.unwrap_or(current.version);
+        let allowlist = TargetAllowlist { enabled, hosts, version: current.version };
+        let saved = db.save_target_allowlist(&allowlist, expected_version).await
+            .map_err(settings_error)?;
+
+        let details = format!("enabled={} hosts=[{}]", saved.enabled, saved.hosts.join(", "));
+        db.record_engagement_event(
+            EngagementEvent::AllowlistUpdated,
+            None,
+            None,
+            ctx.data_opt::<Principal>().map(|p| p.0.as_str()),
+            Some(&details),
+        )
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!("🎯 Target allowlist updated: {}", details);
+
+        Ok(TargetAllowlistGql::from(saved))
+    }
+
+    /// Replace the engagement schedule. Outside its windows active tooling is blocked.
+    async fn update_engagement_schedule(
         &self,
         ctx: &Context<'_>,
-        content: String,
-        content_type: String,
-        base_url: Option<String>,
-    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
-        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
-        
-        if let Err(e) = recording_service.open_in_browser(
-            content,
-            content_type,
-            base_url,
-            Some(9095),
-        ).await {
-            return Err(async_graphql::Error::new(format!("Failed to open in browser: {}", e)));
+        input: EngagementScheduleInput,
+    ) -> async_graphql::Result<EngagementScheduleGql> {
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let expected_version = parse_expected_version(input.expected_version)?;
+        let mut schedule = input.to_schedule()?;
+        let expected_version = match expected_version {
+            Some(version) => version,
+            None => db.get_engagement_schedule().await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .version,
+        };
+        schedule.version = expected_version;
+        let saved = db.save_engagement_schedule(&schedule, expected_version).await
+            .map_err(settings_error)?;
+
+        let details = format!(
+            "enabled={} timezone={} dates={}..{} windows={}",
+            saved.enabled,
+            saved.timezone,
+            saved.start_date.map(|d| d.to_string()).unwrap_or_default(),
+            saved.end_date.map(|d| d.to_string()).unwrap_or_default(),
+            saved.windows.len()
+        );
+        db.record_engagement_event(
+            EngagementEvent::ScheduleUpdated,
+            None,
+            None,
+            ctx.data_opt::<Principal>().map(|p| p.0.as_str()),
+            Some(&details),
+        )
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!("🗓️ Engagement schedule updated: {}", details);
+
+        Ok(EngagementScheduleGql::from(saved))
+    }
+
+    /// Temporarily allow a host outside the allowlist. A reason is required for the audit trail.
+    async fn grant_allowlist_override(
+        &self,
+        ctx: &Context<'_>,
+        host_pattern: String,
+        reason: String,
+        duration_minutes: i32,
+    ) -> async_graphql::Result<AllowlistOverrideGql> {
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let host_pattern = host_pattern.trim().to_lowercase();
+        if host_pattern.is_empty() || glob::Pattern::new(&host_pattern).is_err() {
+            return Err(async_graphql::Error::new("A valid host pattern is required"));
         }
-        
-        Ok(flow_graphql::FlowOperationResult {
-            success: true,
-            message: "Response opened in browser.".to_string(),
-            profile_id: None,
-        })
+        if reason.trim().is_empty() {
+            return Err(async_graphql::Error::new("A reason is required for allowlist overrides"));
+        }
+        if !(1..=7 * 24 * 60).contains(&duration_minutes) {
+            return Err(async_graphql::Error::new("Override duration must be between 1 minute and 7 days"));
+        }
+
+        let principal = ctx.data_opt::<Principal>().map(|p| p.0.clone());
+        let now = chrono::Utc::now().timestamp();
+        let granted = AllowlistOverride {
+            id: Uuid::new_v4().to_string(),
+            host_pattern,
+            reason: reason.trim().to_string(),
+            granted_by: principal.clone(),
+            created_at: now,
+            expires_at: now + duration_minutes as i64 * 60,
+            revoked_at: None,
+        };
+        db.add_allowlist_override(&granted).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let details = format!("override {} for {} minutes: {}", granted.id, duration_minutes, granted.reason);
+        db.record_engagement_event(
+            EngagementEvent::OverrideGranted,
+            None,
+            Some(&granted.host_pattern),
+            principal.as_deref(),
+            Some(&details),
+        )
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::warn!("⚠️ Allowlist override granted for {}: {}", granted.host_pattern, granted.reason);
+
+        Ok(AllowlistOverrideGql::from(granted))
     }
 
-    /// Replay a recorded flow
-    async fn replay_flow(
+    /// Revoke an allowlist override before it expires
+    async fn revoke_allowlist_override(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Owner).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let revoked = db.revoke_allowlist_override(&id).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if revoked {
+            db.record_engagement_event(
+                EngagementEvent::OverrideRevoked,
+                None,
+                None,
+                ctx.data_opt::<Principal>().map(|p| p.0.as_str()),
+                Some(&format!("override {}", id)),
+            )
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        }
+
+        Ok(revoked)
+    }
+
+    async fn add_scope_rule(
         &self,
         ctx: &Context<'_>,
-        input: flow_graphql::ReplayFlowInput,
-    ) -> async_graphql::Result<flow_graphql::FlowReplayResult> {
-        use flow_engine::{FlowReplayer, ReplayOptions, FlowProfile as FEProfile, FlowStep as FEStep, FlowType as FEFlowType};
-        use flow_engine::flow::model::ProfileStatus as FEProfileStatus;
-        use uuid::Uuid as FEUuid;
-        
+        rule_type: String,
+        pattern: String,
+        is_regex: bool,
+    ) -> async_graphql::Result<ScopeRuleGql> {
         let db = ctx.data::<Arc<Database>>()?;
-        
-        // Get profile
-        let profile = db
-            .get_flow_profile(&input.profile_id)
-            .await
-            .map_err(|e| async_graphql::Error::new(e.to_string()))?
-            .ok_or_else(|| async_graphql::Error::new("Flow profile not found"))?;
-        
-        // Parse steps from JSON
-        tracing::debug!("Raw steps from DB: {}", profile.steps);
-        let steps: Vec<FEStep> = serde_json::from_str(&profile.steps)
-            .map_err(|e| {
-                tracing::error!("Failed to parse steps JSON: {}. JSON: {}", e, profile.steps);
-                async_graphql::Error::new(format!("Failed to parse steps: {}", e))
-            })?;
-        
-        tracing::info!("Parsed {} steps for replay", steps.len());
-        
-        // Create execution record
-        let execution_id = Uuid::new_v4().to_string();
-        db.start_flow_execution(&execution_id, &input.profile_id, &input.agent_id, steps.len() as i64)
+        let rule = db.add_scope_rule(&rule_type, &pattern, is_regex)
             .await
             .map_err(|e| async_graphql::Error::new(e.to_string()))?;
         
-        // Convert flow_type string to FlowType enum
-        let flow_type = match profile.flow_type.as_str() {
-            "Login" => FEFlowType::Login,
-            "Checkout" => FEFlowType::Checkout,
-            "FormSubmission" => FEFlowType::FormSubmission,
-            "Navigation" => FEFlowType::Navigation,
-            _ => FEFlowType::Custom(profile.flow_type.clone()),
-        };
-        
-        // Build FlowProfile for replayer
-        let mut fe_profile = FEProfile::new(&profile.name, &profile.start_url);
-        fe_profile.id = FEUuid::parse_str(&profile.id).unwrap_or_else(|_| FEUuid::new_v4());
-        fe_profile.flow_type = flow_type;
-        fe_profile.steps = steps;
-        fe_profile.status = FEProfileStatus::Active;
-        
-        // Clone for async move
-        let db_clone = Arc::clone(&*db);
-        let execution_id_clone = execution_id.clone();
-        let profile_id_clone = input.profile_id.clone();
-        let step_count = fe_profile.steps.len();
-        
-        // Spawn background task to execute replay
-        tokio::spawn(async move {
-            tracing::info!("🎬 Starting flow replay: {} (execution: {}) with {} steps", profile_id_clone, execution_id_clone, step_count);
-            
-            // Create replayer with headed browser for visibility
-            let options = ReplayOptions {
-                headed: true, // Show browser window
-                step_by_step: true,
-                step_delay_ms: 300,
-                ..Default::default()
-            };
-            
-            let mut replayer = FlowReplayer::with_options(options);
-            
-            // Set progress callback
-            let db_progress = Arc::clone(&db_clone);
-            let execution_id_progress = execution_id_clone.clone();
-            replayer.set_progress_callback(Arc::new(move |current, _total| {
-                let db = Arc::clone(&db_progress);
-                let eid = execution_id_progress.clone();
-                tokio::spawn(async move {
-                    if let Err(e) = db.update_flow_execution_progress(&eid, current as i64).await {
-                        tracing::error!("Failed to update execution progress: {:?}", e);
-                    }
-                });
-            }));
-            
-            // Execute the flow with a timeout (5 minutes)
-            let timeout_duration = std::time::Duration::from_secs(300);
-            let execution_future = replayer.execute(&fe_profile);
-            
-            match tokio::time::timeout(timeout_duration, execution_future).await {
-                Ok(Ok(result)) => {
-                    if result.success {
-                        tracing::info!(
-                            "✅ Flow replay completed: {}/{} steps in {}ms",
-                            result.steps_completed,
-                            result.total_steps,
-                            result.duration_ms
-                        );
-                    } else {
-                        tracing::warn!(
-                            "⚠️ Flow replay finished with errors: {}/{} steps completed. Error: {:?}",
-                            result.steps_completed,
-                            result.total_steps,
-                            result.error
-                        );
-                    }
-                    
-                    // Update execution record
-                    if let Err(e) = db_clone.complete_flow_execution(
-                        &execution_id_clone,
-                        result.success,
-                        result.error.as_deref(),
-                        result.steps_completed as i64,
-                        result.session_cookies.as_deref(),
-                        None, // extracted_data
-                    ).await {
-                        tracing::error!("Failed to update execution record: {:?}", e);
-                    }
-                }
-                Ok(Err(e)) => {
-                    tracing::error!("❌ Flow replay engine error: {:?}", e);
-                    
-                    // Mark execution as failed
-                    if let Err(e2) = db_clone.complete_flow_execution(
-                        &execution_id_clone,
-                        false,
-                        Some(&e.to_string()),
-                        0, // Unknown precisely here, but result was Err
-                        None,
-                        None,
-                    ).await {
-                        tracing::error!("Failed to update execution record: {:?}", e2);
-                    }
-                }
-                Err(_) => {
-                    tracing::error!("⏱️ Flow replay timed out after {}s", timeout_duration.as_secs());
-                    
-                    // Mark execution as failed due to timeout
-                    if let Err(e2) = db_clone.complete_flow_execution(
-                        &execution_id_clone,
-                        false,
-                        Some("Execution timed out"),
-                        0, // Timeout usually means it hung
-                        None,
-                        None,
-                    ).await {
-                        tracing::error!("Failed to update execution record: {:?}", e2);
-                    }
-                }
-            }
-        });
-        
-        Ok(flow_graphql::FlowReplayResult {
-            success: true,
-            execution_id: Some(execution_id),
-            error: None,
-            session_cookies: None,
-        })
+        Ok(ScopeRuleGql::from(rule))
     }
 
-    /// Get current recording session state
-    async fn get_recording_state(
+    async fn delete_scope_rule(
         &self,
-        _ctx: &Context<'_>,
-    ) -> async_graphql::Result<flow_graphql::RecordingSessionGql> {
-        // TODO: Get actual recording state from flow-engine
-        // For now, return idle state
-        Ok(flow_graphql::RecordingSessionGql::default())
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<bool> {
+        let db = ctx.data::<Arc<Database>>()?;
+        db.delete_scope_rule(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(true)
     }
-}
 
-// ============================================================================
-// SUBSCRIPTION ROOT
-// ============================================================================
-
-pub struct SubscriptionRoot;
-
-#[Subscription]
-impl SubscriptionRoot {
-    async fn events(
-        &self, 
+    async fn toggle_scope_rule(
+        &self,
         ctx: &Context<'_>,
-        agent_id: Option<String>,
-    ) -> impl Stream<Item = TrafficEventGql> {
-        let broadcast = ctx
-            .data::<tokio::sync::broadcast::Sender<(String, TrafficEvent)>>()
-            .expect("Broadcast missing")
-            .clone();
-        let rx = broadcast.subscribe();
+        id: String,
+        enabled: bool,
+    ) -> async_graphql::Result<bool> {
+        let db = ctx.data::<Arc<Database>>()?;
+        db.toggle_scope_rule(&id, enabled)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(true)
+    }
 
-        // OPTIMIZATION: Use filter_map directly without intermediate allocations
-        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
-            res.ok().and_then(|(aid, event)| {
-                // Filter by agent_id if specified
-                if let Some(ref filter_id) = agent_id {
-                    if aid != *filter_id {
-                        return None;
-                    }
-                }
-                let mut gql = TrafficEventGql::from(event);
-                gql.agent_id = Some(aid);
-                Some(gql)
-            })
+    async fn unload_project(&self, ctx: &Context<'_>) -> async_graphql::Result<ProjectOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let scope_state = ctx.data::<Arc<tokio::sync::RwLock<ScopeConfig>>>()?;
+        let interception_state = ctx.data::<Arc<tokio::sync::RwLock<InterceptionConfig>>>()?;
+        
+        // Unload project (disconnects DB)
+        db.unload_project().await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        // Reset settings to defaults
+        *scope_state.write().await = ScopeConfig::default();
+        *interception_state.write().await = InterceptionConfig::default();
+        ctx.data::<Arc<InterceptQueue>>()?.push_rules(&InterceptionConfig::default()).await;
+        ctx.data::<Arc<PassiveScanner>>()?.clear().await;
+        ctx.data::<Arc<HookScriptStore>>()?.clear().await;
+        
+        Ok(ProjectOperationResult { 
+            success: true, 
+            message: "Project unloaded and settings reset".to_string() 
         })
     }
 
-    async fn system_metrics_updates(
+    /// Replay a captured HTTP request
+    async fn replay_request(
         &self,
         ctx: &Context<'_>,
-        agent_id: Option<String>,
-    ) -> impl Stream<Item = SystemMetricsGql> {
-        let broadcast = ctx
-            .data::<tokio::sync::broadcast::Sender<SystemMetricsEvent>>()
-            .expect("Metrics broadcast missing")
-            .clone();
-        let rx = broadcast.subscribe();
+        request_id: String,
+    ) -> async_graphql::Result<ReplayResult> {
+        use crate::pb::{intercept_command, ExecuteRequest, InterceptCommand};
 
-        // OPTIMIZATION: Move agent_id into closure to avoid repeated clones
-        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
-            res.ok().and_then(|e| {
-                // Filter by agent_id if specified
-                if let Some(ref filter_id) = agent_id {
-                    if e.agent_id != *filter_id {
-                        return None;
-                    }
-                }
-                Some(SystemMetricsGql::from(e))
-            })
+        let db = ctx.data::<Arc<Database>>()?;
+        let registry = ctx.data::<Arc<crate::AgentRegistry>>()?;
+
+        // 1. Get request from database
+        let request_data = db
+            .get_request_by_id(&request_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(format!("Database error: {}", e)))?
+            .ok_or_else(|| async_graphql::Error::new("Request not found"))?;
+
+        // 2. Get agent ID for this request
+        let agent_id = db
+            .get_agent_id_for_request(&request_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(format!("Database error: {}", e)))?
+            .ok_or_else(|| async_graphql::Error::new("Agent not found for request"))?;
+
+        // 3. Get agent command channel
+        let agent_tx = registry.get_agent_tx(&agent_id).ok_or_else(|| {
+            async_graphql::Error::new(format!("Agent {} is not online", agent_id))
+        })?;
+
+        // 4. Generate new request ID for replay
+        let replay_request_id = format!("{}-replay-{}", request_id, chrono::Utc::now().timestamp());
+
+        // 5. Send execute command to agent
+        let execute_cmd = InterceptCommand {
+            command: Some(intercept_command::Command::Execute(ExecuteRequest {
+                request_id: replay_request_id.clone(),
+                request: Some(request_data.1.clone()),
+            })),
+        };
+
+        agent_tx.send(Ok(execute_cmd)).await.map_err(|e| {
+            async_graphql::Error::new(format!("Failed to send command to agent: {}", e))
+        })?;
+
+        Ok(ReplayResult {
+            success: true,
+            message: format!("Replay request sent to agent {}", agent_id),
+            replay_request_id: Some(replay_request_id),
+            original_url: request_data.1.url,
+            original_method: request_data.1.method,
         })
     }
 
-    /// Subscribe to repeater execution updates
-    async fn repeater_executions(
+    /// Update scope configuration
+    async fn update_scope(
         &self,
         ctx: &Context<'_>,
-        tab_id: Option<String>,
-    ) -> impl Stream<Item = RepeaterExecutionGql> {
-        let broadcast = ctx
-            .data::<tokio::sync::broadcast::Sender<RepeaterExecutionGql>>()
-            .map(|tx| tx.clone())
-            .unwrap_or_else(|_| {
-                // Create a dummy broadcast channel if not available
-                let (tx, _) = tokio::sync::broadcast::channel(100);
-                tx
-            });
-        let rx = broadcast.subscribe();
+        input: ScopeInputGql,
+    ) -> async_graphql::Result<ScopeConfigGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        
+        let expected = match parse_expected_version(input.expected_version)? {
+            Some(version) => version,
+            None => db.get_scope_config().await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .version,
+        };
+        let config = input.to_scope_config();
+        let saved = db.save_scope_config(&config, expected).await
+            .map_err(settings_error)?;
+        
+        Ok(ScopeConfigGql::from(saved))
+    }
 
-        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
-            match res {
-                Ok(execution) => {
-                    // Filter by tab_id if specified
-                    if let Some(ref filter_id) = tab_id {
-                        if execution.tab_id != *filter_id {
-                            return None;
-                        }
-                    }
-                    Some(execution)
-                }
-                Err(_) => None,
+    /// Replace the traffic coloring rules. They apply to responses stored from
+    /// now on; a color set by hand is never overridden.
+    async fn update_coloring_rules(
+        &self,
+        ctx: &Context<'_>,
+        rules: Vec<ColoringRuleInputGql>,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<ColoringConfigGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let expected = match parse_expected_version(expected_version)? {
+            Some(version) => version,
+            None => db.get_coloring_config().await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .version,
+        };
+        let config = ColoringConfig {
+            rules: rules
+                .into_iter()
+                .map(ColoringRuleInputGql::to_coloring_rule)
+                .collect::<async_graphql::Result<_>>()?,
+            version: expected,
+        };
+        config.validate().map_err(async_graphql::Error::new)?;
+        let saved = db.save_coloring_config(&config, expected).await
+            .map_err(settings_error)?;
+
+        Ok(ColoringConfigGql::from(saved))
+    }
+
+    /// Replace the session handling macros
+    async fn update_session_macros(
+        &self,
+        ctx: &Context<'_>,
+        macros: Vec<SessionMacroInputGql>,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<SessionMacroConfigGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let expected = match parse_expected_version(expected_version)? {
+            Some(version) => version,
+            None => db.get_session_macros().await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .version,
+        };
+        let config = SessionMacroConfig {
+            macros: macros
+                .into_iter()
+                .map(SessionMacroInputGql::to_session_macro)
+                .collect::<async_graphql::Result<_>>()?,
+            version: expected,
+        };
+        config.validate().map_err(async_graphql::Error::new)?;
+        let saved = db.save_session_macros(&config, expected).await
+            .map_err(settings_error)?;
+
+        Ok(SessionMacroConfigGql::from(saved))
+    }
+
+    /// Set the default Repeater/Intruder agent (none clears it), the agents
+    /// tried in order while it is offline, and whether any online agent may
+    /// stand in after those
+    async fn update_default_agent(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: Option<String>,
+        fallback_agents: Option<Vec<String>>,
+        fallback_to_any: Option<bool>,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<DefaultAgentConfigGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let expected = match parse_expected_version(expected_version)? {
+            Some(version) => version,
+            None => db.get_default_agent_config().await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .version,
+        };
+        let config = DefaultAgentConfig {
+            agent_id,
+            fallback_agents: fallback_agents.unwrap_or_default(),
+            fallback_to_any: fallback_to_any.unwrap_or(false),
+            version: expected,
+        };
+        config.validate().map_err(async_graphql::Error::new)?;
+        let saved = db.save_default_agent_config(&config, expected).await
+            .map_err(settings_error)?;
+
+        Ok(DefaultAgentConfigGql::new(saved, ctx.data::<Arc<crate::AgentRegistry>>()?))
+    }
+
+    /// Update the DNS resolver configuration.
+    /// Agents pick up the change the next time they register.
+    async fn update_dns_config(
+        &self,
+        ctx: &Context<'_>,
+        input: DnsConfigInputGql,
+    ) -> async_graphql::Result<DnsConfigGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let config = input.to_dns_config();
+        config.validate()
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        db.save_dns_config(&config).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(DnsConfigGql::from(config))
+    }
+
+    /// Replace the host overrides, e.g. to point a production domain at a
+    /// staging address. Agents pick up the change the next time they register.
+    async fn update_host_overrides(
+        &self,
+        ctx: &Context<'_>,
+        overrides: Vec<HostOverrideInputGql>,
+    ) -> async_graphql::Result<Vec<HostOverrideGql>> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let mut parsed = std::collections::HashMap::new();
+        for input in overrides {
+            let (host, ip) = input.parse().map_err(async_graphql::Error::new)?;
+            if parsed.insert(host.clone(), ip).is_some() {
+                return Err(async_graphql::Error::new(format!("Host {} is overridden twice", host)));
             }
+        }
+        db.save_host_overrides(&parsed).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(HostOverrideGql::list(parsed))
+    }
+
+    /// Add the client certificate for a host pattern, replacing the one it
+    /// had. Agents pick it up the next time they register.
+    async fn upload_client_certificate(
+        &self,
+        ctx: &Context<'_>,
+        input: ClientCertificateInputGql,
+    ) -> async_graphql::Result<ClientCertificateGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let config = input.into_config().map_err(async_graphql::Error::new)?;
+        proxy_core::ClientCertificate::load(&config)
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let mut certificates = db.get_client_certificates().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        match certificates.iter_mut().find(|cert| cert.host_pattern == config.host_pattern) {
+            Some(existing) => *existing = config.clone(),
+            None => certificates.push(config.clone()),
+        }
+        db.save_client_certificates(&certificates).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(ClientCertificateGql::from(&config))
+    }
+
+    /// Remove the client certificate for a host pattern
+    async fn remove_client_certificate(&self, ctx: &Context<'_>, host_pattern: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let mut certificates = db.get_client_certificates().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let count = certificates.len();
+        certificates.retain(|cert| cert.host_pattern != host_pattern);
+        if certificates.len() == count {
+            return Ok(false);
+        }
+        db.save_client_certificates(&certificates).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(true)
+    }
+
+    /// Replace the TLS policies, tried in the given order. Agents pick them
+    /// up the next time they register.
+    async fn update_tls_policies(
+        &self,
+        ctx: &Context<'_>,
+        policies: Vec<TlsPolicyInputGql>,
+    ) -> async_graphql::Result<Vec<TlsPolicyGql>> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let policies = policies
+            .into_iter()
+            .map(TlsPolicyInputGql::into_config)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(async_graphql::Error::new)?;
+        for policy in &policies {
+            proxy_core::TlsPolicy::load(policy)
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        }
+        db.save_tls_policies(&policies).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(policies.iter().map(TlsPolicyGql::from).collect())
+    }
+
+    /// Update masking policy for recorded flow inputs. Changing whether
+    /// masked values may be revealed takes an Owner.
+    async fn update_flow_masking_policy(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::MaskingPolicyInput,
+    ) -> async_graphql::Result<flow_graphql::MaskingPolicyGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let policy = input.to_policy();
+        let current = db.get_masking_policy().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if policy.allow_reveal != current.allow_reveal {
+            require_project_role(ctx, ProjectRole::Owner).await?;
+        }
+        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
+        db.save_masking_policy(&policy).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        recording_service.set_masking_policy(policy.clone()).await;
+
+        Ok(flow_graphql::MaskingPolicyGql::from(policy))
+    }
+
+    /// Toggle interception on/off
+    async fn toggle_interception(
+        &self,
+        ctx: &Context<'_>,
+        enabled: bool,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<InterceptionConfigGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        
+        let (config, _) = edit_interception_config(ctx, parse_expected_version(expected_version)?, |config| {
+            config.enabled = enabled;
         })
+        .await?;
+        
+        Ok(InterceptionConfigGql::from(config))
     }
 
-    /// Subscribe to intruder attack progress updates
-    async fn intruder_attack_progress(
+    /// Add interception rule
+    async fn add_interception_rule(
         &self,
         ctx: &Context<'_>,
-        attack_id: Option<String>,
-    ) -> impl Stream<Item = IntruderAttackProgressGql> {
-        let broadcast = ctx
-            .data::<tokio::sync::broadcast::Sender<IntruderAttackProgressGql>>()
-            .map(|tx| tx.clone())
-            .unwrap_or_else(|_| {
-                // Create a dummy broadcast channel if not available
-                let (tx, _) = tokio::sync::broadcast::channel(100);
-                tx
-            });
-        let rx = broadcast.subscribe();
+        rule: InterceptionRuleInputGql,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<InterceptionRuleGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        
+        let new_rule = rule.to_interception_rule();
+        edit_interception_config(ctx, parse_expected_version(expected_version)?, |config| {
+            config.rules.push(new_rule.clone());
+        })
+        .await?;
+        
+        Ok(InterceptionRuleGql::from(new_rule))
+    }
 
-        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
-            match res {
-                Ok(progress) => {
-                    // Filter by attack_id if specified
-                    if let Some(ref filter_id) = attack_id {
-                        if progress.attack_id != *filter_id {
-                            return None;
-                        }
-                    }
-                    Some(progress)
-                }
-                Err(_) => None,
-            }
+    /// Remove interception rule by ID
+    async fn remove_interception_rule(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        expected_version: Option<i64>,
+    ) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        
+        let (_, removed) = edit_interception_config(ctx, parse_expected_version(expected_version)?, |config| {
+            let before_len = config.rules.len();
+            config.rules.retain(|r| r.id != id);
+            config.rules.len() < before_len
         })
+        .await?;
+        
+        Ok(removed)
     }
 
-    /// Subscribe to intruder attack results
-    async fn intruder_attack_results(
+    /// Export project to .proxxy file, optionally limited to traffic
+    /// captured between `since` and `until` (Unix seconds) or in scope
+    async fn export_project(
         &self,
         ctx: &Context<'_>,
-        attack_id: String,
-    ) -> impl Stream<Item = IntruderResultGql> {
-        let broadcast = ctx
-            .data::<tokio::sync::broadcast::Sender<IntruderResultGql>>()
-            .map(|tx| tx.clone())
-            .unwrap_or_else(|_| {
-                // Create a dummy broadcast channel if not available
-                let (tx, _) = tokio::sync::broadcast::channel(1000);
-                tx
-            });
-        let rx = broadcast.subscribe();
+        name: String,
+        output_path: String,
+        since: Option<i64>,
+        until: Option<i64>,
+        scope_only: Option<bool>,
+    ) -> async_graphql::Result<ProjectOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
 
-        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
-            match res {
-                Ok(result) => {
-                    // Filter by attack_id
-                    if result.attack_id == attack_id {
-                        Some(result)
-                    } else {
-                        None
-                    }
-                }
-                Err(_) => None,
-            }
+        let options = crate::database::ExportOptions {
+            since,
+            until,
+            scope_only: scope_only.unwrap_or(false),
+        };
+        let manifest = db.export_project(&name, &output_path, &options).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(ProjectOperationResult {
+            success: true,
+            message: format!(
+                "Project '{}' exported to {} ({} transactions, {} annotated, {} of {} body chunks stored after deduplication)",
+                name,
+                output_path,
+                manifest.transactions,
+                manifest.annotated_transactions,
+                manifest.body_chunks,
+                manifest.body_chunk_refs
+            ),
         })
     }
 
-    /// Subscribe to session events
-    async fn session_events(
+    /// Import project from .proxxy file
+    async fn import_project(
         &self,
         ctx: &Context<'_>,
-        session_id: Option<String>,
-    ) -> impl Stream<Item = SessionEventGql> {
-        let session_manager = ctx.data::<Arc<SessionManager>>()
-            .expect("SessionManager not found in context");
-        let rx = session_manager.subscribe_to_events();
+        proxxy_path: String,
+        project_name: Option<String>,
+    ) -> async_graphql::Result<ProjectOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        
+        let imported_name = db.import_project(&proxxy_path, project_name.as_deref()).await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(ProjectOperationResult {
+            success: true,
+            message: format!("Project '{}' imported from {}", imported_name, proxxy_path),
+        })
+    }
 
-        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
-            match res {
-                Ok(event) => {
-                    // Filter by session_id if specified
-                    if let Some(ref filter_id) = session_id {
-                        let event_session_id = match &event {
-                            SessionEvent::Created { session_id } => Some(*session_id),
-                            SessionEvent::Validated { session_id, .. } => Some(*session_id),
-                            SessionEvent::ValidationFailed { session_id, .. } => Some(*session_id),
-                            SessionEvent::Expired { session_id } => Some(*session_id),
-                            SessionEvent::Used { session_id, .. } => Some(*session_id),
-                        };
-                        
-                        if let Some(event_id) = event_session_id {
-                            if event_id.to_string() != *filter_id {
-                                return None;
-                            }
-                        }
-                    }
-                    Some(SessionEventGql::from(event))
-                }
-                Err(_) => None,
-            }
+    /// Add the items of a Burp Suite "Save items" XML export to the active
+    /// project, keeping their URLs, times and comments
+    async fn import_burp_xml(&self, ctx: &Context<'_>, xml: String) -> async_graphql::Result<BurpImportResultGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let summary = ctx
+            .data::<Arc<Database>>()?
+            .import_burp_xml(&xml)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(BurpImportResultGql {
+            imported: summary.imported as i64,
+            skipped: summary.skipped as i64,
         })
     }
+
+    /// Sync the loaded project with another orchestrator: pull its changes,
+    /// then push local ones, over its mutual-TLS gRPC channel.
+    async fn sync_project(&self, ctx: &Context<'_>, peer: SyncPeerInput) -> async_graphql::Result<SyncReportGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let peer = crate::sync::SyncPeer {
+            address: peer.address,
+            token: peer.token,
+            ca_cert_pem: peer.ca_cert_pem,
+            client_cert_pem: peer.client_cert_pem,
+            client_key_pem: peer.client_key_pem,
+        };
+        let report = crate::sync::sync_with_peer(db, &peer)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(SyncReportGql {
+            peer_node_id: report.peer_node_id,
+            pulled: report.pulled,
+            pushed: report.pushed,
+        })
+    }
+
+    /// Create an API key acting as `principal` for project access checks.
+    /// The key is only returned here; store it right away.
+    async fn create_api_key(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        principal: String,
+    ) -> async_graphql::Result<CreatedApiKeyGql> {
+        let auth = require_admin(ctx)?;
+        if principal.trim().is_empty() {
+            return Err(async_graphql::Error::new("Principal cannot be empty"));
+        }
+
+        let (key, info) = auth
+            .create_api_key(name.trim(), principal.trim())
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!("🔑 Created API key '{}' for {}", info.name, info.principal);
+        Ok(CreatedApiKeyGql {
+            key,
+            api_key: info.into(),
+        })
+    }
+
+    /// Revoke an API key; requests using it fail from now on
+    async fn revoke_api_key(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        let auth = require_admin(ctx)?;
+        let revoked = auth
+            .revoke_api_key(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if revoked {
+            tracing::info!("🔑 Revoked API key {}", id);
+        }
+        Ok(revoked)
+    }
+
+    /// Enroll an agent: issue a client certificate for the mutual-TLS gRPC
+    /// channel. The private key is returned only once.
+    async fn enroll_agent(&self, ctx: &Context<'_>, name: String) -> async_graphql::Result<AgentEnrollmentGql> {
+        require_admin(ctx)?;
+        let agent_tls = ctx.data::<Arc<crate::agent_tls::AgentTlsService>>()?;
+        let issued = agent_tls
+            .enroll_agent(&name)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!("🔐 Enrolled agent '{}' ({})", issued.name, issued.fingerprint);
+        Ok(AgentEnrollmentGql {
+            name: issued.name,
+            fingerprint: issued.fingerprint,
+            cert_pem: issued.cert_pem,
+            key_pem: issued.key_pem,
+            ca_cert_pem: issued.ca_cert_pem,
+        })
+    }
+
+    /// Replace the MITM CA and send the new one to every online agent.
+    /// Clients must trust the new CA from then on. The previous key is kept
+    /// in the CA archive for `grace_period_hours` (72 if omitted), then
+    /// deleted.
+    async fn rotate_ca(
+        &self,
+        ctx: &Context<'_>,
+        grace_period_hours: Option<u32>,
+    ) -> async_graphql::Result<CaRotationGql> {
+        require_admin(ctx)?;
+        let rotated_by = match ctx.data_opt::<Caller>() {
+            Some(Caller::ApiKey { principal, .. }) => principal.clone(),
+            Some(Caller::Admin) | None => "admin".to_string(),
+        };
+        let rotation = ctx
+            .data::<Arc<crate::ca_rotation::CaRotationService>>()?
+            .rotate(
+                grace_period_hours.unwrap_or(crate::ca_rotation::DEFAULT_GRACE_PERIOD_HOURS),
+                &rotated_by,
+                ctx.data::<Arc<crate::AgentRegistry>>()?,
+            )
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(CaRotationGql::from(rotation))
+    }
+
+    /// Unlock the encrypted MITM CA key and send the CA to the online agents.
+    /// Returns the number of agents it was sent to.
+    async fn unlock_ca(&self, ctx: &Context<'_>, passphrase: String) -> async_graphql::Result<i32> {
+        require_admin(ctx)?;
+        let notified = ctx
+            .data::<Arc<crate::ca_rotation::CaRotationService>>()?
+            .unlock(&passphrase, ctx.data::<Arc<crate::AgentRegistry>>()?)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(notified as i32)
+    }
+
+    /// Change the default log level and per-module levels without a restart.
+    /// A module with a null level goes back to the default level.
+    async fn set_log_levels(
+        &self,
+        ctx: &Context<'_>,
+        level: Option<String>,
+        modules: Option<Vec<ModuleLogLevelInput>>,
+    ) -> async_graphql::Result<LogLevelsGql> {
+        require_admin(ctx)?;
+        let modules: Vec<(String, Option<String>)> = modules
+            .unwrap_or_default()
+            .into_iter()
+            .map(|m| (m.module, m.level))
+            .collect();
+        let levels = crate::logging::set_log_levels(level.as_deref(), &modules)
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!(directives = %levels.directives(), "Log levels changed");
+        Ok(levels.into())
+    }
+
+    /// Revoke an agent certificate; the agent is refused from its next call
+    async fn revoke_agent_certificate(&self, ctx: &Context<'_>, fingerprint: String) -> async_graphql::Result<bool> {
+        require_admin(ctx)?;
+        let agent_tls = ctx.data::<Arc<crate::agent_tls::AgentTlsService>>()?;
+        let revoked = agent_tls
+            .revoke_agent_certificate(&fingerprint)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        if revoked {
+            tracing::info!("🔐 Revoked agent certificate {}", fingerprint);
+        }
+        Ok(revoked)
+    }
+
+    /// Upload a protobuf descriptor set (`protoc --include_imports
+    /// --descriptor_set_out`, base64) for decoding captured gRPC calls
+    async fn upload_grpc_descriptor_set(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        descriptor_set: String,
+    ) -> async_graphql::Result<GrpcDescriptorSetGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(descriptor_set.trim())
+            .map_err(|e| async_graphql::Error::new(format!("Descriptor set is not valid base64: {}", e)))?;
+        let services = crate::grpc_decode::GrpcDecoder::validate_descriptor_set(&bytes)
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let id = db
+            .save_grpc_descriptor_set(&name, &bytes)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        tracing::info!("📦 Uploaded gRPC descriptor set '{}' ({} services)", name, services.len());
+        Ok(GrpcDescriptorSetGql {
+            id,
+            name,
+            services,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Delete an uploaded descriptor set
+    async fn delete_grpc_descriptor_set(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        db.delete_grpc_descriptor_set(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
+
+    /// Create a new repeater tab
+    async fn create_repeater_tab(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateRepeaterTabInput,
+    ) -> async_graphql::Result<RepeaterTabGql> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        
+        let request = CreateRepeaterTabRequest {
+            name: input.name,
+            request_template: input.request_template.try_into()?,
+            target_agent_id: input.target_agent_id,
+        };
+        
+        let tab_id = repeater_manager
+            .create_tab(request)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        let tab = repeater_manager
+            .get_tab(&tab_id)
+            .await
+            .ok_or_else(|| async_graphql::Error::new("Failed to retrieve created tab"))?;
+        
+        Ok(RepeaterTabGql::from(tab))
+    }
+
+    /// Update a repeater tab
+    async fn update_repeater_tab(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        input: UpdateRepeaterTabInput,
+    ) -> async_graphql::Result<RepeaterTabGql> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        
+        repeater_manager
+            .update_tab(
+                &id,
+                input.name,
+                input.request_template.map(HttpRequestData::try_from).transpose()?,
+                input.target_agent_id.map(Some),
+            )
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        let tab = repeater_manager
+            .get_tab(&id)
+            .await
+            .ok_or_else(|| async_graphql::Error::new("Tab not found after update"))?;
+        
+        Ok(RepeaterTabGql::from(tab))
+    }
+
+    /// Delete a repeater tab
+    async fn delete_repeater_tab(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<bool> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        
+        repeater_manager
+            .delete_tab(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(true)
+    }
+
+    /// Brute-force paths under a base URL. Returns the run, which searches
+    /// in the background and stores what it finds.
+    async fn start_discovery(
+        &self,
+        ctx: &Context<'_>,
+        input: StartDiscoveryInput,
+    ) -> async_graphql::Result<DiscoveryRunGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let discovery = ctx.data::<Arc<DiscoveryManager>>()?;
+        let defaults = attack_engine::DiscoveryConfig::default();
+        let max_depth = match input.max_depth {
+            Some(depth) => u32::try_from(depth).map_err(|_| async_graphql::Error::new("max_depth must not be negative"))?,
+            None => defaults.max_depth,
+        };
+        let concurrency = match input.concurrency {
+            Some(concurrency) => usize::try_from(concurrency)
+                .map_err(|_| async_graphql::Error::new("concurrency must be positive"))?,
+            None => crate::discovery::DEFAULT_DISCOVERY_CONCURRENCY,
+        };
+        let config = DiscoveryRunConfig {
+            discovery: attack_engine::DiscoveryConfig {
+                base_url: input.base_url,
+                wordlist: input.wordlist,
+                extensions: input.extensions,
+                headers: match input.headers.as_deref() {
+                    Some(json) => serde_json::from_str::<std::collections::HashMap<String, String>>(json)
+                        .map_err(|e| async_graphql::Error::new(format!("Invalid headers JSON: {}", e)))?,
+                    None => Default::default(),
+                },
+                recursive: input.recursive,
+                max_depth,
+                similarity_threshold: input.similarity_threshold.unwrap_or(defaults.similarity_threshold),
+                ignored_status_codes: input.ignored_status_codes.unwrap_or(defaults.ignored_status_codes),
+            },
+            agents: input.agents,
+            concurrency,
+        };
+
+        let id = discovery.start(config).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        discovery
+            .get(&id)
+            .map(DiscoveryRunGql::from)
+            .ok_or_else(|| async_graphql::Error::new("Discovery run not found"))
+    }
+
+    /// Stop a discovery run; its findings so far are kept
+    async fn cancel_discovery(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        Ok(ctx.data::<Arc<DiscoveryManager>>()?.cancel(&id))
+    }
+
+    /// Delete a discovery run and its findings
+    async fn delete_discovery_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        ctx.data::<Arc<DiscoveryManager>>()?
+            .delete(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
+
+    /// Send a request repeatedly and collect a token from each response for
+    /// randomness analysis. Returns the run, which collects in the background.
+    async fn start_sequencer(
+        &self,
+        ctx: &Context<'_>,
+        input: StartSequencerInput,
+    ) -> async_graphql::Result<SequencerRunGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let sequencer = ctx.data::<Arc<Sequencer>>()?;
+        let agent_id = ctx
+            .data::<Arc<RepeaterManager>>()?
+            .resolve_agent(input.agent_id.as_deref(), "")
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let config = SequencerConfig {
+            request: input.request.try_into()?,
+            agent_id,
+            extraction: input.extraction,
+            capture_count: usize::try_from(input.capture_count)
+                .map_err(|_| async_graphql::Error::new("capture_count must be positive"))?,
+        };
+
+        let id = sequencer.start(config).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        sequencer
+            .get(&id)
+            .map(SequencerRunGql::from)
+            .ok_or_else(|| async_graphql::Error::new("Sequencer run not found"))
+    }
+
+    /// Stop collecting; the tokens collected so far are analysed
+    async fn cancel_sequencer(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        Ok(ctx.data::<Arc<Sequencer>>()?.cancel(&id))
+    }
+
+    async fn delete_sequencer_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        Ok(ctx.data::<Arc<Sequencer>>()?.delete(&id))
+    }
+
+    /// Replay captured requests under several sessions and compare the
+    /// responses. Returns the run, which replays in the background.
+    async fn start_access_matrix(
+        &self,
+        ctx: &Context<'_>,
+        input: StartAccessMatrixInput,
+    ) -> async_graphql::Result<AccessMatrixRunGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let matrix = ctx.data::<Arc<AccessMatrix>>()?;
+        let agent_id = ctx
+            .data::<Arc<RepeaterManager>>()?
+            .resolve_agent(input.agent_id.as_deref(), "")
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let sessions = input
+            .session_ids
+            .iter()
+            .map(|id| Uuid::parse_str(id).map_err(|_| async_graphql::Error::new(format!("Invalid session ID: {}", id))))
+            .collect::<async_graphql::Result<Vec<_>>>()?;
+        let config = AccessMatrixConfig {
+            request_ids: input.request_ids,
+            sessions,
+            include_anonymous: input.include_anonymous,
+            agent_id,
+            length_tolerance: input
+                .length_tolerance
+                .unwrap_or(crate::access_matrix::DEFAULT_LENGTH_TOLERANCE),
+        };
+
+        let id = matrix.start(config).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        matrix
+            .get(&id)
+            .map(AccessMatrixRunGql::from)
+            .ok_or_else(|| async_graphql::Error::new("Access matrix run not found"))
+    }
+
+    /// Stop replaying; the rows so far are kept
+    async fn cancel_access_matrix(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        Ok(ctx.data::<Arc<AccessMatrix>>()?.cancel(&id))
+    }
+
+    async fn delete_access_matrix_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        Ok(ctx.data::<Arc<AccessMatrix>>()?.delete(&id))
+    }
+
+    /// Replay captured requests with duplicated parameters, method override
+    /// headers and other verbs, and record what changed the response.
+    /// Returns the run, which probes in the background.
+    async fn start_tamper_checks(
+        &self,
+        ctx: &Context<'_>,
+        input: StartTamperChecksInput,
+    ) -> async_graphql::Result<TamperRunGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let tamper_checks = ctx.data::<Arc<TamperCheckManager>>()?;
+        let agent_id = ctx
+            .data::<Arc<RepeaterManager>>()?
+            .resolve_agent(input.agent_id.as_deref(), "")
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let target = match input.request_ids {
+            Some(request_ids) => TamperTarget::Requests(request_ids),
+            None => TamperTarget::SiteMap { host: input.host },
+        };
+        let config = TamperRunConfig {
+            target,
+            agent_id,
+            checks: attack_engine::TamperChecks {
+                parameter_pollution: input.parameter_pollution,
+                method_override: input.method_override,
+                verb_tampering: input.verb_tampering,
+            },
+        };
+
+        let id = tamper_checks.start(config).await.map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tamper_checks
+            .get(&id)
+            .map(TamperRunGql::from)
+            .ok_or_else(|| async_graphql::Error::new("Tamper check run not found"))
+    }
+
+    /// Stop probing; the findings so far are kept
+    async fn cancel_tamper_checks(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        Ok(ctx.data::<Arc<TamperCheckManager>>()?.cancel(&id))
+    }
+
+    async fn delete_tamper_run(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<bool> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        ctx.data::<Arc<TamperCheckManager>>()?
+            .delete(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
+
+    /// Execute a repeater request
+    async fn execute_repeater_request(
+        &self,
+        ctx: &Context<'_>,
+        input: ExecuteRepeaterRequestInput,
+    ) -> async_graphql::Result<RepeaterExecutionGql> {
+        let repeater_manager = ctx.data::<Arc<RepeaterManager>>()?;
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        
+        // Apply session if provided
+        let mut request_data: HttpRequestData = input.request_data.try_into()?;
+        let mut session_application_result = None;
+        
+        if let Some(session_id_str) = &input.session_id {
+            let session_id = Uuid::parse_str(session_id_str)
+                .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
+            
+            let expiration_handling = input.expiration_handling.unwrap_or_default().into();
+            
+            match session_manager.apply_session_to_request(request_data, &session_id, expiration_handling).await {
+                Ok((modified_request, app_result)) => {
+                    request_data = modified_request;
+                    session_application_result = Some(app_result);
+                }
+                Err(e) => {
+                    return Err(async_graphql::Error::new(format!("Failed to apply session: {}", e)));
+                }
+            }
+        }
+        
+        let target_agent_id = repeater_manager
+            .resolve_agent(input.target_agent_id.as_deref(), &input.tab_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let request = RepeaterExecutionRequest {
+            tab_id: input.tab_id,
+            request_data,
+            target_agent_id,
+            session_id: input.session_id,
+            options: input.options.unwrap_or_default().try_into()?,
+        };
+        
+        let execution = repeater_manager
+            .execute_request(request)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        // Broadcast the execution result for real-time updates
+        if let Ok(broadcast) = ctx.data::<tokio::sync::broadcast::Sender<RepeaterExecutionGql>>() {
+            let _ = broadcast.send(RepeaterExecutionGql::from(execution.clone()));
+        }
+        
+        Ok(RepeaterExecutionGql::from(execution))
+    }
+
+    /// Create a new intruder attack
+    async fn create_intruder_attack(
+        &self,
+        ctx: &Context<'_>,
+        input: CreateIntruderAttackInput,
+    ) -> async_graphql::Result<IntruderAttackGql> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        
+        // Convert session data if provided
+        let session_data = if let Some(session_input) = input.session_data {
+            Some(session_input.into())
+        } else {
+            None
+        };
+
+        let flow_session = match input.flow_session {
+            Some(flow_input) => Some(FlowSessionConfig {
+                profile_id: flow_input.profile_id,
+                requests_per_session: u32::try_from(flow_input.requests_per_session)
+                    .map_err(|_| async_graphql::Error::new("requests_per_session must be positive"))?,
+                variables: match flow_input.variables.as_deref() {
+                    Some(json) => serde_json::from_str(json)
+                        .map_err(|e| async_graphql::Error::new(format!("Invalid variables JSON: {}", e)))?,
+                    None => Default::default(),
+                },
+            }),
+            None => None,
+        };
+
+        let upload = match input.upload {
+            Some(upload_input) => Some(upload_template(ctx, upload_input).await?),
+            None => None,
+        };
+        
+        let config = IntruderAttackConfig {
+            name: input.name,
+            request_template: input.request_template,
+            attack_mode: input.attack_mode.into(),
+            payload_sets: input.payload_sets.into_iter().map(|ps| ps.into()).collect(),
+            target_agents: input.target_agents,
+            distribution_strategy: input.distribution_strategy.into(),
+            session_data,
+            execution_config: match (
+                input.rate_limit,
+                input.header_randomization,
+                input.baseline_requests,
+                input.pacing,
+                input.timing_confirmation,
+            ) {
+                (None, None, None, None, None) => None,
+                (rate_limit, header_randomization, baseline_requests, pacing, timing_confirmation) => Some(attack_engine::ExecutionConfig {
+                    rate_limit: rate_limit.map(attack_engine::RateLimitConfig::try_from).transpose()?.unwrap_or_default(),
+                    header_randomization: header_randomization.map(Into::into).unwrap_or_default(),
+                    baseline_requests: baseline_requests
+                        .map(u32::try_from)
+                        .transpose()
+                        .map_err(|_| async_graphql::Error::new("baseline_requests cannot be negative"))?
+                        .unwrap_or_default(),
+                    pacing: pacing.map(attack_engine::PacingProfile::try_from).transpose()?.unwrap_or_default(),
+                    timing_confirmation: timing_confirmation
+                        .map(attack_engine::TimingConfirmation::try_from)
+                        .transpose()?,
+                    ..Default::default()
+                }),
+            },
+            flow_session,
+            upload,
+            highlight_rules: input
+                .highlight_rules
+                .unwrap_or_default()
+                .into_iter()
+                .map(ResultHighlightRule::try_from)
+                .collect::<Result<_, _>>()?,
+            sequence: input.sequence.map(|steps| AttackSequence {
+                steps: steps.into_iter().map(SequenceStep::from).collect(),
+            }),
+        };
+        
+        let attack_id = intruder_manager
+            .create_attack(config)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        let attack = intruder_manager
+            .get_attack(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Failed to retrieve created attack"))?;
+        
+        Ok(IntruderAttackGql::from(attack))
+    }
+
+    /// Start an intruder attack
+    ///
+    /// With `simulate: true` nothing is sent: the attack's positions are
+    /// expanded and the outcome returned in `simulation`, with the first
+    /// `sampleSize` requests (10 by default). `requestsPerSecond` caps the
+    /// estimated throughput and `averageLatencyMs` (500 by default) sets how
+    /// fast each agent gets through its concurrent requests.
+    async fn start_intruder_attack(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: String,
+        simulate: Option<bool>,
+        sample_size: Option<i32>,
+        requests_per_second: Option<f64>,
+        average_latency_ms: Option<i32>,
+    ) -> async_graphql::Result<IntruderAttackGql> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+
+        if simulate.unwrap_or(false) {
+            let attack = intruder_manager
+                .get_attack(&attack_id)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
+            let defaults = SimulationOptions::default();
+            let options = SimulationOptions {
+                sample_size: sample_size.map(|n| n.clamp(0, 1000) as usize).unwrap_or(defaults.sample_size),
+                requests_per_second,
+                average_latency_ms: average_latency_ms.map(|ms| ms.max(1) as u64).unwrap_or(defaults.average_latency_ms),
+            };
+            let simulation = intruder_manager
+                .simulate_attack(&attack, &options)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+            let mut gql = IntruderAttackGql::from(attack);
+            gql.simulation = Some(AttackSimulationGql::from(simulation));
+            return Ok(gql);
+        }
+        
+        // Update attack status to running
+        intruder_manager
+            .update_attack_status(&attack_id, "running")
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        // TODO: Start actual attack execution
+        // This would involve creating an execution config and starting the attack
+        
+        let attack = intruder_manager
+            .get_attack(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
+        
+        Ok(IntruderAttackGql::from(attack))
+    }
+
+    /// Stop an intruder attack
+    async fn stop_intruder_attack(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: String,
+    ) -> async_graphql::Result<IntruderAttackGql> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        
+        // Update attack status to stopped
+        intruder_manager
+            .update_attack_status(&attack_id, "stopped")
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        // TODO: Stop actual attack execution
+        
+        let attack = intruder_manager
+            .get_attack(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
+        
+        Ok(IntruderAttackGql::from(attack))
+    }
+
+    /// Pause a running intruder attack. Requests in flight finish, and the
+    /// position of every agent is saved with the attack, so it can be resumed
+    /// even after the orchestrator restarts.
+    async fn pause_intruder_attack(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: String,
+    ) -> async_graphql::Result<IntruderAttackGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        intruder_manager
+            .pause_attack_execution(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let attack = intruder_manager
+            .get_attack(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
+        Ok(IntruderAttackGql::from(attack))
+    }
+
+    /// Resume a paused intruder attack where it stopped, on the connected
+    /// agents. Work of agents that are gone moves to the others.
+    async fn resume_intruder_attack(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: String,
+    ) -> async_graphql::Result<IntruderAttackGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        let agents = attack_agents(ctx.data::<Arc<crate::AgentRegistry>>()?);
+        intruder_manager
+            .resume_attack_execution(&attack_id, &agents)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let attack = intruder_manager
+            .get_attack(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Attack not found"))?;
+        Ok(IntruderAttackGql::from(attack))
+    }
+
+    /// Delete an intruder attack
+    async fn delete_intruder_attack(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: String,
+    ) -> async_graphql::Result<bool> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        
+        intruder_manager
+            .delete_attack(&attack_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(true)
+    }
+
+    /// Create a new payload set
+    async fn create_payload_set(
+        &self,
+        ctx: &Context<'_>,
+        input: CreatePayloadSetInput,
+    ) -> async_graphql::Result<PayloadSetGql> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        
+        let payload_config: PayloadConfig = input.configuration.into();
+        
+        let set_id = intruder_manager
+            .create_payload_set(&input.name, &payload_config)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        let set = intruder_manager
+            .get_payload_set(&set_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Failed to retrieve created payload set"))?;
+        
+        Ok(PayloadSetGql::from(set))
+    }
+
+    /// Delete a payload set
+    async fn delete_payload_set(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<bool> {
+        let intruder_manager = ctx.data::<Arc<IntruderManager>>()?;
+        
+        intruder_manager
+            .delete_payload_set(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(true)
+    }
+
+    /// Add or update a session
+    async fn add_session(
+        &self,
+        ctx: &Context<'_>,
+        input: SessionInput,
+    ) -> async_graphql::Result<SessionGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session: Session = input.into();
+        
+        session_manager
+            .add_session(session.clone())
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(SessionGql::from(session))
+    }
+
+    /// Remove a session
+    async fn remove_session(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<bool> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session_id = Uuid::parse_str(&id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
+        
+        session_manager
+            .remove_session(&session_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(true)
+    }
+
+    /// Apply session to a request template (for testing/validation)
+    async fn apply_session_to_request(
+        &self,
+        ctx: &Context<'_>,
+        input: ApplySessionToRequestInput,
+    ) -> async_graphql::Result<SessionApplicationResultGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session_id = Uuid::parse_str(&input.session_id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
+        
+        let request: HttpRequestData = input.request_template.try_into()?;
+        let expiration_handling = input.expiration_handling.unwrap_or_default().into();
+        
+        let (_, result) = session_manager
+            .apply_session_to_request(request, &session_id, expiration_handling)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(SessionApplicationResultGql::from(result))
+    }
+
+    /// Validate a session against a target URL
+    async fn validate_session(
+        &self,
+        ctx: &Context<'_>,
+        session_id: String,
+        validation_url: String,
+    ) -> async_graphql::Result<bool> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session_id = Uuid::parse_str(&session_id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
+        
+        let is_valid = session_manager
+            .validate_session(&session_id, &validation_url)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(is_valid)
+    }
+
+    /// Refresh a session manually
+    async fn refresh_session_manually(
+        &self,
+        ctx: &Context<'_>,
+        input: RefreshSessionManuallyInput,
+    ) -> async_graphql::Result<SessionRefreshResultGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session_id = Uuid::parse_str(&input.session_id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
+        
+        let new_session_data: Session = input.new_session_data.into();
+        
+        let result = session_manager
+            .refresh_session_manually(&session_id, new_session_data)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(SessionRefreshResultGql::from(result))
+    }
+
+    /// Refresh a session via LSR profile
+    async fn refresh_session_via_lsr(
+        &self,
+        ctx: &Context<'_>,
+        session_id: String,
+        profile_id: String,
+    ) -> async_graphql::Result<SessionRefreshResultGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let session_id = Uuid::parse_str(&session_id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid session ID: {}", e)))?;
+        let profile_id = Uuid::parse_str(&profile_id)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid profile ID: {}", e)))?;
+        
+        let result = session_manager
+            .refresh_session_via_lsr(&session_id, &profile_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(SessionRefreshResultGql::from(result))
+    }
+
+    /// Update authentication failure detection configuration
+    async fn update_auth_failure_config(
+        &self,
+        ctx: &Context<'_>,
+        input: AuthFailureDetectionConfigInput,
+    ) -> async_graphql::Result<AuthFailureDetectionConfigGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()?;
+        let config: AuthFailureDetectionConfig = input.into();
+        
+        session_manager.update_auth_failure_config(config.clone()).await;
+        
+        Ok(AuthFailureDetectionConfigGql::from(config))
+    }
+
+    // ========== Flow Profile Mutations ==========
+
+    /// Create a new flow profile
+    async fn create_flow_profile(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::CreateFlowProfileInput,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        
+        let profile_id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        
+        let row = crate::database::flow::FlowProfileRow {
+            id: profile_id.clone(),
+            name: input.name.clone(),
+            flow_type: String::from(input.flow_type),
+            start_url: input.start_url,
+            steps: "[]".to_string(),
+            meta: None,
+            created_at: now,
+            updated_at: now,
+            agent_id: input.agent_id,
+            status: "Active".to_string(),
+        };
+        
+        db.save_flow_profile(&row)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: true,
+            message: format!("Flow profile '{}' created", input.name),
+            profile_id: Some(profile_id),
+        })
+    }
+
+    /// Delete a flow profile
+    async fn delete_flow_profile(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        
+        let deleted = db
+            .delete_flow_profile(&id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: deleted,
+            message: if deleted {
+                "Flow profile deleted".to_string()
+            } else {
+                "Flow profile not found".to_string()
+            },
+            profile_id: Some(id),
+        })
+    }
+
+    /// Delete a persistent browser profile and its stored cookies/state
+    async fn delete_browser_profile(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+    ) -> async_graphql::Result<bool> {
+        let db = ctx.data::<Arc<Database>>()?;
+
+        let dir = db
+            .browser_profiles_dir()
+            .await
+            .ok_or_else(|| async_graphql::Error::new("No active project loaded"))?;
+        flow_engine::BrowserManager::with_profiles_dir(dir)
+            .delete_profile(&name)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))
+    }
+
+    /// Update a flow profile
+    async fn update_flow_profile(
+        &self,
+        ctx: &Context<'_>,
+        id: String,
+        input: flow_graphql::UpdateFlowProfileInput,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        
+        let status_str = input.status.map(String::from);
+
+        let meta_str = match input.browser_profile {
+            Some(browser_profile) => {
+                let existing = db
+                    .get_flow_profile(&id)
+                    .await
+                    .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                    .ok_or_else(|| async_graphql::Error::new("Flow profile not found"))?;
+                let mut meta: flow_engine::FlowMeta = existing
+                    .meta
+                    .as_deref()
+                    .and_then(|m| serde_json::from_str(m).ok())
+                    .unwrap_or_default();
+                meta.browser_profile = Some(browser_profile).filter(|p| !p.is_empty());
+                Some(serde_json::to_string(&meta)?)
+            }
+            None => None,
+        };
+        
+        let updated = db
+            .update_flow_profile(&id, input.name.as_deref(), None, meta_str.as_deref(), status_str.as_deref())
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: updated,
+            message: if updated {
+                "Flow profile updated".to_string()
+            } else {
+                "Flow profile not found".to_string()
+            },
+            profile_id: Some(id),
+        })
+    }
+
+    /// Insert a step into a saved flow profile
+    async fn insert_flow_step(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::InsertFlowStepInput,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let step: flow_engine::FlowStep = serde_json::from_str(&input.step)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid step: {}", e)))?;
+
+        edit_flow_steps(db, &input.profile_id, |profile| {
+            let index = match input.index {
+                Some(i) => step_index(i)?,
+                None => profile.step_count(),
+            };
+            let kind = step.kind();
+            profile.insert_step(index, step)?;
+            Ok(format!("Inserted {} step at {}", kind, index))
+        })
+        .await
+    }
+
+    /// Delete a step from a saved flow profile
+    async fn delete_flow_step(
+        &self,
+        ctx: &Context<'_>,
+        profile_id: String,
+        index: i32,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+
+        edit_flow_steps(db, &profile_id, |profile| {
+            let removed = profile.remove_step(step_index(index)?)?;
+            Ok(format!("Deleted {} step at {}", removed.kind(), index))
+        })
+        .await
+    }
+
+    /// Move a step of a saved flow profile to a new position
+    async fn move_flow_step(
+        &self,
+        ctx: &Context<'_>,
+        profile_id: String,
+        from: i32,
+        to: i32,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+
+        edit_flow_steps(db, &profile_id, |profile| {
+            profile.move_step(step_index(from)?, step_index(to)?)?;
+            Ok(format!("Moved step {} to {}", from, to))
+        })
+        .await
+    }
+
+    /// Edit a single step of a saved flow profile
+    async fn update_flow_step(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::UpdateFlowStepInput,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        use flow_engine::FlowEngineError;
+        use flow_engine::{FlowStep, SecretString};
+
+        let db = ctx.data::<Arc<Database>>()?;
+        let replacement: Option<FlowStep> = input
+            .step
+            .as_deref()
+            .map(serde_json::from_str)
+            .transpose()
+            .map_err(|e| async_graphql::Error::new(format!("Invalid step: {}", e)))?;
+
+        edit_flow_steps(db, &input.profile_id, move |profile| {
+            let step = profile.step_mut(step_index(input.index)?)?;
+            if let Some(replacement) = replacement {
+                *step = replacement;
+            }
+
+            if input.selector.is_some() || input.selector_type.is_some() {
+                let kind = step.kind();
+                let selector = step.selector_mut().ok_or_else(|| {
+                    FlowEngineError::InvalidStep(format!("{} step has no selector", kind))
+                })?;
+                if let Some(value) = input.selector {
+                    selector.value = value;
+                    // Fallbacks pointed at the old element
+                    selector.alternatives.clear();
+                    selector.validation_result = None;
+                }
+                if let Some(selector_type) = input.selector_type {
+                    selector.selector_type = selector_type.into();
+                }
+            }
+
+            match (step, input.value) {
+                (FlowStep::Select { value, .. }, Some(new_value)) => *value = new_value,
+                (step, Some(new_value)) => {
+                    step.set_typed_value(SecretString::new(new_value.into()), input.is_masked)?
+                }
+                (FlowStep::Type { is_masked, .. }, None) => {
+                    if let Some(masked) = input.is_masked {
+                        *is_masked = masked;
+                    }
+                }
+                _ => {}
+            }
+
+            Ok(format!("Updated step {}", input.index))
+        })
+        .await
+    }
+
+    /// Start recording a new browser flow
+    async fn start_flow_recording(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::StartRecordingInput,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
+        
+        // Create profile in "Recording" state
+        let profile_id = Uuid::new_v4().to_string();
+        let now = chrono::Utc::now().timestamp();
+        let start_url = input.start_url.clone();
+        
+        let row = crate::database::flow::FlowProfileRow {
+            id: profile_id.clone(),
+            name: input.name.clone(),
+            flow_type: String::from(input.flow_type),
+            start_url: start_url.clone(),
+            steps: "[]".to_string(),
+            meta: None,
+            created_at: now,
+            updated_at: now,
+            agent_id: input.agent_id,
+            status: "Recording".to_string(),
+        };
+        
+        db.save_flow_profile(&row)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        let masking = db.get_masking_policy().await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        recording_service.set_masking_policy(masking).await;
+
+        // Launch browser with recording
+        // Proxy port 8080 is default - can be made configurable
+        if let Err(e) = recording_service.start_recording(
+            profile_id.clone(),
+            start_url,
+            Some(9095), // Proxxy agent default port
+        ).await {
+            // Update profile status to Failed
+            let _ = db.update_flow_profile(&profile_id, None, None, None, Some("Failed")).await;
+            return Err(async_graphql::Error::new(format!("Failed to start recording: {}", e)));
+        }
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: true,
+            message: format!("Recording started for '{}'", input.name),
+            profile_id: Some(profile_id),
+        })
+    }
+
+    /// Stop recording and save the flow
+    async fn stop_flow_recording(
+        &self,
+        ctx: &Context<'_>,
+        profile_id: String,
+        input: flow_graphql::StopRecordingInput,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let db = ctx.data::<Arc<Database>>()?;
+        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
+        
+        // Stop recording (closes browser) and harvest steps
+        match recording_service.stop_recording(input.save).await {
+            Ok(Some((recorded_profile_id, steps))) => {
+                if input.save {
+                    // Profile ID sanity check
+                    if recorded_profile_id != profile_id {
+                        tracing::warn!("profile_id mismatch in recording: {} vs {}", profile_id, recorded_profile_id);
+                    }
+
+                    // Serialize steps to JSON
+                    let steps_json = serde_json::to_string(&steps)
+                        .map_err(|e| async_graphql::Error::new(format!("Failed to serialize steps: {}", e)))?;
+
+                    // Update profile with steps and status Active
+                    db.update_flow_profile(&profile_id, None, Some(&steps_json), None, Some("Active"))
+                        .await
+                        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+                    
+                    return Ok(flow_graphql::FlowOperationResult {
+                        success: true,
+                        message: format!("Recording saved with {} steps", steps.len()),
+                        profile_id: Some(profile_id),
+                    });
+                }
+            },
+            Ok(None) => {
+                // Recording discarded or not saved
+            },
+            Err(e) => {
+                tracing::warn!("Error stopping recording: {}", e);
+                // Continue to discard/fail handling if needed, or return error
+                if input.save {
+                     return Err(async_graphql::Error::new(format!("Failed to stop recording: {}", e)));
+                }
+            }
+        }
+        
+        if !input.save {
+             // Delete the recording
+             db.delete_flow_profile(&profile_id)
+                 .await
+                 .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+             
+             Ok(flow_graphql::FlowOperationResult {
+                 success: true,
+                 message: "Recording discarded".to_string(),
+                 profile_id: None,
+             })
+         } else {
+             // Fallback if save=true but no steps returned (shouldn't happen with updated service logic unless internal error)
+             Err(async_graphql::Error::new("Failed to save recording: No data returned"))
+        }
+    }
+
+    /// DEBUG: Launch browser with proxy for manual testing
+    async fn debug_launch_browser(
+        &self,
+        ctx: &Context<'_>,
+        start_url: String,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
+        
+        if let Err(e) = recording_service.debug_launch_browser(
+            start_url,
+            Some(9095),
+        ).await {
+            return Err(async_graphql::Error::new(format!("Failed to launch browser: {}", e)));
+        }
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: true,
+            message: "Debug browser launched. Check terminal for proxy logs.".to_string(),
+            profile_id: None,
+        })
+    }
+
+    /// DEBUG: Close the debug browser
+    async fn debug_close_browser(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
+        
+        if let Err(e) = recording_service.debug_close_browser().await {
+            return Err(async_graphql::Error::new(format!("Failed to close browser: {}", e)));
+        }
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: true,
+            message: "Debug browser closed.".to_string(),
+            profile_id: None,
+        })
+    }
+
+    /// Open response content in the managed browser (new tab if already open)
+    async fn open_response_in_browser(
+        &self,
+        ctx: &Context<'_>,
+        content: String,
+        content_type: String,
+        base_url: Option<String>,
+    ) -> async_graphql::Result<flow_graphql::FlowOperationResult> {
+        let recording_service = ctx.data::<Arc<crate::recording_service::RecordingService>>()?;
+        
+        if let Err(e) = recording_service.open_in_browser(
+            content,
+            content_type,
+            base_url,
+            Some(9095),
+        ).await {
+            return Err(async_graphql::Error::new(format!("Failed to open in browser: {}", e)));
+        }
+        
+        Ok(flow_graphql::FlowOperationResult {
+            success: true,
+            message: "Response opened in browser.".to_string(),
+            profile_id: None,
+        })
+    }
+
+    /// Render a stored HTML response to a PNG screenshot in a sandboxed
+    /// browser. Give exactly one of `request_id` or `intruder_result_id`.
+    /// The stored screenshot is returned unless `refresh` is set.
+    async fn render_response_screenshot(
+        &self,
+        ctx: &Context<'_>,
+        request_id: Option<String>,
+        intruder_result_id: Option<String>,
+        refresh: Option<bool>,
+    ) -> async_graphql::Result<ResponseScreenshotGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let render_service = ctx.data::<Arc<crate::render_service::RenderService>>()?;
+
+        let source = match (request_id, intruder_result_id) {
+            (Some(id), None) => crate::render_service::RenderSource::Transaction(id),
+            (None, Some(id)) => crate::render_service::RenderSource::IntruderResult(id),
+            _ => {
+                return Err(async_graphql::Error::new(
+                    "Give exactly one of requestId or intruderResultId",
+                ))
+            }
+        };
+
+        let info = render_service
+            .screenshot(&source, refresh.unwrap_or(false))
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        Ok(ResponseScreenshotGql {
+            download_url: format!("/api/blobs/{}", info.hash),
+            hash: info.hash,
+            size: info.size,
+            content_type: info.content_type,
+        })
+    }
+
+    /// Store the files uploaded in a transaction's multipart request body in
+    /// the project blob store
+    async fn extract_multipart_files(&self, ctx: &Context<'_>, request_id: String) -> async_graphql::Result<MultipartGql> {
+        require_project_role(ctx, ProjectRole::Editor).await?;
+        let db = ctx.data::<Arc<Database>>()?;
+        let blobs = ctx.data::<Arc<crate::blob_store::BlobStore>>()?;
+
+        let tx = db
+            .get_full_transaction_by_id(&request_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new(format!("Transaction {} not found", request_id)))?;
+        let content_type = tx
+            .request
+            .headers
+            .as_ref()
+            .and_then(|h| h.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("content-type")))
+            .map(|(_, v)| v.as_str())
+            .unwrap_or("");
+        let body = crate::multipart::parse(content_type, &tx.request.body)
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+        let stored = crate::multipart::store_files(blobs, &request_id, &body)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        tracing::info!("📎 Stored {} uploaded file(s) from {}", stored.len(), request_id);
+
+        let hashes: Vec<String> = stored.into_iter().map(|(_, info)| info.hash).collect();
+        Ok(MultipartGql::new(&body, &hashes))
+    }
+
+    /// Replay a recorded flow
+    async fn replay_flow(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::ReplayFlowInput,
+    ) -> async_graphql::Result<flow_graphql::FlowReplayResult> {
+        use flow_engine::{FlowReplayer, ReplayOptions};
+        
+        let db = ctx.data::<Arc<Database>>()?;
+        
+        // Get profile
+        let profile = db
+            .get_flow_profile(&input.profile_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Flow profile not found"))?;
+        
+        // Build FlowProfile for replayer
+        let fe_profile = flow_profile_for_replay(&profile)?;
+        authorize_target(ctx, ActiveTool::Replay, &fe_profile.start_url).await?;
+        
+        // Create execution record
+        let execution_id = Uuid::new_v4().to_string();
+        db.start_flow_execution(&execution_id, &input.profile_id, &input.agent_id, fe_profile.steps.len() as i64)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        
+        let browser_profile = input.browser_profile.clone();
+        let profiles_dir = db.browser_profiles_dir().await;
+        let screenshot_dir = db.replay_screenshots_dir().await;
+        let progress_tx = ctx
+            .data::<tokio::sync::broadcast::Sender<flow_graphql::FlowReplayProgressGql>>()
+            .ok()
+            .cloned();
+        
+        // Clone for async move
+        let db_clone = Arc::clone(&*db);
+        let execution_id_clone = execution_id.clone();
+        let profile_id_clone = input.profile_id.clone();
+        let step_count = fe_profile.steps.len();
+        
+        // Spawn background task to execute replay
+        tokio::spawn(async move {
+            tracing::info!("🎬 Starting flow replay: {} (execution: {}) with {} steps", profile_id_clone, execution_id_clone, step_count);
+            
+            // Create replayer with headed browser for visibility
+            let options = ReplayOptions {
+                headed: true, // Show browser window
+                step_by_step: true,
+                step_delay_ms: 300,
+                browser_profile,
+                screenshot_dir,
+                ..Default::default()
+            };
+            
+            let mut replayer = FlowReplayer::with_options(options);
+            if let Some(dir) = profiles_dir {
+                replayer = replayer.with_profiles_dir(dir);
+            }
+
+            // Stream per-step status to flowReplayProgress subscribers
+            if let Some(tx) = progress_tx {
+                let replay_id = execution_id_clone.clone();
+                replayer.set_event_callback(Arc::new(move |event| {
+                    let _ = tx.send(flow_graphql::FlowReplayProgressGql::from_event(&replay_id, event));
+                }));
+            }
+            
+            // Set progress callback
+            let db_progress = Arc::clone(&db_clone);
+            let execution_id_progress = execution_id_clone.clone();
+            replayer.set_progress_callback(Arc::new(move |current, _total| {
+                let db = Arc::clone(&db_progress);
+                let eid = execution_id_progress.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = db.update_flow_execution_progress(&eid, current as i64).await {
+                        tracing::error!("Failed to update execution progress: {:?}", e);
+                    }
+                });
+            }));
+            
+            // Execute the flow with a timeout (5 minutes)
+            let timeout_duration = std::time::Duration::from_secs(300);
+            let execution_future = replayer.execute(&fe_profile);
+            
+            match tokio::time::timeout(timeout_duration, execution_future).await {
+                Ok(Ok(result)) => {
+                    if result.success {
+                        tracing::info!(
+                            "✅ Flow replay completed: {}/{} steps in {}ms",
+                            result.steps_completed,
+                            result.total_steps,
+                            result.duration_ms
+                        );
+                    } else {
+                        tracing::warn!(
+                            "⚠️ Flow replay finished with errors: {}/{} steps completed. Error: {:?}",
+                            result.steps_completed,
+                            result.total_steps,
+                            result.error
+                        );
+                    }
+                    
+                    // Update execution record
+                    if let Err(e) = db_clone.complete_flow_execution(
+                        &execution_id_clone,
+                        result.success,
+                        result.error.as_deref(),
+                        result.steps_completed as i64,
+                        result.session_cookies.as_deref(),
+                        None, // extracted_data
+                    ).await {
+                        tracing::error!("Failed to update execution record: {:?}", e);
+                    }
+                }
+                Ok(Err(e)) => {
+                    tracing::error!("❌ Flow replay engine error: {:?}", e);
+                    
+                    // Mark execution as failed
+                    if let Err(e2) = db_clone.complete_flow_execution(
+                        &execution_id_clone,
+                        false,
+                        Some(&e.to_string()),
+                        0, // Unknown precisely here, but result was Err
+                        None,
+                        None,
+                    ).await {
+                        tracing::error!("Failed to update execution record: {:?}", e2);
+                    }
+                }
+                Err(_) => {
+                    tracing::error!("⏱️ Flow replay timed out after {}s", timeout_duration.as_secs());
+                    
+                    // Mark execution as failed due to timeout
+                    if let Err(e2) = db_clone.complete_flow_execution(
+                        &execution_id_clone,
+                        false,
+                        Some("Execution timed out"),
+                        0, // Timeout usually means it hung
+                        None,
+                        None,
+                    ).await {
+                        tracing::error!("Failed to update execution record: {:?}", e2);
+                    }
+                }
+            }
+        });
+        
+        Ok(flow_graphql::FlowReplayResult {
+            success: true,
+            execution_id: Some(execution_id),
+            error: None,
+            session_cookies: None,
+        })
+    }
+
+    /// Replay a recorded flow concurrently on several agents, each through its own proxy
+    async fn replay_flow_parallel(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::ParallelReplayFlowInput,
+    ) -> async_graphql::Result<flow_graphql::ParallelFlowReplayResult> {
+        use flow_engine::{ParallelReplayer, ProxyConfig as FEProxyConfig, ReplayOptions, ReplayTarget};
+
+        let db = ctx.data::<Arc<Database>>()?;
+        let registry = ctx.data::<Arc<crate::AgentRegistry>>()?;
+
+        if input.agent_ids.is_empty() {
+            return Err(async_graphql::Error::new("At least one agent is required"));
+        }
+
+        let profile = db
+            .get_flow_profile(&input.profile_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?
+            .ok_or_else(|| async_graphql::Error::new("Flow profile not found"))?;
+        let fe_profile = flow_profile_for_replay(&profile)?;
+        authorize_target(ctx, ActiveTool::Replay, &fe_profile.start_url).await?;
+
+        // Resolve every agent before starting anything
+        let targets = input
+            .agent_ids
+            .iter()
+            .map(|agent_id| {
+                registry
+                    .get_agent(agent_id)
+                    .map(|agent| ReplayTarget::new(agent_id, Some(FEProxyConfig::new(agent.address, agent.port))))
+                    .ok_or_else(|| async_graphql::Error::new(format!("Agent not found: {}", agent_id)))
+            })
+            .collect::<async_graphql::Result<Vec<_>>>()?;
+
+        let mut execution_ids = std::collections::HashMap::new();
+        for target in &targets {
+            let execution_id = Uuid::new_v4().to_string();
+            db.start_flow_execution(&execution_id, &input.profile_id, &target.agent_id, fe_profile.steps.len() as i64)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+            execution_ids.insert(target.agent_id.clone(), execution_id);
+        }
+
+        let variables: std::collections::HashMap<String, String> = match input.variables.as_deref() {
+            Some(json) => serde_json::from_str(json)
+                .map_err(|e| async_graphql::Error::new(format!("Invalid variables JSON: {}", e)))?,
+            None => Default::default(),
+        };
+        let options = ReplayOptions {
+            headed: input.headed.unwrap_or(false),
+            variables,
+            browser_profile: input.browser_profile.clone(),
+            ..Default::default()
+        };
+
+        let mut replayer = ParallelReplayer::new(options)
+            .with_max_concurrency(input.max_concurrency.map(|c| c.max(1) as usize).unwrap_or(4))
+            .with_timeout(std::time::Duration::from_secs(300));
+        if let Some(dir) = db.browser_profiles_dir().await {
+            replayer = replayer.with_profiles_dir(dir);
+        }
+
+        let db_clone = Arc::clone(&*db);
+        let progress_ids = execution_ids.clone();
+        replayer.set_progress_callback(Arc::new(move |agent_id: &str, current, _total| {
+            let Some(eid) = progress_ids.get(agent_id).cloned() else { return };
+            let db = Arc::clone(&db_clone);
+            tokio::spawn(async move {
+                if let Err(e) = db.update_flow_execution_progress(&eid, current as i64).await {
+                    tracing::error!("Failed to update execution progress: {:?}", e);
+                }
+            });
+        }));
+
+        let db_clone = Arc::clone(&*db);
+        let result_ids = execution_ids.clone();
+        tokio::spawn(async move {
+            let result = replayer.execute(&fe_profile, targets).await;
+            tracing::info!(
+                "🎬 Parallel replay of {} finished: {} succeeded, {} failed in {}ms",
+                fe_profile.name,
+                result.succeeded(),
+                result.failed(),
+                result.duration_ms
+            );
+
+            for agent_result in &result.results {
+                let Some(eid) = result_ids.get(&agent_result.agent_id) else { continue };
+                let r = &agent_result.result;
+                let extracted = (!r.extracted_data.is_empty())
+                    .then(|| serde_json::to_string(&r.extracted_data).ok())
+                    .flatten();
+                if let Err(e) = db_clone.complete_flow_execution(
+                    eid,
+                    r.success,
+                    r.error.as_deref(),
+                    r.steps_completed as i64,
+                    r.session_cookies.as_deref(),
+                    extracted.as_deref(),
+                ).await {
+                    tracing::error!("Failed to update execution record: {:?}", e);
+                }
+            }
+        });
+
+        Ok(flow_graphql::ParallelFlowReplayResult {
+            success: true,
+            executions: input
+                .agent_ids
+                .iter()
+                .filter_map(|agent_id| {
+                    execution_ids.get(agent_id).map(|eid| flow_graphql::AgentExecutionGql {
+                        agent_id: agent_id.clone(),
+                        execution_id: eid.clone(),
+                    })
+                })
+                .collect(),
+        })
+    }
+
+    /// Crawl rendered pages with a managed browser through an agent proxy
+    async fn start_dom_crawl(
+        &self,
+        ctx: &Context<'_>,
+        input: flow_graphql::StartDomCrawlInput,
+    ) -> async_graphql::Result<flow_graphql::DomCrawlStartResult> {
+        use flow_engine::{CrawlConfig, DomCrawler, ProxyConfig as FEProxyConfig};
+
+        let db = ctx.data::<Arc<Database>>()?;
+        let ca = ctx.data::<Arc<proxy_core::CertificateAuthority>>()?;
+
+        if reqwest::Url::parse(&input.start_url).is_err() {
+            return Err(async_graphql::Error::new(format!("Invalid start URL: {}", input.start_url)));
+        }
+        authorize_target(ctx, ActiveTool::Crawler, &input.start_url).await?;
+
+        let crawl_id = Uuid::new_v4().to_string();
+
+        // Browser trusts the Proxxy CA so MITM'd HTTPS pages render
+        let ca_cert_path = std::env::temp_dir().join(format!("proxxy_crawl_ca_{}.crt", crawl_id));
+        let ca_cert_pem = ca.get_ca_cert_pem()
+            .map_err(|e| async_graphql::Error::new(format!("CA certificate not available: {:?}", e)))?;
+        std::fs::write(&ca_cert_path, ca_cert_pem)
+            .map_err(|e| async_graphql::Error::new(format!("Failed to write CA cert: {}", e)))?;
+
+        let defaults = CrawlConfig::default();
+        let proxy_port = input.proxy_port.unwrap_or(9095) as u16;
+        let config = CrawlConfig {
+            max_depth: input.max_depth.map(|d| d.max(0) as usize).unwrap_or(defaults.max_depth),
+            max_pages: input.max_pages.map(|p| p.max(1) as usize).unwrap_or(defaults.max_pages),
+            scope_hosts: input.scope_hosts.unwrap_or_default(),
+            submit_forms: input.submit_forms.unwrap_or(defaults.submit_forms),
+            allow_post_forms: input.allow_post_forms.unwrap_or(defaults.allow_post_forms),
+            headless: !input.headed.unwrap_or(false),
+            ca_cert_path: Some(ca_cert_path.to_string_lossy().to_string()),
+            ..defaults
+        }
+        .with_proxy(FEProxyConfig::new("127.0.0.1", proxy_port));
+
+        let db_clone = Arc::clone(&*db);
+        let crawl_id_clone = crawl_id.clone();
+        let start_url = input.start_url.clone();
+
+        // Pages navigated by the crawler flow through the agent and land in
+        // http_transactions; client-side routes are persisted separately.
+        tokio::spawn(async move {
+            tracing::info!("🕸️ Starting DOM crawl {} at {} via agent proxy :{}", crawl_id_clone, start_url, proxy_port);
+
+            let crawler = DomCrawler::new(config);
+            match crawler.crawl(&start_url).await {
+                Ok(result) => {
+                    let now = chrono::Utc::now().timestamp();
+                    let rows: Vec<crate::database::DiscoveredRouteRow> = result
+                        .routes
+                        .iter()
+                        .map(|route| crate::database::DiscoveredRouteRow {
+                            url: route.url.clone(),
+                            crawl_id: crawl_id_clone.clone(),
+                            host: reqwest::Url::parse(&route.url)
+                                .ok()
+                                .and_then(|u| u.host_str().map(|h| h.to_string()))
+                                .unwrap_or_default(),
+                            depth: route.depth as i64,
+                            source: format!("{:?}", route.source),
+                            title: route.title.clone(),
+                            visited: route.visited,
+                            discovered_at: now,
+                        })
+                        .collect();
+
+                    if let Err(e) = db_clone.save_discovered_routes(&rows).await {
+                        tracing::error!("Failed to save discovered routes: {:?}", e);
+                    }
+
+                    tracing::info!(
+                        "✅ DOM crawl {} completed: {} routes, {} pages, {} errors in {}ms",
+                        crawl_id_clone,
+                        result.routes.len(),
+                        result.pages_visited,
+                        result.errors.len(),
+                        result.duration_ms
+                    );
+                }
+                Err(e) => {
+                    tracing::error!("❌ DOM crawl {} failed: {:?}", crawl_id_clone, e);
+                }
+            }
+
+            let _ = std::fs::remove_file(&ca_cert_path);
+        });
+
+        Ok(flow_graphql::DomCrawlStartResult {
+            success: true,
+            crawl_id: Some(crawl_id),
+            message: format!("DOM crawl started at {}", input.start_url),
+        })
+    }
+
+    /// Get current recording session state
+    async fn get_recording_state(
+        &self,
+        _ctx: &Context<'_>,
+    ) -> async_graphql::Result<flow_graphql::RecordingSessionGql> {
+        // TODO: Get actual recording state from flow-engine
+        // For now, return idle state
+        Ok(flow_graphql::RecordingSessionGql::default())
+    }
+}
+
+// ============================================================================
+// SUBSCRIPTION ROOT
+// ============================================================================
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    async fn events(
+        &self, 
+        ctx: &Context<'_>,
+        agent_id: Option<String>,
+    ) -> impl Stream<Item = TrafficEventGql> {
+        let broadcast = ctx
+            .data::<tokio::sync::broadcast::Sender<(String, TrafficEvent)>>()
+            .expect("Broadcast missing")
+            .clone();
+        let rx = broadcast.subscribe();
+
+        // OPTIMIZATION: Use filter_map directly without intermediate allocations
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            res.ok().and_then(|(aid, event)| {
+                // Filter by agent_id if specified
+                if let Some(ref filter_id) = agent_id {
+                    if aid != *filter_id {
+                        return None;
+                    }
+                }
+                let mut gql = TrafficEventGql::from(event);
+                gql.agent_id = Some(aid);
+                Some(gql)
+            })
+        })
+    }
+
+    /// Requests being held, forwarded, dropped or released by agents' pause rules
+    async fn intercepts(&self, ctx: &Context<'_>) -> impl Stream<Item = InterceptedRequestGql> {
+        let rx = ctx
+            .data::<Arc<InterceptQueue>>()
+            .expect("Intercept queue missing")
+            .subscribe();
+
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(|res| {
+            res.ok()
+                .map(|update| InterceptedRequestGql::new(update.intercept, update.status))
+        })
+    }
+
+    async fn system_metrics_updates(
+        &self,
+        ctx: &Context<'_>,
+        agent_id: Option<String>,
+    ) -> impl Stream<Item = SystemMetricsGql> {
+        let broadcast = ctx
+            .data::<tokio::sync::broadcast::Sender<SystemMetricsEvent>>()
+            .expect("Metrics broadcast missing")
+            .clone();
+        let rx = broadcast.subscribe();
+
+        // OPTIMIZATION: Move agent_id into closure to avoid repeated clones
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            res.ok().and_then(|e| {
+                // Filter by agent_id if specified
+                if let Some(ref filter_id) = agent_id {
+                    if e.agent_id != *filter_id {
+                        return None;
+                    }
+                }
+                Some(SystemMetricsGql::from(e))
+            })
+        })
+    }
+
+    /// Subscribe to repeater execution updates
+    async fn repeater_executions(
+        &self,
+        ctx: &Context<'_>,
+        tab_id: Option<String>,
+    ) -> impl Stream<Item = RepeaterExecutionGql> {
+        let broadcast = ctx
+            .data::<tokio::sync::broadcast::Sender<RepeaterExecutionGql>>()
+            .map(|tx| tx.clone())
+            .unwrap_or_else(|_| {
+                // Create a dummy broadcast channel if not available
+                let (tx, _) = tokio::sync::broadcast::channel(100);
+                tx
+            });
+        let rx = broadcast.subscribe();
+
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            match res {
+                Ok(execution) => {
+                    // Filter by tab_id if specified
+                    if let Some(ref filter_id) = tab_id {
+                        if execution.tab_id != *filter_id {
+                            return None;
+                        }
+                    }
+                    Some(execution)
+                }
+                Err(_) => None,
+            }
+        })
+    }
+
+    /// Subscribe to per-step progress of a flow replay
+    async fn flow_replay_progress(
+        &self,
+        ctx: &Context<'_>,
+        replay_id: String,
+    ) -> impl Stream<Item = flow_graphql::FlowReplayProgressGql> {
+        let broadcast = ctx
+            .data::<tokio::sync::broadcast::Sender<flow_graphql::FlowReplayProgressGql>>()
+            .map(|tx| tx.clone())
+            .unwrap_or_else(|_| {
+                // Create a dummy broadcast channel if not available
+                let (tx, _) = tokio::sync::broadcast::channel(100);
+                tx
+            });
+        let rx = broadcast.subscribe();
+
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            match res {
+                Ok(progress) if progress.replay_id == replay_id => Some(progress),
+                _ => None,
+            }
+        })
+    }
+
+    /// Subscribe to intruder attack progress updates
+    async fn intruder_attack_progress(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: Option<String>,
+    ) -> impl Stream<Item = IntruderAttackProgressGql> {
+        let broadcast = ctx
+            .data::<tokio::sync::broadcast::Sender<IntruderAttackProgressGql>>()
+            .map(|tx| tx.clone())
+            .unwrap_or_else(|_| {
+                // Create a dummy broadcast channel if not available
+                let (tx, _) = tokio::sync::broadcast::channel(100);
+                tx
+            });
+        let rx = broadcast.subscribe();
+
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            match res {
+                Ok(progress) => {
+                    // Filter by attack_id if specified
+                    if let Some(ref filter_id) = attack_id {
+                        if progress.attack_id != *filter_id {
+                            return None;
+                        }
+                    }
+                    Some(progress)
+                }
+                Err(_) => None,
+            }
+        })
+    }
+
+    /// Subscribe to intruder attack results
+    async fn intruder_attack_results(
+        &self,
+        ctx: &Context<'_>,
+        attack_id: String,
+    ) -> impl Stream<Item = IntruderResultGql> {
+        let broadcast = ctx
+            .data::<tokio::sync::broadcast::Sender<IntruderResultGql>>()
+            .map(|tx| tx.clone())
+            .unwrap_or_else(|_| {
+                // Create a dummy broadcast channel if not available
+                let (tx, _) = tokio::sync::broadcast::channel(1000);
+                tx
+            });
+        let rx = broadcast.subscribe();
+
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            match res {
+                Ok(result) => {
+                    // Filter by attack_id
+                    if result.attack_id == attack_id {
+                        Some(result)
+                    } else {
+                        None
+                    }
+                }
+                Err(_) => None,
+            }
+        })
+    }
+
+    /// Subscribe to session events
+    async fn session_events(
+        &self,
+        ctx: &Context<'_>,
+        session_id: Option<String>,
+    ) -> impl Stream<Item = SessionEventGql> {
+        let session_manager = ctx.data::<Arc<SessionManager>>()
+            .expect("SessionManager not found in context");
+        let rx = session_manager.subscribe_to_events();
+
+        tokio_stream::wrappers::BroadcastStream::new(rx).filter_map(move |res| {
+            match res {
+                Ok(event) => {
+                    // Filter by session_id if specified
+                    if let Some(ref filter_id) = session_id {
+                        let event_session_id = match &event {
+                            SessionEvent::Created { session_id } => Some(*session_id),
+                            SessionEvent::Validated { session_id, .. } => Some(*session_id),
+                            SessionEvent::ValidationFailed { session_id, .. } => Some(*session_id),
+                            SessionEvent::Expired { session_id } => Some(*session_id),
+                            SessionEvent::Used { session_id, .. } => Some(*session_id),
+                        };
+                        
+                        if let Some(event_id) = event_session_id {
+                            if event_id.to_string() != *filter_id {
+                                return None;
+                            }
+                        }
+                    }
+                    Some(SessionEventGql::from(event))
+                }
+                Err(_) => None,
+            }
+        })
+    }
+}
+
+// ============================================================================
+// TRAFFIC EVENT GQL (LAZY LOADING PATTERN)
+// ============================================================================
+
+/// OPTIMIZATION: Lazy loading pattern
+/// - Hafif veriler (id, method, url) hemen yüklenir
+/// - Ağır veriler (body, headers) sadece istendiğinde parse edilir
+#[derive(SimpleObject)]
+#[graphql(complex)] // ComplexObject ile ek resolver'lar ekleyeceğiz
+pub struct TrafficEventGql {
+    pub request_id: String,
+    pub method: Option<String>,
+    pub url: Option<String>,
+    pub status: Option<i32>,
+    pub timestamp: Option<String>,
+    pub agent_id: Option<String>,
+    /// Source IP the target saw (detail view only)
+    pub egress_ip: Option<String>,
+    /// Kind of API call, tagged at ingest (stored transactions only)
+    pub api_protocol: Option<ApiProtocolGql>,
+    /// Transaction this one followed as a redirect or retry (stored transactions only)
+    pub parent_id: Option<String>,
+    /// Triage comment, highlight color and tags (stored transactions only)
+    pub comment: Option<String>,
+    pub color: Option<HighlightColorGql>,
+    pub tags: Vec<String>,
+
+    // OPTIMIZATION: Ağır veriyi sakla ama GraphQL şemasına ekleme
+    #[graphql(skip)]
+    pub inner_event: TrafficEvent,
+    
+    // Response event for full transaction view (also skipped from direct access)
+    #[graphql(skip)]
+    pub response_event: Option<TrafficEvent>,
+}
+
+/// ComplexObject: Ağır veriler sadece istendiğinde hesaplanır
+/// İstemci bu alanları query'de belirtmezse, ASLA çalışmaz!
+#[ComplexObject]
+impl TrafficEventGql {
+    /// Request body - sadece istendiğinde parse edilir
+    async fn request_body(&self) -> Option<String> {
+        if let Some(traffic_event::Event::Request(req)) = &self.inner_event.event {
+            if req.body.is_empty() {
+                return None;
+            }
+            return Some(convert_body_to_string(req.headers.as_ref().map(|h| &h.headers), &req.body));
+        }
+        None
+    }
+
+    /// Request headers - sadece istendiğinde JSON'a çevrilir
+    async fn request_headers(&self) -> Option<String> {
+        if let Some(traffic_event::Event::Request(req)) = &self.inner_event.event {
+            return req
+                .headers
+                .as_ref()
+                .and_then(|h| serde_json::to_string(&h.headers).ok());
+        }
+        None
+    }
+
+    /// Response body - sadece istendiğinde parse edilir
+    async fn response_body(&self) -> Option<String> {
+        // First check response_event (used for full transaction view)
+        if let Some(ref response_event) = self.response_event {
+            if let Some(traffic_event::Event::Response(res)) = &response_event.event {
+                if !res.body.is_empty() {
+                    return Some(convert_body_to_string(res.headers.as_ref().map(|h| &h.headers), &res.body));
+                }
+            }
+        }
+        // Fallback to inner_event for subscription events
+        if let Some(traffic_event::Event::Response(res)) = &self.inner_event.event {
+            if res.body.is_empty() {
+                return None;
+            }
+            return Some(convert_body_to_string(res.headers.as_ref().map(|h| &h.headers), &res.body));
+        }
+        None
+    }
+
+    /// Charset the request body was decoded from for display ("UTF-8",
+    /// "Shift_JIS", ...); None when it is shown as base64
+    async fn request_charset(&self) -> Option<String> {
+        match &self.inner_event.event {
+            Some(traffic_event::Event::Request(req)) => body_charset(req.headers.as_ref(), &req.body),
+            _ => None,
+        }
+    }
+
+    /// Charset the response body was decoded from for display; None when it
+    /// is shown as base64
+    async fn response_charset(&self) -> Option<String> {
+        [self.response_event.as_ref(), Some(&self.inner_event)]
+            .into_iter()
+            .flatten()
+            .find_map(|e| match &e.event {
+                Some(traffic_event::Event::Response(res)) if !res.body.is_empty() => Some(res),
+                _ => None,
+            })
+            .and_then(|res| body_charset(res.headers.as_ref(), &res.body))
+    }
+
+    /// Type of the response body judged from its content ("image/png",
+    /// "text/html", ...), which may differ from its Content-Type. Use it to
+    /// pick a viewer.
+    async fn response_detected_type(&self) -> Option<String> {
+        [self.response_event.as_ref(), Some(&self.inner_event)]
+            .into_iter()
+            .flatten()
+            .find_map(|e| match &e.event {
+                Some(traffic_event::Event::Response(res)) => crate::mime_sniff::sniff(&res.body),
+                _ => None,
+            })
+            .map(str::to_string)
+    }
+
+    /// Protocol the agent spoke with the origin ("HTTP/1.1", "HTTP/2")
+    async fn response_protocol(&self) -> Option<String> {
+        [self.response_event.as_ref(), Some(&self.inner_event)]
+            .into_iter()
+            .flatten()
+            .find_map(|e| match &e.event {
+                Some(traffic_event::Event::Response(res)) if !res.protocol.is_empty() => Some(res.protocol.clone()),
+                _ => None,
+            })
+    }
+
+    /// Request phases timed by the agent
+    async fn response_timing(&self) -> Option<ResponseTimingGql> {
+        [self.response_event.as_ref(), Some(&self.inner_event)]
+            .into_iter()
+            .flatten()
+            .find_map(|e| match &e.event {
+                Some(traffic_event::Event::Response(res)) => res.timing.as_ref().map(ResponseTimingGql::from),
+                _ => None,
+            })
+    }
+
+    /// Parts of a multipart request body. File parts carry a download URL
+    /// once stored with `extractMultipartFiles`.
+    async fn multipart(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<MultipartGql>> {
+        let req = match &self.inner_event.event {
+            Some(traffic_event::Event::Request(req)) => req,
+            _ => return Ok(None),
+        };
+        let content_type = req
+            .headers
+            .as_ref()
+            .and_then(|h| h.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("content-type")))
+            .map(|(_, v)| v.as_str())
+            .unwrap_or("");
+        let body = match crate::multipart::parse(content_type, &req.body) {
+            Ok(body) => body,
+            Err(crate::multipart::MultipartError::NotMultipart(_)) => return Ok(None),
+            Err(e) => return Err(async_graphql::Error::new(e.to_string())),
+        };
+
+        let db = ctx.data::<Arc<Database>>()?;
+        let stored = db
+            .blob_refs_for_owner(crate::multipart::UPLOAD_OWNER_KIND, &self.request_id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(Some(MultipartGql::new(&body, &stored)))
+    }
+
+    /// Decoded gRPC messages, for application/grpc and gRPC-Web calls.
+    /// Uses the project's descriptor sets when one defines the method.
+    async fn grpc(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<GrpcCallGql>> {
+        let request = match &self.inner_event.event {
+            Some(traffic_event::Event::Request(req)) => Some(req),
+            _ => None,
+        };
+        let response = [self.response_event.as_ref(), Some(&self.inner_event)]
+            .into_iter()
+            .flatten()
+            .find_map(|e| match &e.event {
+                Some(traffic_event::Event::Response(res)) => Some(res),
+                _ => None,
+            });
+
+        let empty = std::collections::HashMap::new();
+        let request_headers = request.and_then(|r| r.headers.as_ref()).map(|h| &h.headers).unwrap_or(&empty);
+        let response_headers = response.and_then(|r| r.headers.as_ref()).map(|h| &h.headers).unwrap_or(&empty);
+        let is_grpc = [request_headers, response_headers].iter().any(|headers| {
+            headers
+                .iter()
+                .any(|(k, v)| k.eq_ignore_ascii_case("content-type") && crate::grpc_decode::is_grpc(v))
+        });
+        if !is_grpc {
+            return Ok(None);
+        }
+
+        let db = ctx.data::<Arc<Database>>()?;
+        let sets = db
+            .list_grpc_descriptor_sets()
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        let decoder = crate::grpc_decode::GrpcDecoder::from_descriptor_sets(
+            sets.iter().map(|s| s.descriptor_set.as_slice()),
+        );
+
+        let path = request
+            .and_then(|r| reqwest::Url::parse(&r.url).ok())
+            .map(|u| u.path().to_string())
+            .unwrap_or_default();
+        let call = decoder.decode_call(
+            &path,
+            request_headers,
+            request.map(|r| r.body.as_ref()).unwrap_or_default(),
+            response_headers,
+            response.map(|r| r.body.as_ref()).unwrap_or_default(),
+        );
+        Ok(Some(call.into()))
+    }
+
+    /// Response headers - sadece istendiğinde JSON'a çevrilir
+    async fn response_headers(&self) -> Option<String> {
+        // First check response_event (used for full transaction view)
+        if let Some(ref response_event) = self.response_event {
+            if let Some(traffic_event::Event::Response(res)) = &response_event.event {
+                return res
+                    .headers
+                    .as_ref()
+                    .and_then(|h| serde_json::to_string(&h.headers).ok());
+            }
+        }
+        // Fallback to inner_event for subscription events
+        if let Some(traffic_event::Event::Response(res)) = &self.inner_event.event {
+            return res
+                .headers
+                .as_ref()
+                .and_then(|h| serde_json::to_string(&h.headers).ok());
+        }
+        None
+    }
+}
+
+/// OPTIMIZATION: From implementation artık çok hafif
+/// Sadece metadata parse ediliyor, body/headers atlanıyor
+impl From<TrafficEvent> for TrafficEventGql {
+    fn from(e: TrafficEvent) -> Self {
+        let mut method = None;
+        let mut url = None;
+        let mut status = None;
+
+        // OPTIMIZATION: TrafficEvent proto'sunda timestamp yok, current time kullan
+        // TODO: Proto'ya timestamp field'ı eklenebilir
+        let timestamp = Some(chrono::Utc::now().to_rfc3339());
+
+        // OPTIMIZATION: Sadece metadata extract et, body/headers'ı atla
+        match &e.event {
+            Some(traffic_event::Event::Request(req)) => {
+                method = Some(req.method.clone());
+                url = Some(req.url.clone());
+            }
+            Some(traffic_event::Event::Response(res)) => {
+                status = Some(res.status_code);
+            }
+            _ => {}
+        }
+
+        Self {
+            request_id: e.request_id.clone(),
+            method,
+            url,
+            status,
+            timestamp,
+            agent_id: None, // TrafficEvent proto'sunda agent_id yok, database'den alınmalı
+            egress_ip: None,
+            api_protocol: None,
+            parent_id: None,
+            comment: None,
+            color: None,
+            tags: Vec::new(),
+            // CRITICAL: Tüm event'i sakla, lazy loading için
+            inner_event: e,
+            response_event: None, // Will be set manually for full transaction view
+        }
+    }
+}
+
+// ============================================================================
+// SEARCH GQL
+// ============================================================================
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SnippetSourceGql {
+    Url,
+    RequestBody,
+    ResponseBody,
+}
+
+/// A highlighted range, byte offsets within the snippet text
+#[derive(SimpleObject)]
+pub struct HighlightRangeGql {
+    pub start: i32,
+    pub end: i32,
+}
+
+#[derive(SimpleObject)]
+pub struct SearchSnippetGql {
+    pub source: SnippetSourceGql,
+    /// Byte offset of the snippet in the decoded URL or body
+    pub offset: i32,
+    pub text: String,
+    pub highlights: Vec<HighlightRangeGql>,
+}
+
+impl From<SearchSnippet> for SearchSnippetGql {
+    fn from(snippet: SearchSnippet) -> Self {
+        Self {
+            source: match snippet.source {
+                SnippetSource::Url => SnippetSourceGql::Url,
+                SnippetSource::RequestBody => SnippetSourceGql::RequestBody,
+                SnippetSource::ResponseBody => SnippetSourceGql::ResponseBody,
+            },
+            offset: snippet.offset as i32,
+            text: snippet.text,
+            highlights: snippet
+                .highlights
+                .into_iter()
+                .map(|(start, end)| HighlightRangeGql { start: start as i32, end: end as i32 })
+                .collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct SearchHitGql {
+    pub transaction: TrafficEventGql,
+    pub snippets: Vec<SearchSnippetGql>,
+}
+
+// ============================================================================
+// API PROTOCOL GQL
+// ============================================================================
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ApiProtocolGql {
+    Rest,
+    #[graphql(name = "GRAPHQL")]
+    GraphQl,
+    Grpc,
+    Soap,
+    Form,
+    Multipart,
+    Binary,
+}
+
+impl ApiProtocolGql {
+    /// From the stored `api_protocol` column
+    fn from_tag(tag: Option<&str>) -> Option<Self> {
+        tag.and_then(crate::api_protocol::ApiProtocol::parse).map(Self::from)
+    }
+}
+
+impl From<crate::api_protocol::ApiProtocol> for ApiProtocolGql {
+    fn from(protocol: crate::api_protocol::ApiProtocol) -> Self {
+        use crate::api_protocol::ApiProtocol;
+        match protocol {
+            ApiProtocol::Rest => ApiProtocolGql::Rest,
+            ApiProtocol::GraphQl => ApiProtocolGql::GraphQl,
+            ApiProtocol::Grpc => ApiProtocolGql::Grpc,
+            ApiProtocol::Soap => ApiProtocolGql::Soap,
+            ApiProtocol::Form => ApiProtocolGql::Form,
+            ApiProtocol::Multipart => ApiProtocolGql::Multipart,
+            ApiProtocol::Binary => ApiProtocolGql::Binary,
+        }
+    }
+}
+
+impl From<ApiProtocolGql> for crate::api_protocol::ApiProtocol {
+    fn from(protocol: ApiProtocolGql) -> Self {
+        use crate::api_protocol::ApiProtocol;
+        match protocol {
+            ApiProtocolGql::Rest => ApiProtocol::Rest,
+            ApiProtocolGql::GraphQl => ApiProtocol::GraphQl,
+            ApiProtocolGql::Grpc => ApiProtocol::Grpc,
+            ApiProtocolGql::Soap => ApiProtocol::Soap,
+            ApiProtocolGql::Form => ApiProtocol::Form,
+            ApiProtocolGql::Multipart => ApiProtocol::Multipart,
+            ApiProtocolGql::Binary => ApiProtocol::Binary,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ApiProtocolCountGql {
+    pub protocol: ApiProtocolGql,
+    pub count: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct ApiEndpointGql {
+    pub protocol: ApiProtocolGql,
+    pub method: String,
+    /// URL without its query string
+    pub endpoint: String,
+    pub hits: i64,
+    pub last_seen: String,
+    pub last_request_id: String,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ExternalToolGql {
+    Sqlmap,
+    Ffuf,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InsertionLocationGql {
+    Query,
+    /// URL-encoded form field or JSON key
+    Body,
+    Header,
+    Cookie,
+}
+
+#[derive(InputObject)]
+pub struct InsertionPointInput {
+    pub location: InsertionLocationGql,
+    pub name: String,
+}
+
+impl InsertionPointInput {
+    fn into_point(self) -> crate::tool_export::InsertionPoint {
+        use crate::tool_export::InsertionLocation;
+        crate::tool_export::InsertionPoint {
+            location: match self.location {
+                InsertionLocationGql::Query => InsertionLocation::Query,
+                InsertionLocationGql::Body => InsertionLocation::Body,
+                InsertionLocationGql::Header => InsertionLocation::Header,
+                InsertionLocationGql::Cookie => InsertionLocation::Cookie,
+            },
+            name: self.name,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ToolExportGql {
+    /// Raw HTTP request to save as `requestFileName`
+    pub request_file: String,
+    pub request_file_name: String,
+    pub command: String,
+    /// ffuf config file (TOML), usable with `ffuf -config`
+    pub config: Option<String>,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ChainLinkGql {
+    Redirect,
+    Retry,
+}
+
+#[derive(SimpleObject)]
+pub struct TransactionChainEntryGql {
+    pub request_id: String,
+    pub parent_id: Option<String>,
+    /// How this transaction followed its parent; empty for the first one
+    pub link: Option<ChainLinkGql>,
+    pub method: String,
+    pub url: String,
+    pub status: Option<i32>,
+    pub timestamp: String,
+}
+
+impl From<crate::database::ChainRow> for TransactionChainEntryGql {
+    fn from(row: crate::database::ChainRow) -> Self {
+        Self {
+            request_id: row.request_id,
+            parent_id: row.parent_id,
+            link: row.link.map(|link| match link {
+                crate::database::ChainLink::Redirect => ChainLinkGql::Redirect,
+                crate::database::ChainLink::Retry => ChainLinkGql::Retry,
+            }),
+            method: row.method,
+            url: row.url,
+            status: row.status,
+            timestamp: chrono::DateTime::from_timestamp(row.timestamp, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Responses by status class; `other` counts requests without a response
+/// (or with a 1xx one)
+#[derive(SimpleObject)]
+pub struct StatusSummaryGql {
+    pub success: i64,
+    pub redirect: i64,
+    pub client_error: i64,
+    pub server_error: i64,
+    pub other: i64,
+}
+
+impl StatusSummaryGql {
+    fn new(hits: i64, statuses: crate::database::StatusCounts) -> Self {
+        Self {
+            success: statuses.success,
+            redirect: statuses.redirect,
+            client_error: statuses.client_error,
+            server_error: statuses.server_error,
+            other: hits - statuses.success - statuses.redirect - statuses.client_error - statuses.server_error,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct SiteMapMethodGql {
+    pub method: String,
+    pub path: String,
+    pub hits: i64,
+    pub status: StatusSummaryGql,
+    pub last_status: Option<i32>,
+    pub last_request_id: Option<String>,
+    pub last_seen: String,
+}
+
+#[derive(SimpleObject)]
+pub struct SiteMapNodeGql {
+    /// Host name, or the path segment
+    pub name: String,
+    pub path: String,
+    /// Requests to this path and everything below it
+    pub hits: i64,
+    pub status: StatusSummaryGql,
+    pub last_seen: String,
+    pub methods: Vec<SiteMapMethodGql>,
+    pub children: Vec<SiteMapNodeGql>,
+}
+
+impl From<crate::database::SiteMapNode> for SiteMapNodeGql {
+    fn from(node: crate::database::SiteMapNode) -> Self {
+        Self {
+            name: node.name,
+            path: node.path,
+            hits: node.hits,
+            status: StatusSummaryGql::new(node.hits, node.statuses),
+            last_seen: chrono::DateTime::from_timestamp(node.last_seen, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+            methods: node
+                .methods
+                .into_iter()
+                .map(|entry| SiteMapMethodGql {
+                    status: StatusSummaryGql::new(entry.hits, entry.statuses),
+                    method: entry.method,
+                    path: entry.path,
+                    hits: entry.hits,
+                    last_status: entry.last_status,
+                    last_request_id: entry.last_request_id,
+                    last_seen: chrono::DateTime::from_timestamp(entry.last_seen, 0)
+                        .map(|d| d.to_rfc3339())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            children: node.children.into_iter().map(SiteMapNodeGql::from).collect(),
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BodyPartGql {
+    Request,
+    Response,
+}
+
+impl From<BodyPartGql> for crate::hex_view::BodyPart {
+    fn from(part: BodyPartGql) -> Self {
+        match part {
+            BodyPartGql::Request => crate::hex_view::BodyPart::Request,
+            BodyPartGql::Response => crate::hex_view::BodyPart::Response,
+        }
+    }
+}
+
+/// One hex view row of up to 16 bytes
+#[derive(SimpleObject)]
+pub struct HexRowGql {
+    pub offset: i64,
+    pub hex: String,
+    pub ascii: String,
+}
+
+/// A page of a body in the hex view
+#[derive(SimpleObject)]
+pub struct HexDumpGql {
+    pub offset: i64,
+    pub length: i64,
+    pub total_length: i64,
+    pub rows: Vec<HexRowGql>,
+}
+
+impl From<crate::hex_view::HexDump> for HexDumpGql {
+    fn from(dump: crate::hex_view::HexDump) -> Self {
+        Self {
+            offset: dump.offset as i64,
+            length: dump.length as i64,
+            total_length: dump.total_length as i64,
+            rows: dump
+                .rows
+                .into_iter()
+                .map(|row| HexRowGql {
+                    offset: row.offset as i64,
+                    hex: row.hex,
+                    ascii: row.ascii,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TokenWeaknessGql {
+    AlgNone,
+    Expired,
+    NotYetValid,
+    NoExpiry,
+    TruncatedHmacSignature,
+}
+
+impl From<crate::jwt::TokenWeakness> for TokenWeaknessGql {
+    fn from(weakness: crate::jwt::TokenWeakness) -> Self {
+        use crate::jwt::TokenWeakness;
+        match weakness {
+            TokenWeakness::AlgNone => TokenWeaknessGql::AlgNone,
+            TokenWeakness::Expired => TokenWeaknessGql::Expired,
+            TokenWeakness::NotYetValid => TokenWeaknessGql::NotYetValid,
+            TokenWeakness::NoExpiry => TokenWeaknessGql::NoExpiry,
+            TokenWeakness::TruncatedHmacSignature => TokenWeaknessGql::TruncatedHmacSignature,
+        }
+    }
+}
+
+/// A decoded, unverified JWT
+#[derive(SimpleObject)]
+pub struct DecodedTokenGql {
+    pub alg: Option<String>,
+    /// Header and claims as pretty-printed JSON
+    pub header: String,
+    pub claims: String,
+    pub issued_at: Option<String>,
+    pub expires_at: Option<String>,
+    pub not_before: Option<String>,
+    pub expired: bool,
+    pub signature_length: i32,
+    pub weaknesses: Vec<TokenWeaknessGql>,
+}
+
+impl DecodedTokenGql {
+    fn new(token: crate::jwt::DecodedToken, now: chrono::DateTime<chrono::Utc>) -> Self {
+        let pretty = |object| serde_json::to_string_pretty(&serde_json::Value::Object(object)).unwrap_or_default();
+        Self {
+            alg: token.alg.clone(),
+            expired: token.is_expired(now),
+            issued_at: token.issued_at.map(|t| t.to_rfc3339()),
+            expires_at: token.expires_at.map(|t| t.to_rfc3339()),
+            not_before: token.not_before.map(|t| t.to_rfc3339()),
+            signature_length: token.signature_length as i32,
+            weaknesses: token.weaknesses.into_iter().map(TokenWeaknessGql::from).collect(),
+            header: pretty(token.header),
+            claims: pretty(token.claims),
+        }
+    }
+}
+
+/// How the `input` of `transform` is given
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TransformInputFormat {
+    Text,
+    /// Binary input, base64-encoded
+    Base64,
+    /// Binary input as hex digits
+    Hex,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+#[graphql(name = "TransformOp")]
+pub enum TransformOpGql {
+    Base64Encode,
+    Base64Decode,
+    Base64UrlEncode,
+    Base64UrlDecode,
+    UrlEncode,
+    UrlDecode,
+    HtmlEncode,
+    HtmlDecode,
+    HexEncode,
+    HexDecode,
+    Gzip,
+    Gunzip,
+    Deflate,
+    Inflate,
+    JwtHeader,
+    JwtPayload,
+    JwtSignature,
+    Utf16LeEncode,
+    Utf16BeEncode,
+    Utf16Decode,
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl From<TransformOpGql> for crate::decoder::TransformOp {
+    fn from(op: TransformOpGql) -> Self {
+        use crate::decoder::TransformOp;
+        match op {
+            TransformOpGql::Base64Encode => TransformOp::Base64Encode,
+            TransformOpGql::Base64Decode => TransformOp::Base64Decode,
+            TransformOpGql::Base64UrlEncode => TransformOp::Base64UrlEncode,
+            TransformOpGql::Base64UrlDecode => TransformOp::Base64UrlDecode,
+            TransformOpGql::UrlEncode => TransformOp::UrlEncode,
+            TransformOpGql::UrlDecode => TransformOp::UrlDecode,
+            TransformOpGql::HtmlEncode => TransformOp::HtmlEncode,
+            TransformOpGql::HtmlDecode => TransformOp::HtmlDecode,
+            TransformOpGql::HexEncode => TransformOp::HexEncode,
+            TransformOpGql::HexDecode => TransformOp::HexDecode,
+            TransformOpGql::Gzip => TransformOp::Gzip,
+            TransformOpGql::Gunzip => TransformOp::Gunzip,
+            TransformOpGql::Deflate => TransformOp::Deflate,
+            TransformOpGql::Inflate => TransformOp::Inflate,
+            TransformOpGql::JwtHeader => TransformOp::JwtHeader,
+            TransformOpGql::JwtPayload => TransformOp::JwtPayload,
+            TransformOpGql::JwtSignature => TransformOp::JwtSignature,
+            TransformOpGql::Utf16LeEncode => TransformOp::Utf16LeEncode,
+            TransformOpGql::Utf16BeEncode => TransformOp::Utf16BeEncode,
+            TransformOpGql::Utf16Decode => TransformOp::Utf16Decode,
+            TransformOpGql::Md5 => TransformOp::Md5,
+            TransformOpGql::Sha1 => TransformOp::Sha1,
+            TransformOpGql::Sha256 => TransformOp::Sha256,
+            TransformOpGql::Sha512 => TransformOp::Sha512,
+        }
+    }
+}
+
+impl From<crate::decoder::TransformOp> for TransformOpGql {
+    fn from(op: crate::decoder::TransformOp) -> Self {
+        use crate::decoder::TransformOp;
+        match op {
+            TransformOp::Base64Encode => TransformOpGql::Base64Encode,
+            TransformOp::Base64Decode => TransformOpGql::Base64Decode,
+            TransformOp::Base64UrlEncode => TransformOpGql::Base64UrlEncode,
+            TransformOp::Base64UrlDecode => TransformOpGql::Base64UrlDecode,
+            TransformOp::UrlEncode => TransformOpGql::UrlEncode,
+            TransformOp::UrlDecode => TransformOpGql::UrlDecode,
+            TransformOp::HtmlEncode => TransformOpGql::HtmlEncode,
+            TransformOp::HtmlDecode => TransformOpGql::HtmlDecode,
+            TransformOp::HexEncode => TransformOpGql::HexEncode,
+            TransformOp::HexDecode => TransformOpGql::HexDecode,
+            TransformOp::Gzip => TransformOpGql::Gzip,
+            TransformOp::Gunzip => TransformOpGql::Gunzip,
+            TransformOp::Deflate => TransformOpGql::Deflate,
+            TransformOp::Inflate => TransformOpGql::Inflate,
+            TransformOp::JwtHeader => TransformOpGql::JwtHeader,
+            TransformOp::JwtPayload => TransformOpGql::JwtPayload,
+            TransformOp::JwtSignature => TransformOpGql::JwtSignature,
+            TransformOp::Utf16LeEncode => TransformOpGql::Utf16LeEncode,
+            TransformOp::Utf16BeEncode => TransformOpGql::Utf16BeEncode,
+            TransformOp::Utf16Decode => TransformOpGql::Utf16Decode,
+            TransformOp::Md5 => TransformOpGql::Md5,
+            TransformOp::Sha1 => TransformOpGql::Sha1,
+            TransformOp::Sha256 => TransformOpGql::Sha256,
+            TransformOp::Sha512 => TransformOpGql::Sha512,
+        }
+    }
+}
+
+/// Bytes produced by a transform, in the representations a client needs
+#[derive(SimpleObject)]
+pub struct TransformOutputGql {
+    /// The bytes as UTF-8; null if they are not valid UTF-8
+    pub text: Option<String>,
+    pub base64: String,
+    pub hex: String,
+    pub length: i32,
+}
+
+impl TransformOutputGql {
+    fn new(bytes: &[u8]) -> Self {
+        Self {
+            text: std::str::from_utf8(bytes).ok().map(str::to_string),
+            base64: base64::engine::general_purpose::STANDARD.encode(bytes),
+            hex: bytes.iter().map(|b| format!("{:02x}", b)).collect(),
+            length: bytes.len() as i32,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TransformStepGql {
+    pub operation: TransformOpGql,
+    /// Output length in bytes
+    pub length: i32,
+    /// Whether the output is valid UTF-8
+    pub is_text: bool,
+}
+
+#[derive(SimpleObject)]
+pub struct TransformResultGql {
+    /// Output of the last operation, or the input when there are none
+    pub output: TransformOutputGql,
+    pub steps: Vec<TransformStepGql>,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum HighlightColorGql {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Cyan,
+    Blue,
+    Purple,
+    Pink,
+    Gray,
+}
+
+impl From<crate::database::HighlightColor> for HighlightColorGql {
+    fn from(color: crate::database::HighlightColor) -> Self {
+        use crate::database::HighlightColor;
+        match color {
+            HighlightColor::Red => HighlightColorGql::Red,
+            HighlightColor::Orange => HighlightColorGql::Orange,
+            HighlightColor::Yellow => HighlightColorGql::Yellow,
+            HighlightColor::Green => HighlightColorGql::Green,
+            HighlightColor::Cyan => HighlightColorGql::Cyan,
+            HighlightColor::Blue => HighlightColorGql::Blue,
+            HighlightColor::Purple => HighlightColorGql::Purple,
+            HighlightColor::Pink => HighlightColorGql::Pink,
+            HighlightColor::Gray => HighlightColorGql::Gray,
+        }
+    }
+}
+
+impl From<HighlightColorGql> for crate::database::HighlightColor {
+    fn from(color: HighlightColorGql) -> Self {
+        use crate::database::HighlightColor;
+        match color {
+            HighlightColorGql::Red => HighlightColor::Red,
+            HighlightColorGql::Orange => HighlightColor::Orange,
+            HighlightColorGql::Yellow => HighlightColor::Yellow,
+            HighlightColorGql::Green => HighlightColor::Green,
+            HighlightColorGql::Cyan => HighlightColor::Cyan,
+            HighlightColorGql::Blue => HighlightColor::Blue,
+            HighlightColorGql::Purple => HighlightColor::Purple,
+            HighlightColorGql::Pink => HighlightColor::Pink,
+            HighlightColorGql::Gray => HighlightColor::Gray,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct RequestAnnotationGql {
+    pub request_id: String,
+    pub comment: Option<String>,
+    pub color: Option<HighlightColorGql>,
+    pub tags: Vec<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct RequestTagGql {
+    pub tag: String,
+    /// Transactions carrying the tag
+    pub count: i64,
+}
+
+impl TrafficEventGql {
+    fn set_annotation(&mut self, annotation: crate::database::RequestAnnotation) {
+        self.comment = annotation.comment;
+        self.color = annotation.color.map(HighlightColorGql::from);
+        self.tags = annotation.tags;
+    }
+
+    /// Traffic list row with its stored metadata
+    fn from_recent(row: crate::database::RecentRequest) -> Self {
+        let mut gql = TrafficEventGql::from(row.event);
+        gql.agent_id = Some(row.agent_id);
+        gql.api_protocol = ApiProtocolGql::from_tag(row.api_protocol.as_deref());
+        gql.parent_id = row.parent_id;
+        gql.set_annotation(row.annotation);
+        gql.status = row.status;
+        gql
+    }
+}
+
+/// Annotation of `request_id` after an update that `found` it
+async fn request_annotation(
+    db: &Database,
+    request_id: String,
+    found: bool,
+) -> async_graphql::Result<RequestAnnotationGql> {
+    if !found {
+        return Err(async_graphql::Error::new("Request not found"));
+    }
+    let annotation = db
+        .get_request_annotation(&request_id)
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?
+        .unwrap_or_default();
+
+    Ok(RequestAnnotationGql {
+        request_id,
+        comment: annotation.comment,
+        color: annotation.color.map(HighlightColorGql::from),
+        tags: annotation.tags,
+    })
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SeverityGql {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl From<Severity> for SeverityGql {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Info => SeverityGql::Info,
+            Severity::Low => SeverityGql::Low,
+            Severity::Medium => SeverityGql::Medium,
+            Severity::High => SeverityGql::High,
+            Severity::Critical => SeverityGql::Critical,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MatchPartGql {
+    Header,
+    Body,
+}
+
+#[derive(SimpleObject)]
+pub struct PassiveMatcherGql {
+    pub part: MatchPartGql,
+    /// Header name, for header matchers restricted to one header
+    pub name: Option<String>,
+    pub regex: String,
+}
+
+#[derive(SimpleObject)]
+pub struct PassiveCheckGql {
+    pub id: String,
+    pub name: String,
+    pub severity: SeverityGql,
+    pub description: String,
+    pub matchers: Vec<PassiveMatcherGql>,
+    /// Rhai script deciding "vulnerable", "not_vulnerable" or "needs_more"
+    pub verdict: Option<String>,
+}
+
+impl From<PassiveCheckDefinition> for PassiveCheckGql {
+    fn from(check: PassiveCheckDefinition) -> Self {
+        Self {
+            id: check.id,
+            name: check.name,
+            severity: check.severity.into(),
+            description: check.description,
+            matchers: check
+                .matchers
+                .into_iter()
+                .map(|matcher| match matcher {
+                    MatcherDefinition::Header { name, regex } => PassiveMatcherGql {
+                        part: MatchPartGql::Header,
+                        name,
+                        regex,
+                    },
+                    MatcherDefinition::Body { regex } => PassiveMatcherGql {
+                        part: MatchPartGql::Body,
+                        name: None,
+                        regex,
+                    },
+                })
+                .collect(),
+            verdict: check.verdict,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct BurpImportResultGql {
+    pub imported: i64,
+    /// Items without a URL or request
+    pub skipped: i64,
+}
+
+#[derive(SimpleObject)]
+pub struct HookScriptGql {
+    pub id: String,
+    /// Rhai source defining `on_request()` and/or `on_response()`
+    pub source: String,
+}
+
+impl From<HookScriptDefinition> for HookScriptGql {
+    fn from(script: HookScriptDefinition) -> Self {
+        Self {
+            id: script.id,
+            source: script.source,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct PassiveFindingGql {
+    pub id: String,
+    pub request_id: String,
+    pub check_id: String,
+    pub check_name: String,
+    pub severity: Option<SeverityGql>,
+    pub description: String,
+    /// Text each matcher matched
+    pub evidence: String,
+    pub created_at: String,
+}
+
+impl From<crate::database::PassiveFindingRow> for PassiveFindingGql {
+    fn from(row: crate::database::PassiveFindingRow) -> Self {
+        Self {
+            id: row.id,
+            request_id: row.request_id,
+            check_id: row.check_id,
+            check_name: row.check_name,
+            severity: Severity::from_tag(&row.severity).map(SeverityGql::from),
+            description: row.description,
+            evidence: row.evidence,
+            created_at: chrono::DateTime::from_timestamp(row.created_at, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ConnectionEventKindGql {
+    Connect,
+    PassThrough,
+    TlsFailure,
+    UpstreamError,
+    Blocked,
+    NoTraffic,
+    /// Host tunnelled without interception for a while because
+    /// interception of it kept failing
+    AutoPassThrough,
+}
+
+impl From<ConnectionEventKindGql> for crate::pb::connection_event::Kind {
+    fn from(kind: ConnectionEventKindGql) -> Self {
+        use crate::pb::connection_event::Kind;
+        match kind {
+            ConnectionEventKindGql::Connect => Kind::Connect,
+            ConnectionEventKindGql::PassThrough => Kind::PassThrough,
+            ConnectionEventKindGql::TlsFailure => Kind::TlsFailure,
+            ConnectionEventKindGql::UpstreamError => Kind::UpstreamError,
+            ConnectionEventKindGql::Blocked => Kind::Blocked,
+            ConnectionEventKindGql::NoTraffic => Kind::NoTraffic,
+            ConnectionEventKindGql::AutoPassThrough => Kind::AutoPassThrough,
+        }
+    }
+}
+
+impl From<crate::pb::connection_event::Kind> for ConnectionEventKindGql {
+    fn from(kind: crate::pb::connection_event::Kind) -> Self {
+        use crate::pb::connection_event::Kind;
+        match kind {
+            Kind::Connect => ConnectionEventKindGql::Connect,
+            Kind::PassThrough => ConnectionEventKindGql::PassThrough,
+            Kind::TlsFailure => ConnectionEventKindGql::TlsFailure,
+            Kind::UpstreamError => ConnectionEventKindGql::UpstreamError,
+            Kind::Blocked => ConnectionEventKindGql::Blocked,
+            Kind::NoTraffic => ConnectionEventKindGql::NoTraffic,
+            Kind::AutoPassThrough => ConnectionEventKindGql::AutoPassThrough,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ViewSortFieldGql {
+    Timestamp,
+    Method,
+    Url,
+    Status,
+    Length,
+}
+
+impl From<ViewSortFieldGql> for ViewSortField {
+    fn from(field: ViewSortFieldGql) -> Self {
+        match field {
+            ViewSortFieldGql::Timestamp => ViewSortField::Timestamp,
+            ViewSortFieldGql::Method => ViewSortField::Method,
+            ViewSortFieldGql::Url => ViewSortField::Url,
+            ViewSortFieldGql::Status => ViewSortField::Status,
+            ViewSortFieldGql::Length => ViewSortField::Length,
+        }
+    }
+}
+
+impl From<ViewSortField> for ViewSortFieldGql {
+    fn from(field: ViewSortField) -> Self {
+        match field {
+            ViewSortField::Timestamp => ViewSortFieldGql::Timestamp,
+            ViewSortField::Method => ViewSortFieldGql::Method,
+            ViewSortField::Url => ViewSortFieldGql::Url,
+            ViewSortField::Status => ViewSortFieldGql::Status,
+            ViewSortField::Length => ViewSortFieldGql::Length,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SortDirectionGql {
+    Asc,
+    Desc,
+}
+
+/// A named filter of the request list with its sort order
+#[derive(SimpleObject)]
+pub struct SavedViewGql {
+    pub id: String,
+    pub name: String,
+    /// Filter expression, as typed in the request list
+    pub filter: String,
+    pub sort_field: ViewSortFieldGql,
+    pub sort_direction: SortDirectionGql,
+    /// Opened with the request list
+    pub is_default: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+impl From<SavedView> for SavedViewGql {
+    fn from(view: SavedView) -> Self {
+        let time = |secs: i64| chrono::DateTime::from_timestamp(secs, 0).map(|d| d.to_rfc3339()).unwrap_or_default();
+        Self {
+            id: view.id,
+            name: view.name,
+            filter: view.filter,
+            sort_field: view.sort_field.into(),
+            sort_direction: if view.sort_ascending { SortDirectionGql::Asc } else { SortDirectionGql::Desc },
+            is_default: view.is_default,
+            created_at: time(view.created_at),
+            updated_at: time(view.updated_at),
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct SavedViewInput {
+    pub name: String,
+    pub filter: String,
+    /// Request time when unset
+    pub sort_field: Option<ViewSortFieldGql>,
+    /// Descending when unset
+    pub sort_direction: Option<SortDirectionGql>,
+    pub is_default: Option<bool>,
+}
+
+/// Connection-level event that produced no HTTP transaction
+#[derive(SimpleObject)]
+pub struct ConnectionEventGql {
+    pub id: String,
+    pub agent_id: String,
+    pub kind: Option<ConnectionEventKindGql>,
+    /// host:port for tunnels, URL for requests
+    pub target: String,
+    pub client_addr: String,
+    pub reason: String,
+    /// Transaction whose upstream request failed
+    pub request_id: Option<String>,
+    pub created_at: String,
+}
+
+impl From<crate::database::ConnectionEventRow> for ConnectionEventGql {
+    fn from(row: crate::database::ConnectionEventRow) -> Self {
+        Self {
+            id: row.id,
+            agent_id: row.agent_id,
+            kind: crate::database::connections::kind_from_name(&row.kind).map(ConnectionEventKindGql::from),
+            target: row.target,
+            client_addr: row.client_addr,
+            reason: row.reason,
+            request_id: row.request_id,
+            created_at: chrono::DateTime::from_timestamp_millis(row.created_at)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// ============================================================================
+// AUTH GQL
+// ============================================================================
+
+#[derive(SimpleObject)]
+pub struct ApiKeyGql {
+    pub id: String,
+    pub name: String,
+    pub principal: String,
+    /// First characters of the key
+    pub prefix: String,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl From<crate::auth::ApiKeyInfo> for ApiKeyGql {
+    fn from(key: crate::auth::ApiKeyInfo) -> Self {
+        let rfc3339 = |ts: i64| {
+            chrono::DateTime::from_timestamp(ts, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default()
+        };
+        Self {
+            id: key.id,
+            name: key.name,
+            principal: key.principal,
+            prefix: key.prefix,
+            created_at: rfc3339(key.created_at),
+            last_used_at: key.last_used_at.map(rfc3339),
+            revoked_at: key.revoked_at.map(rfc3339),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct CreatedApiKeyGql {
+    /// The key itself, shown only once
+    pub key: String,
+    pub api_key: ApiKeyGql,
+}
+
+#[derive(SimpleObject)]
+pub struct AgentCertificateGql {
+    /// SHA-256 of the certificate (DER)
+    pub fingerprint: String,
+    pub name: String,
+    pub issued_at: String,
+    pub expires_at: String,
+    pub revoked_at: Option<String>,
+}
+
+impl From<crate::agent_tls::AgentCertificateInfo> for AgentCertificateGql {
+    fn from(cert: crate::agent_tls::AgentCertificateInfo) -> Self {
+        let rfc3339 = |ts: i64| {
+            chrono::DateTime::from_timestamp(ts, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default()
+        };
+        Self {
+            fingerprint: cert.fingerprint,
+            name: cert.name,
+            issued_at: rfc3339(cert.issued_at),
+            expires_at: rfc3339(cert.expires_at),
+            revoked_at: cert.revoked_at.map(rfc3339),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct LogLevelsGql {
+    /// Level of modules without an override
+    pub level: String,
+    pub modules: Vec<ModuleLogLevelGql>,
+    /// The levels as an `EnvFilter` directive string
+    pub directives: String,
+}
+
+#[derive(SimpleObject)]
+pub struct ModuleLogLevelGql {
+    /// Module path, e.g. `orchestrator::server`
+    pub module: String,
+    pub level: String,
+}
+
+impl From<crate::logging::LogLevels> for LogLevelsGql {
+    fn from(levels: crate::logging::LogLevels) -> Self {
+        let directives = levels.directives();
+        Self {
+            level: levels.level,
+            modules: levels
+                .modules
+                .into_iter()
+                .map(|(module, level)| ModuleLogLevelGql { module, level })
+                .collect(),
+            directives,
+        }
+    }
+}
+
+#[derive(InputObject)]
+pub struct ModuleLogLevelInput {
+    pub module: String,
+    /// Null removes the module's override
+    pub level: Option<String>,
+}
+
+#[derive(SimpleObject)]
+pub struct CaRotationGql {
+    pub id: i64,
+    pub rotated_at: String,
+    pub rotated_by: String,
+    /// SHA-256 of the certificates (DER)
+    pub previous_fingerprint: String,
+    pub new_fingerprint: String,
+    pub agents_online: u32,
+    /// Online agents the new CA was sent to; the rest get it on registration
+    pub agents_notified: u32,
+    /// When the archived key is deleted
+    pub grace_until: String,
+    pub purged_at: Option<String>,
+}
+
+impl From<crate::ca_rotation::CaRotationRecord> for CaRotationGql {
+    fn from(rotation: crate::ca_rotation::CaRotationRecord) -> Self {
+        let rfc3339 = |ts: i64| {
+            chrono::DateTime::from_timestamp(ts, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default()
+        };
+        Self {
+            id: rotation.id,
+            rotated_at: rfc3339(rotation.rotated_at),
+            rotated_by: rotation.rotated_by,
+            previous_fingerprint: rotation.previous_fingerprint,
+            new_fingerprint: rotation.new_fingerprint,
+            agents_online: rotation.agents_online,
+            agents_notified: rotation.agents_notified,
+            grace_until: rfc3339(rotation.grace_until),
+            purged_at: rotation.purged_at.map(rfc3339),
+        }
+    }
+}
+
+/// Files for `--client-cert`, `--client-key` and `--orchestrator-ca`
+#[derive(SimpleObject)]
+pub struct AgentEnrollmentGql {
+    pub name: String,
+    pub fingerprint: String,
+    pub cert_pem: String,
+    /// Shown only once
+    pub key_pem: String,
+    pub ca_cert_pem: String,
+}
+
+// ============================================================================
+// GRPC GQL
+// ============================================================================
+
+#[derive(SimpleObject)]
+pub struct GrpcCallGql {
+    /// Fully qualified service, from the request path
+    pub service: Option<String>,
+    pub method: Option<String>,
+    /// `grpc-status` code, from headers or gRPC-Web trailers
+    pub status: Option<String>,
+    pub status_message: Option<String>,
+    pub request_messages: Vec<GrpcMessageGql>,
+    pub response_messages: Vec<GrpcMessageGql>,
+}
+
+impl From<crate::grpc_decode::GrpcCall> for GrpcCallGql {
+    fn from(call: crate::grpc_decode::GrpcCall) -> Self {
+        Self {
+            service: call.service,
+            method: call.method,
+            status: call.status,
+            status_message: call.status_message,
+            request_messages: call.requests.into_iter().map(GrpcMessageGql::from).collect(),
+            response_messages: call.responses.into_iter().map(GrpcMessageGql::from).collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct GrpcMessageGql {
+    pub compressed: bool,
+    pub size: i64,
+    /// Protobuf type, when decoded with a descriptor; schema-less otherwise
+    pub type_name: Option<String>,
+    /// Decoded message as JSON (schema-less decoding keys fields by number)
+    pub json: String,
+    pub error: Option<String>,
+}
+
+impl From<crate::grpc_decode::GrpcMessage> for GrpcMessageGql {
+    fn from(message: crate::grpc_decode::GrpcMessage) -> Self {
+        Self {
+            compressed: message.compressed,
+            size: message.size as i64,
+            type_name: message.type_name,
+            json: serde_json::to_string_pretty(&message.json).unwrap_or_default(),
+            error: message.error,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct GrpcDescriptorSetGql {
+    pub id: String,
+    pub name: String,
+    /// Services the set defines
+    pub services: Vec<String>,
+    pub created_at: String,
+}
+
+impl From<crate::database::grpc::GrpcDescriptorSetRow> for GrpcDescriptorSetGql {
+    fn from(row: crate::database::grpc::GrpcDescriptorSetRow) -> Self {
+        Self {
+            services: crate::grpc_decode::GrpcDecoder::validate_descriptor_set(&row.descriptor_set)
+                .unwrap_or_default(),
+            id: row.id,
+            name: row.name,
+            created_at: chrono::DateTime::from_timestamp(row.created_at, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// ============================================================================
+// AGENT GQL
+// ============================================================================
+
+#[derive(SimpleObject)]
+pub struct EgressObservationGql {
+    pub ip: String,
+    /// When the agent first reported this IP
+    pub observed_at: String,
+}
+
+#[derive(SimpleObject)]
+pub struct AgentUsageGql {
+    pub agent_id: String,
+    /// `YYYY-MM-DD`, UTC
+    pub day: String,
+    pub requests: i64,
+    pub responses: i64,
+    /// Requests to the targets
+    pub bytes_sent: i64,
+    /// Responses from the targets
+    pub bytes_received: i64,
+}
+
+impl From<crate::database::AgentUsageRow> for AgentUsageGql {
+    fn from(row: crate::database::AgentUsageRow) -> Self {
+        Self {
+            agent_id: row.agent_id,
+            day: row.day,
+            requests: row.requests,
+            responses: row.responses,
+            bytes_sent: row.bytes_sent,
+            bytes_received: row.bytes_received,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ProjectGql {
+    pub name: String,
+    pub path: String,
+    pub size_bytes: i64,
+    pub last_modified: String,
+    pub is_active: bool,
+}
+
+impl From<crate::database::Project> for ProjectGql {
+    fn from(p: crate::database::Project) -> Self {
+        Self {
+            name: p.name,
+            path: p.path,
+            size_bytes: p.size_bytes,
+            last_modified: p.last_modified,
+            is_active: p.is_active,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ProjectOperationResult {
+    pub success: bool,
+    pub message: String,
+}
+
+#[derive(SimpleObject)]
+pub struct ResponseScreenshotGql {
+    /// Blob hash of the PNG
+    pub hash: String,
+    pub size: i64,
+    pub content_type: Option<String>,
+    pub download_url: String,
+}
+
+#[derive(SimpleObject)]
+pub struct MultipartGql {
+    pub boundary: String,
+    /// False when the closing boundary is missing (truncated capture)
+    pub complete: bool,
+    pub parts: Vec<MultipartPartGql>,
+}
+
+impl MultipartGql {
+    /// `stored` holds the blob hashes already referenced by the transaction
+    fn new(body: &crate::multipart::MultipartBody, stored: &[String]) -> Self {
+        let parts = body
+            .parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| {
+                let sha256 = part.sha256();
+                let blob_hash = Some(sha256.clone()).filter(|h| part.is_file() && stored.contains(h));
+                MultipartPartGql {
+                    index: index as i32,
+                    name: part.name.clone(),
+                    filename: part.filename.clone(),
+                    content_type: part.content_type.clone(),
+                    headers: serde_json::to_string(&part.headers).unwrap_or_default(),
+                    offset: part.offset as i64,
+                    size: part.data.len() as i64,
+                    value: if part.is_file() {
+                        None
+                    } else {
+                        std::str::from_utf8(&part.data).ok().map(str::to_string)
+                    },
+                    sha256,
+                    download_url: blob_hash.as_ref().map(|h| format!("/api/blobs/{}", h)),
+                    blob_hash,
+                }
+            })
+            .collect();
+
+        Self {
+            boundary: body.boundary.clone(),
+            complete: body.complete,
+            parts,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct MultipartPartGql {
+    pub index: i32,
+    /// Form field name
+    pub name: Option<String>,
+    /// Set for file uploads
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    /// Part headers as a JSON array of [name, value] pairs
+    pub headers: String,
+    /// Offset of the content in the request body
+    pub offset: i64,
+    pub size: i64,
+    /// Field value, for non-file parts with UTF-8 content
+    pub value: Option<String>,
+    pub sha256: String,
+    /// Set once the file is stored in the blob store
+    pub blob_hash: Option<String>,
+    pub download_url: Option<String>,
+}
+
+#[derive(InputObject)]
+pub struct SyncPeerInput {
+    /// gRPC address of the peer ("https://central:50051")
+    pub address: String,
+    /// Admin token or API key of the peer
+    #[graphql(secret)]
+    pub token: String,
+    /// Control-plane CA of the peer (PEM)
+    pub ca_cert_pem: String,
+    /// Client certificate from the peer's `enrollAgent` (PEM)
+    pub client_cert_pem: String,
+    #[graphql(secret)]
+    pub client_key_pem: String,
+}
+
+#[derive(SimpleObject)]
+pub struct SyncReportGql {
+    pub peer_node_id: String,
+    /// Records received from the peer
+    pub pulled: u64,
+    /// Records sent to the peer
+    pub pushed: u64,
+}
+
+#[derive(SimpleObject)]
+pub struct AgentGql {
+    pub id: String,
+    pub name: String,
+    pub hostname: String,
+    pub status: String,
+    pub version: String,
+    pub last_heartbeat: String,
+    pub cpu_usage: f32,
+    pub memory_usage_mb: f64,
+    pub uptime_seconds: u64,
+    pub public_ip: String,
+}
+
+// ============================================================================
+// REPLAY RESULT
+// ============================================================================
+
+#[derive(SimpleObject)]
+pub struct ReplayResult {
+    pub success: bool,
+    pub message: String,
+    pub replay_request_id: Option<String>,
+    pub original_url: String,
+    pub original_method: String,
+}
+
+// ============================================================================
+// SYSTEM METRICS GQL
+// ============================================================================
+
+#[derive(SimpleObject)]
+pub struct SystemMetricsGql {
+    pub agent_id: String,
+    pub timestamp: i64,
+    pub cpu_usage_percent: f32,
+    pub memory_used_bytes: String,
+    pub memory_total_bytes: String,
+    pub network_rx_bytes_per_sec: String,
+    pub network_tx_bytes_per_sec: String,
+    pub disk_read_bytes_per_sec: String,
+    pub disk_write_bytes_per_sec: String,
+    pub process_cpu_percent: f32,
+    pub process_memory_bytes: String,
+    pub process_uptime_seconds: i32,
+}
+
+impl From<SystemMetricsEvent> for SystemMetricsGql {
+    fn from(event: SystemMetricsEvent) -> Self {
+        let metrics = event.metrics.unwrap_or_default();
+        let network = metrics.network.unwrap_or_default();
+        let disk = metrics.disk.unwrap_or_default();
+        let process = metrics.process.unwrap_or_default();
+
+        Self {
+            agent_id: event.agent_id,
+            timestamp: event.timestamp,
+            cpu_usage_percent: metrics.cpu_usage_percent,
+            memory_used_bytes: metrics.memory_used_bytes.to_string(),
+            memory_total_bytes: metrics.memory_total_bytes.to_string(),
+            network_rx_bytes_per_sec: network.rx_bytes_per_sec.to_string(),
+            network_tx_bytes_per_sec: network.tx_bytes_per_sec.to_string(),
+            disk_read_bytes_per_sec: disk.read_bytes_per_sec.to_string(),
+            disk_write_bytes_per_sec: disk.write_bytes_per_sec.to_string(),
+            process_cpu_percent: process.cpu_usage_percent,
+            process_memory_bytes: process.memory_bytes.to_string(),
+            process_uptime_seconds: process.uptime_seconds as i32,
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+/// Fail unless the caller holds at least `role` on the active project
+/// Passive checks directory of the active project
+async fn passive_checks_dir(ctx: &Context<'_>) -> async_graphql::Result<std::path::PathBuf> {
+    ctx.data::<Arc<Database>>()?
+        .passive_checks_dir()
+        .await
+        .ok_or_else(|| async_graphql::Error::new("No active project loaded"))
+}
+
+/// Hook scripts directory of the active project
+/// Connected agents as the attack engine sees them
+fn attack_agents(registry: &crate::AgentRegistry) -> Vec<attack_engine::AgentInfo> {
+    registry
+        .list_agents()
+        .into_iter()
+        .map(|agent| attack_engine::AgentInfo {
+            id: agent.id,
+            hostname: agent.hostname,
+            status: attack_engine::AgentStatus::Online,
+            load: (agent.cpu_usage as f64 / 100.0).clamp(0.0, 1.0),
+            response_time_ms: None,
+        })
+        .collect()
+}
+
+async fn hook_scripts_dir(ctx: &Context<'_>) -> async_graphql::Result<std::path::PathBuf> {
+    ctx.data::<Arc<Database>>()?
+        .hook_scripts_dir()
+        .await
+        .ok_or_else(|| async_graphql::Error::new("No active project loaded"))
+}
+
+async fn require_project_role(ctx: &Context<'_>, role: ProjectRole) -> async_graphql::Result<()> {
+    if ctx.data_opt::<Caller>() == Some(&Caller::Admin) {
+        return Ok(());
+    }
+    let db = ctx.data::<Arc<Database>>()?;
+    let members = db
+        .list_project_members()
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+    check_access(&members, ctx.data_opt::<Principal>(), role).map_err(|e| {
+        async_graphql::Error::new(e.to_string()).extend_with(|_, ext| ext.set("code", "FORBIDDEN"))
+    })
+}
+
+/// API key management is for the admin token holder; with authentication
+/// off, anyone may prepare keys before turning it on
+fn require_admin(ctx: &Context<'_>) -> async_graphql::Result<Arc<AuthService>> {
+    let auth = ctx.data::<Arc<AuthService>>()?;
+    if auth.enabled() && ctx.data_opt::<Caller>() != Some(&Caller::Admin) {
+        return Err(async_graphql::Error::new("The admin token is required for this operation")
+            .extend_with(|_, ext| ext.set("code", "FORBIDDEN")));
+    }
+    Ok(auth.clone())
+}
+
+/// Refuse active tooling outside the engagement windows or allowlist
+async fn authorize_target(ctx: &Context<'_>, tool: ActiveTool, url: &str) -> async_graphql::Result<()> {
+    let db = ctx.data::<Arc<Database>>()?;
+    crate::engagement::authorize_target(db, tool, url).await.map_err(|e| match e {
+        attack_engine::AttackError::SecurityViolation { violation_type, details } => {
+            let code = if violation_type == "outside_engagement_window" {
+                "OUTSIDE_ENGAGEMENT_WINDOW"
+            } else {
+                "TARGET_NOT_ALLOWED"
+            };
+            async_graphql::Error::new(details).extend_with(|_, ext| ext.set("code", code))
+        }
+        other => async_graphql::Error::new(other.to_string()),
+    })
+}
+
+/// Upload template from a captured multipart request or from JSON, with the
+/// input's positions added
+async fn upload_template(ctx: &Context<'_>, input: UploadTemplateInput) -> async_graphql::Result<UploadTemplate> {
+    let mut template = match (input.request_id, input.template) {
+        (Some(request_id), _) => {
+            let db = ctx.data::<Arc<Database>>()?;
+            let tx = db
+                .get_full_transaction_by_id(&request_id)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .ok_or_else(|| async_graphql::Error::new(format!("Transaction {} not found", request_id)))?;
+            let content_type = tx
+                .request
+                .headers
+                .as_ref()
+                .and_then(|h| h.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case("content-type")))
+                .map(|(_, v)| v.as_str())
+                .unwrap_or("");
+            crate::multipart::parse(content_type, &tx.request.body)
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+                .upload_template()
+        }
+        (None, Some(json)) => serde_json::from_str::<UploadTemplate>(&json)
+            .map_err(|e| async_graphql::Error::new(format!("Invalid upload template JSON: {}", e)))?,
+        (None, None) => return Err(async_graphql::Error::new("Upload needs a request_id or a template")),
+    };
+
+    for position in input.positions {
+        template.positions.push(UploadPosition {
+            part: usize::try_from(position.part)
+                .map_err(|_| async_graphql::Error::new("Upload part index must not be negative"))?,
+            field: position.field.into(),
+            payload_set_id: position.payload_set_id,
+        });
+    }
+    Ok(template)
+}
+
+/// GraphQL error for a failed settings save; conflicts carry the current version
+fn settings_error(e: SettingsError) -> async_graphql::Error {
+    match &e {
+        SettingsError::VersionConflict { current, .. } => {
+            let current = *current as i64;
+            async_graphql::Error::new(e.to_string()).extend_with(|_, ext| {
+                ext.set("code", "VERSION_CONFLICT");
+                ext.set("currentVersion", current);
+            })
+        }
+        SettingsError::Database(_) => async_graphql::Error::new(e.to_string()),
+    }
+}
+
+/// Convert an optional GraphQL settings version
+fn parse_expected_version(version: Option<i64>) -> async_graphql::Result<Option<u64>> {
+    version
+        .map(|v| u64::try_from(v).map_err(|_| async_graphql::Error::new("expectedVersion must not be negative")))
+        .transpose()
+}
+
+/// Read-modify-write the interception config. With `expected_version` the edit is
+/// rejected if anyone saved since the caller read it; without, it applies to the
+/// latest copy but still fails rather than overwrite a concurrent save. The saved
+/// config is pushed to connected agents.
+async fn edit_interception_config<R>(
+    ctx: &Context<'_>,
+    expected_version: Option<u64>,
+    edit: impl FnOnce(&mut InterceptionConfig) -> R,
+) -> async_graphql::Result<(InterceptionConfig, R)> {
+    let db = ctx.data::<Arc<Database>>()?;
+    let mut config = db.get_interception_config().await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+    let read_version = config.version;
+    if let Some(expected) = expected_version {
+        if expected != read_version {
+            return Err(settings_error(SettingsError::VersionConflict {
+                key: "interception".to_string(),
+                expected,
+                current: read_version,
+            }));
+        }
+    }
+
+    let result = edit(&mut config);
+    let saved = db.save_interception_config(&config, read_version).await
+        .map_err(settings_error)?;
+
+    // Agents and the copy handed to newly connecting agents follow the saved config
+    ctx.data::<Arc<InterceptQueue>>()?.push_rules(&saved).await;
+    *ctx.data::<Arc<tokio::sync::RwLock<InterceptionConfig>>>()?.write().await = saved.clone();
+    Ok((saved, result))
+}
+
+/// Load a flow profile, apply `edit` to it and persist the resulting steps
+async fn edit_flow_steps<F>(
+    db: &Database,
+    profile_id: &str,
+    edit: F,
+) -> async_graphql::Result<flow_graphql::FlowOperationResult>
+where
+    F: FnOnce(&mut flow_engine::FlowProfile) -> flow_engine::error::FlowResult<String>,
+{
+    let row = db
+        .get_flow_profile(profile_id)
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?
+        .ok_or_else(|| async_graphql::Error::new("Flow profile not found"))?;
+    let mut profile = flow_profile_for_replay(&row)?;
+
+    let message = edit(&mut profile).map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+    let steps = serde_json::to_string(&profile.steps)?;
+    let updated = db
+        .update_flow_profile(profile_id, None, Some(&steps), None, None)
+        .await
+        .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+
+    Ok(flow_graphql::FlowOperationResult {
+        success: updated,
+        message,
+        profile_id: Some(profile_id.to_string()),
+    })
+}
+
+/// Convert a GraphQL step index
+fn step_index(index: i32) -> flow_engine::error::FlowResult<usize> {
+    usize::try_from(index).map_err(|_| {
+        flow_engine::FlowEngineError::InvalidStep(format!("index {} out of range", index))
+    })
+}
+
+/// Convert a stored flow profile into the flow-engine model
+fn flow_profile_for_replay(
+    profile: &crate::database::flow::FlowProfileRow,
+) -> async_graphql::Result<flow_engine::FlowProfile> {
+    let fe_profile = profile.to_flow_profile().map_err(|e| {
+        tracing::error!("Failed to parse steps JSON for profile {}: {}", profile.id, e);
+        async_graphql::Error::new(format!("Failed to parse steps: {}", e))
+    })?;
+
+    tracing::info!("Parsed {} steps for replay", fe_profile.steps.len());
+    Ok(fe_profile)
+}
+
+/// OPTIMIZATION: Efficient body conversion
+/// - Reference slice (&[u8]) kullanarak gereksiz clone'ları önler
+/// - Declared charset (Content-Type, meta, BOM) ile UTF-8'e çevrilir
+/// - Binary data için base64 fallback
+#[inline]
+fn convert_body_to_string(headers: Option<&std::collections::HashMap<String, String>>, body: &[u8]) -> String {
+    match crate::charset::decode_body(headers, body) {
+        Some(decoded) => decoded.text.into_owned(),
+        None => base64::engine::general_purpose::STANDARD.encode(body),
+    }
+}
+
+/// Charset a body is shown in, None for binary bodies
+fn body_charset(headers: Option<&crate::pb::HttpHeaders>, body: &[u8]) -> Option<String> {
+    if body.is_empty() {
+        return None;
+    }
+    crate::charset::decode_body(headers.map(|h| &h.headers), body).map(|decoded| decoded.charset.to_string())
+}
+
+// ============================================================================
+// PERFORMANCE NOTES
+// ============================================================================
+//
+// LAZY LOADING PATTERN BENEFITS:
+//
+// 1. **Memory Savings:**
+//    - Body/headers sadece istendiğinde parse edilir
+//    - Çoğu query sadece metadata ister (method, url, status)
+//    - %60-70 daha az memory kullanımı
+//
+// 2. **CPU Savings:**
+//    - JSON serialization sadece gerektiğinde
+//    - Base64 encoding sadece gerektiğinde
+//    - %50-60 daha az CPU kullanımı
+//
+// 3. **Network Savings:**
+//    - İstemci sadece ihtiyacı olanı alır
+//    - GraphQL query'de belirtilmeyen alanlar hesaplanmaz
+//    - %40-50 daha az network trafiği
+//
+// EXAMPLE QUERIES:
+//
+// // Hafif query (sadece metadata)
+// query {
+//   requests {
+//     requestId
+//     method
+//     url
+//     status
+//   }
+// }
+// -> Body/headers ASLA parse edilmez!
+//
+// // Ağır query (tüm data)
+// query {
+//   requests {
+//     requestId
+//     method
+//     url
+//     requestBody      # Sadece burada parse edilir
+//     requestHeaders   # Sadece burada parse edilir
+//   }
+// }
+//
+
+// ============================================================================
+// SETTINGS GQL TYPES
+// ============================================================================
+
+#[derive(SimpleObject)]
+pub struct ProjectSettingsGql {
+    pub scope: ScopeConfigGql,
+    pub interception: InterceptionConfigGql,
+}
+
+#[derive(SimpleObject)]
+pub struct ScopeConfigGql {
+    pub enabled: bool,
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    pub use_regex: bool,
+    /// Pass back as `expectedVersion` when saving
+    pub version: i64,
+}
+
+impl From<ScopeConfig> for ScopeConfigGql {
+    fn from(c: ScopeConfig) -> Self {
+        Self {
+            enabled: c.enabled,
+            include_patterns: c.include_patterns,
+            exclude_patterns: c.exclude_patterns,
+            use_regex: c.use_regex,
+            version: c.version as i64,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ScopeRuleGql {
+    pub id: String,
+    pub rule_type: String,
+    pub pattern: String,
+    pub is_regex: bool,
+    pub enabled: bool,
+    pub created_at: i64,
+}
+
+impl From<crate::database::ScopeRule> for ScopeRuleGql {
+    fn from(r: crate::database::ScopeRule) -> Self {
+        Self {
+            id: r.id,
+            rule_type: r.rule_type,
+            pattern: r.pattern,
+            is_regex: r.is_regex,
+            enabled: r.enabled,
+            created_at: r.created_at,
+        }
+    }
+}
+
+#[derive(async_graphql::InputObject)]
+pub struct ScopeInputGql {
+    pub enabled: bool,
+    pub include_patterns: Vec<String>,
+    pub exclude_patterns: Vec<String>,
+    pub use_regex: bool,
+    /// Version the edit is based on; the save fails if the scope changed since
+    pub expected_version: Option<i64>,
+}
+
+impl ScopeInputGql {
+    pub fn to_scope_config(self) -> ScopeConfig {
+        ScopeConfig {
+            enabled: self.enabled,
+            include_patterns: self.include_patterns,
+            exclude_patterns: self.exclude_patterns,
+            use_regex: self.use_regex,
+            version: 0,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DnsModeGql {
+    System,
+    Udp,
+    Tls,
+    Https,
+}
+
+impl From<proxy_core::DnsMode> for DnsModeGql {
+    fn from(mode: proxy_core::DnsMode) -> Self {
+        match mode {
+            proxy_core::DnsMode::System => DnsModeGql::System,
+            proxy_core::DnsMode::Udp => DnsModeGql::Udp,
+            proxy_core::DnsMode::Tls => DnsModeGql::Tls,
+            proxy_core::DnsMode::Https => DnsModeGql::Https,
+        }
+    }
+}
+
+impl From<DnsModeGql> for proxy_core::DnsMode {
+    fn from(mode: DnsModeGql) -> Self {
+        match mode {
+            DnsModeGql::System => proxy_core::DnsMode::System,
+            DnsModeGql::Udp => proxy_core::DnsMode::Udp,
+            DnsModeGql::Tls => proxy_core::DnsMode::Tls,
+            DnsModeGql::Https => proxy_core::DnsMode::Https,
+        }
+    }
 }
 
-// ============================================================================
-// TRAFFIC EVENT GQL (LAZY LOADING PATTERN)
-// ============================================================================
+#[derive(SimpleObject)]
+pub struct DnsConfigGql {
+    pub mode: DnsModeGql,
+    pub servers: Vec<String>,
+    pub tls_name: Option<String>,
+}
+
+impl From<proxy_core::DnsConfig> for DnsConfigGql {
+    fn from(c: proxy_core::DnsConfig) -> Self {
+        Self {
+            mode: c.mode.into(),
+            servers: c.servers,
+            tls_name: c.tls_name,
+        }
+    }
+}
+
+#[derive(async_graphql::InputObject)]
+pub struct DnsConfigInputGql {
+    pub mode: DnsModeGql,
+    #[graphql(default)]
+    pub servers: Vec<String>,
+    pub tls_name: Option<String>,
+}
+
+impl DnsConfigInputGql {
+    pub fn to_dns_config(self) -> proxy_core::DnsConfig {
+        proxy_core::DnsConfig {
+            mode: self.mode.into(),
+            servers: self.servers.into_iter().filter(|s| !s.trim().is_empty()).collect(),
+            tls_name: self.tls_name.filter(|n| !n.trim().is_empty()),
+        }
+    }
+}
 
-/// OPTIMIZATION: Lazy loading pattern
-/// - Hafif veriler (id, method, url) hemen yüklenir
-/// - Ağır veriler (body, headers) sadece istendiğinde parse edilir
 #[derive(SimpleObject)]
-#[graphql(complex)] // ComplexObject ile ek resolver'lar ekleyeceğiz
-pub struct TrafficEventGql {
-    pub request_id: String,
-    pub method: Option<String>,
-    pub url: Option<String>,
-    pub status: Option<i32>,
-    pub timestamp: Option<String>,
-    pub agent_id: Option<String>,
+pub struct HostOverrideGql {
+    pub host: String,
+    pub address: String,
+}
 
-    // OPTIMIZATION: Ağır veriyi sakla ama GraphQL şemasına ekleme
-    #[graphql(skip)]
-    pub inner_event: TrafficEvent,
-    
-    // Response event for full transaction view (also skipped from direct access)
-    #[graphql(skip)]
-    pub response_event: Option<TrafficEvent>,
+impl HostOverrideGql {
+    /// Overrides sorted by host
+    fn list(overrides: std::collections::HashMap<String, std::net::IpAddr>) -> Vec<Self> {
+        overrides
+            .into_iter()
+            .collect::<std::collections::BTreeMap<_, _>>()
+            .into_iter()
+            .map(|(host, ip)| Self { host, address: ip.to_string() })
+            .collect()
+    }
 }
 
-/// ComplexObject: Ağır veriler sadece istendiğinde hesaplanır
-/// İstemci bu alanları query'de belirtmezse, ASLA çalışmaz!
-#[ComplexObject]
-impl TrafficEventGql {
-    /// Request body - sadece istendiğinde parse edilir
-    async fn request_body(&self) -> Option<String> {
-        if let Some(traffic_event::Event::Request(req)) = &self.inner_event.event {
-            if req.body.is_empty() {
-                return None;
-            }
-            return Some(convert_body_to_string(&req.body));
+#[derive(async_graphql::InputObject)]
+pub struct HostOverrideInputGql {
+    /// Host name, e.g. `shop.example.com`
+    pub host: String,
+    /// IPv4 or IPv6 address the host resolves to
+    pub address: String,
+}
+
+impl HostOverrideInputGql {
+    fn parse(self) -> Result<(String, std::net::IpAddr), String> {
+        let host = proxy_core::override_key(&self.host);
+        if host.is_empty() || host.contains(|c: char| c.is_whitespace() || matches!(c, '/' | ':' | '*')) {
+            return Err(format!("Invalid host name: {}", self.host));
         }
-        None
+        let ip = self.address.trim().parse()
+            .map_err(|_| format!("Invalid IP address for {}: {}", host, self.address))?;
+        Ok((host, ip))
     }
+}
 
-    /// Request headers - sadece istendiğinde JSON'a çevrilir
-    async fn request_headers(&self) -> Option<String> {
-        if let Some(traffic_event::Event::Request(req)) = &self.inner_event.event {
-            return req
-                .headers
-                .as_ref()
-                .and_then(|h| serde_json::to_string(&h.headers).ok());
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq)]
+pub enum ClientCertificateFormatGql {
+    Pem,
+    Pkcs12,
+}
+
+/// Client certificate without its private key
+#[derive(SimpleObject)]
+pub struct ClientCertificateGql {
+    pub host_pattern: String,
+    pub format: ClientCertificateFormatGql,
+    /// SHA-256 of the client certificate, unset if it no longer parses
+    pub fingerprint: Option<String>,
+    /// Intermediates sent along with the client certificate
+    pub intermediates: i32,
+}
+
+impl From<&proxy_core::ClientCertificateConfig> for ClientCertificateGql {
+    fn from(config: &proxy_core::ClientCertificateConfig) -> Self {
+        let format = match config.source {
+            proxy_core::ClientCertificateSource::Pem { .. } => ClientCertificateFormatGql::Pem,
+            proxy_core::ClientCertificateSource::Pkcs12 { .. } => ClientCertificateFormatGql::Pkcs12,
+        };
+        let loaded = proxy_core::ClientCertificate::load(config).ok();
+        Self {
+            host_pattern: config.host_pattern.clone(),
+            format,
+            fingerprint: loaded.as_ref().map(|cert| crate::agent_tls::cert_fingerprint(&cert.chain()[0].0)),
+            intermediates: loaded.map_or(0, |cert| cert.chain().len() as i32 - 1),
         }
-        None
     }
+}
 
-    /// Response body - sadece istendiğinde parse edilir
-    async fn response_body(&self) -> Option<String> {
-        // First check response_event (used for full transaction view)
-        if let Some(ref response_event) = self.response_event {
-            if let Some(traffic_event::Event::Response(res)) = &response_event.event {
-                if !res.body.is_empty() {
-                    return Some(convert_body_to_string(&res.body));
-                }
-            }
+/// Either `cert_pem` and `key_pem`, or `pkcs12_base64` with its password
+#[derive(async_graphql::InputObject)]
+pub struct ClientCertificateInputGql {
+    /// Host pattern, `*` wildcards (e.g. `*.bank.example`)
+    pub host_pattern: String,
+    /// Client certificate, then any intermediates
+    pub cert_pem: Option<String>,
+    pub key_pem: Option<String>,
+    pub pkcs12_base64: Option<String>,
+    pub password: Option<String>,
+}
+
+impl ClientCertificateInputGql {
+    fn into_config(self) -> Result<proxy_core::ClientCertificateConfig, String> {
+        let source = match (self.cert_pem, self.key_pem, self.pkcs12_base64) {
+            (Some(cert_pem), Some(key_pem), None) => proxy_core::ClientCertificateSource::Pem { cert_pem, key_pem },
+            (None, None, Some(pkcs12)) => proxy_core::ClientCertificateSource::Pkcs12 {
+                der: base64::engine::general_purpose::STANDARD
+                    .decode(pkcs12.trim())
+                    .map_err(|e| format!("Invalid base64 PKCS#12 archive: {}", e))?,
+                password: self.password.unwrap_or_default(),
+            },
+            _ => return Err("Provide either certPem and keyPem, or pkcs12Base64".to_string()),
+        };
+        let config = proxy_core::ClientCertificateConfig {
+            host_pattern: self.host_pattern.trim().to_string(),
+            source,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TlsPolicyGql {
+    pub host_pattern: String,
+    /// "1.2" or "1.3"
+    pub min_version: Option<String>,
+    pub max_version: Option<String>,
+    pub skip_verification: bool,
+    pub extra_roots_pem: Option<String>,
+    /// "chrome", "firefox" or "safari"
+    pub client_hello: Option<String>,
+}
+
+impl From<&proxy_core::TlsPolicyConfig> for TlsPolicyGql {
+    fn from(config: &proxy_core::TlsPolicyConfig) -> Self {
+        Self {
+            host_pattern: config.host_pattern.clone(),
+            min_version: config.min_version.map(|v| v.to_string()),
+            max_version: config.max_version.map(|v| v.to_string()),
+            skip_verification: config.skip_verification,
+            extra_roots_pem: config.extra_roots_pem.clone(),
+            client_hello: config.client_hello.map(|profile| profile.to_string()),
         }
-        // Fallback to inner_event for subscription events
-        if let Some(traffic_event::Event::Response(res)) = &self.inner_event.event {
-            if res.body.is_empty() {
-                return None;
-            }
-            return Some(convert_body_to_string(&res.body));
+    }
+}
+
+#[derive(async_graphql::InputObject)]
+pub struct TlsPolicyInputGql {
+    /// Host pattern, `*` wildcards (e.g. `*.corp.example`)
+    pub host_pattern: String,
+    /// "1.2" or "1.3"; TLS 1.0 and 1.1 are not available
+    pub min_version: Option<String>,
+    pub max_version: Option<String>,
+    #[graphql(default)]
+    pub skip_verification: bool,
+    /// PEM root certificates trusted in addition to the public roots
+    pub extra_roots_pem: Option<String>,
+    /// Browser whose ClientHello to imitate: "chrome", "firefox" or "safari"
+    pub client_hello: Option<String>,
+}
+
+impl TlsPolicyInputGql {
+    fn into_config(self) -> Result<proxy_core::TlsPolicyConfig, String> {
+        let version = |value: Option<String>| {
+            value
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| v.parse::<proxy_core::TlsVersion>().map_err(|e| e.to_string()))
+                .transpose()
+        };
+        let config = proxy_core::TlsPolicyConfig {
+            host_pattern: self.host_pattern.trim().to_string(),
+            min_version: version(self.min_version)?,
+            max_version: version(self.max_version)?,
+            skip_verification: self.skip_verification,
+            extra_roots_pem: self.extra_roots_pem.filter(|pem| !pem.trim().is_empty()),
+            client_hello: self
+                .client_hello
+                .filter(|profile| !profile.trim().is_empty())
+                .map(|profile| profile.parse::<proxy_core::ClientHelloProfile>().map_err(|e| e.to_string()))
+                .transpose()?,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct InterceptionConfigGql {
+    pub enabled: bool,
+    pub rules: Vec<InterceptionRuleGql>,
+    /// Pass back as `expectedVersion` when editing
+    pub version: i64,
+}
+
+impl From<InterceptionConfig> for InterceptionConfigGql {
+    fn from(c: InterceptionConfig) -> Self {
+        Self {
+            enabled: c.enabled,
+            rules: c.rules.into_iter().map(InterceptionRuleGql::from).collect(),
+            version: c.version as i64,
         }
-        None
     }
+}
 
-    /// Response headers - sadece istendiğinde JSON'a çevrilir
-    async fn response_headers(&self) -> Option<String> {
-        // First check response_event (used for full transaction view)
-        if let Some(ref response_event) = self.response_event {
-            if let Some(traffic_event::Event::Response(res)) = &response_event.event {
-                return res
-                    .headers
-                    .as_ref()
-                    .and_then(|h| serde_json::to_string(&h.headers).ok());
-            }
+#[derive(SimpleObject)]
+pub struct ColoringConfigGql {
+    pub rules: Vec<ColoringRuleGql>,
+    /// Pass back as `expectedVersion` when editing
+    pub version: i64,
+}
+
+impl From<ColoringConfig> for ColoringConfigGql {
+    fn from(c: ColoringConfig) -> Self {
+        Self {
+            rules: c.rules.into_iter().map(ColoringRuleGql::from).collect(),
+            version: c.version as i64,
         }
-        // Fallback to inner_event for subscription events
-        if let Some(traffic_event::Event::Response(res)) = &self.inner_event.event {
-            return res
-                .headers
-                .as_ref()
-                .and_then(|h| serde_json::to_string(&h.headers).ok());
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct ColoringRuleGql {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub conditions: Vec<ColoringConditionGql>,
+    pub color: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl From<ColoringRule> for ColoringRuleGql {
+    fn from(r: ColoringRule) -> Self {
+        Self {
+            id: r.id,
+            name: r.name,
+            enabled: r.enabled,
+            conditions: r.conditions.into_iter().map(ColoringConditionGql::from).collect(),
+            color: r.color,
+            tags: r.tags,
         }
-        None
     }
 }
 
-/// OPTIMIZATION: From implementation artık çok hafif
-/// Sadece metadata parse ediliyor, body/headers atlanıyor
-impl From<TrafficEvent> for TrafficEventGql {
-    fn from(e: TrafficEvent) -> Self {
-        let mut method = None;
-        let mut url = None;
-        let mut status = None;
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ColoringConditionKindGql {
+    StatusAtLeast,
+    StatusIn,
+    UrlContains,
+    Method,
+    ResponseContains,
+    HeaderMatch,
+}
 
-        // OPTIMIZATION: TrafficEvent proto'sunda timestamp yok, current time kullan
-        // TODO: Proto'ya timestamp field'ı eklenebilir
-        let timestamp = Some(chrono::Utc::now().to_rfc3339());
+/// A coloring condition. Lists (statuses, methods) are comma separated in
+/// `value`; `header` is only used by header matches.
+#[derive(SimpleObject)]
+pub struct ColoringConditionGql {
+    pub kind: ColoringConditionKindGql,
+    pub value: String,
+    pub header: Option<String>,
+}
 
-        // OPTIMIZATION: Sadece metadata extract et, body/headers'ı atla
-        match &e.event {
-            Some(traffic_event::Event::Request(req)) => {
-                method = Some(req.method.clone());
-                url = Some(req.url.clone());
-            }
-            Some(traffic_event::Event::Response(res)) => {
-                status = Some(res.status_code);
-            }
-            _ => {}
+impl From<ColoringCondition> for ColoringConditionGql {
+    fn from(c: ColoringCondition) -> Self {
+        let join = |items: Vec<String>| items.join(",");
+        let (kind, value, header) = match c {
+            ColoringCondition::StatusAtLeast { status } => (ColoringConditionKindGql::StatusAtLeast, status.to_string(), None),
+            ColoringCondition::StatusIn { statuses } => (
+                ColoringConditionKindGql::StatusIn,
+                join(statuses.iter().map(|s| s.to_string()).collect()),
+                None,
+            ),
+            ColoringCondition::UrlContains { pattern } => (ColoringConditionKindGql::UrlContains, pattern, None),
+            ColoringCondition::Method { methods } => (ColoringConditionKindGql::Method, join(methods), None),
+            ColoringCondition::ResponseContains { text } => (ColoringConditionKindGql::ResponseContains, text, None),
+            ColoringCondition::HeaderMatch { header, value } => (ColoringConditionKindGql::HeaderMatch, value, Some(header)),
+        };
+        Self { kind, value, header }
+    }
+}
+
+#[derive(async_graphql::InputObject)]
+pub struct ColoringConditionInputGql {
+    pub kind: ColoringConditionKindGql,
+    pub value: String,
+    pub header: Option<String>,
+}
+
+impl ColoringConditionInputGql {
+    pub fn to_coloring_condition(self) -> async_graphql::Result<ColoringCondition> {
+        let parse_status = |s: &str| {
+            s.trim()
+                .parse::<i32>()
+                .map_err(|_| async_graphql::Error::new(format!("Invalid status code '{}'", s.trim())))
+        };
+        let list = |value: &str| -> Vec<String> {
+            value.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect()
+        };
+        Ok(match self.kind {
+            ColoringConditionKindGql::StatusAtLeast => ColoringCondition::StatusAtLeast {
+                status: parse_status(&self.value)?,
+            },
+            ColoringConditionKindGql::StatusIn => ColoringCondition::StatusIn {
+                statuses: list(&self.value).iter().map(|s| parse_status(s)).collect::<Result<_, _>>()?,
+            },
+            ColoringConditionKindGql::UrlContains => ColoringCondition::UrlContains { pattern: self.value },
+            ColoringConditionKindGql::Method => ColoringCondition::Method { methods: list(&self.value) },
+            ColoringConditionKindGql::ResponseContains => ColoringCondition::ResponseContains { text: self.value },
+            ColoringConditionKindGql::HeaderMatch => ColoringCondition::HeaderMatch {
+                header: self
+                    .header
+                    .filter(|h| !h.trim().is_empty())
+                    .ok_or_else(|| async_graphql::Error::new("Header matches need a header name"))?,
+                value: self.value,
+            },
+        })
+    }
+}
+
+#[derive(async_graphql::InputObject)]
+pub struct ColoringRuleInputGql {
+    /// Keeps the id of an existing rule; new rules get one
+    pub id: Option<String>,
+    pub name: String,
+    pub enabled: bool,
+    pub conditions: Vec<ColoringConditionInputGql>,
+    pub color: Option<String>,
+    #[graphql(default)]
+    pub tags: Vec<String>,
+}
+
+impl ColoringRuleInputGql {
+    pub fn to_coloring_rule(self) -> async_graphql::Result<ColoringRule> {
+        Ok(ColoringRule {
+            id: self.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name: self.name,
+            enabled: self.enabled,
+            conditions: self
+                .conditions
+                .into_iter()
+                .map(ColoringConditionInputGql::to_coloring_condition)
+                .collect::<async_graphql::Result<_>>()?,
+            color: self.color.map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty()),
+            tags: self.tags,
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct DefaultAgentConfigGql {
+    pub agent_id: Option<String>,
+    pub fallback_agents: Vec<String>,
+    pub fallback_to_any: bool,
+    /// Agent executions without an explicit one would run on now
+    pub resolved_agent_id: Option<String>,
+    /// Pass back as `expectedVersion` when editing
+    pub version: i64,
+}
+
+impl DefaultAgentConfigGql {
+    fn new(config: DefaultAgentConfig, registry: &crate::AgentRegistry) -> Self {
+        let online: Vec<String> = registry
+            .list_agents()
+            .into_iter()
+            .filter(|agent| agent.status == "Online")
+            .map(|agent| agent.id)
+            .collect();
+        Self {
+            resolved_agent_id: config.resolve(&online),
+            agent_id: config.agent_id,
+            fallback_agents: config.fallback_agents,
+            fallback_to_any: config.fallback_to_any,
+            version: config.version as i64,
         }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct SessionMacroConfigGql {
+    pub macros: Vec<SessionMacroGql>,
+    /// Pass back as `expectedVersion` when editing
+    pub version: i64,
+}
 
+impl From<SessionMacroConfig> for SessionMacroConfigGql {
+    fn from(c: SessionMacroConfig) -> Self {
         Self {
-            request_id: e.request_id.clone(),
-            method,
-            url,
-            status,
-            timestamp,
-            agent_id: None, // TrafficEvent proto'sunda agent_id yok, database'den alınmalı
-            // CRITICAL: Tüm event'i sakla, lazy loading için
-            inner_event: e,
-            response_event: None, // Will be set manually for full transaction view
+            macros: c.macros.into_iter().map(SessionMacroGql::from).collect(),
+            version: c.version as i64,
         }
     }
 }
 
-// ============================================================================
-// AGENT GQL
-// ============================================================================
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum MacroToolGql {
+    Repeater,
+    Intruder,
+}
+
+impl From<MacroTool> for MacroToolGql {
+    fn from(tool: MacroTool) -> Self {
+        match tool {
+            MacroTool::Repeater => MacroToolGql::Repeater,
+            MacroTool::Intruder => MacroToolGql::Intruder,
+        }
+    }
+}
+
+impl From<MacroToolGql> for MacroTool {
+    fn from(tool: MacroToolGql) -> Self {
+        match tool {
+            MacroToolGql::Repeater => MacroTool::Repeater,
+            MacroToolGql::Intruder => MacroTool::Intruder,
+        }
+    }
+}
 
 #[derive(SimpleObject)]
-pub struct ProjectGql {
+pub struct SessionMacroGql {
+    pub id: String,
     pub name: String,
-    pub path: String,
-    pub size_bytes: i64,
-    pub last_modified: String,
-    pub is_active: bool,
+    pub enabled: bool,
+    pub tools: Vec<MacroToolGql>,
+    pub url_contains: Option<String>,
+    pub steps: Vec<MacroStepGql>,
 }
 
-impl From<crate::database::Project> for ProjectGql {
-    fn from(p: crate::database::Project) -> Self {
+impl From<SessionMacro> for SessionMacroGql {
+    fn from(m: SessionMacro) -> Self {
         Self {
-            name: p.name,
-            path: p.path,
-            size_bytes: p.size_bytes,
-            last_modified: p.last_modified,
-            is_active: p.is_active,
+            id: m.id,
+            name: m.name,
+            enabled: m.enabled,
+            tools: m.tools.into_iter().map(MacroToolGql::from).collect(),
+            url_contains: m.url_contains,
+            steps: m.steps.into_iter().map(MacroStepGql::from).collect(),
         }
     }
 }
 
 #[derive(SimpleObject)]
-pub struct ProjectOperationResult {
-    pub success: bool,
-    pub message: String,
+pub struct MacroStepGql {
+    pub request: HttpRequestTemplateGql,
+    pub extractions: Vec<TokenExtractionGql>,
+}
+
+impl From<MacroStep> for MacroStepGql {
+    fn from(step: MacroStep) -> Self {
+        Self {
+            request: HttpRequestTemplateGql::from(step.request),
+            extractions: step.extractions.into_iter().map(TokenExtractionGql::from).collect(),
+        }
+    }
 }
 
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ExtractionKindGql {
+    Header,
+    Cookie,
+    BodyRegex,
+    JsonPath,
+}
+
+/// A value extracted from a macro step's response. `value` is the header
+/// name, cookie name, body regex or JSONPath, depending on `kind`.
 #[derive(SimpleObject)]
-pub struct AgentGql {
-    pub id: String,
+pub struct TokenExtractionGql {
     pub name: String,
-    pub hostname: String,
-    pub status: String,
-    pub version: String,
-    pub last_heartbeat: String,
-    pub cpu_usage: f32,
-    pub memory_usage_mb: f64,
-    pub uptime_seconds: u64,
-    pub public_ip: String,
+    pub kind: ExtractionKindGql,
+    pub value: String,
 }
 
-// ============================================================================
-// REPLAY RESULT
-// ============================================================================
+impl From<TokenExtraction> for TokenExtractionGql {
+    fn from(e: TokenExtraction) -> Self {
+        let (kind, value) = match e.source {
+            ExtractionSource::Header { header } => (ExtractionKindGql::Header, header),
+            ExtractionSource::Cookie { cookie } => (ExtractionKindGql::Cookie, cookie),
+            ExtractionSource::BodyRegex { pattern } => (ExtractionKindGql::BodyRegex, pattern),
+            ExtractionSource::JsonPath { path } => (ExtractionKindGql::JsonPath, path),
+        };
+        Self { name: e.name, kind, value }
+    }
+}
 
-#[derive(SimpleObject)]
-pub struct ReplayResult {
-    pub success: bool,
-    pub message: String,
-    pub replay_request_id: Option<String>,
-    pub original_url: String,
-    pub original_method: String,
+#[derive(async_graphql::InputObject)]
+pub struct TokenExtractionInputGql {
+    pub name: String,
+    pub kind: ExtractionKindGql,
+    pub value: String,
 }
 
-// ============================================================================
-// SYSTEM METRICS GQL
-// ============================================================================
+impl From<TokenExtractionInputGql> for TokenExtraction {
+    fn from(input: TokenExtractionInputGql) -> Self {
+        let source = match input.kind {
+            ExtractionKindGql::Header => ExtractionSource::Header { header: input.value },
+            ExtractionKindGql::Cookie => ExtractionSource::Cookie { cookie: input.value },
+            ExtractionKindGql::BodyRegex => ExtractionSource::BodyRegex { pattern: input.value },
+            ExtractionKindGql::JsonPath => ExtractionSource::JsonPath { path: input.value },
+        };
+        Self { name: input.name.trim().to_string(), source }
+    }
+}
 
-#[derive(SimpleObject)]
-pub struct SystemMetricsGql {
-    pub agent_id: String,
-    pub timestamp: i64,
-    pub cpu_usage_percent: f32,
-    pub memory_used_bytes: String,
-    pub memory_total_bytes: String,
-    pub network_rx_bytes_per_sec: String,
-    pub network_tx_bytes_per_sec: String,
-    pub disk_read_bytes_per_sec: String,
-    pub disk_write_bytes_per_sec: String,
-    pub process_cpu_percent: f32,
-    pub process_memory_bytes: String,
-    pub process_uptime_seconds: i32,
+#[derive(async_graphql::InputObject)]
+pub struct MacroStepInputGql {
+    pub request: HttpRequestTemplateInput,
+    #[graphql(default)]
+    pub extractions: Vec<TokenExtractionInputGql>,
 }
 
-impl From<SystemMetricsEvent> for SystemMetricsGql {
-    fn from(event: SystemMetricsEvent) -> Self {
-        let metrics = event.metrics.unwrap_or_default();
-        let network = metrics.network.unwrap_or_default();
-        let disk = metrics.disk.unwrap_or_default();
-        let process = metrics.process.unwrap_or_default();
+#[derive(async_graphql::InputObject)]
+pub struct SessionMacroInputGql {
+    /// Keeps the id of an existing macro; new macros get one
+    pub id: Option<String>,
+    pub name: String,
+    pub enabled: bool,
+    pub tools: Vec<MacroToolGql>,
+    pub url_contains: Option<String>,
+    pub steps: Vec<MacroStepInputGql>,
+}
 
-        Self {
-            agent_id: event.agent_id,
-            timestamp: event.timestamp,
-            cpu_usage_percent: metrics.cpu_usage_percent,
-            memory_used_bytes: metrics.memory_used_bytes.to_string(),
-            memory_total_bytes: metrics.memory_total_bytes.to_string(),
-            network_rx_bytes_per_sec: network.rx_bytes_per_sec.to_string(),
-            network_tx_bytes_per_sec: network.tx_bytes_per_sec.to_string(),
-            disk_read_bytes_per_sec: disk.read_bytes_per_sec.to_string(),
-            disk_write_bytes_per_sec: disk.write_bytes_per_sec.to_string(),
-            process_cpu_percent: process.cpu_usage_percent,
-            process_memory_bytes: process.memory_bytes.to_string(),
-            process_uptime_seconds: process.uptime_seconds as i32,
-        }
+impl SessionMacroInputGql {
+    pub fn to_session_macro(self) -> async_graphql::Result<SessionMacro> {
+        Ok(SessionMacro {
+            id: self.id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+            name: self.name,
+            enabled: self.enabled,
+            tools: self.tools.into_iter().map(MacroTool::from).collect(),
+            url_contains: self.url_contains.filter(|u| !u.trim().is_empty()),
+            steps: self
+                .steps
+                .into_iter()
+                .map(|step| -> async_graphql::Result<MacroStep> {
+                    Ok(MacroStep {
+                        request: step.request.try_into()?,
+                        extractions: step.extractions.into_iter().map(TokenExtraction::from).collect(),
+                    })
+                })
+                .collect::<async_graphql::Result<_>>()?,
+        })
     }
 }
 
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ProjectRoleGql {
+    Viewer,
+    Editor,
+    Owner,
+}
 
-/// OPTIMIZATION: Efficient body conversion
-/// - Reference slice (&[u8]) kullanarak gereksiz clone'ları önler
-/// - UTF-8 önce denenir (zero-copy for valid UTF-8)
-/// - Binary data için base64 fallback
-#[inline]
-fn convert_body_to_string(body: &[u8]) -> String {
-    match std::str::from_utf8(body) {
-        Ok(s) => s.to_string(),
-        Err(_) => base64::engine::general_purpose::STANDARD.encode(body),
+impl From<ProjectRoleGql> for ProjectRole {
+    fn from(role: ProjectRoleGql) -> Self {
+        match role {
+            ProjectRoleGql::Viewer => ProjectRole::Viewer,
+            ProjectRoleGql::Editor => ProjectRole::Editor,
+            ProjectRoleGql::Owner => ProjectRole::Owner,
+        }
     }
 }
 
-// ============================================================================
-// PERFORMANCE NOTES
-// ============================================================================
-//
-// LAZY LOADING PATTERN BENEFITS:
-//
-// 1. **Memory Savings:**
-//    - Body/headers sadece istendiğinde parse edilir
-//    - Çoğu query sadece metadata ister (method, url, status)
-//    - %60-70 daha az memory kullanımı
-//
-// 2. **CPU Savings:**
-//    - JSON serialization sadece gerektiğinde
-//    - Base64 encoding sadece gerektiğinde
-//    - %50-60 daha az CPU kullanımı
-//
-// 3. **Network Savings:**
-//    - İstemci sadece ihtiyacı olanı alır
-//    - GraphQL query'de belirtilmeyen alanlar hesaplanmaz
-//    - %40-50 daha az network trafiği
-//
-// EXAMPLE QUERIES:
-//
-// // Hafif query (sadece metadata)
-// query {
-//   requests {
-//     requestId
-//     method
-//     url
-//     status
-//   }
-// }
-// -> Body/headers ASLA parse edilmez!
-//
-// // Ağır query (tüm data)
-// query {
-//   requests {
-//     requestId
-//     method
-//     url
-//     requestBody      # Sadece burada parse edilir
-//     requestHeaders   # Sadece burada parse edilir
-//   }
-// }
-//
-
-// ============================================================================
-// SETTINGS GQL TYPES
-// ============================================================================
+impl From<ProjectRole> for ProjectRoleGql {
+    fn from(role: ProjectRole) -> Self {
+        match role {
+            ProjectRole::Viewer => ProjectRoleGql::Viewer,
+            ProjectRole::Editor => ProjectRoleGql::Editor,
+            ProjectRole::Owner => ProjectRoleGql::Owner,
+        }
+    }
+}
 
 #[derive(SimpleObject)]
-pub struct ProjectSettingsGql {
-    pub scope: ScopeConfigGql,
-    pub interception: InterceptionConfigGql,
+pub struct ProjectMemberGql {
+    pub principal: String,
+    pub role: ProjectRoleGql,
+    pub added_at: i64,
+}
+
+impl From<ProjectMember> for ProjectMemberGql {
+    fn from(m: ProjectMember) -> Self {
+        Self {
+            principal: m.principal,
+            role: m.role.into(),
+            added_at: m.added_at,
+        }
+    }
 }
 
 #[derive(SimpleObject)]
-pub struct ScopeConfigGql {
+pub struct TargetAllowlistGql {
     pub enabled: bool,
-    pub include_patterns: Vec<String>,
-    pub exclude_patterns: Vec<String>,
-    pub use_regex: bool,
+    pub hosts: Vec<String>,
+    pub version: i64,
 }
 
-impl From<ScopeConfig> for ScopeConfigGql {
-    fn from(c: ScopeConfig) -> Self {
+impl From<TargetAllowlist> for TargetAllowlistGql {
+    fn from(a: TargetAllowlist) -> Self {
         Self {
-            enabled: c.enabled,
-            include_patterns: c.include_patterns,
-            exclude_patterns: c.exclude_patterns,
-            use_regex: c.use_regex,
+            enabled: a.enabled,
+            hosts: a.hosts,
+            version: a.version as i64,
         }
     }
 }
 
 #[derive(SimpleObject)]
-pub struct ScopeRuleGql {
-    pub id: String,
-    pub rule_type: String,
-    pub pattern: String,
-    pub is_regex: bool,
+pub struct EngagementWindowGql {
+    /// Weekdays ("Mon".."Sun")
+    pub days: Vec<String>,
+    /// Local start time, "HH:MM"
+    pub start: String,
+    /// Local end time, "HH:MM"; at or before `start` means the window runs past midnight
+    pub end: String,
+}
+
+#[derive(SimpleObject)]
+pub struct EngagementScheduleGql {
     pub enabled: bool,
-    pub created_at: i64,
+    pub timezone: String,
+    /// First day, "YYYY-MM-DD"
+    pub start_date: Option<String>,
+    /// Last day, "YYYY-MM-DD"
+    pub end_date: Option<String>,
+    pub windows: Vec<EngagementWindowGql>,
+    pub version: i64,
+    /// Whether active tooling may run right now
+    pub open_now: bool,
 }
 
-impl From<crate::database::ScopeRule> for ScopeRuleGql {
-    fn from(r: crate::database::ScopeRule) -> Self {
+impl From<EngagementSchedule> for EngagementScheduleGql {
+    fn from(s: EngagementSchedule) -> Self {
+        let open_now = s.is_open(chrono::Utc::now());
         Self {
-            id: r.id,
-            rule_type: r.rule_type,
-            pattern: r.pattern,
-            is_regex: r.is_regex,
-            enabled: r.enabled,
-            created_at: r.created_at,
+            enabled: s.enabled,
+            timezone: s.timezone,
+            start_date: s.start_date.map(|d| d.to_string()),
+            end_date: s.end_date.map(|d| d.to_string()),
+            windows: s
+                .windows
+                .into_iter()
+                .map(|w| EngagementWindowGql {
+                    days: w.days.iter().map(|d| d.to_string()).collect(),
+                    start: w.start.format("%H:%M").to_string(),
+                    end: w.end.format("%H:%M").to_string(),
+                })
+                .collect(),
+            version: s.version as i64,
+            open_now,
         }
     }
 }
 
-#[derive(async_graphql::InputObject)]
-pub struct ScopeInputGql {
+#[derive(InputObject)]
+pub struct EngagementWindowInput {
+    pub days: Vec<String>,
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(InputObject)]
+pub struct EngagementScheduleInput {
     pub enabled: bool,
-    pub include_patterns: Vec<String>,
-    pub exclude_patterns: Vec<String>,
-    pub use_regex: bool,
+    /// IANA time zone, e.g. "Europe/Istanbul"
+    pub timezone: String,
+    pub start_date: Option<String>,
+    pub end_date: Option<String>,
+    pub windows: Vec<EngagementWindowInput>,
+    /// Version the edit is based on; the save fails if someone saved since
+    pub expected_version: Option<i64>,
 }
 
-impl ScopeInputGql {
-    pub fn to_scope_config(self) -> ScopeConfig {
-        ScopeConfig {
+impl EngagementScheduleInput {
+    fn to_schedule(&self) -> async_graphql::Result<EngagementSchedule> {
+        let timezone = self.timezone.trim().to_string();
+        if timezone.parse::<chrono_tz::Tz>().is_err() {
+            return Err(async_graphql::Error::new(format!("Unknown time zone: {}", timezone)));
+        }
+
+        let date = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|d| {
+                    chrono::NaiveDate::parse_from_str(d.trim(), "%Y-%m-%d")
+                        .map_err(|_| async_graphql::Error::new(format!("Invalid date (expected YYYY-MM-DD): {}", d)))
+                })
+                .transpose()
+        };
+        let start_date = date(&self.start_date)?;
+        let end_date = date(&self.end_date)?;
+        if let (Some(start), Some(end)) = (start_date, end_date) {
+            if end < start {
+                return Err(async_graphql::Error::new("The engagement cannot end before it starts"));
+            }
+        }
+
+        let time = |value: &str| {
+            chrono::NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                .map_err(|_| async_graphql::Error::new(format!("Invalid time (expected HH:MM): {}", value)))
+        };
+        let windows = self
+            .windows
+            .iter()
+            .map(|w| {
+                let days = w
+                    .days
+                    .iter()
+                    .map(|d| {
+                        d.trim()
+                            .parse::<chrono::Weekday>()
+                            .map_err(|_| async_graphql::Error::new(format!("Invalid weekday: {}", d)))
+                    })
+                    .collect::<async_graphql::Result<Vec<_>>>()?;
+                if days.is_empty() {
+                    return Err(async_graphql::Error::new("Each window needs at least one weekday"));
+                }
+                Ok(EngagementWindow {
+                    days,
+                    start: time(&w.start)?,
+                    end: time(&w.end)?,
+                })
+            })
+            .collect::<async_graphql::Result<Vec<_>>>()?;
+
+        Ok(EngagementSchedule {
             enabled: self.enabled,
-            include_patterns: self.include_patterns,
-            exclude_patterns: self.exclude_patterns,
-            use_regex: self.use_regex,
+            timezone,
+            start_date,
+            end_date,
+            windows,
+            version: 0,
+        })
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct AllowlistOverrideGql {
+    pub id: String,
+    pub host_pattern: String,
+    pub reason: String,
+    pub granted_by: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+    pub active: bool,
+}
+
+impl From<AllowlistOverride> for AllowlistOverrideGql {
+    fn from(o: AllowlistOverride) -> Self {
+        let active = o.is_active(chrono::Utc::now().timestamp());
+        Self {
+            id: o.id,
+            host_pattern: o.host_pattern,
+            reason: o.reason,
+            granted_by: o.granted_by,
+            created_at: o.created_at,
+            expires_at: o.expires_at,
+            revoked_at: o.revoked_at,
+            active,
         }
     }
 }
 
 #[derive(SimpleObject)]
-pub struct InterceptionConfigGql {
-    pub enabled: bool,
-    pub rules: Vec<InterceptionRuleGql>,
+pub struct EngagementAuditEntryGql {
+    pub id: i64,
+    pub timestamp: i64,
+    pub event: String,
+    pub tool: Option<String>,
+    pub target: Option<String>,
+    pub principal: Option<String>,
+    pub details: Option<String>,
 }
 
-impl From<InterceptionConfig> for InterceptionConfigGql {
-    fn from(c: InterceptionConfig) -> Self {
+impl From<EngagementAuditEntry> for EngagementAuditEntryGql {
+    fn from(e: EngagementAuditEntry) -> Self {
         Self {
-            enabled: c.enabled,
-            rules: c.rules.into_iter().map(InterceptionRuleGql::from).collect(),
+            id: e.id,
+            timestamp: e.timestamp,
+            event: e.event,
+            tool: e.tool,
+            target: e.target,
+            principal: e.principal,
+            details: e.details,
         }
     }
 }
@@ -2291,13 +7346,105 @@ impl InterceptionRuleInputGql {
             _ => RuleAction::Pause,
         };
 
-        InterceptionRule {
-            id: uuid::Uuid::new_v4().to_string(),
-            enabled: self.enabled,
-            name: self.name,
-            condition,
-            action,
+        InterceptionRule {
+            id: uuid::Uuid::new_v4().to_string(),
+            enabled: self.enabled,
+            name: self.name,
+            condition,
+            action,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct InterceptionRuleStatsGql {
+    pub rule_id: String,
+    pub name: String,
+    /// False when agents do not evaluate the rule (disabled, or a modify rule)
+    pub active: bool,
+    pub evaluations: u64,
+    pub hits: u64,
+    pub last_hit: Option<String>,
+    pub avg_eval_micros: f64,
+    pub total_eval_micros: f64,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum InterceptStatusGql {
+    Pending,
+    Forwarded,
+    Dropped,
+    Expired,
+}
+
+impl From<InterceptStatus> for InterceptStatusGql {
+    fn from(status: InterceptStatus) -> Self {
+        match status {
+            InterceptStatus::Pending => Self::Pending,
+            InterceptStatus::Forwarded => Self::Forwarded,
+            InterceptStatus::Dropped => Self::Dropped,
+            InterceptStatus::Expired => Self::Expired,
+        }
+    }
+}
+
+/// Request held by an agent's pause rule
+#[derive(SimpleObject, Clone)]
+pub struct InterceptedRequestGql {
+    pub request_id: String,
+    pub agent_id: String,
+    pub status: InterceptStatusGql,
+    pub method: String,
+    pub url: String,
+    /// JSON object of header name to value
+    pub headers: Option<String>,
+    pub body: Option<String>,
+    pub received_at: i64,
+}
+
+impl InterceptedRequestGql {
+    fn new(intercept: InterceptedRequest, status: InterceptStatus) -> Self {
+        let request = intercept.request;
+        Self {
+            request_id: intercept.request_id,
+            agent_id: intercept.agent_id,
+            status: status.into(),
+            method: request.method,
+            url: request.url,
+            headers: request.headers.as_ref().and_then(|h| serde_json::to_string(&h.headers).ok()),
+            body: (!request.body.is_empty()).then(|| convert_body_to_string(request.headers.as_ref().map(|h| &h.headers), &request.body)),
+            received_at: intercept.received_at,
+        }
+    }
+}
+
+/// Edits to a held request; omitted fields keep their original value
+#[derive(InputObject)]
+pub struct InterceptedRequestEditInput {
+    pub method: Option<String>,
+    pub url: Option<String>,
+    /// JSON object of header name to value, replacing all headers
+    pub headers: Option<String>,
+    pub body: Option<String>,
+}
+
+impl InterceptedRequestEditInput {
+    fn apply(self, mut request: crate::pb::HttpRequestData) -> async_graphql::Result<crate::pb::HttpRequestData> {
+        if let Some(method) = self.method {
+            request.method = method;
+        }
+        if let Some(url) = self.url {
+            request.url = url;
+        }
+        if let Some(headers) = self.headers {
+            let headers = serde_json::from_str::<std::collections::HashMap<String, String>>(&headers)
+                .map_err(|e| async_graphql::Error::new(format!("Invalid headers JSON: {}", e)))?;
+            request.headers = Some(crate::pb::HttpHeaders { headers });
         }
+        if let Some(body) = self.body {
+            request.body = body.into_bytes().into();
+        }
+        Ok(request)
     }
 }
 
@@ -2363,6 +7510,8 @@ pub struct RepeaterExecutionGql {
     pub status_code: Option<i32>,
     pub executed_at: String,
     pub error: Option<String>,
+    /// Redirects followed before the response, in order
+    pub redirect_chain: Vec<RedirectHopGql>,
 
     // Store original data for lazy loading
     #[graphql(skip)]
@@ -2394,12 +7543,34 @@ impl From<RepeaterExecutionResponse> for RepeaterExecutionGql {
             status_code: execution.status_code,
             executed_at: execution.executed_at.to_rfc3339(),
             error: execution.error,
+            redirect_chain: execution.redirect_chain.into_iter().map(RedirectHopGql::from).collect(),
             request_data: execution.request_data,
             response_data: execution.response_data,
         }
     }
 }
 
+#[derive(SimpleObject, Clone)]
+pub struct RedirectHopGql {
+    pub method: String,
+    pub url: String,
+    pub status_code: i32,
+    pub location: String,
+    pub set_cookies: Vec<String>,
+}
+
+impl From<RedirectHop> for RedirectHopGql {
+    fn from(hop: RedirectHop) -> Self {
+        Self {
+            method: hop.method,
+            url: hop.url,
+            status_code: hop.status_code,
+            location: hop.location,
+            set_cookies: hop.set_cookies,
+        }
+    }
+}
+
 /// GraphQL type for HTTP request template
 #[derive(SimpleObject)]
 #[graphql(complex)]
@@ -2419,13 +7590,24 @@ impl HttpRequestTemplateGql {
     async fn headers(&self) -> Option<String> {
         self.headers.as_ref().and_then(|h| serde_json::to_string(h).ok())
     }
+
+    /// The request as raw HTTP/1.1 text, for the raw editor
+    async fn raw(&self) -> Option<String> {
+        let request = HttpRequestData {
+            method: self.method.clone(),
+            url: self.url.clone(),
+            headers: self.headers.clone().map(|headers| attack_engine::HttpHeaders { headers }),
+            body: self.body.clone().into_bytes(),
+            tls: None,
+        };
+        let raw = attack_engine::RawRequest::from_request_data(&request).ok()?;
+        Some(String::from_utf8_lossy(&raw.to_bytes()).into_owned())
+    }
 }
 
 impl From<HttpRequestData> for HttpRequestTemplateGql {
     fn from(request: HttpRequestData) -> Self {
-        let body = String::from_utf8(request.body.clone()).unwrap_or_else(|_| {
-            base64::engine::general_purpose::STANDARD.encode(&request.body)
-        });
+        let body = convert_body_to_string(request.headers.as_ref().map(|h| &h.headers), &request.body);
 
         let headers = request.headers.map(|h| h.headers);
 
@@ -2445,93 +7627,820 @@ pub struct HttpResponseDataGql {
     pub status_code: i32,
     pub body: String,
     pub body_length: i32,
+    /// Phases timed by the agent that sent the request
+    pub timing: Option<ResponseTimingGql>,
+    /// Protocol spoken with the origin ("HTTP/1.1", "HTTP/2"), if known
+    pub protocol: Option<String>,
 
     // Store headers for lazy loading
     #[graphql(skip)]
     pub headers: Option<std::collections::HashMap<String, String>>,
 }
 
-#[ComplexObject]
-impl HttpResponseDataGql {
-    /// Headers - loaded only when requested
-    async fn headers(&self) -> Option<String> {
-        self.headers.as_ref().and_then(|h| serde_json::to_string(h).ok())
+#[ComplexObject]
+impl HttpResponseDataGql {
+    /// Headers - loaded only when requested
+    async fn headers(&self) -> Option<String> {
+        self.headers.as_ref().and_then(|h| serde_json::to_string(h).ok())
+    }
+}
+
+impl From<HttpResponseData> for HttpResponseDataGql {
+    fn from(response: HttpResponseData) -> Self {
+        let body = convert_body_to_string(response.headers.as_ref().map(|h| &h.headers), &response.body);
+
+        let headers = response.headers.map(|h| h.headers);
+
+        Self {
+            status_code: response.status_code,
+            body,
+            body_length: response.body.len() as i32,
+            timing: response.timing.map(ResponseTimingGql::from),
+            protocol: response.protocol,
+            headers,
+        }
+    }
+}
+
+/// Request phases in microseconds of the agent's monotonic clock
+#[derive(SimpleObject, Clone, Copy)]
+pub struct ResponseTimingGql {
+    /// Opening a new connection; 0 when one was reused or it was not timed apart
+    pub connect_us: i64,
+    /// Connection ready to response headers: the server's share
+    pub ttfb_us: i64,
+    /// Response headers to the end of the body
+    pub transfer_us: i64,
+    pub total_us: i64,
+}
+
+impl From<attack_engine::ResponseTiming> for ResponseTimingGql {
+    fn from(t: attack_engine::ResponseTiming) -> Self {
+        Self {
+            connect_us: t.connect_us as i64,
+            ttfb_us: t.ttfb_us as i64,
+            transfer_us: t.transfer_us as i64,
+            total_us: t.total_us as i64,
+        }
+    }
+}
+
+impl From<&crate::pb::RequestTiming> for ResponseTimingGql {
+    fn from(t: &crate::pb::RequestTiming) -> Self {
+        Self {
+            connect_us: t.connect_us as i64,
+            ttfb_us: t.ttfb_us as i64,
+            transfer_us: t.transfer_us as i64,
+            total_us: t.total_us as i64,
+        }
+    }
+}
+
+// ============================================================================
+// REPEATER INPUT TYPES
+// ============================================================================
+
+/// Input for creating a new repeater tab
+#[derive(InputObject)]
+pub struct CreateRepeaterTabInput {
+    pub name: String,
+    pub request_template: HttpRequestTemplateInput,
+    pub target_agent_id: Option<String>,
+}
+
+/// Input for updating a repeater tab
+#[derive(InputObject)]
+pub struct UpdateRepeaterTabInput {
+    pub name: Option<String>,
+    pub request_template: Option<HttpRequestTemplateInput>,
+    pub target_agent_id: Option<String>,
+}
+
+/// Input for executing a repeater request
+#[derive(InputObject)]
+pub struct ExecuteRepeaterRequestInput {
+    pub tab_id: String,
+    pub request_data: HttpRequestTemplateInput,
+    /// Defaults to the tab's agent, then the project's default agent
+    pub target_agent_id: Option<String>,
+    pub session_id: Option<String>,
+    pub expiration_handling: Option<ExpirationHandlingInput>,
+    pub options: Option<RepeaterExecutionOptionsInput>,
+}
+
+/// Per-execution options of a repeater request
+#[derive(InputObject, Default)]
+pub struct RepeaterExecutionOptionsInput {
+    /// Follow up to this many redirects (capped at 20)
+    pub follow_redirects: Option<i32>,
+    /// Send cookies set by redirects with the following hops
+    pub carry_cookies: Option<bool>,
+    pub strip_headers: Option<Vec<String>>,
+    pub override_headers: Option<String>, // JSON object of headers
+}
+
+impl TryFrom<RepeaterExecutionOptionsInput> for RepeaterExecutionOptions {
+    type Error = async_graphql::Error;
+
+    fn try_from(input: RepeaterExecutionOptionsInput) -> Result<Self, Self::Error> {
+        let follow_redirects = u32::try_from(input.follow_redirects.unwrap_or(0))
+            .map_err(|_| async_graphql::Error::new("followRedirects must not be negative"))?;
+        let override_headers = input
+            .override_headers
+            .map(|json| {
+                serde_json::from_str::<std::collections::HashMap<String, String>>(&json)
+                    .map_err(|e| async_graphql::Error::new(format!("Invalid overrideHeaders: {}", e)))
+            })
+            .transpose()?
+            .unwrap_or_default();
+        Ok(Self {
+            follow_redirects,
+            carry_cookies: input.carry_cookies.unwrap_or(false),
+            strip_headers: input.strip_headers.unwrap_or_default(),
+            override_headers,
+        })
+    }
+}
+
+/// Input for starting a content discovery run
+#[derive(InputObject)]
+pub struct StartDiscoveryInput {
+    /// Directory the words are appended to
+    pub base_url: String,
+    /// Paths to try, one per entry; `#` comments are skipped
+    pub wordlist: Vec<String>,
+    /// Extensions tried after each word, e.g. `php` or `bak`
+    #[graphql(default)]
+    pub extensions: Vec<String>,
+    /// JSON object of headers sent with every request, e.g. a session cookie
+    pub headers: Option<String>,
+    #[graphql(default)]
+    pub recursive: bool,
+    /// Directory levels below the base URL searched when recursive (2 by default)
+    pub max_depth: Option<i32>,
+    /// Similarity (0 to 1) to a directory's baseline from which a response
+    /// is a soft-404 (0.9 by default)
+    pub similarity_threshold: Option<f64>,
+    /// Status codes that are never findings (404 by default)
+    pub ignored_status_codes: Option<Vec<i32>>,
+    /// Agents the requests are spread over; the default agent when empty
+    #[graphql(default)]
+    pub agents: Vec<String>,
+    /// Requests in flight at once (10 by default)
+    pub concurrency: Option<i32>,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DiscoveryStatusGql {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl From<DiscoveryStatus> for DiscoveryStatusGql {
+    fn from(status: DiscoveryStatus) -> Self {
+        match status {
+            DiscoveryStatus::Running => DiscoveryStatusGql::Running,
+            DiscoveryStatus::Completed => DiscoveryStatusGql::Completed,
+            DiscoveryStatus::Cancelled => DiscoveryStatusGql::Cancelled,
+            DiscoveryStatus::Failed => DiscoveryStatusGql::Failed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct DiscoveryRunGql {
+    pub id: String,
+    pub base_url: String,
+    pub agents: Vec<String>,
+    pub status: DiscoveryStatusGql,
+    pub directories_queued: i32,
+    pub directories_searched: i32,
+    pub requests_planned: i32,
+    pub requests_sent: i32,
+    pub findings: i32,
+    pub soft_404s: i32,
+    pub failed_requests: i32,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+impl From<DiscoveryRun> for DiscoveryRunGql {
+    fn from(run: DiscoveryRun) -> Self {
+        Self {
+            id: run.id,
+            base_url: run.base_url,
+            agents: run.agents,
+            status: run.status.into(),
+            directories_queued: run.directories_queued as i32,
+            directories_searched: run.directories_searched as i32,
+            requests_planned: run.requests_planned as i32,
+            requests_sent: run.requests_sent as i32,
+            findings: run.findings as i32,
+            soft_404s: run.soft_404s as i32,
+            failed_requests: run.failed_requests as i32,
+            error: run.error,
+            started_at: run.started_at.to_rfc3339(),
+            finished_at: run.finished_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct DiscoveryResultGql {
+    pub id: String,
+    pub run_id: String,
+    pub url: String,
+    /// Relative to the run's base URL
+    pub path: String,
+    pub status_code: i32,
+    /// Response body length in bytes
+    pub size: i64,
+    pub title: Option<String>,
+    pub is_directory: bool,
+    pub depth: i32,
+    pub agent_id: String,
+    pub created_at: i64,
+}
+
+impl From<crate::database::DiscoveryResultRow> for DiscoveryResultGql {
+    fn from(row: crate::database::DiscoveryResultRow) -> Self {
+        Self {
+            id: row.id,
+            run_id: row.run_id,
+            url: row.url,
+            path: row.path,
+            status_code: row.status_code,
+            size: row.size,
+            title: row.title,
+            is_directory: row.is_directory,
+            depth: row.depth as i32,
+            agent_id: row.agent_id,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Input for starting a Sequencer run
+#[derive(InputObject)]
+pub struct StartSequencerInput {
+    pub request: HttpRequestTemplateInput,
+    /// Defaults to the project's default agent
+    pub agent_id: Option<String>,
+    /// Regex over the response headers (`Name: value` lines) and body; the
+    /// first capture group is the token
+    pub extraction: String,
+    /// Tokens to collect (20 to 20000)
+    pub capture_count: i32,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SequencerStatusGql {
+    Collecting,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl From<SequencerStatus> for SequencerStatusGql {
+    fn from(status: SequencerStatus) -> Self {
+        match status {
+            SequencerStatus::Collecting => SequencerStatusGql::Collecting,
+            SequencerStatus::Completed => SequencerStatusGql::Completed,
+            SequencerStatus::Cancelled => SequencerStatusGql::Cancelled,
+            SequencerStatus::Failed => SequencerStatusGql::Failed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct SequencerRunGql {
+    pub id: String,
+    pub status: SequencerStatusGql,
+    pub requested: i32,
+    pub collected: i32,
+    /// Requests that failed or had no token
+    pub misses: i32,
+    pub error: Option<String>,
+    pub started_at: String,
+    /// Last tokens collected, newest last
+    pub latest_tokens: Vec<String>,
+    pub report: Option<RandomnessReportGql>,
+}
+
+impl From<SequencerRun> for SequencerRunGql {
+    fn from(run: SequencerRun) -> Self {
+        Self {
+            status: run.status.into(),
+            requested: run.requested as i32,
+            collected: run.tokens.len() as i32,
+            misses: run.misses as i32,
+            started_at: run.started_at.to_rfc3339(),
+            latest_tokens: run.tokens[run.tokens.len().saturating_sub(10)..].to_vec(),
+            report: run.report.map(RandomnessReportGql::from),
+            error: run.error,
+            id: run.id,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum RandomnessQualityGql {
+    ExtremelyPoor,
+    Poor,
+    Reasonable,
+    Good,
+    Excellent,
+}
+
+impl From<RandomnessQuality> for RandomnessQualityGql {
+    fn from(quality: RandomnessQuality) -> Self {
+        match quality {
+            RandomnessQuality::ExtremelyPoor => RandomnessQualityGql::ExtremelyPoor,
+            RandomnessQuality::Poor => RandomnessQualityGql::Poor,
+            RandomnessQuality::Reasonable => RandomnessQualityGql::Reasonable,
+            RandomnessQuality::Good => RandomnessQualityGql::Good,
+            RandomnessQuality::Excellent => RandomnessQualityGql::Excellent,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct CharacterPositionGql {
+    pub position: i32,
+    pub distinct_characters: i32,
+    pub entropy_bits: f64,
+    pub chi_square: f64,
+    pub p_value: f64,
+    pub passed: bool,
+}
+
+#[derive(SimpleObject)]
+pub struct BitPositionGql {
+    pub character: i32,
+    pub bit: i32,
+    pub ones_ratio: f64,
+    pub p_value: f64,
+    pub passed: bool,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FipsTestGql {
+    Monobit,
+    Poker,
+    Runs,
+    LongRuns,
+}
+
+impl From<FipsTest> for FipsTestGql {
+    fn from(test: FipsTest) -> Self {
+        match test {
+            FipsTest::Monobit => FipsTestGql::Monobit,
+            FipsTest::Poker => FipsTestGql::Poker,
+            FipsTest::Runs => FipsTestGql::Runs,
+            FipsTest::LongRuns => FipsTestGql::LongRuns,
+        }
+    }
+}
+
+/// A FIPS 140-2 test over the 20,000-bit blocks of the token bits
+#[derive(SimpleObject)]
+pub struct FipsTestResultGql {
+    pub test: FipsTestGql,
+    pub blocks: i32,
+    pub blocks_passed: i32,
+    /// Every block passed; false without a full block
+    pub passed: bool,
+}
+
+#[derive(SimpleObject)]
+pub struct RandomnessReportGql {
+    pub sample_size: i32,
+    pub distinct_tokens: i32,
+    pub min_length: i32,
+    pub max_length: i32,
+    pub character_set: String,
+    pub shannon_entropy_bits: f64,
+    pub effective_entropy_bits: i32,
+    pub quality: RandomnessQualityGql,
+    pub character_positions: Vec<CharacterPositionGql>,
+    pub bit_positions: Vec<BitPositionGql>,
+    pub bit_stream_length: i32,
+    pub fips_tests: Vec<FipsTestResultGql>,
+    pub warnings: Vec<String>,
+}
+
+impl From<RandomnessReport> for RandomnessReportGql {
+    fn from(report: RandomnessReport) -> Self {
+        Self {
+            sample_size: report.sample_size as i32,
+            distinct_tokens: report.distinct_tokens as i32,
+            min_length: report.min_length as i32,
+            max_length: report.max_length as i32,
+            character_set: report.character_set,
+            shannon_entropy_bits: report.shannon_entropy_bits,
+            effective_entropy_bits: report.effective_entropy_bits as i32,
+            quality: report.quality.into(),
+            character_positions: report
+                .character_positions
+                .into_iter()
+                .map(|p| CharacterPositionGql {
+                    position: p.position as i32,
+                    distinct_characters: p.distinct_characters as i32,
+                    entropy_bits: p.entropy_bits,
+                    chi_square: p.chi_square,
+                    p_value: p.p_value,
+                    passed: p.passed,
+                })
+                .collect(),
+            bit_positions: report
+                .bit_positions
+                .into_iter()
+                .map(|b| BitPositionGql {
+                    character: b.character as i32,
+                    bit: b.bit as i32,
+                    ones_ratio: b.ones_ratio,
+                    p_value: b.p_value,
+                    passed: b.passed,
+                })
+                .collect(),
+            bit_stream_length: report.bit_stream_length as i32,
+            fips_tests: report
+                .fips_tests
+                .into_iter()
+                .map(|t| FipsTestResultGql {
+                    passed: t.passed(),
+                    test: t.test.into(),
+                    blocks: t.blocks as i32,
+                    blocks_passed: t.blocks_passed as i32,
+                })
+                .collect(),
+            warnings: report.warnings,
+        }
+    }
+}
+
+/// Input for starting an authorization matrix run
+#[derive(InputObject)]
+pub struct StartAccessMatrixInput {
+    /// Captured requests to replay
+    pub request_ids: Vec<String>,
+    /// Stored sessions from most to least privileged; the first is the
+    /// reference
+    pub session_ids: Vec<String>,
+    /// Also replay every request without credentials
+    #[graphql(default = true)]
+    pub include_anonymous: bool,
+    /// Defaults to the project's default agent
+    pub agent_id: Option<String>,
+    /// Relative body length difference still counted as the same response
+    /// (default 0.05)
+    pub length_tolerance: Option<f64>,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum AccessMatrixStatusGql {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl From<AccessMatrixStatus> for AccessMatrixStatusGql {
+    fn from(status: AccessMatrixStatus) -> Self {
+        match status {
+            AccessMatrixStatus::Running => AccessMatrixStatusGql::Running,
+            AccessMatrixStatus::Completed => AccessMatrixStatusGql::Completed,
+            AccessMatrixStatus::Cancelled => AccessMatrixStatusGql::Cancelled,
+            AccessMatrixStatus::Failed => AccessMatrixStatusGql::Failed,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CellVerdictGql {
+    Reference,
+    Denied,
+    Similar,
+    Different,
+    Failed,
+}
+
+impl From<CellVerdict> for CellVerdictGql {
+    fn from(verdict: CellVerdict) -> Self {
+        match verdict {
+            CellVerdict::Reference => CellVerdictGql::Reference,
+            CellVerdict::Denied => CellVerdictGql::Denied,
+            CellVerdict::Similar => CellVerdictGql::Similar,
+            CellVerdict::Different => CellVerdictGql::Different,
+            CellVerdict::Failed => CellVerdictGql::Failed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct AccessCellGql {
+    pub identity: String,
+    pub status_code: Option<i32>,
+    pub length: Option<i32>,
+    pub error: Option<String>,
+    pub verdict: CellVerdictGql,
+}
+
+impl From<AccessCell> for AccessCellGql {
+    fn from(cell: AccessCell) -> Self {
+        Self {
+            identity: cell.identity,
+            status_code: cell.status_code,
+            length: cell.length.map(|length| length as i32),
+            error: cell.error,
+            verdict: cell.verdict.into(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct AccessMatrixRowGql {
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    pub endpoint: String,
+    /// A less privileged identity got about what the reference got
+    pub flagged: bool,
+    pub cells: Vec<AccessCellGql>,
+}
+
+impl From<AccessMatrixRow> for AccessMatrixRowGql {
+    fn from(row: AccessMatrixRow) -> Self {
+        Self {
+            flagged: row.flagged(),
+            request_id: row.request_id,
+            method: row.method,
+            url: row.url,
+            endpoint: row.endpoint,
+            cells: row.cells.into_iter().map(AccessCellGql::from).collect(),
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct SkippedRequestGql {
+    pub request_id: String,
+    pub reason: String,
+}
+
+#[derive(SimpleObject)]
+pub struct AccessMatrixRunGql {
+    pub id: String,
+    pub agent_id: String,
+    pub status: AccessMatrixStatusGql,
+    /// Column labels, the reference first
+    pub columns: Vec<String>,
+    pub requested: i32,
+    pub flagged: i32,
+    pub rows: Vec<AccessMatrixRowGql>,
+    pub skipped: Vec<SkippedRequestGql>,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+impl From<AccessMatrixRun> for AccessMatrixRunGql {
+    fn from(run: AccessMatrixRun) -> Self {
+        Self {
+            status: run.status.into(),
+            requested: run.requested as i32,
+            flagged: run.rows.iter().filter(|row| row.flagged()).count() as i32,
+            rows: run.rows.into_iter().map(AccessMatrixRowGql::from).collect(),
+            skipped: run
+                .skipped
+                .into_iter()
+                .map(|skipped| SkippedRequestGql { request_id: skipped.request_id, reason: skipped.reason })
+                .collect(),
+            started_at: run.started_at.to_rfc3339(),
+            finished_at: run.finished_at.map(|time| time.to_rfc3339()),
+            error: run.error,
+            columns: run.columns,
+            agent_id: run.agent_id,
+            id: run.id,
+        }
+    }
+}
+
+/// Input for starting a tamper check run
+#[derive(InputObject)]
+pub struct StartTamperChecksInput {
+    /// Captured requests to probe; without them, the latest request of
+    /// every method and path in the site map
+    pub request_ids: Option<Vec<String>>,
+    /// Site map host to probe; every host if omitted
+    pub host: Option<String>,
+    /// Defaults to the project's default agent
+    pub agent_id: Option<String>,
+    #[graphql(default = true)]
+    pub parameter_pollution: bool,
+    #[graphql(default = true)]
+    pub method_override: bool,
+    #[graphql(default = true)]
+    pub verb_tampering: bool,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TamperRunStatusGql {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+impl From<TamperRunStatus> for TamperRunStatusGql {
+    fn from(status: TamperRunStatus) -> Self {
+        match status {
+            TamperRunStatus::Running => TamperRunStatusGql::Running,
+            TamperRunStatus::Completed => TamperRunStatusGql::Completed,
+            TamperRunStatus::Cancelled => TamperRunStatusGql::Cancelled,
+            TamperRunStatus::Failed => TamperRunStatusGql::Failed,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TamperRunGql {
+    pub id: String,
+    pub agent_id: String,
+    pub status: TamperRunStatusGql,
+    pub parameter_pollution: bool,
+    pub method_override: bool,
+    pub verb_tampering: bool,
+    pub requested: i32,
+    /// Requests whose probes were all sent
+    pub tested: i32,
+    pub probes_sent: i32,
+    pub findings: i32,
+    pub skipped: Vec<SkippedRequestGql>,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+}
+
+impl From<TamperRun> for TamperRunGql {
+    fn from(run: TamperRun) -> Self {
+        Self {
+            status: run.status.into(),
+            parameter_pollution: run.checks.parameter_pollution,
+            method_override: run.checks.method_override,
+            verb_tampering: run.checks.verb_tampering,
+            requested: run.requested as i32,
+            tested: run.tested as i32,
+            probes_sent: run.probes_sent as i32,
+            findings: run.findings as i32,
+            skipped: run
+                .skipped
+                .into_iter()
+                .map(|skipped| SkippedRequestGql { request_id: skipped.request_id, reason: skipped.reason })
+                .collect(),
+            started_at: run.started_at.to_rfc3339(),
+            finished_at: run.finished_at.map(|time| time.to_rfc3339()),
+            error: run.error,
+            agent_id: run.agent_id,
+            id: run.id,
+        }
     }
 }
 
-impl From<HttpResponseData> for HttpResponseDataGql {
-    fn from(response: HttpResponseData) -> Self {
-        let body = String::from_utf8(response.body.clone()).unwrap_or_else(|_| {
-            base64::engine::general_purpose::STANDARD.encode(&response.body)
-        });
-
-        let headers = response.headers.map(|h| h.headers);
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TamperKindGql {
+    ParameterPollution,
+    MethodOverride,
+    VerbTampering,
+}
 
-        Self {
-            status_code: response.status_code,
-            body,
-            body_length: response.body.len() as i32,
-            headers,
+impl From<attack_engine::TamperKind> for TamperKindGql {
+    fn from(kind: attack_engine::TamperKind) -> Self {
+        match kind {
+            attack_engine::TamperKind::ParameterPollution => TamperKindGql::ParameterPollution,
+            attack_engine::TamperKind::MethodOverride => TamperKindGql::MethodOverride,
+            attack_engine::TamperKind::VerbTampering => TamperKindGql::VerbTampering,
         }
     }
 }
 
-// ============================================================================
-// REPEATER INPUT TYPES
-// ============================================================================
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TamperEffectGql {
+    /// The request was denied but the probe got a 2xx
+    AccessBypass,
+    /// PUT got a 2xx
+    VerbAccepted,
+    /// The duplicated parameter's value is in the response
+    MarkerReflected,
+    StatusChanged,
+    ContentChanged,
+}
 
-/// Input for creating a new repeater tab
-#[derive(InputObject)]
-pub struct CreateRepeaterTabInput {
-    pub name: String,
-    pub request_template: HttpRequestTemplateInput,
-    pub target_agent_id: Option<String>,
+impl From<attack_engine::TamperEffect> for TamperEffectGql {
+    fn from(effect: attack_engine::TamperEffect) -> Self {
+        match effect {
+            attack_engine::TamperEffect::AccessBypass => TamperEffectGql::AccessBypass,
+            attack_engine::TamperEffect::VerbAccepted => TamperEffectGql::VerbAccepted,
+            attack_engine::TamperEffect::MarkerReflected => TamperEffectGql::MarkerReflected,
+            attack_engine::TamperEffect::StatusChanged => TamperEffectGql::StatusChanged,
+            attack_engine::TamperEffect::ContentChanged => TamperEffectGql::ContentChanged,
+        }
+    }
 }
 
-/// Input for updating a repeater tab
-#[derive(InputObject)]
-pub struct UpdateRepeaterTabInput {
-    pub name: Option<String>,
-    pub request_template: Option<HttpRequestTemplateInput>,
-    pub target_agent_id: Option<String>,
+#[derive(SimpleObject)]
+pub struct TamperFindingGql {
+    pub id: String,
+    pub run_id: String,
+    pub request_id: String,
+    pub method: String,
+    pub url: String,
+    pub kind: Option<TamperKindGql>,
+    /// What the probe changed, e.g. `GET → PUT`
+    pub probe: String,
+    pub effect: Option<TamperEffectGql>,
+    pub severity: Option<SeverityGql>,
+    pub baseline_status: i32,
+    pub probe_status: i32,
+    pub baseline_length: i64,
+    pub probe_length: i64,
+    pub agent_id: String,
+    pub created_at: String,
 }
 
-/// Input for executing a repeater request
-#[derive(InputObject)]
-pub struct ExecuteRepeaterRequestInput {
-    pub tab_id: String,
-    pub request_data: HttpRequestTemplateInput,
-    pub target_agent_id: String,
-    pub session_id: Option<String>,
-    pub expiration_handling: Option<ExpirationHandlingInput>,
+impl From<crate::database::TamperFindingRow> for TamperFindingGql {
+    fn from(row: crate::database::TamperFindingRow) -> Self {
+        Self {
+            kind: attack_engine::TamperKind::from_tag(&row.kind).map(TamperKindGql::from),
+            effect: attack_engine::TamperEffect::from_tag(&row.effect).map(TamperEffectGql::from),
+            severity: Severity::from_tag(&row.severity).map(SeverityGql::from),
+            created_at: chrono::DateTime::from_timestamp(row.created_at, 0)
+                .map(|d| d.to_rfc3339())
+                .unwrap_or_default(),
+            id: row.id,
+            run_id: row.run_id,
+            request_id: row.request_id,
+            method: row.method,
+            url: row.url,
+            probe: row.probe,
+            baseline_status: row.baseline_status,
+            probe_status: row.probe_status,
+            baseline_length: row.baseline_length,
+            probe_length: row.probe_length,
+            agent_id: row.agent_id,
+        }
+    }
 }
 
-/// Input for HTTP request template
+/// Input for HTTP request template. Either the structured fields or `raw`,
+/// a complete HTTP/1.1 request (request line, headers, body) sent as typed.
 #[derive(InputObject)]
 pub struct HttpRequestTemplateInput {
-    pub method: String,
-    pub url: String,
+    pub method: Option<String>,
+    /// Target URL; with `raw`, the scheme and host used when the raw request
+    /// has no Host header or absolute target
+    pub url: Option<String>,
     pub headers: Option<String>, // JSON string of headers
-    pub body: String,
+    pub body: Option<String>,
+    pub raw: Option<String>,
 }
 
-impl From<HttpRequestTemplateInput> for HttpRequestData {
-    fn from(input: HttpRequestTemplateInput) -> Self {
+impl TryFrom<HttpRequestTemplateInput> for HttpRequestData {
+    type Error = async_graphql::Error;
+
+    fn try_from(input: HttpRequestTemplateInput) -> Result<Self, Self::Error> {
+        if let Some(raw) = input.raw {
+            if input.method.is_some() || input.headers.is_some() || input.body.is_some() {
+                return Err(async_graphql::Error::new(
+                    "raw cannot be combined with method, headers or body",
+                ));
+            }
+            return attack_engine::RawRequest::parse(raw.as_bytes())
+                .and_then(|raw| raw.to_request_data(input.url.as_deref()))
+                .map_err(|e| async_graphql::Error::new(e.to_string()));
+        }
+
+        let (Some(method), Some(url)) = (input.method, input.url) else {
+            return Err(async_graphql::Error::new("method and url are required unless raw is given"));
+        };
         let headers = input.headers.and_then(|h| {
             serde_json::from_str::<std::collections::HashMap<String, String>>(&h)
                 .ok()
                 .map(|headers| attack_engine::HttpHeaders { headers })
         });
 
-        let body = input.body.into_bytes();
+        let body = input.body.unwrap_or_default().into_bytes();
 
-        Self {
-            method: input.method,
-            url: input.url,
+        Ok(Self {
+            method,
+            url,
             headers,
             body,
             tls: None,
-        }
+        })
     }
 }
 
@@ -2551,6 +8460,8 @@ pub struct IntruderAttackGql {
     pub created_at: String,
     pub updated_at: String,
     pub status: String,
+    /// Dry run outcome, only set by `startIntruderAttack(simulate: true)`
+    pub simulation: Option<AttackSimulationGql>,
 
     // Store complex data for lazy loading
     #[graphql(skip)]
@@ -2573,6 +8484,116 @@ impl IntruderAttackGql {
         
         Ok(payload_sets.into_iter().map(PayloadSetConfigGql::from).collect())
     }
+
+    /// Saved progress of a paused attack
+    async fn checkpoint(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<AttackCheckpointGql>> {
+        let checkpoint = ctx
+            .data::<Arc<IntruderManager>>()?
+            .get_attack_checkpoint(&self.id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(checkpoint.map(AttackCheckpointGql::from))
+    }
+
+    /// Typical response of the unmodified request, measured when the attack
+    /// started with baseline requests
+    async fn baseline(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<AttackBaselineGql>> {
+        let baseline = ctx
+            .data::<Arc<IntruderManager>>()?
+            .get_attack_baseline(&self.id)
+            .await
+            .map_err(|e| async_graphql::Error::new(e.to_string()))?;
+        Ok(baseline.map(AttackBaselineGql::from))
+    }
+}
+
+/// Typical response of an attack's unmodified request
+#[derive(SimpleObject)]
+pub struct AttackBaselineGql {
+    /// Baseline requests that got a response
+    pub samples: i32,
+    /// Most frequent status code
+    pub status_code: i32,
+    /// Median body length in bytes
+    pub response_length: i64,
+    /// Median response time
+    pub duration_ms: i64,
+    /// Slowest minus fastest response time
+    pub duration_spread_ms: i64,
+    pub measured_at: String,
+}
+
+impl From<AttackBaseline> for AttackBaselineGql {
+    fn from(baseline: AttackBaseline) -> Self {
+        Self {
+            samples: baseline.samples as i32,
+            status_code: baseline.status_code,
+            response_length: baseline.response_length,
+            duration_ms: baseline.duration_ms,
+            duration_spread_ms: baseline.duration_spread_ms,
+            measured_at: baseline.measured_at.to_rfc3339(),
+        }
+    }
+}
+
+/// How a result differs from its attack's baseline
+#[derive(SimpleObject)]
+pub struct ResultDeltaGql {
+    /// Whether the status differs from the baseline's most frequent one
+    pub status_changed: Option<bool>,
+    /// Body length minus the baseline's, in bytes
+    pub length: Option<i64>,
+    /// Response time minus the baseline's
+    pub duration_ms: Option<i64>,
+}
+
+impl From<ResultDelta> for ResultDeltaGql {
+    fn from(delta: ResultDelta) -> Self {
+        Self {
+            status_changed: delta.status_changed,
+            length: delta.length,
+            duration_ms: delta.duration_ms,
+        }
+    }
+}
+
+/// Progress saved when an attack was paused
+#[derive(SimpleObject)]
+pub struct AttackCheckpointGql {
+    pub completed_requests: i64,
+    pub total_requests: i64,
+    pub paused_at: String,
+    /// Position of each payload assignment
+    pub assignments: Vec<CheckpointAssignmentGql>,
+}
+
+#[derive(SimpleObject)]
+pub struct CheckpointAssignmentGql {
+    pub agent_id: String,
+    pub payloads: i64,
+    /// Requests of the assignment already sent
+    pub sent: i64,
+}
+
+impl From<crate::intruder::execution::AttackCheckpoint> for AttackCheckpointGql {
+    fn from(checkpoint: crate::intruder::execution::AttackCheckpoint) -> Self {
+        Self {
+            completed_requests: checkpoint.completed_requests as i64,
+            total_requests: checkpoint.total_requests as i64,
+            paused_at: checkpoint.paused_at.to_rfc3339(),
+            assignments: checkpoint
+                .distribution
+                .assignments
+                .iter()
+                .enumerate()
+                .map(|(index, assignment)| CheckpointAssignmentGql {
+                    agent_id: assignment.agent_id.clone(),
+                    payloads: assignment.payloads.len() as i64,
+                    sent: checkpoint.cursors.get(index).copied().unwrap_or(0) as i64,
+                })
+                .collect(),
+        }
+    }
 }
 
 impl From<IntruderAttack> for IntruderAttackGql {
@@ -2593,12 +8614,72 @@ impl From<IntruderAttack> for IntruderAttackGql {
                 .unwrap_or_default()
                 .to_rfc3339(),
             status: attack.status,
+            simulation: None,
             request_template: attack.request_template,
             payload_sets_json: attack.payload_sets,
         }
     }
 }
 
+/// GraphQL type for an intruder dry run
+#[derive(SimpleObject)]
+pub struct AttackSimulationGql {
+    /// Requests the attack would send
+    pub total_requests: u64,
+    pub payload_positions: i32,
+    pub agent_count: i32,
+    pub requests_per_second: f64,
+    pub estimated_duration_seconds: f64,
+    /// First generated requests, in sending order
+    pub sample: Vec<SimulatedRequestGql>,
+}
+
+impl From<AttackSimulation> for AttackSimulationGql {
+    fn from(simulation: AttackSimulation) -> Self {
+        Self {
+            total_requests: simulation.total_requests,
+            payload_positions: simulation.payload_positions as i32,
+            agent_count: simulation.agent_count as i32,
+            requests_per_second: simulation.requests_per_second,
+            estimated_duration_seconds: simulation.estimated_duration_seconds,
+            sample: simulation
+                .sample
+                .into_iter()
+                .map(|request| {
+                    let mut payloads: Vec<SimulatedPayloadGql> = request
+                        .payload_values
+                        .into_iter()
+                        .map(|(marker, value)| SimulatedPayloadGql { marker, value })
+                        .collect();
+                    payloads.sort_by(|a, b| a.marker.cmp(&b.marker));
+                    SimulatedRequestGql {
+                        index: request.index as i32,
+                        request: request.request,
+                        upload_body: request.upload_body,
+                        payloads,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// GraphQL type for one request of an intruder dry run
+#[derive(SimpleObject)]
+pub struct SimulatedRequestGql {
+    pub index: i32,
+    pub request: String,
+    pub upload_body: Option<String>,
+    pub payloads: Vec<SimulatedPayloadGql>,
+}
+
+/// Payload placed at a marker of a simulated request
+#[derive(SimpleObject)]
+pub struct SimulatedPayloadGql {
+    pub marker: String,
+    pub value: String,
+}
+
 /// GraphQL type for intruder attack results
 #[derive(SimpleObject, Clone)]
 #[graphql(complex)]
@@ -2611,6 +8692,10 @@ pub struct IntruderResultGql {
     pub status_code: Option<i32>,
     pub response_length: Option<i32>,
     pub is_highlighted: bool,
+    /// Highlight rules the result matched, with what they saw
+    pub highlight_reason: Option<String>,
+    /// Source IP the target saw
+    pub egress_ip: Option<String>,
 
     // Store complex data for lazy loading
     #[graphql(skip)]
@@ -2619,10 +8704,30 @@ pub struct IntruderResultGql {
     pub response_data_json: Option<String>,
     #[graphql(skip)]
     pub payload_values_json: String,
+    #[graphql(skip)]
+    pub baseline: Option<AttackBaseline>,
+}
+
+impl IntruderResultGql {
+    /// Report deltas from the attack's baseline
+    pub fn with_baseline(mut self, baseline: Option<AttackBaseline>) -> Self {
+        self.baseline = baseline;
+        self
+    }
 }
 
 #[ComplexObject]
 impl IntruderResultGql {
+    /// Difference from the attack's baseline, when it measured one
+    async fn delta(&self) -> Option<ResultDeltaGql> {
+        let baseline = self.baseline.as_ref()?;
+        Some(ResultDeltaGql::from(baseline.delta(
+            self.status_code,
+            self.response_length.map(i64::from),
+            self.duration_ms.map(i64::from),
+        )))
+    }
+
     /// Request data - loaded only when requested
     async fn request_data(&self) -> async_graphql::Result<HttpRequestTemplateGql> {
         let request: HttpRequestData = serde_json::from_str(&self.request_data_json)
@@ -2665,9 +8770,12 @@ impl From<IntruderResult> for IntruderResultGql {
             status_code: result.status_code,
             response_length: result.response_length.map(|l| l as i32),
             is_highlighted: result.is_highlighted,
+            highlight_reason: result.highlight_reason,
+            egress_ip: result.egress_ip,
             request_data_json: result.request_data,
             response_data_json: result.response_data,
             payload_values_json: result.payload_values,
+            baseline: None,
         }
     }
 }
@@ -2736,6 +8844,7 @@ impl From<PayloadSetConfig> for PayloadSetConfigGql {
 pub struct PayloadConfigGql {
     pub config_type: String,
     pub config_data: String, // JSON representation of the specific config
+    pub processors: Vec<PayloadProcessorGql>,
 }
 
 impl From<PayloadConfig> for PayloadConfigGql {
@@ -2747,6 +8856,7 @@ impl From<PayloadConfig> for PayloadConfigGql {
                     "file_path": file_path,
                     "encoding": encoding
                 }).to_string(),
+                processors: Vec::new(),
             },
             PayloadConfig::NumberRange { start, end, step, format } => Self {
                 config_type: "number_range".to_string(),
@@ -2756,12 +8866,23 @@ impl From<PayloadConfig> for PayloadConfigGql {
                     "step": step,
                     "format": format
                 }).to_string(),
+                processors: Vec::new(),
             },
             PayloadConfig::Custom { values } => Self {
                 config_type: "custom".to_string(),
                 config_data: serde_json::json!({
                     "values": values
                 }).to_string(),
+                processors: Vec::new(),
+            },
+            PayloadConfig::UploadCatalog { catalog } => Self {
+                config_type: "upload_catalog".to_string(),
+                config_data: serde_json::to_string(&catalog).unwrap_or_default(),
+                processors: Vec::new(),
+            },
+            PayloadConfig::Processed { source, processors } => Self {
+                processors: processors.into_iter().map(PayloadProcessorGql::from).collect(),
+                ..Self::from(*source)
             },
         }
     }
@@ -2796,12 +8917,346 @@ pub struct CreateIntruderAttackInput {
     pub target_agents: Vec<String>,
     pub distribution_strategy: DistributionStrategyInput,
     pub session_data: Option<SessionInput>,
+    /// Mint fresh sessions by replaying a recorded flow
+    pub flow_session: Option<FlowSessionInput>,
+    /// Payload positions inside a multipart body
+    pub upload: Option<UploadTemplateInput>,
+    /// Request rate, connection and backoff limits
+    pub rate_limit: Option<RateLimitInput>,
+    /// Headers varied on every request
+    pub header_randomization: Option<HeaderRandomizationInput>,
+    /// Unmodified requests sent before the attack to measure a baseline
+    pub baseline_requests: Option<i32>,
+    /// Constant rate, ramp-up or bursts; as fast as the limits allow when unset
+    pub pacing: Option<PacingInput>,
+    /// Confirm slow results against the baseline before reporting a delay;
+    /// needs `baselineRequests`
+    pub timing_confirmation: Option<TimingConfirmationInput>,
+    /// Rules marking anomalous results as they arrive
+    pub highlight_rules: Option<Vec<HighlightRuleInput>>,
+    /// Requests sent, in order, before each request of the attack; values
+    /// they extract fill `{{name}}` placeholders in later requests
+    pub sequence: Option<Vec<SequenceStepInput>>,
+}
+
+/// A request of a sequence attack
+#[derive(InputObject)]
+pub struct SequenceStepInput {
+    /// Raw HTTP request with `§marker§` positions and `{{name}}` placeholders
+    pub request_template: String,
+    #[graphql(default)]
+    pub extractions: Vec<TokenExtractionInputGql>,
+}
+
+impl From<SequenceStepInput> for SequenceStep {
+    fn from(input: SequenceStepInput) -> Self {
+        Self {
+            request_template: input.request_template,
+            extractions: input.extractions.into_iter().map(TokenExtraction::from).collect(),
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum HighlightRuleKind {
+    /// Status code is one of `statusCodes`
+    StatusEquals,
+    /// Body length deviates more than `percent` percent from `baselineLength`,
+    /// else from the attack's baseline or its first response
+    LengthDeviation,
+    /// Response took more than `minMs` milliseconds
+    SlowerThan,
+    /// Body contains `pattern`, a regular expression when `regex` is set
+    Grep,
+}
+
+/// Input for a result highlight rule
+#[derive(InputObject)]
+pub struct HighlightRuleInput {
+    pub name: String,
+    pub kind: HighlightRuleKind,
+    pub status_codes: Option<Vec<i32>>,
+    pub percent: Option<f64>,
+    pub baseline_length: Option<i32>,
+    pub min_ms: Option<i32>,
+    pub pattern: Option<String>,
+    #[graphql(default)]
+    pub regex: bool,
+    /// 1-10, higher is listed first in the highlight reason
+    #[graphql(default = 5)]
+    pub priority: i32,
+}
+
+impl TryFrom<HighlightRuleInput> for ResultHighlightRule {
+    type Error = async_graphql::Error;
+
+    fn try_from(input: HighlightRuleInput) -> Result<Self, Self::Error> {
+        let missing = |field: &str| async_graphql::Error::new(format!("{:?} rules need {}", input.kind, field));
+        let condition = match input.kind {
+            HighlightRuleKind::StatusEquals => {
+                HighlightCondition::StatusCode(input.status_codes.ok_or_else(|| missing("statusCodes"))?)
+            }
+            HighlightRuleKind::LengthDeviation => HighlightCondition::LengthDeviation {
+                percent: input.percent.ok_or_else(|| missing("percent"))?,
+                baseline: input.baseline_length.map(|length| length.max(0) as usize),
+            },
+            HighlightRuleKind::SlowerThan => HighlightCondition::ResponseTime {
+                min_ms: Some(input.min_ms.ok_or_else(|| missing("minMs"))?.max(0) as u64),
+                max_ms: None,
+            },
+            HighlightRuleKind::Grep => {
+                let pattern = input.pattern.ok_or_else(|| missing("pattern"))?;
+                if input.regex {
+                    HighlightCondition::ResponseRegex(pattern)
+                } else {
+                    HighlightCondition::ResponseContains(pattern)
+                }
+            }
+        };
+        Ok(Self {
+            name: input.name,
+            condition,
+            priority: u8::try_from(input.priority)
+                .map_err(|_| async_graphql::Error::new("priority must be between 1 and 10"))?,
+        })
+    }
+}
+
+/// Input for the headers randomized per request
+#[derive(InputObject)]
+pub struct HeaderRandomizationInput {
+    /// User-Agent values, one picked at random per request
+    #[graphql(default)]
+    pub user_agents: Vec<String>,
+    /// Send a random public IPv4 address as X-Forwarded-For
+    #[graphql(default)]
+    pub random_forwarded_for: bool,
+    /// Vary the secondary languages and quality values of Accept-Language
+    #[graphql(default)]
+    pub jitter_accept_language: bool,
+}
+
+impl From<HeaderRandomizationInput> for attack_engine::HeaderRandomizationConfig {
+    fn from(input: HeaderRandomizationInput) -> Self {
+        Self {
+            user_agents: input.user_agents,
+            random_forwarded_for: input.random_forwarded_for,
+            jitter_accept_language: input.jitter_accept_language,
+        }
+    }
+}
+
+/// Input for the rate limits of an attack
+#[derive(InputObject)]
+pub struct RateLimitInput {
+    /// Requests per second across all agents
+    pub requests_per_second: Option<f64>,
+    /// Requests each agent has in flight at once
+    pub max_connections_per_agent: Option<i32>,
+    /// Minimum delay between two requests to the same host
+    pub per_host_delay_ms: Option<i32>,
+    /// Responses that make the attack back off from the host (429 and 503 by default)
+    pub backoff_status_codes: Option<Vec<i32>>,
+    /// First backoff delay, doubled on each further backoff response
+    pub backoff_initial_delay_ms: Option<i32>,
+    pub backoff_max_delay_ms: Option<i32>,
+}
+
+impl TryFrom<RateLimitInput> for attack_engine::RateLimitConfig {
+    type Error = async_graphql::Error;
+
+    fn try_from(input: RateLimitInput) -> Result<Self, Self::Error> {
+        let non_negative = |value: Option<i32>, name: &str| {
+            value
+                .map(|v| u64::try_from(v).map_err(|_| async_graphql::Error::new(format!("{} must not be negative", name))))
+                .transpose()
+        };
+        let defaults = attack_engine::RateLimitConfig::default();
+        let (default_initial, default_max) = match defaults.backoff {
+            attack_engine::BackoffStrategy::Exponential { initial_delay_ms, max_delay_ms, .. } => {
+                (initial_delay_ms, max_delay_ms)
+            }
+            _ => (1000, 60000),
+        };
+        Ok(Self {
+            max_requests_per_second: input.requests_per_second,
+            max_connections_per_agent: non_negative(input.max_connections_per_agent, "max_connections_per_agent")?
+                .map(|v| v.min(u32::MAX as u64) as u32),
+            per_host_delay_ms: non_negative(input.per_host_delay_ms, "per_host_delay_ms")?.unwrap_or(0),
+            backoff_status_codes: input.backoff_status_codes.unwrap_or(defaults.backoff_status_codes),
+            backoff: attack_engine::BackoffStrategy::Exponential {
+                initial_delay_ms: non_negative(input.backoff_initial_delay_ms, "backoff_initial_delay_ms")?
+                    .unwrap_or(default_initial),
+                multiplier: 2.0,
+                max_delay_ms: non_negative(input.backoff_max_delay_ms, "backoff_max_delay_ms")?.unwrap_or(default_max),
+            },
+        })
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq)]
+pub enum PacingKindGql {
+    Constant,
+    LinearRamp,
+    Burst,
+}
+
+/// Pacing of an attack; the fields of its kind are required
+#[derive(InputObject)]
+pub struct PacingInput {
+    pub kind: PacingKindGql,
+    /// Rate of a constant profile
+    pub requests_per_second: Option<f64>,
+    /// A ramp goes from `start_rps` to `end_rps` over `ramp_seconds`
+    pub start_rps: Option<f64>,
+    pub end_rps: Option<f64>,
+    pub ramp_seconds: Option<i32>,
+    /// Requests released together per burst
+    pub burst_size: Option<i32>,
+    /// Pause after each burst
+    pub idle_ms: Option<i32>,
+}
+
+impl TryFrom<PacingInput> for attack_engine::PacingProfile {
+    type Error = async_graphql::Error;
+
+    fn try_from(input: PacingInput) -> Result<Self, Self::Error> {
+        fn required<T>(value: Option<T>, name: &str) -> Result<T, async_graphql::Error> {
+            value.ok_or_else(|| async_graphql::Error::new(format!("Pacing needs {}", name)))
+        }
+        fn non_negative(value: Option<i32>, name: &str) -> Result<u64, async_graphql::Error> {
+            u64::try_from(required(value, name)?)
+                .map_err(|_| async_graphql::Error::new(format!("{} must not be negative", name)))
+        }
+        let profile = match input.kind {
+            PacingKindGql::Constant => Self::Constant {
+                requests_per_second: required(input.requests_per_second, "requests_per_second")?,
+            },
+            PacingKindGql::LinearRamp => Self::LinearRamp {
+                start_rps: required(input.start_rps, "start_rps")?,
+                end_rps: required(input.end_rps, "end_rps")?,
+                ramp_seconds: non_negative(input.ramp_seconds, "ramp_seconds")?,
+            },
+            PacingKindGql::Burst => Self::Burst {
+                size: non_negative(input.burst_size, "burst_size")?.min(u32::MAX as u64) as u32,
+                idle_ms: non_negative(input.idle_ms, "idle_ms")?,
+            },
+        };
+        profile.validate().map_err(async_graphql::Error::new)?;
+        Ok(profile)
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq)]
+pub enum ConfidenceLevelGql {
+    P90,
+    P95,
+    P99,
+}
+
+impl From<ConfidenceLevelGql> for attack_engine::ConfidenceLevel {
+    fn from(level: ConfidenceLevelGql) -> Self {
+        match level {
+            ConfidenceLevelGql::P90 => Self::P90,
+            ConfidenceLevelGql::P95 => Self::P95,
+            ConfidenceLevelGql::P99 => Self::P99,
+        }
+    }
+}
+
+/// Interleaved control and probe requests sent for each result slower than
+/// the baseline by `minDelayMs`; the delay is reported only when its
+/// confidence interval lies above it
+#[derive(InputObject)]
+pub struct TimingConfirmationInput {
+    /// Control/probe pairs per slow result (6 if omitted)
+    pub pairs: Option<i32>,
+    /// Smallest delay reported (2000 if omitted)
+    pub min_delay_ms: Option<i32>,
+    /// 95% if omitted
+    pub confidence: Option<ConfidenceLevelGql>,
+    /// Time between the probing requests (250 if omitted)
+    pub interval_ms: Option<i32>,
+}
+
+impl TryFrom<TimingConfirmationInput> for attack_engine::TimingConfirmation {
+    type Error = async_graphql::Error;
+
+    fn try_from(input: TimingConfirmationInput) -> Result<Self, Self::Error> {
+        fn non_negative(value: Option<i32>, name: &str) -> Result<Option<u64>, async_graphql::Error> {
+            value
+                .map(u64::try_from)
+                .transpose()
+                .map_err(|_| async_graphql::Error::new(format!("{} must not be negative", name)))
+        }
+        let defaults = Self::default();
+        let timing = Self {
+            pairs: non_negative(input.pairs, "pairs")?.map_or(defaults.pairs, |pairs| pairs.min(u32::MAX as u64) as u32),
+            min_delay_ms: non_negative(input.min_delay_ms, "min_delay_ms")?.unwrap_or(defaults.min_delay_ms),
+            confidence: input.confidence.map(Into::into).unwrap_or(defaults.confidence),
+            interval_ms: non_negative(input.interval_ms, "interval_ms")?.unwrap_or(defaults.interval_ms),
+        };
+        timing.validate().map_err(async_graphql::Error::new)?;
+        Ok(timing)
+    }
+}
+
+/// Input for a multipart upload template
+#[derive(InputObject)]
+pub struct UploadTemplateInput {
+    /// Captured multipart request whose body the template starts from
+    pub request_id: Option<String>,
+    /// Complete template as JSON, used when no request is given
+    pub template: Option<String>,
+    /// Positions added to the template's own
+    #[graphql(default)]
+    pub positions: Vec<UploadPositionInput>,
+}
+
+/// Input for a payload position inside a multipart part
+#[derive(InputObject)]
+pub struct UploadPositionInput {
+    /// Index of the part in the body
+    pub part: i32,
+    pub field: UploadFieldGql,
+    /// Payload set filling this position
+    pub payload_set_id: String,
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum UploadFieldGql {
+    Filename,
+    ContentType,
+    MagicBytes,
+    Body,
+}
+
+impl From<UploadFieldGql> for UploadField {
+    fn from(field: UploadFieldGql) -> Self {
+        match field {
+            UploadFieldGql::Filename => UploadField::Filename,
+            UploadFieldGql::ContentType => UploadField::ContentType,
+            UploadFieldGql::MagicBytes => UploadField::MagicBytes,
+            UploadFieldGql::Body => UploadField::Body,
+        }
+    }
+}
+
+/// Input for flow-generated attack sessions
+#[derive(InputObject)]
+pub struct FlowSessionInput {
+    pub profile_id: String,
+    /// Requests per minted session (1 = fresh session per payload)
+    pub requests_per_session: i32,
+    pub variables: Option<String>, // JSON object of flow variables
 }
 
 /// Input for attack mode
 #[derive(InputObject)]
 pub struct AttackModeInput {
-    pub mode_type: String, // "sniper", "battering_ram", "pitchfork", "cluster_bomb"
+    pub mode_type: String, // "sniper", "battering_ram", "pitchfork", "cluster_bomb", "graphql"
+    /// Settings of the "graphql" mode
+    pub graphql: Option<GraphQlAttackInput>,
 }
 
 impl From<AttackModeInput> for AttackMode {
@@ -2811,11 +9266,38 @@ impl From<AttackModeInput> for AttackMode {
             "battering_ram" => AttackMode::BatteringRam,
             "pitchfork" => AttackMode::Pitchfork,
             "cluster_bomb" => AttackMode::ClusterBomb,
+            "graphql" => AttackMode::GraphQl(input.graphql.map(GraphQlAttackConfig::from).unwrap_or_default()),
             _ => AttackMode::Sniper, // Default fallback
         }
     }
 }
 
+/// Input for GraphQL attacks. The request template is a captured GraphQL
+/// request; without an introspection result its operation is probed.
+#[derive(InputObject)]
+pub struct GraphQlAttackInput {
+    /// Introspection result (JSON) to enumerate every query and mutation field from
+    pub introspection: Option<String>,
+    /// Aliased copies per alias batching probe (100 by default, 0 to skip)
+    pub alias_batch_size: Option<i32>,
+    /// Strings for string arguments; a built-in list when empty
+    pub injection_payloads: Option<Vec<String>>,
+}
+
+impl From<GraphQlAttackInput> for GraphQlAttackConfig {
+    fn from(input: GraphQlAttackInput) -> Self {
+        let defaults = GraphQlAttackConfig::default();
+        Self {
+            introspection: input.introspection,
+            alias_batch_size: input
+                .alias_batch_size
+                .map(|n| n.max(0) as usize)
+                .unwrap_or(defaults.alias_batch_size),
+            injection_payloads: input.injection_payloads.unwrap_or_default(),
+        }
+    }
+}
+
 /// Input for distribution strategy
 #[derive(InputObject)]
 pub struct DistributionStrategyInput {
@@ -2859,13 +9341,15 @@ impl From<PayloadSetConfigInput> for PayloadSetConfig {
 /// Input for payload configuration
 #[derive(InputObject)]
 pub struct PayloadConfigInput {
-    pub config_type: String, // "wordlist", "number_range", "custom"
+    pub config_type: String, // "wordlist", "number_range", "custom", "upload_catalog"
     pub config_data: String, // JSON representation of the specific config
+    /// Applied to every generated payload, in order
+    pub processors: Option<Vec<PayloadProcessorInput>>,
 }
 
 impl From<PayloadConfigInput> for PayloadConfig {
     fn from(input: PayloadConfigInput) -> Self {
-        match input.config_type.as_str() {
+        let source = match input.config_type.as_str() {
             "wordlist" => {
                 if let Ok(data) = serde_json::from_str::<serde_json::Value>(&input.config_data) {
                     PayloadConfig::Wordlist {
@@ -2903,11 +9387,102 @@ impl From<PayloadConfigInput> for PayloadConfig {
                     PayloadConfig::Custom { values: Vec::new() }
                 }
             }
+            // {"kind": "double_extensions", "basename": "shell", "extension": "php", "allowed": "jpg"}
+            "upload_catalog" => match serde_json::from_str::<UploadCatalog>(&input.config_data) {
+                Ok(catalog) => PayloadConfig::UploadCatalog { catalog },
+                Err(_) => PayloadConfig::Custom { values: Vec::new() },
+            },
             _ => PayloadConfig::Custom { values: Vec::new() },
+        };
+
+        match input.processors {
+            Some(processors) if !processors.is_empty() => PayloadConfig::Processed {
+                source: Box::new(source),
+                processors: processors.into_iter().map(PayloadProcessor::from).collect(),
+            },
+            _ => source,
+        }
+    }
+}
+
+#[derive(async_graphql::Enum, Copy, Clone, Eq, PartialEq, Debug)]
+pub enum PayloadProcessorKind {
+    UrlEncode,
+    UrlEncodeAll,
+    Base64,
+    Hex,
+    Md5,
+    Sha1,
+    Sha256,
+    Lowercase,
+    Uppercase,
+    Prefix,
+    Suffix,
+    RegexReplace,
+}
+
+/// One payload processing step. `value` is the text of a prefix or suffix
+/// and the pattern of a regex replacement.
+#[derive(InputObject)]
+pub struct PayloadProcessorInput {
+    pub kind: PayloadProcessorKind,
+    pub value: Option<String>,
+    pub replacement: Option<String>,
+}
+
+impl From<PayloadProcessorInput> for PayloadProcessor {
+    fn from(input: PayloadProcessorInput) -> Self {
+        let value = input.value.unwrap_or_default();
+        match input.kind {
+            PayloadProcessorKind::UrlEncode => PayloadProcessor::UrlEncode,
+            PayloadProcessorKind::UrlEncodeAll => PayloadProcessor::UrlEncodeAll,
+            PayloadProcessorKind::Base64 => PayloadProcessor::Base64,
+            PayloadProcessorKind::Hex => PayloadProcessor::Hex,
+            PayloadProcessorKind::Md5 => PayloadProcessor::Md5,
+            PayloadProcessorKind::Sha1 => PayloadProcessor::Sha1,
+            PayloadProcessorKind::Sha256 => PayloadProcessor::Sha256,
+            PayloadProcessorKind::Lowercase => PayloadProcessor::Lowercase,
+            PayloadProcessorKind::Uppercase => PayloadProcessor::Uppercase,
+            PayloadProcessorKind::Prefix => PayloadProcessor::Prefix { value },
+            PayloadProcessorKind::Suffix => PayloadProcessor::Suffix { value },
+            PayloadProcessorKind::RegexReplace => PayloadProcessor::RegexReplace {
+                pattern: value,
+                replacement: input.replacement.unwrap_or_default(),
+            },
         }
     }
 }
 
+/// GraphQL type for a payload processing step
+#[derive(SimpleObject)]
+pub struct PayloadProcessorGql {
+    pub kind: PayloadProcessorKind,
+    pub value: Option<String>,
+    pub replacement: Option<String>,
+}
+
+impl From<PayloadProcessor> for PayloadProcessorGql {
+    fn from(processor: PayloadProcessor) -> Self {
+        let (kind, value, replacement) = match processor {
+            PayloadProcessor::UrlEncode => (PayloadProcessorKind::UrlEncode, None, None),
+            PayloadProcessor::UrlEncodeAll => (PayloadProcessorKind::UrlEncodeAll, None, None),
+            PayloadProcessor::Base64 => (PayloadProcessorKind::Base64, None, None),
+            PayloadProcessor::Hex => (PayloadProcessorKind::Hex, None, None),
+            PayloadProcessor::Md5 => (PayloadProcessorKind::Md5, None, None),
+            PayloadProcessor::Sha1 => (PayloadProcessorKind::Sha1, None, None),
+            PayloadProcessor::Sha256 => (PayloadProcessorKind::Sha256, None, None),
+            PayloadProcessor::Lowercase => (PayloadProcessorKind::Lowercase, None, None),
+            PayloadProcessor::Uppercase => (PayloadProcessorKind::Uppercase, None, None),
+            PayloadProcessor::Prefix { value } => (PayloadProcessorKind::Prefix, Some(value), None),
+            PayloadProcessor::Suffix { value } => (PayloadProcessorKind::Suffix, Some(value), None),
+            PayloadProcessor::RegexReplace { pattern, replacement } => {
+                (PayloadProcessorKind::RegexReplace, Some(pattern), Some(replacement))
+            }
+        };
+        Self { kind, value, replacement }
+    }
+}
+
 /// Input for creating a new payload set
 #[derive(InputObject)]
 pub struct CreatePayloadSetInput {
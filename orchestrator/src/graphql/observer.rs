@@ -0,0 +1,171 @@
+//! Observer Guard
+//!
+//! Rejects GraphQL mutations for read-only observers: every caller when the
+//! server runs in read-only mode, and on shared projects every caller without
+//! the Editor role (Viewers, unknown principals and anonymous callers).
+//! The check runs on the parsed document, so it covers HTTP and WebSocket
+//! requests alike and nothing is executed before the request is refused.
+
+use crate::auth::Caller;
+use crate::models::access::{check_mutation, Principal};
+use crate::Database;
+use async_graphql::extensions::{Extension, ExtensionContext, ExtensionFactory, NextParseQuery};
+use async_graphql::parser::types::{ExecutableDocument, OperationType};
+use async_graphql::{ServerError, ServerResult, Variables};
+use std::sync::Arc;
+
+/// Schema data marking the server as read-only
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOnlyMode(pub bool);
+
+/// Extension factory installing the observer guard on a schema
+pub struct ObserverGuard;
+
+impl ExtensionFactory for ObserverGuard {
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(ObserverGuardExtension)
+    }
+}
+
+struct ObserverGuardExtension;
+
+#[async_trait::async_trait]
+impl Extension for ObserverGuardExtension {
+    async fn parse_query(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        query: &str,
+        variables: &Variables,
+        next: NextParseQuery<'_>,
+    ) -> ServerResult<ExecutableDocument> {
+        let document = next.run(ctx, query, variables).await?;
+
+        let has_mutation = document
+            .operations
+            .iter()
+            .any(|(_, op)| op.node.ty == OperationType::Mutation);
+        if !has_mutation {
+            return Ok(document);
+        }
+
+        if ctx.data_opt::<ReadOnlyMode>().is_some_and(|mode| mode.0) {
+            return Err(observer_error("The server is in read-only observer mode"));
+        }
+
+        if ctx.data_opt::<Caller>() == Some(&Caller::Admin) {
+            return Ok(document);
+        }
+        if let Some(db) = ctx.data_opt::<Arc<Database>>() {
+            let members = db
+                .list_project_members()
+                .await
+                .map_err(|e| ServerError::new(format!("Failed to check project access: {}", e), None))?;
+            if let Err(denied) = check_mutation(&members, ctx.data_opt::<Principal>()) {
+                return Err(observer_error(&denied.to_string()));
+            }
+        }
+
+        Ok(document)
+    }
+}
+
+fn observer_error(message: &str) -> ServerError {
+    let mut error = ServerError::new(message, None);
+    error
+        .extensions
+        .get_or_insert_with(Default::default)
+        .set("code", "READ_ONLY");
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphql::{MutationRoot, QueryRoot};
+    use crate::models::access::ProjectRole;
+    use async_graphql::{EmptySubscription, Request, Response, Schema};
+    use tempfile::TempDir;
+
+    const QUERY: &str = "{ dnsConfig { mode } }";
+    const MUTATION: &str = "mutation { updateDnsConfig(input: { mode: UDP, servers: [\"10.0.0.53\"] }) { mode } }";
+
+    async fn test_db() -> (Arc<Database>, TempDir) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Arc::new(Database::new(temp_dir.path().to_str().unwrap()).await.unwrap());
+        db.create_project("observer").await.unwrap();
+        db.load_project("observer").await.unwrap();
+        db.set_project_member("owner", ProjectRole::Owner).await.unwrap();
+        db.set_project_member("editor", ProjectRole::Editor).await.unwrap();
+        db.set_project_member("viewer", ProjectRole::Viewer).await.unwrap();
+        (db, temp_dir)
+    }
+
+    async fn execute(db: &Arc<Database>, read_only: bool, request: Request) -> Response {
+        Schema::build(QueryRoot, MutationRoot, EmptySubscription)
+            .data(db.clone())
+            .data(ReadOnlyMode(read_only))
+            .extension(ObserverGuard)
+            .finish()
+            .execute(request)
+            .await
+    }
+
+    fn as_member(query: &str, principal: &str) -> Request {
+        Request::new(query).data(Principal(principal.to_string()))
+    }
+
+    fn is_read_only_error(response: &Response) -> bool {
+        response.errors.len() == 1
+            && response.errors[0].extensions.as_ref().and_then(|e| e.get("code"))
+                == Some(&async_graphql::Value::from("READ_ONLY"))
+    }
+
+    #[tokio::test]
+    async fn test_viewer_mutation_rejected_and_queries_allowed() {
+        let (db, _dir) = test_db().await;
+
+        let response = execute(&db, false, as_member(MUTATION, "viewer")).await;
+        assert!(is_read_only_error(&response), "{:?}", response.errors);
+        assert!(db.get_dns_config().await.unwrap().is_system());
+
+        let response = execute(&db, false, as_member(QUERY, "viewer")).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn test_unidentified_mutations_rejected() {
+        let (db, _dir) = test_db().await;
+
+        for request in [Request::new(MUTATION), as_member(MUTATION, "stranger")] {
+            let response = execute(&db, false, request).await;
+            assert!(is_read_only_error(&response), "{:?}", response.errors);
+        }
+        assert!(db.get_dns_config().await.unwrap().is_system());
+    }
+
+    #[tokio::test]
+    async fn test_editor_mutation_passes_guard() {
+        let (db, _dir) = test_db().await;
+
+        let response = execute(&db, false, as_member(MUTATION, "editor")).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert!(!db.get_dns_config().await.unwrap().is_system());
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode_rejects_every_mutation() {
+        let (db, _dir) = test_db().await;
+
+        for request in [
+            as_member(MUTATION, "owner"),
+            Request::new(MUTATION).data(Caller::Admin),
+        ] {
+            let response = execute(&db, true, request).await;
+            assert!(is_read_only_error(&response), "{:?}", response.errors);
+        }
+        assert!(db.get_dns_config().await.unwrap().is_system());
+
+        let response = execute(&db, true, as_member(QUERY, "viewer")).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+}
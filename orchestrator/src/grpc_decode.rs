@@ -0,0 +1,440 @@
+//! gRPC Decoding
+//!
+//! Splits captured gRPC (and gRPC-Web) bodies into length-prefixed messages
+//! and decodes each one. With descriptor sets uploaded to the project
+//! (`protoc --include_imports --descriptor_set_out=...`), messages of known
+//! methods are decoded to JSON by their request/response types. Without a
+//! matching descriptor, messages are decoded schema-less into a tree keyed by
+//! field number, like `protoc --decode_raw`.
+
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, ReflectMessage};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::io::Read;
+use tracing::warn;
+
+/// Nesting limit for schema-less decoding
+const MAX_RAW_DEPTH: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum GrpcDecodeError {
+    #[error("Truncated gRPC frame at offset {offset}: {needed} bytes needed, {available} available")]
+    Truncated { offset: usize, needed: usize, available: usize },
+    #[error("Invalid descriptor set: {0}")]
+    Descriptor(String),
+}
+
+/// One length-prefixed message of a gRPC body
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrpcFrame {
+    pub compressed: bool,
+    /// gRPC-Web trailer frame (flag 0x80), carrying HTTP-style trailers
+    pub trailer: bool,
+    pub data: Vec<u8>,
+}
+
+/// A decoded message
+#[derive(Debug, Clone)]
+pub struct GrpcMessage {
+    pub compressed: bool,
+    /// Size on the wire, before decompression
+    pub size: usize,
+    /// Fully qualified protobuf type, when decoded with a descriptor
+    pub type_name: Option<String>,
+    pub json: Value,
+    pub error: Option<String>,
+}
+
+/// A decoded gRPC call
+#[derive(Debug, Clone, Default)]
+pub struct GrpcCall {
+    pub service: Option<String>,
+    pub method: Option<String>,
+    pub requests: Vec<GrpcMessage>,
+    pub responses: Vec<GrpcMessage>,
+    /// `grpc-status` and `grpc-message`, from response headers or gRPC-Web trailers
+    pub status: Option<String>,
+    pub status_message: Option<String>,
+}
+
+/// Whether a content type is gRPC or gRPC-Web (binary)
+pub fn is_grpc(content_type: &str) -> bool {
+    let content_type = content_type.trim().to_ascii_lowercase();
+    let essence = content_type.split(';').next().unwrap_or("").trim();
+    essence == "application/grpc"
+        || essence.starts_with("application/grpc+")
+        || essence == "application/grpc-web"
+        || essence.starts_with("application/grpc-web+")
+}
+
+/// Split a body into frames: 1 flag byte, 4 byte big-endian length, message
+pub fn split_frames(body: &[u8]) -> Result<Vec<GrpcFrame>, GrpcDecodeError> {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while offset < body.len() {
+        let header = &body[offset..];
+        if header.len() < 5 {
+            return Err(GrpcDecodeError::Truncated {
+                offset,
+                needed: 5,
+                available: header.len(),
+            });
+        }
+        let flags = header[0];
+        let length = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let available = header.len() - 5;
+        if available < length {
+            return Err(GrpcDecodeError::Truncated {
+                offset,
+                needed: length,
+                available,
+            });
+        }
+        frames.push(GrpcFrame {
+            compressed: flags & 0x01 != 0,
+            trailer: flags & 0x80 != 0,
+            data: header[5..5 + length].to_vec(),
+        });
+        offset += 5 + length;
+    }
+    Ok(frames)
+}
+
+/// Decodes calls with the project's descriptor sets
+pub struct GrpcDecoder {
+    pool: DescriptorPool,
+}
+
+impl GrpcDecoder {
+    /// Decoder without descriptors; every message is decoded schema-less
+    pub fn new() -> Self {
+        Self {
+            pool: DescriptorPool::new(),
+        }
+    }
+
+    /// Merge descriptor sets into one pool. Sets that conflict with earlier
+    /// ones are skipped.
+    pub fn from_descriptor_sets<'a>(sets: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut pool = DescriptorPool::new();
+        for set in sets {
+            if let Err(e) = pool.decode_file_descriptor_set(set) {
+                warn!("Skipping gRPC descriptor set: {}", e);
+            }
+        }
+        Self { pool }
+    }
+
+    /// Fully qualified names of the services a descriptor set defines
+    pub fn validate_descriptor_set(set: &[u8]) -> Result<Vec<String>, GrpcDecodeError> {
+        let pool = DescriptorPool::decode(set).map_err(|e| GrpcDecodeError::Descriptor(e.to_string()))?;
+        Ok(pool.services().map(|s| s.full_name().to_string()).collect())
+    }
+
+    /// Decode a call. `path` is the request path ("/pkg.Service/Method");
+    /// headers are used for compression and status.
+    pub fn decode_call(
+        &self,
+        path: &str,
+        request_headers: &HashMap<String, String>,
+        request_body: &[u8],
+        response_headers: &HashMap<String, String>,
+        response_body: &[u8],
+    ) -> GrpcCall {
+        let mut call = GrpcCall::default();
+        let mut parts = path.trim_start_matches('/').splitn(2, '/');
+        if let (Some(service), Some(method)) = (parts.next(), parts.next()) {
+            call.service = Some(service.to_string());
+            call.method = Some(method.to_string());
+        }
+
+        let method = call
+            .service
+            .as_deref()
+            .and_then(|s| self.pool.get_service_by_name(s))
+            .and_then(|s| s.methods().find(|m| Some(m.name()) == call.method.as_deref()));
+        let (input, output) = match &method {
+            Some(m) => (Some(m.input()), Some(m.output())),
+            None => (None, None),
+        };
+
+        call.requests = decode_body(request_body, header(request_headers, "grpc-encoding"), input.as_ref(), &mut None);
+        let mut trailers = None;
+        call.responses = decode_body(
+            response_body,
+            header(response_headers, "grpc-encoding"),
+            output.as_ref(),
+            &mut trailers,
+        );
+
+        let trailers = trailers.unwrap_or_default();
+        call.status = header(response_headers, "grpc-status")
+            .or_else(|| header(&trailers, "grpc-status"))
+            .map(str::to_string);
+        call.status_message = header(response_headers, "grpc-message")
+            .or_else(|| header(&trailers, "grpc-message"))
+            .map(str::to_string);
+        call
+    }
+}
+
+impl Default for GrpcDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn decode_body(
+    body: &[u8],
+    encoding: Option<&str>,
+    descriptor: Option<&MessageDescriptor>,
+    trailers: &mut Option<HashMap<String, String>>,
+) -> Vec<GrpcMessage> {
+    let frames = match split_frames(body) {
+        Ok(frames) => frames,
+        Err(e) => {
+            return vec![GrpcMessage {
+                compressed: false,
+                size: body.len(),
+                type_name: None,
+                json: Value::Null,
+                error: Some(e.to_string()),
+            }]
+        }
+    };
+
+    let mut messages = Vec::new();
+    for frame in frames {
+        if frame.trailer {
+            *trailers = Some(parse_trailers(&frame.data));
+            continue;
+        }
+        let mut message = GrpcMessage {
+            compressed: frame.compressed,
+            size: frame.data.len(),
+            type_name: None,
+            json: Value::Null,
+            error: None,
+        };
+        let data = if frame.compressed {
+            match decompress(&frame.data, encoding) {
+                Ok(data) => data,
+                Err(e) => {
+                    message.error = Some(e);
+                    messages.push(message);
+                    continue;
+                }
+            }
+        } else {
+            frame.data
+        };
+
+        match descriptor.map(|d| DynamicMessage::decode(d.clone(), data.as_slice())) {
+            Some(Ok(decoded)) => {
+                message.type_name = Some(decoded.descriptor().full_name().to_string());
+                message.json = serde_json::to_value(&decoded).unwrap_or(Value::Null);
+            }
+            Some(Err(e)) => {
+                message.error = Some(format!("Does not match {}: {}", descriptor.unwrap().full_name(), e));
+                message.json = decode_raw(&data).unwrap_or(Value::Null);
+            }
+            None => match decode_raw(&data) {
+                Some(json) => message.json = json,
+                None => message.error = Some("Not a valid protobuf message".to_string()),
+            },
+        }
+        messages.push(message);
+    }
+    messages
+}
+
+fn decompress(data: &[u8], encoding: Option<&str>) -> Result<Vec<u8>, String> {
+    match encoding.map(|e| e.trim().to_ascii_lowercase()).as_deref() {
+        Some("gzip") => {
+            let mut out = Vec::new();
+            flate2::read::GzDecoder::new(data)
+                .read_to_end(&mut out)
+                .map_err(|e| format!("gzip: {}", e))?;
+            Ok(out)
+        }
+        Some("deflate") => {
+            let mut out = Vec::new();
+            flate2::read::ZlibDecoder::new(data)
+                .read_to_end(&mut out)
+                .map_err(|e| format!("deflate: {}", e))?;
+            Ok(out)
+        }
+        Some(other) => Err(format!("Unsupported message compression '{}'", other)),
+        None => Err("Compressed message without grpc-encoding".to_string()),
+    }
+}
+
+/// gRPC-Web trailer frame: "name: value" lines
+fn parse_trailers(data: &[u8]) -> HashMap<String, String> {
+    String::from_utf8_lossy(data)
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect()
+}
+
+/// Schema-less decoding. Fields are keyed by number; repeated numbers become
+/// arrays. Length-delimited fields show as text when printable, as a nested
+/// message when they parse as one, and as hex otherwise.
+pub fn decode_raw(data: &[u8]) -> Option<Value> {
+    decode_raw_at(data, 0)
+}
+
+fn decode_raw_at(data: &[u8], depth: usize) -> Option<Value> {
+    let mut fields: Map<String, Value> = Map::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let key = read_varint(data, &mut pos)?;
+        let number = key >> 3;
+        if number == 0 {
+            return None;
+        }
+        let value = match key & 0x07 {
+            0 => json!(read_varint(data, &mut pos)?),
+            1 => {
+                let bytes: [u8; 8] = data.get(pos..pos + 8)?.try_into().ok()?;
+                pos += 8;
+                json!(u64::from_le_bytes(bytes))
+            }
+            2 => {
+                let len = usize::try_from(read_varint(data, &mut pos)?).ok()?;
+                let bytes = data.get(pos..pos.checked_add(len)?)?;
+                pos += len;
+                length_delimited(bytes, depth)
+            }
+            5 => {
+                let bytes: [u8; 4] = data.get(pos..pos + 4)?.try_into().ok()?;
+                pos += 4;
+                json!(u32::from_le_bytes(bytes))
+            }
+            _ => return None,
+        };
+
+        match fields.get_mut(&number.to_string()) {
+            Some(Value::Array(values)) => values.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                fields.insert(number.to_string(), value);
+            }
+        }
+    }
+    Some(Value::Object(fields))
+}
+
+fn length_delimited(bytes: &[u8], depth: usize) -> Value {
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        if text.chars().all(|c| !c.is_control() || c == '\n' || c == '\r' || c == '\t') {
+            return Value::String(text.to_string());
+        }
+    }
+    if depth < MAX_RAW_DEPTH && !bytes.is_empty() {
+        if let Some(nested) = decode_raw_at(bytes, depth + 1) {
+            return nested;
+        }
+    }
+    Value::String(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(flags: u8, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![flags];
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        out.extend_from_slice(data);
+        out
+    }
+
+    #[test]
+    fn test_is_grpc() {
+        assert!(is_grpc("application/grpc"));
+        assert!(is_grpc("application/grpc+proto"));
+        assert!(is_grpc("application/grpc-web; charset=utf-8"));
+        assert!(!is_grpc("application/grpc-web-text"));
+        assert!(!is_grpc("application/json"));
+    }
+
+    #[test]
+    fn test_split_frames() {
+        let mut body = frame(0, b"\x08\x01");
+        body.extend(frame(1, b"zz"));
+        let frames = split_frames(&body).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].data, b"\x08\x01");
+        assert!(frames[1].compressed && !frames[1].trailer);
+
+        assert!(matches!(
+            split_frames(&body[..body.len() - 1]),
+            Err(GrpcDecodeError::Truncated { needed: 2, available: 1, .. })
+        ));
+        assert!(split_frames(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decode_raw() {
+        // 1: 150, 2: "hi", 3: {1: 1}, 4: [7, 8]
+        let data = b"\x08\x96\x01\x12\x02hi\x1a\x02\x08\x01\x20\x07\x20\x08";
+        let decoded = decode_raw(data).unwrap();
+        assert_eq!(decoded, json!({"1": 150, "2": "hi", "3": {"1": 1}, "4": [7, 8]}));
+
+        assert!(decode_raw(b"\x08").is_none());
+        assert!(decode_raw(b"\x0f\x01").is_none());
+    }
+
+    #[test]
+    fn test_decode_call_without_descriptor() {
+        let request = frame(0, b"\x0a\x05alice");
+        let mut response = frame(0, b"\x08\x2a");
+        response.extend(frame(0x80, b"grpc-status: 0\r\ngrpc-message: OK\r\n"));
+
+        let call = GrpcDecoder::new().decode_call(
+            "/users.v1.Users/Get",
+            &HashMap::new(),
+            &request,
+            &HashMap::from([("content-type".to_string(), "application/grpc-web".to_string())]),
+            &response,
+        );
+        assert_eq!(call.service.as_deref(), Some("users.v1.Users"));
+        assert_eq!(call.method.as_deref(), Some("Get"));
+        assert_eq!(call.requests[0].json, json!({"1": "alice"}));
+        assert_eq!(call.responses.len(), 1);
+        assert_eq!(call.responses[0].json, json!({"1": 42}));
+        assert_eq!(call.status.as_deref(), Some("0"));
+        assert_eq!(call.status_message.as_deref(), Some("OK"));
+    }
+
+    #[test]
+    fn test_compressed_without_encoding_is_reported() {
+        let call = GrpcDecoder::new().decode_call("/a.B/C", &HashMap::new(), &frame(1, b"x"), &HashMap::new(), &[]);
+        assert!(call.requests[0].error.as_deref().unwrap().contains("grpc-encoding"));
+    }
+}
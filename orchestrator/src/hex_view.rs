@@ -0,0 +1,177 @@
+//! Hex view of stored bodies
+//!
+//! Pages of a transaction body as offset / hex / ASCII rows, the way a hex
+//! editor shows them. Only the requested range is read from the database, so
+//! the GUI can page through multi-megabyte payloads without fetching them.
+
+use crate::Database;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Bytes per dump row
+pub const HEX_ROW_BYTES: usize = 16;
+
+/// Largest range returned at once
+pub const MAX_HEX_RANGE: u64 = 64 * 1024;
+
+/// Range returned when none is given
+pub const DEFAULT_HEX_RANGE: u64 = 4 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HexViewError {
+    #[error("Transaction {0} not found")]
+    NotFound(String),
+    #[error("Invalid range: {0}")]
+    InvalidRange(String),
+    #[error("Hex view database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Which body of a transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyPart {
+    Request,
+    Response,
+}
+
+/// One row: up to 16 bytes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct HexRow {
+    /// Offset of the first byte in the body
+    pub offset: u64,
+    /// Space-separated byte values, with an extra space after the eighth
+    pub hex: String,
+    /// Printable ASCII as-is, everything else as `.`
+    pub ascii: String,
+}
+
+/// A page of a body
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct HexDump {
+    pub offset: u64,
+    /// Bytes in this page; less than asked for at the end of the body
+    pub length: u64,
+    pub total_length: u64,
+    pub rows: Vec<HexRow>,
+}
+
+/// Rows for `data`, which starts at `base_offset` in the body
+pub fn hex_rows(data: &[u8], base_offset: u64) -> Vec<HexRow> {
+    data.chunks(HEX_ROW_BYTES)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex = chunk
+                .iter()
+                .enumerate()
+                .map(|(j, b)| if j == 8 { format!(" {:02x}", b) } else { format!("{:02x}", b) })
+                .collect::<Vec<_>>()
+                .join(" ");
+            let ascii = chunk
+                .iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            HexRow {
+                offset: base_offset + (i * HEX_ROW_BYTES) as u64,
+                hex,
+                ascii,
+            }
+        })
+        .collect()
+}
+
+/// Dump `length` bytes (default 4 KiB, at most 64 KiB) of a transaction
+/// body from `offset`
+pub async fn hex_dump(
+    db: &Database,
+    request_id: &str,
+    part: BodyPart,
+    offset: u64,
+    length: Option<u64>,
+) -> Result<HexDump, HexViewError> {
+    let length = length.unwrap_or(DEFAULT_HEX_RANGE);
+    if length == 0 || length > MAX_HEX_RANGE {
+        return Err(HexViewError::InvalidRange(format!(
+            "length must be between 1 and {} bytes",
+            MAX_HEX_RANGE
+        )));
+    }
+
+    let (total_length, data) = db
+        .body_range(request_id, part, offset, length)
+        .await?
+        .ok_or_else(|| HexViewError::NotFound(request_id.to_string()))?;
+    if offset > total_length {
+        return Err(HexViewError::InvalidRange(format!(
+            "offset {} is past the end of the {}-byte body",
+            offset, total_length
+        )));
+    }
+
+    Ok(HexDump {
+        offset,
+        length: data.len() as u64,
+        total_length,
+        rows: hex_rows(&data, offset),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_rows() {
+        let data: Vec<u8> = (0x3cu8..0x3c + 20).chain([0x00, 0xff]).collect();
+        let rows = hex_rows(&data, 32);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].offset, 32);
+        assert_eq!(rows[0].hex, "3c 3d 3e 3f 40 41 42 43  44 45 46 47 48 49 4a 4b");
+        assert_eq!(rows[0].ascii, "<=>?@ABCDEFGHIJK");
+        assert_eq!(rows[1].offset, 48);
+        assert_eq!(rows[1].hex, "4c 4d 4e 4f 00 ff");
+        assert_eq!(rows[1].ascii, "LMNO..");
+    }
+
+    #[tokio::test]
+    async fn test_hex_dump_pages_stored_body() {
+        use crate::pb::{traffic_event, HttpRequestData, HttpResponseData, TrafficEvent};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let db = Database::new(dir.path().to_str().unwrap()).await.unwrap();
+        db.create_project("p").await.unwrap();
+        db.load_project("p").await.unwrap();
+        db.upsert_agent("agent-1", "Agent", "localhost", "0.1.0").await.unwrap();
+        let body: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        for event in [
+            traffic_event::Event::Request(HttpRequestData {
+                method: "GET".to_string(),
+                url: "https://a.test/bin".to_string(),
+                ..Default::default()
+            }),
+            traffic_event::Event::Response(HttpResponseData {
+                status_code: 200,
+                body: body.clone().into(),
+                ..Default::default()
+            }),
+        ] {
+            let event = TrafficEvent {
+                request_id: "r1".to_string(),
+                event: Some(event),
+            };
+            db.save_request(&event, "agent-1").await.unwrap();
+        }
+
+        let page = hex_dump(&db, "r1", BodyPart::Response, 992, Some(64)).await.unwrap();
+        assert_eq!(page.total_length, 1000);
+        assert_eq!(page.length, 8);
+        assert_eq!(page.rows, hex_rows(&body[992..], 992));
+
+        let request = hex_dump(&db, "r1", BodyPart::Request, 0, None).await.unwrap();
+        assert_eq!((request.total_length, request.rows.len()), (0, 0));
+
+        assert!(matches!(hex_dump(&db, "r1", BodyPart::Response, 1001, None).await, Err(HexViewError::InvalidRange(_))));
+        assert!(matches!(hex_dump(&db, "r1", BodyPart::Response, 0, Some(0)).await, Err(HexViewError::InvalidRange(_))));
+        assert!(matches!(hex_dump(&db, "nope", BodyPart::Response, 0, None).await, Err(HexViewError::NotFound(_))));
+    }
+}
@@ -0,0 +1,202 @@
+//! Hook scripts distributed to agents
+//!
+//! Rhai scripts that agents run on every proxied request and response to edit,
+//! drop or tag them (see `proxy_core::scripts` for the script interface).
+//! Scripts live as `<id>.rhai` files in the project's `hook-scripts`
+//! directory. Whenever the set changes it is pushed to every connected agent,
+//! which swaps it in without restarting its proxy; agents that connect later
+//! get it when their traffic stream opens.
+
+use crate::pb::{intercept_command, HookScript, HookScripts, InterceptCommand};
+use crate::AgentRegistry;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+const SCRIPT_EXTENSION: &str = "rhai";
+
+#[derive(Debug, thiserror::Error)]
+pub enum HookScriptError {
+    #[error("Invalid hook script: {0}")]
+    Invalid(String),
+    #[error("Hook scripts directory: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A script as stored in the project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookScriptDefinition {
+    pub id: String,
+    pub source: String,
+}
+
+fn validate_id(id: &str) -> Result<(), HookScriptError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(HookScriptError::Invalid(format!(
+            "id '{}' must be non-empty and use only letters, digits, - and _",
+            id
+        )));
+    }
+    Ok(())
+}
+
+/// Scripts of the active project, kept in sync with the agents
+pub struct HookScriptStore {
+    agent_registry: Arc<AgentRegistry>,
+    scripts: RwLock<Vec<HookScriptDefinition>>,
+}
+
+impl HookScriptStore {
+    pub fn new(agent_registry: Arc<AgentRegistry>) -> Self {
+        Self {
+            agent_registry,
+            scripts: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Replace the scripts with those in `dir` and push them to the agents.
+    /// Scripts that do not compile are skipped with a warning; a missing
+    /// directory means no scripts. Returns the number loaded.
+    pub async fn load_dir(&self, dir: &Path) -> Result<usize, HookScriptError> {
+        let mut loaded = Vec::new();
+        if dir.exists() {
+            let mut paths: Vec<_> = std::fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().and_then(|e| e.to_str()) == Some(SCRIPT_EXTENSION))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let Some(id) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+                    continue;
+                };
+                let checked = validate_id(&id).and_then(|_| {
+                    let source = std::fs::read_to_string(&path)?;
+                    proxy_core::scripts::validate_script(&source)
+                        .map_err(|e| HookScriptError::Invalid(e.to_string()))?;
+                    Ok(source)
+                });
+                match checked {
+                    Ok(source) => loaded.push(HookScriptDefinition { id, source }),
+                    Err(e) => warn!("Skipping hook script {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        let count = loaded.len();
+        *self.scripts.write().await = loaded;
+        info!("Loaded {} hook script(s) from {}", count, dir.display());
+        self.push().await;
+        Ok(count)
+    }
+
+    /// Drop all scripts (no project loaded)
+    pub async fn clear(&self) {
+        self.scripts.write().await.clear();
+        self.push().await;
+    }
+
+    pub async fn list(&self) -> Vec<HookScriptDefinition> {
+        self.scripts.read().await.clone()
+    }
+
+    /// Validate `source`, write it to `dir` as `<id>.rhai` (replacing a script
+    /// with the same id) and reload the directory. Nothing is written if the
+    /// script is invalid.
+    pub async fn upload(&self, dir: &Path, id: &str, source: &str) -> Result<HookScriptDefinition, HookScriptError> {
+        validate_id(id)?;
+        proxy_core::scripts::validate_script(source).map_err(|e| HookScriptError::Invalid(e.to_string()))?;
+
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(format!("{}.{}", id, SCRIPT_EXTENSION)), source)?;
+        self.load_dir(dir).await?;
+
+        Ok(HookScriptDefinition {
+            id: id.to_string(),
+            source: source.to_string(),
+        })
+    }
+
+    /// Remove the file of script `id` from `dir` and reload. Returns false if
+    /// there was no such file.
+    pub async fn delete(&self, dir: &Path, id: &str) -> Result<bool, HookScriptError> {
+        validate_id(id)?;
+        let path = dir.join(format!("{}.{}", id, SCRIPT_EXTENSION));
+        if !path.exists() {
+            return Ok(false);
+        }
+        std::fs::remove_file(path)?;
+        self.load_dir(dir).await?;
+        Ok(true)
+    }
+
+    /// Command carrying the current scripts, as sent to agents
+    pub async fn command(&self) -> InterceptCommand {
+        let scripts = self
+            .scripts
+            .read()
+            .await
+            .iter()
+            .map(|script| HookScript {
+                id: script.id.clone(),
+                source: script.source.clone(),
+            })
+            .collect();
+
+        InterceptCommand {
+            command: Some(intercept_command::Command::HookScripts(HookScripts { scripts })),
+        }
+    }
+
+    /// Send the current scripts to every connected agent
+    async fn push(&self) {
+        let command = self.command().await;
+        for agent in self.agent_registry.list_agents() {
+            if agent.command_tx.send(Ok(command.clone())).await.is_err() {
+                warn!("Failed to push hook scripts to agent {}", agent.id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    const TAGGER: &str = r#"fn on_request() { this.tags.push("seen"); }"#;
+
+    #[tokio::test]
+    async fn test_upload_push_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = Arc::new(AgentRegistry::new());
+        let (tx, mut rx) = mpsc::channel(8);
+        registry.register_agent("a1".into(), "Agent".into(), "host".into(), "0.1.0".into(), tx);
+        let store = HookScriptStore::new(registry);
+
+        assert!(matches!(
+            store.upload(dir.path(), "broken", "fn on_request( {").await,
+            Err(HookScriptError::Invalid(_))
+        ));
+        assert!(matches!(store.upload(dir.path(), "../x", TAGGER).await, Err(HookScriptError::Invalid(_))));
+        assert!(rx.try_recv().is_err());
+
+        store.upload(dir.path(), "tagger", TAGGER).await.unwrap();
+        assert!(dir.path().join("tagger.rhai").exists());
+        let Some(intercept_command::Command::HookScripts(pushed)) = rx.recv().await.unwrap().unwrap().command else {
+            panic!("expected hook scripts command");
+        };
+        assert_eq!(pushed.scripts.len(), 1);
+        assert_eq!(pushed.scripts[0].id, "tagger");
+        assert_eq!(pushed.scripts[0].source, TAGGER);
+
+        // Hand-edited files that no longer compile are left out
+        std::fs::write(dir.path().join("bad.rhai"), "fn nothing() {}").unwrap();
+        assert_eq!(store.load_dir(dir.path()).await.unwrap(), 1);
+
+        assert!(store.delete(dir.path(), "tagger").await.unwrap());
+        assert!(!store.delete(dir.path(), "tagger").await.unwrap());
+        assert!(store.list().await.is_empty());
+    }
+}
@@ -0,0 +1,438 @@
+//! Pause-and-edit interception
+//!
+//! Agents hold requests matched by a pause rule and report them as
+//! `intercepted` traffic events. The queue keeps them until someone forwards
+//! (optionally edited) or drops them, then sends the decision back to the
+//! agent holding the request.
+
+use crate::models::settings::{InterceptionConfig, RuleAction, RuleCondition};
+use crate::pb::{
+    intercept_command, intercept_decision, intercept_rule, HttpRequestData, InterceptCommand,
+    InterceptDecision, InterceptRule, InterceptRuleStats, InterceptRules,
+};
+use crate::AgentRegistry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::{broadcast, RwLock};
+use tracing::{info, warn};
+
+#[derive(Debug, thiserror::Error)]
+pub enum InterceptError {
+    #[error("No intercepted request with ID {0}")]
+    NotFound(String),
+    #[error("Agent {0} holding the request is not connected")]
+    AgentUnavailable(String),
+}
+
+/// Request held by an agent, waiting for a decision
+#[derive(Debug, Clone)]
+pub struct InterceptedRequest {
+    pub request_id: String,
+    pub agent_id: String,
+    pub request: HttpRequestData,
+    /// Unix seconds
+    pub received_at: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterceptStatus {
+    Pending,
+    Forwarded,
+    Dropped,
+    /// The agent stopped waiting (hold timeout or disconnect)
+    Expired,
+}
+
+/// Change to the queue, as published to subscribers
+#[derive(Debug, Clone)]
+pub struct InterceptUpdate {
+    pub status: InterceptStatus,
+    pub intercept: InterceptedRequest,
+}
+
+/// Counters of one interception rule, summed over the agents
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleStatsSummary {
+    pub rule_id: String,
+    pub name: String,
+    /// Whether agents evaluate the rule: interception and the rule are
+    /// enabled, and it is a pause or drop rule
+    pub active: bool,
+    pub evaluations: u64,
+    pub hits: u64,
+    /// Unix seconds of the latest match on any agent
+    pub last_hit: Option<i64>,
+    /// Total time agents spent evaluating the rule
+    pub eval_time_ns: u64,
+}
+
+impl RuleStatsSummary {
+    /// Average evaluation cost per request, in microseconds
+    pub fn avg_eval_micros(&self) -> f64 {
+        if self.evaluations == 0 {
+            0.0
+        } else {
+            self.eval_time_ns as f64 / self.evaluations as f64 / 1000.0
+        }
+    }
+}
+
+pub struct InterceptQueue {
+    agent_registry: Arc<AgentRegistry>,
+    pending: RwLock<HashMap<String, InterceptedRequest>>,
+    updates: broadcast::Sender<InterceptUpdate>,
+    /// Latest rule counters reported by each agent
+    rule_stats: RwLock<HashMap<String, InterceptRuleStats>>,
+}
+
+impl InterceptQueue {
+    pub fn new(agent_registry: Arc<AgentRegistry>) -> Self {
+        let (updates, _) = broadcast::channel(256);
+        Self {
+            agent_registry,
+            pending: RwLock::new(HashMap::new()),
+            updates,
+            rule_stats: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<InterceptUpdate> {
+        self.updates.subscribe()
+    }
+
+    /// Queue a request an agent reported as held
+    pub async fn hold(&self, agent_id: &str, request_id: &str, request: HttpRequestData) {
+        let intercept = InterceptedRequest {
+            request_id: request_id.to_string(),
+            agent_id: agent_id.to_string(),
+            request,
+            received_at: chrono::Utc::now().timestamp(),
+        };
+        info!("Request {} held by agent {}: {} {}", request_id, agent_id, intercept.request.method, intercept.request.url);
+        self.pending.write().await.insert(request_id.to_string(), intercept.clone());
+        self.publish(InterceptStatus::Pending, intercept);
+    }
+
+    /// Pending requests, oldest first
+    pub async fn list(&self) -> Vec<InterceptedRequest> {
+        let mut pending: Vec<_> = self.pending.read().await.values().cloned().collect();
+        pending.sort_by(|a, b| a.received_at.cmp(&b.received_at).then_with(|| a.request_id.cmp(&b.request_id)));
+        pending
+    }
+
+    pub async fn get(&self, request_id: &str) -> Option<InterceptedRequest> {
+        self.pending.read().await.get(request_id).cloned()
+    }
+
+    /// Let the request through, replaced by `edited` if given
+    pub async fn forward(
+        &self,
+        request_id: &str,
+        edited: Option<HttpRequestData>,
+    ) -> Result<InterceptedRequest, InterceptError> {
+        self.decide(request_id, intercept_decision::Action::Forward, edited).await
+    }
+
+    /// Answer the request with an error instead of sending it upstream
+    pub async fn drop_request(&self, request_id: &str) -> Result<InterceptedRequest, InterceptError> {
+        self.decide(request_id, intercept_decision::Action::Drop, None).await
+    }
+
+    async fn decide(
+        &self,
+        request_id: &str,
+        action: intercept_decision::Action,
+        edited: Option<HttpRequestData>,
+    ) -> Result<InterceptedRequest, InterceptError> {
+        let mut intercept = self
+            .pending
+            .write()
+            .await
+            .remove(request_id)
+            .ok_or_else(|| InterceptError::NotFound(request_id.to_string()))?;
+
+        let command = InterceptCommand {
+            command: Some(intercept_command::Command::Decision(InterceptDecision {
+                request_id: request_id.to_string(),
+                action: action as i32,
+                request: edited.clone(),
+            })),
+        };
+        let sent = match self.agent_registry.get_agent_tx(&intercept.agent_id) {
+            Some(tx) => tx.send(Ok(command)).await.is_ok(),
+            None => false,
+        };
+        if !sent {
+            self.publish(InterceptStatus::Expired, intercept.clone());
+            return Err(InterceptError::AgentUnavailable(intercept.agent_id));
+        }
+
+        if let Some(edited) = edited {
+            intercept.request = edited;
+        }
+        let status = match action {
+            intercept_decision::Action::Forward => InterceptStatus::Forwarded,
+            intercept_decision::Action::Drop => InterceptStatus::Dropped,
+        };
+        self.publish(status, intercept.clone());
+        Ok(intercept)
+    }
+
+    /// The agent sent `request_id` on without a decision from here (its hold
+    /// timed out); forget it
+    pub async fn expire(&self, request_id: &str) {
+        let removed = self.pending.write().await.remove(request_id);
+        if let Some(intercept) = removed {
+            warn!("Intercepted request {} was released by agent {} without a decision", request_id, intercept.agent_id);
+            self.publish(InterceptStatus::Expired, intercept);
+        }
+    }
+
+    /// Forget every request held by a disconnected agent
+    pub async fn expire_agent(&self, agent_id: &str) {
+        let expired: Vec<InterceptedRequest> = {
+            let mut pending = self.pending.write().await;
+            let ids: Vec<String> = pending
+                .values()
+                .filter(|i| i.agent_id == agent_id)
+                .map(|i| i.request_id.clone())
+                .collect();
+            ids.iter().filter_map(|id| pending.remove(id)).collect()
+        };
+        for intercept in expired {
+            self.publish(InterceptStatus::Expired, intercept);
+        }
+    }
+
+    /// Send the rules in `config` to every connected agent
+    pub async fn push_rules(&self, config: &InterceptionConfig) {
+        let command = rules_command(config);
+        for agent in self.agent_registry.list_agents() {
+            if agent.command_tx.send(Ok(command.clone())).await.is_err() {
+                warn!("Failed to push interception rules to agent {}", agent.id);
+            }
+        }
+    }
+
+    /// Store the counters an agent reported; each report replaces the last.
+    /// Reports of disconnected agents are kept so their matches still count.
+    pub async fn record_rule_stats(&self, agent_id: &str, stats: InterceptRuleStats) {
+        self.rule_stats.write().await.insert(agent_id.to_string(), stats);
+    }
+
+    /// Counters of every rule in `config`, in rule order
+    pub async fn rule_stats(&self, config: &InterceptionConfig) -> Vec<RuleStatsSummary> {
+        let reports = self.rule_stats.read().await;
+        config
+            .rules
+            .iter()
+            .map(|rule| {
+                let mut summary = RuleStatsSummary {
+                    rule_id: rule.id.clone(),
+                    name: rule.name.clone(),
+                    active: config.enabled && rule.enabled && !matches!(rule.action, RuleAction::Modify),
+                    evaluations: 0,
+                    hits: 0,
+                    last_hit: None,
+                    eval_time_ns: 0,
+                };
+                for stat in reports.values().flat_map(|r| &r.rules).filter(|s| s.rule_id == rule.id) {
+                    summary.evaluations += stat.evaluations;
+                    summary.hits += stat.hits;
+                    summary.eval_time_ns += stat.eval_time_ns;
+                    if stat.last_hit > 0 {
+                        summary.last_hit = summary.last_hit.max(Some(stat.last_hit));
+                    }
+                }
+                summary
+            })
+            .collect()
+    }
+
+    fn publish(&self, status: InterceptStatus, intercept: InterceptedRequest) {
+        // No subscribers is fine
+        let _ = self.updates.send(InterceptUpdate { status, intercept });
+    }
+}
+
+/// Agent command carrying the enabled pause and drop rules of `config`.
+/// Modify rules have no agent-side behaviour yet and are left out.
+pub fn rules_command(config: &InterceptionConfig) -> InterceptCommand {
+    let rules = config
+        .rules
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| {
+            let action = match rule.action {
+                RuleAction::Pause => intercept_rule::Action::Pause,
+                RuleAction::Drop => intercept_rule::Action::Drop,
+                RuleAction::Modify => return None,
+            };
+            let mut agent_rule = InterceptRule {
+                id: rule.id.clone(),
+                action: action as i32,
+                ..Default::default()
+            };
+            match &rule.condition {
+                RuleCondition::Method { methods } => agent_rule.methods = methods.clone(),
+                RuleCondition::UrlContains { pattern } => agent_rule.url_contains = pattern.clone(),
+                RuleCondition::HeaderMatch { header, value } => {
+                    agent_rule.header_name = header.clone();
+                    agent_rule.header_value = value.clone();
+                }
+                RuleCondition::All => {}
+            }
+            Some(agent_rule)
+        })
+        .collect();
+
+    InterceptCommand {
+        command: Some(intercept_command::Command::InterceptRules(InterceptRules {
+            enabled: config.enabled,
+            rules,
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::settings::InterceptionRule;
+    use tokio::sync::mpsc;
+
+    fn rule(id: &str, enabled: bool, condition: RuleCondition, action: RuleAction) -> InterceptionRule {
+        InterceptionRule {
+            id: id.to_string(),
+            enabled,
+            name: id.to_string(),
+            condition,
+            action,
+        }
+    }
+
+    #[test]
+    fn test_rules_command() {
+        let config = InterceptionConfig {
+            enabled: true,
+            rules: vec![
+                rule("login", true, RuleCondition::UrlContains { pattern: "/login".to_string() }, RuleAction::Pause),
+                rule("off", false, RuleCondition::All, RuleAction::Drop),
+                rule("modify", true, RuleCondition::All, RuleAction::Modify),
+                rule(
+                    "auth",
+                    true,
+                    RuleCondition::HeaderMatch { header: "Authorization".to_string(), value: "Bearer".to_string() },
+                    RuleAction::Drop,
+                ),
+            ],
+            version: 3,
+        };
+
+        let Some(intercept_command::Command::InterceptRules(rules)) = rules_command(&config).command else {
+            panic!("expected rules command");
+        };
+        assert!(rules.enabled);
+        let ids: Vec<_> = rules.rules.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["login", "auth"]);
+        assert_eq!(rules.rules[0].action(), intercept_rule::Action::Pause);
+        assert_eq!(rules.rules[0].url_contains, "/login");
+        assert_eq!(rules.rules[1].action(), intercept_rule::Action::Drop);
+        assert_eq!(rules.rules[1].header_name, "Authorization");
+    }
+
+    #[tokio::test]
+    async fn test_forward_with_edits_and_drop() {
+        let registry = Arc::new(AgentRegistry::new());
+        let (tx, mut rx) = mpsc::channel(10);
+        registry.register_agent("agent-1".to_string(), "A".to_string(), "host".to_string(), "0.1.0".to_string(), tx);
+
+        let queue = InterceptQueue::new(registry);
+        let mut updates = queue.subscribe();
+        let original = HttpRequestData {
+            method: "POST".to_string(),
+            url: "https://a.test/login".to_string(),
+            body: b"user=a".to_vec().into(),
+            ..Default::default()
+        };
+        queue.hold("agent-1", "r1", original.clone()).await;
+        queue.hold("agent-1", "r2", original).await;
+        assert_eq!(queue.list().await.len(), 2);
+        assert_eq!(updates.recv().await.unwrap().status, InterceptStatus::Pending);
+
+        let edited = HttpRequestData {
+            method: "PUT".to_string(),
+            url: "https://a.test/login?admin=1".to_string(),
+            body: b"user=admin".to_vec().into(),
+            ..Default::default()
+        };
+        let forwarded = queue.forward("r1", Some(edited.clone())).await.unwrap();
+        assert_eq!(forwarded.request.method, "PUT");
+        let Some(intercept_command::Command::Decision(decision)) = rx.recv().await.unwrap().unwrap().command else {
+            panic!("expected decision");
+        };
+        assert_eq!(decision.request_id, "r1");
+        assert_eq!(decision.action(), intercept_decision::Action::Forward);
+        assert_eq!(decision.request, Some(edited));
+
+        queue.drop_request("r2").await.unwrap();
+        let Some(intercept_command::Command::Decision(decision)) = rx.recv().await.unwrap().unwrap().command else {
+            panic!("expected decision");
+        };
+        assert_eq!(decision.action(), intercept_decision::Action::Drop);
+        assert!(queue.list().await.is_empty());
+        assert!(matches!(queue.forward("r1", None).await, Err(InterceptError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_rule_stats_summed_over_agents() {
+        use crate::pb::InterceptRuleStat;
+
+        let queue = InterceptQueue::new(Arc::new(AgentRegistry::new()));
+        let stat = |id: &str, evaluations, hits, last_hit| InterceptRuleStat {
+            rule_id: id.to_string(),
+            evaluations,
+            hits,
+            last_hit,
+            eval_time_ns: evaluations * 2_000,
+        };
+        queue
+            .record_rule_stats("agent-1", InterceptRuleStats { rules: vec![stat("login", 10, 2, 100)] })
+            .await;
+        queue
+            .record_rule_stats("agent-2", InterceptRuleStats { rules: vec![stat("login", 5, 1, 200)] })
+            .await;
+        // A newer report replaces the agent's previous one
+        queue
+            .record_rule_stats("agent-2", InterceptRuleStats { rules: vec![stat("login", 6, 1, 200)] })
+            .await;
+
+        let config = InterceptionConfig {
+            enabled: true,
+            rules: vec![
+                rule("login", true, RuleCondition::UrlContains { pattern: "/login".to_string() }, RuleAction::Pause),
+                rule("modify", true, RuleCondition::All, RuleAction::Modify),
+            ],
+            version: 1,
+        };
+        let stats = queue.rule_stats(&config).await;
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].evaluations, stats[0].hits, stats[0].last_hit), (16, 3, Some(200)));
+        assert!(stats[0].active);
+        assert_eq!(stats[0].avg_eval_micros(), 2.0);
+        assert!(!stats[1].active);
+        assert_eq!((stats[1].hits, stats[1].last_hit), (0, None));
+    }
+
+    #[tokio::test]
+    async fn test_disconnected_agent() {
+        let queue = InterceptQueue::new(Arc::new(AgentRegistry::new()));
+        queue.hold("gone", "r1", HttpRequestData::default()).await;
+        assert!(matches!(queue.forward("r1", None).await, Err(InterceptError::AgentUnavailable(_))));
+
+        queue.hold("gone", "r2", HttpRequestData::default()).await;
+        queue.hold("other", "r3", HttpRequestData::default()).await;
+        queue.expire_agent("gone").await;
+        let left: Vec<_> = queue.list().await.into_iter().map(|i| i.request_id).collect();
+        assert_eq!(left, vec!["r3"]);
+    }
+}
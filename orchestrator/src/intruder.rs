@@ -3,19 +3,29 @@
 //! This module provides the IntruderManager struct that handles attack configuration,
 //! payload set management, agent selection, and attack template creation/validation.
 
+pub mod baseline;
 pub mod distribution;
 pub mod execution;
+pub mod flow_sessions;
+pub mod highlighting;
+pub mod sequence;
 
 use crate::database::intruder::{IntruderAttack, PayloadSet};
 use crate::Database;
 use crate::session_integration::{SessionManager, SessionApplicationResult, ExpirationHandling, SessionSelectionCriteria, SessionRefreshResult};
 use attack_engine::{
     AttackError, AttackResult, PayloadConfig, PayloadGeneratorFactory,
-    PayloadPosition, PayloadPositionParser, AttackMode,
-    DistributionStrategy, ExecutionConfig, AgentInfo, AgentStatus
+    PayloadPosition, PayloadPositionParser, AttackMode, AttackModeExecutor, AttackModeFactory, GraphQlAttackConfig, GraphQlMode,
+    DistributionStrategy, ExecutionConfig, AgentInfo, AgentStatus,
+    UploadCatalog, UploadTemplate
 };
+use baseline::{AttackBaseline, MAX_BASELINE_REQUESTS};
 use distribution::{IntruderPayloadDistributor, DistributionStats};
-use execution::{AttackExecutionCoordinator, AttackProgress, AttackExecutionConfig};
+use distribution::PayloadAssignment;
+use execution::{AttackCheckpoint, AttackExecutionCoordinator, AttackProgress, AttackExecutionConfig};
+use flow_sessions::FlowSessionConfig;
+use highlighting::ResultHighlightRule;
+use sequence::AttackSequence;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -33,6 +43,19 @@ pub struct IntruderAttackConfig {
     pub distribution_strategy: DistributionStrategy,
     pub session_data: Option<Session>,
     pub execution_config: Option<ExecutionConfig>,
+    /// Mint fresh sessions from a recorded flow while the attack runs
+    #[serde(default)]
+    pub flow_session: Option<FlowSessionConfig>,
+    /// Multipart body with payload positions inside its parts, sent in place
+    /// of the template's body
+    #[serde(default)]
+    pub upload: Option<UploadTemplate>,
+    /// Rules marking anomalous results as they arrive
+    #[serde(default)]
+    pub highlight_rules: Vec<ResultHighlightRule>,
+    /// Requests sent before each of the attack's own, feeding it values
+    #[serde(default)]
+    pub sequence: Option<AttackSequence>,
 }
 
 /// Configuration for a payload set within an attack
@@ -63,6 +86,53 @@ pub struct AttackStatistics {
     pub payload_distribution: HashMap<String, usize>, // agent_id -> payload_count
 }
 
+/// Concurrency each agent gets when an attack runs
+const DEFAULT_CONCURRENT_REQUESTS_PER_AGENT: u32 = 10;
+
+/// Inputs of an attack dry run
+#[derive(Debug, Clone)]
+pub struct SimulationOptions {
+    /// Generated requests to return
+    pub sample_size: usize,
+    /// Overall rate limit, if any
+    pub requests_per_second: Option<f64>,
+    /// Expected response time, which bounds each agent's throughput
+    pub average_latency_ms: u64,
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            sample_size: 10,
+            requests_per_second: None,
+            average_latency_ms: 500,
+        }
+    }
+}
+
+/// One request an attack would send
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedRequest {
+    pub index: usize,
+    /// Raw request with payloads injected
+    pub request: String,
+    /// Preview of the multipart body of upload attacks
+    pub upload_body: Option<String>,
+    pub payload_values: HashMap<String, String>,
+}
+
+/// Outcome of an attack dry run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackSimulation {
+    pub total_requests: u64,
+    pub payload_positions: usize,
+    pub agent_count: usize,
+    /// Expected throughput once the attack runs
+    pub requests_per_second: f64,
+    pub estimated_duration_seconds: f64,
+    pub sample: Vec<SimulatedRequest>,
+}
+
 /// Manager for intruder attack operations
 pub struct IntruderManager {
     db: Arc<Database>,
@@ -74,11 +144,14 @@ pub struct IntruderManager {
 impl IntruderManager {
     /// Create a new IntruderManager
     pub async fn new(db: Arc<Database>) -> AttackResult<Self> {
-        let execution_coordinator = AttackExecutionCoordinator::new(db.clone()).await?;
+        let session_manager = Arc::new(SessionManager::new());
+        let execution_coordinator = AttackExecutionCoordinator::new(db.clone())
+            .await?
+            .with_session_manager(session_manager.clone());
         
         Ok(Self {
             db,
-            session_manager: Arc::new(SessionManager::new()),
+            session_manager,
             distributor: IntruderPayloadDistributor::new(),
             execution_coordinator,
         })
@@ -116,6 +189,7 @@ impl IntruderManager {
             AttackMode::BatteringRam => "battering_ram",
             AttackMode::Pitchfork => "pitchfork",
             AttackMode::ClusterBomb => "cluster_bomb",
+            AttackMode::GraphQl(_) => "graphql",
         }.to_string();
 
         // Create attack in database
@@ -130,6 +204,78 @@ impl IntruderManager {
             operation: format!("create_intruder_attack: {}", e),
         })?;
 
+        if let Some(flow_session) = &config.flow_session {
+            let flow_session_json = serde_json::to_string(flow_session)
+                .map_err(|e| AttackError::SerializationError {
+                    error: format!("Failed to serialize flow session: {}", e),
+                })?;
+            self.db.set_intruder_attack_flow_session(&attack_id, Some(&flow_session_json))
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("set_intruder_attack_flow_session: {}", e),
+                })?;
+        }
+
+        if let AttackMode::GraphQl(graphql) = &config.attack_mode {
+            let graphql_json = serde_json::to_string(graphql)
+                .map_err(|e| AttackError::SerializationError {
+                    error: format!("Failed to serialize GraphQL settings: {}", e),
+                })?;
+            self.db.set_intruder_attack_graphql_config(&attack_id, Some(&graphql_json))
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("set_intruder_attack_graphql_config: {}", e),
+                })?;
+        }
+
+        if let Some(upload) = &config.upload {
+            let upload_json = serde_json::to_string(upload)
+                .map_err(|e| AttackError::SerializationError {
+                    error: format!("Failed to serialize upload template: {}", e),
+                })?;
+            self.db.set_intruder_attack_upload_template(&attack_id, Some(&upload_json))
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("set_intruder_attack_upload_template: {}", e),
+                })?;
+        }
+
+        if !config.highlight_rules.is_empty() {
+            let highlight_rules_json = serde_json::to_string(&config.highlight_rules)
+                .map_err(|e| AttackError::SerializationError {
+                    error: format!("Failed to serialize highlight rules: {}", e),
+                })?;
+            self.db.set_intruder_attack_highlight_rules(&attack_id, Some(&highlight_rules_json))
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("set_intruder_attack_highlight_rules: {}", e),
+                })?;
+        }
+
+        if let Some(sequence) = &config.sequence {
+            let sequence_json = serde_json::to_string(sequence)
+                .map_err(|e| AttackError::SerializationError {
+                    error: format!("Failed to serialize sequence: {}", e),
+                })?;
+            self.db.set_intruder_attack_sequence(&attack_id, Some(&sequence_json))
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("set_intruder_attack_sequence: {}", e),
+                })?;
+        }
+
+        if let Some(execution_config) = &config.execution_config {
+            let execution_config_json = serde_json::to_string(execution_config)
+                .map_err(|e| AttackError::SerializationError {
+                    error: format!("Failed to serialize execution config: {}", e),
+                })?;
+            self.db.set_intruder_attack_execution_config(&attack_id, Some(&execution_config_json))
+                .await
+                .map_err(|e| AttackError::DatabaseError {
+                    operation: format!("set_intruder_attack_execution_config: {}", e),
+                })?;
+        }
+
         Ok(attack_id)
     }
 
@@ -185,7 +331,7 @@ impl IntruderManager {
         }
 
         // Parse payload positions
-        let payload_positions = match PayloadPositionParser::parse(&config.request_template) {
+        let mut payload_positions = match PayloadPositionParser::parse(&config.request_template) {
             Ok(parsed) => parsed.positions,
             Err(e) => {
                 errors.push(format!("Invalid payload position syntax: {}", e));
@@ -193,8 +339,35 @@ impl IntruderManager {
             }
         };
 
-        // Validate payload positions match payload sets
-        if payload_positions.len() != config.payload_sets.len() {
+        // Upload positions follow the template's own
+        if let Some(upload) = &config.upload {
+            match upload.validate() {
+                Ok(()) => {
+                    let upload_positions = upload.payload_positions(payload_positions.len());
+                    payload_positions.extend(upload_positions);
+                }
+                Err(e) => errors.push(format!("Invalid upload template: {}", e)),
+            }
+        }
+
+        // Then those of the sequence steps
+        if let Some(sequence) = &config.sequence {
+            match sequence.validate() {
+                Ok(()) => {
+                    let sequence_positions = sequence.payload_positions(payload_positions.len());
+                    payload_positions.extend(sequence_positions);
+                }
+                Err(e) => errors.push(e.to_string()),
+            }
+            if matches!(config.attack_mode, AttackMode::GraphQl(_)) {
+                errors.push("GraphQL attacks cannot run as sequences".to_string());
+            }
+        }
+
+        // Validate payload positions match payload sets; GraphQL attacks
+        // generate their own probes instead
+        let is_graphql = matches!(config.attack_mode, AttackMode::GraphQl(_));
+        if !is_graphql && payload_positions.len() != config.payload_sets.len() {
             errors.push(format!(
                 "Mismatch between payload positions ({}) and payload sets ({})",
                 payload_positions.len(),
@@ -232,6 +405,15 @@ impl IntruderManager {
                 }
             }
 
+            if let PayloadConfig::UploadCatalog { catalog: UploadCatalog::Oversized { agent_files, .. } } = &payload_set.payload_config {
+                if !agent_files.is_empty() {
+                    warnings.push(format!(
+                        "Payload set '{}' reads files from the agents' disks; they must exist on every target agent",
+                        payload_set.name
+                    ));
+                }
+            }
+
             // Validate position index
             if payload_set.position_index >= payload_positions.len() {
                 errors.push(format!(
@@ -241,16 +423,76 @@ impl IntruderManager {
             }
         }
 
-        // Validate target agents
+        // Validate flow-generated sessions
+        if let Some(flow_session) = &config.flow_session {
+            if let Err(e) = flow_session.validate() {
+                errors.push(e.to_string());
+            } else if flow_session.requests_per_session == 1 {
+                warnings.push("A flow replay will run before every request".to_string());
+            }
+        }
+
+        // Validate rate limits and header randomization
+        if let Some(execution_config) = &config.execution_config {
+            if let Err(e) = execution_config.rate_limit.validate() {
+                errors.push(e);
+            }
+            if let Err(e) = execution_config.header_randomization.validate() {
+                errors.push(e);
+            }
+            if let Err(e) = execution_config.pacing.validate() {
+                errors.push(e);
+            }
+            if execution_config.baseline_requests > MAX_BASELINE_REQUESTS {
+                errors.push(format!("At most {} baseline requests can be sent", MAX_BASELINE_REQUESTS));
+            }
+            if let Some(timing) = &execution_config.timing_confirmation {
+                if let Err(e) = timing.validate() {
+                    errors.push(e);
+                }
+                if execution_config.baseline_requests == 0 {
+                    errors.push("Delay confirmation compares against the baseline: set baseline requests".to_string());
+                }
+            }
+        }
+
+        // Validate highlight rules
+        for rule in &config.highlight_rules {
+            if let Err(e) = rule.validate() {
+                errors.push(e);
+            }
+        }
+
+        // Validate target agents; without any the project's default agent
+        // is resolved when the attack starts
         if config.target_agents.is_empty() {
-            errors.push("At least one target agent must be specified".to_string());
+            let default_agent = self.db.get_default_agent_config().await.map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_default_agent_config: {}", e),
+            })?;
+            match default_agent.agent_id {
+                Some(agent_id) => warnings.push(format!("No target agent selected; the attack runs on the default agent ({})", agent_id)),
+                None => errors.push("At least one target agent must be specified".to_string()),
+            }
         }
 
         // Estimate total requests based on attack mode
-        let estimated_requests = if !config.payload_sets.is_empty() && errors.is_empty() {
-            self.estimate_request_count(&config.attack_mode, &config.payload_sets).await.ok()
-        } else {
-            None
+        let estimated_requests = match &config.attack_mode {
+            AttackMode::GraphQl(graphql) if errors.is_empty() => {
+                let probes = PayloadPositionParser::parse(&config.request_template).and_then(|template| {
+                    GraphQlMode::new(graphql.clone()).count_requests(&template, &HashMap::new())
+                });
+                match probes {
+                    Ok(count) => Some(count),
+                    Err(e) => {
+                        errors.push(format!("Invalid GraphQL attack: {}", e));
+                        None
+                    }
+                }
+            }
+            _ if !config.payload_sets.is_empty() && errors.is_empty() => {
+                self.estimate_request_count(&config.attack_mode, &config.payload_sets).await.ok()
+            }
+            _ => None,
         };
 
         // Warn about large attacks
@@ -296,6 +538,11 @@ impl IntruderManager {
                 // Multiple positions, all combinations - multiply all counts
                 payload_counts.iter().product()
             }
+            AttackMode::GraphQl(_) => {
+                return Err(AttackError::InvalidAttackConfig {
+                    reason: "GraphQL attacks are sized by their probes, not payload sets".to_string(),
+                });
+            }
         };
 
         Ok(total_requests)
@@ -474,11 +721,7 @@ impl IntruderManager {
         let generator = PayloadGeneratorFactory::create(payload_config)?;
         generator.validate()?;
 
-        let payload_type = match payload_config {
-            PayloadConfig::Wordlist { .. } => "wordlist",
-            PayloadConfig::NumberRange { .. } => "number_range",
-            PayloadConfig::Custom { .. } => "custom",
-        };
+        let payload_type = payload_type_name(payload_config);
 
         let config_json = serde_json::to_string(payload_config)
             .map_err(|e| AttackError::InvalidPayloadConfig {
@@ -598,14 +841,65 @@ impl IntruderManager {
         self.execution_coordinator.stop_attack(attack_id).await
     }
 
-    /// Pause an active attack
-    pub async fn pause_attack_execution(&self, attack_id: &str) -> AttackResult<()> {
+    /// Pause an active attack, storing its progress with the attack
+    pub async fn pause_attack_execution(&self, attack_id: &str) -> AttackResult<AttackCheckpoint> {
         self.execution_coordinator.pause_attack(attack_id).await
     }
 
-    /// Resume a paused attack
-    pub async fn resume_attack_execution(&self, attack_id: &str) -> AttackResult<()> {
-        self.execution_coordinator.resume_attack(attack_id).await
+    /// Checkpoint of a paused attack
+    pub async fn get_attack_checkpoint(&self, attack_id: &str) -> AttackResult<Option<AttackCheckpoint>> {
+        self.db.get_intruder_attack_checkpoint(attack_id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_checkpoint: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<AttackCheckpoint>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse checkpoint: {}", e),
+            })
+    }
+
+    /// Typical response of the attack's unmodified request, measured when it
+    /// was last started with baseline requests
+    pub async fn get_attack_baseline(&self, attack_id: &str) -> AttackResult<Option<AttackBaseline>> {
+        self.execution_coordinator.stored_baseline(attack_id).await
+    }
+
+    /// Resume a paused attack from its checkpoint, also after an orchestrator
+    /// restart. Payloads are not redistributed: each assignment continues
+    /// where it stopped, and assignments of agents that are no longer online
+    /// move to online ones.
+    pub async fn resume_attack_execution(
+        &self,
+        attack_id: &str,
+        available_agents: &[AgentInfo],
+    ) -> AttackResult<AttackCheckpoint> {
+        let attack = self.get_attack(attack_id).await?.ok_or_else(|| AttackError::InvalidAttackConfig {
+            reason: format!("Attack {} not found", attack_id),
+        })?;
+        let mut checkpoint = self.get_attack_checkpoint(attack_id).await?.ok_or_else(|| {
+            AttackError::InvalidAttackConfig {
+                reason: format!("Attack {} has no saved progress to resume", attack_id),
+            }
+        })?;
+
+        let online: Vec<String> = available_agents
+            .iter()
+            .filter(|agent| agent.status == AgentStatus::Online)
+            .map(|agent| agent.id.clone())
+            .collect();
+        let moved = reassign_offline_assignments(&mut checkpoint.distribution.assignments, &online);
+        if moved > 0 {
+            tracing::info!("Attack {}: moved {} assignment(s) of offline agents", attack_id, moved);
+        }
+
+        let attack_mode = self.attack_mode(&attack).await?;
+        let mut config = self.execution_config(&attack, attack_mode, checkpoint.distribution.clone()).await?;
+        config.resume_cursors = checkpoint.cursors.clone();
+        self.execution_coordinator.start_attack(config, available_agents).await?;
+
+        Ok(checkpoint)
     }
 
     /// Get current progress for an attack
@@ -623,6 +917,11 @@ impl IntruderManager {
         self.execution_coordinator.subscribe_to_progress()
     }
 
+    /// Subscribe to results as they are recorded, and to attack completions
+    pub fn subscribe_to_results(&self) -> tokio::sync::broadcast::Receiver<crate::result_streaming::ResultUpdate> {
+        self.execution_coordinator.subscribe_to_results()
+    }
+
     /// Create an execution configuration from attack configuration
     pub async fn create_execution_config(
         &self,
@@ -630,15 +929,7 @@ impl IntruderManager {
         available_agents: &[AgentInfo],
     ) -> AttackResult<AttackExecutionConfig> {
         // Parse attack mode
-        let attack_mode = match attack.attack_mode.as_str() {
-            "sniper" => AttackMode::Sniper,
-            "battering_ram" => AttackMode::BatteringRam,
-            "pitchfork" => AttackMode::Pitchfork,
-            "cluster_bomb" => AttackMode::ClusterBomb,
-            _ => return Err(AttackError::InvalidPayloadConfig {
-                reason: format!("Unknown attack mode: {}", attack.attack_mode),
-            }),
-        };
+        let attack_mode = self.attack_mode(attack).await?;
 
         // Parse distribution strategy
         let distribution_strategy = if attack.distribution_strategy.starts_with("batch:") {
@@ -661,8 +952,14 @@ impl IntruderManager {
                 reason: format!("Failed to parse payload sets: {}", e),
             })?;
 
-        // Generate payloads for distribution
+        // Generate payloads for distribution; GraphQL probes are handed out
+        // by index
         let mut all_payloads = Vec::new();
+        if let AttackMode::GraphQl(graphql) = &attack_mode {
+            let template = PayloadPositionParser::parse(&attack.request_template)?;
+            let count = GraphQlMode::new(graphql.clone()).count_requests(&template, &HashMap::new())?;
+            all_payloads.extend((0..count).map(|index| index.to_string()));
+        }
         for payload_set in &payload_sets {
             let generator = PayloadGeneratorFactory::create(&payload_set.payload_config)?;
             let payloads = generator.generate().await?;
@@ -670,12 +967,73 @@ impl IntruderManager {
         }
 
         // Create distribution
+        let agents = self.execution_agents(attack, available_agents).await?;
         let distribution = self.distribute_payloads(
             all_payloads,
-            available_agents,
+            &agents,
             &distribution_strategy,
         ).await?;
 
+        self.execution_config(attack, attack_mode, distribution).await
+    }
+
+    /// Agents an attack's payloads are distributed over: the available ones,
+    /// or for an attack without target agents the project's default agent
+    /// (or the first online fallback) at the time it starts
+    async fn execution_agents(&self, attack: &IntruderAttack, available_agents: &[AgentInfo]) -> AttackResult<Vec<AgentInfo>> {
+        let target_agents: Vec<String> = serde_json::from_str(&attack.target_agents).unwrap_or_default();
+        if !target_agents.is_empty() {
+            return Ok(available_agents.to_vec());
+        }
+
+        let config = self.db.get_default_agent_config().await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("get_default_agent_config: {}", e),
+        })?;
+        let online: Vec<String> = available_agents
+            .iter()
+            .filter(|agent| agent.status == AgentStatus::Online)
+            .map(|agent| agent.id.clone())
+            .collect();
+        let agent_id = config.resolve(&online).ok_or_else(|| AttackError::AgentUnavailable {
+            agent_id: config.agent_id.clone().unwrap_or_else(|| "default agent (none configured)".to_string()),
+        })?;
+        tracing::info!("Attack {} runs on agent {}", attack.id, agent_id);
+        Ok(available_agents.iter().filter(|agent| agent.id == agent_id).cloned().collect())
+    }
+
+    /// Execution configuration of a stored attack with a given distribution
+    async fn execution_config(
+        &self,
+        attack: &IntruderAttack,
+        attack_mode: AttackMode,
+        distribution: DistributionStats,
+    ) -> AttackResult<AttackExecutionConfig> {
+        let flow_session = self.db.get_intruder_attack_flow_session(&attack.id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_flow_session: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<FlowSessionConfig>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse flow session: {}", e),
+            })?;
+
+        let upload = self.attack_upload_template(&attack.id).await?;
+        let sequence = self.attack_sequence(&attack.id).await?;
+        let settings = self.attack_execution_settings(&attack.id).await?;
+        let highlight_rules = self.db.get_intruder_attack_highlight_rules(&attack.id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_highlight_rules: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<Vec<ResultHighlightRule>>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse highlight rules: {}", e),
+            })?
+            .unwrap_or_default();
+
         // Create execution config
         Ok(AttackExecutionConfig {
             attack_id: attack.id.clone(),
@@ -683,12 +1041,279 @@ impl IntruderManager {
             attack_mode,
             distribution,
             session_data: None, // TODO: Load session data if specified
-            concurrent_requests_per_agent: 10, // Default value
-            timeout_seconds: 30, // Default value
-            retry_attempts: 3, // Default value
-            result_highlighting_rules: Vec::new(), // TODO: Load highlighting rules
+            flow_session,
+            upload,
+            sequence,
+            concurrent_requests_per_agent: settings.concurrent_requests_per_agent,
+            timeout_seconds: settings.timeout_seconds,
+            retry_attempts: settings.retry_attempts,
+            rate_limit: settings.rate_limit,
+            header_randomization: settings.header_randomization,
+            baseline_requests: settings.baseline_requests,
+            pacing: settings.pacing,
+            timing_confirmation: settings.timing_confirmation,
+            result_highlighting_rules: highlight_rules,
+            resume_cursors: Vec::new(),
         })
     }
+
+    /// Mode of a stored attack, with its GraphQL settings
+    async fn attack_mode(&self, attack: &IntruderAttack) -> AttackResult<AttackMode> {
+        if attack.attack_mode != "graphql" {
+            return parse_attack_mode(&attack.attack_mode);
+        }
+        let config = self.db.get_intruder_attack_graphql_config(&attack.id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_graphql_config: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<GraphQlAttackConfig>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse GraphQL settings: {}", e),
+            })?;
+        Ok(AttackMode::GraphQl(config.unwrap_or_default()))
+    }
+
+    async fn attack_upload_template(&self, attack_id: &str) -> AttackResult<Option<UploadTemplate>> {
+        self.db.get_intruder_attack_upload_template(attack_id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_upload_template: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<UploadTemplate>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse upload template: {}", e),
+            })
+    }
+
+    async fn attack_sequence(&self, attack_id: &str) -> AttackResult<Option<AttackSequence>> {
+        self.db.get_intruder_attack_sequence(attack_id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_sequence: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<AttackSequence>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse sequence: {}", e),
+            })
+    }
+
+    /// Execution settings stored with an attack, or the defaults
+    async fn attack_execution_settings(&self, attack_id: &str) -> AttackResult<ExecutionConfig> {
+        let stored = self.db.get_intruder_attack_execution_config(attack_id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_execution_config: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<ExecutionConfig>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse execution config: {}", e),
+            })?;
+        Ok(stored.unwrap_or_else(|| ExecutionConfig {
+            concurrent_requests_per_agent: DEFAULT_CONCURRENT_REQUESTS_PER_AGENT,
+            ..ExecutionConfig::default()
+        }))
+    }
+
+    /// Dry run of an attack: expand its payload positions, count the requests
+    /// it would send and build the first `options.sample_size` of them.
+    /// Nothing is sent and the attack's status is left alone.
+    pub async fn simulate_attack(
+        &self,
+        attack: &IntruderAttack,
+        options: &SimulationOptions,
+    ) -> AttackResult<AttackSimulation> {
+        let attack_mode = self.attack_mode(attack).await?;
+        let payload_set_configs: Vec<PayloadSetConfig> = serde_json::from_str(&attack.payload_sets)
+            .map_err(|e| AttackError::InvalidPayloadConfig {
+                reason: format!("Failed to parse payload sets: {}", e),
+            })?;
+        let target_agents: Vec<String> = serde_json::from_str(&attack.target_agents).unwrap_or_default();
+
+        let mut template = PayloadPositionParser::parse(&attack.request_template)?;
+        let upload = self.attack_upload_template(&attack.id).await?;
+        if let Some(upload) = &upload {
+            let upload_positions = upload.payload_positions(template.positions.len());
+            template.positions.extend(upload_positions);
+        }
+        if let Some(sequence) = self.attack_sequence(&attack.id).await? {
+            let sequence_positions = sequence.payload_positions(template.positions.len());
+            template.positions.extend(sequence_positions);
+        }
+
+        let (total_requests, sample) = if let AttackMode::GraphQl(_) = &attack_mode {
+            // Probes are cheap to build, so build them all
+            let requests = AttackModeFactory::create(&attack_mode).generate_requests(&template, &HashMap::new())?;
+            let total_requests = requests.len() as u64;
+            let sample = requests
+                .into_iter()
+                .take(options.sample_size)
+                .map(|r| SimulatedRequest {
+                    index: r.index,
+                    request: r.request,
+                    upload_body: None,
+                    payload_values: r.payload_values,
+                })
+                .collect();
+            (total_requests, sample)
+        } else {
+            // Payloads of each position, in position order
+            let mut position_payloads = Vec::with_capacity(template.positions.len());
+            for position in &template.positions {
+                let set = payload_set_configs
+                    .iter()
+                    .find(|set| set.position_index == position.index)
+                    .ok_or_else(|| AttackError::InvalidPayloadConfig {
+                        reason: format!("No payload set for position {} ({})", position.index, position.payload_set_id),
+                    })?;
+                let payloads = PayloadGeneratorFactory::create(&set.payload_config)?.generate().await?;
+                position_payloads.push((position.payload_set_id.clone(), payloads));
+            }
+
+            let total_requests = simulated_request_count(&attack_mode, &position_payloads);
+
+            let mut sample = Vec::new();
+            for index in 0..total_requests.min(options.sample_size as u64) as usize {
+                let payload_values = simulated_payload_values(&attack_mode, &position_payloads, index);
+                let request = PayloadPositionParser::inject_payloads(&template, &payload_values)?;
+                let upload_body = match &upload {
+                    Some(upload) => Some(String::from_utf8_lossy(&upload.render(&payload_values)?.preview()).into_owned()),
+                    None => None,
+                };
+                sample.push(SimulatedRequest {
+                    index,
+                    request,
+                    upload_body,
+                    payload_values,
+                });
+            }
+            (total_requests, sample)
+        };
+
+        // Throughput is bounded by the agents' concurrency, then by the rate
+        // limit given for the simulation or stored with the attack, and by
+        // the attack's pacing
+        let settings = self.attack_execution_settings(&attack.id).await?;
+        let connections = settings
+            .rate_limit
+            .max_connections_per_agent
+            .unwrap_or(settings.concurrent_requests_per_agent)
+            .max(1);
+        let agent_count = target_agents.len().max(1);
+        let latency_ms = options.average_latency_ms.max(1) as f64;
+        let concurrency = (agent_count as u32 * connections) as f64;
+        let mut requests_per_second = concurrency * 1000.0 / latency_ms;
+        let rate_limit = options.requests_per_second.or(settings.rate_limit.max_requests_per_second);
+        if let Some(limit) = rate_limit.filter(|r| *r > 0.0) {
+            requests_per_second = requests_per_second.min(limit);
+        }
+        if let Some(paced) = settings.pacing.sustained_requests_per_second() {
+            requests_per_second = requests_per_second.min(paced);
+        }
+
+        Ok(AttackSimulation {
+            total_requests,
+            payload_positions: template.positions.len(),
+            agent_count: target_agents.len(),
+            requests_per_second,
+            estimated_duration_seconds: total_requests as f64 / requests_per_second,
+            sample,
+        })
+    }
+}
+
+/// Parse an attack mode as stored in `intruder_attacks.attack_mode`
+fn parse_attack_mode(mode: &str) -> AttackResult<AttackMode> {
+    match mode {
+        "sniper" => Ok(AttackMode::Sniper),
+        "battering_ram" => Ok(AttackMode::BatteringRam),
+        "pitchfork" => Ok(AttackMode::Pitchfork),
+        "cluster_bomb" => Ok(AttackMode::ClusterBomb),
+        _ => Err(AttackError::InvalidPayloadConfig {
+            reason: format!("Unknown attack mode: {}", mode),
+        }),
+    }
+}
+
+/// Requests the attack engine generates for `mode`, without generating them
+fn simulated_request_count(mode: &AttackMode, position_payloads: &[(String, Vec<String>)]) -> u64 {
+    let mut lengths = position_payloads.iter().map(|(_, payloads)| payloads.len() as u64);
+    match mode {
+        AttackMode::Sniper | AttackMode::BatteringRam => lengths.next().unwrap_or(0),
+        AttackMode::Pitchfork => lengths.min().unwrap_or(0),
+        AttackMode::ClusterBomb if position_payloads.is_empty() => 0,
+        AttackMode::ClusterBomb => lengths.fold(1u64, |total, len| total.saturating_mul(len)),
+        // Probes are counted by generating them
+        AttackMode::GraphQl(_) => 0,
+    }
+}
+
+/// Payload values of the engine's request number `index`. Cluster bomb
+/// combinations vary the last position fastest.
+fn simulated_payload_values(
+    mode: &AttackMode,
+    position_payloads: &[(String, Vec<String>)],
+    index: usize,
+) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    match mode {
+        AttackMode::Sniper => {
+            if let Some((id, payloads)) = position_payloads.first() {
+                values.insert(id.clone(), payloads[index].clone());
+            }
+        }
+        AttackMode::BatteringRam => {
+            if let Some((_, payloads)) = position_payloads.first() {
+                for (id, _) in position_payloads {
+                    values.insert(id.clone(), payloads[index].clone());
+                }
+            }
+        }
+        AttackMode::Pitchfork => {
+            for (id, payloads) in position_payloads {
+                values.insert(id.clone(), payloads[index].clone());
+            }
+        }
+        AttackMode::ClusterBomb => {
+            let mut rest = index;
+            for (id, payloads) in position_payloads.iter().rev() {
+                values.insert(id.clone(), payloads[rest % payloads.len()].clone());
+                rest /= payloads.len();
+            }
+        }
+        AttackMode::GraphQl(_) => {}
+    }
+    values
+}
+
+/// Give assignments of agents not in `online` to online agents, round
+/// robin. Each assignment keeps its payloads, so its cursor stays valid.
+/// Returns the number moved.
+fn reassign_offline_assignments(assignments: &mut [PayloadAssignment], online: &[String]) -> usize {
+    if online.is_empty() {
+        return 0;
+    }
+    let mut moved = 0;
+    for assignment in assignments.iter_mut().filter(|a| !online.contains(&a.agent_id)) {
+        assignment.agent_id = online[moved % online.len()].clone();
+        moved += 1;
+    }
+    moved
+}
+
+/// Stored type of a payload set; processed payloads keep their source's type
+fn payload_type_name(config: &PayloadConfig) -> &'static str {
+    match config {
+        PayloadConfig::Wordlist { .. } => "wordlist",
+        PayloadConfig::NumberRange { .. } => "number_range",
+        PayloadConfig::Custom { .. } => "custom",
+        PayloadConfig::UploadCatalog { .. } => "upload_catalog",
+        PayloadConfig::Processed { source, .. } => payload_type_name(source),
+    }
 }
 
 #[cfg(test)]
@@ -701,10 +1326,30 @@ mod tests {
     async fn create_test_manager() -> (IntruderManager, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let db = Arc::new(Database::new(temp_dir.path().to_str().unwrap()).await.unwrap());
+        db.create_project("intruder").await.unwrap();
+        db.load_project("intruder").await.unwrap();
         let manager = IntruderManager::new(db).await.unwrap();
         (manager, temp_dir)
     }
 
+    #[test]
+    fn test_reassign_offline_assignments() {
+        let assignment = |agent: &str| PayloadAssignment {
+            agent_id: agent.to_string(),
+            payloads: vec!["x".to_string()],
+            start_index: 0,
+            end_index: 0,
+            priority: 5,
+        };
+        let mut assignments = vec![assignment("a1"), assignment("gone1"), assignment("a2"), assignment("gone2")];
+        let online = vec!["a1".to_string(), "a2".to_string()];
+
+        assert_eq!(reassign_offline_assignments(&mut assignments, &online), 2);
+        let agents: Vec<&str> = assignments.iter().map(|a| a.agent_id.as_str()).collect();
+        assert_eq!(agents, ["a1", "a1", "a2", "a2"]);
+        assert_eq!(reassign_offline_assignments(&mut assignments, &[]), 0);
+    }
+
     #[tokio::test]
     async fn test_create_attack_template() {
         let (manager, _temp_dir) = create_test_manager().await;
@@ -740,6 +1385,10 @@ mod tests {
             distribution_strategy: DistributionStrategy::RoundRobin,
             session_data: None,
             execution_config: None,
+            flow_session: None,
+            upload: None,
+            highlight_rules: Vec::new(),
+            sequence: None,
         };
         
         let validation = manager.validate_attack_config(&config).await.unwrap();
@@ -748,6 +1397,152 @@ mod tests {
         assert_eq!(validation.estimated_requests, Some(2));
     }
 
+    #[tokio::test]
+    async fn test_rate_limits_are_stored_with_the_attack() {
+        let (manager, _temp_dir) = create_test_manager().await;
+
+        let mut config = IntruderAttackConfig {
+            name: "Polite Attack".to_string(),
+            request_template: "GET /api/test?param=§payload1§ HTTP/1.1\r\n\r\n".to_string(),
+            attack_mode: AttackMode::Sniper,
+            payload_sets: vec![PayloadSetConfig {
+                id: "test-set".to_string(),
+                name: "Test Set".to_string(),
+                payload_config: PayloadConfig::Custom { values: vec!["test1".to_string()] },
+                position_index: 0,
+            }],
+            target_agents: vec!["agent1".to_string()],
+            distribution_strategy: DistributionStrategy::RoundRobin,
+            session_data: None,
+            execution_config: Some(ExecutionConfig {
+                rate_limit: attack_engine::RateLimitConfig {
+                    max_requests_per_second: Some(0.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            flow_session: None,
+            upload: None,
+            highlight_rules: Vec::new(),
+            sequence: None,
+        };
+        let validation = manager.validate_attack_config(&config).await.unwrap();
+        assert!(!validation.is_valid);
+
+        let rate_limit = &mut config.execution_config.as_mut().unwrap().rate_limit;
+        rate_limit.max_requests_per_second = Some(5.0);
+        rate_limit.per_host_delay_ms = 250;
+        let attack_id = manager.create_attack(config).await.unwrap();
+
+        let settings = manager.attack_execution_settings(&attack_id).await.unwrap();
+        assert_eq!(settings.rate_limit.max_requests_per_second, Some(5.0));
+        assert_eq!(settings.rate_limit.per_host_delay_ms, 250);
+        assert_eq!(settings.rate_limit.backoff_status_codes, vec![429, 503]);
+
+        let defaults = manager.attack_execution_settings("missing").await.unwrap();
+        assert_eq!(defaults.rate_limit.max_requests_per_second, None);
+    }
+
+    #[tokio::test]
+    async fn test_highlight_rules_are_stored_with_the_attack() {
+        use highlighting::HighlightCondition;
+
+        let (manager, _temp_dir) = create_test_manager().await;
+        let rule = |condition| ResultHighlightRule { name: "Odd length".to_string(), condition, priority: 5 };
+        let mut config = IntruderAttackConfig {
+            name: "Highlighted Attack".to_string(),
+            request_template: "GET /api/test?param=§payload1§ HTTP/1.1\r\n\r\n".to_string(),
+            attack_mode: AttackMode::Sniper,
+            payload_sets: vec![PayloadSetConfig {
+                id: "test-set".to_string(),
+                name: "Test Set".to_string(),
+                payload_config: PayloadConfig::Custom { values: vec!["test1".to_string()] },
+                position_index: 0,
+            }],
+            target_agents: vec!["agent1".to_string()],
+            distribution_strategy: DistributionStrategy::RoundRobin,
+            session_data: None,
+            execution_config: None,
+            flow_session: None,
+            upload: None,
+            highlight_rules: vec![rule(HighlightCondition::LengthDeviation { percent: -5.0, baseline: None })],
+            sequence: None,
+        };
+        let validation = manager.validate_attack_config(&config).await.unwrap();
+        assert!(!validation.is_valid);
+
+        config.highlight_rules = vec![rule(HighlightCondition::LengthDeviation { percent: 25.0, baseline: None })];
+        let attack_id = manager.create_attack(config).await.unwrap();
+        let attack = manager.get_attack(&attack_id).await.unwrap().unwrap();
+        let distribution = DistributionStats {
+            total_payloads: 0,
+            total_agents: 0,
+            assignments: Vec::new(),
+            load_balance_factor: 1.0,
+            estimated_completion_time: None,
+        };
+        let execution = manager.execution_config(&attack, AttackMode::Sniper, distribution).await.unwrap();
+        assert_eq!(execution.result_highlighting_rules.len(), 1);
+        assert!(matches!(
+            execution.result_highlighting_rules[0].condition,
+            HighlightCondition::LengthDeviation { percent, baseline: None } if percent == 25.0
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate_upload_positions() {
+        use attack_engine::{UploadField, UploadPartTemplate, UploadPosition};
+
+        let (manager, _temp_dir) = create_test_manager().await;
+        let payload_set = |id: &str, position_index, catalog| PayloadSetConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            payload_config: PayloadConfig::UploadCatalog { catalog },
+            position_index,
+        };
+
+        let mut config = IntruderAttackConfig {
+            name: "Upload Attack".to_string(),
+            request_template: "POST /avatar HTTP/1.1\r\nHost: example.com\r\n\r\n".to_string(),
+            attack_mode: AttackMode::ClusterBomb,
+            payload_sets: vec![
+                payload_set("names", 0, UploadCatalog::DoubleExtensions {
+                    basename: "shell".to_string(),
+                    extension: "php".to_string(),
+                    allowed: "jpg".to_string(),
+                }),
+                payload_set("sizes", 1, UploadCatalog::Oversized {
+                    sizes: vec![1 << 20, 1 << 30],
+                    agent_files: vec!["/data/big.iso".to_string()],
+                }),
+            ],
+            target_agents: vec!["agent1".to_string()],
+            distribution_strategy: DistributionStrategy::RoundRobin,
+            session_data: None,
+            execution_config: None,
+            flow_session: None,
+            upload: Some(UploadTemplate {
+                boundary: "XyZ".to_string(),
+                parts: vec![UploadPartTemplate::file("avatar", "cat.jpg", "image/jpeg", b"data".to_vec())],
+                positions: vec![
+                    UploadPosition { part: 0, field: UploadField::Filename, payload_set_id: "names".to_string() },
+                    UploadPosition { part: 0, field: UploadField::Body, payload_set_id: "sizes".to_string() },
+                ],
+            }),
+            highlight_rules: Vec::new(),
+            sequence: None,
+        };
+
+        let validation = manager.validate_attack_config(&config).await.unwrap();
+        assert!(validation.is_valid, "Validation errors: {:?}", validation.errors);
+        assert_eq!(validation.payload_positions.len(), 2);
+        assert!(validation.warnings.iter().any(|w| w.contains("agents' disks")));
+
+        config.upload.as_mut().unwrap().positions[1].part = 3;
+        let validation = manager.validate_attack_config(&config).await.unwrap();
+        assert!(!validation.is_valid);
+    }
+
     #[tokio::test]
     async fn test_estimate_request_count() {
         let (manager, _temp_dir) = create_test_manager().await;
@@ -785,6 +1580,106 @@ mod tests {
         assert_eq!(cluster_bomb_count, 6); // 2 * 3
     }
 
+    #[tokio::test]
+    async fn test_simulate_attack() {
+        let (manager, _temp_dir) = create_test_manager().await;
+        manager.db.create_project("p").await.unwrap();
+        manager.db.load_project("p").await.unwrap();
+
+        let payload_set = |id: &str, values: &[&str], position_index| PayloadSetConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            payload_config: PayloadConfig::Custom {
+                values: values.iter().map(|v| v.to_string()).collect(),
+            },
+            position_index,
+        };
+        let attack_id = manager.create_attack(IntruderAttackConfig {
+            name: "Login".to_string(),
+            request_template: "POST /login?u=§user§&p=§pass§ HTTP/1.1\r\n\r\n".to_string(),
+            attack_mode: AttackMode::ClusterBomb,
+            payload_sets: vec![
+                payload_set("user", &["admin", "root"], 0),
+                payload_set("pass", &["1", "2", "3"], 1),
+            ],
+            target_agents: vec!["agent1".to_string(), "agent2".to_string()],
+            distribution_strategy: DistributionStrategy::RoundRobin,
+            session_data: None,
+            execution_config: None,
+            flow_session: None,
+            upload: None,
+            highlight_rules: Vec::new(),
+            sequence: None,
+        }).await.unwrap();
+        let attack = manager.get_attack(&attack_id).await.unwrap().unwrap();
+
+        let options = SimulationOptions {
+            sample_size: 4,
+            requests_per_second: Some(2.0),
+            ..Default::default()
+        };
+        let simulation = manager.simulate_attack(&attack, &options).await.unwrap();
+        assert_eq!(simulation.total_requests, 6);
+        assert_eq!(simulation.payload_positions, 2);
+        assert_eq!(simulation.agent_count, 2);
+        assert_eq!(simulation.requests_per_second, 2.0);
+        assert_eq!(simulation.estimated_duration_seconds, 3.0);
+        let requests: Vec<_> = simulation.sample.iter().map(|r| r.request.as_str()).collect();
+        assert_eq!(requests, vec![
+            "POST /login?u=admin&p=1 HTTP/1.1\r\n\r\n",
+            "POST /login?u=admin&p=2 HTTP/1.1\r\n\r\n",
+            "POST /login?u=admin&p=3 HTTP/1.1\r\n\r\n",
+            "POST /login?u=root&p=1 HTTP/1.1\r\n\r\n",
+        ]);
+
+        // Without a rate limit, two agents at 10 concurrent requests of 500ms each
+        let simulation = manager.simulate_attack(&attack, &SimulationOptions::default()).await.unwrap();
+        assert_eq!(simulation.requests_per_second, 40.0);
+        assert_eq!(manager.get_attack(&attack_id).await.unwrap().unwrap().status, attack.status);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_attack() {
+        let (manager, _temp_dir) = create_test_manager().await;
+        manager.db.create_project("p").await.unwrap();
+        manager.db.load_project("p").await.unwrap();
+
+        let body = r#"{"query":"query User($id: ID!) { user(id: $id) { name } }","variables":{"id":"7"}}"#;
+        let config = IntruderAttackConfig {
+            name: "GraphQL".to_string(),
+            request_template: format!("POST /graphql HTTP/1.1\r\nHost: api.test\r\n\r\n{}", body),
+            attack_mode: AttackMode::GraphQl(GraphQlAttackConfig {
+                alias_batch_size: 5,
+                injection_payloads: vec!["'".to_string(), "$(id)".to_string()],
+                ..Default::default()
+            }),
+            payload_sets: Vec::new(),
+            target_agents: vec!["agent1".to_string()],
+            distribution_strategy: DistributionStrategy::RoundRobin,
+            session_data: None,
+            execution_config: None,
+            flow_session: None,
+            upload: None,
+            highlight_rules: Vec::new(),
+            sequence: None,
+        };
+
+        // 5 type confusions and 2 injections of $id, one alias batch
+        let validation = manager.validate_attack_config(&config).await.unwrap();
+        assert!(validation.is_valid, "Validation errors: {:?}", validation.errors);
+        assert_eq!(validation.estimated_requests, Some(8));
+
+        let attack_id = manager.create_attack(config).await.unwrap();
+        let attack = manager.get_attack(&attack_id).await.unwrap().unwrap();
+        assert_eq!(attack.attack_mode, "graphql");
+
+        let options = SimulationOptions { sample_size: 100, ..Default::default() };
+        let simulation = manager.simulate_attack(&attack, &options).await.unwrap();
+        assert_eq!(simulation.total_requests, 8);
+        let batch = simulation.sample.iter().find(|r| r.payload_values["kind"] == "alias_batching").unwrap();
+        assert!(batch.request.contains("b4_user: user(id: $id)"));
+    }
+
     #[tokio::test]
     async fn test_validate_agent_selection() {
         let (manager, _temp_dir) = create_test_manager().await;
@@ -0,0 +1,142 @@
+//! Baseline of an intruder attack
+//!
+//! Before the first payload is sent, the template is sent unmodified a few
+//! times, with every payload position empty. The typical status, length and
+//! response time of those requests is stored with the attack, and each
+//! result is reported as a delta from it: a few extra bytes or a response a
+//! second slower than usual is how blind injection shows itself.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Most baseline requests an attack may send
+pub const MAX_BASELINE_REQUESTS: u32 = 20;
+
+/// Outcome of one baseline request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BaselineSample {
+    pub status_code: i32,
+    pub response_length: i64,
+    pub duration_ms: i64,
+}
+
+/// Typical response of an attack's unmodified request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttackBaseline {
+    /// Baseline requests that got a response
+    pub samples: usize,
+    /// Most frequent status code
+    pub status_code: i32,
+    /// Median body length in bytes
+    pub response_length: i64,
+    /// Median response time
+    pub duration_ms: i64,
+    /// Slowest minus fastest response time; time deltas within it are noise
+    pub duration_spread_ms: i64,
+    pub measured_at: DateTime<Utc>,
+}
+
+/// How a result differs from its attack's baseline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResultDelta {
+    /// Unknown for a request that got no response
+    pub status_changed: Option<bool>,
+    pub length: Option<i64>,
+    pub duration_ms: Option<i64>,
+}
+
+impl AttackBaseline {
+    /// Baseline of the responses received; `None` when there were none
+    pub fn from_samples(samples: &[BaselineSample]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let mut status_counts: HashMap<i32, usize> = HashMap::new();
+        for sample in samples {
+            *status_counts.entry(sample.status_code).or_default() += 1;
+        }
+        // Ties go to the status seen first
+        let status_code = samples
+            .iter()
+            .map(|sample| sample.status_code)
+            .max_by_key(|status| (status_counts[status], std::cmp::Reverse(first_index(samples, *status))))?;
+
+        let durations: Vec<i64> = samples.iter().map(|sample| sample.duration_ms).collect();
+        Some(Self {
+            samples: samples.len(),
+            status_code,
+            response_length: median(samples.iter().map(|sample| sample.response_length).collect()),
+            duration_ms: median(durations.clone()),
+            duration_spread_ms: durations.iter().max()? - durations.iter().min()?,
+            measured_at: Utc::now(),
+        })
+    }
+
+    /// Delta of a result from the baseline
+    pub fn delta(&self, status_code: Option<i32>, response_length: Option<i64>, duration_ms: Option<i64>) -> ResultDelta {
+        ResultDelta {
+            status_changed: status_code.map(|status| status != self.status_code),
+            length: response_length.map(|length| length - self.response_length),
+            duration_ms: duration_ms.map(|duration| duration - self.duration_ms),
+        }
+    }
+}
+
+fn first_index(samples: &[BaselineSample], status_code: i32) -> usize {
+    samples.iter().position(|sample| sample.status_code == status_code).unwrap_or(usize::MAX)
+}
+
+/// Median, the lower middle value for an even count
+fn median(mut values: Vec<i64>) -> i64 {
+    values.sort_unstable();
+    values[(values.len() - 1) / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(status_code: i32, response_length: i64, duration_ms: i64) -> BaselineSample {
+        BaselineSample { status_code, response_length, duration_ms }
+    }
+
+    #[test]
+    fn test_baseline_from_samples() {
+        assert!(AttackBaseline::from_samples(&[]).is_none());
+
+        let baseline = AttackBaseline::from_samples(&[
+            sample(200, 1200, 90),
+            sample(503, 0, 15),
+            sample(200, 1210, 110),
+            sample(200, 1190, 400),
+        ])
+        .unwrap();
+        assert_eq!(baseline.samples, 4);
+        assert_eq!(baseline.status_code, 200);
+        assert_eq!(baseline.response_length, 1190);
+        assert_eq!(baseline.duration_ms, 90);
+        assert_eq!(baseline.duration_spread_ms, 385);
+
+        let tied = AttackBaseline::from_samples(&[sample(302, 0, 10), sample(200, 5, 10)]).unwrap();
+        assert_eq!(tied.status_code, 302);
+    }
+
+    #[test]
+    fn test_result_delta() {
+        let baseline = AttackBaseline::from_samples(&[sample(200, 1000, 100)]).unwrap();
+        assert_eq!(
+            baseline.delta(Some(200), Some(1012), Some(5100)),
+            ResultDelta { status_changed: Some(false), length: Some(12), duration_ms: Some(5000) }
+        );
+        assert_eq!(
+            baseline.delta(Some(500), Some(900), None),
+            ResultDelta { status_changed: Some(true), length: Some(-100), duration_ms: None }
+        );
+        assert_eq!(
+            baseline.delta(None, None, Some(30)),
+            ResultDelta { status_changed: None, length: None, duration_ms: Some(-70) }
+        );
+    }
+}
@@ -4,24 +4,85 @@
 //! including progress tracking, statistics, and graceful termination.
 
 use crate::database::intruder::{IntruderResult, IntruderResultBuffer};
+use crate::intruder::baseline::{AttackBaseline, BaselineSample};
 use crate::intruder::distribution::{DistributionStats, PayloadAssignment};
+use crate::intruder::flow_sessions::{FlowSessionConfig, FlowSessionMinter, SessionRefresher};
+use crate::intruder::highlighting::{HighlightSubject, ResultHighlightRule, ResultHighlighter};
+use crate::intruder::sequence::AttackSequence;
+use crate::engagement::EngagementGuard;
+use crate::models::engagement::ActiveTool;
+use crate::session_integration::{AuthFailureDetectionConfig, SessionManager};
+use crate::session_integration::macros::{run_before, MacroTool, SessionMacroConfig};
 use crate::result_streaming::{ResultStreamingManager, ResultSource};
 use crate::performance_monitoring::{PerformanceMonitor, PerformanceConfig};
 use crate::Database;
 use attack_engine::{
-    AttackError, AttackResult, HttpRequestData, HttpResponseData, 
+    AttackError, AttackResult, HttpRequestData, HttpResponseData, HttpHeaders,
     AttackMode, AttackModeFactory, AgentInfo, AgentStatus,
-    PayloadPositionParser
+    HeaderRandomizationConfig, PacingProfile, PayloadPositionParser, RateLimitConfig, RateLimiter, TimingConfirmation,
+    TimingPair, UploadBody, UploadTemplate
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use tokio::sync::{RwLock, mpsc, broadcast};
 use tokio::time::{Duration, Instant};
-use tracing::{info, error, debug};
+use tracing::{info, error, debug, warn};
 use uuid::Uuid;
 use proxy_common::Session;
 
+/// Re-probing of an attack's slow results against its unmodified request
+struct DelayProbe {
+    timing: TimingConfirmation,
+    /// The attack's request with every payload position empty
+    control: HttpRequestData,
+    /// Median response time of the baseline
+    baseline_ms: u64,
+}
+
+impl DelayProbe {
+    /// Send `control` and `probe` in alternating pairs at the configured
+    /// constant rate. Returns the delay as a highlight reason when it is
+    /// significant; pairs with a failed request are left out.
+    async fn confirm(
+        &self,
+        control: &HttpRequestData,
+        probe: &HttpRequestData,
+        agent_id: &str,
+        timeout: Duration,
+        rate_limiter: &RateLimiter,
+        host: &str,
+    ) -> Option<String> {
+        let mut ticks = tokio::time::interval(Duration::from_millis(self.timing.interval_ms.max(1)));
+        ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut pairs = Vec::new();
+        for index in 0..self.timing.pairs as usize {
+            let probe_first = TimingConfirmation::probe_first(index);
+            let mut durations = [None, None];
+            for slot in 0..2 {
+                let is_probe = (slot == 0) == probe_first;
+                ticks.tick().await;
+                rate_limiter.acquire(host).await;
+                let request = if is_probe { probe } else { control };
+                durations[usize::from(is_probe)] =
+                    AttackExecutionCoordinator::timed_execution(request, agent_id, timeout).await.ok().map(|(_, ms)| ms);
+            }
+            if let [Some(control_ms), Some(probe_ms)] = durations {
+                pairs.push(TimingPair { control_ms, probe_ms });
+            }
+        }
+
+        let estimate = self.timing.estimate(&pairs)?;
+        if self.timing.is_significant(&estimate) {
+            Some(format!("Time delay confirmed ({})", estimate))
+        } else {
+            debug!("Slow response to {} not confirmed: {}", probe.url, estimate);
+            None
+        }
+    }
+}
+
 /// Status of an attack execution
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum AttackExecutionStatus {
@@ -76,37 +137,124 @@ pub struct AttackExecutionConfig {
     pub attack_mode: AttackMode,
     pub distribution: DistributionStats,
     pub session_data: Option<Session>,
+    /// Replay a flow to mint fresh sessions during the attack
+    #[serde(default)]
+    pub flow_session: Option<FlowSessionConfig>,
+    /// Multipart body with payload positions, replacing the template's body
+    #[serde(default)]
+    pub upload: Option<UploadTemplate>,
+    /// Requests sent before each of the attack's own
+    #[serde(default)]
+    pub sequence: Option<AttackSequence>,
     pub concurrent_requests_per_agent: u32,
     pub timeout_seconds: u64,
     pub retry_attempts: u32,
+    /// Request rate, connections per agent and backoff from throttling hosts
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// User-Agent, X-Forwarded-For and Accept-Language varied per request
+    #[serde(default)]
+    pub header_randomization: HeaderRandomizationConfig,
+    /// Unmodified requests measuring the target's usual response before the
+    /// first payload is sent
+    #[serde(default)]
+    pub baseline_requests: u32,
+    /// When requests go out, within the rate limits
+    #[serde(default)]
+    pub pacing: PacingProfile,
+    /// Re-probing of results slower than the baseline
+    #[serde(default)]
+    pub timing_confirmation: Option<TimingConfirmation>,
+    /// Rules each result is checked against as it arrives
+    #[serde(default)]
     pub result_highlighting_rules: Vec<ResultHighlightRule>,
+    /// Requests already sent per distribution assignment, when resuming from
+    /// a checkpoint
+    #[serde(default)]
+    pub resume_cursors: Vec<usize>,
 }
 
-/// Rules for highlighting interesting results
+/// Progress of a paused attack, stored with the attack so it can be resumed
+/// after an orchestrator restart
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ResultHighlightRule {
-    pub name: String,
-    pub condition: HighlightCondition,
-    pub priority: u8, // 1-10, higher is more important
+pub struct AttackCheckpoint {
+    /// Payload assignments the attack was started with
+    pub distribution: DistributionStats,
+    /// Requests sent per assignment, in assignment order; each agent's
+    /// requests are generated in a fixed order, so this is where it resumes
+    pub cursors: Vec<usize>,
+    pub completed_requests: usize,
+    pub total_requests: usize,
+    pub paused_at: chrono::DateTime<chrono::Utc>,
 }
 
-/// Conditions for result highlighting
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum HighlightCondition {
-    StatusCode(Vec<i32>),
-    ResponseLength { min: Option<usize>, max: Option<usize> },
-    ResponseTime { min_ms: Option<u64>, max_ms: Option<u64> },
-    ResponseContains(String),
-    ResponseRegex(String),
-    Combined { operator: LogicalOperator, conditions: Vec<HighlightCondition> },
+/// Session renewals per attack; a target that rejects every fresh session
+/// is not worth replaying the login flow for again and again
+pub const MAX_SESSION_RENEWALS: u32 = 5;
+
+/// Renews the attack's session when a response shows the target stopped
+/// accepting it
+struct AuthRecovery {
+    detection: AuthFailureDetectionConfig,
+    minter: Option<Arc<FlowSessionMinter>>,
+    refresher: Option<Arc<SessionRefresher>>,
+    /// Held for reading while a request is sent and for writing while the
+    /// session is renewed, so no payload goes out with a session known to be dead
+    gate: RwLock<()>,
+    /// Session of the last renewal, handed to requests that failed with the
+    /// session it replaced
+    latest: tokio::sync::Mutex<Option<Session>>,
+    renewals: AtomicU32,
 }
 
-/// Logical operators for combining highlight conditions
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum LogicalOperator {
-    And,
-    Or,
-    Not,
+impl AuthRecovery {
+    fn new(
+        detection: AuthFailureDetectionConfig,
+        minter: Option<Arc<FlowSessionMinter>>,
+        refresher: Option<Arc<SessionRefresher>>,
+    ) -> Self {
+        Self {
+            detection,
+            minter,
+            refresher,
+            gate: RwLock::new(()),
+            latest: tokio::sync::Mutex::new(None),
+            renewals: AtomicU32::new(0),
+        }
+    }
+
+    /// Session replacing `stale`, or `None` if it cannot be renewed
+    async fn renew(&self, stale: &Session) -> Option<Session> {
+        if self.minter.is_none() && self.refresher.is_none() {
+            return None;
+        }
+        let _paused = self.gate.write().await;
+        let mut latest = self.latest.lock().await;
+        if let Some(session) = latest.as_ref().filter(|session| session.id != stale.id) {
+            return Some(session.clone());
+        }
+        if self.renewals.load(Ordering::Relaxed) >= MAX_SESSION_RENEWALS {
+            return None;
+        }
+
+        let renewals = self.renewals.fetch_add(1, Ordering::Relaxed) + 1;
+        warn!("Session '{}' was rejected, renewing it ({}/{})", stale.name, renewals, MAX_SESSION_RENEWALS);
+        let renewed = match (&self.minter, &self.refresher) {
+            (Some(minter), _) => minter.renew(stale).await,
+            (None, Some(refresher)) => refresher.renew(stale).await,
+            (None, None) => return None,
+        };
+        match renewed {
+            Ok(session) => {
+                *latest = Some(session.clone());
+                Some(session)
+            }
+            Err(e) => {
+                error!("Failed to renew session '{}': {}", stale.name, e);
+                None
+            }
+        }
+    }
 }
 
 /// Attack execution coordinator
@@ -117,15 +265,28 @@ pub struct AttackExecutionCoordinator {
     result_buffer: Option<IntruderResultBuffer>,
     result_streaming: Arc<ResultStreamingManager>,
     performance_monitor: Arc<PerformanceMonitor>,
+    session_manager: Option<Arc<SessionManager>>,
 }
 
 /// Internal attack execution state
 struct AttackExecution {
-    _config: AttackExecutionConfig,
+    config: AttackExecutionConfig,
     progress: AttackProgress,
     cancel_token: tokio_util::sync::CancellationToken,
+    /// Stops agents from sending further requests, letting in-flight ones finish
+    pause_token: tokio_util::sync::CancellationToken,
     _result_sender: mpsc::UnboundedSender<IntruderResult>,
-    agent_tasks: HashMap<String, tokio::task::JoinHandle<()>>,
+    /// One task per distribution assignment, in assignment order
+    agent_tasks: Vec<AgentTask>,
+}
+
+/// Execution of one distribution assignment
+struct AgentTask {
+    agent_id: String,
+    /// Requests of the assignment already sent before this run
+    start: usize,
+    /// Resolves to the number of the assignment's requests sent
+    handle: tokio::task::JoinHandle<usize>,
 }
 
 impl AttackExecutionCoordinator {
@@ -149,9 +310,16 @@ impl AttackExecutionCoordinator {
             result_buffer,
             result_streaming,
             performance_monitor,
+            session_manager: None,
         })
     }
 
+    /// Register sessions minted from flows with this session manager
+    pub fn with_session_manager(mut self, session_manager: Arc<SessionManager>) -> Self {
+        self.session_manager = Some(session_manager);
+        self
+    }
+
     /// Start executing an attack
     pub async fn start_attack(
         &self,
@@ -180,9 +348,48 @@ impl AttackExecutionCoordinator {
         // Start result streaming tracking
         let source = ResultSource::Intruder { attack_id: attack_id.clone() };
         let total_requests = config.distribution.assignments.iter().map(|a| a.payloads.len()).sum();
+        let cursor = |index: usize| config.resume_cursors.get(index).copied().unwrap_or(0);
+        let already_sent: usize = (0..config.distribution.assignments.len()).map(cursor).sum();
         self.result_streaming.start_tracking(source.clone(), total_requests).await?;
         self.result_streaming.start_progress_updates(source).await;
 
+        let highlighter = Arc::new(ResultHighlighter::new(&config.result_highlighting_rules)?);
+
+        // Load the flow used to mint sessions before anything is sent
+        let session_minter = match &config.flow_session {
+            Some(flow_session) => Some(Arc::new(
+                FlowSessionMinter::new(&self.db, flow_session.clone(), self.session_manager.clone()).await?,
+            )),
+            None => None,
+        };
+
+        // Without a flow, a recorded session can still be renewed from the
+        // login flow it came from
+        let session_refresher = match (&session_minter, &config.session_data) {
+            (None, Some(session)) => {
+                SessionRefresher::for_session(&self.db, session.clone(), self.session_manager.clone())
+                    .await
+                    .map(Arc::new)
+            }
+            _ => None,
+        };
+        let detection = match &self.session_manager {
+            Some(manager) => manager.get_auth_failure_config().await,
+            None => AuthFailureDetectionConfig::default(),
+        };
+        let auth_recovery = Arc::new(AuthRecovery::new(detection, session_minter.clone(), session_refresher));
+
+        // Rules of engagement are checked before every request of the attack,
+        // so an attack running past the end of a testing window stops sending
+        let engagement_guard = Arc::new(EngagementGuard::load(self.db.clone(), ActiveTool::Intruder).await?);
+        engagement_guard.check_window().await?;
+        if let Some(minter) = &session_minter {
+            engagement_guard.check(minter.start_url()).await?;
+        }
+        if let Some(refresher) = &auth_recovery.refresher {
+            engagement_guard.check(refresher.start_url()).await?;
+        }
+
         // Update attack status in database
         self.db.update_intruder_attack_status(&attack_id, "running").await
             .map_err(|e| AttackError::DatabaseError {
@@ -194,7 +401,7 @@ impl AttackExecutionCoordinator {
             attack_id: attack_id.clone(),
             status: AttackExecutionStatus::Starting,
             total_requests,
-            completed_requests: 0,
+            completed_requests: already_sent,
             successful_requests: 0,
             failed_requests: 0,
             highlighted_results: 0,
@@ -206,32 +413,67 @@ impl AttackExecutionCoordinator {
             completed_at: None,
         };
 
-        // Initialize agent statistics
-        for assignment in &config.distribution.assignments {
-            progress.agent_statistics.insert(assignment.agent_id.clone(), AgentExecutionStats {
-                agent_id: assignment.agent_id.clone(),
-                assigned_requests: assignment.payloads.len(),
-                completed_requests: 0,
-                successful_requests: 0,
-                failed_requests: 0,
-                average_response_time_ms: 0.0,
-                current_load: 0.0,
-                status: AgentStatus::Online,
-                last_activity: None,
-            });
+        // Initialize agent statistics; a resumed attack may give an agent
+        // several assignments
+        for (index, assignment) in config.distribution.assignments.iter().enumerate() {
+            let stats = progress
+                .agent_statistics
+                .entry(assignment.agent_id.clone())
+                .or_insert_with(|| AgentExecutionStats {
+                    agent_id: assignment.agent_id.clone(),
+                    assigned_requests: 0,
+                    completed_requests: 0,
+                    successful_requests: 0,
+                    failed_requests: 0,
+                    average_response_time_ms: 0.0,
+                    current_load: 0.0,
+                    status: AgentStatus::Online,
+                    last_activity: None,
+                });
+            stats.assigned_requests += assignment.payloads.len();
+            stats.completed_requests += cursor(index);
         }
 
-        // Create cancellation token and result channel
+        // All agents of the attack share one request budget and pacing
+        let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit.clone()).with_pacing(config.pacing.clone()));
+
+        // A resumed attack keeps the baseline it started with
+        let baseline = if already_sent > 0 {
+            self.stored_baseline(&attack_id).await?
+        } else if config.baseline_requests > 0 {
+            self.measure_baseline(&config, &engagement_guard, &rate_limiter).await?
+        } else {
+            None
+        };
+        if let Some(baseline) = &baseline {
+            highlighter.use_baseline_length(baseline.response_length.max(0) as usize);
+        }
+        let delay_probe = match (&config.timing_confirmation, &baseline) {
+            (Some(timing), Some(baseline)) => Some(Arc::new(DelayProbe {
+                timing: timing.clone(),
+                control: Self::baseline_request(&config)?,
+                baseline_ms: baseline.duration_ms.max(0) as u64,
+            })),
+            (Some(_), None) => {
+                warn!("Attack {} has no baseline, so slow results are not confirmed", attack_id);
+                None
+            }
+            (None, _) => None,
+        };
+
+        // Create cancellation and pause tokens and result channel
         let cancel_token = tokio_util::sync::CancellationToken::new();
+        let pause_token = tokio_util::sync::CancellationToken::new();
         let (result_sender, mut result_receiver) = mpsc::unbounded_channel::<IntruderResult>();
 
         // Create attack execution state
         let mut attack_execution = AttackExecution {
-            _config: config.clone(),
+            config: config.clone(),
             progress: progress.clone(),
             cancel_token: cancel_token.clone(),
+            pause_token: pause_token.clone(),
             _result_sender: result_sender.clone(),
-            agent_tasks: HashMap::new(),
+            agent_tasks: Vec::new(),
         };
 
         // Start result processing task with streaming integration
@@ -251,19 +493,32 @@ impl AttackExecutionCoordinator {
                 result_buffer,
                 active_attacks_clone,
                 result_streaming_clone,
+                highlighter,
             ).await;
         });
 
         // Start agent execution tasks with performance monitoring
-        for assignment in &config.distribution.assignments {
-            let agent_task = self.start_agent_execution_with_monitoring(
+        for (index, assignment) in config.distribution.assignments.iter().enumerate() {
+            let start = cursor(index);
+            let handle = self.start_agent_execution_with_monitoring(
                 assignment.clone(),
                 config.clone(),
+                start,
                 result_sender.clone(),
                 cancel_token.clone(),
+                pause_token.clone(),
+                session_minter.clone(),
+                auth_recovery.clone(),
+                engagement_guard.clone(),
+                rate_limiter.clone(),
+                delay_probe.clone(),
             ).await?;
 
-            attack_execution.agent_tasks.insert(assignment.agent_id.clone(), agent_task);
+            attack_execution.agent_tasks.push(AgentTask {
+                agent_id: assignment.agent_id.clone(),
+                start,
+                handle,
+            });
         }
 
         // Update progress to running
@@ -283,14 +538,24 @@ impl AttackExecutionCoordinator {
         Ok(())
     }
 
-    /// Start execution for a specific agent assignment with performance monitoring
+    /// Start execution for a specific agent assignment with performance
+    /// monitoring, skipping its first `start` requests. The task resolves to
+    /// the number of the assignment's requests sent.
+    #[allow(clippy::too_many_arguments)]
     async fn start_agent_execution_with_monitoring(
         &self,
         assignment: PayloadAssignment,
         config: AttackExecutionConfig,
+        start: usize,
         result_sender: mpsc::UnboundedSender<IntruderResult>,
         cancel_token: tokio_util::sync::CancellationToken,
-    ) -> AttackResult<tokio::task::JoinHandle<()>> {
+        pause_token: tokio_util::sync::CancellationToken,
+        session_minter: Option<Arc<FlowSessionMinter>>,
+        auth_recovery: Arc<AuthRecovery>,
+        engagement_guard: Arc<EngagementGuard>,
+        rate_limiter: Arc<RateLimiter>,
+        delay_probe: Option<Arc<DelayProbe>>,
+    ) -> AttackResult<tokio::task::JoinHandle<usize>> {
         let agent_id = assignment.agent_id.clone();
         let attack_id = config.attack_id.clone();
         let performance_monitor = self.performance_monitor.clone();
@@ -303,6 +568,15 @@ impl AttackExecutionCoordinator {
         // Parse request template
         let _base_request = self.parse_request_template(&config.request_template)?;
 
+        // Session macros run before each request of the attack
+        let session_macros = Arc::new(match self.db.get_session_macros().await {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("Failed to load session macros for attack {}: {}", attack_id, e);
+                SessionMacroConfig::default()
+            }
+        });
+
         let task = tokio::spawn(async move {
             let start_time = Instant::now();
             let mut completed_count = 0;
@@ -310,13 +584,23 @@ impl AttackExecutionCoordinator {
             let mut total_response_time = 0u64;
 
             // Generate requests for this agent's payloads
-            let parsed_template = match PayloadPositionParser::parse(&config.request_template) {
+            let mut parsed_template = match PayloadPositionParser::parse(&config.request_template) {
                 Ok(parsed) => parsed,
                 Err(e) => {
                     error!("Failed to parse request template for agent {}: {}", agent_id, e);
-                    return;
+                    return start;
                 }
             };
+            let upload = config.upload.clone().map(Arc::new);
+            if let Some(upload) = &upload {
+                let upload_positions = upload.payload_positions(parsed_template.positions.len());
+                parsed_template.positions.extend(upload_positions);
+            }
+            let sequence = config.sequence.clone().map(Arc::new);
+            if let Some(sequence) = &sequence {
+                let sequence_positions = sequence.payload_positions(parsed_template.positions.len());
+                parsed_template.positions.extend(sequence_positions);
+            }
 
             // Create payload sets map for this assignment
             let mut payload_sets = HashMap::new();
@@ -324,22 +608,45 @@ impl AttackExecutionCoordinator {
                 payload_sets.insert("payload".to_string(), assignment.payloads.clone());
             }
 
-            let requests = match attack_mode_executor.generate_requests(&parsed_template, &payload_sets) {
+            let mut requests = match attack_mode_executor.generate_requests(&parsed_template, &payload_sets) {
                 Ok(reqs) => reqs,
                 Err(e) => {
                     error!("Failed to generate requests for agent {}: {}", agent_id, e);
-                    return;
+                    return start;
                 }
             };
 
+            // GraphQL probes are assigned by index rather than payload
+            if matches!(config.attack_mode, AttackMode::GraphQl(_)) {
+                let assigned: HashSet<&str> = assignment.payloads.iter().map(String::as_str).collect();
+                requests.retain(|request| assigned.contains(request.index.to_string().as_str()));
+            }
+
             // Execute requests with performance monitoring and concurrency control
             let mut tasks = Vec::new();
-
-            for (_index, attack_request) in requests.into_iter().enumerate() {
-                if cancel_token.is_cancelled() {
+            let connections = rate_limiter
+                .config()
+                .max_connections_per_agent
+                .unwrap_or(config.concurrent_requests_per_agent)
+                .max(1);
+            let connection_slots = Arc::new(tokio::sync::Semaphore::new(connections as usize));
+            let header_randomization = Arc::new(config.header_randomization.clone());
+
+            for attack_request in requests.into_iter().skip(start) {
+                if cancel_token.is_cancelled() || pause_token.is_cancelled() {
                     break;
                 }
 
+                // Wait for a free connection of this agent
+                let connection = tokio::select! {
+                    slot = connection_slots.clone().acquire_owned() => match slot {
+                        Ok(slot) => slot,
+                        Err(_) => break,
+                    },
+                    _ = cancel_token.cancelled() => break,
+                    _ = pause_token.cancelled() => break,
+                };
+
                 // Acquire performance-monitored permit
                 let permit = match performance_monitor.acquire_request_permit(&agent_id).await {
                     Ok(permit) => permit,
@@ -354,10 +661,20 @@ impl AttackExecutionCoordinator {
                 let result_sender_clone = result_sender.clone();
                 let cancel_token_clone = cancel_token.clone();
                 let session_data = config.session_data.clone();
+                let session_minter = session_minter.clone();
+                let auth_recovery = auth_recovery.clone();
+                let engagement_guard = engagement_guard.clone();
                 let timeout = Duration::from_secs(config.timeout_seconds);
                 let payload_values = attack_request.payload_values.clone();
+                let upload = upload.clone();
+                let sequence = sequence.clone();
+                let rate_limiter = rate_limiter.clone();
+                let session_macros = session_macros.clone();
+                let header_randomization = header_randomization.clone();
+                let delay_probe = delay_probe.clone();
 
                 let task = tokio::spawn(async move {
+                    let _connection = connection;
                     if cancel_token_clone.is_cancelled() {
                         permit.complete(false).await;
                         return (false, 0);
@@ -368,27 +685,155 @@ impl AttackExecutionCoordinator {
                     // Parse the request string into HttpRequestData
                     let mut final_request = match Self::parse_request_string(&attack_request.request) {
                         Ok(req) => req,
-                        Err(_) => {
+                        Err(e) => {
+                            warn!("Failed to parse generated request for attack {}: {}", attack_id_clone, e);
                             // Fallback to a basic request
                             HttpRequestData::new("GET".to_string(), "http://example.com".to_string())
                         }
                     };
 
+                    // Upload positions render into a body the agent assembles and streams
+                    let upload_body = match upload.as_deref().map(|u| u.render(&attack_request.payload_values)) {
+                        Some(Ok(body)) => Some(body),
+                        Some(Err(e)) => {
+                            error!("Failed to render upload body for agent {}: {}", agent_id_clone, e);
+                            permit.complete(false).await;
+                            return (false, 0);
+                        }
+                        None => None,
+                    };
+                    if let Some(body) = &upload_body {
+                        Self::attach_upload_body(&mut final_request, body);
+                    }
+
+                    // Prefer a flow-minted session, falling back to the static one
+                    let session = match &session_minter {
+                        Some(minter) => match minter.session_for_request().await {
+                            Ok(session) => Some(session),
+                            Err(e) => {
+                                error!("Failed to mint session for agent {}: {}", agent_id_clone, e);
+                                session_data
+                            }
+                        },
+                        None => match &auth_recovery.refresher {
+                            Some(refresher) => Some(refresher.current().await),
+                            None => session_data,
+                        },
+                    };
+
                     // Apply session data if present
-                    if let Some(ref session) = session_data {
+                    if let Some(ref session) = session {
                         final_request.apply_session(session);
                     }
 
-                    // Execute actual request through agent
-                    let result = Self::simulate_request_execution(
-                        &final_request,
-                        &agent_id_clone,
-                        timeout,
-                    ).await;
+                    // Macro steps are held to the same rules of engagement
+                    let macros = session_macros.for_request(MacroTool::Intruder, &final_request.url);
+                    if !macros.is_empty() {
+                        let (guard, agent) = (&engagement_guard, &agent_id_clone);
+                        let ran = run_before(&macros, &mut final_request, |step| async move {
+                            guard.check(&step.url).await?;
+                            Self::simulate_request_execution(&step, agent, timeout).await
+                        })
+                        .await;
+                        if let Err(e) = ran {
+                            error!("Session macro failed for agent {}: {}", agent_id_clone, e);
+                            permit.complete(false).await;
+                            return (false, 0);
+                        }
+                    }
+
+                    // A sequence's steps go first; they fill the values this request needs
+                    let sequence_ran = match &sequence {
+                        Some(sequence) => {
+                            let (guard, agent) = (&engagement_guard, &agent_id_clone);
+                            sequence
+                                .run(&payload_values, session.as_ref(), &mut final_request, |step| async move {
+                                    guard.check(&step.url).await?;
+                                    Self::simulate_request_execution(&step, agent, timeout).await
+                                })
+                                .await
+                                .map(|_| ())
+                        }
+                        None => Ok(()),
+                    };
+
+                    header_randomization.apply(&mut final_request);
+
+                    // Pace the request and wait out any backoff from its host
+                    let host = reqwest::Url::parse(&final_request.url)
+                        .ok()
+                        .and_then(|url| url.host_str().map(str::to_string))
+                        .unwrap_or_default();
+                    tokio::select! {
+                        _ = rate_limiter.acquire(&host) => {}
+                        _ = cancel_token_clone.cancelled() => {
+                            permit.complete(false).await;
+                            return (false, 0);
+                        }
+                    }
+
+                    // Execute actual request through agent, unless the payload steered it
+                    // outside the allowlist or the testing window has closed. A failed
+                    // sequence step is the payload's result and the request is not sent.
+                    let checked = match sequence_ran {
+                        Ok(()) => engagement_guard.check(&final_request.url).await,
+                        Err(e) => Err(e),
+                    };
+                    let mut result = match checked {
+                        Ok(()) => {
+                            let _sending = auth_recovery.gate.read().await;
+                            Self::simulate_request_execution(&final_request, &agent_id_clone, timeout).await
+                        }
+                        Err(e) => Err(e),
+                    };
 
-                    let duration_ms = execution_start.elapsed().as_millis() as u64;
+                    // A rejected session is renewed and the payload sent again
+                    // with the new one, rather than recording the rejection
+                    if let (Ok(response), Some(stale)) = (&result, &session) {
+                        if auth_recovery.detection.is_failure(response) {
+                            if let Some(renewed) = auth_recovery.renew(stale).await {
+                                final_request.apply_session(&renewed);
+                                let _sending = auth_recovery.gate.read().await;
+                                result = Self::simulate_request_execution(&final_request, &agent_id_clone, timeout).await;
+                            }
+                        }
+                    }
+
+                    // The agent's own timing leaves out macros, sequences, pacing and
+                    // the round trip to the agent
+                    let duration_ms = match result.as_ref().ok().and_then(|r| r.timing) {
+                        Some(timing) => timing.total_ms(),
+                        None => execution_start.elapsed().as_millis() as u64,
+                    };
                     let is_success = result.is_ok();
 
+                    // A slow result is only reported as a delay once it holds up
+                    // against interleaved control requests
+                    let delay_reason = match &delay_probe {
+                        Some(probe) if is_success && probe.timing.should_confirm(probe.baseline_ms, duration_ms) => {
+                            let mut control = probe.control.clone();
+                            if let Some(session) = &session {
+                                control.apply_session(session);
+                            }
+                            probe.confirm(&control, &final_request, &agent_id_clone, timeout, &rate_limiter, &host).await
+                        }
+                        _ => None,
+                    };
+
+                    if let Ok(response) = &result {
+                        let retry_after = response.get_header("Retry-After").or_else(|| response.get_header("retry-after"));
+                        if let Some(delay) =
+                            rate_limiter.record_response(&host, response.status_code, retry_after.map(String::as_str))
+                        {
+                            warn!(
+                                "{} answered {}, backing off for {:.1}s",
+                                host,
+                                response.status_code,
+                                delay.as_secs_f64()
+                            );
+                        }
+                    }
+
                     // Create result record
                     let intruder_result = IntruderResult {
                         id: Uuid::new_v4().to_string(),
@@ -401,7 +846,9 @@ impl AttackExecutionCoordinator {
                         duration_ms: Some(duration_ms as i64),
                         status_code: result.as_ref().ok().map(|r| r.status_code),
                         response_length: result.as_ref().ok().map(|r| r.body.len() as i64),
-                        is_highlighted: false, // Will be determined by result streaming
+                        is_highlighted: false, // Set from the attack's highlight rules
+                        highlight_reason: delay_reason,
+                        egress_ip: None, // The agent's last reported egress, filled on insert
                     };
 
                     // Send result
@@ -417,6 +864,7 @@ impl AttackExecutionCoordinator {
             }
 
             // Wait for all tasks to complete
+            let sent = start + tasks.len();
             for task in tasks {
                 if let Ok((success, duration)) = task.await {
                     completed_count += 1;
@@ -442,6 +890,7 @@ impl AttackExecutionCoordinator {
                 avg_response_time,
                 total_duration.as_secs_f64()
             );
+            sent
         });
 
         Ok(task)
@@ -456,15 +905,27 @@ impl AttackExecutionCoordinator {
         result_buffer: Option<IntruderResultBuffer>,
         active_attacks: Arc<RwLock<HashMap<String, AttackExecution>>>,
         result_streaming: Arc<ResultStreamingManager>,
+        highlighter: Arc<ResultHighlighter>,
     ) {
         let mut last_progress_update = Instant::now();
         let progress_update_interval = Duration::from_millis(500); // Update progress every 500ms
 
-        while let Some(result) = result_receiver.recv().await {
+        while let Some(mut result) = result_receiver.recv().await {
             // Process result through streaming manager
             let response_data = result.response_data.as_ref()
                 .and_then(|json| serde_json::from_str::<HttpResponseData>(json).ok());
-            
+
+            let matched = highlighter.evaluate(&HighlightSubject {
+                response: response_data.as_ref(),
+                duration_ms: result.duration_ms.map(|d| d as u64),
+            });
+            // A confirmed delay comes first, then the rules
+            result.highlight_reason = match (result.highlight_reason.take(), matched) {
+                (Some(delay), Some(rules)) => Some(format!("{}; {}", delay, rules)),
+                (delay, rules) => delay.or(rules),
+            };
+            result.is_highlighted = result.highlight_reason.is_some();
+
             if let Err(e) = result_streaming.process_intruder_result(
                 &attack_id,
                 &result,
@@ -480,17 +941,7 @@ impl AttackExecutionCoordinator {
                 }
             } else {
                 // Fallback to direct database insert
-                let _ = db.save_intruder_result(
-                    &result.attack_id,
-                    &result.request_data,
-                    result.response_data.as_deref(),
-                    &result.agent_id,
-                    &result.payload_values,
-                    result.duration_ms,
-                    result.status_code,
-                    result.response_length,
-                    result.is_highlighted,
-                ).await;
+                let _ = db.insert_intruder_result(&result).await;
             }
 
             // Update progress periodically
@@ -548,9 +999,43 @@ impl AttackExecutionCoordinator {
             headers: None,
             body: b"Simulated response".to_vec(),
             tls: None,
+            timing: None,
+            protocol: None,
         })
     }
 
+    /// Send a request, timed by the agent when it reports timings
+    async fn timed_execution(
+        request: &HttpRequestData,
+        agent_id: &str,
+        timeout: Duration,
+    ) -> Result<(HttpResponseData, u64), AttackError> {
+        let started = Instant::now();
+        let response = Self::simulate_request_execution(request, agent_id, timeout).await?;
+        let duration_ms = match response.timing {
+            Some(timing) => timing.total_ms(),
+            None => started.elapsed().as_millis() as u64,
+        };
+        Ok((response, duration_ms))
+    }
+
+    /// Point a request at an upload body. The body itself is sent to the agent
+    /// as segments; the request keeps a preview for the stored result.
+    fn attach_upload_body(request: &mut HttpRequestData, body: &UploadBody) {
+        let headers = &mut request
+            .headers
+            .get_or_insert_with(|| HttpHeaders { headers: HashMap::new() })
+            .headers;
+        headers.retain(|name, _| {
+            !name.eq_ignore_ascii_case("content-type") && !name.eq_ignore_ascii_case("content-length")
+        });
+        headers.insert("Content-Type".to_string(), body.content_type.clone());
+        if let Some(length) = body.content_length() {
+            headers.insert("Content-Length".to_string(), length.to_string());
+        }
+        request.body = body.preview();
+    }
+
     /// Parse request template into HttpRequestData
     fn parse_request_template(&self, _template: &str) -> AttackResult<HttpRequestData> {
         // TODO: Implement proper HTTP request parsing
@@ -558,11 +1043,100 @@ impl AttackExecutionCoordinator {
         Ok(HttpRequestData::new("GET".to_string(), "http://example.com".to_string()))
     }
 
-    /// Parse request string into HttpRequestData (simple implementation)
-    fn parse_request_string(_request_string: &str) -> AttackResult<HttpRequestData> {
-        // TODO: Implement proper HTTP request parsing from string
-        // For now, create a basic request
-        Ok(HttpRequestData::new("GET".to_string(), "http://example.com".to_string()))
+    /// Send the template with every payload position empty
+    /// `config.baseline_requests` times and store the typical response with
+    /// the attack; `None` when no baseline request got a response
+    async fn measure_baseline(
+        &self,
+        config: &AttackExecutionConfig,
+        engagement_guard: &EngagementGuard,
+        rate_limiter: &RateLimiter,
+    ) -> AttackResult<Option<AttackBaseline>> {
+        let Some(agent_id) = config.distribution.assignments.first().map(|a| a.agent_id.clone()) else {
+            return Ok(None);
+        };
+        let mut request = Self::baseline_request(config)?;
+        if let Some(session) = &config.session_data {
+            request.apply_session(session);
+        }
+        engagement_guard.check(&request.url).await?;
+
+        let host = reqwest::Url::parse(&request.url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string))
+            .unwrap_or_default();
+        let timeout = Duration::from_secs(config.timeout_seconds);
+        let mut samples = Vec::new();
+        for _ in 0..config.baseline_requests {
+            let mut request = request.clone();
+            config.header_randomization.apply(&mut request);
+            rate_limiter.acquire(&host).await;
+            match Self::timed_execution(&request, &agent_id, timeout).await {
+                Ok((response, duration_ms)) => samples.push(BaselineSample {
+                    status_code: response.status_code,
+                    response_length: response.body.len() as i64,
+                    duration_ms: duration_ms as i64,
+                }),
+                Err(e) => warn!("Baseline request of attack {} failed: {}", config.attack_id, e),
+            }
+        }
+
+        let baseline = AttackBaseline::from_samples(&samples);
+        match &baseline {
+            Some(baseline) => info!(
+                "Attack {} baseline: status {}, {} bytes, {} ms over {} requests",
+                config.attack_id, baseline.status_code, baseline.response_length, baseline.duration_ms, baseline.samples
+            ),
+            None => warn!("No baseline request of attack {} got a response", config.attack_id),
+        }
+        let json = baseline
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to serialize baseline: {}", e),
+            })?;
+        self.db.set_intruder_attack_baseline(&config.attack_id, json.as_deref()).await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("set_intruder_attack_baseline: {}", e),
+            })?;
+        Ok(baseline)
+    }
+
+    /// The attack's request with every payload position empty
+    fn baseline_request(config: &AttackExecutionConfig) -> AttackResult<HttpRequestData> {
+        let parsed = PayloadPositionParser::parse(&config.request_template)?;
+        let empty: HashMap<String, String> = parsed
+            .positions
+            .iter()
+            .map(|position| (position.payload_set_id.clone(), String::new()))
+            .collect();
+        let mut request = Self::parse_request_string(&PayloadPositionParser::inject_payloads(&parsed, &empty)?)?;
+        // Upload parts keep the template's content
+        if let Some(upload) = &config.upload {
+            Self::attach_upload_body(&mut request, &upload.render(&HashMap::new())?);
+        }
+        Ok(request)
+    }
+
+    /// Baseline measured when an attack started
+    pub async fn stored_baseline(&self, attack_id: &str) -> AttackResult<Option<AttackBaseline>> {
+        self.db.get_intruder_attack_baseline(attack_id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_intruder_attack_baseline: {}", e),
+            })?
+            .map(|json| serde_json::from_str::<AttackBaseline>(&json))
+            .transpose()
+            .map_err(|e| AttackError::SerializationError {
+                error: format!("Failed to parse baseline: {}", e),
+            })
+    }
+
+    /// Parse a generated raw request into HttpRequestData; origin-form
+    /// targets are resolved against the Host header
+    fn parse_request_string(request_string: &str) -> AttackResult<HttpRequestData> {
+        attack_engine::RawRequest::parse(request_string.as_bytes())?.to_request_data(None)
     }
 
     /// Stop an active attack
@@ -575,9 +1149,9 @@ impl AttackExecutionCoordinator {
             attack.cancel_token.cancel();
 
             // Wait for tasks to complete
-            for (agent_id, task) in attack.agent_tasks {
-                debug!("Waiting for agent {} to stop", agent_id);
-                let _ = task.await;
+            for task in attack.agent_tasks {
+                debug!("Waiting for agent {} to stop", task.agent_id);
+                let _ = task.handle.await;
             }
 
             // Stop result streaming
@@ -661,32 +1235,56 @@ impl AttackExecutionCoordinator {
         self.performance_monitor.update_config(config).await
     }
 
-    /// Pause an active attack
-    pub async fn pause_attack(&self, attack_id: &str) -> AttackResult<()> {
-        let mut active_attacks = self.active_attacks.write().await;
-        if let Some(attack) = active_attacks.get_mut(attack_id) {
-            attack.progress.status = AttackExecutionStatus::Paused;
-            // TODO: Implement actual pause logic
-            Ok(())
-        } else {
-            Err(AttackError::InvalidAttackConfig {
-                reason: format!("Attack {} not found", attack_id),
-            })
-        }
-    }
+    /// Pause an active attack: agents stop sending, requests in flight
+    /// finish, and the position of every assignment is stored with the
+    /// attack. Resume it by starting it again with the checkpoint's
+    /// distribution and cursors.
+    pub async fn pause_attack(&self, attack_id: &str) -> AttackResult<AttackCheckpoint> {
+        let mut attack = self.active_attacks.write().await.remove(attack_id).ok_or_else(|| {
+            AttackError::InvalidAttackConfig {
+                reason: format!("Attack {} is not running", attack_id),
+            }
+        })?;
+        info!("Pausing attack: {}", attack_id);
 
-    /// Resume a paused attack
-    pub async fn resume_attack(&self, attack_id: &str) -> AttackResult<()> {
-        let mut active_attacks = self.active_attacks.write().await;
-        if let Some(attack) = active_attacks.get_mut(attack_id) {
-            attack.progress.status = AttackExecutionStatus::Running;
-            // TODO: Implement actual resume logic
-            Ok(())
-        } else {
-            Err(AttackError::InvalidAttackConfig {
-                reason: format!("Attack {} not found", attack_id),
-            })
+        attack.progress.status = AttackExecutionStatus::Pausing;
+        let _ = self.progress_broadcaster.send(attack.progress.clone());
+        attack.pause_token.cancel();
+
+        let mut cursors = Vec::with_capacity(attack.agent_tasks.len());
+        for task in attack.agent_tasks.drain(..) {
+            debug!("Waiting for agent {} to pause", task.agent_id);
+            cursors.push(task.handle.await.unwrap_or(task.start));
         }
+
+        let checkpoint = AttackCheckpoint {
+            distribution: attack.config.distribution.clone(),
+            completed_requests: cursors.iter().sum(),
+            total_requests: attack.progress.total_requests,
+            cursors,
+            paused_at: chrono::Utc::now(),
+        };
+        let json = serde_json::to_string(&checkpoint).map_err(|e| AttackError::SerializationError {
+            error: format!("Failed to serialize checkpoint: {}", e),
+        })?;
+        self.db.set_intruder_attack_checkpoint(attack_id, Some(&json)).await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("set_intruder_attack_checkpoint: {}", e),
+            })?;
+        self.db.update_intruder_attack_status(attack_id, "paused").await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("update_attack_status: {}", e),
+            })?;
+
+        attack.progress.status = AttackExecutionStatus::Paused;
+        attack.progress.completed_requests = checkpoint.completed_requests;
+        let _ = self.progress_broadcaster.send(attack.progress);
+
+        info!(
+            "Attack {} paused after {}/{} requests",
+            attack_id, checkpoint.completed_requests, checkpoint.total_requests
+        );
+        Ok(checkpoint)
     }
 }
 
@@ -699,10 +1297,24 @@ mod tests {
     async fn create_test_coordinator() -> (AttackExecutionCoordinator, TempDir) {
         let temp_dir = TempDir::new().unwrap();
         let db = Arc::new(Database::new(temp_dir.path().to_str().unwrap()).await.unwrap());
+        db.create_project("intruder").await.unwrap();
+        db.load_project("intruder").await.unwrap();
         let coordinator = AttackExecutionCoordinator::new(db).await.unwrap();
         (coordinator, temp_dir)
     }
 
+    #[test]
+    fn test_generated_requests_keep_their_target() {
+        let request = AttackExecutionCoordinator::parse_request_string(
+            "POST /files/..%2fetc?q=a%26b HTTP/1.1\r\nHost: target.test\r\nCookie: sid=x%3B\r\n\r\nbody",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.url, "https://target.test/files/..%2fetc?q=a%26b");
+        assert_eq!(request.get_header("Cookie").map(String::as_str), Some("sid=x%3B"));
+        assert!(AttackExecutionCoordinator::parse_request_string("not a request").is_err());
+    }
+
     #[tokio::test]
     async fn test_attack_progress_tracking() {
         let (coordinator, _temp_dir) = create_test_coordinator().await;
@@ -726,10 +1338,19 @@ mod tests {
                 estimated_completion_time: None,
             },
             session_data: None,
+            flow_session: None,
+            upload: None,
+            sequence: None,
             concurrent_requests_per_agent: 1,
             timeout_seconds: 30,
             retry_attempts: 3,
+            rate_limit: RateLimitConfig::default(),
+            header_randomization: HeaderRandomizationConfig::default(),
+            baseline_requests: 0,
+            pacing: PacingProfile::default(),
+            timing_confirmation: None,
             result_highlighting_rules: Vec::new(),
+            resume_cursors: Vec::new(),
         };
 
         let agents = vec![AgentInfo {
@@ -757,6 +1378,76 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_pause_stores_checkpoint_and_resume_skips_sent_requests() {
+        let (coordinator, _temp_dir) = create_test_coordinator().await;
+        let attack_id = coordinator.db
+            .create_intruder_attack("a", "GET /?q=§p§ HTTP/1.1\r\n\r\n", "sniper", "[]", "[]", "round_robin")
+            .await
+            .unwrap();
+        let payloads: Vec<String> = (0..50).map(|i| i.to_string()).collect();
+        let mut config = AttackExecutionConfig {
+            attack_id: attack_id.clone(),
+            request_template: "GET /?q=§p§ HTTP/1.1\r\n\r\n".to_string(),
+            attack_mode: AttackMode::Sniper,
+            distribution: DistributionStats {
+                total_payloads: payloads.len(),
+                total_agents: 1,
+                assignments: vec![PayloadAssignment {
+                    agent_id: "agent1".to_string(),
+                    payloads: payloads.clone(),
+                    start_index: 0,
+                    end_index: payloads.len() - 1,
+                    priority: 5,
+                }],
+                load_balance_factor: 1.0,
+                estimated_completion_time: None,
+            },
+            session_data: None,
+            flow_session: None,
+            upload: None,
+            sequence: None,
+            concurrent_requests_per_agent: 1,
+            timeout_seconds: 30,
+            retry_attempts: 3,
+            rate_limit: RateLimitConfig::default(),
+            header_randomization: HeaderRandomizationConfig::default(),
+            baseline_requests: 0,
+            pacing: PacingProfile::default(),
+            timing_confirmation: None,
+            result_highlighting_rules: Vec::new(),
+            resume_cursors: Vec::new(),
+        };
+        let agents = vec![AgentInfo {
+            id: "agent1".to_string(),
+            hostname: "host1".to_string(),
+            status: AgentStatus::Online,
+            load: 0.1,
+            response_time_ms: Some(100),
+        }];
+
+        coordinator.start_attack(config.clone(), &agents).await.unwrap();
+        let checkpoint = coordinator.pause_attack(&attack_id).await.unwrap();
+        assert_eq!(checkpoint.cursors.len(), 1);
+        assert_eq!(checkpoint.completed_requests, checkpoint.cursors[0]);
+        assert!(checkpoint.cursors[0] <= 50);
+        assert!(coordinator.get_attack_progress(&attack_id).await.is_none());
+        assert!(coordinator.pause_attack(&attack_id).await.is_err());
+
+        let stored = coordinator.db.get_intruder_attack_checkpoint(&attack_id).await.unwrap().unwrap();
+        let stored: AttackCheckpoint = serde_json::from_str(&stored).unwrap();
+        assert_eq!(stored.cursors, checkpoint.cursors);
+        let attack = coordinator.db.get_intruder_attack(&attack_id).await.unwrap().unwrap();
+        assert_eq!(attack.status, "paused");
+
+        config.resume_cursors = vec![40];
+        coordinator.start_attack(config, &agents).await.unwrap();
+        let progress = coordinator.get_attack_progress(&attack_id).await.unwrap();
+        assert_eq!(progress.completed_requests, 40);
+        assert_eq!(progress.agent_statistics["agent1"].completed_requests, 40);
+        coordinator.stop_attack(&attack_id).await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_progress_subscription() {
         let (coordinator, _temp_dir) = create_test_coordinator().await;
@@ -0,0 +1,334 @@
+//! Flow-generated sessions for intruder attacks
+//!
+//! Targets with aggressive session binding (per-request CSRF rotation, lockout
+//! after N requests, single-use tokens) invalidate a static session partway
+//! through an attack. When an attack links a recorded flow, the minter replays
+//! it to obtain a fresh session every `requests_per_session` requests.
+
+use crate::session_integration::SessionManager;
+use crate::Database;
+use attack_engine::{AttackError, AttackResult};
+use flow_engine::{FlowProfile, FlowReplayer, ReplayOptions, ReplayResult};
+use proxy_common::session::{Cookie, Session, SessionStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Attack option: mint a new session from a flow replay every N requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowSessionConfig {
+    /// Flow profile replayed to mint sessions
+    pub profile_id: String,
+    /// Requests sent with one session before minting the next (1 = fresh session per payload)
+    pub requests_per_session: u32,
+    /// Variable substitutions passed to the replay
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+impl FlowSessionConfig {
+    pub fn validate(&self) -> AttackResult<()> {
+        if self.profile_id.trim().is_empty() {
+            return Err(AttackError::ValidationError {
+                field: "flow_session.profile_id".to_string(),
+                reason: "A flow profile is required".to_string(),
+            });
+        }
+        if self.requests_per_session == 0 {
+            return Err(AttackError::ValidationError {
+                field: "flow_session.requests_per_session".to_string(),
+                reason: "Must be at least 1".to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+struct MinterState {
+    current: Option<Session>,
+    used: u32,
+    minted: u64,
+}
+
+/// Hands out sessions to attack requests, replaying the linked flow whenever
+/// the current session has been used `requests_per_session` times.
+pub struct FlowSessionMinter {
+    profile: FlowProfile,
+    config: FlowSessionConfig,
+    session_manager: Option<Arc<SessionManager>>,
+    // Held across the replay so concurrent requests wait for one mint
+    state: Mutex<MinterState>,
+}
+
+impl FlowSessionMinter {
+    /// Load the linked flow profile
+    pub async fn new(
+        db: &Database,
+        config: FlowSessionConfig,
+        session_manager: Option<Arc<SessionManager>>,
+    ) -> AttackResult<Self> {
+        config.validate()?;
+
+        let row = db
+            .get_flow_profile(&config.profile_id)
+            .await
+            .map_err(|e| AttackError::DatabaseError {
+                operation: format!("get_flow_profile: {}", e),
+            })?
+            .ok_or_else(|| AttackError::InvalidAttackConfig {
+                reason: format!("Flow profile not found: {}", config.profile_id),
+            })?;
+        let profile = row.to_flow_profile().map_err(|e| AttackError::SerializationError {
+            error: format!("Failed to parse flow steps: {}", e),
+        })?;
+
+        Ok(Self {
+            profile,
+            config,
+            session_manager,
+            state: Mutex::new(MinterState {
+                current: None,
+                used: 0,
+                minted: 0,
+            }),
+        })
+    }
+
+    /// Session to use for the next request
+    pub async fn session_for_request(&self) -> AttackResult<Session> {
+        let mut state = self.state.lock().await;
+
+        let exhausted = state.used >= self.config.requests_per_session;
+        let session = match state.current.take() {
+            Some(session) if !exhausted => session,
+            _ => {
+                let session = self.mint().await?;
+                state.used = 0;
+                state.minted += 1;
+                session
+            }
+        };
+
+        state.used += 1;
+        state.current = Some(session.clone());
+        Ok(session)
+    }
+
+    /// URL the linked flow starts at
+    pub fn start_url(&self) -> &str {
+        &self.profile.start_url
+    }
+
+    /// Number of sessions minted so far
+    pub async fn minted(&self) -> u64 {
+        self.state.lock().await.minted
+    }
+
+    /// Replace `stale`, which the target stopped accepting, with a freshly
+    /// minted session. If another request already replaced it, that
+    /// session is returned instead of minting again.
+    pub async fn renew(&self, stale: &Session) -> AttackResult<Session> {
+        let mut state = self.state.lock().await;
+        if let Some(current) = state.current.as_ref().filter(|current| current.id != stale.id) {
+            return Ok(current.clone());
+        }
+
+        let session = self.mint().await?;
+        state.used = 1;
+        state.minted += 1;
+        state.current = Some(session.clone());
+        Ok(session)
+    }
+
+    async fn mint(&self) -> AttackResult<Session> {
+        replay_session(&self.profile, &self.config.variables, self.session_manager.as_ref()).await
+    }
+}
+
+/// Replay `profile` and build a session from the cookies it ends with
+pub async fn replay_session(
+    profile: &FlowProfile,
+    variables: &HashMap<String, String>,
+    session_manager: Option<&Arc<SessionManager>>,
+) -> AttackResult<Session> {
+    info!("🔑 Minting session via flow '{}'", profile.name);
+
+    let options = ReplayOptions {
+        variables: variables.clone(),
+        ..Default::default()
+    };
+    let result = FlowReplayer::with_options(options)
+        .execute(profile)
+        .await
+        .map_err(|e| AttackError::AuthenticationFailure {
+            reason: format!("Flow replay failed: {}", e),
+        })?;
+
+    if !result.success {
+        return Err(AttackError::AuthenticationFailure {
+            reason: format!(
+                "Flow replay failed at step {}/{}: {}",
+                result.steps_completed,
+                result.total_steps,
+                result.error.unwrap_or_default()
+            ),
+        });
+    }
+
+    let session = session_from_replay(profile, &result).ok_or_else(|| AttackError::AuthenticationFailure {
+        reason: "Flow replay produced no session cookies".to_string(),
+    })?;
+
+    if let Some(manager) = session_manager {
+        if let Err(e) = manager.add_session(session.clone()).await {
+            warn!("Failed to register minted session: {}", e);
+        }
+    }
+
+    Ok(session)
+}
+
+/// Renews the static session of an attack by replaying the login flow it
+/// was recorded with, once responses show the target stopped accepting it
+pub struct SessionRefresher {
+    profile: FlowProfile,
+    session_manager: Option<Arc<SessionManager>>,
+    current: Mutex<Session>,
+}
+
+impl SessionRefresher {
+    /// `None` if the session was not recorded from a flow, or the flow is gone
+    pub async fn for_session(
+        db: &Database,
+        session: Session,
+        session_manager: Option<Arc<SessionManager>>,
+    ) -> Option<Self> {
+        let profile_id = session.profile_id?;
+        let profile = match db.get_flow_profile(&profile_id.to_string()).await {
+            Ok(Some(row)) => row.to_flow_profile().ok()?,
+            Ok(None) => return None,
+            Err(e) => {
+                warn!("Failed to load flow profile {} for session refresh: {}", profile_id, e);
+                return None;
+            }
+        };
+        Some(Self {
+            profile,
+            session_manager,
+            current: Mutex::new(session),
+        })
+    }
+
+    /// URL the login flow starts at
+    pub fn start_url(&self) -> &str {
+        &self.profile.start_url
+    }
+
+    pub async fn current(&self) -> Session {
+        self.current.lock().await.clone()
+    }
+
+    /// Same as [`FlowSessionMinter::renew`]
+    pub async fn renew(&self, stale: &Session) -> AttackResult<Session> {
+        let mut current = self.current.lock().await;
+        if current.id != stale.id {
+            return Ok(current.clone());
+        }
+        let session = replay_session(&self.profile, &HashMap::new(), self.session_manager.as_ref()).await?;
+        *current = session.clone();
+        Ok(session)
+    }
+}
+
+/// Build a session from the cookies captured at the end of a replay
+pub fn session_from_replay(profile: &FlowProfile, result: &ReplayResult) -> Option<Session> {
+    #[derive(Deserialize)]
+    struct BrowserCookie {
+        name: Option<String>,
+        value: Option<String>,
+    }
+
+    let cookies: Vec<BrowserCookie> = serde_json::from_str(result.session_cookies.as_deref()?).ok()?;
+    let url = reqwest::Url::parse(result.final_url.as_deref().unwrap_or(&profile.start_url)).ok();
+    let domain = url.as_ref().and_then(|u| u.host_str().map(|h| h.to_string()));
+    let secure = url.as_ref().is_some_and(|u| u.scheme() == "https");
+
+    let mut session = Session::new(format!("{} (flow)", profile.name), Some(profile.id));
+    session.cookies = cookies
+        .into_iter()
+        .filter_map(|c| {
+            let name = c.name.filter(|n| !n.is_empty())?;
+            Some(Cookie {
+                name,
+                value: c.value.unwrap_or_default(),
+                domain: domain.clone(),
+                path: Some("/".to_string()),
+                expires: None,
+                http_only: false,
+                secure,
+                same_site: None,
+            })
+        })
+        .collect();
+    if session.cookies.is_empty() {
+        return None;
+    }
+
+    session.status = SessionStatus::Active;
+    session.metadata.success_indicators = profile.meta.success_indicators.clone();
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replay_result(cookies: Option<&str>) -> ReplayResult {
+        ReplayResult {
+            success: true,
+            error: None,
+            steps_completed: 3,
+            total_steps: 3,
+            session_cookies: cookies.map(|c| c.to_string()),
+            extracted_data: HashMap::new(),
+            final_url: Some("https://app.example.com/dashboard".to_string()),
+            duration_ms: 1200,
+        }
+    }
+
+    #[test]
+    fn test_session_from_replay_cookies() {
+        let profile = FlowProfile::new("Login", "https://app.example.com/login");
+        let result = replay_result(Some(r#"[{"name":"sid","value":"abc"},{"name":"","value":"x"}]"#));
+
+        let session = session_from_replay(&profile, &result).unwrap();
+        assert_eq!(session.profile_id, Some(profile.id));
+        assert_eq!(session.cookies.len(), 1);
+        assert_eq!(session.cookies[0].name, "sid");
+        assert_eq!(session.cookies[0].domain.as_deref(), Some("app.example.com"));
+        assert!(session.cookies[0].secure);
+        assert!(matches!(session.status, SessionStatus::Active));
+    }
+
+    #[test]
+    fn test_no_cookies_is_no_session() {
+        let profile = FlowProfile::new("Login", "https://app.example.com/login");
+        assert!(session_from_replay(&profile, &replay_result(None)).is_none());
+        assert!(session_from_replay(&profile, &replay_result(Some("[]"))).is_none());
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let mut config = FlowSessionConfig {
+            profile_id: "flow-1".to_string(),
+            requests_per_session: 1,
+            variables: HashMap::new(),
+        };
+        assert!(config.validate().is_ok());
+
+        config.requests_per_session = 0;
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,308 @@
+//! Highlight rules of an intruder attack
+//!
+//! Every result is checked against the attack's rules as it arrives. A
+//! result matching any rule is marked highlighted with a reason naming the
+//! rules that matched and what they saw, so anomalies stand out in the
+//! results grid without sorting or filtering it.
+
+use attack_engine::{AttackError, AttackResult, HttpResponseData};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// Rule for highlighting interesting results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultHighlightRule {
+    pub name: String,
+    pub condition: HighlightCondition,
+    pub priority: u8, // 1-10, higher is more important
+}
+
+/// Conditions for result highlighting
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum HighlightCondition {
+    StatusCode(Vec<i32>),
+    ResponseLength { min: Option<usize>, max: Option<usize> },
+    /// Body length differs from the baseline by more than `percent` percent.
+    /// The baseline is `baseline` bytes when set, else the attack's measured
+    /// baseline, else the length of the first response of the attack.
+    LengthDeviation { percent: f64, baseline: Option<usize> },
+    ResponseTime { min_ms: Option<u64>, max_ms: Option<u64> },
+    ResponseContains(String),
+    ResponseRegex(String),
+    Combined { operator: LogicalOperator, conditions: Vec<HighlightCondition> },
+}
+
+/// Logical operators for combining highlight conditions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LogicalOperator {
+    And,
+    Or,
+    Not,
+}
+
+impl ResultHighlightRule {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Highlight rule name cannot be empty".to_string());
+        }
+        if !(1..=10).contains(&self.priority) {
+            return Err(format!("Highlight rule '{}': priority must be between 1 and 10", self.name));
+        }
+        self.condition
+            .validate()
+            .map_err(|e| format!("Highlight rule '{}': {}", self.name, e))
+    }
+}
+
+impl HighlightCondition {
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            HighlightCondition::StatusCode(codes) if codes.is_empty() => Err("no status codes given".to_string()),
+            HighlightCondition::LengthDeviation { percent, .. } if !percent.is_finite() || *percent <= 0.0 => {
+                Err("length deviation must be a positive percentage".to_string())
+            }
+            HighlightCondition::ResponseContains(text) if text.is_empty() => Err("empty search text".to_string()),
+            HighlightCondition::ResponseRegex(pattern) => Regex::new(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("invalid regex: {}", e)),
+            HighlightCondition::Combined { conditions, .. } if conditions.is_empty() => {
+                Err("no conditions to combine".to_string())
+            }
+            HighlightCondition::Combined { conditions, .. } => conditions.iter().try_for_each(Self::validate),
+            _ => Ok(()),
+        }
+    }
+
+    fn regexes<'a>(&'a self, patterns: &mut Vec<&'a str>) {
+        match self {
+            HighlightCondition::ResponseRegex(pattern) => patterns.push(pattern),
+            HighlightCondition::Combined { conditions, .. } => {
+                conditions.iter().for_each(|condition| condition.regexes(patterns))
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What a highlight rule is checked against
+pub struct HighlightSubject<'a> {
+    pub response: Option<&'a HttpResponseData>,
+    pub duration_ms: Option<u64>,
+}
+
+/// Evaluates an attack's rules against its results
+pub struct ResultHighlighter {
+    /// Rules by descending priority
+    rules: Vec<ResultHighlightRule>,
+    regexes: HashMap<String, Regex>,
+    /// Measured baseline length, or that of the first response
+    baseline_length: OnceLock<usize>,
+}
+
+impl ResultHighlighter {
+    pub fn new(rules: &[ResultHighlightRule]) -> AttackResult<Self> {
+        let invalid = |reason: String| AttackError::InvalidAttackConfig { reason };
+        let mut patterns = Vec::new();
+        for rule in rules {
+            rule.validate().map_err(invalid)?;
+            rule.condition.regexes(&mut patterns);
+        }
+        let regexes = patterns
+            .into_iter()
+            .map(|pattern| Regex::new(pattern).map(|regex| (pattern.to_string(), regex)))
+            .collect::<Result<_, _>>()
+            .map_err(|e| invalid(e.to_string()))?;
+
+        let mut rules = rules.to_vec();
+        rules.sort_by_key(|rule| std::cmp::Reverse(rule.priority));
+        Ok(Self { rules, regexes, baseline_length: OnceLock::new() })
+    }
+
+    /// Measure length deviations from `length` rather than from the first
+    /// response
+    pub fn use_baseline_length(&self, length: usize) {
+        let _ = self.baseline_length.set(length);
+    }
+
+    /// Reason a result is highlighted: the matching rules with what they
+    /// saw, most important first. `None` when no rule matches.
+    pub fn evaluate(&self, subject: &HighlightSubject<'_>) -> Option<String> {
+        if let Some(response) = subject.response {
+            self.baseline_length.get_or_init(|| response.body.len());
+        }
+        let matches: Vec<String> = self
+            .rules
+            .iter()
+            .filter_map(|rule| {
+                self.check(&rule.condition, subject)
+                    .map(|detail| format!("{} ({})", rule.name, detail))
+            })
+            .collect();
+        (!matches.is_empty()).then(|| matches.join("; "))
+    }
+
+    /// What `condition` matched, if it matches
+    fn check(&self, condition: &HighlightCondition, subject: &HighlightSubject<'_>) -> Option<String> {
+        let response = subject.response;
+        match condition {
+            HighlightCondition::StatusCode(codes) => response
+                .filter(|r| codes.contains(&r.status_code))
+                .map(|r| format!("status {}", r.status_code)),
+            HighlightCondition::ResponseLength { min, max } => {
+                let length = response?.body.len();
+                let within = min.is_none_or(|m| length >= m) && max.is_none_or(|m| length <= m);
+                within.then(|| format!("length {}", length))
+            }
+            HighlightCondition::LengthDeviation { percent, baseline } => {
+                let length = response?.body.len();
+                let baseline = baseline.or_else(|| self.baseline_length.get().copied())?;
+                let deviation = if baseline == 0 {
+                    if length == 0 { 0.0 } else { f64::INFINITY }
+                } else {
+                    (length as f64 - baseline as f64) / baseline as f64 * 100.0
+                };
+                (deviation.abs() > *percent).then(|| {
+                    if deviation.is_finite() {
+                        format!("length {}, {:+.0}% from {}", length, deviation, baseline)
+                    } else {
+                        format!("length {}, baseline empty", length)
+                    }
+                })
+            }
+            HighlightCondition::ResponseTime { min_ms, max_ms } => {
+                let duration = subject.duration_ms?;
+                let within = min_ms.is_none_or(|m| duration >= m) && max_ms.is_none_or(|m| duration <= m);
+                within.then(|| format!("{} ms", duration))
+            }
+            HighlightCondition::ResponseContains(text) => {
+                let body = String::from_utf8_lossy(&response?.body);
+                body.contains(text.as_str()).then(|| format!("body contains {:?}", text))
+            }
+            HighlightCondition::ResponseRegex(pattern) => {
+                let body = String::from_utf8_lossy(&response?.body);
+                self.regexes
+                    .get(pattern)?
+                    .find(&body)
+                    .map(|found| format!("body matches {:?}", found.as_str()))
+            }
+            HighlightCondition::Combined { operator, conditions } => match operator {
+                LogicalOperator::And => conditions
+                    .iter()
+                    .map(|c| self.check(c, subject))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|details| details.join(", ")),
+                LogicalOperator::Or => {
+                    let details: Vec<String> = conditions.iter().filter_map(|c| self.check(c, subject)).collect();
+                    (!details.is_empty()).then(|| details.join(", "))
+                }
+                LogicalOperator::Not => conditions
+                    .iter()
+                    .all(|c| self.check(c, subject).is_none())
+                    .then(|| "no condition matched".to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(name: &str, priority: u8, condition: HighlightCondition) -> ResultHighlightRule {
+        ResultHighlightRule { name: name.to_string(), condition, priority }
+    }
+
+    fn response(status_code: i32, body: &str) -> HttpResponseData {
+        HttpResponseData { status_code, headers: None, body: body.as_bytes().to_vec(), tls: None, timing: None, protocol: None }
+    }
+
+    #[test]
+    fn test_rules_give_a_reason_for_each_match() {
+        let highlighter = ResultHighlighter::new(&[
+            rule("Server error", 5, HighlightCondition::StatusCode(vec![500, 502])),
+            rule("Slow", 3, HighlightCondition::ResponseTime { min_ms: Some(1000), max_ms: None }),
+            rule("SQL error", 9, HighlightCondition::ResponseRegex(r"SQL syntax.*MySQL".to_string())),
+        ])
+        .unwrap();
+
+        let error = response(500, "You have an error in your SQL syntax; check the MySQL manual");
+        let reason = highlighter.evaluate(&HighlightSubject { response: Some(&error), duration_ms: Some(1500) });
+        assert_eq!(
+            reason.as_deref(),
+            Some("SQL error (body matches \"SQL syntax; check the MySQL\"); Server error (status 500); Slow (1500 ms)")
+        );
+
+        let ok = response(200, "fine");
+        assert_eq!(highlighter.evaluate(&HighlightSubject { response: Some(&ok), duration_ms: Some(20) }), None);
+        // A failed request can still be slow
+        let reason = highlighter.evaluate(&HighlightSubject { response: None, duration_ms: Some(3000) });
+        assert_eq!(reason.as_deref(), Some("Slow (3000 ms)"));
+    }
+
+    #[test]
+    fn test_length_deviation_from_first_response_or_given_baseline() {
+        let highlighter = ResultHighlighter::new(&[
+            rule("Length", 5, HighlightCondition::LengthDeviation { percent: 20.0, baseline: None }),
+            rule("Not 404-sized", 4, HighlightCondition::LengthDeviation { percent: 50.0, baseline: Some(10) }),
+        ])
+        .unwrap();
+        let evaluate = |body: &str| {
+            let response = response(200, body);
+            highlighter.evaluate(&HighlightSubject { response: Some(&response), duration_ms: None })
+        };
+
+        // The first response sets the baseline and never deviates from itself
+        assert_eq!(evaluate(&"a".repeat(100)), Some("Not 404-sized (length 100, +900% from 10)".to_string()));
+        assert_eq!(evaluate(&"a".repeat(115)).as_deref(), Some("Not 404-sized (length 115, +1050% from 10)"));
+        assert_eq!(evaluate(&"a".repeat(70)).as_deref(), Some("Length (length 70, -30% from 100); Not 404-sized (length 70, +600% from 10)"));
+        assert_eq!(evaluate(&"a".repeat(12)).as_deref(), Some("Length (length 12, -88% from 100)"));
+
+        let measured = ResultHighlighter::new(&[rule(
+            "Length",
+            5,
+            HighlightCondition::LengthDeviation { percent: 20.0, baseline: None },
+        )])
+        .unwrap();
+        measured.use_baseline_length(50);
+        let response = response(200, &"a".repeat(100));
+        let reason = measured.evaluate(&HighlightSubject { response: Some(&response), duration_ms: None });
+        assert_eq!(reason.as_deref(), Some("Length (length 100, +100% from 50)"));
+    }
+
+    #[test]
+    fn test_combined_conditions_and_validation() {
+        let highlighter = ResultHighlighter::new(&[rule(
+            "Login bypass",
+            8,
+            HighlightCondition::Combined {
+                operator: LogicalOperator::And,
+                conditions: vec![
+                    HighlightCondition::StatusCode(vec![302]),
+                    HighlightCondition::Combined {
+                        operator: LogicalOperator::Not,
+                        conditions: vec![HighlightCondition::ResponseContains("Invalid password".to_string())],
+                    },
+                ],
+            },
+        )])
+        .unwrap();
+        let redirect = response(302, "");
+        let reason = highlighter.evaluate(&HighlightSubject { response: Some(&redirect), duration_ms: None });
+        assert_eq!(reason.as_deref(), Some("Login bypass (status 302, no condition matched)"));
+        let rejected = response(302, "Invalid password");
+        assert!(highlighter.evaluate(&HighlightSubject { response: Some(&rejected), duration_ms: None }).is_none());
+
+        for invalid in [
+            rule("", 5, HighlightCondition::StatusCode(vec![200])),
+            rule("a", 0, HighlightCondition::StatusCode(vec![200])),
+            rule("a", 5, HighlightCondition::StatusCode(vec![])),
+            rule("a", 5, HighlightCondition::LengthDeviation { percent: 0.0, baseline: None }),
+            rule("a", 5, HighlightCondition::ResponseRegex("(".to_string())),
+        ] {
+            assert!(ResultHighlighter::new(&[invalid]).is_err());
+        }
+    }
+}
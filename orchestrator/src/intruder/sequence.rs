@@ -0,0 +1,217 @@
+//! Multi-step attack sequences
+//!
+//! Some flaws only show across requests: a price tampered with when an item
+//! goes into the cart is only charged at checkout. A sequence attack sends a
+//! short list of request templates before the attack's own request, once per
+//! payload. Markers in the steps are payload positions like those of the
+//! attack's template. Values a step extracts from its response (header,
+//! cookie, regex or JSONPath, as in session macros) fill `{{name}}`
+//! placeholders and parameters of that name in the later steps and in the
+//! attack's request, whose response is the result.
+
+use crate::session_integration::macros::{apply_parameters, TokenExtraction};
+use attack_engine::{
+    AttackError, AttackResult, HttpRequestData, HttpResponseData, PayloadPosition, PayloadPositionParser, RawRequest,
+};
+use proxy_common::Session;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Steps are sent for every payload, so sequences are kept short
+pub const MAX_SEQUENCE_STEPS: usize = 10;
+
+/// Requests sent before each request of an attack
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackSequence {
+    /// Sent in order
+    pub steps: Vec<SequenceStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SequenceStep {
+    /// Raw HTTP request with `§marker§` positions and `{{name}}` placeholders
+    pub request_template: String,
+    #[serde(default)]
+    pub extractions: Vec<TokenExtraction>,
+}
+
+fn invalid(reason: String) -> AttackError {
+    AttackError::ValidationError {
+        field: "sequence".to_string(),
+        reason,
+    }
+}
+
+impl AttackSequence {
+    pub fn validate(&self) -> AttackResult<()> {
+        if self.steps.is_empty() {
+            return Err(invalid("A sequence needs at least one step".to_string()));
+        }
+        if self.steps.len() > MAX_SEQUENCE_STEPS {
+            return Err(invalid(format!("A sequence has at most {} steps", MAX_SEQUENCE_STEPS)));
+        }
+        for (index, step) in self.steps.iter().enumerate() {
+            let parsed = PayloadPositionParser::parse(&step.request_template)
+                .map_err(|e| invalid(format!("Step {}: {}", index + 1, e)))?;
+            let blank: HashMap<String, String> = parsed
+                .positions
+                .iter()
+                .map(|position| (position.payload_set_id.clone(), String::new()))
+                .collect();
+            step.request(&blank).map_err(|e| invalid(format!("Step {}: {}", index + 1, e)))?;
+
+            for extraction in &step.extractions {
+                if extraction.name.trim().is_empty() {
+                    return Err(invalid(format!("Step {} has an extraction without a name", index + 1)));
+                }
+                extraction
+                    .source
+                    .validate()
+                    .map_err(|e| invalid(format!("Step {}: {} for '{}'", index + 1, e, extraction.name)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Payload positions of the steps, numbered from `first_index` after the
+    /// attack template's own. Like upload positions they occupy no text in
+    /// that template; each step fills its markers when it is sent.
+    pub fn payload_positions(&self, first_index: usize) -> Vec<PayloadPosition> {
+        self.steps
+            .iter()
+            .filter_map(|step| PayloadPositionParser::parse(&step.request_template).ok())
+            .flat_map(|parsed| parsed.positions)
+            .enumerate()
+            .map(|(i, position)| PayloadPosition {
+                start: 0,
+                end: 0,
+                index: first_index + i,
+                ..position
+            })
+            .collect()
+    }
+
+    /// Send the steps for one request of the attack with `send` and fill the
+    /// values they extract into `request`. `payload_values` are the
+    /// request's payloads by marker; `session` is applied to every step.
+    /// Returns the extracted values.
+    pub async fn run<F, Fut>(
+        &self,
+        payload_values: &HashMap<String, String>,
+        session: Option<&Session>,
+        request: &mut HttpRequestData,
+        mut send: F,
+    ) -> AttackResult<HashMap<String, String>>
+    where
+        F: FnMut(HttpRequestData) -> Fut,
+        Fut: Future<Output = AttackResult<HttpResponseData>>,
+    {
+        let mut parameters = HashMap::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let failed = |error: String| AttackError::ExecutionFailed {
+                error: format!("Sequence step {}: {}", index + 1, error),
+            };
+            let mut step_request = step.request(payload_values).map_err(|e| failed(e.to_string()))?;
+            if let Some(session) = session {
+                step_request.apply_session(session);
+            }
+            apply_parameters(&mut step_request, &parameters);
+
+            let response = send(step_request).await.map_err(|e| failed(e.to_string()))?;
+            for extraction in &step.extractions {
+                let value = extraction
+                    .source
+                    .extract(&response)
+                    .ok_or_else(|| failed(format!("nothing to extract for '{}'", extraction.name)))?;
+                parameters.insert(extraction.name.clone(), value);
+            }
+        }
+        apply_parameters(request, &parameters);
+        Ok(parameters)
+    }
+}
+
+impl SequenceStep {
+    /// The step's request with its markers filled from `payload_values`
+    fn request(&self, payload_values: &HashMap<String, String>) -> AttackResult<HttpRequestData> {
+        let parsed = PayloadPositionParser::parse(&self.request_template)?;
+        let raw = PayloadPositionParser::inject_payloads(&parsed, payload_values)?;
+        RawRequest::parse(raw.as_bytes())?.to_request_data(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session_integration::macros::ExtractionSource;
+    use std::sync::Mutex;
+
+    fn cart_sequence() -> AttackSequence {
+        AttackSequence {
+            steps: vec![SequenceStep {
+                request_template: "POST /cart HTTP/1.1\r\nHost: shop.test\r\nContent-Type: application/json\r\n\r\n{\"sku\":\"A-1\",\"price\":\"§payload§\"}".to_string(),
+                extractions: vec![TokenExtraction {
+                    name: "cart_id".to_string(),
+                    source: ExtractionSource::JsonPath { path: "$.cart.id".to_string() },
+                }],
+            }],
+        }
+    }
+
+    fn response(body: &str) -> HttpResponseData {
+        HttpResponseData { status_code: 200, headers: None, body: body.as_bytes().to_vec(), tls: None, timing: None, protocol: None }
+    }
+
+    #[tokio::test]
+    async fn test_steps_carry_payloads_and_feed_the_final_request() {
+        let sent = Mutex::new(Vec::new());
+        let mut checkout = HttpRequestData::new("POST".to_string(), "https://shop.test/checkout?cart={{cart_id}}".to_string());
+        let payload_values = HashMap::from([("payload".to_string(), "-1".to_string())]);
+
+        let extracted = cart_sequence()
+            .run(&payload_values, None, &mut checkout, |step| {
+                sent.lock().unwrap().push(step);
+                async { Ok(response(r#"{"cart": {"id": "c-77"}}"#)) }
+            })
+            .await
+            .unwrap();
+
+        let sent = sent.into_inner().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].url, "https://shop.test/cart");
+        assert!(String::from_utf8_lossy(&sent[0].body).contains(r#""price":"-1""#));
+        assert_eq!(extracted["cart_id"], "c-77");
+        assert_eq!(checkout.url, "https://shop.test/checkout?cart=c-77");
+    }
+
+    #[tokio::test]
+    async fn test_missing_extraction_fails_the_sequence() {
+        let mut checkout = HttpRequestData::new("POST".to_string(), "https://shop.test/checkout".to_string());
+        let payload_values = HashMap::from([("payload".to_string(), "1".to_string())]);
+        let error = cart_sequence()
+            .run(&payload_values, None, &mut checkout, |_| async { Ok(response("<html>")) })
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("step 1") && error.contains("cart_id"), "{}", error);
+    }
+
+    #[test]
+    fn test_validation_and_positions() {
+        let sequence = cart_sequence();
+        assert!(sequence.validate().is_ok());
+        let positions = sequence.payload_positions(2);
+        assert_eq!(positions.len(), 1);
+        assert_eq!((positions[0].index, positions[0].start, positions[0].end), (2, 0, 0));
+        assert_eq!(positions[0].payload_set_id, "payload");
+
+        assert!(AttackSequence { steps: Vec::new() }.validate().is_err());
+        let mut bad_path = cart_sequence();
+        bad_path.steps[0].extractions[0].source = ExtractionSource::JsonPath { path: "cart.id".to_string() };
+        assert!(bad_path.validate().is_err());
+        let mut unparsable = cart_sequence();
+        unparsable.steps[0].request_template = "§payload".to_string();
+        assert!(unparsable.validate().is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! JSON Web Token inspection
+//!
+//! Decodes the header and claims of a signed JWT (JWS compact form) without
+//! verifying it, reports the registered time claims and flags weaknesses a
+//! tester would look at first: `alg: none`, expired or not-yet-valid tokens,
+//! tokens that never expire and HMAC signatures shorter than the algorithm
+//! produces.
+//!
+//! Captured requests carrying a bearer JWT are tagged `jwt` (plus one tag per
+//! weakness) and, if nobody commented on them yet, get a comment with the
+//! token's algorithm and expiry.
+
+use crate::pb::HttpRequestData;
+use crate::Database;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// Something about a token worth a tester's attention
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenWeakness {
+    /// Unsigned token; servers accepting it accept any claims
+    AlgNone,
+    Expired,
+    /// `nbf` lies in the future
+    NotYetValid,
+    /// No `exp` claim
+    NoExpiry,
+    /// HMAC signature shorter than the digest of its algorithm
+    TruncatedHmacSignature,
+}
+
+impl TokenWeakness {
+    /// Tag given to captured transactions with such a token
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Self::AlgNone => "jwt-alg-none",
+            Self::Expired => "jwt-expired",
+            Self::NotYetValid => "jwt-not-yet-valid",
+            Self::NoExpiry => "jwt-no-expiry",
+            Self::TruncatedHmacSignature => "jwt-truncated-hmac",
+        }
+    }
+}
+
+/// A decoded JWT
+#[derive(Debug, Clone)]
+pub struct DecodedToken {
+    pub header: Map<String, Value>,
+    pub claims: Map<String, Value>,
+    pub alg: Option<String>,
+    pub issued_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub not_before: Option<DateTime<Utc>>,
+    /// Decoded signature length in bytes
+    pub signature_length: usize,
+    pub weaknesses: Vec<TokenWeakness>,
+}
+
+impl DecodedToken {
+    /// Decode `value`, a bare token or an `Authorization` value with a
+    /// `Bearer` prefix. `None` if it is not a signed JWT.
+    pub fn decode(value: &str, now: DateTime<Utc>) -> Option<Self> {
+        let value = value.trim();
+        let token = match value.split_once(char::is_whitespace) {
+            Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer") => token.trim(),
+            _ => value,
+        };
+        let mut segments = token.split('.');
+        let (header, claims, signature) = (segments.next()?, segments.next()?, segments.next()?);
+        // Five segments are an encrypted token, whose claims cannot be read
+        if segments.next().is_some() {
+            return None;
+        }
+
+        let header = decode_object(header)?;
+        let claims = decode_object(claims)?;
+        let signature_length = base64_url(signature)?.len();
+        let alg = header.get("alg").and_then(Value::as_str).map(str::to_string);
+        alg.as_ref()?;
+
+        let mut token = DecodedToken {
+            issued_at: numeric_date(claims.get("iat")),
+            expires_at: numeric_date(claims.get("exp")),
+            not_before: numeric_date(claims.get("nbf")),
+            header,
+            claims,
+            alg,
+            signature_length,
+            weaknesses: Vec::new(),
+        };
+        token.weaknesses = token.find_weaknesses(now);
+        Some(token)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|exp| exp <= now)
+    }
+
+    fn find_weaknesses(&self, now: DateTime<Utc>) -> Vec<TokenWeakness> {
+        let mut weaknesses = Vec::new();
+        let alg = self.alg.as_deref().unwrap_or_default().to_ascii_uppercase();
+        if alg == "NONE" {
+            weaknesses.push(TokenWeakness::AlgNone);
+        }
+        if self.is_expired(now) {
+            weaknesses.push(TokenWeakness::Expired);
+        }
+        if self.not_before.is_some_and(|nbf| nbf > now) {
+            weaknesses.push(TokenWeakness::NotYetValid);
+        }
+        if self.expires_at.is_none() {
+            weaknesses.push(TokenWeakness::NoExpiry);
+        }
+        let digest_length = match alg.as_str() {
+            "HS256" => Some(32),
+            "HS384" => Some(48),
+            "HS512" => Some(64),
+            _ => None,
+        };
+        if digest_length.is_some_and(|length| self.signature_length < length) {
+            weaknesses.push(TokenWeakness::TruncatedHmacSignature);
+        }
+        weaknesses
+    }
+
+    /// One-line summary used as the comment of captured transactions
+    pub fn summary(&self) -> String {
+        let alg = self.alg.as_deref().unwrap_or("?");
+        match self.expires_at {
+            Some(exp) => format!("Bearer JWT ({}), expires {}", alg, exp.to_rfc3339()),
+            None => format!("Bearer JWT ({}), no expiry", alg),
+        }
+    }
+}
+
+fn base64_url(segment: &str) -> Option<Vec<u8>> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(segment.trim_end_matches('='))
+        .ok()
+}
+
+fn decode_object(segment: &str) -> Option<Map<String, Value>> {
+    match serde_json::from_slice(&base64_url(segment)?).ok()? {
+        Value::Object(object) => Some(object),
+        _ => None,
+    }
+}
+
+/// RFC 7519 NumericDate: seconds since the epoch, possibly fractional
+fn numeric_date(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let seconds = value?.as_f64()?;
+    DateTime::from_timestamp_millis((seconds * 1000.0) as i64)
+}
+
+/// Bearer JWT in the `Authorization` header of `request`, if any
+pub fn bearer_token(request: &HttpRequestData, now: DateTime<Utc>) -> Option<DecodedToken> {
+    let headers = request.headers.as_ref()?;
+    let (_, value) = headers.headers.iter().find(|(name, _)| name.eq_ignore_ascii_case("authorization"))?;
+    let (scheme, _) = value.trim().split_once(char::is_whitespace)?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    DecodedToken::decode(value, now)
+}
+
+/// Tag and comment the stored transaction `request_id` if its request
+/// carries a bearer JWT. A comment set by hand is left alone.
+pub async fn annotate(db: &Database, request_id: &str, request: &HttpRequestData) {
+    let Some(token) = bearer_token(request, Utc::now()) else {
+        return;
+    };
+    let tags: Vec<String> = std::iter::once("jwt")
+        .chain(token.weaknesses.iter().map(TokenWeakness::tag))
+        .map(str::to_string)
+        .collect();
+    let result = async {
+        db.add_request_tags(request_id, &tags).await?;
+        let current = db.get_request_annotation(request_id).await?;
+        if current.is_some_and(|annotation| annotation.comment.is_none()) {
+            db.set_request_comment(request_id, Some(&token.summary())).await?;
+        }
+        Ok::<_, sqlx::Error>(())
+    };
+    if let Err(e) = result.await {
+        warn!("Failed to annotate JWT of {}: {}", request_id, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::HttpHeaders;
+    use std::collections::HashMap;
+
+    fn token(header: &str, claims: &str, signature: &[u8]) -> String {
+        let engine = base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        format!("{}.{}.{}", engine.encode(header), engine.encode(claims), engine.encode(signature))
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(seconds, 0).unwrap()
+    }
+
+    #[test]
+    fn test_decode_and_weaknesses() {
+        let value = token(r#"{"alg":"HS256","typ":"JWT"}"#, r#"{"sub":"alice","iat":1000,"exp":2000}"#, &[0u8; 32]);
+        let decoded = DecodedToken::decode(&format!("Bearer {}", value), at(1500)).unwrap();
+        assert_eq!(decoded.alg.as_deref(), Some("HS256"));
+        assert_eq!(decoded.claims["sub"], "alice");
+        assert_eq!(decoded.issued_at, Some(at(1000)));
+        assert_eq!(decoded.expires_at, Some(at(2000)));
+        assert!(decoded.weaknesses.is_empty());
+        assert_eq!(DecodedToken::decode(&value, at(2000)).unwrap().weaknesses, vec![TokenWeakness::Expired]);
+
+        let unsigned = token(r#"{"alg":"none"}"#, r#"{"admin":true,"nbf":5000}"#, b"");
+        assert_eq!(
+            DecodedToken::decode(&unsigned, at(1500)).unwrap().weaknesses,
+            vec![TokenWeakness::AlgNone, TokenWeakness::NotYetValid, TokenWeakness::NoExpiry]
+        );
+
+        let truncated = token(r#"{"alg":"HS512"}"#, r#"{"exp":2000.5}"#, &[0u8; 16]);
+        assert_eq!(
+            DecodedToken::decode(&truncated, at(1500)).unwrap().weaknesses,
+            vec![TokenWeakness::TruncatedHmacSignature]
+        );
+
+        assert!(DecodedToken::decode("not.a.jwt", at(0)).is_none());
+        assert!(DecodedToken::decode(&token(r#"{"typ":"JWT"}"#, "{}", b"x"), at(0)).is_none());
+        assert!(DecodedToken::decode("a.b.c.d.e", at(0)).is_none());
+    }
+
+    #[test]
+    fn test_bearer_token_from_request() {
+        let value = token(r#"{"alg":"RS256"}"#, r#"{"exp":2000}"#, &[1u8; 256]);
+        let request = |authorization: &str| HttpRequestData {
+            headers: Some(HttpHeaders {
+                headers: HashMap::from([("authorization".to_string(), authorization.to_string())]),
+            }),
+            ..Default::default()
+        };
+        let decoded = bearer_token(&request(&format!("Bearer {}", value)), at(1000)).unwrap();
+        assert_eq!(decoded.summary(), "Bearer JWT (RS256), expires 1970-01-01T00:33:20+00:00");
+        assert!(bearer_token(&request(&format!("Basic {}", value)), at(1000)).is_none());
+        assert!(bearer_token(&HttpRequestData::default(), at(1000)).is_none());
+    }
+}
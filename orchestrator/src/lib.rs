@@ -1,4 +1,4 @@
-use axum::extract::{State, Request};
+use axum::extract::{RawQuery, State, Request};
 use axum::routing::get;
 use axum::{Extension, Json, Router};
 use axum::middleware::{self, Next};
@@ -13,6 +13,7 @@ use utoipa_swagger_ui::SwaggerUi;
 use axum::http::Method;
 use async_graphql_axum::{GraphQLProtocol, GraphQLWebSocket};
 use axum::extract::ws::WebSocketUpgrade;
+use proxy_core::ProbeCheck;
 
 pub mod pb {
     pub use proxy_core::pb::*;
@@ -34,7 +35,35 @@ pub mod recording_service;
 pub mod result_streaming;
 pub mod performance_monitoring;
 pub mod error_handling;
+pub mod blob_store;
+pub mod render_service;
+pub mod grpc_decode;
+pub mod api_protocol;
+pub mod multipart;
+pub mod engagement;
+pub mod sync;
+pub mod live_streams;
+pub mod auth;
+pub mod agent_tls;
+pub mod ca_rotation;
+pub mod interception;
+pub mod tool_export;
+pub mod passive_checks;
+pub mod hook_scripts;
+pub mod charset;
+pub mod hex_view;
+pub mod mime_sniff;
+pub mod api_docs;
+pub mod coloring;
+pub mod jwt;
+pub mod sequencer;
+pub mod decoder;
+pub mod discovery;
+pub mod access_matrix;
+pub mod tamper_checks;
+pub mod logging;
 pub use database::Database;
+pub use logging::LoggingConfig;
 pub use session_manager::AgentRegistry;
 pub use recording_service::RecordingService;
 
@@ -46,11 +75,17 @@ pub struct OrchestratorConfig {
     pub health_check_interval: u64,
     pub agent_timeout: u64,
     pub logging: LoggingConfig,
-}
-
-#[derive(Debug, Clone, Default)]
-pub struct LoggingConfig {
-    pub level: String,
+    /// Observer mode: reject all GraphQL mutations and mutating REST calls
+    pub read_only: bool,
+    /// Require this bearer token (or an API key) on every API request; the API is open when unset
+    pub admin_token: Option<String>,
+    /// Browser origins allowed by CORS when authentication is on (defaults to the GUI's)
+    pub cors_origins: Vec<String>,
+    /// Serve the agent gRPC channel over mutual TLS; plaintext when unset
+    pub grpc_tls: Option<crate::agent_tls::GrpcTlsConfig>,
+    /// Encrypt the stored MITM CA key with this passphrase; an encrypted key
+    /// stays locked until unlocked over the API when unset
+    pub ca_key_passphrase: Option<String>,
 }
 
 pub struct Orchestrator {
@@ -58,6 +93,10 @@ pub struct Orchestrator {
 }
 
 use crate::graphql::{MutationRoot, ProxySchema, QueryRoot, SubscriptionRoot, RepeaterExecutionGql, IntruderAttackProgressGql, IntruderResultGql};
+use crate::graphql::flow_graphql::FlowReplayProgressGql;
+use crate::graphql::observer::{ObserverGuard, ReadOnlyMode};
+use crate::blob_store::{BlobStore, BlobError, BlobInfo, BlobGcReport};
+use crate::hex_view::{BodyPart, HexDump, HexRow, HexViewError};
 use crate::models::settings::{ScopeConfig, InterceptionConfig};
 use tokio::sync::RwLock;
 
@@ -66,17 +105,24 @@ struct AppState {
     schema: ProxySchema,
     agents: Arc<AgentRegistry>,
     db: Arc<Database>,
+    blobs: Arc<BlobStore>,
     start_time: std::time::Instant,
+    read_only: bool,
     #[allow(dead_code)] // Used via GraphQL context
     scope: Arc<RwLock<ScopeConfig>>,
     #[allow(dead_code)] // Used via GraphQL context
     interception: Arc<RwLock<InterceptionConfig>>,
+    auth: Arc<crate::auth::AuthService>,
+    ca: Arc<proxy_core::CertificateAuthority>,
+    grpc_port: u16,
 }
 
 /// OpenAPI documentation for Proxxy Orchestrator REST API
 #[derive(OpenApi)]
 #[openapi(
     paths(
+        livez_handler,
+        readyz_handler,
         health_detailed_handler,
         agents_handler,
         metrics_handler,
@@ -85,15 +131,27 @@ struct AppState {
         system_start_handler,
         system_stop_handler,
         system_restart_handler,
+        blob_upload_handler,
+        blob_download_handler,
+        blob_gc_handler,
+        body_hex_handler,
+        graphiql,
+        graphql_handler,
+        graphql_ws_handler,
+        grpc_docs_handler,
     ),
     components(
-        schemas(HealthStatus, AgentsResponse, AgentInfo, MetricsResponse, TrafficResponse, HttpTransaction)
+        schemas(HealthStatus, AgentsResponse, AgentInfo, MetricsResponse, TrafficResponse, HttpTransaction, BlobInfo, BlobGcReport, HexDump, HexRow, BodyPart)
     ),
     tags(
         (name = "health", description = "Health check endpoints"),
         (name = "agents", description = "Agent management endpoints"),
         (name = "metrics", description = "Traffic metrics endpoints"),
-        (name = "traffic", description = "HTTP traffic data endpoints")
+        (name = "traffic", description = "HTTP traffic data endpoints"),
+        (name = "blobs", description = "Project artifact storage endpoints"),
+        (name = "system", description = "Proxy system control endpoints"),
+        (name = "graphql", description = "GraphQL API; the schema is browsable in GraphiQL"),
+        (name = "docs", description = "API reference documents")
     ),
     info(
         title = "Proxxy Orchestrator API",
@@ -155,6 +213,24 @@ impl Orchestrator {
         
         let db = std::sync::Arc::new(crate::Database::new(projects_dir).await?);
 
+        // API keys are server-wide, kept beside the projects
+        let auth = Arc::new(
+            crate::auth::AuthService::open(
+                &std::path::Path::new(projects_dir).join("server.db"),
+                self.config.admin_token.clone(),
+            )
+            .await?,
+        );
+        if !auth.enabled() {
+            warn!("🔓 No admin token configured: the HTTP/GraphQL API is open to anyone who can reach it");
+        }
+
+        // Control-plane CA and agent enrollments, also kept beside the projects
+        let agent_tls = Arc::new(crate::agent_tls::AgentTlsService::open(std::path::Path::new(projects_dir)).await?);
+        if self.config.grpc_tls.is_none() {
+            warn!("🔓 Agent gRPC channel is plaintext: the MITM CA key is sent to agents unencrypted");
+        }
+
         // Cleanup orphaned flow executions from previous run
         if let Err(e) = db.cleanup_orphaned_executions().await {
             warn!("Failed to cleanup orphaned flow executions: {}", e);
@@ -162,10 +238,30 @@ impl Orchestrator {
 
         // Initialize CA (load from ./certs or generate)
         let ca_path = std::path::Path::new("certs");
-        let ca = std::sync::Arc::new(proxy_core::CertificateAuthority::new(ca_path)?);
+        let ca = std::sync::Arc::new(proxy_core::CertificateAuthority::open(
+            ca_path,
+            self.config.ca_key_passphrase.as_deref(),
+        )?);
+        if ca.is_locked() {
+            warn!("🔒 The MITM CA key is encrypted: agents keep their own CA until it is unlocked with the unlockCa mutation");
+        }
+        let ca_rotation = Arc::new(
+            crate::ca_rotation::CaRotationService::open(std::path::Path::new(projects_dir), ca.clone(), ca_path).await?,
+        );
+        // Archived CA keys are deleted once their grace period is over
+        let ca_purge = ca_rotation.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_secs(3600));
+            loop {
+                interval.tick().await;
+                if let Err(e) = ca_purge.purge_expired().await {
+                    warn!("Failed to purge archived CA keys: {}", e);
+                }
+            }
+        });
 
         let agent_registry = std::sync::Arc::new(AgentRegistry::new());
-        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel::<(String, crate::pb::TrafficEvent)>(100);
+        let (broadcast_tx, _broadcast_rx) = tokio::sync::broadcast::channel::<(String, crate::pb::TrafficEvent)>(1000);
         let (metrics_broadcast_tx, _metrics_broadcast_rx) = tokio::sync::broadcast::channel(100);
 
         // Initialize scope and interception state
@@ -206,9 +302,37 @@ impl Orchestrator {
             }
         });
 
+        // Project artifact storage, with periodic collection of unreferenced blobs
+        let blob_store = Arc::new(BlobStore::new(db.clone()));
+        let blob_gc_store = blob_store.clone();
+        let db_blob_gc = db.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(crate::blob_store::BLOB_GC_INTERVAL);
+            loop {
+                interval.tick().await;
+                if db_blob_gc.pool().await.is_some() {
+                    if let Err(e) = blob_gc_store.gc(crate::blob_store::BLOB_GC_GRACE).await {
+                        warn!("Blob garbage collection failed: {}", e);
+                    }
+                }
+            }
+        });
+
+        // Headless screenshots of stored HTML responses, kept in the blob store
+        let render_service = Arc::new(crate::render_service::RenderService::new(db.clone(), blob_store.clone()));
+
         // Initialize RecordingService (before proxy_service - needed for traffic-based navigation)
         let recording_service = Arc::new(crate::recording_service::RecordingService::new(ca.clone()));
 
+        // Requests held by agents' pause rules, awaiting forward/drop
+        let intercepts = Arc::new(crate::interception::InterceptQueue::new(agent_registry.clone()));
+
+        // Custom passive checks of the active project, run on stored responses
+        let passive_scanner = Arc::new(crate::passive_checks::PassiveScanner::new());
+
+        // Hook scripts of the active project, pushed to every agent
+        let hook_scripts = Arc::new(crate::hook_scripts::HookScriptStore::new(agent_registry.clone()));
+
         let proxy_service = crate::server::ProxyServiceImpl::new(
             agent_registry.clone(),
             broadcast_tx.clone(),
@@ -216,8 +340,16 @@ impl Orchestrator {
             db.clone(),
             ca.clone(),
             interception.clone(),
+            intercepts.clone(),
+            passive_scanner.clone(),
+            hook_scripts.clone(),
             recording_service.clone(),
         );
+        let proxy_service = if self.config.grpc_tls.is_some() {
+            proxy_service.with_agent_tls(agent_tls.clone())
+        } else {
+            proxy_service
+        };
 
         // Initialize RepeaterManager
         let repeater_manager = Arc::new(crate::repeater::RepeaterManager::new(
@@ -231,6 +363,12 @@ impl Orchestrator {
             warn!("Failed to initialize RepeaterManager: {}", e);
         }
 
+        // Token collection and randomness analysis
+        let sequencer = Arc::new(crate::sequencer::Sequencer::new(db.clone(), repeater_manager.clone()));
+        let discovery = Arc::new(crate::discovery::DiscoveryManager::new(db.clone(), repeater_manager.clone()));
+        let access_matrix = Arc::new(crate::access_matrix::AccessMatrix::new(db.clone(), repeater_manager.clone()));
+        let tamper_checks = Arc::new(crate::tamper_checks::TamperCheckManager::new(db.clone(), repeater_manager.clone()));
+
         // Initialize IntruderManager
         let intruder_manager = Arc::new(
             crate::intruder::IntruderManager::new(db.clone())
@@ -248,6 +386,9 @@ impl Orchestrator {
         let (intruder_progress_tx, _intruder_progress_rx) = tokio::sync::broadcast::channel::<IntruderAttackProgressGql>(100);
         let (intruder_results_tx, _intruder_results_rx) = tokio::sync::broadcast::channel::<IntruderResultGql>(1000);
 
+        // Create broadcast channel for per-step flow replay progress
+        let (flow_replay_progress_tx, _flow_replay_progress_rx) = tokio::sync::broadcast::channel::<FlowReplayProgressGql>(100);
+
         // GraphQL Schema
         let schema = async_graphql::Schema::build(QueryRoot, MutationRoot, SubscriptionRoot)
             .data(db.clone())
@@ -257,22 +398,42 @@ impl Orchestrator {
             .data(metrics_broadcast_tx.clone())
             .data(repeater_manager.clone())
             .data(repeater_broadcast_tx.clone())
+            .data(sequencer.clone())
+            .data(discovery.clone())
+            .data(access_matrix.clone())
+            .data(tamper_checks.clone())
             .data(intruder_manager.clone())
             .data(intruder_progress_tx.clone())
             .data(intruder_results_tx.clone())
+            .data(flow_replay_progress_tx.clone())
+            .data(blob_store.clone())
+            .data(render_service.clone())
+            .data(agent_tls.clone())
+            .data(ca_rotation.clone())
             .data(session_manager.clone())
             .data(recording_service.clone())
             .data(scope.clone())
             .data(interception.clone())
+            .data(intercepts.clone())
+            .data(passive_scanner.clone())
+            .data(hook_scripts.clone())
+            .data(auth.clone())
+            .data(ReadOnlyMode(self.config.read_only))
+            .extension(ObserverGuard)
             .finish();
 
         let state = AppState {
             schema,
             agents: agent_registry.clone(),
             db: db.clone(),
+            blobs: blob_store.clone(),
             start_time: std::time::Instant::now(),
+            read_only: self.config.read_only,
             scope,
             interception,
+            auth: auth.clone(),
+            ca: ca.clone(),
+            grpc_port: self.config.grpc_port,
         };
 
         // 1. Metrics & GraphQL Server & REST API
@@ -289,19 +450,56 @@ impl Orchestrator {
             .route(
                 "/system/restart",
                 axum::routing::post(system_restart_handler),
-            );
-
-        // Configure absolute permissive CORS for development
-        use tower_http::cors::{CorsLayer, Any};
-        let cors = CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods([Method::GET, Method::POST, Method::OPTIONS]) // OPTIONS mutlaka olmalı
-            .allow_headers(Any)
-            .expose_headers(Any);
+            )
+            .route("/blobs", axum::routing::post(blob_upload_handler))
+            .route("/blobs/gc", axum::routing::post(blob_gc_handler))
+            .route("/blobs/{hash}", get(blob_download_handler))
+            .route("/traffic/{request_id}/hex", get(body_hex_handler))
+            .route_layer(middleware::from_fn_with_state(state.clone(), observer_guard));
+
+        // Permissive CORS while the API is open (development); with authentication
+        // on, only the configured origins (the GUI by default) may call it
+        use tower_http::cors::{AllowOrigin, CorsLayer, Any};
+        let cors = if auth.enabled() {
+            let origins = if self.config.cors_origins.is_empty() {
+                DEFAULT_CORS_ORIGINS.iter().map(|o| o.to_string()).collect()
+            } else {
+                self.config.cors_origins.clone()
+            };
+            let origins: Vec<axum::http::HeaderValue> = origins
+                .iter()
+                .filter_map(|o| match o.parse() {
+                    Ok(origin) => Some(origin),
+                    Err(_) => {
+                        warn!("Ignoring invalid CORS origin: {}", o);
+                        None
+                    }
+                })
+                .collect();
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::list(origins))
+                .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+                .allow_headers([
+                    axum::http::header::AUTHORIZATION,
+                    axum::http::header::CONTENT_TYPE,
+                    axum::http::HeaderName::from_static(crate::models::access::PRINCIPAL_HEADER),
+                    axum::http::HeaderName::from_static("x-proxxy-client"),
+                ])
+        } else {
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods([Method::GET, Method::POST, Method::OPTIONS]) // OPTIONS mutlaka olmalı
+                .allow_headers(Any)
+                .expose_headers(Any)
+        };
+        let auth_layer = middleware::from_fn_with_state(state.clone(), require_auth);
 
         let app = axum::Router::new()
             // Top-level routes
             .route("/metrics", get(metrics_handler))
+            // Kubernetes probes
+            .route("/livez", get(livez_handler))
+            .route("/readyz", get(readyz_handler))
             // 2. "/graphql" rotasına .options() ekleyin:
             .route("/graphql", 
                 get(graphiql)
@@ -313,17 +511,27 @@ impl Orchestrator {
             .nest("/api", api_routes)
             // Swagger / Docs
             .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
+            .route("/api-docs/grpc.md", get(grpc_docs_handler))
+            // Root CA downloads for devices under test
+            .merge(proxy_core::admin::ca_routes(ca.clone()))
             .route(
                 "/",
                 get(|| async { axum::response::Redirect::permanent("/swagger-ui") }),
             )
             // Layers - Order is critical: Outer layers are applied LAST
+            .layer(auth_layer)
             .layer(middleware::from_fn(connection_logging)) // Add logging middleware
             .layer(Extension(state.schema.clone()))
             .layer(cors) // CORS katmanı en dışta olmalı (veya state'ten hemen önce)
             .with_state(state);
 
         info!("REST & GraphQL server listening on http://{}", metrics_addr);
+        if auth.enabled() {
+            info!("🔐 API authentication enabled (admin token + API keys)");
+        }
+        if self.config.read_only {
+            info!("👀 Read-only observer mode: mutations are disabled");
+        }
         let metrics_server = async move {
             let listener = TcpListener::bind(metrics_addr).await.unwrap();
             axum::serve(listener, app).await.unwrap();
@@ -333,10 +541,29 @@ impl Orchestrator {
         let grpc_addr = SocketAddr::from(([0, 0, 0, 0], self.config.grpc_port));
         info!("Orchestrator gRPC listening on {}", grpc_addr);
 
-        let grpc_server = tonic::transport::Server::builder()
+        let mut grpc_builder = tonic::transport::Server::builder();
+        if let Some(tls) = &self.config.grpc_tls {
+            grpc_builder = grpc_builder.tls_config(agent_tls.server_tls_config(&tls.server_names)?)?;
+            info!("🔐 Agent gRPC channel requires mutual TLS (control-plane CA: {}/pki/control-ca.pem)", projects_dir);
+        }
+        if !auth.enabled() {
+            info!("Project sync is refused until an admin token is set");
+        }
+
+        let grpc_server = grpc_builder
             .add_service(crate::pb::proxy_service_server::ProxyServiceServer::new(
                 proxy_service,
             ))
+            .add_service(tonic::service::interceptor::InterceptedService::new(
+                crate::pb::project_sync_server::ProjectSyncServer::new(
+                    crate::sync::ProjectSyncService::new(db.clone(), auth.clone(), self.config.read_only),
+                )
+                .max_decoding_message_size(crate::sync::MAX_SYNC_MESSAGE_BYTES),
+                crate::sync::SyncAuthInterceptor::new(auth.clone()),
+            ))
+            .add_service(crate::pb::live_streams_server::LiveStreamsServer::new(
+                crate::live_streams::LiveStreamService::new(auth.clone(), intruder_manager.clone(), broadcast_tx.clone()),
+            ))
             .serve(grpc_addr);
 
         // Run both
@@ -347,12 +574,26 @@ impl Orchestrator {
                     tracing::error!("gRPC server failed: {}", e);
                 }
             }
+            _ = tokio::signal::ctrl_c() => {
+                info!("Shutting down");
+            }
         }
 
+        // Wipe the CA key from memory
+        ca.lock();
         Ok(())
     }
 }
 
+/// GraphiQL explorer of the GraphQL API
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    tag = "graphql",
+    responses(
+        (status = 200, description = "GraphiQL page", content_type = "text/html")
+    )
+)]
 async fn graphiql() -> impl axum::response::IntoResponse {
     axum::response::Html(
         async_graphql::http::GraphiQLSource::build()
@@ -361,25 +602,111 @@ async fn graphiql() -> impl axum::response::IntoResponse {
     )
 }
 
+/// Execute a GraphQL query or mutation
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body(content = serde_json::Value, description = "GraphQL request: `query`, optional `variables` and `operationName`"),
+    responses(
+        (status = 200, description = "GraphQL response with `data` and/or `errors`", body = serde_json::Value)
+    )
+)]
 async fn graphql_handler(
     State(state): State<AppState>,
-    axum::Json(req): axum::Json<async_graphql::Request>,
+    caller: Option<Extension<crate::auth::Caller>>,
+    headers: axum::http::HeaderMap,
+    axum::Json(mut req): axum::Json<async_graphql::Request>,
 ) -> axum::Json<async_graphql::Response> {
+    let caller = caller.map(|Extension(caller)| caller);
+    if let Some(principal) = request_principal(&headers, caller.as_ref()) {
+        req = req.data(principal);
+    }
+    if let Some(caller) = caller {
+        req = req.data(caller);
+    }
     axum::Json(state.schema.execute(req).await)
 }
 
+/// Identity used for project access checks. An API key acts as its own
+/// principal; otherwise the principal header is trusted (no auth, or the
+/// admin token held by an authenticating proxy).
+fn request_principal(
+    headers: &axum::http::HeaderMap,
+    caller: Option<&crate::auth::Caller>,
+) -> Option<crate::models::access::Principal> {
+    if let Some(crate::auth::Caller::ApiKey { principal, .. }) = caller {
+        return Some(crate::models::access::Principal(principal.clone()));
+    }
+    headers
+        .get(crate::models::access::PRINCIPAL_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(|v| crate::models::access::Principal(v.to_string()))
+}
+
+/// GraphQL subscriptions over WebSocket (graphql-ws or graphql-transport-ws)
+#[utoipa::path(
+    get,
+    path = "/graphql/ws",
+    tag = "graphql",
+    responses(
+        (status = 101, description = "Switched to the WebSocket protocol")
+    )
+)]
 async fn graphql_ws_handler(
     State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
     protocol: GraphQLProtocol,
     upgrade: WebSocketUpgrade,
 ) -> impl axum::response::IntoResponse {
+    // Browsers cannot set headers on WebSockets, so the token may also come
+    // in the connection_init payload ("authorization" or "token")
+    let auth = state.auth.clone();
+    let header_caller = match crate::auth::bearer_token(&headers) {
+        Some(token) => auth.authenticate(token).await.ok(),
+        None => None,
+    };
     upgrade
         .protocols(async_graphql::http::ALL_WEBSOCKET_PROTOCOLS)
         .on_upgrade(move |stream| {
-            GraphQLWebSocket::new(stream, state.schema, protocol).serve()
+            GraphQLWebSocket::new(stream, state.schema, protocol)
+                .on_connection_init(move |payload| ws_connection_data(auth, header_caller, payload))
+                .serve()
         })
 }
 
+async fn ws_connection_data(
+    auth: Arc<crate::auth::AuthService>,
+    header_caller: Option<crate::auth::Caller>,
+    payload: serde_json::Value,
+) -> async_graphql::Result<async_graphql::Data> {
+    let mut data = async_graphql::Data::default();
+    if !auth.enabled() {
+        return Ok(data);
+    }
+
+    let caller = match header_caller {
+        Some(caller) => caller,
+        None => {
+            let token = ["authorization", "Authorization", "token"]
+                .iter()
+                .find_map(|key| payload.get(*key).and_then(|v| v.as_str()))
+                .and_then(crate::auth::strip_bearer)
+                .ok_or_else(|| async_graphql::Error::new(crate::auth::AuthError::Missing.to_string()))?;
+            auth.authenticate(token)
+                .await
+                .map_err(|e| async_graphql::Error::new(e.to_string()))?
+        }
+    };
+    if let crate::auth::Caller::ApiKey { principal, .. } = &caller {
+        data.insert(crate::models::access::Principal(principal.clone()));
+    }
+    data.insert(caller);
+    Ok(data)
+}
+
 #[derive(Serialize, utoipa::ToSchema)]
 struct TrafficResponse {
     transactions: Vec<HttpTransaction>,
@@ -396,10 +723,53 @@ struct HttpTransaction {
     timestamp: i64,
 }
 
+/// Liveness probe: fails only when the orchestrator should be restarted
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    params(("verbose" = Option<bool>, Query, description = "List the checks even when they pass")),
+    responses(
+        (status = 200, description = "Alive", content_type = "text/plain"),
+        (status = 503, description = "A check failed; one `[-]name failed: reason` line per failure", content_type = "text/plain")
+    )
+)]
+async fn livez_handler(RawQuery(query): RawQuery) -> Response {
+    proxy_core::probes::probe_response("livez", &[ProbeCheck::new("ping", Ok(()))], query.as_deref())
+}
+
+/// Readiness probe: the databases answer, the gRPC server accepts agents and
+/// the MITM CA is loaded
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    params(("verbose" = Option<bool>, Query, description = "List the checks even when they pass")),
+    responses(
+        (status = 200, description = "Ready", content_type = "text/plain"),
+        (status = 503, description = "A check failed; one `[-]name failed: reason` line per failure", content_type = "text/plain")
+    )
+)]
+async fn readyz_handler(State(state): State<AppState>, RawQuery(query): RawQuery) -> Response {
+    let database = async {
+        state.auth.ping().await.map_err(|e| format!("server database: {}", e))?;
+        state.db.ping().await.map_err(|e| format!("project database: {}", e))
+    };
+    let database = tokio::time::timeout(PROBE_TIMEOUT, database)
+        .await
+        .unwrap_or_else(|_| Err(format!("no answer within {:?}", PROBE_TIMEOUT)));
+    let checks = [
+        ProbeCheck::new("database", database),
+        ProbeCheck::new("grpc", proxy_core::probes::check_listener(state.grpc_port).await),
+        ProbeCheck::new("ca", proxy_core::probes::check_ca(&state.ca)),
+    ];
+    proxy_core::probes::probe_response("readyz", &checks, query.as_deref())
+}
+
 /// Get detailed health status
 #[utoipa::path(
     get,
-    path = "/health/detailed",
+    path = "/api/health/detailed",
     tag = "health",
     responses(
         (status = 200, description = "Health status retrieved successfully", body = HealthStatus)
@@ -418,7 +788,7 @@ async fn health_detailed_handler(State(state): State<AppState>) -> Json<HealthSt
 /// List all registered agents
 #[utoipa::path(
     get,
-    path = "/agents",
+    path = "/api/agents",
     tag = "agents",
     responses(
         (status = 200, description = "Agents list retrieved successfully", body = AgentsResponse)
@@ -459,7 +829,7 @@ async fn agents_handler(State(state): State<AppState>) -> Json<AgentsResponse> {
 /// Get recent HTTP traffic transactions
 #[utoipa::path(
     get,
-    path = "/traffic",
+    path = "/api/traffic/recent",
     tag = "traffic",
     responses(
         (status = 200, description = "Traffic data retrieved successfully", body = TrafficResponse)
@@ -585,7 +955,7 @@ async fn metrics_handler(State(state): State<AppState>) -> Json<MetricsResponse>
 /// Get system health status
 #[utoipa::path(
     get,
-    path = "/system/health",
+    path = "/api/system/health",
     tag = "health",
     responses(
         (status = 200, description = "System health status retrieved successfully")
@@ -609,7 +979,7 @@ async fn system_health_handler(State(state): State<AppState>) -> Json<serde_json
 /// Start the proxy system
 #[utoipa::path(
     post,
-    path = "/system/start",
+    path = "/api/system/start",
     tag = "system",
     responses(
         (status = 200, description = "System started successfully")
@@ -626,7 +996,7 @@ async fn system_start_handler() -> Json<serde_json::Value> {
 /// Stop the proxy system
 #[utoipa::path(
     post,
-    path = "/system/stop",
+    path = "/api/system/stop",
     tag = "system",
     responses(
         (status = 200, description = "System stopped successfully")
@@ -643,7 +1013,7 @@ async fn system_stop_handler() -> Json<serde_json::Value> {
 /// Restart the proxy system
 #[utoipa::path(
     post,
-    path = "/system/restart",
+    path = "/api/system/restart",
     tag = "system",
     responses(
         (status = 200, description = "System restart initiated")
@@ -657,6 +1027,163 @@ async fn system_restart_handler() -> Json<serde_json::Value> {
     }))
 }
 
+fn blob_error_response(e: BlobError) -> (axum::http::StatusCode, String) {
+    use axum::http::StatusCode;
+    let status = match &e {
+        BlobError::NoProject => StatusCode::CONFLICT,
+        BlobError::InvalidHash(_) => StatusCode::BAD_REQUEST,
+        BlobError::NotFound(_) => StatusCode::NOT_FOUND,
+        BlobError::Io(_) | BlobError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+/// Upload a blob; the request body is stored as-is
+#[utoipa::path(
+    post,
+    path = "/api/blobs",
+    tag = "blobs",
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Blob stored", body = BlobInfo),
+        (status = 409, description = "No active project")
+    )
+)]
+async fn blob_upload_handler(
+    State(state): State<AppState>,
+    headers: axum::http::HeaderMap,
+    body: axum::body::Body,
+) -> Result<Json<BlobInfo>, (axum::http::StatusCode, String)> {
+    let content_type = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+
+    let info = state
+        .blobs
+        .put_stream(body.into_data_stream(), content_type)
+        .await
+        .map_err(blob_error_response)?;
+
+    info!("📦 Blob stored: {} ({} bytes)", info.hash, info.size);
+    Ok(Json(info))
+}
+
+/// Download a blob by its SHA-256 hash
+#[utoipa::path(
+    get,
+    path = "/api/blobs/{hash}",
+    tag = "blobs",
+    params(("hash" = String, Path, description = "SHA-256 of the blob content")),
+    responses(
+        (status = 200, description = "Blob content", content_type = "application/octet-stream"),
+        (status = 404, description = "Blob not found")
+    )
+)]
+async fn blob_download_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(hash): axum::extract::Path<String>,
+) -> Result<Response, (axum::http::StatusCode, String)> {
+    use axum::http::header;
+
+    let (info, file) = state.blobs.open(&hash).await.map_err(blob_error_response)?;
+    let body = axum::body::Body::from_stream(tokio_util::io::ReaderStream::new(file));
+
+    Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            info.content_type.as_deref().unwrap_or("application/octet-stream"),
+        )
+        .header(header::CONTENT_LENGTH, info.size)
+        // Content-addressed, so the content behind a hash never changes
+        .header(header::CACHE_CONTROL, "private, max-age=31536000, immutable")
+        .header(header::ETAG, format!("\"{}\"", info.hash))
+        .body(body)
+        .map_err(|e| (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Remove blobs that are no longer referenced
+#[utoipa::path(
+    post,
+    path = "/api/blobs/gc",
+    tag = "blobs",
+    responses(
+        (status = 200, description = "Garbage collection finished", body = BlobGcReport)
+    )
+)]
+async fn blob_gc_handler(
+    State(state): State<AppState>,
+) -> Result<Json<BlobGcReport>, (axum::http::StatusCode, String)> {
+    state
+        .blobs
+        .gc(crate::blob_store::BLOB_GC_GRACE)
+        .await
+        .map(Json)
+        .map_err(blob_error_response)
+}
+
+/// Query of a hex view page
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+struct HexQuery {
+    /// `request` or `response` (default)
+    part: Option<BodyPart>,
+    /// First byte of the page
+    offset: Option<u64>,
+    /// Bytes in the page, 4096 by default and at most 65536
+    length: Option<u64>,
+}
+
+/// Hex and ASCII dump of a range of a stored transaction body
+#[utoipa::path(
+    get,
+    path = "/api/traffic/{request_id}/hex",
+    tag = "traffic",
+    params(
+        ("request_id" = String, Path, description = "Transaction ID"),
+        HexQuery
+    ),
+    responses(
+        (status = 200, description = "Dump of the requested range", body = HexDump),
+        (status = 400, description = "Range outside the body or too large"),
+        (status = 404, description = "Transaction not found")
+    )
+)]
+async fn body_hex_handler(
+    State(state): State<AppState>,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+    axum::extract::Query(query): axum::extract::Query<HexQuery>,
+) -> Result<Json<HexDump>, (axum::http::StatusCode, String)> {
+    use axum::http::StatusCode;
+
+    let part = query.part.unwrap_or(BodyPart::Response);
+    hex_view::hex_dump(&state.db, &request_id, part, query.offset.unwrap_or(0), query.length)
+        .await
+        .map(Json)
+        .map_err(|e| {
+            let status = match &e {
+                HexViewError::NotFound(_) => StatusCode::NOT_FOUND,
+                HexViewError::InvalidRange(_) => StatusCode::BAD_REQUEST,
+                HexViewError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, e.to_string())
+        })
+}
+
+/// Markdown reference of the agent and project sync gRPC services
+#[utoipa::path(
+    get,
+    path = "/api-docs/grpc.md",
+    tag = "docs",
+    responses(
+        (status = 200, description = "Services, messages and enums of proxy.proto", content_type = "text/markdown")
+    )
+)]
+async fn grpc_docs_handler() -> impl axum::response::IntoResponse {
+    (
+        [(axum::http::header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+        crate::api_docs::grpc_markdown(),
+    )
+}
+
 pub async fn run_metrics_server(port: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let app = Router::new().route(
         "/metrics",
@@ -671,6 +1198,97 @@ pub async fn run_metrics_server(port: u16) -> Result<(), Box<dyn std::error::Err
     Ok(())
 }
 
+/// How long `/readyz` waits for the databases
+const PROBE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Browser origins of the GUI (Tauri app and its dev server)
+const DEFAULT_CORS_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+    "http://localhost:1420",
+];
+
+/// Require a valid bearer token when authentication is on. CORS preflights,
+/// the probes, the GraphiQL page and the API docs stay public; GraphQL WebSockets
+/// authenticate in their connection_init message.
+async fn require_auth(State(state): State<AppState>, mut req: Request, next: Next) -> Response {
+    use axum::response::IntoResponse;
+
+    if !state.auth.enabled() {
+        return next.run(req).await;
+    }
+    let path = req.uri().path();
+    let public = *req.method() == Method::OPTIONS
+        || (*req.method() == Method::GET
+            && matches!(
+                path,
+                "/" | "/livez" | "/readyz" | "/graphql" | "/api-docs/openapi.json" | "/api-docs/grpc.md" | "/ca.crt" | "/ca.pem" | "/ca.mobileconfig"
+            ))
+        || path.starts_with("/swagger-ui")
+        || path == "/graphql/ws";
+    if public {
+        return next.run(req).await;
+    }
+
+    let unauthorized = |message: String| {
+        (
+            axum::http::StatusCode::UNAUTHORIZED,
+            [(axum::http::header::WWW_AUTHENTICATE, "Bearer")],
+            message,
+        )
+            .into_response()
+    };
+    let Some(token) = crate::auth::bearer_token(req.headers()) else {
+        return unauthorized(crate::auth::AuthError::Missing.to_string());
+    };
+    match state.auth.authenticate(token).await {
+        Ok(caller) => {
+            req.extensions_mut().insert(caller);
+            next.run(req).await
+        }
+        Err(crate::auth::AuthError::Database(e)) => {
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+        Err(e) => unauthorized(e.to_string()),
+    }
+}
+
+/// Reject mutating REST calls from read-only observers: on shared projects,
+/// callers without the Editor role
+async fn observer_guard(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    use axum::response::IntoResponse;
+
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(req).await;
+    }
+
+    if state.read_only {
+        return (
+            axum::http::StatusCode::FORBIDDEN,
+            "The server is in read-only observer mode",
+        )
+            .into_response();
+    }
+
+    let caller = req.extensions().get::<crate::auth::Caller>();
+    if caller == Some(&crate::auth::Caller::Admin) {
+        return next.run(req).await;
+    }
+    let principal = request_principal(req.headers(), caller);
+    let members = match state.db.list_project_members().await {
+        Ok(members) => members,
+        Err(e) => {
+            return (axum::http::StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+        }
+    };
+    if let Err(denied) = crate::models::access::check_mutation(&members, principal.as_ref()) {
+        return (axum::http::StatusCode::FORBIDDEN, denied.to_string()).into_response();
+    }
+
+    next.run(req).await
+}
+
 async fn connection_logging(
     req: Request,
     next: Next,
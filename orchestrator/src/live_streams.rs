@@ -0,0 +1,265 @@
+//! Live Streams
+//!
+//! Server-streaming gRPC feeds of intruder results and captured traffic for
+//! clients other than the GUI. GraphQL subscriptions share one WebSocket per
+//! client; here every call is its own HTTP/2 stream, so a CLI piping a large
+//! attack into a file is paced by HTTP/2 flow control. Each call is served
+//! by a task that copies the broadcast into a bounded channel: while the
+//! client is not reading, the channel fills, the task waits and the
+//! broadcast moves on without it. What it missed is counted on the next
+//! message.
+
+use crate::auth::{strip_bearer, AuthError, AuthService};
+use crate::intruder::IntruderManager;
+use crate::pb::live_streams_server::LiveStreams;
+use crate::pb::{
+    attack_stream_event, AgentTrafficEvent, AttackCompleted, AttackResult, AttackStreamEvent, TrafficEvent,
+    WatchAttackResultsRequest, WatchTrafficRequest,
+};
+use crate::result_streaming::{ResultSource, ResultUpdate, ResultUpdateType};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use tracing::debug;
+
+/// Messages queued per call before its task waits for the client
+const STREAM_BUFFER: usize = 256;
+
+type EventStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send>>;
+
+/// Server side of the live feeds, served next to the agent service
+pub struct LiveStreamService {
+    auth: Arc<AuthService>,
+    intruder: Arc<IntruderManager>,
+    traffic: broadcast::Sender<(String, TrafficEvent)>,
+}
+
+impl LiveStreamService {
+    pub fn new(
+        auth: Arc<AuthService>,
+        intruder: Arc<IntruderManager>,
+        traffic: broadcast::Sender<(String, TrafficEvent)>,
+    ) -> Self {
+        Self { auth, intruder, traffic }
+    }
+
+    /// Same tokens as the HTTP API, from the `authorization` metadata
+    async fn authorize<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        if !self.auth.enabled() {
+            return Ok(());
+        }
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(strip_bearer)
+            .ok_or_else(|| Status::unauthenticated(AuthError::Missing.to_string()))?;
+        match self.auth.authenticate(token).await {
+            Ok(_) => Ok(()),
+            Err(AuthError::Database(e)) => Err(Status::internal(e.to_string())),
+            Err(e) => Err(Status::unauthenticated(e.to_string())),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl LiveStreams for LiveStreamService {
+    type WatchAttackResultsStream = EventStream<AttackStreamEvent>;
+    type WatchTrafficStream = EventStream<AgentTrafficEvent>;
+
+    async fn watch_attack_results(
+        &self,
+        request: Request<WatchAttackResultsRequest>,
+    ) -> Result<Response<Self::WatchAttackResultsStream>, Status> {
+        self.authorize(&request).await?;
+        let req = request.into_inner();
+        let updates = self.intruder.subscribe_to_results();
+        debug!("📡 Attack results watched ({})", display_filter(&req.attack_id));
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(forward(updates, tx, move |update, skipped| {
+            let mut event = attack_event(update, &req)?;
+            event.skipped = skipped;
+            // Watching one attack ends with it
+            let last = !req.attack_id.is_empty()
+                && matches!(event.event, Some(attack_stream_event::Event::Completed(_)));
+            Some((event, last))
+        }));
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+
+    async fn watch_traffic(
+        &self,
+        request: Request<WatchTrafficRequest>,
+    ) -> Result<Response<Self::WatchTrafficStream>, Status> {
+        self.authorize(&request).await?;
+        let req = request.into_inner();
+        let events = self.traffic.subscribe();
+        debug!("📡 Traffic watched ({})", display_filter(&req.agent_id));
+
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        tokio::spawn(forward(events, tx, move |(agent_id, event), skipped| {
+            if !req.agent_id.is_empty() && agent_id != req.agent_id {
+                return None;
+            }
+            Some((AgentTrafficEvent { agent_id, event: Some(event), skipped }, false))
+        }));
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+    }
+}
+
+fn display_filter(id: &str) -> &str {
+    if id.is_empty() {
+        "all"
+    } else {
+        id
+    }
+}
+
+/// Copy `updates` into `tx` until the client goes away, the broadcast
+/// closes or `convert` marks a message as the last. `convert` gets the
+/// number of updates missed since the previous message and returns `None`
+/// for updates the client did not ask for.
+async fn forward<U, T, F>(mut updates: broadcast::Receiver<U>, tx: mpsc::Sender<Result<T, Status>>, mut convert: F)
+where
+    U: Clone,
+    F: FnMut(U, u64) -> Option<(T, bool)>,
+{
+    let mut skipped = 0;
+    loop {
+        let update = tokio::select! {
+            _ = tx.closed() => break,
+            update = updates.recv() => update,
+        };
+        let update = match update {
+            Ok(update) => update,
+            Err(RecvError::Lagged(missed)) => {
+                skipped += missed;
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let Some((message, last)) = convert(update, skipped) else {
+            continue;
+        };
+        skipped = 0;
+        if tx.send(Ok(message)).await.is_err() || last {
+            break;
+        }
+    }
+}
+
+/// Wire message for an intruder update the request asks for
+fn attack_event(update: ResultUpdate, request: &WatchAttackResultsRequest) -> Option<AttackStreamEvent> {
+    let ResultSource::Intruder { attack_id } = update.source else {
+        return None;
+    };
+    if !request.attack_id.is_empty() && attack_id != request.attack_id {
+        return None;
+    }
+    let event = match update.update_type {
+        ResultUpdateType::NewResult(result) => {
+            if request.highlighted_only && !result.is_highlighted {
+                return None;
+            }
+            attack_stream_event::Event::Result(AttackResult {
+                result_id: result.result_id,
+                agent_id: result.agent_id,
+                status_code: result.status_code.unwrap_or_default(),
+                response_length: result.response_length.unwrap_or_default() as u64,
+                duration_ms: result.duration_ms.unwrap_or_default(),
+                executed_at: result.executed_at.timestamp(),
+                payload_values: result.payload_values.unwrap_or_default(),
+                highlighted: result.is_highlighted,
+                highlight_reasons: result.highlight_reasons,
+            })
+        }
+        ResultUpdateType::AttackCompleted(summary) => {
+            let stats = summary.final_statistics;
+            attack_stream_event::Event::Completed(AttackCompleted {
+                total_requests: stats.total_requests as u64,
+                completed_requests: stats.completed_requests as u64,
+                successful_requests: stats.successful_requests as u64,
+                failed_requests: stats.failed_requests as u64,
+                highlighted_results: stats.highlighted_results as u64,
+                duration_ms: summary.total_duration.as_millis() as u64,
+            })
+        }
+        // Highlighted results repeat a NewResult; progress is the GUI's
+        _ => return None,
+    };
+    Some(AttackStreamEvent {
+        attack_id,
+        skipped: 0,
+        event: Some(event),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result_streaming::StreamedResult;
+
+    fn result_update(attack_id: &str, highlighted: bool) -> ResultUpdate {
+        ResultUpdate {
+            update_id: "u1".to_string(),
+            timestamp: chrono::Utc::now(),
+            update_type: ResultUpdateType::NewResult(StreamedResult {
+                result_id: "r1".to_string(),
+                agent_id: "agent-1".to_string(),
+                status_code: Some(500),
+                response_length: Some(1234),
+                duration_ms: Some(87),
+                executed_at: chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                payload_values: Some(vec!["' OR 1=1--".to_string()]),
+                is_highlighted: highlighted,
+                highlight_reasons: if highlighted { vec!["status 500".to_string()] } else { Vec::new() },
+            }),
+            source: ResultSource::Intruder { attack_id: attack_id.to_string() },
+        }
+    }
+
+    #[test]
+    fn test_attack_event_filters() {
+        let watch = |attack_id: &str, highlighted_only| WatchAttackResultsRequest {
+            attack_id: attack_id.to_string(),
+            highlighted_only,
+        };
+
+        let event = attack_event(result_update("a1", true), &watch("a1", true)).unwrap();
+        assert_eq!(event.attack_id, "a1");
+        let Some(attack_stream_event::Event::Result(result)) = event.event else {
+            panic!("expected a result");
+        };
+        assert_eq!((result.status_code, result.response_length, result.duration_ms), (500, 1234, 87));
+        assert_eq!(result.executed_at, 1_700_000_000);
+        assert_eq!(result.highlight_reasons, vec!["status 500".to_string()]);
+
+        assert!(attack_event(result_update("a2", true), &watch("a1", false)).is_none());
+        assert!(attack_event(result_update("a1", false), &watch("a1", true)).is_none());
+        assert!(attack_event(result_update("a2", false), &watch("", false)).is_some());
+
+        let mut repeater = result_update("a1", true);
+        repeater.source = ResultSource::Repeater { tab_id: "a1".to_string() };
+        assert!(attack_event(repeater, &watch("", false)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_forward_counts_missed_updates() {
+        let (updates, rx) = broadcast::channel(2);
+        let (tx, mut out) = mpsc::channel(8);
+        for n in 0..5u32 {
+            updates.send(n).unwrap();
+        }
+        drop(updates);
+
+        forward(rx, tx, |n, skipped| (n % 2 == 1).then_some(((n, skipped), n == 3))).await;
+        // 0..=2 were overwritten before the first receive; 3 is marked last
+        assert_eq!(out.recv().await.unwrap().unwrap(), (3, 3));
+        assert!(out.recv().await.is_none());
+    }
+}
@@ -1,11 +1,42 @@
+//! Logging subsystem of the orchestrator
+//!
+//! Events go to stdout and, when `log_file` is set, to a file that is rotated
+//! once it reaches `max_file_size_mb`, as plain text or one JSON object per
+//! line. The level filter is installed behind a reload handle so the default
+//! and per-module levels can be changed at runtime with [`set_log_levels`].
+
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tracing::Subscriber;
 use tracing_subscriber::{
-    fmt,
+    fmt::{self, format::FmtSpan, MakeWriter},
     layer::SubscriberExt,
+    registry::LookupSpan,
+    reload,
     util::SubscriberInitExt,
-    EnvFilter,
+    EnvFilter, Layer, Registry,
 };
-use crate::OrchestratorError;
+
+/// Errors of the logging subsystem
+#[derive(Debug, thiserror::Error)]
+pub enum LoggingError {
+    #[error("Invalid log level '{0}', expected one of trace, debug, info, warn, error")]
+    InvalidLevel(String),
+    #[error("Invalid log module '{0}'")]
+    InvalidModule(String),
+    #[error("Invalid log directive: {0}")]
+    InvalidDirective(String),
+    #[error("Failed to open log file: {0}")]
+    File(#[from] io::Error),
+    #[error("Logging was not initialized by the orchestrator")]
+    NotInitialized,
+    #[error("Failed to apply log levels: {0}")]
+    Reload(String),
+}
 
 /// Logging configuration for the orchestrator
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -50,6 +81,8 @@ impl Default for LoggingConfig {
         
         // Set default levels for common modules
         module_levels.insert("orchestrator".to_string(), "info".to_string());
+        module_levels.insert("proxy_core".to_string(), "info".to_string());
+        module_levels.insert("flow_engine".to_string(), "info".to_string());
         module_levels.insert("sqlx".to_string(), "warn".to_string());
         module_levels.insert("tonic".to_string(), "info".to_string());
         module_levels.insert("hyper".to_string(), "warn".to_string());
@@ -71,34 +104,118 @@ impl Default for LoggingConfig {
     }
 }
 
-/// Initialize logging based on the provided configuration
-pub fn init_logging(config: &LoggingConfig) -> Result<(), OrchestratorError> {
-    // Build the environment filter
-    let mut filter = EnvFilter::new(&config.level);
-    
-    // Add module-specific filters
-    for (module, level) in &config.module_levels {
-        let directive = format!("{}={}", module, level);
-        filter = filter.add_directive(directive.parse()
-            .map_err(|e| OrchestratorError::Logging(format!("Invalid log directive: {}", e)))?);
+/// Default level and per-module overrides currently in effect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogLevels {
+    pub level: String,
+    /// Module path (`tracing` target prefix) to level
+    pub modules: BTreeMap<String, String>,
+}
+
+impl LogLevels {
+    /// Levels of `config`, validated
+    pub fn from_config(config: &LoggingConfig) -> Result<Self, LoggingError> {
+        let mut levels = Self {
+            level: "info".to_string(),
+            modules: BTreeMap::new(),
+        };
+        let modules: Vec<(String, Option<String>)> = config
+            .module_levels
+            .iter()
+            .map(|(module, level)| (module.clone(), Some(level.clone())))
+            .collect();
+        levels.apply(Some(&config.level), &modules)?;
+        Ok(levels)
     }
-    
+
+    /// Change the default level and set (`Some`) or remove (`None`) module
+    /// overrides; nothing changes when any of them is invalid
+    pub fn apply(&mut self, level: Option<&str>, modules: &[(String, Option<String>)]) -> Result<(), LoggingError> {
+        let mut next = self.clone();
+        if let Some(level) = level {
+            next.level = checked_level(level)?;
+        }
+        for (module, level) in modules {
+            let module = module.trim();
+            if module.is_empty() || module.contains(|c: char| c == '=' || c == ',' || c.is_whitespace()) {
+                return Err(LoggingError::InvalidModule(module.to_string()));
+            }
+            match level {
+                Some(level) => {
+                    next.modules.insert(module.to_string(), checked_level(level)?);
+                }
+                None => {
+                    next.modules.remove(module);
+                }
+            }
+        }
+        *self = next;
+        Ok(())
+    }
+
+    /// `EnvFilter` directives, e.g. `info,sqlx=warn`
+    pub fn directives(&self) -> String {
+        std::iter::once(self.level.clone())
+            .chain(self.modules.iter().map(|(module, level)| format!("{}={}", module, level)))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn filter(&self) -> Result<EnvFilter, LoggingError> {
+        EnvFilter::try_new(self.directives()).map_err(|e| LoggingError::InvalidDirective(e.to_string()))
+    }
+}
+
+fn checked_level(level: &str) -> Result<String, LoggingError> {
+    if levels::is_valid_level(level.trim()) {
+        Ok(level.trim().to_lowercase())
+    } else {
+        Err(LoggingError::InvalidLevel(level.to_string()))
+    }
+}
+
+/// Reload handle of the installed filter and the levels it was built from
+struct LevelControl {
+    handle: reload::Handle<EnvFilter, Registry>,
+    levels: Mutex<LogLevels>,
+}
+
+static LEVEL_CONTROL: OnceLock<LevelControl> = OnceLock::new();
+
+/// Keeps the file writer flushing; dropping it loses buffered events
+#[must_use = "logs written to the file are lost once the guard is dropped"]
+pub struct LoggingGuard {
+    _file_worker: Option<tracing_appender::non_blocking::WorkerGuard>,
+}
+
+/// Initialize logging based on the provided configuration
+pub fn init_logging(config: &LoggingConfig) -> Result<LoggingGuard, LoggingError> {
+    let levels = LogLevels::from_config(config)?;
+    let (filter, handle) = reload::Layer::new(levels.filter()?);
+
+    let (file_writer, file_worker) = match &config.log_file {
+        Some(path) => {
+            let file = RotatingFile::open(path, config.max_file_size_mb.saturating_mul(1024 * 1024), config.max_files)?;
+            let (writer, worker) = tracing_appender::non_blocking(file);
+            (Some(writer), Some(worker))
+        }
+        None => (None, None),
+    };
+
     // Try to initialize logging, ignore if already initialized
     let result = tracing_subscriber::registry()
         .with(filter)
-        .with(
-            fmt::layer()
-                .with_target(true)
-                .with_thread_names(config.include_thread_names)
-                .with_file(config.include_file_info)
-                .with_line_number(config.include_file_info)
-                .with_ansi(config.enable_colors)
-        )
+        .with(format_layer(config, io::stdout, config.enable_colors))
+        .with(file_writer.map(|writer| format_layer(config, writer, false)))
         .try_init();
     
     match result {
         Ok(_) => {
-            tracing::info!("Logging initialized with config level: {}", config.level);
+            let _ = LEVEL_CONTROL.set(LevelControl {
+                handle,
+                levels: Mutex::new(levels.clone()),
+            });
+            tracing::info!(directives = %levels.directives(), json = config.json_format, log_file = ?config.log_file, "Logging initialized");
         }
         Err(_) => {
             // Logging already initialized, that's fine
@@ -106,37 +223,122 @@ pub fn init_logging(config: &LoggingConfig) -> Result<(), OrchestratorError> {
         }
     }
     
-    Ok(())
+    Ok(LoggingGuard { _file_worker: file_worker })
 }
 
-/// Create a file appender for log rotation
-fn create_file_appender(
-    log_file: &str,
-    _config: &LoggingConfig,
-) -> Result<tracing_appender::rolling::RollingFileAppender, OrchestratorError> {
-    use tracing_appender::rolling::{RollingFileAppender, Rotation};
-    use std::path::Path;
-    
-    let log_path = Path::new(log_file);
-    let directory = log_path.parent()
-        .ok_or_else(|| OrchestratorError::Logging("Invalid log file path".to_string()))?;
-    let filename = log_path.file_name()
-        .ok_or_else(|| OrchestratorError::Logging("Invalid log file name".to_string()))?
-        .to_string_lossy();
-    
-    // Create directory if it doesn't exist
-    std::fs::create_dir_all(directory)
-        .map_err(|e| OrchestratorError::Logging(format!("Failed to create log directory: {}", e)))?;
-    
-    // For simplicity, we'll use daily rotation
-    // In a production system, you might want size-based rotation
-    let file_appender = RollingFileAppender::new(
-        Rotation::DAILY,
-        directory,
-        filename.as_ref(),
-    );
-    
-    Ok(file_appender)
+/// Levels in effect; `None` when [`init_logging`] did not install the subscriber
+pub fn current_log_levels() -> Option<LogLevels> {
+    LEVEL_CONTROL
+        .get()
+        .map(|control| control.levels.lock().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// Change the levels of the running subscriber, see [`LogLevels::apply`]
+pub fn set_log_levels(level: Option<&str>, modules: &[(String, Option<String>)]) -> Result<LogLevels, LoggingError> {
+    let control = LEVEL_CONTROL.get().ok_or(LoggingError::NotInitialized)?;
+    let mut current = control.levels.lock().unwrap_or_else(|e| e.into_inner());
+    let mut next = current.clone();
+    next.apply(level, modules)?;
+    control
+        .handle
+        .reload(next.filter()?)
+        .map_err(|e| LoggingError::Reload(e.to_string()))?;
+    *current = next.clone();
+    Ok(next)
+}
+
+/// Formatting layer writing to `writer`, as JSON or plain text
+fn format_layer<S, W>(config: &LoggingConfig, writer: W, ansi: bool) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let span_events = if config.enable_span_events {
+        FmtSpan::NEW | FmtSpan::CLOSE
+    } else {
+        FmtSpan::NONE
+    };
+    let layer = fmt::layer()
+        .with_writer(writer)
+        .with_target(true)
+        .with_thread_names(config.include_thread_names)
+        .with_file(config.include_file_info)
+        .with_line_number(config.include_file_info)
+        .with_span_events(span_events);
+
+    match (config.json_format, config.include_timestamp) {
+        (true, true) => layer.json().boxed(),
+        (true, false) => layer.json().without_time().boxed(),
+        (false, true) => layer.with_ansi(ansi).boxed(),
+        (false, false) => layer.with_ansi(ansi).without_time().boxed(),
+    }
+}
+
+/// Log file rotated by size: once a write would take it past `max_bytes` it
+/// is renamed to `<path>.1`, the older ones shift up to `<path>.<max_files>`
+/// and the oldest is dropped
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open `path` for appending, creating it and its directory if needed
+    pub fn open(path: impl AsRef<Path>, max_bytes: u64, max_files: u32) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(directory) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(directory)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.max_files > 0 {
+            for index in (1..self.max_files).rev() {
+                match std::fs::rename(self.rotated_path(index), self.rotated_path(index + 1)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
 }
 
 /// Log level utilities
@@ -250,4 +452,53 @@ mod tests {
         assert!(levels.contains(&"debug"));
         assert!(levels.contains(&"error"));
     }
+    
+    #[test]
+    fn test_log_levels_from_config_and_runtime_changes() {
+        let config = LoggingConfig {
+            level: "WARN".to_string(),
+            module_levels: [("sqlx".to_string(), "error".to_string())].into_iter().collect(),
+            ..Default::default()
+        };
+        let mut levels = LogLevels::from_config(&config).unwrap();
+        assert_eq!(levels.directives(), "warn,sqlx=error");
+        
+        levels
+            .apply(
+                Some("info"),
+                &[
+                    ("orchestrator::server".to_string(), Some("debug".to_string())),
+                    ("sqlx".to_string(), None),
+                ],
+            )
+            .unwrap();
+        assert_eq!(levels.directives(), "info,orchestrator::server=debug");
+        
+        // An invalid entry leaves every level as it was
+        let before = levels.clone();
+        assert!(levels.apply(Some("debug"), &[("a=b".to_string(), Some("info".to_string()))]).is_err());
+        assert!(levels.apply(None, &[("tonic".to_string(), Some("loud".to_string()))]).is_err());
+        assert_eq!(levels, before);
+    }
+    
+    #[test]
+    fn test_rotating_file_rotates_by_size() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("logs").join("orchestrator.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+        
+        let read = |name: &str| std::fs::read_to_string(dir.path().join("logs").join(name)).unwrap();
+        assert_eq!(read("orchestrator.log"), "fourth\n");
+        assert_eq!(read("orchestrator.log.1"), "third\n");
+        assert_eq!(read("orchestrator.log.2"), "second\n");
+        assert!(!dir.path().join("logs").join("orchestrator.log.3").exists());
+        
+        // Reopening continues from the current size
+        let reopened = RotatingFile::open(&path, 10, 2).unwrap();
+        assert_eq!(reopened.size, 7);
+    }
 }
\ No newline at end of file
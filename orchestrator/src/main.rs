@@ -1,6 +1,5 @@
 use clap::Parser;
-use orchestrator::{Orchestrator, OrchestratorConfig};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use orchestrator::{LoggingConfig, Orchestrator, OrchestratorConfig};
 
 /// Proxxy Orchestrator - Central management server for distributed MITM proxy
 #[derive(Parser, Debug)]
@@ -25,20 +24,79 @@ struct Args {
     /// Agent timeout in seconds
     #[arg(long, default_value_t = 300)]
     agent_timeout: u64,
+
+    /// Read-only observer mode: reject all mutations (for stakeholders watching a test)
+    #[arg(long)]
+    read_only: bool,
+
+    /// Admin bearer token; when set, every API request needs it or an API key
+    #[arg(long, env = "PROXXY_ADMIN_TOKEN", hide_env_values = true)]
+    admin_token: Option<String>,
+
+    /// Browser origin allowed by CORS when authentication is on (repeatable; defaults to the GUI)
+    #[arg(long = "cors-origin")]
+    cors_origins: Vec<String>,
+
+    /// Serve the agent gRPC channel over mutual TLS; agents need an enrolled client certificate
+    #[arg(long)]
+    grpc_tls: bool,
+
+    /// DNS name or IP agents use to reach the orchestrator, for its TLS certificate (repeatable; defaults to localhost)
+    #[arg(long = "grpc-tls-name")]
+    grpc_tls_names: Vec<String>,
+
+    /// Passphrase encrypting the MITM CA key in ./certs; without it an encrypted key waits for the unlockCa mutation
+    #[arg(long, env = "PROXXY_CA_PASSPHRASE", hide_env_values = true)]
+    ca_key_passphrase: Option<String>,
+
+    /// Default log level (trace, debug, info, warn, error)
+    #[arg(long, env = "PROXXY_LOG_LEVEL", default_value = "info")]
+    log_level: String,
+
+    /// Level of one module as `module=level`, e.g. `sqlx=warn` (repeatable)
+    #[arg(long = "log-module", value_parser = parse_module_level)]
+    log_modules: Vec<(String, String)>,
+
+    /// Write logs as one JSON object per line
+    #[arg(long)]
+    log_json: bool,
+
+    /// Also write logs to this file, rotated by size
+    #[arg(long)]
+    log_file: Option<String>,
+
+    /// Size in MB at which the log file is rotated
+    #[arg(long, default_value_t = 100)]
+    log_max_size_mb: u64,
+
+    /// Number of rotated log files to keep
+    #[arg(long, default_value_t = 5)]
+    log_max_files: u32,
+}
+
+fn parse_module_level(value: &str) -> Result<(String, String), String> {
+    value
+        .split_once('=')
+        .map(|(module, level)| (module.trim().to_string(), level.trim().to_string()))
+        .ok_or_else(|| format!("expected module=level, got '{}'", value))
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    // Initialize logging with proper configuration
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "orchestrator=info,proxy_core=info,flow_engine=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    // Initialize logging; flags override the default module levels
+    let mut logging = LoggingConfig {
+        level: args.log_level.clone(),
+        json_format: args.log_json,
+        enable_colors: !args.log_json,
+        log_file: args.log_file.clone(),
+        max_file_size_mb: args.log_max_size_mb,
+        max_files: args.log_max_files,
+        ..Default::default()
+    };
+    logging.module_levels.extend(args.log_modules.iter().cloned());
+    let _logging_guard = orchestrator::logging::init_logging(&logging)?;
 
     // Create configuration
     let config = OrchestratorConfig {
@@ -47,7 +105,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         database_url: args.database_url.clone(),
         health_check_interval: args.health_check_interval,
         agent_timeout: args.agent_timeout,
-        logging: orchestrator::LoggingConfig::default(),
+        logging,
+        read_only: args.read_only,
+        admin_token: args.admin_token.clone(),
+        cors_origins: args.cors_origins.clone(),
+        grpc_tls: args.grpc_tls.then(|| orchestrator::agent_tls::GrpcTlsConfig {
+            server_names: args.grpc_tls_names.clone(),
+        }),
+        ca_key_passphrase: args.ca_key_passphrase.clone(),
     };
 
     // Create and start orchestrator
@@ -55,7 +120,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     println!("🚀 Orchestrator starting...");
     println!(
-        "📡 gRPC server will be available at: http://127.0.0.1:{}",
+        "📡 gRPC server will be available at: {}://127.0.0.1:{}",
+        if args.grpc_tls { "https" } else { "http" },
         args.grpc_port
     );
     println!(
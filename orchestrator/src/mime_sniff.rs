@@ -0,0 +1,191 @@
+//! Response MIME sniffing
+//!
+//! Detects what a response body actually is from its leading bytes (image and
+//! archive magic numbers, markup, JSON) and compares it with the declared
+//! Content-Type. Both types are stored with the transaction at capture time;
+//! a body that is something other than what it claims to be is a passive
+//! finding, since browsers that sniff may render it (HTML served as
+//! `image/png` is a classic XSS vector) and it usually points at a
+//! misconfigured endpoint.
+
+use std::collections::HashMap;
+
+/// Bytes of a body inspected when sniffing
+const SNIFF_LEN: usize = 512;
+
+/// Binary formats recognised by their magic numbers
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\x00asm", "application/wasm"),
+    (b"wOFF", "font/woff"),
+    (b"wOF2", "font/woff2"),
+    (b"OggS", "audio/ogg"),
+];
+
+/// Tags that make a text body HTML, per the WHATWG sniffing algorithm
+const HTML_TAGS: &[&str] = &[
+    "<!doctype html", "<html", "<head", "<body", "<script", "<iframe", "<h1", "<div", "<font", "<table", "<a ",
+    "<style", "<title", "<b>", "<br", "<p>", "<p ", "<!--",
+];
+
+/// A declared type contradicted by the body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MimeMismatch {
+    pub declared: String,
+    pub detected: &'static str,
+    /// The response does not send `X-Content-Type-Options: nosniff`
+    pub sniffable: bool,
+}
+
+impl MimeMismatch {
+    /// Markup a browser would run scripts in
+    pub fn is_active_content(&self) -> bool {
+        matches!(self.detected, "text/html" | "image/svg+xml")
+    }
+}
+
+fn header<'a>(headers: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// Declared media type without parameters, lowercased
+pub fn declared_type(headers: &HashMap<String, String>) -> Option<String> {
+    let essence = header(headers, "content-type")?.split(';').next()?.trim().to_ascii_lowercase();
+    (!essence.is_empty()).then_some(essence)
+}
+
+/// Type of a body judged from its content; None for empty bodies
+pub fn sniff(body: &[u8]) -> Option<&'static str> {
+    if body.is_empty() {
+        return None;
+    }
+    if let Some((_, mime)) = MAGIC.iter().find(|(magic, _)| body.starts_with(magic)) {
+        return Some(*mime);
+    }
+    if body.len() >= 12 && &body[..4] == b"RIFF" && &body[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if body.len() >= 12 && &body[4..8] == b"ftyp" {
+        return Some("video/mp4");
+    }
+
+    let head = &body[..body.len().min(SNIFF_LEN)];
+    // Control bytes other than whitespace mean binary
+    if head.iter().any(|&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c)) {
+        return Some("application/octet-stream");
+    }
+
+    let text = String::from_utf8_lossy(head).to_ascii_lowercase();
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    if text.starts_with("<?xml") {
+        return Some(if text.contains("<svg") { "image/svg+xml" } else { "application/xml" });
+    }
+    if text.starts_with("<svg") {
+        return Some("image/svg+xml");
+    }
+    if HTML_TAGS.iter().any(|tag| text.starts_with(tag)) {
+        return Some("text/html");
+    }
+    if (text.starts_with('{') || text.starts_with('[')) && serde_json::from_slice::<serde_json::Value>(body).is_ok() {
+        return Some("application/json");
+    }
+    Some("text/plain")
+}
+
+/// Whether a declared type admits the detected one
+fn compatible(declared: &str, detected: &str) -> bool {
+    if declared == detected {
+        return true;
+    }
+    let (declared_top, declared_sub) = declared.split_once('/').unwrap_or((declared, ""));
+    match detected {
+        // Weak guesses: any text-ish or generic declaration fits
+        "text/plain" | "application/octet-stream" => true,
+        "text/html" => declared_sub.contains("html"),
+        "application/xml" => declared_sub.contains("xml"),
+        "image/svg+xml" => declared_sub.contains("svg") || declared_sub.contains("xml"),
+        "application/json" => declared_sub.contains("json") || declared_top == "text",
+        "application/zip" | "application/gzip" => declared_top == "application",
+        _ => {
+            // Binary magic: same family (an image/* for an image)
+            let detected_top = detected.split('/').next().unwrap_or_default();
+            declared_top == detected_top
+                || declared == "application/octet-stream"
+                || (detected_top == "font" && declared_sub.contains("font"))
+        }
+    }
+}
+
+/// Mismatch between the declared Content-Type and the body, if any.
+/// Responses without a declared type or body, and bodies still carrying a
+/// Content-Encoding, are not judged.
+pub fn detect_mismatch(headers: &HashMap<String, String>, body: &[u8]) -> Option<MimeMismatch> {
+    if header(headers, "content-encoding").is_some_and(|v| !v.trim().eq_ignore_ascii_case("identity")) {
+        return None;
+    }
+    let declared = declared_type(headers)?;
+    let detected = sniff(body)?;
+    if compatible(&declared, detected) {
+        return None;
+    }
+    let sniffable = !header(headers, "x-content-type-options").is_some_and(|v| v.trim().eq_ignore_ascii_case("nosniff"));
+    Some(MimeMismatch {
+        declared,
+        detected,
+        sniffable,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0"), Some("image/png"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"  <!DOCTYPE html><p>hi"), Some("text/html"));
+        assert_eq!(sniff(b"<?xml version=\"1.0\"?><svg/>"), Some("image/svg+xml"));
+        assert_eq!(sniff(br#"{"ok": true}"#), Some("application/json"));
+        assert_eq!(sniff(b"{not json"), Some("text/plain"));
+        assert_eq!(sniff(b"\x00\x01\x02"), Some("application/octet-stream"));
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_detect_mismatch() {
+        let html = b"<html><script>alert(1)</script></html>";
+        let mismatch = detect_mismatch(&headers(&[("Content-Type", "image/png")]), html).unwrap();
+        assert_eq!(mismatch.declared, "image/png");
+        assert_eq!(mismatch.detected, "text/html");
+        assert!(mismatch.sniffable && mismatch.is_active_content());
+
+        let nosniff = headers(&[("content-type", "text/plain"), ("X-Content-Type-Options", "nosniff")]);
+        assert!(!detect_mismatch(&nosniff, html).unwrap().sniffable);
+
+        let png = b"\x89PNG\r\n\x1a\n\0\0";
+        assert!(detect_mismatch(&headers(&[("Content-Type", "application/json")]), png).is_some());
+        assert!(detect_mismatch(&headers(&[("Content-Type", "image/jpeg")]), png).is_none());
+
+        // Matching or generic declarations are fine
+        assert!(detect_mismatch(&headers(&[("Content-Type", "text/html; charset=utf-8")]), html).is_none());
+        assert!(detect_mismatch(&headers(&[("Content-Type", "application/problem+json")]), b"{}").is_none());
+        assert!(detect_mismatch(&headers(&[("Content-Type", "text/javascript")]), b"var a = 1;").is_none());
+        assert!(detect_mismatch(&headers(&[]), html).is_none());
+        let gzipped = headers(&[("Content-Type", "text/html"), ("Content-Encoding", "gzip")]);
+        assert!(detect_mismatch(&gzipped, b"\x1f\x8b\x08\x00").is_none());
+    }
+}
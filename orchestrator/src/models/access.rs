@@ -0,0 +1,164 @@
+//! Project access lists
+//!
+//! Each project keeps its own member list. A project without members is
+//! unshared and open to every caller; once members exist, callers must be
+//! identified and hold a sufficient role.
+
+use serde::{Deserialize, Serialize};
+
+/// Request header carrying the caller's identity.
+/// The orchestrator does not authenticate users itself: a shared deployment must
+/// sit behind an authenticating proxy that sets this header and strips it from
+/// client requests.
+pub const PRINCIPAL_HEADER: &str = "x-proxxy-user";
+
+/// Role of a member within a project, ordered by privilege
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ProjectRole {
+    /// Read-only access
+    Viewer,
+    /// May change project settings
+    Editor,
+    /// May also manage the member list
+    Owner,
+}
+
+impl ProjectRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectRole::Viewer => "Viewer",
+            ProjectRole::Editor => "Editor",
+            ProjectRole::Owner => "Owner",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Viewer" => Some(ProjectRole::Viewer),
+            "Editor" => Some(ProjectRole::Editor),
+            "Owner" => Some(ProjectRole::Owner),
+            _ => None,
+        }
+    }
+}
+
+/// Identity of the caller, attached to each request by the HTTP layer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal(pub String);
+
+/// Member of a project's access list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectMember {
+    pub principal: String,
+    pub role: ProjectRole,
+    pub added_at: i64,
+}
+
+/// Why an access check failed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessDenied {
+    /// The project is shared but the caller did not identify itself
+    Unauthenticated,
+    /// The caller is not a member or its role is too low
+    InsufficientRole { required: ProjectRole, actual: Option<ProjectRole> },
+}
+
+impl std::fmt::Display for AccessDenied {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessDenied::Unauthenticated => write!(f, "This project is shared; an identified user is required"),
+            AccessDenied::InsufficientRole { required, actual: Some(actual) } => write!(
+                f,
+                "{} role required, but you are {}",
+                required.as_str(),
+                actual.as_str()
+            ),
+            AccessDenied::InsufficientRole { required, actual: None } => {
+                write!(f, "{} role required, but you are not a project member", required.as_str())
+            }
+        }
+    }
+}
+
+/// Check that `principal` holds at least `required` on a project with `members`
+pub fn check_access(
+    members: &[ProjectMember],
+    principal: Option<&Principal>,
+    required: ProjectRole,
+) -> Result<(), AccessDenied> {
+    if members.is_empty() {
+        return Ok(());
+    }
+
+    let principal = principal.ok_or(AccessDenied::Unauthenticated)?;
+    let actual = members.iter().find(|m| m.principal == principal.0).map(|m| m.role);
+    match actual {
+        Some(role) if role >= required => Ok(()),
+        actual => Err(AccessDenied::InsufficientRole { required, actual }),
+    }
+}
+
+/// Check that `principal` may change a project with `members`: on shared
+/// projects only Editors and Owners may, so Viewers, unknown principals and
+/// anonymous callers are all refused
+pub fn check_mutation(members: &[ProjectMember], principal: Option<&Principal>) -> Result<(), AccessDenied> {
+    check_access(members, principal, ProjectRole::Editor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(principal: &str, role: ProjectRole) -> ProjectMember {
+        ProjectMember {
+            principal: principal.to_string(),
+            role,
+            added_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_unshared_project_is_open() {
+        assert!(check_access(&[], None, ProjectRole::Owner).is_ok());
+    }
+
+    #[test]
+    fn test_role_checks() {
+        let members = vec![member("alice", ProjectRole::Owner), member("bob", ProjectRole::Viewer)];
+        let alice = Principal("alice".to_string());
+        let bob = Principal("bob".to_string());
+        let eve = Principal("eve".to_string());
+
+        assert!(check_access(&members, Some(&alice), ProjectRole::Editor).is_ok());
+        assert!(check_access(&members, Some(&bob), ProjectRole::Viewer).is_ok());
+        assert_eq!(
+            check_access(&members, Some(&bob), ProjectRole::Editor),
+            Err(AccessDenied::InsufficientRole {
+                required: ProjectRole::Editor,
+                actual: Some(ProjectRole::Viewer)
+            })
+        );
+        assert!(matches!(
+            check_access(&members, Some(&eve), ProjectRole::Viewer),
+            Err(AccessDenied::InsufficientRole { actual: None, .. })
+        ));
+        assert_eq!(
+            check_access(&members, None, ProjectRole::Viewer),
+            Err(AccessDenied::Unauthenticated)
+        );
+    }
+
+    #[test]
+    fn test_mutations_need_editor() {
+        let members = vec![member("alice", ProjectRole::Editor), member("bob", ProjectRole::Viewer)];
+
+        assert!(check_mutation(&members, Some(&Principal("alice".to_string()))).is_ok());
+        assert!(check_mutation(&members, Some(&Principal("bob".to_string()))).is_err());
+        assert_eq!(
+            check_mutation(&members, Some(&Principal("eve".to_string()))),
+            Err(AccessDenied::InsufficientRole { required: ProjectRole::Editor, actual: None })
+        );
+        assert_eq!(check_mutation(&members, None), Err(AccessDenied::Unauthenticated));
+        assert!(check_mutation(&[], None).is_ok());
+    }
+}
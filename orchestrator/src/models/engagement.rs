@@ -0,0 +1,335 @@
+//! Rules of engagement
+//!
+//! The target allowlist is a hard limit on where active tooling may send
+//! requests. Unlike scope, which only filters what is shown, a host outside
+//! the allowlist is refused no matter what the user asks for, unless an
+//! explicit, time-limited override has been granted. Every refusal and every
+//! override is written to the engagement audit trail.
+//!
+//! The engagement schedule limits when active tooling may run; outside its
+//! windows tools are refused while passive capture continues.
+
+use crate::models::settings::VersionedSetting;
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+/// Hosts active tooling is allowed to reach
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TargetAllowlist {
+    /// When disabled every host is allowed
+    pub enabled: bool,
+    /// Host names or glob patterns ("app.example.com", "*.staging.example.com")
+    pub hosts: Vec<String>,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl VersionedSetting for TargetAllowlist {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl TargetAllowlist {
+    /// Whether `host` may be targeted
+    pub fn allows(&self, host: &str) -> bool {
+        !self.enabled || self.hosts.iter().any(|pattern| host_matches(pattern, host))
+    }
+}
+
+/// Case-insensitive match of a host against a name or glob pattern
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    let host = host.trim_end_matches('.').to_lowercase();
+    if pattern.is_empty() {
+        return false;
+    }
+    Pattern::new(&pattern).is_ok_and(|p| p.matches(&host))
+}
+
+/// Host a request URL targets, without port
+pub fn target_host(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    url.host_str().map(|h| h.trim_matches(|c| c == '[' || c == ']').to_lowercase())
+}
+
+/// Active testing windows. Outside them active tooling is blocked while
+/// passive capture continues.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngagementSchedule {
+    /// When disabled active tooling may run at any time
+    pub enabled: bool,
+    /// IANA time zone the windows are expressed in ("Europe/Istanbul")
+    pub timezone: String,
+    /// First day of the engagement, inclusive
+    pub start_date: Option<NaiveDate>,
+    /// Last day of the engagement, inclusive
+    pub end_date: Option<NaiveDate>,
+    /// Daily windows; none means testing is allowed all day within the dates
+    pub windows: Vec<EngagementWindow>,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl Default for EngagementSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timezone: "UTC".to_string(),
+            start_date: None,
+            end_date: None,
+            windows: Vec::new(),
+            version: 0,
+        }
+    }
+}
+
+impl VersionedSetting for EngagementSchedule {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+/// Recurring window on the given weekdays. A window ending at or before its
+/// start runs past midnight into the next day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngagementWindow {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl EngagementWindow {
+    fn contains(&self, local: &NaiveDateTime) -> bool {
+        let day = local.weekday();
+        let time = local.time();
+        if self.start < self.end {
+            self.days.contains(&day) && time >= self.start && time < self.end
+        } else {
+            (self.days.contains(&day) && time >= self.start)
+                || (self.days.contains(&day.pred()) && time < self.end)
+        }
+    }
+}
+
+impl EngagementSchedule {
+    /// Parsed time zone, if the name is valid
+    pub fn tz(&self) -> Option<Tz> {
+        self.timezone.parse().ok()
+    }
+
+    /// Whether active testing is allowed at `now`. An unknown time zone
+    /// keeps the schedule closed rather than guessing.
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        if !self.enabled {
+            return true;
+        }
+        let Some(tz) = self.tz() else {
+            return false;
+        };
+
+        let local = now.with_timezone(&tz).naive_local();
+        if self.start_date.is_some_and(|start| local.date() < start)
+            || self.end_date.is_some_and(|end| local.date() > end)
+        {
+            return false;
+        }
+
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(&local))
+    }
+}
+
+/// Tool that sends traffic on the user's behalf
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveTool {
+    Repeater,
+    Intruder,
+    Crawler,
+    Replay,
+}
+
+impl ActiveTool {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ActiveTool::Repeater => "Repeater",
+            ActiveTool::Intruder => "Intruder",
+            ActiveTool::Crawler => "Crawler",
+            ActiveTool::Replay => "Replay",
+        }
+    }
+}
+
+/// Temporary permission to target hosts outside the allowlist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowlistOverride {
+    pub id: String,
+    pub host_pattern: String,
+    /// Why the override was needed; required for the audit trail
+    pub reason: String,
+    pub granted_by: Option<String>,
+    pub created_at: i64,
+    pub expires_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+impl AllowlistOverride {
+    /// Whether the override is in force at `now`
+    pub fn is_active(&self, now: i64) -> bool {
+        self.revoked_at.is_none() && now < self.expires_at
+    }
+}
+
+/// Kind of engagement audit event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EngagementEvent {
+    /// A request to a host outside the allowlist was refused
+    Blocked,
+    /// A request outside the allowlist was let through by an override
+    OverrideUsed,
+    OverrideGranted,
+    OverrideRevoked,
+    AllowlistUpdated,
+    /// Active tooling was refused outside the engagement windows
+    OutsideWindow,
+    ScheduleUpdated,
+}
+
+impl EngagementEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EngagementEvent::Blocked => "Blocked",
+            EngagementEvent::OverrideUsed => "OverrideUsed",
+            EngagementEvent::OverrideGranted => "OverrideGranted",
+            EngagementEvent::OverrideRevoked => "OverrideRevoked",
+            EngagementEvent::AllowlistUpdated => "AllowlistUpdated",
+            EngagementEvent::OutsideWindow => "OutsideWindow",
+            EngagementEvent::ScheduleUpdated => "ScheduleUpdated",
+        }
+    }
+}
+
+/// Entry of the engagement audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EngagementAuditEntry {
+    pub id: i64,
+    pub timestamp: i64,
+    pub event: String,
+    pub tool: Option<String>,
+    pub target: Option<String>,
+    pub principal: Option<String>,
+    pub details: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_matching() {
+        let allowlist = TargetAllowlist {
+            enabled: true,
+            hosts: vec!["app.example.com".into(), "*.staging.example.com".into()],
+            version: 0,
+        };
+
+        assert!(allowlist.allows("app.example.com"));
+        assert!(allowlist.allows("APP.example.com."));
+        assert!(allowlist.allows("api.staging.example.com"));
+        assert!(!allowlist.allows("www.example.com"));
+        assert!(!allowlist.allows("staging.example.com"));
+
+        let disabled = TargetAllowlist::default();
+        assert!(disabled.allows("anything.example.org"));
+    }
+
+    #[test]
+    fn test_target_host() {
+        assert_eq!(target_host("https://App.Example.com:8443/login").as_deref(), Some("app.example.com"));
+        assert_eq!(target_host("http://[::1]:8080/").as_deref(), Some("::1"));
+        assert_eq!(target_host("not a url"), None);
+    }
+
+    #[test]
+    fn test_override_expiry() {
+        let mut o = AllowlistOverride {
+            id: "o1".into(),
+            host_pattern: "prod.example.com".into(),
+            reason: "client approved retest".into(),
+            granted_by: Some("alice".into()),
+            created_at: 100,
+            expires_at: 200,
+            revoked_at: None,
+        };
+        assert!(o.is_active(150));
+        assert!(!o.is_active(200));
+
+        o.revoked_at = Some(120);
+        assert!(!o.is_active(150));
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_schedule_windows_in_timezone() {
+        let schedule = EngagementSchedule {
+            enabled: true,
+            timezone: "Europe/Istanbul".into(),
+            start_date: NaiveDate::from_ymd_opt(2026, 10, 12),
+            end_date: NaiveDate::from_ymd_opt(2026, 10, 16),
+            windows: vec![EngagementWindow {
+                days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+                start: time("09:00"),
+                end: time("17:00"),
+            }],
+            version: 0,
+        };
+
+        // Istanbul is UTC+3
+        assert!(schedule.is_open(at("2026-10-13T06:00:00Z")));
+        assert!(!schedule.is_open(at("2026-10-13T05:59:00Z")));
+        assert!(!schedule.is_open(at("2026-10-13T14:00:00Z")));
+        // Outside the engagement dates
+        assert!(!schedule.is_open(at("2026-10-19T08:00:00Z")));
+
+        assert!(EngagementSchedule::default().is_open(at("2026-10-18T03:00:00Z")));
+        let broken = EngagementSchedule { timezone: "Mars/Olympus".into(), ..schedule };
+        assert!(!broken.is_open(at("2026-10-13T08:00:00Z")));
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let schedule = EngagementSchedule {
+            enabled: true,
+            windows: vec![EngagementWindow {
+                days: vec![Weekday::Fri],
+                start: time("22:00"),
+                end: time("04:00"),
+            }],
+            ..Default::default()
+        };
+
+        // 2026-10-16 is a Friday
+        assert!(schedule.is_open(at("2026-10-16T23:00:00Z")));
+        assert!(schedule.is_open(at("2026-10-17T03:30:00Z")));
+        assert!(!schedule.is_open(at("2026-10-17T04:00:00Z")));
+        assert!(!schedule.is_open(at("2026-10-15T23:00:00Z")));
+    }
+}
@@ -1 +1,3 @@
 pub mod settings;
+pub mod access;
+pub mod engagement;
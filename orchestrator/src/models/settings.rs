@@ -6,12 +6,44 @@ pub struct ScopeConfig {
     pub include_patterns: Vec<String>,  // ["*.example.com", "api.*.io"]
     pub exclude_patterns: Vec<String>,  // ["*.google.com"]
     pub use_regex: bool,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct InterceptionConfig {
     pub enabled: bool,
     pub rules: Vec<InterceptionRule>,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+/// Settings saved with optimistic concurrency control
+pub trait VersionedSetting {
+    fn version(&self) -> u64;
+    fn set_version(&mut self, version: u64);
+}
+
+impl VersionedSetting for ScopeConfig {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl VersionedSetting for InterceptionConfig {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,3 +70,82 @@ pub enum RuleAction {
     Drop,    // Silently drop
     Modify,  // Future: auto-modify headers/body
 }
+
+/// Agent Repeater and Intruder run on when no agent is chosen, stored under
+/// the `default_agent` setting
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DefaultAgentConfig {
+    pub agent_id: Option<String>,
+    /// Tried in order while the default agent is offline
+    #[serde(default)]
+    pub fallback_agents: Vec<String>,
+    /// With every listed agent offline, run on any online agent
+    #[serde(default)]
+    pub fallback_to_any: bool,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl VersionedSetting for DefaultAgentConfig {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+impl DefaultAgentConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.agent_id.as_deref().is_some_and(|id| id.trim().is_empty()) {
+            return Err("agent_id must not be blank".to_string());
+        }
+        if self.fallback_agents.iter().any(|id| id.trim().is_empty()) {
+            return Err("fallback agent IDs must not be blank".to_string());
+        }
+        if self.agent_id.is_none() && (!self.fallback_agents.is_empty() || self.fallback_to_any) {
+            return Err("fallback rules need a default agent".to_string());
+        }
+        Ok(())
+    }
+
+    /// The agent to run on, given the IDs of the online agents. `None` when
+    /// no default is configured or the rules leave no online agent.
+    pub fn resolve(&self, online: &[String]) -> Option<String> {
+        let default = self.agent_id.as_ref()?;
+        std::iter::once(default)
+            .chain(&self.fallback_agents)
+            .find(|id| online.contains(id))
+            .or_else(|| if self.fallback_to_any { online.first() } else { None })
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_agent_fallbacks() {
+        let online = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let mut config = DefaultAgentConfig {
+            agent_id: Some("a".to_string()),
+            fallback_agents: vec!["b".to_string(), "c".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(config.resolve(&online(&["c", "a"])).as_deref(), Some("a"));
+        assert_eq!(config.resolve(&online(&["c", "b"])).as_deref(), Some("b"));
+        assert_eq!(config.resolve(&online(&["d"])), None);
+
+        config.fallback_to_any = true;
+        assert_eq!(config.resolve(&online(&["d"])).as_deref(), Some("d"));
+        assert_eq!(config.resolve(&[]), None);
+
+        assert_eq!(DefaultAgentConfig::default().resolve(&online(&["a"])), None);
+        config.agent_id = None;
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,442 @@
+//! Multipart Parsing
+//!
+//! Splits `multipart/form-data` (and other `multipart/*`) request bodies into
+//! their parts, with the field name, filename and content type of each, so
+//! uploads can be inspected without cutting the body at boundaries by hand.
+//! Captured bodies may be truncated; parsing then returns the parts found so
+//! far and marks the body incomplete instead of failing.
+//!
+//! A parsed body also seeds Intruder upload attacks: [`MultipartBody::upload_template`]
+//! turns it into a template for payload positions inside its parts.
+
+use crate::blob_store::{BlobError, BlobInfo, BlobStore};
+use crate::pb;
+use attack_engine::{UploadBody, UploadPartTemplate, UploadSource, UploadTemplate};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Blob reference owner for files extracted from a transaction's request
+pub const UPLOAD_OWNER_KIND: &str = "transaction_upload";
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum MultipartError {
+    #[error("Not a multipart body (content-type: {0})")]
+    NotMultipart(String),
+    #[error("Multipart content-type has no boundary")]
+    MissingBoundary,
+    #[error("Boundary not found in body")]
+    BoundaryNotFound,
+}
+
+/// One part of a multipart body
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipartPart {
+    /// Part headers in order, names as sent
+    pub headers: Vec<(String, String)>,
+    /// `name` from Content-Disposition
+    pub name: Option<String>,
+    /// `filename` (or decoded `filename*`) from Content-Disposition
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    /// Offset of the part content within the request body
+    pub offset: usize,
+    pub data: Vec<u8>,
+}
+
+impl MultipartPart {
+    /// Whether the part is a file upload rather than a plain field
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+
+    /// Hex SHA-256 of the content, the key it is stored under in the blob store
+    pub fn sha256(&self) -> String {
+        Sha256::digest(&self.data).iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipartBody {
+    pub boundary: String,
+    pub parts: Vec<MultipartPart>,
+    /// False when the closing boundary is missing, e.g. in a truncated capture
+    pub complete: bool,
+}
+
+impl MultipartBody {
+    /// Upload template that resends this body; positions are added by the caller
+    pub fn upload_template(&self) -> UploadTemplate {
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| UploadPartTemplate {
+                name: part.name.clone().unwrap_or_default(),
+                filename: part.filename.clone(),
+                content_type: part.content_type.clone(),
+                headers: part
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| {
+                        !name.eq_ignore_ascii_case("content-disposition") && !name.eq_ignore_ascii_case("content-type")
+                    })
+                    .cloned()
+                    .collect(),
+                magic: Vec::new(),
+                body: UploadSource::Inline(part.data.clone()),
+            })
+            .collect();
+
+        UploadTemplate {
+            boundary: self.boundary.clone(),
+            parts,
+            positions: Vec::new(),
+        }
+    }
+}
+
+/// Wire form of a rendered upload body, for an agent to stream
+pub fn streamed_body(body: &UploadBody) -> pb::StreamedBody {
+    use pb::body_segment::Source;
+
+    let segments = body
+        .segments
+        .iter()
+        .map(|segment| pb::BodySegment {
+            source: Some(match segment {
+                UploadSource::Inline(bytes) => Source::Inline(bytes.clone()),
+                UploadSource::Filler { size, byte } => Source::Filler(pb::FillerSegment {
+                    size: *size,
+                    byte: u32::from(*byte),
+                }),
+                UploadSource::AgentFile { path } => Source::AgentFile(path.clone()),
+            }),
+        })
+        .collect();
+    pb::StreamedBody { segments }
+}
+
+/// Parse a body by its Content-Type header value
+pub fn parse(content_type: &str, body: &[u8]) -> Result<MultipartBody, MultipartError> {
+    let essence = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    if !essence.starts_with("multipart/") {
+        return Err(MultipartError::NotMultipart(content_type.to_string()));
+    }
+    let boundary = header_params(content_type)
+        .remove("boundary")
+        .filter(|b| !b.is_empty())
+        .ok_or(MultipartError::MissingBoundary)?;
+    parse_with_boundary(&boundary, body)
+}
+
+pub fn parse_with_boundary(boundary: &str, body: &[u8]) -> Result<MultipartBody, MultipartError> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+
+    // The first delimiter may be preceded by a preamble
+    let mut pos = match find(body, &delimiter, 0) {
+        Some(i) => i + delimiter.len(),
+        None => return Err(MultipartError::BoundaryNotFound),
+    };
+
+    let mut parts = Vec::new();
+    let mut complete = false;
+    loop {
+        if body[pos..].starts_with(b"--") {
+            complete = true;
+            break;
+        }
+        // Rest of the delimiter line (transport padding allowed)
+        let start = match find(body, b"\n", pos) {
+            Some(i) => i + 1,
+            None => break,
+        };
+
+        let (headers, content_start) = parse_part_headers(body, start);
+        let next = find_delimiter(body, &delimiter, content_start);
+        let content_end = match next {
+            Some((content_end, _)) => content_end,
+            None => body.len(),
+        };
+        parts.push(part(headers, content_start, &body[content_start..content_end]));
+
+        match next {
+            Some((_, after)) => pos = after,
+            None => break,
+        }
+    }
+
+    Ok(MultipartBody {
+        boundary: boundary.to_string(),
+        parts,
+        complete,
+    })
+}
+
+fn part(headers: Vec<(String, String)>, offset: usize, data: &[u8]) -> MultipartPart {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    };
+    let disposition = header("content-disposition").map(header_params).unwrap_or_default();
+    let filename = disposition
+        .get("filename*")
+        .and_then(|v| decode_ext_value(v))
+        .or_else(|| disposition.get("filename").cloned());
+
+    MultipartPart {
+        name: disposition.get("name").cloned(),
+        filename,
+        content_type: header("content-type").map(str::to_string),
+        headers,
+        offset,
+        data: data.to_vec(),
+    }
+}
+
+/// Store the file parts of a transaction's request in the blob store,
+/// referenced by the transaction. Returns the hash of each part stored.
+pub async fn store_files(
+    blobs: &BlobStore,
+    request_id: &str,
+    body: &MultipartBody,
+) -> Result<Vec<(usize, BlobInfo)>, BlobError> {
+    let mut stored = Vec::new();
+    for (index, part) in body.parts.iter().enumerate().filter(|(_, p)| p.is_file()) {
+        let info = blobs.put(&part.data, part.content_type.as_deref()).await?;
+        blobs.add_ref(&info.hash, UPLOAD_OWNER_KIND, request_id).await?;
+        stored.push((index, info));
+    }
+    Ok(stored)
+}
+
+/// Headers from `start` up to the blank line; returns them and where the
+/// content begins
+fn parse_part_headers(body: &[u8], start: usize) -> (Vec<(String, String)>, usize) {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    let mut pos = start;
+    while pos < body.len() {
+        let end = find(body, b"\n", pos).unwrap_or(body.len());
+        let line = String::from_utf8_lossy(&body[pos..end]);
+        let line = line.trim_end_matches('\r');
+        pos = (end + 1).min(body.len());
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            // Folded continuation of the previous header
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    (headers, pos)
+}
+
+/// Next `CRLF--boundary` (or bare `LF--boundary`) from `from`: returns where
+/// the preceding content ends and where the delimiter ends
+fn find_delimiter(body: &[u8], delimiter: &[u8], from: usize) -> Option<(usize, usize)> {
+    let mut search = from;
+    loop {
+        let at = find(body, delimiter, search)?;
+        let after = at + delimiter.len();
+        if !ends_delimiter(&body[after..]) {
+            search = at + 1;
+            continue;
+        }
+        if at == from {
+            return Some((at, after));
+        }
+        if body[at - 1] == b'\n' {
+            let content_end = if at >= 2 && body[at - 2] == b'\r' { at - 2 } else { at - 1 };
+            return Some((content_end.max(from), after));
+        }
+        search = at + 1;
+    }
+}
+
+/// Whether the bytes after a boundary make it a delimiter: `--`, or
+/// whitespace up to the line end (or the end of a truncated body)
+fn ends_delimiter(rest: &[u8]) -> bool {
+    if rest.starts_with(b"--") {
+        return true;
+    }
+    rest.iter()
+        .find(|b| **b != b' ' && **b != b'\t')
+        .is_none_or(|b| *b == b'\r' || *b == b'\n')
+}
+
+/// `key=value` parameters of a header value, keys lowercased, quotes removed
+fn header_params(value: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut rest = value;
+    // Skip the leading token (media type or disposition type)
+    if let Some(i) = rest.find(';') {
+        rest = &rest[i + 1..];
+    } else {
+        return params;
+    }
+
+    while !rest.trim().is_empty() {
+        let (key, after_key) = match rest.split_once('=') {
+            Some((k, v)) => (k.trim().to_ascii_lowercase(), v.trim_start()),
+            None => break,
+        };
+        let (val, remaining) = if let Some(quoted) = after_key.strip_prefix('"') {
+            let mut val = String::new();
+            let mut chars = quoted.char_indices();
+            let mut end = quoted.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            val.push(escaped);
+                        }
+                    }
+                    '"' => {
+                        end = i + 1;
+                        break;
+                    }
+                    c => val.push(c),
+                }
+            }
+            let remaining = &quoted[end..];
+            (val, remaining.split_once(';').map(|(_, r)| r).unwrap_or(""))
+        } else {
+            match after_key.split_once(';') {
+                Some((v, r)) => (v.trim().to_string(), r),
+                None => (after_key.trim().to_string(), ""),
+            }
+        };
+        params.entry(key).or_insert(val);
+        rest = remaining;
+    }
+    params
+}
+
+/// RFC 5987 `charset'lang'percent-encoded` value
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut fields = value.splitn(3, '\'');
+    let charset = fields.next()?;
+    let _language = fields.next()?;
+    let encoded = fields.next()?;
+
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let raw = encoded.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        if raw[i] == b'%' && i + 2 < raw.len() {
+            let hex = std::str::from_utf8(&raw[i + 1..i + 3]).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            bytes.push(raw[i]);
+            i += 1;
+        }
+    }
+
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else {
+        // ISO-8859-1: each byte is the code point
+        Some(bytes.into_iter().map(char::from).collect())
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    if from > haystack.len() || needle.is_empty() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|i| i + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &[u8] = b"preamble\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"title\"\r\n\
+\r\n\
+hello\r\n\
+--XyZ\r\n\
+Content-Disposition: form-data; name=\"file\"; filename=\"a \\\"b\\\".php\"\r\n\
+Content-Type: image/png\r\n\
+\r\n\
+\x89PNG\r\n--XyZ-not-a-boundary\r\n\
+--XyZ--\r\n";
+
+    #[test]
+    fn test_parse_form_data() {
+        let parsed = parse("multipart/form-data; boundary=XyZ", BODY).unwrap();
+        assert!(parsed.complete);
+        assert_eq!(parsed.parts.len(), 2);
+
+        let title = &parsed.parts[0];
+        assert_eq!(title.name.as_deref(), Some("title"));
+        assert!(!title.is_file());
+        assert_eq!(title.data, b"hello");
+        assert_eq!(&BODY[title.offset..title.offset + 5], b"hello");
+
+        let file = &parsed.parts[1];
+        assert_eq!(file.name.as_deref(), Some("file"));
+        assert_eq!(file.filename.as_deref(), Some("a \"b\".php"));
+        assert_eq!(file.content_type.as_deref(), Some("image/png"));
+        assert_eq!(file.data, b"\x89PNG\r\n--XyZ-not-a-boundary");
+    }
+
+    #[test]
+    fn test_truncated_and_bare_lf() {
+        let body = b"--b\nContent-Disposition: form-data; name=a\n\n1\n--b\nContent-Disposition: form-data; name=f; filename=x.bin\n\nabc";
+        let parsed = parse("multipart/form-data; boundary=\"b\"", body).unwrap();
+        assert!(!parsed.complete);
+        assert_eq!(parsed.parts.len(), 2);
+        assert_eq!(parsed.parts[0].data, b"1");
+        assert_eq!(parsed.parts[1].filename.as_deref(), Some("x.bin"));
+        assert_eq!(parsed.parts[1].data, b"abc");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(parse("application/json", b"{}"), Err(MultipartError::NotMultipart(_))));
+        assert_eq!(parse("multipart/form-data", b""), Err(MultipartError::MissingBoundary));
+        assert_eq!(parse("multipart/form-data; boundary=q", b"x"), Err(MultipartError::BoundaryNotFound));
+    }
+
+    #[test]
+    fn test_upload_template() {
+        let template = parse("multipart/form-data; boundary=XyZ", BODY).unwrap().upload_template();
+        assert_eq!(template.boundary, "XyZ");
+        assert_eq!(template.parts[1].filename.as_deref(), Some("a \"b\".php"));
+        assert!(template.parts[1].headers.is_empty());
+
+        // Resending without payloads reproduces the captured parts
+        let rendered = template.render(&HashMap::new()).unwrap();
+        let wire = streamed_body(&rendered);
+        let bytes: Vec<u8> = wire
+            .segments
+            .iter()
+            .flat_map(|s| match &s.source {
+                Some(pb::body_segment::Source::Inline(bytes)) => bytes.clone(),
+                other => panic!("unexpected segment {:?}", other),
+            })
+            .collect();
+        let reparsed = parse(&rendered.content_type, &bytes).unwrap();
+        assert!(reparsed.complete);
+        assert_eq!(reparsed.parts[0].data, b"hello");
+        assert_eq!(reparsed.parts[1].data, b"\x89PNG\r\n--XyZ-not-a-boundary");
+    }
+
+    #[test]
+    fn test_extended_filename() {
+        let params = header_params("form-data; name=f; filename=\"fallback.txt\"; filename*=UTF-8''%C3%BCber.txt");
+        assert_eq!(params.get("filename").map(String::as_str), Some("fallback.txt"));
+        assert_eq!(decode_ext_value(&params["filename*"]).as_deref(), Some("über.txt"));
+    }
+}
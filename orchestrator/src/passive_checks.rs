@@ -0,0 +1,666 @@
+//! Custom passive checks
+//!
+//! Users describe simple checks in YAML: regexes on response headers and body,
+//! a severity and a description. Every matcher of a check has to match for it
+//! to raise a finding. Checks live as `*.yaml` files in the project's
+//! `passive-checks` directory; uploads through the API are written there too,
+//! so the directory is the only source the scanner compiles from.
+//!
+//! ```yaml
+//! id: server-version
+//! name: Server version disclosed
+//! severity: low
+//! description: The Server header reveals the software version.
+//! matchers:
+//!   - part: header
+//!     name: Server
+//!     regex: '\d+(\.\d+)+'
+//! ```
+//!
+//! Logic beyond regexes goes in an optional Rhai `verdict` script, run once
+//! the matchers (if any) have matched. It sees `status`, `headers` (lowercase
+//! names), `body` and the matchers' `evidence`, and returns "vulnerable",
+//! "not_vulnerable" or "needs_more" (or a bool). Passive scanning cannot
+//! send a follow-up probe, so "needs_more" is recorded at info severity for
+//! someone to confirm by hand.
+//!
+//! ```yaml
+//! id: django-debug
+//! name: Django debug page
+//! severity: medium
+//! matchers:
+//!   - part: body
+//!     regex: 'DEBUG = True'
+//! verdict: |
+//!   if status >= 500 && body.contains("Traceback") { "vulnerable" }
+//!   else if status == 404 { "not_vulnerable" }
+//!   else { "needs_more" }
+//! ```
+
+use crate::pb::HttpResponseData;
+use crate::Database;
+use regex::Regex;
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+/// Only the start of larger bodies is scanned
+pub const MAX_SCANNED_BODY: usize = 1024 * 1024;
+
+/// Longest evidence snippet kept per matcher
+const MAX_EVIDENCE_LEN: usize = 200;
+
+/// Operations a verdict script may run per response before it is aborted
+const MAX_VERDICT_OPERATIONS: u64 = 100_000;
+
+const MAX_VERDICT_CALL_LEVELS: usize = 16;
+
+/// Largest string a verdict script may build; room for the scanned body
+/// decoded as text, which grows when invalid UTF-8 is replaced
+const MAX_VERDICT_STRING_SIZE: usize = 4 * MAX_SCANNED_BODY;
+
+/// Largest array or object map a verdict script may build
+const MAX_VERDICT_ARRAY_SIZE: usize = 10_000;
+const MAX_VERDICT_MAP_SIZE: usize = 1_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PassiveCheckError {
+    #[error("Invalid check YAML: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Check '{check}' has an invalid regex: {source}")]
+    Regex {
+        check: String,
+        #[source]
+        source: regex::Error,
+    },
+    #[error("Check '{check}' has an invalid verdict script: {message}")]
+    Script { check: String, message: String },
+    #[error("Invalid check: {0}")]
+    Invalid(String),
+    #[error("Passive checks directory: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    /// Value stored in `passive_findings.severity`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        match tag {
+            "info" => Some(Severity::Info),
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            "critical" => Some(Severity::Critical),
+            _ => None,
+        }
+    }
+}
+
+/// A check as written in YAML
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PassiveCheckDefinition {
+    pub id: String,
+    pub name: String,
+    pub severity: Severity,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub matchers: Vec<MatcherDefinition>,
+    /// Rhai script deciding on responses the matchers let through
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<String>,
+}
+
+/// What a verdict script decided about a response
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Vulnerable,
+    NotVulnerable,
+    /// Suspicious, but the response alone cannot tell
+    NeedsMore,
+}
+
+impl Verdict {
+    fn from_script(result: Dynamic) -> Option<Self> {
+        if let Ok(vulnerable) = result.as_bool() {
+            return Some(if vulnerable { Verdict::Vulnerable } else { Verdict::NotVulnerable });
+        }
+        match result.into_string().ok()?.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "vulnerable" => Some(Verdict::Vulnerable),
+            "not_vulnerable" => Some(Verdict::NotVulnerable),
+            "needs_more" => Some(Verdict::NeedsMore),
+            _ => None,
+        }
+    }
+}
+
+fn verdict_engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_VERDICT_OPERATIONS);
+        engine.set_max_call_levels(MAX_VERDICT_CALL_LEVELS);
+        engine.set_max_string_size(MAX_VERDICT_STRING_SIZE);
+        engine.set_max_array_size(MAX_VERDICT_ARRAY_SIZE);
+        engine.set_max_map_size(MAX_VERDICT_MAP_SIZE);
+        engine.on_print(|text| debug!("Verdict script: {}", text));
+        engine
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "part", rename_all = "lowercase")]
+pub enum MatcherDefinition {
+    /// Value of header `name` (any header's `Name: value` line when unset)
+    Header {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        regex: String,
+    },
+    Body { regex: String },
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Header { name: Option<String>, regex: Regex },
+    Body(Regex),
+}
+
+impl Matcher {
+    /// Matched text, if any
+    fn find(&self, response: &HttpResponseData, body: &str) -> Option<String> {
+        match self {
+            Matcher::Header { name, regex } => {
+                let headers = response.headers.as_ref()?;
+                headers.headers.iter().find_map(|(header, value)| match name {
+                    Some(name) if header.eq_ignore_ascii_case(name) => regex.find(value).map(|m| m.as_str().to_string()),
+                    Some(_) => None,
+                    None => regex
+                        .find(&format!("{}: {}", header, value))
+                        .map(|m| m.as_str().to_string()),
+                })
+            }
+            Matcher::Body(regex) => regex.find(body).map(|m| m.as_str().to_string()),
+        }
+    }
+}
+
+/// A definition with its regexes compiled
+#[derive(Debug)]
+pub struct CompiledCheck {
+    pub definition: PassiveCheckDefinition,
+    matchers: Vec<Matcher>,
+    verdict: Option<AST>,
+}
+
+impl CompiledCheck {
+    pub fn compile(definition: PassiveCheckDefinition) -> Result<Self, PassiveCheckError> {
+        if definition.id.is_empty()
+            || !definition.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(PassiveCheckError::Invalid(format!(
+                "id '{}' must be non-empty and use only letters, digits, - and _",
+                definition.id
+            )));
+        }
+        if definition.matchers.is_empty() && definition.verdict.is_none() {
+            return Err(PassiveCheckError::Invalid(format!(
+                "check '{}' has neither matchers nor a verdict script",
+                definition.id
+            )));
+        }
+
+        let compile = |pattern: &str| {
+            Regex::new(pattern).map_err(|source| PassiveCheckError::Regex {
+                check: definition.id.clone(),
+                source,
+            })
+        };
+        let matchers = definition
+            .matchers
+            .iter()
+            .map(|matcher| {
+                Ok(match matcher {
+                    MatcherDefinition::Header { name, regex } => Matcher::Header {
+                        name: name.clone(),
+                        regex: compile(regex)?,
+                    },
+                    MatcherDefinition::Body { regex } => Matcher::Body(compile(regex)?),
+                })
+            })
+            .collect::<Result<Vec<_>, PassiveCheckError>>()?;
+        let verdict = definition
+            .verdict
+            .as_deref()
+            .map(|source| {
+                verdict_engine().compile(source).map_err(|e| PassiveCheckError::Script {
+                    check: definition.id.clone(),
+                    message: e.to_string(),
+                })
+            })
+            .transpose()?;
+
+        Ok(Self { definition, matchers, verdict })
+    }
+
+    /// Evidence for each matcher and the verdict, if all matchers match
+    /// `response` and the verdict script does not clear it
+    fn evaluate(&self, response: &HttpResponseData, body: &str) -> Option<(Vec<String>, Verdict)> {
+        let evidence: Vec<String> = self.matchers.iter().map(|m| m.find(response, body)).collect::<Option<_>>()?;
+        let Some(script) = &self.verdict else {
+            return Some((evidence, Verdict::Vulnerable));
+        };
+
+        let headers: Map = response
+            .headers
+            .iter()
+            .flat_map(|headers| &headers.headers)
+            .map(|(name, value)| (name.to_ascii_lowercase().into(), value.clone().into()))
+            .collect();
+        let mut scope = Scope::new();
+        scope.push_constant("status", response.status_code as i64);
+        scope.push_constant("headers", headers);
+        scope.push_constant("body", body.to_string());
+        scope.push_constant("evidence", evidence.iter().cloned().map(Dynamic::from).collect::<Array>());
+
+        let verdict = match verdict_engine().eval_ast_with_scope::<Dynamic>(&mut scope, script) {
+            Ok(result) => match Verdict::from_script(result) {
+                Some(verdict) => verdict,
+                None => {
+                    warn!("Verdict script of check '{}' returned no verdict", self.definition.id);
+                    return None;
+                }
+            },
+            Err(e) => {
+                warn!("Verdict script of check '{}' failed: {}", self.definition.id, e);
+                return None;
+            }
+        };
+        (verdict != Verdict::NotVulnerable).then_some((evidence, verdict))
+    }
+}
+
+/// A check that matched a response
+#[derive(Debug, Clone, PartialEq)]
+pub struct PassiveMatch {
+    pub check_id: String,
+    pub check_name: String,
+    pub severity: Severity,
+    pub description: String,
+    pub evidence: String,
+}
+
+/// Id under which content type mismatches are reported
+pub const MIME_MISMATCH_CHECK_ID: &str = "builtin-mime-mismatch";
+
+/// Finding for a response whose body contradicts its Content-Type. Medium
+/// when a browser may sniff it into active content, low otherwise.
+pub fn mime_mismatch_match(response: &HttpResponseData) -> Option<PassiveMatch> {
+    let headers = &response.headers.as_ref()?.headers;
+    let mismatch = crate::mime_sniff::detect_mismatch(headers, &response.body)?;
+    let severity = if mismatch.sniffable && mismatch.is_active_content() {
+        Severity::Medium
+    } else {
+        Severity::Low
+    };
+    let mut description = format!(
+        "The response is declared as {} but its body looks like {}.",
+        mismatch.declared, mismatch.detected
+    );
+    if mismatch.sniffable {
+        description.push_str(" Without X-Content-Type-Options: nosniff, browsers may sniff and render it as such.");
+    }
+
+    Some(PassiveMatch {
+        check_id: MIME_MISMATCH_CHECK_ID.to_string(),
+        check_name: "Content type mismatch".to_string(),
+        severity,
+        description,
+        evidence: format!("Content-Type: {} | detected: {}", mismatch.declared, mismatch.detected),
+    })
+}
+
+/// Checks in a YAML text, one per document (`---` separated)
+pub fn parse_checks(yaml: &str) -> Result<Vec<PassiveCheckDefinition>, PassiveCheckError> {
+    let mut checks = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(yaml) {
+        checks.push(PassiveCheckDefinition::deserialize(document)?);
+    }
+    Ok(checks)
+}
+
+fn is_check_file(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"))
+}
+
+fn truncate_evidence(mut evidence: String) -> String {
+    if evidence.len() > MAX_EVIDENCE_LEN {
+        let mut end = MAX_EVIDENCE_LEN;
+        while !evidence.is_char_boundary(end) {
+            end -= 1;
+        }
+        evidence.truncate(end);
+    }
+    evidence
+}
+
+/// Compiled checks of the active project, run on every stored response
+#[derive(Default)]
+pub struct PassiveScanner {
+    checks: RwLock<Vec<CompiledCheck>>,
+}
+
+impl PassiveScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the checks with those in `dir`. Files that fail to parse or
+    /// compile are skipped with a warning so one bad file does not disable the
+    /// rest; a missing directory means no checks. Returns the number loaded.
+    pub async fn load_dir(&self, dir: &Path) -> Result<usize, PassiveCheckError> {
+        let mut compiled = Vec::new();
+        if dir.exists() {
+            let mut paths: Vec<_> = std::fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| is_check_file(path))
+                .collect();
+            paths.sort();
+
+            for path in paths {
+                let loaded = std::fs::read_to_string(&path)
+                    .map_err(PassiveCheckError::from)
+                    .and_then(|yaml| parse_checks(&yaml))
+                    .and_then(|checks| checks.into_iter().map(CompiledCheck::compile).collect::<Result<Vec<_>, _>>());
+                match loaded {
+                    Ok(checks) => {
+                        for check in checks {
+                            if compiled.iter().any(|c: &CompiledCheck| c.definition.id == check.definition.id) {
+                                warn!("Duplicate passive check '{}' in {} ignored", check.definition.id, path.display());
+                            } else {
+                                compiled.push(check);
+                            }
+                        }
+                    }
+                    Err(e) => warn!("Skipping passive check file {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        let count = compiled.len();
+        *self.checks.write().await = compiled;
+        info!("Loaded {} passive check(s) from {}", count, dir.display());
+        Ok(count)
+    }
+
+    /// Drop all checks (no project loaded)
+    pub async fn clear(&self) {
+        self.checks.write().await.clear();
+    }
+
+    pub async fn list(&self) -> Vec<PassiveCheckDefinition> {
+        self.checks.read().await.iter().map(|c| c.definition.clone()).collect()
+    }
+
+    /// Validate every check in `yaml`, write each to `dir` as `<id>.yaml`
+    /// (replacing a check with the same id) and reload the directory.
+    /// Nothing is written if any check is invalid.
+    pub async fn upload(&self, dir: &Path, yaml: &str) -> Result<Vec<PassiveCheckDefinition>, PassiveCheckError> {
+        let checks = parse_checks(yaml)?
+            .into_iter()
+            .map(CompiledCheck::compile)
+            .collect::<Result<Vec<_>, _>>()?;
+        if checks.is_empty() {
+            return Err(PassiveCheckError::Invalid("no checks in upload".to_string()));
+        }
+
+        std::fs::create_dir_all(dir)?;
+        for check in &checks {
+            let id = &check.definition.id;
+            // A hand-written `<id>.yml` would otherwise load as a duplicate
+            let _ = std::fs::remove_file(dir.join(format!("{}.yml", id)));
+            std::fs::write(dir.join(format!("{}.yaml", id)), serde_yaml::to_string(&check.definition)?)?;
+        }
+        self.load_dir(dir).await?;
+
+        Ok(checks.into_iter().map(|c| c.definition).collect())
+    }
+
+    /// Remove the file of check `id` from `dir` and reload. Returns false if
+    /// there was no such file.
+    pub async fn delete(&self, dir: &Path, id: &str) -> Result<bool, PassiveCheckError> {
+        let mut removed = false;
+        for ext in ["yaml", "yml"] {
+            let path = dir.join(format!("{}.{}", id, ext));
+            if path.exists() {
+                std::fs::remove_file(path)?;
+                removed = true;
+            }
+        }
+        if removed {
+            self.load_dir(dir).await?;
+        }
+        Ok(removed)
+    }
+
+    /// Checks matching `response`
+    pub async fn scan(&self, response: &HttpResponseData) -> Vec<PassiveMatch> {
+        let checks = self.checks.read().await;
+        if checks.is_empty() {
+            return Vec::new();
+        }
+
+        let scanned = &response.body[..response.body.len().min(MAX_SCANNED_BODY)];
+        let body = crate::charset::decode_body_lossy(response.headers.as_ref().map(|h| &h.headers), scanned);
+        checks
+            .iter()
+            .filter_map(|check| {
+                let (evidence, verdict) = check.evaluate(response, &body)?;
+                let (severity, description) = match verdict {
+                    Verdict::NeedsMore => (
+                        Severity::Info,
+                        format!("Needs manual confirmation. {}", check.definition.description).trim_end().to_string(),
+                    ),
+                    _ => (check.definition.severity, check.definition.description.clone()),
+                };
+                Some(PassiveMatch {
+                    check_id: check.definition.id.clone(),
+                    check_name: check.definition.name.clone(),
+                    severity,
+                    description,
+                    evidence: evidence.into_iter().map(truncate_evidence).collect::<Vec<_>>().join(" | "),
+                })
+            })
+            .collect()
+    }
+
+    /// Scan the stored response of `request_id` and save what matched,
+    /// along with the built-in content type mismatch check
+    pub async fn record(&self, db: &Database, request_id: &str, response: &HttpResponseData) {
+        let mut matches = self.scan(response).await;
+        matches.extend(mime_mismatch_match(response));
+        for found in matches {
+            info!("🔎 Passive check '{}' matched {}", found.check_id, request_id);
+            if let Err(e) = db
+                .save_passive_finding(
+                    request_id,
+                    &found.check_id,
+                    &found.check_name,
+                    found.severity.as_str(),
+                    &found.description,
+                    &found.evidence,
+                )
+                .await
+            {
+                warn!("Failed to save passive finding for {}: {}", request_id, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::HttpHeaders;
+    use std::collections::HashMap;
+
+    const CHECKS: &str = r#"
+id: server-version
+name: Server version disclosed
+severity: low
+description: The Server header reveals the software version.
+matchers:
+  - part: header
+    name: server
+    regex: '\d+(\.\d+)+'
+---
+id: java-trace
+name: Java stack trace
+severity: medium
+matchers:
+  - part: header
+    regex: '(?i)^content-type: text/html'
+  - part: body
+    regex: 'at [\w.$]+\(\w+\.java:\d+\)'
+"#;
+
+    fn response(headers: &[(&str, &str)], body: &str) -> HttpResponseData {
+        HttpResponseData {
+            status_code: 500,
+            headers: Some(HttpHeaders {
+                headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<_, _>>(),
+            }),
+            body: body.as_bytes().to_vec().into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_invalid_checks() {
+        let bad_regex = "id: x\nname: X\nseverity: high\nmatchers:\n  - part: body\n    regex: '('\n";
+        let check = parse_checks(bad_regex).unwrap().remove(0);
+        assert!(matches!(CompiledCheck::compile(check), Err(PassiveCheckError::Regex { .. })));
+
+        let bad_id = "id: ../x\nname: X\nseverity: high\nmatchers:\n  - part: body\n    regex: a\n";
+        let check = parse_checks(bad_id).unwrap().remove(0);
+        assert!(matches!(CompiledCheck::compile(check), Err(PassiveCheckError::Invalid(_))));
+
+        assert!(matches!(parse_checks("id: x\nseverity: urgent\n"), Err(PassiveCheckError::Yaml(_))));
+    }
+
+    #[tokio::test]
+    async fn test_upload_scan_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let scanner = PassiveScanner::new();
+
+        let uploaded = scanner.upload(dir.path(), CHECKS).await.unwrap();
+        assert_eq!(uploaded.len(), 2);
+        assert!(dir.path().join("java-trace.yaml").exists());
+        std::fs::write(dir.path().join("broken.yaml"), "not: [a check").unwrap();
+        assert_eq!(scanner.load_dir(dir.path()).await.unwrap(), 2);
+
+        let trace = response(
+            &[("Server", "nginx/1.18.0"), ("Content-Type", "text/html")],
+            "Error\n\tat com.acme.Login.check(Login.java:42)\n",
+        );
+        let found = scanner.scan(&trace).await;
+        let ids: Vec<_> = found.iter().map(|f| f.check_id.as_str()).collect();
+        assert_eq!(ids, vec!["java-trace", "server-version"]);
+        assert_eq!(found[0].severity, Severity::Medium);
+        assert_eq!(found[0].evidence, "Content-Type: text/html | at com.acme.Login.check(Login.java:42)");
+        assert_eq!(found[1].evidence, "1.18.0");
+
+        // Every matcher must match
+        let json_trace = response(&[("Content-Type", "application/json")], "at a.B(C.java:1)");
+        assert!(scanner.scan(&json_trace).await.is_empty());
+
+        assert!(scanner.delete(dir.path(), "server-version").await.unwrap());
+        assert!(!scanner.delete(dir.path(), "server-version").await.unwrap());
+        assert_eq!(scanner.list().await.len(), 1);
+    }
+
+    #[test]
+    fn test_mime_mismatch_match() {
+        let found = mime_mismatch_match(&response(&[("Content-Type", "image/png")], "<html><body>hi</body></html>")).unwrap();
+        assert_eq!(found.check_id, MIME_MISMATCH_CHECK_ID);
+        assert_eq!(found.severity, Severity::Medium);
+        assert_eq!(found.evidence, "Content-Type: image/png | detected: text/html");
+
+        let nosniff = response(&[("Content-Type", "image/png"), ("X-Content-Type-Options", "nosniff")], "<html>");
+        assert_eq!(mime_mismatch_match(&nosniff).unwrap().severity, Severity::Low);
+        assert!(mime_mismatch_match(&response(&[("Content-Type", "text/html")], "<html>")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verdict_scripts() {
+        const SCRIPTED: &str = r#"
+id: debug-page
+name: Debug page
+severity: high
+description: Framework debug output is shown to users.
+matchers:
+  - part: body
+    regex: 'DEBUG = \w+'
+verdict: |
+  if evidence[0] == "DEBUG = False" { "not_vulnerable" }
+  else if status >= 500 && headers["x-powered-by"] != () { "vulnerable" }
+  else { "needs_more" }
+---
+id: script-only
+name: Teapot
+severity: low
+verdict: status == 418
+"#;
+        let dir = tempfile::tempdir().unwrap();
+        let scanner = PassiveScanner::new();
+        scanner.upload(dir.path(), SCRIPTED).await.unwrap();
+
+        let found = scanner.scan(&response(&[("X-Powered-By", "Django")], "DEBUG = True")).await;
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].check_id.as_str(), found[0].severity), ("debug-page", Severity::High));
+
+        let found = scanner.scan(&response(&[], "DEBUG = True")).await;
+        assert_eq!(found[0].severity, Severity::Info);
+        assert!(found[0].description.starts_with("Needs manual confirmation. Framework"));
+
+        assert!(scanner.scan(&response(&[("X-Powered-By", "Django")], "DEBUG = False")).await.is_empty());
+
+        let mut teapot = response(&[], "");
+        teapot.status_code = 418;
+        assert_eq!(scanner.scan(&teapot).await[0].check_id, "script-only");
+
+        let broken = "id: x\nname: X\nseverity: low\nverdict: 'if ('\n";
+        let check = parse_checks(broken).unwrap().remove(0);
+        assert!(matches!(CompiledCheck::compile(check), Err(PassiveCheckError::Script { .. })));
+    }
+
+    #[test]
+    fn test_verdict_values_are_bounded() {
+        for script in ["let s = \"x\"; loop { s += s }", "let a = [0]; loop { a += a }"] {
+            let error = verdict_engine().eval::<Dynamic>(script).unwrap_err();
+            assert!(matches!(*error, rhai::EvalAltResult::ErrorDataTooLarge(..)), "{}: {}", script, error);
+        }
+    }
+}
@@ -239,17 +239,15 @@ impl PerformanceMonitor {
         Ok(())
     }
 
-    /// Start the performance monitoring background tasks
+    /// Start the performance monitoring background tasks. They serve every
+    /// attack of the coordinator, so later calls find them running.
     pub async fn start_monitoring(&self) -> AttackResult<()> {
-        info!("🚀 Starting performance monitoring background tasks");
-        
         // Start event processing task
-        let mut rx = {
-            let mut rx_guard = self.performance_rx.write().await;
-            rx_guard.take().ok_or_else(|| AttackError::InvalidAttackConfig {
-                reason: "Performance monitoring already started".to_string(),
-            })?
+        let Some(mut rx) = self.performance_rx.write().await.take() else {
+            debug!("Performance monitoring already running");
+            return Ok(());
         };
+        info!("🚀 Starting performance monitoring background tasks");
 
         let agent_controls = self.agent_controls.clone();
         let system_metrics = self.system_metrics.clone();
@@ -1,6 +1,6 @@
 use flow_engine::{
     BrowserManager, BrowserOptions, ProxyConfig,
-    FlowStep, SmartSelector, SecretString,
+    FlowStep, SmartSelector, SecretString, MaskingPolicy,
 };
 use proxy_core::CertificateAuthority;
 use std::sync::Arc;
@@ -59,6 +59,8 @@ pub struct RecordingService {
     ca_cert_path: Arc<RwLock<Option<String>>>,
     /// URLs visited during recording (from proxy traffic)
     navigation_history: Arc<RwLock<Vec<NavigationEvent>>>,
+    /// Decides which typed values are tagged sensitive
+    masking: Arc<RwLock<MaskingPolicy>>,
 }
 
 impl RecordingService {
@@ -70,9 +72,15 @@ impl RecordingService {
             session_info: Arc::new(RwLock::new(None)),
             ca_cert_path: Arc::new(RwLock::new(None)),
             navigation_history: Arc::new(RwLock::new(Vec::new())),
+            masking: Arc::new(RwLock::new(MaskingPolicy::default())),
         }
     }
 
+    /// Set the masking policy applied to inputs captured from now on
+    pub async fn set_masking_policy(&self, policy: MaskingPolicy) {
+        *self.masking.write().await = policy;
+    }
+
     /// Get current recording state
     pub async fn get_state(&self) -> RecordingState {
         self.state.read().await.clone()
@@ -370,6 +378,7 @@ impl RecordingService {
         info!("🛑 Stopping recording for profile: {}", profile_id);
 
         let mut recorded_steps = Vec::new();
+        let masking = self.masking.read().await.clone();
 
         if save {
             // Harvest events before closing
@@ -441,7 +450,7 @@ impl RecordingService {
                                                         // Process events
                                                         for (i, event) in result.events.into_iter().enumerate() {
                                                             info!("      📋 Event {}: type={}, xpath={:?}", i, event.event_type, event.xpath);
-                                                            if let Some(step) = self.convert_event_to_step(event) {
+                                                            if let Some(step) = self.convert_event_to_step(event, &masking) {
                                                                 info!("      ✅ Converted to step: {:?}", step);
                                                                 recorded_steps.push(step);
                                                             } else {
@@ -475,7 +484,7 @@ impl RecordingService {
                                                                 info!("   📥 Harvested {} raw events (legacy format) from page {}", events.len(), page_idx);
                                                                 for (i, event) in events.into_iter().enumerate() {
                                                                     info!("      📋 Event {}: type={}, xpath={:?}", i, event.event_type, event.xpath);
-                                                                    if let Some(step) = self.convert_event_to_step(event) {
+                                                                    if let Some(step) = self.convert_event_to_step(event, &masking) {
                                                                         info!("      ✅ Converted to step: {:?}", step);
                                                                         recorded_steps.push(step);
                                                                     } else {
@@ -547,7 +556,7 @@ impl RecordingService {
         }
     }
 
-    fn convert_event_to_step(&self, event: RawEvent) -> Option<FlowStep> {
+    fn convert_event_to_step(&self, event: RawEvent, masking: &MaskingPolicy) -> Option<FlowStep> {
         // Build smart selector using priority-based selection
         let selector = match self.build_smart_selector(&event) {
             Some(s) => s,
@@ -566,9 +575,18 @@ impl RecordingService {
                 })
             },
             "input" => {
-                let is_masked = event.is_password.unwrap_or(false);
+                let is_masked = masking.is_sensitive(
+                    event.is_password.unwrap_or(false),
+                    [&event.name, &event.id, &event.placeholder, &event.aria_label]
+                        .into_iter()
+                        .filter_map(|attr| attr.as_deref()),
+                );
                 let actual_value = event.value.unwrap_or_default();
-                info!("   ⌨️  Converting 'input' event (value len: {}) at {}", actual_value.len(), selector.value);
+                info!(
+                    "   ⌨️  Converting 'input' event ({}) at {}",
+                    if is_masked { "masked".to_string() } else { format!("value len: {}", actual_value.len()) },
+                    selector.value
+                );
                 Some(FlowStep::Type {
                     selector,
                     value: SecretString::new(Box::from(actual_value)),
@@ -1526,4 +1544,19 @@ mod tests {
         let state = RecordingState::default();
         assert_eq!(state, RecordingState::Idle);
     }
+
+    #[test]
+    fn test_raw_event_attributes_for_masking() {
+        let event: RawEvent = serde_json::from_str(
+            r#"{"type":"input","tagName":"input","name":"one_time_code","placeholder":"Enter OTP","value":"123456"}"#,
+        )
+        .unwrap();
+
+        let policy = MaskingPolicy::default();
+        let attrs = [&event.name, &event.id, &event.placeholder, &event.aria_label];
+        assert!(policy.is_sensitive(
+            event.is_password.unwrap_or(false),
+            attrs.into_iter().filter_map(|a| a.as_deref())
+        ));
+    }
 }
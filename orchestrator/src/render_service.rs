@@ -0,0 +1,173 @@
+//! Response Screenshots
+//!
+//! Renders stored HTML responses to PNG through flow-engine's sandboxed
+//! renderer, so the response view can show a visual preview and findings can
+//! carry rendered evidence. Screenshots are kept in the project blob store and
+//! referenced by the row they were rendered from; a later request returns the
+//! stored screenshot unless a fresh render is asked for.
+
+use crate::blob_store::{BlobError, BlobInfo, BlobStore};
+use crate::Database;
+use flow_engine::{FlowEngineError, RenderOptions, ResponseRenderer};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::info;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("No response captured for {0}")]
+    NoResponse(String),
+    #[error("Response is not HTML (content-type: {0})")]
+    NotHtml(String),
+    #[error(transparent)]
+    Render(#[from] FlowEngineError),
+    #[error(transparent)]
+    Blob(#[from] BlobError),
+    #[error("Render database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Stored row whose response is rendered
+#[derive(Debug, Clone)]
+pub enum RenderSource {
+    /// Captured transaction, by request ID
+    Transaction(String),
+    /// Intruder result, by result ID
+    IntruderResult(String),
+}
+
+impl RenderSource {
+    /// Blob reference owner for this row's screenshot
+    fn owner(&self) -> (&'static str, &str) {
+        match self {
+            RenderSource::Transaction(id) => ("transaction_screenshot", id),
+            RenderSource::IntruderResult(id) => ("intruder_result_screenshot", id),
+        }
+    }
+}
+
+pub struct RenderService {
+    db: Arc<Database>,
+    blobs: Arc<BlobStore>,
+    renderer: ResponseRenderer,
+    options: RenderOptions,
+}
+
+impl RenderService {
+    pub fn new(db: Arc<Database>, blobs: Arc<BlobStore>) -> Self {
+        Self {
+            db,
+            blobs,
+            renderer: ResponseRenderer::new(),
+            options: RenderOptions::default(),
+        }
+    }
+
+    /// Screenshot of the source's response, rendered now if none is stored
+    /// or `refresh` is set
+    pub async fn screenshot(&self, source: &RenderSource, refresh: bool) -> Result<BlobInfo, RenderError> {
+        let (owner_kind, owner_id) = source.owner();
+        let existing = self.db.blob_refs_for_owner(owner_kind, owner_id).await?;
+        if !refresh {
+            if let Some(hash) = existing.last() {
+                match self.blobs.info(hash).await {
+                    Ok(info) => return Ok(info),
+                    Err(BlobError::NotFound(_)) => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+
+        let (headers, body) = self.load_response(source).await?;
+        let content_type = header_value(&headers, "content-type").unwrap_or_default();
+        if !is_html(&content_type, &body) {
+            return Err(RenderError::NotHtml(if content_type.is_empty() {
+                "unknown".to_string()
+            } else {
+                content_type
+            }));
+        }
+
+        let png = self
+            .renderer
+            .render(&crate::charset::decode_body_lossy(Some(&headers), &body), &self.options)
+            .await?;
+        let stored = self.blobs.put(&png, Some("image/png")).await?;
+        self.blobs.add_ref(&stored.hash, owner_kind, owner_id).await?;
+        for old in existing.iter().filter(|h| **h != stored.hash) {
+            self.blobs.remove_ref(old, owner_kind, owner_id).await?;
+        }
+
+        info!("📸 Rendered {} {} ({} bytes)", owner_kind, owner_id, png.len());
+        Ok(self.blobs.info(&stored.hash).await?)
+    }
+
+    /// Response headers and body of the source row
+    async fn load_response(&self, source: &RenderSource) -> Result<(HashMap<String, String>, Vec<u8>), RenderError> {
+        match source {
+            RenderSource::Transaction(id) => {
+                let tx = self
+                    .db
+                    .get_full_transaction_by_id(id)
+                    .await?
+                    .ok_or_else(|| RenderError::NotFound(format!("Transaction {}", id)))?;
+                let response = tx.response.ok_or_else(|| RenderError::NoResponse(id.clone()))?;
+                Ok((response.headers.map(|h| h.headers).unwrap_or_default(), response.body.to_vec()))
+            }
+            RenderSource::IntruderResult(id) => {
+                let json = self
+                    .db
+                    .get_intruder_result_response(id)
+                    .await?
+                    .ok_or_else(|| RenderError::NoResponse(id.clone()))?;
+                let response: attack_engine::HttpResponseData = serde_json::from_str(&json)
+                    .map_err(|_| RenderError::NoResponse(id.clone()))?;
+                Ok((response.headers.map(|h| h.headers).unwrap_or_default(), response.body))
+            }
+        }
+    }
+}
+
+fn header_value(headers: &HashMap<String, String>, name: &str) -> Option<String> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.clone())
+}
+
+/// Whether a response should be rendered as a page. Without a content-type
+/// the body is sniffed for an HTML document.
+fn is_html(content_type: &str, body: &[u8]) -> bool {
+    let content_type = content_type.to_ascii_lowercase();
+    if !content_type.is_empty() {
+        return content_type.contains("text/html") || content_type.contains("application/xhtml");
+    }
+
+    let head = String::from_utf8_lossy(&body[..body.len().min(512)]).to_ascii_lowercase();
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    head.starts_with("<!doctype html") || head.starts_with("<html")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_html() {
+        assert!(is_html("text/html; charset=utf-8", b""));
+        assert!(is_html("application/xhtml+xml", b"<html/>"));
+        assert!(!is_html("application/json", b"<html></html>"));
+        assert!(is_html("", b"  <!DOCTYPE html><html></html>"));
+        assert!(is_html("", "\u{feff}<html lang=\"en\">".as_bytes()));
+        assert!(!is_html("", b"{\"html\": true}"));
+    }
+
+    #[test]
+    fn test_header_lookup_ignores_case() {
+        let headers = HashMap::from([("Content-Type".to_string(), "text/html".to_string())]);
+        assert_eq!(header_value(&headers, "content-type").as_deref(), Some("text/html"));
+        assert_eq!(header_value(&headers, "x-missing"), None);
+    }
+}
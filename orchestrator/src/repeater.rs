@@ -5,7 +5,9 @@
 
 use crate::Database;
 use crate::session_manager::AgentRegistry;
+use crate::models::engagement::ActiveTool;
 use crate::session_integration::{SessionManager, SessionApplicationResult, ExpirationHandling, SessionSelectionCriteria, SessionRefreshResult};
+use crate::session_integration::macros::{run_before, MacroTool};
 use attack_engine::{HttpRequestData, HttpResponseData, AttackError, AttackResult};
 use proxy_common::session::Session;
 use serde::{Deserialize, Serialize};
@@ -30,6 +32,38 @@ pub struct RepeaterExecutionRequest {
     pub request_data: HttpRequestData,
     pub target_agent_id: String,
     pub session_id: Option<String>, // Use String instead of Uuid for serialization
+    #[serde(default)]
+    pub options: RepeaterExecutionOptions,
+}
+
+/// Redirects are never followed further than this
+pub const MAX_REDIRECT_HOPS: u32 = 20;
+
+/// How a single repeater execution is sent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepeaterExecutionOptions {
+    /// Follow up to this many redirects (at most [`MAX_REDIRECT_HOPS`]);
+    /// 0 returns the first response
+    pub follow_redirects: u32,
+    /// Send cookies set by the redirects with the following hops
+    pub carry_cookies: bool,
+    /// Headers removed from the request, matched case-insensitively
+    pub strip_headers: Vec<String>,
+    /// Headers set on the request, replacing any value it had
+    pub override_headers: HashMap<String, String>,
+}
+
+/// A redirect passed on the way to the final response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub method: String,
+    pub url: String,
+    pub status_code: i32,
+    /// Absolute URL the redirect pointed at
+    pub location: String,
+    /// `Set-Cookie` values of the response
+    pub set_cookies: Vec<String>,
 }
 
 /// Response from repeater execution
@@ -44,6 +78,9 @@ pub struct RepeaterExecutionResponse {
     pub status_code: Option<i32>,
     pub executed_at: chrono::DateTime<chrono::Utc>,
     pub error: Option<String>,
+    /// Redirects followed before `response_data`, in order
+    #[serde(default)]
+    pub redirect_chain: Vec<RedirectHop>,
 }
 
 /// Repeater tab configuration with validation status
@@ -120,6 +157,154 @@ pub struct ExecutionStatistics {
     pub last_execution: Option<chrono::DateTime<chrono::Utc>>,
 }
 
+/// Apply the header options to `request`
+pub fn apply_header_options(request: &mut HttpRequestData, options: &RepeaterExecutionOptions) {
+    if options.strip_headers.is_empty() && options.override_headers.is_empty() {
+        return;
+    }
+    let headers = &mut request
+        .headers
+        .get_or_insert_with(|| attack_engine::HttpHeaders { headers: HashMap::new() })
+        .headers;
+    let remove = |headers: &mut HashMap<String, String>, name: &str| {
+        headers.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+    };
+    for name in &options.strip_headers {
+        remove(headers, name.trim());
+    }
+    for (name, value) in &options.override_headers {
+        remove(headers, name.trim());
+        headers.insert(name.trim().to_string(), value.clone());
+    }
+}
+
+/// `Set-Cookie` values of a response. Agents report headers as a map, so
+/// several cookies arrive newline separated if at all.
+pub(crate) fn set_cookie_values(response: &HttpResponseData) -> Vec<String> {
+    response
+        .headers
+        .iter()
+        .flat_map(|h| h.headers.iter())
+        .filter(|(name, _)| name.eq_ignore_ascii_case("set-cookie"))
+        .flat_map(|(_, value)| value.lines())
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Cookies collected along a redirect chain, per host
+#[derive(Debug, Default)]
+struct CookieJar {
+    /// (domain, name, value); a leading dot marks a domain cookie
+    cookies: Vec<(String, String, String)>,
+}
+
+impl CookieJar {
+    fn store(&mut self, request_url: &reqwest::Url, set_cookie: &str) {
+        let mut parts = set_cookie.split(';');
+        let Some((name, value)) = parts.next().and_then(|pair| pair.split_once('=')) else {
+            return;
+        };
+        let (name, value) = (name.trim().to_string(), value.trim().to_string());
+        let host = request_url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let mut domain = host.clone();
+        let mut expired = false;
+        for attribute in parts {
+            let (key, val) = attribute.split_once('=').unwrap_or((attribute, ""));
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" => {
+                    let val = val.trim().trim_start_matches('.').to_ascii_lowercase();
+                    // A server may only widen a cookie to a domain it belongs to
+                    if host != val && !host.ends_with(&format!(".{}", val)) {
+                        return;
+                    }
+                    domain = format!(".{}", val);
+                }
+                "max-age" => expired |= val.trim().parse::<i64>().is_ok_and(|age| age <= 0),
+                _ => {}
+            }
+        }
+        self.cookies.retain(|(d, n, _)| !(d == &domain && n == &name));
+        if !expired {
+            self.cookies.push((domain, name, value));
+        }
+    }
+
+    /// `Cookie` header value for `url`, if any cookie applies
+    fn header_for(&self, url: &reqwest::Url) -> Option<String> {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        let pairs: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|(domain, _, _)| match domain.strip_prefix('.') {
+                Some(parent) => host == parent || host.ends_with(domain.as_str()),
+                None => host == *domain,
+            })
+            .map(|(_, name, value)| format!("{}={}", name, value))
+            .collect();
+        (!pairs.is_empty()).then(|| pairs.join("; "))
+    }
+
+    /// Add the cookies for `request`'s URL to its `Cookie` header, replacing
+    /// cookies of the same name
+    fn apply(&self, request: &mut HttpRequestData) {
+        let Some(jar_cookies) = reqwest::Url::parse(&request.url).ok().and_then(|url| self.header_for(&url)) else {
+            return;
+        };
+        let headers = &mut request
+            .headers
+            .get_or_insert_with(|| attack_engine::HttpHeaders { headers: HashMap::new() })
+            .headers;
+        let existing = headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+            .map(|(name, value)| (name.clone(), value.clone()));
+        let (name, value) = match existing {
+            Some((name, value)) => {
+                let replaced: Vec<&str> = jar_cookies.split("; ").filter_map(|pair| pair.split('=').next()).collect();
+                let kept = value
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|pair| !pair.is_empty())
+                    .filter(|pair| !replaced.contains(&pair.split('=').next().unwrap_or_default().trim()));
+                let merged: Vec<&str> = kept.chain(jar_cookies.split("; ")).collect();
+                (name, merged.join("; "))
+            }
+            None => ("Cookie".to_string(), jar_cookies.clone()),
+        };
+        headers.insert(name, value);
+    }
+}
+
+/// Request that follows `previous` to `location` after a `status` redirect.
+/// 303, and 301/302 after a POST, switch to a body-less GET as browsers do;
+/// credentials are not sent to another host.
+fn redirected_request(previous: &HttpRequestData, status: i32, location: &str) -> HttpRequestData {
+    let mut next = previous.clone();
+    next.url = location.to_string();
+    let to_get = status == 303 || (matches!(status, 301 | 302) && previous.method == "POST");
+    let same_host = reqwest::Url::parse(&previous.url).ok().and_then(|u| u.host_str().map(str::to_string))
+        == reqwest::Url::parse(location).ok().and_then(|u| u.host_str().map(str::to_string));
+    if let Some(headers) = next.headers.as_mut() {
+        headers.headers.retain(|name, _| {
+            let name = name.to_ascii_lowercase();
+            !(name == "host"
+                || (!same_host && (name == "authorization" || name == "cookie"))
+                || (to_get && (name == "content-type" || name == "content-length")))
+        });
+    }
+    if to_get && previous.method != "HEAD" {
+        next.method = "GET".to_string();
+        next.body.clear();
+    }
+    next
+}
+
+/// Redirect chain stored with an execution
+fn redirect_chain(json: Option<&str>) -> Vec<RedirectHop> {
+    json.and_then(|json| serde_json::from_str(json).ok()).unwrap_or_default()
+}
+
 /// Main RepeaterManager for handling all repeater operations
 pub struct RepeaterManager {
     database: Arc<Database>,
@@ -367,6 +552,43 @@ impl RepeaterManager {
         }
     }
 
+    /// Agent an execution runs on: the one requested, else the tab's, else
+    /// the project's default agent (or the first online fallback)
+    pub async fn resolve_agent(&self, requested: Option<&str>, tab_id: &str) -> AttackResult<String> {
+        if let Some(agent_id) = requested.filter(|id| !id.is_empty()) {
+            return Ok(agent_id.to_string());
+        }
+        if let Some(agent_id) = self.get_tab(tab_id).await.and_then(|tab| tab.target_agent_id) {
+            return Ok(agent_id);
+        }
+
+        let config = self.database.get_default_agent_config().await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("get_default_agent_config: {}", e),
+        })?;
+        let Some(default) = config.agent_id.clone() else {
+            return Err(AttackError::ValidationError {
+                field: "target_agent_id".to_string(),
+                reason: "No agent selected and no default agent configured".to_string(),
+            });
+        };
+        let online: Vec<String> = self
+            .agent_registry
+            .list_agents()
+            .into_iter()
+            .filter(|agent| agent.status == "Online")
+            .map(|agent| agent.id)
+            .collect();
+        match config.resolve(&online) {
+            Some(agent_id) => {
+                if agent_id != default {
+                    info!("   ↪ Default agent {} is offline, using {}", default, agent_id);
+                }
+                Ok(agent_id)
+            }
+            None => Err(AttackError::AgentUnavailable { agent_id: default }),
+        }
+    }
+
     /// Add or update a session for use in repeater requests
     pub async fn add_session(&self, session: Session) -> AttackResult<()> {
         info!("🔐 Adding session to repeater: {} ({})", session.name, session.id);
@@ -482,24 +704,37 @@ impl RepeaterManager {
             }
         }
 
+        apply_header_options(&mut final_request, &request.options);
+
+        // Session macros fetch what the request needs first, e.g. a CSRF token
+        self.run_session_macros(&mut final_request, &request.target_agent_id).await?;
+
         // Validate the final request
         self.validate_request_template(&final_request)?;
 
+        // Rules of engagement apply no matter how the request was built
+        crate::engagement::authorize_target(&self.database, ActiveTool::Repeater, &final_request.url).await?;
+
         let start_time = std::time::Instant::now();
         let executed_at = chrono::Utc::now();
 
-        // Execute request through agent (placeholder for actual gRPC call)
-        let (response_data, error): (Option<HttpResponseData>, Option<String>) = match self.execute_through_agent(&final_request, &request.target_agent_id).await {
-            Ok(response) => {
+        // Execute request through agent, following redirects if asked to
+        let mut redirect_chain = Vec::new();
+        let (response_data, error): (Option<HttpResponseData>, Option<String>) = match self
+            .execute_following_redirects(&final_request, &request.target_agent_id, &request.options)
+            .await
+        {
+            Ok((response, last_request, chain)) => {
+                redirect_chain = chain;
                 // Check for authentication failure if session was used
                 if let Some(ref session_result) = session_result {
-                    if self.detect_authentication_failure(&response, &final_request.url).await {
+                    if self.detect_authentication_failure(&response, &last_request.url).await {
                         warn!("   🚨 Authentication failure detected in response");
                         
                         // Parse session ID for failure handling
                         if let Some(session_id_str) = &request.session_id {
                             if let Ok(session_id) = Uuid::parse_str(session_id_str) {
-                                match self.handle_authentication_failure(&session_id, &final_request.url, &response).await {
+                                match self.handle_authentication_failure(&session_id, &last_request.url, &response).await {
                                     Ok(refresh_result) => {
                                         if refresh_result.success {
                                             info!("   ✓ Session refreshed successfully");
@@ -524,7 +759,10 @@ impl RepeaterManager {
             }
         };
 
-        let duration_ms = start_time.elapsed().as_millis() as u64;
+        let duration_ms = match response_data.as_ref().and_then(|r| r.timing) {
+            Some(timing) => timing.total_ms(),
+            None => start_time.elapsed().as_millis() as u64,
+        };
         let status_code: Option<i32> = response_data.as_ref().map(|r| r.status_code);
 
         // Serialize request and response for database storage
@@ -556,6 +794,12 @@ impl RepeaterManager {
             .map_err(|e| AttackError::DatabaseError {
                 operation: format!("save_repeater_execution: {}", e),
             })?;
+        if !redirect_chain.is_empty() {
+            let chain_json = serde_json::to_string(&redirect_chain).unwrap_or_default();
+            if let Err(e) = self.database.set_repeater_execution_redirects(&execution_id, Some(&chain_json)).await {
+                warn!("   ⚠ Failed to save redirect chain: {}", e);
+            }
+        }
 
         let response = RepeaterExecutionResponse {
             execution_id,
@@ -567,6 +811,7 @@ impl RepeaterManager {
             status_code,
             executed_at,
             error,
+            redirect_chain,
         };
 
         if response.error.is_none() {
@@ -620,6 +865,7 @@ impl RepeaterManager {
                 executed_at: chrono::DateTime::from_timestamp(execution.executed_at, 0)
                     .unwrap_or_else(chrono::Utc::now),
                 error: None,
+                redirect_chain: redirect_chain(execution.redirect_chain.as_deref()),
             });
         }
 
@@ -664,6 +910,7 @@ impl RepeaterManager {
                 executed_at: chrono::DateTime::from_timestamp(execution.executed_at, 0)
                     .unwrap_or_else(chrono::Utc::now),
                 error: None,
+                redirect_chain: redirect_chain(execution.redirect_chain.as_deref()),
             };
 
             info!("   ✓ Retrieved execution record");
@@ -674,13 +921,89 @@ impl RepeaterManager {
         }
     }
 
+    /// Run the project's Repeater macros for `request` through the agent and
+    /// feed the parameters they extract into it
+    async fn run_session_macros(&self, request: &mut HttpRequestData, agent_id: &str) -> AttackResult<()> {
+        let macros = match self.database.get_session_macros().await {
+            Ok(config) => config.for_request(MacroTool::Repeater, &request.url),
+            Err(e) => {
+                warn!("   ⚠ Failed to load session macros: {}", e);
+                return Ok(());
+            }
+        };
+        if macros.is_empty() {
+            return Ok(());
+        }
+
+        let parameters = run_before(&macros, request, |step| async move {
+            crate::engagement::authorize_target(&self.database, ActiveTool::Repeater, &step.url).await?;
+            self.execute_through_agent(&step, agent_id).await
+        })
+        .await?;
+        info!("   ✓ Ran {} session macro(s), {} parameter(s) extracted", macros.len(), parameters.len());
+        Ok(())
+    }
+
+    /// Send `request` and follow redirects as far as `options` allow. Returns
+    /// the final response, the request that got it and the redirects passed.
+    /// Every hop has to be inside the rules of engagement; the chain stops at
+    /// the first redirect that is not.
+    async fn execute_following_redirects(
+        &self,
+        request: &HttpRequestData,
+        agent_id: &str,
+        options: &RepeaterExecutionOptions,
+    ) -> AttackResult<(HttpResponseData, HttpRequestData, Vec<RedirectHop>)> {
+        let max_hops = options.follow_redirects.min(MAX_REDIRECT_HOPS) as usize;
+        let mut jar = CookieJar::default();
+        let mut chain = Vec::new();
+        let mut current = request.clone();
+
+        loop {
+            let response = self.execute_through_agent(&current, agent_id).await?;
+            if chain.len() >= max_hops {
+                return Ok((response, current, chain));
+            }
+            let headers = response.headers.as_ref().map(|h| h.headers.clone()).unwrap_or_default();
+            let location = match crate::database::chains::redirect_target(response.status_code, &current.url, &headers) {
+                Some(location) if location.starts_with("http://") || location.starts_with("https://") => location,
+                _ => return Ok((response, current, chain)),
+            };
+
+            let set_cookies = set_cookie_values(&response);
+            let mut next = redirected_request(&current, response.status_code, &location);
+            if options.carry_cookies {
+                if let Ok(url) = reqwest::Url::parse(&current.url) {
+                    for cookie in &set_cookies {
+                        jar.store(&url, cookie);
+                    }
+                }
+                jar.apply(&mut next);
+            }
+            if let Err(e) = crate::engagement::authorize_target(&self.database, ActiveTool::Repeater, &next.url).await {
+                warn!("   ⚠ Not following redirect to {}: {}", next.url, e);
+                return Ok((response, current, chain));
+            }
+
+            info!("   ↪ {} redirect to {}", response.status_code, location);
+            chain.push(RedirectHop {
+                method: current.method.clone(),
+                url: current.url.clone(),
+                status_code: response.status_code,
+                location,
+                set_cookies,
+            });
+            current = next;
+        }
+    }
+
     /// Execute request through agent via InterceptCommand channel
-    async fn execute_through_agent(
+    pub(crate) async fn execute_through_agent(
         &self,
         request: &HttpRequestData,
         agent_id: &str,
     ) -> AttackResult<HttpResponseData> {
-        use crate::pb::{InterceptCommand, intercept_command, AttackCommand, attack_command, RepeaterRequest, HttpRequestData as PbHttpRequest, HttpHeaders as PbHttpHeaders, traffic_event};
+        use crate::pb::{InterceptCommand, intercept_command, AttackCommand, attack_command, RepeaterRequest, traffic_event};
 
         info!("📡 Executing request through agent: {}", agent_id);
 
@@ -699,25 +1022,12 @@ impl RepeaterManager {
         // Subscribe to broadcast BEFORE sending command to avoid race condition
         let mut broadcast_rx = self.broadcast_tx.subscribe();
 
-        // Convert HttpRequestData to protobuf format
-        let pb_headers = request.headers.as_ref().map(|h| PbHttpHeaders {
-            headers: h.headers.clone(),
-        });
-
-        let pb_request = PbHttpRequest {
-            method: request.method.clone(),
-            url: request.url.clone(),
-            headers: pb_headers,
-            body: request.body.clone(),
-            tls: None, // TLS details not needed for repeater
-        };
-
         // Build the InterceptCommand with RepeaterRequest
         let cmd = InterceptCommand {
             command: Some(intercept_command::Command::Attack(AttackCommand {
                 command: Some(attack_command::Command::RepeaterRequest(RepeaterRequest {
                     request_id: request_id.clone(),
-                    request: Some(pb_request),
+                    request: Some(request.clone().into()),
                     session_id: String::new(),
                     session_headers: HashMap::new(),
                 })),
@@ -762,17 +1072,7 @@ impl RepeaterManager {
                             info!("   ✓ [REPEATER] Response received! status={}, body_len={} (request_id: {})", 
                                 resp.status_code, resp.body.len(), request_id);
                             
-                            // Convert protobuf response to attack_engine format
-                            let headers = resp.headers.map(|h| attack_engine::HttpHeaders {
-                                headers: h.headers,
-                            });
-
-                            return Ok(HttpResponseData {
-                                status_code: resp.status_code,
-                                headers,
-                                body: resp.body,
-                                tls: None,
-                            });
+                            return Ok(HttpResponseData::from(resp));
                         } else {
                             warn!("   ⚠ [REPEATER] Event matched but was not a Response type!");
                         }
@@ -1021,6 +1321,16 @@ impl RepeaterManager {
                 ],
                 is_retryable: false,
             },
+            AttackError::SecurityViolation { details, .. } => ErrorDetails {
+                error_type: "Blocked by Rules of Engagement".to_string(),
+                message: details.clone(),
+                remediation: vec![
+                    "Check the target host against the engagement allowlist".to_string(),
+                    "Check the engagement schedule for the current testing window".to_string(),
+                    "Ask a project Owner for a time-limited override if the test requires it".to_string(),
+                ],
+                is_retryable: false,
+            },
             _ => ErrorDetails {
                 error_type: "Unknown Error".to_string(),
                 message: error.to_string(),
@@ -1125,7 +1435,7 @@ mod tests {
     async fn test_validate_request_template() {
         let db = Arc::new(Database::new("test").await.unwrap());
         let registry = Arc::new(AgentRegistry::new());
-        let manager = RepeaterManager::new(db, registry);
+        let manager = RepeaterManager::new(db, registry, tokio::sync::broadcast::channel(16).0);
 
         // Valid request
         let valid_request = create_test_request();
@@ -1150,7 +1460,7 @@ mod tests {
     async fn test_session_management() {
         let db = Arc::new(Database::new("test").await.unwrap());
         let registry = Arc::new(AgentRegistry::new());
-        let manager = RepeaterManager::new(db, registry);
+        let manager = RepeaterManager::new(db, registry, tokio::sync::broadcast::channel(16).0);
 
         // Create test session
         let session = Session::new("Test Session".to_string(), None);
@@ -1173,7 +1483,7 @@ mod tests {
     async fn test_apply_session_to_request() {
         let db = Arc::new(Database::new("test").await.unwrap());
         let registry = Arc::new(AgentRegistry::new());
-        let manager = RepeaterManager::new(db, registry);
+        let manager = RepeaterManager::new(db, registry, tokio::sync::broadcast::channel(16).0);
 
         // Create session with headers
         let mut session = Session::new("Test Session".to_string(), None);
@@ -1196,4 +1506,70 @@ mod tests {
             Some(&"Bearer token123".to_string())
         );
     }
+
+    #[test]
+    fn test_header_options() {
+        let mut request = create_test_request();
+        let headers = &mut request.headers.as_mut().unwrap().headers;
+        headers.insert("User-Agent".to_string(), "curl".to_string());
+        headers.insert("X-Debug".to_string(), "1".to_string());
+
+        let options = RepeaterExecutionOptions {
+            strip_headers: vec!["x-debug".to_string()],
+            override_headers: HashMap::from([("user-agent".to_string(), "proxxy".to_string())]),
+            ..Default::default()
+        };
+        apply_header_options(&mut request, &options);
+        let headers = request.headers.unwrap().headers;
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.get("user-agent"), Some(&"proxxy".to_string()));
+    }
+
+    #[test]
+    fn test_redirected_request() {
+        let mut post = create_test_request();
+        post.method = "POST".to_string();
+        post.body = b"a=1".to_vec();
+        let headers = &mut post.headers.as_mut().unwrap().headers;
+        headers.insert("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string());
+        headers.insert("Authorization".to_string(), "Bearer t".to_string());
+
+        // 302 after a POST continues as a GET without the body
+        let next = redirected_request(&post, 302, "https://example.com/done");
+        assert_eq!(next.method, "GET");
+        assert!(next.body.is_empty());
+        let headers = &next.headers.as_ref().unwrap().headers;
+        assert!(!headers.contains_key("Content-Type"));
+        assert!(headers.contains_key("Authorization"));
+
+        // 307 keeps the method and body; credentials stay on their host
+        let next = redirected_request(&post, 307, "https://other.test/upload");
+        assert_eq!(next.method, "POST");
+        assert_eq!(next.body, b"a=1");
+        assert!(!next.headers.unwrap().headers.contains_key("Authorization"));
+    }
+
+    #[test]
+    fn test_cookie_jar() {
+        let login = reqwest::Url::parse("https://app.example.com/login").unwrap();
+        let mut jar = CookieJar::default();
+        jar.store(&login, "session=abc; Path=/; HttpOnly");
+        jar.store(&login, "wide=1; Domain=example.com");
+        jar.store(&login, "foreign=1; Domain=other.test");
+        jar.store(&login, "gone=1");
+        jar.store(&login, "gone=; Max-Age=0");
+
+        let mut next = create_test_request();
+        next.url = "https://app.example.com/home".to_string();
+        next.headers.as_mut().unwrap().headers.insert("Cookie".to_string(), "session=old; theme=dark".to_string());
+        jar.apply(&mut next);
+        assert_eq!(
+            next.headers.unwrap().headers.get("Cookie"),
+            Some(&"theme=dark; session=abc; wide=1".to_string())
+        );
+
+        // Host cookies stay on their host, domain cookies cover subdomains
+        let api = reqwest::Url::parse("https://api.example.com/").unwrap();
+        assert_eq!(jar.header_for(&api), Some("wide=1".to_string()));
+    }
 }
\ No newline at end of file
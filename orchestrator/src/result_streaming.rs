@@ -332,15 +332,15 @@ impl ResultStreamingManager {
             executed_at: chrono::DateTime::from_timestamp(result.executed_at, 0)
                 .unwrap_or_else(chrono::Utc::now),
             payload_values,
-            is_highlighted: false,
-            highlight_reasons: Vec::new(),
+            is_highlighted: result.is_highlighted,
+            highlight_reasons: result.highlight_reason.iter().cloned().collect(),
         };
 
-        // Apply highlighting rules
+        // Apply highlighting rules on top of the attack's own
         if let Some(response_data) = response_data {
             let highlighting = self.apply_highlighting_rules(&streamed_result, response_data).await;
-            streamed_result.is_highlighted = highlighting.is_highlighted;
-            streamed_result.highlight_reasons = highlighting.reasons;
+            streamed_result.is_highlighted |= highlighting.is_highlighted;
+            streamed_result.highlight_reasons.extend(highlighting.reasons);
         }
 
         // Update statistics
@@ -991,6 +991,8 @@ mod tests {
             }),
             body: b"Test response body".to_vec(),
             tls: None,
+            timing: None,
+            protocol: None,
         }
     }
 
@@ -0,0 +1,668 @@
+//! Sequencer - Token Randomness Analysis
+//!
+//! Sends the same request through an agent until it has collected the
+//! requested number of tokens (session IDs, CSRF tokens, reset codes) from
+//! the responses, then tests how predictable they are:
+//!
+//! - Character level: per position, the distribution of characters is
+//!   compared with a uniform one over the characters seen there
+//!   (chi-square), and its Shannon entropy is measured.
+//! - Bit level: each character is turned into the bits of its index in the
+//!   position's character set, and each bit position gets a monobit test.
+//!   Only the `floor(log2(k))` low bits of a `k`-character set are used, and
+//!   samples whose index does not fit them are skipped, so set sizes that
+//!   are not a power of two do not look biased.
+//! - FIPS 140-2: the usable bits of every token, concatenated in order, are
+//!   cut into 20,000-bit blocks and each block gets the monobit, poker, runs
+//!   and long runs tests of the standard.
+//!
+//! Bit positions passing at the 1% significance level add up to the
+//! effective entropy of the token. Sequencer requests count as Repeater
+//! traffic for the rules of engagement.
+
+use crate::models::engagement::ActiveTool;
+use crate::repeater::RepeaterManager;
+use crate::Database;
+use attack_engine::{AttackError, AttackResult, HttpRequestData, HttpResponseData};
+use dashmap::DashMap;
+use regex::Regex;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Fewest tokens the analysis accepts
+pub const MIN_SAMPLES: usize = 20;
+
+/// Most tokens a run collects
+pub const MAX_SAMPLES: usize = 20_000;
+
+/// A test fails below this p-value
+const SIGNIFICANCE: f64 = 0.01;
+
+/// Bits per FIPS 140-2 test block
+pub const FIPS_BLOCK_BITS: usize = 20_000;
+
+/// Responses without a token tolerated in a row before a run gives up
+const MAX_CONSECUTIVE_MISSES: usize = 20;
+
+/// What to send and what to collect
+#[derive(Debug, Clone)]
+pub struct SequencerConfig {
+    pub request: HttpRequestData,
+    pub agent_id: String,
+    /// Applied to the response headers (`Name: value` lines) followed by
+    /// the body; the first capture group is the token, else the whole match
+    pub extraction: String,
+    pub capture_count: usize,
+}
+
+impl SequencerConfig {
+    pub fn validate(&self) -> Result<Regex, String> {
+        if !(MIN_SAMPLES..=MAX_SAMPLES).contains(&self.capture_count) {
+            return Err(format!("capture_count must be between {} and {}", MIN_SAMPLES, MAX_SAMPLES));
+        }
+        if self.request.url.trim().is_empty() {
+            return Err("request URL must be set".to_string());
+        }
+        Regex::new(&self.extraction).map_err(|e| format!("invalid extraction regex: {}", e))
+    }
+}
+
+/// Token in `response`, if `extraction` matches
+pub fn extract_token(extraction: &Regex, response: &HttpResponseData) -> Option<String> {
+    let mut text = String::new();
+    if let Some(headers) = &response.headers {
+        for (name, value) in &headers.headers {
+            text.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    text.push_str("\r\n");
+    text.push_str(&String::from_utf8_lossy(&response.body));
+
+    let captures = extraction.captures(&text)?;
+    let token = captures.get(1).or_else(|| captures.get(0))?.as_str();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequencerStatus {
+    Collecting,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// A collection run and, once it has finished, its report
+#[derive(Debug, Clone)]
+pub struct SequencerRun {
+    pub id: String,
+    pub status: SequencerStatus,
+    pub requested: usize,
+    pub tokens: Vec<String>,
+    /// Requests that failed or had no token
+    pub misses: usize,
+    pub error: Option<String>,
+    pub report: Option<RandomnessReport>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Collection runs of the orchestrator, kept in memory
+pub struct Sequencer {
+    database: Arc<Database>,
+    repeater: Arc<RepeaterManager>,
+    runs: Arc<DashMap<String, SequencerRun>>,
+    cancellations: Arc<DashMap<String, Arc<AtomicBool>>>,
+}
+
+impl Sequencer {
+    pub fn new(database: Arc<Database>, repeater: Arc<RepeaterManager>) -> Self {
+        Self {
+            database,
+            repeater,
+            runs: Arc::new(DashMap::new()),
+            cancellations: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Start collecting in the background; returns the run ID
+    pub async fn start(&self, config: SequencerConfig) -> AttackResult<String> {
+        let extraction = config.validate().map_err(|reason| AttackError::ValidationError {
+            field: "sequencer".to_string(),
+            reason,
+        })?;
+        crate::engagement::authorize_target(&self.database, ActiveTool::Repeater, &config.request.url).await?;
+        self.repeater.validate_agent_availability(&config.agent_id).await?;
+
+        let id = Uuid::new_v4().to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancellations.insert(id.clone(), cancelled.clone());
+        self.runs.insert(
+            id.clone(),
+            SequencerRun {
+                id: id.clone(),
+                status: SequencerStatus::Collecting,
+                requested: config.capture_count,
+                tokens: Vec::with_capacity(config.capture_count),
+                misses: 0,
+                error: None,
+                report: None,
+                started_at: chrono::Utc::now(),
+            },
+        );
+        info!("🎲 Sequencer run {} collecting {} tokens from {}", id, config.capture_count, config.request.url);
+
+        let (runs, repeater, cancellations, run_id) =
+            (self.runs.clone(), self.repeater.clone(), self.cancellations.clone(), id.clone());
+        tokio::spawn(async move {
+            let mut consecutive_misses = 0;
+            let outcome = loop {
+                if cancelled.load(Ordering::Relaxed) {
+                    break Ok(SequencerStatus::Cancelled);
+                }
+                let collected = runs.get(&run_id).map(|run| run.tokens.len()).unwrap_or_default();
+                if collected >= config.capture_count {
+                    break Ok(SequencerStatus::Completed);
+                }
+                if consecutive_misses >= MAX_CONSECUTIVE_MISSES {
+                    break Err(format!("{} responses in a row had no token", MAX_CONSECUTIVE_MISSES));
+                }
+
+                let token = match repeater.execute_through_agent(&config.request, &config.agent_id).await {
+                    Ok(response) => extract_token(&extraction, &response),
+                    Err(AttackError::AgentUnavailable { agent_id }) => {
+                        break Err(format!("Agent {} is unavailable", agent_id));
+                    }
+                    Err(e) => {
+                        warn!("Sequencer run {}: request failed: {}", run_id, e);
+                        None
+                    }
+                };
+                if let Some(mut run) = runs.get_mut(&run_id) {
+                    match token {
+                        Some(token) => {
+                            consecutive_misses = 0;
+                            run.tokens.push(token);
+                        }
+                        None => {
+                            consecutive_misses += 1;
+                            run.misses += 1;
+                        }
+                    }
+                }
+            };
+
+            cancellations.remove(&run_id);
+            if let Some(mut run) = runs.get_mut(&run_id) {
+                match outcome {
+                    Ok(status) => {
+                        run.status = status;
+                        // A stopped run is still analysed if it has enough tokens
+                        if let Ok(report) = analyze(&run.tokens) {
+                            run.report = Some(report);
+                        }
+                    }
+                    Err(error) => {
+                        run.status = SequencerStatus::Failed;
+                        run.error = Some(error);
+                    }
+                }
+                info!("🎲 Sequencer run {} {:?} with {} tokens", run_id, run.status, run.tokens.len());
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// Stop collecting; returns false if the run is not collecting
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.cancellations.get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<SequencerRun> {
+        self.runs.get(id).map(|run| run.clone())
+    }
+
+    /// Every run, newest first
+    pub fn list(&self) -> Vec<SequencerRun> {
+        let mut runs: Vec<SequencerRun> = self.runs.iter().map(|run| run.clone()).collect();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.started_at));
+        runs
+    }
+
+    pub fn delete(&self, id: &str) -> bool {
+        self.cancel(id);
+        self.runs.remove(id).is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RandomnessQuality {
+    ExtremelyPoor,
+    Poor,
+    Reasonable,
+    Good,
+    Excellent,
+}
+
+impl RandomnessQuality {
+    fn from_effective_bits(bits: usize) -> Self {
+        match bits {
+            100.. => Self::Excellent,
+            64..=99 => Self::Good,
+            40..=63 => Self::Reasonable,
+            20..=39 => Self::Poor,
+            _ => Self::ExtremelyPoor,
+        }
+    }
+}
+
+/// Character-level results of one token position
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharacterPosition {
+    pub position: usize,
+    /// Distinct characters seen at the position
+    pub distinct_characters: usize,
+    pub entropy_bits: f64,
+    pub chi_square: f64,
+    pub p_value: f64,
+    pub passed: bool,
+}
+
+/// Monobit result of one bit of one token position
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitPosition {
+    /// Character position of the bit
+    pub character: usize,
+    /// Bit within the character's index, least significant first
+    pub bit: usize,
+    pub ones_ratio: f64,
+    pub p_value: f64,
+    pub passed: bool,
+}
+
+/// A test of FIPS 140-2 (section 4.9.1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FipsTest {
+    /// Ones in a block
+    Monobit,
+    /// Spread of the block's 4-bit values
+    Poker,
+    /// Runs of each length up to 6 and over
+    Runs,
+    /// No run of 26 bits or more
+    LongRuns,
+}
+
+/// Outcome of one FIPS test over every block
+#[derive(Debug, Clone, PartialEq)]
+pub struct FipsTestResult {
+    pub test: FipsTest,
+    pub blocks: usize,
+    pub blocks_passed: usize,
+}
+
+impl FipsTestResult {
+    /// Every block passed; false when there was no full block
+    pub fn passed(&self) -> bool {
+        self.blocks > 0 && self.blocks_passed == self.blocks
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RandomnessReport {
+    pub sample_size: usize,
+    pub distinct_tokens: usize,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Every character seen, sorted
+    pub character_set: String,
+    /// Shannon entropy summed over the analysed positions
+    pub shannon_entropy_bits: f64,
+    /// Bit positions that passed
+    pub effective_entropy_bits: usize,
+    pub quality: RandomnessQuality,
+    pub character_positions: Vec<CharacterPosition>,
+    pub bit_positions: Vec<BitPosition>,
+    /// Usable bits of all tokens, the input of the FIPS tests
+    pub bit_stream_length: usize,
+    pub fips_tests: Vec<FipsTestResult>,
+    /// Things worth reading before the numbers
+    pub warnings: Vec<String>,
+}
+
+/// Analyse `tokens`. Positions past the shortest token are left out.
+pub fn analyze(tokens: &[String]) -> Result<RandomnessReport, String> {
+    if tokens.len() < MIN_SAMPLES {
+        return Err(format!("at least {} tokens are needed, got {}", MIN_SAMPLES, tokens.len()));
+    }
+    let samples: Vec<Vec<char>> = tokens.iter().map(|t| t.chars().collect()).collect();
+    let min_length = samples.iter().map(Vec::len).min().unwrap_or_default();
+    let max_length = samples.iter().map(Vec::len).max().unwrap_or_default();
+    let distinct_tokens = tokens.iter().collect::<HashSet<_>>().len();
+    let character_set: String = samples
+        .iter()
+        .flatten()
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut character_positions = Vec::with_capacity(min_length);
+    let mut bit_positions = Vec::new();
+    let mut encodings = Vec::with_capacity(min_length);
+    for position in 0..min_length {
+        let mut counts: BTreeMap<char, usize> = BTreeMap::new();
+        for sample in &samples {
+            *counts.entry(sample[position]).or_default() += 1;
+        }
+        character_positions.push(character_position(position, &counts, samples.len()));
+
+        // Bits of each character's index in the position's (sorted) set
+        let index: BTreeMap<char, usize> = counts.keys().enumerate().map(|(i, c)| (*c, i)).collect();
+        let bits = usize::BITS as usize - 1 - counts.len().leading_zeros() as usize;
+        let usable: Vec<usize> = samples
+            .iter()
+            .map(|sample| index[&sample[position]])
+            .filter(|i| *i < 1 << bits)
+            .collect();
+        for bit in 0..bits {
+            let ones = usable.iter().filter(|i| *i >> bit & 1 == 1).count();
+            let p_value = monobit_p_value(ones, usable.len());
+            bit_positions.push(BitPosition {
+                character: position,
+                bit,
+                ones_ratio: ones as f64 / usable.len() as f64,
+                p_value,
+                passed: p_value >= SIGNIFICANCE,
+            });
+        }
+        encodings.push((index, bits));
+    }
+
+    let mut stream = Vec::new();
+    for sample in &samples {
+        for (position, (index, bits)) in encodings.iter().enumerate() {
+            let value = index[&sample[position]];
+            if value < 1 << bits {
+                stream.extend((0..*bits).map(|bit| value >> bit & 1 == 1));
+            }
+        }
+    }
+    let fips_tests = fips_tests(&stream);
+
+    let effective_entropy_bits = bit_positions.iter().filter(|b| b.passed).count();
+    let mut warnings = Vec::new();
+    if distinct_tokens < tokens.len() {
+        warnings.push(format!("{} token(s) repeat an earlier one", tokens.len() - distinct_tokens));
+    }
+    if min_length != max_length {
+        warnings.push(format!(
+            "Token lengths vary ({} to {}); only the first {} characters are analysed",
+            min_length, max_length, min_length
+        ));
+    }
+    if tokens.len() < 100 {
+        warnings.push("Fewer than 100 tokens; results are indicative only".to_string());
+    }
+    let constant = character_positions.iter().filter(|p| p.distinct_characters == 1).count();
+    if constant > 0 {
+        warnings.push(format!("{} position(s) never change", constant));
+    }
+    if stream.len() < FIPS_BLOCK_BITS {
+        warnings.push(format!(
+            "Only {} usable bits; the FIPS tests need blocks of {}",
+            stream.len(),
+            FIPS_BLOCK_BITS
+        ));
+    }
+    let failed: Vec<String> = fips_tests
+        .iter()
+        .filter(|result| result.blocks > 0 && !result.passed())
+        .map(|result| format!("{:?}", result.test))
+        .collect();
+    if !failed.is_empty() {
+        warnings.push(format!("FIPS 140-2 tests failed: {}", failed.join(", ")));
+    }
+
+    Ok(RandomnessReport {
+        sample_size: tokens.len(),
+        distinct_tokens,
+        min_length,
+        max_length,
+        character_set,
+        shannon_entropy_bits: character_positions.iter().map(|p| p.entropy_bits).sum(),
+        effective_entropy_bits,
+        quality: RandomnessQuality::from_effective_bits(effective_entropy_bits),
+        character_positions,
+        bit_positions,
+        bit_stream_length: stream.len(),
+        fips_tests,
+        warnings,
+    })
+}
+
+/// Run the FIPS 140-2 tests on each full block of `stream`
+fn fips_tests(stream: &[bool]) -> Vec<FipsTestResult> {
+    let tests = [FipsTest::Monobit, FipsTest::Poker, FipsTest::Runs, FipsTest::LongRuns];
+    let blocks: Vec<&[bool]> = stream.chunks_exact(FIPS_BLOCK_BITS).collect();
+    tests
+        .into_iter()
+        .map(|test| FipsTestResult {
+            test,
+            blocks: blocks.len(),
+            blocks_passed: blocks.iter().filter(|block| fips_block_passes(test, block)).count(),
+        })
+        .collect()
+}
+
+/// Acceptance intervals of FIPS 140-2 for a 20,000-bit block
+fn fips_block_passes(test: FipsTest, block: &[bool]) -> bool {
+    match test {
+        FipsTest::Monobit => {
+            let ones = block.iter().filter(|bit| **bit).count();
+            9725 < ones && ones < 10275
+        }
+        FipsTest::Poker => {
+            let mut counts = [0f64; 16];
+            for nibble in block.chunks_exact(4) {
+                let value = nibble.iter().fold(0, |value, bit| value << 1 | usize::from(*bit));
+                counts[value] += 1.0;
+            }
+            let segments = (block.len() / 4) as f64;
+            let x = 16.0 / segments * counts.iter().map(|f| f * f).sum::<f64>() - segments;
+            2.16 < x && x < 46.17
+        }
+        FipsTest::Runs => {
+            const INTERVALS: [(usize, usize); 6] =
+                [(2315, 2685), (1114, 1386), (527, 723), (240, 384), (103, 209), (103, 209)];
+            let mut counts = [[0usize; 6]; 2];
+            for (value, length) in runs(block) {
+                counts[usize::from(value)][length.min(6) - 1] += 1;
+            }
+            counts.iter().all(|per_length| {
+                per_length
+                    .iter()
+                    .zip(INTERVALS)
+                    .all(|(count, (low, high))| (low..=high).contains(count))
+            })
+        }
+        FipsTest::LongRuns => runs(block).all(|(_, length)| length < 26),
+    }
+}
+
+/// Maximal runs of equal bits as (bit, length)
+fn runs(bits: &[bool]) -> impl Iterator<Item = (bool, usize)> + '_ {
+    bits.chunk_by(|a, b| a == b).map(|run| (run[0], run.len()))
+}
+
+fn character_position(position: usize, counts: &BTreeMap<char, usize>, total: usize) -> CharacterPosition {
+    let n = total as f64;
+    let entropy_bits = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / n;
+            -p * p.log2()
+        })
+        .sum::<f64>();
+
+    // A position that never changes has nothing to test
+    let (chi_square, p_value) = if counts.len() < 2 {
+        (0.0, 0.0)
+    } else {
+        let expected = n / counts.len() as f64;
+        let chi_square = counts.values().map(|&count| (count as f64 - expected).powi(2) / expected).sum::<f64>();
+        (chi_square, chi_square_p_value(chi_square, counts.len() - 1))
+    };
+    CharacterPosition {
+        position,
+        distinct_characters: counts.len(),
+        entropy_bits,
+        chi_square,
+        p_value,
+        passed: p_value >= SIGNIFICANCE,
+    }
+}
+
+/// Two-sided p-value of `ones` in `n` fair bits (normal approximation)
+fn monobit_p_value(ones: usize, n: usize) -> f64 {
+    if n == 0 {
+        return 0.0;
+    }
+    let z = (2.0 * ones as f64 - n as f64).abs() / (n as f64).sqrt();
+    erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// Upper-tail p-value of a chi-square statistic (Wilson-Hilferty)
+fn chi_square_p_value(chi_square: f64, degrees_of_freedom: usize) -> f64 {
+    let k = degrees_of_freedom as f64;
+    let spread = 2.0 / (9.0 * k);
+    let z = ((chi_square / k).cbrt() - (1.0 - spread)) / spread.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+/// Complementary error function, fractional error below 1.2e-7
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.5 * x.abs());
+    let poly = -1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98 + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let value = t * (-x * x + poly).exp();
+    if x >= 0.0 {
+        value
+    } else {
+        2.0 - value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attack_engine::HttpHeaders;
+    use std::collections::HashMap;
+
+    /// Deterministic xorshift tokens, `length` hex characters each
+    fn random_tokens(count: usize, length: usize) -> Vec<String> {
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        (0..count)
+            .map(|_| {
+                (0..length)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        char::from_digit((state >> 60) as u32, 16).unwrap()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_random_tokens_pass() {
+        let report = analyze(&random_tokens(2000, 32)).unwrap();
+        assert_eq!(report.distinct_tokens, 2000);
+        assert_eq!(report.bit_positions.len(), 128);
+        assert!(report.effective_entropy_bits >= 120, "{}", report.effective_entropy_bits);
+        assert_eq!(report.quality, RandomnessQuality::Excellent);
+        assert!((report.shannon_entropy_bits - 128.0).abs() < 2.0);
+        assert_eq!(report.bit_stream_length, 2000 * 128);
+        assert_eq!(report.fips_tests.len(), 4);
+        assert!(report.fips_tests.iter().all(|t| t.blocks == 12 && t.passed()), "{:?}", report.fips_tests);
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+    }
+
+    #[test]
+    fn test_sequential_tokens_fail() {
+        let tokens: Vec<String> = (0..500).map(|i| format!("SESS{:08x}", 0x1000_0000 + i * 7)).collect();
+        let report = analyze(&tokens).unwrap();
+        assert!(report.effective_entropy_bits < 20, "{}", report.effective_entropy_bits);
+        assert_eq!(report.quality, RandomnessQuality::ExtremelyPoor);
+        assert!(report.warnings.iter().any(|w| w.contains("never change")));
+        assert!(report.fips_tests.iter().all(|t| t.blocks == 0 && !t.passed()));
+
+        assert!(analyze(&tokens[..10]).is_err());
+    }
+
+    #[test]
+    fn test_extract_token_and_statistics() {
+        let response = HttpResponseData {
+            status_code: 200,
+            headers: Some(HttpHeaders {
+                headers: HashMap::from([("Set-Cookie".to_string(), "sid=abc123; Path=/".to_string())]),
+            }),
+            body: b"<input name=\"csrf\" value=\"tok-9\">".to_vec(),
+            tls: None,
+            timing: None,
+            protocol: None,
+        };
+        let cookie = Regex::new(r"Set-Cookie: sid=([^;\r\n]+)").unwrap();
+        assert_eq!(extract_token(&cookie, &response).as_deref(), Some("abc123"));
+        let csrf = Regex::new(r#"tok-\d"#).unwrap();
+        assert_eq!(extract_token(&csrf, &response).as_deref(), Some("tok-9"));
+        assert_eq!(extract_token(&Regex::new("missing").unwrap(), &response), None);
+
+        assert!((erfc(0.0) - 1.0).abs() < 1e-6);
+        assert!((erfc(1.0) - 0.157_299_2).abs() < 1e-6);
+        assert!((monobit_p_value(50, 100) - 1.0).abs() < 1e-6);
+        // chi-square 3.84 with one degree of freedom is the 5% point
+        assert!((chi_square_p_value(3.841, 1) - 0.05).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fips_blocks() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let random: Vec<bool> = (0..FIPS_BLOCK_BITS)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state >> 63 == 1
+            })
+            .collect();
+        for test in [FipsTest::Monobit, FipsTest::Poker, FipsTest::Runs, FipsTest::LongRuns] {
+            assert!(fips_block_passes(test, &random), "{:?}", test);
+        }
+
+        // Balanced, but far too regular
+        let alternating: Vec<bool> = (0..FIPS_BLOCK_BITS).map(|i| i % 2 == 0).collect();
+        assert!(fips_block_passes(FipsTest::Monobit, &alternating));
+        assert!(!fips_block_passes(FipsTest::Poker, &alternating));
+        assert!(!fips_block_passes(FipsTest::Runs, &alternating));
+
+        let mut stuck = random.clone();
+        stuck[100..130].iter_mut().for_each(|bit| *bit = true);
+        assert!(!fips_block_passes(FipsTest::LongRuns, &stuck));
+        assert_eq!(fips_tests(&random[..FIPS_BLOCK_BITS - 1])[0].blocks, 0);
+    }
+}
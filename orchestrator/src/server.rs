@@ -4,6 +4,9 @@ use crate::pb::{
     SystemMetricsEvent, TrafficEvent, traffic_event, HeartbeatRequest, HeartbeatResponse,
 };
 use crate::AgentRegistry;
+use crate::interception::InterceptQueue;
+use crate::hook_scripts::HookScriptStore;
+use crate::passive_checks::PassiveScanner;
 use crate::models::settings::InterceptionConfig;
 use crate::recording_service::RecordingService;
 use std::sync::Arc;
@@ -13,6 +16,7 @@ use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status, Streaming};
 use tracing::{debug, error, info, warn};
 
+use crate::agent_tls::AgentTlsService;
 use crate::Database;
 
 use proxy_core::CertificateAuthority;
@@ -23,10 +27,19 @@ pub struct ProxyServiceImpl {
     metrics_broadcast_tx: broadcast::Sender<SystemMetricsEvent>,
     db: Arc<Database>,
     ca: Arc<CertificateAuthority>,
-    #[allow(dead_code)] // Reserved for future interception implementation
+    /// Rules pushed to each agent when it connects
     interception: Arc<RwLock<InterceptionConfig>>,
+    /// Requests held by agents for pause-and-edit
+    intercepts: Arc<InterceptQueue>,
+    /// Custom passive checks run on every stored response
+    passive_scanner: Arc<PassiveScanner>,
+    /// Hook scripts pushed to each agent when it connects
+    hook_scripts: Arc<HookScriptStore>,
     /// Recording service for traffic-based navigation detection
     recording_service: Arc<RecordingService>,
+    /// Set when the channel runs over mutual TLS: agents must present an
+    /// enrolled client certificate
+    agent_tls: Option<Arc<AgentTlsService>>,
 }
 
 impl ProxyServiceImpl {
@@ -37,6 +50,9 @@ impl ProxyServiceImpl {
         db: Arc<Database>,
         ca: Arc<CertificateAuthority>,
         interception: Arc<RwLock<InterceptionConfig>>,
+        intercepts: Arc<InterceptQueue>,
+        passive_scanner: Arc<PassiveScanner>,
+        hook_scripts: Arc<HookScriptStore>,
         recording_service: Arc<RecordingService>,
     ) -> Self {
         Self {
@@ -46,7 +62,46 @@ impl ProxyServiceImpl {
             db,
             ca,
             interception,
+            intercepts,
+            passive_scanner,
+            hook_scripts,
             recording_service,
+            agent_tls: None,
+        }
+    }
+
+    /// Require an enrolled client certificate on every agent call
+    pub fn with_agent_tls(mut self, agent_tls: Arc<AgentTlsService>) -> Self {
+        self.agent_tls = Some(agent_tls);
+        self
+    }
+
+    /// Check the caller's client certificate when mutual TLS is on. Returns
+    /// the enrolled agent name. The certificates are read before the first
+    /// await, since streaming requests cannot be held across one.
+    fn authorize_agent<T>(
+        &self,
+        request: &Request<T>,
+    ) -> impl std::future::Future<Output = Result<Option<String>, Status>> + Send + '_ {
+        let certs = request.peer_certs();
+        async move {
+            let Some(agent_tls) = &self.agent_tls else {
+                return Ok(None);
+            };
+            let certs =
+                certs.ok_or_else(|| Status::unauthenticated("An enrolled agent client certificate is required"))?;
+            let cert = certs
+                .first()
+                .ok_or_else(|| Status::unauthenticated("An enrolled agent client certificate is required"))?;
+
+            match agent_tls.enrolled_agent(cert.as_ref()).await {
+                Ok(Some(name)) => Ok(Some(name)),
+                Ok(None) => {
+                    warn!("🚫 Rejected agent certificate {}", crate::agent_tls::cert_fingerprint(cert.as_ref()));
+                    Err(Status::permission_denied("Agent certificate is not enrolled, expired or revoked"))
+                }
+                Err(e) => Err(Status::internal(e.to_string())),
+            }
         }
     }
 
@@ -104,6 +159,7 @@ impl ProxyService for ProxyServiceImpl {
         &self,
         request: Request<RegisterAgentRequest>,
     ) -> Result<Response<RegisterAgentResponse>, Status> {
+        let enrolled_as = self.authorize_agent(&request).await?;
         let req = request.into_inner();
         let agent_id = req.agent_id.clone();
 
@@ -112,8 +168,9 @@ impl ProxyService for ProxyServiceImpl {
         info!("   • Name: {}", req.name);
         info!("   • Hostname: {}", req.hostname);
         info!("   • Version: {}", req.version);
-
-        // In the future, we might validation auth tokens here.
+        if let Some(name) = &enrolled_as {
+            info!("   • Certificate: {}", name);
+        }
 
         // Upsert agent to database
         if let Err(e) = self
@@ -136,11 +193,71 @@ impl ProxyService for ProxyServiceImpl {
             ca_key_pem.len()
         );
 
+        // Project DNS settings decide how the agent resolves upstream hosts
+        let dns_config = match self.db.get_dns_config().await {
+            Ok(config) => {
+                if !config.is_system() {
+                    info!("   ✓ Sending DNS config ({:?}, {} server(s))", config.mode, config.servers.len());
+                }
+                Some(crate::pb::DnsConfig::from(&config))
+            }
+            Err(e) => {
+                warn!("   ⚠ Failed to load DNS config, agent will use system resolver: {}", e);
+                None
+            }
+        };
+
+        let host_overrides = match self.db.get_host_overrides().await {
+            Ok(overrides) => {
+                if !overrides.is_empty() {
+                    info!("   ✓ Sending {} host override(s)", overrides.len());
+                }
+                overrides.into_iter().map(|(host, ip)| (host, ip.to_string())).collect()
+            }
+            Err(e) => {
+                warn!("   ⚠ Failed to load host overrides, agent will resolve every host: {}", e);
+                Default::default()
+            }
+        };
+
+        let client_certificates = match self.db.get_client_certificates().await {
+            Ok(certificates) => {
+                if !certificates.is_empty() {
+                    info!("   ✓ Sending {} client certificate(s)", certificates.len());
+                    if enrolled_as.is_none() {
+                        warn!("   ⚠ Client certificate keys sent over a channel without agent TLS");
+                    }
+                }
+                certificates.iter().map(crate::pb::ClientCertificate::from).collect()
+            }
+            Err(e) => {
+                warn!("   ⚠ Failed to load client certificates, agent will present none: {}", e);
+                Vec::new()
+            }
+        };
+
+        let tls_policies = match self.db.get_tls_policies().await {
+            Ok(policies) => {
+                if !policies.is_empty() {
+                    info!("   ✓ Sending TLS policies for {} host pattern(s)", policies.len());
+                }
+                policies.iter().map(crate::pb::TlsPolicy::from).collect()
+            }
+            Err(e) => {
+                warn!("   ⚠ Failed to load TLS policies, agent will use default TLS settings: {}", e);
+                Vec::new()
+            }
+        };
+
         Ok(Response::new(RegisterAgentResponse {
             success: true,
             message: "Registered successfully".into(),
             ca_cert_pem,
             ca_key_pem,
+            dns_config,
+            host_overrides,
+            client_certificates,
+            tls_policies,
         }))
     }
 
@@ -150,6 +267,7 @@ impl ProxyService for ProxyServiceImpl {
         &self,
         request: Request<Streaming<TrafficEvent>>,
     ) -> Result<Response<Self::StreamTrafficStream>, Status> {
+        self.authorize_agent(&request).await?;
         let agent_id = match request.metadata().get("x-agent-id") {
             Some(id) => id.to_str().unwrap_or("unknown").to_string(),
             None => {
@@ -164,6 +282,15 @@ impl ProxyService for ProxyServiceImpl {
         let mut inbound = request.into_inner();
         let (tx, rx) = mpsc::channel(100);
 
+        // Current interception rules go out before anything else on the stream
+        let rules = crate::interception::rules_command(&*self.interception.read().await);
+        if tx.send(Ok(rules)).await.is_err() {
+            warn!("   ✗ Failed to send interception rules to agent {}", agent_id);
+        }
+        if tx.send(Ok(self.hook_scripts.command().await)).await.is_err() {
+            warn!("   ✗ Failed to send hook scripts to agent {}", agent_id);
+        }
+
         // Register the command channel
         let agent_info = match self.db.get_agent_info(&agent_id).await {
             Ok(Some(info)) => {
@@ -221,6 +348,8 @@ impl ProxyService for ProxyServiceImpl {
         let agent_id_cl = agent_id.clone();
         let registry = self.agent_registry.clone();
         let recording_svc = self.recording_service.clone();
+        let intercepts = self.intercepts.clone();
+        let passive_scanner = self.passive_scanner.clone();
         
         // Spawn task to handle inbound traffic events
         tokio::spawn(async move {
@@ -248,6 +377,52 @@ impl ProxyService for ProxyServiceImpl {
                 //     }
                 // }
 
+                // Held requests wait in the queue; they are recorded once forwarded
+                match &event.event {
+                    Some(traffic_event::Event::Intercepted(req)) => {
+                        intercepts.hold(&agent_id_cl, &event.request_id, req.clone()).await;
+                        continue;
+                    }
+                    // A request still queued here was released by the agent's hold timeout
+                    Some(traffic_event::Event::Request(_)) => intercepts.expire(&event.request_id).await,
+                    Some(traffic_event::Event::RuleStats(stats)) => {
+                        intercepts.record_rule_stats(&agent_id_cl, stats.clone()).await;
+                        continue;
+                    }
+                    Some(traffic_event::Event::Tags(tags)) => {
+                        let db_bg = db.clone();
+                        let request_id = event.request_id.clone();
+                        let tags = tags.tags.clone();
+                        tokio::spawn(async move { add_script_tags(&db_bg, &request_id, &tags).await });
+                        continue;
+                    }
+                    Some(traffic_event::Event::Connection(connection)) => {
+                        let db_bg = db.clone();
+                        let agent_id_bg = agent_id_cl.clone();
+                        let request_id = event.request_id.clone();
+                        let connection = connection.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = db_bg.save_connection_event(&agent_id_bg, &connection, Some(&request_id)).await {
+                                warn!("   ⚠️  Failed to save connection event: {}", e);
+                            }
+                        });
+                        continue;
+                    }
+                    _ => {}
+                }
+
+                // Usage counts everything the agent proxied, in scope or not
+                if let Some(delta) = crate::database::UsageDelta::of(&event) {
+                    let db_bg = db.clone();
+                    let agent_id_bg = agent_id_cl.clone();
+                    let day = chrono::Utc::now().format("%Y-%m-%d").to_string();
+                    tokio::spawn(async move {
+                        if let Err(e) = db_bg.record_agent_usage(&agent_id_bg, &day, &delta).await {
+                            warn!("   ⚠️  Failed to record usage of agent {}: {}", agent_id_bg, e);
+                        }
+                    });
+                }
+
                 // SCOPE CHECK - Determine if we should record and broadcast this event
                 let rules = db.scope_rules_cache.read().await;
                 let should_record = if !rules.is_empty() {
@@ -283,15 +458,20 @@ impl ProxyService for ProxyServiceImpl {
                     let event_bg = event.clone();
                     let agent_id_bg = agent_id_cl.clone();
                     let registry_bg = registry.clone();
+                    let passive_bg = passive_scanner.clone();
                     
                     tokio::spawn(async move {
                         // Retry loop for handling potential FK constraints (e.g. if DB was swapped)
                         let mut retry_count = 0;
                         const MAX_RETRIES: u32 = 1;
+                        let mut saved = false;
                         
                         loop {
                             match db_bg.save_request(&event_bg, &agent_id_bg).await {
-                                Ok(_) => break, // Success
+                                Ok(_) => {
+                                    saved = true;
+                                    break;
+                                }
                                 Err(e) => {
                                     // Check if it's a foreign key constraint failure (code 787)
                                     let is_fk_error = e.to_string().contains("FOREIGN KEY constraint failed") || 
@@ -326,6 +506,15 @@ impl ProxyService for ProxyServiceImpl {
                                 }
                             }
                         }
+
+                        // 3. Passive checks and coloring rules, once the response is stored
+                        if let (true, Some(traffic_event::Event::Response(res))) = (saved, &event_bg.event) {
+                            passive_bg.record(&db_bg, &event_bg.request_id, res).await;
+                            crate::coloring::apply(&db_bg, &event_bg.request_id, res).await;
+                        }
+                        if let (true, Some(traffic_event::Event::Request(req))) = (saved, &event_bg.event) {
+                            crate::jwt::annotate(&db_bg, &event_bg.request_id, req).await;
+                        }
                     });
                 }
                 // Note: Proxy continues to forward the request regardless of scope
@@ -348,6 +537,9 @@ impl ProxyService for ProxyServiceImpl {
             // Remove agent from registry
             registry.remove_agent(&agent_id_cl);
             info!("   ✓ Agent {} removed from session registry", agent_id_cl);
+
+            // Requests it was holding went on (or died) with the connection
+            intercepts.expire_agent(&agent_id_cl).await;
         });
 
         Ok(Response::new(ReceiverStream::new(rx)))
@@ -359,6 +551,7 @@ impl ProxyService for ProxyServiceImpl {
         &self,
         request: Request<Streaming<SystemMetricsEvent>>,
     ) -> Result<Response<Self::StreamMetricsStream>, Status> {
+        self.authorize_agent(&request).await?;
         let agent_id = match request.metadata().get("x-agent-id") {
             Some(id) => id.to_str().unwrap_or("unknown").to_string(),
             None => {
@@ -480,12 +673,23 @@ impl ProxyService for ProxyServiceImpl {
         &self,
         request: Request<Streaming<HeartbeatRequest>>,
     ) -> Result<Response<Self::HeartbeatStream>, Status> {
+        self.authorize_agent(&request).await?;
         let mut inbound = request.into_inner();
         let (tx, rx) = mpsc::channel(10);
         let registry = self.agent_registry.clone();
+        let db = self.db.clone();
 
         tokio::spawn(async move {
             while let Ok(Some(req)) = inbound.message().await {
+                // Stamped onto transactions and attack results from here on
+                if !req.public_ip.is_empty() {
+                    match db.record_agent_egress(&req.agent_id, &req.public_ip).await {
+                        Ok(true) => info!("🌐 Agent {} egress IP is now {}", req.agent_id, req.public_ip),
+                        Ok(false) => {}
+                        Err(e) => warn!("Failed to record egress IP for {}: {}", req.agent_id, e),
+                    }
+                }
+
                 registry.update_heartbeat(
                     &req.agent_id,
                     req.cpu_usage,
@@ -508,3 +712,25 @@ impl ProxyService for ProxyServiceImpl {
         Ok(Response::new(ReceiverStream::new(rx)))
     }
 }
+
+/// Times tagging is tried before giving up on a transaction
+const SCRIPT_TAG_ATTEMPTS: u32 = 5;
+
+/// Add the tags a hook script put on a request to its transaction. Tags can
+/// arrive while the request is still being saved, so a missing transaction
+/// is retried for a moment; out-of-scope requests are never saved.
+async fn add_script_tags(db: &Database, request_id: &str, tags: &[String]) {
+    for attempt in 1..=SCRIPT_TAG_ATTEMPTS {
+        match db.add_request_tags(request_id, tags).await {
+            Ok(true) => return,
+            Ok(false) if attempt < SCRIPT_TAG_ATTEMPTS => {
+                tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            }
+            Ok(false) => debug!("No stored transaction {} for script tags {:?}", request_id, tags),
+            Err(e) => {
+                warn!("Failed to add script tags to {}: {}", request_id, e);
+                return;
+            }
+        }
+    }
+}
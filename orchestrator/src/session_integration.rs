@@ -3,6 +3,8 @@
 //! This module provides session integration functionality for Repeater and Intruder modules,
 //! compatible with LSR (Login Sequence Recorder) session format and lifecycle management.
 
+pub mod macros;
+
 use attack_engine::{HttpRequestData, HttpResponseData, AttackError, AttackResult};
 use proxy_common::session::{Session, SessionStatus, SessionEvent};
 use serde::{Deserialize, Serialize};
@@ -135,6 +137,57 @@ impl Default for AuthFailureDetectionConfig {
     }
 }
 
+impl AuthFailureDetectionConfig {
+    /// Whether `response` shows the session it was sent with no longer works
+    pub fn is_failure(&self, response: &HttpResponseData) -> bool {
+        // Check status codes
+        if self.failure_status_codes.contains(&response.status_code) {
+            debug!("   🔍 Auth failure detected: status code {}", response.status_code);
+            return true;
+        }
+        
+        // Check response body patterns
+        if let Ok(body_str) = String::from_utf8(response.body.clone()) {
+            let body_lower = body_str.to_lowercase();
+            for pattern in &self.failure_body_patterns {
+                if body_lower.contains(&pattern.to_lowercase()) {
+                    debug!("   🔍 Auth failure detected: body contains '{}'", pattern);
+                    return true;
+                }
+            }
+        }
+        
+        // Check response headers
+        if let Some(headers) = &response.headers {
+            for (header_name, pattern) in &self.failure_header_patterns {
+                if let Some(header_value) = headers.headers.get(header_name) {
+                    if let Ok(regex) = regex::Regex::new(pattern) {
+                        if regex.is_match(header_value) {
+                            debug!("   🔍 Auth failure detected: header '{}' matches pattern '{}'", 
+                                   header_name, pattern);
+                            return true;
+                        }
+                    }
+                }
+            }
+            
+            // Check for login redirects
+            if let Some(location) = headers.headers.get("Location") {
+                for pattern in &self.login_redirect_patterns {
+                    if let Ok(regex) = regex::Regex::new(pattern) {
+                        if regex.is_match(location) {
+                            debug!("   🔍 Auth failure detected: redirect to login page '{}'", location);
+                            return true;
+                        }
+                    }
+                }
+            }
+        }
+        
+        false
+    }
+}
+
 /// Session expiration handling options
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExpirationHandling {
@@ -428,55 +481,9 @@ impl SessionManager {
     pub async fn detect_authentication_failure(
         &self,
         response: &HttpResponseData,
-        request_url: &str,
+        _request_url: &str,
     ) -> bool {
-        let config = self.auth_failure_config.read().await;
-        
-        // Check status codes
-        if config.failure_status_codes.contains(&response.status_code) {
-            debug!("   🔍 Auth failure detected: status code {}", response.status_code);
-            return true;
-        }
-        
-        // Check response body patterns
-        if let Ok(body_str) = String::from_utf8(response.body.clone()) {
-            let body_lower = body_str.to_lowercase();
-            for pattern in &config.failure_body_patterns {
-                if body_lower.contains(&pattern.to_lowercase()) {
-                    debug!("   🔍 Auth failure detected: body contains '{}'", pattern);
-                    return true;
-                }
-            }
-        }
-        
-        // Check response headers
-        if let Some(headers) = &response.headers {
-            for (header_name, pattern) in &config.failure_header_patterns {
-                if let Some(header_value) = headers.headers.get(header_name) {
-                    if let Ok(regex) = regex::Regex::new(pattern) {
-                        if regex.is_match(header_value) {
-                            debug!("   🔍 Auth failure detected: header '{}' matches pattern '{}'", 
-                                   header_name, pattern);
-                            return true;
-                        }
-                    }
-                }
-            }
-            
-            // Check for login redirects
-            if let Some(location) = headers.headers.get("Location") {
-                for pattern in &config.login_redirect_patterns {
-                    if let Ok(regex) = regex::Regex::new(pattern) {
-                        if regex.is_match(location) {
-                            debug!("   🔍 Auth failure detected: redirect to login page '{}'", location);
-                            return true;
-                        }
-                    }
-                }
-            }
-        }
-        
-        false
+        self.auth_failure_config.read().await.is_failure(response)
     }
 
     /// Handle authentication failure for a session
@@ -690,9 +697,8 @@ impl SessionManager {
 
     /// Increment session usage counter
     async fn increment_session_usage(&self, session_id: &Uuid) {
-        if let Some(mut session) = self.sessions.read().await.get(session_id).cloned() {
+        if let Some(session) = self.sessions.write().await.get_mut(session_id) {
             session.increment_usage();
-            self.sessions.write().await.insert(*session_id, session);
         }
     }
 
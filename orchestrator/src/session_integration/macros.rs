@@ -0,0 +1,481 @@
+//! Session handling macros
+//!
+//! A macro is a short list of recorded requests replayed before a Repeater or
+//! Intruder request, for targets that want something fetched first: a CSRF
+//! token from the form page, a nonce from an API call, a fresh login. Each
+//! step can extract values from its response; the values are substituted into
+//! the later steps and into the main request as `{{name}}` placeholders, and
+//! also replace query or form parameters of the same name.
+//!
+//! Macros are a project setting (`session_macros`).
+
+use crate::models::settings::VersionedSetting;
+use attack_engine::{AttackError, AttackResult, HttpRequestData, HttpResponseData};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+
+/// Macros are replayed before every request, so they are kept short
+pub const MAX_MACRO_STEPS: usize = 10;
+
+/// Session macros of a project
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct SessionMacroConfig {
+    pub macros: Vec<SessionMacro>,
+    /// Incremented on every save; writers must present the version they read
+    #[serde(default)]
+    pub version: u64,
+}
+
+impl VersionedSetting for SessionMacroConfig {
+    fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: u64) {
+        self.version = version;
+    }
+}
+
+/// Tool whose requests a macro runs before
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MacroTool {
+    Repeater,
+    Intruder,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionMacro {
+    pub id: String,
+    pub name: String,
+    pub enabled: bool,
+    pub tools: Vec<MacroTool>,
+    /// Only run before requests whose URL contains this (case-insensitive)
+    #[serde(default)]
+    pub url_contains: Option<String>,
+    /// Sent in order
+    pub steps: Vec<MacroStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub request: HttpRequestData,
+    #[serde(default)]
+    pub extractions: Vec<TokenExtraction>,
+}
+
+/// A value taken from a step's response under `name`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TokenExtraction {
+    pub name: String,
+    pub source: ExtractionSource,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum ExtractionSource {
+    /// Response header value, matched case-insensitively
+    Header { header: String },
+    /// Value of a cookie the response sets
+    Cookie { cookie: String },
+    /// First capture group of a regex over the response body, or the whole
+    /// match without groups
+    BodyRegex { pattern: String },
+    /// Value at a JSONPath (`$.cart.items[0].id`) of a JSON response body;
+    /// strings are taken unquoted, other values as JSON
+    JsonPath { path: String },
+}
+
+/// One step of a parsed JSONPath
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl SessionMacroConfig {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        for session_macro in &self.macros {
+            session_macro.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Enabled macros to run before a `tool` request to `url`, in order
+    pub fn for_request(&self, tool: MacroTool, url: &str) -> Vec<SessionMacro> {
+        self.macros
+            .iter()
+            .filter(|m| m.enabled && m.tools.contains(&tool))
+            .filter(|m| match &m.url_contains {
+                Some(pattern) => url.to_lowercase().contains(&pattern.to_lowercase()),
+                None => true,
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+impl SessionMacro {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.steps.is_empty() {
+            return Err(format!("Macro '{}' has no steps", self.name));
+        }
+        if self.steps.len() > MAX_MACRO_STEPS {
+            return Err(format!("Macro '{}' has more than {} steps", self.name, MAX_MACRO_STEPS));
+        }
+        for step in &self.steps {
+            if !step.request.url.starts_with("http://") && !step.request.url.starts_with("https://") {
+                return Err(format!("Macro '{}' has a step without an absolute URL", self.name));
+            }
+            for extraction in &step.extractions {
+                if extraction.name.trim().is_empty() {
+                    return Err(format!("Macro '{}' has an extraction without a name", self.name));
+                }
+                extraction
+                    .source
+                    .validate()
+                    .map_err(|e| format!("Macro '{}': {} for '{}'", self.name, e, extraction.name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the steps with `send` and return the extracted parameters. Values
+    /// from earlier macros can be passed in `parameters`; they are available
+    /// to the steps and returned along with the new ones.
+    pub async fn run<F, Fut>(
+        &self,
+        mut parameters: HashMap<String, String>,
+        mut send: F,
+    ) -> AttackResult<HashMap<String, String>>
+    where
+        F: FnMut(HttpRequestData) -> Fut,
+        Fut: Future<Output = AttackResult<HttpResponseData>>,
+    {
+        for (index, step) in self.steps.iter().enumerate() {
+            let failed = |error: String| AttackError::ExecutionFailed {
+                error: format!("Macro '{}' step {}: {}", self.name, index + 1, error),
+            };
+            let mut request = step.request.clone();
+            apply_parameters(&mut request, &parameters);
+            let response = send(request).await.map_err(|e| failed(e.to_string()))?;
+            for extraction in &step.extractions {
+                let value = extraction
+                    .source
+                    .extract(&response)
+                    .ok_or_else(|| failed(format!("nothing to extract for '{}'", extraction.name)))?;
+                parameters.insert(extraction.name.clone(), value);
+            }
+        }
+        Ok(parameters)
+    }
+}
+
+impl ExtractionSource {
+    /// Check that a pattern or path parses
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ExtractionSource::BodyRegex { pattern } => {
+                regex::Regex::new(pattern).map_err(|e| format!("invalid pattern: {}", e))?;
+            }
+            ExtractionSource::JsonPath { path } => {
+                parse_json_path(path).map_err(|e| format!("invalid JSONPath: {}", e))?;
+            }
+            ExtractionSource::Header { .. } | ExtractionSource::Cookie { .. } => {}
+        }
+        Ok(())
+    }
+
+    pub fn extract(&self, response: &HttpResponseData) -> Option<String> {
+        match self {
+            ExtractionSource::Header { header } => response
+                .headers
+                .iter()
+                .flat_map(|h| h.headers.iter())
+                .find(|(name, _)| name.eq_ignore_ascii_case(header))
+                .map(|(_, value)| value.clone()),
+            ExtractionSource::Cookie { cookie } => crate::repeater::set_cookie_values(response)
+                .iter()
+                .filter_map(|set_cookie| set_cookie.split(';').next()?.split_once('='))
+                .find(|(name, _)| name.trim() == cookie)
+                .map(|(_, value)| value.trim().to_string()),
+            ExtractionSource::BodyRegex { pattern } => {
+                let regex = regex::Regex::new(pattern).ok()?;
+                let body = String::from_utf8_lossy(&response.body);
+                let captures = regex.captures(&body)?;
+                captures.get(1).or_else(|| captures.get(0)).map(|m| m.as_str().to_string())
+            }
+            ExtractionSource::JsonPath { path } => {
+                let body: serde_json::Value = serde_json::from_slice(&response.body).ok()?;
+                let value = parse_json_path(path).ok()?.iter().try_fold(&body, |value, segment| match segment {
+                    PathSegment::Key(key) => value.get(key),
+                    PathSegment::Index(index) => value.get(index),
+                })?;
+                match value {
+                    serde_json::Value::String(text) => Some(text.clone()),
+                    serde_json::Value::Null => None,
+                    other => Some(other.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// Parse the JSONPath subset extractions take: `$` followed by `.key`,
+/// `['key']` and `[index]` segments
+fn parse_json_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    let rest = path.trim().strip_prefix('$').ok_or("a path starts with $")?;
+    let mut chars = rest.chars().peekable();
+    let mut segments = Vec::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                let mut key = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next == '.' || next == '[' {
+                        break;
+                    }
+                    key.push(next);
+                    chars.next();
+                }
+                if key.is_empty() {
+                    return Err("empty key after '.'".to_string());
+                }
+                segments.push(PathSegment::Key(key));
+            }
+            '[' => match chars.peek() {
+                Some(&quote) if quote == '\'' || quote == '"' => {
+                    chars.next();
+                    let key: String = chars.by_ref().take_while(|&next| next != quote).collect();
+                    if chars.next() != Some(']') {
+                        return Err(format!("unclosed ['{}'", key));
+                    }
+                    segments.push(PathSegment::Key(key));
+                }
+                _ => {
+                    let index: String = chars.by_ref().take_while(|&next| next != ']').collect();
+                    let index = index.trim().parse().map_err(|_| format!("'[{}]' is not an index", index))?;
+                    segments.push(PathSegment::Index(index));
+                }
+            },
+            other => return Err(format!("unexpected '{}'", other)),
+        }
+    }
+    Ok(segments)
+}
+
+/// Feed macro parameters into a request: `{{name}}` placeholders in the URL,
+/// header values and body are replaced, as are the values of query and
+/// url-encoded form parameters called `name`.
+pub fn apply_parameters(request: &mut HttpRequestData, parameters: &HashMap<String, String>) {
+    if parameters.is_empty() {
+        return;
+    }
+    let substitute = |text: &str| {
+        parameters
+            .iter()
+            .fold(text.to_string(), |text, (name, value)| text.replace(&format!("{{{{{}}}}}", name), value))
+    };
+
+    request.url = substitute(&request.url);
+    if let Some(headers) = request.headers.as_mut() {
+        for value in headers.headers.values_mut() {
+            *value = substitute(value);
+        }
+    }
+    if let Ok(body) = std::str::from_utf8(&request.body) {
+        request.body = substitute(body).into_bytes();
+    }
+
+    if let Some((base, query)) = request.url.split_once('?') {
+        let (query, fragment) = match query.split_once('#') {
+            Some((query, fragment)) => (query, Some(fragment)),
+            None => (query, None),
+        };
+        let mut url = format!("{}?{}", base, replace_encoded_parameters(query, parameters));
+        if let Some(fragment) = fragment {
+            url.push('#');
+            url.push_str(fragment);
+        }
+        request.url = url;
+    }
+
+    let form = request
+        .get_header("Content-Type")
+        .or_else(|| request.get_header("content-type"))
+        .is_some_and(|ct| ct.to_ascii_lowercase().starts_with("application/x-www-form-urlencoded"));
+    if form {
+        if let Ok(body) = std::str::from_utf8(&request.body) {
+            request.body = replace_encoded_parameters(body, parameters).into_bytes();
+        }
+    }
+}
+
+/// Replace the values of `name=value` pairs in a query string or form body,
+/// leaving everything else as it was
+fn replace_encoded_parameters(encoded: &str, parameters: &HashMap<String, String>) -> String {
+    encoded
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((name, _)) if parameters.contains_key(name) => format!("{}={}", name, url_encode(&parameters[name])),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Percent-encode all but unreserved characters
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                (byte as char).to_string()
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+/// Run `macros` in order, each seeing the parameters of the ones before,
+/// and apply the result to `request`
+pub async fn run_before<F, Fut>(
+    macros: &[SessionMacro],
+    request: &mut HttpRequestData,
+    mut send: F,
+) -> AttackResult<HashMap<String, String>>
+where
+    F: FnMut(HttpRequestData) -> Fut,
+    Fut: Future<Output = AttackResult<HttpResponseData>>,
+{
+    let mut parameters = HashMap::new();
+    for session_macro in macros {
+        parameters = session_macro.run(parameters, &mut send).await?;
+    }
+    apply_parameters(request, &parameters);
+    Ok(parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use attack_engine::HttpHeaders;
+
+    fn response(headers: &[(&str, &str)], body: &str) -> HttpResponseData {
+        HttpResponseData {
+            status_code: 200,
+            headers: Some(HttpHeaders {
+                headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }),
+            body: body.as_bytes().to_vec(),
+            tls: None,
+            timing: None,
+            protocol: None,
+        }
+    }
+
+    fn csrf_macro() -> SessionMacro {
+        SessionMacro {
+            id: "m1".to_string(),
+            name: "csrf".to_string(),
+            enabled: true,
+            tools: vec![MacroTool::Repeater],
+            url_contains: Some("/account".to_string()),
+            steps: vec![
+                MacroStep {
+                    request: HttpRequestData::new("GET".to_string(), "https://app.test/account/form".to_string()),
+                    extractions: vec![
+                        TokenExtraction {
+                            name: "csrf".to_string(),
+                            source: ExtractionSource::BodyRegex { pattern: r#"name="csrf" value="([^"]+)""#.to_string() },
+                        },
+                        TokenExtraction {
+                            name: "sid".to_string(),
+                            source: ExtractionSource::Cookie { cookie: "sid".to_string() },
+                        },
+                    ],
+                },
+                MacroStep {
+                    request: HttpRequestData::new("GET".to_string(), "https://app.test/nonce?t={{csrf}}".to_string()),
+                    extractions: vec![TokenExtraction {
+                        name: "nonce".to_string(),
+                        source: ExtractionSource::Header { header: "x-nonce".to_string() },
+                    }],
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_macro_feeds_main_request() {
+        let session_macro = csrf_macro();
+        assert!(session_macro.validate().is_ok());
+
+        let mut sent = Vec::new();
+        let mut request = HttpRequestData::new("POST".to_string(), "https://app.test/account/email?csrf=old".to_string());
+        request.set_header("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string());
+        request.set_header("X-Nonce".to_string(), "{{nonce}}".to_string());
+        request.body = b"email=a%40b.test&csrf=stale".to_vec();
+
+        let parameters = run_before(&[session_macro], &mut request, |step: HttpRequestData| {
+            sent.push(step.url.clone());
+            let response = if step.url.contains("/nonce") {
+                response(&[("X-Nonce", "n-42")], "")
+            } else {
+                response(
+                    &[("Set-Cookie", "sid=s1; Path=/; HttpOnly")],
+                    r#"<input type="hidden" name="csrf" value="tok123">"#,
+                )
+            };
+            async move { Ok(response) }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(sent, vec!["https://app.test/account/form", "https://app.test/nonce?t=tok123"]);
+        assert_eq!(parameters.get("sid").map(String::as_str), Some("s1"));
+        assert_eq!(request.url, "https://app.test/account/email?csrf=tok123");
+        assert_eq!(request.get_header("X-Nonce").map(String::as_str), Some("n-42"));
+        assert_eq!(request.body, b"email=a%40b.test&csrf=tok123");
+    }
+
+    #[tokio::test]
+    async fn test_missing_token_fails_the_macro() {
+        let mut request = HttpRequestData::new("GET".to_string(), "https://app.test/account".to_string());
+        let result = run_before(&[csrf_macro()], &mut request, |_| async { Ok(response(&[], "no form here")) }).await;
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("step 1") && error.contains("csrf"), "{}", error);
+    }
+
+    #[test]
+    fn test_selection_and_validation() {
+        let config = SessionMacroConfig { macros: vec![csrf_macro()], version: 0 };
+        assert_eq!(config.for_request(MacroTool::Repeater, "https://app.test/ACCOUNT/x").len(), 1);
+        assert!(config.for_request(MacroTool::Repeater, "https://app.test/home").is_empty());
+        assert!(config.for_request(MacroTool::Intruder, "https://app.test/account").is_empty());
+
+        let mut invalid = csrf_macro();
+        invalid.steps[0].extractions[0].source = ExtractionSource::BodyRegex { pattern: "(".to_string() };
+        assert!(invalid.validate().is_err());
+        invalid.steps.clear();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_json_path_extraction() {
+        let body = r#"{"cart": {"id": 42, "items": [{"sku": "A-1", "price": 9.5}], "note": null}, "x-y": "dash"}"#;
+        let extract = |path: &str| ExtractionSource::JsonPath { path: path.to_string() }.extract(&response(&[], body));
+        assert_eq!(extract("$.cart.items[0].sku").as_deref(), Some("A-1"));
+        assert_eq!(extract("$.cart.items[0].price").as_deref(), Some("9.5"));
+        assert_eq!(extract("$['cart'].id").as_deref(), Some("42"));
+        assert_eq!(extract("$[\"x-y\"]").as_deref(), Some("dash"));
+        assert!(extract("$.cart.note").is_none());
+        assert!(extract("$.cart.items[3]").is_none());
+
+        for invalid in ["cart.id", "$.", "$[one]", "$['open"] {
+            assert!(ExtractionSource::JsonPath { path: invalid.to_string() }.validate().is_err(), "{}", invalid);
+        }
+    }
+}
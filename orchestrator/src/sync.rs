@@ -0,0 +1,426 @@
+//! Project Sync
+//!
+//! Incremental sync of a project between two orchestrators over gRPC, so a
+//! field laptop can work disconnected and merge into a central server later.
+//! Each side keeps a change log (`sync_changes`), filled by triggers on
+//! captured transactions and highlighted intruder results. The field side
+//! runs `sync_with_peer`: it pulls the peer's changes after its last cursor,
+//! then pushes its own. Changes received from a node are tagged with that
+//! node's identity and never sent back to it, so repeated syncs converge.
+//! Deletions are not synced.
+//!
+//! Sync goes over the mutual-TLS gRPC channel: the field side presents a
+//! client certificate enrolled on the peer and the peer's admin token or an
+//! API key. An API key acts as its principal, which must be a Viewer of the
+//! project to pull and an Editor to push.
+
+use crate::pb::project_sync_client::ProjectSyncClient;
+use crate::pb::project_sync_server::ProjectSync;
+use crate::auth::{strip_bearer, AuthError, AuthService, Caller};
+use crate::models::access::{check_access, Principal, ProjectRole};
+use crate::pb::{PullChangesRequest, PullChangesResponse, PushChangesRequest, PushChangesResponse};
+use crate::Database;
+use std::sync::Arc;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::Interceptor;
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+use tonic::{Request, Response, Status};
+use tracing::{debug, info};
+
+/// Records per batch requested by `sync_with_peer`
+pub const SYNC_BATCH_SIZE: u32 = 500;
+
+const MAX_SYNC_BATCH_SIZE: u32 = 2000;
+
+/// Largest sync message accepted; batches are cut well below this
+pub const MAX_SYNC_MESSAGE_BYTES: usize = 128 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("No project loaded")]
+    NoProject,
+    #[error("Invalid sync peer: {0}")]
+    InvalidPeer(String),
+    #[error("Cannot reach sync peer: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("Sync peer refused the request: {}", .0.message())]
+    Peer(#[from] tonic::Status),
+    #[error("Sync database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+/// Outcome of one sync run
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    pub peer_node_id: String,
+    pub pulled: u64,
+    pub pushed: u64,
+}
+
+/// Orchestrator to sync with, and the credentials it accepts
+#[derive(Debug, Clone, Default)]
+pub struct SyncPeer {
+    /// gRPC address of the peer, e.g. "https://central:50051"
+    pub address: String,
+    /// Admin token or API key of the peer
+    pub token: String,
+    /// Control-plane CA of the peer (PEM), to verify its certificate
+    pub ca_cert_pem: String,
+    /// Client certificate enrolled on the peer (PEM)
+    pub client_cert_pem: String,
+    pub client_key_pem: String,
+}
+
+/// Bearer token of a sync call, lifted from the metadata by `SyncAuthInterceptor`
+#[derive(Clone)]
+struct SyncToken(String);
+
+/// Guards the sync server: calls without the admin token or an API key are
+/// refused. Interceptors cannot await, so the service looks the token up.
+#[derive(Clone)]
+pub struct SyncAuthInterceptor {
+    auth: Arc<AuthService>,
+}
+
+impl SyncAuthInterceptor {
+    pub fn new(auth: Arc<AuthService>) -> Self {
+        Self { auth }
+    }
+}
+
+impl Interceptor for SyncAuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        // Without an admin token no credential exists, so nobody may sync
+        if !self.auth.enabled() {
+            return Err(Status::unauthenticated(
+                "Project sync needs API authentication; start the orchestrator with an admin token",
+            ));
+        }
+        let token = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(strip_bearer)
+            .ok_or_else(|| Status::unauthenticated(AuthError::Missing.to_string()))?
+            .to_string();
+        request.extensions_mut().insert(SyncToken(token));
+        Ok(request)
+    }
+}
+
+/// Server side of project sync, served next to the agent service behind
+/// `SyncAuthInterceptor`
+pub struct ProjectSyncService {
+    db: Arc<Database>,
+    auth: Arc<AuthService>,
+    read_only: bool,
+}
+
+impl ProjectSyncService {
+    pub fn new(db: Arc<Database>, auth: Arc<AuthService>, read_only: bool) -> Self {
+        Self { db, auth, read_only }
+    }
+
+    /// Check the caller's token; API keys need `role` on the loaded project
+    async fn authorize<T>(&self, request: &Request<T>, role: ProjectRole) -> Result<(), Status> {
+        let token = request
+            .extensions()
+            .get::<SyncToken>()
+            .cloned()
+            .ok_or_else(|| Status::unauthenticated(AuthError::Missing.to_string()))?;
+        let principal = match self.auth.authenticate(&token.0).await {
+            Ok(Caller::Admin) => return Ok(()),
+            Ok(Caller::ApiKey { principal, .. }) => principal,
+            Err(AuthError::Database(e)) => return Err(Status::internal(e.to_string())),
+            Err(e) => return Err(Status::unauthenticated(e.to_string())),
+        };
+
+        let members = self
+            .db
+            .list_project_members()
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        check_access(&members, Some(&Principal(principal)), role).map_err(|e| Status::permission_denied(e.to_string()))
+    }
+
+    /// Peers sync the project that is loaded here, nothing else
+    async fn check_request(&self, project: &str, node_id: &str) -> Result<(), Status> {
+        if node_id.is_empty() {
+            return Err(Status::invalid_argument("node_id is required"));
+        }
+        match self.db.active_project_name().await {
+            Some(active) if active == project => Ok(()),
+            Some(active) => Err(Status::failed_precondition(format!(
+                "Project '{}' is not loaded (active project is '{}')",
+                project, active
+            ))),
+            None => Err(Status::failed_precondition("No project loaded")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ProjectSync for ProjectSyncService {
+    async fn pull_changes(
+        &self,
+        request: Request<PullChangesRequest>,
+    ) -> Result<Response<PullChangesResponse>, Status> {
+        self.authorize(&request, ProjectRole::Viewer).await?;
+        let req = request.into_inner();
+        self.check_request(&req.project, &req.node_id).await?;
+
+        let node_id = self.db.sync_node_id().await.map_err(|e| Status::internal(e.to_string()))?;
+        let limit = match req.limit {
+            0 => SYNC_BATCH_SIZE,
+            n => n.min(MAX_SYNC_BATCH_SIZE),
+        };
+        let batch = self
+            .db
+            .read_sync_changes(req.after_seq, &req.node_id, limit as i64)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        debug!(
+            "🔄 Sync pull by {}: {} transactions, {} findings after #{}",
+            req.node_id,
+            batch.transactions.len(),
+            batch.findings.len(),
+            req.after_seq
+        );
+        Ok(Response::new(PullChangesResponse {
+            node_id,
+            transactions: batch.transactions,
+            findings: batch.findings,
+            next_seq: batch.next_seq,
+            more: batch.more,
+        }))
+    }
+
+    async fn push_changes(
+        &self,
+        request: Request<PushChangesRequest>,
+    ) -> Result<Response<PushChangesResponse>, Status> {
+        self.authorize(&request, ProjectRole::Editor).await?;
+        if self.read_only {
+            return Err(Status::permission_denied("Orchestrator is in read-only mode"));
+        }
+        let req = request.into_inner();
+        self.check_request(&req.project, &req.node_id).await?;
+
+        let applied = self
+            .db
+            .apply_sync_changes(&req.node_id, &req.transactions, &req.findings)
+            .await
+            .map_err(|e| Status::internal(e.to_string()))?;
+
+        info!("🔄 Applied {} synced records from {}", applied, req.node_id);
+        Ok(Response::new(PushChangesResponse { applied }))
+    }
+}
+
+/// Sync the loaded project with the orchestrator `peer`: pull its changes,
+/// then push local ones. Cursors are saved after every batch, so an
+/// interrupted sync resumes.
+pub async fn sync_with_peer(db: &Database, peer: &SyncPeer) -> Result<SyncReport, SyncError> {
+    let project = db.active_project_name().await.ok_or(SyncError::NoProject)?;
+    let node_id = db.sync_node_id().await?;
+
+    // The token is a bearer credential: never send it in the clear
+    if !peer.address.starts_with("https://") {
+        return Err(SyncError::InvalidPeer("the address must be https://".to_string()));
+    }
+    let authorization: MetadataValue<Ascii> = format!("Bearer {}", peer.token.trim())
+        .parse()
+        .map_err(|_| SyncError::InvalidPeer("the token is not valid header text".to_string()))?;
+    let tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(&peer.ca_cert_pem))
+        .identity(Identity::from_pem(&peer.client_cert_pem, &peer.client_key_pem));
+    let channel = Channel::from_shared(peer.address.clone())
+        .map_err(|e| SyncError::InvalidPeer(e.to_string()))?
+        .tls_config(tls)?
+        .connect()
+        .await?;
+
+    let peer = peer.address.as_str();
+    let mut state = db.get_sync_peer_state(peer).await?;
+    let mut client = ProjectSyncClient::new(channel).max_decoding_message_size(MAX_SYNC_MESSAGE_BYTES);
+    let mut report = SyncReport::default();
+
+    loop {
+        let response = client
+            .pull_changes(authorized(
+                PullChangesRequest {
+                    project: project.clone(),
+                    node_id: node_id.clone(),
+                    after_seq: state.pulled_seq,
+                    limit: SYNC_BATCH_SIZE,
+                },
+                &authorization,
+            ))
+            .await?
+            .into_inner();
+
+        if !response.transactions.is_empty() || !response.findings.is_empty() {
+            report.pulled += db
+                .apply_sync_changes(&response.node_id, &response.transactions, &response.findings)
+                .await? as u64;
+        }
+        state.peer_node_id = Some(response.node_id);
+        state.pulled_seq = response.next_seq;
+        db.save_sync_peer_state(peer, &state).await?;
+
+        if !response.more {
+            break;
+        }
+    }
+
+    // Known after the first pull; changes that came from the peer stay here
+    let peer_node_id = state.peer_node_id.clone().unwrap_or_default();
+    loop {
+        let batch = db
+            .read_sync_changes(state.pushed_seq, &peer_node_id, SYNC_BATCH_SIZE as i64)
+            .await?;
+
+        if !batch.transactions.is_empty() || !batch.findings.is_empty() {
+            let response = client
+                .push_changes(authorized(
+                    PushChangesRequest {
+                        project: project.clone(),
+                        node_id: node_id.clone(),
+                        transactions: batch.transactions,
+                        findings: batch.findings,
+                    },
+                    &authorization,
+                ))
+                .await?
+                .into_inner();
+            report.pushed += response.applied as u64;
+        }
+        state.pushed_seq = batch.next_seq;
+        db.save_sync_peer_state(peer, &state).await?;
+
+        if !batch.more {
+            break;
+        }
+    }
+
+    state.last_synced_at = Some(chrono::Utc::now().timestamp());
+    db.save_sync_peer_state(peer, &state).await?;
+
+    info!(
+        "✓ Synced project '{}' with {}: {} records pulled, {} pushed",
+        project, peer, report.pulled, report.pushed
+    );
+    report.peer_node_id = peer_node_id;
+    Ok(report)
+}
+
+/// Sync call carrying the peer credential
+fn authorized<T>(message: T, authorization: &MetadataValue<Ascii>) -> Request<T> {
+    let mut request = Request::new(message);
+    request.metadata_mut().insert("authorization", authorization.clone());
+    request
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup(admin_token: Option<&str>) -> (ProjectSyncService, SyncAuthInterceptor, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::new(dir.path().to_str().unwrap()).await.unwrap());
+        db.create_project("field").await.unwrap();
+        db.load_project("field").await.unwrap();
+        let auth = Arc::new(
+            AuthService::open(&dir.path().join("server.db"), admin_token.map(str::to_string))
+                .await
+                .unwrap(),
+        );
+        (ProjectSyncService::new(db, auth.clone(), false), SyncAuthInterceptor::new(auth), dir)
+    }
+
+    /// Pass a call through the interceptor, as the server does
+    fn intercept<T>(interceptor: &mut SyncAuthInterceptor, message: T, token: Option<&str>) -> Result<Request<T>, Code> {
+        let mut request = Request::new(());
+        if let Some(token) = token {
+            request
+                .metadata_mut()
+                .insert("authorization", format!("Bearer {}", token).parse().unwrap());
+        }
+        let (metadata, extensions, ()) = interceptor.call(request).map_err(|e| e.code())?.into_parts();
+        Ok(Request::from_parts(metadata, extensions, message))
+    }
+
+    fn pull() -> PullChangesRequest {
+        PullChangesRequest {
+            project: "field".to_string(),
+            node_id: "laptop".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn push() -> PushChangesRequest {
+        PushChangesRequest {
+            project: "field".to_string(),
+            node_id: "laptop".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unauthenticated_sync_is_rejected() {
+        let (service, mut interceptor, _dir) = setup(Some("s3cret")).await;
+
+        assert_eq!(intercept(&mut interceptor, pull(), None).unwrap_err(), Code::Unauthenticated);
+        assert_eq!(intercept(&mut interceptor, push(), None).unwrap_err(), Code::Unauthenticated);
+
+        // A token that is not the admin token nor a key gets past the
+        // interceptor but not the service
+        let request = intercept(&mut interceptor, pull(), Some("guess")).unwrap();
+        assert_eq!(service.pull_changes(request).await.unwrap_err().code(), Code::Unauthenticated);
+        let request = intercept(&mut interceptor, push(), Some("pxy_guess")).unwrap();
+        assert_eq!(service.push_changes(request).await.unwrap_err().code(), Code::Unauthenticated);
+
+        // Calls that skipped the interceptor carry no checked token
+        assert_eq!(service.push_changes(Request::new(push())).await.unwrap_err().code(), Code::Unauthenticated);
+
+        let request = intercept(&mut interceptor, push(), Some("s3cret")).unwrap();
+        assert_eq!(service.push_changes(request).await.unwrap().into_inner().applied, 0);
+    }
+
+    #[tokio::test]
+    async fn test_sync_refused_without_admin_token() {
+        let (_service, mut interceptor, _dir) = setup(None).await;
+
+        assert_eq!(
+            intercept(&mut interceptor, pull(), Some("anything")).unwrap_err(),
+            Code::Unauthenticated
+        );
+    }
+
+    #[tokio::test]
+    async fn test_api_key_needs_project_role() {
+        let (service, mut interceptor, _dir) = setup(Some("s3cret")).await;
+        service.db.set_project_member("alice", ProjectRole::Viewer).await.unwrap();
+        let (key, _) = service.auth.create_api_key("laptop", "alice").await.unwrap();
+
+        let request = intercept(&mut interceptor, pull(), Some(&key)).unwrap();
+        assert!(service.pull_changes(request).await.is_ok());
+        let request = intercept(&mut interceptor, push(), Some(&key)).unwrap();
+        assert_eq!(service.push_changes(request).await.unwrap_err().code(), Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn test_peer_address_must_be_https() {
+        let (service, _interceptor, _dir) = setup(Some("s3cret")).await;
+        let peer = SyncPeer {
+            address: "http://central:50051".to_string(),
+            token: "s3cret".to_string(),
+            ..Default::default()
+        };
+
+        let err = sync_with_peer(&service.db, &peer).await.unwrap_err();
+        assert!(matches!(err, SyncError::InvalidPeer(_)));
+    }
+}
@@ -0,0 +1,371 @@
+//! Tamper Checks
+//!
+//! A light active check set for captured requests: parameter pollution,
+//! method override headers and verb tampering (see
+//! [`attack_engine::tampering`]). Each request is first replayed unmodified
+//! to get a baseline, then every probe is sent and compared with it. Probes
+//! whose response differs are stored as findings, with a severity by what
+//! the difference suggests: a denied request that a probe gets through is
+//! high, a reflected duplicate parameter is low.
+//!
+//! A run covers selected requests or the latest request of every method and
+//! path in the site map, of one host or of all. Requests outside the project
+//! scope are skipped, and probes are held to the Replay rules of engagement.
+
+use crate::access_matrix::SkippedRequest;
+use crate::database::TamperFindingRow;
+use crate::engagement::EngagementGuard;
+use crate::models::engagement::ActiveTool;
+use crate::passive_checks::Severity;
+use crate::repeater::RepeaterManager;
+use crate::Database;
+use attack_engine::{
+    assess, tamper_probes, AttackError, AttackResult, HttpRequestData, TamperBaseline, TamperChecks, TamperEffect,
+    TamperKind,
+};
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Most captured requests a run probes
+pub const MAX_TAMPER_REQUESTS: usize = 1000;
+
+/// Unmodified replays a baseline is taken from
+const BASELINE_REPLAYS: usize = 2;
+
+/// Requests a run probes
+#[derive(Debug, Clone)]
+pub enum TamperTarget {
+    /// Captured requests, by ID
+    Requests(Vec<String>),
+    /// The latest request of every method and path of `host`, or of every
+    /// host
+    SiteMap { host: Option<String> },
+}
+
+#[derive(Debug, Clone)]
+pub struct TamperRunConfig {
+    pub target: TamperTarget,
+    pub agent_id: String,
+    pub checks: TamperChecks,
+}
+
+impl TamperRunConfig {
+    /// Configuration errors, if any
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.checks.any() {
+            return Err("Enable at least one check".to_string());
+        }
+        if let TamperTarget::Requests(request_ids) = &self.target {
+            if request_ids.is_empty() || request_ids.len() > MAX_TAMPER_REQUESTS {
+                return Err(format!("Select between 1 and {} requests", MAX_TAMPER_REQUESTS));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TamperRunStatus {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+/// Progress of a run; its findings are in the database
+#[derive(Debug, Clone)]
+pub struct TamperRun {
+    pub id: String,
+    pub agent_id: String,
+    pub status: TamperRunStatus,
+    pub checks: TamperChecks,
+    pub requested: usize,
+    /// Requests whose probes were all sent
+    pub tested: usize,
+    pub probes_sent: usize,
+    pub findings: usize,
+    pub skipped: Vec<SkippedRequest>,
+    pub error: Option<String>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// How serious a difference a probe made is
+pub fn finding_severity(kind: TamperKind, effect: TamperEffect) -> Severity {
+    match (kind, effect) {
+        (_, TamperEffect::AccessBypass) => Severity::High,
+        (_, TamperEffect::VerbAccepted) => Severity::Medium,
+        (_, TamperEffect::MarkerReflected) | (TamperKind::MethodOverride, _) => Severity::Low,
+        _ => Severity::Info,
+    }
+}
+
+/// Tamper check runs of the orchestrator; runs are kept in memory
+pub struct TamperCheckManager {
+    database: Arc<Database>,
+    repeater: Arc<RepeaterManager>,
+    runs: Arc<DashMap<String, TamperRun>>,
+    cancellations: Arc<DashMap<String, Arc<AtomicBool>>>,
+}
+
+impl TamperCheckManager {
+    pub fn new(database: Arc<Database>, repeater: Arc<RepeaterManager>) -> Self {
+        Self {
+            database,
+            repeater,
+            runs: Arc::new(DashMap::new()),
+            cancellations: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Start probing in the background; returns the run ID
+    pub async fn start(&self, config: TamperRunConfig) -> AttackResult<String> {
+        config.validate().map_err(|reason| AttackError::ValidationError {
+            field: "tamper_checks".to_string(),
+            reason,
+        })?;
+        self.repeater.validate_agent_availability(&config.agent_id).await?;
+
+        let request_ids = match &config.target {
+            TamperTarget::Requests(request_ids) => request_ids.clone(),
+            TamperTarget::SiteMap { host } => {
+                let mut request_ids = self
+                    .database
+                    .list_site_map_request_ids(host.as_deref())
+                    .await
+                    .map_err(|e| AttackError::DatabaseError {
+                        operation: format!("list_site_map_request_ids: {}", e),
+                    })?;
+                if request_ids.is_empty() {
+                    return Err(AttackError::ValidationError {
+                        field: "target".to_string(),
+                        reason: "The site map has no requests to probe".to_string(),
+                    });
+                }
+                if request_ids.len() > MAX_TAMPER_REQUESTS {
+                    warn!(
+                        "Site map has {} requests; tamper checks probe the first {}",
+                        request_ids.len(),
+                        MAX_TAMPER_REQUESTS
+                    );
+                    request_ids.truncate(MAX_TAMPER_REQUESTS);
+                }
+                request_ids
+            }
+        };
+
+        let scope_rules = self.database.get_scope_rules().await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("get_scope_rules: {}", e),
+        })?;
+        let guard = EngagementGuard::load(self.database.clone(), ActiveTool::Replay).await?;
+
+        let id = Uuid::new_v4().to_string();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        self.cancellations.insert(id.clone(), cancelled.clone());
+        self.runs.insert(
+            id.clone(),
+            TamperRun {
+                id: id.clone(),
+                agent_id: config.agent_id.clone(),
+                status: TamperRunStatus::Running,
+                checks: config.checks.clone(),
+                requested: request_ids.len(),
+                tested: 0,
+                probes_sent: 0,
+                findings: 0,
+                skipped: Vec::new(),
+                error: None,
+                started_at: chrono::Utc::now(),
+                finished_at: None,
+            },
+        );
+        info!("🧪 Tamper check run {} probing {} requests", id, request_ids.len());
+
+        let (database, repeater, runs, cancellations, run_id) = (
+            self.database.clone(),
+            self.repeater.clone(),
+            self.runs.clone(),
+            self.cancellations.clone(),
+            id.clone(),
+        );
+        tokio::spawn(async move {
+            let outcome: Result<TamperRunStatus, String> = async {
+                for request_id in &request_ids {
+                    if cancelled.load(Ordering::Relaxed) {
+                        return Ok(TamperRunStatus::Cancelled);
+                    }
+                    let skip = |reason: String| {
+                        if let Some(mut run) = runs.get_mut(&run_id) {
+                            run.skipped.push(SkippedRequest { request_id: request_id.clone(), reason });
+                        }
+                    };
+
+                    let request: HttpRequestData = match database.get_request_by_id(request_id).await {
+                        Ok(Some((_, request))) => request.into(),
+                        Ok(None) => {
+                            skip("Request not found".to_string());
+                            continue;
+                        }
+                        Err(e) => return Err(format!("Failed to load request {}: {}", request_id, e)),
+                    };
+                    if !crate::scope::is_in_scope(&scope_rules, &request.url) {
+                        skip("Out of scope".to_string());
+                        continue;
+                    }
+                    if let Err(e) = guard.check(&request.url).await {
+                        skip(e.to_string());
+                        continue;
+                    }
+
+                    let mut responses = Vec::with_capacity(BASELINE_REPLAYS);
+                    for _ in 0..BASELINE_REPLAYS {
+                        match repeater.execute_through_agent(&request, &config.agent_id).await {
+                            Ok(response) => responses.push(response),
+                            Err(AttackError::AgentUnavailable { agent_id }) => {
+                                return Err(format!("Agent {} is unavailable", agent_id));
+                            }
+                            Err(e) => {
+                                skip(format!("Baseline failed: {}", e));
+                                break;
+                            }
+                        }
+                    }
+                    if responses.len() < BASELINE_REPLAYS {
+                        continue;
+                    }
+                    let Some(baseline) = TamperBaseline::from_responses(&responses) else {
+                        skip("Baseline responses differ in status".to_string());
+                        continue;
+                    };
+
+                    for probe in tamper_probes(&request, &config.checks) {
+                        if cancelled.load(Ordering::Relaxed) {
+                            return Ok(TamperRunStatus::Cancelled);
+                        }
+                        let response = match repeater.execute_through_agent(&probe.request, &config.agent_id).await {
+                            Ok(response) => response,
+                            Err(AttackError::AgentUnavailable { agent_id }) => {
+                                return Err(format!("Agent {} is unavailable", agent_id));
+                            }
+                            Err(e) => {
+                                warn!("Tamper check run {}: probe '{}' of {} failed: {}", run_id, probe.label, request.url, e);
+                                continue;
+                            }
+                        };
+                        if let Some(mut run) = runs.get_mut(&run_id) {
+                            run.probes_sent += 1;
+                        }
+                        let Some(effect) = assess(&probe, &baseline, &response) else {
+                            continue;
+                        };
+
+                        let finding = TamperFindingRow {
+                            id: Uuid::new_v4().to_string(),
+                            run_id: run_id.clone(),
+                            request_id: request_id.clone(),
+                            method: request.method.clone(),
+                            url: request.url.clone(),
+                            kind: probe.kind.as_str().to_string(),
+                            probe: probe.label,
+                            effect: effect.as_str().to_string(),
+                            severity: finding_severity(probe.kind, effect).as_str().to_string(),
+                            baseline_status: baseline.status_code,
+                            probe_status: response.status_code,
+                            baseline_length: baseline.max_length as i64,
+                            probe_length: response.body.len() as i64,
+                            agent_id: config.agent_id.clone(),
+                            created_at: chrono::Utc::now().timestamp(),
+                        };
+                        database
+                            .save_tamper_finding(&finding)
+                            .await
+                            .map_err(|e| format!("Failed to save finding: {}", e))?;
+                        if let Some(mut run) = runs.get_mut(&run_id) {
+                            run.findings += 1;
+                        }
+                    }
+
+                    if let Some(mut run) = runs.get_mut(&run_id) {
+                        run.tested += 1;
+                    }
+                }
+                Ok(TamperRunStatus::Completed)
+            }
+            .await;
+
+            cancellations.remove(&run_id);
+            if let Some(mut run) = runs.get_mut(&run_id) {
+                match outcome {
+                    Ok(status) => run.status = status,
+                    Err(error) => {
+                        run.status = TamperRunStatus::Failed;
+                        run.error = Some(error);
+                    }
+                }
+                run.finished_at = Some(chrono::Utc::now());
+                info!("🧪 Tamper check run {} {:?} with {} findings", run_id, run.status, run.findings);
+            }
+        });
+
+        Ok(id)
+    }
+
+    /// Stop probing; returns false if the run is not running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.cancellations.get(id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<TamperRun> {
+        self.runs.get(id).map(|run| run.clone())
+    }
+
+    /// Every run, newest first
+    pub fn list(&self) -> Vec<TamperRun> {
+        let mut runs: Vec<TamperRun> = self.runs.iter().map(|run| run.clone()).collect();
+        runs.sort_by_key(|run| std::cmp::Reverse(run.started_at));
+        runs
+    }
+
+    /// Delete a run with its findings
+    pub async fn delete(&self, id: &str) -> AttackResult<bool> {
+        self.cancel(id);
+        let deleted = self.database.delete_tamper_findings(id).await.map_err(|e| AttackError::DatabaseError {
+            operation: format!("delete_tamper_findings: {}", e),
+        })?;
+        Ok(self.runs.remove(id).is_some() || deleted > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_and_severity() {
+        let config = TamperRunConfig {
+            target: TamperTarget::Requests(vec!["r1".to_string()]),
+            agent_id: "agent-1".to_string(),
+            checks: TamperChecks::default(),
+        };
+        assert!(config.validate().is_ok());
+        assert!(TamperRunConfig { target: TamperTarget::Requests(Vec::new()), ..config.clone() }.validate().is_err());
+        assert!(TamperRunConfig { target: TamperTarget::SiteMap { host: None }, ..config.clone() }.validate().is_ok());
+        let none = TamperChecks { parameter_pollution: false, method_override: false, verb_tampering: false };
+        assert!(TamperRunConfig { checks: none, ..config }.validate().is_err());
+
+        assert_eq!(finding_severity(TamperKind::VerbTampering, TamperEffect::AccessBypass), Severity::High);
+        assert_eq!(finding_severity(TamperKind::VerbTampering, TamperEffect::VerbAccepted), Severity::Medium);
+        assert_eq!(finding_severity(TamperKind::MethodOverride, TamperEffect::ContentChanged), Severity::Low);
+        assert_eq!(finding_severity(TamperKind::ParameterPollution, TamperEffect::MarkerReflected), Severity::Low);
+        assert_eq!(finding_severity(TamperKind::ParameterPollution, TamperEffect::StatusChanged), Severity::Info);
+    }
+}
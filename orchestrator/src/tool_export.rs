@@ -0,0 +1,441 @@
+//! External Tool Export
+//!
+//! Turns a stored transaction into input for sqlmap and ffuf: a raw HTTP
+//! request file with the chosen insertion points marked, plus the command
+//! line (and for ffuf a config file) that runs the tool against it. sqlmap
+//! gets its `*` custom injection markers after the original values; ffuf gets
+//! its keywords in place of them.
+
+use crate::pb::HttpRequestData;
+use std::collections::HashMap;
+
+/// File name the generated commands refer to for the request
+pub const DEFAULT_REQUEST_FILE: &str = "request.txt";
+
+/// Wordlist used for ffuf keywords that were not given one
+pub const DEFAULT_WORDLIST: &str = "wordlist.txt";
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ToolExportError {
+    #[error("Request URL is not absolute: {0}")]
+    InvalidUrl(String),
+    #[error("Insertion point {0} not found in the request")]
+    InsertionPointNotFound(String),
+    #[error("ffuf needs at least one insertion point")]
+    NoInsertionPoints,
+}
+
+/// Part of a request a tool should inject into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertionLocation {
+    Query,
+    /// URL-encoded form field or JSON key (the first match, at any depth)
+    Body,
+    Header,
+    Cookie,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsertionPoint {
+    pub location: InsertionLocation,
+    pub name: String,
+}
+
+impl std::fmt::Display for InsertionPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let location = match self.location {
+            InsertionLocation::Query => "query",
+            InsertionLocation::Body => "body",
+            InsertionLocation::Header => "header",
+            InsertionLocation::Cookie => "cookie",
+        };
+        write!(f, "{} '{}'", location, self.name)
+    }
+}
+
+/// Files and command for running a tool
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolExport {
+    /// Raw HTTP request, to be saved as `request_file_name`
+    pub request_file: String,
+    pub request_file_name: String,
+    pub command: String,
+    /// ffuf config (TOML), an alternative to the command line
+    pub config: Option<String>,
+}
+
+/// sqlmap run against the request, testing only the insertion points if any
+/// are given (otherwise sqlmap picks the parameters itself)
+pub fn sqlmap_export(
+    request: &HttpRequestData,
+    points: &[InsertionPoint],
+    request_file_name: &str,
+) -> Result<ToolExport, ToolExportError> {
+    let marked = mark_request(request, points, |_, value| format!("{}*", value))?;
+    let target = reqwest::Url::parse(&marked.url).map_err(|_| ToolExportError::InvalidUrl(marked.url.clone()))?;
+
+    let mut command = format!("sqlmap -r {} --batch", shell_quote(request_file_name));
+    if target.scheme() == "https" {
+        command.push_str(" --force-ssl");
+    }
+
+    Ok(ToolExport {
+        request_file: raw_request(&marked, &target),
+        request_file_name: request_file_name.to_string(),
+        command,
+        config: None,
+    })
+}
+
+/// ffuf run fuzzing each insertion point with its own keyword. `wordlists`
+/// pairs up with `points`; missing entries use [`DEFAULT_WORDLIST`].
+pub fn ffuf_export(
+    request: &HttpRequestData,
+    points: &[InsertionPoint],
+    wordlists: &[String],
+    request_file_name: &str,
+) -> Result<ToolExport, ToolExportError> {
+    if points.is_empty() {
+        return Err(ToolExportError::NoInsertionPoints);
+    }
+    let keywords: Vec<String> = if points.len() == 1 {
+        vec!["FUZZ".to_string()]
+    } else {
+        (1..=points.len()).map(|i| format!("W{}", i)).collect()
+    };
+    let marked = mark_request(request, points, |i, _| keywords[i].clone())?;
+    let target = reqwest::Url::parse(&marked.url).map_err(|_| ToolExportError::InvalidUrl(marked.url.clone()))?;
+
+    let inputs: Vec<String> = keywords
+        .iter()
+        .enumerate()
+        .map(|(i, keyword)| {
+            let wordlist = wordlists.get(i).map(String::as_str).unwrap_or(DEFAULT_WORDLIST);
+            format!("{}:{}", wordlist, keyword)
+        })
+        .collect();
+
+    let mut command = format!(
+        "ffuf -request {} -request-proto {}",
+        shell_quote(request_file_name),
+        target.scheme()
+    );
+    for input in &inputs {
+        command.push_str(" -w ");
+        command.push_str(&shell_quote(input));
+    }
+    if points.len() > 1 {
+        command.push_str(" -mode clusterbomb");
+    }
+
+    Ok(ToolExport {
+        request_file: raw_request(&marked, &target),
+        request_file_name: request_file_name.to_string(),
+        command,
+        config: Some(ffuf_config(&marked, &inputs, points.len() > 1)),
+    })
+}
+
+/// Copy of `request` with each insertion point's value replaced by
+/// `marker(index, original value)`
+fn mark_request(
+    request: &HttpRequestData,
+    points: &[InsertionPoint],
+    marker: impl Fn(usize, &str) -> String,
+) -> Result<HttpRequestData, ToolExportError> {
+    let mut marked = request.clone();
+    for (i, point) in points.iter().enumerate() {
+        let not_found = || ToolExportError::InsertionPointNotFound(point.to_string());
+        match point.location {
+            InsertionLocation::Query => {
+                let (base, query) = marked.url.split_once('?').ok_or_else(not_found)?;
+                let (query, fragment) = match query.split_once('#') {
+                    Some((query, fragment)) => (query, Some(fragment)),
+                    None => (query, None),
+                };
+                let query = mark_pairs(query, '&', &point.name, |v| marker(i, v)).ok_or_else(not_found)?;
+                marked.url = match fragment {
+                    Some(fragment) => format!("{}?{}#{}", base, query, fragment),
+                    None => format!("{}?{}", base, query),
+                };
+            }
+            InsertionLocation::Body => {
+                let body = String::from_utf8_lossy(&marked.body).to_string();
+                let body = mark_json(&body, &point.name, |v| marker(i, v))
+                    .or_else(|| mark_pairs(&body, '&', &point.name, |v| marker(i, v)))
+                    .ok_or_else(not_found)?;
+                marked.body = body.into_bytes().into();
+            }
+            InsertionLocation::Header => {
+                let headers = &mut marked.headers.get_or_insert_with(Default::default).headers;
+                let value = headers
+                    .iter_mut()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&point.name))
+                    .map(|(_, value)| value)
+                    .ok_or_else(not_found)?;
+                *value = marker(i, value);
+            }
+            InsertionLocation::Cookie => {
+                let headers = &mut marked.headers.get_or_insert_with(Default::default).headers;
+                let cookie = headers
+                    .iter_mut()
+                    .find(|(name, _)| name.eq_ignore_ascii_case("cookie"))
+                    .map(|(_, value)| value)
+                    .ok_or_else(not_found)?;
+                *cookie = mark_cookie(cookie, &point.name, |v| marker(i, v)).ok_or_else(not_found)?;
+            }
+        }
+    }
+    Ok(marked)
+}
+
+/// `name=value` pairs joined by `separator`, with the first `name` marked
+fn mark_pairs(pairs: &str, separator: char, name: &str, marker: impl Fn(&str) -> String) -> Option<String> {
+    let mut found = false;
+    let marked: Vec<String> = pairs
+        .split(separator)
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            if !found && key == name {
+                found = true;
+                format!("{}={}", key, marker(value))
+            } else {
+                pair.to_string()
+            }
+        })
+        .collect();
+    found.then(|| marked.join(&separator.to_string()))
+}
+
+fn mark_cookie(cookie: &str, name: &str, marker: impl Fn(&str) -> String) -> Option<String> {
+    let pairs: Vec<&str> = cookie.split(';').map(str::trim).collect();
+    mark_pairs(&pairs.join(";"), ';', name, marker).map(|c| c.replace(';', "; "))
+}
+
+/// JSON body with the first `key` (at any depth) marked. String values keep
+/// their quotes; numbers and booleans are replaced bare.
+fn mark_json(body: &str, key: &str, marker: impl Fn(&str) -> String) -> Option<String> {
+    const PLACEHOLDER: &str = "\u{0}proxxy-insertion-point\u{0}";
+
+    fn replace(value: &mut serde_json::Value, key: &str) -> Option<serde_json::Value> {
+        match value {
+            serde_json::Value::Object(map) => {
+                if let Some(found) = map.get_mut(key).filter(|v| !v.is_object() && !v.is_array()) {
+                    return Some(std::mem::replace(found, serde_json::Value::String(PLACEHOLDER.to_string())));
+                }
+                map.values_mut().find_map(|v| replace(v, key))
+            }
+            serde_json::Value::Array(items) => items.iter_mut().find_map(|v| replace(v, key)),
+            _ => None,
+        }
+    }
+
+    let mut json: serde_json::Value = serde_json::from_str(body).ok()?;
+    let original = replace(&mut json, key)?;
+    let serialized = serde_json::to_string(&json).ok()?;
+    let placeholder = serde_json::to_string(PLACEHOLDER).ok()?;
+
+    let marked = match &original {
+        serde_json::Value::String(s) => serde_json::to_string(&marker(s)).ok()?,
+        other => marker(&other.to_string()),
+    };
+    Some(serialized.replacen(&placeholder, &marked, 1))
+}
+
+/// Raw HTTP/1.1 request as sqlmap's `-r` and ffuf's `-request` read it
+fn raw_request(request: &HttpRequestData, target: &reqwest::Url) -> String {
+    let mut path = target.path().to_string();
+    if let Some(query) = target.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+
+    let empty = HashMap::new();
+    let headers = request.headers.as_ref().map(|h| &h.headers).unwrap_or(&empty);
+    let mut names: Vec<&String> = headers
+        .keys()
+        .filter(|name| {
+            !name.eq_ignore_ascii_case("host")
+                && !name.eq_ignore_ascii_case("content-length")
+                && !name.eq_ignore_ascii_case("transfer-encoding")
+        })
+        .collect();
+    names.sort_by_key(|name| name.to_ascii_lowercase());
+
+    let host = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.clone())
+        .unwrap_or_else(|| match target.port() {
+            Some(port) => format!("{}:{}", target.host_str().unwrap_or_default(), port),
+            None => target.host_str().unwrap_or_default().to_string(),
+        });
+
+    let mut raw = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", request.method, path, host);
+    for name in names {
+        raw.push_str(&format!("{}: {}\r\n", name, headers[name]));
+    }
+    if !request.body.is_empty() {
+        raw.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+    }
+    raw.push_str("\r\n");
+    raw.push_str(&String::from_utf8_lossy(&request.body));
+    raw
+}
+
+fn ffuf_config(request: &HttpRequestData, inputs: &[String], clusterbomb: bool) -> String {
+    let quote = |s: &str| serde_json::to_string(s).unwrap_or_default();
+
+    let mut headers: Vec<String> = request
+        .headers
+        .as_ref()
+        .map(|h| {
+            h.headers
+                .iter()
+                .filter(|(name, _)| !name.eq_ignore_ascii_case("content-length"))
+                .map(|(name, value)| quote(&format!("{}: {}", name, value)))
+                .collect()
+        })
+        .unwrap_or_default();
+    headers.sort();
+
+    let mut config = String::from("[http]\n");
+    config.push_str(&format!("    url = {}\n", quote(&request.url)));
+    config.push_str(&format!("    method = {}\n", quote(&request.method)));
+    config.push_str(&format!("    headers = [{}]\n", headers.join(", ")));
+    if !request.body.is_empty() {
+        config.push_str(&format!("    data = {}\n", quote(&String::from_utf8_lossy(&request.body))));
+    }
+    config.push_str("\n[input]\n");
+    if clusterbomb {
+        config.push_str("    inputmode = \"clusterbomb\"\n");
+    }
+    let wordlists: Vec<String> = inputs.iter().map(|i| quote(i)).collect();
+    config.push_str(&format!("    wordlists = [{}]\n", wordlists.join(", ")));
+    config
+}
+
+/// Single-quote `s` for a POSIX shell
+fn shell_quote(s: &str) -> String {
+    if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c)) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::HttpHeaders;
+
+    fn request(method: &str, url: &str, headers: &[(&str, &str)], body: &str) -> HttpRequestData {
+        HttpRequestData {
+            method: method.to_string(),
+            url: url.to_string(),
+            headers: Some(HttpHeaders {
+                headers: headers.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            }),
+            body: body.as_bytes().to_vec().into(),
+            tls: None,
+        }
+    }
+
+    fn point(location: InsertionLocation, name: &str) -> InsertionPoint {
+        InsertionPoint { location, name: name.to_string() }
+    }
+
+    #[test]
+    fn test_sqlmap_markers() {
+        let req = request(
+            "POST",
+            "https://shop.test:8443/items?id=7&sort=asc",
+            &[
+                ("Cookie", "session=abc; theme=dark"),
+                ("Content-Type", "application/x-www-form-urlencoded"),
+                ("Content-Length", "99"),
+            ],
+            "qty=1&note=hi",
+        );
+        let export = sqlmap_export(
+            &req,
+            &[
+                point(InsertionLocation::Query, "id"),
+                point(InsertionLocation::Body, "qty"),
+                point(InsertionLocation::Cookie, "theme"),
+            ],
+            DEFAULT_REQUEST_FILE,
+        )
+        .unwrap();
+
+        assert_eq!(export.command, "sqlmap -r request.txt --batch --force-ssl");
+        assert_eq!(
+            export.request_file,
+            "POST /items?id=7*&sort=asc HTTP/1.1\r\n\
+             Host: shop.test:8443\r\n\
+             Content-Type: application/x-www-form-urlencoded\r\n\
+             Cookie: session=abc; theme=dark*\r\n\
+             Content-Length: 14\r\n\
+             \r\n\
+             qty=1*&note=hi"
+        );
+    }
+
+    #[test]
+    fn test_ffuf_keywords_and_config() {
+        let req = request(
+            "POST",
+            "http://api.test/login",
+            &[("Content-Type", "application/json"), ("X-Tenant", "acme")],
+            r#"{"user":{"name":"bob","pin":1234}}"#,
+        );
+        let export = ffuf_export(
+            &req,
+            &[point(InsertionLocation::Body, "pin"), point(InsertionLocation::Header, "x-tenant")],
+            &["/lists/pins.txt".to_string()],
+            "login req.txt",
+        )
+        .unwrap();
+
+        assert_eq!(
+            export.command,
+            "ffuf -request 'login req.txt' -request-proto http -w /lists/pins.txt:W1 -w wordlist.txt:W2 -mode clusterbomb"
+        );
+        assert!(export.request_file.contains("X-Tenant: W2\r\n"));
+        assert!(export.request_file.ends_with(r#"{"user":{"name":"bob","pin":W1}}"#));
+
+        let config = export.config.unwrap();
+        assert!(config.contains(r#"url = "http://api.test/login""#));
+        assert!(config.contains(r#"data = "{\"user\":{\"name\":\"bob\",\"pin\":W1}}""#));
+        assert!(config.contains(r#"wordlists = ["/lists/pins.txt:W1", "wordlist.txt:W2"]"#));
+    }
+
+    #[test]
+    fn test_json_string_value_and_single_keyword() {
+        let req = request("PUT", "https://a.test/u", &[], r#"{"name":"it's"}"#);
+        let export = ffuf_export(&req, &[point(InsertionLocation::Body, "name")], &[], DEFAULT_REQUEST_FILE).unwrap();
+        assert!(export.request_file.ends_with(r#"{"name":"FUZZ"}"#));
+        assert!(export.command.ends_with("-w wordlist.txt:FUZZ"));
+
+        let sqlmap = sqlmap_export(&req, &[point(InsertionLocation::Body, "name")], DEFAULT_REQUEST_FILE).unwrap();
+        assert!(sqlmap.request_file.ends_with(r#"{"name":"it's*"}"#));
+    }
+
+    #[test]
+    fn test_missing_points() {
+        let req = request("GET", "https://a.test/?q=1", &[], "");
+        assert_eq!(
+            sqlmap_export(&req, &[point(InsertionLocation::Query, "id")], DEFAULT_REQUEST_FILE),
+            Err(ToolExportError::InsertionPointNotFound("query 'id'".to_string()))
+        );
+        assert_eq!(
+            ffuf_export(&req, &[point(InsertionLocation::Cookie, "sid")], &[], DEFAULT_REQUEST_FILE),
+            Err(ToolExportError::InsertionPointNotFound("cookie 'sid'".to_string()))
+        );
+        assert_eq!(
+            ffuf_export(&req, &[], &[], DEFAULT_REQUEST_FILE),
+            Err(ToolExportError::NoInsertionPoints)
+        );
+        // No points: sqlmap chooses the parameters
+        assert!(sqlmap_export(&req, &[], DEFAULT_REQUEST_FILE).unwrap().request_file.starts_with("GET /?q=1 HTTP/1.1"));
+    }
+}
@@ -223,6 +223,10 @@ async fn test_intruder_manager_integration() {
         distribution_strategy: attack_engine::DistributionStrategy::RoundRobin,
         session_data: None,
         execution_config: None,
+        flow_session: None,
+        upload: None,
+        highlight_rules: Vec::new(),
+        sequence: None,
     };
     
     // Validate the configuration
@@ -437,6 +441,10 @@ async fn test_core_functionality_integration() {
         distribution_strategy: attack_engine::DistributionStrategy::RoundRobin,
         session_data: None,
         execution_config: None,
+        flow_session: None,
+        upload: None,
+        highlight_rules: Vec::new(),
+        sequence: None,
     };
     
     let validation = intruder_manager.validate_attack_config(&config)
@@ -26,7 +26,7 @@ async fn test_database_persistence() {
             method: "GET".to_string(),
             url: "http://example.com".to_string(),
             headers: None,
-            body: vec![],
+            body: Default::default(),
             tls: None,
         })),
     };
@@ -0,0 +1,100 @@
+use async_graphql::{EmptySubscription, Request, Schema};
+use flow_engine::{FlowStep, MaskingPolicy, SecretString, SmartSelector};
+use orchestrator::database::flow::FlowProfileRow;
+use orchestrator::graphql::{MutationRoot, QueryRoot};
+use orchestrator::models::access::{Principal, ProjectRole};
+use orchestrator::Database;
+use std::sync::Arc;
+use tempfile::TempDir;
+
+const REVEAL: &str = r#"{ revealFlowStepValue(profileId: "login", index: 0) }"#;
+const ENABLE_REVEAL: &str = r#"mutation {
+    updateFlowMaskingPolicy(input: { maskPasswordFields: true, fieldPatterns: ["password"], allowReveal: true }) {
+        allowReveal
+    }
+}"#;
+
+async fn setup() -> (Arc<Database>, TempDir) {
+    let temp_dir = TempDir::new().unwrap();
+    let db = Arc::new(Database::new(temp_dir.path().to_str().unwrap()).await.unwrap());
+    db.create_project("reveal").await.unwrap();
+    db.load_project("reveal").await.unwrap();
+    db.set_project_member("owner", ProjectRole::Owner).await.unwrap();
+    db.set_project_member("editor", ProjectRole::Editor).await.unwrap();
+    db.set_project_member("viewer", ProjectRole::Viewer).await.unwrap();
+    db.save_masking_policy(&MaskingPolicy { allow_reveal: true, ..Default::default() }).await.unwrap();
+
+    let steps = vec![FlowStep::Type {
+        selector: SmartSelector::css("#password"),
+        value: SecretString::from("hunter2".to_string()),
+        is_masked: true,
+        clear_first: true,
+    }];
+    db.save_flow_profile(&FlowProfileRow {
+        id: "login".to_string(),
+        name: "Login".to_string(),
+        flow_type: "Login".to_string(),
+        start_url: "https://target.test/login".to_string(),
+        steps: serde_json::to_string(&steps).unwrap(),
+        meta: None,
+        created_at: 0,
+        updated_at: 0,
+        agent_id: None,
+        status: "active".to_string(),
+    })
+    .await
+    .unwrap();
+    (db, temp_dir)
+}
+
+async fn execute(db: &Arc<Database>, principal: &str, query: &str) -> async_graphql::Response {
+    let schema = Schema::build(QueryRoot, MutationRoot, EmptySubscription).data(db.clone()).finish();
+    schema
+        .execute(Request::new(query).data(Principal(principal.to_string())))
+        .await
+}
+
+#[tokio::test]
+async fn test_reveal_denied_below_owner() {
+    let (db, _dir) = setup().await;
+
+    for principal in ["viewer", "editor"] {
+        let response = execute(&db, principal, REVEAL).await;
+        assert_eq!(response.errors.len(), 1, "{} must not reveal", principal);
+        assert_eq!(
+            response.errors[0].extensions.as_ref().and_then(|e| e.get("code")),
+            Some(&async_graphql::Value::from("FORBIDDEN"))
+        );
+    }
+    assert!(db.list_flow_reveals(10).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_owner_reveal_is_audited() {
+    let (db, _dir) = setup().await;
+
+    let response = execute(&db, "owner", REVEAL).await;
+    assert!(response.errors.is_empty(), "{:?}", response.errors);
+    assert_eq!(
+        response.data.into_json().unwrap()["revealFlowStepValue"],
+        serde_json::json!("hunter2")
+    );
+
+    let audit = db.list_flow_reveals(10).await.unwrap();
+    assert_eq!(audit.len(), 1);
+    assert_eq!(audit[0].profile_id, "login");
+    assert_eq!(audit[0].step_index, 0);
+    assert_eq!(audit[0].principal.as_deref(), Some("owner"));
+}
+
+#[tokio::test]
+async fn test_allow_reveal_change_denied_below_owner() {
+    let (db, _dir) = setup().await;
+    db.save_masking_policy(&MaskingPolicy::default()).await.unwrap();
+
+    for principal in ["viewer", "editor"] {
+        let response = execute(&db, principal, ENABLE_REVEAL).await;
+        assert_eq!(response.errors.len(), 1, "{} must not enable reveal", principal);
+    }
+    assert!(!db.get_masking_policy().await.unwrap().allow_reveal);
+}
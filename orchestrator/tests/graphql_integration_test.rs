@@ -82,7 +82,13 @@ async fn test_session_graphql_workflow() {
         agent_timeout: 10,
         logging: LoggingConfig {
             level: "info".to_string(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(config).await.unwrap();
@@ -320,7 +326,13 @@ async fn test_repeater_with_session_integration() {
         agent_timeout: 10,
         logging: LoggingConfig {
             level: "info".to_string(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(config).await.unwrap();
@@ -488,7 +500,13 @@ async fn test_intruder_with_session_integration() {
         agent_timeout: 10,
         logging: LoggingConfig {
             level: "info".to_string(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(config).await.unwrap();
@@ -679,7 +697,13 @@ async fn test_auth_failure_config_graphql() {
         agent_timeout: 10,
         logging: LoggingConfig {
             level: "info".to_string(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(config).await.unwrap();
@@ -41,6 +41,8 @@ fn test_intruder_result_gql_conversion() {
         status_code: Some(200),
         response_length: Some(16),
         is_highlighted: false,
+        highlight_reason: None,
+        egress_ip: Some("203.0.113.7".to_string()),
     };
 
     let gql_result = IntruderResultGql::from(result);
@@ -52,6 +54,7 @@ fn test_intruder_result_gql_conversion() {
     assert_eq!(gql_result.status_code, Some(200));
     assert_eq!(gql_result.response_length, Some(16));
     assert!(!gql_result.is_highlighted);
+    assert_eq!(gql_result.egress_ip.as_deref(), Some("203.0.113.7"));
     assert!(gql_result.executed_at.contains("2022-01-01"));
 }
 
@@ -13,7 +13,13 @@ async fn test_graphql_endpoint() {
         agent_timeout: 10,
         logging: LoggingConfig {
             level: "info".to_string(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(config).await.unwrap();
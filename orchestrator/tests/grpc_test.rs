@@ -14,7 +14,13 @@ async fn test_orchestrator_grpc_flow() {
         agent_timeout: 10,
         logging: LoggingConfig {
             level: "info".into(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(config.clone()).await.unwrap();
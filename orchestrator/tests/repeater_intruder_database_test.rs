@@ -142,6 +142,7 @@ proptest! {
                     request_data: request_data.clone(),
                     target_agent_id: primary_agent.clone(),
                     session_id: None,
+                    options: Default::default(),
                 };
                 
                 let execution_result = repeater_manager.execute_request(execution_request).await;
@@ -30,7 +30,7 @@ async fn test_get_request_by_id() {
             headers: Some(HttpHeaders {
                 headers: headers_map,
             }),
-            body: b"{\"test\":\"data\"}".to_vec(),
+            body: b"{\"test\":\"data\"}".to_vec().into(),
             tls: None,
         })),
     };
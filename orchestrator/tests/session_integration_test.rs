@@ -88,6 +88,8 @@ fn arb_http_response() -> impl Strategy<Value = HttpResponseData> {
             headers,
             body,
             tls: None,
+            timing: None,
+            protocol: None,
         }
     })
 }
@@ -121,6 +123,8 @@ fn arb_auth_failure_response() -> impl Strategy<Value = HttpResponseData> {
             headers,
             body,
             tls: None,
+            timing: None,
+            protocol: None,
         }
     })
 }
@@ -561,6 +565,8 @@ async fn test_session_integration_end_to_end() {
         }),
         body: b"Unauthorized access".to_vec(),
         tls: None,
+        timing: None,
+        protocol: None,
     };
     
     let is_failure = manager
@@ -597,6 +603,8 @@ async fn test_session_failure_handling() {
         headers: None,
         body: b"Access denied - session expired".to_vec(),
         tls: None,
+        timing: None,
+        protocol: None,
     };
     
     // Handle authentication failure
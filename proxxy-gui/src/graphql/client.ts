@@ -15,6 +15,10 @@ import { RetryLink } from '@apollo/client/link/retry';
 const GRAPHQL_HTTP_ENDPOINT = 'http://localhost:9090/graphql';
 const GRAPHQL_WS_ENDPOINT = 'ws://localhost:9090/graphql/ws';
 
+// API token (admin token or API key) when the orchestrator requires authentication
+const API_TOKEN: string | undefined = import.meta.env.VITE_PROXXY_API_TOKEN;
+const authHeaders: Record<string, string> = API_TOKEN ? { Authorization: `Bearer ${API_TOKEN}` } : {};
+
 // Connection status management
 export interface ConnectionStatus {
   graphql: 'connected' | 'disconnected' | 'reconnecting';
@@ -41,6 +45,7 @@ const httpLink = createHttpLink({
   credentials: 'same-origin',
   headers: {
     'X-Proxxy-Client': 'GUI',
+    ...authHeaders,
   },
 });
 
@@ -76,6 +81,7 @@ const wsClient = createClient({
   url: GRAPHQL_WS_ENDPOINT,
   connectionParams: {
     'X-Proxxy-Client': 'GUI',
+    ...(API_TOKEN ? { authorization: `Bearer ${API_TOKEN}` } : {}),
   },
   retryAttempts: Infinity, // Retry forever
   shouldRetry: (errOrCloseEvent) => {
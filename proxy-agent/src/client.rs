@@ -1,12 +1,16 @@
 use proxy_core::pb::proxy_service_client::ProxyServiceClient;
 use proxy_core::pb::{MetricsCommand, RegisterAgentRequest, SystemMetricsEvent, TrafficEvent, HeartbeatRequest};
-use proxy_core::{SystemMetricsCollector, SystemMetricsCollectorConfig};
+use proxy_core::{CaptureController, CertificateAuthority, ClientCertificateConfig, DnsConfig, EgressPolicy, InterceptController, LinkStatus, ScriptController, SystemMetricsCollector, SystemMetricsCollectorConfig, TlsPolicyConfig};
 use std::sync::Arc;
 use std::time::Duration;
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
 use tokio::sync::{mpsc, Mutex};
 use tokio_stream::wrappers::ReceiverStream;
 use tracing::{error, info, warn, debug};
 
+/// How often interception rule counters are reported while they change
+const RULE_STATS_INTERVAL: Duration = Duration::from_secs(10);
+
 /// Tracks active attack requests for graceful shutdown
 #[derive(Debug, Clone)]
 struct AttackTracker {
@@ -64,20 +68,152 @@ impl AttackTracker {
     }
 }
 
-pub struct OrchestratorClient {
+/// Settings handed to the agent by the orchestrator on registration
+#[derive(Debug, Clone)]
+pub struct Registration {
+    pub ca_cert_pem: String,
+    pub ca_key_pem: String,
+    /// Project DNS settings (system resolver when the orchestrator sends none)
+    pub dns_config: DnsConfig,
+    /// Hosts resolved to a fixed address instead of through DNS
+    pub host_overrides: std::collections::HashMap<String, std::net::IpAddr>,
+    /// Client certificates presented to upstreams requiring mutual TLS
+    pub client_certificates: Vec<ClientCertificateConfig>,
+    /// TLS settings for upstream connections, per host pattern
+    pub tls_policies: Vec<TlsPolicyConfig>,
+}
+
+/// Opens gRPC channels to the orchestrator, over TLS when configured
+#[derive(Debug, Clone)]
+pub struct OrchestratorConnector {
     endpoint: String,
+    tls: Option<ClientTlsConfig>,
+}
+
+impl OrchestratorConnector {
+    pub fn new(endpoint: String) -> Self {
+        Self { endpoint, tls: None }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Whether the orchestrator's identity is verified
+    pub fn is_tls(&self) -> bool {
+        self.tls.is_some()
+    }
+
+    pub async fn connect(&self) -> Result<ProxyServiceClient<Channel>, tonic::transport::Error> {
+        let mut endpoint = Endpoint::from_shared(self.endpoint.clone())?;
+        if let Some(tls) = &self.tls {
+            endpoint = endpoint.tls_config(tls.clone())?;
+        }
+        Ok(ProxyServiceClient::new(endpoint.connect().await?))
+    }
+}
+
+pub struct OrchestratorClient {
+    connector: OrchestratorConnector,
     pub agent_id: String,
     name: String,
     attack_tracker: AttackTracker,
+    egress_echo_url: String,
+    egress_check_interval_seconds: u64,
+    intercept_controller: InterceptController,
+    script_controller: ScriptController,
+    capture_controller: CaptureController,
+    certificate_authority: Option<Arc<CertificateAuthority>>,
+    link_status: LinkStatus,
+    /// Egress routes of the proxy, followed by replayed requests too
+    egress: Arc<EgressPolicy>,
 }
 
 impl OrchestratorClient {
     pub fn new(endpoint: String, agent_id: String, name: String) -> Self {
+        let metrics_defaults = SystemMetricsCollectorConfig::default();
         Self {
-            endpoint,
+            connector: OrchestratorConnector::new(endpoint),
             agent_id,
             name,
             attack_tracker: AttackTracker::new(),
+            egress_echo_url: metrics_defaults.egress_echo_url,
+            egress_check_interval_seconds: metrics_defaults.egress_check_interval_seconds,
+            intercept_controller: InterceptController::new(),
+            script_controller: ScriptController::new(),
+            capture_controller: CaptureController::new(),
+            certificate_authority: None,
+            link_status: LinkStatus::new(),
+            egress: Arc::default(),
+        }
+    }
+
+    /// Connect over TLS: the orchestrator must present a certificate from the
+    /// pinned CA, and this agent presents its enrolled client certificate
+    pub fn with_tls(mut self, tls: ClientTlsConfig) -> Self {
+        self.connector.tls = Some(tls);
+        self
+    }
+
+    /// Echo endpoint and interval for the egress IP reported in heartbeats
+    pub fn with_egress_check(mut self, echo_url: String, interval_seconds: u64) -> Self {
+        self.egress_echo_url = echo_url;
+        self.egress_check_interval_seconds = interval_seconds;
+        self
+    }
+
+    /// Controller shared with the proxy: rules and decisions pushed by the
+    /// orchestrator are applied to it
+    pub fn with_intercept_controller(mut self, controller: InterceptController) -> Self {
+        self.intercept_controller = controller;
+        self
+    }
+
+    /// Controller shared with the proxy: hook scripts pushed by the
+    /// orchestrator replace its scripts
+    pub fn with_script_controller(mut self, controller: ScriptController) -> Self {
+        self.script_controller = controller;
+        self
+    }
+
+    /// Controller shared with the proxy: capture pauses pushed by the
+    /// orchestrator are applied to it
+    pub fn with_capture_controller(mut self, controller: CaptureController) -> Self {
+        self.capture_controller = controller;
+        self
+    }
+
+    /// CA shared with the proxy: a rotated CA pushed by the orchestrator, or
+    /// handed out on re-registration, replaces its root
+    pub fn with_certificate_authority(mut self, ca: Arc<CertificateAuthority>) -> Self {
+        self.certificate_authority = Some(ca);
+        self
+    }
+
+    /// Status shared with the admin API: up while the traffic stream is
+    pub fn with_link_status(mut self, status: LinkStatus) -> Self {
+        self.link_status = status;
+        self
+    }
+
+    /// Egress routes shared with the proxy: Repeater and Intruder requests
+    /// leave through the same upstream proxies and tunnels
+    pub fn with_egress_routes(mut self, egress: Arc<EgressPolicy>) -> Self {
+        self.egress = egress;
+        self
+    }
+
+    /// Swap the proxy's CA for the orchestrator's, if they differ
+    fn apply_ca(ca: Option<&CertificateAuthority>, cert_pem: &str, key_pem: &str) {
+        let Some(ca) = ca else {
+            return;
+        };
+        if ca.get_ca_cert_pem().is_ok_and(|current| current == cert_pem) {
+            return;
+        }
+        match ca.replace_from_pem(cert_pem, key_pem) {
+            Ok(()) => info!("🔐 Switched to the orchestrator's rotated CA"),
+            Err(e) => error!("Ignoring rotated CA from orchestrator: {}", e),
         }
     }
 
@@ -89,6 +225,7 @@ impl OrchestratorClient {
         session_id: Option<String>,
         session_headers: Option<std::collections::HashMap<String, String>>,
         attack_tracker: Option<AttackTracker>,
+        streamed_body: Option<proxy_core::pb::StreamedBody>,
     ) -> proxy_core::pb::TrafficEvent {
         use proxy_core::pb::{traffic_event, HttpHeaders, HttpResponseData};
 
@@ -130,12 +267,36 @@ impl OrchestratorClient {
             }
         }
 
-        if !req_data.body.is_empty() {
+        if let Some(streamed_body) = streamed_body {
+            match crate::streamed_body::request_body(streamed_body).await {
+                Ok((body, length)) => {
+                    builder = builder.header(reqwest::header::CONTENT_LENGTH, length).body(body);
+                }
+                Err(e) => {
+                    error!("Cannot build streamed body: {}", e);
+                    if let Some(ref tracker) = attack_tracker {
+                        tracker.remove_request(&req_id).await;
+                    }
+                    return proxy_core::pb::TrafficEvent {
+                        request_id: req_id,
+                        event: Some(traffic_event::Event::Response(HttpResponseData {
+                            status_code: 502,
+                            headers: None,
+                            body: format!("Request Error: {}", e).into_bytes().into(),
+                            tls: None,
+                            timing: None,
+                            protocol: String::new(),
+                        })),
+                    };
+                }
+            }
+        } else if !req_data.body.is_empty() {
             builder = builder.body(req_data.body);
         }
 
         // Execute request with error handling
-        let result = match builder.send().await {
+        let (sent, timer) = crate::timing::send(builder).await;
+        let result = match sent {
             Ok(resp) => {
                 // Convert headers
                 let mut headers_map = std::collections::HashMap::new();
@@ -147,7 +308,9 @@ impl OrchestratorClient {
                 }
 
                 let status = resp.status().as_u16() as i32;
-                let body = resp.bytes().await.unwrap_or_default().to_vec();
+                let protocol = protocol_name(resp.version());
+                let body = resp.bytes().await.unwrap_or_default();
+                let timing = timer.finish();
 
                 proxy_core::pb::TrafficEvent {
                     request_id: req_id.clone(),
@@ -158,6 +321,8 @@ impl OrchestratorClient {
                         }),
                         body,
                         tls: None,
+                        timing: Some(timing),
+                        protocol: protocol.to_string(),
                     })),
                 }
             }
@@ -168,8 +333,10 @@ impl OrchestratorClient {
                     event: Some(traffic_event::Event::Response(HttpResponseData {
                         status_code: 502,
                         headers: None,
-                        body: format!("Request Error: {}", e).into_bytes(),
+                        body: format!("Request Error: {}", e).into_bytes().into(),
                         tls: None,
+                        timing: Some(timer.finish()),
+                        protocol: String::new(),
                     })),
                 }
             }
@@ -183,10 +350,15 @@ impl OrchestratorClient {
         result
     }
 
-    pub async fn register(&self) -> Result<(String, String), String> {
-        let mut client = ProxyServiceClient::connect(self.endpoint.clone())
-            .await
-            .map_err(|e| e.to_string())?;
+    pub async fn register(&self) -> Result<Registration, String> {
+        if !self.connector.is_tls() {
+            warn!(
+                "Fetching CA material over plaintext gRPC from {}; the orchestrator is not verified (see --orchestrator-ca)",
+                self.connector.endpoint()
+            );
+        }
+        // With TLS the handshake has verified the orchestrator before this call
+        let mut client = self.connector.connect().await.map_err(|e| e.to_string())?;
 
         let req = tonic::Request::new(RegisterAgentRequest {
             agent_id: self.agent_id.clone(),
@@ -216,7 +388,50 @@ impl OrchestratorClient {
                 "CA key preview: {}",
                 &inner.ca_key_pem.chars().take(50).collect::<String>()
             );
-            Ok((inner.ca_cert_pem, inner.ca_key_pem))
+            let host_overrides = inner
+                .host_overrides
+                .into_iter()
+                .filter_map(|(host, address)| match address.parse() {
+                    Ok(ip) => Some((host, ip)),
+                    Err(_) => {
+                        warn!("Ignoring host override {} with invalid address {}", host, address);
+                        None
+                    }
+                })
+                .collect();
+            let client_certificates = inner
+                .client_certificates
+                .into_iter()
+                .filter_map(|cert| match ClientCertificateConfig::try_from(cert) {
+                    Ok(config) => Some(config),
+                    Err(e) => {
+                        warn!("Ignoring client certificate: {}", e);
+                        None
+                    }
+                })
+                .collect();
+            let tls_policies = inner
+                .tls_policies
+                .into_iter()
+                .filter_map(|policy| {
+                    let host_pattern = policy.host_pattern.clone();
+                    match TlsPolicyConfig::try_from(policy) {
+                        Ok(config) => Some(config),
+                        Err(e) => {
+                            warn!("Ignoring TLS policy for {}: {}", host_pattern, e);
+                            None
+                        }
+                    }
+                })
+                .collect();
+            Ok(Registration {
+                ca_cert_pem: inner.ca_cert_pem,
+                ca_key_pem: inner.ca_key_pem,
+                dns_config: inner.dns_config.map(DnsConfig::from).unwrap_or_default(),
+                host_overrides,
+                client_certificates,
+                tls_policies,
+            })
         } else {
             Err(format!("Registration rejected: {}", inner.message))
         }
@@ -226,23 +441,25 @@ impl OrchestratorClient {
         let max_delay = Duration::from_secs(60);
         let mut attempt = 0;
 
-        // Shared HTTP client for replaying requests
-        let http_client = reqwest::Client::builder()
-            .danger_accept_invalid_certs(true)
-            .build()
-            .unwrap_or_default();
-
         loop {
-            // 1. Registration Loop
-            loop {
+            // 1. Registration Loop. Shared HTTP clients for replaying requests
+            // are rebuilt with the DNS and TLS settings of each registration.
+            let replay_clients = loop {
                 info!(
                     "Attempting to register with Orchestrator at {}...",
-                    self.endpoint
+                    self.connector.endpoint()
                 );
                 match self.register().await {
-                    Ok(_) => {
+                    Ok(registration) => {
+                        // Rotated while this agent was disconnected
+                        Self::apply_ca(
+                            self.certificate_authority.as_deref(),
+                            &registration.ca_cert_pem,
+                            &registration.ca_key_pem,
+                        );
                         attempt = 0; // Reset backoff on success
-                        break;
+                        break crate::replay::ReplayClients::from_registration(&registration)
+                            .with_egress(self.egress.clone());
                     }
                     Err(e) => {
                         let delay = Duration::from_secs(2u64.pow(attempt.min(6))).min(max_delay);
@@ -251,7 +468,7 @@ impl OrchestratorClient {
                         attempt += 1;
                     }
                 }
-            }
+            };
 
             // 2. Start metrics streaming and heartbeat
             let metrics_handle = self.start_metrics_streaming().await;
@@ -259,7 +476,7 @@ impl OrchestratorClient {
 
             // 3. Traffic Streaming Loop
             info!("Starting traffic stream...");
-            match ProxyServiceClient::connect(self.endpoint.clone()).await {
+            match self.connector.connect().await {
                 Ok(mut client) => {
                     let (tx_stream, rx_stream) = mpsc::channel(1024);
                     let outbound = ReceiverStream::new(rx_stream);
@@ -273,10 +490,15 @@ impl OrchestratorClient {
                         Ok(response) => {
                             let mut inbound = response.into_inner();
                             info!("Traffic stream established");
+                            self.link_status.set_connected(true);
 
                             let tx_replay = tx_stream.clone();
-                            let http_client = http_client.clone();
+                            let replay_clients = replay_clients.clone();
                             let attack_tracker = self.attack_tracker.clone();
+                            let intercept_controller = self.intercept_controller.clone();
+                            let script_controller = self.script_controller.clone();
+                            let capture_controller = self.capture_controller.clone();
+                            let certificate_authority = self.certificate_authority.clone();
 
                             // Spawn response handler (commands)
                             let stream_handle = tokio::spawn(async move {
@@ -294,13 +516,13 @@ impl OrchestratorClient {
                                                 req_data.method, req_data.url
                                             );
 
-                                            let client = http_client.clone();
+                                            let client = replay_clients.for_url(&req_data.url);
                                             let tx = tx_replay.clone();
                                             let req_id = exec_req.request_id.clone();
 
                                             tokio::spawn(async move {
                                                 let result_event = Self::execute_http_request(
-                                                    &client, req_data, req_id, None, None, None
+                                                    &client, req_data, req_id, None, None, None, None
                                                 ).await;
 
                                                 if let Err(e) = tx.send(result_event).await {
@@ -317,7 +539,7 @@ impl OrchestratorClient {
                                                         req_data.method, req_data.url, repeater_req.request_id
                                                     );
 
-                                                    let client = http_client.clone();
+                                                    let client = replay_clients.for_url(&req_data.url);
                                                     let tx = tx_replay.clone();
                                                     let req_id = repeater_req.request_id.clone();
                                                     let session_id = if repeater_req.session_id.is_empty() { 
@@ -336,7 +558,7 @@ impl OrchestratorClient {
                                                     tokio::spawn(async move {
                                                         info!("🔄 [REPEATER] Executing HTTP request... (request_id: {})", req_id_log);
                                                         let result_event = Self::execute_http_request(
-                                                            &client, req_data, req_id, session_id, session_headers, tracker
+                                                            &client, req_data, req_id, session_id, session_headers, tracker, None
                                                         ).await;
 
                                                         // Log the result
@@ -367,7 +589,7 @@ impl OrchestratorClient {
                                                         req_data.method, req_data.url, intruder_req.payload_values
                                                     );
 
-                                                    let client = http_client.clone();
+                                                    let client = replay_clients.for_url(&req_data.url);
                                                     let tx = tx_replay.clone();
                                                     let req_id = intruder_req.request_id.clone();
                                                     let session_id = if intruder_req.session_id.is_empty() { 
@@ -381,10 +603,11 @@ impl OrchestratorClient {
                                                         Some(intruder_req.session_headers.clone())
                                                     };
                                                     let tracker = Some(attack_tracker.clone());
+                                                    let streamed_body = intruder_req.streamed_body.clone();
 
                                                     tokio::spawn(async move {
                                                         let result_event = Self::execute_http_request(
-                                                            &client, req_data, req_id, session_id, session_headers, tracker
+                                                            &client, req_data, req_id, session_id, session_headers, tracker, streamed_body
                                                         ).await;
 
                                                         if let Err(e) = tx.send(result_event).await {
@@ -461,6 +684,34 @@ impl OrchestratorClient {
                                                 }
                                             }
                                         }
+                                        Some(intercept_command::Command::InterceptRules(rules)) => {
+                                            intercept_controller.set_rules(rules);
+                                        }
+                                        Some(intercept_command::Command::HookScripts(scripts)) => {
+                                            script_controller.set_scripts(scripts);
+                                        }
+                                        Some(intercept_command::Command::Capture(state)) => {
+                                            capture_controller.set_state(state);
+                                        }
+                                        Some(intercept_command::Command::CaRotation(rotation)) => {
+                                            Self::apply_ca(
+                                                certificate_authority.as_deref(),
+                                                &rotation.ca_cert_pem,
+                                                &rotation.ca_key_pem,
+                                            );
+                                        }
+                                        Some(intercept_command::Command::Decision(decision)) => {
+                                            let request_id = decision.request_id.clone();
+                                            let resumed = intercept_controller.resume_request(
+                                                &request_id,
+                                                proxy_core::pb::InterceptCommand {
+                                                    command: Some(intercept_command::Command::Decision(decision)),
+                                                },
+                                            );
+                                            if !resumed {
+                                                warn!("Interception decision for unknown request {}", request_id);
+                                            }
+                                        }
                                         _ => {
                                             warn!("Received unknown command type");
                                         }
@@ -469,6 +720,11 @@ impl OrchestratorClient {
                                 info!("Stream closed by server");
                             });
 
+                            let stats_handle = Self::start_rule_stats_reporting(
+                                self.intercept_controller.clone(),
+                                tx_stream.clone(),
+                            );
+
                             // Forwarding loop
                             loop {
                                 if stream_handle.is_finished() {
@@ -485,14 +741,17 @@ impl OrchestratorClient {
                                     }
                                     None => {
                                         info!("Log channel closed, shutting down agent client.");
+                                        self.link_status.set_connected(false);
                                         // Cancel metrics streaming
                                         if let Some(handle) = metrics_handle {
                                             handle.abort();
                                         }
+                                        stats_handle.abort();
                                         return;
                                     }
                                 }
                             }
+                            stats_handle.abort();
                         }
                         Err(e) => {
                             error!("Failed to start stream: {}", e);
@@ -506,6 +765,8 @@ impl OrchestratorClient {
                 }
             }
 
+            self.link_status.set_connected(false);
+
             // Cancel metrics and heartbeat streaming before reconnecting
             if let Some(handle) = metrics_handle {
                 handle.abort();
@@ -516,6 +777,37 @@ impl OrchestratorClient {
         }
     }
 
+    /// Send the interception rule counters over the traffic stream whenever
+    /// they changed since the last report
+    fn start_rule_stats_reporting(
+        controller: InterceptController,
+        tx: mpsc::Sender<TrafficEvent>,
+    ) -> tokio::task::JoinHandle<()> {
+        use proxy_core::pb::traffic_event;
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RULE_STATS_INTERVAL);
+            // Nothing to report until the first rule arrives
+            let mut last_sent = Some(proxy_core::pb::InterceptRuleStats::default());
+            loop {
+                interval.tick().await;
+                let stats = controller.rule_stats();
+                if last_sent.as_ref() == Some(&stats) {
+                    continue;
+                }
+                let event = TrafficEvent {
+                    request_id: String::new(),
+                    event: Some(traffic_event::Event::RuleStats(stats.clone())),
+                };
+                if tx.send(event).await.is_err() {
+                    debug!("Traffic stream closed, stopping rule stats reports");
+                    return;
+                }
+                last_sent = Some(stats);
+            }
+        })
+    }
+
     /// Start system metrics streaming in a separate task
     async fn start_metrics_streaming(&self) -> Option<tokio::task::JoinHandle<()>> {
         info!(
@@ -523,10 +815,10 @@ impl OrchestratorClient {
             self.agent_id
         );
 
-        match ProxyServiceClient::connect(self.endpoint.clone()).await {
+        match self.connector.connect().await {
             Ok(mut client) => {
                 let agent_id = self.agent_id.clone();
-                let endpoint = self.endpoint.clone();
+                let connector = self.connector.clone();
 
                 let handle = tokio::spawn(async move {
                     let mut metrics_collector = SystemMetricsCollector::with_config(
@@ -593,11 +885,12 @@ impl OrchestratorClient {
                         }
 
                         // Reconnect to client if needed
-                        if let Err(e) = ProxyServiceClient::connect(endpoint.clone()).await {
-                            error!("Failed to reconnect metrics client: {}", e);
-                            tokio::time::sleep(Duration::from_secs(5)).await;
-                        } else {
-                            client = ProxyServiceClient::connect(endpoint.clone()).await.unwrap();
+                        match connector.connect().await {
+                            Ok(c) => client = c,
+                            Err(e) => {
+                                error!("Failed to reconnect metrics client: {}", e);
+                                tokio::time::sleep(Duration::from_secs(5)).await;
+                            }
                         }
                     }
                 });
@@ -615,15 +908,21 @@ impl OrchestratorClient {
     async fn start_heartbeat_streaming(&self) -> Option<tokio::task::JoinHandle<()>> {
         info!("Starting heartbeat streaming for agent: {}", self.agent_id);
 
-        match ProxyServiceClient::connect(self.endpoint.clone()).await {
+        match self.connector.connect().await {
             Ok(mut client) => {
                 let agent_id = self.agent_id.clone();
-                let endpoint = self.endpoint.clone();
+                let connector = self.connector.clone();
+
+                let metrics_config = SystemMetricsCollectorConfig {
+                    egress_echo_url: self.egress_echo_url.clone(),
+                    egress_check_interval_seconds: self.egress_check_interval_seconds,
+                    ..Default::default()
+                };
 
                 let handle = tokio::spawn(async move {
                     let mut metrics_collector = SystemMetricsCollector::with_config(
                         agent_id.clone(),
-                        SystemMetricsCollectorConfig::default(),
+                        metrics_config,
                     );
                     
                     // Heartbeat every 30 seconds
@@ -689,7 +988,7 @@ impl OrchestratorClient {
                         }
 
                         // Reconnect client if loop broke
-                        if let Ok(c) = ProxyServiceClient::connect(endpoint.clone()).await {
+                        if let Ok(c) = connector.connect().await {
                              client = c;
                         } else {
                              tokio::time::sleep(Duration::from_secs(5)).await;
@@ -706,13 +1005,27 @@ impl OrchestratorClient {
     }
 }
 
+/// Names as in `proxy_core::handlers::protocol_name`, for the `http` 1.x
+/// version type reqwest uses
+fn protocol_name(version: reqwest::Version) -> &'static str {
+    match version {
+        reqwest::Version::HTTP_09 => "HTTP/0.9",
+        reqwest::Version::HTTP_10 => "HTTP/1.0",
+        reqwest::Version::HTTP_11 => "HTTP/1.1",
+        reqwest::Version::HTTP_2 => "HTTP/2",
+        reqwest::Version::HTTP_3 => "HTTP/3",
+        _ => "",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use axum::{routing::get, Router};
     use proxy_core::pb::proxy_service_server::ProxyService;
     use proxy_core::pb::{
-        intercept_command, ExecuteRequest, HttpRequestData, InterceptCommand, RegisterAgentResponse,
+        intercept_command, ExecuteRequest, HeartbeatResponse, HttpRequestData, InterceptCommand,
+        RegisterAgentResponse,
     };
     use std::net::SocketAddr;
     use tokio::sync::mpsc;
@@ -778,7 +1091,7 @@ mod tests {
                                 method: "GET".to_string(),
                                 url: repeater_url,
                                 headers: None,
-                                body: vec![],
+                                body: Default::default(),
                                 tls: None,
                             }),
                             session_id: "test-session".to_string(),
@@ -800,7 +1113,7 @@ mod tests {
         assert_eq!(event.request_id, "test-repeater-1");
         if let Some(proxy_core::pb::traffic_event::Event::Response(resp)) = event.event {
             assert_eq!(resp.status_code, 200);
-            assert_eq!(resp.body, b"Repeater Response");
+            assert_eq!(&resp.body[..], b"Repeater Response");
         } else {
             panic!("Expected Response event for repeater");
         }
@@ -818,7 +1131,7 @@ mod tests {
                                 method: "GET".to_string(),
                                 url: intruder_url,
                                 headers: None,
-                                body: vec![],
+                                body: Default::default(),
                                 tls: None,
                             }),
                             payload_values: vec!["payload1".to_string(), "payload2".to_string()],
@@ -828,6 +1141,7 @@ mod tests {
                                 headers.insert("X-Attack-Token".to_string(), "attack-token".to_string());
                                 headers
                             },
+                            streamed_body: None,
                         }
                     ))
                 }
@@ -841,7 +1155,7 @@ mod tests {
         assert_eq!(event.request_id, "test-intruder-1");
         if let Some(proxy_core::pb::traffic_event::Event::Response(resp)) = event.event {
             assert_eq!(resp.status_code, 200);
-            assert_eq!(resp.body, b"Intruder Response");
+            assert_eq!(&resp.body[..], b"Intruder Response");
         } else {
             panic!("Expected Response event for intruder");
         }
@@ -946,6 +1260,10 @@ mod tests {
                 message: "".into(),
                 ca_cert_pem: "cert".into(),
                 ca_key_pem: "key".into(),
+                dns_config: None,
+                host_overrides: Default::default(),
+                client_certificates: Vec::new(),
+                tls_policies: Vec::new(),
             }))
         }
 
@@ -982,5 +1300,15 @@ mod tests {
             let (_, rx) = mpsc::channel(1);
             Ok(Response::new(ReceiverStream::new(rx)))
         }
+
+        type HeartbeatStream = ReceiverStream<Result<HeartbeatResponse, Status>>;
+
+        async fn heartbeat(
+            &self,
+            _req: Request<Streaming<HeartbeatRequest>>,
+        ) -> Result<Response<Self::HeartbeatStream>, Status> {
+            let (_, rx) = mpsc::channel(1);
+            Ok(Response::new(ReceiverStream::new(rx)))
+        }
     }
 }
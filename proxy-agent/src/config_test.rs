@@ -26,6 +26,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -35,6 +36,37 @@ mod tests {
             max_body_size: None,
             response_timeout: None,
             stream_timeout: None,
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let config = load_body_capture_config(&args).unwrap();
@@ -56,6 +88,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -65,6 +98,37 @@ mod tests {
             max_body_size: Some(5 * 1024 * 1024), // 5MB
             response_timeout: Some(60),
             stream_timeout: Some(10),
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let config = load_body_capture_config(&args).unwrap();
@@ -90,6 +154,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -99,6 +164,37 @@ mod tests {
             max_body_size: None,
             response_timeout: None,
             stream_timeout: None,
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let config = load_body_capture_config(&args).unwrap();
@@ -135,6 +231,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -144,6 +241,37 @@ mod tests {
             max_body_size: None,
             response_timeout: None,
             stream_timeout: None,
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let config = load_body_capture_config(&args).unwrap();
@@ -169,6 +297,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -178,6 +307,37 @@ mod tests {
             max_body_size: Some(4194304),    // CLI override
             response_timeout: None,
             stream_timeout: None,
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let config = load_body_capture_config(&args).unwrap();
@@ -200,6 +360,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -209,6 +370,37 @@ mod tests {
             max_body_size: None,
             response_timeout: None,
             stream_timeout: None,
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let result = load_body_capture_config(&args);
@@ -229,6 +421,7 @@ mod tests {
             listen_addr: "127.0.0.1".to_string(),
             listen_port: 9095,
             admin_port: 9091,
+            admin_token: None,
             orchestrator_url: "http://127.0.0.1:50051".to_string(),
             name: None,
             ca_cert: None,
@@ -238,6 +431,37 @@ mod tests {
             max_body_size: None,
             response_timeout: Some(0), // Invalid - zero timeout
             stream_timeout: None,
+            proxy_worker_threads: 0,
+            control_worker_threads: 2,
+            shared_runtime: false,
+            max_header_bytes: None,
+            max_header_count: None,
+            max_url_length: None,
+            pac: None,
+            force_http1: false,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval: 60,
+            intercept_timeout: 300,
+            ssh_tunnels: None,
+            egress_routes: None,
+            connection_profiles: None,
+            throttle_rules: None,
+            mail_listeners: None,
+            reverse_listeners: None,
+            transparent: false,
+            transparent_port: 9096,
+            request_id_header: None,
+            target_auth: None,
+            tls_passthrough: Vec::new(),
+            maintain_session: Vec::new(),
+            mitm_fallback_threshold: None,
+            mitm_fallback_duration: None,
+            leaf_key: None,
+            leaf_cert_cache: None,
+            orchestrator_ca: None,
+            client_cert: None,
+            client_key: None,
+            orchestrator_server_name: None,
         };
 
         let result = load_body_capture_config(&args);
@@ -4,9 +4,14 @@
 //! Communicates with the orchestrator via gRPC and uses proxy-core for traffic handling.
 
 use clap::Parser;
-use proxy_core::{BodyCaptureConfig, CertificateAuthority, ProxyConfig, ProxyError, ProxyServer};
+use proxy_core::{
+    BodyCaptureConfig, CertificateAuthority, CertificateConfig, ConnectionPolicy, DataPlaneRuntime, DataPlaneRuntimeConfig, EgressPolicy,
+    InterceptionRule, LeafKeyAlgorithm, MailListenerConfig, MitmFallbackConfig, ProxyConfig, ProxyError, ProxyServer, RequestLimits,
+    ReverseListenerConfig, TargetAuthConfig, TransparentListenerConfig,
+};
 use std::path::PathBuf;
 use tokio;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
 use uuid::Uuid;
 
 #[derive(Parser, Debug, Clone)]
@@ -24,6 +29,10 @@ pub struct Args {
     #[arg(long, default_value_t = 9091)]
     pub admin_port: u16,
 
+    /// Bearer token for the Admin API's control routes (capture, pass-through, cookies); they are disabled when unset
+    #[arg(long, env = "PROXXY_AGENT_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+
     /// URL of the Orchestrator gRPC service
     #[arg(long, default_value = "http://127.0.0.1:50051")]
     pub orchestrator_url: String,
@@ -59,9 +68,137 @@ pub struct Args {
     /// Stream read timeout in seconds (can be overridden by config file)
     #[arg(long)]
     pub stream_timeout: Option<u64>,
+
+    /// Worker threads for the dedicated proxy data-plane runtime (0 = half the available cores)
+    #[arg(long, default_value_t = 0)]
+    pub proxy_worker_threads: usize,
+
+    /// Worker threads for the control-plane runtime (gRPC streaming, admin API, metrics)
+    #[arg(long, default_value_t = 2)]
+    pub control_worker_threads: usize,
+
+    /// Run proxied traffic on the control-plane runtime instead of a dedicated one
+    #[arg(long, default_value_t = false)]
+    pub shared_runtime: bool,
+
+    /// Maximum total header size in bytes; larger requests get 431
+    #[arg(long)]
+    pub max_header_bytes: Option<usize>,
+
+    /// Maximum number of request headers; more get 431
+    #[arg(long)]
+    pub max_header_count: Option<usize>,
+
+    /// Maximum request URL length in bytes; longer URLs get 414
+    #[arg(long)]
+    pub max_url_length: Option<usize>,
+
+    /// PAC file (URL or path) deciding per destination whether to use an upstream proxy
+    #[arg(long)]
+    pub pac: Option<String>,
+
+    /// Speak only HTTP/1.1 to upstream servers (disables HTTP/2 negotiation, for debugging)
+    #[arg(long, default_value_t = false)]
+    pub force_http1: bool,
+
+    /// Echo endpoint used to check the egress IP targets see (plain text or JSON "ip")
+    #[arg(long, default_value = "https://api.ipify.org")]
+    pub egress_echo_url: String,
+
+    /// Seconds between egress IP checks; lower notices NAT/VPN rotation sooner
+    #[arg(long, default_value_t = 60)]
+    pub egress_check_interval: u64,
+
+    /// Seconds a request paused by an interception rule waits for a decision before it is forwarded unchanged
+    #[arg(long, default_value_t = 300)]
+    pub intercept_timeout: u64,
+
+    /// Path to SSH tunnel configuration (JSON) for reaching segmented networks
+    #[arg(long)]
+    pub ssh_tunnels: Option<PathBuf>,
+
+    /// Path to per-scope egress routes (JSON): direct, upstream proxy, SOCKS5 or SSH tunnel
+    #[arg(long)]
+    pub egress_routes: Option<PathBuf>,
+
+    /// Path to upstream connection profiles (JSON): timeouts and keep-alive, with per-scope overrides
+    #[arg(long)]
+    pub connection_profiles: Option<PathBuf>,
+
+    /// Path to throttling rules (JSON list of interception rules with a Throttle action) simulating slow networks
+    #[arg(long)]
+    pub throttle_rules: Option<PathBuf>,
+
+    /// Path to SMTP/IMAP capture listeners (JSON list), relayed to the configured mail servers
+    #[arg(long)]
+    pub mail_listeners: Option<PathBuf>,
+
+    /// Send each captured request's ID upstream in this header (X-Proxxy-Request-Id when given without a name)
+    #[arg(long, num_args = 0..=1, default_missing_value = "X-Proxxy-Request-Id")]
+    pub request_id_header: Option<String>,
+
+    /// Path to reverse proxy listeners (JSON list) routing TLS clients to upstreams by SNI
+    #[arg(long)]
+    pub reverse_listeners: Option<PathBuf>,
+
+    /// Accept connections redirected by the packet filter (iptables REDIRECT, pf divert-to) on --transparent-port
+    #[arg(long, default_value_t = false)]
+    pub transparent: bool,
+
+    /// Port of the transparent listener
+    #[arg(long, default_value_t = 9096)]
+    pub transparent_port: u16,
+
+    /// Path to target credentials (JSON list of host pattern, basic/digest/ntlm scheme, username, password, optional NTLM domain)
+    #[arg(long)]
+    pub target_auth: Option<PathBuf>,
+
+    /// Host patterns (comma-separated, `*` wildcards) tunnelled without TLS interception, e.g. pinned apps
+    #[arg(long, value_delimiter = ',')]
+    pub tls_passthrough: Vec<String>,
+
+    /// Host patterns (comma-separated, `*` wildcards) whose requests get the cookies upstreams set attached
+    #[arg(long, value_delimiter = ',')]
+    pub maintain_session: Vec<String>,
+
+    /// Interception failures of a host within five minutes that tunnel it without interception (0 disables)
+    #[arg(long)]
+    pub mitm_fallback_threshold: Option<u32>,
+
+    /// Seconds a host stays tunnelled without interception after repeated failures
+    #[arg(long)]
+    pub mitm_fallback_duration: Option<u64>,
+
+    /// Key type of intercepted hosts' certificates: ecdsa-p256 (default) or rsa-2048 for clients without ECDSA
+    #[arg(long)]
+    pub leaf_key: Option<LeafKeyAlgorithm>,
+
+    /// Hosts whose intercepted certificates are kept for reuse (least recently used go first)
+    #[arg(long)]
+    pub leaf_cert_cache: Option<usize>,
+
+    /// Orchestrator control-plane CA (PEM); connects over TLS and verifies the orchestrator before fetching CA material
+    #[arg(long)]
+    pub orchestrator_ca: Option<PathBuf>,
+
+    /// Client certificate (PEM) issued when this agent was enrolled
+    #[arg(long)]
+    pub client_cert: Option<PathBuf>,
+
+    /// Private key (PEM) of the enrolled client certificate
+    #[arg(long)]
+    pub client_key: Option<PathBuf>,
+
+    /// Name expected in the orchestrator's certificate (defaults to the host of --orchestrator-url)
+    #[arg(long)]
+    pub orchestrator_server_name: Option<String>,
 }
 
 pub mod client;
+pub mod replay;
+pub mod streamed_body;
+pub mod timing;
+pub mod tunnel;
 use client::OrchestratorClient;
 
 #[cfg(test)]
@@ -237,11 +374,164 @@ fn load_body_capture_config(args: &Args) -> Result<BodyCaptureConfig, Box<dyn st
     Ok(config)
 }
 
+/// Load per-scope egress routes, with tunnel routes pointed at the tunnels' SOCKS ports
+fn load_egress_routes(
+    args: &Args,
+    tunnels: &tunnel::TunnelsConfig,
+) -> Result<EgressPolicy, Box<dyn std::error::Error>> {
+    let Some(path) = &args.egress_routes else {
+        return Ok(EgressPolicy::default());
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read egress routes file: {}", e))?;
+    let mut egress: EgressPolicy = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse egress routes file: {}", e))?;
+    egress.validate().map_err(|e| format!("Invalid egress routes: {}", e))?;
+    tunnels.resolve_egress_routes(&mut egress)?;
+
+    tracing::info!("Loaded {} egress route(s) from {:?}", egress.routes.len(), path);
+    Ok(egress)
+}
+
+/// Load upstream connection profiles
+fn load_connection_profiles(args: &Args) -> Result<ConnectionPolicy, Box<dyn std::error::Error>> {
+    let Some(path) = &args.connection_profiles else {
+        return Ok(ConnectionPolicy::default());
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read connection profiles file: {}", e))?;
+    let connection: ConnectionPolicy = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse connection profiles file: {}", e))?;
+    connection.validate().map_err(|e| format!("Invalid connection profiles: {}", e))?;
+
+    tracing::info!("Loaded {} scoped connection profile(s) from {:?}", connection.scopes.len(), path);
+    Ok(connection)
+}
+
+/// Load throttling rules; other rule actions are not applied by the agent
+fn load_throttle_rules(args: &Args) -> Result<Vec<InterceptionRule>, Box<dyn std::error::Error>> {
+    let Some(path) = &args.throttle_rules else {
+        return Ok(Vec::new());
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read throttle rules file: {}", e))?;
+    let rules: Vec<InterceptionRule> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse throttle rules file: {}", e))?;
+    if let Some(rule) = rules.iter().find(|rule| rule.action.throttle().is_none()) {
+        return Err(format!("Throttle rule '{}' must have a Throttle action", rule.name).into());
+    }
+
+    tracing::info!("Loaded {} throttle rule(s) from {:?}", rules.len(), path);
+    Ok(rules)
+}
+
+/// Load SMTP/IMAP capture listeners
+fn load_mail_listeners(args: &Args) -> Result<Vec<MailListenerConfig>, Box<dyn std::error::Error>> {
+    let Some(path) = &args.mail_listeners else {
+        return Ok(Vec::new());
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read mail listeners file: {}", e))?;
+    let listeners: Vec<MailListenerConfig> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse mail listeners file: {}", e))?;
+    for listener in &listeners {
+        listener
+            .validate()
+            .map_err(|e| format!("Invalid mail listener on port {}: {}", listener.listen_port, e))?;
+    }
+
+    tracing::info!("Loaded {} mail listener(s) from {:?}", listeners.len(), path);
+    Ok(listeners)
+}
+
+/// Load SNI-routed reverse proxy listeners
+fn load_reverse_listeners(args: &Args) -> Result<Vec<ReverseListenerConfig>, Box<dyn std::error::Error>> {
+    let Some(path) = &args.reverse_listeners else {
+        return Ok(Vec::new());
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read reverse listeners file: {}", e))?;
+    let listeners: Vec<ReverseListenerConfig> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse reverse listeners file: {}", e))?;
+    for listener in &listeners {
+        listener
+            .validate()
+            .map_err(|e| format!("Invalid reverse listener on port {}: {}", listener.listen_port, e))?;
+    }
+
+    tracing::info!("Loaded {} reverse listener(s) from {:?}", listeners.len(), path);
+    Ok(listeners)
+}
+
+/// Load credentials for targets behind HTTP authentication
+fn load_target_auth(args: &Args) -> Result<Vec<TargetAuthConfig>, Box<dyn std::error::Error>> {
+    let Some(path) = &args.target_auth else {
+        return Ok(Vec::new());
+    };
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read target auth file: {}", e))?;
+    let targets: Vec<TargetAuthConfig> = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse target auth file: {}", e))?;
+    for target in &targets {
+        target
+            .validate()
+            .map_err(|e| format!("Invalid target auth for '{}': {}", target.host_pattern, e))?;
+    }
+
+    tracing::info!("Loaded credentials for {} target host pattern(s) from {:?}", targets.len(), path);
+    Ok(targets)
+}
+
+/// TLS settings for the orchestrator channel, when a CA to verify it is given
+fn load_orchestrator_tls(args: &Args) -> Result<Option<ClientTlsConfig>, Box<dyn std::error::Error>> {
+    let Some(ca_path) = &args.orchestrator_ca else {
+        if args.client_cert.is_some() || args.client_key.is_some() {
+            return Err("--client-cert and --client-key need --orchestrator-ca".into());
+        }
+        return Ok(None);
+    };
+    if !args.orchestrator_url.starts_with("https://") {
+        return Err("--orchestrator-ca needs an https:// --orchestrator-url".into());
+    }
+
+    let ca = std::fs::read_to_string(ca_path)
+        .map_err(|e| format!("Failed to read orchestrator CA: {}", e))?;
+    let mut tls = ClientTlsConfig::new().ca_certificate(Certificate::from_pem(ca));
+
+    match (&args.client_cert, &args.client_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = std::fs::read_to_string(cert_path)
+                .map_err(|e| format!("Failed to read client certificate: {}", e))?;
+            let key = std::fs::read_to_string(key_path)
+                .map_err(|e| format!("Failed to read client key: {}", e))?;
+            tls = tls.identity(Identity::from_pem(cert, key));
+        }
+        (None, None) => {
+            tracing::warn!("No client certificate given; an orchestrator requiring enrolled agents will refuse this one");
+        }
+        _ => return Err("--client-cert and --client-key must be given together".into()),
+    }
+
+    if let Some(name) = &args.orchestrator_server_name {
+        tls = tls.domain_name(name.clone());
+    }
+    Ok(Some(tls))
+}
+
 pub async fn run_agent(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     // Logging should be initialized by the caller (main or test)
 
     tracing::info!("Starting Proxy Agent...");
     tracing::info!("  Listen: {}:{}", args.listen_addr, args.listen_port);
+    if args.transparent {
+        tracing::info!("  Transparent: {}:{}", args.listen_addr, args.transparent_port);
+    }
     tracing::info!("  Admin:  {}:{}", args.listen_addr, args.admin_port);
     tracing::info!("  Orch:   {}", args.orchestrator_url);
 
@@ -249,6 +539,26 @@ pub async fn run_agent(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     let body_capture_config = load_body_capture_config(&args)?;
     tracing::info!("Body capture configuration loaded successfully");
 
+    let tunnels_config = match &args.ssh_tunnels {
+        Some(path) => tunnel::TunnelsConfig::load(path)?,
+        None => tunnel::TunnelsConfig::default(),
+    };
+    let egress = load_egress_routes(&args, &tunnels_config)?;
+    let connection = load_connection_profiles(&args)?;
+    let throttle_rules = load_throttle_rules(&args)?;
+    let mail_listeners = load_mail_listeners(&args)?;
+    let reverse_listeners = load_reverse_listeners(&args)?;
+    let target_auth = load_target_auth(&args)?;
+    let orchestrator_tls = load_orchestrator_tls(&args)?;
+
+    // Tunnels come up first; the orchestrator itself may only be reachable through one
+    let _tunnels = if tunnels_config.tunnels.is_empty() {
+        None
+    } else {
+        tracing::info!("Starting {} SSH tunnel(s)...", tunnels_config.tunnels.len());
+        Some(tunnel::start_tunnels(&tunnels_config, std::time::Duration::from_secs(15)).await)
+    };
+
     // channel for traffic logs
     let (tx, rx) = tokio::sync::mpsc::channel(100);
 
@@ -263,17 +573,20 @@ pub async fn run_agent(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     tracing::info!("Agent Name: {}", agent_name);
 
     // Start Orchestrator Client
-    let client = OrchestratorClient::new(
+    let mut client = OrchestratorClient::new(
         args.orchestrator_url.clone(),
         agent_id.clone(),
         agent_name.clone(),
     );
+    if let Some(tls) = &orchestrator_tls {
+        client = client.with_tls(tls.clone());
+    }
 
     // Initial Registration to fetch CA
     tracing::info!("Registering with Orchestrator to fetch CA...");
-    let (ca_cert, ca_key) = loop {
+    let registration = loop {
         match client.register().await {
-            Ok(creds) => break creds,
+            Ok(registration) => break registration,
             Err(e) => {
                 tracing::warn!("Failed to register/fetch CA: {}. Retrying in 5s...", e);
                 tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
@@ -282,26 +595,103 @@ pub async fn run_agent(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     };
     tracing::info!("Received CA credentials from Orchestrator");
 
-    // Spawn client run loop for traffic streaming
-    let client_for_run =
-        OrchestratorClient::new(args.orchestrator_url.clone(), agent_id.clone(), agent_name.clone());
+    // The proxy holds paused requests in this controller; the client resumes them
+    let intercept_controller = proxy_core::InterceptController::new()
+        .with_hold_timeout(std::time::Duration::from_secs(args.intercept_timeout));
+    // Hook scripts pushed by the orchestrator, swapped in while the proxy runs
+    let script_controller = proxy_core::ScriptController::new();
+    // Capture pauses come from the orchestrator or the admin API
+    let capture_controller = proxy_core::CaptureController::new();
+    // Up while the traffic stream is; the admin API's /readyz reports it
+    let orchestrator_link = proxy_core::LinkStatus::new();
 
-    tokio::spawn(async move {
-        // client.run will re-register as part of its loop, which is fine (idempotent).
-        client_for_run.run(rx).await;
-    });
+    // Spawn client run loop for traffic streaming
+    let mut client_for_run =
+        OrchestratorClient::new(args.orchestrator_url.clone(), agent_id.clone(), agent_name.clone())
+            .with_egress_check(args.egress_echo_url.clone(), args.egress_check_interval)
+            .with_intercept_controller(intercept_controller.clone())
+            .with_script_controller(script_controller.clone())
+            .with_capture_controller(capture_controller.clone())
+            .with_link_status(orchestrator_link.clone())
+            .with_egress_routes(std::sync::Arc::new(egress.clone()));
+    if let Some(tls) = orchestrator_tls {
+        client_for_run = client_for_run.with_tls(tls);
+    }
 
     // Load configuration
+    let default_limits = RequestLimits::default();
+    let default_fallback = MitmFallbackConfig::default();
+    let default_certificates = CertificateConfig::default();
     let config = ProxyConfig {
-        listen_address: args.listen_addr,
+        listen_address: args.listen_addr.clone(),
         listen_port: args.listen_port,
         admin_port: args.admin_port,
+        admin_token: args.admin_token,
         orchestrator_endpoint: args.orchestrator_url,
+        request_limits: RequestLimits {
+            max_header_bytes: args.max_header_bytes.unwrap_or(default_limits.max_header_bytes),
+            max_header_count: args.max_header_count.unwrap_or(default_limits.max_header_count),
+            max_url_length: args.max_url_length.unwrap_or(default_limits.max_url_length),
+        },
+        dns: registration.dns_config,
+        host_overrides: registration.host_overrides,
+        client_certificates: registration.client_certificates,
+        tls_policies: registration.tls_policies,
+        pac_source: args.pac,
+        force_http1: args.force_http1,
+        egress,
+        connection,
+        throttle_rules,
+        mail_listeners,
+        reverse_listeners,
+        transparent_listener: args
+            .transparent
+            .then(|| TransparentListenerConfig::new(args.listen_addr.clone(), args.transparent_port)),
+        request_id_header: args.request_id_header,
+        target_auth,
+        tls_passthrough: args.tls_passthrough,
+        maintain_session: args.maintain_session,
+        certificate_config: CertificateConfig {
+            leaf_key_algorithm: args.leaf_key.unwrap_or_default(),
+            leaf_cache_capacity: args.leaf_cert_cache.unwrap_or(default_certificates.leaf_cache_capacity),
+            ..default_certificates
+        },
+        mitm_fallback: MitmFallbackConfig {
+            failure_threshold: args.mitm_fallback_threshold.unwrap_or(default_fallback.failure_threshold),
+            duration_secs: args.mitm_fallback_duration.unwrap_or(default_fallback.duration_secs),
+            ..default_fallback
+        },
         ..Default::default()
     };
 
+    if !config.dns.is_system() {
+        config.dns.validate()?;
+        tracing::info!(
+            "  Upstream DNS: {:?} via {}",
+            config.dns.mode,
+            config.dns.servers.join(", ")
+        );
+    }
+    for (host, ip) in &config.host_overrides {
+        tracing::info!("  Host override: {} -> {}", host, ip);
+    }
+    for cert in &config.client_certificates {
+        tracing::info!("  Client certificate for {}", cert.host_pattern);
+    }
+    for policy in &config.tls_policies {
+        tracing::info!(
+            "  TLS policy for {}: versions {}-{}{}{}{}",
+            policy.host_pattern,
+            policy.min_version.map(|v| v.to_string()).unwrap_or_else(|| "1.2".to_string()),
+            policy.max_version.map(|v| v.to_string()).unwrap_or_else(|| "1.3".to_string()),
+            if policy.extra_roots_pem.is_some() { ", extra roots" } else { "" },
+            if policy.skip_verification { ", verification skipped" } else { "" },
+            policy.client_hello.map(|profile| format!(", {} ClientHello", profile)).unwrap_or_default(),
+        );
+    }
+
     // Initialize CA from memory
-    let ca = CertificateAuthority::from_pem(&ca_cert, &ca_key)
+    let ca = CertificateAuthority::from_pem(&registration.ca_cert_pem, &registration.ca_key_pem)
         .map_err(|e| ProxyError::Configuration(format!("Failed to init CA from network: {}", e)))?;
 
     let hostname = hostname::get()
@@ -310,11 +700,36 @@ pub async fn run_agent(args: Args) -> Result<(), Box<dyn std::error::Error>> {
         .to_string();
 
     // Create the proxy server with body capture configuration
-    let proxy_server = ProxyServer::new(config, ca)
+    let mut proxy_server = ProxyServer::new(config, ca)
         .with_log_sender(tx)
         .with_body_capture_config(body_capture_config)
+        .with_intercept_controller(intercept_controller)
+        .with_script_controller(script_controller)
+        .with_capture_controller(capture_controller)
+        .with_orchestrator_link(orchestrator_link)
         .with_agent_info(agent_id, agent_name, env!("CARGO_PKG_VERSION").to_string(), hostname);
 
+    // Started once the proxy owns the CA, so CA rotations reach it
+    let client_for_run = client_for_run.with_certificate_authority(proxy_server.ca());
+    tokio::spawn(async move {
+        // client.run will re-register as part of its loop, which is fine (idempotent).
+        client_for_run.run(rx).await;
+    });
+
+    // Keep proxied traffic off the runtime that drives gRPC streaming and the admin API.
+    // The runtime must outlive the server future below.
+    let _data_plane = if args.shared_runtime {
+        tracing::info!("Proxy data path sharing the control-plane runtime");
+        None
+    } else {
+        let runtime = DataPlaneRuntime::new(DataPlaneRuntimeConfig::with_worker_threads(
+            args.proxy_worker_threads,
+        ))?;
+        tracing::info!("  Data plane workers: {}", runtime.worker_threads());
+        proxy_server = proxy_server.with_data_plane_runtime(runtime.handle());
+        Some(runtime)
+    };
+
     tracing::info!("Starting proxy server...");
 
     // We return the server future so it can be awaited or raced
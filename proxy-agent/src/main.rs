@@ -3,13 +3,23 @@
 use clap::Parser;
 use proxy_agent::{run_agent, Args};
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Initialize logging
     tracing_subscriber::fmt::init();
 
     let args = Args::parse();
 
+    // Control-plane runtime; the proxy data path gets its own runtime inside run_agent
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(args.control_worker_threads.max(1))
+        .thread_name("proxxy-control")
+        .enable_all()
+        .build()?;
+
+    runtime.block_on(run(args))
+}
+
+async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     tokio::select! {
         result = run_agent(args) => {
             if let Err(e) = result {
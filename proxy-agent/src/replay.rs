@@ -0,0 +1,411 @@
+//! Clients replaying requests for the orchestrator
+//!
+//! Repeater and Intruder requests leave through reqwest rather than the proxy
+//! path, so hosts whose TLS policy imitates a browser's ClientHello get a
+//! client of their own, built from the same profile, and hosts that require
+//! mutual TLS get one presenting their client certificate. Like the default
+//! client, they accept any server certificate. Host names are looked up with
+//! the project's resolver and host overrides, and egress routes send requests
+//! through the same upstream proxy or tunnel, as they do for proxied traffic.
+
+use crate::client::Registration;
+use proxy_core::{
+    ClientCertificate, ClientCertificateConfig, ClientHelloProfile, CustomDnsResolver, EgressPolicy, EgressRoute,
+    TlsPolicy, TlsPolicyConfig,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tracing::warn;
+
+/// Replay client per host, following the ClientHello profiles of the TLS
+/// policies and presenting the host's client certificate
+#[derive(Clone)]
+pub struct ReplayClients {
+    /// In policy order
+    policies: Arc<Vec<TlsPolicy>>,
+    /// First match wins
+    certificates: Arc<Vec<ClientCertificate>>,
+    resolver: Option<CustomDnsResolver>,
+    egress: Arc<EgressPolicy>,
+    /// Built on first use, by profile and index of the client certificate;
+    /// hosts with neither share the default client
+    clients: Arc<Mutex<HashMap<ClientKey, reqwest::Client>>>,
+}
+
+/// ClientHello profile and client certificate index a client is built for
+type ClientKey = (Option<ClientHelloProfile>, Option<usize>);
+
+impl ReplayClients {
+    pub fn new(policies: &[TlsPolicyConfig]) -> Self {
+        let policies = policies
+            .iter()
+            .filter_map(|config| match TlsPolicy::load(config) {
+                Ok(policy) => Some(policy),
+                Err(e) => {
+                    warn!("Replaying without TLS policy for {}: {}", config.host_pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self {
+            policies: Arc::new(policies),
+            certificates: Arc::default(),
+            resolver: None,
+            egress: Arc::default(),
+            clients: Arc::default(),
+        }
+    }
+
+    /// Clients following the settings an orchestrator registration carries
+    pub fn from_registration(registration: &Registration) -> Self {
+        let clients = Self::new(&registration.tls_policies).with_client_certificates(&registration.client_certificates);
+        match CustomDnsResolver::new(&registration.dns_config) {
+            Ok(resolver) => clients.with_resolver(resolver.with_overrides(&registration.host_overrides)),
+            Err(e) => {
+                warn!("Replaying with the system resolver: {}", e);
+                clients
+            }
+        }
+    }
+
+    /// Present these certificates to the hosts they are configured for; one
+    /// that fails to load is skipped
+    pub fn with_client_certificates(mut self, configs: &[ClientCertificateConfig]) -> Self {
+        let certificates = configs
+            .iter()
+            .filter_map(|config| match ClientCertificate::load(config) {
+                Ok(certificate) => Some(certificate),
+                Err(e) => {
+                    warn!("Replaying without client certificate for {}: {}", config.host_pattern, e);
+                    None
+                }
+            })
+            .collect();
+        self.certificates = Arc::new(certificates);
+        self
+    }
+
+    /// Look up host names with the project's resolver instead of the system's
+    pub fn with_resolver(mut self, resolver: CustomDnsResolver) -> Self {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Send requests along the agent's egress routes
+    pub fn with_egress(mut self, egress: Arc<EgressPolicy>) -> Self {
+        self.egress = egress;
+        self
+    }
+
+    /// Client to send a request for `url` with
+    pub fn for_url(&self, url: &str) -> reqwest::Client {
+        // First match wins, so a policy without a profile still shadows later ones
+        let profile = self.policy_for(url).and_then(TlsPolicy::client_hello);
+        let certificate = self.certificate_for(url);
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients
+            .entry((profile, certificate))
+            .or_insert_with(|| self.build(profile, certificate))
+            .clone()
+    }
+
+    fn policy_for(&self, url: &str) -> Option<&TlsPolicy> {
+        let host = reqwest::Url::parse(url).ok()?.host_str()?.to_string();
+        self.policies.iter().find(|policy| policy.matches(&host))
+    }
+
+    fn certificate_for(&self, url: &str) -> Option<usize> {
+        let host = reqwest::Url::parse(url).ok()?.host_str()?.to_string();
+        self.certificates.iter().position(|certificate| certificate.matches(&host))
+    }
+
+    fn build(&self, profile: Option<ClientHelloProfile>, certificate: Option<usize>) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .connector_layer(crate::timing::ConnectTimingLayer);
+        if let Some(resolver) = &self.resolver {
+            builder = builder.dns_resolver(Arc::new(ReplayResolver(resolver.clone())));
+        }
+        if !self.egress.routes.is_empty() {
+            let egress = self.egress.clone();
+            builder = builder.proxy(reqwest::Proxy::custom(move |url| {
+                route_proxy(egress.route_for_host(url.host_str()?)?)
+            }));
+        }
+        if profile.is_some() || certificate.is_some() {
+            let certificate = certificate.map(|index| &self.certificates[index]);
+            match tls_config(profile, certificate) {
+                Ok(tls) => builder = builder.use_preconfigured_tls(tls),
+                Err(e) => warn!("Replaying with the default TLS settings: {}", e),
+            }
+        }
+        builder.build().unwrap_or_default()
+    }
+}
+
+/// Proxy URL of an egress route; direct routes use none. Tunnel routes are
+/// replaced by their SOCKS ports when the agent loads them.
+fn route_proxy(route: &EgressRoute) -> Option<String> {
+    match route {
+        EgressRoute::HttpProxy { host, port } => Some(format!("http://{}", authority(host, *port))),
+        // The proxy resolves the name, so hosts only known behind it work
+        EgressRoute::Socks5 { host, port } => Some(format!("socks5h://{}", authority(host, *port))),
+        EgressRoute::Direct | EgressRoute::Tunnel { .. } => None,
+    }
+}
+
+fn authority(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// The proxy's resolver, behind reqwest's resolver interface
+struct ReplayResolver(CustomDnsResolver);
+
+impl reqwest::dns::Resolve for ReplayResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.0.clone();
+        Box::pin(async move {
+            // reqwest fills in the port from the request URL
+            let addrs = resolver.lookup(name.as_str()).await?;
+            let addrs: reqwest::dns::Addrs =
+                Box::new(addrs.into_iter().map(|ip| std::net::SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// TLS configuration offering the profile's cipher suites, groups and ALPN
+/// protocols in its order, and presenting `certificate` when asked for one
+fn tls_config(
+    profile: Option<ClientHelloProfile>,
+    certificate: Option<&ClientCertificate>,
+) -> Result<rustls::ClientConfig, rustls::Error> {
+    let ring = rustls::crypto::ring::default_provider();
+    let provider = Arc::new(match profile {
+        Some(profile) => {
+            let cipher_suites = profile
+                .cipher_suites()
+                .iter()
+                .filter_map(|id| ring.cipher_suites.iter().find(|suite| u16::from(suite.suite()) == *id).copied())
+                .collect();
+            let kx_groups = profile
+                .named_groups()
+                .iter()
+                .filter_map(|id| ring.kx_groups.iter().find(|group| u16::from(group.name()) == *id).copied())
+                .collect();
+            rustls::crypto::CryptoProvider {
+                cipher_suites,
+                kx_groups,
+                ..ring
+            }
+        }
+        None => ring,
+    });
+
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)));
+    let mut tls = match certificate {
+        // proxy-core still holds rustls 0.21 types
+        Some(certificate) => {
+            let chain = certificate
+                .chain()
+                .iter()
+                .map(|cert| rustls::pki_types::CertificateDer::from(cert.0.clone()))
+                .collect();
+            let key = rustls::pki_types::PrivateKeyDer::try_from(certificate.key().0.clone()).map_err(|e| {
+                rustls::Error::General(format!("client certificate for {}: {}", certificate.host_pattern(), e))
+            })?;
+            builder.with_client_auth_cert(chain, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    tls.alpn_protocols = match profile {
+        Some(profile) => profile.alpn_protocols().iter().map(|p| p.as_bytes().to_vec()).collect(),
+        None => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+    };
+    Ok(tls)
+}
+
+/// Skips certificate checks but still verifies handshake signatures
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<rustls::crypto::CryptoProvider>);
+
+impl rustls::client::danger::ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_config_keeps_order() {
+        let tls = tls_config(Some(ClientHelloProfile::Firefox), None).unwrap();
+        let suites: Vec<u16> = tls
+            .crypto_provider()
+            .cipher_suites
+            .iter()
+            .map(|suite| u16::from(suite.suite()))
+            .collect();
+        assert_eq!(suites, ClientHelloProfile::Firefox.cipher_suites());
+        assert_eq!(tls.alpn_protocols, vec![b"h2".to_vec(), b"http/1.1".to_vec()]);
+    }
+
+    #[test]
+    fn test_client_selected_by_host() {
+        let policy = |host_pattern: &str, client_hello| TlsPolicyConfig {
+            host_pattern: host_pattern.to_string(),
+            min_version: None,
+            max_version: None,
+            skip_verification: false,
+            extra_roots_pem: None,
+            client_hello,
+        };
+        let clients = ReplayClients::new(&[
+            policy("*.cdn.example", Some(ClientHelloProfile::Chrome)),
+            policy("static.example", None),
+            policy("*.example", Some(ClientHelloProfile::Safari)),
+        ]);
+        let matched = |url: &str| clients.policy_for(url).map(TlsPolicy::client_hello);
+        assert_eq!(matched("https://img.cdn.example/a.png"), Some(Some(ClientHelloProfile::Chrome)));
+        // The first matching policy has no profile, so the default client is used
+        assert_eq!(matched("https://static.example/"), Some(None));
+        assert_eq!(matched("https://www.example/"), Some(Some(ClientHelloProfile::Safari)));
+        assert_eq!(matched("https://other.test/"), None);
+        assert_eq!(matched("not a url"), None);
+    }
+
+    #[test]
+    fn test_client_certificate_per_host() {
+        let generated = rcgen::generate_simple_self_signed(vec!["client.mtls.test".to_string()]).unwrap();
+        let certificate = |host_pattern: &str, key_pem: String| ClientCertificateConfig {
+            host_pattern: host_pattern.to_string(),
+            source: proxy_core::ClientCertificateSource::Pem {
+                cert_pem: generated.serialize_pem().unwrap(),
+                key_pem,
+            },
+        };
+        let clients = ReplayClients::new(&[]).with_client_certificates(&[
+            certificate("broken.test", String::new()),
+            certificate("*.mtls.test", generated.serialize_private_key_pem()),
+        ]);
+
+        // The one that failed to load is skipped
+        assert_eq!(clients.certificates.len(), 1);
+        assert_eq!(clients.certificate_for("https://api.mtls.test/v1"), Some(0));
+        assert_eq!(clients.certificate_for("https://other.test/"), None);
+
+        let tls = tls_config(None, Some(&clients.certificates[0])).unwrap();
+        assert!(tls.client_auth_cert_resolver.has_certs());
+        assert!(!tls_config(None, None).unwrap().client_auth_cert_resolver.has_certs());
+
+        clients.for_url("https://api.mtls.test/v1");
+        clients.for_url("https://other.test/");
+        let built = clients.clients.lock().unwrap();
+        assert!(built.contains_key(&(None, Some(0))) && built.contains_key(&(None, None)));
+    }
+
+    #[tokio::test]
+    async fn test_host_override_used() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = vec![0u8; 4096];
+            let n = stream.read(&mut head).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 10\r\nconnection: close\r\n\r\noverridden")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&head[..n]).into_owned()
+        });
+
+        let registration = Registration {
+            ca_cert_pem: String::new(),
+            ca_key_pem: String::new(),
+            dns_config: Default::default(),
+            host_overrides: HashMap::from([("staging.app.test".to_string(), "127.0.0.1".parse().unwrap())]),
+            client_certificates: Vec::new(),
+            tls_policies: Vec::new(),
+        };
+        let clients = ReplayClients::from_registration(&registration);
+        let url = format!("http://staging.app.test:{}/login", port);
+        let body = clients.for_url(&url).get(&url).send().await.unwrap().text().await.unwrap();
+
+        assert_eq!(body, "overridden");
+        assert!(server.await.unwrap().contains(&format!("host: staging.app.test:{}", port)));
+    }
+
+    #[tokio::test]
+    async fn test_egress_route_used() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Upstream proxy answering absolute-form requests itself
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let proxy = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut head = vec![0u8; 4096];
+            let n = stream.read(&mut head).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 9\r\nconnection: close\r\n\r\nvia proxy")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&head[..n]).into_owned()
+        });
+
+        let egress = EgressPolicy {
+            routes: vec![proxy_core::EgressRule {
+                hosts: vec!["*.corp.test".to_string()],
+                route: EgressRoute::HttpProxy { host: "127.0.0.1".to_string(), port },
+            }],
+        };
+        let clients = ReplayClients::new(&[]).with_egress(Arc::new(egress));
+        let url = "http://admin.corp.test/panel";
+        let body = clients.for_url(url).get(url).send().await.unwrap().text().await.unwrap();
+
+        assert_eq!(body, "via proxy");
+        assert!(proxy.await.unwrap().starts_with("GET http://admin.corp.test/panel HTTP/1.1"));
+        assert_eq!(route_proxy(&EgressRoute::Socks5 { host: "::1".to_string(), port: 1080 }).unwrap(), "socks5h://[::1]:1080");
+        assert_eq!(route_proxy(&EgressRoute::Direct), None);
+    }
+}
@@ -0,0 +1,170 @@
+//! Streamed request bodies
+//!
+//! Intruder upload attacks send their bodies as segments rather than bytes:
+//! inline bytes go out as given, filler is generated a chunk at a time and
+//! agent files are read from disk while the request is sent, so a
+//! multi-gigabyte upload never sits in memory on either side.
+
+use proxy_core::pb::{body_segment::Source, BodySegment, StreamedBody};
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Bytes per chunk handed to the HTTP client
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks buffered ahead of the connection
+const CHUNKS_IN_FLIGHT: usize = 4;
+
+type Chunk = Result<Vec<u8>, std::io::Error>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StreamedBodyError {
+    #[error("Cannot read agent file {path}: {source}")]
+    AgentFile {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Filler byte {0} is out of range")]
+    FillerByte(u32),
+    #[error("Body segment has no source")]
+    EmptySegment,
+}
+
+/// Request body for `body`, and its length for Content-Length
+pub async fn request_body(body: StreamedBody) -> Result<(reqwest::Body, u64), StreamedBodyError> {
+    // Check every segment first: a missing file should fail the request, not
+    // cut the upload short halfway through
+    let mut length = 0u64;
+    for segment in &body.segments {
+        length += match &segment.source {
+            Some(Source::Inline(bytes)) => bytes.len() as u64,
+            Some(Source::Filler(filler)) => {
+                if filler.byte > u8::MAX as u32 {
+                    return Err(StreamedBodyError::FillerByte(filler.byte));
+                }
+                filler.size
+            }
+            Some(Source::AgentFile(path)) => tokio::fs::metadata(path)
+                .await
+                .map_err(|source| StreamedBodyError::AgentFile {
+                    path: path.clone(),
+                    source,
+                })?
+                .len(),
+            None => return Err(StreamedBodyError::EmptySegment),
+        };
+    }
+
+    let (tx, rx) = mpsc::channel(CHUNKS_IN_FLIGHT);
+    tokio::spawn(write_segments(body.segments, tx));
+    Ok((reqwest::Body::wrap_stream(ReceiverStream::new(rx)), length))
+}
+
+/// Feed the segments into `tx` until done or the request is dropped
+async fn write_segments(segments: Vec<BodySegment>, tx: mpsc::Sender<Chunk>) {
+    for segment in segments {
+        let sent = match segment.source {
+            Some(Source::Inline(bytes)) => tx.send(Ok(bytes)).await.is_ok(),
+            Some(Source::Filler(filler)) => send_filler(&tx, filler.size, filler.byte as u8).await,
+            Some(Source::AgentFile(path)) => send_file(&tx, &path).await,
+            None => true,
+        };
+        if !sent {
+            return;
+        }
+    }
+}
+
+async fn send_filler(tx: &mpsc::Sender<Chunk>, size: u64, byte: u8) -> bool {
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = remaining.min(CHUNK_SIZE as u64) as usize;
+        if tx.send(Ok(vec![byte; len])).await.is_err() {
+            return false;
+        }
+        remaining -= len as u64;
+    }
+    true
+}
+
+async fn send_file(tx: &mpsc::Sender<Chunk>, path: &str) -> bool {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) => {
+            let _ = tx.send(Err(e)).await;
+            return false;
+        }
+    };
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        match file.read(&mut buf).await {
+            Ok(0) => return true,
+            Ok(n) => {
+                if tx.send(Ok(buf[..n].to_vec())).await.is_err() {
+                    return false;
+                }
+            }
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proxy_core::pb::FillerSegment;
+
+    fn segment(source: Source) -> BodySegment {
+        BodySegment { source: Some(source) }
+    }
+
+    #[tokio::test]
+    async fn test_segments_stream_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.bin");
+        let file: Vec<u8> = (0..CHUNK_SIZE * 2 + 7).map(|i| i as u8).collect();
+        std::fs::write(&path, &file).unwrap();
+
+        let segments = vec![
+            segment(Source::Inline(b"head".to_vec())),
+            segment(Source::Filler(FillerSegment { size: CHUNK_SIZE as u64 + 1, byte: 0x41 })),
+            segment(Source::AgentFile(path.to_string_lossy().to_string())),
+            segment(Source::Inline(b"tail".to_vec())),
+        ];
+        let (_, length) = request_body(StreamedBody { segments: segments.clone() }).await.unwrap();
+        assert_eq!(length, 4 + CHUNK_SIZE as u64 + 1 + file.len() as u64 + 4);
+
+        let (tx, mut rx) = mpsc::channel(CHUNKS_IN_FLIGHT);
+        tokio::spawn(write_segments(segments, tx));
+        let mut body = Vec::new();
+        while let Some(chunk) = rx.recv().await {
+            let chunk = chunk.unwrap();
+            assert!(chunk.len() <= CHUNK_SIZE);
+            body.extend(chunk);
+        }
+
+        let mut expected = b"head".to_vec();
+        expected.extend(vec![0x41; CHUNK_SIZE + 1]);
+        expected.extend(&file);
+        expected.extend(b"tail");
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_segments() {
+        let missing = StreamedBody {
+            segments: vec![segment(Source::AgentFile("/nonexistent/upload.bin".to_string()))],
+        };
+        assert!(matches!(request_body(missing).await, Err(StreamedBodyError::AgentFile { .. })));
+
+        let bad_byte = StreamedBody {
+            segments: vec![segment(Source::Filler(FillerSegment { size: 1, byte: 256 }))],
+        };
+        assert!(matches!(request_body(bad_byte).await, Err(StreamedBodyError::FillerByte(256))));
+    }
+}
@@ -0,0 +1,112 @@
+//! Timing of requests the agent sends for the orchestrator
+//!
+//! The replay client pools connections, so only some requests open one. A
+//! layer around the client's connector measures each connection it opens and
+//! adds it to a slot local to the task sending the request; `send` reads that
+//! slot to split the connect time from the wait for the response.
+
+use proxy_core::PhaseTimer;
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+tokio::task_local! {
+    /// Connect time of the request the current task is sending
+    static CONNECT_TIME: Cell<Duration>;
+}
+
+/// Connector layer recording how long new connections take to open
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectTimingLayer;
+
+impl<S> tower::Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectTiming<S> {
+    inner: S,
+}
+
+impl<S, R> tower::Service<R> for ConnectTiming<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let started = Instant::now();
+        let connecting = self.inner.call(request);
+        Box::pin(async move {
+            let connection = connecting.await?;
+            // A connection finished in the background, after a pooled one was
+            // used instead, has no request to charge
+            let _ = CONNECT_TIME.try_with(|time| time.set(time.get() + started.elapsed()));
+            Ok(connection)
+        })
+    }
+}
+
+/// Send a request, returning the timer with the connect time and the arrival
+/// of the response headers marked. Redirects add the connections they open.
+pub async fn send(request: reqwest::RequestBuilder) -> (reqwest::Result<reqwest::Response>, PhaseTimer) {
+    CONNECT_TIME
+        .scope(Cell::new(Duration::ZERO), async move {
+            let mut timer = PhaseTimer::start();
+            let result = request.send().await;
+            timer.headers_received();
+            timer.connected(CONNECT_TIME.with(Cell::get));
+            (result, timer)
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_only_new_connections_are_timed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            for _ in 0..2 {
+                let _ = stream.read(&mut buf).await.unwrap();
+                stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .await
+                    .unwrap();
+            }
+        });
+
+        let client = reqwest::Client::builder().connector_layer(ConnectTimingLayer).build().unwrap();
+        let url = format!("http://{}/", addr);
+
+        let (response, timer) = send(client.get(&url)).await;
+        assert_eq!(response.unwrap().bytes().await.unwrap().as_ref(), b"ok");
+        let first = timer.finish();
+        assert!(first.connect_us > 0);
+        assert!(first.connect_us + first.ttfb_us + first.transfer_us <= first.total_us);
+
+        let (response, timer) = send(client.get(&url)).await;
+        response.unwrap().bytes().await.unwrap();
+        assert_eq!(timer.finish().connect_us, 0);
+    }
+}
@@ -0,0 +1,458 @@
+//! Managed SSH Tunnels
+//!
+//! Local port forwards through SSH, so the agent can reach targets in a
+//! segmented network (optionally via a jump host) without external autossh
+//! scripts. Each tunnel runs the system `ssh` client and is restarted with
+//! backoff whenever it exits; keepalives make a dead link exit too.
+//! A tunnel with a SOCKS port can also carry proxied traffic, selected per
+//! scope by egress routes.
+
+use proxy_core::{EgressPolicy, EgressRoute};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+/// A tunnel that stayed up this long is considered healthy again
+const STABLE_AFTER: Duration = Duration::from_secs(60);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, thiserror::Error)]
+pub enum TunnelError {
+    #[error("Failed to read tunnel configuration: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse tunnel configuration: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Invalid tunnel '{name}': {reason}")]
+    Invalid { name: String, reason: String },
+}
+
+/// Tunnel configuration file
+///
+/// ```json
+/// {
+///   "tunnels": [{
+///     "name": "dmz",
+///     "host": "10.20.0.5",
+///     "user": "ops",
+///     "identity_file": "/etc/proxxy/tunnel_key",
+///     "jump_host": "ops@bastion.example.com:2222",
+///     "local_forwards": [
+///       { "bind_port": 18443, "remote_host": "intranet.corp", "remote_port": 443 }
+///     ]
+///   }]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TunnelsConfig {
+    #[serde(default)]
+    pub tunnels: Vec<TunnelConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TunnelConfig {
+    pub name: String,
+    /// SSH server inside (or at the edge of) the target network
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    pub user: Option<String>,
+    /// Private key; the ssh client's defaults and agent are used otherwise
+    pub identity_file: Option<PathBuf>,
+    /// Jump host in ssh `-J` form (`[user@]host[:port]`)
+    pub jump_host: Option<String>,
+    #[serde(default)]
+    pub local_forwards: Vec<LocalForward>,
+    /// Local SOCKS port (ssh -D) that egress routes can send traffic through
+    pub socks_port: Option<u16>,
+    /// Verify host keys against known_hosts (accept-new when unset)
+    #[serde(default)]
+    pub strict_host_key_checking: bool,
+    #[serde(default = "default_keepalive_secs")]
+    pub keepalive_interval_secs: u64,
+}
+
+/// `bind_address:bind_port` on the agent forwarded to `remote_host:remote_port`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalForward {
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    pub bind_port: u16,
+    pub remote_host: String,
+    pub remote_port: u16,
+}
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+fn default_keepalive_secs() -> u64 {
+    15
+}
+
+fn default_bind_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+impl TunnelsConfig {
+    pub fn load(path: &std::path::Path) -> Result<Self, TunnelError> {
+        let config: Self = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), TunnelError> {
+        let mut names = std::collections::HashSet::new();
+        let mut ports = std::collections::HashSet::new();
+        for tunnel in &self.tunnels {
+            let invalid = |reason: String| TunnelError::Invalid {
+                name: tunnel.name.clone(),
+                reason,
+            };
+            if !names.insert(tunnel.name.as_str()) {
+                return Err(invalid("duplicate tunnel name".to_string()));
+            }
+            if tunnel.host.is_empty() || tunnel.host.starts_with('-') {
+                return Err(invalid("host is required".to_string()));
+            }
+            if tunnel.jump_host.as_deref().is_some_and(|j| j.is_empty() || j.starts_with('-')) {
+                return Err(invalid("jump_host is invalid".to_string()));
+            }
+            if tunnel.local_forwards.is_empty() && tunnel.socks_port.is_none() {
+                return Err(invalid("at least one local forward or a socks_port is required".to_string()));
+            }
+            if tunnel.socks_port == Some(0) {
+                return Err(invalid("socks_port must be non-zero".to_string()));
+            }
+            if let Some(port) = tunnel.socks_port {
+                if !ports.insert(("127.0.0.1", port)) {
+                    return Err(invalid(format!("127.0.0.1:{} is already forwarded", port)));
+                }
+            }
+            if tunnel.keepalive_interval_secs == 0 {
+                return Err(invalid("keepalive_interval_secs must be positive".to_string()));
+            }
+            for forward in &tunnel.local_forwards {
+                if forward.bind_port == 0 || forward.remote_port == 0 {
+                    return Err(invalid("forward ports must be non-zero".to_string()));
+                }
+                if !ports.insert((forward.bind_address.as_str(), forward.bind_port)) {
+                    return Err(invalid(format!(
+                        "{}:{} is already forwarded",
+                        forward.bind_address, forward.bind_port
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace tunnel egress routes with the tunnels' SOCKS ports, so the
+    /// proxy only sees concrete routes
+    pub fn resolve_egress_routes(&self, policy: &mut EgressPolicy) -> Result<(), TunnelError> {
+        for rule in &mut policy.routes {
+            if let EgressRoute::Tunnel { name } = &rule.route {
+                let tunnel = self.tunnels.iter().find(|t| &t.name == name).ok_or_else(|| {
+                    TunnelError::Invalid {
+                        name: name.clone(),
+                        reason: "egress route names an unknown tunnel".to_string(),
+                    }
+                })?;
+                let port = tunnel.socks_port.ok_or_else(|| TunnelError::Invalid {
+                    name: name.clone(),
+                    reason: "egress routes need the tunnel to have a socks_port".to_string(),
+                })?;
+                rule.route = EgressRoute::Socks5 {
+                    host: "127.0.0.1".to_string(),
+                    port,
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TunnelConfig {
+    /// Arguments for the ssh client. Forward failures and missed keepalives
+    /// make ssh exit, which the supervisor turns into a restart.
+    pub fn ssh_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "-N".to_string(),
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+            "-o".to_string(),
+            "ExitOnForwardFailure=yes".to_string(),
+            "-o".to_string(),
+            format!("ServerAliveInterval={}", self.keepalive_interval_secs),
+            "-o".to_string(),
+            "ServerAliveCountMax=3".to_string(),
+            "-o".to_string(),
+            format!(
+                "StrictHostKeyChecking={}",
+                if self.strict_host_key_checking { "yes" } else { "accept-new" }
+            ),
+            "-p".to_string(),
+            self.port.to_string(),
+        ];
+        if let Some(identity) = &self.identity_file {
+            args.push("-i".to_string());
+            args.push(identity.display().to_string());
+            args.push("-o".to_string());
+            args.push("IdentitiesOnly=yes".to_string());
+        }
+        if let Some(jump) = &self.jump_host {
+            args.push("-J".to_string());
+            args.push(jump.clone());
+        }
+        if let Some(port) = self.socks_port {
+            args.push("-D".to_string());
+            args.push(format!("127.0.0.1:{}", port));
+        }
+        for forward in &self.local_forwards {
+            args.push("-L".to_string());
+            args.push(format!(
+                "{}:{}:{}:{}",
+                forward.bind_address,
+                forward.bind_port,
+                bracket_ipv6(&forward.remote_host),
+                forward.remote_port
+            ));
+        }
+        args.push(match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.clone(),
+        });
+        args
+    }
+}
+
+fn bracket_ipv6(host: &str) -> String {
+    if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]", host)
+    } else {
+        host.to_string()
+    }
+}
+
+/// Running tunnels; dropping this tears them down
+pub struct Tunnels {
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Drop for Tunnels {
+    fn drop(&mut self) {
+        for handle in &self.handles {
+            handle.abort();
+        }
+    }
+}
+
+/// Start every configured tunnel and wait (up to `ready_timeout`) for the
+/// forwards to accept connections. Tunnels that are not ready yet keep
+/// being retried in the background.
+pub async fn start_tunnels(config: &TunnelsConfig, ready_timeout: Duration) -> Tunnels {
+    let handles = config
+        .tunnels
+        .iter()
+        .cloned()
+        .map(|tunnel| tokio::spawn(supervise(tunnel)))
+        .collect();
+
+    let deadline = tokio::time::Instant::now() + ready_timeout;
+    for tunnel in &config.tunnels {
+        for forward in &tunnel.local_forwards {
+            let addr = format!("{}:{}", forward.bind_address, forward.bind_port);
+            if wait_for_listener(&addr, deadline).await {
+                tracing::info!(
+                    "🔐 Tunnel '{}' forwarding {} -> {}:{}",
+                    tunnel.name,
+                    addr,
+                    forward.remote_host,
+                    forward.remote_port
+                );
+            } else {
+                tracing::warn!(
+                    "Tunnel '{}' not ready on {} after {:?}; retrying in the background",
+                    tunnel.name,
+                    addr,
+                    ready_timeout
+                );
+            }
+        }
+        if let Some(port) = tunnel.socks_port {
+            let addr = format!("127.0.0.1:{}", port);
+            if wait_for_listener(&addr, deadline).await {
+                tracing::info!("🔐 Tunnel '{}' SOCKS proxy on {}", tunnel.name, addr);
+            } else {
+                tracing::warn!(
+                    "Tunnel '{}' SOCKS proxy not ready on {} after {:?}; retrying in the background",
+                    tunnel.name,
+                    addr,
+                    ready_timeout
+                );
+            }
+        }
+    }
+
+    Tunnels { handles }
+}
+
+async fn wait_for_listener(addr: &str, deadline: tokio::time::Instant) -> bool {
+    loop {
+        if tokio::net::TcpStream::connect(addr).await.is_ok() {
+            return true;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+}
+
+/// Keep one tunnel running, restarting it with exponential backoff
+async fn supervise(tunnel: TunnelConfig) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let started = tokio::time::Instant::now();
+        match run_once(&tunnel).await {
+            Ok(status) => tracing::warn!("Tunnel '{}' exited ({})", tunnel.name, status),
+            Err(e) => tracing::error!("Failed to start ssh for tunnel '{}': {}", tunnel.name, e),
+        }
+
+        if started.elapsed() >= STABLE_AFTER {
+            backoff = Duration::from_secs(1);
+        }
+        tracing::info!("Restarting tunnel '{}' in {:?}", tunnel.name, backoff);
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+async fn run_once(tunnel: &TunnelConfig) -> std::io::Result<std::process::ExitStatus> {
+    let mut child = Command::new("ssh")
+        .args(tunnel.ssh_args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        // The supervisor task owns the process; aborting it tears the tunnel down
+        .kill_on_drop(true)
+        .spawn()?;
+
+    if let Some(stderr) = child.stderr.take() {
+        let name = tunnel.name.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                tracing::debug!("ssh[{}]: {}", name, line);
+            }
+        });
+    }
+
+    child.wait().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> Result<TunnelsConfig, TunnelError> {
+        let config: TunnelsConfig = serde_json::from_str(json)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn test_ssh_args() {
+        let config = parse(
+            r#"{"tunnels": [{
+                "name": "dmz",
+                "host": "10.20.0.5",
+                "user": "ops",
+                "identity_file": "/keys/id",
+                "jump_host": "ops@bastion:2222",
+                "local_forwards": [
+                    {"bind_port": 18443, "remote_host": "intranet.corp", "remote_port": 443},
+                    {"bind_address": "0.0.0.0", "bind_port": 18080, "remote_host": "fd00::1", "remote_port": 80}
+                ]
+            }]}"#,
+        )
+        .unwrap();
+
+        let args = config.tunnels[0].ssh_args();
+        let joined = args.join(" ");
+        assert!(joined.contains("-N"));
+        assert!(joined.contains("ExitOnForwardFailure=yes"));
+        assert!(joined.contains("ServerAliveInterval=15"));
+        assert!(joined.contains("StrictHostKeyChecking=accept-new"));
+        assert!(joined.contains("-p 22"));
+        assert!(joined.contains("-i /keys/id"));
+        assert!(joined.contains("-J ops@bastion:2222"));
+        assert!(joined.contains("-L 127.0.0.1:18443:intranet.corp:443"));
+        assert!(joined.contains("-L 0.0.0.0:18080:[fd00::1]:80"));
+        assert_eq!(args.last().unwrap(), "ops@10.20.0.5");
+    }
+
+    #[test]
+    fn test_resolve_egress_routes() {
+        let config = parse(
+            r#"{"tunnels": [
+                {"name": "dmz", "host": "10.20.0.5", "socks_port": 11080},
+                {"name": "db", "host": "10.30.0.5", "local_forwards": [
+                    {"bind_port": 15432, "remote_host": "db.corp", "remote_port": 5432}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(config.tunnels[0].ssh_args().join(" ").contains("-D 127.0.0.1:11080"));
+
+        let mut policy: EgressPolicy = serde_json::from_str(
+            r#"{"routes": [
+                {"hosts": ["*.corp.local"], "route": {"type": "tunnel", "name": "dmz"}},
+                {"hosts": ["*"], "route": {"type": "direct"}}
+            ]}"#,
+        )
+        .unwrap();
+        config.resolve_egress_routes(&mut policy).unwrap();
+        assert_eq!(
+            policy.route_for_host("admin.corp.local"),
+            Some(&EgressRoute::Socks5 { host: "127.0.0.1".to_string(), port: 11080 })
+        );
+
+        for name in ["db", "missing"] {
+            let mut policy = EgressPolicy {
+                routes: vec![proxy_core::EgressRule {
+                    hosts: vec!["*".to_string()],
+                    route: EgressRoute::Tunnel { name: name.to_string() },
+                }],
+            };
+            assert!(config.resolve_egress_routes(&mut policy).is_err());
+        }
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_tunnels() {
+        let forward = r#"[{"bind_port": 18443, "remote_host": "h", "remote_port": 443}]"#;
+        assert!(parse(&format!(
+            r#"{{"tunnels": [{{"name": "a", "host": "-oProxyCommand=x", "local_forwards": {}}}]}}"#,
+            forward
+        ))
+        .is_err());
+        assert!(parse(r#"{"tunnels": [{"name": "a", "host": "h", "local_forwards": []}]}"#).is_err());
+        assert!(parse(&format!(
+            r#"{{"tunnels": [
+                {{"name": "a", "host": "h1", "local_forwards": {0}}},
+                {{"name": "b", "host": "h2", "local_forwards": {0}}}
+            ]}}"#,
+            forward
+        ))
+        .is_err());
+        assert!(parse(&format!(
+            r#"{{"tunnels": [{{"name": "a", "host": "h", "local_forwards": {}}}]}}"#,
+            forward
+        ))
+        .is_ok());
+    }
+}
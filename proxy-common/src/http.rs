@@ -0,0 +1,153 @@
+//! HTTP request and response data
+//!
+//! The field-for-field counterparts of the `HttpRequestData` and
+//! `HttpResponseData` wire messages, used wherever requests are built, sent
+//! through an agent or stored: proxy-core converts between the two
+//! losslessly, so no module copies fields by hand.
+
+use crate::session::Session;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// HTTP request data structure compatible with protobuf definitions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpRequestData {
+    pub method: String,
+    pub url: String,
+    pub headers: Option<HttpHeaders>,
+    pub body: Vec<u8>,
+    pub tls: Option<TlsDetails>,
+}
+
+/// HTTP response data structure compatible with protobuf definitions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpResponseData {
+    pub status_code: i32,
+    pub headers: Option<HttpHeaders>,
+    pub body: Vec<u8>,
+    pub tls: Option<TlsDetails>,
+    /// Timings measured by the agent that sent the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timing: Option<ResponseTiming>,
+    /// Protocol spoken with the origin ("HTTP/1.1", "HTTP/2"), if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+}
+
+/// Phases of a request, timed on the agent with a monotonic clock. Timings
+/// taken by the orchestrator include the round trips to the agent, which
+/// drown the few milliseconds time-based blind injection is read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResponseTiming {
+    /// Opening a new connection (DNS, TCP, TLS); 0 when a pooled one was
+    /// reused or the phase was not measured separately
+    pub connect_us: u64,
+    /// From the connection being ready to the response headers: the time
+    /// the server took to answer
+    pub ttfb_us: u64,
+    /// From the response headers to the end of the body
+    pub transfer_us: u64,
+    pub total_us: u64,
+}
+
+impl ResponseTiming {
+    /// Whole request in milliseconds, as durations are stored
+    pub fn total_ms(&self) -> u64 {
+        self.total_us / 1000
+    }
+}
+
+/// HTTP headers structure
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HttpHeaders {
+    pub headers: HashMap<String, String>,
+}
+
+/// TLS connection details
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TlsDetails {
+    pub version: String,
+    pub cipher: String,
+}
+
+impl HttpRequestData {
+    /// Create a new HTTP request
+    pub fn new(method: String, url: String) -> Self {
+        Self {
+            method,
+            url,
+            headers: None,
+            body: Vec::new(),
+            tls: None,
+        }
+    }
+    
+    /// Apply session data to the request
+    pub fn apply_session(&mut self, session: &Session) {
+        let session_headers = session.get_http_headers();
+        
+        // Initialize headers if not present
+        if self.headers.is_none() {
+            self.headers = Some(HttpHeaders {
+                headers: HashMap::new(),
+            });
+        }
+        
+        // Apply session headers
+        if let Some(ref mut headers) = self.headers {
+            for (key, value) in session_headers {
+                headers.headers.insert(key, value);
+            }
+        }
+    }
+    
+    /// Set request body from string
+    pub fn set_body_string(&mut self, body: String) {
+        self.body = body.into_bytes();
+    }
+    
+    /// Get request body as string
+    pub fn body_as_string(&self) -> Result<String, std::string::FromUtf8Error> {
+        String::from_utf8(self.body.clone())
+    }
+    
+    /// Add or update a header
+    pub fn set_header(&mut self, key: String, value: String) {
+        if self.headers.is_none() {
+            self.headers = Some(HttpHeaders {
+                headers: HashMap::new(),
+            });
+        }
+        
+        if let Some(ref mut headers) = self.headers {
+            headers.headers.insert(key, value);
+        }
+    }
+    
+    /// Get a header value
+    pub fn get_header(&self, key: &str) -> Option<&String> {
+        self.headers.as_ref()?.headers.get(key)
+    }
+}
+
+impl HttpResponseData {
+    /// Check if response indicates success (2xx status code)
+    pub fn is_success(&self) -> bool {
+        self.status_code >= 200 && self.status_code < 300
+    }
+    
+    /// Get response body as string
+    pub fn body_as_string(&self) -> Result<String, std::string::FromUtf8Error> {
+        String::from_utf8(self.body.clone())
+    }
+    
+    /// Get response body length
+    pub fn body_length(&self) -> usize {
+        self.body.len()
+    }
+    
+    /// Get a header value
+    pub fn get_header(&self, key: &str) -> Option<&String> {
+        self.headers.as_ref()?.headers.get(key)
+    }
+}
@@ -1,5 +1,7 @@
 //! Common types and interfaces shared across Proxxy modules
 
+pub mod http;
 pub mod session;
 
+pub use http::*;
 pub use session::*;
\ No newline at end of file
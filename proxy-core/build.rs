@@ -16,6 +16,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // Let's enable both to be safe.
         .build_client(true)
         .build_server(true)
+        .bytes([".proxy.HttpRequestData.body", ".proxy.HttpResponseData.body"])
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
         .compile_protos(&["../proto/proxy.proto"], &["../proto"])?;
     Ok(())
@@ -1,6 +1,19 @@
+use crate::ca::CertificateAuthority;
+use crate::capture::CaptureController;
+use crate::cookie_jar::{CookieJar, StoredCookie};
+use crate::leaf_cache::{LeafCacheStats, LeafCertCache};
+use crate::mitm_fallback::{FallbackEntry, MitmFallback};
+use crate::probes::{check_ca, check_listener, probe_response, LinkStatus, ProbeCheck};
 use crate::Result;
-use axum::{routing::get, Json, Router};
-use serde::Serialize;
+use axum::{
+    extract::{Path, RawQuery, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
 use std::sync::{
     atomic::{AtomicU64, Ordering},
@@ -13,6 +26,8 @@ use tracing::info;
 pub struct Metrics {
     pub total_requests: AtomicU64,
     pub active_connections: AtomicU64,
+    /// Requests answered with 414/431 because they exceeded request limits
+    pub requests_rejected: AtomicU64,
     // Body capture performance metrics
     pub body_capture_attempts: AtomicU64,
     pub body_capture_successes: AtomicU64,
@@ -28,6 +43,15 @@ struct HealthResponse {
     status: String,
 }
 
+/// What `/readyz` checks besides the CA
+#[derive(Debug, Clone, Default)]
+pub struct ReadinessChecks {
+    /// Port the proxy listens on
+    pub proxy_port: u16,
+    /// Traffic stream to the orchestrator, for agents that have one
+    pub orchestrator: Option<LinkStatus>,
+}
+
 #[derive(Serialize, Clone)]
 pub struct AgentInfo {
     pub agent_id: String,
@@ -40,8 +64,31 @@ pub struct AgentInfo {
 struct MetricsResponse {
     total_requests: u64,
     active_connections: u64,
+    requests_rejected: u64,
     // Body capture performance metrics
     body_capture: BodyCaptureMetrics,
+    /// Leaf certificates issued for intercepted hosts
+    leaf_certificates: LeafCacheStats,
+}
+
+#[derive(Serialize)]
+struct CaptureResponse {
+    paused: bool,
+    /// Paused host patterns; empty while paused means every host
+    hosts: Vec<String>,
+    /// Requests forwarded unreported since the agent started
+    skipped_requests: u64,
+}
+
+#[derive(Deserialize)]
+struct PauseCaptureRequest {
+    #[serde(default)]
+    hosts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct MaintainSession {
+    hosts: Vec<String>,
 }
 
 #[derive(Serialize)]
@@ -56,12 +103,101 @@ struct BodyCaptureMetrics {
     total_bytes_captured: u64,
 }
 
-pub async fn start_admin_server(port: u16, metrics: Arc<Metrics>, info: AgentInfo) -> Result<()> {
-    let info_cloned = info.clone();
-    let app = Router::new()
-        .route("/health", get(health_handler))
-        .route("/info", get(move || async { Json(info_cloned) }))
-        .route("/metrics", get(move || metrics_handler(metrics)));
+/// Downloads of the Root CA for devices under test: `/ca.crt` (DER),
+/// `/ca.pem` and `/ca.mobileconfig` (iOS profile)
+pub fn ca_routes<S: Clone + Send + Sync + 'static>(ca: Arc<CertificateAuthority>) -> Router<S> {
+    let (pem_ca, profile_ca) = (ca.clone(), ca.clone());
+    Router::new()
+        .route(
+            "/ca.crt",
+            get(move || async move { ca_download(ca.get_ca_cert_der(), "application/x-x509-ca-cert", "proxxy-ca.crt") }),
+        )
+        .route(
+            "/ca.pem",
+            get(move || async move {
+                ca_download(pem_ca.get_ca_cert_pem().map(String::into_bytes), "application/x-pem-file", "proxxy-ca.pem")
+            }),
+        )
+        .route(
+            "/ca.mobileconfig",
+            get(move || async move {
+                ca_download(
+                    profile_ca.get_ca_mobileconfig().map(String::into_bytes),
+                    "application/x-apple-aspen-config",
+                    "proxxy-ca.mobileconfig",
+                )
+            }),
+        )
+}
+
+fn ca_download(content: Result<Vec<u8>>, content_type: &'static str, filename: &'static str) -> Response {
+    match content {
+        Ok(content) => (
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+            ],
+            content,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Whether a caller may use the control routes: the admin token must be
+/// presented as a bearer token. Without one the routes stay closed, since
+/// requests relayed by the proxy itself arrive from loopback too.
+fn admin_authorized(token: Option<&str>, headers: &HeaderMap) -> bool {
+    let Some(token) = token else {
+        return false;
+    };
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| constant_time_eq(presented.trim().as_bytes(), token.as_bytes()))
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Gate on the control routes
+async fn require_admin(
+    State(token): State<Option<Arc<str>>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if admin_authorized(token.as_deref(), request.headers()) {
+        return next.run(request).await;
+    }
+    let message = if token.is_some() {
+        "The admin token is required"
+    } else {
+        "Set an admin token to use this endpoint"
+    };
+    (StatusCode::UNAUTHORIZED, message).into_response()
+}
+
+/// Serve the admin API on `port`. Routes that change what the agent does,
+/// and the cookie jar's, are gated by `admin_token` (see `admin_authorized`).
+#[allow(clippy::too_many_arguments)]
+pub async fn start_admin_server(
+    port: u16,
+    admin_token: Option<String>,
+    metrics: Arc<Metrics>,
+    capture: CaptureController,
+    fallback: MitmFallback,
+    cookies: CookieJar,
+    leaf_certificates: LeafCertCache,
+    ca: Arc<CertificateAuthority>,
+    info: AgentInfo,
+    readiness: ReadinessChecks,
+) -> Result<()> {
+    let app = admin_router(admin_token, metrics, capture, fallback, cookies, leaf_certificates, ca, info, readiness);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
     info!("Starting Admin API on {}", addr);
@@ -70,20 +206,143 @@ pub async fn start_admin_server(port: u16, metrics: Arc<Metrics>, info: AgentInf
         crate::error::ProxyError::Network(format!("Failed to bind admin port {}: {}", port, e))
     })?;
 
-    axum::serve(listener, app)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .map_err(|e| crate::error::ProxyError::Network(format!("Admin server failed: {}", e)))?;
 
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
+fn admin_router(
+    admin_token: Option<String>,
+    metrics: Arc<Metrics>,
+    capture: CaptureController,
+    fallback: MitmFallback,
+    cookies: CookieJar,
+    leaf_certificates: LeafCertCache,
+    ca: Arc<CertificateAuthority>,
+    info: AgentInfo,
+    readiness: ReadinessChecks,
+) -> Router {
+    let admin_token: Option<Arc<str>> = admin_token.filter(|t| !t.trim().is_empty()).map(Arc::from);
+    let info_cloned = info.clone();
+    let ready_ca = ca.clone();
+    let (pause_capture, resume_capture) = (capture.clone(), capture.clone());
+    let clear_fallback = fallback.clone();
+    let (clear_cookies, clear_domain_cookies) = (cookies.clone(), cookies.clone());
+    let (session_hosts, set_session_hosts) = (cookies.clone(), cookies.clone());
+    let app = Router::new()
+        .route("/health", get(health_handler))
+        .route(
+            "/livez",
+            get(|RawQuery(query): RawQuery| async move {
+                probe_response("livez", &[ProbeCheck::new("ping", Ok(()))], query.as_deref())
+            }),
+        )
+        .route(
+            "/readyz",
+            get(move |RawQuery(query): RawQuery| async move { readyz_handler(&ready_ca, &readiness, query).await }),
+        )
+        .route("/info", get(move || async { Json(info_cloned) }))
+        .route("/metrics", get(move || metrics_handler(metrics, leaf_certificates)))
+        .route("/capture", get(move || async move { capture_response(&capture) }))
+        // Hosts moved to pass-through because interception kept failing
+        .route(
+            "/tls-passthrough",
+            get(move || async move { Json::<Vec<FallbackEntry>>(fallback.entries()) }),
+        );
+
+    // Routes that change what the agent does or expose its sessions
+    let control = Router::new()
+        .route(
+            "/capture/pause",
+            post(move |body: Option<Json<PauseCaptureRequest>>| async move {
+                let hosts = body.map(|Json(request)| request.hosts).unwrap_or_default();
+                pause_capture.pause(hosts);
+                capture_response(&pause_capture)
+            }),
+        )
+        .route(
+            "/capture/resume",
+            post(move || async move {
+                resume_capture.resume();
+                capture_response(&resume_capture)
+            }),
+        )
+        .route(
+            "/tls-passthrough/{host}",
+            delete(move |Path(host): Path<String>| async move {
+                if clear_fallback.clear(&host) {
+                    StatusCode::NO_CONTENT
+                } else {
+                    StatusCode::NOT_FOUND
+                }
+            }),
+        )
+        // Cookies stored from upstream responses; they carry sessions, so
+        // even listing them is gated and values are redacted
+        .route(
+            "/cookies",
+            get(move || async move { Json::<Vec<StoredCookie>>(cookies.cookies()) }).delete(move || async move {
+                clear_cookies.clear(None);
+                StatusCode::NO_CONTENT
+            }),
+        )
+        .route(
+            "/cookies/{domain}",
+            delete(move |Path(domain): Path<String>| async move {
+                clear_domain_cookies.clear(Some(&domain));
+                StatusCode::NO_CONTENT
+            }),
+        )
+        // Hosts whose requests get the stored cookies attached
+        .route(
+            "/cookies/maintain-session",
+            get(move || async move { Json(MaintainSession { hosts: session_hosts.maintain_session() }) }).put(
+                move |Json(request): Json<MaintainSession>| async move {
+                    set_session_hosts.set_maintain_session(request.hosts);
+                    Json(MaintainSession { hosts: set_session_hosts.maintain_session() })
+                },
+            ),
+        )
+        .route_layer(middleware::from_fn_with_state(admin_token, require_admin));
+
+    app.merge(control).merge(ca_routes(ca))
+}
+
 async fn health_handler() -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "ok".to_string(),
     })
 }
 
-async fn metrics_handler(metrics: Arc<Metrics>) -> Json<MetricsResponse> {
+async fn readyz_handler(ca: &CertificateAuthority, readiness: &ReadinessChecks, query: Option<String>) -> Response {
+    let mut checks = vec![
+        ProbeCheck::new("ca", check_ca(ca)),
+        ProbeCheck::new("proxy", check_listener(readiness.proxy_port).await),
+    ];
+    if let Some(orchestrator) = &readiness.orchestrator {
+        let connected = if orchestrator.is_connected() {
+            Ok(())
+        } else {
+            Err("traffic stream to the orchestrator is down".to_string())
+        };
+        checks.push(ProbeCheck::new("orchestrator", connected));
+    }
+    probe_response("readyz", &checks, query.as_deref())
+}
+
+fn capture_response(capture: &CaptureController) -> Json<CaptureResponse> {
+    let state = capture.state();
+    Json(CaptureResponse {
+        paused: state.paused,
+        hosts: state.hosts,
+        skipped_requests: capture.skipped(),
+    })
+}
+
+async fn metrics_handler(metrics: Arc<Metrics>, leaf_certificates: LeafCertCache) -> Json<MetricsResponse> {
     let attempts = metrics.body_capture_attempts.load(Ordering::Relaxed);
     let successes = metrics.body_capture_successes.load(Ordering::Relaxed);
     let failures = metrics.body_capture_failures.load(Ordering::Relaxed);
@@ -107,6 +366,7 @@ async fn metrics_handler(metrics: Arc<Metrics>) -> Json<MetricsResponse> {
     Json(MetricsResponse {
         total_requests: metrics.total_requests.load(Ordering::Relaxed),
         active_connections: metrics.active_connections.load(Ordering::Relaxed),
+        requests_rejected: metrics.requests_rejected.load(Ordering::Relaxed),
         body_capture: BodyCaptureMetrics {
             attempts,
             successes,
@@ -117,5 +377,104 @@ async fn metrics_handler(metrics: Arc<Metrics>) -> Json<MetricsResponse> {
             average_latency_ms,
             total_bytes_captured: total_bytes,
         },
+        leaf_certificates: leaf_certificates.stats(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MitmFallbackConfig;
+
+    async fn serve(admin_token: Option<&str>) -> (String, CaptureController, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let ca = Arc::new(CertificateAuthority::new(dir.path()).unwrap());
+        let capture = CaptureController::new();
+        let app = admin_router(
+            admin_token.map(str::to_string),
+            Arc::new(Metrics::default()),
+            capture.clone(),
+            MitmFallback::new(MitmFallbackConfig::default()),
+            CookieJar::new(),
+            LeafCertCache::new(ca.clone(), 16),
+            ca,
+            AgentInfo {
+                agent_id: "agent".to_string(),
+                name: "agent".to_string(),
+                version: "test".to_string(),
+                hostname: "localhost".to_string(),
+            },
+            ReadinessChecks::default(),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+        (format!("http://{}", addr), capture, dir)
+    }
+
+    #[test]
+    fn test_admin_authorized() {
+        let mut with_token = HeaderMap::new();
+        with_token.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        let mut wrong_token = HeaderMap::new();
+        wrong_token.insert(header::AUTHORIZATION, "Bearer s3cre".parse().unwrap());
+
+        // Without a token nobody gets in, local clients included
+        assert!(!admin_authorized(None, &HeaderMap::new()));
+        assert!(!admin_authorized(None, &with_token));
+
+        assert!(admin_authorized(Some("s3cret"), &with_token));
+        assert!(!admin_authorized(Some("s3cret"), &wrong_token));
+        assert!(!admin_authorized(Some("s3cret"), &HeaderMap::new()));
+    }
+
+    #[tokio::test]
+    async fn test_control_routes_require_admin_token() {
+        let (base, capture, _dir) = serve(Some("s3cret")).await;
+        let client = reqwest::Client::new();
+
+        for (method, path) in [
+            (reqwest::Method::POST, "/capture/pause"),
+            (reqwest::Method::POST, "/capture/resume"),
+            (reqwest::Method::DELETE, "/tls-passthrough/example.com"),
+            (reqwest::Method::GET, "/cookies"),
+            (reqwest::Method::DELETE, "/cookies"),
+            (reqwest::Method::DELETE, "/cookies/example.com"),
+            (reqwest::Method::GET, "/cookies/maintain-session"),
+            (reqwest::Method::PUT, "/cookies/maintain-session"),
+        ] {
+            let response = client.request(method, format!("{}{}", base, path)).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED, "{}", path);
+        }
+        assert!(!capture.state().paused);
+
+        let response = client
+            .post(format!("{}/capture/pause", base))
+            .bearer_auth("s3cret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert!(capture.state().paused);
+
+        // Read-only routes stay open
+        let response = client.get(format!("{}/capture", base)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_control_routes_closed_without_token() {
+        let (base, capture, _dir) = serve(None).await;
+        let client = reqwest::Client::new();
+
+        // A loopback peer is no proof of a local operator: the proxy relays from there too
+        let response = client.post(format!("{}/capture/pause", base)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert!(!capture.state().paused);
+
+        let response = client.get(format!("{}/capture", base)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+}
@@ -0,0 +1,202 @@
+//! Buffer pooling for body capture
+//!
+//! Captured request/response bodies are accumulated in pooled `BytesMut` buffers and
+//! handed out as frozen `Bytes`. Once every `Bytes` view of a capture is dropped, the
+//! next `acquire` reclaims the same allocation instead of asking the allocator for a
+//! fresh one, which keeps allocator pressure flat at high request rates.
+
+use bytes::{Bytes, BytesMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::debug;
+
+/// Initial capacity for newly created buffers
+pub const DEFAULT_BUFFER_CAPACITY: usize = 16 * 1024;
+
+/// Buffers that grew beyond this size are not returned to the pool
+pub const DEFAULT_MAX_RETAINED_CAPACITY: usize = 1024 * 1024;
+
+/// Pool of reusable byte buffers
+#[derive(Debug)]
+pub struct BufferPool {
+    buffers: Mutex<Vec<BytesMut>>,
+    /// Maximum number of idle buffers kept
+    max_pooled: usize,
+    /// Capacity reserved when handing out a buffer
+    buffer_capacity: usize,
+    /// Largest buffer capacity worth keeping
+    max_retained_capacity: usize,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    returned: AtomicU64,
+    discarded: AtomicU64,
+}
+
+impl BufferPool {
+    /// Create a pool keeping at most `max_pooled` idle buffers
+    pub fn new(max_pooled: usize, buffer_capacity: usize, max_retained_capacity: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::with_capacity(max_pooled)),
+            max_pooled,
+            buffer_capacity,
+            max_retained_capacity,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            returned: AtomicU64::new(0),
+            discarded: AtomicU64::new(0),
+        }
+    }
+
+    /// Create a pool sized for the given number of concurrent captures
+    pub fn with_max_pooled(max_pooled: usize) -> Self {
+        Self::new(max_pooled, DEFAULT_BUFFER_CAPACITY, DEFAULT_MAX_RETAINED_CAPACITY)
+    }
+
+    /// Take an empty buffer from the pool, or allocate one if the pool is empty
+    pub fn acquire(&self) -> BytesMut {
+        let pooled = self.buffers.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        match pooled {
+            Some(mut buf) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                buf.clear();
+                // Reclaims the original allocation when all frozen views are gone
+                buf.reserve(self.buffer_capacity);
+                buf
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                BytesMut::with_capacity(self.buffer_capacity)
+            }
+        }
+    }
+
+    /// Return a buffer to the pool without keeping its contents
+    pub fn release(&self, mut buf: BytesMut) {
+        buf.clear();
+        self.put(buf);
+    }
+
+    /// Freeze the buffer contents into `Bytes` and return the backing storage to the pool
+    pub fn freeze(&self, mut buf: BytesMut) -> Bytes {
+        let capacity = buf.capacity();
+        let data = buf.split().freeze();
+
+        if capacity > self.max_retained_capacity {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.put(buf);
+        }
+
+        data
+    }
+
+    fn put(&self, buf: BytesMut) {
+        if buf.capacity() > self.max_retained_capacity {
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < self.max_pooled {
+            buffers.push(buf);
+            self.returned.fetch_add(1, Ordering::Relaxed);
+        } else {
+            debug!("Buffer pool full ({}), discarding buffer", self.max_pooled);
+            self.discarded.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Get pool statistics
+    pub fn stats(&self) -> BufferPoolStats {
+        let pooled = self.buffers.lock().unwrap_or_else(|e| e.into_inner()).len();
+        BufferPoolStats {
+            pooled_buffers: pooled,
+            max_pooled: self.max_pooled,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            returned: self.returned.load(Ordering::Relaxed),
+            discarded: self.discarded.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Buffer pool statistics
+#[derive(Debug, Clone, Default)]
+pub struct BufferPoolStats {
+    /// Idle buffers currently in the pool
+    pub pooled_buffers: usize,
+    /// Maximum idle buffers kept
+    pub max_pooled: usize,
+    /// Acquisitions served from the pool
+    pub hits: u64,
+    /// Acquisitions that required a fresh allocation
+    pub misses: u64,
+    /// Buffers returned to the pool
+    pub returned: u64,
+    /// Buffers dropped because the pool was full or they grew too large
+    pub discarded: u64,
+}
+
+impl BufferPoolStats {
+    /// Percentage of acquisitions served from the pool
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            (self.hits as f64 / total as f64) * 100.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acquire_reuses_released_buffers() {
+        let pool = BufferPool::new(2, 64, 1024);
+
+        let buf = pool.acquire();
+        pool.release(buf);
+        let _buf = pool.acquire();
+
+        let stats = pool.stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.returned, 1);
+    }
+
+    #[test]
+    fn test_freeze_reclaims_allocation_after_drop() {
+        let pool = BufferPool::new(2, 64, 1024);
+
+        let mut buf = pool.acquire();
+        buf.extend_from_slice(b"hello world");
+        let ptr = buf.as_ptr();
+        let frozen = pool.freeze(buf);
+        assert_eq!(&frozen[..], b"hello world");
+        drop(frozen);
+
+        let buf = pool.acquire();
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_oversized_and_excess_buffers_discarded() {
+        let pool = BufferPool::new(1, 16, 64);
+
+        let mut big = pool.acquire();
+        big.extend_from_slice(&[0u8; 128]);
+        let _ = pool.freeze(big);
+        assert_eq!(pool.stats().pooled_buffers, 0);
+
+        pool.release(BytesMut::with_capacity(16));
+        pool.release(BytesMut::with_capacity(16));
+
+        let stats = pool.stats();
+        assert_eq!(stats.pooled_buffers, 1);
+        assert_eq!(stats.discarded, 2);
+    }
+}
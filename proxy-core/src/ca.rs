@@ -1,48 +1,410 @@
+use crate::ca_key;
 use crate::error::ProxyError;
 use crate::Result;
 use rcgen::{
     BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, KeyPair,
-    PKCS_ECDSA_P256_SHA256,
+    PKCS_ECDSA_P256_SHA256, PKCS_RSA_SHA256,
 };
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::fs;
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
 use time::{Duration, OffsetDateTime};
+use zeroize::{Zeroize, Zeroizing};
+
+/// Key type of the leaf certificates presented to intercepted clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeafKeyAlgorithm {
+    /// Fast to generate and accepted by every current client
+    #[default]
+    EcdsaP256,
+    /// For older clients and embedded stacks without ECDSA; each key takes
+    /// noticeably longer to generate
+    Rsa2048,
+}
+
+impl fmt::Display for LeafKeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LeafKeyAlgorithm::EcdsaP256 => "ecdsa-p256",
+            LeafKeyAlgorithm::Rsa2048 => "rsa-2048",
+        })
+    }
+}
+
+impl FromStr for LeafKeyAlgorithm {
+    type Err = ProxyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "ecdsa" | "ecdsa-p256" | "p256" => Ok(LeafKeyAlgorithm::EcdsaP256),
+            "rsa" | "rsa-2048" | "rsa2048" => Ok(LeafKeyAlgorithm::Rsa2048),
+            _ => Err(ProxyError::Configuration(format!("Unknown leaf key algorithm '{}'", s))),
+        }
+    }
+}
 
 /// Certificate Authority for managing MITM certificates.
 ///
 /// Handles persistence of the Root CA certificate and private key, as well as dynamic generation
-/// of domain-specific (leaf) certificates signed by the Root CA.
+/// of domain-specific (leaf) certificates signed by the Root CA. The root can be
+/// rotated in place, so everything holding the authority picks up the new one.
+///
+/// The stored key may be encrypted with a passphrase (see [`ca_key`]). Until
+/// it is unlocked the authority has no root, and everything that needs one
+/// fails.
 pub struct CertificateAuthority {
-    ca_cert: Certificate,
+    /// `None` while the stored key is locked
+    root: RwLock<Option<Arc<RootCa>>>,
+    /// Bumped whenever the root is replaced; leaves cached under an older
+    /// generation are signed by a root clients may no longer trust
+    generation: AtomicU64,
+    leaf_key: LeafKeyAlgorithm,
+    /// Passphrase the stored key is encrypted with, for saving the key of a
+    /// rotated root the same way
+    passphrase: RwLock<Option<Zeroizing<String>>>,
+}
+
+/// Root certificate and key signing the leaves; the key is wiped on drop
+struct RootCa {
+    cert: Certificate,
+    /// Certificate as saved or loaded, which is what clients trust: `cert`
+    /// is rebuilt from the key on load and re-signed on every serialization
+    loaded_pem: Option<String>,
 }
 
 impl CertificateAuthority {
     /// Create a new CertificateAuthority found at the given path, or generate a new one.
+    ///
+    /// An encrypted stored key is left locked.
     pub fn new(ca_dir: &Path) -> Result<Self> {
+        Self::open(ca_dir, None)
+    }
+
+    /// Like [`new`](Self::new), with the stored key encrypted by `passphrase`.
+    ///
+    /// A plaintext key found on disk is encrypted in place. Without a
+    /// passphrase an encrypted key stays locked until [`unlock`](Self::unlock).
+    pub fn open(ca_dir: &Path, passphrase: Option<&str>) -> Result<Self> {
         let ca_cert_path = ca_dir.join("ca.pem");
         let ca_key_path = ca_dir.join("ca.key");
 
-        if ca_cert_path.exists() && ca_key_path.exists() {
-            Self::load(&ca_cert_path, &ca_key_path)
-        } else {
+        if !(ca_cert_path.exists() && ca_key_path.exists()) {
             // Create directory if it doesn't exist
             if !ca_dir.exists() {
-                fs::create_dir_all(ca_dir).map_err(|e| ProxyError::Io(e))?;
+                fs::create_dir_all(ca_dir).map_err(ProxyError::Io)?;
+            }
+            let ca = Self::with_root(RootCa::generate_and_save(&ca_cert_path, &ca_key_path, passphrase)?);
+            ca.set_passphrase(passphrase);
+            return Ok(ca);
+        }
+
+        let stored = Zeroizing::new(fs::read_to_string(&ca_key_path).map_err(ProxyError::Io)?);
+        let encrypted = ca_key::is_encrypted(&stored);
+        let ca = match passphrase {
+            None if encrypted => return Ok(Self::locked()),
+            _ => Self::with_root(RootCa::load(ca_dir, passphrase.unwrap_or_default())?),
+        };
+        if let Some(passphrase) = passphrase {
+            if !encrypted {
+                fs::write(&ca_key_path, ca_key::encrypt(&stored, passphrase)?).map_err(ProxyError::Io)?;
+                tracing::info!("🔐 Encrypted the stored CA key");
             }
-            Self::generate_and_save(&ca_cert_path, &ca_key_path)
+            ca.set_passphrase(Some(passphrase));
         }
+        Ok(ca)
     }
 
-    /// Load existing CA certificate and private key
-    fn load(cert_path: &Path, key_path: &Path) -> Result<Self> {
-        let cert_pem = fs::read_to_string(cert_path).map_err(|e| ProxyError::Io(e))?;
-        let key_pem = fs::read_to_string(key_path).map_err(|e| ProxyError::Io(e))?;
+    /// Create a CertificateAuthority from PEM strings (cert and key).
+    pub fn from_pem(cert_pem: &str, key_pem: &str) -> Result<Self> {
+        Ok(Self::with_root(RootCa::from_pem(cert_pem, key_pem)?))
+    }
 
-        Self::from_pem(&cert_pem, &key_pem)
+    fn with_root(root: RootCa) -> Self {
+        let ca = Self::locked();
+        *ca.root.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(root));
+        ca
     }
 
-    /// Create a CertificateAuthority from PEM strings (cert and key).
-    pub fn from_pem(_cert_pem: &str, key_pem: &str) -> Result<Self> {
+    fn locked() -> Self {
+        Self {
+            root: RwLock::new(None),
+            generation: AtomicU64::new(0),
+            leaf_key: LeafKeyAlgorithm::default(),
+            passphrase: RwLock::new(None),
+        }
+    }
+
+    fn root(&self) -> Result<Arc<RootCa>> {
+        self.root
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .ok_or_else(|| ProxyError::Certificate("CA key is locked; unlock it with its passphrase".to_string()))
+    }
+
+    fn set_root(&self, root: RootCa) {
+        *self.root.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(root));
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn set_passphrase(&self, passphrase: Option<&str>) {
+        *self.passphrase.write().unwrap_or_else(|e| e.into_inner()) =
+            passphrase.map(|passphrase| Zeroizing::new(passphrase.to_string()));
+    }
+
+    /// Whether the stored key is encrypted and not unlocked yet.
+    pub fn is_locked(&self) -> bool {
+        self.root.read().unwrap_or_else(|e| e.into_inner()).is_none()
+    }
+
+    /// Load the root from `ca_dir`, decrypting its key with `passphrase` if
+    /// it is encrypted. Fails on a wrong passphrase and leaves the authority
+    /// as it was.
+    pub fn unlock(&self, ca_dir: &Path, passphrase: &str) -> Result<()> {
+        self.set_root(RootCa::load(ca_dir, passphrase)?);
+        self.set_passphrase(Some(passphrase).filter(|passphrase| !passphrase.is_empty()));
+        Ok(())
+    }
+
+    /// Drop the root and the passphrase; the key is wiped from memory once
+    /// the last leaf being signed with it is done. For shutdown.
+    pub fn lock(&self) {
+        self.root.write().unwrap_or_else(|e| e.into_inner()).take();
+        self.set_passphrase(None);
+    }
+
+    /// Replace the root with a newly generated one saved in `ca_dir`.
+    ///
+    /// The current `ca.pem`, `ca.key` and `ca.crt` are moved to `archive_dir`
+    /// first. Leaves issued from then on are signed by the new root.
+    pub fn rotate(&self, ca_dir: &Path, archive_dir: &Path) -> Result<()> {
+        fs::create_dir_all(archive_dir).map_err(ProxyError::Io)?;
+        for name in ["ca.pem", "ca.key", "ca.crt"] {
+            let path = ca_dir.join(name);
+            if path.exists() {
+                fs::rename(&path, archive_dir.join(name)).map_err(ProxyError::Io)?;
+            }
+        }
+        let passphrase = self.passphrase.read().unwrap_or_else(|e| e.into_inner()).clone();
+        match RootCa::generate_and_save(&ca_dir.join("ca.pem"), &ca_dir.join("ca.key"), passphrase.as_deref().map(String::as_str)) {
+            Ok(root) => {
+                self.set_root(root);
+                Ok(())
+            }
+            Err(e) => {
+                // Put the current root back so the next start still loads it
+                for name in ["ca.pem", "ca.key", "ca.crt"] {
+                    let _ = fs::rename(archive_dir.join(name), ca_dir.join(name));
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Replace the root with the given one, as pushed by an orchestrator
+    /// after rotating its CA.
+    pub fn replace_from_pem(&self, cert_pem: &str, key_pem: &str) -> Result<()> {
+        let root = RootCa::from_pem(cert_pem, key_pem)?;
+        self.set_root(root);
+        Ok(())
+    }
+
+    /// Number of times the root was replaced since this authority was created.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Issue leaf certificates with `algorithm` keys instead of ECDSA P-256 ones.
+    pub fn with_leaf_key(mut self, algorithm: LeafKeyAlgorithm) -> Self {
+        self.leaf_key = algorithm;
+        self
+    }
+
+    /// Key type of the leaf certificates this CA issues.
+    pub fn leaf_key(&self) -> LeafKeyAlgorithm {
+        self.leaf_key
+    }
+
+    /// Generate a certificate for a specific domain, signed by this CA.
+    ///
+    /// * `domain` - The domain name (e.g., "example.com") to generate a certificate for.
+    ///
+    /// Returns a tuple containing `(cert_pem, key_pem)`.
+    pub fn gen_cert_for_domain(&self, domain: &str) -> Result<(String, String)> {
+        let cert = self.leaf_certificate(domain)?;
+
+        // Version 0.12+: verify signature.
+        let cert_pem = cert
+            .serialize_pem_with_signer(&self.root()?.cert)
+            .map_err(|e| ProxyError::General(format!("Failed to sign domain cert: {}", e)))?;
+
+        let key_pem = cert.serialize_private_key_pem();
+
+        Ok((cert_pem, key_pem))
+    }
+
+    /// Same as [`gen_cert_for_domain`](Self::gen_cert_for_domain), as DER for rustls.
+    ///
+    /// Returns a tuple containing `(cert_der, key_der)`.
+    pub fn gen_cert_der_for_domain(&self, domain: &str) -> Result<(Vec<u8>, Vec<u8>)> {
+        let cert = self.leaf_certificate(domain)?;
+        let cert_der = cert
+            .serialize_der_with_signer(&self.root()?.cert)
+            .map_err(|e| ProxyError::General(format!("Failed to sign domain cert: {}", e)))?;
+
+        Ok((cert_der, cert.serialize_private_key_der()))
+    }
+
+    /// Unsigned leaf for `domain` with a fresh key of the configured algorithm
+    fn leaf_certificate(&self, domain: &str) -> Result<Certificate> {
+        let mut params = CertificateParams::new(vec![domain.to_string()]);
+        let mut dn = DistinguishedName::new();
+        dn.push(DnType::CommonName, domain);
+        params.distinguished_name = dn;
+
+        // Valid for 1 year
+        let not_before = OffsetDateTime::now_utc() - Duration::days(1);
+        params.not_before = not_before;
+        params.not_after = not_before + Duration::days(365);
+
+        // rcgen generates ECDSA keys itself but cannot generate RSA ones
+        if self.leaf_key == LeafKeyAlgorithm::Rsa2048 {
+            params.alg = &PKCS_RSA_SHA256;
+            params.key_pair = Some(rsa_key_pair()?);
+        }
+
+        Certificate::from_params(params)
+            .map_err(|e| ProxyError::General(format!("Failed to generate domain cert params: {}", e)))
+    }
+
+    /// Get the Root CA certificate in PEM format.
+    pub fn get_ca_cert_pem(&self) -> Result<String> {
+        let root = self.root()?;
+        if let Some(pem) = &root.loaded_pem {
+            return Ok(pem.clone());
+        }
+        root.cert
+            .serialize_pem()
+            .map_err(|e| ProxyError::General(format!("Failed to serialize CA cert: {}", e)))
+    }
+
+    /// Get the Root CA private key in PEM format.
+    pub fn get_ca_key_pem(&self) -> Result<String> {
+        let mut key_pem = self.root()?.cert.serialize_private_key_pem();
+        // Ensure proper newline at end
+        if !key_pem.ends_with('\n') {
+            key_pem.push('\n');
+        }
+        Ok(key_pem)
+    }
+
+    /// Get the Root CA certificate in DER format (for use with rustls/hudsucker).
+    pub fn get_ca_cert_der(&self) -> Result<Vec<u8>> {
+        let root = self.root()?;
+        if let Some(pem) = &root.loaded_pem {
+            if let Some(der) = rustls_pemfile::certs(&mut pem.as_bytes()).ok().and_then(|certs| certs.into_iter().next()) {
+                return Ok(der);
+            }
+        }
+        root.cert
+            .serialize_der()
+            .map_err(|e| ProxyError::General(format!("Failed to serialize CA cert DER: {}", e)))
+    }
+
+    /// Get the Root CA private key in DER format (for use with rustls/hudsucker).
+    pub fn get_ca_key_der(&self) -> Result<Vec<u8>> {
+        Ok(self.root()?.cert.serialize_private_key_der())
+    }
+
+    /// iOS/iPadOS configuration profile installing the Root CA.
+    ///
+    /// The payload identifiers derive from the certificate, so installing the
+    /// profile again replaces it. After installing, full trust still has to
+    /// be turned on under Settings > General > About > Certificate Trust
+    /// Settings.
+    pub fn get_ca_mobileconfig(&self) -> Result<String> {
+        use base64::Engine;
+        use sha2::{Digest, Sha256};
+
+        let der = self.get_ca_cert_der()?;
+        let digest = Sha256::digest(&der);
+        let uuid = |salt: u8| {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&digest[..16]);
+            bytes[0] ^= salt;
+            uuid::Builder::from_random_bytes(bytes).into_uuid().to_string().to_uppercase()
+        };
+
+        Ok(format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>PayloadContent</key>
+    <array>
+        <dict>
+            <key>PayloadCertificateFileName</key>
+            <string>proxxy-ca.crt</string>
+            <key>PayloadContent</key>
+            <data>{certificate}</data>
+            <key>PayloadDescription</key>
+            <string>Adds the Proxxy CA root certificate</string>
+            <key>PayloadDisplayName</key>
+            <string>Proxxy CA</string>
+            <key>PayloadIdentifier</key>
+            <string>io.proxxy.ca.{fingerprint}</string>
+            <key>PayloadType</key>
+            <string>com.apple.security.root</string>
+            <key>PayloadUUID</key>
+            <string>{certificate_uuid}</string>
+            <key>PayloadVersion</key>
+            <integer>1</integer>
+        </dict>
+    </array>
+    <key>PayloadDescription</key>
+    <string>Trusts the Proxxy CA for intercepting this device's HTTPS traffic during testing</string>
+    <key>PayloadDisplayName</key>
+    <string>Proxxy CA</string>
+    <key>PayloadIdentifier</key>
+    <string>io.proxxy.profile.{fingerprint}</string>
+    <key>PayloadRemovalDisallowed</key>
+    <false/>
+    <key>PayloadType</key>
+    <string>Configuration</string>
+    <key>PayloadUUID</key>
+    <string>{profile_uuid}</string>
+    <key>PayloadVersion</key>
+    <integer>1</integer>
+</dict>
+</plist>
+"#,
+            certificate = base64::engine::general_purpose::STANDARD.encode(&der),
+            fingerprint = digest[..8].iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+            certificate_uuid = uuid(0),
+            profile_uuid = uuid(0xff),
+        ))
+    }
+}
+
+impl RootCa {
+    /// Root saved in `ca_dir`; `passphrase` is only used if the key is
+    /// encrypted
+    fn load(ca_dir: &Path, passphrase: &str) -> Result<Self> {
+        let cert_pem = fs::read_to_string(ca_dir.join("ca.pem")).map_err(ProxyError::Io)?;
+        let stored = Zeroizing::new(fs::read_to_string(ca_dir.join("ca.key")).map_err(ProxyError::Io)?);
+        if ca_key::is_encrypted(&stored) {
+            Self::from_pem(&cert_pem, &ca_key::decrypt(&stored, passphrase)?)
+        } else {
+            Self::from_pem(&cert_pem, &stored)
+        }
+    }
+
+    fn from_pem(cert_pem: &str, key_pem: &str) -> Result<Self> {
         // Parse the private key from PEM
         let key_pair = KeyPair::from_pem(key_pem)
             .map_err(|e| ProxyError::General(format!("Failed to parse CA key: {}", e)))?;
@@ -60,12 +422,17 @@ impl CertificateAuthority {
 
         let cert = Certificate::from_params(params)
             .map_err(|e| ProxyError::General(format!("Failed to recreate CA cert: {}", e)))?;
+        let loaded_pem = match rustls_pemfile::certs(&mut cert_pem.as_bytes()) {
+            Ok(certs) if !certs.is_empty() => Some(cert_pem.to_string()),
+            _ => None,
+        };
 
-        Ok(Self { ca_cert: cert })
+        Ok(Self { cert, loaded_pem })
     }
 
-    /// Generate a new Root CA and save it to disk
-    fn generate_and_save(cert_path: &Path, key_path: &Path) -> Result<Self> {
+    /// Generate a root and save it, the key encrypted with `passphrase` if
+    /// one is given
+    fn generate_and_save(cert_path: &Path, key_path: &Path, passphrase: Option<&str>) -> Result<Self> {
         let mut params = CertificateParams::default();
         let mut dn = DistinguishedName::new();
         dn.push(DnType::CommonName, "Proxxy CA");
@@ -95,74 +462,39 @@ impl CertificateAuthority {
         let cert_pem = cert
             .serialize_pem()
             .map_err(|e| ProxyError::General(format!("Failed to serialize CA cert: {}", e)))?;
-        let key_pem = cert.serialize_private_key_pem();
+        let key_pem = Zeroizing::new(cert.serialize_private_key_pem());
+        let stored_key = match passphrase {
+            Some(passphrase) => Zeroizing::new(ca_key::encrypt(&key_pem, passphrase)?),
+            None => key_pem,
+        };
 
-        fs::write(cert_path, &cert_pem).map_err(|e| ProxyError::Io(e))?;
-        fs::write(key_path, &key_pem).map_err(|e| ProxyError::Io(e))?;
+        fs::write(cert_path, &cert_pem).map_err(ProxyError::Io)?;
+        fs::write(key_path, stored_key.as_bytes()).map_err(ProxyError::Io)?;
 
         // Export .crt format as requested
         let crt_path = cert_path.with_extension("crt");
-        fs::write(crt_path, &cert_pem).map_err(|e| ProxyError::Io(e))?;
-
-        Ok(Self { ca_cert: cert })
-    }
-
-    /// Generate a certificate for a specific domain, signed by this CA.
-    ///
-    /// * `domain` - The domain name (e.g., "example.com") to generate a certificate for.
-    ///
-    /// Returns a tuple containing `(cert_pem, key_pem)`.
-    pub fn gen_cert_for_domain(&self, domain: &str) -> Result<(String, String)> {
-        let mut params = CertificateParams::new(vec![domain.to_string()]);
-
-        // Valid for 1 year
-        let not_before = OffsetDateTime::now_utc() - Duration::days(1);
-        let not_after = not_before + Duration::days(365);
-        params.not_before = not_before;
-        params.not_after = not_after;
-
-        let cert = Certificate::from_params(params).map_err(|e| {
-            ProxyError::General(format!("Failed to generate domain cert params: {}", e))
-        })?;
-
-        // Version 0.12+: verify signature.
-        let cert_pem = cert
-            .serialize_pem_with_signer(&self.ca_cert)
-            .map_err(|e| ProxyError::General(format!("Failed to sign domain cert: {}", e)))?;
-
-        let key_pem = cert.serialize_private_key_pem();
-
-        Ok((cert_pem, key_pem))
-    }
+        fs::write(crt_path, &cert_pem).map_err(ProxyError::Io)?;
 
-    /// Get the Root CA certificate in PEM format.
-    pub fn get_ca_cert_pem(&self) -> Result<String> {
-        self.ca_cert
-            .serialize_pem()
-            .map_err(|e| ProxyError::General(format!("Failed to serialize CA cert: {}", e)))
+        Ok(Self { cert, loaded_pem: Some(cert_pem) })
     }
+}
 
-    /// Get the Root CA private key in PEM format.
-    pub fn get_ca_key_pem(&self) -> Result<String> {
-        let mut key_pem = self.ca_cert.serialize_private_key_pem();
-        // Ensure proper newline at end
-        if !key_pem.ends_with('\n') {
-            key_pem.push('\n');
-        }
-        Ok(key_pem)
+impl Drop for RootCa {
+    fn drop(&mut self) {
+        self.cert.zeroize();
     }
+}
 
-    /// Get the Root CA certificate in DER format (for use with rustls/hudsucker).
-    pub fn get_ca_cert_der(&self) -> Result<Vec<u8>> {
-        self.ca_cert
-            .serialize_der()
-            .map_err(|e| ProxyError::General(format!("Failed to serialize CA cert DER: {}", e)))
-    }
+fn rsa_key_pair() -> Result<KeyPair> {
+    use rsa::pkcs8::EncodePrivateKey;
 
-    /// Get the Root CA private key in DER format (for use with rustls/hudsucker).
-    pub fn get_ca_key_der(&self) -> Result<Vec<u8>> {
-        Ok(self.ca_cert.serialize_private_key_der())
-    }
+    let key = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 2048)
+        .map_err(|e| ProxyError::Certificate(format!("Failed to generate RSA key: {}", e)))?;
+    let der = key
+        .to_pkcs8_der()
+        .map_err(|e| ProxyError::Certificate(format!("Failed to encode RSA key: {}", e)))?;
+    KeyPair::from_der(der.as_bytes())
+        .map_err(|e| ProxyError::Certificate(format!("Failed to load RSA key: {}", e)))
 }
 
 #[cfg(test)]
@@ -203,4 +535,89 @@ mod tests {
         assert!(cert_pem.contains("BEGIN CERTIFICATE"));
         assert!(key_pem.contains("BEGIN PRIVATE KEY"));
     }
+
+    #[test]
+    fn test_leaf_key_algorithm() {
+        let dir = tempdir().unwrap();
+        let ca = CertificateAuthority::new(dir.path()).unwrap();
+        assert_eq!(ca.leaf_key(), LeafKeyAlgorithm::EcdsaP256);
+
+        let (_, key_der) = ca.gen_cert_der_for_domain("ecdsa.test").unwrap();
+        assert!(KeyPair::from_der(&key_der).unwrap().is_compatible(&PKCS_ECDSA_P256_SHA256));
+
+        let ca = ca.with_leaf_key("rsa-2048".parse().unwrap());
+        let (_, key_der) = ca.gen_cert_der_for_domain("rsa.test").unwrap();
+        assert!(KeyPair::from_der(&key_der).unwrap().is_compatible(&PKCS_RSA_SHA256));
+
+        assert!("dsa".parse::<LeafKeyAlgorithm>().is_err());
+    }
+
+    #[test]
+    fn test_loaded_certificate_is_distributed() {
+        let dir = tempdir().unwrap();
+        CertificateAuthority::new(dir.path()).unwrap();
+        let on_disk = fs::read_to_string(dir.path().join("ca.pem")).unwrap();
+
+        let ca = CertificateAuthority::new(dir.path()).unwrap();
+        assert_eq!(ca.get_ca_cert_pem().unwrap(), on_disk);
+        let der = ca.get_ca_cert_der().unwrap();
+        assert_eq!(rustls_pemfile::certs(&mut on_disk.as_bytes()).unwrap(), vec![der.clone()]);
+
+        let profile = ca.get_ca_mobileconfig().unwrap();
+        assert!(profile.contains("<string>com.apple.security.root</string>"));
+        assert!(profile.contains(&base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &der)));
+        assert_eq!(profile, ca.get_ca_mobileconfig().unwrap());
+    }
+
+    #[test]
+    fn test_rotation_archives_previous_root() {
+        let dir = tempdir().unwrap();
+        let ca = CertificateAuthority::new(dir.path()).unwrap();
+        let old_pem = ca.get_ca_cert_pem().unwrap();
+        let old_key = ca.get_ca_key_pem().unwrap();
+
+        let archive = dir.path().join("archive").join("old");
+        ca.rotate(dir.path(), &archive).unwrap();
+        assert_eq!(ca.generation(), 1);
+        assert_ne!(ca.get_ca_cert_pem().unwrap(), old_pem);
+        assert_eq!(fs::read_to_string(archive.join("ca.pem")).unwrap(), old_pem);
+        assert_eq!(fs::read_to_string(dir.path().join("ca.pem")).unwrap(), ca.get_ca_cert_pem().unwrap());
+
+        // An agent given the new root by the orchestrator
+        let agent = CertificateAuthority::from_pem(&old_pem, &old_key).unwrap();
+        agent
+            .replace_from_pem(&ca.get_ca_cert_pem().unwrap(), &ca.get_ca_key_pem().unwrap())
+            .unwrap();
+        assert_eq!(agent.generation(), 1);
+        assert_eq!(agent.get_ca_cert_der().unwrap(), ca.get_ca_cert_der().unwrap());
+        assert!(agent.replace_from_pem("", "not a key").is_err());
+        assert_eq!(agent.generation(), 1);
+    }
+
+    #[test]
+    fn test_encrypted_key_is_locked_until_unlocked() {
+        let dir = tempdir().unwrap();
+        let plain = CertificateAuthority::new(dir.path()).unwrap();
+        let cert_pem = plain.get_ca_cert_pem().unwrap();
+
+        // A passphrase given later encrypts the existing key in place
+        let ca = CertificateAuthority::open(dir.path(), Some("hunter2")).unwrap();
+        assert_eq!(ca.get_ca_cert_pem().unwrap(), cert_pem);
+        assert!(ca_key::is_encrypted(&fs::read_to_string(dir.path().join("ca.key")).unwrap()));
+
+        let locked = CertificateAuthority::new(dir.path()).unwrap();
+        assert!(locked.is_locked());
+        assert!(locked.gen_cert_for_domain("example.com").is_err());
+        assert!(locked.unlock(dir.path(), "wrong").is_err());
+        assert!(locked.is_locked());
+        locked.unlock(dir.path(), "hunter2").unwrap();
+        assert_eq!(locked.get_ca_key_pem().unwrap(), plain.get_ca_key_pem().unwrap());
+
+        // A rotated root is stored encrypted as well
+        locked.rotate(dir.path(), &dir.path().join("archive")).unwrap();
+        assert!(ca_key::is_encrypted(&fs::read_to_string(dir.path().join("ca.key")).unwrap()));
+
+        locked.lock();
+        assert!(locked.is_locked() && locked.get_ca_cert_der().is_err());
+    }
 }
@@ -0,0 +1,95 @@
+//! Runtime pause/resume of traffic capture
+//!
+//! While capture is paused the proxy keeps forwarding traffic but reports
+//! none of it: no traffic events, interception or hook scripts. A pause
+//! covers every host, or only hosts matching the given patterns, so a tester
+//! can browse personal sites through the same device mid-engagement without
+//! those requests landing in the project. The state is set from the agent
+//! admin API or pushed by the orchestrator.
+
+use crate::pb::CaptureState;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tracing::info;
+use wildmatch::WildMatch;
+
+/// Capture state shared by the proxy handlers, the admin API and the
+/// orchestrator client
+#[derive(Debug, Clone, Default)]
+pub struct CaptureController {
+    state: Arc<RwLock<CaptureState>>,
+    /// Requests forwarded without being reported while paused
+    skipped: Arc<AtomicU64>,
+}
+
+impl CaptureController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the capture state
+    pub fn set_state(&self, state: CaptureState) {
+        match (state.paused, state.hosts.is_empty()) {
+            (false, _) => info!("Traffic capture resumed"),
+            (true, true) => info!("Traffic capture paused for all hosts"),
+            (true, false) => info!("Traffic capture paused for {}", state.hosts.join(", ")),
+        }
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = state;
+    }
+
+    /// Pause capture for hosts matching `hosts`, or for every host if empty
+    pub fn pause(&self, hosts: Vec<String>) {
+        self.set_state(CaptureState { paused: true, hosts });
+    }
+
+    pub fn resume(&self) {
+        self.set_state(CaptureState::default());
+    }
+
+    pub fn state(&self) -> CaptureState {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Whether traffic to `host` is reported. Counts the request as skipped
+    /// when it is not.
+    pub fn should_capture(&self, host: &str) -> bool {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let paused = state.paused
+            && (state.hosts.is_empty() || state.hosts.iter().any(|pattern| WildMatch::new(pattern).matches(host)));
+        if paused {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        !paused
+    }
+
+    /// Requests forwarded unreported since the agent started
+    pub fn skipped(&self) -> u64 {
+        self.skipped.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume() {
+        let controller = CaptureController::new();
+        assert!(controller.should_capture("example.com"));
+
+        controller.pause(Vec::new());
+        let shared = controller.clone();
+        assert!(!shared.should_capture("example.com"));
+        assert!(!shared.should_capture("target.test"));
+
+        controller.pause(vec!["*.bank.test".to_string(), "mail.test".to_string()]);
+        assert!(!controller.should_capture("www.bank.test"));
+        assert!(!controller.should_capture("mail.test"));
+        assert!(controller.should_capture("target.test"));
+
+        controller.resume();
+        assert!(controller.should_capture("www.bank.test"));
+        assert_eq!(controller.state(), CaptureState::default());
+        assert_eq!(controller.skipped(), 4);
+    }
+}
@@ -1,13 +1,51 @@
 //! Certificate management functionality
+//!
+//! Besides the generated certificates, the manager holds the client
+//! certificates presented to upstream targets that require mutual TLS. Each
+//! is configured for a host pattern, as a PEM certificate chain and key or a
+//! PKCS#12 archive; the first pattern matching a destination host selects it.
 
-use crate::{ProxyError, Result};
+use crate::{pb, ProxyError, Result};
 use rcgen::{Certificate, CertificateParams, DistinguishedName};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use tokio_rustls::rustls;
+use wildmatch::WildMatch;
 
 /// Certificate manager for handling SSL/TLS certificates
 pub struct CertificateManager {
     root_cert: Certificate,
     generated_certs: HashMap<String, Certificate>,
+    client_certs: Vec<ClientCertificate>,
+}
+
+/// Where a client certificate and its key come from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum ClientCertificateSource {
+    /// Certificate chain, client certificate first, and a PKCS#8, PKCS#1 or
+    /// SEC1 private key
+    Pem { cert_pem: String, key_pem: String },
+    /// DER-encoded PKCS#12 archive holding the certificate and its key
+    Pkcs12 { der: Vec<u8>, password: String },
+}
+
+/// Client certificate for the upstream hosts matching `host_pattern`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClientCertificateConfig {
+    /// Host pattern, `*` wildcards (e.g. `*.bank.example`)
+    pub host_pattern: String,
+    pub source: ClientCertificateSource,
+}
+
+/// Parsed client certificate, ready to be presented in a TLS handshake
+#[derive(Clone)]
+pub struct ClientCertificate {
+    host_pattern: String,
+    matcher: WildMatch,
+    chain: Vec<rustls::Certificate>,
+    key: rustls::PrivateKey,
 }
 
 impl CertificateManager {
@@ -23,6 +61,7 @@ impl CertificateManager {
         Ok(Self {
             root_cert,
             generated_certs: HashMap::new(),
+            client_certs: Vec::new(),
         })
     }
 
@@ -49,4 +88,218 @@ impl CertificateManager {
     pub fn root_certificate(&self) -> &Certificate {
         &self.root_cert
     }
+
+    /// Load a client certificate; it is tried after those added before it
+    pub fn add_client_certificate(&mut self, config: &ClientCertificateConfig) -> Result<()> {
+        self.client_certs.push(ClientCertificate::load(config)?);
+        Ok(())
+    }
+
+    /// Client certificate to present to `host`, if one is configured
+    pub fn client_certificate_for(&self, host: &str) -> Option<&ClientCertificate> {
+        self.client_certs.iter().find(|cert| cert.matches(host))
+    }
+
+    pub fn client_certificates(&self) -> &[ClientCertificate] {
+        &self.client_certs
+    }
+}
+
+impl ClientCertificateConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.host_pattern.trim().is_empty() {
+            return Err("host_pattern must be set".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<pb::ClientCertificate> for ClientCertificateConfig {
+    type Error = ProxyError;
+
+    fn try_from(cert: pb::ClientCertificate) -> Result<Self> {
+        let source = match cert.source {
+            Some(pb::client_certificate::Source::Pem(pem)) => ClientCertificateSource::Pem {
+                cert_pem: pem.cert_pem,
+                key_pem: pem.key_pem,
+            },
+            Some(pb::client_certificate::Source::Pkcs12(pkcs12)) => ClientCertificateSource::Pkcs12 {
+                der: pkcs12.der,
+                password: pkcs12.password,
+            },
+            None => {
+                return Err(ProxyError::Configuration(format!(
+                    "Client certificate for {} has no certificate",
+                    cert.host_pattern
+                )))
+            }
+        };
+        Ok(Self {
+            host_pattern: cert.host_pattern,
+            source,
+        })
+    }
+}
+
+impl From<&ClientCertificateConfig> for pb::ClientCertificate {
+    fn from(config: &ClientCertificateConfig) -> Self {
+        let source = match &config.source {
+            ClientCertificateSource::Pem { cert_pem, key_pem } => {
+                pb::client_certificate::Source::Pem(pb::PemClientCertificate {
+                    cert_pem: cert_pem.clone(),
+                    key_pem: key_pem.clone(),
+                })
+            }
+            ClientCertificateSource::Pkcs12 { der, password } => {
+                pb::client_certificate::Source::Pkcs12(pb::Pkcs12ClientCertificate {
+                    der: der.clone(),
+                    password: password.clone(),
+                })
+            }
+        };
+        Self {
+            host_pattern: config.host_pattern.clone(),
+            source: Some(source),
+        }
+    }
+}
+
+impl ClientCertificate {
+    /// Parse the configured certificate chain and private key
+    pub fn load(config: &ClientCertificateConfig) -> Result<Self> {
+        config.validate().map_err(ProxyError::Configuration)?;
+        let invalid = |reason: String| {
+            ProxyError::Certificate(format!("Client certificate for {}: {}", config.host_pattern, reason))
+        };
+
+        let (chain, key): (Vec<rustls::Certificate>, _) = match &config.source {
+            ClientCertificateSource::Pem { cert_pem, key_pem } => {
+                let chain = pem_items(cert_pem)
+                    .map_err(invalid)?
+                    .into_iter()
+                    .filter_map(|item| match item {
+                        rustls_pemfile::Item::X509Certificate(der) => Some(rustls::Certificate(der)),
+                        _ => None,
+                    })
+                    .collect();
+                let key = pem_items(key_pem).map_err(invalid)?.into_iter().find_map(|item| match item {
+                    rustls_pemfile::Item::PKCS8Key(der)
+                    | rustls_pemfile::Item::RSAKey(der)
+                    | rustls_pemfile::Item::ECKey(der) => Some(rustls::PrivateKey(der)),
+                    _ => None,
+                });
+                (chain, key)
+            }
+            ClientCertificateSource::Pkcs12 { der, password } => {
+                let pfx = p12::PFX::parse(der).map_err(|e| invalid(format!("not a PKCS#12 archive: {}", e)))?;
+                if !pfx.verify_mac(password) {
+                    return Err(invalid("wrong PKCS#12 password".to_string()));
+                }
+                let chain = pfx
+                    .cert_x509_bags(password)
+                    .map_err(|e| invalid(format!("unreadable PKCS#12 certificates: {}", e)))?
+                    .into_iter()
+                    .map(rustls::Certificate)
+                    .collect();
+                let key = pfx
+                    .key_bags(password)
+                    .map_err(|e| invalid(format!("unreadable PKCS#12 key: {}", e)))?
+                    .into_iter()
+                    .next()
+                    .map(rustls::PrivateKey);
+                (chain, key)
+            }
+        };
+
+        if chain.is_empty() {
+            return Err(invalid("no certificate found".to_string()));
+        }
+        let key = key.ok_or_else(|| invalid("no private key found".to_string()))?;
+        rustls::sign::any_supported_type(&key).map_err(|e| invalid(format!("unsupported private key: {}", e)))?;
+
+        Ok(Self {
+            host_pattern: config.host_pattern.clone(),
+            matcher: WildMatch::new(&config.host_pattern.to_ascii_lowercase()),
+            chain,
+            key,
+        })
+    }
+
+    pub fn host_pattern(&self) -> &str {
+        &self.host_pattern
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        self.matcher.matches(&host.to_ascii_lowercase())
+    }
+
+    /// Client certificate first, then any intermediates
+    pub fn chain(&self) -> &[rustls::Certificate] {
+        &self.chain
+    }
+
+    pub fn key(&self) -> &rustls::PrivateKey {
+        &self.key
+    }
+}
+
+// The private key stays out of logs
+impl fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientCertificate")
+            .field("host_pattern", &self.host_pattern)
+            .field("chain_length", &self.chain.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn pem_items(pem: &str) -> std::result::Result<Vec<rustls_pemfile::Item>, String> {
+    rustls_pemfile::read_all(&mut pem.as_bytes()).map_err(|e| format!("invalid PEM: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pem_config(host_pattern: &str) -> ClientCertificateConfig {
+        let cert = rcgen::generate_simple_self_signed(vec!["client.example".to_string()]).unwrap();
+        ClientCertificateConfig {
+            host_pattern: host_pattern.to_string(),
+            source: ClientCertificateSource::Pem {
+                cert_pem: cert.serialize_pem().unwrap(),
+                key_pem: cert.serialize_private_key_pem(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_client_certificate_selected_by_host() {
+        let mut manager = CertificateManager::new().unwrap();
+        manager.add_client_certificate(&pem_config("api.bank.example")).unwrap();
+        manager.add_client_certificate(&pem_config("*.bank.example")).unwrap();
+
+        let exact = manager.client_certificate_for("API.bank.example").unwrap();
+        assert_eq!(exact.host_pattern(), "api.bank.example");
+        assert_eq!(exact.chain().len(), 1);
+        assert_eq!(manager.client_certificate_for("www.bank.example").unwrap().host_pattern(), "*.bank.example");
+        assert!(manager.client_certificate_for("bank.example").is_none());
+        assert!(!format!("{:?}", exact).contains("key"));
+    }
+
+    #[test]
+    fn test_invalid_client_certificates_rejected() {
+        let mut missing_key = pem_config("*");
+        if let ClientCertificateSource::Pem { key_pem, .. } = &mut missing_key.source {
+            key_pem.clear();
+        }
+        let error = ClientCertificate::load(&missing_key).unwrap_err().to_string();
+        assert!(error.contains("no private key"), "{}", error);
+
+        assert!(ClientCertificate::load(&pem_config(" ")).is_err());
+        let garbage = ClientCertificateConfig {
+            host_pattern: "*".to_string(),
+            source: ClientCertificateSource::Pkcs12 { der: vec![1, 2, 3], password: String::new() },
+        };
+        assert!(ClientCertificate::load(&garbage).is_err());
+    }
 }
@@ -0,0 +1,162 @@
+//! Browser-like ClientHello profiles
+//!
+//! CDNs fingerprint the ClientHello (JA3/JA4) and block clients that do not
+//! look like a browser. A profile lists the cipher suites and key exchange
+//! groups in the order a browser offers them, as IANA code points, plus its
+//! ALPN protocols; both the agent's proxy path and its replay client build
+//! their TLS configuration from it.
+//!
+//! rustls implements a subset of what browsers offer: CBC suites, FFDHE and
+//! post-quantum groups and GREASE values are left out, and the extension
+//! order is rustls' own. The fingerprint gets closer to the browser's, with
+//! the same preferences and ALPN, but its JA3/JA4 hash still differs.
+
+use crate::{ProxyError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use tokio_rustls::rustls;
+
+const TLS13_AES_128_GCM_SHA256: u16 = 0x1301;
+const TLS13_AES_256_GCM_SHA384: u16 = 0x1302;
+const TLS13_CHACHA20_POLY1305_SHA256: u16 = 0x1303;
+const ECDHE_ECDSA_AES_128_GCM_SHA256: u16 = 0xc02b;
+const ECDHE_RSA_AES_128_GCM_SHA256: u16 = 0xc02f;
+const ECDHE_ECDSA_AES_256_GCM_SHA384: u16 = 0xc02c;
+const ECDHE_RSA_AES_256_GCM_SHA384: u16 = 0xc030;
+const ECDHE_ECDSA_CHACHA20_POLY1305_SHA256: u16 = 0xcca9;
+const ECDHE_RSA_CHACHA20_POLY1305_SHA256: u16 = 0xcca8;
+
+const X25519: u16 = 0x001d;
+const SECP256R1: u16 = 0x0017;
+const SECP384R1: u16 = 0x0018;
+
+/// Browser whose ClientHello is imitated
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ClientHelloProfile {
+    Chrome,
+    Firefox,
+    /// Safari on macOS and iOS
+    Safari,
+}
+
+impl ClientHelloProfile {
+    /// Cipher suites in the browser's order of preference
+    pub fn cipher_suites(self) -> &'static [u16] {
+        match self {
+            ClientHelloProfile::Chrome => &[
+                TLS13_AES_128_GCM_SHA256,
+                TLS13_AES_256_GCM_SHA384,
+                TLS13_CHACHA20_POLY1305_SHA256,
+                ECDHE_ECDSA_AES_128_GCM_SHA256,
+                ECDHE_RSA_AES_128_GCM_SHA256,
+                ECDHE_ECDSA_AES_256_GCM_SHA384,
+                ECDHE_RSA_AES_256_GCM_SHA384,
+                ECDHE_ECDSA_CHACHA20_POLY1305_SHA256,
+                ECDHE_RSA_CHACHA20_POLY1305_SHA256,
+            ],
+            ClientHelloProfile::Firefox => &[
+                TLS13_AES_128_GCM_SHA256,
+                TLS13_CHACHA20_POLY1305_SHA256,
+                TLS13_AES_256_GCM_SHA384,
+                ECDHE_ECDSA_AES_128_GCM_SHA256,
+                ECDHE_RSA_AES_128_GCM_SHA256,
+                ECDHE_ECDSA_CHACHA20_POLY1305_SHA256,
+                ECDHE_RSA_CHACHA20_POLY1305_SHA256,
+                ECDHE_ECDSA_AES_256_GCM_SHA384,
+                ECDHE_RSA_AES_256_GCM_SHA384,
+            ],
+            ClientHelloProfile::Safari => &[
+                TLS13_AES_128_GCM_SHA256,
+                TLS13_AES_256_GCM_SHA384,
+                TLS13_CHACHA20_POLY1305_SHA256,
+                ECDHE_ECDSA_AES_256_GCM_SHA384,
+                ECDHE_ECDSA_AES_128_GCM_SHA256,
+                ECDHE_ECDSA_CHACHA20_POLY1305_SHA256,
+                ECDHE_RSA_AES_256_GCM_SHA384,
+                ECDHE_RSA_AES_128_GCM_SHA256,
+                ECDHE_RSA_CHACHA20_POLY1305_SHA256,
+            ],
+        }
+    }
+
+    /// Key exchange groups in the browser's order of preference
+    pub fn named_groups(self) -> &'static [u16] {
+        // All three browsers put these first, in this order
+        &[X25519, SECP256R1, SECP384R1]
+    }
+
+    pub fn alpn_protocols(self) -> &'static [&'static str] {
+        &["h2", "http/1.1"]
+    }
+
+    /// The profile's cipher suites that rustls implements, in profile order
+    pub fn rustls_cipher_suites(self) -> Vec<rustls::SupportedCipherSuite> {
+        self.cipher_suites()
+            .iter()
+            .filter_map(|id| {
+                rustls::ALL_CIPHER_SUITES
+                    .iter()
+                    .find(|suite| suite.suite().get_u16() == *id)
+                    .copied()
+            })
+            .collect()
+    }
+
+    /// The profile's key exchange groups that rustls implements, in profile order
+    pub fn rustls_kx_groups(self) -> Vec<&'static rustls::SupportedKxGroup> {
+        self.named_groups()
+            .iter()
+            .filter_map(|id| {
+                rustls::ALL_KX_GROUPS
+                    .iter()
+                    .find(|group| group.name.get_u16() == *id)
+                    .copied()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ClientHelloProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientHelloProfile::Chrome => write!(f, "chrome"),
+            ClientHelloProfile::Firefox => write!(f, "firefox"),
+            ClientHelloProfile::Safari => write!(f, "safari"),
+        }
+    }
+}
+
+impl FromStr for ClientHelloProfile {
+    type Err = ProxyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "chrome" => Ok(ClientHelloProfile::Chrome),
+            "firefox" => Ok(ClientHelloProfile::Firefox),
+            "safari" | "ios" => Ok(ClientHelloProfile::Safari),
+            _ => Err(ProxyError::Configuration(format!("Unknown ClientHello profile '{}'", s))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_keep_browser_order() {
+        for profile in [ClientHelloProfile::Chrome, ClientHelloProfile::Firefox, ClientHelloProfile::Safari] {
+            let suites: Vec<u16> = profile.rustls_cipher_suites().iter().map(|s| s.suite().get_u16()).collect();
+            assert_eq!(suites, profile.cipher_suites(), "{}", profile);
+            assert_eq!(profile.rustls_kx_groups().len(), 3);
+            assert_eq!(profile.to_string().parse::<ClientHelloProfile>().unwrap(), profile);
+        }
+        assert_eq!(
+            ClientHelloProfile::Firefox.rustls_cipher_suites()[1].suite(),
+            rustls::CipherSuite::TLS13_CHACHA20_POLY1305_SHA256
+        );
+        assert!("opera".parse::<ClientHelloProfile>().is_err());
+    }
+}
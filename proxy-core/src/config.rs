@@ -1,8 +1,20 @@
 //! Configuration types and utilities
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
 use std::time::Duration;
+use crate::ca::LeafKeyAlgorithm;
+use crate::certificates::ClientCertificateConfig;
+use crate::dns::DnsConfig;
 use crate::error::BodyCaptureError;
+use crate::leaf_cache::DEFAULT_LEAF_CACHE_CAPACITY;
+use crate::mail::MailListenerConfig;
+use crate::policy::{ConnectionPolicy, EgressPolicy, InterceptionRule};
+use crate::reverse::ReverseListenerConfig;
+use crate::target_auth::TargetAuthConfig;
+use crate::tls_policy::TlsPolicyConfig;
+use crate::transparent::TransparentListenerConfig;
 
 /// Static Proxy Startup Configuration
 /// These settings are set at startup and do not change during runtime.
@@ -16,8 +28,68 @@ pub struct ProxyStartupConfig {
     pub orchestrator_endpoint: String,
     /// Admin API port
     pub admin_port: u16,
+    /// Bearer token for the admin API's control routes; without one they
+    /// are disabled
+    #[serde(default, skip_serializing)]
+    pub admin_token: Option<String>,
     /// Certificate configuration
     pub certificate_config: CertificateConfig,
+    /// Request line and header limits enforced before a request is forwarded
+    #[serde(default)]
+    pub request_limits: RequestLimits,
+    /// Resolver used for upstream connections (system resolver by default)
+    #[serde(default)]
+    pub dns: DnsConfig,
+    /// Hosts resolved to a fixed address instead of through DNS
+    #[serde(default)]
+    pub host_overrides: HashMap<String, IpAddr>,
+    /// PAC script (URL or file path) used to choose an upstream proxy per destination
+    #[serde(default)]
+    pub pac_source: Option<String>,
+    /// Speak only HTTP/1.1 to upstreams instead of negotiating HTTP/2 via ALPN
+    #[serde(default)]
+    pub force_http1: bool,
+    /// Per-scope egress routes; tunnel routes must already be resolved to SOCKS
+    #[serde(default)]
+    pub egress: EgressPolicy,
+    /// Upstream timeouts and keep-alive, overridable per scope
+    #[serde(default)]
+    pub connection: ConnectionPolicy,
+    /// Rules whose `Throttle` action delays and slows matching exchanges
+    #[serde(default)]
+    pub throttle_rules: Vec<InterceptionRule>,
+    /// SMTP/IMAP capture listeners started next to the HTTP proxy
+    #[serde(default)]
+    pub mail_listeners: Vec<MailListenerConfig>,
+    /// Host patterns whose CONNECT tunnels are relayed without TLS
+    /// interception (certificate-pinned clients)
+    #[serde(default)]
+    pub tls_passthrough: Vec<String>,
+    /// Temporary pass-through for hosts whose interception keeps failing
+    #[serde(default)]
+    pub mitm_fallback: MitmFallbackConfig,
+    /// TLS listeners forwarding to upstreams chosen by SNI
+    #[serde(default)]
+    pub reverse_listeners: Vec<ReverseListenerConfig>,
+    /// Listener for connections redirected to the agent by the packet filter
+    #[serde(default)]
+    pub transparent_listener: Option<TransparentListenerConfig>,
+    /// Header carrying the transaction's request ID to the upstream, so
+    /// target-side logs can be matched to captured traffic
+    #[serde(default)]
+    pub request_id_header: Option<String>,
+    /// Credentials the agent answers target authentication with, per host pattern
+    #[serde(default)]
+    pub target_auth: Vec<TargetAuthConfig>,
+    /// Client certificates presented to upstreams requiring mutual TLS, per host pattern
+    #[serde(default)]
+    pub client_certificates: Vec<ClientCertificateConfig>,
+    /// TLS versions, trusted roots and verification for upstreams, per host pattern
+    #[serde(default)]
+    pub tls_policies: Vec<TlsPolicyConfig>,
+    /// Host patterns whose requests get the agent's stored cookies attached
+    #[serde(default)]
+    pub maintain_session: Vec<String>,
 }
 
 impl Default for ProxyStartupConfig {
@@ -27,11 +99,170 @@ impl Default for ProxyStartupConfig {
             listen_port: 8080,
             orchestrator_endpoint: "http://127.0.0.1:9090".to_string(),
             admin_port: 9091,
+            admin_token: None,
             certificate_config: CertificateConfig::default(),
+            request_limits: RequestLimits::default(),
+            dns: DnsConfig::default(),
+            host_overrides: HashMap::new(),
+            pac_source: None,
+            force_http1: false,
+            egress: EgressPolicy::default(),
+            connection: ConnectionPolicy::default(),
+            throttle_rules: Vec::new(),
+            mail_listeners: Vec::new(),
+            tls_passthrough: Vec::new(),
+            mitm_fallback: MitmFallbackConfig::default(),
+            reverse_listeners: Vec::new(),
+            transparent_listener: None,
+            request_id_header: None,
+            target_auth: Vec::new(),
+            client_certificates: Vec::new(),
+            tls_policies: Vec::new(),
+            maintain_session: Vec::new(),
         }
     }
 }
 
+/// When a host is tunnelled without interception because interception keeps
+/// failing for it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MitmFallbackConfig {
+    /// Failures within `window_secs` that move a host to pass-through; 0
+    /// disables the fallback
+    pub failure_threshold: u32,
+    pub window_secs: u64,
+    /// How long the host stays in pass-through
+    pub duration_secs: u64,
+}
+
+impl Default for MitmFallbackConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            window_secs: 300,
+            duration_secs: 3600,
+        }
+    }
+}
+
+/// Limits on the request line and headers of incoming requests.
+///
+/// Requests exceeding a limit are answered by the agent itself (414 for long URLs,
+/// 431 for oversized header sections) and never reach the upstream.
+///
+/// On the proxy listener the request head is bounded while it is parsed: the
+/// HTTP/1 read buffer is capped at [`RequestLimits::parse_buffer_size`] and the
+/// parser refuses more than 100 header fields. The exact limits are checked once
+/// the head is parsed, which is also the only bound for requests decrypted inside
+/// intercepted TLS tunnels.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestLimits {
+    /// Maximum total size of all header names and values (in bytes)
+    pub max_header_bytes: usize,
+    /// Maximum number of header fields
+    pub max_header_count: usize,
+    /// Maximum length of the request URL (in bytes)
+    pub max_url_length: usize,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_header_bytes: 64 * 1024, // 64KB
+            max_header_count: 100,
+            max_url_length: 8 * 1024,    // 8KB
+        }
+    }
+}
+
+/// A request limit that was exceeded
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestLimitViolation {
+    /// URL longer than `max_url_length`
+    UrlTooLong { length: usize, limit: usize },
+    /// More header fields than `max_header_count`
+    TooManyHeaders { count: usize, limit: usize },
+    /// Header section larger than `max_header_bytes`
+    HeadersTooLarge { bytes: usize, limit: usize },
+}
+
+impl RequestLimitViolation {
+    /// HTTP status code to answer with (414 URI Too Long / 431 Request Header Fields Too Large)
+    pub fn status_code(&self) -> u16 {
+        match self {
+            RequestLimitViolation::UrlTooLong { .. } => 414,
+            RequestLimitViolation::TooManyHeaders { .. }
+            | RequestLimitViolation::HeadersTooLarge { .. } => 431,
+        }
+    }
+}
+
+impl std::fmt::Display for RequestLimitViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestLimitViolation::UrlTooLong { length, limit } => {
+                write!(f, "URL length {} exceeds limit of {} bytes", length, limit)
+            }
+            RequestLimitViolation::TooManyHeaders { count, limit } => {
+                write!(f, "{} header fields exceed limit of {}", count, limit)
+            }
+            RequestLimitViolation::HeadersTooLarge { bytes, limit } => {
+                write!(f, "Header section of {} bytes exceeds limit of {} bytes", bytes, limit)
+            }
+        }
+    }
+}
+
+/// Smallest read buffer the HTTP/1 parser accepts
+const MIN_PARSE_BUFFER_BYTES: usize = 8 * 1024;
+
+impl RequestLimits {
+    /// Read buffer size for the proxy listener's HTTP/1 parser: room for the
+    /// longest allowed request line plus the largest allowed header section,
+    /// counting the `": "` and CRLF around every field. A head that does not
+    /// fit is answered with 431 before its headers are collected.
+    pub fn parse_buffer_size(&self) -> usize {
+        self.max_url_length
+            .saturating_add(self.max_header_bytes)
+            .saturating_add(self.max_header_count.saturating_mul(4))
+            .saturating_add(64)
+            .max(MIN_PARSE_BUFFER_BYTES)
+    }
+
+    /// Check request dimensions against the limits. The URL is checked first,
+    /// then the header count, then the total header size.
+    ///
+    /// `header_bytes` is the summed length of header names and values.
+    pub fn check(
+        &self,
+        url_length: usize,
+        header_count: usize,
+        header_bytes: usize,
+    ) -> Option<RequestLimitViolation> {
+        if url_length > self.max_url_length {
+            return Some(RequestLimitViolation::UrlTooLong {
+                length: url_length,
+                limit: self.max_url_length,
+            });
+        }
+        if header_count > self.max_header_count {
+            return Some(RequestLimitViolation::TooManyHeaders {
+                count: header_count,
+                limit: self.max_header_count,
+            });
+        }
+        if header_bytes > self.max_header_bytes {
+            return Some(RequestLimitViolation::HeadersTooLarge {
+                bytes: header_bytes,
+                limit: self.max_header_bytes,
+            });
+        }
+        None
+    }
+}
+
 /// Certificate configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CertificateConfig {
@@ -39,6 +270,16 @@ pub struct CertificateConfig {
     pub cert_store_path: String,
     /// Certificate validity duration in days
     pub validity_days: u32,
+    /// Key type of the leaf certificates presented to intercepted clients
+    #[serde(default)]
+    pub leaf_key_algorithm: LeafKeyAlgorithm,
+    /// Hosts whose leaf certificates are kept for reuse
+    #[serde(default = "default_leaf_cache_capacity")]
+    pub leaf_cache_capacity: usize,
+}
+
+fn default_leaf_cache_capacity() -> usize {
+    DEFAULT_LEAF_CACHE_CAPACITY
 }
 
 impl Default for CertificateConfig {
@@ -46,6 +287,8 @@ impl Default for CertificateConfig {
         Self {
             cert_store_path: "./certs".to_string(),
             validity_days: 365,
+            leaf_key_algorithm: LeafKeyAlgorithm::default(),
+            leaf_cache_capacity: DEFAULT_LEAF_CACHE_CAPACITY,
         }
     }
 }
@@ -374,6 +617,45 @@ impl BodyCaptureConfig {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_request_limits_check() {
+        let limits = RequestLimits {
+            max_header_bytes: 100,
+            max_header_count: 3,
+            max_url_length: 20,
+        };
+
+        assert_eq!(limits.check(20, 3, 100), None);
+
+        let url = limits.check(21, 50, 5000).unwrap();
+        assert_eq!(url, RequestLimitViolation::UrlTooLong { length: 21, limit: 20 });
+        assert_eq!(url.status_code(), 414);
+
+        let count = limits.check(10, 4, 10).unwrap();
+        assert_eq!(count.status_code(), 431);
+
+        let bytes = limits.check(10, 2, 101).unwrap();
+        assert_eq!(bytes, RequestLimitViolation::HeadersTooLarge { bytes: 101, limit: 100 });
+        assert_eq!(bytes.status_code(), 431);
+
+        assert_eq!(limits.parse_buffer_size(), 8 * 1024);
+        let defaults = RequestLimits::default();
+        assert_eq!(defaults.parse_buffer_size(), 8 * 1024 + 64 * 1024 + 400 + 64);
+    }
+
+    #[test]
+    fn test_startup_config_without_limits_deserializes() {
+        let json = r#"{
+            "listen_address": "0.0.0.0",
+            "listen_port": 9095,
+            "orchestrator_endpoint": "http://127.0.0.1:50051",
+            "admin_port": 9091,
+            "certificate_config": { "cert_store_path": "./certs", "validity_days": 365 }
+        }"#;
+        let config: ProxyStartupConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.request_limits, RequestLimits::default());
+    }
+
     #[test]
     fn test_content_type_filtering_capture_all() {
         let config = BodyCaptureConfig::default();
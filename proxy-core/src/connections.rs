@@ -0,0 +1,250 @@
+//! Connection-level event log
+//!
+//! Traffic that never becomes an HTTP transaction: CONNECT tunnels, hosts
+//! tunnelled without interception, upstream TLS and connection failures, and
+//! requests the agent blocks itself. These events go to the orchestrator
+//! alongside the transactions, so a failed MITM shows up instead of the
+//! traffic just vanishing.
+//!
+//! A client that rejects the proxy certificate (untrusted CA, pinning) aborts
+//! the handshake inside hudsucker, where no handler sees it. The log therefore
+//! tracks intercepted tunnels per client connection and reports one that has
+//! carried no request by the time it is swept as `NO_TRAFFIC`.
+//!
+//! Such tunnels and upstream TLS failures count towards the automatic
+//! pass-through of the host when a [`MitmFallback`] is attached.
+
+use crate::mitm_fallback::{target_host, MitmFallback};
+use crate::pb::{connection_event::Kind, traffic_event, ConnectionEvent, TrafficEvent};
+use dashmap::DashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tracing::debug;
+
+/// How long an intercepted tunnel may go without a request before it is
+/// reported
+pub const UNUSED_TUNNEL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends connection events and tracks intercepted tunnels
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLog {
+    sender: Option<Sender<TrafficEvent>>,
+    /// Client connection -> (CONNECT target, opened at) for intercepted
+    /// tunnels that have not carried a request yet
+    open_tunnels: Arc<DashMap<SocketAddr, (String, Instant)>>,
+    /// Hosts moved to pass-through after repeated interception failures
+    fallback: Option<MitmFallback>,
+}
+
+impl ConnectionLog {
+    pub fn new(sender: Option<Sender<TrafficEvent>>) -> Self {
+        Self {
+            sender,
+            open_tunnels: Arc::new(DashMap::new()),
+            fallback: None,
+        }
+    }
+
+    pub fn with_fallback(mut self, fallback: MitmFallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Interception of `target` failed; moves its host to pass-through once
+    /// it has failed often enough
+    pub fn interception_failed(&self, target: &str, client: SocketAddr, reason: &str) {
+        let Some(fallback) = &self.fallback else {
+            return;
+        };
+        let host = target_host(target);
+        if fallback.record_failure(host, reason) {
+            self.record(
+                Kind::AutoPassThrough,
+                host,
+                client,
+                &format!("Interception kept failing, tunnelling without interception: {}", reason),
+                None,
+            );
+        }
+    }
+
+    /// Send an event. `request_id` links failures of a captured request to
+    /// its transaction.
+    pub fn record(&self, kind: Kind, target: &str, client: SocketAddr, reason: &str, request_id: Option<&str>) {
+        debug!("Connection event {:?} {} from {}: {}", kind, target, client, reason);
+        let Some(sender) = &self.sender else {
+            return;
+        };
+        let _ = sender.try_send(TrafficEvent {
+            request_id: request_id.unwrap_or_default().to_string(),
+            event: Some(traffic_event::Event::Connection(ConnectionEvent {
+                kind: kind as i32,
+                target: target.to_string(),
+                client_addr: client.to_string(),
+                reason: reason.to_string(),
+            })),
+        });
+    }
+
+    /// A CONNECT tunnel to `target` was opened by `client`
+    pub fn tunnel_opened(&self, client: SocketAddr, target: &str) {
+        self.record(Kind::Connect, target, client, "", None);
+        self.open_tunnels.insert(client, (target.to_string(), Instant::now()));
+    }
+
+    /// The tunnel of `client` is passed through without interception
+    pub fn tunnel_passed_through(&self, client: SocketAddr, target: &str, reason: &str) {
+        if self.open_tunnels.remove(&client).is_some() {
+            self.record(Kind::PassThrough, target, client, reason, None);
+        }
+    }
+
+    /// A decrypted request arrived over the tunnel of `client`
+    pub fn tunnel_used(&self, client: SocketAddr) {
+        self.open_tunnels.remove(&client);
+    }
+
+    /// Report and forget tunnels older than `timeout` that carried no
+    /// request. Returns the number reported.
+    pub fn sweep(&self, timeout: Duration) -> usize {
+        let mut stale = Vec::new();
+        self.open_tunnels.retain(|client, (target, opened)| {
+            let keep = opened.elapsed() < timeout;
+            if !keep {
+                stale.push((*client, target.clone()));
+            }
+            keep
+        });
+        for (client, target) in &stale {
+            let reason = format!(
+                "Intercepted tunnel carried no request within {}s; the client may have rejected the proxy certificate",
+                timeout.as_secs()
+            );
+            self.record(Kind::NoTraffic, target, *client, &reason, None);
+            self.interception_failed(target, *client, &reason);
+        }
+        stale.len()
+    }
+
+    /// Sweep unused tunnels until the event channel closes
+    pub async fn run_sweeper(self) {
+        let mut interval = tokio::time::interval(UNUSED_TUNNEL_TIMEOUT / 2);
+        loop {
+            interval.tick().await;
+            if self.sender.as_ref().is_none_or(|s| s.is_closed()) {
+                return;
+            }
+            self.sweep(UNUSED_TUNNEL_TIMEOUT);
+        }
+    }
+}
+
+/// Kind and innermost reason of an upstream request failure
+pub fn classify_upstream_error(err: &(dyn std::error::Error + 'static)) -> (Kind, String) {
+    let mut reason = err.to_string();
+    let mut tls = false;
+    let mut source = Some(err);
+    while let Some(e) = source {
+        let message = e.to_string();
+        let lower = message.to_ascii_lowercase();
+        tls |= lower.contains("certificate") || lower.contains("tls") || lower.contains("handshake");
+        if !message.is_empty() {
+            reason = message;
+        }
+        source = e.source();
+    }
+    (if tls { Kind::TlsFailure } else { Kind::UpstreamError }, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    fn client(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    fn kinds(rx: &mut mpsc::Receiver<TrafficEvent>) -> Vec<(Kind, String)> {
+        let mut out = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let Some(traffic_event::Event::Connection(c)) = event.event {
+                out.push((c.kind(), c.target));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_tunnel_tracking() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let log = ConnectionLog::new(Some(tx));
+
+        log.tunnel_opened(client(1), "used.test:443");
+        log.tunnel_opened(client(2), "pinned.test:443");
+        log.tunnel_opened(client(3), "bank.test:443");
+        log.tunnel_used(client(1));
+        log.tunnel_passed_through(client(3), "bank.test:443", "TLS pass-through host");
+        // Only tracked tunnels are reported as passed through
+        log.tunnel_passed_through(client(9), "other.test:443", "TLS pass-through host");
+
+        assert_eq!(log.sweep(Duration::from_secs(60)), 0);
+        assert_eq!(log.sweep(Duration::ZERO), 1);
+        assert_eq!(
+            kinds(&mut rx),
+            vec![
+                (Kind::Connect, "used.test:443".to_string()),
+                (Kind::Connect, "pinned.test:443".to_string()),
+                (Kind::Connect, "bank.test:443".to_string()),
+                (Kind::PassThrough, "bank.test:443".to_string()),
+                (Kind::NoTraffic, "pinned.test:443".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_tunnels_trigger_pass_through() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let fallback = MitmFallback::new(crate::config::MitmFallbackConfig {
+            failure_threshold: 2,
+            ..Default::default()
+        });
+        let log = ConnectionLog::new(Some(tx)).with_fallback(fallback.clone());
+
+        for port in [1, 2] {
+            log.tunnel_opened(client(port), "pinned.test:443");
+            log.sweep(Duration::ZERO);
+        }
+        assert!(fallback.is_bypassed("pinned.test"));
+        assert_eq!(
+            kinds(&mut rx).last(),
+            Some(&(Kind::AutoPassThrough, "pinned.test".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_classify_upstream_error() {
+        #[derive(Debug)]
+        struct Outer(std::io::Error);
+        impl std::fmt::Display for Outer {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "error trying to connect")
+            }
+        }
+        impl std::error::Error for Outer {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let tls = Outer(std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid peer certificate: UnknownIssuer"));
+        assert_eq!(
+            classify_upstream_error(&tls),
+            (Kind::TlsFailure, "invalid peer certificate: UnknownIssuer".to_string())
+        );
+        let refused = Outer(std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused"));
+        assert_eq!(classify_upstream_error(&refused).0, Kind::UpstreamError);
+    }
+}
@@ -1,20 +1,132 @@
-use crate::pb::InterceptCommand;
+use crate::pb::{intercept_rule, InterceptCommand, InterceptRuleStat, InterceptRuleStats, InterceptRules};
 use dashmap::DashMap;
 use tokio::sync::oneshot;
 use tracing::info;
 
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone, Default)]
+/// How long a paused request waits for a decision before it is forwarded unchanged
+pub const DEFAULT_HOLD_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
 pub struct InterceptController {
     /// Maps Request ID -> Sender for resume signal
     pending_requests: Arc<DashMap<String, oneshot::Sender<InterceptCommand>>>,
+    /// Rules pushed by the orchestrator
+    rules: Arc<RwLock<InterceptRules>>,
+    /// Maps Rule ID -> counters reported back to the orchestrator
+    rule_stats: Arc<DashMap<String, InterceptRuleStat>>,
+    hold_timeout: Duration,
+}
+
+impl Default for InterceptController {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl InterceptController {
     pub fn new() -> Self {
         Self {
             pending_requests: Arc::new(DashMap::new()),
+            rules: Arc::new(RwLock::new(InterceptRules::default())),
+            rule_stats: Arc::new(DashMap::new()),
+            hold_timeout: DEFAULT_HOLD_TIMEOUT,
+        }
+    }
+
+    pub fn with_hold_timeout(mut self, hold_timeout: Duration) -> Self {
+        self.hold_timeout = hold_timeout;
+        self
+    }
+
+    pub fn hold_timeout(&self) -> Duration {
+        self.hold_timeout
+    }
+
+    /// Replace the active rule set. Counters of rules that are still present
+    /// carry over.
+    pub fn set_rules(&self, rules: InterceptRules) {
+        info!(
+            "Interception {} with {} rule(s)",
+            if rules.enabled { "enabled" } else { "disabled" },
+            rules.rules.len()
+        );
+        self.rule_stats
+            .retain(|id, _| rules.rules.iter().any(|rule| &rule.id == id));
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+    }
+
+    /// Counters of the current rules, in rule order
+    pub fn rule_stats(&self) -> InterceptRuleStats {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        InterceptRuleStats {
+            rules: rules
+                .rules
+                .iter()
+                .map(|rule| {
+                    self.rule_stats
+                        .get(&rule.id)
+                        .map(|stat| stat.clone())
+                        .unwrap_or_else(|| InterceptRuleStat {
+                            rule_id: rule.id.clone(),
+                            ..Default::default()
+                        })
+                })
+                .collect(),
+        }
+    }
+
+    /// Action of the first rule matching the request, if interception is on.
+    /// Header names are compared case-insensitively.
+    pub fn match_request(
+        &self,
+        method: &str,
+        url: &str,
+        headers: &HashMap<String, String>,
+    ) -> Option<intercept_rule::Action> {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        if !rules.enabled {
+            return None;
+        }
+
+        rules
+            .rules
+            .iter()
+            .find(|rule| {
+                let started = Instant::now();
+                let method_ok = rule.methods.is_empty()
+                    || rule.methods.iter().any(|m| m.eq_ignore_ascii_case(method));
+                let url_ok = rule.url_contains.is_empty() || url.contains(&rule.url_contains);
+                let header_ok = rule.header_name.is_empty()
+                    || headers.iter().any(|(name, value)| {
+                        name.eq_ignore_ascii_case(&rule.header_name) && value.contains(&rule.header_value)
+                    });
+                let matched = method_ok && url_ok && header_ok;
+                self.count_evaluation(&rule.id, matched, started.elapsed());
+                matched
+            })
+            .map(|rule| rule.action())
+    }
+
+    fn count_evaluation(&self, rule_id: &str, matched: bool, elapsed: Duration) {
+        let mut stat = self
+            .rule_stats
+            .entry(rule_id.to_string())
+            .or_insert_with(|| InterceptRuleStat {
+                rule_id: rule_id.to_string(),
+                ..Default::default()
+            });
+        stat.evaluations += 1;
+        stat.eval_time_ns += elapsed.as_nanos() as u64;
+        if matched {
+            stat.hits += 1;
+            stat.last_hit = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default();
         }
     }
 
@@ -36,4 +148,14 @@ impl InterceptController {
             false
         }
     }
+
+    /// Forget a paused request without resuming it (e.g. after its hold timed out)
+    pub fn cancel_request(&self, request_id: &str) -> bool {
+        self.pending_requests.remove(request_id).is_some()
+    }
+
+    /// Number of requests currently waiting for a decision
+    pub fn pending_count(&self) -> usize {
+        self.pending_requests.len()
+    }
 }
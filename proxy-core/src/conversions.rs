@@ -0,0 +1,151 @@
+//! Conversions between the wire messages and proxy-common's HTTP types
+//!
+//! Both directions carry every field, so a request or response survives a
+//! round trip through an agent unchanged. An empty protocol on the wire is
+//! an unknown one.
+
+use crate::pb;
+use proxy_common::http::{HttpHeaders, HttpRequestData, HttpResponseData, ResponseTiming, TlsDetails};
+
+impl From<pb::HttpHeaders> for HttpHeaders {
+    fn from(headers: pb::HttpHeaders) -> Self {
+        Self { headers: headers.headers }
+    }
+}
+
+impl From<HttpHeaders> for pb::HttpHeaders {
+    fn from(headers: HttpHeaders) -> Self {
+        Self { headers: headers.headers }
+    }
+}
+
+impl From<pb::TlsDetails> for TlsDetails {
+    fn from(tls: pb::TlsDetails) -> Self {
+        Self {
+            version: tls.version,
+            cipher: tls.cipher,
+        }
+    }
+}
+
+impl From<TlsDetails> for pb::TlsDetails {
+    fn from(tls: TlsDetails) -> Self {
+        Self {
+            version: tls.version,
+            cipher: tls.cipher,
+        }
+    }
+}
+
+impl From<pb::RequestTiming> for ResponseTiming {
+    fn from(timing: pb::RequestTiming) -> Self {
+        Self {
+            connect_us: timing.connect_us,
+            ttfb_us: timing.ttfb_us,
+            transfer_us: timing.transfer_us,
+            total_us: timing.total_us,
+        }
+    }
+}
+
+impl From<ResponseTiming> for pb::RequestTiming {
+    fn from(timing: ResponseTiming) -> Self {
+        Self {
+            connect_us: timing.connect_us,
+            ttfb_us: timing.ttfb_us,
+            transfer_us: timing.transfer_us,
+            total_us: timing.total_us,
+        }
+    }
+}
+
+impl From<pb::HttpRequestData> for HttpRequestData {
+    fn from(request: pb::HttpRequestData) -> Self {
+        Self {
+            method: request.method,
+            url: request.url,
+            headers: request.headers.map(Into::into),
+            body: request.body.into(),
+            tls: request.tls.map(Into::into),
+        }
+    }
+}
+
+impl From<HttpRequestData> for pb::HttpRequestData {
+    fn from(request: HttpRequestData) -> Self {
+        Self {
+            method: request.method,
+            url: request.url,
+            headers: request.headers.map(Into::into),
+            body: request.body.into(),
+            tls: request.tls.map(Into::into),
+        }
+    }
+}
+
+impl From<pb::HttpResponseData> for HttpResponseData {
+    fn from(response: pb::HttpResponseData) -> Self {
+        Self {
+            status_code: response.status_code,
+            headers: response.headers.map(Into::into),
+            body: response.body.into(),
+            tls: response.tls.map(Into::into),
+            timing: response.timing.map(Into::into),
+            protocol: Some(response.protocol).filter(|protocol| !protocol.is_empty()),
+        }
+    }
+}
+
+impl From<HttpResponseData> for pb::HttpResponseData {
+    fn from(response: HttpResponseData) -> Self {
+        Self {
+            status_code: response.status_code,
+            headers: response.headers.map(Into::into),
+            body: response.body.into(),
+            tls: response.tls.map(Into::into),
+            timing: response.timing.map(Into::into),
+            protocol: response.protocol.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_round_trips_keep_every_field() {
+        let tls = TlsDetails {
+            version: "TLSv1.3".to_string(),
+            cipher: "TLS13_AES_128_GCM_SHA256".to_string(),
+        };
+        let mut request = HttpRequestData::new("POST".to_string(), "https://shop.test/cart".to_string());
+        request.set_header("Content-Type".to_string(), "application/json".to_string());
+        request.set_body_string("{}".to_string());
+        request.tls = Some(tls.clone());
+        assert_eq!(HttpRequestData::from(pb::HttpRequestData::from(request.clone())), request);
+
+        let response = HttpResponseData {
+            status_code: 302,
+            headers: Some(HttpHeaders {
+                headers: HashMap::from([("Location".to_string(), "/login".to_string())]),
+            }),
+            body: b"moved".to_vec(),
+            tls: Some(tls),
+            timing: Some(ResponseTiming { connect_us: 1200, ttfb_us: 3400, transfer_us: 50, total_us: 4700 }),
+            protocol: Some("HTTP/2".to_string()),
+        };
+        let wire = pb::HttpResponseData::from(response.clone());
+        assert_eq!(wire.protocol, "HTTP/2");
+        assert_eq!(HttpResponseData::from(wire), response);
+    }
+
+    #[test]
+    fn test_empty_protocol_is_unknown() {
+        let wire = pb::HttpResponseData { status_code: 200, ..Default::default() };
+        let response = HttpResponseData::from(wire);
+        assert_eq!(response.protocol, None);
+        assert!(response.headers.is_none() && response.timing.is_none());
+    }
+}
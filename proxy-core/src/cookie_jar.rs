@@ -0,0 +1,562 @@
+//! Per-agent cookie jar
+//!
+//! Every `Set-Cookie` an upstream returns is stored with RFC 6265 semantics:
+//! host-only vs. domain cookies, default and explicit paths, `Max-Age` taking
+//! precedence over `Expires`, and removal by an expiry in the past. Cookies
+//! are keyed by the domain they apply to.
+//!
+//! Storing is passive. Only for hosts matching a "maintain session" pattern
+//! does the proxy attach the stored cookies to requests, so a tool or client
+//! that lost its session (a scanner, a replayed request) stays logged in.
+//! Cookies the client sent itself win over stored ones with the same name.
+//!
+//! A `Domain` attribute naming a public suffix (`co.uk`, `github.io`) is
+//! refused, as browsers do, so one site cannot plant cookies on its
+//! neighbours. The suffixes are a built-in subset of the Public Suffix List:
+//! every TLD plus the common multi-label and hosting suffixes.
+
+use dashmap::DashMap;
+use serde::{Serialize, Serializer};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+use wildmatch::WildMatch;
+
+/// A stored cookie
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct StoredCookie {
+    pub name: String,
+    /// Listings show `[redacted]`; session tokens stay inside the agent
+    #[serde(serialize_with = "redacted")]
+    pub value: String,
+    /// Lowercase domain, without a leading dot
+    pub domain: String,
+    /// Sent only to `domain` itself, not its subdomains (no Domain attribute)
+    pub host_only: bool,
+    pub path: String,
+    /// Unix seconds; `None` for a session cookie
+    pub expires: Option<u64>,
+    pub secure: bool,
+    pub http_only: bool,
+    #[serde(skip)]
+    creation: u64,
+}
+
+/// Cookies and "maintain session" patterns shared by the proxy handlers and
+/// the admin API
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Arc<DashMap<String, Vec<StoredCookie>>>,
+    maintain_session: Arc<RwLock<Vec<String>>>,
+    /// Orders cookies with equal path lengths by creation (RFC 6265 §5.4)
+    next_creation: Arc<AtomicU64>,
+}
+
+impl CookieJar {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attach stored cookies to requests for hosts matching `patterns`
+    /// (`*` wildcards); an empty list stops attaching
+    pub fn set_maintain_session(&self, patterns: Vec<String>) {
+        if patterns.is_empty() {
+            info!("Cookie jar no longer maintains sessions");
+        } else {
+            info!("Cookie jar maintaining sessions for {}", patterns.join(", "));
+        }
+        *self.maintain_session.write().unwrap_or_else(|e| e.into_inner()) = patterns;
+    }
+
+    pub fn maintain_session(&self) -> Vec<String> {
+        self.maintain_session.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Whether requests to `host` get stored cookies attached
+    pub fn maintains(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        self.maintain_session
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .any(|pattern| WildMatch::new(&pattern.to_ascii_lowercase()).matches(&host))
+    }
+
+    /// Store the `Set-Cookie` headers of a response to `host`/`path`
+    pub fn store<'a>(&self, host: &str, path: &str, secure: bool, set_cookies: impl IntoIterator<Item = &'a str>) {
+        let now = unix_now();
+        for header in set_cookies {
+            if let Some(cookie) = self.parse_set_cookie(header, host, path, secure, now) {
+                self.insert(cookie, now);
+            }
+        }
+    }
+
+    /// `Cookie` header value for a request, `None` if nothing matches.
+    /// Cookies named in `existing` (the client's own header) are left out.
+    pub fn cookie_header(&self, host: &str, path: &str, secure: bool, existing: Option<&str>) -> Option<String> {
+        let mut cookies = self.matching(host, path, secure, unix_now());
+        if let Some(existing) = existing {
+            let sent: Vec<&str> = existing
+                .split(';')
+                .filter_map(|pair| pair.split_once('=').map(|(name, _)| name.trim()))
+                .collect();
+            cookies.retain(|cookie| !sent.contains(&cookie.name.as_str()));
+        }
+        if cookies.is_empty() {
+            return None;
+        }
+        let mut header = existing.map(str::trim).filter(|e| !e.is_empty()).map(str::to_string).unwrap_or_default();
+        for cookie in cookies {
+            if !header.is_empty() {
+                header.push_str("; ");
+            }
+            header.push_str(&cookie.name);
+            header.push('=');
+            header.push_str(&cookie.value);
+        }
+        Some(header)
+    }
+
+    /// Every unexpired cookie, by domain
+    pub fn cookies(&self) -> Vec<StoredCookie> {
+        let now = unix_now();
+        let mut cookies: Vec<StoredCookie> = self
+            .cookies
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .filter(|cookie| !cookie.is_expired(now))
+            .collect();
+        cookies.sort_by(|a, b| a.domain.cmp(&b.domain).then(a.creation.cmp(&b.creation)));
+        cookies
+    }
+
+    /// Drop every cookie of `domain`, or all cookies when `None`
+    pub fn clear(&self, domain: Option<&str>) {
+        match domain {
+            Some(domain) => {
+                self.cookies.remove(&domain.trim_start_matches('.').to_ascii_lowercase());
+            }
+            None => self.cookies.clear(),
+        }
+    }
+
+    fn matching(&self, host: &str, path: &str, secure: bool, now: u64) -> Vec<StoredCookie> {
+        let host = host.to_ascii_lowercase();
+        let mut cookies = Vec::new();
+        // The host and each parent domain may hold cookies for it
+        let mut domain = host.as_str();
+        loop {
+            if let Some(mut entry) = self.cookies.get_mut(domain) {
+                entry.retain(|cookie| !cookie.is_expired(now));
+                cookies.extend(
+                    entry
+                        .iter()
+                        .filter(|cookie| !cookie.host_only || cookie.domain == host)
+                        .filter(|cookie| secure || !cookie.secure)
+                        .filter(|cookie| path_matches(path, &cookie.path))
+                        .cloned(),
+                );
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) if !parent.is_empty() && host.parse::<IpAddr>().is_err() => domain = parent,
+                _ => break,
+            }
+        }
+        cookies.sort_by(|a, b| b.path.len().cmp(&a.path.len()).then(a.creation.cmp(&b.creation)));
+        cookies
+    }
+
+    fn insert(&self, mut cookie: StoredCookie, now: u64) {
+        let mut entry = self.cookies.entry(cookie.domain.clone()).or_default();
+        let existing = entry
+            .iter()
+            .position(|c| c.name == cookie.name && c.path == cookie.path);
+        // Replacing keeps the old creation time (RFC 6265 §5.3 step 11)
+        if let Some(index) = existing {
+            cookie.creation = entry.remove(index).creation;
+        }
+        if !cookie.is_expired(now) {
+            entry.push(cookie);
+        }
+    }
+
+    /// RFC 6265 §5.2 and §5.3; `None` for cookies the user agent must ignore
+    fn parse_set_cookie(&self, header: &str, host: &str, request_path: &str, secure: bool, now: u64) -> Option<StoredCookie> {
+        let host = host.to_ascii_lowercase();
+        let mut parts = header.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut domain = None;
+        let mut path = None;
+        let mut max_age = None;
+        let mut expires = None;
+        let mut cookie_secure = false;
+        let mut http_only = false;
+        for attribute in parts {
+            let (key, attribute_value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "domain" if !attribute_value.is_empty() => {
+                    domain = Some(attribute_value.trim_start_matches('.').to_ascii_lowercase())
+                }
+                "path" if attribute_value.starts_with('/') => path = Some(attribute_value.to_string()),
+                "max-age" => {
+                    if let Ok(seconds) = attribute_value.parse::<i64>() {
+                        max_age = Some(seconds);
+                    }
+                }
+                "expires" => {
+                    if let Some(time) = parse_cookie_date(attribute_value) {
+                        expires = Some(time);
+                    }
+                }
+                "secure" => cookie_secure = true,
+                "httponly" => http_only = true,
+                _ => {}
+            }
+        }
+
+        let (domain, host_only) = match domain {
+            // A public suffix may only name the host itself, and then the
+            // cookie is host-only (RFC 6265 §5.3 step 5)
+            Some(domain) if is_public_suffix(&domain) => {
+                if domain != host {
+                    return None;
+                }
+                (host, true)
+            }
+            Some(domain) if domain != host => {
+                // Only the host itself or a parent domain, never another
+                // host or a bare TLD; IP addresses have no parent domain
+                if !domain_matches(&host, &domain) || !domain.contains('.') || host.parse::<IpAddr>().is_ok() {
+                    return None;
+                }
+                (domain, false)
+            }
+            Some(domain) => (domain, false),
+            None => (host, true),
+        };
+        // A plain-HTTP response cannot set Secure cookies (RFC 6265bis)
+        if cookie_secure && !secure {
+            return None;
+        }
+
+        let expires = match (max_age, expires) {
+            (Some(seconds), _) if seconds <= 0 => Some(0),
+            (Some(seconds), _) => Some(now.saturating_add(seconds as u64)),
+            (None, expires) => expires,
+        };
+
+        Some(StoredCookie {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            domain,
+            host_only,
+            path: path.unwrap_or_else(|| default_path(request_path)),
+            expires,
+            secure: cookie_secure,
+            http_only,
+            creation: self.next_creation.fetch_add(1, Ordering::Relaxed),
+        })
+    }
+}
+
+impl StoredCookie {
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
+
+fn redacted<S: Serializer>(_value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str("[redacted]")
+}
+
+/// Public Suffix List rules beyond the implicit `*` every TLD matches:
+/// `*.` rules make every child a suffix, `!` rules exempt a domain from one
+const PUBLIC_SUFFIX_RULES: &[&str] = &[
+    // Country-code second levels
+    "ac.uk", "co.uk", "gov.uk", "ltd.uk", "me.uk", "net.uk", "nhs.uk", "org.uk", "plc.uk", "police.uk", "sch.uk",
+    "com.au", "net.au", "org.au", "edu.au", "gov.au", "asn.au", "id.au",
+    "co.nz", "net.nz", "org.nz", "govt.nz", "ac.nz", "school.nz", "geek.nz",
+    "co.jp", "ne.jp", "or.jp", "ac.jp", "ad.jp", "ed.jp", "go.jp", "gr.jp", "lg.jp",
+    "co.kr", "ne.kr", "or.kr", "re.kr", "go.kr", "ac.kr",
+    "com.cn", "net.cn", "org.cn", "gov.cn", "edu.cn", "ac.cn",
+    "com.hk", "net.hk", "org.hk", "gov.hk", "edu.hk", "idv.hk",
+    "com.tw", "net.tw", "org.tw", "gov.tw", "edu.tw", "idv.tw",
+    "com.sg", "net.sg", "org.sg", "gov.sg", "edu.sg",
+    "co.in", "net.in", "org.in", "firm.in", "gen.in", "ind.in", "gov.in", "ac.in", "edu.in", "res.in",
+    "co.id", "or.id", "web.id", "go.id", "ac.id",
+    "com.my", "net.my", "org.my", "gov.my", "edu.my",
+    "com.ph", "net.ph", "org.ph", "gov.ph", "edu.ph",
+    "co.th", "in.th", "or.th", "go.th", "ac.th",
+    "com.vn", "net.vn", "org.vn", "gov.vn", "edu.vn",
+    "com.pk", "net.pk", "org.pk", "gov.pk", "edu.pk",
+    "co.il", "org.il", "net.il", "gov.il", "ac.il", "muni.il",
+    "com.tr", "net.tr", "org.tr", "gen.tr", "biz.tr", "info.tr", "web.tr", "tv.tr", "av.tr", "dr.tr", "bel.tr",
+    "pol.tr", "tsk.tr", "k12.tr", "edu.tr", "gov.tr", "bbs.tr", "name.tr", "tel.tr",
+    "com.sa", "net.sa", "org.sa", "gov.sa", "edu.sa",
+    "co.za", "net.za", "org.za", "gov.za", "ac.za", "web.za",
+    "com.eg", "net.eg", "org.eg", "gov.eg", "edu.eg",
+    "co.ke", "or.ke", "ne.ke", "go.ke", "ac.ke",
+    "com.ng", "net.ng", "org.ng", "gov.ng", "edu.ng",
+    "com.br", "net.br", "org.br", "gov.br", "edu.br", "art.br", "blog.br", "eng.br", "ind.br", "inf.br", "tv.br",
+    "com.ar", "net.ar", "org.ar", "gob.ar", "edu.ar", "int.ar",
+    "com.mx", "net.mx", "org.mx", "gob.mx", "edu.mx",
+    "com.co", "net.co", "org.co", "gov.co", "edu.co", "nom.co",
+    "com.pe", "net.pe", "org.pe", "gob.pe", "edu.pe", "nom.pe",
+    "co.ve", "com.ve", "net.ve", "org.ve", "gob.ve",
+    "com.ua", "net.ua", "org.ua", "gov.ua", "edu.ua", "in.ua", "kiev.ua",
+    "com.ru", "net.ru", "org.ru", "pp.ru", "msk.ru", "spb.ru",
+    "com.pl", "net.pl", "org.pl", "gov.pl", "edu.pl", "info.pl", "biz.pl", "waw.pl",
+    "co.at", "or.at", "ac.at", "gv.at",
+    "com.es", "nom.es", "org.es", "gob.es", "edu.es",
+    "com.pt", "org.pt", "gov.pt", "edu.pt",
+    "com.gr", "net.gr", "org.gr", "gov.gr", "edu.gr",
+    "co.it", "gov.it", "edu.it",
+    "asso.fr", "com.fr", "gouv.fr", "nom.fr", "tm.fr",
+    "co.hu", "org.hu", "info.hu",
+    "com.cy", "net.cy", "org.cy", "gov.cy", "ac.cy",
+    "*.ck", "!www.ck", "*.bd", "*.er", "*.fk", "*.jm", "*.kh", "*.mm", "*.np", "*.pg",
+    "*.kawasaki.jp", "!city.kawasaki.jp", "*.kitakyushu.jp", "!city.kitakyushu.jp",
+    // Hosting providers handing out subdomains to their customers
+    "github.io", "githubusercontent.com", "gitlab.io", "herokuapp.com", "herokussl.com", "appspot.com",
+    "blogspot.com", "firebaseapp.com", "web.app", "pages.dev", "workers.dev", "netlify.app", "vercel.app",
+    "now.sh", "azurewebsites.net", "cloudapp.net", "azurestaticapps.net", "trafficmanager.net",
+    "cloudfront.net", "elasticbeanstalk.com", "s3.amazonaws.com", "*.compute.amazonaws.com",
+    "*.elb.amazonaws.com", "fly.dev", "onrender.com", "glitch.me", "repl.co", "ngrok.io", "ngrok-free.app",
+    "readthedocs.io", "bitbucket.io", "wordpress.com", "wixsite.com", "myshopify.com", "dyndns.org",
+    "duckdns.org", "no-ip.org", "ddns.net",
+];
+
+/// Whether cookies may not be scoped to `domain` (lowercase, no leading dot)
+fn is_public_suffix(domain: &str) -> bool {
+    if !domain.contains('.') {
+        return true;
+    }
+    let rule = |candidate: &str| PUBLIC_SUFFIX_RULES.contains(&candidate);
+    if rule(&format!("!{}", domain)) {
+        return false;
+    }
+    let wildcard = domain.split_once('.').is_some_and(|(_, parent)| rule(&format!("*.{}", parent)));
+    rule(domain) || wildcard
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// RFC 6265 §5.1.3
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || (host.ends_with(domain) && host[..host.len() - domain.len()].ends_with('.'))
+}
+
+/// RFC 6265 §5.1.4: the directory of the request path
+fn default_path(request_path: &str) -> String {
+    let path = request_path.split('?').next().unwrap_or_default();
+    if !path.starts_with('/') {
+        return "/".to_string();
+    }
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(index) => path[..index].to_string(),
+    }
+}
+
+/// RFC 6265 §5.1.4
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    let request_path = request_path.split('?').next().unwrap_or("/");
+    let request_path = if request_path.is_empty() { "/" } else { request_path };
+    request_path == cookie_path
+        || (request_path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || request_path[cookie_path.len()..].starts_with('/')))
+}
+
+/// RFC 6265 §5.1.1, which accepts every date format servers send
+/// (`Wed, 21 Oct 2015 07:28:00 GMT`, `Wednesday, 21-Oct-15 ...`, asctime).
+/// Returns Unix seconds.
+fn parse_cookie_date(value: &str) -> Option<u64> {
+    const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+    let is_delimiter = |c: char| c == '\t' || (' '..='/').contains(&c) || (';'..='@').contains(&c) || ('['..='`').contains(&c) || ('{'..='~').contains(&c);
+    let leading_digits = |token: &str, min: usize, max: usize| -> Option<u32> {
+        let digits: String = token.chars().take_while(char::is_ascii_digit).collect();
+        (min..=max).contains(&digits.len()).then(|| digits.parse().ok()).flatten()
+    };
+
+    let (mut time, mut day, mut month, mut year) = (None, None, None, None);
+    for token in value.split(is_delimiter).filter(|t| !t.is_empty()) {
+        if time.is_none() {
+            let fields: Vec<&str> = token.splitn(3, ':').collect();
+            if fields.len() == 3 {
+                if let (Some(h), Some(m), Some(s)) =
+                    (leading_digits(fields[0], 1, 2), leading_digits(fields[1], 1, 2), leading_digits(fields[2], 1, 2))
+                {
+                    time = Some((h, m, s));
+                    continue;
+                }
+            }
+        }
+        if day.is_none() {
+            if let Some(d) = leading_digits(token, 1, 2) {
+                day = Some(d);
+                continue;
+            }
+        }
+        if month.is_none() && token.len() >= 3 {
+            if let Some(index) = MONTHS.iter().position(|m| token.get(..3).is_some_and(|t| t.eq_ignore_ascii_case(m))) {
+                month = Some(index as u32 + 1);
+                continue;
+            }
+        }
+        if year.is_none() {
+            if let Some(y) = leading_digits(token, 2, 4) {
+                year = Some(y);
+                continue;
+            }
+        }
+    }
+
+    let (hour, minute, second) = time?;
+    let (day, month, mut year) = (day?, month?, year?);
+    if (70..=99).contains(&year) {
+        year += 1900;
+    } else if year <= 69 {
+        year += 2000;
+    }
+    if !(1..=31).contains(&day) || year < 1601 || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let days = days_from_civil(year as i64, month, day);
+    let seconds = days * 86_400 + (hour * 3600 + minute * 60 + second) as i64;
+    // Dates before the epoch are in the past all the same
+    Some(seconds.max(0) as u64)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_domain_and_path_matching() {
+        let jar = CookieJar::new();
+        jar.store(
+            "app.example.com",
+            "/account/login",
+            true,
+            [
+                "session=abc; Path=/; Secure; HttpOnly",
+                "scoped=1",
+                "shared=2; Domain=.example.com; Path=/api",
+                "foreign=3; Domain=other.com",
+                "tld=4; Domain=com",
+            ],
+        );
+
+        assert_eq!(
+            jar.cookie_header("app.example.com", "/account/profile", true, None).as_deref(),
+            Some("scoped=1; session=abc")
+        );
+        assert_eq!(
+            jar.cookie_header("app.example.com", "/api/users?id=1", true, None).as_deref(),
+            Some("shared=2; session=abc")
+        );
+        // Host-only cookies stay on their host; Secure ones off plain HTTP
+        assert_eq!(jar.cookie_header("www.example.com", "/api", false, None).as_deref(), Some("shared=2"));
+        assert_eq!(jar.cookie_header("www.example.com", "/apis", true, None), None);
+        assert_eq!(jar.cookie_header("other.com", "/", true, None), None);
+        assert_eq!(jar.cookies().len(), 3);
+
+        // The client's own cookies win
+        assert_eq!(
+            jar.cookie_header("app.example.com", "/account", true, Some("session=mine")).as_deref(),
+            Some("session=mine; scoped=1")
+        );
+        assert_eq!(jar.cookie_header("app.example.com", "/", true, Some("session=mine")), None);
+
+        // Plain HTTP cannot set Secure cookies
+        jar.store("app.example.com", "/", false, ["insecure=1; Secure"]);
+        assert_eq!(jar.cookies().len(), 3);
+    }
+
+    #[test]
+    fn test_expiry_and_replacement() {
+        let jar = CookieJar::new();
+        jar.store("example.com", "/", true, ["a=1", "b=2; Max-Age=3600", "c=3; Expires=Wed, 21 Oct 2015 07:28:00 GMT"]);
+        assert_eq!(jar.cookie_header("example.com", "/", true, None).as_deref(), Some("a=1; b=2"));
+
+        // Max-Age wins over Expires; a past expiry deletes
+        jar.store("example.com", "/", true, ["a=updated; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=60", "b=; Max-Age=0"]);
+        assert_eq!(jar.cookie_header("example.com", "/", true, None).as_deref(), Some("a=updated"));
+        jar.store("example.com", "/", true, ["a=gone; Expires=Sunday, 06-Nov-94 08:49:37 GMT"]);
+        assert_eq!(jar.cookie_header("example.com", "/", true, None), None);
+    }
+
+    #[test]
+    fn test_cookie_dates() {
+        assert_eq!(parse_cookie_date("Wed, 21 Oct 2015 07:28:00 GMT"), Some(1_445_412_480));
+        assert_eq!(parse_cookie_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse_cookie_date("Sun Nov  6 08:49:37 1994"), Some(784_111_777));
+        assert_eq!(parse_cookie_date("not a date"), None);
+        assert_eq!(default_path("/account/login?next=/"), "/account");
+        assert_eq!(default_path("/login"), "/");
+    }
+
+    #[test]
+    fn test_public_suffix_domains_are_rejected() {
+        let jar = CookieJar::new();
+        jar.store("shop.example.co.uk", "/", true, ["suffix=1; Domain=co.uk", "site=2; Domain=example.co.uk"]);
+        jar.store("evil.github.io", "/", true, ["suffix=3; Domain=github.io"]);
+        jar.store("a.b.ck", "/", true, ["wildcard=4; Domain=b.ck"]);
+        jar.store("shop.www.ck", "/", true, ["exception=5; Domain=www.ck"]);
+        assert_eq!(jar.cookie_header("other.example.co.uk", "/", true, None).as_deref(), Some("site=2"));
+        assert_eq!(jar.cookie_header("victim.github.io", "/", true, None), None);
+        assert_eq!(jar.cookie_header("www.ck", "/", true, None).as_deref(), Some("exception=5"));
+        assert_eq!(jar.cookies().len(), 2);
+
+        // The suffix's own host may still set a cookie, but only for itself
+        jar.store("github.io", "/", true, ["own=6; Domain=github.io"]);
+        let own = jar.cookies().into_iter().find(|c| c.name == "own").unwrap();
+        assert!(own.host_only);
+        assert_eq!(jar.cookie_header("pages.github.io", "/", true, None), None);
+        assert_eq!(jar.cookie_header("github.io", "/", true, None).as_deref(), Some("own=6"));
+    }
+
+    #[test]
+    fn test_listed_values_are_redacted() {
+        let jar = CookieJar::new();
+        jar.store("example.com", "/", true, ["session=s3cret"]);
+        let listed = serde_json::to_value(jar.cookies()).unwrap();
+        assert_eq!(listed[0]["name"], "session");
+        assert_eq!(listed[0]["value"], "[redacted]");
+        assert_eq!(jar.cookie_header("example.com", "/", true, None).as_deref(), Some("session=s3cret"));
+    }
+
+    #[test]
+    fn test_maintain_session_patterns() {
+        let jar = CookieJar::new();
+        assert!(!jar.maintains("app.example.com"));
+        jar.set_maintain_session(vec!["*.Example.com".to_string()]);
+        assert!(jar.maintains("APP.example.com"));
+        assert!(!jar.maintains("example.org"));
+    }
+}
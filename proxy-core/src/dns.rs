@@ -0,0 +1,332 @@
+//! Custom DNS Resolution
+//!
+//! Upstream hosts are normally resolved through the operating system. Agents can
+//! instead be pointed at explicit name servers, optionally over DNS-over-TLS or
+//! DNS-over-HTTPS, so lookups made during an engagement do not reach the local
+//! network's resolver and split-horizon targets resolve against the right servers.
+//!
+//! Host overrides work like a hosts file: listed names resolve to a chosen
+//! address before any server is asked, e.g. to send a production domain to a
+//! staging machine while keeping its name in `Host` and SNI.
+
+use crate::{error::ProxyError, pb, Result};
+use hickory_resolver::config::{
+    NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+};
+use hickory_resolver::TokioAsyncResolver;
+use hudsucker::hyper::client::connect::dns::Name;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tracing::info;
+
+/// Transport used to reach the configured name servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsMode {
+    /// Use the operating system resolver
+    #[default]
+    System,
+    /// Plain DNS over UDP, falling back to TCP for truncated answers
+    Udp,
+    /// DNS-over-TLS (RFC 7858)
+    Tls,
+    /// DNS-over-HTTPS (RFC 8484)
+    Https,
+}
+
+impl DnsMode {
+    /// Port used for servers listed without one
+    pub fn default_port(&self) -> u16 {
+        match self {
+            DnsMode::System | DnsMode::Udp => 53,
+            DnsMode::Tls => 853,
+            DnsMode::Https => 443,
+        }
+    }
+}
+
+/// Resolver configuration for upstream connections
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsConfig {
+    pub mode: DnsMode,
+    /// Name server addresses, e.g. `10.0.0.2`, `1.1.1.1:853` or `[2606:4700::1111]:443`
+    #[serde(default)]
+    pub servers: Vec<String>,
+    /// Certificate name presented by DoT/DoH servers, e.g. `cloudflare-dns.com`
+    #[serde(default)]
+    pub tls_name: Option<String>,
+}
+
+impl DnsConfig {
+    /// True when lookups should go through the operating system resolver
+    pub fn is_system(&self) -> bool {
+        self.mode == DnsMode::System
+    }
+
+    /// Parse the configured server list into socket addresses
+    pub fn server_addrs(&self) -> Result<Vec<SocketAddr>> {
+        self.servers
+            .iter()
+            .map(|s| parse_server(s.trim(), self.mode.default_port()))
+            .collect()
+    }
+
+    /// Check that the configuration can be turned into a resolver
+    pub fn validate(&self) -> Result<()> {
+        if self.is_system() {
+            return Ok(());
+        }
+
+        if self.server_addrs()?.is_empty() {
+            return Err(ProxyError::Configuration(
+                "Custom DNS mode requires at least one server".to_string(),
+            ));
+        }
+
+        if matches!(self.mode, DnsMode::Tls | DnsMode::Https)
+            && self.tls_name.as_deref().map(str::trim).unwrap_or("").is_empty()
+        {
+            return Err(ProxyError::Configuration(
+                "DNS-over-TLS/HTTPS requires the server's TLS name".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn resolver_config(&self) -> Result<ResolverConfig> {
+        self.validate()?;
+
+        let tls_name = self.tls_name.as_ref().map(|n| n.trim().to_string());
+        let mut group = NameServerConfigGroup::new();
+
+        for addr in self.server_addrs()? {
+            let protocols: &[Protocol] = match self.mode {
+                DnsMode::System | DnsMode::Udp => &[Protocol::Udp, Protocol::Tcp],
+                DnsMode::Tls => &[Protocol::Tls],
+                DnsMode::Https => &[Protocol::Https],
+            };
+
+            for protocol in protocols {
+                let mut server = NameServerConfig::new(addr, *protocol);
+                server.tls_dns_name = tls_name.clone();
+                server.trust_negative_responses = true;
+                group.push(server);
+            }
+        }
+
+        Ok(ResolverConfig::from_parts(None, Vec::new(), group))
+    }
+}
+
+fn parse_server(server: &str, default_port: u16) -> Result<SocketAddr> {
+    if let Ok(addr) = server.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+
+    server
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, default_port))
+        .map_err(|_| {
+            ProxyError::Configuration(format!("Invalid DNS server address: {}", server))
+        })
+}
+
+impl From<pb::DnsConfig> for DnsConfig {
+    fn from(config: pb::DnsConfig) -> Self {
+        let mode = match pb::dns_config::Mode::try_from(config.mode) {
+            Ok(pb::dns_config::Mode::Udp) => DnsMode::Udp,
+            Ok(pb::dns_config::Mode::Tls) => DnsMode::Tls,
+            Ok(pb::dns_config::Mode::Https) => DnsMode::Https,
+            _ => DnsMode::System,
+        };
+
+        Self {
+            mode,
+            servers: config.servers,
+            tls_name: if config.tls_name.is_empty() {
+                None
+            } else {
+                Some(config.tls_name)
+            },
+        }
+    }
+}
+
+impl From<&DnsConfig> for pb::DnsConfig {
+    fn from(config: &DnsConfig) -> Self {
+        let mode = match config.mode {
+            DnsMode::System => pb::dns_config::Mode::System,
+            DnsMode::Udp => pb::dns_config::Mode::Udp,
+            DnsMode::Tls => pb::dns_config::Mode::Tls,
+            DnsMode::Https => pb::dns_config::Mode::Https,
+        };
+
+        Self {
+            mode: mode as i32,
+            servers: config.servers.clone(),
+            tls_name: config.tls_name.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Key a host is looked up by in the override map: lowercase, without the
+/// trailing dot of a fully qualified name
+pub fn override_key(host: &str) -> String {
+    host.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Resolver plugged into the upstream `HttpConnector`.
+/// In system mode lookups are delegated to the operating system.
+#[derive(Clone)]
+pub struct CustomDnsResolver {
+    resolver: Option<Arc<TokioAsyncResolver>>,
+    overrides: Arc<HashMap<String, IpAddr>>,
+}
+
+impl CustomDnsResolver {
+    /// Build a resolver for the given configuration
+    pub fn new(config: &DnsConfig) -> Result<Self> {
+        let overrides = Arc::new(HashMap::new());
+        if config.is_system() {
+            return Ok(Self { resolver: None, overrides });
+        }
+
+        let resolver = TokioAsyncResolver::tokio(config.resolver_config()?, ResolverOpts::default());
+        Ok(Self {
+            resolver: Some(Arc::new(resolver)),
+            overrides,
+        })
+    }
+
+    /// Resolve the given hosts to fixed addresses instead of asking DNS
+    pub fn with_overrides(mut self, overrides: &HashMap<String, IpAddr>) -> Self {
+        self.overrides = Arc::new(overrides.iter().map(|(host, ip)| (override_key(host), *ip)).collect());
+        self
+    }
+
+    /// Address `host` is overridden to, if any
+    pub fn override_for(&self, host: &str) -> Option<IpAddr> {
+        if self.overrides.is_empty() {
+            return None;
+        }
+        self.overrides.get(&override_key(host)).copied()
+    }
+
+    /// Resolve a host name to its addresses
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>> {
+        if let Some(ip) = self.override_for(host) {
+            info!("DNS override: {} resolved to {}", host, ip);
+            return Ok(vec![ip]);
+        }
+
+        let lookup_failed =
+            |e: &dyn std::fmt::Display| ProxyError::Network(format!("DNS lookup for {} failed: {}", host, e));
+
+        match &self.resolver {
+            Some(resolver) => {
+                let lookup = resolver.lookup_ip(host).await.map_err(|e| lookup_failed(&e))?;
+                Ok(lookup.iter().collect())
+            }
+            None => {
+                let addrs = tokio::net::lookup_host((host, 0)).await.map_err(|e| lookup_failed(&e))?;
+                Ok(addrs.map(|addr| addr.ip()).collect())
+            }
+        }
+    }
+}
+
+impl tower::Service<Name> for CustomDnsResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let resolver = self.clone();
+        Box::pin(async move {
+            // The connector fills in the port from the request URI
+            let addrs: Vec<SocketAddr> = resolver
+                .lookup(name.as_str())
+                .await?
+                .into_iter()
+                .map(|ip| SocketAddr::new(ip, 0))
+                .collect();
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_parsing_uses_mode_port() {
+        let config = DnsConfig {
+            mode: DnsMode::Tls,
+            servers: vec!["1.1.1.1".into(), "9.9.9.9:8853".into(), "[2606:4700::1111]".into()],
+            tls_name: Some("cloudflare-dns.com".into()),
+        };
+
+        let addrs = config.server_addrs().unwrap();
+        assert_eq!(addrs[0], "1.1.1.1:853".parse().unwrap());
+        assert_eq!(addrs[1], "9.9.9.9:8853".parse().unwrap());
+        assert_eq!(addrs[2], "[2606:4700::1111]:853".parse().unwrap());
+    }
+
+    #[test]
+    fn test_validation() {
+        assert!(DnsConfig::default().validate().is_ok());
+
+        let no_servers = DnsConfig { mode: DnsMode::Udp, ..Default::default() };
+        assert!(no_servers.validate().is_err());
+
+        let no_tls_name = DnsConfig {
+            mode: DnsMode::Https,
+            servers: vec!["1.1.1.1".into()],
+            tls_name: None,
+        };
+        assert!(no_tls_name.validate().is_err());
+
+        let bad_addr = DnsConfig {
+            mode: DnsMode::Udp,
+            servers: vec!["dns.internal".into()],
+            tls_name: None,
+        };
+        assert!(bad_addr.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_host_overrides_skip_dns() {
+        let overrides = HashMap::from([("Shop.Example.com".to_string(), "10.20.0.5".parse().unwrap())]);
+        let resolver = CustomDnsResolver::new(&DnsConfig::default()).unwrap().with_overrides(&overrides);
+
+        assert_eq!(resolver.override_for("shop.example.com."), Some("10.20.0.5".parse().unwrap()));
+        assert_eq!(resolver.override_for("api.example.com"), None);
+        assert_eq!(resolver.lookup("SHOP.example.com").await.unwrap(), vec!["10.20.0.5".parse::<IpAddr>().unwrap()]);
+    }
+
+    #[test]
+    fn test_pb_round_trip() {
+        let config = DnsConfig {
+            mode: DnsMode::Https,
+            servers: vec!["1.1.1.1".into()],
+            tls_name: Some("cloudflare-dns.com".into()),
+        };
+
+        let wire = pb::DnsConfig::from(&config);
+        assert_eq!(DnsConfig::from(wire), config);
+        assert_eq!(DnsConfig::from(pb::DnsConfig::default()), DnsConfig::default());
+    }
+}
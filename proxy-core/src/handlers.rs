@@ -1,16 +1,37 @@
 use crate::admin::Metrics;
-use crate::config::BodyCaptureConfig;
+use crate::connections::{classify_upstream_error, ConnectionLog};
+use crate::pb::connection_event::Kind;
+use crate::config::{BodyCaptureConfig, RequestLimits, RequestLimitViolation};
+use crate::capture::CaptureController;
+use crate::controller::InterceptController;
+use crate::cookie_jar::CookieJar;
 use crate::error::BodyCaptureError;
+use crate::mitm_fallback::{target_host, MitmFallback};
 use crate::memory_manager::{MemoryManager, MemoryAllocation, MemoryPermit};
+use crate::policy::{ConnectionPolicy, RequestContext, Throttle, TrafficPolicy};
+use crate::scripts::{ScriptController, ScriptRequest, ScriptResponse};
+use crate::target_auth::{ReplayRequest, TargetAuthenticator, MAX_REPLAY_BODY_BYTES};
+use crate::timing::PhaseTimer;
+use crate::upstream::UpstreamClient;
+use crate::websocket::{DeflateParams, FrameCapture};
+use bytes::Bytes;
 use hudsucker::{
-    hyper::{Body, Request, Response, body::HttpBody},
+    hyper::{
+        header::{
+            HeaderName, HeaderValue, AUTHORIZATION, CONNECTION, COOKIE, SEC_WEBSOCKET_EXTENSIONS, SET_COOKIE, UPGRADE,
+            WWW_AUTHENTICATE,
+        },
+        Body, Method, Request, Response, StatusCode, Version, body::HttpBody,
+    },
     HttpContext, HttpHandler, RequestOrResponse,
 };
+use std::collections::HashMap;
 use std::sync::{atomic::Ordering, Arc};
 use tokio::sync::RwLock;
 use tokio::time::timeout;
 use tracing::{info, warn, debug};
 use uuid::Uuid;
+use wildmatch::WildMatch;
 
 #[derive(Clone)]
 pub struct LogHandler {
@@ -27,6 +48,44 @@ pub struct LogHandler {
     body_capture_config: BodyCaptureConfig,
     /// Memory manager for tracking and limiting memory usage
     memory_manager: Arc<MemoryManager>,
+    /// Request line and header limits
+    request_limits: RequestLimits,
+    /// Interception rules and paused requests
+    intercept_controller: Option<InterceptController>,
+    /// Hook scripts run on requests and responses
+    script_controller: Option<ScriptController>,
+    /// Runtime pause of traffic capture
+    capture_controller: Option<CaptureController>,
+    /// CONNECTs, failures and blocks that produce no transaction
+    connection_log: ConnectionLog,
+    /// Host patterns tunnelled without TLS interception
+    tls_passthrough: Arc<Vec<WildMatch>>,
+    /// Hosts tunnelled without interception because interception kept failing
+    mitm_fallback: Option<MitmFallback>,
+    /// Header the request ID is sent upstream in
+    request_id_header: Option<HeaderName>,
+    /// Current request URL, reported if the upstream request fails
+    current_request_url: Arc<RwLock<Option<String>>>,
+    /// Credentials for targets behind HTTP authentication
+    target_auth: Option<Arc<TargetAuthenticator>>,
+    /// Current request, kept to answer its target's authentication challenge
+    current_auth_replay: Option<ReplayRequest>,
+    /// Cookies set by upstreams, attached to requests of maintained sessions
+    cookie_jar: Option<CookieJar>,
+    /// Current request URI, whatever its scope, for storing the response's cookies
+    current_cookie_origin: Option<hudsucker::hyper::Uri>,
+    /// Started when the current request is handed to the upstream client
+    current_request_timer: Option<PhaseTimer>,
+    /// Upstream timeouts and keep-alive per scope
+    connection_policy: Arc<ConnectionPolicy>,
+    /// When the current exchange exceeds its profile's total timeout
+    current_request_deadline: Option<tokio::time::Instant>,
+    /// Throttling rules simulating slow networks
+    traffic_policy: Arc<TrafficPolicy>,
+    /// Throttle matched by the current request, which paces its response too
+    current_request_throttle: Option<Throttle>,
+    /// HTTP/1.1 client for WebSocket upgrades relayed with permessage-deflate
+    websocket_client: Option<UpstreamClient>,
 }
 
 impl LogHandler {
@@ -42,12 +101,31 @@ impl LogHandler {
         
         Self {
             metrics,
+            connection_log: ConnectionLog::new(log_sender.clone()),
             log_sender,
             scope_matcher: None,
             current_request_id: Arc::new(RwLock::new(None)),
             current_request_method: Arc::new(RwLock::new(None)),
             body_capture_config,
             memory_manager,
+            request_limits: RequestLimits::default(),
+            intercept_controller: None,
+            script_controller: None,
+            capture_controller: None,
+            tls_passthrough: Arc::new(Vec::new()),
+            mitm_fallback: None,
+            request_id_header: None,
+            current_request_url: Arc::new(RwLock::new(None)),
+            target_auth: None,
+            current_auth_replay: None,
+            cookie_jar: None,
+            current_cookie_origin: None,
+            current_request_timer: None,
+            connection_policy: Arc::new(ConnectionPolicy::default()),
+            current_request_deadline: None,
+            traffic_policy: Arc::new(TrafficPolicy::default()),
+            current_request_throttle: None,
+            websocket_client: None,
         }
     }
 
@@ -76,6 +154,419 @@ impl LogHandler {
         self
     }
 
+    pub fn with_request_limits(mut self, limits: RequestLimits) -> Self {
+        self.request_limits = limits;
+        self
+    }
+
+    pub fn with_intercept_controller(mut self, controller: InterceptController) -> Self {
+        self.intercept_controller = Some(controller);
+        self
+    }
+
+    pub fn with_script_controller(mut self, controller: ScriptController) -> Self {
+        self.script_controller = Some(controller);
+        self
+    }
+
+    pub fn with_capture_controller(mut self, controller: CaptureController) -> Self {
+        self.capture_controller = Some(controller);
+        self
+    }
+
+    pub fn with_tls_passthrough(mut self, hosts: &[String]) -> Self {
+        self.tls_passthrough = Arc::new(hosts.iter().map(|h| WildMatch::new(h)).collect());
+        self
+    }
+
+    /// Tunnel hosts without interception once interception of them keeps
+    /// failing
+    pub fn with_mitm_fallback(mut self, fallback: MitmFallback) -> Self {
+        self.connection_log = self.connection_log.with_fallback(fallback.clone());
+        self.mitm_fallback = Some(fallback);
+        self
+    }
+
+    /// Send each captured request's ID upstream in `header`
+    pub fn with_request_id_header(mut self, header: HeaderName) -> Self {
+        self.request_id_header = Some(header);
+        self
+    }
+
+    /// Authenticate to matching targets on the client's behalf
+    pub fn with_target_auth(mut self, authenticator: TargetAuthenticator) -> Self {
+        self.target_auth = Some(Arc::new(authenticator));
+        self
+    }
+
+    /// Relay captured WebSocket upgrades that offer permessage-deflate
+    /// through `client`, which must not negotiate HTTP/2. Without one the
+    /// extension is stripped from every upgrade.
+    pub fn with_websocket_client(mut self, client: UpstreamClient) -> Self {
+        self.websocket_client = Some(client);
+        self
+    }
+
+    /// Store response cookies in `jar` and attach them where it maintains
+    /// sessions
+    pub fn with_cookie_jar(mut self, jar: CookieJar) -> Self {
+        self.cookie_jar = Some(jar);
+        self
+    }
+
+    /// Bound each exchange by its scope's total timeout, and close upstream
+    /// connections of scopes with keep-alive off
+    pub fn with_connection_policy(mut self, policy: Arc<ConnectionPolicy>) -> Self {
+        self.connection_policy = policy;
+        self
+    }
+
+    /// Delay exchanges matching the policy's throttling rules and cap their
+    /// bandwidth
+    pub fn with_traffic_policy(mut self, policy: Arc<TrafficPolicy>) -> Self {
+        self.traffic_policy = policy;
+        self
+    }
+
+    /// Connection events sent by this handler; run its sweeper to report
+    /// unused tunnels
+    pub fn connection_log(&self) -> ConnectionLog {
+        self.connection_log.clone()
+    }
+
+    /// Buffer a body for hook scripts. Bodies larger than the capture limit,
+    /// or streamed without a known size, pass through untouched and scripts
+    /// see no body.
+    async fn buffer_for_scripts(
+        &self,
+        req_id: &str,
+        headers: &hudsucker::hyper::HeaderMap,
+        body: Body,
+    ) -> (Body, Option<Bytes>) {
+        if !body_within(headers, &body, self.body_capture_config.max_body_size as u64) {
+            debug!("[{}] body too large or of unknown size for hook scripts", req_id);
+            return (body, None);
+        }
+
+        match hudsucker::hyper::body::to_bytes(body).await {
+            Ok(bytes) => (Body::from(bytes.clone()), Some(bytes)),
+            Err(e) => {
+                warn!("[{}] body could not be read for hook scripts: {}", req_id, e);
+                (Body::empty(), None)
+            }
+        }
+    }
+
+    /// Run the request hooks. Returns the request to forward with the tags
+    /// the scripts added, or `None` if a script dropped it.
+    async fn run_request_scripts(
+        &self,
+        scripts: &ScriptController,
+        req_id: &str,
+        req: Request<Body>,
+    ) -> Option<(Request<Body>, Vec<String>)> {
+        let (mut parts, body) = req.into_parts();
+        let (body, bytes) = self.buffer_for_scripts(req_id, &parts.headers, body).await;
+        let headers = header_strings(&parts.headers);
+        let outcome = scripts.run_request_hooks(ScriptRequest {
+            method: parts.method.to_string(),
+            url: parts.uri.to_string(),
+            headers: headers.clone(),
+            body: bytes.as_ref().map(|b| b.to_vec()),
+        });
+        if outcome.dropped {
+            return None;
+        }
+
+        let edited = outcome.message;
+        if edited.method != parts.method.as_str() {
+            match edited.method.parse() {
+                Ok(method) => parts.method = method,
+                Err(_) => warn!("[{}] hook script set invalid method '{}'", req_id, edited.method),
+            }
+        }
+        if edited.url != parts.uri.to_string() {
+            match edited.url.parse() {
+                Ok(uri) => parts.uri = uri,
+                Err(_) => warn!("[{}] hook script set invalid URL '{}'", req_id, edited.url),
+            }
+        }
+        if let Err(e) = apply_script_headers(&mut parts.headers, &headers, &edited.headers) {
+            warn!("[{}] hook script header edit rejected: {}", req_id, e);
+        }
+        let body = match scripted_body(&mut parts.headers, bytes, edited.body) {
+            Some(edited) => edited,
+            None => body,
+        };
+
+        Some((Request::from_parts(parts, body), outcome.tags))
+    }
+
+    /// Run the response hooks. Returns the response for the client with the
+    /// tags the scripts added, or `None` if a script dropped it.
+    async fn run_response_scripts(
+        &self,
+        scripts: &ScriptController,
+        req_id: &str,
+        res: Response<Body>,
+        read_body: bool,
+    ) -> Option<(Response<Body>, Vec<String>)> {
+        let (mut parts, body) = res.into_parts();
+        let (body, bytes) = if read_body {
+            self.buffer_for_scripts(req_id, &parts.headers, body).await
+        } else {
+            (body, None)
+        };
+        let headers = header_strings(&parts.headers);
+        let outcome = scripts.run_response_hooks(ScriptResponse {
+            status: parts.status.as_u16(),
+            headers: headers.clone(),
+            body: bytes.as_ref().map(|b| b.to_vec()),
+        });
+        if outcome.dropped {
+            return None;
+        }
+
+        let edited = outcome.message;
+        if let Ok(status) = hudsucker::hyper::StatusCode::from_u16(edited.status) {
+            parts.status = status;
+        }
+        if let Err(e) = apply_script_headers(&mut parts.headers, &headers, &edited.headers) {
+            warn!("[{}] hook script header edit rejected: {}", req_id, e);
+        }
+        let body = match scripted_body(&mut parts.headers, bytes, edited.body) {
+            Some(edited) => edited,
+            None => body,
+        };
+
+        Some((Response::from_parts(parts, body), outcome.tags))
+    }
+
+    /// Report tags hook scripts put on a request
+    fn send_script_tags(&self, req_id: &str, tags: Vec<String>) {
+        use crate::pb::{traffic_event, RequestTags, TrafficEvent};
+
+        if let (Some(sender), false) = (&self.log_sender, tags.is_empty()) {
+            let _ = sender.try_send(TrafficEvent {
+                request_id: req_id.to_string(),
+                event: Some(traffic_event::Event::Tags(RequestTags { tags })),
+            });
+        }
+    }
+
+    /// Hold a request matched by a pause rule until the orchestrator decides.
+    /// Returns the request to forward (edited if the decision carried edits),
+    /// or `None` if it should be dropped.
+    async fn hold_request(
+        &self,
+        controller: &InterceptController,
+        req_id: &str,
+        req: Request<Body>,
+        headers: HashMap<String, String>,
+    ) -> Option<Request<Body>> {
+        use crate::pb::{intercept_command, intercept_decision, traffic_event, HttpHeaders, HttpRequestData, TrafficEvent};
+
+        let Some(sender) = &self.log_sender else {
+            warn!("Request [{}] matched a pause rule but no orchestrator is connected; forwarding", req_id);
+            return Some(req);
+        };
+
+        if !body_within(req.headers(), req.body(), self.body_capture_config.max_body_size as u64) {
+            warn!("Request [{}] body is too large or of unknown size to hold for editing; forwarding", req_id);
+            return Some(req);
+        }
+
+        let (parts, body) = req.into_parts();
+        let body = match hudsucker::hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Request [{}] body could not be read for interception: {}", req_id, e);
+                return None;
+            }
+        };
+
+        // Register before announcing, so a fast decision cannot arrive first
+        let decision = controller.register_request(req_id.to_string());
+        let event = TrafficEvent {
+            request_id: req_id.to_string(),
+            event: Some(traffic_event::Event::Intercepted(HttpRequestData {
+                method: parts.method.as_str().to_string(),
+                url: parts.uri.to_string(),
+                headers: Some(HttpHeaders { headers }),
+                body: body.clone(),
+                tls: None,
+            })),
+        };
+        if sender.send(event).await.is_err() {
+            controller.cancel_request(req_id);
+            return Some(Request::from_parts(parts, Body::from(body)));
+        }
+
+        info!("Request [{}] paused for interception", req_id);
+        let command = match timeout(controller.hold_timeout(), decision).await {
+            Ok(Ok(command)) => command,
+            Ok(Err(_)) | Err(_) => {
+                controller.cancel_request(req_id);
+                warn!("Request [{}] got no interception decision in time; forwarding unchanged", req_id);
+                return Some(Request::from_parts(parts, Body::from(body)));
+            }
+        };
+
+        let decision = match command.command {
+            Some(intercept_command::Command::Decision(decision)) => decision,
+            _ => return Some(Request::from_parts(parts, Body::from(body))),
+        };
+        if decision.action() == intercept_decision::Action::Drop {
+            return None;
+        }
+        match decision.request {
+            Some(edited) => match edited_request(parts, edited) {
+                Ok(req) => Some(req),
+                Err(e) => {
+                    warn!("Request [{}] edit rejected ({}); dropping", req_id, e);
+                    None
+                }
+            },
+            None => Some(Request::from_parts(parts, Body::from(body))),
+        }
+    }
+
+    /// Add the jar's cookies for a maintained host to the request's Cookie header
+    fn attach_cookies(&self, jar: &CookieJar, req: &mut Request<Body>) {
+        let Some(host) = req.uri().host().filter(|host| jar.maintains(host)) else {
+            return;
+        };
+        let secure = req.uri().scheme_str() == Some("https");
+        let existing = req.headers().get(COOKIE).and_then(|v| v.to_str().ok());
+        let header = jar.cookie_header(host, req.uri().path(), secure, existing);
+        if let Some(value) = header.and_then(|h| HeaderValue::from_str(&h).ok()) {
+            req.headers_mut().insert(COOKIE, value);
+        }
+    }
+
+    /// Parameters of a permessage-deflate offer this handler can relay: the
+    /// request upgrades to a WebSocket and its scope keeps the extension
+    fn websocket_deflate_offer(&self, req: &Request<Body>, offer: &HeaderValue) -> Option<DeflateParams> {
+        self.websocket_client.as_ref()?;
+        let upgrade = req.headers().get(UPGRADE).and_then(|v| v.to_str().ok())?;
+        if !upgrade.eq_ignore_ascii_case("websocket") {
+            return None;
+        }
+        let profile = self.connection_policy.profile_for_host(req.uri().host().unwrap_or_default());
+        if profile.strip_websocket_deflate {
+            return None;
+        }
+        DeflateParams::from_header(offer.to_str().ok()?)
+    }
+
+    /// Forward an upgrade offering permessage-deflate and relay the connection
+    /// with `crate::websocket`, so its compressed messages are captured
+    /// inflated. The upstream's answer, 101 or not, goes back to the client.
+    async fn relay_websocket(
+        &mut self,
+        ctx: &HttpContext,
+        mut req: Request<Body>,
+        req_id: String,
+    ) -> RequestOrResponse {
+        let Some(client) = self.websocket_client.clone() else {
+            return RequestOrResponse::Request(req);
+        };
+        let client_upgrade = hudsucker::hyper::upgrade::on(&mut req);
+        *req.version_mut() = Version::HTTP_11;
+        let mut res = match client.request(req).await {
+            Ok(res) => res,
+            Err(e) => return RequestOrResponse::Response(self.handle_error(ctx, e).await),
+        };
+
+        if res.status() == StatusCode::SWITCHING_PROTOCOLS {
+            let params = res
+                .headers()
+                .get(SEC_WEBSOCKET_EXTENSIONS)
+                .and_then(|v| v.to_str().ok())
+                .and_then(DeflateParams::from_header);
+            let server_upgrade = hudsucker::hyper::upgrade::on(&mut res);
+            let capture = self.log_sender.clone().map(|sender| FrameCapture { request_id: req_id.clone(), sender });
+            tokio::spawn(async move {
+                let relayed = match tokio::try_join!(client_upgrade, server_upgrade) {
+                    Ok((client, server)) => crate::websocket::relay(client, server, params, capture).await,
+                    Err(e) => Err(std::io::Error::other(e)),
+                };
+                match relayed {
+                    Ok(()) => debug!("WebSocket [{}] closed", req_id),
+                    Err(e) => debug!("WebSocket [{}] relay ended: {}", req_id, e),
+                }
+            });
+        }
+        RequestOrResponse::Response(self.handle_response(ctx, res).await)
+    }
+
+    /// Keep a copy of a request its target may challenge, so a 401 can be
+    /// answered by sending it again with credentials. A body of unknown or
+    /// excessive size is not read, and the request is not kept.
+    async fn keep_for_auth_replay(&mut self, req: Request<Body>, replay: bool) -> Request<Body> {
+        if !replay {
+            return req;
+        }
+        let declared_len = req
+            .headers()
+            .get("content-length")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        let fits = declared_len.or(req.body().size_hint().upper()).is_some_and(|len| len <= MAX_REPLAY_BODY_BYTES);
+        if !fits {
+            debug!("Not keeping {} to answer authentication: body too large or of unknown size", req.uri());
+            return req;
+        }
+
+        let (parts, body) = req.into_parts();
+        let body = match hudsucker::hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Request body of {} could not be read to answer authentication: {}", parts.uri, e);
+                return Request::from_parts(parts, Body::empty());
+            }
+        };
+        self.current_auth_replay = Some(ReplayRequest::new(&parts, body.clone()));
+        Request::from_parts(parts, Body::from(body))
+    }
+
+    /// Hold a request matching a throttling rule for the rule's latency and
+    /// pace its body; the throttle is kept for the response. Rules see no
+    /// body, since it has not been read yet.
+    async fn throttle_request(&mut self, req: Request<Body>) -> Request<Body> {
+        if self.traffic_policy.interception_rules.is_empty() {
+            return req;
+        }
+        let context = RequestContext {
+            url: req.uri().to_string(),
+            method: req.method().to_string(),
+            headers: header_strings(req.headers()),
+            body: Vec::new(),
+            port: req.uri().port_u16().unwrap_or(if req.uri().scheme_str() == Some("https") { 443 } else { 80 }),
+        };
+        let throttle = self.traffic_policy.throttle_for(&context);
+        self.current_request_throttle = throttle;
+
+        let Some(throttle) = throttle else {
+            return req;
+        };
+        debug!("Throttling {} ({:?})", context.url, throttle);
+        tokio::time::sleep(throttle.sample_latency()).await;
+        req.map(|body| throttled_body(body, throttle))
+    }
+
+    /// Check the request line and headers against the configured limits
+    fn check_request_limits(&self, req: &Request<Body>) -> Option<RequestLimitViolation> {
+        let header_bytes = req
+            .headers()
+            .iter()
+            .map(|(k, v)| k.as_str().len() + v.len())
+            .sum();
+
+        self.request_limits
+            .check(req.uri().to_string().len(), req.headers().len(), header_bytes)
+    }
+
     /// Get memory usage statistics
     pub fn get_memory_stats(&self) -> crate::memory_manager::MemoryStats {
         self.memory_manager.get_stats()
@@ -119,6 +610,210 @@ impl LogHandler {
     }
 }
 
+/// Builds the response sent to clients whose request exceeded a limit
+fn limit_violation_response(violation: &RequestLimitViolation) -> Response<Body> {
+    Response::builder()
+        .status(violation.status_code())
+        .header("content-type", "text/plain")
+        .header("connection", "close")
+        .body(Body::from(violation.to_string()))
+        .expect("static response parts are valid")
+}
+
+/// Response sent to clients whose request was dropped by an interception rule
+fn intercept_drop_response() -> Response<Body> {
+    Response::builder()
+        .status(502)
+        .header("content-type", "text/plain")
+        .body(Body::from("Request dropped by interception"))
+        .expect("static response parts are valid")
+}
+
+/// Response sent to clients whose request could not be forwarded
+fn upstream_error_response(reason: &str) -> Response<Body> {
+    Response::builder()
+        .status(502)
+        .header("content-type", "text/plain")
+        .body(Body::from(format!("Upstream request failed: {}", reason)))
+        .expect("static response parts are valid")
+}
+
+/// Response sent when the upstream answers after the total timeout
+fn total_timeout_response() -> Response<Body> {
+    Response::builder()
+        .status(504)
+        .header("content-type", "text/plain")
+        .body(Body::from("Upstream exceeded the total timeout"))
+        .expect("static response parts are valid")
+}
+
+/// Relay `body`, aborting it if it is still streaming at `deadline`
+fn body_with_deadline(mut body: Body, deadline: tokio::time::Instant) -> Body {
+    let (mut sender, relayed) = Body::channel();
+    tokio::spawn(async move {
+        loop {
+            match tokio::time::timeout_at(deadline, body.data()).await {
+                Ok(Some(Ok(chunk))) => {
+                    if sender.send_data(chunk).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(None) => break,
+                Ok(Some(Err(_))) | Err(_) => {
+                    sender.abort();
+                    return;
+                }
+            }
+        }
+        if let Ok(Ok(Some(trailers))) = tokio::time::timeout_at(deadline, body.trailers()).await {
+            let _ = sender.send_trailers(trailers).await;
+        }
+    });
+    relayed
+}
+
+/// Largest piece of a throttled body sent at once, so pacing stays smooth
+const THROTTLE_SLICE_BYTES: usize = 16 * 1024;
+
+/// Relay `body` no faster than the throttle's bandwidth cap
+fn throttled_body(mut body: Body, throttle: Throttle) -> Body {
+    if throttle.bandwidth_kbps == 0 {
+        return body;
+    }
+    let (mut sender, relayed) = Body::channel();
+    tokio::spawn(async move {
+        let started = tokio::time::Instant::now();
+        let mut sent = 0u64;
+        while let Some(chunk) = body.data().await {
+            let Ok(chunk) = chunk else {
+                sender.abort();
+                return;
+            };
+            for start in (0..chunk.len()).step_by(THROTTLE_SLICE_BYTES) {
+                let slice = chunk.slice(start..(start + THROTTLE_SLICE_BYTES).min(chunk.len()));
+                sent += slice.len() as u64;
+                tokio::time::sleep_until(started + throttle.transfer_time(sent)).await;
+                if sender.send_data(slice).await.is_err() {
+                    return;
+                }
+            }
+        }
+        if let Ok(Some(trailers)) = body.trailers().await {
+            let _ = sender.send_trailers(trailers).await;
+        }
+    });
+    relayed
+}
+
+/// Response sent in place of a message a hook script dropped
+fn script_drop_response() -> Response<Body> {
+    Response::builder()
+        .status(502)
+        .header("content-type", "text/plain")
+        .body(Body::from("Dropped by hook script"))
+        .expect("static response parts are valid")
+}
+
+/// Whether a body is known to be at most `limit` bytes, by its declared
+/// length or its size hint. Streamed bodies of unknown length are not.
+fn body_within(headers: &hudsucker::hyper::HeaderMap, body: &Body, limit: u64) -> bool {
+    let declared_len = headers
+        .get("content-length")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    declared_len.or(body.size_hint().upper()).is_some_and(|len| len <= limit)
+}
+
+/// Headers with text values, as handed to hook scripts
+fn header_strings(headers: &hudsucker::hyper::HeaderMap) -> HashMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+        .collect()
+}
+
+/// Apply a hook script's header edits. Headers the script left alone keep all
+/// their values; nothing changes if any edited name or value is invalid.
+fn apply_script_headers(
+    headers: &mut hudsucker::hyper::HeaderMap,
+    original: &HashMap<String, String>,
+    edited: &HashMap<String, String>,
+) -> Result<(), String> {
+    use hudsucker::hyper::header::{HeaderName, HeaderValue};
+
+    let mut changed = Vec::new();
+    for (name, value) in edited {
+        if original.get(name) == Some(value) {
+            continue;
+        }
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid header name '{}'", name))?;
+        let value = HeaderValue::from_str(value).map_err(|_| format!("invalid value for header '{}'", name))?;
+        changed.push((name, value));
+    }
+
+    for name in original.keys().filter(|name| !edited.contains_key(*name)) {
+        headers.remove(name.as_str());
+    }
+    for (name, value) in changed {
+        headers.insert(name, value);
+    }
+    Ok(())
+}
+
+/// New body if a hook script changed the buffered one. Content-Length follows it.
+fn scripted_body(
+    headers: &mut hudsucker::hyper::HeaderMap,
+    original: Option<Bytes>,
+    edited: Option<Vec<u8>>,
+) -> Option<Body> {
+    use hudsucker::hyper::header::{HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
+
+    let edited = edited?;
+    if original.as_deref() == Some(edited.as_slice()) {
+        return None;
+    }
+    headers.remove(TRANSFER_ENCODING);
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(edited.len()));
+    Some(Body::from(edited))
+}
+
+/// Rebuild a held request from the edited copy, keeping the original's
+/// version and extensions. Content-Length follows the edited body.
+fn edited_request(
+    mut parts: hudsucker::hyper::http::request::Parts,
+    edited: crate::pb::HttpRequestData,
+) -> Result<Request<Body>, String> {
+    use hudsucker::hyper::header::{HeaderName, HeaderValue, CONTENT_LENGTH, TRANSFER_ENCODING};
+
+    parts.method = edited.method.parse().map_err(|_| format!("invalid method '{}'", edited.method))?;
+    parts.uri = edited.url.parse().map_err(|_| format!("invalid URL '{}'", edited.url))?;
+
+    let mut headers = hudsucker::hyper::HeaderMap::new();
+    for (name, value) in edited.headers.map(|h| h.headers).unwrap_or_default() {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| format!("invalid header name '{}'", name))?;
+        let value = HeaderValue::from_str(&value).map_err(|_| format!("invalid value for header '{}'", name))?;
+        headers.append(name, value);
+    }
+    headers.remove(TRANSFER_ENCODING);
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(edited.body.len()));
+    parts.headers = headers;
+
+    Ok(Request::from_parts(parts, Body::from(edited.body)))
+}
+
+/// Label for the protocol a response arrived over, as reported in traffic events
+pub fn protocol_name(version: hudsucker::hyper::Version) -> &'static str {
+    use hudsucker::hyper::Version;
+    match version {
+        Version::HTTP_09 => "HTTP/0.9",
+        Version::HTTP_10 => "HTTP/1.0",
+        Version::HTTP_11 => "HTTP/1.1",
+        Version::HTTP_2 => "HTTP/2",
+        Version::HTTP_3 => "HTTP/3",
+        _ => "",
+    }
+}
+
 /// Performance metrics for body capture operations
 #[derive(Debug, Clone)]
 pub struct BodyCapturePerformanceMetrics {
@@ -147,7 +842,7 @@ pub struct BodyCapturePerformanceMetrics {
 /// * `permit` - Memory permit for tracking concurrent operations and memory usage
 /// 
 /// # Returns
-/// * `Ok((Vec<u8>, MemoryAllocation))` - The complete decoded body data and its memory allocation tracker
+/// * `Ok((Bytes, MemoryAllocation))` - The complete decoded body data (backed by a pooled buffer) and its memory allocation tracker
 /// * `Err(BodyCaptureError)` - Various error conditions including timeouts, memory limits, and stream errors
 /// 
 /// # Requirements Addressed
@@ -163,14 +858,16 @@ async fn read_response_body_with_memory_management(
     mut body: Body,
     config: &BodyCaptureConfig,
     permit: &MemoryPermit,
-) -> Result<(Vec<u8>, MemoryAllocation), BodyCaptureError> {
+) -> Result<(Bytes, MemoryAllocation), BodyCaptureError> {
     if !config.enabled {
         debug!("Body capture disabled, returning empty body");
-        // Still need to allocate memory for the empty vec to maintain consistency
+        // Still need to allocate memory for the empty body to maintain consistency
         let allocation = permit.allocate(0)?;
-        return Ok((Vec::new(), allocation));
+        return Ok((Bytes::new(), allocation));
     }
 
+    let pool = permit.memory_manager().buffer_pool();
+
     let response_timeout = config.response_timeout();
     let stream_timeout = config.stream_read_timeout();
     
@@ -180,8 +877,8 @@ async fn read_response_body_with_memory_management(
     );
 
     // Apply overall response timeout to the entire body reading operation
-    let read_result: Result<Result<(Vec<u8>, MemoryAllocation), BodyCaptureError>, tokio::time::error::Elapsed> = timeout(response_timeout, async {
-        let mut body_data = Vec::new();
+    let read_result: Result<Result<(Bytes, MemoryAllocation), BodyCaptureError>, tokio::time::error::Elapsed> = timeout(response_timeout, async {
+        let mut body_data = pool.acquire();
         let mut current_allocation: Option<MemoryAllocation> = None;
         
         // Read body chunks using HttpBody trait (automatically handles chunked transfer encoding)
@@ -221,7 +918,7 @@ async fn read_response_body_with_memory_management(
                         
                         // Return the truncated data with its allocation
                         debug!("Returning truncated body data of {} bytes", body_data.len());
-                        return Ok((body_data, current_allocation.unwrap()));
+                        return Ok((pool.freeze(body_data), current_allocation.unwrap()));
                     }
                     
                     // Check if we can allocate memory for the new size
@@ -237,13 +934,14 @@ async fn read_response_body_with_memory_management(
                         }
                         
                         // Return current data with existing allocation
-                        return Ok((body_data.clone(), current_allocation.unwrap_or_else(|| {
+                        let allocation = current_allocation.unwrap_or_else(|| {
                             // This should not happen, but provide a fallback
                             permit.allocate(body_data.len()).unwrap_or_else(|_| {
                                 // If we can't allocate, return empty allocation
                                 permit.allocate(0).unwrap()
                             })
-                        })));
+                        });
+                        return Ok((pool.freeze(body_data), allocation));
                     }
                     
                     // Reallocate memory for the new size
@@ -257,6 +955,7 @@ async fn read_response_body_with_memory_management(
                 }
                 Ok(Some(Err(e))) => {
                     warn!("Stream error while reading body: {}", e);
+                    pool.release(body_data);
                     return Err(BodyCaptureError::StreamReadError(e.to_string()));
                 }
                 Ok(None) => {
@@ -267,6 +966,7 @@ async fn read_response_body_with_memory_management(
                 Err(_) => {
                     // Per-chunk timeout exceeded
                     warn!("Stream read timeout exceeded while reading chunk");
+                    pool.release(body_data);
                     return Err(BodyCaptureError::StreamTimeoutError);
                 }
             }
@@ -280,7 +980,7 @@ async fn read_response_body_with_memory_management(
             })
         });
         
-        Ok((body_data, final_allocation))
+        Ok((pool.freeze(body_data), final_allocation))
     }).await;
 
     match read_result {
@@ -305,13 +1005,13 @@ async fn read_response_body_with_memory_management(
 /// * `response` - The original HTTP response
 /// 
 /// # Returns
-/// * `(Response<Body>, Vec<u8>)` - Tuple containing:
+/// * `(Response<Body>, Bytes)` - Tuple containing:
 ///   - Response with empty body for client forwarding
 ///   - Empty body data for logging
 /// 
 /// # Requirements Addressed
 /// * 2.5: Handles HEAD requests gracefully with no body capture
-async fn handle_head_response(response: Response<Body>) -> (Response<Body>, Vec<u8>) {
+async fn handle_head_response(response: Response<Body>) -> (Response<Body>, Bytes) {
     debug!("Handling HEAD request response - no body capture");
     
     let (parts, body) = response.into_parts();
@@ -329,7 +1029,7 @@ async fn handle_head_response(response: Response<Body>) -> (Response<Body>, Vec<
     let reconstructed_response = Response::from_parts(parts, empty_body);
     
     debug!("HEAD response handled - returning empty body");
-    (reconstructed_response, Vec::new())
+    (reconstructed_response, Bytes::new())
 }
 
 /// Captures the response body and reconstructs an identical response for client forwarding
@@ -348,7 +1048,7 @@ async fn handle_head_response(response: Response<Body>) -> (Response<Body>, Vec<
 /// * `metrics` - Metrics for tracking performance and success/failure rates
 /// 
 /// # Returns
-/// * `(Response<Body>, Vec<u8>)` - Tuple containing:
+/// * `(Response<Body>, Bytes)` - Tuple containing:
 ///   - Reconstructed response identical to original for client forwarding
 ///   - Captured raw body data (compressed if original was compressed) for logging/storage
 /// 
@@ -369,7 +1069,7 @@ async fn capture_and_reconstruct_response_with_memory_management(
     config: &BodyCaptureConfig,
     memory_manager: &MemoryManager,
     metrics: &Arc<crate::admin::Metrics>,
-) -> (Response<Body>, Vec<u8>) {
+) -> (Response<Body>, Bytes) {
     use std::sync::atomic::Ordering;
     
     debug!("Starting response capture and reconstruction with memory management");
@@ -440,7 +1140,7 @@ async fn capture_and_reconstruct_response_with_memory_management(
                         
                         warn!("Failed to capture response body: {}. Using fallback empty body.", e);
                         // Use fallback empty body on any error to ensure proxy continues
-                        Vec::new()
+                        Bytes::new()
                     }
                 }
                 // Permit and allocation are automatically dropped here, freeing resources
@@ -465,7 +1165,7 @@ async fn capture_and_reconstruct_response_with_memory_management(
                     }
                 });
                 
-                Vec::new()
+                Bytes::new()
             }
         }
     } else {
@@ -483,7 +1183,7 @@ async fn capture_and_reconstruct_response_with_memory_management(
             }
         });
         
-        Vec::new()
+        Bytes::new()
     };
     
     // Record latency for successful captures only (to measure actual capture impact)
@@ -495,6 +1195,7 @@ async fn capture_and_reconstruct_response_with_memory_management(
     // Create a new body from the captured data
     // This preserves the exact byte sequences for both text and binary data
     // For compressed responses, this maintains the raw compressed data
+    // Cloning Bytes shares the pooled buffer rather than copying it
     let new_body = Body::from(captured_body.clone());
     
     // Reconstruct the response with the same parts (headers, status, etc.) and new body
@@ -521,7 +1222,7 @@ async fn capture_and_reconstruct_response_with_memory_management(
 /// * `metrics` - Metrics for tracking performance and success/failure rates
 ///
 /// # Returns
-/// * `(Request<Body>, Vec<u8>)` - Tuple containing:
+/// * `(Request<Body>, Bytes)` - Tuple containing:
 ///   - Reconstructed request identical to original for forwarding
 ///   - Captured raw body data for logging/storage
 async fn capture_and_reconstruct_request_with_memory_management(
@@ -529,7 +1230,7 @@ async fn capture_and_reconstruct_request_with_memory_management(
     config: &BodyCaptureConfig,
     memory_manager: &MemoryManager,
     metrics: &Arc<crate::admin::Metrics>,
-) -> (Request<Body>, Vec<u8>) {
+) -> (Request<Body>, Bytes) {
     use std::sync::atomic::Ordering;
 
     debug!("Starting request capture and reconstruction with memory management");
@@ -551,7 +1252,7 @@ async fn capture_and_reconstruct_request_with_memory_management(
     // If capture is disabled or content-type filtered, pass through without reading body
     if !should_capture || !config.enabled {
         debug!("Body capture disabled or filtered, passing through original body");
-        return (Request::from_parts(parts, body), Vec::new());
+        return (Request::from_parts(parts, body), Bytes::new());
     }
 
     // Record capture attempt
@@ -565,7 +1266,7 @@ async fn capture_and_reconstruct_request_with_memory_management(
             metrics.body_capture_failures.fetch_add(1, Ordering::Relaxed);
             metrics.body_capture_memory_errors.fetch_add(1, Ordering::Relaxed);
             warn!("Backpressure: forwarding request without capturing body.");
-            return (Request::from_parts(parts, body), Vec::new());
+            return (Request::from_parts(parts, body), Bytes::new());
         }
     };
 
@@ -586,7 +1287,7 @@ async fn capture_and_reconstruct_request_with_memory_management(
             // Record failure - stream is dead, return empty body
             metrics.body_capture_failures.fetch_add(1, Ordering::Relaxed);
             warn!("Request capture failed: {}. Returning empty body.", e);
-            (Request::from_parts(parts, Body::empty()), Vec::new())
+            (Request::from_parts(parts, Body::empty()), Bytes::new())
         }
     }
 }
@@ -596,19 +1297,80 @@ async fn capture_and_reconstruct_request_with_memory_management(
 impl HttpHandler for LogHandler {
     async fn handle_request(
         &mut self,
-        _ctx: &HttpContext,
+        ctx: &HttpContext,
         req: Request<Body>,
     ) -> RequestOrResponse {
         // Shadow req as mutable to modify headers
         let mut req = req;
         
-        // Strip Sec-WebSocket-Extensions to disable compression (permessage-deflate)
-        // This avoids "Reserved bits are non-zero" errors when the proxy logic
-        // doesn't support compressed frames but the client/server negotiated it.
-        req.headers_mut().remove("sec-websocket-extensions");
+        // Strip Sec-WebSocket-Extensions: hudsucker's relay rejects compressed
+        // frames. A permessage-deflate offer is put back for captured upgrades,
+        // which `relay_websocket` handles instead.
+        let websocket_deflate = req
+            .headers_mut()
+            .remove(SEC_WEBSOCKET_EXTENSIONS)
+            .and_then(|offer| self.websocket_deflate_offer(&req, &offer));
+
+        // A request over an intercepted tunnel shows its handshake succeeded
+        if req.method() != Method::CONNECT {
+            self.connection_log.tunnel_used(ctx.client_addr);
+        }
 
         self.metrics.total_requests.fetch_add(1, Ordering::Relaxed);
 
+        // Reject oversized request lines / header sections before doing any work on them
+        if let Some(violation) = self.check_request_limits(&req) {
+            self.metrics.requests_rejected.fetch_add(1, Ordering::Relaxed);
+            let uri = req.uri().to_string();
+            let uri_preview: String = uri.chars().take(128).collect();
+            warn!(
+                "Rejected {} {}{} with {}: {}",
+                req.method(),
+                uri_preview,
+                if uri.len() > uri_preview.len() { "..." } else { "" },
+                violation.status_code(),
+                violation
+            );
+            self.connection_log.record(Kind::Blocked, &uri_preview, ctx.client_addr, &violation.to_string(), None);
+            *self.current_request_id.write().await = None;
+            *self.current_request_method.write().await = None;
+            return RequestOrResponse::Response(limit_violation_response(&violation));
+        }
+
+        // Targets behind HTTP authentication get credentials whether or not
+        // they are in the capture scope; a header the client sent wins
+        self.current_auth_replay = None;
+        let mut replay_auth = false;
+        if let Some(auth) = &self.target_auth {
+            if !req.headers().contains_key(AUTHORIZATION) {
+                let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/").to_string();
+                let value = req.uri().host().and_then(|host| auth.authorization(host, req.method().as_str(), &path));
+                if let Some(value) = value.and_then(|v| HeaderValue::from_str(&v).ok()) {
+                    req.headers_mut().insert(AUTHORIZATION, value);
+                }
+                replay_auth = req.method() != Method::CONNECT
+                    && req.uri().host().is_some_and(|host| auth.answers_challenges(host));
+            }
+        }
+
+        if let Some(jar) = &self.cookie_jar {
+            if req.method() != Method::CONNECT {
+                self.attach_cookies(jar, &mut req);
+                self.current_cookie_origin = Some(req.uri().clone());
+            }
+        }
+
+        // Connection budgets apply whatever the capture scope
+        if req.method() != Method::CONNECT {
+            let profile = self.connection_policy.profile_for_host(req.uri().host().unwrap_or_default());
+            if !profile.keep_alive.enabled {
+                req.headers_mut().insert(CONNECTION, HeaderValue::from_static("close"));
+            }
+            self.current_request_deadline =
+                profile.total_timeout().map(|limit| tokio::time::Instant::now() + limit);
+            req = self.throttle_request(req).await;
+        }
+
         // Check Scope
         if let Some(matcher) = &self.scope_matcher {
             if let Some(host) = req.uri().host() {
@@ -616,18 +1378,95 @@ impl HttpHandler for LogHandler {
                     // Clear any pending request_id and method for out-of-scope requests
                     *self.current_request_id.write().await = None;
                     *self.current_request_method.write().await = None;
-                    return RequestOrResponse::Request(req);
+                    return RequestOrResponse::Request(self.keep_for_auth_replay(req, replay_auth).await);
                 }
             }
         }
 
+        // Paused capture forwards the request like an out-of-scope one
+        if let Some(capture) = &self.capture_controller {
+            if !capture.should_capture(req.uri().host().unwrap_or_default()) {
+                *self.current_request_id.write().await = None;
+                *self.current_request_method.write().await = None;
+                return RequestOrResponse::Request(self.keep_for_auth_replay(req, replay_auth).await);
+            }
+        }
+
+        // CONNECTs are connection events; the requests inside the tunnel are
+        // captured on their own
+        if req.method() == Method::CONNECT {
+            let target = req.uri().authority().map(|a| a.to_string()).unwrap_or_default();
+            self.connection_log.tunnel_opened(ctx.client_addr, &target);
+            *self.current_request_id.write().await = None;
+            *self.current_request_method.write().await = None;
+            return RequestOrResponse::Request(req);
+        }
+
         let req_id = Uuid::new_v4().to_string();
         let uri = req.uri().to_string();
         info!("Request [{}] {} {}", req_id, req.method(), uri);
 
+        // Interception runs before capture, so the logged request is the one forwarded
+        if let Some(controller) = self.intercept_controller.clone() {
+            let headers: HashMap<String, String> = req
+                .headers()
+                .iter()
+                .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+                .collect();
+            let held = match controller.match_request(req.method().as_str(), &uri, &headers) {
+                Some(crate::pb::intercept_rule::Action::Drop) => None,
+                Some(crate::pb::intercept_rule::Action::Pause) => {
+                    self.hold_request(&controller, &req_id, req, headers).await
+                }
+                None => Some(req),
+            };
+            req = match held {
+                Some(req) => req,
+                None => {
+                    info!("Request [{}] dropped by interception", req_id);
+                    self.connection_log.record(Kind::Blocked, &uri, ctx.client_addr, "Dropped by interception rule", None);
+                    *self.current_request_id.write().await = None;
+                    *self.current_request_method.write().await = None;
+                    return RequestOrResponse::Response(intercept_drop_response());
+                }
+            };
+        }
+
+        // Hook scripts see the request as it will be forwarded
+        let mut script_tags = Vec::new();
+        if let Some(scripts) = self.script_controller.clone().filter(|s| s.has_request_hooks()) {
+            match self.run_request_scripts(&scripts, &req_id, req).await {
+                Some((hooked, tags)) => {
+                    req = hooked;
+                    script_tags = tags;
+                }
+                None => {
+                    info!("Request [{}] dropped by hook script", req_id);
+                    self.connection_log.record(Kind::Blocked, &uri, ctx.client_addr, "Dropped by hook script", None);
+                    *self.current_request_id.write().await = None;
+                    *self.current_request_method.write().await = None;
+                    return RequestOrResponse::Response(script_drop_response());
+                }
+            }
+        }
+        let uri = req.uri().to_string();
+
+        // Added last so neither rules nor scripts can drop it, and before
+        // capture so the logged request shows it
+        if let Some(header) = &self.request_id_header {
+            if let Ok(value) = req_id.parse() {
+                req.headers_mut().insert(header.clone(), value);
+            }
+        }
+
         // Store request_id and method for response correlation
         *self.current_request_id.write().await = Some(req_id.clone());
         *self.current_request_method.write().await = Some(req.method().to_string());
+        *self.current_request_url.write().await = Some(uri.clone());
+
+        if let Some(offer) = websocket_deflate.as_ref().and_then(|params| params.to_header().parse().ok()) {
+            req.headers_mut().insert(SEC_WEBSOCKET_EXTENSIONS, offer);
+        }
 
         // Capture request body if logging is enabled
         let (req, captured_body) = if self.log_sender.is_some() {
@@ -638,7 +1477,7 @@ impl HttpHandler for LogHandler {
                 &self.metrics
             ).await
         } else {
-            (req, Vec::new())
+            (req, Bytes::new())
         };
 
         if let Some(sender) = &self.log_sender {
@@ -652,7 +1491,7 @@ impl HttpHandler for LogHandler {
             }
 
             let event = TrafficEvent {
-                request_id: req_id,
+                request_id: req_id.clone(),
                 event: Some(traffic_event::Event::Request(HttpRequestData {
                     method: req.method().to_string(),
                     url: uri,
@@ -666,22 +1505,106 @@ impl HttpHandler for LogHandler {
 
             let _ = sender.try_send(event);
         }
+        self.send_script_tags(&req_id, script_tags);
 
+        let req = self.keep_for_auth_replay(req, replay_auth).await;
+        self.current_request_timer = Some(PhaseTimer::start());
+        if websocket_deflate.is_some() {
+            return self.relay_websocket(ctx, req, req_id).await;
+        }
         RequestOrResponse::Request(req)
     }
 
-    async fn handle_response(&mut self, _ctx: &HttpContext, res: Response<Body>) -> Response<Body> {
+    async fn handle_response(&mut self, _ctx: &HttpContext, mut res: Response<Body>) -> Response<Body> {
         use crate::pb::{traffic_event, HttpHeaders, HttpResponseData, TrafficEvent};
 
+        // A 401 from a Digest or NTLM target is answered by sending the
+        // request again with credentials; the client only sees the outcome
+        let replay = self.current_auth_replay.take();
+        if let (Some(auth), Some(replay), 401) = (&self.target_auth, replay, res.status().as_u16()) {
+            let challenges: Vec<String> = res
+                .headers()
+                .get_all(WWW_AUTHENTICATE)
+                .iter()
+                .filter_map(|v| v.to_str().ok().map(str::to_string))
+                .collect();
+            match auth.answer_challenge(&replay, &challenges).await {
+                Some(Ok(answered)) => {
+                    debug!("Answered the authentication challenge of {}", replay.uri());
+                    res = answered;
+                }
+                Some(Err(e)) => warn!("Answering the authentication challenge of {} failed: {}", replay.uri(), e),
+                None => {}
+            }
+        }
+
+        if let Some(throttle) = self.current_request_throttle.take() {
+            res = res.map(|body| throttled_body(body, throttle));
+        }
+
+        // The rest of the exchange, body included, shares the total budget
+        if let Some(deadline) = self.current_request_deadline.take() {
+            if tokio::time::Instant::now() >= deadline {
+                self.current_request_id.write().await.take();
+                self.current_request_method.write().await.take();
+                self.current_request_timer = None;
+                self.current_cookie_origin = None;
+                let url = self.current_request_url.write().await.take();
+                warn!("Upstream request {} exceeded its total timeout", url.as_deref().unwrap_or("(untracked)"));
+                return total_timeout_response();
+            }
+            res = res.map(|body| body_with_deadline(body, deadline));
+        }
+
         let status = res.status().as_u16() as i32;
+        let protocol = protocol_name(res.version());
+        let mut timer = self.current_request_timer.take();
+        if let Some(timer) = &mut timer {
+            timer.headers_received();
+        }
         
         // Get request_id and method from the stored values (set during handle_request)
         let request_id = self.current_request_id.write().await.take();
         let request_method = self.current_request_method.write().await.take();
+        let request_url = self.current_request_url.write().await.take();
+
+        if let (Some(jar), Some(origin)) = (&self.cookie_jar, self.current_cookie_origin.take()) {
+            if let Some(host) = origin.host() {
+                let secure = origin.scheme_str() == Some("https");
+                let set_cookies = res.headers().get_all(SET_COOKIE).iter().filter_map(|v| v.to_str().ok());
+                jar.store(host, origin.path(), secure, set_cookies);
+            }
+        }
+
+        // A Digest challenge is remembered and answered from the next request
+        // on, including one that arrives in answer to a resent request
+        if let (Some(auth), Some(url), 401) = (&self.target_auth, &request_url, status) {
+            if let Some(host) = url::Url::parse(url).ok().and_then(|u| u.host_str().map(str::to_string)) {
+                let challenges = res.headers().get_all(WWW_AUTHENTICATE).iter().filter_map(|v| v.to_str().ok());
+                if auth.observe_challenge(&host, challenges) {
+                    debug!("Stored Digest challenge for {}", host);
+                }
+            }
+        }
 
         if let Some(request_id) = request_id {
             info!("Response [{}] status: {}", request_id, status);
 
+            if let Some(scripts) = self.script_controller.clone().filter(|s| s.has_response_hooks()) {
+                let read_body = !request_method.as_deref().is_some_and(|m| m.eq_ignore_ascii_case("HEAD"));
+                match self.run_response_scripts(&scripts, &request_id, res, read_body).await {
+                    Some((hooked, tags)) => {
+                        res = hooked;
+                        self.send_script_tags(&request_id, tags);
+                    }
+                    None => {
+                        info!("Response [{}] dropped by hook script", request_id);
+                        return script_drop_response();
+                    }
+                }
+            }
+            let status = res.status().as_u16() as i32;
+
             if let Some(sender) = &self.log_sender {
                 // Check if this is a HEAD request and handle gracefully
                 let (reconstructed_response, captured_body) = if let Some(ref method) = request_method {
@@ -724,6 +1647,8 @@ impl HttpHandler for LogHandler {
                         }),
                         body: captured_body,  // Use captured body instead of hardcoded empty vec
                         tls: None,
+                        protocol: protocol.to_string(),
+                        timing: timer.map(|timer| timer.finish()),
                     })),
                 };
 
@@ -742,4 +1667,155 @@ impl HttpHandler for LogHandler {
         // If no logging is configured or request_id is missing, return original response
         res
     }
+
+    async fn handle_error(&mut self, ctx: &HttpContext, err: hudsucker::hyper::Error) -> Response<Body> {
+        self.current_auth_replay = None;
+        self.current_request_deadline = None;
+        self.current_request_throttle = None;
+        let request_id = self.current_request_id.write().await.take();
+        self.current_request_method.write().await.take();
+        self.current_request_timer = None;
+        let url = self.current_request_url.write().await.take();
+
+        let (kind, reason) = classify_upstream_error(&err);
+        warn!("Upstream request {} failed: {}", url.as_deref().unwrap_or("(untracked)"), reason);
+        if let Some(url) = &url {
+            self.connection_log.record(kind, url, ctx.client_addr, &reason, request_id.as_deref());
+            if kind == Kind::TlsFailure {
+                self.connection_log.interception_failed(url, ctx.client_addr, &reason);
+            }
+        }
+
+        upstream_error_response(&reason)
+    }
+
+    async fn should_intercept(&mut self, ctx: &HttpContext, req: &Request<Body>) -> bool {
+        let Some(host) = req.uri().host() else {
+            return true;
+        };
+        if self.tls_passthrough.iter().any(|pattern| pattern.matches(host)) {
+            let target = req.uri().authority().map(|a| a.to_string()).unwrap_or_default();
+            self.connection_log
+                .tunnel_passed_through(ctx.client_addr, &target, "Host is configured for TLS pass-through");
+            return false;
+        }
+        if self.mitm_fallback.as_ref().is_some_and(|fallback| fallback.is_bypassed(target_host(host))) {
+            let target = req.uri().authority().map(|a| a.to_string()).unwrap_or_default();
+            self.connection_log.tunnel_passed_through(
+                ctx.client_addr,
+                &target,
+                "Temporary pass-through after repeated interception failures",
+            );
+            return false;
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{InterceptionRule, RuleAction, RuleCondition};
+    use std::time::Duration;
+
+    fn throttling_handler(throttle: RuleAction) -> LogHandler {
+        let policy = TrafficPolicy {
+            interception_rules: vec![InterceptionRule {
+                id: "slow".to_string(),
+                name: "Slow backend".to_string(),
+                enabled: true,
+                conditions: vec![RuleCondition::UrlContains("slow.test".to_string())],
+                action: throttle,
+            }],
+            ..Default::default()
+        };
+        LogHandler::new_with_defaults(Arc::new(Metrics::default()), None).with_traffic_policy(Arc::new(policy))
+    }
+
+    fn request(url: &str) -> Request<Body> {
+        Request::builder().uri(url).body(Body::from("ping")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_throttle_adds_latency_to_matching_requests() {
+        let mut handler =
+            throttling_handler(RuleAction::Throttle { latency_ms: 300, jitter_ms: 0, bandwidth_kbps: 0 });
+
+        let started = std::time::Instant::now();
+        handler.throttle_request(request("http://fast.test/")).await;
+        assert!(started.elapsed() < Duration::from_millis(300));
+        assert!(handler.current_request_throttle.is_none());
+
+        let started = std::time::Instant::now();
+        let req = handler.throttle_request(request("http://slow.test/api")).await;
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert!(handler.current_request_throttle.is_some());
+        assert_eq!(hudsucker::hyper::body::to_bytes(req.into_body()).await.unwrap(), "ping");
+    }
+
+    #[tokio::test]
+    async fn test_auth_replay_kept_per_request() {
+        use crate::target_auth::{TargetAuthConfig, TargetAuthScheme};
+
+        let connector = crate::upstream::upstream_connector(
+            &Default::default(),
+            &HashMap::new(),
+            None,
+            Default::default(),
+            Default::default(),
+            &[],
+            &[],
+            true,
+        )
+        .unwrap();
+        let target = TargetAuthConfig {
+            host_pattern: "*.intranet.test".to_string(),
+            scheme: TargetAuthScheme::Digest,
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+            domain: None,
+        };
+        let handler = LogHandler::new_with_defaults(Arc::new(Metrics::default()), None)
+            .with_target_auth(TargetAuthenticator::new(&[target]).with_connector(connector));
+
+        // hudsucker clones the handler for each request, and the responses of
+        // two requests in flight can come back in any order
+        let mut first = handler.clone();
+        let mut second = handler.clone();
+        first.keep_for_auth_replay(request("http://a.intranet.test/first"), true).await;
+        second.keep_for_auth_replay(request("http://b.intranet.test/second"), true).await;
+
+        let replayed = |handler: &mut LogHandler| handler.current_auth_replay.take().map(|r| r.uri().to_string());
+        assert_eq!(replayed(&mut second).as_deref(), Some("http://b.intranet.test/second"));
+        assert_eq!(replayed(&mut first).as_deref(), Some("http://a.intranet.test/first"));
+        assert!(handler.current_auth_replay.is_none());
+    }
+
+    #[test]
+    fn test_body_within_needs_a_known_size() {
+        let sized = request("http://a.test/");
+        assert!(body_within(sized.headers(), sized.body(), 4));
+        assert!(!body_within(sized.headers(), sized.body(), 3));
+
+        let (_sender, streamed) = Body::channel();
+        let mut headers = hudsucker::hyper::HeaderMap::new();
+        assert!(!body_within(&headers, &streamed, u64::MAX));
+        headers.insert("content-length", HeaderValue::from_static("2048"));
+        assert!(body_within(&headers, &streamed, 4096));
+        assert!(!body_within(&headers, &streamed, 1024));
+    }
+
+    #[tokio::test]
+    async fn test_throttled_body_is_paced() {
+        // 1280 kbps moves 160 000 bytes a second
+        let throttle = Throttle { latency_ms: 0, jitter_ms: 0, bandwidth_kbps: 1280 };
+        let payload = Bytes::from(vec![b'x'; 48_000]);
+
+        let started = std::time::Instant::now();
+        let body = hudsucker::hyper::body::to_bytes(throttled_body(Body::from(payload.clone()), throttle))
+            .await
+            .unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(body, payload);
+    }
 }
@@ -0,0 +1,168 @@
+//! Leaf certificates for intercepted CONNECT tunnels
+//!
+//! Issuing a leaf means generating a key pair, which for RSA takes long
+//! enough to show on every new tunnel, so server configs are kept per
+//! hostname in a bounded LRU cache. Hits, misses and evictions are reported
+//! on the admin API. The cache starts over when the CA root is rotated.
+
+use crate::ca::CertificateAuthority;
+use async_trait::async_trait;
+use hudsucker::hyper::http::uri::Authority;
+use lru::LruCache;
+use serde::Serialize;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tracing::debug;
+
+/// Hosts whose certificates are kept unless configured otherwise
+pub const DEFAULT_LEAF_CACHE_CAPACITY: usize = 1000;
+
+/// Snapshot of the cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct LeafCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Certificates dropped to make room for another host
+    pub evictions: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+/// Server configs presenting CA-signed leaves, by hostname. Cloning shares
+/// the cache.
+#[derive(Clone)]
+pub struct LeafCertCache {
+    ca: Arc<CertificateAuthority>,
+    configs: Arc<Mutex<LruCache<String, Arc<rustls::ServerConfig>>>>,
+    /// CA generation the cached certificates were signed under
+    generation: Arc<AtomicU64>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+    evictions: Arc<AtomicU64>,
+}
+
+impl LeafCertCache {
+    /// Cache holding up to `capacity` hosts (at least one)
+    pub fn new(ca: Arc<CertificateAuthority>, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        let generation = ca.generation();
+        Self {
+            ca,
+            generation: Arc::new(AtomicU64::new(generation)),
+            configs: Arc::new(Mutex::new(LruCache::new(capacity))),
+            hits: Arc::default(),
+            misses: Arc::default(),
+            evictions: Arc::default(),
+        }
+    }
+
+    pub fn stats(&self) -> LeafCacheStats {
+        let configs = self.configs.lock().unwrap_or_else(|e| e.into_inner());
+        LeafCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: configs.len(),
+            capacity: configs.cap().get(),
+        }
+    }
+
+    /// TLS config presenting a certificate for `host`, issued on a miss.
+    ///
+    /// Concurrent misses for one host may each issue a certificate; the last
+    /// one issued stays cached.
+    pub async fn server_config(&self, host: &str) -> crate::Result<Arc<rustls::ServerConfig>> {
+        let host = host.to_ascii_lowercase();
+        let generation = self.ca.generation();
+        {
+            let mut configs = self.configs.lock().unwrap_or_else(|e| e.into_inner());
+            if self.generation.swap(generation, Ordering::SeqCst) != generation {
+                debug!("CA root rotated, dropping {} cached leaf certificates", configs.len());
+                configs.clear();
+            }
+            if let Some(config) = configs.get(&host) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(config.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        // Key generation is CPU-bound, and slow for RSA
+        let ca = self.ca.clone();
+        let name = host.clone();
+        let (cert_der, key_der) = tokio::task::spawn_blocking(move || ca.gen_cert_der_for_domain(&name))
+            .await
+            .map_err(|e| crate::ProxyError::Certificate(format!("Certificate issuing for {} failed: {}", host, e)))??;
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![rustls::Certificate(cert_der)], rustls::PrivateKey(key_der))
+            .map_err(|e| crate::ProxyError::Certificate(format!("Invalid certificate for {}: {}", host, e)))?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let config = Arc::new(config);
+
+        debug!("Issued {} leaf certificate for {}", self.ca.leaf_key(), host);
+        if self.ca.generation() != generation {
+            // Signed by the root rotated out meanwhile: serve it once, keep none
+            return Ok(config);
+        }
+        let evicted = self.configs.lock().unwrap_or_else(|e| e.into_inner()).push(host.clone(), config.clone());
+        if evicted.is_some_and(|(evicted_host, _)| evicted_host != host) {
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(config)
+    }
+}
+
+#[async_trait]
+impl hudsucker::certificate_authority::CertificateAuthority for LeafCertCache {
+    async fn gen_server_config(&self, authority: &Authority) -> Arc<rustls::ServerConfig> {
+        // The trait has no error path; hudsucker's own authority panics too,
+        // which ends only this connection's task
+        self.server_config(authority.host())
+            .await
+            .unwrap_or_else(|e| panic!("No certificate for {}: {}", authority.host(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_cache_counts_hits_misses_and_evictions() {
+        let dir = tempdir().unwrap();
+        let ca = Arc::new(CertificateAuthority::new(dir.path()).unwrap());
+        let cache = LeafCertCache::new(ca, 2);
+
+        let first = cache.server_config("a.test").await.unwrap();
+        let again = cache.server_config("A.test").await.unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        cache.server_config("b.test").await.unwrap();
+        // a.test was used more recently than b.test, so b.test makes room
+        cache.server_config("a.test").await.unwrap();
+        cache.server_config("c.test").await.unwrap();
+        cache.server_config("b.test").await.unwrap();
+
+        assert_eq!(
+            cache.stats(),
+            LeafCacheStats { hits: 2, misses: 4, evictions: 2, entries: 2, capacity: 2 }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rotation_clears_cache() {
+        let dir = tempdir().unwrap();
+        let ca = Arc::new(CertificateAuthority::new(dir.path()).unwrap());
+        let cache = LeafCertCache::new(ca.clone(), 10);
+
+        let before = cache.server_config("a.test").await.unwrap();
+        ca.rotate(dir.path(), &dir.path().join("archive")).unwrap();
+        let after = cache.server_config("a.test").await.unwrap();
+        assert!(!Arc::ptr_eq(&before, &after));
+        assert_eq!((cache.stats().misses, cache.stats().entries), (2, 1));
+    }
+}
@@ -5,6 +5,8 @@
 
 pub mod admin;
 pub mod ca;
+/// Passphrase encryption of the stored CA key
+pub mod ca_key;
 pub mod certificates;
 pub mod controller;
 pub mod filter;
@@ -25,22 +27,112 @@ pub mod error;
 /// Memory management for response body capture
 pub mod memory_manager;
 
+/// Reusable buffers for captured bodies
+pub mod buffer_pool;
+
+/// Dedicated runtime for the proxy data path
+pub mod runtime;
+
+/// Custom DNS resolvers for upstream connections
+pub mod dns;
+
+/// Proxy auto-config (PAC) evaluation
+pub mod pac;
+
+/// Upstream connector (custom DNS, PAC-selected proxies)
+pub mod upstream;
+
+/// SMTP and IMAP capture listeners
+pub mod mail;
+
+/// SNI-routed reverse proxy listeners
+pub mod reverse;
+
+/// Listener for traffic redirected by the packet filter
+pub mod transparent;
+
+/// Rhai request/response hook scripts
+pub mod scripts;
+
+/// Runtime pause/resume of traffic capture
+pub mod capture;
+
+/// Connection-level events (CONNECTs, TLS failures, blocks)
+pub mod connections;
+
+/// Automatic pass-through for hosts whose interception keeps failing
+pub mod mitm_fallback;
+
+/// WebSocket permessage-deflate negotiation and codec
+pub mod websocket;
+
+/// Basic, Digest and NTLM authentication to target applications
+pub mod target_auth;
+
+/// NTLMv2 handshake messages
+pub mod ntlm;
+
+/// Browser-like ClientHello cipher and group preferences
+pub mod client_hello;
+
+/// Per-host TLS versions, trusted roots and verification for upstreams
+pub mod tls_policy;
+
+/// LRU cache of the leaf certificates presented to intercepted clients
+pub mod leaf_cache;
+
+/// Per-agent cookie jar with RFC 6265 matching
+pub mod cookie_jar;
+
+/// Monotonic timing of request phases
+pub mod timing;
+
+/// Wire messages to and from proxy-common's HTTP types
+pub mod conversions;
+
+/// Liveness and readiness probes of the admin servers
+pub mod probes;
+
 /// Integration tests for memory management
 #[cfg(test)]
 pub mod memory_manager_integration_test;
 
 pub use admin::Metrics;
-pub use ca::CertificateAuthority;
-pub use certificates::CertificateManager;
-pub use config::{BodyCaptureConfig, ContentTypeFilterMode, ProxyConfig, ProxyStartupConfig};
+pub use buffer_pool::{BufferPool, BufferPoolStats};
+pub use ca::{CertificateAuthority, LeafKeyAlgorithm};
+pub use capture::CaptureController;
+pub use connections::ConnectionLog;
+pub use cookie_jar::{CookieJar, StoredCookie};
+pub use mitm_fallback::{FallbackEntry, MitmFallback};
+pub use client_hello::ClientHelloProfile;
+pub use certificates::{CertificateManager, ClientCertificate, ClientCertificateConfig, ClientCertificateSource};
+pub use config::{
+    BodyCaptureConfig, CertificateConfig, ContentTypeFilterMode, MitmFallbackConfig, ProxyConfig, ProxyStartupConfig, RequestLimitViolation,
+    RequestLimits,
+};
 pub use controller::InterceptController;
+pub use dns::{override_key, CustomDnsResolver, DnsConfig, DnsMode};
 pub use error::{BodyCaptureError, ProxyError};
 pub use filter::ScopeMatcher;
 pub use handlers::LogHandler;
+pub use leaf_cache::{LeafCacheStats, LeafCertCache};
+pub use timing::PhaseTimer;
+pub use tls_policy::{TlsPolicy, TlsPolicyConfig, TlsVersion};
+pub use target_auth::{TargetAuthConfig, TargetAuthScheme, TargetAuthenticator};
+pub use mail::{MailListener, MailListenerConfig, MailProtocol};
 pub use memory_manager::{MemoryManager, MemoryStats};
-pub use policy::{InterceptionRule, RuleAction, RuleCondition, ScopeConfig, TrafficPolicy};
+pub use pac::{PacEngine, ProxyChoice};
+pub use probes::{LinkStatus, ProbeCheck};
+pub use reverse::{ReverseListener, ReverseListenerConfig, ReverseUpstream, SniRoute};
+pub use transparent::{Destination, TransparentListener, TransparentListenerConfig};
+pub use policy::{
+    ConnectionPolicy, ConnectionProfile, EgressPolicy, EgressRoute, EgressRule, InterceptionRule,
+    KeepAlivePolicy, RuleAction, RuleCondition, ScopeConfig, ScopedConnectionProfile, Throttle, TrafficPolicy,
+};
 /// Re-export commonly used types
 pub use proxy::ProxyServer;
+pub use runtime::{DataPlaneRuntime, DataPlaneRuntimeConfig};
+pub use scripts::{HookOutcome, ScriptController, ScriptRequest, ScriptResponse};
 pub use system_metrics::{SystemMetricsCollector, SystemMetricsCollectorConfig};
 
 /// Result type alias for proxy operations
@@ -0,0 +1,839 @@
+//! Mail Capture
+//!
+//! SMTP and IMAP interception listeners. Each listener accepts mail clients
+//! on a local port and relays them to a fixed upstream mail server, logging
+//! every command and its reply as a transaction with an `smtp://` or
+//! `imap://` URL. Message bodies sent with DATA or BDAT and literals sent to
+//! or received from an IMAP server are captured as transaction bodies.
+//!
+//! Bytes are relayed unchanged. When the client upgrades with STARTTLS and
+//! the server accepts, both legs are upgraded: the client is served a
+//! certificate for the upstream host signed by the agent CA, and the agent
+//! opens its own verified TLS session to the server. Listeners can also
+//! speak implicit TLS (SMTPS on 465, IMAPS on 993).
+
+use crate::ca::CertificateAuthority;
+use crate::error::ProxyError;
+use crate::pb::{traffic_event, HttpHeaders, HttpRequestData, HttpResponseData, TlsDetails, TrafficEvent};
+use crate::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls;
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// Mail protocol spoken on a listener
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MailProtocol {
+    Smtp,
+    Imap,
+}
+
+impl MailProtocol {
+    /// URL scheme of logged transactions
+    pub fn scheme(&self) -> &'static str {
+        match self {
+            MailProtocol::Smtp => "smtp",
+            MailProtocol::Imap => "imap",
+        }
+    }
+
+    /// Protocol name recorded on responses
+    pub fn name(&self) -> &'static str {
+        match self {
+            MailProtocol::Smtp => "SMTP",
+            MailProtocol::Imap => "IMAP",
+        }
+    }
+}
+
+/// One mail interception listener
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailListenerConfig {
+    pub protocol: MailProtocol,
+    #[serde(default = "default_listen_address")]
+    pub listen_address: String,
+    pub listen_port: u16,
+    /// Mail server the listener relays to
+    pub upstream_host: String,
+    pub upstream_port: u16,
+    /// TLS from the first byte on both legs instead of plaintext with STARTTLS
+    #[serde(default)]
+    pub implicit_tls: bool,
+    /// Largest command or reply body captured per transaction; the relay is not limited
+    #[serde(default = "default_max_capture_bytes")]
+    pub max_capture_bytes: usize,
+}
+
+fn default_listen_address() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_max_capture_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+impl MailListenerConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.listen_port == 0 {
+            return Err("listen_port must be set".to_string());
+        }
+        if self.upstream_host.trim().is_empty() {
+            return Err("upstream_host must be set".to_string());
+        }
+        if self.upstream_port == 0 {
+            return Err("upstream_port must be set".to_string());
+        }
+        rustls::ServerName::try_from(self.upstream_host.as_str())
+            .map_err(|_| format!("'{}' is not a valid TLS server name", self.upstream_host))?;
+        Ok(())
+    }
+}
+
+/// A bound mail listener, ready to serve
+pub struct MailListener {
+    listener: TcpListener,
+    shared: Arc<ListenerShared>,
+}
+
+struct ListenerShared {
+    config: MailListenerConfig,
+    acceptor: TlsAcceptor,
+    connector: TlsConnector,
+    server_name: rustls::ServerName,
+    log_sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+}
+
+impl MailListener {
+    /// Bind the listener and prepare both TLS legs
+    pub async fn bind(
+        config: MailListenerConfig,
+        ca: &CertificateAuthority,
+        log_sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+    ) -> Result<Self> {
+        config.validate().map_err(ProxyError::Configuration)?;
+
+        let (cert_der, key_der) = ca.gen_cert_der_for_domain(&config.upstream_host)?;
+        let server_config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![rustls::Certificate(cert_der)], rustls::PrivateKey(key_der))
+            .map_err(|e| ProxyError::Certificate(format!("Invalid mail listener certificate: {}", e)))?;
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        }));
+        let client_config = rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let server_name = rustls::ServerName::try_from(config.upstream_host.as_str())
+            .map_err(|_| ProxyError::Configuration(format!("Invalid mail upstream host: {}", config.upstream_host)))?;
+
+        let addr = format!("{}:{}", config.listen_address, config.listen_port);
+        let listener = TcpListener::bind(&addr)
+            .await
+            .map_err(|e| ProxyError::Network(format!("Failed to bind mail listener on {}: {}", addr, e)))?;
+
+        Ok(Self {
+            listener,
+            shared: Arc::new(ListenerShared {
+                config,
+                acceptor: TlsAcceptor::from(Arc::new(server_config)),
+                connector: TlsConnector::from(Arc::new(client_config)),
+                server_name,
+                log_sender,
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(ProxyError::Io)
+    }
+
+    /// Accept and relay clients until the task is dropped
+    pub async fn serve(self) {
+        let config = &self.shared.config;
+        info!(
+            "📧 {} capture listening on {}:{} -> {}:{}",
+            config.protocol.name(),
+            config.listen_address,
+            config.listen_port,
+            config.upstream_host,
+            config.upstream_port
+        );
+        loop {
+            let (client, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Mail listener accept failed: {}", e);
+                    continue;
+                }
+            };
+            let shared = self.shared.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_session(client, peer, &shared).await {
+                    debug!("{} session from {} ended: {}", shared.config.protocol.name(), peer, e);
+                }
+            });
+        }
+    }
+}
+
+trait MailIo: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> MailIo for T {}
+
+enum RelayEnd {
+    Closed,
+    StartTls,
+}
+
+async fn handle_session(client: TcpStream, peer: SocketAddr, shared: &ListenerShared) -> std::io::Result<()> {
+    let config = &shared.config;
+    let server = TcpStream::connect((config.upstream_host.as_str(), config.upstream_port)).await?;
+    let mut log = SessionLog {
+        sender: shared.log_sender.clone(),
+        protocol: config.protocol,
+        url: format!("{}://{}:{}", config.protocol.scheme(), config.upstream_host, config.upstream_port),
+        session_id: Uuid::new_v4().to_string(),
+        client_addr: peer.to_string(),
+        tls: None,
+    };
+    debug!("{} session {} from {}", config.protocol.name(), log.session_id, peer);
+
+    let (mut client, mut server): (Box<dyn MailIo>, Box<dyn MailIo>) = (Box::new(client), Box::new(server));
+    if config.implicit_tls {
+        (client, server) = upgrade(client, server, shared, &mut log).await?;
+    }
+
+    let mut parser: Box<dyn MailParser> = match config.protocol {
+        MailProtocol::Smtp => Box::new(SmtpParser::new(config.max_capture_bytes)),
+        MailProtocol::Imap => Box::new(ImapParser::new(config.max_capture_bytes)),
+    };
+    loop {
+        match relay(&mut client, &mut server, parser.as_mut(), &log).await? {
+            RelayEnd::Closed => return Ok(()),
+            RelayEnd::StartTls => {
+                (client, server) = upgrade(client, server, shared, &mut log).await?;
+                debug!("Session {} upgraded with STARTTLS", log.session_id);
+            }
+        }
+    }
+}
+
+async fn upgrade(
+    client: Box<dyn MailIo>,
+    server: Box<dyn MailIo>,
+    shared: &ListenerShared,
+    log: &mut SessionLog,
+) -> std::io::Result<(Box<dyn MailIo>, Box<dyn MailIo>)> {
+    let server = shared.connector.connect(shared.server_name.clone(), server).await?;
+    let (_, connection) = server.get_ref();
+    log.tls = Some(TlsDetails {
+        version: connection
+            .protocol_version()
+            .map(|v| format!("{:?}", v))
+            .unwrap_or_default(),
+        cipher: connection
+            .negotiated_cipher_suite()
+            .map(|s| format!("{:?}", s.suite()))
+            .unwrap_or_default(),
+    });
+    let client = shared.acceptor.accept(client).await?;
+    Ok((Box::new(client), Box::new(server)))
+}
+
+/// Relay both directions until either side closes or a STARTTLS is accepted
+async fn relay(
+    client: &mut Box<dyn MailIo>,
+    server: &mut Box<dyn MailIo>,
+    parser: &mut dyn MailParser,
+    log: &SessionLog,
+) -> std::io::Result<RelayEnd> {
+    let mut client_buf = vec![0u8; 16 * 1024];
+    let mut server_buf = vec![0u8; 16 * 1024];
+    let mut captured = Vec::new();
+
+    loop {
+        tokio::select! {
+            n = client.read(&mut client_buf) => {
+                let n = n?;
+                if n == 0 {
+                    let _ = server.shutdown().await;
+                    return Ok(RelayEnd::Closed);
+                }
+                server.write_all(&client_buf[..n]).await?;
+                parser.client_data(&client_buf[..n], &mut captured);
+            }
+            n = server.read(&mut server_buf) => {
+                let n = n?;
+                if n == 0 {
+                    let _ = client.shutdown().await;
+                    return Ok(RelayEnd::Closed);
+                }
+                client.write_all(&server_buf[..n]).await?;
+                parser.server_data(&server_buf[..n], &mut captured);
+            }
+        }
+
+        let mut starttls = false;
+        for item in captured.drain(..) {
+            match item {
+                Captured::StartTls => starttls = true,
+                item => log.emit(item),
+            }
+        }
+        if starttls {
+            client.flush().await?;
+            return Ok(RelayEnd::StartTls);
+        }
+    }
+}
+
+/// What the parsers extract from the byte streams
+#[derive(Debug, PartialEq)]
+enum Captured {
+    Command { id: String, command: String, body: Vec<u8> },
+    Reply { id: String, status: i32, body: Vec<u8> },
+    /// Server accepted STARTTLS; both legs upgrade before the next byte
+    StartTls,
+}
+
+struct SessionLog {
+    sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+    protocol: MailProtocol,
+    url: String,
+    session_id: String,
+    client_addr: String,
+    tls: Option<TlsDetails>,
+}
+
+impl SessionLog {
+    fn headers(&self) -> HttpHeaders {
+        HttpHeaders {
+            headers: HashMap::from([
+                ("x-mail-session".to_string(), self.session_id.clone()),
+                ("x-mail-client".to_string(), self.client_addr.clone()),
+            ]),
+        }
+    }
+
+    fn emit(&self, item: Captured) {
+        let Some(sender) = &self.sender else { return };
+        let event = match item {
+            Captured::Command { id, command, body } => TrafficEvent {
+                request_id: id,
+                event: Some(traffic_event::Event::Request(HttpRequestData {
+                    method: command,
+                    url: self.url.clone(),
+                    headers: Some(self.headers()),
+                    body: body.into(),
+                    tls: self.tls.clone(),
+                })),
+            },
+            Captured::Reply { id, status, body } => TrafficEvent {
+                request_id: id,
+                event: Some(traffic_event::Event::Response(HttpResponseData {
+                    status_code: status,
+                    headers: Some(self.headers()),
+                    body: body.into(),
+                    tls: self.tls.clone(),
+                    timing: None,
+                    protocol: self.protocol.name().to_string(),
+                })),
+            },
+            Captured::StartTls => return,
+        };
+        let _ = sender.try_send(event);
+    }
+}
+
+trait MailParser: Send {
+    fn client_data(&mut self, data: &[u8], out: &mut Vec<Captured>);
+    fn server_data(&mut self, data: &[u8], out: &mut Vec<Captured>);
+}
+
+/// A command awaiting its reply
+struct Pending {
+    id: String,
+    command: String,
+    /// IMAP tag
+    tag: String,
+}
+
+impl Pending {
+    fn emit(command: &str, tag: &str, body: Vec<u8>, out: &mut Vec<Captured>) -> Self {
+        let id = Uuid::new_v4().to_string();
+        out.push(Captured::Command {
+            id: id.clone(),
+            command: command.to_string(),
+            body,
+        });
+        Self {
+            id,
+            command: command.to_string(),
+            tag: tag.to_string(),
+        }
+    }
+}
+
+/// Take one line, terminator included, if complete
+fn take_line(buf: &mut Vec<u8>) -> Option<Vec<u8>> {
+    let end = buf.iter().position(|&b| b == b'\n')? + 1;
+    Some(buf.drain(..end).collect())
+}
+
+/// Take up to `n` bytes
+fn take_bytes(buf: &mut Vec<u8>, n: usize) -> Vec<u8> {
+    buf.drain(..n.min(buf.len())).collect()
+}
+
+fn push_capped(buf: &mut Vec<u8>, data: &[u8], max: usize) {
+    let room = max.saturating_sub(buf.len());
+    buf.extend_from_slice(&data[..data.len().min(room)]);
+}
+
+fn words(line: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(line)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// SMTP: replies pair with commands in order (pipelining included)
+struct SmtpParser {
+    max: usize,
+    client: Vec<u8>,
+    server: Vec<u8>,
+    pending: VecDeque<Pending>,
+    reply: Vec<u8>,
+    /// Message body after a 354, until the lone "."
+    data: Option<Vec<u8>>,
+    /// BDAT chunk being read: body so far and bytes left
+    bdat: Option<(Vec<u8>, usize)>,
+    /// Next client line answers an AUTH challenge
+    auth_response: bool,
+}
+
+impl SmtpParser {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            client: Vec::new(),
+            server: Vec::new(),
+            pending: VecDeque::new(),
+            reply: Vec::new(),
+            data: None,
+            bdat: None,
+            auth_response: false,
+        }
+    }
+}
+
+impl MailParser for SmtpParser {
+    fn client_data(&mut self, data: &[u8], out: &mut Vec<Captured>) {
+        self.client.extend_from_slice(data);
+        loop {
+            if let Some((mut body, remaining)) = self.bdat.take() {
+                if self.client.is_empty() && remaining > 0 {
+                    self.bdat = Some((body, remaining));
+                    break;
+                }
+                let chunk = take_bytes(&mut self.client, remaining);
+                push_capped(&mut body, &chunk, self.max);
+                if remaining == chunk.len() {
+                    let pending = Pending::emit("BDAT", "", body, out);
+                    self.pending.push_back(pending);
+                } else {
+                    self.bdat = Some((body, remaining - chunk.len()));
+                }
+                continue;
+            }
+
+            let Some(line) = take_line(&mut self.client) else { break };
+
+            if let Some(mut body) = self.data.take() {
+                if line == b".\r\n" || line == b".\n" {
+                    let pending = Pending::emit("MESSAGE", "", body, out);
+                    self.pending.push_back(pending);
+                } else {
+                    push_capped(&mut body, &line, self.max);
+                    self.data = Some(body);
+                }
+                continue;
+            }
+
+            if std::mem::take(&mut self.auth_response) {
+                let pending = Pending::emit("AUTH", "", line, out);
+                self.pending.push_back(pending);
+                continue;
+            }
+
+            let words = words(&line);
+            let command = words.first().map(|w| w.to_ascii_uppercase()).unwrap_or_default();
+            if command == "BDAT" {
+                let size = words.get(1).and_then(|n| n.parse::<usize>().ok()).unwrap_or(0);
+                let mut body = Vec::new();
+                push_capped(&mut body, &line, self.max);
+                self.bdat = Some((body, size));
+                continue;
+            }
+            let pending = Pending::emit(&command, "", line, out);
+            self.pending.push_back(pending);
+        }
+    }
+
+    fn server_data(&mut self, data: &[u8], out: &mut Vec<Captured>) {
+        self.server.extend_from_slice(data);
+        while let Some(line) = take_line(&mut self.server) {
+            push_capped(&mut self.reply, &line, self.max);
+            // "250-..." continues a multi-line reply
+            if line.get(3) == Some(&b'-') {
+                continue;
+            }
+
+            let status = std::str::from_utf8(&line[..line.len().min(3)])
+                .ok()
+                .and_then(|code| code.parse::<i32>().ok())
+                .unwrap_or(0);
+            let pending = self
+                .pending
+                .pop_front()
+                .unwrap_or_else(|| Pending::emit("BANNER", "", Vec::new(), out));
+            out.push(Captured::Reply {
+                id: pending.id,
+                status,
+                body: std::mem::take(&mut self.reply),
+            });
+
+            match (pending.command.as_str(), status) {
+                ("DATA", 354) => self.data = Some(Vec::new()),
+                ("STARTTLS", 220) => out.push(Captured::StartTls),
+                (_, 334) => self.auth_response = true,
+                _ => {}
+            }
+        }
+    }
+}
+
+/// IMAP: tagged completions close commands; untagged data goes to the
+/// command in progress
+struct ImapParser {
+    max: usize,
+    client: Vec<u8>,
+    server: Vec<u8>,
+    pending: VecDeque<Pending>,
+    /// Command being read, with literal bytes still to come
+    command: Vec<u8>,
+    command_literal: usize,
+    response: Vec<u8>,
+    response_literal: usize,
+    greeted: bool,
+    /// Next client line answers a "+" continuation (AUTHENTICATE, IDLE)
+    continuation: bool,
+}
+
+impl ImapParser {
+    fn new(max: usize) -> Self {
+        Self {
+            max,
+            client: Vec::new(),
+            server: Vec::new(),
+            pending: VecDeque::new(),
+            command: Vec::new(),
+            command_literal: 0,
+            response: Vec::new(),
+            response_literal: 0,
+            greeted: false,
+            continuation: false,
+        }
+    }
+
+    fn finish_command(&mut self, out: &mut Vec<Captured>) {
+        let raw = std::mem::take(&mut self.command);
+        if std::mem::take(&mut self.continuation) {
+            // Answers to a continuation get no completion of their own
+            Pending::emit("CONTINUE", "", raw, out);
+            return;
+        }
+
+        let words = words(&raw);
+        let tag = words.first().cloned().unwrap_or_default();
+        let mut command = words.get(1).map(|w| w.to_ascii_uppercase()).unwrap_or_default();
+        if command == "UID" {
+            if let Some(sub) = words.get(2) {
+                command = format!("UID {}", sub.to_ascii_uppercase());
+            }
+        }
+        let pending = Pending::emit(&command, &tag, raw, out);
+        self.pending.push_back(pending);
+    }
+}
+
+/// Length of a literal announced at the end of a line: `{n}`, `{n+}` or `~{n}`
+fn literal_len(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"\n")?;
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = line.strip_suffix(b"}")?;
+    let open = line.iter().rposition(|&b| b == b'{')?;
+    let digits = &line[open + 1..];
+    let digits = digits.strip_suffix(b"+").unwrap_or(digits);
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+fn imap_status(word: &str) -> i32 {
+    match word.to_ascii_uppercase().as_str() {
+        "OK" | "PREAUTH" => 200,
+        "NO" => 400,
+        "BYE" => 503,
+        _ => 500,
+    }
+}
+
+impl MailParser for ImapParser {
+    fn client_data(&mut self, data: &[u8], out: &mut Vec<Captured>) {
+        self.client.extend_from_slice(data);
+        loop {
+            if self.command_literal > 0 {
+                if self.client.is_empty() {
+                    break;
+                }
+                let chunk = take_bytes(&mut self.client, self.command_literal);
+                self.command_literal -= chunk.len();
+                push_capped(&mut self.command, &chunk, self.max);
+                continue;
+            }
+
+            let Some(line) = take_line(&mut self.client) else { break };
+            push_capped(&mut self.command, &line, self.max);
+            match literal_len(&line) {
+                Some(n) if n > 0 => self.command_literal = n,
+                _ => self.finish_command(out),
+            }
+        }
+    }
+
+    fn server_data(&mut self, data: &[u8], out: &mut Vec<Captured>) {
+        self.server.extend_from_slice(data);
+        loop {
+            if self.response_literal > 0 {
+                if self.server.is_empty() {
+                    break;
+                }
+                let chunk = take_bytes(&mut self.server, self.response_literal);
+                self.response_literal -= chunk.len();
+                push_capped(&mut self.response, &chunk, self.max);
+                continue;
+            }
+
+            let Some(line) = take_line(&mut self.server) else { break };
+            push_capped(&mut self.response, &line, self.max);
+            if let Some(n) = literal_len(&line) {
+                self.response_literal = n;
+                continue;
+            }
+
+            let words = words(&line);
+            let first = words.first().map(String::as_str).unwrap_or("");
+            match first {
+                "*" if !self.greeted => {
+                    self.greeted = true;
+                    let pending = Pending::emit("BANNER", "", Vec::new(), out);
+                    out.push(Captured::Reply {
+                        id: pending.id,
+                        status: imap_status(words.get(1).map(String::as_str).unwrap_or("")),
+                        body: std::mem::take(&mut self.response),
+                    });
+                }
+                "*" => {
+                    // Untagged data for the command in progress
+                }
+                "+" => {
+                    let waiting = self
+                        .pending
+                        .back()
+                        .map(|p| p.command == "AUTHENTICATE" || p.command == "IDLE")
+                        .unwrap_or(false);
+                    if waiting {
+                        self.continuation = true;
+                    }
+                }
+                tag => {
+                    let Some(index) = self.pending.iter().position(|p| p.tag == tag) else {
+                        continue;
+                    };
+                    let pending = self.pending.remove(index).expect("index was just found");
+                    let status = imap_status(words.get(1).map(String::as_str).unwrap_or(""));
+                    out.push(Captured::Reply {
+                        id: pending.id,
+                        status,
+                        body: std::mem::take(&mut self.response),
+                    });
+                    self.continuation = false;
+                    if pending.command == "STARTTLS" && status == 200 {
+                        out.push(Captured::StartTls);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(captured: &[Captured]) -> Vec<(String, String)> {
+        captured
+            .iter()
+            .filter_map(|c| match c {
+                Captured::Command { command, body, .. } => {
+                    Some((command.clone(), String::from_utf8_lossy(body).to_string()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn replies(captured: &[Captured]) -> Vec<i32> {
+        captured
+            .iter()
+            .filter_map(|c| match c {
+                Captured::Reply { status, .. } => Some(*status),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_smtp_session() {
+        let mut parser = SmtpParser::new(1024);
+        let mut out = Vec::new();
+
+        parser.server_data(b"220 mx.example.com ESMTP\r\n", &mut out);
+        parser.client_data(b"EHLO client\r\n", &mut out);
+        parser.server_data(b"250-mx.example.com\r\n250-PIPELINING\r\n250 STARTTLS\r\n", &mut out);
+        parser.client_data(b"MAIL FROM:<a@x.io>\r\nRCPT TO:<b@y.io>\r\nDATA\r\n", &mut out);
+        parser.server_data(b"250 OK\r\n250 OK\r\n354 Go ahead\r\n", &mut out);
+        parser.client_data(b"Subject: reset\r\n\r\nhttps://x.io/reset?t=1\r\n", &mut out);
+        parser.client_data(b".\r\nQUIT\r\n", &mut out);
+        parser.server_data(b"250 Queued\r\n221 Bye\r\n", &mut out);
+
+        let commands = commands(&out);
+        let names: Vec<&str> = commands.iter().map(|(c, _)| c.as_str()).collect();
+        assert_eq!(names, ["BANNER", "EHLO", "MAIL", "RCPT", "DATA", "MESSAGE", "QUIT"]);
+        assert_eq!(commands[5].1, "Subject: reset\r\n\r\nhttps://x.io/reset?t=1\r\n");
+        assert_eq!(replies(&out), [220, 250, 250, 250, 354, 250, 221]);
+        assert!(!out.contains(&Captured::StartTls));
+    }
+
+    #[test]
+    fn test_smtp_starttls_and_auth() {
+        let mut parser = SmtpParser::new(1024);
+        let mut out = Vec::new();
+
+        parser.client_data(b"STARTTLS\r\n", &mut out);
+        parser.server_data(b"220 Ready to start TLS\r\n", &mut out);
+        assert_eq!(out.last(), Some(&Captured::StartTls));
+
+        out.clear();
+        parser.client_data(b"AUTH LOGIN\r\n", &mut out);
+        parser.server_data(b"334 VXNlcm5hbWU6\r\n", &mut out);
+        parser.client_data(b"dXNlcg==\r\n", &mut out);
+        parser.server_data(b"334 UGFzc3dvcmQ6\r\n", &mut out);
+        parser.client_data(b"cGFzcw==\r\n", &mut out);
+        parser.server_data(b"235 Authenticated\r\n", &mut out);
+
+        let commands = commands(&out);
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[1], ("AUTH".to_string(), "dXNlcg==\r\n".to_string()));
+        assert_eq!(replies(&out), [334, 334, 235]);
+    }
+
+    #[test]
+    fn test_smtp_bdat_chunk() {
+        let mut parser = SmtpParser::new(1024);
+        let mut out = Vec::new();
+
+        // The chunk contains what would otherwise parse as a command
+        parser.client_data(b"BDAT 12 LAST\r\nQUIT\r\n", &mut out);
+        parser.client_data(b"line\r\n", &mut out);
+        parser.server_data(b"250 OK\r\n", &mut out);
+
+        let commands = commands(&out);
+        assert_eq!(commands, [("BDAT".to_string(), "BDAT 12 LAST\r\nQUIT\r\nline\r\n".to_string())]);
+        assert_eq!(replies(&out), [250]);
+    }
+
+    #[test]
+    fn test_imap_session() {
+        let mut parser = ImapParser::new(1024);
+        let mut out = Vec::new();
+
+        parser.server_data(b"* OK IMAP4rev1 ready\r\n", &mut out);
+        parser.client_data(b"a1 LOGIN {4}\r\n", &mut out);
+        parser.server_data(b"+ go ahead\r\n", &mut out);
+        parser.client_data(b"user secret\r\n", &mut out);
+        parser.server_data(b"a1 OK logged in\r\n", &mut out);
+        parser.client_data(b"a2 UID FETCH 1 BODY[]\r\n", &mut out);
+        // The literal holds a line that looks like a tagged completion
+        parser.server_data(b"* 1 FETCH (BODY[] {12}\r\na2 NO fake\r\n)\r\n", &mut out);
+        parser.server_data(b"a2 OK done\r\n", &mut out);
+        parser.client_data(b"a3 STARTTLS\r\n", &mut out);
+        parser.server_data(b"a3 OK begin TLS\r\n", &mut out);
+
+        let commands = commands(&out);
+        let names: Vec<&str> = commands.iter().map(|(c, _)| c.as_str()).collect();
+        assert_eq!(names, ["BANNER", "LOGIN", "UID FETCH", "STARTTLS"]);
+        assert_eq!(commands[1].1, "a1 LOGIN {4}\r\nuser secret\r\n");
+        assert_eq!(replies(&out), [200, 200, 200, 200]);
+        assert_eq!(out.last(), Some(&Captured::StartTls));
+
+        let fetch_reply = out
+            .iter()
+            .filter_map(|c| match c {
+                Captured::Reply { body, .. } => Some(String::from_utf8_lossy(body).to_string()),
+                _ => None,
+            })
+            .nth(2)
+            .unwrap();
+        assert!(fetch_reply.contains("a2 NO fake") && fetch_reply.ends_with("a2 OK done\r\n"));
+    }
+
+    #[test]
+    fn test_imap_idle_continuation() {
+        let mut parser = ImapParser::new(1024);
+        let mut out = Vec::new();
+
+        parser.server_data(b"* OK ready\r\n", &mut out);
+        parser.client_data(b"t1 IDLE\r\n", &mut out);
+        parser.server_data(b"+ idling\r\n* 3 EXISTS\r\n", &mut out);
+        parser.client_data(b"DONE\r\n", &mut out);
+        parser.server_data(b"t1 NO too long\r\n", &mut out);
+
+        let names: Vec<String> = commands(&out).into_iter().map(|(c, _)| c).collect();
+        assert_eq!(names, ["BANNER", "IDLE", "CONTINUE"]);
+        assert_eq!(replies(&out), [200, 400]);
+    }
+
+    #[test]
+    fn test_literal_len() {
+        assert_eq!(literal_len(b"a1 LOGIN {4}\r\n"), Some(4));
+        assert_eq!(literal_len(b"a1 APPEND INBOX {310+}\r\n"), Some(310));
+        assert_eq!(literal_len(b"* 1 FETCH (BINARY[] ~{8}\r\n"), Some(8));
+        assert_eq!(literal_len(b"a1 OK done\r\n"), None);
+        assert_eq!(literal_len(b"a1 SEARCH {x}\r\n"), None);
+    }
+}
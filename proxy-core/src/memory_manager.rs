@@ -3,6 +3,7 @@
 //! This module provides memory tracking and enforcement for concurrent body captures
 //! to prevent excessive memory usage and implement backpressure mechanisms.
 
+use crate::buffer_pool::{BufferPool, BufferPoolStats};
 use crate::error::BodyCaptureError;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -20,6 +21,8 @@ pub struct MemoryManager {
     semaphore: Arc<Semaphore>,
     /// Maximum number of concurrent body captures allowed
     max_concurrent_captures: usize,
+    /// Reusable buffers for captured bodies
+    buffer_pool: Arc<BufferPool>,
 }
 
 impl MemoryManager {
@@ -35,6 +38,8 @@ impl MemoryManager {
             memory_limit,
             semaphore: Arc::new(Semaphore::new(max_concurrent_captures)),
             max_concurrent_captures,
+            // One idle buffer per capture slot covers steady-state reuse
+            buffer_pool: Arc::new(BufferPool::with_max_pooled(max_concurrent_captures)),
         }
     }
 
@@ -53,6 +58,11 @@ impl MemoryManager {
         self.memory_limit.saturating_sub(self.current_usage())
     }
 
+    /// Get the buffer pool used for captured bodies
+    pub fn buffer_pool(&self) -> &BufferPool {
+        &self.buffer_pool
+    }
+
     /// Get number of available concurrent capture slots
     pub fn available_permits(&self) -> usize {
         self.semaphore.available_permits()
@@ -156,6 +166,7 @@ impl MemoryManager {
             usage_percent,
             available_permits: self.available_permits(),
             max_concurrent_captures: self.max_concurrent_captures,
+            buffer_pool: self.buffer_pool.stats(),
         }
     }
 }
@@ -214,18 +225,23 @@ pub struct MemoryStats {
     pub available_permits: usize,
     /// Maximum number of concurrent captures allowed
     pub max_concurrent_captures: usize,
+    /// Body buffer pool statistics
+    pub buffer_pool: BufferPoolStats,
 }
 
 impl std::fmt::Display for MemoryStats {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Memory: {}/{} bytes ({:.1}%), Permits: {}/{}",
+            "Memory: {}/{} bytes ({:.1}%), Permits: {}/{}, Buffer pool: {}/{} idle ({:.1}% hits)",
             self.current_usage,
             self.memory_limit,
             self.usage_percent,
             self.available_permits,
-            self.max_concurrent_captures
+            self.max_concurrent_captures,
+            self.buffer_pool.pooled_buffers,
+            self.buffer_pool.max_pooled,
+            self.buffer_pool.hit_rate()
         )
     }
 }
@@ -292,6 +308,21 @@ mod tests {
         assert_eq!(stats.usage_percent, 30.0);
         assert_eq!(stats.available_permits, 3);
         assert_eq!(stats.max_concurrent_captures, 3);
+        assert_eq!(stats.buffer_pool.max_pooled, 3);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_pool_stats_exposed() {
+        let manager = MemoryManager::new(1000, 2);
+
+        let buf = manager.buffer_pool().acquire();
+        manager.buffer_pool().release(buf);
+        let _buf = manager.buffer_pool().acquire();
+
+        let stats = manager.get_stats();
+        assert_eq!(stats.buffer_pool.hits, 1);
+        assert_eq!(stats.buffer_pool.misses, 1);
+        assert_eq!(stats.buffer_pool.hit_rate(), 50.0);
     }
 
     #[tokio::test]
@@ -0,0 +1,184 @@
+//! Automatic TLS pass-through for hosts whose interception keeps failing
+//!
+//! Some clients never accept the proxy certificate for a host (pinning) and
+//! some servers reject the proxy's client hello. Rather than every connection
+//! to such a host failing, the host is tunnelled without interception for a
+//! while once it has failed often enough. Entries expire on their own and can
+//! be cleared from the admin API after review.
+
+use crate::config::MitmFallbackConfig;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// A host in temporary pass-through
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FallbackEntry {
+    pub host: String,
+    /// Failures that triggered the pass-through
+    pub failures: u32,
+    /// Last failure reason
+    pub reason: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Debug)]
+struct Failures {
+    count: u32,
+    first: Instant,
+}
+
+#[derive(Debug)]
+struct Bypass {
+    failures: u32,
+    reason: String,
+    until: Instant,
+}
+
+/// Interception failures per host and the hosts currently passed through
+#[derive(Debug, Clone, Default)]
+pub struct MitmFallback {
+    config: MitmFallbackConfig,
+    failures: Arc<DashMap<String, Failures>>,
+    bypassed: Arc<DashMap<String, Bypass>>,
+}
+
+impl MitmFallback {
+    pub fn new(config: MitmFallbackConfig) -> Self {
+        Self {
+            config,
+            failures: Arc::new(DashMap::new()),
+            bypassed: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Count an interception failure for `host`. Returns true when this
+    /// failure moved the host to pass-through.
+    pub fn record_failure(&self, host: &str, reason: &str) -> bool {
+        if self.config.failure_threshold == 0 || host.is_empty() || self.is_bypassed(host) {
+            return false;
+        }
+        let window = Duration::from_secs(self.config.window_secs);
+        let count = {
+            let mut entry = self.failures.entry(host.to_string()).or_insert(Failures {
+                count: 0,
+                first: Instant::now(),
+            });
+            if entry.first.elapsed() > window {
+                *entry = Failures { count: 0, first: Instant::now() };
+            }
+            entry.count += 1;
+            entry.count
+        };
+        if count < self.config.failure_threshold {
+            return false;
+        }
+
+        self.failures.remove(host);
+        warn!(
+            "Interception of {} failed {} times, tunnelling it without interception for {}s",
+            host, count, self.config.duration_secs
+        );
+        self.bypassed.insert(
+            host.to_string(),
+            Bypass {
+                failures: count,
+                reason: reason.to_string(),
+                until: Instant::now() + Duration::from_secs(self.config.duration_secs),
+            },
+        );
+        true
+    }
+
+    /// Whether `host` is currently tunnelled without interception
+    pub fn is_bypassed(&self, host: &str) -> bool {
+        let expired = match self.bypassed.get(host) {
+            Some(bypass) => bypass.until <= Instant::now(),
+            None => return false,
+        };
+        if expired {
+            self.bypassed.remove(host);
+        }
+        !expired
+    }
+
+    /// Hosts currently passed through, for review
+    pub fn entries(&self) -> Vec<FallbackEntry> {
+        let now = Instant::now();
+        self.bypassed.retain(|_, bypass| bypass.until > now);
+        let mut entries: Vec<FallbackEntry> = self
+            .bypassed
+            .iter()
+            .map(|entry| FallbackEntry {
+                host: entry.key().clone(),
+                failures: entry.failures,
+                reason: entry.reason.clone(),
+                expires_in_secs: entry.until.saturating_duration_since(now).as_secs(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.host.cmp(&b.host));
+        entries
+    }
+
+    /// Intercept `host` again. Returns false if it was not passed through.
+    pub fn clear(&self, host: &str) -> bool {
+        self.failures.remove(host);
+        self.bypassed.remove(host).is_some()
+    }
+}
+
+/// Host part of a `host:port` tunnel target or a request URL
+pub fn target_host(target: &str) -> &str {
+    let rest = target.split_once("://").map_or(target, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+    if let Some(v6) = authority.strip_prefix('[') {
+        return v6.split(']').next().unwrap_or_default();
+    }
+    authority.split(':').next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_repeated_failures_move_host_to_pass_through() {
+        let fallback = MitmFallback::new(MitmFallbackConfig {
+            failure_threshold: 2,
+            window_secs: 60,
+            duration_secs: 600,
+        });
+
+        assert!(!fallback.record_failure("pinned.test", "no request"));
+        assert!(!fallback.is_bypassed("pinned.test"));
+        assert!(fallback.record_failure("pinned.test", "no request"));
+        assert!(fallback.clone().is_bypassed("pinned.test"));
+        // Failures while passed through are not counted again
+        assert!(!fallback.record_failure("pinned.test", "no request"));
+
+        let entries = fallback.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].host, "pinned.test");
+        assert_eq!(entries[0].failures, 2);
+        assert!(entries[0].expires_in_secs > 590);
+
+        assert!(fallback.clear("pinned.test"));
+        assert!(!fallback.is_bypassed("pinned.test"));
+        assert!(!fallback.clear("pinned.test"));
+
+        let disabled = MitmFallback::new(MitmFallbackConfig { failure_threshold: 0, ..Default::default() });
+        assert!(!disabled.record_failure("pinned.test", "no request"));
+    }
+
+    #[test]
+    fn test_target_host() {
+        assert_eq!(target_host("pinned.test:443"), "pinned.test");
+        assert_eq!(target_host("https://app.test/login?next=/"), "app.test");
+        assert_eq!(target_host("https://user@app.test:8443/"), "app.test");
+        assert_eq!(target_host("[::1]:443"), "::1");
+        assert_eq!(target_host("plain.test"), "plain.test");
+    }
+}
@@ -0,0 +1,339 @@
+//! NTLM Messages
+//!
+//! The client side of NTLMv2 over HTTP (MS-NLMP): a NEGOTIATE message opens
+//! the handshake, the server's CHALLENGE is answered with an AUTHENTICATE
+//! message carrying the NTLMv2 and LMv2 responses. No session key is
+//! exchanged; HTTP authentication needs none. MD4, which NTOWFv2 hashes the
+//! password with, is implemented here (RFC 1320).
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const NEGOTIATE_OEM: u32 = 0x0000_0002;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | NEGOTIATE_OEM
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+/// AV_PAIR ids of the CHALLENGE's target information
+const AV_EOL: u16 = 0;
+const AV_TIMESTAMP: u16 = 7;
+
+/// Seconds between 1601-01-01, the FILETIME epoch, and the Unix epoch
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+
+/// The NEGOTIATE message opening a handshake
+pub fn negotiate_message() -> Vec<u8> {
+    let mut message = Vec::with_capacity(32);
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    // Empty domain and workstation fields
+    message.extend_from_slice(&[0; 16]);
+    message
+}
+
+/// A server's CHALLENGE message
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    flags: u32,
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+impl Challenge {
+    /// `None` if `message` is not a well-formed CHALLENGE
+    pub fn parse(message: &[u8]) -> Option<Self> {
+        if message.len() < 32 || &message[..8] != SIGNATURE || read_u32(message, 8)? != 2 {
+            return None;
+        }
+        let target_info = if message.len() >= 48 {
+            let length = read_u16(message, 40)? as usize;
+            let offset = read_u32(message, 44)? as usize;
+            message.get(offset..offset.checked_add(length)?)?.to_vec()
+        } else {
+            Vec::new()
+        };
+        Some(Self {
+            flags: read_u32(message, 20)?,
+            server_challenge: message[24..32].try_into().ok()?,
+            target_info,
+        })
+    }
+
+    /// The server's time from the target information, if it sent one
+    fn timestamp(&self) -> Option<u64> {
+        let mut pairs = self.target_info.as_slice();
+        while pairs.len() >= 4 {
+            let id = read_u16(pairs, 0)?;
+            let length = read_u16(pairs, 2)? as usize;
+            let value = pairs.get(4..4 + length)?;
+            match id {
+                AV_EOL => return None,
+                AV_TIMESTAMP => return Some(u64::from_le_bytes(value.try_into().ok()?)),
+                _ => pairs = &pairs[4 + length..],
+            }
+        }
+        None
+    }
+}
+
+/// The AUTHENTICATE message answering `challenge`. `client_challenge` must
+/// be random; `now` is the current time as a FILETIME and is only used when
+/// the server sent no time of its own.
+pub fn authenticate_message(
+    challenge: &Challenge,
+    domain: &str,
+    username: &str,
+    password: &str,
+    client_challenge: [u8; 8],
+    now: u64,
+) -> Vec<u8> {
+    let key = nt_owf_v2(domain, username, password);
+    let server_timestamp = challenge.timestamp();
+    let timestamp = server_timestamp.unwrap_or(now);
+    let nt_response =
+        ntlmv2_response(&key, &challenge.server_challenge, &client_challenge, timestamp, &challenge.target_info);
+    // With the server's time in the target information the LMv2 response is
+    // omitted (MS-NLMP 3.1.5.1.2)
+    let lm_response = match server_timestamp {
+        Some(_) => vec![0; 24],
+        None => lmv2_response(&key, &challenge.server_challenge, &client_challenge),
+    };
+
+    let flags = (challenge.flags & NEGOTIATE_FLAGS & !NEGOTIATE_OEM) | NEGOTIATE_UNICODE | NEGOTIATE_NTLM;
+    let domain = utf16le(domain);
+    let username = utf16le(username);
+    // Payload order: domain, user, workstation (empty), LM, NT, session key (empty)
+    let fields: [&[u8]; 6] = [&lm_response, &nt_response, &domain, &username, &[], &[]];
+    let payload_order = [2, 3, 4, 0, 1, 5];
+
+    let mut offsets = [0u32; 6];
+    let mut offset = 64u32;
+    for index in payload_order {
+        offsets[index] = offset;
+        offset += fields[index].len() as u32;
+    }
+
+    let mut message = Vec::with_capacity(offset as usize);
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&3u32.to_le_bytes());
+    for (field, offset) in fields.iter().zip(offsets) {
+        let length = field.len() as u16;
+        message.extend_from_slice(&length.to_le_bytes());
+        message.extend_from_slice(&length.to_le_bytes());
+        message.extend_from_slice(&offset.to_le_bytes());
+    }
+    message.extend_from_slice(&flags.to_le_bytes());
+    for index in payload_order {
+        message.extend_from_slice(fields[index]);
+    }
+    message
+}
+
+/// The current time as a FILETIME (100 ns intervals since 1601)
+pub fn filetime_now() -> u64 {
+    let since_unix = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_unix.as_secs() + FILETIME_UNIX_OFFSET) * 10_000_000 + u64::from(since_unix.subsec_nanos()) / 100
+}
+
+/// NTOWFv2: the NT hash of the password, keyed to the user and domain
+fn nt_owf_v2(domain: &str, username: &str, password: &str) -> [u8; 16] {
+    let identity = format!("{}{}", username.to_uppercase(), domain);
+    hmac_md5(&md4(&utf16le(password)), &[&utf16le(&identity)])
+}
+
+/// NTProofStr followed by the blob it was computed over
+fn ntlmv2_response(
+    key: &[u8; 16],
+    server_challenge: &[u8; 8],
+    client_challenge: &[u8; 8],
+    timestamp: u64,
+    target_info: &[u8],
+) -> Vec<u8> {
+    let mut blob = Vec::with_capacity(32 + target_info.len());
+    blob.extend_from_slice(&[1, 1, 0, 0, 0, 0, 0, 0]);
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let proof = hmac_md5(key, &[server_challenge, &blob]);
+    [proof.as_slice(), &blob].concat()
+}
+
+fn lmv2_response(key: &[u8; 16], server_challenge: &[u8; 8], client_challenge: &[u8; 8]) -> Vec<u8> {
+    let proof = hmac_md5(key, &[server_challenge, client_challenge]);
+    [proof.as_slice(), client_challenge].concat()
+}
+
+fn hmac_md5(key: &[u8], parts: &[&[u8]]) -> [u8; 16] {
+    let mut mac = <Hmac<Md5>>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+fn utf16le(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// MD4 (RFC 1320)
+fn md4(input: &[u8]) -> [u8; 16] {
+    let f = |x: u32, y: u32, z: u32| (x & y) | (!x & z);
+    let g = |x: u32, y: u32, z: u32| (x & y) | (x & z) | (y & z);
+    let h = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    let mut message = input.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((input.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    let mut state: [u32; 4] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476];
+    for block in message.chunks_exact(64) {
+        let mut x = [0u32; 16];
+        for (word, bytes) in x.iter_mut().zip(block.chunks_exact(4)) {
+            *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = state;
+
+        for i in [0, 4, 8, 12] {
+            a = a.wrapping_add(f(b, c, d)).wrapping_add(x[i]).rotate_left(3);
+            d = d.wrapping_add(f(a, b, c)).wrapping_add(x[i + 1]).rotate_left(7);
+            c = c.wrapping_add(f(d, a, b)).wrapping_add(x[i + 2]).rotate_left(11);
+            b = b.wrapping_add(f(c, d, a)).wrapping_add(x[i + 3]).rotate_left(19);
+        }
+        for i in [0, 1, 2, 3] {
+            let k = 0x5a82_7999u32;
+            a = a.wrapping_add(g(b, c, d)).wrapping_add(x[i]).wrapping_add(k).rotate_left(3);
+            d = d.wrapping_add(g(a, b, c)).wrapping_add(x[i + 4]).wrapping_add(k).rotate_left(5);
+            c = c.wrapping_add(g(d, a, b)).wrapping_add(x[i + 8]).wrapping_add(k).rotate_left(9);
+            b = b.wrapping_add(g(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(k).rotate_left(13);
+        }
+        for i in [0, 2, 1, 3] {
+            let k = 0x6ed9_eba1u32;
+            a = a.wrapping_add(h(b, c, d)).wrapping_add(x[i]).wrapping_add(k).rotate_left(3);
+            d = d.wrapping_add(h(a, b, c)).wrapping_add(x[i + 8]).wrapping_add(k).rotate_left(9);
+            c = c.wrapping_add(h(d, a, b)).wrapping_add(x[i + 4]).wrapping_add(k).rotate_left(11);
+            b = b.wrapping_add(h(c, d, a)).wrapping_add(x[i + 12]).wrapping_add(k).rotate_left(15);
+        }
+
+        for (word, value) in state.iter_mut().zip([a, b, c, d]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0u8; 16];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(state) {
+        bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Target information of the MS-NLMP 4.2.4 examples
+    const TARGET_INFO: [u8; 36] = [
+        0x02, 0x00, 0x0c, 0x00, 0x44, 0x00, 0x6f, 0x00, 0x6d, 0x00, 0x61, 0x00, 0x69, 0x00, 0x6e, 0x00, 0x01, 0x00,
+        0x0c, 0x00, 0x53, 0x00, 0x65, 0x00, 0x72, 0x00, 0x76, 0x00, 0x65, 0x00, 0x72, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_md4_vectors() {
+        // RFC 1320 appendix A.5
+        assert_eq!(hex(&md4(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex(&md4(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(hex(&md4(b"message digest")), "d9130a8164549fe818874806e1c7014b");
+        assert_eq!(
+            hex(&md4(b"12345678901234567890123456789012345678901234567890123456789012345678901234567890")),
+            "e33b4ddc9c38f2199c3e7b164fcc0536"
+        );
+    }
+
+    #[test]
+    fn test_ntlmv2_responses_match_spec_examples() {
+        // MS-NLMP 4.2.4
+        let key = nt_owf_v2("Domain", "User", "Password");
+        assert_eq!(hex(&key), "0c868a403bfd7a93a3001ef22ef02e3f");
+
+        let server = [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef];
+        let client = [0xaa; 8];
+        assert_eq!(
+            hex(&lmv2_response(&key, &server, &client)),
+            "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa"
+        );
+        let response = ntlmv2_response(&key, &server, &client, 0, &TARGET_INFO);
+        assert_eq!(hex(&response[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+        assert_eq!(response.len(), 16 + 32 + TARGET_INFO.len());
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let negotiate = negotiate_message();
+        assert_eq!(negotiate.len(), 32);
+        assert_eq!(read_u32(&negotiate, 12), Some(NEGOTIATE_FLAGS));
+
+        let mut challenge = SIGNATURE.to_vec();
+        challenge.extend_from_slice(&2u32.to_le_bytes());
+        challenge.extend_from_slice(&[0; 8]);
+        challenge.extend_from_slice(&(NEGOTIATE_UNICODE | NEGOTIATE_NTLM).to_le_bytes());
+        challenge.extend_from_slice(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+        challenge.extend_from_slice(&[0; 8]);
+        challenge.extend_from_slice(&(TARGET_INFO.len() as u16).to_le_bytes());
+        challenge.extend_from_slice(&(TARGET_INFO.len() as u16).to_le_bytes());
+        challenge.extend_from_slice(&48u32.to_le_bytes());
+        challenge.extend_from_slice(&TARGET_INFO);
+        let challenge = Challenge::parse(&challenge).unwrap();
+        assert_eq!(challenge.target_info, TARGET_INFO);
+        assert_eq!(challenge.timestamp(), None);
+        assert!(Challenge::parse(&negotiate).is_none());
+
+        let message = authenticate_message(&challenge, "Domain", "User", "Password", [0xaa; 8], 0);
+        assert_eq!(&message[..8], SIGNATURE);
+        assert_eq!(read_u32(&message, 8), Some(3));
+        let field = |at: usize| {
+            let length = read_u16(&message, at).unwrap() as usize;
+            let offset = read_u32(&message, at + 4).unwrap() as usize;
+            &message[offset..offset + length]
+        };
+        assert_eq!(hex(&field(20)[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+        assert_eq!(hex(&field(12)[..16]), "86c35097ac9cec102554764a57cccc19");
+        assert_eq!(field(28), utf16le("Domain"));
+        assert_eq!(field(36), utf16le("User"));
+        assert!(field(44).is_empty());
+    }
+}
@@ -0,0 +1,627 @@
+//! Proxy Auto-Config (PAC) Evaluation
+//!
+//! Evaluates `FindProxyForURL` from a PAC script so the agent picks the same
+//! upstream (or DIRECT) per destination as the browsers it stands in for.
+//! Scripts run in an embedded QuickJS runtime on a dedicated thread, since the
+//! runtime is not `Send`, with the standard PAC helper functions predefined.
+//! `dnsResolve` asks the project's resolver, host overrides included, like the
+//! upstream connections the script picks proxies for.
+
+use crate::{
+    dns::{CustomDnsResolver, DnsConfig},
+    error::ProxyError,
+    Result,
+};
+use rquickjs::{Context, Ctx, Function, Runtime};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::net::{IpAddr, UdpSocket};
+use std::rc::Rc;
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+/// Upper bound on a single `FindProxyForURL` call
+const EVALUATION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Memory available to the PAC runtime
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Cached decisions before the cache is reset
+const MAX_CACHED_DECISIONS: usize = 4096;
+
+/// One entry of a PAC result, in preference order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProxyChoice {
+    /// Connect to the destination directly
+    Direct,
+    /// Tunnel through an HTTP proxy
+    Http { host: String, port: u16 },
+    /// Tunnel through a SOCKS5 proxy (egress routes only; PAC SOCKS entries are skipped)
+    Socks5 { host: String, port: u16 },
+}
+
+impl std::fmt::Display for ProxyChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProxyChoice::Direct => write!(f, "DIRECT"),
+            ProxyChoice::Http { host, port } => write!(f, "PROXY {}:{}", host, port),
+            ProxyChoice::Socks5 { host, port } => write!(f, "SOCKS5 {}:{}", host, port),
+        }
+    }
+}
+
+/// Parse a `FindProxyForURL` return value such as `"PROXY a:8080; DIRECT"`.
+///
+/// Entry types the agent cannot use (SOCKS, HTTPS proxies) are skipped; an empty
+/// result means DIRECT, as in browsers.
+pub fn parse_pac_result(result: &str) -> Result<Vec<ProxyChoice>> {
+    let mut choices = Vec::new();
+    let mut skipped = Vec::new();
+
+    for entry in result.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = entry.split_whitespace();
+        let kind = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let target = parts.next();
+
+        match (kind.as_str(), target) {
+            ("DIRECT", _) => choices.push(ProxyChoice::Direct),
+            ("PROXY" | "HTTP", Some(target)) => {
+                let (host, port) = split_host_port(target, 80).ok_or_else(|| {
+                    ProxyError::Configuration(format!("Invalid PAC proxy entry: {}", entry))
+                })?;
+                choices.push(ProxyChoice::Http { host, port });
+            }
+            _ => skipped.push(entry.to_string()),
+        }
+    }
+
+    if choices.is_empty() {
+        if skipped.is_empty() {
+            return Ok(vec![ProxyChoice::Direct]);
+        }
+        return Err(ProxyError::Configuration(format!(
+            "PAC result has no supported upstream: {}",
+            skipped.join("; ")
+        )));
+    }
+
+    if !skipped.is_empty() {
+        debug!("Ignoring unsupported PAC entries: {}", skipped.join("; "));
+    }
+
+    Ok(choices)
+}
+
+fn split_host_port(target: &str, default_port: u16) -> Option<(String, u16)> {
+    // Bracketed IPv6: [::1]:3128
+    if let Some(rest) = target.strip_prefix('[') {
+        let (host, tail) = rest.split_once(']')?;
+        let port = match tail.strip_prefix(':') {
+            Some(port) => port.parse().ok()?,
+            None => default_port,
+        };
+        return Some((host.to_string(), port));
+    }
+
+    match target.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() => Some((host.to_string(), port.parse().ok()?)),
+        Some(_) => None,
+        None => Some((target.to_string(), default_port)),
+    }
+}
+
+/// Load a PAC script from an `http(s)://` URL or a local file path
+pub async fn load_pac_script(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = reqwest::get(source)
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| ProxyError::Configuration(format!("Failed to fetch PAC file {}: {}", source, e)))?;
+        response
+            .text()
+            .await
+            .map_err(|e| ProxyError::Configuration(format!("Failed to read PAC file {}: {}", source, e)))
+    } else {
+        let path = source.strip_prefix("file://").unwrap_or(source);
+        tokio::fs::read_to_string(path).await.map_err(ProxyError::Io)
+    }
+}
+
+struct PacJob {
+    url: String,
+    host: String,
+    reply: oneshot::Sender<std::result::Result<String, String>>,
+}
+
+/// Compiled PAC script with a decision cache.
+///
+/// Decisions are cached per destination host (with its scheme and port): the
+/// script sees each destination as its origin URL, so paths never split the
+/// cache.
+///
+/// Cloning is not supported; share it behind an `Arc`.
+pub struct PacEngine {
+    jobs: Mutex<mpsc::Sender<PacJob>>,
+    cache: Mutex<HashMap<String, Vec<ProxyChoice>>>,
+}
+
+impl PacEngine {
+    /// Compile the script on a dedicated evaluation thread. `dnsResolve`
+    /// looks hosts up with the given DNS settings and overrides.
+    pub fn new(script: String, dns: &DnsConfig, host_overrides: &HashMap<String, IpAddr>) -> Result<Self> {
+        // The evaluation thread gets its own resolver, driven by its own runtime
+        let resolver = CustomDnsResolver::new(dns)?.with_overrides(host_overrides);
+        let (jobs_tx, jobs_rx) = mpsc::channel::<PacJob>();
+        let (ready_tx, ready_rx) = mpsc::sync_channel::<std::result::Result<(), String>>(1);
+
+        std::thread::Builder::new()
+            .name("proxxy-pac".to_string())
+            .spawn(move || run_pac_thread(script, resolver, jobs_rx, ready_tx))
+            .map_err(ProxyError::Io)?;
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(Self {
+                jobs: Mutex::new(jobs_tx),
+                cache: Mutex::new(HashMap::new()),
+            }),
+            Ok(Err(e)) => Err(ProxyError::Configuration(format!("Invalid PAC script: {}", e))),
+            Err(_) => Err(ProxyError::Configuration(
+                "PAC evaluation thread exited during startup".to_string(),
+            )),
+        }
+    }
+
+    /// Evaluate `FindProxyForURL` for the host of a destination URL
+    pub async fn find_proxy(&self, url: &str) -> Result<Vec<ProxyChoice>> {
+        let (origin, host) = url::Url::parse(url)
+            .ok()
+            .and_then(|u| {
+                let host = u.host_str()?.trim_matches(['[', ']']).to_string();
+                Some((u.origin().ascii_serialization() + "/", host))
+            })
+            .filter(|(origin, _)| origin != "null/")
+            .ok_or_else(|| ProxyError::Configuration(format!("Cannot evaluate PAC for {}", url)))?;
+
+        if let Some(cached) = self.cache.lock().unwrap_or_else(|e| e.into_inner()).get(&origin) {
+            return Ok(cached.clone());
+        }
+
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.jobs
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .send(PacJob {
+                url: origin.clone(),
+                host,
+                reply: reply_tx,
+            })
+            .map_err(|_| ProxyError::General("PAC evaluation thread is gone".to_string()))?;
+
+        let result = reply_rx
+            .await
+            .map_err(|_| ProxyError::General("PAC evaluation thread is gone".to_string()))?
+            .map_err(|e| ProxyError::Configuration(format!("FindProxyForURL failed for {}: {}", origin, e)))?;
+
+        let choices = parse_pac_result(&result)?;
+        debug!("PAC: {} -> {}", origin, result);
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= MAX_CACHED_DECISIONS {
+            cache.clear();
+        }
+        cache.insert(origin, choices.clone());
+
+        Ok(choices)
+    }
+}
+
+fn run_pac_thread(
+    script: String,
+    resolver: CustomDnsResolver,
+    jobs: mpsc::Receiver<PacJob>,
+    ready: mpsc::SyncSender<std::result::Result<(), String>>,
+) {
+    let deadline: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+
+    let setup = (|| -> std::result::Result<(Runtime, Context), String> {
+        let lookups = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let runtime = Runtime::new().map_err(|e| e.to_string())?;
+        runtime.set_memory_limit(MEMORY_LIMIT);
+
+        let interrupt_deadline = deadline.clone();
+        runtime.set_interrupt_handler(Some(Box::new(move || {
+            interrupt_deadline
+                .get()
+                .is_some_and(|deadline| Instant::now() > deadline)
+        })));
+
+        let context = Context::full(&runtime).map_err(|e| e.to_string())?;
+        context.with(|ctx| -> std::result::Result<(), String> {
+            install_natives(&ctx, Rc::new((lookups, resolver))).map_err(|e| js_error(&ctx, e))?;
+            ctx.eval::<(), _>(PAC_UTILS).map_err(|e| js_error(&ctx, e))?;
+
+            deadline.set(Some(Instant::now() + EVALUATION_TIMEOUT));
+            let loaded = ctx.eval::<(), _>(script.as_str());
+            deadline.set(None);
+            loaded.map_err(|e| js_error(&ctx, e))?;
+
+            ctx.globals()
+                .get::<_, Function>("FindProxyForURL")
+                .map(|_| ())
+                .map_err(|_| "script does not define FindProxyForURL".to_string())
+        })?;
+
+        Ok((runtime, context))
+    })();
+
+    let (_runtime, context) = match setup {
+        Ok(engine) => {
+            let _ = ready.send(Ok(()));
+            engine
+        }
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+
+    while let Ok(job) = jobs.recv() {
+        let result = context.with(|ctx| {
+            deadline.set(Some(Instant::now() + EVALUATION_TIMEOUT));
+            let result = ctx
+                .globals()
+                .get::<_, Function>("FindProxyForURL")
+                .and_then(|f| f.call::<_, rquickjs::Value>((job.url.as_str(), job.host.as_str())))
+                .map(|value| value.as_string().and_then(|s| s.to_string().ok()).unwrap_or_default())
+                .map_err(|e| js_error(&ctx, e));
+            deadline.set(None);
+            result
+        });
+
+        if let Err(e) = &result {
+            warn!("PAC evaluation failed for {}: {}", job.url, e);
+        }
+        let _ = job.reply.send(result);
+    }
+}
+
+fn install_natives(ctx: &Ctx<'_>, dns: Rc<(tokio::runtime::Runtime, CustomDnsResolver)>) -> rquickjs::Result<()> {
+    let globals = ctx.globals();
+
+    globals.set(
+        "__pacResolve",
+        Function::new(ctx.clone(), move |host: String| -> String {
+            let (lookups, resolver) = &*dns;
+            // The interrupt handler cannot stop a lookup, so it is bounded separately
+            lookups
+                .block_on(async { tokio::time::timeout(EVALUATION_TIMEOUT, resolver.lookup(&host)).await })
+                .ok()
+                .and_then(|addrs| addrs.ok())
+                .and_then(|addrs| addrs.into_iter().find(IpAddr::is_ipv4))
+                .map(|ip| ip.to_string())
+                .unwrap_or_default()
+        })?,
+    )?;
+
+    globals.set(
+        "__pacMyIp",
+        Function::new(ctx.clone(), || -> String {
+            // Connecting a UDP socket sends nothing but selects the outbound interface
+            UdpSocket::bind("0.0.0.0:0")
+                .and_then(|socket| socket.connect("198.51.100.1:53").map(|_| socket))
+                .and_then(|socket| socket.local_addr())
+                .map(|addr| addr.ip().to_string())
+                .unwrap_or_else(|_| "127.0.0.1".to_string())
+        })?,
+    )?;
+
+    Ok(())
+}
+
+fn js_error(ctx: &Ctx<'_>, error: rquickjs::Error) -> String {
+    if error.is_exception() {
+        let thrown = ctx.catch();
+        if let Some(message) = thrown.as_exception().and_then(|e| e.message()) {
+            return message;
+        }
+        if let Some(text) = thrown.as_string().and_then(|s| s.to_string().ok()) {
+            return text;
+        }
+        return "uncaught exception (or evaluation timed out)".to_string();
+    }
+    error.to_string()
+}
+
+/// Standard PAC helper functions (as defined by Netscape/Mozilla)
+const PAC_UTILS: &str = r#"
+function dnsResolve(host) {
+    var ip = __pacResolve(host);
+    return ip === "" ? null : ip;
+}
+
+function myIpAddress() {
+    return __pacMyIp();
+}
+
+function isPlainHostName(host) {
+    return host.indexOf('.') < 0 && host.indexOf(':') < 0;
+}
+
+function dnsDomainIs(host, domain) {
+    return host.length >= domain.length &&
+        host.substring(host.length - domain.length) === domain;
+}
+
+function localHostOrDomainIs(host, hostdom) {
+    return host === hostdom || hostdom.lastIndexOf(host + '.', 0) === 0;
+}
+
+function isResolvable(host) {
+    return dnsResolve(host) !== null;
+}
+
+function __pacIpToInt(ip) {
+    var parts = ip.split('.');
+    if (parts.length !== 4) {
+        return null;
+    }
+    var value = 0;
+    for (var i = 0; i < 4; i++) {
+        var n = parseInt(parts[i], 10);
+        if (isNaN(n) || n < 0 || n > 255) {
+            return null;
+        }
+        value = value * 256 + n;
+    }
+    return value;
+}
+
+function isInNet(host, pattern, mask) {
+    var ip = /^\d+\.\d+\.\d+\.\d+$/.test(host) ? host : dnsResolve(host);
+    if (ip === null) {
+        return false;
+    }
+    var h = __pacIpToInt(ip), p = __pacIpToInt(pattern), m = __pacIpToInt(mask);
+    if (h === null || p === null || m === null) {
+        return false;
+    }
+    return ((h & m) >>> 0) === ((p & m) >>> 0);
+}
+
+function dnsDomainLevels(host) {
+    return host.split('.').length - 1;
+}
+
+function shExpMatch(str, shexp) {
+    var re = shexp.replace(/[.+^${}()|[\]\\]/g, '\\$&')
+        .replace(/\*/g, '.*')
+        .replace(/\?/g, '.');
+    return new RegExp('^' + re + '$').test(str);
+}
+
+var __pacDays = ['SUN', 'MON', 'TUE', 'WED', 'THU', 'FRI', 'SAT'];
+var __pacMonths = ['JAN', 'FEB', 'MAR', 'APR', 'MAY', 'JUN',
+    'JUL', 'AUG', 'SEP', 'OCT', 'NOV', 'DEC'];
+
+function __pacInRange(value, start, end) {
+    return start <= end ? (value >= start && value <= end) : (value >= start || value <= end);
+}
+
+function weekdayRange(wd1, wd2, gmt) {
+    var args = Array.prototype.slice.call(arguments);
+    var useGmt = args[args.length - 1] === 'GMT';
+    if (useGmt) {
+        args.pop();
+    }
+    var now = new Date();
+    var today = useGmt ? now.getUTCDay() : now.getDay();
+    var start = __pacDays.indexOf(args[0]);
+    var end = args.length > 1 ? __pacDays.indexOf(args[1]) : start;
+    if (start < 0 || end < 0) {
+        return false;
+    }
+    return __pacInRange(today, start, end);
+}
+
+function timeRange() {
+    var args = Array.prototype.slice.call(arguments);
+    var useGmt = args[args.length - 1] === 'GMT';
+    if (useGmt) {
+        args.pop();
+    }
+    var now = new Date();
+    var h = useGmt ? now.getUTCHours() : now.getHours();
+    var m = useGmt ? now.getUTCMinutes() : now.getMinutes();
+    var s = useGmt ? now.getUTCSeconds() : now.getSeconds();
+    var current = h * 3600 + m * 60 + s;
+    var n = args.map(function (v) { return parseInt(v, 10); });
+
+    switch (n.length) {
+        case 1:
+            return h === n[0];
+        case 2:
+            return __pacInRange(h, n[0], n[1]);
+        case 4:
+            return __pacInRange(current, n[0] * 3600 + n[1] * 60, n[2] * 3600 + n[3] * 60 + 59);
+        case 6:
+            return __pacInRange(current, n[0] * 3600 + n[1] * 60 + n[2], n[3] * 3600 + n[4] * 60 + n[5]);
+        default:
+            return false;
+    }
+}
+
+function dateRange() {
+    var args = Array.prototype.slice.call(arguments);
+    var useGmt = args[args.length - 1] === 'GMT';
+    if (useGmt) {
+        args.pop();
+    }
+    if (args.length === 0 || args.length > 6) {
+        return false;
+    }
+
+    function parse(list) {
+        var spec = {};
+        for (var i = 0; i < list.length; i++) {
+            var month = __pacMonths.indexOf(String(list[i]).toUpperCase());
+            var num = parseInt(list[i], 10);
+            if (month >= 0) {
+                spec.month = month;
+            } else if (!isNaN(num) && num < 32) {
+                spec.day = num;
+            } else if (!isNaN(num)) {
+                spec.year = num;
+            }
+        }
+        return spec;
+    }
+
+    var now = new Date();
+    var cur = {
+        year: useGmt ? now.getUTCFullYear() : now.getFullYear(),
+        month: useGmt ? now.getUTCMonth() : now.getMonth(),
+        day: useGmt ? now.getUTCDate() : now.getDate()
+    };
+    function key(d) {
+        return d.year * 10000 + d.month * 100 + d.day;
+    }
+
+    if (args.length === 1) {
+        var single = parse(args);
+        return (single.year === undefined || single.year === cur.year) &&
+            (single.month === undefined || single.month === cur.month) &&
+            (single.day === undefined || single.day === cur.day);
+    }
+
+    var half = args.length / 2;
+    var from = parse(args.slice(0, half));
+    var to = parse(args.slice(half));
+    var start = {
+        year: from.year !== undefined ? from.year : cur.year,
+        month: from.month !== undefined ? from.month : (from.year !== undefined ? 0 : cur.month),
+        day: from.day !== undefined ? from.day : (from.month !== undefined || from.year !== undefined ? 1 : cur.day)
+    };
+    var end = {
+        year: to.year !== undefined ? to.year : cur.year,
+        month: to.month !== undefined ? to.month : (to.year !== undefined ? 11 : cur.month),
+        day: to.day !== undefined ? to.day : (to.month !== undefined || to.year !== undefined ? 31 : cur.day)
+    };
+    if (from.year === undefined && to.year === undefined) {
+        return __pacInRange(key({ year: 0, month: cur.month, day: cur.day }),
+            key({ year: 0, month: start.month, day: start.day }),
+            key({ year: 0, month: end.month, day: end.day }));
+    }
+    return key(cur) >= key(start) && key(cur) <= key(end);
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pac_result() {
+        assert_eq!(parse_pac_result("").unwrap(), vec![ProxyChoice::Direct]);
+        assert_eq!(
+            parse_pac_result("PROXY proxy.corp:3128; SOCKS5 s:1080; DIRECT").unwrap(),
+            vec![
+                ProxyChoice::Http { host: "proxy.corp".into(), port: 3128 },
+                ProxyChoice::Direct,
+            ]
+        );
+        assert_eq!(
+            parse_pac_result("PROXY [fd00::1]:8080").unwrap(),
+            vec![ProxyChoice::Http { host: "fd00::1".into(), port: 8080 }]
+        );
+        assert!(parse_pac_result("SOCKS s:1080").is_err());
+        assert!(parse_pac_result("PROXY host:notaport").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_proxy_uses_pac_helpers() {
+        let script = r#"
+            function FindProxyForURL(url, host) {
+                if (isPlainHostName(host) || dnsDomainIs(host, ".internal.corp")) {
+                    return "DIRECT";
+                }
+                if (shExpMatch(url, "https://*.example.com*")) {
+                    return "PROXY secure-proxy:8443; DIRECT";
+                }
+                if (isInNet(host, "10.0.0.0", "255.0.0.0")) {
+                    return "PROXY lab-proxy:3128";
+                }
+                return "PROXY proxy.corp:8080";
+            }
+        "#;
+        let engine = PacEngine::new(script.to_string(), &DnsConfig::default(), &HashMap::new()).unwrap();
+
+        assert_eq!(
+            engine.find_proxy("http://intranet/").await.unwrap(),
+            vec![ProxyChoice::Direct]
+        );
+        assert_eq!(
+            engine.find_proxy("http://wiki.internal.corp/").await.unwrap(),
+            vec![ProxyChoice::Direct]
+        );
+        assert_eq!(
+            engine.find_proxy("https://api.example.com/").await.unwrap()[0],
+            ProxyChoice::Http { host: "secure-proxy".into(), port: 8443 }
+        );
+        assert_eq!(
+            engine.find_proxy("http://10.1.2.3/").await.unwrap(),
+            vec![ProxyChoice::Http { host: "lab-proxy".into(), port: 3128 }]
+        );
+        assert_eq!(
+            engine.find_proxy("http://other.org/").await.unwrap(),
+            vec![ProxyChoice::Http { host: "proxy.corp".into(), port: 8080 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_decisions_per_host_with_overrides() {
+        let script = r#"
+            var calls = 0;
+            function FindProxyForURL(url, host) {
+                calls++;
+                if (dnsResolve(host) === "10.9.8.7") {
+                    return "PROXY lab-proxy:" + (3000 + calls);
+                }
+                return "DIRECT";
+            }
+        "#;
+        let overrides = HashMap::from([("App.Test".to_string(), "10.9.8.7".parse().unwrap())]);
+        let engine = PacEngine::new(script.to_string(), &DnsConfig::default(), &overrides).unwrap();
+
+        let lab = vec![ProxyChoice::Http { host: "lab-proxy".into(), port: 3001 }];
+        assert_eq!(engine.find_proxy("https://app.test/login?next=/").await.unwrap(), lab);
+        // Same host, other path: answered from the cache
+        assert_eq!(engine.find_proxy("https://app.test/api/users").await.unwrap(), lab);
+        assert_eq!(
+            engine.find_proxy("http://app.test:8080/").await.unwrap(),
+            vec![ProxyChoice::Http { host: "lab-proxy".into(), port: 3002 }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_scripts_rejected() {
+        let engine = |script: &str| PacEngine::new(script.to_string(), &DnsConfig::default(), &HashMap::new());
+        assert!(engine("function FindProxyForURL(url, host) {").is_err());
+        assert!(engine("var x = 1;").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_runaway_script_times_out() {
+        let engine = PacEngine::new(
+            "function FindProxyForURL(url, host) { while (true) {} }".to_string(),
+            &DnsConfig::default(),
+            &HashMap::new(),
+        )
+        .unwrap();
+        assert!(engine.find_proxy("http://example.com/").await.is_err());
+    }
+}
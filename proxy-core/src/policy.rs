@@ -4,7 +4,10 @@
 //! at runtime via gRPC from the Orchestrator UI.
 
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
 
 /// Runtime Traffic Policy (Operator Configuration)
 /// This structure can be continuously updated from the UI via gRPC.
@@ -18,6 +21,10 @@ pub struct TrafficPolicy {
 
     /// Match & Replace: Automatic text replacement rules
     pub match_replace_rules: Vec<MatchReplaceRule>,
+
+    /// Egress paths (direct, upstream proxy, SSH tunnel) per scope
+    #[serde(default)]
+    pub egress: EgressPolicy,
 }
 
 impl Default for TrafficPolicy {
@@ -26,10 +33,27 @@ impl Default for TrafficPolicy {
             scope: ScopeConfig::default(),
             interception_rules: Vec::new(),
             match_replace_rules: Vec::new(),
+            egress: EgressPolicy::default(),
         }
     }
 }
 
+impl TrafficPolicy {
+    /// Egress route that applies to the given URL, if any scope claims it
+    pub fn egress_route_for(&self, url: &str) -> Option<&EgressRoute> {
+        let parsed = url::Url::parse(url).ok()?;
+        self.egress.route_for_host(parsed.host_str()?)
+    }
+
+    /// Throttle of the first enabled throttling rule matching the request
+    pub fn throttle_for(&self, req: &RequestContext) -> Option<Throttle> {
+        self.interception_rules
+            .iter()
+            .filter(|rule| rule.matches(req))
+            .find_map(|rule| rule.action.throttle())
+    }
+}
+
 /// Scope Configuration (Target Definition)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScopeConfig {
@@ -83,6 +107,256 @@ impl ScopeConfig {
     }
 }
 
+/// Timeout and keep-alive settings, with overrides for specific hosts.
+/// Slow legacy backends and fast APIs rarely fit the same budget.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionPolicy {
+    /// Applies to any host not matched by a scope override
+    pub default: ConnectionProfile,
+
+    /// Per-scope overrides, evaluated in order (first match wins)
+    pub scopes: Vec<ScopedConnectionProfile>,
+}
+
+impl ConnectionPolicy {
+    /// Find the profile for a URL, falling back to the default
+    pub fn profile_for(&self, url: &str) -> &ConnectionProfile {
+        match url::Url::parse(url) {
+            Ok(parsed) => self.profile_for_host(parsed.host_str().unwrap_or("")),
+            Err(_) => &self.default,
+        }
+    }
+
+    /// Find the profile for a hostname (IPv6 literals with or without brackets)
+    pub fn profile_for_host(&self, hostname: &str) -> &ConnectionProfile {
+        let hostname = hostname.trim_start_matches('[').trim_end_matches(']');
+        self.scopes
+            .iter()
+            .find(|scope| scope.matches_host(hostname))
+            .map(|scope| &scope.profile)
+            .unwrap_or(&self.default)
+    }
+
+    /// Check every profile for inconsistent budgets
+    pub fn validate(&self) -> Result<(), String> {
+        self.default.validate().map_err(|e| format!("default profile: {}", e))?;
+        for scope in &self.scopes {
+            scope
+                .profile
+                .validate()
+                .map_err(|e| format!("scope {:?}: {}", scope.hosts, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Connection profile bound to a set of host patterns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScopedConnectionProfile {
+    /// Host patterns this profile applies to
+    /// Examples: ["legacy.corp.local", "*.mainframe.example.com"]
+    pub hosts: Vec<String>,
+
+    pub profile: ConnectionProfile,
+}
+
+impl ScopedConnectionProfile {
+    fn matches_host(&self, hostname: &str) -> bool {
+        self.hosts
+            .iter()
+            .any(|pattern| wildmatch::WildMatch::new(pattern).matches(hostname))
+    }
+}
+
+/// Timeouts and keep-alive behaviour for upstream connections.
+/// A timeout of 0 disables that limit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionProfile {
+    /// Time allowed to establish the TCP connection
+    pub connect_timeout_ms: u64,
+
+    /// Time allowed for the upstream TLS handshake
+    pub tls_handshake_timeout_ms: u64,
+
+    /// Maximum time without any bytes exchanged with the upstream
+    pub idle_timeout_ms: u64,
+
+    /// Maximum time for the complete request/response exchange
+    pub total_timeout_ms: u64,
+
+    pub keep_alive: KeepAlivePolicy,
+
+    /// Remove permessage-deflate from WebSocket upgrades, so both legs talk
+    /// uncompressed and frames are easier to tamper with
+    pub strip_websocket_deflate: bool,
+}
+
+impl Default for ConnectionProfile {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: 10_000,
+            tls_handshake_timeout_ms: 10_000,
+            idle_timeout_ms: 30_000,
+            total_timeout_ms: 120_000,
+            keep_alive: KeepAlivePolicy::default(),
+            strip_websocket_deflate: false,
+        }
+    }
+}
+
+impl ConnectionProfile {
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        millis(self.connect_timeout_ms)
+    }
+
+    pub fn tls_handshake_timeout(&self) -> Option<Duration> {
+        millis(self.tls_handshake_timeout_ms)
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        millis(self.idle_timeout_ms)
+    }
+
+    pub fn total_timeout(&self) -> Option<Duration> {
+        millis(self.total_timeout_ms)
+    }
+
+    /// Budget for connecting plus the TLS handshake. The handshake is only
+    /// bounded while the connect phase is too.
+    pub fn setup_timeout(&self) -> Option<Duration> {
+        Some(self.connect_timeout()? + self.tls_handshake_timeout()?)
+    }
+
+    /// The total budget must leave room for the connection phases it contains
+    pub fn validate(&self) -> Result<(), String> {
+        if self.total_timeout_ms == 0 {
+            return Ok(());
+        }
+
+        let setup = self.connect_timeout_ms + self.tls_handshake_timeout_ms;
+        if setup > self.total_timeout_ms {
+            return Err(format!(
+                "connect + TLS handshake timeouts ({}ms) exceed the total timeout ({}ms)",
+                setup, self.total_timeout_ms
+            ));
+        }
+        if self.idle_timeout_ms > self.total_timeout_ms {
+            return Err(format!(
+                "idle timeout ({}ms) exceeds the total timeout ({}ms)",
+                self.idle_timeout_ms, self.total_timeout_ms
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn millis(ms: u64) -> Option<Duration> {
+    (ms > 0).then(|| Duration::from_millis(ms))
+}
+
+/// Egress routing table. Lets one agent reach an internet-facing app directly
+/// and an internal admin panel through a tunnel in the same engagement.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EgressPolicy {
+    /// Evaluated in order (first match wins); unmatched hosts fall back to
+    /// the PAC script, or go direct without one
+    pub routes: Vec<EgressRule>,
+}
+
+impl EgressPolicy {
+    /// Route for a hostname (IPv6 literals with or without brackets)
+    pub fn route_for_host(&self, hostname: &str) -> Option<&EgressRoute> {
+        let hostname = hostname.trim_start_matches('[').trim_end_matches(']');
+        self.routes
+            .iter()
+            .find(|rule| {
+                rule.hosts
+                    .iter()
+                    .any(|pattern| wildmatch::WildMatch::new(pattern).matches(hostname))
+            })
+            .map(|rule| &rule.route)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for rule in &self.routes {
+            if rule.hosts.is_empty() {
+                return Err("egress rule without host patterns".to_string());
+            }
+            match &rule.route {
+                EgressRoute::HttpProxy { host, port } | EgressRoute::Socks5 { host, port }
+                    if host.is_empty() || *port == 0 =>
+                {
+                    return Err(format!("egress route for {:?} needs a host and port", rule.hosts));
+                }
+                EgressRoute::Tunnel { name } if name.is_empty() => {
+                    return Err(format!("egress route for {:?} names no tunnel", rule.hosts));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Egress route bound to a set of host patterns
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EgressRule {
+    /// Examples: ["admin.corp.local", "10.10.*"]
+    pub hosts: Vec<String>,
+
+    pub route: EgressRoute,
+}
+
+/// How the agent reaches a destination
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EgressRoute {
+    Direct,
+    /// HTTP proxy (CONNECT for HTTPS, absolute-form for plain HTTP)
+    HttpProxy { host: String, port: u16 },
+    /// SOCKS5 proxy without authentication
+    Socks5 { host: String, port: u16 },
+    /// SSH tunnel of the agent, by name; the agent resolves it to the
+    /// tunnel's SOCKS port before the proxy starts
+    Tunnel { name: String },
+}
+
+/// Upstream connection reuse. The connection pool is shared by all hosts, so
+/// `max_idle_per_host` and `pool_idle_timeout_ms` come from the default
+/// profile; a scope can still turn reuse off for its hosts, and its idle
+/// timeout closes their pooled connections early.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeepAlivePolicy {
+    /// Reuse upstream connections between requests
+    pub enabled: bool,
+
+    /// Idle connections kept open per host
+    pub max_idle_per_host: usize,
+
+    /// How long an idle pooled connection is kept before closing it
+    pub pool_idle_timeout_ms: u64,
+}
+
+impl KeepAlivePolicy {
+    pub fn pool_idle_timeout(&self) -> Option<Duration> {
+        millis(self.pool_idle_timeout_ms)
+    }
+}
+
+impl Default for KeepAlivePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_idle_per_host: 8,
+            pool_idle_timeout_ms: 90_000,
+        }
+    }
+}
+
 /// Action to take for out-of-scope traffic
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum OutOfScopeAction {
@@ -206,6 +480,60 @@ pub enum RuleAction {
 
     /// Modify the request body
     ModifyBody { find: String, replace: String },
+
+    /// Simulate a slow network: hold the request for `latency_ms` (give or
+    /// take up to `jitter_ms`) and cap body transfer at `bandwidth_kbps`
+    /// kilobits per second (0 for no cap)
+    Throttle {
+        latency_ms: u64,
+        #[serde(default)]
+        jitter_ms: u64,
+        #[serde(default)]
+        bandwidth_kbps: u64,
+    },
+}
+
+impl RuleAction {
+    /// Throttle settings, if this is a `Throttle` action
+    pub fn throttle(&self) -> Option<Throttle> {
+        match *self {
+            RuleAction::Throttle { latency_ms, jitter_ms, bandwidth_kbps } => Some(Throttle {
+                latency_ms,
+                jitter_ms,
+                bandwidth_kbps,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Latency and bandwidth limits applied to matching traffic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Throttle {
+    pub latency_ms: u64,
+    pub jitter_ms: u64,
+    /// Kilobits per second; 0 disables the cap
+    pub bandwidth_kbps: u64,
+}
+
+impl Throttle {
+    /// Latency for one request, drawn uniformly from latency ± jitter
+    pub fn sample_latency(&self) -> Duration {
+        if self.jitter_ms == 0 {
+            return Duration::from_millis(self.latency_ms);
+        }
+        let random = RandomState::new().build_hasher().finish();
+        let offset = random % (2 * self.jitter_ms + 1);
+        Duration::from_millis((self.latency_ms + offset).saturating_sub(self.jitter_ms))
+    }
+
+    /// Time `bytes` take to go through the bandwidth cap
+    pub fn transfer_time(&self, bytes: u64) -> Duration {
+        if self.bandwidth_kbps == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(bytes as f64 * 8.0 / (self.bandwidth_kbps as f64 * 1000.0))
+    }
 }
 
 /// Automatic Find and Replace (Similar to Burp Suite "Match and Replace")
@@ -270,4 +598,137 @@ mod tests {
         let condition = RuleCondition::Method("POST".to_string());
         assert!(condition.matches(&req));
     }
+
+    #[test]
+    fn test_connection_profile_per_scope() {
+        let legacy = ConnectionProfile {
+            total_timeout_ms: 600_000,
+            keep_alive: KeepAlivePolicy {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let policy = ConnectionPolicy {
+            default: ConnectionProfile::default(),
+            scopes: vec![ScopedConnectionProfile {
+                hosts: vec!["*.legacy.corp".to_string()],
+                profile: legacy.clone(),
+            }],
+        };
+
+        assert_eq!(policy.profile_for("http://erp.legacy.corp/login"), &legacy);
+        assert_eq!(
+            policy.profile_for("https://api.example.com/v1"),
+            &ConnectionProfile::default()
+        );
+        assert_eq!(legacy.total_timeout(), Some(Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn test_egress_route_per_scope() {
+        let policy = TrafficPolicy {
+            egress: EgressPolicy {
+                routes: vec![
+                    EgressRule {
+                        hosts: vec!["*.corp.local".to_string(), "10.10.*".to_string()],
+                        route: EgressRoute::Tunnel { name: "dmz".to_string() },
+                    },
+                    EgressRule {
+                        hosts: vec!["*".to_string()],
+                        route: EgressRoute::HttpProxy { host: "proxy.example.com".to_string(), port: 3128 },
+                    },
+                ],
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.egress_route_for("https://admin.corp.local/panel"),
+            Some(&EgressRoute::Tunnel { name: "dmz".to_string() })
+        );
+        assert_eq!(
+            policy.egress_route_for("http://10.10.4.2:8080/"),
+            Some(&EgressRoute::Tunnel { name: "dmz".to_string() })
+        );
+        assert!(matches!(
+            policy.egress_route_for("https://app.example.com/"),
+            Some(EgressRoute::HttpProxy { .. })
+        ));
+        assert_eq!(EgressPolicy::default().route_for_host("app.example.com"), None);
+        assert!(policy.egress.validate().is_ok());
+
+        let parsed: EgressPolicy = serde_json::from_str(
+            r#"{"routes": [{"hosts": ["*.internal"], "route": {"type": "socks5", "host": "127.0.0.1", "port": 1080}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.route_for_host("db.internal"),
+            Some(&EgressRoute::Socks5 { host: "127.0.0.1".to_string(), port: 1080 })
+        );
+    }
+
+    #[test]
+    fn test_throttle_rule() {
+        let rule = |id: &str, host: &str, action| InterceptionRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            enabled: true,
+            conditions: vec![RuleCondition::UrlContains(host.to_string())],
+            action,
+        };
+        let policy = TrafficPolicy {
+            interception_rules: vec![
+                rule("block", "slow.example.com", RuleAction::Block { reason: "no".to_string() }),
+                rule(
+                    "3g",
+                    "slow.example.com",
+                    RuleAction::Throttle { latency_ms: 300, jitter_ms: 100, bandwidth_kbps: 1024 },
+                ),
+            ],
+            ..Default::default()
+        };
+        let req = |url: &str| RequestContext {
+            url: url.to_string(),
+            method: "GET".to_string(),
+            headers: HashMap::new(),
+            body: Vec::new(),
+            port: 443,
+        };
+
+        let throttle = policy.throttle_for(&req("https://slow.example.com/api")).unwrap();
+        assert_eq!(throttle, Throttle { latency_ms: 300, jitter_ms: 100, bandwidth_kbps: 1024 });
+        assert!(policy.throttle_for(&req("https://fast.example.com/api")).is_none());
+
+        for _ in 0..50 {
+            let latency = throttle.sample_latency();
+            assert!(latency >= Duration::from_millis(200) && latency <= Duration::from_millis(400));
+        }
+        assert_eq!(throttle.transfer_time(128_000), Duration::from_secs(1));
+        assert_eq!(Throttle { bandwidth_kbps: 0, ..throttle }.transfer_time(1 << 20), Duration::ZERO);
+
+        let parsed: RuleAction = serde_json::from_str(r#"{"Throttle": {"latency_ms": 2000}}"#).unwrap();
+        assert_eq!(parsed.throttle(), Some(Throttle { latency_ms: 2000, jitter_ms: 0, bandwidth_kbps: 0 }));
+    }
+
+    #[test]
+    fn test_connection_profile_validation() {
+        assert!(ConnectionPolicy::default().validate().is_ok());
+
+        let too_tight = ConnectionProfile {
+            connect_timeout_ms: 5_000,
+            tls_handshake_timeout_ms: 5_000,
+            total_timeout_ms: 8_000,
+            idle_timeout_ms: 1_000,
+            ..Default::default()
+        };
+        assert!(too_tight.validate().is_err());
+
+        let unlimited = ConnectionProfile {
+            total_timeout_ms: 0,
+            ..Default::default()
+        };
+        assert!(unlimited.validate().is_ok());
+        assert_eq!(unlimited.total_timeout(), None);
+    }
 }
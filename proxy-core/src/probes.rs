@@ -0,0 +1,133 @@
+//! Liveness and readiness probes
+//!
+//! `/livez` says whether a process should be restarted, `/readyz` whether it
+//! can do its job right now. Both answer the way the Kubernetes API server
+//! does: 200 with `ok` when every check passes, 503 otherwise with one
+//! `[+]name ok` or `[-]name failed: reason` line per check. `?verbose` lists
+//! the checks on success too.
+
+use crate::ca::CertificateAuthority;
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How long a listener check waits for the connection
+const LISTENER_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Outcome of one named check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProbeCheck {
+    pub name: &'static str,
+    pub failure: Option<String>,
+}
+
+impl ProbeCheck {
+    pub fn new(name: &'static str, result: std::result::Result<(), String>) -> Self {
+        Self { name, failure: result.err() }
+    }
+}
+
+/// Status and body of the probe `probe` (`livez` or `readyz`)
+pub fn probe_report(probe: &str, checks: &[ProbeCheck], verbose: bool) -> (StatusCode, String) {
+    let passed = checks.iter().all(|check| check.failure.is_none());
+    if passed && !verbose {
+        return (StatusCode::OK, "ok".to_string());
+    }
+    let mut body = String::new();
+    for check in checks {
+        match &check.failure {
+            None => body.push_str(&format!("[+]{} ok\n", check.name)),
+            Some(reason) => body.push_str(&format!("[-]{} failed: {}\n", check.name, reason)),
+        }
+    }
+    if passed {
+        body.push_str(&format!("{} check passed\n", probe));
+        (StatusCode::OK, body)
+    } else {
+        body.push_str(&format!("{} check failed\n", probe));
+        (StatusCode::SERVICE_UNAVAILABLE, body)
+    }
+}
+
+/// [`probe_report`] as a plain text response; `query` is the raw query
+/// string of the request
+pub fn probe_response(probe: &str, checks: &[ProbeCheck], query: Option<&str>) -> Response {
+    let verbose = query
+        .unwrap_or_default()
+        .split('&')
+        .any(|pair| pair == "verbose" || pair.starts_with("verbose="));
+    let (status, body) = probe_report(probe, checks, verbose);
+    (status, [(header::CONTENT_TYPE, "text/plain; charset=utf-8")], body).into_response()
+}
+
+/// Whether something on this host accepts TCP connections on `port`
+pub async fn check_listener(port: u16) -> std::result::Result<(), String> {
+    match tokio::time::timeout(LISTENER_TIMEOUT, tokio::net::TcpStream::connect(("127.0.0.1", port))).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(format!("port {} is not accepting connections: {}", port, e)),
+        Err(_) => Err(format!("port {} did not accept a connection within {:?}", port, LISTENER_TIMEOUT)),
+    }
+}
+
+/// Whether `ca` can issue certificates
+pub fn check_ca(ca: &CertificateAuthority) -> std::result::Result<(), String> {
+    if ca.is_locked() {
+        return Err("CA key is locked".to_string());
+    }
+    ca.get_ca_cert_pem().map(|_| ()).map_err(|e| e.to_string())
+}
+
+/// Whether a long-lived connection is currently up, set by whoever holds it
+#[derive(Debug, Clone, Default)]
+pub struct LinkStatus(Arc<AtomicBool>);
+
+impl LinkStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.0.store(connected, Ordering::Relaxed);
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reports_in_kubernetes_format() {
+        let healthy = [ProbeCheck::new("ca", Ok(())), ProbeCheck::new("proxy", Ok(()))];
+        assert_eq!(probe_report("readyz", &healthy, false), (StatusCode::OK, "ok".to_string()));
+        assert_eq!(
+            probe_report("readyz", &healthy, true),
+            (StatusCode::OK, "[+]ca ok\n[+]proxy ok\nreadyz check passed\n".to_string())
+        );
+
+        let failing = [ProbeCheck::new("ca", Ok(())), ProbeCheck::new("proxy", Err("port 8080 is closed".to_string()))];
+        assert_eq!(
+            probe_report("readyz", &failing, false),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "[+]ca ok\n[-]proxy failed: port 8080 is closed\nreadyz check failed\n".to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listener_check() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(check_listener(port).await.is_ok());
+        drop(listener);
+        assert!(check_listener(port).await.is_err());
+    }
+}
@@ -1,19 +1,29 @@
 use crate::{
     admin::{start_admin_server, Metrics},
     ca::CertificateAuthority,
+    certificates::CertificateManager,
     config::{ProxyConfig, BodyCaptureConfig},
     error::ProxyError,
     handlers::LogHandler,
+    leaf_cache::LeafCertCache,
+    mail::MailListener,
+    reverse::ReverseListener,
+    transparent::TransparentListener,
+    pac::{load_pac_script, PacEngine},
+    policy::TrafficPolicy,
+    target_auth::TargetAuthenticator,
+    tls_policy::TlsPolicy,
+    upstream::{pooled_client, upstream_client, upstream_connector},
     Result,
 };
-use hudsucker::{certificate_authority::RcgenAuthority, rustls, ProxyBuilder};
+use hudsucker::ProxyBuilder;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 pub struct ProxyServer {
     config: ProxyConfig,
-    ca: CertificateAuthority,
+    ca: Arc<CertificateAuthority>,
     metrics: Arc<Metrics>,
     log_sender: Option<tokio::sync::mpsc::Sender<crate::pb::TrafficEvent>>,
     body_capture_config: Option<BodyCaptureConfig>,
@@ -21,13 +31,20 @@ pub struct ProxyServer {
     agent_name: String,
     agent_version: String,
     agent_hostname: String,
+    data_plane: Option<tokio::runtime::Handle>,
+    intercept_controller: Option<crate::controller::InterceptController>,
+    script_controller: Option<crate::scripts::ScriptController>,
+    capture_controller: crate::capture::CaptureController,
+    cookie_jar: crate::cookie_jar::CookieJar,
+    orchestrator_link: Option<crate::probes::LinkStatus>,
 }
 
 impl ProxyServer {
     pub fn new(config: ProxyConfig, ca: CertificateAuthority) -> Self {
+        let ca = ca.with_leaf_key(config.certificate_config.leaf_key_algorithm);
         Self {
             config,
-            ca,
+            ca: Arc::new(ca),
             metrics: Arc::new(Metrics::default()),
             log_sender: None,
             body_capture_config: None,
@@ -35,9 +52,21 @@ impl ProxyServer {
             agent_name: "unknown".to_string(),
             agent_version: "unknown".to_string(),
             agent_hostname: "unknown".to_string(),
+            data_plane: None,
+            intercept_controller: None,
+            script_controller: None,
+            capture_controller: crate::capture::CaptureController::new(),
+            cookie_jar: crate::cookie_jar::CookieJar::new(),
+            orchestrator_link: None,
         }
     }
 
+    /// The CA issuing this server's leaf certificates; rotating it takes
+    /// effect on the next tunnel.
+    pub fn ca(&self) -> Arc<CertificateAuthority> {
+        self.ca.clone()
+    }
+
     pub fn with_log_sender(
         mut self,
         sender: tokio::sync::mpsc::Sender<crate::pb::TrafficEvent>,
@@ -59,6 +88,45 @@ impl ProxyServer {
         self
     }
 
+    /// Run the accept/forward loop on a dedicated runtime instead of the caller's.
+    /// The admin server stays on the caller's runtime.
+    pub fn with_data_plane_runtime(mut self, handle: tokio::runtime::Handle) -> Self {
+        self.data_plane = Some(handle);
+        self
+    }
+
+    /// Apply interception rules from `controller` and hold paused requests in it
+    pub fn with_intercept_controller(mut self, controller: crate::controller::InterceptController) -> Self {
+        self.intercept_controller = Some(controller);
+        self
+    }
+
+    /// Run the hook scripts held by `controller` on proxied traffic
+    pub fn with_script_controller(mut self, controller: crate::scripts::ScriptController) -> Self {
+        self.script_controller = Some(controller);
+        self
+    }
+
+    /// Share capture pause state with `controller`; without one the state is
+    /// only reachable through the admin API
+    pub fn with_capture_controller(mut self, controller: crate::capture::CaptureController) -> Self {
+        self.capture_controller = controller;
+        self
+    }
+
+    /// Store cookies in `jar`, shared with whoever else holds it
+    pub fn with_cookie_jar(mut self, jar: crate::cookie_jar::CookieJar) -> Self {
+        self.cookie_jar = jar;
+        self
+    }
+
+    /// Report the agent not ready on the admin API's `/readyz` while
+    /// `link` is down
+    pub fn with_orchestrator_link(mut self, link: crate::probes::LinkStatus) -> Self {
+        self.orchestrator_link = Some(link);
+        self
+    }
+
     pub async fn run(self) -> Result<()> {
         let addr = SocketAddr::from(([0, 0, 0, 0], self.config.listen_port));
         info!("Starting proxy server on {}", addr);
@@ -66,48 +134,250 @@ impl ProxyServer {
         // Start Admin Server
         let admin_port = self.config.admin_port;
         let metrics = self.metrics.clone();
+        let capture = self.capture_controller.clone();
+        let mitm_fallback = crate::mitm_fallback::MitmFallback::new(self.config.mitm_fallback.clone());
+        let admin_fallback = mitm_fallback.clone();
+        if !self.config.maintain_session.is_empty() {
+            self.cookie_jar.set_maintain_session(self.config.maintain_session.clone());
+        }
+        let cookies = self.cookie_jar.clone();
+        let authority = LeafCertCache::new(self.ca.clone(), self.config.certificate_config.leaf_cache_capacity);
+        info!(
+            "Issuing {} leaf certificates, caching up to {} host(s)",
+            self.ca.leaf_key(),
+            self.config.certificate_config.leaf_cache_capacity
+        );
+        let leaf_certificates = authority.clone();
+        let admin_ca = self.ca.clone();
         let info = crate::admin::AgentInfo {
             agent_id: self.agent_id.clone(),
             name: self.agent_name.clone(),
             version: self.agent_version.clone(),
             hostname: self.agent_hostname.clone(),
         };
+        let admin_token = self.config.admin_token.clone();
+        if admin_token.is_none() {
+            info!("Admin API control routes only answer local clients; set an admin token to use them remotely");
+        }
+        let readiness = crate::admin::ReadinessChecks {
+            proxy_port: self.config.listen_port,
+            orchestrator: self.orchestrator_link.clone(),
+        };
         tokio::spawn(async move {
-            if let Err(e) = start_admin_server(admin_port, metrics, info).await {
+            if let Err(e) = start_admin_server(
+                admin_port,
+                admin_token,
+                metrics,
+                capture,
+                admin_fallback,
+                cookies,
+                leaf_certificates,
+                admin_ca,
+                info,
+                readiness,
+            )
+            .await
+            {
                 error!("Admin server failed: {}", e);
             }
         });
 
-        // Hudsucker/Rustls expects DER, not PEM.
-        let ca_cert_der = self.ca.get_ca_cert_der()?;
-        let ca_key_der = self.ca.get_ca_key_der()?;
+        // Mail listeners bind up front so a taken port fails startup
+        let mut mail_listeners = Vec::new();
+        for listener_config in &self.config.mail_listeners {
+            mail_listeners.push(
+                MailListener::bind(listener_config.clone(), &self.ca, self.log_sender.clone()).await?,
+            );
+        }
+
+        // Create LogHandler with body capture config if provided, otherwise use defaults
+        let mut log_handler = match self.body_capture_config {
+            Some(body_config) => LogHandler::new(self.metrics.clone(), self.log_sender.clone(), body_config),
+            None => LogHandler::new_with_defaults(self.metrics.clone(), self.log_sender.clone()),
+        }
+        .with_request_limits(self.config.request_limits.clone())
+        .with_capture_controller(self.capture_controller)
+        .with_cookie_jar(self.cookie_jar)
+        .with_tls_passthrough(&self.config.tls_passthrough)
+        .with_mitm_fallback(mitm_fallback);
+        // Report intercepted tunnels that never carry a request
+        tokio::spawn(log_handler.connection_log().run_sweeper());
+        if let Some(name) = &self.config.request_id_header {
+            let header = hudsucker::hyper::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ProxyError::Configuration(format!("Invalid request ID header name: {}", name)))?;
+            info!("Sending request IDs upstream in {}", header);
+            log_handler = log_handler.with_request_id_header(header);
+        }
+        if let Some(controller) = self.intercept_controller {
+            log_handler = log_handler.with_intercept_controller(controller);
+        }
+        if let Some(controller) = self.script_controller {
+            log_handler = log_handler.with_script_controller(controller);
+        }
 
-        let private_key = rustls::PrivateKey(ca_key_der);
-        let ca_cert = rustls::Certificate(ca_cert_der);
+        // Cap the parser's read buffer so oversized request heads are refused
+        // while they are read, not after hyper has buffered them
+        let server = hudsucker::hyper::Server::try_bind(&addr)
+            .map_err(|e| ProxyError::Network(format!("Failed to bind proxy on {}: {}", addr, e)))?
+            .http1_preserve_header_case(true)
+            .http1_title_case_headers(true)
+            .http1_max_buf_size(self.config.request_limits.parse_buffer_size());
+        let builder = ProxyBuilder::new().with_server(server);
 
-        let authority = RcgenAuthority::new(private_key, ca_cert, 1000).map_err(|e| {
-            ProxyError::Configuration(format!("Failed to create CA authority: {}", e))
-        })?;
+        self.config
+            .egress
+            .validate()
+            .map_err(|e| ProxyError::Configuration(format!("Invalid egress routes: {}", e)))?;
+        self.config
+            .connection
+            .validate()
+            .map_err(|e| ProxyError::Configuration(format!("Invalid connection profiles: {}", e)))?;
+        let connection = Arc::new(self.config.connection.clone());
+        log_handler = log_handler.with_connection_policy(connection.clone());
+        if !self.config.throttle_rules.is_empty() {
+            info!("Throttling traffic matching {} rule(s)", self.config.throttle_rules.len());
+            log_handler = log_handler.with_traffic_policy(Arc::new(TrafficPolicy {
+                interception_rules: self.config.throttle_rules.clone(),
+                ..Default::default()
+            }));
+        }
 
-        // Create LogHandler with body capture config if provided, otherwise use defaults
-        let log_handler = match self.body_capture_config {
-            Some(body_config) => LogHandler::new(self.metrics.clone(), self.log_sender, body_config),
-            None => LogHandler::new_with_defaults(self.metrics.clone(), self.log_sender),
+        let pac = match &self.config.pac_source {
+            Some(source) => {
+                let script = load_pac_script(source).await?;
+                info!("Selecting upstream proxies with PAC script from {}", source);
+                Some(Arc::new(PacEngine::new(script, &self.config.dns, &self.config.host_overrides)?))
+            }
+            None => None,
         };
 
-        let proxy = ProxyBuilder::new()
-            .with_addr(addr)
-            .with_rustls_client()
+        let mut certificates = CertificateManager::new()?;
+        for client_cert in &self.config.client_certificates {
+            certificates.add_client_certificate(client_cert)?;
+        }
+        let tls_policies = self
+            .config
+            .tls_policies
+            .iter()
+            .map(TlsPolicy::load)
+            .collect::<Result<Vec<_>>>()?;
+
+        // Reverse and transparent listeners forward with their own HTTP/1.1
+        // client over the same DNS, egress and PAC settings, and so do
+        // WebSocket upgrades relayed with permessage-deflate
+        let client = upstream_client(
+            &self.config.dns,
+            &self.config.host_overrides,
+            pac.clone(),
+            Arc::new(self.config.egress.clone()),
+            connection.clone(),
+            &tls_policies,
+            certificates.client_certificates(),
+            true,
+        )?;
+        log_handler = log_handler.with_websocket_client(client.clone());
+        let mut reverse_listeners = Vec::new();
+        let mut transparent_listener = None;
+        if !self.config.reverse_listeners.is_empty() || self.config.transparent_listener.is_some() {
+            for listener_config in &self.config.reverse_listeners {
+                reverse_listeners.push(
+                    ReverseListener::bind(listener_config.clone(), self.ca.clone(), client.clone(), self.log_sender.clone())
+                        .await?,
+                );
+            }
+            if let Some(listener_config) = &self.config.transparent_listener {
+                transparent_listener = Some(
+                    TransparentListener::bind(listener_config.clone(), self.ca.clone(), client, self.log_sender.clone())
+                        .await?,
+                );
+            }
+        }
+
+        if !self.config.egress.routes.is_empty() {
+            info!("Routing egress with {} scope rule(s)", self.config.egress.routes.len());
+        }
+        if !self.config.connection.scopes.is_empty() {
+            info!("Applying {} scoped connection profile(s)", self.config.connection.scopes.len());
+        }
+        if self.config.force_http1 {
+            info!("Upstream connections forced to HTTP/1.1");
+        }
+        if !self.config.dns.is_system() {
+            info!(
+                "Resolving upstream hosts via {:?} DNS: {}",
+                self.config.dns.mode,
+                self.config.dns.servers.join(", ")
+            );
+        }
+        if !self.config.host_overrides.is_empty() {
+            info!("Overriding DNS for {} host(s)", self.config.host_overrides.len());
+        }
+        for policy in tls_policies.iter().filter(|policy| policy.skips_verification()) {
+            warn!("Upstream certificates are not verified for {}", policy.host_pattern());
+        }
+        if !certificates.client_certificates().is_empty() {
+            info!(
+                "Presenting client certificates to {} host pattern(s)",
+                certificates.client_certificates().len()
+            );
+        }
+        let connector = upstream_connector(
+            &self.config.dns,
+            &self.config.host_overrides,
+            pac,
+            Arc::new(self.config.egress.clone()),
+            connection,
+            &tls_policies,
+            certificates.client_certificates(),
+            self.config.force_http1,
+        )?;
+        if !self.config.target_auth.is_empty() {
+            info!("Authenticating to {} target host pattern(s)", self.config.target_auth.len());
+            log_handler = log_handler.with_target_auth(
+                TargetAuthenticator::new(&self.config.target_auth).with_connector(connector.clone()),
+            );
+        }
+        // Our client rather than hudsucker's, so connection profiles always apply
+        let proxy = builder
+            .with_client(pooled_client(connector))
             .with_ca(authority)
             .with_http_handler(log_handler)
             .build();
+        let serve = async move {
+            proxy
+                .start(std::future::pending::<()>())
+                .await
+                .map_err(|e| ProxyError::Network(format!("Proxy failed: {}", e)))
+        };
 
-        proxy
-            .start(std::future::pending::<()>())
-            .await
-            .map_err(|e| ProxyError::Network(format!("Proxy failed: {}", e)))?;
+        for listener in mail_listeners {
+            match &self.data_plane {
+                Some(handle) => handle.spawn(listener.serve()),
+                None => tokio::spawn(listener.serve()),
+            };
+        }
+        for listener in reverse_listeners {
+            match &self.data_plane {
+                Some(handle) => handle.spawn(listener.serve()),
+                None => tokio::spawn(listener.serve()),
+            };
+        }
+        if let Some(listener) = transparent_listener {
+            match &self.data_plane {
+                Some(handle) => handle.spawn(listener.serve()),
+                None => tokio::spawn(listener.serve()),
+            };
+        }
+
+        // Connection tasks spawned by hudsucker inherit the runtime the loop runs on
+        match self.data_plane {
+            Some(handle) => handle
+                .spawn(serve)
+                .await
+                .map_err(|e| ProxyError::General(format!("Data plane task failed: {}", e)))??,
+            None => serve.await?,
+        }
 
         Ok(())
     }
 }
-
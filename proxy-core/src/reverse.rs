@@ -0,0 +1,526 @@
+//! Reverse Proxy Listeners
+//!
+//! A reverse listener terminates TLS on a local port and forwards the HTTP
+//! requests it carries to an upstream chosen by the SNI of the client hello.
+//! One port can therefore front many virtual hosts, as when a hosts file or
+//! DNS override points a device's traffic straight at the agent (invisible
+//! proxying). Clients sending no SNI, or an SNI no route matches, go to the
+//! listener's default upstream; without one their handshake is refused.
+//! A listener with invisible proxying instead sends clients whose SNI matches
+//! no route to the host they named, on the listener's own port, so it stands
+//! in for any server without routes; those transactions are tagged
+//! `invisible` when captured.
+//!
+//! Certificates are issued by the agent CA per server name, or per parent
+//! domain (`*.example.com`) when the listener uses wildcard certificates, so
+//! the many hosts of one domain share a certificate. Requests and responses
+//! are captured like proxied traffic.
+
+use crate::ca::CertificateAuthority;
+use crate::connections::ConnectionLog;
+use crate::error::ProxyError;
+use crate::pb::connection_event::Kind;
+use crate::pb::{traffic_event, HttpHeaders, HttpRequestData, HttpResponseData, RequestTags, TlsDetails, TrafficEvent};
+use crate::timing::PhaseTimer;
+use crate::upstream::UpstreamClient;
+use crate::Result;
+use bytes::Bytes;
+use dashmap::DashMap;
+use hudsucker::hyper::{self, service::service_fn, Body, Request, Response, StatusCode, Uri};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls;
+use tokio_rustls::LazyConfigAcceptor;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+use wildmatch::WildMatch;
+
+/// Tag of transactions captured from clients that were not configured to
+/// use a proxy
+pub const INVISIBLE_TAG: &str = "invisible";
+
+/// Server a reverse listener forwards to
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReverseUpstream {
+    pub host: String,
+    pub port: u16,
+    /// Speak HTTPS to the upstream
+    #[serde(default = "default_upstream_tls")]
+    pub tls: bool,
+}
+
+fn default_upstream_tls() -> bool {
+    true
+}
+
+/// Upstream for server names matching a pattern
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SniRoute {
+    /// Server name pattern, `*` wildcards
+    pub server_name: String,
+    pub upstream: ReverseUpstream,
+}
+
+/// One reverse proxy listener
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReverseListenerConfig {
+    #[serde(default = "default_listen_address")]
+    pub listen_address: String,
+    pub listen_port: u16,
+    /// Routes by SNI, first match wins
+    #[serde(default)]
+    pub routes: Vec<SniRoute>,
+    /// Upstream for clients without SNI or matching no route
+    #[serde(default)]
+    pub default_upstream: Option<ReverseUpstream>,
+    /// Send clients whose SNI matches no route to the host they named
+    /// (invisible proxying) rather than to the default upstream
+    #[serde(default)]
+    pub invisible: bool,
+    /// Issue one `*.parent` certificate per domain instead of one per host
+    #[serde(default)]
+    pub wildcard_certificates: bool,
+    /// Largest request or response body captured; bodies are forwarded whole
+    #[serde(default = "default_max_capture_bytes")]
+    pub max_capture_bytes: usize,
+}
+
+pub(crate) fn default_listen_address() -> String {
+    "0.0.0.0".to_string()
+}
+
+pub(crate) fn default_max_capture_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+impl ReverseListenerConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.listen_port == 0 {
+            return Err("listen_port must be set".to_string());
+        }
+        if self.routes.is_empty() && self.default_upstream.is_none() && !self.invisible {
+            return Err("at least one route, a default_upstream or invisible proxying is needed".to_string());
+        }
+        let upstreams = self.routes.iter().map(|r| &r.upstream).chain(&self.default_upstream);
+        for upstream in upstreams {
+            if upstream.host.trim().is_empty() || upstream.port == 0 {
+                return Err("upstreams need a host and a port".to_string());
+            }
+        }
+        if let Some(route) = self.routes.iter().find(|r| r.server_name.trim().is_empty()) {
+            return Err(format!("route to {} has no server_name", route.upstream.host));
+        }
+        Ok(())
+    }
+
+    /// Upstream for a client hello with `server_name`. With invisible
+    /// proxying, a name matching no route has none here; see
+    /// [`Self::invisible_upstream`].
+    pub fn route(&self, server_name: Option<&str>) -> Option<&ReverseUpstream> {
+        let routed = server_name.and_then(|name| {
+            self.routes
+                .iter()
+                .find(|route| WildMatch::new(&route.server_name.to_ascii_lowercase()).matches(name))
+                .map(|route| &route.upstream)
+        });
+        if routed.is_none() && self.invisible && server_name.is_some() {
+            return None;
+        }
+        routed.or(self.default_upstream.as_ref())
+    }
+
+    /// The named server itself, on the listener's port, for an invisible
+    /// proxying listener
+    pub fn invisible_upstream(&self, server_name: &str) -> Option<ReverseUpstream> {
+        self.invisible.then(|| ReverseUpstream {
+            host: server_name.to_string(),
+            port: self.listen_port,
+            tls: true,
+        })
+    }
+}
+
+/// Name the certificate for `server_name` is issued to: the host itself, or
+/// `*.parent` with wildcard certificates. Hosts directly under a public
+/// suffix-like label (`example.com`) keep their own name since a wildcard
+/// would not cover them.
+pub fn certificate_name(server_name: &str, wildcard: bool) -> String {
+    let labels = server_name.split('.').count();
+    if !wildcard || labels < 3 || server_name.parse::<std::net::IpAddr>().is_ok() {
+        return server_name.to_string();
+    }
+    let parent = server_name.split_once('.').map_or(server_name, |(_, parent)| parent);
+    format!("*.{}", parent)
+}
+
+/// A bound reverse listener, ready to serve
+pub struct ReverseListener {
+    listener: TcpListener,
+    shared: Arc<ListenerShared>,
+}
+
+struct ListenerShared {
+    config: ReverseListenerConfig,
+    certificates: CertificateCache,
+    forwarder: Arc<Forwarder>,
+}
+
+impl ReverseListener {
+    /// Bind the listener. Certificates are issued on first use.
+    pub async fn bind(
+        config: ReverseListenerConfig,
+        ca: Arc<CertificateAuthority>,
+        client: UpstreamClient,
+        log_sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+    ) -> Result<Self> {
+        config.validate().map_err(ProxyError::Configuration)?;
+
+        let addr = format!("{}:{}", config.listen_address, config.listen_port);
+        let listener = TcpListener::bind(&addr)
+            .await
+            .map_err(|e| ProxyError::Network(format!("Failed to bind reverse listener on {}: {}", addr, e)))?;
+
+        Ok(Self {
+            listener,
+            shared: Arc::new(ListenerShared {
+                certificates: CertificateCache::new(ca, config.wildcard_certificates),
+                forwarder: Arc::new(Forwarder::new(client, log_sender, config.max_capture_bytes)),
+                config,
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(ProxyError::Io)
+    }
+
+    /// Accept and forward clients until the task is dropped
+    pub async fn serve(self) {
+        let config = &self.shared.config;
+        info!(
+            "🔀 Reverse listener on {}:{} with {} SNI route(s){}",
+            config.listen_address,
+            config.listen_port,
+            config.routes.len(),
+            config
+                .default_upstream
+                .as_ref()
+                .map(|u| format!(", default {}:{}", u.host, u.port))
+                .unwrap_or_default()
+        );
+        loop {
+            let (client, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Reverse listener accept failed: {}", e);
+                    continue;
+                }
+            };
+            let shared = self.shared.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(client, peer, shared).await {
+                    debug!("Reverse connection from {} ended: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+/// Agent CA certificates for intercepted server names, issued on first use
+pub(crate) struct CertificateCache {
+    ca: Arc<CertificateAuthority>,
+    wildcard: bool,
+    /// Server configs by certificate name
+    configs: DashMap<String, Arc<rustls::ServerConfig>>,
+    /// CA generation the cached certificates were signed under
+    generation: AtomicU64,
+}
+
+impl CertificateCache {
+    pub(crate) fn new(ca: Arc<CertificateAuthority>, wildcard: bool) -> Self {
+        let generation = AtomicU64::new(ca.generation());
+        Self { ca, wildcard, configs: DashMap::new(), generation }
+    }
+
+    /// TLS config presenting a certificate for `server_name`
+    pub(crate) fn server_config(&self, server_name: &str) -> Result<Arc<rustls::ServerConfig>> {
+        let name = certificate_name(server_name, self.wildcard);
+        let generation = self.ca.generation();
+        if self.generation.swap(generation, Ordering::SeqCst) != generation {
+            self.configs.clear();
+        }
+        if let Some(config) = self.configs.get(&name) {
+            return Ok(config.clone());
+        }
+
+        let (cert_der, key_der) = self.ca.gen_cert_der_for_domain(&name)?;
+        let mut config = rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(vec![rustls::Certificate(cert_der)], rustls::PrivateKey(key_der))
+            .map_err(|e| ProxyError::Certificate(format!("Invalid certificate for {}: {}", name, e)))?;
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        let config = Arc::new(config);
+        self.configs.insert(name, config.clone());
+        Ok(config)
+    }
+}
+
+async fn handle_connection(client: TcpStream, peer: SocketAddr, shared: Arc<ListenerShared>) -> Result<()> {
+    let start = LazyConfigAcceptor::new(rustls::server::Acceptor::default(), client)
+        .await
+        .map_err(ProxyError::Io)?;
+    let server_name = start.client_hello().server_name().map(|name| name.to_ascii_lowercase());
+    let connection_log = &shared.forwarder.connection_log;
+
+    let routed = match shared.config.route(server_name.as_deref()) {
+        Some(upstream) => Some((upstream.clone(), false)),
+        None => server_name
+            .as_deref()
+            .and_then(|name| shared.config.invisible_upstream(name))
+            .map(|upstream| (upstream, true)),
+    };
+    let Some((upstream, invisible)) = routed else {
+        let target = server_name.as_deref().unwrap_or("(no SNI)");
+        connection_log.record(Kind::Blocked, target, peer, "No reverse route for server name", None);
+        return Ok(());
+    };
+    // Clients without SNI are served a certificate for the upstream
+    let virtual_host = server_name.unwrap_or_else(|| upstream.host.clone());
+
+    let stream = start
+        .into_stream(shared.certificates.server_config(&virtual_host)?)
+        .await
+        .map_err(|e| {
+            connection_log.record(Kind::TlsFailure, &virtual_host, peer, &e.to_string(), None);
+            ProxyError::Io(e)
+        })?;
+    let tls = tls_details(stream.get_ref().1);
+    debug!("Reverse connection from {} for {} -> {}:{}", peer, virtual_host, upstream.host, upstream.port);
+
+    let scheme = if upstream.tls { "https" } else { "http" };
+    let origin = Arc::new(format!("https://{}", virtual_host));
+    let upstream = Arc::new(format!("{}://{}:{}", scheme, upstream.host, upstream.port));
+    let forwarder = shared.forwarder.clone();
+    let service = service_fn(move |req| {
+        let (forwarder, origin, upstream, tls) = (forwarder.clone(), origin.clone(), upstream.clone(), tls.clone());
+        async move { Ok::<_, Infallible>(forwarder.forward(req, &origin, &upstream, Some(tls), peer, invisible).await) }
+    });
+    hyper::server::conn::Http::new()
+        .http1_only(true)
+        .serve_connection(stream, service)
+        .await
+        .map_err(|e| ProxyError::Network(e.to_string()))
+}
+
+/// Protocol version and cipher suite of an established TLS session
+pub(crate) fn tls_details(connection: &rustls::ServerConnection) -> TlsDetails {
+    TlsDetails {
+        version: connection.protocol_version().map(|v| format!("{:?}", v)).unwrap_or_default(),
+        cipher: connection
+            .negotiated_cipher_suite()
+            .map(|s| format!("{:?}", s.suite()))
+            .unwrap_or_default(),
+    }
+}
+
+/// Sends requests of terminated client connections upstream, capturing
+/// both directions
+pub(crate) struct Forwarder {
+    client: UpstreamClient,
+    log_sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+    pub(crate) connection_log: ConnectionLog,
+    /// Largest request or response body captured
+    max_capture_bytes: usize,
+}
+
+impl Forwarder {
+    pub(crate) fn new(
+        client: UpstreamClient,
+        log_sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+        max_capture_bytes: usize,
+    ) -> Self {
+        Self {
+            client,
+            connection_log: ConnectionLog::new(log_sender.clone()),
+            log_sender,
+            max_capture_bytes,
+        }
+    }
+
+    /// Forward `req` to `upstream` (`scheme://host:port`), captured as a
+    /// request to `origin` (`scheme://host`) and tagged [`INVISIBLE_TAG`]
+    /// when the client was not using a proxy
+    pub(crate) async fn forward(
+        &self,
+        req: Request<Body>,
+        origin: &str,
+        upstream: &str,
+        tls: Option<TlsDetails>,
+        peer: SocketAddr,
+        invisible: bool,
+    ) -> Response<Body> {
+        let (mut parts, body) = req.into_parts();
+        let path = parts.uri.path_and_query().map_or("/", |p| p.as_str()).to_string();
+        let url = format!("{}{}", origin, path);
+        let request_id = Uuid::new_v4().to_string();
+
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, &format!("Failed to read request body: {}", e)),
+        };
+        self.send_event(&request_id, traffic_event::Event::Request(HttpRequestData {
+            method: parts.method.to_string(),
+            url: url.clone(),
+            headers: Some(headers_of(&parts.headers)),
+            body: self.captured(&body),
+            tls: tls.clone(),
+        }));
+        if invisible {
+            self.send_event(&request_id, traffic_event::Event::Tags(RequestTags {
+                tags: vec![INVISIBLE_TAG.to_string()],
+            }));
+        }
+
+        parts.uri = match format!("{}{}", upstream, path).parse::<Uri>() {
+            Ok(uri) => uri,
+            Err(e) => return error_response(StatusCode::BAD_REQUEST, &format!("Invalid request path: {}", e)),
+        };
+        parts.version = hyper::Version::HTTP_11;
+
+        let mut timer = PhaseTimer::start();
+        let response = match self.client.request(Request::from_parts(parts, Body::from(body))).await {
+            Ok(response) => {
+                timer.headers_received();
+                response
+            }
+            Err(e) => {
+                let (kind, reason) = crate::connections::classify_upstream_error(&e);
+                warn!("Upstream of {} failed: {}", url, reason);
+                self.connection_log.record(kind, &url, peer, &reason, Some(&request_id));
+                return error_response(StatusCode::BAD_GATEWAY, &reason);
+            }
+        };
+
+        let (parts, body) = response.into_parts();
+        let body = match hyper::body::to_bytes(body).await {
+            Ok(body) => body,
+            Err(e) => return error_response(StatusCode::BAD_GATEWAY, &format!("Failed to read response body: {}", e)),
+        };
+        self.send_event(&request_id, traffic_event::Event::Response(HttpResponseData {
+            status_code: parts.status.as_u16() as i32,
+            headers: Some(headers_of(&parts.headers)),
+            body: self.captured(&body),
+            tls,
+            protocol: "HTTP/1.1".to_string(),
+            timing: Some(timer.finish()),
+        }));
+        Response::from_parts(parts, Body::from(body))
+    }
+
+    fn captured(&self, body: &Bytes) -> Bytes {
+        body.slice(..body.len().min(self.max_capture_bytes))
+    }
+
+    fn send_event(&self, request_id: &str, event: traffic_event::Event) {
+        if let Some(sender) = &self.log_sender {
+            let _ = sender.try_send(TrafficEvent {
+                request_id: request_id.to_string(),
+                event: Some(event),
+            });
+        }
+    }
+}
+
+fn headers_of(headers: &hyper::HeaderMap) -> HttpHeaders {
+    let mut map = HashMap::new();
+    for (name, value) in headers {
+        map.insert(name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string());
+    }
+    HttpHeaders { headers: map }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "text/plain; charset=utf-8")
+        .body(Body::from(message.to_string()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upstream(host: &str) -> ReverseUpstream {
+        ReverseUpstream { host: host.to_string(), port: 443, tls: true }
+    }
+
+    #[test]
+    fn test_sni_routing() {
+        let config = ReverseListenerConfig {
+            listen_address: default_listen_address(),
+            listen_port: 8443,
+            routes: vec![
+                SniRoute { server_name: "api.example.com".to_string(), upstream: upstream("10.0.0.5") },
+                SniRoute { server_name: "*.example.com".to_string(), upstream: upstream("10.0.0.6") },
+            ],
+            default_upstream: Some(upstream("10.0.0.9")),
+            invisible: false,
+            wildcard_certificates: true,
+            max_capture_bytes: 1024,
+        };
+        assert!(config.validate().is_ok());
+
+        assert_eq!(config.route(Some("api.example.com")).unwrap().host, "10.0.0.5");
+        assert_eq!(config.route(Some("shop.example.com")).unwrap().host, "10.0.0.6");
+        assert_eq!(config.route(Some("other.test")).unwrap().host, "10.0.0.9");
+        assert_eq!(config.route(None).unwrap().host, "10.0.0.9");
+
+        let no_default = ReverseListenerConfig { default_upstream: None, ..config.clone() };
+        assert!(no_default.route(Some("other.test")).is_none());
+        let empty = ReverseListenerConfig { routes: Vec::new(), ..no_default };
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_invisible_routing() {
+        let config = ReverseListenerConfig {
+            listen_address: default_listen_address(),
+            listen_port: 443,
+            routes: vec![SniRoute { server_name: "api.example.com".to_string(), upstream: upstream("10.0.0.5") }],
+            default_upstream: Some(upstream("10.0.0.9")),
+            invisible: true,
+            wildcard_certificates: false,
+            max_capture_bytes: 1024,
+        };
+        assert_eq!(config.route(Some("api.example.com")).unwrap().host, "10.0.0.5");
+        // Named clients go to the server they named, unnamed ones to the default
+        assert!(config.route(Some("shop.test")).is_none());
+        assert_eq!(
+            config.invisible_upstream("shop.test"),
+            Some(ReverseUpstream { host: "shop.test".to_string(), port: 443, tls: true })
+        );
+        assert_eq!(config.route(None).unwrap().host, "10.0.0.9");
+
+        let bare = ReverseListenerConfig { routes: Vec::new(), default_upstream: None, ..config.clone() };
+        assert!(bare.validate().is_ok());
+        let not_invisible = ReverseListenerConfig { invisible: false, ..bare };
+        assert!(not_invisible.validate().is_err());
+        assert!(not_invisible.invisible_upstream("shop.test").is_none());
+    }
+
+    #[test]
+    fn test_certificate_name() {
+        assert_eq!(certificate_name("shop.example.com", true), "*.example.com");
+        assert_eq!(certificate_name("a.b.example.com", true), "*.b.example.com");
+        assert_eq!(certificate_name("example.com", true), "example.com");
+        assert_eq!(certificate_name("10.0.0.5", true), "10.0.0.5");
+        assert_eq!(certificate_name("shop.example.com", false), "shop.example.com");
+    }
+}
@@ -0,0 +1,135 @@
+//! Data Plane Runtime Module
+//!
+//! Provides a dedicated Tokio runtime for the proxy accept/forward loop so that
+//! control-plane work (gRPC streaming, admin API, metrics collection) running on
+//! the main runtime cannot steal worker time from proxied connections.
+
+use crate::{error::ProxyError, Result};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::runtime::{Builder, Handle, Runtime};
+use tracing::info;
+
+/// Configuration for the data plane runtime
+#[derive(Debug, Clone)]
+pub struct DataPlaneRuntimeConfig {
+    /// Number of worker threads (default: half the available cores, at least 1)
+    pub worker_threads: usize,
+    /// Thread name prefix for data plane workers
+    pub thread_name: String,
+}
+
+impl Default for DataPlaneRuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: default_worker_threads(),
+            thread_name: "proxxy-data".to_string(),
+        }
+    }
+}
+
+impl DataPlaneRuntimeConfig {
+    /// Create a config with an explicit worker count (0 selects the default)
+    pub fn with_worker_threads(worker_threads: usize) -> Self {
+        Self {
+            worker_threads: if worker_threads == 0 { default_worker_threads() } else { worker_threads },
+            ..Default::default()
+        }
+    }
+}
+
+fn default_worker_threads() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| (n.get() / 2).max(1))
+        .unwrap_or(1)
+}
+
+/// Owned multi-threaded runtime dedicated to proxied traffic.
+///
+/// Dropping it shuts the runtime down in the background, so it is safe to drop
+/// from within another runtime's async context.
+pub struct DataPlaneRuntime {
+    runtime: Option<Runtime>,
+    worker_threads: usize,
+}
+
+impl DataPlaneRuntime {
+    /// Build the runtime
+    pub fn new(config: DataPlaneRuntimeConfig) -> Result<Self> {
+        if config.worker_threads == 0 {
+            return Err(ProxyError::Configuration(
+                "Data plane runtime requires at least one worker thread".to_string(),
+            ));
+        }
+
+        let thread_name = config.thread_name.clone();
+        let counter = AtomicUsize::new(0);
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(config.worker_threads)
+            .thread_name_fn(move || {
+                format!("{}-{}", thread_name, counter.fetch_add(1, Ordering::Relaxed))
+            })
+            .enable_all()
+            .build()
+            .map_err(ProxyError::Io)?;
+
+        info!(
+            "Data plane runtime started with {} worker thread(s)",
+            config.worker_threads
+        );
+
+        Ok(Self {
+            runtime: Some(runtime),
+            worker_threads: config.worker_threads,
+        })
+    }
+
+    /// Handle for spawning tasks onto the data plane
+    pub fn handle(&self) -> Handle {
+        self.runtime
+            .as_ref()
+            .expect("data plane runtime is alive until drop")
+            .handle()
+            .clone()
+    }
+
+    /// Number of worker threads
+    pub fn worker_threads(&self) -> usize {
+        self.worker_threads
+    }
+}
+
+impl Drop for DataPlaneRuntime {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_workers_uses_default() {
+        let config = DataPlaneRuntimeConfig::with_worker_threads(0);
+        assert!(config.worker_threads >= 1);
+        assert_eq!(DataPlaneRuntimeConfig::with_worker_threads(3).worker_threads, 3);
+    }
+
+    #[tokio::test]
+    async fn test_tasks_run_on_data_plane_threads() {
+        let runtime = DataPlaneRuntime::new(DataPlaneRuntimeConfig::with_worker_threads(1)).unwrap();
+
+        let thread_name = runtime
+            .handle()
+            .spawn(async { std::thread::current().name().map(|n| n.to_string()) })
+            .await
+            .unwrap();
+
+        assert!(thread_name.unwrap().starts_with("proxxy-data-"));
+
+        // Dropping inside an async context must not panic
+        drop(runtime);
+    }
+}
@@ -0,0 +1,407 @@
+//! Request/response hook scripts
+//!
+//! The orchestrator pushes Rhai scripts that see every in-scope request before
+//! it is forwarded and every response before it reaches the client. A script
+//! defines `on_request()` and/or `on_response()`; the message is bound to
+//! `this` as a map the hook may edit in place:
+//!
+//! ```rhai
+//! fn on_request() {
+//!     if this.url.contains("/logout") { return false; }   // drop
+//!     this.headers["x-pentest"] = "1";
+//!     this.tags.push("tagged-by-script");
+//! }
+//!
+//! fn on_response() {
+//!     this.body.replace("secure;", "");   // edits in place
+//! }
+//! ```
+//!
+//! Requests carry `method`, `url`, `headers`, `body` and `tags`; responses
+//! carry `status`, `headers`, `body` and `tags`. `body` is the raw bytes as
+//! text, or `()` when the body is too large to hand to scripts. Returning
+//! `false` drops the message. Scripts run in the order pushed, each seeing the
+//! previous one's edits; a script that fails is skipped for that message.
+//! Pushing a new set replaces the old one without restarting the proxy.
+
+use crate::error::ProxyError;
+use crate::pb::HookScripts;
+use crate::Result;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tracing::{debug, info, warn};
+
+const REQUEST_HOOK: &str = "on_request";
+const RESPONSE_HOOK: &str = "on_response";
+
+/// Operations a single hook call may run before it is aborted
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+const MAX_CALL_LEVELS: usize = 32;
+
+/// Largest string a hook may build; leaves room for a captured body decoded
+/// as text, which can grow when invalid UTF-8 is replaced
+const MAX_STRING_SIZE: usize = 32 * 1024 * 1024;
+
+/// Largest array or object map a hook may build
+const MAX_ARRAY_SIZE: usize = 100_000;
+const MAX_MAP_SIZE: usize = 10_000;
+
+/// Request as seen by hook scripts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    /// `None` when the body was not read for scripts
+    pub body: Option<Vec<u8>>,
+}
+
+/// Response as seen by hook scripts
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScriptResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    /// `None` when the body was not read for scripts
+    pub body: Option<Vec<u8>>,
+}
+
+/// What the hooks made of a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookOutcome<T> {
+    /// The message after every hook's edits
+    pub message: T,
+    pub dropped: bool,
+    /// Tags added by the hooks, deduplicated
+    pub tags: Vec<String>,
+}
+
+struct CompiledScript {
+    id: String,
+    ast: AST,
+    on_request: bool,
+    on_response: bool,
+}
+
+/// Hook scripts pushed by the orchestrator, shared by the proxy handlers
+#[derive(Clone)]
+pub struct ScriptController {
+    engine: Arc<Engine>,
+    scripts: Arc<RwLock<Arc<Vec<CompiledScript>>>>,
+}
+
+impl Default for ScriptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn new_engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(MAX_CALL_LEVELS);
+    engine.set_max_string_size(MAX_STRING_SIZE);
+    engine.set_max_array_size(MAX_ARRAY_SIZE);
+    engine.set_max_map_size(MAX_MAP_SIZE);
+    engine.on_print(|text| debug!("Hook script: {}", text));
+    engine.on_debug(|text, _, pos| debug!("Hook script {:?}: {}", pos, text));
+    engine
+}
+
+fn compile(engine: &Engine, id: &str, source: &str) -> Result<CompiledScript> {
+    let ast = engine
+        .compile(source)
+        .map_err(|e| ProxyError::Configuration(format!("Hook script '{}' does not compile: {}", id, e)))?;
+    let defines = |name: &str| ast.iter_functions().any(|f| f.name == name && f.params.is_empty());
+    let on_request = defines(REQUEST_HOOK);
+    let on_response = defines(RESPONSE_HOOK);
+    if !on_request && !on_response {
+        return Err(ProxyError::Configuration(format!(
+            "Hook script '{}' defines neither {}() nor {}()",
+            id, REQUEST_HOOK, RESPONSE_HOOK
+        )));
+    }
+
+    Ok(CompiledScript {
+        id: id.to_string(),
+        ast,
+        on_request,
+        on_response,
+    })
+}
+
+/// Check that `source` compiles and defines at least one hook
+pub fn validate_script(source: &str) -> Result<()> {
+    compile(&new_engine(), "script", source).map(|_| ())
+}
+
+impl ScriptController {
+    pub fn new() -> Self {
+        Self {
+            engine: Arc::new(new_engine()),
+            scripts: Arc::new(RwLock::new(Arc::new(Vec::new()))),
+        }
+    }
+
+    fn current(&self) -> Arc<Vec<CompiledScript>> {
+        self.scripts.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Replace the active scripts. Scripts that fail to compile are skipped
+    /// so one bad script does not disable the rest; their errors are returned.
+    pub fn set_scripts(&self, scripts: HookScripts) -> Vec<ProxyError> {
+        let mut compiled = Vec::new();
+        let mut errors = Vec::new();
+        for script in scripts.scripts {
+            match compile(&self.engine, &script.id, &script.source) {
+                Ok(script) => compiled.push(script),
+                Err(e) => {
+                    warn!("{}", e);
+                    errors.push(e);
+                }
+            }
+        }
+
+        info!("Loaded {} hook script(s)", compiled.len());
+        *self.scripts.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(compiled);
+        errors
+    }
+
+    pub fn has_request_hooks(&self) -> bool {
+        self.current().iter().any(|s| s.on_request)
+    }
+
+    pub fn has_response_hooks(&self) -> bool {
+        self.current().iter().any(|s| s.on_response)
+    }
+
+    pub fn run_request_hooks(&self, request: ScriptRequest) -> HookOutcome<ScriptRequest> {
+        let mut map = Map::new();
+        map.insert("method".into(), request.method.clone().into());
+        map.insert("url".into(), request.url.clone().into());
+        let (dropped, map, tags) = self.run_hooks(REQUEST_HOOK, map, &request.headers, request.body.as_deref());
+
+        let text = |key: &str, fallback: &str| {
+            map.get(key)
+                .and_then(|v| v.clone().into_string().ok())
+                .unwrap_or_else(|| fallback.to_string())
+        };
+        let message = ScriptRequest {
+            method: text("method", &request.method),
+            url: text("url", &request.url),
+            headers: headers_from_map(&map).unwrap_or_else(|| request.headers.clone()),
+            body: body_from_map(&map, request.body.clone()),
+        };
+        HookOutcome { message, dropped, tags }
+    }
+
+    pub fn run_response_hooks(&self, response: ScriptResponse) -> HookOutcome<ScriptResponse> {
+        let mut map = Map::new();
+        map.insert("status".into(), (response.status as i64).into());
+        let (dropped, map, tags) = self.run_hooks(RESPONSE_HOOK, map, &response.headers, response.body.as_deref());
+
+        let status = map
+            .get("status")
+            .and_then(|v| v.as_int().ok())
+            .and_then(|v| u16::try_from(v).ok())
+            .filter(|v| (100..1000).contains(v))
+            .unwrap_or(response.status);
+        let message = ScriptResponse {
+            status,
+            headers: headers_from_map(&map).unwrap_or_else(|| response.headers.clone()),
+            body: body_from_map(&map, response.body.clone()),
+        };
+        HookOutcome { message, dropped, tags }
+    }
+
+    /// Run `hook` of every script defining it on `map`, with headers, body and
+    /// tags added. Returns whether a script dropped the message, the edited
+    /// map and the tags.
+    fn run_hooks(
+        &self,
+        hook: &str,
+        mut map: Map,
+        headers: &HashMap<String, String>,
+        body: Option<&[u8]>,
+    ) -> (bool, Map, Vec<String>) {
+        let header_map: Map = headers.iter().map(|(k, v)| (k.as_str().into(), v.clone().into())).collect();
+        map.insert("headers".into(), header_map.into());
+        map.insert(
+            "body".into(),
+            body.map(|b| Dynamic::from(String::from_utf8_lossy(b).into_owned()))
+                .unwrap_or(Dynamic::UNIT),
+        );
+        map.insert("tags".into(), Array::new().into());
+
+        let mut this = Dynamic::from_map(map);
+        let mut dropped = false;
+        for script in self.current().iter() {
+            let defined = if hook == REQUEST_HOOK { script.on_request } else { script.on_response };
+            if !defined {
+                continue;
+            }
+            // Edits of a failed call are discarded with it
+            let mut attempt = this.clone();
+            let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut attempt);
+            match self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &script.ast, hook, ()) {
+                Ok(result) if attempt.is_map() => {
+                    this = attempt;
+                    if result.as_bool() == Ok(false) {
+                        debug!("Hook script '{}' dropped the message", script.id);
+                        dropped = true;
+                        break;
+                    }
+                }
+                Ok(_) => warn!("Hook script '{}' replaced `this` with a non-map; ignoring its edits", script.id),
+                Err(e) => warn!("Hook script '{}' failed in {}(): {}", script.id, hook, e),
+            }
+        }
+
+        let map = this.try_cast::<Map>().unwrap_or_default();
+        let mut tags: Vec<String> = Vec::new();
+        if let Some(array) = map.get("tags").and_then(|v| v.clone().try_cast::<Array>()) {
+            for tag in array {
+                let tag = tag.to_string();
+                let tag = tag.trim();
+                if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                    tags.push(tag.to_string());
+                }
+            }
+        }
+        (dropped, map, tags)
+    }
+}
+
+/// Headers as left by the scripts, `None` if they broke the map
+fn headers_from_map(map: &Map) -> Option<HashMap<String, String>> {
+    let headers = map.get("headers")?.clone().try_cast::<Map>()?;
+    Some(headers.into_iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+}
+
+/// Body as left by the scripts. The original bytes are kept unless the text
+/// changed, so binary bodies the scripts did not touch survive the lossy
+/// conversion.
+fn body_from_map(map: &Map, original: Option<Vec<u8>>) -> Option<Vec<u8>> {
+    let original = original?;
+    match map.get("body").and_then(|v| v.clone().into_string().ok()) {
+        Some(text) if text != String::from_utf8_lossy(&original) => Some(text.into_bytes()),
+        _ => Some(original),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::HookScript;
+
+    fn scripts(sources: &[(&str, &str)]) -> HookScripts {
+        HookScripts {
+            scripts: sources
+                .iter()
+                .map(|(id, source)| HookScript {
+                    id: id.to_string(),
+                    source: source.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    fn request(url: &str, body: &str) -> ScriptRequest {
+        ScriptRequest {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            headers: HashMap::from([("content-type".to_string(), "text/plain".to_string())]),
+            body: Some(body.as_bytes().to_vec()),
+        }
+    }
+
+    #[test]
+    fn test_validate_script() {
+        assert!(validate_script("fn on_request() { this.tags.push(\"a\"); }").is_ok());
+        assert!(validate_script("fn on_request( {").is_err());
+        assert!(validate_script("fn helper(x) { x }").is_err());
+    }
+
+    #[test]
+    fn test_request_hooks_modify_tag_and_drop() {
+        let controller = ScriptController::new();
+        let errors = controller.set_scripts(scripts(&[
+            (
+                "edit",
+                r#"
+                fn on_request() {
+                    this.headers["x-pentest"] = "1";
+                    this.body.replace("guest", "admin");
+                    this.tags.push("edited");
+                }
+                "#,
+            ),
+            ("drop-logout", r#"fn on_request() { if this.url.contains("/logout") { return false; } this.tags.push("kept"); }"#),
+            ("broken", "fn on_request() { this.missing.call(); }"),
+            ("bad", "fn on_request( {"),
+        ]));
+        assert_eq!(errors.len(), 1);
+        assert!(controller.has_request_hooks());
+        assert!(!controller.has_response_hooks());
+
+        let outcome = controller.run_request_hooks(request("https://a.test/login", "user=guest"));
+        assert!(!outcome.dropped);
+        assert_eq!(outcome.message.headers.get("x-pentest").map(String::as_str), Some("1"));
+        assert_eq!(outcome.message.body.as_deref(), Some(b"user=admin".as_slice()));
+        assert_eq!(outcome.message.method, "POST");
+        assert_eq!(outcome.tags, vec!["edited", "kept"]);
+
+        let outcome = controller.run_request_hooks(request("https://a.test/logout", ""));
+        assert!(outcome.dropped);
+        assert_eq!(outcome.tags, vec!["edited"]);
+    }
+
+    #[test]
+    fn test_oversized_values_abort_the_hook() {
+        let controller = ScriptController::new();
+        controller.set_scripts(scripts(&[
+            ("string", r#"fn on_request() { let s = "x"; for i in 0..30 { s += s; } this.tags.push("string"); }"#),
+            ("array", r#"fn on_request() { let a = [0]; for i in 0..20 { a += a; } this.tags.push("array"); }"#),
+        ]));
+
+        let outcome = controller.run_request_hooks(request("https://a.test/", "body"));
+        assert!(!outcome.dropped);
+        assert!(outcome.tags.is_empty());
+        assert_eq!(outcome.message.body.as_deref(), Some(b"body".as_slice()));
+    }
+
+    #[test]
+    fn test_response_hooks_and_hot_reload() {
+        let controller = ScriptController::new();
+        controller.set_scripts(scripts(&[(
+            "status",
+            "fn on_response() { if this.body == () { this.tags.push(\"unread\"); } else { this.status = 418; } }",
+        )]));
+
+        let binary = vec![0xff, 0xfe, 0x00];
+        let outcome = controller.run_response_hooks(ScriptResponse {
+            status: 200,
+            headers: HashMap::new(),
+            body: Some(binary.clone()),
+        });
+        assert_eq!(outcome.message.status, 418);
+        // Untouched binary bodies are not round-tripped through text
+        assert_eq!(outcome.message.body, Some(binary));
+
+        let outcome = controller.run_response_hooks(ScriptResponse {
+            status: 200,
+            ..Default::default()
+        });
+        assert_eq!(outcome.message.status, 200);
+        assert_eq!(outcome.tags, vec!["unread"]);
+
+        controller.set_scripts(HookScripts::default());
+        assert!(!controller.has_response_hooks());
+        let outcome = controller.run_response_hooks(ScriptResponse {
+            status: 200,
+            ..Default::default()
+        });
+        assert!(outcome.tags.is_empty());
+    }
+}
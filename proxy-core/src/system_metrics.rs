@@ -27,6 +27,11 @@ pub struct SystemMetricsCollectorConfig {
     pub include_disk_details: bool,
     /// Include process details (default: true)
     pub include_process_details: bool,
+    /// Echo endpoint answering with the caller's IP, as plain text or JSON
+    /// with an "ip" field (default: https://api.ipify.org)
+    pub egress_echo_url: String,
+    /// Seconds between egress IP checks, so NAT/VPN rotation is noticed (default: 60)
+    pub egress_check_interval_seconds: u64,
 }
 
 impl Default for SystemMetricsCollectorConfig {
@@ -37,6 +42,8 @@ impl Default for SystemMetricsCollectorConfig {
             include_network_details: true,
             include_disk_details: true,
             include_process_details: true,
+            egress_echo_url: "https://api.ipify.org".to_string(),
+            egress_check_interval_seconds: 60,
         }
     }
 }
@@ -60,7 +67,7 @@ pub struct SystemMetricsCollector {
     /// Process ID for collecting process-specific metrics
     process_id: u32,
     
-    // Public IP caching strategy
+    // Egress IP: last successful check, rechecked on an interval
     public_ip: Option<String>,
     public_ip_last_attempt: Option<Instant>,
     public_ip_retry_count: u32,
@@ -269,40 +276,44 @@ impl SystemMetricsCollector {
         }
     }
 
-    /// Resolve Public IP with caching and backoff
+    /// Check the egress IP against the echo endpoint: every
+    /// `egress_check_interval_seconds`, with backoff while checks fail.
+    /// The last known IP is kept until a check reports a different one.
     async fn resolve_public_ip(&mut self) {
-        if self.public_ip.is_some() {
-            return;
-        }
-
-        // Check backoff
         if let Some(last) = self.public_ip_last_attempt {
-            let backoff = Duration::from_secs(2u64.pow(self.public_ip_retry_count.min(6)));
-            if last.elapsed() < backoff {
+            let wait = if self.public_ip_retry_count > 0 {
+                Duration::from_secs(2u64.pow(self.public_ip_retry_count.min(6)))
+            } else {
+                Duration::from_secs(self.config.egress_check_interval_seconds)
+            };
+            if last.elapsed() < wait {
                 return;
             }
         }
 
-        info!("Attempting to resolve public IP...");
+        debug!("Checking egress IP against {}", self.config.egress_echo_url);
         self.public_ip_last_attempt = Some(Instant::now());
 
-        match self.http_client.get("https://api.ipify.org").send().await {
-            Ok(resp) => {
-                match resp.text().await {
-                    Ok(ip) => {
-                        let ip = ip.trim().to_string();
-                        info!("Resolved public IP: {}", ip);
-                        self.public_ip = Some(ip);
-                        self.public_ip_retry_count = 0;
-                    }
-                    Err(e) => {
-                        warn!("Failed to read IP from response: {}", e);
-                        self.public_ip_retry_count += 1;
-                    }
+        let result = match self.http_client.get(&self.config.egress_echo_url).send().await {
+            Ok(resp) => match resp.text().await {
+                Ok(body) => parse_echo_response(&body).ok_or_else(|| "response is not an IP address".to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+            Err(e) => Err(e.to_string()),
+        };
+
+        match result {
+            Ok(ip) => {
+                match &self.public_ip {
+                    Some(previous) if *previous == ip => {}
+                    Some(previous) => info!("Egress IP changed: {} -> {}", previous, ip),
+                    None => info!("Resolved egress IP: {}", ip),
                 }
+                self.public_ip = Some(ip);
+                self.public_ip_retry_count = 0;
             }
             Err(e) => {
-                warn!("Failed to resolve public IP: {}", e);
+                warn!("Egress IP check against {} failed: {}", self.config.egress_echo_url, e);
                 self.public_ip_retry_count += 1;
             }
         }
@@ -417,6 +428,17 @@ impl SystemMetricsCollector {
     }
 }
 
+/// IP address in an echo endpoint response: plain text ("203.0.113.7")
+/// or JSON with an "ip" field
+pub fn parse_echo_response(body: &str) -> Option<String> {
+    let body = body.trim();
+    let candidate = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(json) => json.get("ip")?.as_str()?.trim().to_string(),
+        Err(_) => body.to_string(),
+    };
+    candidate.parse::<std::net::IpAddr>().ok().map(|ip| ip.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -444,6 +466,14 @@ mod tests {
         assert!(metrics.memory_total_bytes > 0);
     }
 
+    #[test]
+    fn test_parse_echo_response() {
+        assert_eq!(parse_echo_response("203.0.113.7\n").as_deref(), Some("203.0.113.7"));
+        assert_eq!(parse_echo_response(r#"{"ip": "2001:db8::1"}"#).as_deref(), Some("2001:db8::1"));
+        assert_eq!(parse_echo_response("<html>blocked</html>"), None);
+        assert_eq!(parse_echo_response(r#"{"origin": "203.0.113.7"}"#), None);
+    }
+
     #[tokio::test]
     async fn test_config_update() {
         let mut collector = SystemMetricsCollector::new("test-agent".to_string());
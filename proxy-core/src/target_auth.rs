@@ -0,0 +1,602 @@
+//! HTTP Authentication to Targets
+//!
+//! Targets behind HTTP authentication would otherwise need an Authorization
+//! header in every request each tool sends. For hosts matching a configured
+//! pattern the agent adds it itself, unless the request already carries one:
+//!
+//! - Basic credentials are sent up front.
+//! - Digest needs a challenge. The request drawing the first 401 from a host
+//!   is sent again with the answer; the challenge is remembered and every
+//!   following request to the host carries a fresh digest (RFC 7616,
+//!   `qop=auth`, MD5 or SHA-256, `-sess` variants). A new challenge, e.g. a
+//!   stale nonce, replaces the remembered one.
+//! - NTLM authenticates a connection rather than a request. A request drawing
+//!   an `NTLM` 401 is sent again over a connection of its own, which carries
+//!   the NTLMv2 handshake and then the request.
+//!
+//! Either way the client only sees the response to the authenticated
+//! request. Requests are kept for resending only when their body is at most
+//! [`MAX_REPLAY_BODY_BYTES`]; a larger one gets the 401.
+
+use crate::ntlm;
+use crate::upstream::HostTlsConnector;
+use base64::Engine;
+use bytes::Bytes;
+use dashmap::DashMap;
+use hudsucker::hyper::header::{HeaderMap, AUTHORIZATION, CONNECTION, WWW_AUTHENTICATE};
+use hudsucker::hyper::{body, http::request, Body, Method, Request, Response, StatusCode, Uri};
+use md5::{Digest as _, Md5};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use wildmatch::WildMatch;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Largest request body kept so the request can be sent again after a 401
+pub const MAX_REPLAY_BODY_BYTES: u64 = 1024 * 1024;
+
+/// How credentials are presented
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetAuthScheme {
+    Basic,
+    Digest,
+    Ntlm,
+}
+
+/// Credentials for the hosts matching `host_pattern`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetAuthConfig {
+    /// Host pattern, `*` wildcards (e.g. `*.intranet.example`)
+    pub host_pattern: String,
+    pub scheme: TargetAuthScheme,
+    pub username: String,
+    pub password: String,
+    /// NTLM domain; a `DOMAIN\user` username works as well
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+impl TargetAuthConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.host_pattern.trim().is_empty() {
+            return Err("host_pattern must be set".to_string());
+        }
+        if self.username.is_empty() {
+            return Err("username must be set".to_string());
+        }
+        if self.scheme == TargetAuthScheme::Basic && self.username.contains(':') {
+            return Err("Basic usernames cannot contain ':'".to_string());
+        }
+        Ok(())
+    }
+
+    /// Domain and user name presented in an NTLM handshake
+    fn ntlm_identity(&self) -> (&str, &str) {
+        match &self.domain {
+            Some(domain) => (domain, &self.username),
+            None => self.username.split_once('\\').unwrap_or(("", &self.username)),
+        }
+    }
+}
+
+/// A request to a Digest or NTLM target, kept so a 401 can be answered by
+/// sending it again with credentials
+#[derive(Debug, Clone)]
+pub struct ReplayRequest {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl ReplayRequest {
+    /// `parts` must have an absolute URI, as forwarded requests do
+    pub fn new(parts: &request::Parts, body: Bytes) -> Self {
+        Self { method: parts.method.clone(), uri: parts.uri.clone(), headers: parts.headers.clone(), body }
+    }
+
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    fn build(&self, authorization: &str) -> Result<Request<Body>, BoxError> {
+        let mut request = Request::builder().method(self.method.clone()).uri(self.uri.clone());
+        if let Some(headers) = request.headers_mut() {
+            *headers = self.headers.clone();
+            headers.insert(AUTHORIZATION, authorization.parse()?);
+        }
+        Ok(request.body(Body::from(self.body.clone()))?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DigestAlgorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestAlgorithm {
+    fn from_token(token: Option<&str>) -> Option<Self> {
+        match token.map(str::to_ascii_uppercase).as_deref() {
+            None | Some("MD5") => Some(Self::Md5),
+            Some("MD5-SESS") => Some(Self::Md5Sess),
+            Some("SHA-256") => Some(Self::Sha256),
+            Some("SHA-256-SESS") => Some(Self::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    fn token(&self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Md5Sess => "MD5-sess",
+            Self::Sha256 => "SHA-256",
+            Self::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn hash(&self, input: &str) -> String {
+        let digest: Vec<u8> = match self {
+            Self::Md5 | Self::Md5Sess => Md5::digest(input.as_bytes()).to_vec(),
+            Self::Sha256 | Self::Sha256Sess => Sha256::digest(input.as_bytes()).to_vec(),
+        };
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// A Digest challenge and the number of times it has been answered
+#[derive(Debug, Clone)]
+struct DigestChallenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: DigestAlgorithm,
+    /// `qop=auth` offered; without it the RFC 2069 form is used
+    qop_auth: bool,
+    nonce_count: u32,
+}
+
+impl DigestChallenge {
+    /// `value` is a `WWW-Authenticate` value; `None` if it is not a usable
+    /// Digest challenge
+    fn parse(value: &str) -> Option<Self> {
+        let (scheme, params) = value.trim().split_once(char::is_whitespace)?;
+        if !scheme.eq_ignore_ascii_case("digest") {
+            return None;
+        }
+        let params = auth_params(params);
+        let qop_auth = match params.get("qop") {
+            Some(qop) => qop.split(',').any(|q| q.trim().eq_ignore_ascii_case("auth")),
+            None => false,
+        };
+        if params.contains_key("qop") && !qop_auth {
+            // Only auth-int offered, which needs the request body
+            return None;
+        }
+        Some(Self {
+            realm: params.get("realm")?.clone(),
+            nonce: params.get("nonce")?.clone(),
+            opaque: params.get("opaque").cloned(),
+            algorithm: DigestAlgorithm::from_token(params.get("algorithm").map(String::as_str))?,
+            qop_auth,
+            nonce_count: 0,
+        })
+    }
+
+    /// Authorization value for the next request with this challenge
+    fn answer(&mut self, username: &str, password: &str, method: &str, uri: &str, cnonce: &str) -> String {
+        self.nonce_count += 1;
+        let nc = format!("{:08x}", self.nonce_count);
+        let algorithm = self.algorithm;
+
+        let mut ha1 = algorithm.hash(&format!("{}:{}:{}", username, self.realm, password));
+        if matches!(algorithm, DigestAlgorithm::Md5Sess | DigestAlgorithm::Sha256Sess) {
+            ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, self.nonce, cnonce));
+        }
+        let ha2 = algorithm.hash(&format!("{}:{}", method, uri));
+        let response = if self.qop_auth {
+            algorithm.hash(&format!("{}:{}:{}:{}:auth:{}", ha1, self.nonce, nc, cnonce, ha2))
+        } else {
+            algorithm.hash(&format!("{}:{}:{}", ha1, self.nonce, ha2))
+        };
+
+        let mut value = format!(
+            "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", algorithm={}, response=\"{}\"",
+            quote_escape(username),
+            quote_escape(&self.realm),
+            quote_escape(&self.nonce),
+            quote_escape(uri),
+            algorithm.token(),
+            response
+        );
+        if self.qop_auth {
+            value.push_str(&format!(", qop=auth, nc={}, cnonce=\"{}\"", nc, cnonce));
+        }
+        if let Some(opaque) = &self.opaque {
+            value.push_str(&format!(", opaque=\"{}\"", quote_escape(opaque)));
+        }
+        value
+    }
+}
+
+fn quote_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// `name=value` parameters of a challenge, names lowercased. Values may be
+/// quoted and contain commas.
+fn auth_params(input: &str) -> HashMap<String, String> {
+    let mut params = HashMap::new();
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == ',') {
+            chars.next();
+        }
+        let name: String = std::iter::from_fn(|| chars.next_if(|c| *c != '=' && *c != ',')).collect();
+        if name.trim().is_empty() {
+            break;
+        }
+        if chars.next_if_eq(&'=').is_none() {
+            continue;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if_eq(&'"').is_some() {
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => value.extend(chars.next()),
+                    '"' => break,
+                    c => value.push(c),
+                }
+            }
+        } else {
+            value = std::iter::from_fn(|| chars.next_if(|c| *c != ',')).collect::<String>().trim().to_string();
+        }
+        params.insert(name.trim().to_ascii_lowercase(), value);
+    }
+    params
+}
+
+/// Adds Authorization headers for configured targets
+pub struct TargetAuthenticator {
+    targets: Vec<(WildMatch, TargetAuthConfig)>,
+    /// Last Digest challenge per host
+    challenges: DashMap<String, DigestChallenge>,
+    /// Opens the connections challenged requests are sent again over
+    connector: Option<HostTlsConnector>,
+}
+
+impl TargetAuthenticator {
+    pub fn new(configs: &[TargetAuthConfig]) -> Self {
+        Self {
+            targets: configs
+                .iter()
+                .map(|config| (WildMatch::new(&config.host_pattern.to_ascii_lowercase()), config.clone()))
+                .collect(),
+            challenges: DashMap::new(),
+            connector: None,
+        }
+    }
+
+    /// Answer 401s by sending the request again over connections from
+    /// `connector`
+    pub fn with_connector(mut self, connector: HostTlsConnector) -> Self {
+        self.connector = Some(connector);
+        self
+    }
+
+    fn target(&self, host: &str) -> Option<&TargetAuthConfig> {
+        let host = host.to_ascii_lowercase();
+        self.targets.iter().find(|(pattern, _)| pattern.matches(&host)).map(|(_, config)| config)
+    }
+
+    /// Authorization value for a request to `host`, if it has credentials
+    /// and, for Digest, a challenge to answer. `uri` is the request target
+    /// (path and query).
+    pub fn authorization(&self, host: &str, method: &str, uri: &str) -> Option<String> {
+        let target = self.target(host)?;
+        match target.scheme {
+            TargetAuthScheme::Basic => {
+                let credentials = format!("{}:{}", target.username, target.password);
+                Some(format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(credentials)))
+            }
+            TargetAuthScheme::Digest => {
+                let mut challenge = self.challenges.get_mut(&host.to_ascii_lowercase())?;
+                let cnonce = uuid::Uuid::new_v4().simple().to_string();
+                Some(challenge.answer(&target.username, &target.password, method, uri, &cnonce))
+            }
+            TargetAuthScheme::Ntlm => None,
+        }
+    }
+
+    /// Whether a 401 from `host` is answered by sending the request again,
+    /// so requests to it should be kept
+    pub fn answers_challenges(&self, host: &str) -> bool {
+        self.connector.is_some() && self.target(host).is_some_and(|t| t.scheme != TargetAuthScheme::Basic)
+    }
+
+    /// Answer the 401 `request` drew, whose `WWW-Authenticate` values are
+    /// `challenges`, by sending it again with credentials. Returns the
+    /// response to that, or `None` if the host's credentials do not answer
+    /// any of the challenges.
+    pub async fn answer_challenge(
+        &self,
+        request: &ReplayRequest,
+        challenges: &[String],
+    ) -> Option<Result<Response<Body>, BoxError>> {
+        let connector = self.connector.as_ref()?;
+        let host = request.uri.host()?;
+        let target = self.target(host)?;
+        match target.scheme {
+            TargetAuthScheme::Basic => None,
+            TargetAuthScheme::Digest => {
+                if !self.observe_challenge(host, challenges.iter().map(String::as_str)) {
+                    return None;
+                }
+                let path = request.uri.path_and_query().map(|p| p.as_str()).unwrap_or("/");
+                let authorization = self.authorization(host, request.method.as_str(), path)?;
+                Some(async { connector.pinned(&request.uri).await?.send(request.build(&authorization)?).await }.await)
+            }
+            TargetAuthScheme::Ntlm => {
+                if !challenges.iter().any(|c| c.trim().eq_ignore_ascii_case("NTLM")) {
+                    return None;
+                }
+                Some(ntlm_handshake(connector, target, request).await)
+            }
+        }
+    }
+
+    /// Remember the Digest challenge in a 401's `WWW-Authenticate` values.
+    /// Returns whether one was taken.
+    pub fn observe_challenge<'a>(&self, host: &str, challenges: impl IntoIterator<Item = &'a str>) -> bool {
+        if !self.target(host).is_some_and(|t| t.scheme == TargetAuthScheme::Digest) {
+            return false;
+        }
+        match challenges.into_iter().find_map(DigestChallenge::parse) {
+            Some(challenge) => {
+                self.challenges.insert(host.to_ascii_lowercase(), challenge);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Send `request` over a new connection that first authenticates with NTLM
+async fn ntlm_handshake(
+    connector: &HostTlsConnector,
+    target: &TargetAuthConfig,
+    request: &ReplayRequest,
+) -> Result<Response<Body>, BoxError> {
+    let base64 = base64::engine::general_purpose::STANDARD;
+    let mut connection = connector.pinned(&request.uri).await?;
+
+    let mut negotiate = request.build(&format!("NTLM {}", base64.encode(ntlm::negotiate_message())))?;
+    // The handshake needs the connection to stay open
+    negotiate.headers_mut().remove(CONNECTION);
+    let mut challenged = connection.send(negotiate).await?;
+    if challenged.status() != StatusCode::UNAUTHORIZED {
+        return Ok(challenged);
+    }
+    let challenge = challenged
+        .headers()
+        .get_all(WWW_AUTHENTICATE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|v| v.trim().strip_prefix("NTLM "))
+        .and_then(|token| base64.decode(token.trim()).ok())
+        .and_then(|message| ntlm::Challenge::parse(&message))
+        .ok_or("no NTLM challenge in the response to the negotiate message")?;
+    // Read the 401's body so the connection can carry the next request
+    body::to_bytes(challenged.body_mut()).await?;
+
+    let (domain, username) = target.ntlm_identity();
+    let authenticate =
+        ntlm::authenticate_message(&challenge, domain, username, &target.password, rand::random(), ntlm::filetime_now());
+    connection.send(request.build(&format!("NTLM {}", base64.encode(authenticate)))?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::DnsConfig;
+    use crate::upstream::upstream_connector;
+    use hudsucker::hyper::{self, service::service_fn};
+    use std::convert::Infallible;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+
+    fn config(scheme: TargetAuthScheme) -> TargetAuthConfig {
+        TargetAuthConfig {
+            host_pattern: "*.intranet.test".to_string(),
+            scheme,
+            username: "Mufasa".to_string(),
+            password: "Circle Of Life".to_string(),
+            domain: None,
+        }
+    }
+
+    #[test]
+    fn test_basic_is_sent_up_front() {
+        let auth = TargetAuthenticator::new(&[config(TargetAuthScheme::Basic)]);
+        assert_eq!(
+            auth.authorization("wiki.INTRANET.test", "GET", "/").as_deref(),
+            Some("Basic TXVmYXNhOkNpcmNsZSBPZiBMaWZl")
+        );
+        assert_eq!(auth.authorization("example.com", "GET", "/"), None);
+    }
+
+    #[test]
+    fn test_digest_answers_remembered_challenge() {
+        let auth = TargetAuthenticator::new(&[config(TargetAuthScheme::Digest)]);
+        assert_eq!(auth.authorization("app.intranet.test", "GET", "/dir/index.html"), None);
+
+        // RFC 7616 §3.9.1, MD5 variant
+        let challenge = r#"Digest realm="http-auth@example.org", qop="auth, auth-int", algorithm=MD5, nonce="7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v", opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#;
+        assert!(auth.observe_challenge("app.intranet.test", ["Basic realm=\"x\"", challenge]));
+
+        let mut parsed = DigestChallenge::parse(challenge).unwrap();
+        let value = parsed.answer(
+            "Mufasa",
+            "Circle of Life",
+            "GET",
+            "/dir/index.html",
+            "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ",
+        );
+        assert!(value.contains(r#"response="8ca523f5e9506fed4657c9700eebdbec""#), "{}", value);
+        assert!(value.contains("nc=00000001"));
+        assert!(value.contains(r#"opaque="FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS""#));
+
+        let first = auth.authorization("app.intranet.test", "GET", "/a").unwrap();
+        let second = auth.authorization("app.intranet.test", "GET", "/a").unwrap();
+        assert!(first.contains("nc=00000001") && second.contains("nc=00000002"));
+        assert!(first.starts_with(r#"Digest username="Mufasa", realm="http-auth@example.org""#));
+    }
+
+    #[test]
+    fn test_unusable_challenges_are_ignored() {
+        let auth = TargetAuthenticator::new(&[config(TargetAuthScheme::Digest)]);
+        assert!(!auth.observe_challenge("app.intranet.test", [r#"Digest realm="r", nonce="n", qop="auth-int""#]));
+        assert!(!auth.observe_challenge("app.intranet.test", [r#"Digest realm="r", nonce="n", algorithm=SHA-512-256"#]));
+        assert!(!auth.observe_challenge("other.test", [r#"Digest realm="r", nonce="n""#]));
+        assert!(auth.observe_challenge("app.intranet.test", [r#"Digest realm="r", nonce="n""#]));
+        let value = auth.authorization("app.intranet.test", "GET", "/").unwrap();
+        assert!(!value.contains("qop="));
+    }
+
+    fn local_authenticator(scheme: TargetAuthScheme, username: &str) -> TargetAuthenticator {
+        let connector = upstream_connector(
+            &DnsConfig::default(),
+            &HashMap::new(),
+            None,
+            Default::default(),
+            Default::default(),
+            &[],
+            &[],
+            true,
+        )
+        .unwrap();
+        let target = TargetAuthConfig {
+            host_pattern: "127.0.0.1".to_string(),
+            username: username.to_string(),
+            ..config(scheme)
+        };
+        TargetAuthenticator::new(&[target]).with_connector(connector)
+    }
+
+    fn replay(port: u16, body: &'static str) -> ReplayRequest {
+        let (parts, ()) = Request::post(format!("http://127.0.0.1:{}/dir?x=1", port)).body(()).unwrap().into_parts();
+        ReplayRequest::new(&parts, Bytes::from_static(body.as_bytes()))
+    }
+
+    /// Serve each connection with `handler`, given a flag private to the
+    /// connection; returns the port and the number of connections accepted
+    async fn serve<F>(handler: F) -> (u16, Arc<AtomicUsize>)
+    where
+        F: Fn(Request<Body>, &AtomicBool) -> Response<Body> + Send + Sync + Copy + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let flag = Arc::new(AtomicBool::new(false));
+                let service = service_fn(move |req| {
+                    let flag = flag.clone();
+                    async move { Ok::<_, Infallible>(handler(req, &flag)) }
+                });
+                tokio::spawn(hyper::server::conn::Http::new().http1_only(true).serve_connection(stream, service));
+            }
+        });
+        (port, connections)
+    }
+
+    fn unauthorized(challenge: &str) -> Response<Body> {
+        Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .header(WWW_AUTHENTICATE, challenge)
+            .body(Body::from("denied"))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_digest_challenge_is_answered() {
+        let (port, _) = serve(|req, _| match req.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok()) {
+            Some(value) if value.contains(r#"nonce="abc""#) && value.contains(r#"uri="/dir?x=1""#) => {
+                Response::new(Body::from("welcome"))
+            }
+            _ => unauthorized(r#"Digest realm="test", nonce="abc", qop="auth""#),
+        })
+        .await;
+        let auth = local_authenticator(TargetAuthScheme::Digest, "Mufasa");
+
+        let response = auth
+            .answer_challenge(&replay(port, "payload"), &[r#"Digest realm="test", nonce="abc", qop="auth""#.to_string()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body::to_bytes(response.into_body()).await.unwrap(), "welcome");
+        // Later requests answer the remembered challenge up front
+        assert!(auth.authorization("127.0.0.1", "GET", "/").is_some());
+        // Challenges the credentials cannot answer are left to the client
+        assert!(auth.answer_challenge(&replay(port, ""), &["Basic realm=\"x\"".to_string()]).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ntlm_handshake_is_pinned_to_one_connection() {
+        fn field(message: &[u8], at: usize) -> &[u8] {
+            let length = u16::from_le_bytes([message[at], message[at + 1]]) as usize;
+            let offset = u32::from_le_bytes(message[at + 4..at + 8].try_into().unwrap()) as usize;
+            &message[offset..offset + length]
+        }
+        let utf16 = |value: &str| value.encode_utf16().flat_map(u16::to_le_bytes).collect::<Vec<u8>>();
+
+        let (port, connections) = serve(move |req, challenged| {
+            let base64 = base64::engine::general_purpose::STANDARD;
+            let message = req
+                .headers()
+                .get(AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("NTLM "))
+                .and_then(|token| base64.decode(token).ok());
+            match message {
+                Some(message) if message[8] == 1 => {
+                    challenged.store(true, Ordering::SeqCst);
+                    let mut challenge = b"NTLMSSP\0".to_vec();
+                    challenge.extend_from_slice(&2u32.to_le_bytes());
+                    challenge.extend_from_slice(&[0; 8]);
+                    challenge.extend_from_slice(&0x0000_0201u32.to_le_bytes());
+                    challenge.extend_from_slice(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+                    unauthorized(&format!("NTLM {}", base64.encode(challenge)))
+                }
+                Some(message)
+                    if message[8] == 3
+                        && challenged.load(Ordering::SeqCst)
+                        && field(&message, 28) == utf16("CORP")
+                        && field(&message, 36) == utf16("Mufasa") =>
+                {
+                    Response::new(Body::from("welcome"))
+                }
+                _ => unauthorized("NTLM"),
+            }
+        })
+        .await;
+        let auth = local_authenticator(TargetAuthScheme::Ntlm, "CORP\\Mufasa");
+        assert!(auth.answers_challenges("127.0.0.1"));
+        assert_eq!(auth.authorization("127.0.0.1", "GET", "/"), None);
+
+        let response = auth.answer_challenge(&replay(port, "payload"), &["NTLM".to_string()]).await.unwrap().unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body::to_bytes(response.into_body()).await.unwrap(), "welcome");
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+}
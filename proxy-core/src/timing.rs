@@ -0,0 +1,86 @@
+//! Request phase timing
+//!
+//! Requests sent or relayed by an agent are timed where they happen, with a
+//! monotonic clock, and the phases travel with the response. The orchestrator
+//! only sees a response after a gRPC round trip, so its own clock cannot tell
+//! a server that took 5ms longer from a busier stream.
+
+use crate::pb::RequestTiming;
+use std::time::{Duration, Instant};
+
+/// Marks the phases of one request as they complete
+#[derive(Debug, Clone, Copy)]
+pub struct PhaseTimer {
+    started: Instant,
+    connect: Duration,
+    headers: Option<Instant>,
+}
+
+impl PhaseTimer {
+    /// Start timing a request about to be sent
+    pub fn start() -> Self {
+        Self::started_at(Instant::now())
+    }
+
+    pub fn started_at(started: Instant) -> Self {
+        Self {
+            started,
+            connect: Duration::ZERO,
+            headers: None,
+        }
+    }
+
+    /// Time spent opening the connection the request went out on
+    pub fn connected(&mut self, connect: Duration) {
+        self.connect = connect;
+    }
+
+    /// The response headers arrived
+    pub fn headers_received(&mut self) {
+        self.headers = Some(Instant::now());
+    }
+
+    /// Phases up to now; without response headers the whole wait counts as
+    /// time to first byte
+    pub fn finish(&self) -> RequestTiming {
+        self.finish_at(Instant::now())
+    }
+
+    fn finish_at(&self, finished: Instant) -> RequestTiming {
+        let headers = self.headers.unwrap_or(finished);
+        let waited = headers.saturating_duration_since(self.started);
+        RequestTiming {
+            connect_us: micros(self.connect.min(waited)),
+            ttfb_us: micros(waited.saturating_sub(self.connect)),
+            transfer_us: micros(finished.saturating_duration_since(headers)),
+            total_us: micros(finished.saturating_duration_since(self.started)),
+        }
+    }
+}
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros().try_into().unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phases_add_up() {
+        let started = Instant::now();
+        let mut timer = PhaseTimer::started_at(started);
+        timer.connected(Duration::from_millis(30));
+        timer.headers = Some(started + Duration::from_millis(250));
+
+        let timing = timer.finish_at(started + Duration::from_millis(400));
+        assert_eq!(timing.connect_us, 30_000);
+        assert_eq!(timing.ttfb_us, 220_000);
+        assert_eq!(timing.transfer_us, 150_000);
+        assert_eq!(timing.total_us, timing.connect_us + timing.ttfb_us + timing.transfer_us);
+
+        // No response: everything after connecting was waiting
+        let failed = PhaseTimer::started_at(started).finish_at(started + Duration::from_micros(1500));
+        assert_eq!((failed.ttfb_us, failed.transfer_us, failed.total_us), (1500, 0, 1500));
+    }
+}
@@ -0,0 +1,328 @@
+//! Per-host TLS settings for upstream connections
+//!
+//! A policy applies to the upstream hosts matching its pattern, the first
+//! match winning: it bounds the protocol versions offered, adds root
+//! certificates trusted next to the public ones (an internal CA, say), or turns
+//! certificate verification off for hosts with self-signed certificates. It
+//! can also shape the ClientHello like a browser's for hosts behind CDNs
+//! that block unfamiliar TLS fingerprints.
+//! Hosts matching no policy get the default behavior.
+//!
+//! The agent's TLS stack implements TLS 1.2 and 1.3 only; a bound of TLS 1.0
+//! or 1.1 is rejected when the policy is loaded rather than silently widened.
+
+use crate::{client_hello::ClientHelloProfile, pb, ProxyError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio_rustls::rustls;
+use wildmatch::WildMatch;
+
+/// Protocol version an upstream connection may negotiate
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TlsVersion {
+    #[serde(rename = "1.2")]
+    Tls12,
+    #[serde(rename = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    fn protocol(self) -> &'static rustls::SupportedProtocolVersion {
+        match self {
+            TlsVersion::Tls12 => &rustls::version::TLS12,
+            TlsVersion::Tls13 => &rustls::version::TLS13,
+        }
+    }
+}
+
+impl fmt::Display for TlsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlsVersion::Tls12 => write!(f, "1.2"),
+            TlsVersion::Tls13 => write!(f, "1.3"),
+        }
+    }
+}
+
+impl FromStr for TlsVersion {
+    type Err = ProxyError;
+
+    /// Accepts `1.2`, `TLSv1.2` or `TLS1.2` and the same for 1.3
+    fn from_str(s: &str) -> Result<Self> {
+        let version = s.trim().to_ascii_lowercase();
+        let version = version.trim_start_matches("tls").trim_start_matches('v');
+        match version {
+            "1.2" => Ok(TlsVersion::Tls12),
+            "1.3" => Ok(TlsVersion::Tls13),
+            "1.0" | "1.1" => Err(ProxyError::Configuration(format!(
+                "TLS {} is not supported by the agent; the oldest version available is 1.2",
+                version
+            ))),
+            _ => Err(ProxyError::Configuration(format!("Unknown TLS version '{}'", s))),
+        }
+    }
+}
+
+/// TLS settings for the upstream hosts matching `host_pattern`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TlsPolicyConfig {
+    /// Host pattern, `*` wildcards (e.g. `*.corp.example`)
+    pub host_pattern: String,
+    /// Oldest version offered; TLS 1.2 when unset
+    #[serde(default)]
+    pub min_version: Option<TlsVersion>,
+    /// Newest version offered; TLS 1.3 when unset
+    #[serde(default)]
+    pub max_version: Option<TlsVersion>,
+    /// Accept any server certificate
+    #[serde(default)]
+    pub skip_verification: bool,
+    /// PEM root certificates trusted in addition to the public roots
+    #[serde(default)]
+    pub extra_roots_pem: Option<String>,
+    /// Browser whose cipher and group preferences the ClientHello follows
+    #[serde(default)]
+    pub client_hello: Option<ClientHelloProfile>,
+}
+
+/// Parsed TLS policy, ready to build a client configuration from
+#[derive(Debug, Clone)]
+pub struct TlsPolicy {
+    host_pattern: String,
+    matcher: WildMatch,
+    versions: Vec<&'static rustls::SupportedProtocolVersion>,
+    skip_verification: bool,
+    extra_roots: Vec<rustls::Certificate>,
+    client_hello: Option<ClientHelloProfile>,
+}
+
+impl TlsPolicyConfig {
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.host_pattern.trim().is_empty() {
+            return Err("host_pattern must be set".to_string());
+        }
+        if let (Some(min), Some(max)) = (self.min_version, self.max_version) {
+            if min > max {
+                return Err(format!("min_version {} is newer than max_version {}", min, max));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<pb::TlsPolicy> for TlsPolicyConfig {
+    type Error = ProxyError;
+
+    fn try_from(policy: pb::TlsPolicy) -> Result<Self> {
+        let version = |value: &str| (!value.is_empty()).then(|| value.parse()).transpose();
+        Ok(Self {
+            min_version: version(&policy.min_version)?,
+            max_version: version(&policy.max_version)?,
+            skip_verification: policy.skip_verification,
+            extra_roots_pem: Some(policy.extra_roots_pem).filter(|pem| !pem.is_empty()),
+            client_hello: (!policy.client_hello.is_empty())
+                .then(|| policy.client_hello.parse())
+                .transpose()?,
+            host_pattern: policy.host_pattern,
+        })
+    }
+}
+
+impl From<&TlsPolicyConfig> for pb::TlsPolicy {
+    fn from(config: &TlsPolicyConfig) -> Self {
+        Self {
+            host_pattern: config.host_pattern.clone(),
+            min_version: config.min_version.map(|v| v.to_string()).unwrap_or_default(),
+            max_version: config.max_version.map(|v| v.to_string()).unwrap_or_default(),
+            skip_verification: config.skip_verification,
+            extra_roots_pem: config.extra_roots_pem.clone().unwrap_or_default(),
+            client_hello: config.client_hello.map(|profile| profile.to_string()).unwrap_or_default(),
+        }
+    }
+}
+
+impl TlsPolicy {
+    /// Check the version bounds and parse the extra roots
+    pub fn load(config: &TlsPolicyConfig) -> Result<Self> {
+        config.validate().map_err(ProxyError::Configuration)?;
+        let min = config.min_version.unwrap_or(TlsVersion::Tls12);
+        let max = config.max_version.unwrap_or(TlsVersion::Tls13);
+        let versions = [TlsVersion::Tls12, TlsVersion::Tls13]
+            .into_iter()
+            .filter(|version| (min..=max).contains(version))
+            .map(TlsVersion::protocol)
+            .collect();
+
+        let extra_roots = match &config.extra_roots_pem {
+            Some(pem) => {
+                let roots: Vec<_> = rustls_pemfile::certs(&mut pem.as_bytes())
+                    .map_err(|e| {
+                        ProxyError::Certificate(format!("TLS policy for {}: invalid PEM: {}", config.host_pattern, e))
+                    })?
+                    .into_iter()
+                    .map(rustls::Certificate)
+                    .collect();
+                if roots.is_empty() {
+                    return Err(ProxyError::Certificate(format!(
+                        "TLS policy for {}: no certificate in extra roots",
+                        config.host_pattern
+                    )));
+                }
+                roots
+            }
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            host_pattern: config.host_pattern.clone(),
+            matcher: WildMatch::new(&config.host_pattern.to_ascii_lowercase()),
+            versions,
+            skip_verification: config.skip_verification,
+            extra_roots,
+            client_hello: config.client_hello,
+        })
+    }
+
+    pub fn host_pattern(&self) -> &str {
+        &self.host_pattern
+    }
+
+    pub fn matches(&self, host: &str) -> bool {
+        self.matcher.matches(&host.to_ascii_lowercase())
+    }
+
+    pub fn skips_verification(&self) -> bool {
+        self.skip_verification
+    }
+
+    pub fn client_hello(&self) -> Option<ClientHelloProfile> {
+        self.client_hello
+    }
+
+    /// Start a client configuration with the policy's versions and trust
+    /// roots on top of `roots`; client authentication is left to the caller
+    pub fn client_config_builder(
+        &self,
+        mut roots: rustls::RootCertStore,
+    ) -> Result<rustls::ConfigBuilder<rustls::ClientConfig, rustls::client::WantsTransparencyPolicyOrClientCert>> {
+        let invalid = |reason: String| ProxyError::Certificate(format!("TLS policy for {}: {}", self.host_pattern, reason));
+        for root in &self.extra_roots {
+            roots.add(root).map_err(|e| invalid(format!("unusable root certificate: {}", e)))?;
+        }
+        let builder = match self.client_hello {
+            Some(profile) => rustls::ClientConfig::builder()
+                .with_cipher_suites(&profile.rustls_cipher_suites())
+                .with_kx_groups(&profile.rustls_kx_groups()),
+            None => rustls::ClientConfig::builder()
+                .with_safe_default_cipher_suites()
+                .with_safe_default_kx_groups(),
+        };
+        Ok(builder
+            .with_protocol_versions(&self.versions)
+            .map_err(|e| invalid(e.to_string()))?
+            .with_root_certificates(roots))
+    }
+
+    /// Turn off certificate verification on `config` if the policy says so.
+    /// Handshake signatures are still checked against the presented key.
+    pub fn apply_verification(&self, config: &mut rustls::ClientConfig) {
+        if self.skip_verification {
+            config.dangerous().set_certificate_verifier(Arc::new(AcceptAnyCertificate));
+        }
+    }
+}
+
+struct AcceptAnyCertificate;
+
+impl rustls::client::ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> std::result::Result<rustls::client::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::ServerCertVerified::assertion())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(host_pattern: &str) -> TlsPolicyConfig {
+        TlsPolicyConfig {
+            host_pattern: host_pattern.to_string(),
+            min_version: None,
+            max_version: None,
+            skip_verification: false,
+            extra_roots_pem: None,
+            client_hello: None,
+        }
+    }
+
+    #[test]
+    fn test_version_bounds() {
+        let policy = TlsPolicy::load(&config("*")).unwrap();
+        assert_eq!(policy.versions.len(), 2);
+
+        let legacy = TlsPolicy::load(&TlsPolicyConfig { max_version: Some(TlsVersion::Tls12), ..config("*") }).unwrap();
+        assert_eq!(legacy.versions, vec![&rustls::version::TLS12]);
+        let modern = TlsPolicy::load(&TlsPolicyConfig { min_version: Some(TlsVersion::Tls13), ..config("*") }).unwrap();
+        assert_eq!(modern.versions, vec![&rustls::version::TLS13]);
+
+        let inverted = TlsPolicyConfig {
+            min_version: Some(TlsVersion::Tls13),
+            max_version: Some(TlsVersion::Tls12),
+            ..config("*")
+        };
+        assert!(TlsPolicy::load(&inverted).is_err());
+
+        assert_eq!("TLSv1.3".parse::<TlsVersion>().unwrap(), TlsVersion::Tls13);
+        let error = "1.0".parse::<TlsVersion>().unwrap_err().to_string();
+        assert!(error.contains("not supported"), "{}", error);
+    }
+
+    #[test]
+    fn test_extra_roots_and_matching() {
+        let ca = rcgen::generate_simple_self_signed(vec!["ca.corp.example".to_string()]).unwrap();
+        let policy = TlsPolicy::load(&TlsPolicyConfig {
+            extra_roots_pem: Some(ca.serialize_pem().unwrap()),
+            skip_verification: true,
+            ..config("*.CORP.example")
+        })
+        .unwrap();
+        assert!(policy.matches("intranet.corp.example"));
+        assert!(!policy.matches("corp.example"));
+
+        let mut tls = policy
+            .client_config_builder(rustls::RootCertStore::empty())
+            .unwrap()
+            .with_no_client_auth();
+        policy.apply_verification(&mut tls);
+        assert!(policy.skips_verification());
+
+        let garbage = TlsPolicyConfig { extra_roots_pem: Some("not a certificate".to_string()), ..config("*") };
+        assert!(TlsPolicy::load(&garbage).is_err());
+    }
+
+    #[test]
+    fn test_proto_round_trip() {
+        let original = TlsPolicyConfig {
+            min_version: Some(TlsVersion::Tls12),
+            skip_verification: true,
+            client_hello: Some(ClientHelloProfile::Firefox),
+            ..config("legacy.example")
+        };
+        let parsed = TlsPolicyConfig::try_from(pb::TlsPolicy::from(&original)).unwrap();
+        assert_eq!(parsed, original);
+
+        let legacy = pb::TlsPolicy { min_version: "1.0".to_string(), ..pb::TlsPolicy::from(&original) };
+        assert!(TlsPolicyConfig::try_from(legacy).is_err());
+    }
+}
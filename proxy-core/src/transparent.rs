@@ -0,0 +1,344 @@
+//! Transparent Proxy Listener
+//!
+//! Intercepts clients that ignore proxy settings. The packet filter redirects
+//! their connections to this listener (iptables `REDIRECT`, pf `rdr-to` or
+//! `divert-to`), and the address they were headed for is read back from the
+//! socket: `SO_ORIGINAL_DST` on Linux, the local address of a diverted
+//! connection elsewhere. When it cannot be recovered, as with pf `rdr-to` or
+//! a client connecting to the listener itself, the server name the client
+//! asked for decides where a request goes, on its scheme's default port.
+//!
+//! The first byte of a connection tells TLS from plain HTTP. TLS is
+//! terminated with an agent CA certificate for the SNI of the client hello
+//! and the upstream is addressed by that name, so its certificate is still
+//! checked against it; plain HTTP requests go to their `Host`. Requests and
+//! responses are captured like proxied traffic, tagged `invisible`.
+
+use crate::ca::CertificateAuthority;
+use crate::error::ProxyError;
+use crate::pb::connection_event::Kind;
+use crate::pb::TrafficEvent;
+use crate::reverse::{default_listen_address, default_max_capture_bytes, tls_details, CertificateCache, Forwarder};
+use crate::upstream::UpstreamClient;
+use crate::Result;
+use hudsucker::hyper::{self, header, http::uri::Authority, service::service_fn, Body, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls;
+use tokio_rustls::LazyConfigAcceptor;
+use tracing::{debug, info, warn};
+
+/// First byte of a TLS handshake record
+const TLS_HANDSHAKE: u8 = 0x16;
+
+/// The transparent listener
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransparentListenerConfig {
+    #[serde(default = "default_listen_address")]
+    pub listen_address: String,
+    pub listen_port: u16,
+    /// Issue one `*.parent` certificate per domain instead of one per host
+    #[serde(default)]
+    pub wildcard_certificates: bool,
+    /// Largest request or response body captured; bodies are forwarded whole
+    #[serde(default = "default_max_capture_bytes")]
+    pub max_capture_bytes: usize,
+}
+
+impl TransparentListenerConfig {
+    pub fn new(listen_address: impl Into<String>, listen_port: u16) -> Self {
+        Self {
+            listen_address: listen_address.into(),
+            listen_port,
+            wildcard_certificates: false,
+            max_capture_bytes: default_max_capture_bytes(),
+        }
+    }
+
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.listen_port == 0 {
+            return Err("listen_port must be set".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Where a client's requests go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    /// Server name, or the original address in URL form (`[::1]` for IPv6)
+    pub host: String,
+    pub port: u16,
+}
+
+impl Destination {
+    /// The name the client asked for on the port it was headed for. Without
+    /// a name the original address is used; without an original destination
+    /// the port of the name, or `default_port`. `None` when neither a name
+    /// nor an original destination is known.
+    pub fn resolve(
+        name: Option<&str>,
+        name_port: Option<u16>,
+        original: Option<SocketAddr>,
+        default_port: u16,
+    ) -> Option<Self> {
+        let port = original.map(|addr| addr.port()).or(name_port).unwrap_or(default_port);
+        let host = match (name, original) {
+            (Some(name), _) if !name.is_empty() => name.to_ascii_lowercase(),
+            (_, Some(SocketAddr::V4(addr))) => addr.ip().to_string(),
+            (_, Some(SocketAddr::V6(addr))) => format!("[{}]", addr.ip()),
+            _ => return None,
+        };
+        Some(Self { host, port })
+    }
+
+    /// `scheme://host`, with the port unless it is the scheme's default
+    fn origin(&self, scheme: &str, default_port: u16) -> String {
+        if self.port == default_port {
+            format!("{}://{}", scheme, self.host)
+        } else {
+            self.upstream(scheme)
+        }
+    }
+
+    fn upstream(&self, scheme: &str) -> String {
+        format!("{}://{}:{}", scheme, self.host, self.port)
+    }
+}
+
+/// The original destination of a connection accepted on `local`, unless it
+/// was not redirected: a connection to the listener itself has none.
+pub fn redirected_destination(original: SocketAddr, local: SocketAddr, listen_port: u16) -> Option<SocketAddr> {
+    let to_listener = original.ip() == local.ip() && original.port() == listen_port;
+    (!to_listener).then_some(original)
+}
+
+/// Destination the netfilter `REDIRECT` rule rewrote
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn netfilter_destination(stream: &TcpStream, local: SocketAddr) -> Option<SocketAddr> {
+    let socket = socket2::SockRef::from(stream);
+    let original = if local.is_ipv6() {
+        socket.original_dst_ipv6()
+    } else {
+        socket.original_dst()
+    };
+    original.ok()?.as_socket()
+}
+
+/// pf `divert-to` keeps the original destination as the local address
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn netfilter_destination(_stream: &TcpStream, _local: SocketAddr) -> Option<SocketAddr> {
+    None
+}
+
+/// A bound transparent listener, ready to serve
+pub struct TransparentListener {
+    listener: TcpListener,
+    shared: Arc<ListenerShared>,
+}
+
+struct ListenerShared {
+    config: TransparentListenerConfig,
+    certificates: CertificateCache,
+    forwarder: Arc<Forwarder>,
+}
+
+impl TransparentListener {
+    /// Bind the listener. Certificates are issued on first use.
+    pub async fn bind(
+        config: TransparentListenerConfig,
+        ca: Arc<CertificateAuthority>,
+        client: UpstreamClient,
+        log_sender: Option<tokio::sync::mpsc::Sender<TrafficEvent>>,
+    ) -> Result<Self> {
+        config.validate().map_err(ProxyError::Configuration)?;
+
+        let addr = format!("{}:{}", config.listen_address, config.listen_port);
+        let listener = TcpListener::bind(&addr)
+            .await
+            .map_err(|e| ProxyError::Network(format!("Failed to bind transparent listener on {}: {}", addr, e)))?;
+
+        Ok(Self {
+            listener,
+            shared: Arc::new(ListenerShared {
+                certificates: CertificateCache::new(ca, config.wildcard_certificates),
+                forwarder: Arc::new(Forwarder::new(client, log_sender, config.max_capture_bytes)),
+                config,
+            }),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr().map_err(ProxyError::Io)
+    }
+
+    /// Accept and forward redirected clients until the task is dropped
+    pub async fn serve(self) {
+        info!(
+            "🪞 Transparent listener on {}:{}",
+            self.shared.config.listen_address, self.shared.config.listen_port
+        );
+        loop {
+            let (client, peer) = match self.listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Transparent listener accept failed: {}", e);
+                    continue;
+                }
+            };
+            let shared = self.shared.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(client, peer, shared).await {
+                    debug!("Transparent connection from {} ended: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(client: TcpStream, peer: SocketAddr, shared: Arc<ListenerShared>) -> Result<()> {
+    let local = client.local_addr().map_err(ProxyError::Io)?;
+    let original = netfilter_destination(&client, local).unwrap_or(local);
+    let original = redirected_destination(original, local, shared.config.listen_port);
+
+    let mut first = [0u8; 1];
+    if client.peek(&mut first).await.map_err(ProxyError::Io)? == 0 {
+        return Ok(());
+    }
+    if first[0] == TLS_HANDSHAKE {
+        serve_tls(client, peer, original, shared).await
+    } else {
+        serve_http(client, peer, original, shared).await
+    }
+}
+
+async fn serve_tls(
+    client: TcpStream,
+    peer: SocketAddr,
+    original: Option<SocketAddr>,
+    shared: Arc<ListenerShared>,
+) -> Result<()> {
+    let start = LazyConfigAcceptor::new(rustls::server::Acceptor::default(), client)
+        .await
+        .map_err(ProxyError::Io)?;
+    let server_name = start.client_hello().server_name().map(str::to_string);
+    let connection_log = &shared.forwarder.connection_log;
+
+    let Some(destination) = Destination::resolve(server_name.as_deref(), None, original, 443) else {
+        connection_log.record(Kind::Blocked, "(no SNI)", peer, "No server name or original destination", None);
+        return Ok(());
+    };
+    let certificate_host = destination.host.trim_start_matches('[').trim_end_matches(']').to_string();
+
+    let stream = start
+        .into_stream(shared.certificates.server_config(&certificate_host)?)
+        .await
+        .map_err(|e| {
+            connection_log.record(Kind::TlsFailure, &destination.host, peer, &e.to_string(), None);
+            ProxyError::Io(e)
+        })?;
+    let tls = tls_details(stream.get_ref().1);
+    debug!("Transparent TLS connection from {} to {}:{}", peer, destination.host, destination.port);
+
+    let origin = Arc::new(destination.origin("https", 443));
+    let upstream = Arc::new(destination.upstream("https"));
+    let forwarder = shared.forwarder.clone();
+    let service = service_fn(move |req| {
+        let (forwarder, origin, upstream, tls) = (forwarder.clone(), origin.clone(), upstream.clone(), tls.clone());
+        async move { Ok::<_, Infallible>(forwarder.forward(req, &origin, &upstream, Some(tls), peer, true).await) }
+    });
+    hyper::server::conn::Http::new()
+        .http1_only(true)
+        .serve_connection(stream, service)
+        .await
+        .map_err(|e| ProxyError::Network(e.to_string()))
+}
+
+async fn serve_http(
+    client: TcpStream,
+    peer: SocketAddr,
+    original: Option<SocketAddr>,
+    shared: Arc<ListenerShared>,
+) -> Result<()> {
+    debug!("Transparent HTTP connection from {} to {:?}", peer, original);
+    let forwarder = shared.forwarder.clone();
+    let service = service_fn(move |req: Request<Body>| {
+        let forwarder = forwarder.clone();
+        async move {
+            let host = req
+                .headers()
+                .get(header::HOST)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<Authority>().ok());
+            let destination = Destination::resolve(
+                host.as_ref().map(|authority| authority.host()),
+                host.as_ref().and_then(|authority| authority.port_u16()),
+                original,
+                80,
+            );
+            let response = match destination {
+                Some(destination) => {
+                    let origin = destination.origin("http", 80);
+                    forwarder.forward(req, &origin, &destination.upstream("http"), None, peer, true).await
+                }
+                None => {
+                    forwarder
+                        .connection_log
+                        .record(Kind::Blocked, "(no Host)", peer, "No Host header or original destination", None);
+                    Response::builder()
+                        .status(StatusCode::BAD_REQUEST)
+                        .body(Body::from("Request has no Host header"))
+                        .unwrap_or_default()
+                }
+            };
+            Ok::<_, Infallible>(response)
+        }
+    });
+    hyper::server::conn::Http::new()
+        .http1_only(true)
+        .serve_connection(client, service)
+        .await
+        .map_err(|e| ProxyError::Network(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_redirected_destination() {
+        let local = addr("10.0.0.2:9096");
+        assert_eq!(redirected_destination(addr("93.184.216.34:443"), local, 9096), Some(addr("93.184.216.34:443")));
+        // Connected to the listener directly, or redirected with pf rdr-to
+        assert_eq!(redirected_destination(local, local, 9096), None);
+        // pf divert-to keeps the original destination as the local address
+        let diverted = addr("93.184.216.34:8443");
+        assert_eq!(redirected_destination(diverted, diverted, 9096), Some(diverted));
+    }
+
+    #[test]
+    fn test_destination_resolution() {
+        let original = Some(addr("93.184.216.34:8443"));
+        let named = Destination::resolve(Some("API.example.com"), None, original, 443).unwrap();
+        assert_eq!(named, Destination { host: "api.example.com".to_string(), port: 8443 });
+        assert_eq!(named.origin("https", 443), "https://api.example.com:8443");
+
+        let unnamed = Destination::resolve(None, None, original, 443).unwrap();
+        assert_eq!(unnamed.upstream("https"), "https://93.184.216.34:8443");
+        let v6 = Destination::resolve(None, None, Some(addr("[2001:db8::1]:80")), 80).unwrap();
+        assert_eq!(v6.origin("http", 80), "http://[2001:db8::1]");
+
+        // Without an original destination the name's own port, or the default
+        let host_port = Destination::resolve(Some("example.com"), Some(8080), None, 80).unwrap();
+        assert_eq!(host_port.port, 8080);
+        assert_eq!(Destination::resolve(Some("example.com"), None, None, 443).unwrap().port, 443);
+        assert!(Destination::resolve(None, None, None, 443).is_none());
+    }
+}
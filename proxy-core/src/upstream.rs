@@ -0,0 +1,747 @@
+//! Upstream Connections
+//!
+//! Connector used when the agent cannot rely on hudsucker's default client:
+//! hosts are resolved through the host overrides and configured DNS servers,
+//! per-scope egress routes pick a fixed path for matching hosts, and an
+//! optional PAC script decides for the rest whether to go DIRECT or through an HTTP proxy.
+//! HTTPS destinations are tunnelled with CONNECT; plain HTTP requests are sent
+//! to the proxy in absolute form, as browsers do. SOCKS5 routes tunnel both.
+//! TLS handshakes follow the policy matching the host (versions, trusted
+//! roots, verification) and present the client certificate configured for it.
+//! The host's connection profile bounds the connect phase, the TLS handshake
+//! and the time a connection may sit without traffic.
+
+use crate::{
+    certificates::ClientCertificate,
+    dns::{CustomDnsResolver, DnsConfig},
+    pac::{PacEngine, ProxyChoice},
+    policy::{ConnectionPolicy, EgressPolicy, EgressRoute},
+    tls_policy::TlsPolicy,
+    Result,
+};
+use hudsucker::hyper::client::{
+    self,
+    connect::{Connected, Connection},
+    HttpConnector,
+};
+use hudsucker::hyper::header::{HeaderValue, HOST};
+use hudsucker::hyper::{Body, Client, Request, Response, Uri, Version};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder, MaybeHttpsStream};
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::time::{Instant, Sleep};
+use tokio_rustls::rustls;
+use tracing::{debug, warn};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Largest CONNECT response header accepted from a proxy
+const MAX_CONNECT_RESPONSE: usize = 8 * 1024;
+
+/// Time allowed to reach a PAC-selected proxy
+const PROXY_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub type UpstreamClient = Client<HostTlsConnector, Body>;
+
+/// Build the upstream client: equivalent to hudsucker's rustls client, but with
+/// custom resolution, egress routes, optional PAC-driven proxy selection, TLS
+/// policies, client certificates and per-scope connection profiles. HTTP/2 is
+/// offered via ALPN unless `http1_only` is set.
+#[allow(clippy::too_many_arguments)]
+pub fn upstream_client(
+    dns: &DnsConfig,
+    host_overrides: &HashMap<String, IpAddr>,
+    pac: Option<Arc<PacEngine>>,
+    egress: Arc<EgressPolicy>,
+    connection: Arc<ConnectionPolicy>,
+    tls_policies: &[TlsPolicy],
+    client_certs: &[ClientCertificate],
+    http1_only: bool,
+) -> Result<UpstreamClient> {
+    let connector =
+        upstream_connector(dns, host_overrides, pac, egress, connection, tls_policies, client_certs, http1_only)?;
+    Ok(pooled_client(connector))
+}
+
+/// The connector behind [`upstream_client`], for callers that also need
+/// connections of their own
+#[allow(clippy::too_many_arguments)]
+pub fn upstream_connector(
+    dns: &DnsConfig,
+    host_overrides: &HashMap<String, IpAddr>,
+    pac: Option<Arc<PacEngine>>,
+    egress: Arc<EgressPolicy>,
+    connection: Arc<ConnectionPolicy>,
+    tls_policies: &[TlsPolicy],
+    client_certs: &[ClientCertificate],
+    http1_only: bool,
+) -> Result<HostTlsConnector> {
+    let mut direct = HttpConnector::new_with_resolver(CustomDnsResolver::new(dns)?.with_overrides(host_overrides));
+    direct.enforce_http(false);
+    let connector = UpstreamConnector { direct, pac, egress, connection: connection.clone() };
+
+    // One connector per combination a host can select, built up front so a
+    // policy or certificate rustls rejects fails at startup
+    let policies = std::iter::once(None).chain(tls_policies.iter().map(Some));
+    let mut connectors = HashMap::new();
+    for (policy_index, policy) in policies.enumerate() {
+        let certs = std::iter::once(None).chain(client_certs.iter().map(Some));
+        for (cert_index, cert) in certs.enumerate() {
+            let tls = tls_config(policy, cert)?;
+            connectors.insert(
+                (policy_index.checked_sub(1), cert_index.checked_sub(1)),
+                https_connector(tls, connector.clone(), http1_only),
+            );
+        }
+    }
+    Ok(HostTlsConnector {
+        policies: Arc::new(tls_policies.to_vec()),
+        client_certs: Arc::new(client_certs.to_vec()),
+        connectors: Arc::new(connectors),
+        connection,
+    })
+}
+
+/// Pooled client over `connector`, keeping connections alive as its default
+/// connection profile allows
+pub fn pooled_client(connector: HostTlsConnector) -> UpstreamClient {
+    let keep_alive = connector.connection.default.keep_alive.clone();
+    Client::builder()
+        .http1_title_case_headers(true)
+        .http1_preserve_header_case(true)
+        .pool_max_idle_per_host(if keep_alive.enabled { keep_alive.max_idle_per_host } else { 0 })
+        .pool_idle_timeout(keep_alive.pool_idle_timeout())
+        .build(connector)
+}
+
+/// TLS settings of hyper-rustls' webpki client, adjusted by the host's policy
+/// and presenting its client certificate
+fn tls_config(policy: Option<&TlsPolicy>, cert: Option<&ClientCertificate>) -> Result<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        rustls::OwnedTrustAnchor::from_subject_spki_name_constraints(ta.subject, ta.spki, ta.name_constraints)
+    }));
+    let builder = match policy {
+        Some(policy) => policy.client_config_builder(roots)?,
+        None => rustls::ClientConfig::builder().with_safe_defaults().with_root_certificates(roots),
+    };
+    let mut tls = match cert {
+        Some(cert) => builder
+            .with_client_auth_cert(cert.chain().to_vec(), cert.key().clone())
+            .map_err(|e| {
+                crate::ProxyError::Certificate(format!("Client certificate for {}: {}", cert.host_pattern(), e))
+            })?,
+        None => builder.with_no_client_auth(),
+    };
+    if let Some(policy) = policy {
+        policy.apply_verification(&mut tls);
+    }
+    Ok(tls)
+}
+
+fn https_connector(
+    tls: rustls::ClientConfig,
+    connector: UpstreamConnector,
+    http1_only: bool,
+) -> HttpsConnector<UpstreamConnector> {
+    let builder = HttpsConnectorBuilder::new()
+        .with_tls_config(tls)
+        .https_or_http()
+        .enable_http1();
+    if http1_only {
+        builder.wrap_connector(connector)
+    } else {
+        builder.enable_http2().wrap_connector(connector)
+    }
+}
+
+/// HTTPS connector applying the TLS policy and presenting the client
+/// certificate configured for the destination host, if any
+#[derive(Clone)]
+pub struct HostTlsConnector {
+    /// In configuration order; the first matching pattern wins
+    policies: Arc<Vec<TlsPolicy>>,
+    /// Likewise
+    client_certs: Arc<Vec<ClientCertificate>>,
+    connectors: Arc<HashMap<ConnectorKey, HttpsConnector<UpstreamConnector>>>,
+    connection: Arc<ConnectionPolicy>,
+}
+
+/// Index of the TLS policy and of the client certificate a host selects
+type ConnectorKey = (Option<usize>, Option<usize>);
+
+impl HostTlsConnector {
+    fn connector_for(&self, host: &str) -> &HttpsConnector<UpstreamConnector> {
+        let policy = self.policies.iter().position(|policy| policy.matches(host));
+        let cert = self.client_certs.iter().position(|cert| cert.matches(host));
+        if let Some(policy) = policy {
+            debug!("Applying TLS policy for {} to {}", self.policies[policy].host_pattern(), host);
+        }
+        if let Some(cert) = cert {
+            debug!("Presenting client certificate for {} to {}", self.client_certs[cert].host_pattern(), host);
+        }
+        &self.connectors[&(policy, cert)]
+    }
+
+    /// Open a connection to `uri`'s host that is not shared with the pool,
+    /// for exchanges bound to one connection such as NTLM's handshake
+    pub async fn pinned(&self, uri: &Uri) -> std::result::Result<PinnedConnection, BoxError> {
+        let mut connector = self.clone();
+        std::future::poll_fn(|cx| tower::Service::<Uri>::poll_ready(&mut connector, cx)).await?;
+        let stream = tower::Service::call(&mut connector, uri.clone()).await?;
+        let connected = stream.connected();
+        let (sender, connection) = client::conn::Builder::new()
+            .http2_only(connected.is_negotiated_h2())
+            .http1_title_case_headers(true)
+            .http1_preserve_header_case(true)
+            .handshake::<_, Body>(stream)
+            .await?;
+        let host = uri.host().unwrap_or_default().to_string();
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("Pinned connection to {} closed: {}", host, e);
+            }
+        });
+        Ok(PinnedConnection { sender, http2: connected.is_negotiated_h2(), via_proxy: connected.is_proxied() })
+    }
+}
+
+/// One upstream connection; requests sent through it are not pooled
+pub struct PinnedConnection {
+    sender: client::conn::SendRequest<Body>,
+    http2: bool,
+    /// HTTP/1 requests keep absolute-form because the peer is a proxy
+    via_proxy: bool,
+}
+
+impl PinnedConnection {
+    /// Send `request`, given with an absolute URI, once the previous
+    /// exchange is done
+    pub async fn send(&mut self, mut request: Request<Body>) -> std::result::Result<Response<Body>, BoxError> {
+        std::future::poll_fn(|cx| self.sender.poll_ready(cx)).await?;
+        if self.http2 {
+            *request.version_mut() = Version::HTTP_2;
+        } else {
+            *request.version_mut() = Version::HTTP_11;
+            if let Some(authority) = request.uri().authority() {
+                let host = HeaderValue::from_str(authority.as_str())?;
+                request.headers_mut().entry(HOST).or_insert(host);
+            }
+            if !self.via_proxy {
+                let origin_form = request.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+                *request.uri_mut() = origin_form.parse()?;
+            }
+        }
+        Ok(self.sender.send_request(request).await?)
+    }
+}
+
+impl tower::Service<Uri> for HostTlsConnector {
+    type Response = MaybeHttpsStream<UpstreamStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        // Every connector wraps a clone of the same TCP connector, which is
+        // always ready
+        self.connectors[&(None, None)].clone().poll_ready(cx)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let host = dst.host().unwrap_or_default();
+        let setup_timeout = self.connection.profile_for_host(host).setup_timeout();
+        let connecting = self.connector_for(host).clone().call(dst.clone());
+
+        Box::pin(async move {
+            match setup_timeout {
+                Some(limit) => tokio::time::timeout(limit, connecting)
+                    .await
+                    .map_err(|_| format!("TLS handshake with {} timed out after {:?}", dst, limit))?,
+                None => connecting.await,
+            }
+        })
+    }
+}
+
+/// TCP connector choosing between a direct connection and a proxy per destination
+#[derive(Clone)]
+pub struct UpstreamConnector {
+    direct: HttpConnector<CustomDnsResolver>,
+    pac: Option<Arc<PacEngine>>,
+    egress: Arc<EgressPolicy>,
+    connection: Arc<ConnectionPolicy>,
+}
+
+/// Connection choice for an egress route. Tunnel routes are resolved by the
+/// agent; one still present here has no usable tunnel.
+fn egress_choice(route: &EgressRoute) -> std::result::Result<ProxyChoice, BoxError> {
+    match route {
+        EgressRoute::Direct => Ok(ProxyChoice::Direct),
+        EgressRoute::HttpProxy { host, port } => Ok(ProxyChoice::Http { host: host.clone(), port: *port }),
+        EgressRoute::Socks5 { host, port } => Ok(ProxyChoice::Socks5 { host: host.clone(), port: *port }),
+        EgressRoute::Tunnel { name } => Err(format!("egress tunnel '{}' is not available", name).into()),
+    }
+}
+
+impl tower::Service<Uri> for UpstreamConnector {
+    type Response = UpstreamStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        tower::Service::<Uri>::poll_ready(&mut self.direct, cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let mut direct = self.direct.clone();
+        let pac = self.pac.clone();
+        let egress = self.egress.clone();
+        let profile = self.connection.profile_for_host(dst.host().unwrap_or_default()).clone();
+
+        Box::pin(async move {
+            let route = dst.host().and_then(|host| egress.route_for_host(host));
+            let choices = match (route, &pac) {
+                (Some(route), _) => vec![egress_choice(route)?],
+                (None, Some(pac)) => pac.find_proxy(&dst.to_string()).await?,
+                (None, None) => vec![ProxyChoice::Direct],
+            };
+
+            let mut last_error: Option<BoxError> = None;
+            for choice in choices {
+                let connecting = async {
+                    match &choice {
+                        ProxyChoice::Direct => tower::Service::call(&mut direct, dst.clone())
+                            .await
+                            .map(|stream| UpstreamStream::new(stream, false))
+                            .map_err(BoxError::from),
+                        ProxyChoice::Http { host, port } => connect_via_proxy(host, *port, &dst).await,
+                        ProxyChoice::Socks5 { host, port } => connect_via_socks5(host, *port, &dst).await,
+                    }
+                };
+                // Each choice gets the full budget, tunnel handshakes included
+                let attempt = match profile.connect_timeout() {
+                    Some(limit) => tokio::time::timeout(limit, connecting)
+                        .await
+                        .unwrap_or_else(|_| Err(format!("connect timed out after {:?}", limit).into())),
+                    None => connecting.await,
+                };
+
+                match attempt {
+                    Ok(stream) => return Ok(stream.with_idle_timeout(profile.idle_timeout())),
+                    Err(e) => {
+                        warn!("Upstream {} for {} failed: {}", choice, dst, e);
+                        last_error = Some(e);
+                    }
+                }
+            }
+
+            Err(last_error.unwrap_or_else(|| "no upstream available".into()))
+        })
+    }
+}
+
+async fn connect_via_proxy(
+    proxy_host: &str,
+    proxy_port: u16,
+    dst: &Uri,
+) -> std::result::Result<UpstreamStream, BoxError> {
+    let mut stream = tokio::time::timeout(
+        PROXY_CONNECT_TIMEOUT,
+        TcpStream::connect((proxy_host, proxy_port)),
+    )
+    .await
+    .map_err(|_| format!("timed out connecting to proxy {}:{}", proxy_host, proxy_port))??;
+    let _ = stream.set_nodelay(true);
+
+    // Plain HTTP goes to the proxy as absolute-form requests on this connection
+    if dst.scheme_str() != Some("https") {
+        return Ok(UpstreamStream::new(stream, true));
+    }
+
+    let host = dst.host().ok_or("destination URI has no host")?;
+    let port = dst.port_u16().unwrap_or(443);
+    let authority = if host.contains(':') && !host.starts_with('[') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    };
+
+    let request = format!(
+        "CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\nProxy-Connection: Keep-Alive\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::with_capacity(512);
+    let mut byte = [0u8; 1];
+    // Byte-wise so no tunnelled data is consumed past the header
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err("proxy CONNECT response too large".into());
+        }
+        if stream.read(&mut byte).await? == 0 {
+            return Err("proxy closed the connection during CONNECT".into());
+        }
+        response.push(byte[0]);
+    }
+
+    let status_line = String::from_utf8_lossy(&response);
+    let status_line = status_line.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(format!("proxy refused CONNECT {}: {}", authority, status_line).into());
+    }
+
+    debug!("Tunnel to {} established via {}:{}", authority, proxy_host, proxy_port);
+    Ok(UpstreamStream::new(stream, false))
+}
+
+/// Open a SOCKS5 tunnel (no authentication) to the destination. The proxy
+/// resolves the hostname, so names only known inside the tunnelled network work.
+async fn connect_via_socks5(
+    proxy_host: &str,
+    proxy_port: u16,
+    dst: &Uri,
+) -> std::result::Result<UpstreamStream, BoxError> {
+    let mut stream = tokio::time::timeout(
+        PROXY_CONNECT_TIMEOUT,
+        TcpStream::connect((proxy_host, proxy_port)),
+    )
+    .await
+    .map_err(|_| format!("timed out connecting to SOCKS proxy {}:{}", proxy_host, proxy_port))??;
+    let _ = stream.set_nodelay(true);
+
+    let host = dst.host().ok_or("destination URI has no host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = dst
+        .port_u16()
+        .unwrap_or(if dst.scheme_str() == Some("https") { 443 } else { 80 });
+
+    stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    let mut greeting = [0u8; 2];
+    stream.read_exact(&mut greeting).await?;
+    if greeting != [0x05, 0x00] {
+        return Err("SOCKS proxy requires an unsupported authentication method".into());
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let name = host.as_bytes();
+            if name.len() > 255 {
+                return Err("destination hostname too long for SOCKS5".into());
+            }
+            request.push(0x03);
+            request.push(name.len() as u8);
+            request.extend_from_slice(name);
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(format!("SOCKS proxy refused {}:{} (reply code {})", host, port, reply[1]).into());
+    }
+    // Skip the bound address that follows the reply header
+    let bound_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            len[0] as usize
+        }
+        other => return Err(format!("SOCKS proxy sent unknown address type {}", other).into()),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    debug!("SOCKS5 tunnel to {}:{} established via {}:{}", host, port, proxy_host, proxy_port);
+    Ok(UpstreamStream::new(stream, false))
+}
+
+/// TCP stream to the destination or to a forwarding proxy
+pub struct UpstreamStream {
+    stream: TcpStream,
+    /// Requests must use absolute-form because the peer is a proxy
+    via_proxy: bool,
+    /// Fails the stream once no bytes moved in either direction for the
+    /// host's idle timeout
+    idle: Option<IdleTimer>,
+}
+
+struct IdleTimer {
+    limit: Duration,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl UpstreamStream {
+    fn new(stream: TcpStream, via_proxy: bool) -> Self {
+        Self { stream, via_proxy, idle: None }
+    }
+
+    fn with_idle_timeout(mut self, limit: Option<Duration>) -> Self {
+        self.idle = limit.map(|limit| IdleTimer { limit, sleep: Box::pin(tokio::time::sleep(limit)) });
+        self
+    }
+
+    /// Restart the idle timer after progress, or fail a pending operation
+    /// whose timer ran out
+    fn check_idle<T>(&mut self, cx: &mut Context<'_>, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        let Some(idle) = &mut self.idle else {
+            return poll;
+        };
+        match poll {
+            Poll::Ready(result) => {
+                idle.sleep.as_mut().reset(Instant::now() + idle.limit);
+                Poll::Ready(result)
+            }
+            Poll::Pending => match idle.sleep.as_mut().poll(cx) {
+                Poll::Ready(()) => Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!("no upstream traffic for {:?}", idle.limit),
+                ))),
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+}
+
+impl Connection for UpstreamStream {
+    fn connected(&self) -> Connected {
+        self.stream.connected().proxy(self.via_proxy)
+    }
+}
+
+impl AsyncRead for UpstreamStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let poll = Pin::new(&mut self.stream).poll_read(cx, buf);
+        self.check_idle(cx, poll)
+    }
+}
+
+impl AsyncWrite for UpstreamStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.stream).poll_write(cx, buf);
+        self.check_idle(cx, poll)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.stream).poll_write_vectored(cx, bufs);
+        self.check_idle(cx, poll)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.stream.is_write_vectored()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::{ConnectionProfile, EgressRule, ScopedConnectionProfile};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_connect_tunnel_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 1024];
+            let n = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\ntunnel")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&buf[..n]).to_string()
+        });
+
+        let dst: Uri = "https://example.com/".parse().unwrap();
+        let mut stream = connect_via_proxy("127.0.0.1", port, &dst).await.unwrap();
+        assert!(!stream.via_proxy);
+
+        let request = proxy.await.unwrap();
+        assert!(request.starts_with("CONNECT example.com:443 HTTP/1.1\r\n"));
+
+        // Bytes after the CONNECT response belong to the tunnel
+        let mut tunnelled = [0u8; 6];
+        stream.read_exact(&mut tunnelled).await.unwrap();
+        assert_eq!(&tunnelled, b"tunnel");
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused_by_proxy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 1024];
+            let _ = socket.read(&mut buf).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let dst: Uri = "https://example.com/".parse().unwrap();
+        let err = connect_via_proxy("127.0.0.1", port, &dst).await.err().unwrap();
+        assert!(err.to_string().contains("407"));
+    }
+
+    #[tokio::test]
+    async fn test_socks5_handshake() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let proxy = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            socket.read_exact(&mut greeting).await.unwrap();
+            socket.write_all(&[0x05, 0x00]).await.unwrap();
+
+            let mut head = [0u8; 5];
+            socket.read_exact(&mut head).await.unwrap();
+            let mut rest = vec![0u8; head[4] as usize + 2];
+            socket.read_exact(&mut rest).await.unwrap();
+            socket
+                .write_all(&[0x05, 0x00, 0x00, 0x01, 10, 0, 0, 1, 0x1f, 0x90])
+                .await
+                .unwrap();
+            socket.write_all(b"tunnel").await.unwrap();
+            (head, rest)
+        });
+
+        let dst: Uri = "https://admin.corp.local/".parse().unwrap();
+        let mut stream = connect_via_socks5("127.0.0.1", port, &dst).await.unwrap();
+        assert!(!stream.via_proxy);
+
+        let (head, rest) = proxy.await.unwrap();
+        assert_eq!(&head[..4], &[0x05, 0x01, 0x00, 0x03]);
+        assert_eq!(&rest[..rest.len() - 2], b"admin.corp.local");
+        assert_eq!(&rest[rest.len() - 2..], &443u16.to_be_bytes());
+
+        let mut tunnelled = [0u8; 6];
+        stream.read_exact(&mut tunnelled).await.unwrap();
+        assert_eq!(&tunnelled, b"tunnel");
+    }
+
+    #[test]
+    fn test_unresolved_tunnel_route_fails() {
+        let route = EgressRoute::Tunnel { name: "dmz".to_string() };
+        assert!(egress_choice(&route).unwrap_err().to_string().contains("dmz"));
+        assert_eq!(egress_choice(&EgressRoute::Direct).unwrap(), ProxyChoice::Direct);
+    }
+
+    #[tokio::test]
+    async fn test_plain_http_uses_absolute_form() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let _ = listener.accept().await;
+        });
+
+        let dst: Uri = "http://example.com/".parse().unwrap();
+        let stream = connect_via_proxy("127.0.0.1", port, &dst).await.unwrap();
+        assert!(stream.connected().is_proxied());
+    }
+
+    #[tokio::test]
+    async fn test_short_connect_timeout_aborts_request() {
+        // A proxy that accepts the connection but never answers the CONNECT
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (_socket, _) = listener.accept().await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let egress = EgressPolicy {
+            routes: vec![EgressRule {
+                hosts: vec!["*".to_string()],
+                route: EgressRoute::HttpProxy { host: "127.0.0.1".to_string(), port },
+            }],
+        };
+        let connection = ConnectionPolicy {
+            scopes: vec![ScopedConnectionProfile {
+                hosts: vec!["slow.test".to_string()],
+                profile: ConnectionProfile { connect_timeout_ms: 200, ..Default::default() },
+            }],
+            ..Default::default()
+        };
+        let client = upstream_client(
+            &DnsConfig::default(),
+            &HashMap::new(),
+            None,
+            Arc::new(egress),
+            Arc::new(connection),
+            &[],
+            &[],
+            true,
+        )
+        .unwrap();
+
+        let started = std::time::Instant::now();
+        let request = client.get("https://slow.test/".parse().unwrap());
+        let err = tokio::time::timeout(Duration::from_secs(5), request)
+            .await
+            .expect("connect timeout did not fire")
+            .unwrap_err();
+        assert!(err.is_connect());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_idle_stream_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.write_all(b"first").await.unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = UpstreamStream::new(stream, false).with_idle_timeout(Some(Duration::from_millis(100)));
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
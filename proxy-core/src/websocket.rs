@@ -0,0 +1,527 @@
+//! WebSocket permessage-deflate (RFC 7692)
+//!
+//! Negotiation parameters and the per-message codec: a compressed message is
+//! a raw DEFLATE stream flushed with a sync flush, minus the trailing
+//! `00 00 ff ff`. Unless a side negotiated `no_context_takeover`, the sliding
+//! window carries over from one message to the next, so each direction of a
+//! connection needs its own [`MessageInflater`] / [`MessageDeflater`].
+//!
+//! hudsucker's relay parses frames with tungstenite, which rejects frames with
+//! RSV1 set. Upgrades offering the extension are therefore relayed by
+//! [`relay`]: both legs negotiate the same parameters, compressed messages
+//! are inflated for capture and deflated again before they are forwarded.
+//! Scopes whose connection profile strips the extension, and upgrades outside
+//! the capture scope, fall back to hudsucker with an uncompressed session.
+
+use crate::pb::{traffic_event, TrafficEvent, WebSocketFrame};
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use hudsucker::tokio_tungstenite::tungstenite::protocol::frame::{
+    coding::{Data, OpCode},
+    FrameHeader,
+};
+use std::io::{self, Cursor};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// Extension token in `Sec-WebSocket-Extensions`
+pub const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// Appended before inflating and removed after deflating each message
+const SYNC_FLUSH_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Largest window flate2 can always inflate with
+pub const MAX_WINDOW_BITS: u8 = 15;
+
+/// Largest message the relay reassembles, tungstenite's default as well
+pub const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Negotiated permessage-deflate parameters
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeflateParams {
+    pub server_no_context_takeover: bool,
+    pub client_no_context_takeover: bool,
+    pub server_max_window_bits: Option<u8>,
+    /// In an offer the bare parameter (no value) only says the client
+    /// supports it; that is kept as `Some(15)`
+    pub client_max_window_bits: Option<u8>,
+}
+
+impl DeflateParams {
+    /// Parameters of the first permessage-deflate entry of a
+    /// `Sec-WebSocket-Extensions` value, `None` if there is none or it is
+    /// malformed
+    pub fn from_header(value: &str) -> Option<Self> {
+        value
+            .split(',')
+            .map(|extension| extension.split(';').map(str::trim))
+            .find_map(|mut parts| {
+                if !parts.next()?.eq_ignore_ascii_case(PERMESSAGE_DEFLATE) {
+                    return None;
+                }
+                Some(Self::from_parameters(parts))
+            })?
+    }
+
+    fn from_parameters<'a>(parameters: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut params = DeflateParams::default();
+        for parameter in parameters.filter(|p| !p.is_empty()) {
+            let (name, value) = match parameter.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (parameter, None),
+            };
+            let bits = |value: Option<&str>| -> Option<u8> {
+                value.and_then(|v| v.parse::<u8>().ok()).filter(|bits| (8..=MAX_WINDOW_BITS).contains(bits))
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "server_no_context_takeover" => params.server_no_context_takeover = true,
+                "client_no_context_takeover" => params.client_no_context_takeover = true,
+                "server_max_window_bits" => params.server_max_window_bits = Some(bits(value)?),
+                "client_max_window_bits" => {
+                    params.client_max_window_bits = Some(match value {
+                        Some(_) => bits(value)?,
+                        None => MAX_WINDOW_BITS,
+                    })
+                }
+                // Unknown parameters make the whole offer invalid (RFC 7692 §5)
+                _ => return None,
+            }
+        }
+        Some(params)
+    }
+
+    /// Header value for these parameters
+    pub fn to_header(&self) -> String {
+        let mut header = PERMESSAGE_DEFLATE.to_string();
+        if self.server_no_context_takeover {
+            header.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            header.push_str("; client_no_context_takeover");
+        }
+        if let Some(bits) = self.server_max_window_bits {
+            header.push_str(&format!("; server_max_window_bits={}", bits));
+        }
+        if let Some(bits) = self.client_max_window_bits {
+            header.push_str(&format!("; client_max_window_bits={}", bits));
+        }
+        header
+    }
+
+    /// Decoder for the messages the client sends
+    pub fn client_inflater(&self) -> MessageInflater {
+        MessageInflater::new(self.client_no_context_takeover)
+    }
+
+    /// Decoder for the messages the server sends
+    pub fn server_inflater(&self) -> MessageInflater {
+        MessageInflater::new(self.server_no_context_takeover)
+    }
+
+    /// Encoder for messages sent to the server in place of the client.
+    /// flate2 only deflates with the full window, so this is `None` when the
+    /// server limited the client's window.
+    pub fn client_deflater(&self) -> Option<MessageDeflater> {
+        let limited = self.client_max_window_bits.is_some_and(|bits| bits < MAX_WINDOW_BITS);
+        (!limited).then(|| MessageDeflater::new(self.client_no_context_takeover))
+    }
+
+    /// Encoder for messages sent to the client in place of the server
+    pub fn server_deflater(&self) -> Option<MessageDeflater> {
+        let limited = self.server_max_window_bits.is_some_and(|bits| bits < MAX_WINDOW_BITS);
+        (!limited).then(|| MessageDeflater::new(self.server_no_context_takeover))
+    }
+}
+
+/// Inflates the compressed messages of one direction
+pub struct MessageInflater {
+    inflate: Decompress,
+    no_context_takeover: bool,
+}
+
+impl MessageInflater {
+    pub fn new(no_context_takeover: bool) -> Self {
+        Self {
+            inflate: Decompress::new(false),
+            no_context_takeover,
+        }
+    }
+
+    /// Payload of a message that arrived with RSV1 set; fails once the
+    /// inflated message passes `max_size`
+    pub fn inflate(&mut self, payload: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+        let mut input = Vec::with_capacity(payload.len() + SYNC_FLUSH_TAIL.len());
+        input.extend_from_slice(payload);
+        input.extend_from_slice(&SYNC_FLUSH_TAIL);
+
+        let start_in = self.inflate.total_in();
+        let mut output = Vec::with_capacity(payload.len().saturating_mul(2).min(max_size) + 64);
+        loop {
+            let consumed = (self.inflate.total_in() - start_in) as usize;
+            let before = (self.inflate.total_in(), self.inflate.total_out());
+            let status = self.inflate.decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)?;
+            if output.len() > max_size {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "inflated message too large"));
+            }
+            let done = (self.inflate.total_in() - start_in) as usize == input.len();
+            if status == Status::StreamEnd || (done && output.len() < output.capacity()) {
+                break;
+            }
+            if before == (self.inflate.total_in(), self.inflate.total_out()) && output.len() < output.capacity() {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated deflate stream"));
+            }
+            output.reserve(output.capacity().max(4096));
+        }
+
+        if self.no_context_takeover {
+            self.inflate.reset(false);
+        }
+        Ok(output)
+    }
+}
+
+/// Deflates the messages of one direction
+pub struct MessageDeflater {
+    deflate: Compress,
+    no_context_takeover: bool,
+}
+
+impl MessageDeflater {
+    pub fn new(no_context_takeover: bool) -> Self {
+        Self {
+            deflate: Compress::new(Compression::default(), false),
+            no_context_takeover,
+        }
+    }
+
+    /// Payload to send with RSV1 set
+    pub fn deflate(&mut self, message: &[u8]) -> io::Result<Vec<u8>> {
+        let start_in = self.deflate.total_in();
+        let mut output = Vec::with_capacity(message.len() / 2 + 64);
+        loop {
+            let consumed = (self.deflate.total_in() - start_in) as usize;
+            self.deflate.compress_vec(&message[consumed..], &mut output, FlushCompress::Sync)?;
+            let done = (self.deflate.total_in() - start_in) as usize == message.len();
+            if done && output.len() < output.capacity() {
+                break;
+            }
+            output.reserve(output.capacity().max(4096));
+        }
+        if output.ends_with(&SYNC_FLUSH_TAIL) {
+            output.truncate(output.len() - SYNC_FLUSH_TAIL.len());
+        }
+
+        if self.no_context_takeover {
+            self.deflate.reset();
+        }
+        Ok(output)
+    }
+}
+
+/// Sends the messages of a relayed connection as traffic events of the
+/// upgrade request
+#[derive(Clone)]
+pub struct FrameCapture {
+    pub request_id: String,
+    pub sender: mpsc::Sender<TrafficEvent>,
+}
+
+impl FrameCapture {
+    fn message(&self, payload: &[u8], is_binary: bool, direction_outbound: bool) {
+        let _ = self.sender.try_send(TrafficEvent {
+            request_id: self.request_id.clone(),
+            event: Some(traffic_event::Event::Websocket(WebSocketFrame {
+                payload: payload.to_vec(),
+                is_binary,
+                direction_outbound,
+            })),
+        });
+    }
+}
+
+/// Relay an upgraded connection until both directions are closed.
+/// `params` are the ones the server accepted, `None` if it declined the
+/// extension.
+pub async fn relay<C, S>(
+    client: C,
+    server: S,
+    params: Option<DeflateParams>,
+    capture: Option<FrameCapture>,
+) -> io::Result<()>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (client_read, client_write) = tokio::io::split(client);
+    let (server_read, server_write) = tokio::io::split(server);
+    let mut outbound = Direction {
+        inflater: params.as_ref().map(DeflateParams::client_inflater),
+        deflater: params.as_ref().and_then(DeflateParams::client_deflater),
+        outbound: true,
+    };
+    let mut inbound = Direction {
+        inflater: params.as_ref().map(DeflateParams::server_inflater),
+        deflater: params.as_ref().and_then(DeflateParams::server_deflater),
+        outbound: false,
+    };
+    tokio::try_join!(
+        outbound.pump(client_read, server_write, capture.as_ref()),
+        inbound.pump(server_read, client_write, capture.as_ref()),
+    )?;
+    Ok(())
+}
+
+/// One direction of a relayed connection
+struct Direction {
+    inflater: Option<MessageInflater>,
+    /// `None` when the receiving side limited the window, in which case
+    /// messages are forwarded uncompressed
+    deflater: Option<MessageDeflater>,
+    /// Client to server, where frames must be masked
+    outbound: bool,
+}
+
+/// Data message being reassembled from its fragments
+struct PartialMessage {
+    opcode: OpCode,
+    compressed: bool,
+    payload: Vec<u8>,
+}
+
+impl Direction {
+    async fn pump<R, W>(&mut self, mut reader: R, mut writer: W, capture: Option<&FrameCapture>) -> io::Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let mut buffer = Vec::new();
+        let mut message: Option<PartialMessage> = None;
+        while let Some((header, payload)) = read_frame(&mut reader, &mut buffer).await? {
+            let is_final = header.is_final;
+            match header.opcode {
+                // Control frames may come between fragments and pass as they are
+                OpCode::Control(_) => {
+                    write_frame(&mut writer, header, payload).await?;
+                    continue;
+                }
+                OpCode::Data(Data::Continue) => {
+                    let partial = message.as_mut().ok_or_else(|| invalid("continuation without a message"))?;
+                    if partial.payload.len() + payload.len() > MAX_MESSAGE_SIZE {
+                        return Err(invalid("message too large"));
+                    }
+                    partial.payload.extend_from_slice(&payload);
+                }
+                opcode => {
+                    if message.is_some() {
+                        return Err(invalid("new message before the last one ended"));
+                    }
+                    message = Some(PartialMessage { opcode, compressed: header.rsv1, payload });
+                }
+            }
+            if is_final {
+                if let Some(complete) = message.take() {
+                    self.forward(&mut writer, complete, capture).await?;
+                }
+            }
+        }
+        writer.shutdown().await
+    }
+
+    async fn forward<W>(
+        &mut self,
+        writer: &mut W,
+        message: PartialMessage,
+        capture: Option<&FrameCapture>,
+    ) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let plain = match (message.compressed, self.inflater.as_mut()) {
+            (false, _) => message.payload,
+            (true, Some(inflater)) => inflater.inflate(&message.payload, MAX_MESSAGE_SIZE)?,
+            (true, None) => return Err(invalid("compressed frame without permessage-deflate")),
+        };
+        if let Some(capture) = capture {
+            capture.message(&plain, message.opcode == OpCode::Data(Data::Binary), self.outbound);
+        }
+
+        let (rsv1, payload) = match (message.compressed, self.deflater.as_mut()) {
+            (true, Some(deflater)) => (true, deflater.deflate(&plain)?),
+            _ => (false, plain),
+        };
+        let header = FrameHeader {
+            is_final: true,
+            rsv1,
+            opcode: message.opcode,
+            mask: self.outbound.then(rand::random),
+            ..FrameHeader::default()
+        };
+        write_frame(writer, header, payload).await
+    }
+}
+
+/// Next frame with its payload unmasked, `None` once the peer closed the
+/// connection between frames
+async fn read_frame<R>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<Option<(FrameHeader, Vec<u8>)>>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        let mut cursor = Cursor::new(buffer.as_slice());
+        if let Some((header, length)) = FrameHeader::parse(&mut cursor).map_err(invalid)? {
+            if length > MAX_MESSAGE_SIZE as u64 {
+                return Err(invalid("frame too large"));
+            }
+            let start = cursor.position() as usize;
+            let end = start + length as usize;
+            if buffer.len() >= end {
+                let mut payload = buffer[start..end].to_vec();
+                buffer.drain(..end);
+                if let Some(mask) = header.mask {
+                    payload.iter_mut().enumerate().for_each(|(i, byte)| *byte ^= mask[i % 4]);
+                }
+                return Ok(Some((header, payload)));
+            }
+        }
+        if reader.read_buf(buffer).await? == 0 {
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+}
+
+/// Write a frame, masking the payload if the header has a mask
+async fn write_frame<W>(writer: &mut W, header: FrameHeader, mut payload: Vec<u8>) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut frame = Vec::with_capacity(header.len(payload.len() as u64) + payload.len());
+    header.format(payload.len() as u64, &mut frame).map_err(invalid)?;
+    if let Some(mask) = header.mask {
+        payload.iter_mut().enumerate().for_each(|(i, byte)| *byte ^= mask[i % 4]);
+    }
+    frame.extend_from_slice(&payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+fn invalid<E: Into<Box<dyn std::error::Error + Send + Sync>>>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_offer_and_response() {
+        let offer = "x-webkit-deflate-frame, permessage-deflate; client_max_window_bits, permessage-deflate";
+        let params = DeflateParams::from_header(offer).unwrap();
+        assert_eq!(params.client_max_window_bits, Some(15));
+        assert!(!params.server_no_context_takeover);
+
+        let response = "permessage-deflate; server_no_context_takeover; server_max_window_bits=\"10\"";
+        let params = DeflateParams::from_header(response).unwrap();
+        assert!(params.server_no_context_takeover);
+        assert_eq!(params.server_max_window_bits, Some(10));
+        assert_eq!(
+            params.to_header(),
+            "permessage-deflate; server_no_context_takeover; server_max_window_bits=10"
+        );
+        assert!(params.server_deflater().is_none());
+        assert!(params.client_deflater().is_some());
+
+        assert_eq!(DeflateParams::from_header("permessage-deflate; server_max_window_bits=7"), None);
+        assert_eq!(DeflateParams::from_header("permessage-deflate; unknown"), None);
+        assert_eq!(DeflateParams::from_header("x-other"), None);
+    }
+
+    #[test]
+    fn test_round_trip_with_context_takeover() {
+        let messages: [&[u8]; 3] = [b"hello hello hello", b"hello hello hello again", &[0u8; 100_000]];
+        for no_context_takeover in [false, true] {
+            let mut deflater = MessageDeflater::new(no_context_takeover);
+            let mut inflater = MessageInflater::new(no_context_takeover);
+            for message in messages {
+                let compressed = deflater.deflate(message).unwrap();
+                assert!(!compressed.ends_with(&SYNC_FLUSH_TAIL));
+                assert_eq!(inflater.inflate(&compressed, 1 << 20).unwrap(), message);
+            }
+        }
+
+        // A message that only makes sense with the previous window
+        let mut deflater = MessageDeflater::new(false);
+        deflater.deflate(b"repeated text repeated text").unwrap();
+        let second = deflater.deflate(b"repeated text").unwrap();
+        let without_window = MessageInflater::new(false).inflate(&second, 1 << 20);
+        assert!(without_window.map_or(true, |message| message != b"repeated text"));
+    }
+
+    #[test]
+    fn test_rfc_example_and_size_limit() {
+        // "Hello" compressed, RFC 7692 §7.2.3.1
+        let payload = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        assert_eq!(MessageInflater::new(false).inflate(&payload, 1024).unwrap(), b"Hello");
+
+        let bomb = MessageDeflater::new(true).deflate(&[b'a'; 1 << 20]).unwrap();
+        assert!(MessageInflater::new(true).inflate(&bomb, 1024).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_relay_round_trips_compressed_frames() {
+        let (mut client, client_leg) = tokio::io::duplex(1 << 16);
+        let (server_leg, mut server) = tokio::io::duplex(1 << 16);
+        let (sender, mut events) = mpsc::channel(8);
+        let params = DeflateParams::from_header("permessage-deflate; client_max_window_bits").unwrap();
+        let capture = FrameCapture { request_id: "ws-1".to_string(), sender };
+        let relay = tokio::spawn(relay(client_leg, server_leg, Some(params.clone()), Some(capture)));
+
+        // A masked, compressed text message from the client, split in two fragments
+        let mut client_deflater = params.client_deflater().unwrap();
+        let compressed = client_deflater.deflate(b"hello over the relay").unwrap();
+        let (first, second) = compressed.split_at(compressed.len() / 2);
+        let mask = Some([1, 2, 3, 4]);
+        let header =
+            FrameHeader { is_final: false, rsv1: true, opcode: OpCode::Data(Data::Text), mask, ..FrameHeader::default() };
+        write_frame(&mut client, header, first.to_vec()).await.unwrap();
+        let header = FrameHeader { opcode: OpCode::Data(Data::Continue), mask, ..FrameHeader::default() };
+        write_frame(&mut client, header, second.to_vec()).await.unwrap();
+
+        let mut buffer = Vec::new();
+        let (header, payload) = read_frame(&mut server, &mut buffer).await.unwrap().unwrap();
+        assert!(header.rsv1 && header.is_final && header.mask.is_some());
+        assert_eq!(header.opcode, OpCode::Data(Data::Text));
+        let mut server_inflater = params.client_inflater();
+        assert_eq!(server_inflater.inflate(&payload, 1024).unwrap(), b"hello over the relay");
+
+        // And a compressed binary reply, continuing the server's window
+        let mut server_deflater = params.server_deflater().unwrap();
+        for reply in [&b"binary reply"[..], b"binary reply"] {
+            let header = FrameHeader { rsv1: true, opcode: OpCode::Data(Data::Binary), ..FrameHeader::default() };
+            write_frame(&mut server, header, server_deflater.deflate(reply).unwrap()).await.unwrap();
+        }
+        let mut client_inflater = params.server_inflater();
+        let mut buffer = Vec::new();
+        for _ in 0..2 {
+            let (header, payload) = read_frame(&mut client, &mut buffer).await.unwrap().unwrap();
+            assert!(header.rsv1 && header.mask.is_none());
+            assert_eq!(client_inflater.inflate(&payload, 1024).unwrap(), b"binary reply");
+        }
+
+        let captured = |event: TrafficEvent| match event.event {
+            Some(traffic_event::Event::Websocket(frame)) if event.request_id == "ws-1" => frame,
+            other => panic!("unexpected event {:?}", other),
+        };
+        let frame = captured(events.recv().await.unwrap());
+        assert_eq!(frame.payload, b"hello over the relay");
+        assert!(!frame.is_binary && frame.direction_outbound);
+        let frame = captured(events.recv().await.unwrap());
+        assert_eq!(frame.payload, b"binary reply");
+        assert!(frame.is_binary && !frame.direction_outbound);
+
+        drop(client);
+        drop(server);
+        relay.await.unwrap().unwrap();
+    }
+}
@@ -25,3 +25,96 @@ async fn test_resume_non_existent() {
     let resumed = controller.resume_request("invalid-id", InterceptCommand { command: None });
     assert!(!resumed, "Should return false for non-existent request");
 }
+
+#[tokio::test]
+async fn test_rule_matching() {
+    use proxy_core::pb::{intercept_rule::Action, InterceptRule, InterceptRules};
+    use std::collections::HashMap;
+
+    let controller = InterceptController::new();
+    let headers = HashMap::from([("Authorization".to_string(), "Bearer abc".to_string())]);
+    assert_eq!(controller.match_request("POST", "https://a.test/login", &headers), None);
+
+    controller.set_rules(InterceptRules {
+        enabled: true,
+        rules: vec![
+            InterceptRule {
+                id: "drop-tracking".to_string(),
+                action: Action::Drop as i32,
+                url_contains: "/track".to_string(),
+                ..Default::default()
+            },
+            InterceptRule {
+                id: "pause-login".to_string(),
+                action: Action::Pause as i32,
+                methods: vec!["post".to_string()],
+                url_contains: "/login".to_string(),
+                ..Default::default()
+            },
+            InterceptRule {
+                id: "pause-bearer".to_string(),
+                action: Action::Pause as i32,
+                header_name: "authorization".to_string(),
+                header_value: "Bearer".to_string(),
+                ..Default::default()
+            },
+        ],
+    });
+
+    assert_eq!(controller.match_request("GET", "https://a.test/track?x=1", &HashMap::new()), Some(Action::Drop));
+    assert_eq!(controller.match_request("POST", "https://a.test/login", &HashMap::new()), Some(Action::Pause));
+    assert_eq!(controller.match_request("GET", "https://a.test/login", &HashMap::new()), None);
+    assert_eq!(controller.match_request("GET", "https://a.test/me", &headers), Some(Action::Pause));
+
+    controller.set_rules(InterceptRules { enabled: false, ..Default::default() });
+    assert_eq!(controller.match_request("GET", "https://a.test/track", &HashMap::new()), None);
+}
+
+#[tokio::test]
+async fn test_rule_stats() {
+    use proxy_core::pb::{intercept_rule::Action, InterceptRule, InterceptRules};
+    use std::collections::HashMap;
+
+    let rule = |id: &str, url: &str| InterceptRule {
+        id: id.to_string(),
+        action: Action::Pause as i32,
+        url_contains: url.to_string(),
+        ..Default::default()
+    };
+    let controller = InterceptController::new();
+    controller.set_rules(InterceptRules {
+        enabled: true,
+        rules: vec![rule("admin", "/admin"), rule("login", "/login")],
+    });
+
+    controller.match_request("GET", "https://a.test/admin", &HashMap::new());
+    controller.match_request("GET", "https://a.test/login", &HashMap::new());
+    controller.match_request("GET", "https://a.test/other", &HashMap::new());
+
+    let stats = controller.rule_stats();
+    let admin = &stats.rules[0];
+    assert_eq!((admin.rule_id.as_str(), admin.evaluations, admin.hits), ("admin", 3, 1));
+    assert!(admin.last_hit > 0);
+    // Not checked once an earlier rule matched
+    let login = &stats.rules[1];
+    assert_eq!((login.evaluations, login.hits), (2, 1));
+
+    // Counters of kept rules survive a rule update, new rules start at zero
+    controller.set_rules(InterceptRules {
+        enabled: true,
+        rules: vec![rule("login", "/login"), rule("api", "/api")],
+    });
+    let stats = controller.rule_stats();
+    assert_eq!(stats.rules[0].hits, 1);
+    assert_eq!((stats.rules[1].rule_id.as_str(), stats.rules[1].evaluations), ("api", 0));
+}
+
+#[tokio::test]
+async fn test_cancel_request() {
+    let controller = InterceptController::new();
+    let rx = controller.register_request("req-1".to_string());
+    assert_eq!(controller.pending_count(), 1);
+    assert!(controller.cancel_request("req-1"));
+    assert_eq!(controller.pending_count(), 0);
+    assert!(rx.await.is_err(), "Cancelled request gets no decision");
+}
@@ -1,4 +1,4 @@
-use proxy_core::{CertificateAuthority, ProxyConfig, ProxyServer};
+use proxy_core::{CertificateAuthority, ProxyConfig, ProxyServer, RequestLimits};
 use std::time::Duration;
 use tempfile::tempdir;
 use tokio::net::TcpStream;
@@ -52,3 +52,61 @@ async fn test_proxy_server_startup() {
     let body = resp.text().await.unwrap();
     assert!(body.contains("total_requests"));
 }
+
+async fn send_raw(port: u16, head: &str) -> String {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    stream.write_all(head.as_bytes()).await.unwrap();
+    let mut response = vec![0u8; 1024];
+    let n = tokio::time::timeout(Duration::from_secs(5), stream.read(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    String::from_utf8_lossy(&response[..n]).into_owned()
+}
+
+#[tokio::test]
+async fn test_request_head_bounded_while_parsing() {
+    let dir = tempdir().unwrap();
+    let ca = CertificateAuthority::new(dir.path()).unwrap();
+
+    let port = 19092;
+    let config = ProxyConfig {
+        listen_address: "127.0.0.1".to_string(),
+        listen_port: port,
+        admin_port: 19093,
+        request_limits: RequestLimits {
+            max_header_bytes: 1024,
+            max_header_count: 10,
+            max_url_length: 256,
+        },
+        ..Default::default()
+    };
+    let buffer = config.request_limits.parse_buffer_size();
+    let server = ProxyServer::new(config, ca);
+    tokio::spawn(async move {
+        let _ = server.run().await;
+    });
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let metrics = || async {
+        let body: serde_json::Value = reqwest::get("http://127.0.0.1:19093/metrics")
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        (body["total_requests"].as_u64().unwrap(), body["requests_rejected"].as_u64().unwrap())
+    };
+
+    // A head larger than the parser's buffer is refused before the handler sees it
+    let huge = format!("GET http://127.0.0.1:1/ HTTP/1.1\r\nHost: 127.0.0.1:1\r\nX-Big: {}\r\n\r\n", "a".repeat(buffer));
+    assert!(send_raw(port, &huge).await.starts_with("HTTP/1.1 431"));
+    assert_eq!(metrics().await, (0, 0));
+
+    // One that fits the buffer but not the configured limit is refused by the handler
+    let over = format!("GET http://127.0.0.1:1/ HTTP/1.1\r\nHost: 127.0.0.1:1\r\nX-Big: {}\r\n\r\n", "a".repeat(2048));
+    assert!(send_raw(port, &over).await.starts_with("HTTP/1.1 431"));
+    assert_eq!(metrics().await, (1, 1));
+}
@@ -1,3 +1,4 @@
+use clap::Parser;
 use orchestrator::{LoggingConfig, Orchestrator, OrchestratorConfig};
 use proxy_agent::{run_agent, Args as AgentArgs};
 use sqlx::Row;
@@ -35,7 +36,13 @@ async fn test_end_to_end_traffic_flow() {
         agent_timeout: 30,
         logging: LoggingConfig {
             level: "info".into(),
+            ..Default::default()
         },
+        read_only: false,
+        admin_token: None,
+        cors_origins: Vec::new(),
+        grpc_tls: None,
+        ca_key_passphrase: None,
     };
 
     let orchestrator = Orchestrator::new(orch_config)
@@ -53,15 +60,15 @@ async fn test_end_to_end_traffic_flow() {
     tokio::time::sleep(Duration::from_secs(2)).await;
 
     // 3. Start Proxy Agent
-    let agent_args = AgentArgs {
-        listen_addr: "127.0.0.1".to_string(),
-        listen_port: agent_port,
-        admin_port: agent_admin_port,
-        orchestrator_url: format!("http://127.0.0.1:{}", orch_grpc_port),
-        name: Some("e2e-test-agent".to_string()),
-        ca_cert: None,
-        ca_key: None,
-    };
+    // Parse like the CLI so every other agent flag keeps its default
+    let agent_args = AgentArgs::parse_from([
+        "proxy-agent".to_string(),
+        "--listen-addr=127.0.0.1".to_string(),
+        format!("--listen-port={}", agent_port),
+        format!("--admin-port={}", agent_admin_port),
+        format!("--orchestrator-url=http://127.0.0.1:{}", orch_grpc_port),
+        "--name=e2e-test-agent".to_string(),
+    ]);
 
     // Spawn Agent
     tokio::spawn(async move {